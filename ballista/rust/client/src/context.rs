@@ -18,18 +18,33 @@
 //! Distributed execution context.
 
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use ballista_core::client::{create_channel, scheduler_request};
 use ballista_core::config::BallistaConfig;
-use ballista_core::{datasource::DfTableAdapter, utils::create_datafusion_context};
+use ballista_core::datasource::{BroadcastTable, DfTableAdapter};
+use ballista_core::execution_plans::fetch_job_results;
+use ballista_core::result_cache::ResultCache;
+use ballista_core::serde::protobuf::{
+    execute_query_params::Query, scheduler_grpc_client::SchedulerGrpcClient,
+    CreateSessionParams, ExecuteQueryParams, ExecuteStatementsParams, KeyValuePair,
+    PlanQueryResult, SessionStatement,
+};
+use ballista_core::utils::create_datafusion_context;
+use ballista_core::wasm::{get_or_register_wasm_udf, WasmScalarUdf};
 
+use datafusion::arrow::datatypes::{DataType, SchemaRef};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::catalog::catalog::CatalogProvider;
 use datafusion::catalog::TableReference;
 use datafusion::dataframe::DataFrame;
-use datafusion::error::Result;
-use datafusion::logical_plan::LogicalPlan;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::logical_plan::{LogicalPlan, LogicalPlanBuilder};
 use datafusion::physical_plan::csv::CsvReadOptions;
+use datafusion::physical_plan::SendableRecordBatchStream;
 
 struct BallistaContextState {
     /// Ballista configuration
@@ -40,6 +55,12 @@ struct BallistaContextState {
     scheduler_port: u16,
     /// Tables that have been registered with this context
     tables: HashMap<String, LogicalPlan>,
+    /// Catalogs that have been registered with this context
+    catalogs: HashMap<String, Arc<dyn CatalogProvider>>,
+    /// WebAssembly UDFs that have been registered with this context
+    wasm_udfs: HashMap<String, Arc<WasmScalarUdf>>,
+    /// Cache of the results of the queries of this context, if it is enabled
+    result_cache: Option<Arc<ResultCache>>,
 }
 
 impl BallistaContextState {
@@ -53,6 +74,9 @@ impl BallistaContextState {
             scheduler_host,
             scheduler_port,
             tables: HashMap::new(),
+            catalogs: HashMap::new(),
+            wasm_udfs: HashMap::new(),
+            result_cache: ResultCache::from_config(config).map(Arc::new),
         }
     }
 
@@ -86,12 +110,23 @@ impl BallistaContextState {
             scheduler_host: "localhost".to_string(),
             scheduler_port: addr.port(),
             tables: HashMap::new(),
+            catalogs: HashMap::new(),
+            wasm_udfs: HashMap::new(),
+            result_cache: ResultCache::from_config(config).map(Arc::new),
         })
     }
 
     pub fn config(&self) -> &BallistaConfig {
         &self.config
     }
+
+    /// Forgets the cached query results, which may have been read from tables or
+    /// computed by functions that are being replaced
+    fn clear_result_cache(&self) {
+        if let Some(result_cache) = &self.result_cache {
+            result_cache.clear();
+        }
+    }
 }
 
 pub struct BallistaContext {
@@ -134,6 +169,7 @@ impl BallistaContext {
             &guard.scheduler_host,
             guard.scheduler_port,
             guard.config(),
+            guard.result_cache.clone(),
         );
         let df = ctx.read_parquet(path.to_str().unwrap())?;
         Ok(df)
@@ -156,6 +192,7 @@ impl BallistaContext {
             &guard.scheduler_host,
             guard.scheduler_port,
             guard.config(),
+            guard.result_cache.clone(),
         );
         let df = ctx.read_csv(path.to_str().unwrap(), options)?;
         Ok(df)
@@ -164,6 +201,7 @@ impl BallistaContext {
     /// Register a DataFrame as a table that can be referenced from a SQL query
     pub fn register_table(&self, name: &str, table: &dyn DataFrame) -> Result<()> {
         let mut state = self.state.lock().unwrap();
+        state.clear_result_cache();
         state
             .tables
             .insert(name.to_owned(), table.to_logical_plan());
@@ -185,6 +223,160 @@ impl BallistaContext {
         self.register_table(name, df.as_ref())
     }
 
+    /// Register a small table, such as a dimension table, that is sent once per job to
+    /// every executor instead of being read from storage by every task. Its batches are
+    /// serialized with each query that references it.
+    pub fn register_broadcast(
+        &self,
+        name: &str,
+        schema: SchemaRef,
+        batches: Vec<RecordBatch>,
+    ) -> Result<()> {
+        let table = BroadcastTable::new(name, schema, batches);
+        let plan = LogicalPlanBuilder::scan(name, Arc::new(table), None)?.build()?;
+        let mut state = self.state.lock().unwrap();
+        state.clear_result_cache();
+        state.tables.insert(name.to_owned(), plan);
+        Ok(())
+    }
+
+    /// Register a catalog, such as a Hive Metastore catalog, whose tables can be
+    /// queried as `name.schema.table`. The tables are resolved when a query is planned
+    /// and their scans are sent to the cluster with the query.
+    pub fn register_catalog(&self, name: &str, catalog: Arc<dyn CatalogProvider>) {
+        let mut state = self.state.lock().unwrap();
+        state.clear_result_cache();
+        state.catalogs.insert(name.to_owned(), catalog);
+    }
+
+    /// Register a scalar UDF compiled to WebAssembly. The module must export a function
+    /// with the given name whose signature matches `arg_types` and `return_type`. The
+    /// module is shipped to executors along with the plans that reference it.
+    pub fn register_wasm_udf(
+        &self,
+        name: &str,
+        module: Vec<u8>,
+        arg_types: Vec<DataType>,
+        return_type: DataType,
+    ) -> Result<()> {
+        let udf = get_or_register_wasm_udf(name, module, arg_types, return_type)
+            .map_err(|e| DataFusionError::Plan(e.to_string()))?;
+        let mut state = self.state.lock().unwrap();
+        state.clear_result_cache();
+        state.wasm_udfs.insert(name.to_owned(), udf);
+        Ok(())
+    }
+
+    /// Forget the query results cached by this context, so that the next queries are
+    /// executed by the cluster. Results are only cached when the
+    /// `ballista.client.result_cache.max_bytes` setting of the context is not 0.
+    pub fn clear_result_cache(&self) {
+        self.state.lock().unwrap().clear_result_cache();
+    }
+
+    /// Attach to a job that was previously submitted to the scheduler, e.g. by a client
+    /// that has since disconnected, and fetch its results once it has completed
+    pub async fn fetch_job(&self, job_id: &str) -> Result<SendableRecordBatchStream> {
+        let (scheduler_url, page_rows) = {
+            let state = self.state.lock().unwrap();
+            (
+                format!("http://{}:{}", state.scheduler_host, state.scheduler_port),
+                state.config.results_page_rows(),
+            )
+        };
+        fetch_job_results(scheduler_url, job_id, page_rows).await
+    }
+
+    /// Create a session on the scheduler, whose SQL statements share the tables and
+    /// functions they register and the settings of their `SET` statements. The session
+    /// starts with the settings of this context and expires once it has not been used
+    /// for the session time to live of the scheduler. Returns the id of the session.
+    pub async fn create_session(&self) -> Result<String> {
+        let (scheduler_url, settings) = self.scheduler_url_and_settings();
+        let mut scheduler = create_channel(&scheduler_url)
+            .await
+            .map(SchedulerGrpcClient::new)
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        let result = scheduler
+            .create_session(scheduler_request(CreateSessionParams { settings }))
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
+            .into_inner();
+        Ok(result.session_id)
+    }
+
+    /// Submit SQL statements that the scheduler executes within the session
+    /// `session_id`, created by [BallistaContext::create_session], whose tables are
+    /// kept across requests. The result of a named statement is registered as a table
+    /// of the session once its job completed, so the statements that depend on it can
+    /// reference it. Returns the job id of each statement, whose results can be fetched
+    /// with [BallistaContext::fetch_job].
+    pub async fn submit_statements(
+        &self,
+        session_id: &str,
+        statements: Vec<SessionStatement>,
+    ) -> Result<Vec<String>> {
+        let (scheduler_url, settings) = self.scheduler_url_and_settings();
+        let mut scheduler = create_channel(&scheduler_url)
+            .await
+            .map(SchedulerGrpcClient::new)
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        let result = scheduler
+            .execute_statements(scheduler_request(ExecuteStatementsParams {
+                session_id: session_id.to_owned(),
+                statements,
+                settings,
+            }))
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
+            .into_inner();
+        Ok(result.job_ids)
+    }
+
+    /// Plan a SQL query into stages on the scheduler without executing it, to inspect
+    /// how it would be distributed. Returns the stages with their inputs, the number of
+    /// their tasks and output partitions, and estimates of their output.
+    pub async fn plan_query(&self, sql: &str) -> Result<PlanQueryResult> {
+        let plan = self.sql(sql)?.to_logical_plan();
+        let query = Query::LogicalPlan(
+            (&plan)
+                .try_into()
+                .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?,
+        );
+        let (scheduler_url, settings) = self.scheduler_url_and_settings();
+        let mut scheduler = create_channel(&scheduler_url)
+            .await
+            .map(SchedulerGrpcClient::new)
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        let result = scheduler
+            .plan_query(scheduler_request(ExecuteQueryParams {
+                query: Some(query),
+                settings,
+                session_id: String::new(),
+            }))
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
+            .into_inner();
+        Ok(result)
+    }
+
+    fn scheduler_url_and_settings(&self) -> (String, Vec<KeyValuePair>) {
+        let state = self.state.lock().unwrap();
+        let settings = state
+            .config
+            .settings()
+            .iter()
+            .map(|(k, v)| KeyValuePair {
+                key: k.to_owned(),
+                value: v.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        (
+            format!("http://{}:{}", state.scheduler_host, state.scheduler_port),
+            settings,
+        )
+    }
+
     /// Create a DataFrame from a SQL statement
     pub fn sql(&self, sql: &str) -> Result<Arc<dyn DataFrame>> {
         // use local DataFusion context for now but later this might call the scheduler
@@ -194,6 +386,7 @@ impl BallistaContext {
             &state.scheduler_host,
             state.scheduler_port,
             state.config(),
+            state.result_cache.clone(),
         );
         for (name, plan) in &state.tables {
             let plan = ctx.optimize(plan)?;
@@ -203,6 +396,12 @@ impl BallistaContext {
                 Arc::new(DfTableAdapter::new(plan, execution_plan)),
             )?;
         }
+        for (name, catalog) in &state.catalogs {
+            ctx.register_catalog(name.as_str(), catalog.clone());
+        }
+        for udf in state.wasm_udfs.values() {
+            ctx.register_udf(udf.to_scalar_udf());
+        }
         ctx.sql(sql)
     }
 }
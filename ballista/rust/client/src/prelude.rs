@@ -21,5 +21,6 @@ pub use crate::context::BallistaContext;
 pub use ballista_core::config::BallistaConfig;
 pub use ballista_core::config::BALLISTA_DEFAULT_SHUFFLE_PARTITIONS;
 pub use ballista_core::error::{BallistaError, Result};
+pub use ballista_core::serde::protobuf::SessionStatement;
 
 pub use futures::StreamExt;
@@ -31,13 +31,15 @@ use crate::serde::scheduler::{
     Action, ExecutePartition, ExecutePartitionResult, PartitionId, PartitionStats,
 };
 
-use arrow_flight::utils::flight_data_to_arrow_batch;
-use arrow_flight::Ticket;
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::utils::{flight_data_from_arrow_batch, flight_data_to_arrow_batch};
 use arrow_flight::{flight_service_client::FlightServiceClient, FlightData};
+use arrow_flight::{FlightDescriptor, SchemaAsIpc, Ticket};
 use datafusion::arrow::{
     array::{StringArray, StructArray},
     datatypes::{Schema, SchemaRef},
     error::{ArrowError, Result as ArrowResult},
+    ipc::writer::IpcWriteOptions,
     record_batch::RecordBatch,
 };
 use datafusion::physical_plan::common::collect;
@@ -45,10 +47,90 @@ use datafusion::physical_plan::{ExecutionPlan, SendableRecordBatchStream};
 use datafusion::{logical_plan::LogicalPlan, physical_plan::RecordBatchStream};
 use futures::{Stream, StreamExt};
 use log::debug;
+use once_cell::sync::OnceCell;
 use prost::Message;
-use tonic::Streaming;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Uri};
+use tonic::{Request, Streaming};
 use uuid::Uuid;
 
+/// TLS settings for the connections made by this process, see [configure_tls]
+static TLS_CONFIG: OnceCell<ClientTlsConfig> = OnceCell::new();
+
+/// Enables TLS for all connections this process makes to Ballista schedulers and
+/// executors. Server certificates must be signed by the PEM encoded `ca_cert`.
+///
+/// Certificates are verified against the host name that is connected to, unless
+/// `domain_name` is set, which is needed when cluster members are addressed by IP.
+/// This can only be called once, before any connection is made.
+pub fn configure_tls(ca_cert: &[u8], domain_name: Option<String>) -> Result<()> {
+    let mut config =
+        ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca_cert));
+    if let Some(domain_name) = domain_name {
+        config = config.domain_name(domain_name);
+    }
+    TLS_CONFIG
+        .set(config)
+        .map_err(|_| BallistaError::General("TLS has already been configured".to_owned()))
+}
+
+/// Token presented to schedulers by this process, see [configure_auth_token]
+static AUTH_TOKEN: OnceCell<MetadataValue<Ascii>> = OnceCell::new();
+
+/// Sets the token that this process presents to schedulers that require callers to
+/// authenticate. The token determines what the process is allowed to do, e.g.
+/// register as an executor or submit queries. This can only be called once.
+pub fn configure_auth_token(token: &str) -> Result<()> {
+    let token = MetadataValue::from_str(&format!("Bearer {}", token))
+        .map_err(|_| BallistaError::General("Invalid auth token".to_owned()))?;
+    AUTH_TOKEN.set(token).map_err(|_| {
+        BallistaError::General("An auth token has already been configured".to_owned())
+    })
+}
+
+/// Wraps `message` in a request to the scheduler, which carries the token set with
+/// [configure_auth_token] if any
+pub fn scheduler_request<T>(message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(token) = AUTH_TOKEN.get() {
+        request
+            .metadata_mut()
+            .insert("authorization", token.clone());
+    }
+    request
+}
+
+/// Opens a channel to the scheduler or executor at `url`, which uses TLS if it was
+/// enabled with [configure_tls]
+pub async fn create_channel(url: &str) -> Result<Channel> {
+    match TLS_CONFIG.get() {
+        Some(tls_config) => {
+            let url = match url.strip_prefix("http://") {
+                Some(address) => format!("https://{}", address),
+                None => url.to_owned(),
+            };
+            Ok(Endpoint::from_shared(url)?
+                .tls_config(tls_config.clone())?
+                .connect()
+                .await?)
+        }
+        None => Ok(Endpoint::from_shared(url.to_owned())?.connect().await?),
+    }
+}
+
+/// Opens a channel to the executor serving its Flight endpoint on the unix domain
+/// socket at `path`
+#[cfg(unix)]
+async fn create_unix_channel(path: &str) -> Result<Channel> {
+    let path = path.to_owned();
+    // tonic requires a URI, which is not used to connect
+    Ok(Endpoint::from_static("http://localhost")
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            tokio::net::UnixStream::connect(path.clone())
+        }))
+        .await?)
+}
+
 /// Client for interacting with Ballista executors.
 #[derive(Clone)]
 pub struct BallistaClient {
@@ -61,37 +143,219 @@ impl BallistaClient {
     pub async fn try_new(host: &str, port: u16) -> Result<Self> {
         let addr = format!("http://{}:{}", host, port);
         debug!("BallistaClient connecting to {}", addr);
-        let flight_client =
-            FlightServiceClient::connect(addr.clone())
-                .await
-                .map_err(|e| {
-                    BallistaError::General(format!(
-                        "Error connecting to Ballista scheduler or executor at {}: {:?}",
-                        addr, e
-                    ))
-                })?;
+        let channel = create_channel(&addr).await.map_err(|e| {
+            BallistaError::General(format!(
+                "Error connecting to Ballista scheduler or executor at {}: {:?}",
+                addr, e
+            ))
+        })?;
+        let flight_client = FlightServiceClient::new(channel);
         debug!("BallistaClient connected OK");
 
         Ok(Self { flight_client })
     }
 
-    /// Fetch a partition from an executor
+    /// Create a new BallistaClient to connect to an executor through `flight_socket`,
+    /// the unix domain socket it serves its Flight endpoint on, when the executor runs
+    /// on the same host. Falls back to the host and port otherwise, or when the executor
+    /// has no socket.
+    pub async fn try_new_local_or_remote(
+        host: &str,
+        port: u16,
+        flight_socket: &str,
+    ) -> Result<Self> {
+        // sockets are named after the executor id, so that a socket at the path is
+        // the executor's own
+        #[cfg(unix)]
+        if !flight_socket.is_empty() && std::path::Path::new(flight_socket).exists() {
+            match create_unix_channel(flight_socket).await {
+                Ok(channel) => {
+                    debug!("BallistaClient connected to {}", flight_socket);
+                    let flight_client = FlightServiceClient::new(channel);
+                    return Ok(Self { flight_client });
+                }
+                Err(e) => debug!(
+                    "Could not connect to {}, connecting to {}:{} instead: {:?}",
+                    flight_socket, host, port, e
+                ),
+            }
+        }
+        Self::try_new(host, port).await
+    }
+
+    /// Fetch a partition from an executor, presenting the `auth_token` of the job
     pub async fn fetch_partition(
         &mut self,
         job_id: &str,
         stage_id: usize,
         partition_id: usize,
         path: &str,
+        auth_token: &str,
     ) -> Result<SendableRecordBatchStream> {
         let action = Action::FetchPartition {
             job_id: job_id.to_string(),
             stage_id,
             partition_id,
             path: path.to_owned(),
+            auth_token: auth_token.to_owned(),
         };
         self.execute_action(&action).await
     }
 
+    /// Fetch at most `max_rows` rows of a partition of the results of a job, or all of
+    /// them if 0, starting at row `offset`, from the results service of an executor,
+    /// presenting the `auth_token` of the job. `stage_id` is the final stage of the job.
+    pub async fn fetch_results(
+        &mut self,
+        job_id: &str,
+        stage_id: usize,
+        partition_id: usize,
+        offset: u64,
+        max_rows: u64,
+        auth_token: &str,
+    ) -> Result<SendableRecordBatchStream> {
+        let action = Action::FetchResults {
+            job_id: job_id.to_owned(),
+            stage_id,
+            partition_id,
+            offset,
+            max_rows,
+            auth_token: auth_token.to_owned(),
+        };
+        self.execute_action(&action).await
+    }
+
+    /// Fetch the schema of a partition of the results of a job from the results service
+    /// of an executor, presenting the `auth_token` of the job
+    pub async fn fetch_results_schema(
+        &mut self,
+        job_id: &str,
+        stage_id: usize,
+        partition_id: usize,
+        auth_token: &str,
+    ) -> Result<SchemaRef> {
+        let action: protobuf::Action = Action::FetchResults {
+            job_id: job_id.to_owned(),
+            stage_id,
+            partition_id,
+            offset: 0,
+            max_rows: 0,
+            auth_token: auth_token.to_owned(),
+        }
+        .try_into()?;
+        let mut cmd: Vec<u8> = Vec::with_capacity(action.encoded_len());
+        action
+            .encode(&mut cmd)
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?;
+
+        let result = self
+            .flight_client
+            .get_schema(FlightDescriptor {
+                r#type: DescriptorType::Cmd as i32,
+                cmd,
+                path: vec![],
+            })
+            .await
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?
+            .into_inner();
+        Ok(Arc::new(Schema::try_from(&result)?))
+    }
+
+    /// Push the `batches` of the broadcast variable `name` to an executor, which keeps
+    /// them for the tasks of the job, presenting the `auth_token` of the job
+    pub async fn broadcast(
+        &mut self,
+        job_id: &str,
+        name: &str,
+        auth_token: &str,
+        schema: &Schema,
+        batches: &[RecordBatch],
+    ) -> Result<()> {
+        let action: protobuf::Action = Action::PutBroadcast {
+            job_id: job_id.to_owned(),
+            name: name.to_owned(),
+            auth_token: auth_token.to_owned(),
+        }
+        .try_into()?;
+        let mut cmd: Vec<u8> = Vec::with_capacity(action.encoded_len());
+        action
+            .encode(&mut cmd)
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?;
+
+        // the first message carries the action and the schema, the batches follow
+        let options = IpcWriteOptions::default();
+        let mut schema_data: FlightData = SchemaAsIpc::new(schema, &options).into();
+        schema_data.flight_descriptor = Some(FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd,
+            path: vec![],
+        });
+        let mut messages = vec![schema_data];
+        for batch in batches {
+            let (dictionaries, data) = flight_data_from_arrow_batch(batch, &options);
+            messages.extend(dictionaries);
+            messages.push(data);
+        }
+
+        let mut response = self
+            .flight_client
+            .do_exchange(futures::stream::iter(messages))
+            .await
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?
+            .into_inner();
+        while response
+            .message()
+            .await
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?
+            .is_some()
+        {}
+        Ok(())
+    }
+
+    /// Fetch the last `max_bytes` of the captured log records of a task from the
+    /// executor that ran it, or all of them if 0, presenting the `auth_token` of the job
+    pub async fn fetch_task_logs(
+        &mut self,
+        job_id: &str,
+        stage_id: usize,
+        partition_id: usize,
+        max_bytes: u64,
+        auth_token: &str,
+    ) -> Result<String> {
+        let action: protobuf::Action = Action::FetchTaskLogs {
+            job_id: job_id.to_owned(),
+            stage_id,
+            partition_id,
+            max_bytes,
+            auth_token: auth_token.to_owned(),
+        }
+        .try_into()?;
+        let mut body: Vec<u8> = Vec::with_capacity(action.encoded_len());
+        action
+            .encode(&mut body)
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?;
+
+        let mut response = self
+            .flight_client
+            .do_action(arrow_flight::Action {
+                r#type: "FetchTaskLogs".to_owned(),
+                body,
+            })
+            .await
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?
+            .into_inner();
+        let mut logs = vec![];
+        while let Some(result) = response
+            .message()
+            .await
+            .map_err(|e| BallistaError::General(format!("{:?}", e)))?
+        {
+            logs.extend_from_slice(&result.body);
+        }
+        String::from_utf8(logs)
+            .map_err(|e| BallistaError::General(format!("Invalid logs of task: {:?}", e)))
+    }
+
     /// Execute an action and retrieve the results
     pub async fn execute_action(
         &mut self,
@@ -18,14 +18,30 @@
 
 //! Ballista configuration
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::error::{BallistaError, Result};
+use crate::object_store::object_store_for;
+use crate::serde::compression::parse_plan_compression;
+use crate::serde::protobuf::PlanCompression;
 
 use datafusion::arrow::datatypes::DataType;
+use datafusion::physical_plan::HashScheme;
 use log::warn;
 
 pub const BALLISTA_DEFAULT_SHUFFLE_PARTITIONS: &str = "ballista.shuffle.partitions";
+pub const BALLISTA_PLAN_CHECK_SERIALIZABLE: &str = "ballista.plan.check_serializable";
+pub const BALLISTA_BROADCAST_JOIN_THRESHOLD: &str = "ballista.join.broadcast_threshold";
+pub const BALLISTA_EXECUTOR_CONSTRAINTS: &str = "ballista.executor.constraints";
+pub const BALLISTA_PLAN_CHUNK_SIZE: &str = "ballista.plan.chunk_size";
+pub const BALLISTA_PLAN_COMPRESSION: &str = "ballista.plan.compression";
+pub const BALLISTA_RESULTS_PATH: &str = "ballista.results.path";
+pub const BALLISTA_SHUFFLE_HASH_SCHEME: &str = "ballista.shuffle.hash_scheme";
+pub const BALLISTA_RESULT_CACHE_MAX_BYTES: &str =
+    "ballista.client.result_cache.max_bytes";
+pub const BALLISTA_RESULT_CACHE_TTL_SECONDS: &str =
+    "ballista.client.result_cache.ttl_seconds";
+pub const BALLISTA_RESULTS_PAGE_ROWS: &str = "ballista.client.results.page_rows";
 
 /// Configuration option meta-data
 #[derive(Debug, Clone)]
@@ -103,9 +119,9 @@ impl BallistaConfig {
         for (name, entry) in &supported_entries {
             if let Some(v) = settings.get(name) {
                 // validate that we can parse the user-supplied value
-                let _ = v.parse::<usize>().map_err(|e| BallistaError::General(format!("Failed to parse user-supplied value '{}' for configuration setting '{}': {:?}", name, v, e)))?;
+                Self::parse_value(name, v, &entry.data_type).map_err(|e| BallistaError::General(format!("Failed to parse user-supplied value '{}' for configuration setting '{}': {}", name, v, e)))?;
             } else if let Some(v) = entry.default_value.clone() {
-                let _ = v.parse::<usize>().map_err(|e| BallistaError::General(format!("Failed to parse default value '{}' for configuration setting '{}': {:?}", name, v, e)))?;
+                Self::parse_value(name, &v, &entry.data_type).map_err(|e| BallistaError::General(format!("Failed to parse default value '{}' for configuration setting '{}': {}", name, v, e)))?;
             } else {
                 return Err(BallistaError::General(format!(
                     "No value specified for mandatory configuration setting '{}'",
@@ -117,12 +133,80 @@ impl BallistaConfig {
         Ok(Self { settings })
     }
 
+    /// Check that the `value` of setting `name` can be parsed as `data_type`, returning the
+    /// debug representation of the parse error if it cannot
+    fn parse_value(
+        name: &str,
+        value: &str,
+        data_type: &DataType,
+    ) -> std::result::Result<(), String> {
+        match data_type {
+            DataType::Boolean => value
+                .parse::<bool>()
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+            DataType::Utf8 if name == BALLISTA_PLAN_COMPRESSION => {
+                parse_plan_compression(value)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            DataType::Utf8 if name == BALLISTA_SHUFFLE_HASH_SCHEME => value
+                .parse::<HashScheme>()
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            DataType::Utf8 if name == BALLISTA_RESULTS_PATH => {
+                if value.is_empty() {
+                    Ok(())
+                } else {
+                    object_store_for(value)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }
+            }
+            DataType::Utf8 => parse_labels(value).map(|_| ()).map_err(|e| e.to_string()),
+            _ => value
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|e| format!("{:?}", e)),
+        }
+    }
+
     /// All available configuration options
     pub fn valid_entries() -> HashMap<String, ConfigEntry> {
         let entries = vec![
             ConfigEntry::new(BALLISTA_DEFAULT_SHUFFLE_PARTITIONS.to_string(),
                 "Sets the default number of partitions to create when repartitioning query stages".to_string(),
                 DataType::UInt16, Some("2".to_string())),
+            ConfigEntry::new(BALLISTA_PLAN_CHECK_SERIALIZABLE.to_string(),
+                "Check that all operators of the physical plan can be serialized before a job is created, and report the unsupported ones to the client".to_string(),
+                DataType::Boolean, Some("true".to_string())),
+            ConfigEntry::new(BALLISTA_BROADCAST_JOIN_THRESHOLD.to_string(),
+                "Joins whose build side is estimated to have at most this many rows are planned as broadcast joins, which do not shuffle the probe side. 0 disables broadcast joins".to_string(),
+                DataType::UInt64, Some("100000".to_string())),
+            ConfigEntry::new(BALLISTA_EXECUTOR_CONSTRAINTS.to_string(),
+                "Comma separated key=value labels, such as gpu=true,zone=us-east-1a, that an executor must have been started with to run the tasks of the query".to_string(),
+                DataType::Utf8, Some("".to_string())),
+            ConfigEntry::new(BALLISTA_PLAN_CHUNK_SIZE.to_string(),
+                "Queries whose plan is larger than this many bytes are streamed to the scheduler in chunks of this size, instead of being sent in a single message".to_string(),
+                DataType::UInt64, Some("1048576".to_string())),
+            ConfigEntry::new(BALLISTA_PLAN_COMPRESSION.to_string(),
+                "Compression of the plans that are streamed to the scheduler: none, gzip or zstd. Compressed plans are always streamed".to_string(),
+                DataType::Utf8, Some("none".to_string())),
+            ConfigEntry::new(BALLISTA_RESULTS_PATH.to_string(),
+                "URL of an object store location, such as s3://bucket/results, that the final stage writes the results of the query to instead of the local disk of the executors. Empty to keep the results on the executors".to_string(),
+                DataType::Utf8, Some("".to_string())),
+            ConfigEntry::new(BALLISTA_SHUFFLE_HASH_SCHEME.to_string(),
+                "Hash function that assigns the rows of hash partitioned shuffles to partitions: internal, which may change between releases, or murmur3_v1, which is specified and stable across releases".to_string(),
+                DataType::Utf8, Some("internal".to_string())),
+            ConfigEntry::new(BALLISTA_RESULT_CACHE_MAX_BYTES.to_string(),
+                "Size in bytes of the cache of query results in the client, which answers queries whose plan and settings it has already executed without submitting them to the cluster. 0 disables the cache".to_string(),
+                DataType::UInt64, Some("0".to_string())),
+            ConfigEntry::new(BALLISTA_RESULT_CACHE_TTL_SECONDS.to_string(),
+                "Number of seconds for which the client reuses cached query results. 0 keeps them until they are evicted".to_string(),
+                DataType::UInt64, Some("300".to_string())),
+            ConfigEntry::new(BALLISTA_RESULTS_PAGE_ROWS.to_string(),
+                "Number of rows of the results of a query that the client fetches from an executor per request. 0 fetches each partition of the results with a single request".to_string(),
+                DataType::UInt64, Some("65536".to_string())),
         ];
         entries
             .iter()
@@ -138,6 +222,77 @@ impl BallistaConfig {
         self.get_usize_setting(BALLISTA_DEFAULT_SHUFFLE_PARTITIONS)
     }
 
+    pub fn check_plan_serializable(&self) -> bool {
+        self.get_bool_setting(BALLISTA_PLAN_CHECK_SERIALIZABLE)
+    }
+
+    pub fn broadcast_join_threshold(&self) -> usize {
+        self.get_usize_setting(BALLISTA_BROADCAST_JOIN_THRESHOLD)
+    }
+
+    /// The labels that the executors running the tasks of the query must have
+    pub fn executor_constraints(&self) -> BTreeMap<String, String> {
+        let v = self
+            .settings
+            .get(BALLISTA_EXECUTOR_CONSTRAINTS)
+            .map(|v| v.as_str())
+            .unwrap_or_default();
+        // infallible because we validate all configs in the constructor
+        parse_labels(v).unwrap()
+    }
+
+    /// Size in bytes above which the plan of the query is streamed to the scheduler
+    pub fn plan_chunk_size(&self) -> usize {
+        self.get_usize_setting(BALLISTA_PLAN_CHUNK_SIZE).max(1)
+    }
+
+    /// Compression of the plan of the query, if it is streamed to the scheduler
+    pub fn plan_compression(&self) -> PlanCompression {
+        let v = self
+            .settings
+            .get(BALLISTA_PLAN_COMPRESSION)
+            .map(|v| v.as_str())
+            .unwrap_or_default();
+        // infallible because we validate all configs in the constructor
+        parse_plan_compression(v).unwrap()
+    }
+
+    /// Object store location that the results of the query are written to, if any
+    pub fn results_path(&self) -> Option<&str> {
+        self.settings
+            .get(BALLISTA_RESULTS_PATH)
+            .map(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Hash function of the hash partitioned shuffles of the query
+    pub fn shuffle_hash_scheme(&self) -> HashScheme {
+        let v = self
+            .settings
+            .get(BALLISTA_SHUFFLE_HASH_SCHEME)
+            .map(|v| v.as_str())
+            .unwrap_or("internal");
+        // infallible because we validate all configs in the constructor
+        v.parse().unwrap()
+    }
+
+    /// Size in bytes of the cache of query results in the client, 0 if it is disabled
+    pub fn result_cache_max_bytes(&self) -> usize {
+        self.get_usize_setting(BALLISTA_RESULT_CACHE_MAX_BYTES)
+    }
+
+    /// Number of seconds for which cached query results are reused, 0 if they do not
+    /// expire
+    pub fn result_cache_ttl_seconds(&self) -> usize {
+        self.get_usize_setting(BALLISTA_RESULT_CACHE_TTL_SECONDS)
+    }
+
+    /// Number of rows of the results of a query fetched per request, 0 to fetch each
+    /// partition at once
+    pub fn results_page_rows(&self) -> usize {
+        self.get_usize_setting(BALLISTA_RESULTS_PAGE_ROWS)
+    }
+
     fn get_usize_setting(&self, key: &str) -> usize {
         if let Some(v) = self.settings.get(key) {
             // infallible because we validate all configs in the constructor
@@ -149,6 +304,47 @@ impl BallistaConfig {
             v.parse().unwrap()
         }
     }
+
+    fn get_bool_setting(&self, key: &str) -> bool {
+        if let Some(v) = self.settings.get(key) {
+            // infallible because we validate all configs in the constructor
+            v.parse().unwrap()
+        } else {
+            let entries = Self::valid_entries();
+            // infallible because we validate all configs in the constructor
+            let v = entries.get(key).unwrap().default_value.as_ref().unwrap();
+            v.parse().unwrap()
+        }
+    }
+}
+
+/// Parses comma separated `key=value` labels, such as `gpu=true,zone=us-east-1a`,
+/// which describe executors and the executors that queries are constrained to
+pub fn parse_labels(labels: &str) -> Result<BTreeMap<String, String>> {
+    labels
+        .split(',')
+        .map(|label| label.trim())
+        .filter(|label| !label.is_empty())
+        .map(|label| match label.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_owned(), value.trim().to_owned()))
+            }
+            _ => Err(BallistaError::General(format!(
+                "Invalid label '{}', expected key=value",
+                label
+            ))),
+        })
+        .collect()
+}
+
+/// Returns true if the executor `labels` have all the `constraints` labels
+pub fn satisfies_constraints(
+    labels: &BTreeMap<String, String>,
+    constraints: &BTreeMap<String, String>,
+) -> bool {
+    constraints
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
 }
 
 #[cfg(test)]
@@ -159,6 +355,9 @@ mod tests {
     fn default_config() -> Result<()> {
         let config = BallistaConfig::new()?;
         assert_eq!(2, config.default_shuffle_partitions());
+        assert!(config.check_plan_serializable());
+        assert_eq!(100000, config.broadcast_join_threshold());
+        assert_eq!(65536, config.results_page_rows());
         Ok(())
     }
 
@@ -171,6 +370,101 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn custom_bool_config() -> Result<()> {
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_PLAN_CHECK_SERIALIZABLE, "false")
+            .build()?;
+        assert!(!config.check_plan_serializable());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_PLAN_CHECK_SERIALIZABLE, "123")
+            .build();
+        assert!(config.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn executor_constraints() -> Result<()> {
+        let config = BallistaConfig::new()?;
+        assert!(config.executor_constraints().is_empty());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_EXECUTOR_CONSTRAINTS, "gpu=true, zone=us-east-1a")
+            .build()?;
+        let constraints = config.executor_constraints();
+        assert_eq!(2, constraints.len());
+        assert_eq!(
+            Some("us-east-1a"),
+            constraints.get("zone").map(|v| v.as_str())
+        );
+
+        let labels = parse_labels("zone=us-east-1a,gpu=true,memory=64g")?;
+        assert!(satisfies_constraints(&labels, &constraints));
+        let labels = parse_labels("zone=us-east-1b,gpu=true")?;
+        assert!(!satisfies_constraints(&labels, &constraints));
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_EXECUTOR_CONSTRAINTS, "gpu")
+            .build();
+        assert!(config.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn plan_compression() -> Result<()> {
+        let config = BallistaConfig::new()?;
+        assert_eq!(PlanCompression::None, config.plan_compression());
+        assert_eq!(1048576, config.plan_chunk_size());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_PLAN_COMPRESSION, "zstd")
+            .set(BALLISTA_PLAN_CHUNK_SIZE, "1024")
+            .build()?;
+        assert_eq!(PlanCompression::Zstd, config.plan_compression());
+        assert_eq!(1024, config.plan_chunk_size());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_PLAN_COMPRESSION, "lz4")
+            .build();
+        assert!(config.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn shuffle_hash_scheme() -> Result<()> {
+        let config = BallistaConfig::new()?;
+        assert_eq!(HashScheme::Internal, config.shuffle_hash_scheme());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_SHUFFLE_HASH_SCHEME, "murmur3_v1")
+            .build()?;
+        assert_eq!(HashScheme::Murmur3V1, config.shuffle_hash_scheme());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_SHUFFLE_HASH_SCHEME, "md5")
+            .build();
+        assert!(config.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn results_path() -> Result<()> {
+        let config = BallistaConfig::new()?;
+        assert_eq!(None, config.results_path());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_RESULTS_PATH, "file:///tmp/results")
+            .build()?;
+        assert_eq!(Some("file:///tmp/results"), config.results_path());
+
+        let config = BallistaConfig::builder()
+            .set(BALLISTA_RESULTS_PATH, "/tmp/results")
+            .build();
+        assert!(config.is_err());
+        Ok(())
+    }
+
     #[test]
     fn custom_config_invalid() -> Result<()> {
         let config = BallistaConfig::builder()
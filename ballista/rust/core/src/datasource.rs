@@ -17,12 +17,17 @@
 
 use std::{any::Any, sync::Arc};
 
+use crate::execution_plans::{BroadcastExec, ShuffleReaderExec};
+use crate::serde::scheduler::PartitionLocation;
 use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::error::Result as DFResult;
 use datafusion::{
     datasource::{datasource::Statistics, TableProvider},
     logical_plan::{Expr, LogicalPlan},
-    physical_plan::ExecutionPlan,
+    physical_plan::{
+        expressions::Column, projection::ProjectionExec, ExecutionPlan, PhysicalExpr,
+    },
 };
 
 /// This ugly adapter is needed because we use DataFusion's logical plan when building queries
@@ -70,3 +75,145 @@ impl TableProvider for DfTableAdapter {
         }
     }
 }
+
+/// A table of batches that the scheduler broadcasts to every executor once per job,
+/// which plans read with a [BroadcastExec]. Intended for small tables, such as
+/// dimension tables, that would otherwise be re-read from storage by every task.
+pub struct BroadcastTable {
+    /// Name of the broadcast variable
+    name: String,
+    schema: SchemaRef,
+    batches: Arc<Vec<RecordBatch>>,
+}
+
+impl BroadcastTable {
+    pub fn new(name: &str, schema: SchemaRef, batches: Vec<RecordBatch>) -> Self {
+        Self {
+            name: name.to_owned(),
+            schema,
+            batches: Arc::new(batches),
+        }
+    }
+
+    /// Name of the broadcast variable
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The broadcast batches
+    pub fn batches(&self) -> &[RecordBatch] {
+        &self.batches
+    }
+}
+
+impl TableProvider for BroadcastTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(BroadcastExec::try_new(
+            &self.name,
+            self.schema.clone(),
+            self.batches.clone(),
+            projection.clone(),
+        )?))
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics {
+            num_rows: Some(self.batches.iter().map(|b| b.num_rows()).sum()),
+            total_byte_size: None,
+            column_statistics: None,
+        }
+    }
+}
+
+/// The output of a completed job, which the later statements of a session read from the
+/// executors that hold the partitions of its final stage
+pub struct JobResultTable {
+    schema: SchemaRef,
+    partition_locations: Vec<PartitionLocation>,
+    /// Kept for as long as the table, e.g. to keep the files of the job
+    _pin: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl JobResultTable {
+    pub fn new(schema: SchemaRef, partition_locations: Vec<PartitionLocation>) -> Self {
+        Self {
+            schema,
+            partition_locations,
+            _pin: None,
+        }
+    }
+
+    /// Keeps `pin` until the table is dropped, e.g. a guard that keeps the executors from
+    /// deleting the files of the job while the table is registered
+    pub fn with_pin(mut self, pin: impl Any + Send + Sync) -> Self {
+        self._pin = Some(Box::new(pin));
+        self
+    }
+}
+
+impl TableProvider for JobResultTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        let partitions = self
+            .partition_locations
+            .iter()
+            .map(|location| vec![location.clone()])
+            .collect();
+        let reader =
+            Arc::new(ShuffleReaderExec::try_new(partitions, self.schema.clone())?);
+        match projection {
+            Some(projection) => {
+                let exprs = projection
+                    .iter()
+                    .map(|i| {
+                        let name = self.schema.field(*i).name();
+                        let expr: Arc<dyn PhysicalExpr> = Arc::new(Column::new(name, *i));
+                        (expr, name.to_owned())
+                    })
+                    .collect();
+                Ok(Arc::new(ProjectionExec::try_new(exprs, reader)?))
+            }
+            None => Ok(reader),
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        let num_rows = self
+            .partition_locations
+            .iter()
+            .map(|location| location.partition_stats.num_rows)
+            .sum::<Option<u64>>();
+        Statistics {
+            num_rows: num_rows.map(|n| n as usize),
+            total_byte_size: None,
+            column_statistics: None,
+        }
+    }
+}
@@ -0,0 +1,403 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Encryption of shuffle files at rest with AES-256-GCM, using a key that the scheduler
+//! generates for each job and sends to the executors with the tasks of the job.
+//!
+//! An encrypted file starts with a magic number and a random nonce prefix, followed by
+//! the chunks of the plain file, each prefixed with its length. A chunk is encrypted
+//! with a nonce made of the prefix, the index of the chunk and a flag that marks the
+//! last chunk of the file, as in the STREAM construction. Chunks cannot be reordered or
+//! moved to another file without failing authentication, and a file that is truncated
+//! at the boundary of a chunk is rejected as it misses its last chunk.
+
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand::RngCore;
+
+use crate::error::BallistaError;
+
+/// Size in bytes of the keys that encrypt shuffle files
+pub const KEY_SIZE: usize = 32;
+
+/// Maximum number of bytes of the plain file in each chunk of an encrypted file
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Magic number at the start of encrypted files
+const MAGIC: &[u8] = b"BSE2";
+
+const NONCE_PREFIX_SIZE: usize = 7;
+
+/// A key that encrypts the shuffle files of a job
+#[derive(Clone, PartialEq)]
+pub struct ShuffleKey([u8; KEY_SIZE]);
+
+impl ShuffleKey {
+    /// Generates a random key
+    pub fn generate() -> Self {
+        let mut key = [0; KEY_SIZE];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// Returns the key encoded in `bytes`, or none if `bytes` is empty, which is how
+    /// the tasks of jobs without encrypted shuffle files are sent
+    pub fn from_optional_bytes(bytes: &[u8]) -> Result<Option<Self>, BallistaError> {
+        if bytes.is_empty() {
+            Ok(None)
+        } else {
+            Self::try_from(bytes).map(Some)
+        }
+    }
+
+    /// The bytes of the key
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::from_slice(&self.0))
+    }
+}
+
+impl TryFrom<&[u8]> for ShuffleKey {
+    type Error = BallistaError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key = bytes.try_into().map_err(|_| {
+            BallistaError::General(format!(
+                "Invalid shuffle encryption key of {} bytes, expected {}",
+                bytes.len(),
+                KEY_SIZE
+            ))
+        })?;
+        Ok(Self(key))
+    }
+}
+
+impl fmt::Debug for ShuffleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // keys must never end up in logs
+        f.write_str("ShuffleKey(..)")
+    }
+}
+
+/// Writes the encrypted chunks of a file to an inner writer. Flushing the writer writes
+/// the buffered data as a chunk, and the last chunk is written by [Self::finish]; a file
+/// whose writer was not finished cannot be decrypted.
+pub struct EncryptedWriter<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+    chunk_index: u32,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    /// Create a writer that encrypts the file written to `inner` with `key`
+    pub fn try_new(mut inner: W, key: &ShuffleKey) -> io::Result<Self> {
+        let mut nonce_prefix = [0; NONCE_PREFIX_SIZE];
+        rand::thread_rng().fill_bytes(&mut nonce_prefix);
+        inner.write_all(MAGIC)?;
+        inner.write_all(&nonce_prefix)?;
+        Ok(Self {
+            inner,
+            cipher: key.cipher(),
+            nonce_prefix,
+            chunk_index: 0,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    /// Writes the last chunk and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_chunk(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        if self.buffer.is_empty() && !last {
+            return Ok(());
+        }
+        let nonce = chunk_nonce(&self.nonce_prefix, self.chunk_index, last);
+        let chunk = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), self.buffer.as_slice())
+            .map_err(|_| invalid_data("Could not encrypt shuffle file"))?;
+        self.inner.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&chunk)?;
+        // a nonce must never be used twice with the same key
+        self.chunk_index = self
+            .chunk_index
+            .checked_add(1)
+            .ok_or_else(|| invalid_data("Shuffle file is too large to encrypt"))?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk(false)?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk(false)?;
+        self.inner.flush()
+    }
+}
+
+/// Decrypts the contents of a file that was encrypted with `key`
+pub fn decrypt(data: &[u8], key: &ShuffleKey) -> io::Result<Vec<u8>> {
+    let header_size = MAGIC.len() + NONCE_PREFIX_SIZE;
+    if data.len() < header_size || &data[..MAGIC.len()] != MAGIC {
+        return Err(invalid_data("Shuffle file is not encrypted"));
+    }
+    let nonce_prefix = &data[MAGIC.len()..header_size];
+    let cipher = key.cipher();
+    let mut decrypted = Vec::with_capacity(data.len());
+    let mut chunks = &data[header_size..];
+    let mut chunk_index = 0;
+    loop {
+        if chunks.len() < 4 {
+            return Err(invalid_data("Encrypted shuffle file is truncated"));
+        }
+        let (len, rest) = chunks.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(invalid_data("Encrypted shuffle file is truncated"));
+        }
+        let (chunk, rest) = rest.split_at(len);
+        // only the last chunk of the data authenticates as the last chunk of the file
+        let last = rest.is_empty();
+        let nonce = chunk_nonce(nonce_prefix, chunk_index, last);
+        let chunk = cipher
+            .decrypt(Nonce::from_slice(&nonce), chunk)
+            .map_err(|_| {
+                invalid_data(
+                    "Could not decrypt shuffle file with the key, or it is truncated",
+                )
+            })?;
+        decrypted.extend_from_slice(&chunk);
+        if last {
+            return Ok(decrypted);
+        }
+        chunk_index += 1;
+        chunks = rest;
+    }
+}
+
+fn chunk_nonce(nonce_prefix: &[u8], chunk_index: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[..NONCE_PREFIX_SIZE].copy_from_slice(nonce_prefix);
+    nonce[NONCE_PREFIX_SIZE..11].copy_from_slice(&chunk_index.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A shuffle file being written, which is encrypted if its job has a key. The Arrow IPC
+/// writer owns its output, so it is given a clone of the writer and the file is
+/// completed with [Self::finish] once the IPC writer is finished.
+#[derive(Clone)]
+pub struct ShuffleFileWriter(Arc<Mutex<ShuffleFile>>);
+
+enum ShuffleFile {
+    Plain(File),
+    Encrypted(EncryptedWriter<File>),
+    Finished,
+}
+
+impl ShuffleFileWriter {
+    /// Completes the file once everything was written to it
+    pub fn finish(&self) -> io::Result<()> {
+        let mut file = self.0.lock().unwrap();
+        match std::mem::replace(&mut *file, ShuffleFile::Finished) {
+            ShuffleFile::Plain(mut file) => file.flush(),
+            ShuffleFile::Encrypted(writer) => writer.finish().map(|_| ()),
+            ShuffleFile::Finished => Ok(()),
+        }
+    }
+}
+
+impl Write for ShuffleFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut *self.0.lock().unwrap() {
+            ShuffleFile::Plain(file) => file.write(buf),
+            ShuffleFile::Encrypted(writer) => writer.write(buf),
+            ShuffleFile::Finished => Err(finished()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut *self.0.lock().unwrap() {
+            ShuffleFile::Plain(file) => file.flush(),
+            ShuffleFile::Encrypted(writer) => writer.flush(),
+            ShuffleFile::Finished => Err(finished()),
+        }
+    }
+}
+
+fn finished() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "Shuffle file was already finished")
+}
+
+/// A shuffle file being read. Encrypted files are decrypted into memory when they are
+/// opened, as the Arrow IPC reader seeks to the footer at the end of the file.
+pub enum ShuffleFileReader {
+    Plain(File),
+    Decrypted(Cursor<Vec<u8>>),
+}
+
+impl Read for ShuffleFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ShuffleFileReader::Plain(file) => file.read(buf),
+            ShuffleFileReader::Decrypted(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Seek for ShuffleFileReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            ShuffleFileReader::Plain(file) => file.seek(pos),
+            ShuffleFileReader::Decrypted(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+/// Creates the shuffle file at `path`, which is encrypted with `key` if any
+pub fn create_shuffle_file(
+    path: &str,
+    key: Option<&ShuffleKey>,
+) -> io::Result<ShuffleFileWriter> {
+    let file = File::create(path)?;
+    let file = match key {
+        Some(key) => ShuffleFile::Encrypted(EncryptedWriter::try_new(file, key)?),
+        None => ShuffleFile::Plain(file),
+    };
+    Ok(ShuffleFileWriter(Arc::new(Mutex::new(file))))
+}
+
+/// Opens the shuffle file at `path`, which was encrypted with `key` if any
+pub fn open_shuffle_file(
+    path: &str,
+    key: Option<&ShuffleKey>,
+) -> io::Result<ShuffleFileReader> {
+    match key {
+        Some(_) => Ok(ShuffleFileReader::Decrypted(Cursor::new(
+            read_shuffle_file(path, key)?,
+        ))),
+        None => Ok(ShuffleFileReader::Plain(File::open(path)?)),
+    }
+}
+
+/// Reads the plain contents of the shuffle file at `path`, which was encrypted with
+/// `key` if any
+pub fn read_shuffle_file(path: &str, key: Option<&ShuffleKey>) -> io::Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    match key {
+        Some(key) => decrypt(&data, key),
+        None => Ok(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt(data: &[u8], key: &ShuffleKey, write_size: usize) -> Vec<u8> {
+        let mut writer = EncryptedWriter::try_new(vec![], key).unwrap();
+        for part in data.chunks(write_size) {
+            writer.write_all(part).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let key = ShuffleKey::generate();
+        let data: Vec<u8> = (0..3 * CHUNK_SIZE + 10).map(|i| i as u8).collect();
+        for write_size in &[1000, CHUNK_SIZE, 2 * CHUNK_SIZE + 1] {
+            let encrypted = encrypt(&data, &key, *write_size);
+            assert!(!encrypted.windows(1000).any(|w| w == &data[..1000]));
+            assert_eq!(decrypt(&encrypted, &key).unwrap(), data);
+        }
+        // flushing in the middle of a chunk starts a new one
+        let mut writer = EncryptedWriter::try_new(vec![], &key).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b" world").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            decrypt(&writer.finish().unwrap(), &key).unwrap(),
+            b"hello world"
+        );
+        assert_eq!(decrypt(&encrypt(b"", &key, 1), &key).unwrap(), b"");
+    }
+
+    #[test]
+    fn authentication() {
+        let key = ShuffleKey::generate();
+        let encrypted = encrypt(b"hello world", &key, 5);
+        assert!(decrypt(&encrypted, &ShuffleKey::generate()).is_err());
+        assert!(decrypt(b"hello world", &key).is_err());
+        let mut modified = encrypted.clone();
+        *modified.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&modified, &key).is_err());
+        assert!(decrypt(&encrypted[..encrypted.len() - 1], &key).is_err());
+
+        // a file that is cut at the boundary of a chunk misses its last chunk
+        let data = vec![7; 2 * CHUNK_SIZE];
+        let encrypted = encrypt(&data, &key, CHUNK_SIZE);
+        let first_chunk = MAGIC.len() + NONCE_PREFIX_SIZE + 4 + CHUNK_SIZE + 16;
+        assert!(decrypt(&encrypted[..first_chunk], &key).is_err());
+        let mut writer = EncryptedWriter::try_new(vec![], &key).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.flush().unwrap();
+        assert!(decrypt(&writer.inner, &key).is_err());
+    }
+
+    #[test]
+    fn keys() {
+        let key = ShuffleKey::generate();
+        assert_ne!(key.as_bytes(), ShuffleKey::generate().as_bytes());
+        assert_eq!(
+            ShuffleKey::from_optional_bytes(key.as_bytes()).unwrap(),
+            Some(key.clone())
+        );
+        assert_eq!(ShuffleKey::from_optional_bytes(&[]).unwrap(), None);
+        assert!(ShuffleKey::from_optional_bytes(&[1, 2, 3]).is_err());
+        assert_eq!(format!("{:?}", key), "ShuffleKey(..)");
+    }
+}
@@ -0,0 +1,340 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::{any::Any, pin::Pin};
+
+use crate::error::{BallistaError, Result as BallistaResult};
+use crate::memory_stream::MemoryStream;
+
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::{Schema, SchemaRef};
+use datafusion::arrow::ipc::reader::{FileReader, StreamReader};
+use datafusion::arrow::ipc::writer::StreamWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::datasource::Statistics;
+use datafusion::physical_plan::{DisplayFormatType, ExecutionPlan, Partitioning};
+use datafusion::{
+    error::{DataFusionError, Result},
+    physical_plan::RecordBatchStream,
+};
+
+/// Where the batches of a [BroadcastExec] are read from
+#[derive(Clone)]
+enum BroadcastSource {
+    /// The batches registered by the client, which the scheduler sends to the executors
+    Memory(Arc<Vec<RecordBatch>>),
+    /// The file that an executor saved the broadcast batches to
+    File(String),
+    /// The batches have not been located on this executor yet
+    Unresolved,
+}
+
+/// BroadcastExec reads a small set of batches, such as a dimension table, that the
+/// scheduler sends once per job to every executor, instead of every task reading it
+/// from storage.
+///
+/// Only the name of the broadcast variable is serialized with the plan. Executors
+/// resolve it to the file they saved the batches to, see [BroadcastExec::with_path].
+#[derive(Clone)]
+pub struct BroadcastExec {
+    /// Name of the broadcast variable
+    name: String,
+    /// Schema of the broadcast batches
+    schema: SchemaRef,
+    /// Indices of the columns that are read
+    projection: Vec<usize>,
+    /// Schema of the columns that are read
+    projected_schema: SchemaRef,
+    source: BroadcastSource,
+}
+
+impl BroadcastExec {
+    /// Create a BroadcastExec that reads the `batches` of the broadcast variable `name`
+    pub fn try_new(
+        name: &str,
+        schema: SchemaRef,
+        batches: Arc<Vec<RecordBatch>>,
+        projection: Option<Vec<usize>>,
+    ) -> Result<Self> {
+        Self::try_new_with_source(
+            name,
+            schema,
+            projection,
+            BroadcastSource::Memory(batches),
+        )
+    }
+
+    /// Create a BroadcastExec for the broadcast variable `name` that is yet to be
+    /// located on the executor that runs it
+    pub fn try_new_unresolved(
+        name: &str,
+        schema: SchemaRef,
+        projection: Option<Vec<usize>>,
+    ) -> Result<Self> {
+        Self::try_new_with_source(name, schema, projection, BroadcastSource::Unresolved)
+    }
+
+    fn try_new_with_source(
+        name: &str,
+        schema: SchemaRef,
+        projection: Option<Vec<usize>>,
+        source: BroadcastSource,
+    ) -> Result<Self> {
+        validate_broadcast_name(name)
+            .map_err(|e| DataFusionError::Plan(e.to_string()))?;
+        let projection =
+            projection.unwrap_or_else(|| (0..schema.fields().len()).collect());
+        let projected_schema = Arc::new(schema.project(&projection)?);
+        Ok(Self {
+            name: name.to_owned(),
+            schema,
+            projection,
+            projected_schema,
+            source,
+        })
+    }
+
+    /// Name of the broadcast variable
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Schema of the broadcast batches, before the projection
+    pub fn broadcast_schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Indices of the columns that are read
+    pub fn projection(&self) -> &[usize] {
+        &self.projection
+    }
+
+    /// The batches to send to the executors, if this plan was created with them
+    pub fn batches(&self) -> Option<Arc<Vec<RecordBatch>>> {
+        match &self.source {
+            BroadcastSource::Memory(batches) => Some(batches.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of this plan that reads the batches from the IPC file at `path`
+    pub fn with_path(&self, path: String) -> Self {
+        Self {
+            source: BroadcastSource::File(path),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for BroadcastExec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the batches are left out, they are not part of the serialized plan
+        f.debug_struct("BroadcastExec")
+            .field("name", &self.name)
+            .field("schema", &self.schema)
+            .field("projection", &self.projection)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for BroadcastExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.projected_schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        &self,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Err(DataFusionError::Plan(
+            "Ballista BroadcastExec does not support with_new_children()".to_owned(),
+        ))
+    }
+
+    async fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Pin<Box<dyn RecordBatchStream + Send + Sync>>> {
+        if partition != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "BroadcastExec invalid partition {}",
+                partition
+            )));
+        }
+        let batches = match &self.source {
+            BroadcastSource::Memory(batches) => batches.as_ref().clone(),
+            BroadcastSource::File(path) => {
+                let file = File::open(path).map_err(|e| {
+                    DataFusionError::Execution(format!(
+                        "Broadcast variable {} was not received by this executor: {}",
+                        self.name, e
+                    ))
+                })?;
+                FileReader::try_new(file)?.collect::<std::result::Result<_, _>>()?
+            }
+            BroadcastSource::Unresolved => {
+                return Err(DataFusionError::Plan(format!(
+                    "Broadcast variable {} must be resolved by the executor",
+                    self.name
+                )))
+            }
+        };
+        Ok(Box::pin(MemoryStream::try_new(
+            batches,
+            self.projected_schema.clone(),
+            Some(self.projection.clone()),
+        )?))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(f, "BroadcastExec: name={}", self.name)
+            }
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        match &self.source {
+            BroadcastSource::Memory(batches) => Statistics {
+                num_rows: Some(batches.iter().map(|b| b.num_rows()).sum()),
+                ..Statistics::default()
+            },
+            _ => Statistics::default(),
+        }
+    }
+}
+
+/// Checks that `name` can be used as the name of a broadcast variable, which is also
+/// the name of the file that executors save it to
+pub fn validate_broadcast_name(name: &str) -> BallistaResult<()> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(BallistaError::General(format!(
+            "Invalid broadcast variable name '{}', only letters, digits, '_' and '-' are allowed",
+            name
+        )))
+    }
+}
+
+/// Encodes `batches` in the Arrow IPC stream format
+pub fn encode_batches(
+    schema: &Schema,
+    batches: &[RecordBatch],
+) -> BallistaResult<Vec<u8>> {
+    let mut writer = StreamWriter::try_new(vec![], schema)?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+/// Decodes batches encoded by [encode_batches]
+pub fn decode_batches(bytes: &[u8]) -> BallistaResult<(SchemaRef, Vec<RecordBatch>)> {
+    let reader = StreamReader::try_new(Cursor::new(bytes))?;
+    let schema = reader.schema();
+    let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((schema, batches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::collect_stream;
+    use datafusion::arrow::array::{Int32Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field};
+
+    fn batches() -> (SchemaRef, Vec<RecordBatch>) {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["a", "b"])),
+            ],
+        )
+        .unwrap();
+        (schema, vec![batch])
+    }
+
+    #[tokio::test]
+    async fn read_projected_batches() -> Result<()> {
+        let (schema, batches) = batches();
+        let exec =
+            BroadcastExec::try_new("dim", schema, Arc::new(batches), Some(vec![1]))?;
+        assert_eq!(exec.schema().field(0).name(), "name");
+
+        let mut stream = exec.execute(0).await?;
+        let result = collect_stream(&mut stream).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].num_columns(), 1);
+        assert_eq!(result[0].num_rows(), 2);
+
+        let unresolved =
+            BroadcastExec::try_new_unresolved("dim", exec.broadcast_schema(), None)?;
+        assert!(unresolved.execute(0).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn encode_and_decode_batches() -> BallistaResult<()> {
+        let (schema, batches) = batches();
+        let bytes = encode_batches(&schema, &batches)?;
+        let (decoded_schema, decoded) = decode_batches(&bytes)?;
+        assert_eq!(schema, decoded_schema);
+        assert_eq!(batches.len(), decoded.len());
+        assert_eq!(batches[0].column(1).as_ref(), decoded[0].column(1).as_ref());
+        Ok(())
+    }
+
+    #[test]
+    fn broadcast_names() {
+        assert!(validate_broadcast_name("dim_date-2021").is_ok());
+        assert!(validate_broadcast_name("").is_err());
+        assert!(validate_broadcast_name("../dim").is_err());
+    }
+}
@@ -17,30 +17,44 @@
 
 use std::any::Any;
 use std::convert::TryInto;
+use std::io::Cursor;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::client::BallistaClient;
+use crate::client::{create_channel, scheduler_request, BallistaClient};
 use crate::config::BallistaConfig;
+use crate::memory_stream::MemoryStream;
+use crate::object_store::{is_object_store_url, object_store_for};
+use crate::result_cache::{cache_key, ResultCache};
+use crate::serde::compression::encode_chunks;
 use crate::serde::protobuf::{
     execute_query_params::Query, job_status, scheduler_grpc_client::SchedulerGrpcClient,
-    ExecuteQueryParams, GetJobStatusParams, GetJobStatusResult, KeyValuePair,
-    PartitionLocation,
+    ExecuteQueryChunk, ExecuteQueryParams, ExecutorMetadata, GetJobStatusParams,
+    GetJobStatusResult, KeyValuePair, PartitionId, PartitionLocation, PlanCompression,
+};
+use crate::serde::protocol::{
+    executor_protocol_version, RESULTS_SERVICE_PROTOCOL_VERSION,
 };
 use crate::utils::WrappedStream;
 
 use datafusion::arrow::datatypes::{Schema, SchemaRef};
+use datafusion::arrow::error::{ArrowError, Result as ArrowResult};
+use datafusion::arrow::ipc::reader::FileReader;
+use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::error::{DataFusionError, Result};
 use datafusion::logical_plan::LogicalPlan;
 use datafusion::physical_plan::{
-    ExecutionPlan, Partitioning, RecordBatchStream, SendableRecordBatchStream,
+    common, ExecutionPlan, Partitioning, RecordBatchStream, SendableRecordBatchStream,
 };
 
 use async_trait::async_trait;
+use futures::channel::mpsc;
 use futures::future;
-use futures::StreamExt;
-use log::{error, info};
+use futures::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use prost::Message;
+use tonic::transport::Channel;
 
 /// This operator sends a logial plan to a Ballista scheduler for execution and
 /// polls the scheduler until the query is complete and then fetches the resulting
@@ -54,6 +68,8 @@ pub struct DistributedQueryExec {
     config: BallistaConfig,
     /// Logical plan to execute
     plan: LogicalPlan,
+    /// Cache of the results of the queries of the client, if it is enabled
+    result_cache: Option<Arc<ResultCache>>,
 }
 
 impl DistributedQueryExec {
@@ -62,7 +78,92 @@ impl DistributedQueryExec {
             scheduler_url,
             config,
             plan,
+            result_cache: None,
+        }
+    }
+
+    /// Answer the query from `result_cache` if it has its results, and cache the
+    /// results otherwise
+    pub fn with_result_cache(mut self, result_cache: Option<Arc<ResultCache>>) -> Self {
+        self.result_cache = result_cache;
+        self
+    }
+
+    /// Submit the query to the scheduler and fetch its results once it completed
+    async fn execute_query(&self) -> Result<SendableRecordBatchStream> {
+        info!("Connecting to Ballista scheduler at {}", self.scheduler_url);
+
+        let mut scheduler = create_channel(&self.scheduler_url)
+            .await
+            .map(SchedulerGrpcClient::new)
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+
+        let schema: Schema = self.plan.schema().as_ref().clone().into();
+
+        let params = ExecuteQueryParams {
+            query: Some(Query::LogicalPlan(
+                (&self.plan)
+                    .try_into()
+                    .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?,
+            )),
+            settings: self
+                .config
+                .settings()
+                .iter()
+                .map(|(k, v)| KeyValuePair {
+                    key: k.to_owned(),
+                    value: v.to_owned(),
+                })
+                .collect::<Vec<_>>(),
+            session_id: String::new(),
+        };
+
+        // plans that could exceed the message size limits are streamed in chunks
+        let compression = self.config.plan_compression();
+        let chunk_size = self.config.plan_chunk_size();
+        let result = if compression != PlanCompression::None
+            || params.encoded_len() > chunk_size
+        {
+            let chunks = encode_chunks(&params, compression, chunk_size)
+                .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+            debug!(
+                "Streaming a query of {} bytes to the scheduler in {} chunks",
+                params.encoded_len(),
+                chunks.len()
+            );
+            let chunks = chunks.into_iter().map(move |data| ExecuteQueryChunk {
+                data,
+                compression: compression as i32,
+            });
+            scheduler
+                .execute_query_stream(scheduler_request(futures::stream::iter(chunks)))
+                .await
+        } else {
+            scheduler.execute_query(scheduler_request(params)).await
+        }
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
+        .into_inner();
+
+        if !result.unsupported_nodes.is_empty() {
+            let unsupported = result
+                .unsupported_nodes
+                .iter()
+                .map(|node| format!("{}: {}", node.operator, node.reason))
+                .collect::<Vec<_>>();
+            return Err(DataFusionError::NotImplemented(format!(
+                "The plan contains operators that cannot be distributed by Ballista:\n{}",
+                unsupported.join("\n")
+            )));
         }
+        let job_id = result.job_id;
+
+        wait_for_job_results(
+            &mut scheduler,
+            &job_id,
+            Some(Arc::new(schema)),
+            self.config.results_page_rows(),
+        )
+        .await
     }
 }
 
@@ -88,11 +189,7 @@ impl ExecutionPlan for DistributedQueryExec {
         &self,
         _children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(DistributedQueryExec::new(
-            self.scheduler_url.clone(),
-            self.config.clone(),
-            self.plan.clone(),
-        )))
+        Ok(Arc::new(self.clone()))
     }
 
     async fn execute(
@@ -101,113 +198,322 @@ impl ExecutionPlan for DistributedQueryExec {
     ) -> datafusion::error::Result<SendableRecordBatchStream> {
         assert_eq!(0, partition);
 
-        info!("Connecting to Ballista scheduler at {}", self.scheduler_url);
+        let result_cache = match &self.result_cache {
+            Some(result_cache) => result_cache,
+            None => return self.execute_query().await,
+        };
+        let key = match cache_key(&self.plan, &self.config) {
+            Some(key) => key,
+            None => {
+                debug!("Bypassing the result cache for a query with volatile functions");
+                return self.execute_query().await;
+            }
+        };
+        if let Some((schema, batches)) = result_cache.get(&key) {
+            debug!("Answering the query from the result cache");
+            return Ok(Box::pin(MemoryStream::try_new(batches, schema, None)?));
+        }
+        let stream = self.execute_query().await?;
+        let schema = stream.schema();
+        let batches = common::collect(stream).await?;
+        result_cache.insert(key, schema.clone(), batches.clone());
+        Ok(Box::pin(MemoryStream::try_new(batches, schema, None)?))
+    }
+}
 
-        let mut scheduler = SchedulerGrpcClient::connect(self.scheduler_url.clone())
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+/// Attach to a job that was previously submitted to the scheduler at `scheduler_url`,
+/// wait for it to complete and then fetch the resulting batches directly from the
+/// executors that hold the results from the final query stage.
+///
+/// This allows a client other than the one that submitted the query to retrieve its
+/// results, as long as it knows the job id. The results are fetched `page_rows` rows
+/// per request, or a partition per request if 0.
+pub async fn fetch_job_results(
+    scheduler_url: String,
+    job_id: &str,
+    page_rows: usize,
+) -> Result<SendableRecordBatchStream> {
+    info!("Connecting to Ballista scheduler at {}", scheduler_url);
 
-        let schema: Schema = self.plan.schema().as_ref().clone().into();
+    let mut scheduler = create_channel(&scheduler_url)
+        .await
+        .map(SchedulerGrpcClient::new)
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
 
-        let job_id = scheduler
-            .execute_query(ExecuteQueryParams {
-                query: Some(Query::LogicalPlan(
-                    (&self.plan)
-                        .try_into()
-                        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?,
-                )),
-                settings: self
-                    .config
-                    .settings()
-                    .iter()
-                    .map(|(k, v)| KeyValuePair {
-                        key: k.to_owned(),
-                        value: v.to_owned(),
-                    })
-                    .collect::<Vec<_>>(),
-            })
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
-            .into_inner()
-            .job_id;
+    wait_for_job_results(&mut scheduler, job_id, None, page_rows).await
+}
 
-        let mut prev_status: Option<job_status::Status> = None;
+/// Poll the scheduler until the job is complete and then fetch the final stage
+/// partitions, `page_rows` rows per request. If `schema` is not provided, the schema
+/// reported by the scheduler for the final stage is used.
+async fn wait_for_job_results(
+    scheduler: &mut SchedulerGrpcClient<Channel>,
+    job_id: &str,
+    schema: Option<SchemaRef>,
+    page_rows: usize,
+) -> Result<SendableRecordBatchStream> {
+    let mut prev_status: Option<job_status::Status> = None;
 
-        loop {
-            let GetJobStatusResult { status } = scheduler
-                .get_job_status(GetJobStatusParams {
-                    job_id: job_id.clone(),
-                })
-                .await
-                .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
-                .into_inner();
-            let status = status.and_then(|s| s.status).ok_or_else(|| {
-                DataFusionError::Internal("Received empty status message".to_owned())
-            })?;
-            let wait_future = tokio::time::sleep(Duration::from_millis(100));
-            let has_status_change = prev_status.map(|x| x != status).unwrap_or(true);
-            match status {
-                job_status::Status::Queued(_) => {
-                    if has_status_change {
-                        info!("Job {} still queued...", job_id);
-                    }
-                    wait_future.await;
-                    prev_status = Some(status);
-                }
-                job_status::Status::Running(_) => {
-                    if has_status_change {
-                        info!("Job {} is running...", job_id);
-                    }
-                    wait_future.await;
-                    prev_status = Some(status);
+    loop {
+        let GetJobStatusResult { status } = scheduler
+            .get_job_status(scheduler_request(GetJobStatusParams {
+                job_id: job_id.to_owned(),
+            }))
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?
+            .into_inner();
+        let status = status.and_then(|s| s.status).ok_or_else(|| {
+            DataFusionError::Internal("Received empty status message".to_owned())
+        })?;
+        let wait_future = tokio::time::sleep(Duration::from_millis(100));
+        let has_status_change = prev_status.map(|x| x != status).unwrap_or(true);
+        match status {
+            job_status::Status::Queued(_) => {
+                if has_status_change {
+                    info!("Job {} still queued...", job_id);
                 }
-                job_status::Status::Failed(err) => {
-                    let msg = format!("Job {} failed: {}", job_id, err.error);
-                    error!("{}", msg);
-                    break Err(DataFusionError::Execution(msg));
+                wait_future.await;
+                prev_status = Some(status);
+            }
+            job_status::Status::Running(_) => {
+                if has_status_change {
+                    info!("Job {} is running...", job_id);
                 }
-                job_status::Status::Completed(completed) => {
-                    let result = future::join_all(
-                        completed
-                            .partition_location
-                            .into_iter()
-                            .map(fetch_partition),
-                    )
-                    .await
-                    .into_iter()
-                    .collect::<Result<Vec<_>>>()?;
+                wait_future.await;
+                prev_status = Some(status);
+            }
+            job_status::Status::Failed(err) => {
+                let msg = format!("Job {} failed: {}", job_id, err.error);
+                error!("{}", msg);
+                break Err(DataFusionError::Execution(msg));
+            }
+            job_status::Status::Completed(completed) => {
+                let schema = match schema {
+                    Some(schema) => schema,
+                    None => {
+                        let schema: Schema = completed
+                            .schema
+                            .as_ref()
+                            .ok_or_else(|| {
+                                DataFusionError::Execution(format!(
+                                    "Scheduler did not report a schema for job {}",
+                                    job_id
+                                ))
+                            })?
+                            .try_into()
+                            .map_err(|e| {
+                                DataFusionError::Execution(format!("{:?}", e))
+                            })?;
+                        Arc::new(schema)
+                    }
+                };
 
-                    let result = WrappedStream::new(
-                        Box::pin(futures::stream::iter(result).flatten()),
-                        Arc::new(schema),
-                    );
-                    break Ok(Box::pin(result));
-                }
-            };
-        }
+                let result = future::join_all(
+                    completed
+                        .partition_location
+                        .into_iter()
+                        .map(|location| fetch_partition(location, page_rows)),
+                )
+                .await
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+                let result = WrappedStream::new(
+                    Box::pin(futures::stream::iter(result).flatten()),
+                    schema,
+                );
+                break Ok(Box::pin(result));
+            }
+        };
     }
 }
 
 async fn fetch_partition(
     location: PartitionLocation,
+    page_rows: usize,
 ) -> Result<SendableRecordBatchStream> {
+    if is_object_store_url(&location.path) {
+        return fetch_object(&location.path).await;
+    }
     let metadata = location.executor_meta.ok_or_else(|| {
         DataFusionError::Internal("Received empty executor metadata".to_owned())
     })?;
     let partition_id = location.partition_id.ok_or_else(|| {
         DataFusionError::Internal("Received empty partition id".to_owned())
     })?;
-    let mut ballista_client =
-        BallistaClient::try_new(metadata.host.as_str(), metadata.port as u16)
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+    if executor_protocol_version(metadata.protocol_version)
+        >= RESULTS_SERVICE_PROTOCOL_VERSION
+    {
+        return fetch_results(metadata, partition_id, location.auth_token, page_rows)
+            .await;
+    }
+    // older executors only serve the shuffle files at the paths the scheduler reports
+    let mut ballista_client = connect(&metadata).await?;
     Ok(ballista_client
         .fetch_partition(
             &partition_id.job_id,
             partition_id.stage_id as usize,
             partition_id.partition_id as usize,
             &location.path,
+            &location.auth_token,
         )
         .await
         .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?)
 }
+
+async fn connect(metadata: &ExecutorMetadata) -> Result<BallistaClient> {
+    BallistaClient::try_new_local_or_remote(
+        metadata.host.as_str(),
+        metadata.port as u16,
+        &metadata.flight_socket,
+    )
+    .await
+    .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))
+}
+
+/// Number of times a fetch of results that failed is resumed
+const FETCH_RESULTS_RETRIES: usize = 3;
+
+/// Outcome of fetching a page of results
+enum PageFetch {
+    /// All the rows of the page were fetched
+    Complete(usize),
+    /// The fetch failed after the given number of rows
+    Failed(usize, DataFusionError),
+    /// The consumer of the results dropped them
+    Cancelled,
+}
+
+/// Fetches a partition of the results of a job from the results service of the
+/// executor that holds it, `page_rows` rows per request or all of them at once if 0.
+/// A fetch that fails is resumed from the first row that was not received.
+async fn fetch_results(
+    metadata: ExecutorMetadata,
+    partition_id: PartitionId,
+    auth_token: String,
+    page_rows: usize,
+) -> Result<SendableRecordBatchStream> {
+    let mut client = connect(&metadata).await?;
+    let schema = client
+        .fetch_results_schema(
+            &partition_id.job_id,
+            partition_id.stage_id as usize,
+            partition_id.partition_id as usize,
+            &auth_token,
+        )
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+
+    // the pages are fetched by a task, as the futures of the requests are not Sync
+    let (mut tx, rx) = mpsc::channel(2);
+    tokio::spawn(async move {
+        let mut client = Some(client);
+        let mut offset = 0;
+        let mut failures = 0;
+        loop {
+            let fetch = match client.as_mut() {
+                Some(client) => {
+                    fetch_page(
+                        client,
+                        &partition_id,
+                        &auth_token,
+                        offset,
+                        page_rows,
+                        &mut tx,
+                    )
+                    .await
+                }
+                None => match connect(&metadata).await {
+                    Ok(connected) => {
+                        client = Some(connected);
+                        continue;
+                    }
+                    Err(e) => PageFetch::Failed(0, e),
+                },
+            };
+            match fetch {
+                // a page with fewer rows than requested is the last one
+                PageFetch::Complete(rows) if page_rows == 0 || rows < page_rows => break,
+                PageFetch::Complete(rows) => {
+                    offset += rows;
+                    failures = 0;
+                }
+                PageFetch::Failed(rows, e) => {
+                    offset += rows;
+                    if failures == FETCH_RESULTS_RETRIES {
+                        let _ =
+                            tx.send(Err(ArrowError::ExternalError(Box::new(e)))).await;
+                        break;
+                    }
+                    failures += 1;
+                    warn!(
+                        "Resuming the fetch of results {:?} from row {}: {}",
+                        partition_id, offset, e
+                    );
+                    client = None;
+                    tokio::time::sleep(Duration::from_millis(100 * failures as u64))
+                        .await;
+                }
+                PageFetch::Cancelled => break,
+            }
+        }
+    });
+    Ok(Box::pin(WrappedStream::new(Box::pin(rx), schema)))
+}
+
+/// Fetches the `page_rows` rows of a partition of results that start at row `offset`,
+/// or all the remaining rows if `page_rows` is 0, and sends them to `tx`
+async fn fetch_page(
+    client: &mut BallistaClient,
+    partition_id: &PartitionId,
+    auth_token: &str,
+    offset: usize,
+    page_rows: usize,
+    tx: &mut mpsc::Sender<ArrowResult<RecordBatch>>,
+) -> PageFetch {
+    let mut stream = match client
+        .fetch_results(
+            &partition_id.job_id,
+            partition_id.stage_id as usize,
+            partition_id.partition_id as usize,
+            offset as u64,
+            page_rows as u64,
+            auth_token,
+        )
+        .await
+    {
+        Ok(stream) => stream,
+        Err(e) => {
+            return PageFetch::Failed(0, DataFusionError::Execution(format!("{:?}", e)))
+        }
+    };
+    let mut rows = 0;
+    while let Some(batch) = stream.next().await {
+        match batch {
+            Ok(batch) => {
+                let num_rows = batch.num_rows();
+                if tx.send(Ok(batch)).await.is_err() {
+                    return PageFetch::Cancelled;
+                }
+                rows += num_rows;
+            }
+            Err(e) => return PageFetch::Failed(rows, DataFusionError::ArrowError(e)),
+        }
+    }
+    PageFetch::Complete(rows)
+}
+
+/// Reads the results that the final stage wrote to an object store, without going
+/// through the executor that wrote them
+async fn fetch_object(url: &str) -> Result<SendableRecordBatchStream> {
+    let store = object_store_for(url)
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+    let data = store
+        .get(url)
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+    let reader = FileReader::try_new(Cursor::new(data))?;
+    let schema = reader.schema();
+    let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(Box::pin(MemoryStream::try_new(batches, schema, None)?))
+}
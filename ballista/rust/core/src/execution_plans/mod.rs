@@ -18,12 +18,16 @@
 //! This module contains execution plans that are needed to distribute Datafusion's execution plans into
 //! several Ballista executors.
 
+mod broadcast;
 mod distributed_query;
 mod shuffle_reader;
 mod shuffle_writer;
 mod unresolved_shuffle;
 
-pub use distributed_query::DistributedQueryExec;
+pub use broadcast::{
+    decode_batches, encode_batches, validate_broadcast_name, BroadcastExec,
+};
+pub use distributed_query::{fetch_job_results, DistributedQueryExec};
 pub use shuffle_reader::ShuffleReaderExec;
 pub use shuffle_writer::ShuffleWriterExec;
 pub use unresolved_shuffle::UnresolvedShuffleExec;
@@ -161,16 +161,20 @@ async fn fetch_partition(
 ) -> Result<Pin<Box<dyn RecordBatchStream + Send + Sync>>> {
     let metadata = &location.executor_meta;
     let partition_id = &location.partition_id;
-    let mut ballista_client =
-        BallistaClient::try_new(metadata.host.as_str(), metadata.port as u16)
-            .await
-            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+    let mut ballista_client = BallistaClient::try_new_local_or_remote(
+        metadata.host.as_str(),
+        metadata.port as u16,
+        metadata.flight_socket.as_deref().unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
     Ok(ballista_client
         .fetch_partition(
             &partition_id.job_id,
             partition_id.stage_id as usize,
             partition_id.partition_id as usize,
             &location.path,
+            &location.auth_token,
         )
         .await
         .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?)
@@ -20,15 +20,18 @@
 //! partition is re-partitioned and streamed to disk in Arrow IPC format. Future stages of the query
 //! will use the ShuffleReaderExec to read these results.
 
-use std::fs::File;
 use std::iter::Iterator;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use std::{any::Any, pin::Pin};
 
+use crate::encryption::{
+    create_shuffle_file, read_shuffle_file, ShuffleFileWriter, ShuffleKey,
+};
 use crate::error::BallistaError;
 use crate::memory_stream::MemoryStream;
+use crate::object_store::{join_url, object_store_for};
 use crate::utils;
 
 use crate::serde::protobuf::ShuffleWritePartition;
@@ -43,8 +46,9 @@ use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::arrow::ipc::reader::FileReader;
 use datafusion::arrow::ipc::writer::FileWriter;
 use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::datasource::Statistics;
 use datafusion::error::{DataFusionError, Result};
-use datafusion::physical_plan::hash_utils::create_hashes;
+use datafusion::physical_plan::partition_hash::{create_partition_hashes, HashScheme};
 use datafusion::physical_plan::repartition::RepartitionExec;
 use datafusion::physical_plan::Partitioning::RoundRobinBatch;
 use datafusion::physical_plan::{
@@ -71,6 +75,14 @@ pub struct ShuffleWriterExec {
     work_dir: String,
     /// Optional shuffle output partitioning
     shuffle_output_partitioning: Option<Partitioning>,
+    /// Hash function of a hash output partitioning
+    hash_scheme: HashScheme,
+    /// Object store location that the final stage of the job moves its results to, see
+    /// [crate::config::BALLISTA_RESULTS_PATH]
+    results_path: Option<String>,
+    /// Key that the shuffle files are encrypted with, which is never serialized with
+    /// the plan but set by the executor from the task
+    encryption_key: Option<ShuffleKey>,
     /// Shuffle write metrics
     metrics: ShuffleWriteMetrics,
 }
@@ -79,8 +91,12 @@ pub struct ShuffleWriterExec {
 struct ShuffleWriteMetrics {
     /// Time spend writing batches to shuffle files
     write_time: Arc<SQLMetric>,
+    /// Rows of the input, counted as they are read so that they report the progress
+    /// of a running task
     input_rows: Arc<SQLMetric>,
     output_rows: Arc<SQLMetric>,
+    /// Size of the shuffle files on disk
+    bytes_written: Arc<SQLMetric>,
 }
 
 impl ShuffleWriteMetrics {
@@ -89,6 +105,7 @@ impl ShuffleWriteMetrics {
             write_time: SQLMetric::time_nanos(),
             input_rows: SQLMetric::counter(),
             output_rows: SQLMetric::counter(),
+            bytes_written: SQLMetric::counter(),
         }
     }
 }
@@ -108,10 +125,33 @@ impl ShuffleWriterExec {
             plan,
             work_dir,
             shuffle_output_partitioning,
+            hash_scheme: HashScheme::default(),
+            results_path: None,
+            encryption_key: None,
             metrics: ShuffleWriteMetrics::new(),
         })
     }
 
+    /// Hash the rows of a hash output partitioning with `hash_scheme` rather than the
+    /// default [`HashScheme::Internal`]
+    pub fn with_hash_scheme(mut self, hash_scheme: HashScheme) -> Self {
+        self.hash_scheme = hash_scheme;
+        self
+    }
+
+    /// Move the results of the stage to `results_path` in an object store once they
+    /// are written, which only applies to the unpartitioned final stage of a job
+    pub fn with_results_path(mut self, results_path: Option<String>) -> Self {
+        self.results_path = results_path;
+        self
+    }
+
+    /// Encrypt the shuffle files with `encryption_key`, the key of the job
+    pub fn with_encryption_key(mut self, encryption_key: Option<ShuffleKey>) -> Self {
+        self.encryption_key = encryption_key;
+        self
+    }
+
     /// Get the Job ID for this query stage
     pub fn job_id(&self) -> &str {
         &self.job_id
@@ -127,6 +167,85 @@ impl ShuffleWriterExec {
         self.shuffle_output_partitioning.as_ref()
     }
 
+    /// Get the hash function of a hash output partitioning
+    pub fn hash_scheme(&self) -> HashScheme {
+        self.hash_scheme
+    }
+
+    /// Get the object store location that the results of the stage are moved to
+    pub fn results_path(&self) -> Option<&str> {
+        self.results_path.as_deref()
+    }
+
+    /// Directory of the task that executes `input_partition`, which holds all the files
+    /// that the task writes and is not shared with any other task
+    pub fn task_dir(&self, input_partition: usize) -> PathBuf {
+        let mut path = PathBuf::from(&self.work_dir);
+        path.push(&self.job_id);
+        path.push(&format!("{}", self.stage_id));
+        path.push(&format!("{}", input_partition));
+        path
+    }
+
+    /// Appends `batch` to the shuffle file of `output_partition`, creating the file if
+    /// it is the first batch of the partition
+    fn write_partition(
+        &self,
+        writers: &mut [Option<ShuffleWriter>],
+        task_dir: &Path,
+        output_partition: usize,
+        batch: &RecordBatch,
+    ) -> Result<()> {
+        //TODO optimize so we don't write or fetch empty partitions
+        let start = Instant::now();
+        match &mut writers[output_partition] {
+            Some(w) => {
+                w.write(batch)?;
+            }
+            None => {
+                let mut path = task_dir.to_path_buf();
+                path.push(format!("data-{}.arrow", output_partition));
+                let path = path.to_str().unwrap();
+                info!("Writing results to {}", path);
+
+                let mut writer = ShuffleWriter::new(
+                    path,
+                    self.schema().as_ref(),
+                    self.encryption_key.as_ref(),
+                )?;
+
+                writer.write(batch)?;
+                writers[output_partition] = Some(writer);
+            }
+        }
+        self.metrics.output_rows.add(batch.num_rows());
+        self.metrics.write_time.add_elapsed(start);
+        Ok(())
+    }
+
+    /// Moves the results of `input_partition` from the local file at `path` to the
+    /// results location of the job, returning the URL of the object
+    async fn upload_results(
+        &self,
+        results_path: &str,
+        input_partition: usize,
+        path: &str,
+    ) -> Result<String> {
+        let url = join_url(
+            results_path,
+            &[&self.job_id, &format!("part-{}.arrow", input_partition)],
+        );
+        info!("Uploading results to {}", url);
+        let store = object_store_for(&url)
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        store
+            .put(&url, read_shuffle_file(path, self.encryption_key.as_ref())?)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        std::fs::remove_file(path)?;
+        Ok(url)
+    }
+
     pub async fn execute_shuffle_write(
         &self,
         input_partition: usize,
@@ -135,15 +254,12 @@ impl ShuffleWriterExec {
 
         let mut stream = self.plan.execute(input_partition).await?;
 
-        let mut path = PathBuf::from(&self.work_dir);
-        path.push(&self.job_id);
-        path.push(&format!("{}", self.stage_id));
+        let mut path = self.task_dir(input_partition);
+        std::fs::create_dir_all(&path)?;
 
         match &self.shuffle_output_partitioning {
             None => {
                 let start = Instant::now();
-                path.push(&format!("{}", input_partition));
-                std::fs::create_dir_all(&path)?;
                 path.push("data.arrow");
                 let path = path.to_str().unwrap();
                 info!("Writing results to {}", path);
@@ -152,14 +268,16 @@ impl ShuffleWriterExec {
                 let stats = utils::write_stream_to_disk(
                     &mut stream,
                     path,
+                    self.encryption_key.as_ref(),
                     self.metrics.write_time.clone(),
+                    self.metrics.input_rows.clone(),
                 )
                 .await
                 .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
 
                 self.metrics
-                    .input_rows
-                    .add(stats.num_rows.unwrap_or(0) as usize);
+                    .bytes_written
+                    .add(std::fs::metadata(path)?.len() as usize);
                 self.metrics
                     .output_rows
                     .add(stats.num_rows.unwrap_or(0) as usize);
@@ -172,9 +290,17 @@ impl ShuffleWriterExec {
                     stats
                 );
 
+                let path = match &self.results_path {
+                    Some(results_path) => {
+                        self.upload_results(results_path, input_partition, path)
+                            .await?
+                    }
+                    None => path.to_owned(),
+                };
+
                 Ok(vec![ShuffleWritePartition {
                     partition_id: input_partition as u64,
-                    path: path.to_owned(),
+                    path,
                     num_batches: stats.num_batches.unwrap_or(0),
                     num_rows: stats.num_rows.unwrap_or(0),
                     num_bytes: stats.num_bytes.unwrap_or(0),
@@ -192,7 +318,6 @@ impl ShuffleWriterExec {
                 }
 
                 let hashes_buf = &mut vec![];
-                let random_state = ahash::RandomState::with_seeds(0, 0, 0, 0);
 
                 while let Some(result) = stream.next().await {
                     let input_batch = result?;
@@ -208,9 +333,10 @@ impl ShuffleWriterExec {
                         })
                         .collect::<Result<Vec<_>>>()?;
                     hashes_buf.clear();
-                    hashes_buf.resize(arrays[0].len(), 0);
+                    hashes_buf.resize(input_batch.num_rows(), 0);
                     // Hash arrays and compute buckets based on number of partitions
-                    let hashes = create_hashes(&arrays, &random_state, hashes_buf)?;
+                    let hashes =
+                        create_partition_hashes(&arrays, self.hash_scheme, hashes_buf)?;
                     let mut indices = vec![vec![]; num_output_partitions];
                     for (index, hash) in hashes.iter().enumerate() {
                         indices[(*hash % num_output_partitions as u64) as usize]
@@ -235,34 +361,12 @@ impl ShuffleWriterExec {
                         let output_batch =
                             RecordBatch::try_new(input_batch.schema(), columns)?;
 
-                        // write non-empty batch out
-
-                        //TODO optimize so we don't write or fetch empty partitions
-                        //if output_batch.num_rows() > 0 {
-                        let start = Instant::now();
-                        match &mut writers[output_partition] {
-                            Some(w) => {
-                                w.write(&output_batch)?;
-                            }
-                            None => {
-                                let mut path = path.clone();
-                                path.push(&format!("{}", output_partition));
-                                std::fs::create_dir_all(&path)?;
-
-                                path.push(format!("data-{}.arrow", input_partition));
-                                let path = path.to_str().unwrap();
-                                info!("Writing results to {}", path);
-
-                                let mut writer =
-                                    ShuffleWriter::new(path, stream.schema().as_ref())?;
-
-                                writer.write(&output_batch)?;
-                                writers[output_partition] = Some(writer);
-                            }
-                        }
-                        self.metrics.output_rows.add(output_batch.num_rows());
-                        self.metrics.write_time.add_elapsed(start);
-                        //}
+                        self.write_partition(
+                            &mut writers,
+                            &path,
+                            output_partition,
+                            &output_batch,
+                        )?;
                     }
                 }
 
@@ -272,6 +376,9 @@ impl ShuffleWriterExec {
                     match w {
                         Some(w) => {
                             w.finish()?;
+                            self.metrics
+                                .bytes_written
+                                .add(std::fs::metadata(w.path())?.len() as usize);
                             info!(
                                 "Finished writing shuffle partition {} at {}. Batches: {}. Rows: {}. Bytes: {}.",
                                 i,
@@ -329,13 +436,18 @@ impl ExecutionPlan for ShuffleWriterExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         assert!(children.len() == 1);
-        Ok(Arc::new(ShuffleWriterExec::try_new(
-            self.job_id.clone(),
-            self.stage_id,
-            children[0].clone(),
-            self.work_dir.clone(),
-            self.shuffle_output_partitioning.clone(),
-        )?))
+        Ok(Arc::new(
+            ShuffleWriterExec::try_new(
+                self.job_id.clone(),
+                self.stage_id,
+                children[0].clone(),
+                self.work_dir.clone(),
+                self.shuffle_output_partitioning.clone(),
+            )?
+            .with_hash_scheme(self.hash_scheme)
+            .with_results_path(self.results_path.clone())
+            .with_encryption_key(self.encryption_key.clone()),
+        ))
     }
 
     async fn execute(
@@ -388,11 +500,20 @@ impl ExecutionPlan for ShuffleWriterExec {
         Ok(Box::pin(MemoryStream::try_new(vec![batch], schema, None)?))
     }
 
+    fn statistics(&self) -> Statistics {
+        // the shuffle output holds the rows of the input
+        self.plan.statistics()
+    }
+
     fn metrics(&self) -> HashMap<String, SQLMetric> {
         let mut metrics = HashMap::new();
         metrics.insert("inputRows".to_owned(), (*self.metrics.input_rows).clone());
         metrics.insert("outputRows".to_owned(), (*self.metrics.output_rows).clone());
         metrics.insert("writeTime".to_owned(), (*self.metrics.write_time).clone());
+        metrics.insert(
+            "bytesWritten".to_owned(),
+            (*self.metrics.bytes_written).clone(),
+        );
         metrics
     }
 
@@ -407,7 +528,11 @@ impl ExecutionPlan for ShuffleWriterExec {
                     f,
                     "ShuffleWriterExec: {:?}",
                     self.shuffle_output_partitioning
-                )
+                )?;
+                if self.hash_scheme != HashScheme::default() {
+                    write!(f, ", hash_scheme={}", self.hash_scheme)?;
+                }
+                Ok(())
             }
         }
     }
@@ -424,15 +549,16 @@ fn result_schema() -> SchemaRef {
 
 struct ShuffleWriter {
     path: String,
-    writer: FileWriter<File>,
+    file: ShuffleFileWriter,
+    writer: FileWriter<ShuffleFileWriter>,
     num_batches: u64,
     num_rows: u64,
     num_bytes: u64,
 }
 
 impl ShuffleWriter {
-    fn new(path: &str, schema: &Schema) -> Result<Self> {
-        let file = File::create(path)
+    fn new(path: &str, schema: &Schema, key: Option<&ShuffleKey>) -> Result<Self> {
+        let file = create_shuffle_file(path, key)
             .map_err(|e| {
                 BallistaError::General(format!(
                     "Failed to create partition file at {}: {:?}",
@@ -445,7 +571,8 @@ impl ShuffleWriter {
             num_rows: 0,
             num_bytes: 0,
             path: path.to_owned(),
-            writer: FileWriter::try_new(file, schema)?,
+            writer: FileWriter::try_new(file.clone(), schema)?,
+            file,
         })
     }
 
@@ -463,7 +590,8 @@ impl ShuffleWriter {
     }
 
     fn finish(&mut self) -> Result<()> {
-        self.writer.finish().map_err(DataFusionError::ArrowError)
+        self.writer.finish().map_err(DataFusionError::ArrowError)?;
+        self.file.finish().map_err(DataFusionError::IoError)
     }
 
     fn path(&self) -> &str {
@@ -474,11 +602,15 @@ impl ShuffleWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::encryption::open_shuffle_file;
     use datafusion::arrow::array::{StringArray, StructArray, UInt32Array, UInt64Array};
+    use datafusion::logical_plan::Operator;
     use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
-    use datafusion::physical_plan::expressions::Column;
+    use datafusion::physical_plan::expressions::{binary, lit, Column};
     use datafusion::physical_plan::limit::GlobalLimitExec;
     use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::scalar::ScalarValue;
+    use std::fs::File;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -512,8 +644,16 @@ mod tests {
         );
         let file1 = path.value(1);
         assert!(
-            file1.ends_with("/jobOne/1/1/data-0.arrow")
-                || file1.ends_with("\\jobOne\\1\\1\\data-0.arrow")
+            file1.ends_with("/jobOne/1/0/data-1.arrow")
+                || file1.ends_with("\\jobOne\\1\\0\\data-1.arrow")
+        );
+        let bytes_written: u64 = [file0, file1]
+            .iter()
+            .map(|file| std::fs::metadata(file).unwrap().len())
+            .sum();
+        assert_eq!(
+            bytes_written as usize,
+            query_stage.metrics()["bytesWritten"].value()
         );
 
         let stats = batch.columns()[2]
@@ -568,6 +708,113 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_partitioned_by_expression() -> Result<()> {
+        let input_plan = create_input_plan()?;
+        let schema = input_plan.schema();
+        let work_dir = TempDir::new()?;
+        // all rows have the same key, so they are written to a single partition
+        let key = binary(
+            Arc::new(Column::new("a", 0)),
+            Operator::Modulus,
+            lit(ScalarValue::UInt32(Some(1))),
+            &schema,
+        )?;
+        let query_stage = ShuffleWriterExec::try_new(
+            "jobOne".to_owned(),
+            1,
+            input_plan,
+            work_dir.into_path().to_str().unwrap().to_owned(),
+            Some(Partitioning::Hash(vec![key], 2)),
+        )?;
+        let mut stream = query_stage.execute(0).await?;
+        let batches = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(1, batch.num_rows());
+        let stats = batch.columns()[2]
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .unwrap();
+        let num_rows = stats
+            .column_by_name("num_rows")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(4, num_rows.value(0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_results_path() -> Result<()> {
+        let work_dir = TempDir::new()?;
+        let results_dir = TempDir::new()?;
+        let results_path = format!("file://{}", results_dir.path().to_str().unwrap());
+        let query_stage = ShuffleWriterExec::try_new(
+            "jobOne".to_owned(),
+            1,
+            create_input_plan()?,
+            work_dir.path().to_str().unwrap().to_owned(),
+            None,
+        )?
+        .with_results_path(Some(results_path.clone()));
+        let mut stream = query_stage.execute(1).await?;
+        let batches = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        let path = batches[0].columns()[1]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+
+        // the results are moved from the task directory to the results location
+        assert_eq!(
+            format!("{}/jobOne/part-1.arrow", results_path),
+            path.value(0)
+        );
+        assert!(results_dir.path().join("jobOne/part-1.arrow").exists());
+        assert!(!query_stage.task_dir(1).join("data.arrow").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encryption() -> Result<()> {
+        let work_dir = TempDir::new()?;
+        let key = ShuffleKey::generate();
+        let query_stage = ShuffleWriterExec::try_new(
+            "jobOne".to_owned(),
+            1,
+            create_input_plan()?,
+            work_dir.path().to_str().unwrap().to_owned(),
+            Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 2)),
+        )?
+        .with_encryption_key(Some(key.clone()));
+        let mut stream = query_stage.execute(0).await?;
+        let batches = utils::collect_stream(&mut stream)
+            .await
+            .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
+        let path = batches[0].columns()[1]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+
+        // the files can only be read with the key of the job
+        let mut num_rows = 0;
+        for i in 0..path.len() {
+            assert!(FileReader::try_new(File::open(path.value(i))?).is_err());
+            let file = open_shuffle_file(path.value(i), Some(&key))?;
+            for batch in FileReader::try_new(file)? {
+                num_rows += batch?.num_rows();
+            }
+        }
+        assert_eq!(4, num_rows);
+        Ok(())
+    }
+
     fn create_input_plan() -> Result<Arc<dyn ExecutionPlan>> {
         let schema = Arc::new(Schema::new(vec![
             Field::new("a", DataType::UInt32, true),
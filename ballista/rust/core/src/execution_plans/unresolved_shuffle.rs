@@ -23,6 +23,7 @@ use crate::serde::scheduler::PartitionLocation;
 
 use async_trait::async_trait;
 use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::datasource::datasource::Statistics;
 use datafusion::physical_plan::{DisplayFormatType, ExecutionPlan, Partitioning};
 use datafusion::{
     error::{DataFusionError, Result},
@@ -48,6 +49,14 @@ pub struct UnresolvedShuffleExec {
 
     // The partition count this node will have once it is replaced with a ShuffleReaderExec
     pub output_partition_count: usize,
+
+    // The number of rows that the consumer needs at most, e.g. because of a LIMIT. Once
+    // the completed tasks of the stage produced that many rows, the others are cancelled.
+    pub limit: Option<usize>,
+
+    // The estimated statistics of the output of the stage, which are only known while
+    // the job is planned
+    pub statistics: Statistics,
 }
 
 impl UnresolvedShuffleExec {
@@ -63,8 +72,22 @@ impl UnresolvedShuffleExec {
             schema,
             input_partition_count,
             output_partition_count,
+            limit: None,
+            statistics: Statistics::default(),
         }
     }
+
+    /// Set the estimated statistics of the output of the stage
+    pub fn with_statistics(mut self, statistics: Statistics) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    /// Only wait for the tasks of the stage until they produced `limit` rows
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 }
 
 #[async_trait]
@@ -87,6 +110,10 @@ impl ExecutionPlan for UnresolvedShuffleExec {
         vec![]
     }
 
+    fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+
     fn with_new_children(
         &self,
         _children: Vec<Arc<dyn ExecutionPlan>>,
@@ -112,9 +139,10 @@ impl ExecutionPlan for UnresolvedShuffleExec {
         f: &mut std::fmt::Formatter,
     ) -> std::fmt::Result {
         match t {
-            DisplayFormatType::Default => {
-                write!(f, "UnresolvedShuffleExec")
-            }
+            DisplayFormatType::Default => match self.limit {
+                Some(limit) => write!(f, "UnresolvedShuffleExec: limit={}", limit),
+                None => write!(f, "UnresolvedShuffleExec"),
+            },
         }
     }
 }
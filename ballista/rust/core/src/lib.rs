@@ -31,10 +31,14 @@ pub fn print_version() {
 pub mod client;
 pub mod config;
 pub mod datasource;
+pub mod encryption;
 pub mod error;
 pub mod execution_plans;
 pub mod memory_stream;
+pub mod object_store;
+pub mod result_cache;
 pub mod utils;
+pub mod wasm;
 
 #[macro_use]
 pub mod serde;
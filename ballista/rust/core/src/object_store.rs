@@ -0,0 +1,209 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Object stores that the final stage of a job writes its results to when
+//! [`BALLISTA_RESULTS_PATH`](crate::config::BALLISTA_RESULTS_PATH) is set.
+//!
+//! Results in an object store are fetched by the client directly instead of through the
+//! executors, and remain available after the executors that wrote them are gone. Objects
+//! are addressed by URLs such as `file:///tmp/results/part-0.arrow`, or
+//! `s3://bucket/results/part-0.arrow` if the crate is built with the `s3` feature.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{BallistaError, Result};
+
+use async_trait::async_trait;
+
+/// A store of objects addressed by URL
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Writes `data` to the object at `url`, replacing the object if it exists
+    async fn put(&self, url: &str, data: Vec<u8>) -> Result<()>;
+
+    /// Reads the object at `url`
+    async fn get(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// Returns true if `path` is the URL of an object rather than a path on an executor
+pub fn is_object_store_url(path: &str) -> bool {
+    path.contains("://")
+}
+
+/// Appends the `/` separated `parts` to the URL `base`
+pub fn join_url(base: &str, parts: &[&str]) -> String {
+    let mut url = base.trim_end_matches('/').to_owned();
+    for part in parts {
+        url.push('/');
+        url.push_str(part);
+    }
+    url
+}
+
+/// Returns the store of the objects at `url`, based on the scheme of the URL
+pub fn object_store_for(url: &str) -> Result<Arc<dyn ObjectStore>> {
+    match url.split_once("://") {
+        Some(("file", _)) => Ok(Arc::new(LocalObjectStore)),
+        #[cfg(feature = "s3")]
+        Some(("s3", _)) => Ok(Arc::new(s3::S3ObjectStore::new())),
+        #[cfg(not(feature = "s3"))]
+        Some(("s3", _)) => Err(BallistaError::NotImplemented(format!(
+            "Cannot access {}, Ballista was built without the s3 feature",
+            url
+        ))),
+        Some((scheme, _)) => Err(BallistaError::NotImplemented(format!(
+            "Unsupported object store scheme '{}' in {}",
+            scheme, url
+        ))),
+        None => Err(BallistaError::General(format!(
+            "Invalid object store URL '{}', expected scheme://path",
+            url
+        ))),
+    }
+}
+
+/// Store of the files of the local file system, or of a file system that is mounted at
+/// the same path on the executors and the clients, addressed by `file://` URLs
+#[derive(Debug, Default)]
+pub struct LocalObjectStore;
+
+impl LocalObjectStore {
+    fn path(url: &str) -> Result<&Path> {
+        url.strip_prefix("file://")
+            .map(Path::new)
+            .ok_or_else(|| BallistaError::General(format!("Invalid file URL '{}'", url)))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    async fn put(&self, url: &str, data: Vec<u8>) -> Result<()> {
+        let path = Self::path(url)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    async fn get(&self, url: &str) -> Result<Vec<u8>> {
+        Ok(std::fs::read(Self::path(url)?)?)
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use super::*;
+
+    use futures::TryStreamExt;
+    use rusoto_core::Region;
+    use rusoto_s3::{GetObjectRequest, PutObjectRequest, S3Client, S3};
+
+    /// Store of the objects of S3 buckets, addressed by `s3://bucket/key` URLs. The
+    /// region and the credentials are read from the environment.
+    pub struct S3ObjectStore {
+        client: S3Client,
+    }
+
+    impl S3ObjectStore {
+        pub fn new() -> Self {
+            Self {
+                client: S3Client::new(Region::default()),
+            }
+        }
+
+        /// Splits `url` into its bucket and key
+        fn location(url: &str) -> Result<(String, String)> {
+            url.strip_prefix("s3://")
+                .and_then(|location| location.split_once('/'))
+                .map(|(bucket, key)| (bucket.to_owned(), key.to_owned()))
+                .ok_or_else(|| {
+                    BallistaError::General(format!(
+                        "Invalid S3 URL '{}', expected s3://bucket/key",
+                        url
+                    ))
+                })
+        }
+    }
+
+    impl Default for S3ObjectStore {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for S3ObjectStore {
+        async fn put(&self, url: &str, data: Vec<u8>) -> Result<()> {
+            let (bucket, key) = Self::location(url)?;
+            let request = PutObjectRequest {
+                bucket,
+                key,
+                content_length: Some(data.len() as i64),
+                body: Some(data.into()),
+                ..Default::default()
+            };
+            self.client.put_object(request).await.map_err(|e| {
+                BallistaError::General(format!("Could not write {}: {}", url, e))
+            })?;
+            Ok(())
+        }
+
+        async fn get(&self, url: &str) -> Result<Vec<u8>> {
+            let (bucket, key) = Self::location(url)?;
+            let request = GetObjectRequest {
+                bucket,
+                key,
+                ..Default::default()
+            };
+            let output = self.client.get_object(request).await.map_err(|e| {
+                BallistaError::General(format!("Could not read {}: {}", url, e))
+            })?;
+            match output.body {
+                Some(body) => {
+                    Ok(body.map_ok(|bytes| bytes.to_vec()).try_concat().await?)
+                }
+                None => Ok(vec![]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_object_store() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let base = format!("file://{}", dir.path().to_str().unwrap());
+        let url = join_url(&base, &["job", "1", "part-0.arrow"]);
+        assert!(is_object_store_url(&url));
+        assert!(!is_object_store_url(dir.path().to_str().unwrap()));
+
+        let store = object_store_for(&url)?;
+        store.put(&url, b"results".to_vec()).await?;
+        assert_eq!(b"results".to_vec(), store.get(&url).await?);
+        assert!(dir.path().join("job/1/part-0.arrow").exists());
+
+        assert!(store.get(&join_url(&base, &["missing"])).await.is_err());
+        assert!(object_store_for("hdfs://namenode/results").is_err());
+        assert!(object_store_for("/tmp/results").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Client side cache of the results of queries.
+//!
+//! Dashboards run the same queries over and over, so a client that enables the cache
+//! answers a query whose plan and settings it has already executed from the batches it
+//! fetched then, without submitting a job to the cluster.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{
+    BallistaConfig, BALLISTA_RESULTS_PAGE_ROWS, BALLISTA_RESULT_CACHE_MAX_BYTES,
+    BALLISTA_RESULT_CACHE_TTL_SECONDS,
+};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::Result;
+use datafusion::logical_plan::{Expr, ExpressionVisitor, LogicalPlan, Recursion};
+use datafusion::physical_plan::functions::BuiltinScalarFunction;
+
+/// The results of a query and when they were fetched
+#[derive(Debug)]
+struct CachedResult {
+    key: String,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    num_bytes: usize,
+    fetched_at: Instant,
+}
+
+/// Least recently used cache of query results, keyed by the normalized plan and the
+/// settings of the query, and bounded by the memory size of the cached batches
+#[derive(Debug)]
+pub struct ResultCache {
+    max_bytes: usize,
+    ttl: Option<Duration>,
+    /// The cached results, the most recently used first
+    results: Mutex<VecDeque<CachedResult>>,
+}
+
+impl ResultCache {
+    /// Create a cache of results that take at most `max_bytes` bytes in total and are
+    /// reused for at most `ttl`, or until they are evicted if `ttl` is `None`
+    pub fn new(max_bytes: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            max_bytes,
+            ttl,
+            results: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Create the cache configured by the `ballista.client.result_cache.*` settings of
+    /// `config`, if it is enabled
+    pub fn from_config(config: &BallistaConfig) -> Option<Self> {
+        let max_bytes = config.result_cache_max_bytes();
+        if max_bytes == 0 {
+            return None;
+        }
+        let ttl = match config.result_cache_ttl_seconds() {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds as u64)),
+        };
+        Some(Self::new(max_bytes, ttl))
+    }
+
+    /// Returns the cached results of the query with the cache key `key`, unless they
+    /// expired
+    pub fn get(&self, key: &str) -> Option<(SchemaRef, Vec<RecordBatch>)> {
+        let mut results = self.results.lock().unwrap();
+        let index = results.iter().position(|cached| cached.key == key)?;
+        let cached = results.remove(index)?;
+        if self.is_expired(&cached) {
+            return None;
+        }
+        let result = (cached.schema.clone(), cached.batches.clone());
+        results.push_front(cached);
+        Some(result)
+    }
+
+    /// Caches the results of the query with the cache key `key`, evicting the least
+    /// recently used results until they fit. Results larger than the cache are not
+    /// cached.
+    pub fn insert(&self, key: String, schema: SchemaRef, batches: Vec<RecordBatch>) {
+        let num_bytes = batches_size(&batches);
+        if num_bytes > self.max_bytes {
+            return;
+        }
+        let mut results = self.results.lock().unwrap();
+        results.retain(|cached| cached.key != key && !self.is_expired(cached));
+        let mut total_bytes: usize = results.iter().map(|cached| cached.num_bytes).sum();
+        while total_bytes + num_bytes > self.max_bytes {
+            match results.pop_back() {
+                Some(evicted) => total_bytes -= evicted.num_bytes,
+                None => break,
+            }
+        }
+        results.push_front(CachedResult {
+            key,
+            schema,
+            batches,
+            num_bytes,
+            fetched_at: Instant::now(),
+        });
+    }
+
+    /// Forgets all cached results, e.g. because the tables they were read from changed
+    pub fn clear(&self) {
+        self.results.lock().unwrap().clear();
+    }
+
+    fn is_expired(&self, cached: &CachedResult) -> bool {
+        self.ttl
+            .map(|ttl| cached.fetched_at.elapsed() >= ttl)
+            .unwrap_or(false)
+    }
+}
+
+/// Returns the key of the results of the query that executes the optimized `plan`
+/// with `config`. Queries that only differ in their formatting, the case of their
+/// keywords, the order of their settings or how their results are fetched have the
+/// same plan and the same key.
+///
+/// Returns `None` if the results of `plan` may differ between executions because it
+/// calls volatile functions, such as `random()`, `now()` or user defined functions,
+/// so that these queries bypass the cache.
+pub fn cache_key(plan: &LogicalPlan, config: &BallistaConfig) -> Option<String> {
+    if is_volatile(plan) {
+        return None;
+    }
+    let settings = config
+        .settings()
+        .iter()
+        .filter(|(k, _)| {
+            k.as_str() != BALLISTA_RESULT_CACHE_MAX_BYTES
+                && k.as_str() != BALLISTA_RESULT_CACHE_TTL_SECONDS
+                && k.as_str() != BALLISTA_RESULTS_PAGE_ROWS
+        })
+        .collect::<BTreeMap<_, _>>();
+    Some(format!("{:?}\n{:?}", plan, settings))
+}
+
+/// Returns whether `plan` or its inputs call functions whose results may differ
+/// between executions
+fn is_volatile(plan: &LogicalPlan) -> bool {
+    let mut exprs = plan.expressions();
+    if let LogicalPlan::TableScan { filters, .. } = plan {
+        exprs.extend(filters.iter().cloned());
+    }
+    exprs.iter().any(|expr| {
+        expr.accept(VolatileVisitor { volatile: false })
+            .map_or(true, |visitor| visitor.volatile)
+    }) || plan.inputs().into_iter().any(is_volatile)
+}
+
+/// Finds calls of functions that are evaluated anew by every execution of a query
+struct VolatileVisitor {
+    volatile: bool,
+}
+
+impl ExpressionVisitor for VolatileVisitor {
+    fn pre_visit(mut self, expr: &Expr) -> Result<Recursion<Self>> {
+        match expr {
+            Expr::ScalarFunction { fun, .. }
+                if *fun == BuiltinScalarFunction::Random || fun.is_query_constant() =>
+            {
+                self.volatile = true;
+                Ok(Recursion::Stop(self))
+            }
+            // user defined functions are not known to be deterministic
+            Expr::ScalarUDF { .. } => {
+                self.volatile = true;
+                Ok(Recursion::Stop(self))
+            }
+            _ => Ok(Recursion::Continue(self)),
+        }
+    }
+}
+
+fn batches_size(batches: &[RecordBatch]) -> usize {
+    batches
+        .iter()
+        .flat_map(|batch| batch.columns())
+        .map(|array| array.get_array_memory_size())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::logical_plan::LogicalPlanBuilder;
+    use std::sync::Arc;
+
+    fn batch(len: usize) -> (SchemaRef, Vec<RecordBatch>) {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let array = Int32Array::from(vec![1; len]);
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(array)]).unwrap();
+        (schema, vec![batch])
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let (schema, batches) = batch(100);
+        let size = batches_size(&batches);
+        let cache = ResultCache::new(2 * size, None);
+        cache.insert("a".to_owned(), schema.clone(), batches.clone());
+        cache.insert("b".to_owned(), schema.clone(), batches.clone());
+        // a is used again, so b is evicted by c
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_owned(), schema.clone(), batches.clone());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+
+        // results larger than the cache are not cached
+        let (schema, batches) = batch(1000);
+        cache.insert("d".to_owned(), schema, batches);
+        assert!(cache.get("d").is_none());
+        assert!(cache.get("a").is_some());
+
+        cache.clear();
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn expires_results() {
+        let (schema, batches) = batch(10);
+        let cache = ResultCache::new(usize::MAX, Some(Duration::from_secs(0)));
+        cache.insert("a".to_owned(), schema.clone(), batches.clone());
+        assert!(cache.get("a").is_none());
+
+        let cache = ResultCache::new(usize::MAX, Some(Duration::from_secs(3600)));
+        cache.insert("a".to_owned(), schema, batches);
+        assert!(cache.get("a").is_some());
+    }
+
+    #[test]
+    fn keys() -> Result<()> {
+        let plan = LogicalPlanBuilder::empty(false).build()?;
+        let config = BallistaConfig::builder()
+            .set("ballista.shuffle.partitions", "4")
+            .build()
+            .unwrap();
+        let cached = BallistaConfig::builder()
+            .set("ballista.shuffle.partitions", "4")
+            .set(BALLISTA_RESULT_CACHE_MAX_BYTES, "1000")
+            .build()
+            .unwrap();
+        let other = BallistaConfig::builder()
+            .set("ballista.shuffle.partitions", "8")
+            .build()
+            .unwrap();
+        assert!(cache_key(&plan, &config).is_some());
+        assert_eq!(cache_key(&plan, &config), cache_key(&plan, &cached));
+        assert_ne!(cache_key(&plan, &config), cache_key(&plan, &other));
+
+        // queries with volatile functions are not cached
+        let random = Expr::ScalarFunction {
+            fun: BuiltinScalarFunction::Random,
+            args: vec![],
+        };
+        let plan = LogicalPlanBuilder::empty(true)
+            .project(vec![random.alias("r")])?
+            .build()?;
+        assert!(cache_key(&plan, &config).is_none());
+        let input = LogicalPlanBuilder::from(plan).limit(1)?.build()?;
+        assert!(cache_key(&input, &config).is_none());
+
+        assert!(ResultCache::from_config(&config).is_none());
+        assert!(ResultCache::from_config(&cached).is_some());
+        Ok(())
+    }
+}
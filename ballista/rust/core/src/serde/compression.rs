@@ -0,0 +1,195 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Compression and chunking of the plans that are too large to send in a single
+//! message, which clients stream to the scheduler and executors stream from it.
+
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use prost::Message;
+
+use crate::error::{BallistaError, Result};
+use crate::serde::protobuf::PlanCompression;
+
+/// Parses the name of a plan compression: `none`, `gzip` or `zstd`
+pub fn parse_plan_compression(name: &str) -> Result<PlanCompression> {
+    match name.trim().to_lowercase().as_str() {
+        "" | "none" => Ok(PlanCompression::None),
+        "gzip" => Ok(PlanCompression::Gzip),
+        "zstd" => Ok(PlanCompression::Zstd),
+        _ => Err(BallistaError::General(format!(
+            "Unknown plan compression '{}', expected none, gzip or zstd",
+            name
+        ))),
+    }
+}
+
+/// Compresses `data` with `compression`
+pub fn compress(compression: PlanCompression, data: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        PlanCompression::None => Ok(data.to_vec()),
+        PlanCompression::Gzip => {
+            let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        PlanCompression::Zstd => Ok(zstd::encode_all(data, 0)?),
+    }
+}
+
+/// Decompresses `data`, which was compressed with `compression`. With a `max_size`,
+/// decompression stops after `max_size + 1` bytes, so that a small input cannot expand
+/// into an arbitrary amount of memory and the caller can tell that the data exceeds the
+/// limit from the length of the result.
+pub fn decompress(
+    compression: PlanCompression,
+    data: &[u8],
+    max_size: Option<usize>,
+) -> Result<Vec<u8>> {
+    let limit = max_size.map_or(u64::MAX, |max_size| max_size as u64 + 1);
+    let mut decompressed = vec![];
+    match compression {
+        PlanCompression::None => data.take(limit).read_to_end(&mut decompressed)?,
+        PlanCompression::Gzip => GzDecoder::new(data)
+            .take(limit)
+            .read_to_end(&mut decompressed)?,
+        PlanCompression::Zstd => zstd::stream::read::Decoder::new(data)?
+            .take(limit)
+            .read_to_end(&mut decompressed)?,
+    };
+    Ok(decompressed)
+}
+
+/// Encodes `message`, compresses it and splits the result into chunks of at most
+/// `chunk_size` bytes
+pub fn encode_chunks<T: Message>(
+    message: &T,
+    compression: PlanCompression,
+    chunk_size: usize,
+) -> Result<Vec<Vec<u8>>> {
+    let mut encoded = Vec::with_capacity(message.encoded_len());
+    message.encode(&mut encoded).map_err(|e| {
+        BallistaError::Internal(format!("Could not encode the chunked message: {}", e))
+    })?;
+    Ok(compress(compression, &encoded)?
+        .chunks(chunk_size.max(1))
+        .map(|chunk| chunk.to_vec())
+        .collect())
+}
+
+/// Decodes a message from the concatenation of the chunks returned by [encode_chunks],
+/// failing if the message is more than `max_size` bytes once decompressed
+pub fn decode_chunks<T: Message + Default>(
+    compression: PlanCompression,
+    data: &[u8],
+    max_size: Option<usize>,
+) -> Result<T> {
+    let decompressed = decompress(compression, data, max_size)?;
+    if let Some(max_size) = max_size {
+        if decompressed.len() > max_size {
+            return Err(BallistaError::General(format!(
+                "The chunked message exceeds the limit of {} bytes once decompressed",
+                max_size
+            )));
+        }
+    }
+    T::decode(decompressed.as_slice()).map_err(|e| {
+        BallistaError::Internal(format!("Could not decode the chunked message: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::protobuf::{ExecuteQueryParams, KeyValuePair};
+
+    #[test]
+    fn chunks_roundtrip() -> Result<()> {
+        let params = ExecuteQueryParams {
+            query: None,
+            settings: (0..100)
+                .map(|i| KeyValuePair {
+                    key: format!("key{}", i),
+                    value: "value".to_owned(),
+                })
+                .collect(),
+            session_id: String::new(),
+        };
+        for compression in [
+            PlanCompression::None,
+            PlanCompression::Gzip,
+            PlanCompression::Zstd,
+        ] {
+            let chunks = encode_chunks(&params, compression, 100)?;
+            assert!(chunks.iter().all(|chunk| chunk.len() <= 100));
+            let decoded: ExecuteQueryParams =
+                decode_chunks(compression, &chunks.concat(), None)?;
+            assert_eq!(params, decoded);
+        }
+        // the repeated settings compress well
+        assert!(
+            encode_chunks(&params, PlanCompression::Gzip, 100)?.len()
+                < encode_chunks(&params, PlanCompression::None, 100)?.len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decompress_limit() -> Result<()> {
+        // a megabyte of zeros compresses to a few kilobytes
+        let data = vec![0u8; 1 << 20];
+        for compression in [
+            PlanCompression::None,
+            PlanCompression::Gzip,
+            PlanCompression::Zstd,
+        ] {
+            let compressed = compress(compression, &data)?;
+            assert_eq!(decompress(compression, &compressed, None)?, data);
+            assert_eq!(
+                decompress(compression, &compressed, Some(1000))?.len(),
+                1001
+            );
+            assert_eq!(
+                decompress(compression, &compressed, Some(1 << 20))?.len(),
+                1 << 20
+            );
+        }
+
+        let params = ExecuteQueryParams {
+            query: None,
+            settings: vec![],
+            session_id: "x".repeat(1000),
+        };
+        let chunks = encode_chunks(&params, PlanCompression::Gzip, 100)?.concat();
+        assert!(chunks.len() < 100);
+        let result: Result<ExecuteQueryParams> =
+            decode_chunks(PlanCompression::Gzip, &chunks, Some(100));
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_compression() -> Result<()> {
+        assert_eq!(PlanCompression::None, parse_plan_compression("")?);
+        assert_eq!(PlanCompression::Gzip, parse_plan_compression("GZIP")?);
+        assert_eq!(PlanCompression::Zstd, parse_plan_compression("zstd")?);
+        assert!(parse_plan_compression("lz4").is_err());
+        Ok(())
+    }
+}
@@ -17,17 +17,24 @@
 
 //! Serde code to convert from protocol buffers to Rust data structures.
 
+use crate::datasource::BroadcastTable;
 use crate::error::BallistaError;
+use crate::execution_plans::decode_batches;
 use crate::serde::{from_proto_binary_op, proto_error, protobuf};
+use crate::wasm::{get_or_register_wasm_udf, WasmScalarUdf};
 use crate::{convert_box_required, convert_required};
 use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use datafusion::datasource::delta::DeltaTable;
+use datafusion::datasource::parquet::ParquetTable;
+use datafusion::datasource::partitioned::PartitionedTable;
+use datafusion::datasource::TableProvider;
 use datafusion::logical_plan::window_frames::{
-    WindowFrame, WindowFrameBound, WindowFrameUnits,
+    WindowFrame, WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
 };
 use datafusion::logical_plan::{
-    abs, acos, asin, atan, ceil, cos, exp, floor, ln, log10, log2, round, signum, sin,
-    sqrt, tan, trunc, Column, DFField, DFSchema, Expr, JoinConstraint, JoinType,
-    LogicalPlan, LogicalPlanBuilder, Operator,
+    abs, acos, asin, atan, ceil, cos, current_user, exp, floor, ln, log10, log2, round,
+    signum, sin, sqrt, tan, trunc, version, Column, DFField, DFSchema, ExplainFormat,
+    Expr, JoinConstraint, JoinType, LogicalPlan, LogicalPlanBuilder, Operator,
 };
 use datafusion::physical_plan::aggregates::AggregateFunction;
 use datafusion::physical_plan::csv::CsvReadOptions;
@@ -110,11 +117,32 @@ impl TryInto<LogicalPlan> for &protobuf::LogicalPlanNode {
             }
             LogicalPlanType::CsvScan(scan) => {
                 let schema: Schema = convert_required!(scan.schema)?;
-                let options = CsvReadOptions::new()
+                let file_compression_type = protobuf::FileCompressionType::from_i32(
+                    scan.file_compression_type,
+                )
+                .ok_or_else(|| {
+                    proto_error(format!(
+                        "Received a CsvTableScanNode message with unknown FileCompressionType {}",
+                        scan.file_compression_type
+                    ))
+                })?;
+                let mut options = CsvReadOptions::new()
                     .schema(&schema)
                     .delimiter(scan.delimiter.as_bytes()[0])
                     .file_extension(&scan.file_extension)
-                    .has_header(scan.has_header);
+                    .has_header(scan.has_header)
+                    .file_list_options(
+                        scan.file_list_options.clone().unwrap_or_default().into(),
+                    );
+                options.file_compression_type = file_compression_type.into();
+                if let Some(
+                    protobuf::csv_table_scan_node::OptionalTargetSplitSize::TargetSplitSize(
+                        size,
+                    ),
+                ) = scan.optional_target_split_size
+                {
+                    options = options.target_split_size(size as usize);
+                }
 
                 let mut projection = None;
                 if let Some(columns) = &scan.projection {
@@ -156,14 +184,126 @@ impl TryInto<LogicalPlan> for &protobuf::LogicalPlanNode {
                         Some(r?)
                     }
                 };
-                LogicalPlanBuilder::scan_parquet_with_name(
+                let schema: Schema = convert_required!(scan.schema)?;
+                let table = ParquetTable::try_new_with_schema(
                     &scan.path,
-                    projection,
-                    24,
-                    &scan.table_name,
-                )? //TODO concurrency
-                .build()
-                .map_err(|e| e.into())
+                    Some(Arc::new(schema)),
+                    24, //TODO concurrency
+                    scan.file_list_options.clone().unwrap_or_default().into(),
+                )?;
+                LogicalPlanBuilder::scan(&scan.table_name, Arc::new(table), projection)?
+                    .build()
+                    .map_err(|e| e.into())
+            }
+            LogicalPlanType::DeltaScan(scan) => {
+                // the version of the client is read, so that both see the same files
+                let table = DeltaTable::try_new_with_version(
+                    &scan.path,
+                    Some(scan.version),
+                    24, //TODO concurrency
+                )?;
+                let schema = table.schema();
+                let projection = scan
+                    .projection
+                    .as_ref()
+                    .map(|columns| {
+                        columns
+                            .columns
+                            .iter()
+                            .map(|name| {
+                                schema.index_of(name).map_err(|_| {
+                                    proto_error(format!(
+                                        "Delta Lake projection contains column {} that is not present in schema {:?}",
+                                        name, schema
+                                    ))
+                                })
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .transpose()?;
+                LogicalPlanBuilder::scan(&scan.table_name, Arc::new(table), projection)?
+                    .build()
+                    .map_err(|e| e.into())
+            }
+            LogicalPlanType::PartitionedScan(scan) => {
+                let schema: Schema = convert_required!(scan.schema)?;
+                let num_file_columns = schema
+                    .fields()
+                    .len()
+                    .checked_sub(scan.partition_cols.len())
+                    .ok_or_else(|| {
+                        proto_error(
+                            "Received a PartitionedTableScanNode message with more partition columns than columns",
+                        )
+                    })?;
+                let file_schema =
+                    Arc::new(Schema::new(schema.fields()[..num_file_columns].to_vec()));
+                let partitions = scan
+                    .partitions
+                    .iter()
+                    .map(|partition| {
+                        let plan: LogicalPlan = convert_required!(partition.scan)?;
+                        match plan {
+                            LogicalPlan::TableScan { source, .. } => {
+                                Ok((partition.values.clone(), source))
+                            }
+                            _ => Err(proto_error(
+                                "Received a TablePartition message whose scan is not a table scan",
+                            )),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, BallistaError>>()?;
+                let table = PartitionedTable::try_new_with_partitions(
+                    file_schema,
+                    &scan.partition_cols,
+                    partitions,
+                )?;
+                let projection = scan
+                    .projection
+                    .as_ref()
+                    .map(|columns| {
+                        columns
+                            .columns
+                            .iter()
+                            .map(|name| {
+                                schema.index_of(name).map_err(|_| {
+                                    proto_error(format!(
+                                        "Partitioned table projection contains column {} that is not present in schema {:?}",
+                                        name, schema
+                                    ))
+                                })
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .transpose()?;
+                LogicalPlanBuilder::scan(&scan.table_name, Arc::new(table), projection)?
+                    .build()
+                    .map_err(|e| e.into())
+            }
+            LogicalPlanType::BroadcastScan(scan) => {
+                let schema: Schema = convert_required!(scan.schema)?;
+                let projection = match scan.projection.as_ref() {
+                    None => None,
+                    Some(columns) => Some(
+                        columns
+                            .columns
+                            .iter()
+                            .map(|col_name| {
+                                schema.index_of(col_name).map_err(|_| {
+                                    proto_error(format!(
+                                        "Broadcast projection contains column name that is not present in schema: {}",
+                                        col_name
+                                    ))
+                                })
+                            })
+                            .collect::<Result<Vec<usize>, _>>()?,
+                    ),
+                };
+                let (_, batches) = decode_batches(&scan.data)?;
+                let table = BroadcastTable::new(&scan.name, Arc::new(schema), batches);
+                LogicalPlanBuilder::scan(&scan.table_name, Arc::new(table), projection)?
+                    .build()
+                    .map_err(|e| e.into())
             }
             LogicalPlanType::Sort(sort) => {
                 let input: LogicalPlan = convert_box_required!(sort.input)?;
@@ -229,19 +369,35 @@ impl TryInto<LogicalPlan> for &protobuf::LogicalPlanNode {
                     location: create_extern_table.location.clone(),
                     file_type: pb_file_type.into(),
                     has_header: create_extern_table.has_header,
+                    table_partition_cols: create_extern_table
+                        .table_partition_cols
+                        .clone(),
+                    options: create_extern_table.options.clone(),
                 })
             }
             LogicalPlanType::Explain(explain) => {
                 let input: LogicalPlan = convert_box_required!(explain.input)?;
+                let format = protobuf::ExplainFormat::from_i32(explain.format)
+                    .ok_or_else(|| {
+                        proto_error(format!(
+                            "Received an ExplainNode message with unknown ExplainFormat {}",
+                            explain.format
+                        ))
+                    })?;
+                let format = match format {
+                    protobuf::ExplainFormat::Text => ExplainFormat::Text,
+                    protobuf::ExplainFormat::Json => ExplainFormat::Json,
+                    protobuf::ExplainFormat::Graphviz => ExplainFormat::Graphviz,
+                };
                 LogicalPlanBuilder::from(input)
-                    .explain(explain.verbose)?
+                    .explain_with_format(explain.verbose, format)?
                     .build()
                     .map_err(|e| e.into())
             }
             LogicalPlanType::Limit(limit) => {
                 let input: LogicalPlan = convert_box_required!(limit.input)?;
                 LogicalPlanBuilder::from(input)
-                    .limit(limit.limit as usize)?
+                    .limit_with_offset(limit.limit as usize, limit.offset as usize)?
                     .build()
                     .map_err(|e| e.into())
             }
@@ -269,10 +425,11 @@ impl TryInto<LogicalPlan> for &protobuf::LogicalPlanNode {
 
                 let builder = LogicalPlanBuilder::from(convert_box_required!(join.left)?);
                 let builder = match join_constraint.into() {
-                    JoinConstraint::On => builder.join(
+                    JoinConstraint::On => builder.join_detailed(
                         &convert_box_required!(join.right)?,
                         join_type.into(),
                         (left_keys, right_keys),
+                        join.null_equals_null,
                     )?,
                     JoinConstraint::Using => builder.join_using(
                         &convert_box_required!(join.right)?,
@@ -441,6 +598,12 @@ fn typechecked_scalar_value_conversion(
         (Value::LargeUtf8Value(v), PrimitiveScalarType::LargeUtf8) => {
             ScalarValue::LargeUtf8(Some(v.to_owned()))
         }
+        (Value::BinaryValue(v), PrimitiveScalarType::Binary) => {
+            ScalarValue::Binary(Some(v.to_owned()))
+        }
+        (Value::LargeBinaryValue(v), PrimitiveScalarType::LargeBinary) => {
+            ScalarValue::LargeBinary(Some(v.to_owned()))
+        }
 
         (Value::NullValue(i32_enum), required_scalar_type) => {
             if *i32_enum == *required_scalar_type as i32 {
@@ -464,6 +627,8 @@ fn typechecked_scalar_value_conversion(
                     PrimitiveScalarType::Float64 => ScalarValue::Float64(None),
                     PrimitiveScalarType::Utf8 => ScalarValue::Utf8(None),
                     PrimitiveScalarType::LargeUtf8 => ScalarValue::LargeUtf8(None),
+                    PrimitiveScalarType::Binary => ScalarValue::Binary(None),
+                    PrimitiveScalarType::LargeBinary => ScalarValue::LargeBinary(None),
                     PrimitiveScalarType::Date32 => ScalarValue::Date32(None),
                     PrimitiveScalarType::TimeMicrosecond => {
                         ScalarValue::TimestampMicrosecond(None)
@@ -499,6 +664,12 @@ impl TryInto<datafusion::scalar::ScalarValue> for &protobuf::scalar_value::Value
             protobuf::scalar_value::Value::LargeUtf8Value(v) => {
                 ScalarValue::LargeUtf8(Some(v.to_owned()))
             }
+            protobuf::scalar_value::Value::BinaryValue(v) => {
+                ScalarValue::Binary(Some(v.to_owned()))
+            }
+            protobuf::scalar_value::Value::LargeBinaryValue(v) => {
+                ScalarValue::LargeBinary(Some(v.to_owned()))
+            }
             protobuf::scalar_value::Value::Int8Value(v) => {
                 ScalarValue::Int8(Some(*v as i8))
             }
@@ -692,6 +863,8 @@ impl TryInto<datafusion::scalar::ScalarValue> for protobuf::PrimitiveScalarType
             protobuf::PrimitiveScalarType::Float64 => ScalarValue::Float64(None),
             protobuf::PrimitiveScalarType::Utf8 => ScalarValue::Utf8(None),
             protobuf::PrimitiveScalarType::LargeUtf8 => ScalarValue::LargeUtf8(None),
+            protobuf::PrimitiveScalarType::Binary => ScalarValue::Binary(None),
+            protobuf::PrimitiveScalarType::LargeBinary => ScalarValue::LargeBinary(None),
             protobuf::PrimitiveScalarType::Date32 => ScalarValue::Date32(None),
             protobuf::PrimitiveScalarType::TimeMicrosecond => {
                 ScalarValue::TimestampMicrosecond(None)
@@ -717,6 +890,12 @@ impl TryInto<datafusion::scalar::ScalarValue> for &protobuf::ScalarValue {
             protobuf::scalar_value::Value::LargeUtf8Value(v) => {
                 ScalarValue::LargeUtf8(Some(v.to_owned()))
             }
+            protobuf::scalar_value::Value::BinaryValue(v) => {
+                ScalarValue::Binary(Some(v.to_owned()))
+            }
+            protobuf::scalar_value::Value::LargeBinaryValue(v) => {
+                ScalarValue::LargeBinary(Some(v.to_owned()))
+            }
             protobuf::scalar_value::Value::Int8Value(v) => {
                 ScalarValue::Int8(Some(*v as i8))
             }
@@ -815,6 +994,11 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                     .window_function
                     .as_ref()
                     .ok_or_else(|| proto_error("Received empty window function"))?;
+                let args = expr
+                    .args
+                    .iter()
+                    .map(|e| e.try_into())
+                    .collect::<Result<Vec<_>, _>>()?;
                 let partition_by = expr
                     .partition_by
                     .iter()
@@ -856,10 +1040,11 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                             fun: window_functions::WindowFunction::AggregateFunction(
                                 AggregateFunction::from(aggr_function),
                             ),
-                            args: vec![parse_required_expr(&expr.expr)?],
+                            args,
                             partition_by,
                             order_by,
                             window_frame,
+                            distinct: expr.distinct,
                         })
                     }
                     window_expr_node::WindowFunction::BuiltInFunction(i) => {
@@ -877,10 +1062,11 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                             fun: window_functions::WindowFunction::BuiltInWindowFunction(
                                 BuiltInWindowFunction::from(built_in_function),
                             ),
-                            args: vec![parse_required_expr(&expr.expr)?],
+                            args,
                             partition_by,
                             order_by,
                             window_frame,
+                            distinct: false,
                         })
                     }
                 }
@@ -895,11 +1081,16 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                             ))
                         })?;
                 let fun = AggregateFunction::from(aggr_function);
+                let mut args = vec![parse_required_expr(&expr.expr)?];
+                for arg in &expr.other_args {
+                    args.push(arg.try_into()?);
+                }
 
                 Ok(Expr::AggregateFunction {
                     fun,
-                    args: vec![parse_required_expr(&expr.expr)?],
+                    args,
                     distinct: false, //TODO
+                    filter: parse_optional_expr(&expr.filter)?.map(Box::new),
                 })
             }
             ExprType::Alias(alias) => Ok(Expr::Alias(
@@ -1028,6 +1219,17 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                     protobuf::ScalarFunction::Datetrunc => {
                         Ok(date_trunc((&args[0]).try_into()?, (&args[1]).try_into()?))
                     }
+                    // the origin of date_bin is optional
+                    protobuf::ScalarFunction::Datebin => Ok(Expr::ScalarFunction {
+                        fun: functions::BuiltinScalarFunction::DateBin,
+                        args: args
+                            .iter()
+                            .map(|arg| arg.try_into())
+                            .collect::<Result<Vec<_>, _>>()?,
+                    }),
+                    protobuf::ScalarFunction::Tochar => {
+                        Ok(to_char((&args[0]).try_into()?, (&args[1]).try_into()?))
+                    }
                     // protobuf::ScalarFunction::Md5 => Ok(md5((&args[0]).try_into()?)),
                     protobuf::ScalarFunction::Sha224 => {
                         Ok(sha224((&args[0]).try_into()?))
@@ -1041,15 +1243,50 @@ impl TryInto<Expr> for &protobuf::LogicalExprNode {
                     protobuf::ScalarFunction::Sha512 => {
                         Ok(sha512((&args[0]).try_into()?))
                     }
+                    protobuf::ScalarFunction::Now => Ok(Expr::ScalarFunction {
+                        fun: functions::BuiltinScalarFunction::Now,
+                        args: vec![],
+                    }),
+                    protobuf::ScalarFunction::Currentuser => Ok(current_user()),
+                    protobuf::ScalarFunction::Version => Ok(version()),
                     _ => Err(proto_error(
                         "Protobuf deserialization error: Unsupported scalar function",
                     )),
                 }
             }
+            ExprType::WasmUdf(expr) => {
+                let udf: Arc<WasmScalarUdf> = convert_required!(expr.udf)?;
+                let args = expr
+                    .args
+                    .iter()
+                    .map(|e| e.try_into())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Expr::ScalarUDF {
+                    fun: Arc::new(udf.to_scalar_udf()),
+                    args,
+                })
+            }
         }
     }
 }
 
+impl TryInto<Arc<WasmScalarUdf>> for &protobuf::WasmScalarUdf {
+    type Error = BallistaError;
+    fn try_into(self) -> Result<Arc<WasmScalarUdf>, Self::Error> {
+        let arg_types = self
+            .arg_types
+            .iter()
+            .map(|t| t.try_into())
+            .collect::<Result<Vec<DataType>, _>>()?;
+        get_or_register_wasm_udf(
+            &self.name,
+            self.module.clone(),
+            arg_types,
+            convert_required!(self.return_type)?,
+        )
+    }
+}
+
 impl TryInto<DataType> for &protobuf::ScalarType {
     type Error = BallistaError;
     fn try_into(self) -> Result<DataType, Self::Error> {
@@ -1100,10 +1337,10 @@ impl TryInto<Field> for &protobuf::Field {
     }
 }
 
-use datafusion::physical_plan::{aggregates, windows};
+use datafusion::physical_plan::{aggregates, functions, windows};
 use datafusion::prelude::{
     array, date_part, date_trunc, length, lower, ltrim, md5, rtrim, sha224, sha256,
-    sha384, sha512, trim, upper,
+    sha384, sha512, to_char, trim, upper,
 };
 use std::convert::TryFrom;
 
@@ -1115,6 +1352,7 @@ impl TryFrom<i32> for protobuf::FileType {
             _x if _x == FileType::NdJson as i32 => Ok(FileType::NdJson),
             _x if _x == FileType::Parquet as i32 => Ok(FileType::Parquet),
             _x if _x == FileType::Csv as i32 => Ok(FileType::Csv),
+            _x if _x == FileType::Delta as i32 => Ok(FileType::Delta),
             invalid => Err(BallistaError::General(format!(
                 "Attempted to convert invalid i32 to protobuf::Filetype: {}",
                 invalid
@@ -1131,6 +1369,7 @@ impl Into<datafusion::sql::parser::FileType> for protobuf::FileType {
             protobuf::FileType::NdJson => FileType::NdJson,
             protobuf::FileType::Parquet => FileType::Parquet,
             protobuf::FileType::Csv => FileType::CSV,
+            protobuf::FileType::Delta => FileType::Delta,
         }
     }
 }
@@ -1173,19 +1412,19 @@ impl TryFrom<protobuf::WindowFrameBound> for WindowFrameBound {
                 bound.window_frame_bound_type
             ))
         })?;
+        // a missing value is an unbounded frame boundary
+        let bound_value = bound.bound_value.map(|v| match v {
+            protobuf::window_frame_bound::BoundValue::Value(v) => v,
+        });
         match bound_type {
             protobuf::WindowFrameBoundType::CurrentRow => {
                 Ok(WindowFrameBound::CurrentRow)
             }
             protobuf::WindowFrameBoundType::Preceding => {
-                // FIXME implement bound value parsing
-                // https://github.com/apache/arrow-datafusion/issues/361
-                Ok(WindowFrameBound::Preceding(Some(1)))
+                Ok(WindowFrameBound::Preceding(bound_value))
             }
             protobuf::WindowFrameBoundType::Following => {
-                // FIXME implement bound value parsing
-                // https://github.com/apache/arrow-datafusion/issues/361
-                Ok(WindowFrameBound::Following(Some(1)))
+                Ok(WindowFrameBound::Following(bound_value))
             }
         }
     }
@@ -1220,10 +1459,31 @@ impl TryFrom<protobuf::WindowFrame> for WindowFrame {
             })
             .transpose()?
             .unwrap_or(WindowFrameBound::CurrentRow);
+        let exclude = match protobuf::WindowFrameExclusion::from_i32(window.exclude) {
+            Some(protobuf::WindowFrameExclusion::ExcludeNoOthers) => {
+                WindowFrameExclusion::NoOthers
+            }
+            Some(protobuf::WindowFrameExclusion::ExcludeCurrentRow) => {
+                WindowFrameExclusion::CurrentRow
+            }
+            Some(protobuf::WindowFrameExclusion::ExcludeGroup) => {
+                WindowFrameExclusion::Group
+            }
+            Some(protobuf::WindowFrameExclusion::ExcludeTies) => {
+                WindowFrameExclusion::Ties
+            }
+            None => {
+                return Err(proto_error(format!(
+                    "Received a WindowFrame message with unknown WindowFrameExclusion {}",
+                    window.exclude
+                )))
+            }
+        };
         Ok(WindowFrame {
             units,
             start_bound,
             end_bound,
+            exclude,
         })
     }
 }
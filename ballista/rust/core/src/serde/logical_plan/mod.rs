@@ -26,16 +26,27 @@ mod roundtrip_tests {
     use core::panic;
     use datafusion::{
         arrow::datatypes::{DataType, Field, IntervalUnit, Schema, TimeUnit},
+        datasource::{partitioned::PartitionedTable, CsvFile, TableProvider},
+        logical_plan::window_frames::{
+            WindowFrame, WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
+        },
         logical_plan::{
-            col, Expr, LogicalPlan, LogicalPlanBuilder, Partitioning, ToDFSchema,
+            col, current_user, version, ExplainFormat, Expr, LogicalPlan,
+            LogicalPlanBuilder, Partitioning, ToDFSchema,
+        },
+        physical_plan::aggregates::AggregateFunction,
+        physical_plan::window_functions::{BuiltInWindowFunction, WindowFunction},
+        physical_plan::{
+            csv::CsvReadOptions,
+            functions::BuiltinScalarFunction::{Now, Sqrt},
         },
-        physical_plan::{csv::CsvReadOptions, functions::BuiltinScalarFunction::Sqrt},
         prelude::*,
         scalar::ScalarValue,
         sql::parser::FileType,
     };
     use protobuf::arrow_type;
     use std::convert::TryInto;
+    use std::sync::Arc;
 
     //Given a identity of a LogicalPlan converts it to protobuf and back, using debug formatting to test equality.
     macro_rules! roundtrip_test {
@@ -208,6 +219,8 @@ mod roundtrip_tests {
             ScalarValue::UInt64(None),
             ScalarValue::Utf8(None),
             ScalarValue::LargeUtf8(None),
+            ScalarValue::Binary(None),
+            ScalarValue::LargeBinary(None),
             ScalarValue::List(None, Box::new(DataType::Boolean)),
             ScalarValue::Date32(None),
             ScalarValue::TimestampMicrosecond(None),
@@ -248,6 +261,8 @@ mod roundtrip_tests {
             ScalarValue::UInt64(Some(0)),
             ScalarValue::Utf8(Some(String::from("Test string   "))),
             ScalarValue::LargeUtf8(Some(String::from("Test Large utf8"))),
+            ScalarValue::Binary(Some(b"Test binary".to_vec())),
+            ScalarValue::LargeBinary(Some(b"Test large binary".to_vec())),
             ScalarValue::Date32(Some(0)),
             ScalarValue::Date32(Some(i32::MAX)),
             ScalarValue::TimestampNanosecond(Some(0)),
@@ -329,8 +344,11 @@ mod roundtrip_tests {
             DataType::Time64(TimeUnit::Nanosecond),
             DataType::Utf8,
             DataType::LargeUtf8,
+            DataType::Binary,
+            DataType::LargeBinary,
             //Recursive list tests
             DataType::List(new_box_field("Level1", DataType::Boolean, true)),
+            DataType::List(new_box_field("Level1", DataType::Binary, true)),
             DataType::List(new_box_field(
                 "Level1",
                 DataType::List(new_box_field("Level2", DataType::Date32, true)),
@@ -356,14 +374,11 @@ mod roundtrip_tests {
             DataType::Duration(TimeUnit::Nanosecond),
             DataType::Interval(IntervalUnit::YearMonth),
             DataType::Interval(IntervalUnit::DayTime),
-            DataType::Binary,
             DataType::FixedSizeBinary(0),
             DataType::FixedSizeBinary(1234),
             DataType::FixedSizeBinary(-432),
-            DataType::LargeBinary,
             DataType::Decimal(1345, 5431),
             //Recursive list tests
-            DataType::List(new_box_field("Level1", DataType::Binary, true)),
             DataType::List(new_box_field(
                 "Level1",
                 DataType::List(new_box_field(
@@ -612,6 +627,8 @@ mod roundtrip_tests {
             ScalarValue::UInt64(None),
             ScalarValue::Utf8(None),
             ScalarValue::LargeUtf8(None),
+            ScalarValue::Binary(None),
+            ScalarValue::LargeBinary(None),
             ScalarValue::Date32(None),
             ScalarValue::TimestampMicrosecond(None),
             ScalarValue::TimestampNanosecond(None),
@@ -643,8 +660,12 @@ mod roundtrip_tests {
 
         let df_schema_ref = schema.to_dfschema_ref()?;
 
-        let filetypes: [FileType; 3] =
-            [FileType::NdJson, FileType::Parquet, FileType::CSV];
+        let filetypes: [FileType; 4] = [
+            FileType::NdJson,
+            FileType::Parquet,
+            FileType::CSV,
+            FileType::Delta,
+        ];
 
         for file in filetypes.iter() {
             let create_table_node = LogicalPlan::CreateExternalTable {
@@ -653,6 +674,10 @@ mod roundtrip_tests {
                 location: String::from("employee.csv"),
                 file_type: *file,
                 has_header: true,
+                table_partition_cols: vec!["year".to_string()],
+                options: vec![("compression".to_string(), "gzip".to_string())]
+                    .into_iter()
+                    .collect(),
             };
 
             roundtrip_test!(create_table_node);
@@ -695,6 +720,25 @@ mod roundtrip_tests {
 
         roundtrip_test!(verbose_plan);
 
+        let json_plan = LogicalPlanBuilder::scan_csv(
+            "employee.csv",
+            CsvReadOptions::new().schema(&schema).has_header(true),
+            Some(vec![3, 4]),
+        )
+        .and_then(|plan| plan.explain_with_format(false, ExplainFormat::Json))
+        .and_then(|plan| plan.build())
+        .map_err(BallistaError::DataFusionError)?;
+
+        let proto: protobuf::LogicalPlanNode = (&json_plan).try_into()?;
+        let round_trip: LogicalPlan = (&proto).try_into()?;
+        assert!(matches!(
+            round_trip,
+            LogicalPlan::Explain {
+                format: ExplainFormat::Json,
+                ..
+            }
+        ));
+
         Ok(())
     }
 
@@ -793,6 +837,55 @@ mod roundtrip_tests {
         Ok(())
     }
 
+    #[test]
+    fn roundtrip_partitioned_table_scan() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        let partitions = ["2020", "2021"]
+            .iter()
+            .map(|year| {
+                let options = CsvReadOptions::new().schema(&schema).file_extension("");
+                let table = CsvFile::try_new(format!("sales/year={}", year), options)?;
+                Ok((
+                    vec![year.to_string()],
+                    Arc::new(table) as Arc<dyn TableProvider>,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let table = PartitionedTable::try_new_with_partitions(
+            Arc::new(schema.clone()),
+            &["year".to_owned()],
+            partitions,
+        )?;
+
+        let plan = LogicalPlanBuilder::scan("sales", Arc::new(table), Some(vec![1, 2]))
+            .and_then(|plan| plan.build())
+            .map_err(BallistaError::DataFusionError)?;
+        roundtrip_test!(plan);
+
+        let proto: protobuf::LogicalPlanNode = (&plan).try_into()?;
+        let round_trip: LogicalPlan = (&proto).try_into()?;
+        let source = match round_trip {
+            LogicalPlan::TableScan { source, .. } => source,
+            _ => panic!("expected a table scan"),
+        };
+        let table = source
+            .as_any()
+            .downcast_ref::<PartitionedTable>()
+            .expect("expected a partitioned table");
+        assert_eq!(table.partition_cols(), vec!["year"]);
+        let values = table
+            .partitions()
+            .iter()
+            .map(|(values, _)| values.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![vec!["2020"], vec!["2021"]]);
+
+        Ok(())
+    }
+
     #[test]
     fn roundtrip_not() -> Result<()> {
         let test_expr = Expr::Not(Box::new(Expr::Literal((1.0).into())));
@@ -820,6 +913,38 @@ mod roundtrip_tests {
         Ok(())
     }
 
+    #[test]
+    fn roundtrip_pattern_match() -> Result<()> {
+        let pattern = || Expr::Literal(ScalarValue::Utf8(Some("a%".to_owned())));
+        let test_exprs = vec![
+            col("name").like(pattern()),
+            col("name").not_like(pattern()),
+            col("name").ilike(pattern()),
+            col("name").not_ilike(pattern()),
+            col("name").similar_to(pattern()),
+            col("name").not_similar_to(pattern()),
+        ];
+        for test_expr in test_exprs {
+            roundtrip_test!(test_expr, protobuf::LogicalExprNode, Expr);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip_filtered_aggregate() -> Result<()> {
+        let test_expr = Expr::AggregateFunction {
+            fun: AggregateFunction::Sum,
+            args: vec![col("salary")],
+            distinct: false,
+            filter: Some(Box::new(col("state").eq(lit("CO")))),
+        };
+
+        roundtrip_test!(test_expr, protobuf::LogicalExprNode, Expr);
+
+        Ok(())
+    }
+
     #[test]
     fn roundtrip_between() -> Result<()> {
         let test_expr = Expr::Between {
@@ -916,4 +1041,50 @@ mod roundtrip_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn roundtrip_query_constants() -> Result<()> {
+        let now = Expr::ScalarFunction {
+            fun: Now,
+            args: vec![],
+        };
+        roundtrip_test!(now, protobuf::LogicalExprNode, Expr);
+        let user = current_user();
+        roundtrip_test!(user, protobuf::LogicalExprNode, Expr);
+        let release = version();
+        roundtrip_test!(release, protobuf::LogicalExprNode, Expr);
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip_window_function() -> Result<()> {
+        let test_expr = Expr::WindowFunction {
+            fun: WindowFunction::BuiltInWindowFunction(BuiltInWindowFunction::NthValue),
+            args: vec![col("a"), lit(2_i64)],
+            partition_by: vec![col("b")],
+            order_by: vec![col("c").sort(true, false)],
+            window_frame: Some(WindowFrame {
+                units: WindowFrameUnits::Rows,
+                start_bound: WindowFrameBound::Preceding(Some(3)),
+                end_bound: WindowFrameBound::Following(None),
+                exclude: WindowFrameExclusion::NoOthers,
+            }),
+            distinct: false,
+        };
+        roundtrip_test!(test_expr, protobuf::LogicalExprNode, Expr);
+
+        // ranking functions have no arguments
+        let test_expr = Expr::WindowFunction {
+            fun: WindowFunction::BuiltInWindowFunction(BuiltInWindowFunction::CumeDist),
+            args: vec![],
+            partition_by: vec![],
+            order_by: vec![col("c").sort(true, false)],
+            window_frame: None,
+            distinct: false,
+        };
+        roundtrip_test!(test_expr, protobuf::LogicalExprNode, Expr);
+
+        Ok(())
+    }
 }
@@ -20,13 +20,20 @@
 //! processes.
 
 use super::super::proto_error;
-use crate::datasource::DfTableAdapter;
+use crate::datasource::{BroadcastTable, DfTableAdapter};
+use crate::execution_plans::encode_batches;
 use crate::serde::{protobuf, BallistaError};
+use crate::wasm::{find_wasm_udf, WasmScalarUdf};
 use datafusion::arrow::datatypes::{DataType, Field, IntervalUnit, Schema, TimeUnit};
-use datafusion::datasource::CsvFile;
+use datafusion::datasource::delta::DeltaTable;
+use datafusion::datasource::partitioned::PartitionedTable;
+use datafusion::datasource::{CsvFile, TableProvider};
 use datafusion::logical_plan::{
-    window_frames::{WindowFrame, WindowFrameBound, WindowFrameUnits},
-    Column, Expr, JoinConstraint, JoinType, LogicalPlan,
+    window_frames::{
+        WindowFrame, WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
+    },
+    Column, ExplainFormat, Expr, JoinConstraint, JoinType, LogicalPlan,
+    LogicalPlanBuilder,
 };
 use datafusion::physical_plan::aggregates::AggregateFunction;
 use datafusion::physical_plan::functions::BuiltinScalarFunction;
@@ -35,7 +42,8 @@ use datafusion::physical_plan::window_functions::{
 };
 use datafusion::{datasource::parquet::ParquetTable, logical_plan::exprlist_to_fields};
 use protobuf::{
-    arrow_type, logical_expr_node::ExprType, scalar_type, DateUnit, PrimitiveScalarType,
+    arrow_type, csv_table_scan_node::OptionalTargetSplitSize,
+    logical_expr_node::ExprType, scalar_type, DateUnit, PrimitiveScalarType,
     ScalarListValue, ScalarType,
 };
 use std::{
@@ -125,6 +133,17 @@ impl From<&Field> for protobuf::Field {
     }
 }
 
+impl From<&WasmScalarUdf> for protobuf::WasmScalarUdf {
+    fn from(udf: &WasmScalarUdf) -> protobuf::WasmScalarUdf {
+        protobuf::WasmScalarUdf {
+            name: udf.name().to_owned(),
+            module: udf.module_bytes().to_vec(),
+            arg_types: udf.arg_types().iter().map(|t| t.into()).collect(),
+            return_type: Some(udf.return_type().into()),
+        }
+    }
+}
+
 impl From<&DataType> for protobuf::ArrowType {
     fn from(val: &DataType) -> protobuf::ArrowType {
         protobuf::ArrowType {
@@ -372,6 +391,8 @@ fn is_valid_scalar_type_no_list_check(datatype: &DataType) -> bool {
         | DataType::Float64
         | DataType::LargeUtf8
         | DataType::Utf8
+        | DataType::Binary
+        | DataType::LargeBinary
         | DataType::Date32 => true,
         DataType::Time64(time_unit) => {
             matches!(time_unit, TimeUnit::Microsecond | TimeUnit::Nanosecond)
@@ -411,6 +432,8 @@ impl TryFrom<&DataType> for protobuf::scalar_type::Datatype {
             },
             DataType::Utf8 => scalar_type::Datatype::Scalar(PrimitiveScalarType::Utf8 as i32),
             DataType::LargeUtf8 => scalar_type::Datatype::Scalar(PrimitiveScalarType::LargeUtf8 as i32),
+            DataType::Binary => scalar_type::Datatype::Scalar(PrimitiveScalarType::Binary as i32),
+            DataType::LargeBinary => scalar_type::Datatype::Scalar(PrimitiveScalarType::LargeBinary as i32),
             DataType::List(field_type) => {
                 let mut field_names: Vec<String> = Vec::new();
                 let mut curr_field = field_type.as_ref();
@@ -455,6 +478,8 @@ impl TryFrom<&DataType> for protobuf::scalar_type::Datatype {
 
                     DataType::Utf8 => PrimitiveScalarType::Utf8,
                     DataType::LargeUtf8 => PrimitiveScalarType::LargeUtf8,
+                    DataType::Binary => PrimitiveScalarType::Binary,
+                    DataType::LargeBinary => PrimitiveScalarType::LargeBinary,
                     _ => {
                         return Err(proto_error(format!(
                             "Error converting to Datatype to scalar type, {:?} is invalid as a datafusion scalar.",
@@ -557,6 +582,16 @@ impl TryFrom<&datafusion::scalar::ScalarValue> for protobuf::ScalarValue {
                     Value::LargeUtf8Value(s.to_owned())
                 })
             }
+            scalar::ScalarValue::Binary(val) => {
+                create_proto_scalar(val, PrimitiveScalarType::Binary, |s| {
+                    Value::BinaryValue(s.to_owned())
+                })
+            }
+            scalar::ScalarValue::LargeBinary(val) => {
+                create_proto_scalar(val, PrimitiveScalarType::LargeBinary, |s| {
+                    Value::LargeBinaryValue(s.to_owned())
+                })
+            }
             scalar::ScalarValue::List(value, datatype) => {
                 println!("Current datatype of list: {:?}", datatype);
                 match value {
@@ -610,6 +645,8 @@ impl TryFrom<&datafusion::scalar::ScalarValue> for protobuf::ScalarValue {
                                     (scalar::ScalarValue::UInt64(_), DataType::UInt64) => scalar.try_into(),
                                     (scalar::ScalarValue::Utf8(_), DataType::Utf8) => scalar.try_into(),
                                     (scalar::ScalarValue::LargeUtf8(_), DataType::LargeUtf8) => scalar.try_into(),
+                                    (scalar::ScalarValue::Binary(_), DataType::Binary) => scalar.try_into(),
+                                    (scalar::ScalarValue::LargeBinary(_), DataType::LargeBinary) => scalar.try_into(),
                                     _ => Err(proto_error(format!(
                                         "Protobuf serialization error, {:?} was inconsistent with designated type {:?}",
                                         scalar, datatype
@@ -714,6 +751,9 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                                 projection,
                                 schema: Some(schema),
                                 filters,
+                                file_list_options: Some(
+                                    parquet.file_list_options().into(),
+                                ),
                             },
                         )),
                     })
@@ -722,6 +762,10 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                     let delimiter = std::str::from_utf8(&delimiter).map_err(|_| {
                         BallistaError::General("Invalid CSV delimiter".to_owned())
                     })?;
+                    let optional_target_split_size =
+                        csv.target_split_size().map(|size| {
+                            OptionalTargetSplitSize::TargetSplitSize(size as u64)
+                        });
                     Ok(protobuf::LogicalPlanNode {
                         logical_plan_type: Some(LogicalPlanType::CsvScan(
                             protobuf::CsvTableScanNode {
@@ -733,6 +777,70 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                                 delimiter: delimiter.to_string(),
                                 file_extension: csv.file_extension().to_string(),
                                 filters,
+                                file_compression_type: protobuf::FileCompressionType::from(
+                                    csv.file_compression_type(),
+                                )
+                                    as i32,
+                                file_list_options: Some(csv.file_list_options().into()),
+                                optional_target_split_size,
+                            },
+                        )),
+                    })
+                } else if let Some(delta) = source.downcast_ref::<DeltaTable>() {
+                    Ok(protobuf::LogicalPlanNode {
+                        logical_plan_type: Some(LogicalPlanType::DeltaScan(
+                            protobuf::DeltaTableScanNode {
+                                table_name: table_name.to_owned(),
+                                path: delta.path().to_owned(),
+                                version: delta.version(),
+                                projection,
+                                schema: Some(schema),
+                                filters,
+                            },
+                        )),
+                    })
+                } else if let Some(partitioned) =
+                    source.downcast_ref::<PartitionedTable>()
+                {
+                    // the table of each partition is serialized as a scan of it
+                    let partitions = partitioned
+                        .partitions()
+                        .iter()
+                        .map(|(values, table)| {
+                            let scan = LogicalPlanBuilder::scan(
+                                table_name,
+                                table.clone(),
+                                None,
+                            )?
+                            .build()?;
+                            Ok(protobuf::TablePartition {
+                                values: values.clone(),
+                                scan: Some((&scan).try_into()?),
+                            })
+                        })
+                        .collect::<Result<Vec<_>, BallistaError>>()?;
+                    Ok(protobuf::LogicalPlanNode {
+                        logical_plan_type: Some(LogicalPlanType::PartitionedScan(
+                            protobuf::PartitionedTableScanNode {
+                                table_name: table_name.to_owned(),
+                                projection,
+                                schema: Some(schema),
+                                filters,
+                                partition_cols: partitioned.partition_cols(),
+                                partitions,
+                            },
+                        )),
+                    })
+                } else if let Some(broadcast) = source.downcast_ref::<BroadcastTable>() {
+                    let data = encode_batches(&broadcast.schema(), broadcast.batches())?;
+                    Ok(protobuf::LogicalPlanNode {
+                        logical_plan_type: Some(LogicalPlanType::BroadcastScan(
+                            protobuf::BroadcastScanNode {
+                                table_name: table_name.to_owned(),
+                                name: broadcast.name().to_owned(),
+                                schema: Some(schema),
+                                projection,
+                                data,
                             },
                         )),
                     })
@@ -812,6 +920,7 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                 on,
                 join_type,
                 join_constraint,
+                null_equals_null,
                 ..
             } => {
                 let left: protobuf::LogicalPlanNode = left.as_ref().try_into()?;
@@ -830,17 +939,19 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                             join_constraint: join_constraint.into(),
                             left_join_column,
                             right_join_column,
+                            null_equals_null: *null_equals_null,
                         },
                     ))),
                 })
             }
-            LogicalPlan::Limit { input, n } => {
+            LogicalPlan::Limit { input, n, offset } => {
                 let input: protobuf::LogicalPlanNode = input.as_ref().try_into()?;
                 Ok(protobuf::LogicalPlanNode {
                     logical_plan_type: Some(LogicalPlanType::Limit(Box::new(
                         protobuf::LimitNode {
                             input: Some(Box::new(input)),
                             limit: *n as u32,
+                            offset: *offset as u32,
                         },
                     ))),
                 })
@@ -910,6 +1021,8 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                 file_type,
                 has_header,
                 schema: df_schema,
+                table_partition_cols,
+                options,
             } => {
                 use datafusion::sql::parser::FileType;
 
@@ -917,6 +1030,7 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                     FileType::NdJson => protobuf::FileType::NdJson,
                     FileType::Parquet => protobuf::FileType::Parquet,
                     FileType::CSV => protobuf::FileType::Csv,
+                    FileType::Delta => protobuf::FileType::Delta,
                 };
 
                 Ok(protobuf::LogicalPlanNode {
@@ -927,23 +1041,48 @@ impl TryInto<protobuf::LogicalPlanNode> for &LogicalPlan {
                             file_type: pb_file_type as i32,
                             has_header: *has_header,
                             schema: Some(df_schema.into()),
+                            table_partition_cols: table_partition_cols.clone(),
+                            options: options.clone(),
                         },
                     )),
                 })
             }
-            LogicalPlan::Explain { verbose, plan, .. } => {
+            LogicalPlan::Explain {
+                verbose,
+                format,
+                plan,
+                ..
+            } => {
                 let input: protobuf::LogicalPlanNode = plan.as_ref().try_into()?;
+                let format = match format {
+                    ExplainFormat::Text => protobuf::ExplainFormat::Text,
+                    ExplainFormat::Json => protobuf::ExplainFormat::Json,
+                    ExplainFormat::Graphviz => protobuf::ExplainFormat::Graphviz,
+                };
                 Ok(protobuf::LogicalPlanNode {
                     logical_plan_type: Some(LogicalPlanType::Explain(Box::new(
                         protobuf::ExplainNode {
                             input: Some(Box::new(input)),
                             verbose: *verbose,
+                            format: format.into(),
                         },
                     ))),
                 })
             }
             LogicalPlan::Extension { .. } => unimplemented!(),
             LogicalPlan::Union { .. } => unimplemented!(),
+            LogicalPlan::Unnest { .. } => Err(BallistaError::NotImplemented(
+                "Unnest cannot be serialized".to_owned(),
+            )),
+            LogicalPlan::CreateFunction { name, .. } => {
+                Err(BallistaError::NotImplemented(format!(
+                    "CREATE FUNCTION {} cannot be serialized",
+                    name
+                )))
+            }
+            LogicalPlan::CopyTo { path, .. } => Err(BallistaError::NotImplemented(
+                format!("COPY TO '{}' cannot be serialized", path),
+            )),
             LogicalPlan::CrossJoin { .. } => unimplemented!(),
         }
     }
@@ -1006,6 +1145,7 @@ impl TryInto<protobuf::LogicalExprNode> for &Expr {
                 ref partition_by,
                 ref order_by,
                 ref window_frame,
+                distinct,
             } => {
                 let window_function = match fun {
                     WindowFunction::AggregateFunction(fun) => {
@@ -1018,8 +1158,17 @@ impl TryInto<protobuf::LogicalExprNode> for &Expr {
                             protobuf::BuiltInWindowFunction::from(fun).into(),
                         )
                     }
+                    WindowFunction::WindowUDF(fun) => {
+                        return Err(BallistaError::NotImplemented(format!(
+                            "Window UDF {} cannot be serialized",
+                            fun.name
+                        )));
+                    }
                 };
-                let arg = &args[0];
+                let args = args
+                    .iter()
+                    .map(|e| e.try_into())
+                    .collect::<Result<Vec<_>, _>>()?;
                 let partition_by = partition_by
                     .iter()
                     .map(|e| e.try_into())
@@ -1031,32 +1180,39 @@ impl TryInto<protobuf::LogicalExprNode> for &Expr {
                 let window_frame = window_frame.map(|window_frame| {
                     protobuf::window_expr_node::WindowFrame::Frame(window_frame.into())
                 });
-                let window_expr = Box::new(protobuf::WindowExprNode {
-                    expr: Some(Box::new(arg.try_into()?)),
+                let window_expr = protobuf::WindowExprNode {
+                    args,
                     window_function: Some(window_function),
                     partition_by,
                     order_by,
                     window_frame,
-                });
+                    distinct,
+                };
                 Ok(protobuf::LogicalExprNode {
                     expr_type: Some(ExprType::WindowExpr(window_expr)),
                 })
             }
             Expr::AggregateFunction {
-                ref fun, ref args, ..
+                ref fun,
+                ref args,
+                ref filter,
+                ..
             } => {
-                let aggr_function = match fun {
-                    AggregateFunction::Min => protobuf::AggregateFunction::Min,
-                    AggregateFunction::Max => protobuf::AggregateFunction::Max,
-                    AggregateFunction::Sum => protobuf::AggregateFunction::Sum,
-                    AggregateFunction::Avg => protobuf::AggregateFunction::Avg,
-                    AggregateFunction::Count => protobuf::AggregateFunction::Count,
-                };
+                let aggr_function = protobuf::AggregateFunction::from(fun);
 
                 let arg = &args[0];
+                let other_args = args[1..]
+                    .iter()
+                    .map(|e| e.try_into())
+                    .collect::<Result<Vec<_>, BallistaError>>()?;
                 let aggregate_expr = Box::new(protobuf::AggregateExprNode {
                     aggr_function: aggr_function.into(),
                     expr: Some(Box::new(arg.try_into()?)),
+                    other_args,
+                    filter: match filter {
+                        Some(e) => Some(Box::new(e.as_ref().try_into()?)),
+                        None => None,
+                    },
                 });
                 Ok(protobuf::LogicalExprNode {
                     expr_type: Some(ExprType::AggregateExpr(aggregate_expr)),
@@ -1080,7 +1236,24 @@ impl TryInto<protobuf::LogicalExprNode> for &Expr {
                     ),
                 })
             }
-            Expr::ScalarUDF { .. } => unimplemented!(),
+            Expr::ScalarUDF { ref fun, ref args } => {
+                let udf = find_wasm_udf(&fun.fun).ok_or_else(|| {
+                    BallistaError::NotImplemented(format!(
+                        "UDF {} cannot be serialized, only WebAssembly UDFs are supported",
+                        fun.name
+                    ))
+                })?;
+                let args: Vec<protobuf::LogicalExprNode> = args
+                    .iter()
+                    .map(|e| e.try_into())
+                    .collect::<Result<Vec<protobuf::LogicalExprNode>, BallistaError>>()?;
+                Ok(protobuf::LogicalExprNode {
+                    expr_type: Some(ExprType::WasmUdf(protobuf::WasmScalarUdfExprNode {
+                        udf: Some(udf.as_ref().into()),
+                        args,
+                    })),
+                })
+            }
             Expr::AggregateUDF { .. } => unimplemented!(),
             Expr::Not(expr) => {
                 let expr = Box::new(protobuf::Not {
@@ -1266,6 +1439,10 @@ impl From<&AggregateFunction> for protobuf::AggregateFunction {
             AggregateFunction::Sum => Self::Sum,
             AggregateFunction::Avg => Self::Avg,
             AggregateFunction::Count => Self::Count,
+            AggregateFunction::ArrayAgg => Self::ArrayAgg,
+            AggregateFunction::FirstValue => Self::FirstValue,
+            AggregateFunction::LastValue => Self::LastValue,
+            AggregateFunction::StringAgg => Self::StringAgg,
         }
     }
 }
@@ -1326,6 +1503,18 @@ impl From<WindowFrame> for protobuf::WindowFrame {
             end_bound: Some(protobuf::window_frame::EndBound::Bound(
                 window.end_bound.into(),
             )),
+            exclude: protobuf::WindowFrameExclusion::from(window.exclude).into(),
+        }
+    }
+}
+
+impl From<WindowFrameExclusion> for protobuf::WindowFrameExclusion {
+    fn from(exclude: WindowFrameExclusion) -> Self {
+        match exclude {
+            WindowFrameExclusion::NoOthers => Self::ExcludeNoOthers,
+            WindowFrameExclusion::CurrentRow => Self::ExcludeCurrentRow,
+            WindowFrameExclusion::Group => Self::ExcludeGroup,
+            WindowFrameExclusion::Ties => Self::ExcludeTies,
         }
     }
 }
@@ -1376,11 +1565,18 @@ impl TryInto<protobuf::ScalarFunction> for &BuiltinScalarFunction {
             BuiltinScalarFunction::NullIf => Ok(protobuf::ScalarFunction::Nullif),
             BuiltinScalarFunction::DatePart => Ok(protobuf::ScalarFunction::Datepart),
             BuiltinScalarFunction::DateTrunc => Ok(protobuf::ScalarFunction::Datetrunc),
+            BuiltinScalarFunction::DateBin => Ok(protobuf::ScalarFunction::Datebin),
+            BuiltinScalarFunction::ToChar => Ok(protobuf::ScalarFunction::Tochar),
             BuiltinScalarFunction::MD5 => Ok(protobuf::ScalarFunction::Md5),
             BuiltinScalarFunction::SHA224 => Ok(protobuf::ScalarFunction::Sha224),
             BuiltinScalarFunction::SHA256 => Ok(protobuf::ScalarFunction::Sha256),
             BuiltinScalarFunction::SHA384 => Ok(protobuf::ScalarFunction::Sha384),
             BuiltinScalarFunction::SHA512 => Ok(protobuf::ScalarFunction::Sha512),
+            BuiltinScalarFunction::Now => Ok(protobuf::ScalarFunction::Now),
+            BuiltinScalarFunction::CurrentUser => {
+                Ok(protobuf::ScalarFunction::Currentuser)
+            }
+            BuiltinScalarFunction::Version => Ok(protobuf::ScalarFunction::Version),
             _ => Err(BallistaError::General(format!(
                 "logical_plan::to_proto() unsupported scalar function {:?}",
                 self
@@ -22,6 +22,9 @@ use std::{convert::TryInto, io::Cursor};
 
 use datafusion::logical_plan::{JoinConstraint, JoinType, Operator};
 use datafusion::physical_plan::aggregates::AggregateFunction;
+use datafusion::physical_plan::common::FileListOptions;
+use datafusion::physical_plan::file_compression::FileCompressionType;
+use datafusion::physical_plan::partition_hash::HashScheme;
 use datafusion::physical_plan::window_functions::BuiltInWindowFunction;
 
 use crate::{error::BallistaError, serde::scheduler::Action as BallistaAction};
@@ -34,8 +37,10 @@ pub mod protobuf {
     include!(concat!(env!("OUT_DIR"), "/ballista.protobuf.rs"));
 }
 
+pub mod compression;
 pub mod logical_plan;
 pub mod physical_plan;
+pub mod protocol;
 pub mod scheduler;
 
 pub fn decode_protobuf(bytes: &[u8]) -> Result<BallistaAction, BallistaError> {
@@ -97,7 +102,15 @@ pub(crate) fn from_proto_binary_op(op: &str) -> Result<Operator, BallistaError>
         "Minus" => Ok(Operator::Minus),
         "Multiply" => Ok(Operator::Multiply),
         "Divide" => Ok(Operator::Divide),
+        "Modulus" => Ok(Operator::Modulus),
         "Like" => Ok(Operator::Like),
+        "NotLike" => Ok(Operator::NotLike),
+        "ILike" => Ok(Operator::ILike),
+        "NotILike" => Ok(Operator::NotILike),
+        "SimilarTo" => Ok(Operator::SimilarTo),
+        "NotSimilarTo" => Ok(Operator::NotSimilarTo),
+        "IsDistinctFrom" => Ok(Operator::IsDistinctFrom),
+        "IsNotDistinctFrom" => Ok(Operator::IsNotDistinctFrom),
         other => Err(proto_error(format!(
             "Unsupported binary operator '{:?}'",
             other
@@ -113,6 +126,10 @@ impl From<protobuf::AggregateFunction> for AggregateFunction {
             protobuf::AggregateFunction::Sum => AggregateFunction::Sum,
             protobuf::AggregateFunction::Avg => AggregateFunction::Avg,
             protobuf::AggregateFunction::Count => AggregateFunction::Count,
+            protobuf::AggregateFunction::ArrayAgg => AggregateFunction::ArrayAgg,
+            protobuf::AggregateFunction::FirstValue => AggregateFunction::FirstValue,
+            protobuf::AggregateFunction::LastValue => AggregateFunction::LastValue,
+            protobuf::AggregateFunction::StringAgg => AggregateFunction::StringAgg,
         }
     }
 }
@@ -280,6 +297,8 @@ impl Into<datafusion::arrow::datatypes::DataType> for protobuf::PrimitiveScalarT
             protobuf::PrimitiveScalarType::Float64 => DataType::Float64,
             protobuf::PrimitiveScalarType::Utf8 => DataType::Utf8,
             protobuf::PrimitiveScalarType::LargeUtf8 => DataType::LargeUtf8,
+            protobuf::PrimitiveScalarType::Binary => DataType::Binary,
+            protobuf::PrimitiveScalarType::LargeBinary => DataType::LargeBinary,
             protobuf::PrimitiveScalarType::Date32 => DataType::Date32,
             protobuf::PrimitiveScalarType::TimeMicrosecond => {
                 DataType::Time64(TimeUnit::Microsecond)
@@ -301,6 +320,7 @@ impl From<protobuf::JoinType> for JoinType {
             protobuf::JoinType::Full => JoinType::Full,
             protobuf::JoinType::Semi => JoinType::Semi,
             protobuf::JoinType::Anti => JoinType::Anti,
+            protobuf::JoinType::NullAwareAnti => JoinType::NullAwareAnti,
         }
     }
 }
@@ -314,6 +334,7 @@ impl From<JoinType> for protobuf::JoinType {
             JoinType::Full => protobuf::JoinType::Full,
             JoinType::Semi => protobuf::JoinType::Semi,
             JoinType::Anti => protobuf::JoinType::Anti,
+            JoinType::NullAwareAnti => protobuf::JoinType::NullAwareAnti,
         }
     }
 }
@@ -335,3 +356,73 @@ impl From<JoinConstraint> for protobuf::JoinConstraint {
         }
     }
 }
+
+impl From<protobuf::FileCompressionType> for Option<FileCompressionType> {
+    fn from(t: protobuf::FileCompressionType) -> Self {
+        match t {
+            protobuf::FileCompressionType::Auto => None,
+            protobuf::FileCompressionType::Uncompressed => {
+                Some(FileCompressionType::Uncompressed)
+            }
+            protobuf::FileCompressionType::Gzip => Some(FileCompressionType::Gzip),
+            protobuf::FileCompressionType::Bzip2 => Some(FileCompressionType::Bzip2),
+            protobuf::FileCompressionType::Zstd => Some(FileCompressionType::Zstd),
+        }
+    }
+}
+
+impl From<Option<FileCompressionType>> for protobuf::FileCompressionType {
+    fn from(t: Option<FileCompressionType>) -> Self {
+        match t {
+            None => protobuf::FileCompressionType::Auto,
+            Some(FileCompressionType::Uncompressed) => {
+                protobuf::FileCompressionType::Uncompressed
+            }
+            Some(FileCompressionType::Gzip) => protobuf::FileCompressionType::Gzip,
+            Some(FileCompressionType::Bzip2) => protobuf::FileCompressionType::Bzip2,
+            Some(FileCompressionType::Zstd) => protobuf::FileCompressionType::Zstd,
+        }
+    }
+}
+
+impl From<protobuf::HashScheme> for HashScheme {
+    fn from(t: protobuf::HashScheme) -> Self {
+        match t {
+            protobuf::HashScheme::Internal => HashScheme::Internal,
+            protobuf::HashScheme::Murmur3V1 => HashScheme::Murmur3V1,
+        }
+    }
+}
+
+impl From<HashScheme> for protobuf::HashScheme {
+    fn from(t: HashScheme) -> Self {
+        match t {
+            HashScheme::Internal => protobuf::HashScheme::Internal,
+            HashScheme::Murmur3V1 => protobuf::HashScheme::Murmur3V1,
+        }
+    }
+}
+
+impl From<protobuf::FileListOptions> for FileListOptions {
+    fn from(options: protobuf::FileListOptions) -> Self {
+        Self {
+            max_depth: options.optional_max_depth.map(|max_depth| match max_depth {
+                protobuf::file_list_options::OptionalMaxDepth::MaxDepth(max_depth) => {
+                    max_depth as usize
+                }
+            }),
+            include_hidden: options.include_hidden,
+        }
+    }
+}
+
+impl From<FileListOptions> for protobuf::FileListOptions {
+    fn from(options: FileListOptions) -> Self {
+        Self {
+            optional_max_depth: options.max_depth.map(|max_depth| {
+                protobuf::file_list_options::OptionalMaxDepth::MaxDepth(max_depth as u64)
+            }),
+            include_hidden: options.include_hidden,
+        }
+    }
+}
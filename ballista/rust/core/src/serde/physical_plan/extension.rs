@@ -0,0 +1,63 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Extension point for serializing user-defined physical operators, which lets
+//! custom [ExecutionPlan] implementations be distributed by Ballista.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use datafusion::physical_plan::ExecutionPlan;
+use once_cell::sync::OnceCell;
+
+use crate::error::{BallistaError, Result};
+
+/// Encodes and decodes the physical operators that the built-in protobuf codec does
+/// not know about. The encoded bytes are opaque to Ballista and are carried in a
+/// `PhysicalExtensionNode` together with the operator's inputs, which are
+/// serialized by Ballista itself.
+pub trait PhysicalExtensionCodec: Debug + Send + Sync {
+    /// Create the operator encoded in `buf`, with the given, already decoded, inputs
+    fn try_decode(
+        &self,
+        buf: &[u8],
+        inputs: &[Arc<dyn ExecutionPlan>],
+    ) -> Result<Arc<dyn ExecutionPlan>>;
+
+    /// Encode `node` into `buf`, or return an error if the operator is not supported
+    /// by this codec. The inputs of `node` must not be encoded.
+    fn try_encode(&self, node: Arc<dyn ExecutionPlan>, buf: &mut Vec<u8>) -> Result<()>;
+}
+
+/// The codec for user-defined operators of this process, see [configure_extension_codec]
+static EXTENSION_CODEC: OnceCell<Arc<dyn PhysicalExtensionCodec>> = OnceCell::new();
+
+/// Sets the codec used to serialize user-defined physical operators in this process.
+/// The scheduler and all executors of a cluster must be configured with compatible
+/// codecs. This can only be called once, before any plan is serialized.
+pub fn configure_extension_codec(codec: Arc<dyn PhysicalExtensionCodec>) -> Result<()> {
+    EXTENSION_CODEC.set(codec).map_err(|_| {
+        BallistaError::General(
+            "A physical extension codec has already been configured".to_owned(),
+        )
+    })
+}
+
+/// The codec set with [configure_extension_codec], if any
+pub(crate) fn extension_codec() -> Option<&'static Arc<dyn PhysicalExtensionCodec>> {
+    EXTENSION_CODEC.get()
+}
@@ -23,28 +23,35 @@ use std::sync::Arc;
 
 use crate::error::BallistaError;
 use crate::execution_plans::{
-    ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec,
+    BroadcastExec, ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec,
 };
+use crate::serde::physical_plan::extension::extension_codec;
 use crate::serde::protobuf::repartition_exec_node::PartitionMethod;
 use crate::serde::protobuf::ShuffleReaderPartition;
 use crate::serde::scheduler::PartitionLocation;
 use crate::serde::{from_proto_binary_op, proto_error, protobuf};
+use crate::wasm::WasmScalarUdf;
 use crate::{convert_box_required, convert_required, into_required};
 use datafusion::arrow::datatypes::{DataType, Schema, SchemaRef};
 use datafusion::catalog::catalog::{
     CatalogList, CatalogProvider, MemoryCatalogList, MemoryCatalogProvider,
 };
+use datafusion::datasource::datasource::Statistics;
 use datafusion::execution::context::{
     ExecutionConfig, ExecutionContextState, ExecutionProps,
 };
 use datafusion::logical_plan::{
     window_frames::WindowFrame, DFSchema, Expr, JoinConstraint, JoinType,
 };
-use datafusion::physical_plan::aggregates::{create_aggregate_expr, AggregateFunction};
+use datafusion::physical_plan::aggregates::{
+    create_aggregate_expr_with_ordering, AggregateFunction,
+};
 use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
 use datafusion::physical_plan::hash_aggregate::{AggregateMode, HashAggregateExec};
 use datafusion::physical_plan::hash_join::PartitionMode;
+use datafusion::physical_plan::partition_hash::HashScheme;
 use datafusion::physical_plan::planner::DefaultPhysicalPlanner;
+use datafusion::physical_plan::sorted_aggregate::SortedAggregateExec;
 use datafusion::physical_plan::window_functions::{
     BuiltInWindowFunction, WindowFunction,
 };
@@ -54,18 +61,19 @@ use datafusion::physical_plan::{
     csv::CsvExec,
     empty::EmptyExec,
     expressions::{
-        col, Avg, BinaryExpr, CaseExpr, CastExpr, Column, InListExpr, IsNotNullExpr,
-        IsNullExpr, Literal, NegativeExpr, NotExpr, PhysicalSortExpr, TryCastExpr,
-        DEFAULT_DATAFUSION_CAST_OPTIONS,
+        col, Avg, BinaryExpr, CaseExpr, CastExpr, Column, FilteredAggregate,
+        FusedPredicateExpr, InListExpr, IsNotNullExpr, IsNullExpr, Literal, NegativeExpr,
+        NotExpr, PhysicalSortExpr, TryCastExpr, DEFAULT_DATAFUSION_CAST_OPTIONS,
     },
     filter::FilterExec,
     functions::{self, BuiltinScalarFunction, ScalarFunctionExpr},
     hash_join::HashJoinExec,
     limit::{GlobalLimitExec, LocalLimitExec},
-    parquet::ParquetExec,
+    parquet::{ParquetExec, ParquetExecMetrics, ParquetPartition},
     projection::ProjectionExec,
     repartition::RepartitionExec,
     sort::{SortExec, SortOptions},
+    sort_preserving_merge::SortPreservingMergeExec,
     Partitioning,
 };
 use datafusion::physical_plan::{AggregateExpr, ExecutionPlan, PhysicalExpr, WindowExpr};
@@ -73,6 +81,7 @@ use datafusion::prelude::CsvReadOptions;
 use log::debug;
 use protobuf::physical_expr_node::ExprType;
 use protobuf::physical_plan_node::PhysicalPlanType;
+use protobuf::physical_window_expr_node::WindowFrame as WindowFrameNode;
 
 impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
     type Error = BallistaError;
@@ -113,11 +122,29 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
             }
             PhysicalPlanType::CsvScan(scan) => {
                 let schema = Arc::new(convert_required!(scan.schema)?);
-                let options = CsvReadOptions::new()
+                let file_compression_type = protobuf::FileCompressionType::from_i32(
+                    scan.file_compression_type,
+                )
+                .ok_or_else(|| {
+                    proto_error(format!(
+                        "Received a CsvScanExecNode message with unknown FileCompressionType {}",
+                        scan.file_compression_type
+                    ))
+                })?;
+                let mut options = CsvReadOptions::new()
                     .has_header(scan.has_header)
                     .file_extension(&scan.file_extension)
                     .delimiter(scan.delimiter.as_bytes()[0])
                     .schema(&schema);
+                options.file_compression_type = file_compression_type.into();
+                if let Some(
+                    protobuf::csv_scan_exec_node::OptionalTargetSplitSize::TargetSplitSize(
+                        size,
+                    ),
+                ) = scan.optional_target_split_size
+                {
+                    options = options.target_split_size(size as usize);
+                }
                 let projection = scan.projection.iter().map(|i| *i as usize).collect();
                 Ok(Arc::new(CsvExec::try_new(
                     &scan.path,
@@ -131,8 +158,47 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                 let projection = scan.projection.iter().map(|i| *i as usize).collect();
                 let filenames: Vec<&str> =
                     scan.filename.iter().map(|s| s.as_str()).collect();
-                Ok(Arc::new(ParquetExec::try_from_files(
+                let schema = match &scan.schema {
+                    Some(schema) => {
+                        let schema: Schema = schema.try_into()?;
+                        Some(Arc::new(schema))
+                    }
+                    None => None,
+                };
+                if !scan.partitions.is_empty() {
+                    let schema = schema.ok_or_else(|| {
+                        proto_error("Received a ParquetScanExecNode without a schema")
+                    })?;
+                    // the partitions are read as planned, the statistics of their
+                    // files are not needed to execute them
+                    let partitions = scan
+                        .partitions
+                        .iter()
+                        .map(|part| {
+                            let mut partition = ParquetPartition::new(
+                                part.filename.clone(),
+                                Statistics::default(),
+                            );
+                            partition.row_groups = part
+                                .row_groups
+                                .as_ref()
+                                .map(|range| range.start as usize..range.end as usize);
+                            partition
+                        })
+                        .collect();
+                    return Ok(Arc::new(ParquetExec::new(
+                        partitions,
+                        schema,
+                        Some(projection),
+                        ParquetExecMetrics::new(),
+                        None,
+                        scan.batch_size as usize,
+                        None,
+                    )));
+                }
+                Ok(Arc::new(ParquetExec::try_from_files_with_schema(
                     &filenames,
+                    schema,
                     Some(projection),
                     None,
                     scan.batch_size as usize,
@@ -162,13 +228,16 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                             .map(|e| e.try_into())
                             .collect::<Result<Vec<Arc<dyn PhysicalExpr>>, _>>()?;
 
-                        Ok(Arc::new(RepartitionExec::try_new(
-                            input,
-                            Partitioning::Hash(
-                                expr,
-                                hash_part.partition_count.try_into().unwrap(),
-                            ),
-                        )?))
+                        Ok(Arc::new(
+                            RepartitionExec::try_new(
+                                input,
+                                Partitioning::Hash(
+                                    expr,
+                                    hash_part.partition_count.try_into().unwrap(),
+                                ),
+                            )?
+                            .with_hash_scheme(parse_hash_scheme(hash_part.hash_scheme)?),
+                        ))
                     }
                     Some(PartitionMethod::RoundRobin(partition_count)) => {
                         Ok(Arc::new(RepartitionExec::try_new(
@@ -193,7 +262,10 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
             }
             PhysicalPlanType::GlobalLimit(limit) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(limit.input)?;
-                Ok(Arc::new(GlobalLimitExec::new(input, limit.limit as usize)))
+                Ok(Arc::new(
+                    GlobalLimitExec::new(input, limit.limit as usize)
+                        .with_offset(limit.offset as usize),
+                ))
             }
             PhysicalPlanType::LocalLimit(limit) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(limit.input)?;
@@ -224,15 +296,41 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                         })?;
 
                         match expr_type {
-                            ExprType::WindowExpr(window_node) => Ok(create_window_expr(
-                                &convert_required!(window_node.window_function)?,
-                                name.to_owned(),
-                                &[convert_box_required!(window_node.expr)?],
-                                &[],
-                                &[],
-                                Some(WindowFrame::default()),
-                                &physical_schema,
-                            )?),
+                            ExprType::WindowExpr(window_node) => {
+                                let args = window_node
+                                    .args
+                                    .iter()
+                                    .map(|e| e.try_into())
+                                    .collect::<Result<Vec<_>, BallistaError>>()?;
+                                let partition_by = window_node
+                                    .partition_by
+                                    .iter()
+                                    .map(|e| e.try_into())
+                                    .collect::<Result<Vec<_>, BallistaError>>()?;
+                                let order_by =
+                                    parse_sort_exprs(self, &window_node.order_by)?;
+                                let window_frame = window_node
+                                    .window_frame
+                                    .as_ref()
+                                    .map::<Result<WindowFrame, BallistaError>, _>(
+                                        |window_frame| match window_frame {
+                                            WindowFrameNode::Frame(frame) => {
+                                                frame.clone().try_into()
+                                            }
+                                        },
+                                    )
+                                    .transpose()?;
+                                Ok(create_window_expr(
+                                    &convert_required!(window_node.window_function)?,
+                                    name.to_owned(),
+                                    &args,
+                                    &partition_by,
+                                    &order_by,
+                                    window_frame,
+                                    window_node.distinct,
+                                    &physical_schema,
+                                )?)
+                            }
                             _ => Err(BallistaError::General(
                                 "Invalid expression for WindowAggrExec".to_string(),
                             )),
@@ -261,6 +359,9 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     protobuf::AggregateMode::FinalPartitioned => {
                         AggregateMode::FinalPartitioned
                     }
+                    protobuf::AggregateMode::SinglePartitioned => {
+                        AggregateMode::SinglePartitioned
+                    }
                 };
                 let group = hash_agg
                     .group_expr
@@ -307,13 +408,31 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                                         },
                                     )?;
 
-                                Ok(create_aggregate_expr(
+                                let mut args = vec![convert_box_required!(agg_node.expr)?];
+                                for arg in &agg_node.other_args {
+                                    args.push(arg.try_into()?);
+                                }
+                                let ordering = parse_sort_exprs(self, &agg_node.ordering)?;
+
+                                let aggregate = create_aggregate_expr_with_ordering(
                                     &aggr_function.into(),
                                     false,
-                                    &[convert_box_required!(agg_node.expr)?],
+                                    &args,
+                                    &ordering,
                                     &physical_schema,
                                     name.to_string(),
-                                )?)
+                                )?;
+                                match &agg_node.filter {
+                                    Some(filter) => Ok(Arc::new(
+                                        FilteredAggregate::try_new(
+                                            aggregate,
+                                            filter.as_ref().try_into()?,
+                                            &physical_schema,
+                                        )?,
+                                    )
+                                        as Arc<dyn AggregateExpr>),
+                                    None => Ok(aggregate),
+                                }
                             }
                             _ => Err(BallistaError::General(
                                 "Invalid aggregate  expression for HashAggregateExec"
@@ -323,13 +442,24 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
-                Ok(Arc::new(HashAggregateExec::try_new(
-                    agg_mode,
-                    group,
-                    physical_aggr_expr,
-                    input,
-                    Arc::new((&input_schema).try_into()?),
-                )?))
+                let input_schema = Arc::new((&input_schema).try_into()?);
+                if hash_agg.sorted {
+                    Ok(Arc::new(SortedAggregateExec::try_new(
+                        agg_mode,
+                        group,
+                        physical_aggr_expr,
+                        input,
+                        input_schema,
+                    )?))
+                } else {
+                    Ok(Arc::new(HashAggregateExec::try_new(
+                        agg_mode,
+                        group,
+                        physical_aggr_expr,
+                        input,
+                        input_schema,
+                    )?))
+                }
             }
             PhysicalPlanType::HashJoin(hashjoin) => {
                 let left: Arc<dyn ExecutionPlan> = convert_box_required!(hashjoin.left)?;
@@ -370,6 +500,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     on,
                     &join_type.into(),
                     partition_mode,
+                    hashjoin.null_equals_null,
                 )?))
             }
             PhysicalPlanType::ShuffleWriter(shuffle_writer) => {
@@ -379,14 +510,25 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                 let output_partitioning = parse_protobuf_hash_partitioning(
                     shuffle_writer.output_partitioning.as_ref(),
                 )?;
+                let hash_scheme = match &shuffle_writer.output_partitioning {
+                    Some(hash_part) => parse_hash_scheme(hash_part.hash_scheme)?,
+                    None => HashScheme::default(),
+                };
 
-                Ok(Arc::new(ShuffleWriterExec::try_new(
-                    shuffle_writer.job_id.clone(),
-                    shuffle_writer.stage_id as usize,
-                    input,
-                    "".to_string(), // this is intentional but hacky - the executor will fill this in
-                    output_partitioning,
-                )?))
+                let results_path = Some(shuffle_writer.results_path.clone())
+                    .filter(|path| !path.is_empty());
+
+                Ok(Arc::new(
+                    ShuffleWriterExec::try_new(
+                        shuffle_writer.job_id.clone(),
+                        shuffle_writer.stage_id as usize,
+                        input,
+                        "".to_string(), // this is intentional but hacky - the executor will fill this in
+                        output_partitioning,
+                    )?
+                    .with_hash_scheme(hash_scheme)
+                    .with_results_path(results_path),
+                ))
             }
             PhysicalPlanType::ShuffleReader(shuffle_reader) => {
                 let schema = Arc::new(convert_required!(shuffle_reader.schema)?);
@@ -410,44 +552,22 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
             }
             PhysicalPlanType::Sort(sort) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(sort.input)?;
-                let exprs = sort
-                    .expr
-                    .iter()
-                    .map(|expr| {
-                        let expr = expr.expr_type.as_ref().ok_or_else(|| {
-                            proto_error(format!(
-                                "physical_plan::from_proto() Unexpected expr {:?}",
-                                self
-                            ))
-                        })?;
-                        if let protobuf::physical_expr_node::ExprType::Sort(sort_expr) = expr {
-                            let expr = sort_expr
-                                .expr
-                                .as_ref()
-                                .ok_or_else(|| {
-                                    proto_error(format!(
-                                        "physical_plan::from_proto() Unexpected sort expr {:?}",
-                                        self
-                                    ))
-                                })?
-                                .as_ref();
-                            Ok(PhysicalSortExpr {
-                                expr: expr.try_into()?,
-                                options: SortOptions {
-                                    descending: !sort_expr.asc,
-                                    nulls_first: sort_expr.nulls_first,
-                                },
-                            })
-                        } else {
-                            Err(BallistaError::General(format!(
-                                "physical_plan::from_proto() {:?}",
-                                self
-                            )))
-                        }
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
+                let exprs = parse_sort_exprs(self, &sort.expr)?;
                 // Update concurrency here in the future
-                Ok(Arc::new(SortExec::try_new(exprs, input)?))
+                Ok(Arc::new(SortExec::new_with_partitioning(
+                    exprs,
+                    input,
+                    sort.preserve_partitioning,
+                )))
+            }
+            PhysicalPlanType::SortPreservingMerge(merge) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(merge.input)?;
+                let exprs = parse_sort_exprs(self, &merge.expr)?;
+                Ok(Arc::new(SortPreservingMergeExec::new(
+                    exprs,
+                    input,
+                    merge.target_batch_size as usize,
+                )))
             }
             PhysicalPlanType::Unresolved(unresolved_shuffle) => {
                 let schema = Arc::new(convert_required!(unresolved_shuffle.schema)?);
@@ -458,8 +578,40 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                         as usize,
                     output_partition_count: unresolved_shuffle.output_partition_count
                         as usize,
+                    limit: unresolved_shuffle.optional_limit.as_ref().map(|limit| {
+                        match limit {
+                            protobuf::unresolved_shuffle_exec_node::OptionalLimit::Limit(
+                                limit,
+                            ) => *limit as usize,
+                        }
+                    }),
+                    // statistics are only used while the job is planned
+                    statistics: Default::default(),
                 }))
             }
+            PhysicalPlanType::Broadcast(broadcast) => {
+                let schema = Arc::new(convert_required!(broadcast.schema)?);
+                let projection =
+                    broadcast.projection.iter().map(|i| *i as usize).collect();
+                Ok(Arc::new(BroadcastExec::try_new_unresolved(
+                    &broadcast.name,
+                    schema,
+                    Some(projection),
+                )?))
+            }
+            PhysicalPlanType::Extension(extension) => {
+                let codec = extension_codec().ok_or_else(|| {
+                    proto_error(
+                        "Received a PhysicalExtensionNode but no physical extension codec is configured",
+                    )
+                })?;
+                let inputs = extension
+                    .inputs
+                    .iter()
+                    .map(|input| input.try_into())
+                    .collect::<Result<Vec<_>, BallistaError>>()?;
+                codec.try_decode(&extension.node, &inputs)
+            }
         }
     }
 }
@@ -503,12 +655,17 @@ impl From<&protobuf::ScalarFunction> for BuiltinScalarFunction {
             ScalarFunction::Nullif => BuiltinScalarFunction::NullIf,
             ScalarFunction::Datepart => BuiltinScalarFunction::DatePart,
             ScalarFunction::Datetrunc => BuiltinScalarFunction::DateTrunc,
+            ScalarFunction::Datebin => BuiltinScalarFunction::DateBin,
             ScalarFunction::Md5 => BuiltinScalarFunction::MD5,
             ScalarFunction::Sha224 => BuiltinScalarFunction::SHA224,
             ScalarFunction::Sha256 => BuiltinScalarFunction::SHA256,
             ScalarFunction::Sha384 => BuiltinScalarFunction::SHA384,
             ScalarFunction::Sha512 => BuiltinScalarFunction::SHA512,
             ScalarFunction::Ln => BuiltinScalarFunction::Ln,
+            ScalarFunction::Tochar => BuiltinScalarFunction::ToChar,
+            ScalarFunction::Now => BuiltinScalarFunction::Now,
+            ScalarFunction::Currentuser => BuiltinScalarFunction::CurrentUser,
+            ScalarFunction::Version => BuiltinScalarFunction::Version,
         }
     }
 }
@@ -597,6 +754,9 @@ impl TryFrom<&protobuf::PhysicalExprNode> for Arc<dyn PhysicalExpr> {
                 convert_box_required!(e.expr)?,
                 convert_required!(e.arrow_type)?,
             )),
+            ExprType::FusedPredicate(e) => Arc::new(FusedPredicateExpr::try_new(
+                convert_box_required!(e.predicate)?,
+            )?),
             ExprType::ScalarFunction(e) => {
                 let scalar_function = protobuf::ScalarFunction::from_i32(e.fun)
                     .ok_or_else(|| {
@@ -606,6 +766,15 @@ impl TryFrom<&protobuf::PhysicalExprNode> for Arc<dyn PhysicalExpr> {
                         ))
                     })?;
 
+                let fun: BuiltinScalarFunction = (&scalar_function).into();
+                if fun.is_query_constant() {
+                    // every executor would evaluate it with its own value
+                    return Err(proto_error(format!(
+                        "Query constant function {} must be planned as a literal",
+                        fun
+                    )));
+                }
+
                 let args = e
                     .args
                     .iter()
@@ -619,14 +788,15 @@ impl TryFrom<&protobuf::PhysicalExprNode> for Arc<dyn PhysicalExpr> {
                     scalar_functions: Default::default(),
                     var_provider: Default::default(),
                     aggregate_functions: Default::default(),
+                    window_functions: Default::default(),
+                    sql_macros: Default::default(),
                     config: ExecutionConfig::new(),
                     execution_props: ExecutionProps::new(),
+                    runtime_env: Default::default(),
+                    optimizer_rule_times: vec![],
                 };
 
-                let fun_expr = functions::create_physical_fun(
-                    &(&scalar_function).into(),
-                    &ctx_state,
-                )?;
+                let fun_expr = functions::create_physical_fun(&fun, &ctx_state)?;
 
                 Arc::new(ScalarFunctionExpr::new(
                     &e.name,
@@ -635,6 +805,20 @@ impl TryFrom<&protobuf::PhysicalExprNode> for Arc<dyn PhysicalExpr> {
                     &convert_required!(e.return_type)?,
                 ))
             }
+            ExprType::WasmUdf(e) => {
+                let udf: Arc<WasmScalarUdf> = convert_required!(e.udf)?;
+                let args = e
+                    .args
+                    .iter()
+                    .map(|x| x.try_into())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Arc::new(ScalarFunctionExpr::new(
+                    udf.name(),
+                    udf.to_scalar_udf().fun,
+                    args,
+                    udf.return_type(),
+                ))
+            }
         };
 
         Ok(pexpr)
@@ -673,6 +857,48 @@ impl TryFrom<&protobuf::physical_window_expr_node::WindowFunction> for WindowFun
     }
 }
 
+/// Parse the sort expressions of a sort operator in `node`
+fn parse_sort_exprs(
+    node: &protobuf::PhysicalPlanNode,
+    exprs: &[protobuf::PhysicalExprNode],
+) -> Result<Vec<PhysicalSortExpr>, BallistaError> {
+    exprs
+        .iter()
+        .map(|expr| {
+            let expr = expr.expr_type.as_ref().ok_or_else(|| {
+                proto_error(format!(
+                    "physical_plan::from_proto() Unexpected expr {:?}",
+                    node
+                ))
+            })?;
+            if let protobuf::physical_expr_node::ExprType::Sort(sort_expr) = expr {
+                let expr = sort_expr
+                    .expr
+                    .as_ref()
+                    .ok_or_else(|| {
+                        proto_error(format!(
+                            "physical_plan::from_proto() Unexpected sort expr {:?}",
+                            node
+                        ))
+                    })?
+                    .as_ref();
+                Ok(PhysicalSortExpr {
+                    expr: expr.try_into()?,
+                    options: SortOptions {
+                        descending: !sort_expr.asc,
+                        nulls_first: sort_expr.nulls_first,
+                    },
+                })
+            } else {
+                Err(BallistaError::General(format!(
+                    "physical_plan::from_proto() {:?}",
+                    node
+                )))
+            }
+        })
+        .collect()
+}
+
 pub fn parse_protobuf_hash_partitioning(
     partitioning: Option<&protobuf::PhysicalHashRepartition>,
 ) -> Result<Option<Partitioning>, BallistaError> {
@@ -692,3 +918,14 @@ pub fn parse_protobuf_hash_partitioning(
         None => Ok(None),
     }
 }
+
+fn parse_hash_scheme(hash_scheme: i32) -> Result<HashScheme, BallistaError> {
+    protobuf::HashScheme::from_i32(hash_scheme)
+        .map(HashScheme::from)
+        .ok_or_else(|| {
+            proto_error(format!(
+                "Received a PhysicalHashRepartition message with unknown HashScheme {}",
+                hash_scheme
+            ))
+        })
+}
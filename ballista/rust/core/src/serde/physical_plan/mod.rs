@@ -15,37 +15,52 @@
 // specific language governing permissions and limitations
 // under the License.
 
+pub mod extension;
 pub mod from_proto;
 pub mod to_proto;
+pub mod unsupported;
 
 #[cfg(test)]
 mod roundtrip_tests {
-    use std::{convert::TryInto, sync::Arc};
+    use std::{any::Any, convert::TryInto, sync::Arc};
+
+    use async_trait::async_trait;
 
     use datafusion::{
         arrow::{
             compute::kernels::sort::SortOptions,
-            datatypes::{DataType, Field, Schema},
+            datatypes::{DataType, Field, Schema, SchemaRef},
+        },
+        logical_plan::window_frames::{
+            WindowFrame, WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
         },
         logical_plan::{JoinType, Operator},
         physical_plan::{
+            aggregates::{create_aggregate_expr_with_ordering, AggregateFunction},
             empty::EmptyExec,
-            expressions::{binary, col, lit, InListExpr, NotExpr},
-            expressions::{Avg, Column, PhysicalSortExpr},
+            expressions::{binary, col, fuse_predicate, lit, InListExpr, NotExpr},
+            expressions::{Avg, Column, FilteredAggregate, PhysicalSortExpr},
             filter::FilterExec,
             hash_aggregate::{AggregateMode, HashAggregateExec},
             hash_join::{HashJoinExec, PartitionMode},
             limit::{GlobalLimitExec, LocalLimitExec},
+            parquet::ParquetExec,
+            repartition::RepartitionExec,
             sort::SortExec,
-            AggregateExpr, ColumnarValue, Distribution, ExecutionPlan, Partitioning,
-            PhysicalExpr,
+            sort_preserving_merge::SortPreservingMergeExec,
+            sorted_aggregate::SortedAggregateExec,
+            window_functions::{BuiltInWindowFunction, WindowFunction},
+            windows::{create_window_expr, WindowAggExec},
+            AggregateExpr, ColumnarValue, Distribution, ExecutionPlan, HashScheme,
+            Partitioning, PhysicalExpr, SendableRecordBatchStream,
         },
         scalar::ScalarValue,
     };
 
-    use super::super::super::error::Result;
+    use super::super::super::error::{BallistaError, Result};
     use super::super::protobuf;
-    use crate::execution_plans::ShuffleWriterExec;
+    use super::extension::{configure_extension_codec, PhysicalExtensionCodec};
+    use crate::execution_plans::{BroadcastExec, ShuffleWriterExec};
 
     fn roundtrip_test(exec_plan: Arc<dyn ExecutionPlan>) -> Result<()> {
         let proto: protobuf::PhysicalPlanNode = exec_plan.clone().try_into()?;
@@ -75,7 +90,14 @@ mod roundtrip_tests {
         roundtrip_test(Arc::new(GlobalLimitExec::new(
             Arc::new(EmptyExec::new(false, Arc::new(Schema::empty()))),
             25,
-        )))
+        )))?;
+        roundtrip_test(Arc::new(
+            GlobalLimitExec::new(
+                Arc::new(EmptyExec::new(false, Arc::new(Schema::empty()))),
+                25,
+            )
+            .with_offset(10),
+        ))
     }
 
     #[test]
@@ -97,17 +119,21 @@ mod roundtrip_tests {
             JoinType::Full,
             JoinType::Anti,
             JoinType::Semi,
+            JoinType::NullAwareAnti,
         ] {
             for partition_mode in
                 &[PartitionMode::Partitioned, PartitionMode::CollectLeft]
             {
-                roundtrip_test(Arc::new(HashJoinExec::try_new(
-                    Arc::new(EmptyExec::new(false, schema_left.clone())),
-                    Arc::new(EmptyExec::new(false, schema_right.clone())),
-                    on.clone(),
-                    join_type,
-                    *partition_mode,
-                )?))?;
+                for null_equals_null in &[false, true] {
+                    roundtrip_test(Arc::new(HashJoinExec::try_new(
+                        Arc::new(EmptyExec::new(false, schema_left.clone())),
+                        Arc::new(EmptyExec::new(false, schema_right.clone())),
+                        on.clone(),
+                        join_type,
+                        *partition_mode,
+                        *null_equals_null,
+                    )?))?;
+                }
             }
         }
         Ok(())
@@ -137,6 +163,155 @@ mod roundtrip_tests {
         )?))
     }
 
+    #[test]
+    fn roundtrip_sorted_aggregate() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, false);
+        let field_b = Field::new("b", DataType::Int64, false);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        let groups: Vec<(Arc<dyn PhysicalExpr>, String)> =
+            vec![(col("a", &schema)?, "a".to_string())];
+
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(Avg::new(
+            col("b", &schema)?,
+            "AVG(b)".to_string(),
+            DataType::Float64,
+        ))];
+
+        roundtrip_test(Arc::new(SortedAggregateExec::try_new(
+            AggregateMode::Partial,
+            groups,
+            aggregates,
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            schema,
+        )?))
+    }
+
+    #[test]
+    fn roundtrip_window() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, false);
+        let field_b = Field::new("b", DataType::Int64, false);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        let partition_by = vec![col("a", &schema)?];
+        let order_by = vec![PhysicalSortExpr {
+            expr: col("b", &schema)?,
+            options: SortOptions {
+                descending: true,
+                nulls_first: false,
+            },
+        }];
+        let frame = WindowFrame {
+            units: WindowFrameUnits::Rows,
+            start_bound: WindowFrameBound::Preceding(Some(2)),
+            end_bound: WindowFrameBound::Following(None),
+            exclude: WindowFrameExclusion::NoOthers,
+        };
+        let window_expr = vec![
+            create_window_expr(
+                &WindowFunction::BuiltInWindowFunction(BuiltInWindowFunction::NthValue),
+                "NTH_VALUE(b,2)".to_owned(),
+                &[col("b", &schema)?, lit(ScalarValue::Int64(Some(2)))],
+                &partition_by,
+                &order_by,
+                Some(frame),
+                false,
+                &schema,
+            )?,
+            create_window_expr(
+                &WindowFunction::BuiltInWindowFunction(BuiltInWindowFunction::CumeDist),
+                "CUME_DIST()".to_owned(),
+                &[],
+                &partition_by,
+                &order_by,
+                None,
+                false,
+                &schema,
+            )?,
+            create_window_expr(
+                &WindowFunction::AggregateFunction(AggregateFunction::Sum),
+                "SUM(DISTINCT b)".to_owned(),
+                &[col("b", &schema)?],
+                &partition_by,
+                &order_by,
+                Some(frame),
+                true,
+                &schema,
+            )?,
+        ];
+
+        roundtrip_test(Arc::new(WindowAggExec::try_new(
+            window_expr,
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            schema,
+        )?))
+    }
+
+    #[test]
+    fn roundtrip_ordered_aggregate() -> Result<()> {
+        let field_a = Field::new("a", DataType::Utf8, false);
+        let field_b = Field::new("b", DataType::Int64, true);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        let ordering = vec![PhysicalSortExpr {
+            expr: col("b", &schema)?,
+            options: SortOptions {
+                descending: true,
+                nulls_first: false,
+            },
+        }];
+        let aggregates = vec![
+            create_aggregate_expr_with_ordering(
+                &AggregateFunction::StringAgg,
+                false,
+                &[
+                    col("a", &schema)?,
+                    lit(ScalarValue::Utf8(Some(",".to_owned()))),
+                ],
+                &ordering,
+                &schema,
+                "STRING_AGG(a)",
+            )?,
+            create_aggregate_expr_with_ordering(
+                &AggregateFunction::FirstValue,
+                false,
+                &[col("a", &schema)?],
+                &ordering,
+                &schema,
+                "FIRST_VALUE(a)",
+            )?,
+        ];
+
+        roundtrip_test(Arc::new(HashAggregateExec::try_new(
+            AggregateMode::Partial,
+            vec![],
+            aggregates,
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            schema,
+        )?))
+    }
+
+    #[test]
+    fn roundtrip_filtered_aggregate() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, false);
+        let field_b = Field::new("b", DataType::Boolean, true);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        let avg: Arc<dyn AggregateExpr> =
+            Arc::new(Avg::new(col("a", &schema)?, "AVG(a)", DataType::Float64));
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(
+            FilteredAggregate::try_new(avg, col("b", &schema)?, &schema)?,
+        )];
+
+        roundtrip_test(Arc::new(HashAggregateExec::try_new(
+            AggregateMode::Partial,
+            vec![],
+            aggregates,
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            schema,
+        )?))
+    }
+
     #[test]
     fn roundtrip_filter_with_not_and_in_list() -> Result<()> {
         let field_a = Field::new("a", DataType::Boolean, false);
@@ -159,6 +334,31 @@ mod roundtrip_tests {
         )?))
     }
 
+    #[test]
+    fn roundtrip_filter_with_fused_predicate() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int32, false);
+        let field_b = Field::new("b", DataType::Int64, false);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+        let a_gt_1 = binary(
+            col("a", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::Int64(Some(1))),
+            &schema,
+        )?;
+        let b_lt_a = binary(
+            col("b", &schema)?,
+            Operator::Lt,
+            col("a", &schema)?,
+            &schema,
+        )?;
+        let predicate =
+            fuse_predicate(binary(a_gt_1, Operator::And, b_lt_a, &schema)?, &schema)?;
+        roundtrip_test(Arc::new(FilterExec::try_new(
+            predicate,
+            Arc::new(EmptyExec::new(false, schema.clone())),
+        )?))
+    }
+
     #[test]
     fn roundtrip_sort() -> Result<()> {
         let field_a = Field::new("a", DataType::Boolean, false);
@@ -181,9 +381,32 @@ mod roundtrip_tests {
             },
         ];
         roundtrip_test(Arc::new(SortExec::try_new(
+            sort_exprs.clone(),
+            Arc::new(EmptyExec::new(false, schema.clone())),
+        )?))?;
+        roundtrip_test(Arc::new(SortExec::new_with_partitioning(
             sort_exprs,
             Arc::new(EmptyExec::new(false, schema)),
-        )?))
+            true,
+        )))
+    }
+
+    #[test]
+    fn roundtrip_sort_preserving_merge() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, true);
+        let schema = Arc::new(Schema::new(vec![field_a]));
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: col("a", &schema)?,
+            options: SortOptions {
+                descending: false,
+                nulls_first: false,
+            },
+        }];
+        roundtrip_test(Arc::new(SortPreservingMergeExec::new(
+            sort_exprs,
+            Arc::new(EmptyExec::new(false, schema)),
+            4096,
+        )))
     }
 
     #[test]
@@ -192,12 +415,210 @@ mod roundtrip_tests {
         let field_b = Field::new("b", DataType::Int64, false);
         let schema = Arc::new(Schema::new(vec![field_a, field_b]));
 
-        roundtrip_test(Arc::new(ShuffleWriterExec::try_new(
-            "job123".to_string(),
-            123,
+        roundtrip_test(Arc::new(
+            ShuffleWriterExec::try_new(
+                "job123".to_string(),
+                123,
+                Arc::new(EmptyExec::new(false, schema)),
+                "".to_string(),
+                Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 4)),
+            )?
+            .with_hash_scheme(HashScheme::Murmur3V1),
+        ))
+    }
+
+    #[test]
+    fn roundtrip_shuffle_writer_results_path() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+
+        roundtrip_test(Arc::new(
+            ShuffleWriterExec::try_new(
+                "job123".to_string(),
+                123,
+                Arc::new(EmptyExec::new(false, schema)),
+                "".to_string(),
+                None,
+            )?
+            .with_results_path(Some("s3://bucket/results".to_string())),
+        ))
+    }
+
+    #[test]
+    fn roundtrip_repartition_by_expression() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, false);
+        let field_b = Field::new("b", DataType::Int64, false);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        let key = binary(
+            col("a", &schema)?,
+            Operator::Modulus,
+            lit(ScalarValue::Int64(Some(8))),
+            &schema,
+        )?;
+        roundtrip_test(Arc::new(RepartitionExec::try_new(
             Arc::new(EmptyExec::new(false, schema)),
-            "".to_string(),
-            Some(Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 4)),
+            Partitioning::Hash(vec![key], 4),
+        )?))
+    }
+
+    #[test]
+    fn roundtrip_broadcast() -> Result<()> {
+        let field_a = Field::new("a", DataType::Int64, false);
+        let field_b = Field::new("b", DataType::Utf8, false);
+        let schema = Arc::new(Schema::new(vec![field_a, field_b]));
+
+        roundtrip_test(Arc::new(BroadcastExec::try_new(
+            "dim",
+            schema,
+            Arc::new(vec![]),
+            Some(vec![1]),
         )?))
     }
+
+    /// A user-defined operator that is unknown to the built-in codec
+    #[derive(Debug)]
+    struct TagExec {
+        tag: String,
+        input: Arc<dyn ExecutionPlan>,
+    }
+
+    #[async_trait]
+    impl ExecutionPlan for TagExec {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn schema(&self) -> SchemaRef {
+            self.input.schema()
+        }
+
+        fn output_partitioning(&self) -> Partitioning {
+            self.input.output_partitioning()
+        }
+
+        fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+            vec![self.input.clone()]
+        }
+
+        fn with_new_children(
+            &self,
+            children: Vec<Arc<dyn ExecutionPlan>>,
+        ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+            Ok(Arc::new(TagExec {
+                tag: self.tag.clone(),
+                input: children[0].clone(),
+            }))
+        }
+
+        async fn execute(
+            &self,
+            partition: usize,
+        ) -> datafusion::error::Result<SendableRecordBatchStream> {
+            self.input.execute(partition).await
+        }
+    }
+
+    #[derive(Debug)]
+    struct TagCodec {}
+
+    impl PhysicalExtensionCodec for TagCodec {
+        fn try_decode(
+            &self,
+            buf: &[u8],
+            inputs: &[Arc<dyn ExecutionPlan>],
+        ) -> Result<Arc<dyn ExecutionPlan>> {
+            Ok(Arc::new(TagExec {
+                tag: String::from_utf8(buf.to_vec()).unwrap(),
+                input: inputs[0].clone(),
+            }))
+        }
+
+        fn try_encode(
+            &self,
+            node: Arc<dyn ExecutionPlan>,
+            buf: &mut Vec<u8>,
+        ) -> Result<()> {
+            match node.as_any().downcast_ref::<TagExec>() {
+                Some(exec) => {
+                    buf.extend_from_slice(exec.tag.as_bytes());
+                    Ok(())
+                }
+                None => Err(BallistaError::NotImplemented(format!(
+                    "TagCodec cannot encode {:?}",
+                    node
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_parquet_row_groups() -> Result<()> {
+        use datafusion::arrow::{array::Int32Array, record_batch::RecordBatch};
+        use datafusion::parquet::{
+            arrow::ArrowWriter, file::properties::WriterProperties,
+        };
+        use std::fs::File;
+
+        let tmp_dir = tempfile::TempDir::new()?;
+        let filename = tmp_dir.path().join("row_groups.parquet");
+        let filename = filename.to_str().unwrap();
+
+        // 8 rows in 4 row groups of 2 rows each, split into 2 partitions
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let write = || -> datafusion::error::Result<()> {
+            let mut writer = ArrowWriter::try_new(
+                File::create(filename)?,
+                schema.clone(),
+                Some(props),
+            )?;
+            for i in 0..4 {
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from(vec![i * 2, i * 2 + 1]))],
+                )?;
+                writer.write(&batch)?;
+            }
+            writer.close()?;
+            Ok(())
+        };
+        write()?;
+
+        let exec_plan: Arc<dyn ExecutionPlan> = Arc::new(ParquetExec::try_from_files(
+            &[filename],
+            None,
+            None,
+            1024,
+            2,
+            None,
+        )?);
+        let proto: protobuf::PhysicalPlanNode = exec_plan.try_into()?;
+        let result_exec_plan: Arc<dyn ExecutionPlan> = (&proto).try_into()?;
+        let scan = result_exec_plan
+            .as_any()
+            .downcast_ref::<ParquetExec>()
+            .unwrap();
+        assert_eq!(scan.output_partitioning().partition_count(), 2);
+        assert_eq!(scan.partitions()[0].filenames(), &[filename.to_owned()]);
+        assert_eq!(scan.partitions()[0].row_groups(), Some(&(0..2)));
+        assert_eq!(scan.partitions()[1].filenames(), &[filename.to_owned()]);
+        assert_eq!(scan.partitions()[1].row_groups(), Some(&(2..4)));
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip_extension() -> Result<()> {
+        configure_extension_codec(Arc::new(TagCodec {}))?;
+
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        roundtrip_test(Arc::new(GlobalLimitExec::new(
+            Arc::new(TagExec {
+                tag: "tagged".to_owned(),
+                input: Arc::new(EmptyExec::new(false, schema)),
+            }),
+            10,
+        )))
+    }
 }
@@ -26,11 +26,13 @@ use std::{
     sync::Arc,
 };
 
+use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::logical_plan::JoinType;
 use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
 use datafusion::physical_plan::csv::CsvExec;
 use datafusion::physical_plan::expressions::{
-    CaseExpr, InListExpr, IsNotNullExpr, IsNullExpr, NegativeExpr, NotExpr,
+    CaseExpr, FilteredAggregate, FusedPredicateExpr, InListExpr, IsNotNullExpr,
+    IsNullExpr, NegativeExpr, NotExpr,
 };
 use datafusion::physical_plan::expressions::{CastExpr, TryCastExpr};
 use datafusion::physical_plan::filter::FilterExec;
@@ -40,27 +42,40 @@ use datafusion::physical_plan::limit::{GlobalLimitExec, LocalLimitExec};
 use datafusion::physical_plan::parquet::ParquetExec;
 use datafusion::physical_plan::projection::ProjectionExec;
 use datafusion::physical_plan::sort::SortExec;
+use datafusion::physical_plan::sort_preserving_merge::SortPreservingMergeExec;
 use datafusion::{
     physical_plan::expressions::{Count, Literal},
     scalar::ScalarValue,
 };
 
+use datafusion::physical_plan::window_functions::WindowFunction;
+use datafusion::physical_plan::windows::{
+    AggregateWindowExpr, BuiltInWindowExpr, WindowAggExec,
+};
 use datafusion::physical_plan::{
     empty::EmptyExec,
-    expressions::{Avg, BinaryExpr, Column, Max, Min, Sum},
+    expressions::{
+        Avg, BinaryExpr, Column, Max, Min, OrderedAggregate, PhysicalSortExpr, Sum,
+    },
     Partitioning,
 };
-use datafusion::physical_plan::{AggregateExpr, ExecutionPlan, PhysicalExpr};
+use datafusion::physical_plan::{AggregateExpr, ExecutionPlan, PhysicalExpr, WindowExpr};
 
 use datafusion::physical_plan::hash_aggregate::HashAggregateExec;
+use datafusion::physical_plan::sorted_aggregate::SortedAggregateExec;
 use protobuf::physical_plan_node::PhysicalPlanType;
+use protobuf::physical_window_expr_node::{
+    WindowFrame as WindowFrameNode, WindowFunction as WindowFunctionNode,
+};
 
 use crate::execution_plans::{
-    ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec,
+    BroadcastExec, ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec,
 };
+use crate::serde::physical_plan::extension::extension_codec;
 use crate::serde::protobuf::repartition_exec_node::PartitionMethod;
 use crate::serde::scheduler::PartitionLocation;
 use crate::serde::{protobuf, BallistaError};
+use crate::wasm::find_wasm_udf;
 use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
 use datafusion::physical_plan::functions::{BuiltinScalarFunction, ScalarFunctionExpr};
 use datafusion::physical_plan::repartition::RepartitionExec;
@@ -106,6 +121,7 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                     protobuf::GlobalLimitExecNode {
                         input: Some(Box::new(input)),
                         limit: limit.limit() as u32,
+                        offset: limit.offset() as u32,
                     },
                 ))),
             })
@@ -152,56 +168,50 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                         on,
                         join_type: join_type.into(),
                         partition_mode: partition_mode.into(),
+                        null_equals_null: exec.null_equals_null(),
                     },
                 ))),
             })
-        } else if let Some(exec) = plan.downcast_ref::<HashAggregateExec>() {
-            let groups = exec
-                .group_expr()
-                .iter()
-                .map(|expr| expr.0.to_owned().try_into())
-                .collect::<Result<Vec<_>, BallistaError>>()?;
-            let group_names = exec
-                .group_expr()
-                .iter()
-                .map(|expr| expr.1.to_owned())
-                .collect();
-            let agg = exec
-                .aggr_expr()
+        } else if let Some(exec) = plan.downcast_ref::<WindowAggExec>() {
+            let input: protobuf::PhysicalPlanNode = exec.input().to_owned().try_into()?;
+            let window_expr = exec
+                .window_expr()
                 .iter()
                 .map(|expr| expr.to_owned().try_into())
                 .collect::<Result<Vec<_>, BallistaError>>()?;
-            let agg_names = exec
-                .aggr_expr()
+            let window_expr_name = exec
+                .window_expr()
                 .iter()
-                .map(|expr| match expr.field() {
-                    Ok(field) => Ok(field.name().clone()),
-                    Err(e) => Err(BallistaError::DataFusionError(e)),
-                })
-                .collect::<Result<_, Self::Error>>()?;
-
-            let agg_mode = match exec.mode() {
-                AggregateMode::Partial => protobuf::AggregateMode::Partial,
-                AggregateMode::Final => protobuf::AggregateMode::Final,
-                AggregateMode::FinalPartitioned => {
-                    protobuf::AggregateMode::FinalPartitioned
-                }
-            };
-            let input_schema = exec.input_schema();
-            let input: protobuf::PhysicalPlanNode = exec.input().to_owned().try_into()?;
+                .map(|expr| expr.name().to_owned())
+                .collect();
             Ok(protobuf::PhysicalPlanNode {
-                physical_plan_type: Some(PhysicalPlanType::HashAggregate(Box::new(
-                    protobuf::HashAggregateExecNode {
-                        group_expr: groups,
-                        group_expr_name: group_names,
-                        aggr_expr: agg,
-                        aggr_expr_name: agg_names,
-                        mode: agg_mode as i32,
+                physical_plan_type: Some(PhysicalPlanType::Window(Box::new(
+                    protobuf::WindowAggExecNode {
                         input: Some(Box::new(input)),
-                        input_schema: Some(input_schema.as_ref().into()),
+                        window_expr,
+                        window_expr_name,
+                        input_schema: Some(exec.input_schema().as_ref().into()),
                     },
                 ))),
             })
+        } else if let Some(exec) = plan.downcast_ref::<HashAggregateExec>() {
+            aggregate_node(
+                exec.mode(),
+                exec.group_expr(),
+                exec.aggr_expr(),
+                exec.input(),
+                exec.input_schema(),
+                false,
+            )
+        } else if let Some(exec) = plan.downcast_ref::<SortedAggregateExec>() {
+            aggregate_node(
+                exec.mode(),
+                exec.group_expr(),
+                exec.aggr_expr(),
+                exec.input(),
+                exec.input_schema(),
+                true,
+            )
         } else if let Some(empty) = plan.downcast_ref::<EmptyExec>() {
             let schema = empty.schema().as_ref().into();
             Ok(protobuf::PhysicalPlanNode {
@@ -252,6 +262,14 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                         has_header: exec.has_header(),
                         delimiter: delimiter.to_string(),
                         batch_size: exec.batch_size() as u32,
+                        file_compression_type: protobuf::FileCompressionType::from(
+                            exec.file_compression_type(),
+                        ) as i32,
+                        optional_target_split_size: exec.target_split_size().map(|size| {
+                            protobuf::csv_scan_exec_node::OptionalTargetSplitSize::TargetSplitSize(
+                                size as u64,
+                            )
+                        }),
                     },
                 )),
             })
@@ -261,6 +279,19 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                 .iter()
                 .flat_map(|part| part.filenames().to_owned())
                 .collect();
+            // the row groups of the partitions that split a file are read by one
+            // partition each
+            let partitions = exec
+                .partitions()
+                .iter()
+                .map(|part| protobuf::ParquetPartitionNode {
+                    filename: part.filenames().to_owned(),
+                    row_groups: part.row_groups().map(|range| protobuf::RowGroupRange {
+                        start: range.start as u64,
+                        end: range.end as u64,
+                    }),
+                })
+                .collect();
             Ok(protobuf::PhysicalPlanNode {
                 physical_plan_type: Some(PhysicalPlanType::ParquetScan(
                     protobuf::ParquetScanExecNode {
@@ -273,6 +304,8 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                             .collect(),
                         num_partitions: exec.partitions().len() as u32,
                         batch_size: exec.batch_size() as u32,
+                        schema: Some(exec.table_schema().as_ref().into()),
+                        partitions,
                     },
                 )),
             })
@@ -306,6 +339,7 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
         } else if let Some(exec) = plan.downcast_ref::<RepartitionExec>() {
             let input: protobuf::PhysicalPlanNode = exec.input().to_owned().try_into()?;
 
+            let hash_scheme = protobuf::HashScheme::from(exec.hash_scheme());
             let pb_partition_method = match exec.partitioning() {
                 Partitioning::Hash(exprs, partition_count) => {
                     PartitionMethod::Hash(protobuf::PhysicalHashRepartition {
@@ -314,6 +348,7 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                             .map(|expr| expr.clone().try_into())
                             .collect::<Result<Vec<_>, BallistaError>>()?,
                         partition_count: *partition_count as u64,
+                        hash_scheme: hash_scheme.into(),
                     })
                 }
                 Partitioning::RoundRobinBatch(partition_count) => {
@@ -334,35 +369,34 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
             })
         } else if let Some(exec) = plan.downcast_ref::<SortExec>() {
             let input: protobuf::PhysicalPlanNode = exec.input().to_owned().try_into()?;
-            let expr = exec
-                .expr()
-                .iter()
-                .map(|expr| {
-                    let sort_expr = Box::new(protobuf::PhysicalSortExprNode {
-                        expr: Some(Box::new(expr.expr.to_owned().try_into()?)),
-                        asc: !expr.options.descending,
-                        nulls_first: expr.options.nulls_first,
-                    });
-                    Ok(protobuf::PhysicalExprNode {
-                        expr_type: Some(protobuf::physical_expr_node::ExprType::Sort(
-                            sort_expr,
-                        )),
-                    })
-                })
-                .collect::<Result<Vec<_>, Self::Error>>()?;
+            let expr = sort_exprs_to_proto(exec.expr())?;
             Ok(protobuf::PhysicalPlanNode {
                 physical_plan_type: Some(PhysicalPlanType::Sort(Box::new(
                     protobuf::SortExecNode {
                         input: Some(Box::new(input)),
                         expr,
+                        preserve_partitioning: exec.preserve_partitioning(),
                     },
                 ))),
             })
+        } else if let Some(exec) = plan.downcast_ref::<SortPreservingMergeExec>() {
+            let input: protobuf::PhysicalPlanNode = exec.input().to_owned().try_into()?;
+            let expr = sort_exprs_to_proto(exec.expr())?;
+            Ok(protobuf::PhysicalPlanNode {
+                physical_plan_type: Some(PhysicalPlanType::SortPreservingMerge(
+                    Box::new(protobuf::SortPreservingMergeExecNode {
+                        input: Some(Box::new(input)),
+                        expr,
+                        target_batch_size: exec.target_batch_size() as u32,
+                    }),
+                )),
+            })
         } else if let Some(exec) = plan.downcast_ref::<ShuffleWriterExec>() {
             let input: protobuf::PhysicalPlanNode =
                 exec.children()[0].to_owned().try_into()?;
             // note that we use shuffle_output_partitioning() rather than output_partitioning()
             // to get the true output partitioning
+            let hash_scheme = protobuf::HashScheme::from(exec.hash_scheme());
             let output_partitioning = match exec.shuffle_output_partitioning() {
                 Some(Partitioning::Hash(exprs, partition_count)) => {
                     Some(protobuf::PhysicalHashRepartition {
@@ -371,6 +405,7 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                             .map(|expr| expr.clone().try_into())
                             .collect::<Result<Vec<_>, BallistaError>>()?,
                         partition_count: *partition_count as u64,
+                        hash_scheme: hash_scheme.into(),
                     })
                 }
                 None => None,
@@ -388,6 +423,10 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                         stage_id: exec.stage_id() as u32,
                         input: Some(Box::new(input)),
                         output_partitioning,
+                        results_path: exec
+                            .results_path()
+                            .map(str::to_owned)
+                            .unwrap_or_default(),
                     },
                 ))),
             })
@@ -399,9 +438,43 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
                         schema: Some(exec.schema().as_ref().into()),
                         input_partition_count: exec.input_partition_count as u32,
                         output_partition_count: exec.output_partition_count as u32,
+                        optional_limit: exec.limit.map(|limit| {
+                            protobuf::unresolved_shuffle_exec_node::OptionalLimit::Limit(
+                                limit as u64,
+                            )
+                        }),
+                    },
+                )),
+            })
+        } else if let Some(exec) = plan.downcast_ref::<BroadcastExec>() {
+            // the batches are sent to the executors separately, see Action::PutBroadcast
+            Ok(protobuf::PhysicalPlanNode {
+                physical_plan_type: Some(PhysicalPlanType::Broadcast(
+                    protobuf::BroadcastExecNode {
+                        name: exec.name().to_owned(),
+                        schema: Some(exec.broadcast_schema().as_ref().into()),
+                        projection: exec.projection().iter().map(|i| *i as u32).collect(),
                     },
                 )),
             })
+        } else if let Some(codec) = extension_codec() {
+            let mut node = vec![];
+            codec.try_encode(self.clone(), &mut node).map_err(|e| {
+                BallistaError::General(format!(
+                    "physical plan to_proto unsupported plan {:?}: {}",
+                    self, e
+                ))
+            })?;
+            let inputs = self
+                .children()
+                .into_iter()
+                .map(|input| input.try_into())
+                .collect::<Result<Vec<_>, BallistaError>>()?;
+            Ok(protobuf::PhysicalPlanNode {
+                physical_plan_type: Some(PhysicalPlanType::Extension(
+                    protobuf::PhysicalExtensionNode { node, inputs },
+                )),
+            })
         } else {
             Err(BallistaError::General(format!(
                 "physical plan to_proto unsupported plan {:?}",
@@ -411,10 +484,90 @@ impl TryInto<protobuf::PhysicalPlanNode> for Arc<dyn ExecutionPlan> {
     }
 }
 
+/// Convert the sort expressions of a sort operator to protobuf
+fn sort_exprs_to_proto(
+    exprs: &[PhysicalSortExpr],
+) -> Result<Vec<protobuf::PhysicalExprNode>, BallistaError> {
+    exprs
+        .iter()
+        .map(|expr| {
+            let sort_expr = Box::new(protobuf::PhysicalSortExprNode {
+                expr: Some(Box::new(expr.expr.to_owned().try_into()?)),
+                asc: !expr.options.descending,
+                nulls_first: expr.options.nulls_first,
+            });
+            Ok(protobuf::PhysicalExprNode {
+                expr_type: Some(protobuf::physical_expr_node::ExprType::Sort(sort_expr)),
+            })
+        })
+        .collect()
+}
+
+/// Convert a hash or sorted aggregate to protobuf, `sorted` telling them apart
+fn aggregate_node(
+    mode: &AggregateMode,
+    group_expr: &[(Arc<dyn PhysicalExpr>, String)],
+    aggr_expr: &[Arc<dyn AggregateExpr>],
+    input: &Arc<dyn ExecutionPlan>,
+    input_schema: SchemaRef,
+    sorted: bool,
+) -> Result<protobuf::PhysicalPlanNode, BallistaError> {
+    let groups = group_expr
+        .iter()
+        .map(|expr| expr.0.to_owned().try_into())
+        .collect::<Result<Vec<_>, BallistaError>>()?;
+    let group_names = group_expr.iter().map(|expr| expr.1.to_owned()).collect();
+    let agg = aggr_expr
+        .iter()
+        .map(|expr| expr.to_owned().try_into())
+        .collect::<Result<Vec<_>, BallistaError>>()?;
+    let agg_names = aggr_expr
+        .iter()
+        .map(|expr| match expr.field() {
+            Ok(field) => Ok(field.name().clone()),
+            Err(e) => Err(BallistaError::DataFusionError(e)),
+        })
+        .collect::<Result<_, BallistaError>>()?;
+
+    let agg_mode = match mode {
+        AggregateMode::Partial => protobuf::AggregateMode::Partial,
+        AggregateMode::Final => protobuf::AggregateMode::Final,
+        AggregateMode::FinalPartitioned => protobuf::AggregateMode::FinalPartitioned,
+        AggregateMode::SinglePartitioned => protobuf::AggregateMode::SinglePartitioned,
+    };
+    let input: protobuf::PhysicalPlanNode = input.to_owned().try_into()?;
+    Ok(protobuf::PhysicalPlanNode {
+        physical_plan_type: Some(PhysicalPlanType::HashAggregate(Box::new(
+            protobuf::HashAggregateExecNode {
+                group_expr: groups,
+                group_expr_name: group_names,
+                aggr_expr: agg,
+                aggr_expr_name: agg_names,
+                mode: agg_mode as i32,
+                input: Some(Box::new(input)),
+                input_schema: Some(input_schema.as_ref().into()),
+                sorted,
+            },
+        ))),
+    })
+}
+
 impl TryInto<protobuf::PhysicalExprNode> for Arc<dyn AggregateExpr> {
     type Error = BallistaError;
 
     fn try_into(self) -> Result<protobuf::PhysicalExprNode, Self::Error> {
+        // the filter of a filtered aggregate is serialized with the aggregate it wraps
+        if let Some(filtered) = self.as_any().downcast_ref::<FilteredAggregate>() {
+            let mut node: protobuf::PhysicalExprNode =
+                filtered.aggregate().clone().try_into()?;
+            if let Some(protobuf::physical_expr_node::ExprType::AggregateExpr(
+                aggregate,
+            )) = &mut node.expr_type
+            {
+                aggregate.filter = Some(Box::new(filtered.filter().clone().try_into()?));
+            }
+            return Ok(node);
+        }
         let aggr_function = if self.as_any().downcast_ref::<Avg>().is_some() {
             Ok(protobuf::AggregateFunction::Avg.into())
         } else if self.as_any().downcast_ref::<Sum>().is_some() {
@@ -425,6 +578,8 @@ impl TryInto<protobuf::PhysicalExprNode> for Arc<dyn AggregateExpr> {
             Ok(protobuf::AggregateFunction::Min.into())
         } else if self.as_any().downcast_ref::<Max>().is_some() {
             Ok(protobuf::AggregateFunction::Max.into())
+        } else if let Some(ordered) = self.as_any().downcast_ref::<OrderedAggregate>() {
+            Ok(protobuf::AggregateFunction::from(ordered.fun()).into())
         } else {
             Err(BallistaError::NotImplemented(format!(
                 "Aggregate function not supported: {:?}",
@@ -436,17 +591,97 @@ impl TryInto<protobuf::PhysicalExprNode> for Arc<dyn AggregateExpr> {
             .iter()
             .map(|e| e.clone().try_into())
             .collect::<Result<Vec<_>, BallistaError>>()?;
+        // the expressions of ordered aggregates are their value and sort keys, whose
+        // options and delimiter are serialized on their own
+        let (other_args, ordering) =
+            match self.as_any().downcast_ref::<OrderedAggregate>() {
+                Some(ordered) => {
+                    let other_args = ordered
+                    .delimiter()
+                    .map(|delimiter| {
+                        let delimiter = ScalarValue::Utf8(Some(delimiter.to_owned()));
+                        let literal: Arc<dyn PhysicalExpr> =
+                            Arc::new(Literal::new(delimiter));
+                        literal.try_into()
+                    })
+                    .into_iter()
+                    .collect::<Result<Vec<protobuf::PhysicalExprNode>, BallistaError>>(
+                    )?;
+                    (other_args, sort_exprs_to_proto(ordered.ordering())?)
+                }
+                None => (vec![], vec![]),
+            };
         Ok(protobuf::PhysicalExprNode {
             expr_type: Some(protobuf::physical_expr_node::ExprType::AggregateExpr(
                 Box::new(protobuf::PhysicalAggregateExprNode {
                     aggr_function,
                     expr: Some(Box::new(expressions[0].clone())),
+                    other_args,
+                    ordering,
+                    filter: None,
                 }),
             )),
         })
     }
 }
 
+impl TryInto<protobuf::PhysicalExprNode> for Arc<dyn WindowExpr> {
+    type Error = BallistaError;
+
+    fn try_into(self) -> Result<protobuf::PhysicalExprNode, Self::Error> {
+        let expr = self.as_any();
+        let (window_function, args, window_frame, distinct) =
+            if let Some(expr) = expr.downcast_ref::<AggregateWindowExpr>() {
+                let fun = protobuf::AggregateFunction::from(expr.fun());
+                let window_function = WindowFunctionNode::AggrFunction(fun.into());
+                let distinct = expr.distinct();
+                (window_function, expr.args(), expr.window_frame(), distinct)
+            } else if let Some(expr) = expr.downcast_ref::<BuiltInWindowExpr>() {
+                let fun = match expr.fun() {
+                    WindowFunction::BuiltInWindowFunction(fun) => {
+                        protobuf::BuiltInWindowFunction::from(fun)
+                    }
+                    fun => {
+                        return Err(BallistaError::NotImplemented(format!(
+                            "Window function {} cannot be serialized",
+                            fun
+                        )))
+                    }
+                };
+                let window_function = WindowFunctionNode::BuiltInFunction(fun.into());
+                (window_function, expr.args(), expr.window_frame(), false)
+            } else {
+                return Err(BallistaError::NotImplemented(format!(
+                    "Window expression not supported: {:?}",
+                    self
+                )));
+            };
+        let args = args
+            .iter()
+            .map(|e| e.clone().try_into())
+            .collect::<Result<Vec<_>, BallistaError>>()?;
+        let partition_by = self
+            .partition_by()
+            .iter()
+            .map(|e| e.clone().try_into())
+            .collect::<Result<Vec<_>, BallistaError>>()?;
+        let window_frame = window_frame
+            .map(|window_frame| WindowFrameNode::Frame((*window_frame).into()));
+        Ok(protobuf::PhysicalExprNode {
+            expr_type: Some(protobuf::physical_expr_node::ExprType::WindowExpr(
+                protobuf::PhysicalWindowExprNode {
+                    window_function: Some(window_function),
+                    args,
+                    partition_by,
+                    order_by: sort_exprs_to_proto(self.order_by())?,
+                    window_frame,
+                    distinct,
+                },
+            )),
+        })
+    }
+}
+
 impl TryFrom<Arc<dyn PhysicalExpr>> for protobuf::PhysicalExprNode {
     type Error = BallistaError;
 
@@ -581,15 +816,40 @@ impl TryFrom<Arc<dyn PhysicalExpr>> for protobuf::PhysicalExprNode {
                     }),
                 )),
             })
+        } else if let Some(fused) = expr.downcast_ref::<FusedPredicateExpr>() {
+            Ok(protobuf::PhysicalExprNode {
+                expr_type: Some(protobuf::physical_expr_node::ExprType::FusedPredicate(
+                    Box::new(protobuf::PhysicalFusedPredicateNode {
+                        predicate: Some(Box::new(fused.predicate().clone().try_into()?)),
+                    }),
+                )),
+            })
         } else if let Some(expr) = expr.downcast_ref::<ScalarFunctionExpr>() {
-            let fun: BuiltinScalarFunction =
-                BuiltinScalarFunction::from_str(expr.name())?;
-            let fun: protobuf::ScalarFunction = (&fun).try_into()?;
             let args: Vec<protobuf::PhysicalExprNode> = expr
                 .args()
                 .iter()
                 .map(|e| e.to_owned().try_into())
                 .collect::<Result<Vec<_>, _>>()?;
+            let fun = match BuiltinScalarFunction::from_str(expr.name()) {
+                Ok(fun) => fun,
+                Err(e) => {
+                    // UDFs are planned as scalar functions with the name of the UDF
+                    return match find_wasm_udf(expr.fun()) {
+                        Some(udf) => Ok(protobuf::PhysicalExprNode {
+                            expr_type: Some(
+                                protobuf::physical_expr_node::ExprType::WasmUdf(
+                                    protobuf::PhysicalWasmScalarUdfExprNode {
+                                        udf: Some(udf.as_ref().into()),
+                                        args,
+                                    },
+                                ),
+                            ),
+                        }),
+                        None => Err(e.into()),
+                    };
+                }
+            };
+            let fun: protobuf::ScalarFunction = (&fun).try_into()?;
             Ok(protobuf::PhysicalExprNode {
                 expr_type: Some(protobuf::physical_expr_node::ExprType::ScalarFunction(
                     protobuf::PhysicalScalarFunctionNode {
@@ -0,0 +1,117 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Pre-flight check that finds the operators and expressions of a physical plan that
+//! cannot be serialized, so that they can be reported before the plan is distributed.
+
+use std::{convert::TryInto, sync::Arc};
+
+use datafusion::physical_plan::{empty::EmptyExec, ExecutionPlan};
+
+use crate::serde::protobuf;
+
+/// Walk `plan` and return every node that cannot be converted to protobuf, together
+/// with the reason. Every node is checked on its own with its children replaced by
+/// placeholders, so an unsupported node does not make its ancestors unsupported.
+pub fn find_unsupported_nodes(
+    plan: &Arc<dyn ExecutionPlan>,
+) -> Vec<protobuf::UnsupportedPlanNode> {
+    let mut unsupported = vec![];
+    find_unsupported_nodes_recurse(plan, &mut unsupported);
+    unsupported
+}
+
+fn find_unsupported_nodes_recurse(
+    plan: &Arc<dyn ExecutionPlan>,
+    unsupported: &mut Vec<protobuf::UnsupportedPlanNode>,
+) {
+    let children = plan.children();
+    for child in &children {
+        find_unsupported_nodes_recurse(child, unsupported);
+    }
+
+    let placeholders = children
+        .iter()
+        .map(|child| {
+            Arc::new(EmptyExec::new(false, child.schema())) as Arc<dyn ExecutionPlan>
+        })
+        .collect::<Vec<_>>();
+    let node = if placeholders.is_empty() {
+        Ok(plan.clone())
+    } else {
+        plan.with_new_children(placeholders)
+    };
+    let result: Result<protobuf::PhysicalPlanNode, String> = match node {
+        Ok(node) => node.try_into().map_err(|e| format!("{}", e)),
+        Err(e) => Err(format!("{}", e)),
+    };
+
+    if let Err(reason) = result {
+        // the derived Debug representation starts with the name of the operator
+        let operator = format!("{:?}", plan)
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        unsupported.push(protobuf::UnsupportedPlanNode { operator, reason });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::{
+        cross_join::CrossJoinExec, limit::GlobalLimitExec, union::UnionExec,
+    };
+
+    fn empty(schema: &Arc<Schema>) -> Arc<dyn ExecutionPlan> {
+        Arc::new(EmptyExec::new(false, schema.clone()))
+    }
+
+    #[test]
+    fn supported_plan() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let plan: Arc<dyn ExecutionPlan> =
+            Arc::new(GlobalLimitExec::new(empty(&schema), 10));
+        assert!(find_unsupported_nodes(&plan).is_empty());
+    }
+
+    #[test]
+    fn unsupported_nodes() -> datafusion::error::Result<()> {
+        let left = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let right = Arc::new(Schema::new(vec![Field::new("b", DataType::Int64, false)]));
+        let cross_join: Arc<dyn ExecutionPlan> =
+            Arc::new(CrossJoinExec::try_new(empty(&left), empty(&right))?);
+        let union = Arc::new(UnionExec::new(vec![cross_join.clone(), cross_join]));
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(GlobalLimitExec::new(union, 10));
+
+        let unsupported = find_unsupported_nodes(&plan);
+        let operators = unsupported
+            .iter()
+            .map(|node| node.operator.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["CrossJoinExec", "CrossJoinExec", "UnionExec"],
+            operators
+        );
+        assert!(unsupported
+            .iter()
+            .all(|node| node.reason.contains("unsupported plan")));
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Versions of the protocol between the scheduler and the executors, which let both run
+//! different releases during a rolling upgrade of a cluster.
+//!
+//! The scheduler only sends a task to an executor whose protocol version can decode
+//! every feature of the plan of the task, so that the tasks that need a newer version
+//! wait for an upgraded executor instead of failing on an older one.
+//!
+//! Versions:
+//! * 1: the plans understood by the executors that predate the version exchange, which
+//!   report version 0
+//! * 2: null aware anti joins, used to plan `NOT IN` subqueries
+//! * 3: task plans that are too large to send inline, which executors stream in chunks
+//! * 4: compressed chunked task plans
+//! * 5: window functions with all their arguments, partitioning, ordering and frame
+//! * 6: shuffle files encrypted with the key of their job
+//! * 7: task timeouts, and progress reports of the running tasks
+//! * 8: hash partitionings with the `murmur3_v1` hash scheme
+//! * 9: filter predicates with fused comparisons
+//! * 10: the results service of the executors, which serves pages of the results of
+//!   a job
+//! * 11: Parquet scans with the row groups of each partition, which older executors
+//!   would read in full from every partition of a split file
+//! * 12: broadcast variables that executors fetch from the scheduler, which no longer
+//!   pushes them to older executors
+//! * 13: CSV scans split into byte ranges that the executors align to the records,
+//!   which older executors would split at other offsets
+//! * 14: pinned jobs, whose shuffle files executors keep because their results are
+//!   registered as tables, which older executors would delete after their time to live
+
+use crate::execution_plans::{BroadcastExec, ShuffleWriterExec};
+use datafusion::logical_plan::JoinType;
+use datafusion::physical_plan::{
+    csv::CsvExec,
+    expressions::{BinaryExpr, FusedPredicateExpr},
+    filter::FilterExec,
+    hash_join::HashJoinExec,
+    parquet::ParquetExec,
+    repartition::RepartitionExec,
+    windows::WindowAggExec,
+    ExecutionPlan, HashScheme, Partitioning,
+};
+
+/// Protocol version of this release
+pub const PROTOCOL_VERSION: u32 = 15;
+
+/// Oldest protocol version of the executors that can stream chunked task plans
+pub const CHUNKED_PLAN_PROTOCOL_VERSION: u32 = 3;
+
+/// Oldest protocol version of the executors that can decompress chunked task plans
+pub const COMPRESSED_PLAN_PROTOCOL_VERSION: u32 = 4;
+
+/// Oldest protocol version of the executors that encrypt the shuffle files of the jobs
+/// that have a key, which older executors would write in plain text
+pub const ENCRYPTED_SHUFFLE_PROTOCOL_VERSION: u32 = 6;
+
+/// Oldest protocol version of the schedulers that accept progress reports of running
+/// tasks, and of the executors that enforce the timeout of their tasks
+pub const TASK_PROGRESS_PROTOCOL_VERSION: u32 = 7;
+
+/// Oldest protocol version of the executors that serve the results of jobs by page,
+/// which clients fetch from older executors by the paths of their shuffle files
+pub const RESULTS_SERVICE_PROTOCOL_VERSION: u32 = 10;
+
+/// Oldest protocol version of the executors that keep the shuffle files of pinned jobs.
+/// Older executors do not get the time to live of the shuffle files while jobs are
+/// pinned.
+pub const PINNED_JOBS_PROTOCOL_VERSION: u32 = 14;
+
+/// Oldest protocol version of the schedulers and executors that delete the shuffle files
+/// of a job by the time the job finished. Older executors delete them by the time they
+/// last modified them.
+pub const JOB_CLEANUP_PROTOCOL_VERSION: u32 = 15;
+
+/// Oldest protocol version of the executors that the scheduler of this release accepts
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Returns the protocol version reported by an executor, where 0 is the version of the
+/// executors that do not report one
+pub fn executor_protocol_version(reported: u32) -> u32 {
+    reported.max(1)
+}
+
+/// Whether the scheduler of this release can serve an executor with `protocol_version`
+pub fn is_supported_protocol_version(protocol_version: u32) -> bool {
+    protocol_version >= MIN_PROTOCOL_VERSION
+}
+
+/// Returns the oldest protocol version that can decode all the features of `plan`
+pub fn required_protocol_version(plan: &dyn ExecutionPlan) -> u32 {
+    let version = match plan.as_any().downcast_ref::<HashJoinExec>() {
+        Some(join) if *join.join_type() == JoinType::NullAwareAnti => 2,
+        // older executors ignore the partitioning, ordering and frames of window
+        // functions, and only decode one of their arguments
+        _ if plan.as_any().is::<WindowAggExec>() => 5,
+        // older executors would partition the rows with the internal hash scheme
+        _ if hash_scheme(plan) == Some(HashScheme::Murmur3V1) => 8,
+        _ if has_fused_predicate(plan) => 9,
+        _ if has_row_group_partitions(plan) => 11,
+        // older executors wait for the scheduler to push the broadcast variables
+        _ if plan.as_any().is::<BroadcastExec>() => 12,
+        _ if has_byte_range_partitions(plan) => 13,
+        _ => 1,
+    };
+    plan.children()
+        .iter()
+        .map(|child| required_protocol_version(child.as_ref()))
+        .fold(version, u32::max)
+}
+
+/// Returns the hash scheme of the partitioning of a repartition or shuffle write
+fn hash_scheme(plan: &dyn ExecutionPlan) -> Option<HashScheme> {
+    let (partitioning, hash_scheme) =
+        if let Some(repart) = plan.as_any().downcast_ref::<RepartitionExec>() {
+            (Some(repart.partitioning()), repart.hash_scheme())
+        } else {
+            let writer = plan.as_any().downcast_ref::<ShuffleWriterExec>()?;
+            (writer.shuffle_output_partitioning(), writer.hash_scheme())
+        };
+    match partitioning {
+        Some(Partitioning::Hash(_, _)) => Some(hash_scheme),
+        _ => None,
+    }
+}
+
+/// Returns whether `plan` is a Parquet scan with partitions that read a range of the row
+/// groups of a file
+fn has_row_group_partitions(plan: &dyn ExecutionPlan) -> bool {
+    match plan.as_any().downcast_ref::<ParquetExec>() {
+        Some(scan) => scan
+            .partitions()
+            .iter()
+            .any(|partition| partition.row_groups().is_some()),
+        None => false,
+    }
+}
+
+/// Returns whether `plan` is a CSV scan that may split its files into byte ranges
+fn has_byte_range_partitions(plan: &dyn ExecutionPlan) -> bool {
+    match plan.as_any().downcast_ref::<CsvExec>() {
+        Some(scan) => scan.target_split_size().is_some(),
+        None => false,
+    }
+}
+
+/// Returns whether `plan` is a filter whose predicate has fused comparisons
+fn has_fused_predicate(plan: &dyn ExecutionPlan) -> bool {
+    match plan.as_any().downcast_ref::<FilterExec>() {
+        Some(filter) => {
+            let mut exprs = vec![filter.predicate().clone()];
+            while let Some(expr) = exprs.pop() {
+                if expr.as_any().is::<FusedPredicateExpr>() {
+                    return true;
+                }
+                if let Some(binary) = expr.as_any().downcast_ref::<BinaryExpr>() {
+                    exprs.push(binary.left().clone());
+                    exprs.push(binary.right().clone());
+                }
+            }
+            false
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::datasource::datasource::Statistics;
+    use datafusion::error::Result;
+    use datafusion::logical_plan::Operator;
+    use datafusion::physical_plan::csv::CsvReadOptions;
+    use datafusion::physical_plan::{
+        empty::EmptyExec,
+        expressions::{lit, Column},
+        hash_join::PartitionMode,
+        hash_utils::JoinOn,
+        parquet::{ParquetExecMetrics, ParquetPartition},
+    };
+    use datafusion::scalar::ScalarValue;
+    use std::sync::Arc;
+
+    fn join(join_type: JoinType) -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+        let left = Arc::new(EmptyExec::new(false, schema.clone()));
+        let right = Arc::new(EmptyExec::new(false, schema));
+        let on: JoinOn = vec![(Column::new("a", 0), Column::new("a", 0))];
+        Ok(Arc::new(HashJoinExec::try_new(
+            left,
+            right,
+            on,
+            &join_type,
+            PartitionMode::CollectLeft,
+            false,
+        )?))
+    }
+
+    #[test]
+    fn required_versions() -> Result<()> {
+        assert_eq!(1, required_protocol_version(join(JoinType::Anti)?.as_ref()));
+        assert_eq!(
+            2,
+            required_protocol_version(join(JoinType::NullAwareAnti)?.as_ref())
+        );
+
+        // the features of the inputs count too
+        let filter = FilterExec::try_new(
+            lit(ScalarValue::Boolean(Some(true))),
+            join(JoinType::NullAwareAnti)?,
+        )?;
+        assert_eq!(2, required_protocol_version(&filter));
+
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+        let window = WindowAggExec::try_new(
+            vec![],
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            schema.clone(),
+        )?;
+        assert_eq!(5, required_protocol_version(&window));
+
+        let partitioning = || Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 2);
+        let empty = Arc::new(EmptyExec::new(false, schema.clone()));
+        let repart = RepartitionExec::try_new(empty.clone(), partitioning())?;
+        assert_eq!(1, required_protocol_version(&repart));
+        let repart = repart.with_hash_scheme(HashScheme::Murmur3V1);
+        assert_eq!(8, required_protocol_version(&repart));
+        let writer = ShuffleWriterExec::try_new(
+            "job".to_owned(),
+            1,
+            empty.clone(),
+            "".to_owned(),
+            Some(partitioning()),
+        )?
+        .with_hash_scheme(HashScheme::Murmur3V1);
+        assert_eq!(8, required_protocol_version(&writer));
+
+        let a_gt_1 = Arc::new(BinaryExpr::new(
+            Arc::new(Column::new("a", 0)),
+            Operator::Gt,
+            lit(ScalarValue::Int64(Some(1))),
+        ));
+        let fused =
+            FilterExec::try_new(Arc::new(FusedPredicateExpr::try_new(a_gt_1)?), empty)?;
+        assert_eq!(9, required_protocol_version(&fused));
+
+        let scan = |row_groups| {
+            let mut partition = ParquetPartition::new(
+                vec!["a.parquet".to_owned()],
+                Statistics::default(),
+            );
+            partition.row_groups = row_groups;
+            ParquetExec::new(
+                vec![partition],
+                schema.clone(),
+                None,
+                ParquetExecMetrics::new(),
+                None,
+                1024,
+                None,
+            )
+        };
+        assert_eq!(1, required_protocol_version(&scan(None)));
+        assert_eq!(11, required_protocol_version(&scan(Some(0..2))));
+
+        let broadcast = BroadcastExec::try_new_unresolved("dim", schema.clone(), None)?;
+        assert_eq!(12, required_protocol_version(&broadcast));
+
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("a.csv");
+        std::fs::write(&path, "a\n1\n2\n")?;
+        let path = path.to_str().unwrap();
+        let csv =
+            |options: CsvReadOptions| CsvExec::try_new(path, options, None, 1024, None);
+        let options = CsvReadOptions::new().schema(&schema);
+        assert_eq!(1, required_protocol_version(&csv(options)?));
+        let options = options.target_split_size(2);
+        assert_eq!(13, required_protocol_version(&csv(options)?));
+        Ok(())
+    }
+
+    #[test]
+    fn executor_versions() {
+        assert_eq!(1, executor_protocol_version(0));
+        assert_eq!(2, executor_protocol_version(2));
+        assert!(CHUNKED_PLAN_PROTOCOL_VERSION <= PROTOCOL_VERSION);
+        assert!(ENCRYPTED_SHUFFLE_PROTOCOL_VERSION <= PROTOCOL_VERSION);
+        assert!(RESULTS_SERVICE_PROTOCOL_VERSION <= PROTOCOL_VERSION);
+        assert!(PINNED_JOBS_PROTOCOL_VERSION <= PROTOCOL_VERSION);
+        assert!(JOB_CLEANUP_PROTOCOL_VERSION <= PROTOCOL_VERSION);
+        assert!(is_supported_protocol_version(executor_protocol_version(0)));
+        assert!(is_supported_protocol_version(PROTOCOL_VERSION));
+    }
+}
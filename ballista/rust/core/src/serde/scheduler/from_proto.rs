@@ -37,6 +37,27 @@ impl TryInto<Action> for protobuf::Action {
                 stage_id: fetch.stage_id as usize,
                 partition_id: fetch.partition_id as usize,
                 path: fetch.path,
+                auth_token: fetch.auth_token,
+            }),
+            Some(ActionType::PutBroadcast(put)) => Ok(Action::PutBroadcast {
+                job_id: put.job_id,
+                name: put.name,
+                auth_token: put.auth_token,
+            }),
+            Some(ActionType::FetchTaskLogs(fetch)) => Ok(Action::FetchTaskLogs {
+                job_id: fetch.job_id,
+                stage_id: fetch.stage_id as usize,
+                partition_id: fetch.partition_id as usize,
+                max_bytes: fetch.max_bytes,
+                auth_token: fetch.auth_token,
+            }),
+            Some(ActionType::FetchResults(fetch)) => Ok(Action::FetchResults {
+                job_id: fetch.job_id,
+                stage_id: fetch.stage_id as usize,
+                partition_id: fetch.partition_id as usize,
+                offset: fetch.offset,
+                max_rows: fetch.max_rows,
+                auth_token: fetch.auth_token,
             }),
             _ => Err(BallistaError::General(
                 "scheduler::from_proto(Action) invalid or missing action".to_owned(),
@@ -106,6 +127,7 @@ impl TryInto<PartitionLocation> for protobuf::PartitionLocation {
                 })?
                 .into(),
             path: self.path,
+            auth_token: self.auth_token,
         })
     }
 }
@@ -15,7 +15,11 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use std::{collections::HashMap, fmt, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
 
 use datafusion::arrow::array::{
     ArrayBuilder, ArrayRef, StructArray, StructBuilder, UInt64Array, UInt64Builder,
@@ -28,6 +32,7 @@ use serde::Serialize;
 use uuid::Uuid;
 
 use super::protobuf;
+use super::protocol::executor_protocol_version;
 use crate::error::BallistaError;
 
 pub mod from_proto;
@@ -42,6 +47,36 @@ pub enum Action {
         stage_id: usize,
         partition_id: usize,
         path: String,
+        /// Token of the job, empty if shuffle authentication is disabled
+        auth_token: String,
+    },
+    /// Push the batches of a broadcast variable, which follow in the same exchange
+    PutBroadcast {
+        job_id: String,
+        name: String,
+        /// Token of the job, empty if shuffle authentication is disabled
+        auth_token: String,
+    },
+    /// Fetch the last `max_bytes` of the captured log records of a task, or all of them
+    /// if 0
+    FetchTaskLogs {
+        job_id: String,
+        stage_id: usize,
+        partition_id: usize,
+        max_bytes: u64,
+        /// Token of the job, empty if shuffle authentication is disabled
+        auth_token: String,
+    },
+    /// Fetch at most `max_rows` rows of a partition of the results of a job, or all of
+    /// them if 0, starting at row `offset`
+    FetchResults {
+        job_id: String,
+        stage_id: usize,
+        partition_id: usize,
+        offset: u64,
+        max_rows: u64,
+        /// Token of the job, empty if shuffle authentication is disabled
+        auth_token: String,
     },
 }
 
@@ -69,6 +104,8 @@ pub struct PartitionLocation {
     pub executor_meta: ExecutorMeta,
     pub partition_stats: PartitionStats,
     pub path: String,
+    /// Token to present when fetching the partition
+    pub auth_token: String,
 }
 
 /// Meta-data for an executor, used when fetching shuffle partitions from other executors
@@ -77,6 +114,14 @@ pub struct ExecutorMeta {
     pub id: String,
     pub host: String,
     pub port: u16,
+    /// Labels of the executor, such as `gpu=true`, that queries can constrain their
+    /// tasks to
+    pub labels: BTreeMap<String, String>,
+    /// Unix domain socket that the executor also serves its Flight endpoint on, which
+    /// clients on the same host connect to instead of the port
+    pub flight_socket: Option<String>,
+    /// Protocol version of the executor, see [crate::serde::protocol]
+    pub protocol_version: u32,
 }
 
 #[allow(clippy::from_over_into)]
@@ -86,6 +131,13 @@ impl Into<protobuf::ExecutorMetadata> for ExecutorMeta {
             id: self.id,
             host: self.host,
             port: self.port as u32,
+            labels: self
+                .labels
+                .into_iter()
+                .map(|(key, value)| protobuf::KeyValuePair { key, value })
+                .collect(),
+            flight_socket: self.flight_socket.unwrap_or_default(),
+            protocol_version: self.protocol_version,
         }
     }
 }
@@ -96,6 +148,13 @@ impl From<protobuf::ExecutorMetadata> for ExecutorMeta {
             id: meta.id,
             host: meta.host,
             port: meta.port as u16,
+            labels: meta
+                .labels
+                .into_iter()
+                .map(|label| (label.key, label.value))
+                .collect(),
+            flight_socket: Some(meta.flight_socket).filter(|path| !path.is_empty()),
+            protocol_version: executor_protocol_version(meta.protocol_version),
         }
     }
 }
@@ -18,12 +18,13 @@
 use std::convert::TryInto;
 
 use crate::error::BallistaError;
+use crate::execution_plans::ShuffleWriterExec;
 use crate::serde::protobuf;
 use crate::serde::protobuf::action::ActionType;
 use crate::serde::scheduler::{
     Action, ExecutePartition, PartitionId, PartitionLocation, PartitionStats,
 };
-use datafusion::physical_plan::Partitioning;
+use datafusion::physical_plan::{HashScheme, Partitioning};
 
 impl TryInto<protobuf::Action> for Action {
     type Error = BallistaError;
@@ -35,12 +36,60 @@ impl TryInto<protobuf::Action> for Action {
                 stage_id,
                 partition_id,
                 path,
+                auth_token,
             } => Ok(protobuf::Action {
                 action_type: Some(ActionType::FetchPartition(protobuf::FetchPartition {
                     job_id,
                     stage_id: stage_id as u32,
                     partition_id: partition_id as u32,
                     path,
+                    auth_token,
+                })),
+                settings: vec![],
+            }),
+            Action::PutBroadcast {
+                job_id,
+                name,
+                auth_token,
+            } => Ok(protobuf::Action {
+                action_type: Some(ActionType::PutBroadcast(protobuf::PutBroadcast {
+                    job_id,
+                    name,
+                    auth_token,
+                })),
+                settings: vec![],
+            }),
+            Action::FetchTaskLogs {
+                job_id,
+                stage_id,
+                partition_id,
+                max_bytes,
+                auth_token,
+            } => Ok(protobuf::Action {
+                action_type: Some(ActionType::FetchTaskLogs(protobuf::FetchTaskLogs {
+                    job_id,
+                    stage_id: stage_id as u32,
+                    partition_id: partition_id as u32,
+                    max_bytes,
+                    auth_token,
+                })),
+                settings: vec![],
+            }),
+            Action::FetchResults {
+                job_id,
+                stage_id,
+                partition_id,
+                offset,
+                max_rows,
+                auth_token,
+            } => Ok(protobuf::Action {
+                action_type: Some(ActionType::FetchResults(protobuf::FetchResults {
+                    job_id,
+                    stage_id: stage_id as u32,
+                    partition_id: partition_id as u32,
+                    offset,
+                    max_rows,
+                    auth_token,
                 })),
                 settings: vec![],
             }),
@@ -52,6 +101,11 @@ impl TryInto<protobuf::ExecutePartition> for ExecutePartition {
     type Error = BallistaError;
 
     fn try_into(self) -> Result<protobuf::ExecutePartition, Self::Error> {
+        let hash_scheme = self
+            .plan
+            .as_any()
+            .downcast_ref::<ShuffleWriterExec>()
+            .map_or(HashScheme::default(), |writer| writer.hash_scheme());
         Ok(protobuf::ExecutePartition {
             job_id: self.job_id,
             stage_id: self.stage_id as u32,
@@ -60,6 +114,7 @@ impl TryInto<protobuf::ExecutePartition> for ExecutePartition {
             partition_location: vec![],
             output_partitioning: hash_partitioning_to_proto(
                 self.output_partitioning.as_ref(),
+                hash_scheme,
             )?,
         })
     }
@@ -85,6 +140,7 @@ impl TryInto<protobuf::PartitionLocation> for PartitionLocation {
             executor_meta: Some(self.executor_meta.into()),
             partition_stats: Some(self.partition_stats.into()),
             path: self.path,
+            auth_token: self.auth_token,
         })
     }
 }
@@ -104,6 +160,7 @@ impl Into<protobuf::PartitionStats> for PartitionStats {
 
 pub fn hash_partitioning_to_proto(
     output_partitioning: Option<&Partitioning>,
+    hash_scheme: HashScheme,
 ) -> Result<Option<protobuf::PhysicalHashRepartition>, BallistaError> {
     match output_partitioning {
         Some(Partitioning::Hash(exprs, partition_count)) => {
@@ -113,6 +170,7 @@ pub fn hash_partitioning_to_proto(
                     .map(|expr| expr.clone().try_into())
                     .collect::<Result<Vec<_>, BallistaError>>()?,
                 partition_count: *partition_count as u64,
+                hash_scheme: protobuf::HashScheme::from(hash_scheme).into(),
             }))
         }
         None => Ok(None),
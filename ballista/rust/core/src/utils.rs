@@ -22,11 +22,13 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::{fs::File, pin::Pin};
 
+use crate::encryption::{create_shuffle_file, ShuffleKey};
 use crate::error::{BallistaError, Result};
 use crate::execution_plans::{
     DistributedQueryExec, ShuffleWriterExec, UnresolvedShuffleExec,
 };
 use crate::memory_stream::MemoryStream;
+use crate::result_cache::ResultCache;
 use crate::serde::scheduler::PartitionStats;
 
 use crate::config::BallistaConfig;
@@ -58,20 +60,24 @@ use datafusion::physical_plan::hash_join::HashJoinExec;
 use datafusion::physical_plan::parquet::ParquetExec;
 use datafusion::physical_plan::projection::ProjectionExec;
 use datafusion::physical_plan::sort::SortExec;
+use datafusion::physical_plan::sorted_aggregate::SortedAggregateExec;
 use datafusion::physical_plan::{
     AggregateExpr, ExecutionPlan, PhysicalExpr, RecordBatchStream, SQLMetric,
 };
 use futures::{future, Stream, StreamExt};
 use std::time::Instant;
 
-/// Stream data to disk in Arrow IPC format
+/// Stream data to disk in Arrow IPC format, encrypted with `encryption_key` if any.
+/// `num_rows_metric` counts the rows as they are written.
 
 pub async fn write_stream_to_disk(
     stream: &mut Pin<Box<dyn RecordBatchStream + Send + Sync>>,
     path: &str,
+    encryption_key: Option<&ShuffleKey>,
     disk_write_metric: Arc<SQLMetric>,
+    num_rows_metric: Arc<SQLMetric>,
 ) -> Result<PartitionStats> {
-    let file = File::create(&path).map_err(|e| {
+    let file = create_shuffle_file(path, encryption_key).map_err(|e| {
         BallistaError::General(format!(
             "Failed to create partition file at {}: {:?}",
             path, e
@@ -81,7 +87,7 @@ pub async fn write_stream_to_disk(
     let mut num_rows = 0;
     let mut num_batches = 0;
     let mut num_bytes = 0;
-    let mut writer = FileWriter::try_new(file, stream.schema().as_ref())?;
+    let mut writer = FileWriter::try_new(file.clone(), stream.schema().as_ref())?;
 
     while let Some(result) = stream.next().await {
         let batch = result?;
@@ -94,6 +100,7 @@ pub async fn write_stream_to_disk(
         num_batches += 1;
         num_rows += batch.num_rows();
         num_bytes += batch_size_bytes;
+        num_rows_metric.add(batch.num_rows());
 
         let start = Instant::now();
         writer.write(&batch)?;
@@ -101,6 +108,7 @@ pub async fn write_stream_to_disk(
     }
     let start = Instant::now();
     writer.finish()?;
+    file.finish()?;
     disk_write_metric.add_elapsed(start);
     Ok(PartitionStats::new(
         Some(num_rows as u64),
@@ -164,6 +172,12 @@ fn build_exec_plan_diagram(
 ) -> Result<usize> {
     let operator_str = if plan.as_any().downcast_ref::<HashAggregateExec>().is_some() {
         "HashAggregateExec"
+    } else if plan
+        .as_any()
+        .downcast_ref::<SortedAggregateExec>()
+        .is_some()
+    {
+        "SortedAggregateExec"
     } else if plan.as_any().downcast_ref::<SortExec>().is_some() {
         "SortExec"
     } else if plan.as_any().downcast_ref::<ProjectionExec>().is_some() {
@@ -236,25 +250,29 @@ fn build_exec_plan_diagram(
     Ok(node_id)
 }
 
-/// Create a DataFusion context that is compatible with Ballista
+/// Create a DataFusion context that is compatible with Ballista, whose queries are
+/// answered from `result_cache` when it has their results
 pub fn create_datafusion_context(
     scheduler_host: &str,
     scheduler_port: u16,
     config: &BallistaConfig,
+    result_cache: Option<Arc<ResultCache>>,
 ) -> ExecutionContext {
     let scheduler_url = format!("http://{}:{}", scheduler_host, scheduler_port);
     let config = ExecutionConfig::new()
-        .with_query_planner(Arc::new(BallistaQueryPlanner::new(
-            scheduler_url,
-            config.clone(),
-        )))
-        .with_concurrency(config.default_shuffle_partitions());
+        .with_query_planner(Arc::new(
+            BallistaQueryPlanner::new(scheduler_url, config.clone())
+                .with_result_cache(result_cache),
+        ))
+        .with_concurrency(config.default_shuffle_partitions())
+        .with_hash_scheme(config.shuffle_hash_scheme());
     ExecutionContext::with_config(config)
 }
 
 pub struct BallistaQueryPlanner {
     scheduler_url: String,
     config: BallistaConfig,
+    result_cache: Option<Arc<ResultCache>>,
 }
 
 impl BallistaQueryPlanner {
@@ -262,8 +280,15 @@ impl BallistaQueryPlanner {
         Self {
             scheduler_url,
             config,
+            result_cache: None,
         }
     }
+
+    /// Cache the results of the planned queries in `result_cache`
+    pub fn with_result_cache(mut self, result_cache: Option<Arc<ResultCache>>) -> Self {
+        self.result_cache = result_cache;
+        self
+    }
 }
 
 impl QueryPlanner for BallistaQueryPlanner {
@@ -272,11 +297,14 @@ impl QueryPlanner for BallistaQueryPlanner {
         logical_plan: &LogicalPlan,
         _ctx_state: &ExecutionContextState,
     ) -> std::result::Result<Arc<dyn ExecutionPlan>, DataFusionError> {
-        Ok(Arc::new(DistributedQueryExec::new(
-            self.scheduler_url.clone(),
-            self.config.clone(),
-            logical_plan.clone(),
-        )))
+        Ok(Arc::new(
+            DistributedQueryExec::new(
+                self.scheduler_url.clone(),
+                self.config.clone(),
+                logical_plan.clone(),
+            )
+            .with_result_cache(self.result_cache.clone()),
+        ))
     }
 }
 
@@ -314,3 +342,9 @@ impl Stream for WrappedStream {
         self.stream.size_hint()
     }
 }
+
+/// Compares two byte strings, such as auth tokens, in time that does not depend on
+/// their contents
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
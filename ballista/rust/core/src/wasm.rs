@@ -0,0 +1,472 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Scalar UDFs that are compiled to WebAssembly. Their modules are shipped to the
+//! scheduler and executors together with the plans that use them, so that custom
+//! functions can run without rebuilding the executors.
+//!
+//! A module must export a function with the name of the UDF, whose parameters and
+//! result are the WebAssembly counterparts of the argument and return types of the
+//! UDF: `i32` for `Int32`, `i64` for `Int64`, `f32` for `Float32` and `f64` for
+//! `Float64`. The function is called once per row and returns NULL when any of its
+//! arguments is NULL.
+//!
+//! Modules are sandboxed: they cannot import any host functions, and every call
+//! is limited in the number of instructions it can execute.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+use datafusion::arrow::array::{
+    Array, ArrayRef, Float32Array, Float64Array, Int32Array, Int64Array,
+};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::error::DataFusionError;
+use datafusion::logical_plan::create_udf;
+use datafusion::physical_plan::functions::{
+    make_scalar_function, ScalarFunctionImplementation,
+};
+use datafusion::physical_plan::udf::ScalarUDF;
+use once_cell::sync::Lazy;
+use wasmtime::{Config, Engine, ExternType, Instance, Module, Store, Val, ValType};
+
+use crate::error::{BallistaError, Result};
+
+/// Fuel, which is roughly the number of WebAssembly instructions, available for
+/// each row that a UDF is called for
+const FUEL_PER_ROW: u64 = 100_000;
+
+/// The WebAssembly UDFs known to this process, by name and hash of their module, as
+/// UDFs with the same name may come from different modules
+static WASM_UDFS: Lazy<RwLock<HashMap<(String, u64), Arc<WasmScalarUdf>>>> =
+    Lazy::new(Default::default);
+
+/// A scalar UDF implemented by a function of a WebAssembly module
+pub struct WasmScalarUdf {
+    name: String,
+    module_bytes: Vec<u8>,
+    arg_types: Vec<DataType>,
+    return_type: DataType,
+    /// The implementation of the UDF in the plans, which identifies the UDF when they
+    /// are serialized
+    fun: ScalarFunctionImplementation,
+}
+
+/// The compiled function of a WebAssembly UDF
+struct WasmFunction {
+    name: String,
+    return_type: DataType,
+    engine: Engine,
+    module: Module,
+}
+
+impl WasmScalarUdf {
+    /// Compile the WebAssembly module in `module_bytes`, which may be in binary or
+    /// text format, and check that it exports a function `name` with the given
+    /// signature
+    pub fn try_new(
+        name: &str,
+        module_bytes: Vec<u8>,
+        arg_types: Vec<DataType>,
+        return_type: DataType,
+    ) -> Result<Self> {
+        let expected_params = arg_types
+            .iter()
+            .map(wasm_type)
+            .collect::<Result<Vec<_>>>()?;
+        let expected_results = vec![wasm_type(&return_type)?];
+
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(wasm_error)?;
+        let module = Module::new(&engine, &module_bytes).map_err(wasm_error)?;
+
+        match module.get_export(name) {
+            Some(ExternType::Func(ty)) => {
+                let params = ty.params().collect::<Vec<_>>();
+                let results = ty.results().collect::<Vec<_>>();
+                if params != expected_params || results != expected_results {
+                    return Err(BallistaError::General(format!(
+                        "WebAssembly function {} has signature {:?} -> {:?}, expected {:?} -> {:?}",
+                        name, params, results, expected_params, expected_results
+                    )));
+                }
+            }
+            _ => {
+                return Err(BallistaError::General(format!(
+                    "WebAssembly module does not export a function named {}",
+                    name
+                )))
+            }
+        }
+
+        let function = Arc::new(WasmFunction {
+            name: name.to_owned(),
+            return_type: return_type.clone(),
+            engine,
+            module,
+        });
+        let fun = make_scalar_function(move |args: &[ArrayRef]| function.invoke(args));
+        Ok(Self {
+            name: name.to_owned(),
+            module_bytes,
+            arg_types,
+            return_type,
+            fun,
+        })
+    }
+
+    /// The name of the UDF and of the function exported by the module
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The WebAssembly module, as it was provided by the user
+    pub fn module_bytes(&self) -> &[u8] {
+        &self.module_bytes
+    }
+
+    /// The types of the arguments of the UDF
+    pub fn arg_types(&self) -> &[DataType] {
+        &self.arg_types
+    }
+
+    /// The type of the values returned by the UDF
+    pub fn return_type(&self) -> &DataType {
+        &self.return_type
+    }
+
+    /// The implementation of the UDF, which every DataFusion UDF created by
+    /// [Self::to_scalar_udf] shares
+    pub fn fun(&self) -> &ScalarFunctionImplementation {
+        &self.fun
+    }
+
+    /// Create a DataFusion UDF that calls the WebAssembly function
+    pub fn to_scalar_udf(&self) -> ScalarUDF {
+        create_udf(
+            &self.name,
+            self.arg_types.clone(),
+            Arc::new(self.return_type.clone()),
+            self.fun.clone(),
+        )
+    }
+}
+
+impl WasmFunction {
+    /// Call the WebAssembly function for every row of `args`
+    fn invoke(&self, args: &[ArrayRef]) -> datafusion::error::Result<ArrayRef> {
+        let num_rows = args.first().map(|arg| arg.len()).unwrap_or(1);
+
+        // every batch runs in a new instance, so no state is shared between calls
+        let mut store = Store::new(&self.engine, ());
+        store
+            .add_fuel(FUEL_PER_ROW * num_rows.max(1) as u64)
+            .map_err(execution_error)?;
+        let instance =
+            Instance::new(&mut store, &self.module, &[]).map_err(execution_error)?;
+        let func = instance.get_func(&mut store, &self.name).ok_or_else(|| {
+            DataFusionError::Execution(format!(
+                "WebAssembly module does not export a function named {}",
+                self.name
+            ))
+        })?;
+
+        let mut params = Vec::with_capacity(args.len());
+        let mut results = Vec::with_capacity(num_rows);
+        for row in 0..num_rows {
+            if args.iter().any(|arg| arg.is_null(row)) {
+                results.push(None);
+                continue;
+            }
+            params.clear();
+            for arg in args {
+                params.push(wasm_value(arg, row)?);
+            }
+            let result = func.call(&mut store, &params).map_err(execution_error)?;
+            results.push(result.first().cloned());
+        }
+
+        let array: ArrayRef = match self.return_type {
+            DataType::Int32 => Arc::new(
+                results
+                    .iter()
+                    .map(|v| v.as_ref().and_then(|v| v.i32()))
+                    .collect::<Int32Array>(),
+            ),
+            DataType::Int64 => Arc::new(
+                results
+                    .iter()
+                    .map(|v| v.as_ref().and_then(|v| v.i64()))
+                    .collect::<Int64Array>(),
+            ),
+            DataType::Float32 => Arc::new(
+                results
+                    .iter()
+                    .map(|v| v.as_ref().and_then(|v| v.f32()))
+                    .collect::<Float32Array>(),
+            ),
+            DataType::Float64 => Arc::new(
+                results
+                    .iter()
+                    .map(|v| v.as_ref().and_then(|v| v.f64()))
+                    .collect::<Float64Array>(),
+            ),
+            ref other => {
+                return Err(DataFusionError::Internal(format!(
+                    "Unsupported WebAssembly UDF return type {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(array)
+    }
+}
+
+impl fmt::Debug for WasmScalarUdf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WasmScalarUdf")
+            .field("name", &self.name)
+            .field("arg_types", &self.arg_types)
+            .field("return_type", &self.return_type)
+            .finish()
+    }
+}
+
+/// Make a WebAssembly UDF known to this process, so that plans that use it can be
+/// serialized. A UDF that was registered before with the same name and module is
+/// replaced.
+pub fn register_wasm_udf(udf: Arc<WasmScalarUdf>) {
+    let key = (udf.name().to_owned(), module_hash(udf.module_bytes()));
+    WASM_UDFS.write().unwrap().insert(key, udf);
+}
+
+/// The WebAssembly UDF whose implementation is `fun`, if it is known to this process.
+/// UDFs are looked up by implementation rather than by name, so that a plan is
+/// serialized with the module that it was planned with.
+pub fn find_wasm_udf(fun: &ScalarFunctionImplementation) -> Option<Arc<WasmScalarUdf>> {
+    // the data pointers are compared, as the vtables of a closure may be duplicated
+    let fun = Arc::as_ptr(fun) as *const u8;
+    WASM_UDFS
+        .read()
+        .unwrap()
+        .values()
+        .find(|udf| Arc::as_ptr(&udf.fun) as *const u8 == fun)
+        .cloned()
+}
+
+fn module_hash(module_bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module_bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the known WebAssembly UDF with the given definition, or compiles and
+/// registers it if it is not known yet. This avoids compiling the module again for
+/// every plan that uses it.
+pub fn get_or_register_wasm_udf(
+    name: &str,
+    module_bytes: Vec<u8>,
+    arg_types: Vec<DataType>,
+    return_type: DataType,
+) -> Result<Arc<WasmScalarUdf>> {
+    let key = (name.to_owned(), module_hash(&module_bytes));
+    if let Some(udf) = WASM_UDFS.read().unwrap().get(&key).cloned() {
+        if udf.module_bytes == module_bytes
+            && udf.arg_types == arg_types
+            && udf.return_type == return_type
+        {
+            return Ok(udf);
+        }
+    }
+    let udf = Arc::new(WasmScalarUdf::try_new(
+        name,
+        module_bytes,
+        arg_types,
+        return_type,
+    )?);
+    register_wasm_udf(udf.clone());
+    Ok(udf)
+}
+
+/// The WebAssembly type that represents values of `data_type`
+fn wasm_type(data_type: &DataType) -> Result<ValType> {
+    match data_type {
+        DataType::Int32 => Ok(ValType::I32),
+        DataType::Int64 => Ok(ValType::I64),
+        DataType::Float32 => Ok(ValType::F32),
+        DataType::Float64 => Ok(ValType::F64),
+        other => Err(BallistaError::NotImplemented(format!(
+            "WebAssembly UDFs do not support values of type {:?}",
+            other
+        ))),
+    }
+}
+
+/// The value of `array[row]` as a WebAssembly value
+fn wasm_value(array: &ArrayRef, row: usize) -> datafusion::error::Result<Val> {
+    let any = array.as_any();
+    match array.data_type() {
+        DataType::Int32 => Ok(Val::I32(
+            any.downcast_ref::<Int32Array>().unwrap().value(row),
+        )),
+        DataType::Int64 => Ok(Val::I64(
+            any.downcast_ref::<Int64Array>().unwrap().value(row),
+        )),
+        DataType::Float32 => Ok(Val::F32(
+            any.downcast_ref::<Float32Array>()
+                .unwrap()
+                .value(row)
+                .to_bits(),
+        )),
+        DataType::Float64 => Ok(Val::F64(
+            any.downcast_ref::<Float64Array>()
+                .unwrap()
+                .value(row)
+                .to_bits(),
+        )),
+        other => Err(DataFusionError::Internal(format!(
+            "Unsupported WebAssembly UDF argument type {:?}",
+            other
+        ))),
+    }
+}
+
+fn wasm_error(e: anyhow::Error) -> BallistaError {
+    BallistaError::General(format!("Invalid WebAssembly module: {}", e))
+}
+
+fn execution_error(e: anyhow::Error) -> DataFusionError {
+    DataFusionError::Execution(format!("WebAssembly UDF failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::physical_plan::ColumnarValue;
+
+    fn invoke(udf: &WasmScalarUdf, args: &[ArrayRef]) -> Result<ArrayRef> {
+        let args = args
+            .iter()
+            .map(|arg| ColumnarValue::Array(arg.clone()))
+            .collect::<Vec<_>>();
+        match (udf.fun())(&args)? {
+            ColumnarValue::Array(array) => Ok(array),
+            ColumnarValue::Scalar(scalar) => Ok(scalar.to_array()),
+        }
+    }
+
+    const ADD: &str = r#"
+        (module
+          (func (export "wasm_add") (param i64 i64) (result i64)
+            local.get 0
+            local.get 1
+            i64.add))
+    "#;
+
+    const SPIN: &str = r#"
+        (module
+          (func (export "wasm_spin") (param f64) (result f64)
+            (loop (br 0))
+            local.get 0))
+    "#;
+
+    #[test]
+    fn invoke_wasm_udf() -> Result<()> {
+        let udf = WasmScalarUdf::try_new(
+            "wasm_add",
+            ADD.as_bytes().to_vec(),
+            vec![DataType::Int64, DataType::Int64],
+            DataType::Int64,
+        )?;
+        let args: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![Some(1), None, Some(3)])),
+            Arc::new(Int64Array::from(vec![Some(10), Some(20), Some(30)])),
+        ];
+        let result = invoke(&udf, &args)?;
+        let result = result.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(result.value(0), 11);
+        assert!(result.is_null(1));
+        assert_eq!(result.value(2), 33);
+        Ok(())
+    }
+
+    #[test]
+    fn udfs_with_the_same_name() -> Result<()> {
+        const MUL: &str = r#"
+            (module
+              (func (export "wasm_add") (param i64 i64) (result i64)
+                local.get 0
+                local.get 1
+                i64.mul))
+        "#;
+        let types = || vec![DataType::Int64, DataType::Int64];
+        let add = get_or_register_wasm_udf(
+            "wasm_add",
+            ADD.as_bytes().to_vec(),
+            types(),
+            DataType::Int64,
+        )?;
+        let mul = get_or_register_wasm_udf(
+            "wasm_add",
+            MUL.as_bytes().to_vec(),
+            types(),
+            DataType::Int64,
+        )?;
+        // registering the second module does not replace the first one
+        let found = find_wasm_udf(&add.to_scalar_udf().fun).unwrap();
+        assert_eq!(found.module_bytes(), ADD.as_bytes());
+        let found = find_wasm_udf(&mul.to_scalar_udf().fun).unwrap();
+        assert_eq!(found.module_bytes(), MUL.as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn signature_mismatch() {
+        let result = WasmScalarUdf::try_new(
+            "wasm_add",
+            ADD.as_bytes().to_vec(),
+            vec![DataType::Int32, DataType::Int32],
+            DataType::Int32,
+        );
+        assert!(result.is_err());
+
+        let result = WasmScalarUdf::try_new(
+            "missing",
+            ADD.as_bytes().to_vec(),
+            vec![DataType::Int64, DataType::Int64],
+            DataType::Int64,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn runaway_function_is_stopped() -> Result<()> {
+        let udf = WasmScalarUdf::try_new(
+            "wasm_spin",
+            SPIN.as_bytes().to_vec(),
+            vec![DataType::Float64],
+            DataType::Float64,
+        )?;
+        let args: Vec<ArrayRef> = vec![Arc::new(Float64Array::from(vec![1.0]))];
+        assert!(invoke(&udf, &args).is_err());
+        Ok(())
+    }
+}
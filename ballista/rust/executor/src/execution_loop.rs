@@ -15,26 +15,55 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::collections::HashSet;
 use std::convert::TryInto;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use datafusion::physical_plan::ExecutionPlan;
 use log::{debug, error, info, warn};
 use tonic::transport::Channel;
 
 use ballista_core::serde::protobuf::ExecutorRegistration;
 use ballista_core::serde::protobuf::{
     self, scheduler_grpc_client::SchedulerGrpcClient, task_status, FailedTask,
-    PartitionId, PollWorkParams, PollWorkResult, ShuffleWritePartition, TaskDefinition,
-    TaskStatus,
+    GetBroadcastsParams, GetTaskPlanParams, PartitionId, PhysicalPlanNode,
+    PlanCompression, PollWorkParams, PollWorkResult, RunningTask, ShuffleWritePartition,
+    TaskDefinition, TaskStatus,
 };
 use protobuf::CompletedTask;
 
 use crate::executor::Executor;
+use crate::task_logs;
+use ballista_core::client::scheduler_request;
 use ballista_core::error::BallistaError;
+use ballista_core::execution_plans::decode_batches;
+use ballista_core::serde::compression::decode_chunks;
 use ballista_core::serde::physical_plan::from_proto::parse_protobuf_hash_partitioning;
+use ballista_core::serde::protocol::{
+    JOB_CLEANUP_PROTOCOL_VERSION, PROTOCOL_VERSION, TASK_PROGRESS_PROTOCOL_VERSION,
+};
+
+/// Minimum time between two scans of the work directory for expired shuffle files
+const SHUFFLE_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time between two polls of an executor that is running tasks, whose statuses are
+/// reported by the next poll
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Maximum time between two polls of an idle executor, when the scheduler does not hold
+/// the polls or cannot be reached
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time that the scheduler may hold the polls of an idle executor until a task is
+/// available, which schedulers that predate long polling ignore
+const LONG_POLL_WAIT: Duration = Duration::from_secs(20);
+
+/// Minimum time between two reports of the progress of the running tasks
+const TASK_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
 
 pub async fn poll_loop(
     mut scheduler: SchedulerGrpcClient<Channel>,
@@ -45,34 +74,122 @@ pub async fn poll_loop(
     let available_tasks_slots = Arc::new(AtomicUsize::new(concurrent_tasks));
     let (task_status_sender, mut task_status_receiver) =
         std::sync::mpsc::channel::<TaskStatus>();
+    let mut last_shuffle_cleanup = Instant::now();
+    let mut last_task_progress = Instant::now();
+    let mut scheduler_protocol_version = None;
+    let mut backoff = PollBackoff::new(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL);
 
     loop {
         debug!("Starting registration loop with scheduler");
 
-        let task_status: Vec<TaskStatus> =
+        let mut task_status: Vec<TaskStatus> =
             sample_tasks_status(&mut task_status_receiver).await;
+        // tasks stop reporting progress before they send their final status, which
+        // therefore always follows their last progress
+        if scheduler_protocol_version
+            .map_or(false, |version| version >= TASK_PROGRESS_PROTOCOL_VERSION)
+            && last_task_progress.elapsed() >= TASK_PROGRESS_INTERVAL
+        {
+            last_task_progress = Instant::now();
+            task_status.extend(running_tasks_status(&executor, &executor_meta.id));
+        }
+        // the scheduler returns which of the reported jobs finished long enough ago
+        let mut shuffle_job_ids = vec![];
+        if scheduler_protocol_version
+            .map_or(false, |version| version >= JOB_CLEANUP_PROTOCOL_VERSION)
+            && last_shuffle_cleanup.elapsed() >= SHUFFLE_CLEANUP_INTERVAL
+        {
+            last_shuffle_cleanup = Instant::now();
+            match executor.shuffle_job_ids() {
+                Ok(job_ids) => shuffle_job_ids = job_ids,
+                Err(e) => warn!("Failed to list the jobs with shuffle files: {}", e),
+            }
+        }
 
         // Keeps track of whether we received task in last iteration
         // to avoid going in sleep mode between polling
         let mut active_job = false;
 
+        // an executor without running tasks has no status to report later, so the
+        // scheduler may hold its poll until a task is available
+        let running_tasks =
+            available_tasks_slots.load(Ordering::SeqCst) < concurrent_tasks;
+        let max_wait = if running_tasks {
+            Duration::default()
+        } else {
+            LONG_POLL_WAIT
+        };
+        let poll_start = Instant::now();
+
         let poll_work_result: anyhow::Result<
             tonic::Response<PollWorkResult>,
             tonic::Status,
         > = scheduler
-            .poll_work(PollWorkParams {
+            .poll_work(scheduler_request(PollWorkParams {
                 metadata: Some(executor_meta.clone()),
                 can_accept_task: available_tasks_slots.load(Ordering::SeqCst) > 0,
                 task_status,
-            })
+                max_wait_ms: max_wait.as_millis() as u64,
+                shuffle_job_ids,
+            }))
             .await;
 
         let task_status_sender = task_status_sender.clone();
 
         match poll_work_result {
             Ok(result) => {
-                if let Some(task) = result.into_inner().task {
+                let result = result.into_inner();
+                if scheduler_protocol_version != Some(result.protocol_version) {
+                    // schedulers that predate the version exchange report 0
+                    if result.protocol_version != PROTOCOL_VERSION {
+                        info!(
+                            "Scheduler has protocol version {}, this executor has version {}",
+                            result.protocol_version, PROTOCOL_VERSION
+                        );
+                    }
+                    scheduler_protocol_version = Some(result.protocol_version);
+                }
+                if !result.expired_job_ids.is_empty() {
+                    let executor = executor.clone();
+                    let job_ids = result.expired_job_ids;
+                    tokio::task::spawn_blocking(move || {
+                        match executor.remove_shuffle_files(&job_ids) {
+                            Ok(jobs) if !jobs.is_empty() => {
+                                info!("Removed shuffle files of expired jobs {:?}", jobs)
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                warn!("Failed to remove expired shuffle files: {}", e)
+                            }
+                        }
+                    });
+                }
+                // the time to live is part of the cluster configuration, which can
+                // change at any time, and schedulers that report the expired jobs
+                // send none
+                if result.shuffle_ttl_seconds > 0
+                    && last_shuffle_cleanup.elapsed() >= SHUFFLE_CLEANUP_INTERVAL
+                {
+                    last_shuffle_cleanup = Instant::now();
+                    let ttl = Duration::from_secs(result.shuffle_ttl_seconds);
+                    let pinned: HashSet<String> =
+                        result.pinned_job_ids.iter().cloned().collect();
+                    let executor = executor.clone();
+                    tokio::task::spawn_blocking(move || {
+                        match executor.remove_expired_shuffle_files(ttl, &pinned) {
+                            Ok(jobs) if !jobs.is_empty() => {
+                                info!("Removed expired shuffle files of jobs {:?}", jobs)
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                warn!("Failed to remove expired shuffle files: {}", e)
+                            }
+                        }
+                    });
+                }
+                if let Some(task) = result.task {
                     match run_received_tasks(
+                        &mut scheduler,
                         executor.clone(),
                         executor_meta.id.clone(),
                         available_tasks_slots.clone(),
@@ -97,13 +214,50 @@ pub async fn poll_loop(
                 warn!("Executor registration failed. If this continues to happen the executor might be marked as dead by the scheduler. Error: {}", error);
             }
         }
+        // running tasks report their statuses promptly
+        if active_job || running_tasks {
+            backoff.reset();
+        }
         if !active_job {
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            // a poll that the scheduler held already waited for part of the delay
+            let delay = backoff.next_delay().saturating_sub(poll_start.elapsed());
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Delay between the polls of an executor that receives no task, which doubles after
+/// every such poll up to a maximum
+struct PollBackoff {
+    min: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl PollBackoff {
+    fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            next: min,
         }
     }
+
+    /// Starts again from the minimum delay
+    fn reset(&mut self) {
+        self.next = self.min;
+    }
+
+    /// Returns the delay before the next poll, and doubles the following one
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
 }
 
 async fn run_received_tasks(
+    scheduler: &mut SchedulerGrpcClient<Channel>,
     executor: Arc<Executor>,
     executor_id: String,
     available_tasks_slots: Arc<AtomicUsize>,
@@ -111,17 +265,32 @@ async fn run_received_tasks(
     task: TaskDefinition,
 ) -> Result<(), BallistaError> {
     let task_id = task.task_id.unwrap();
+    executor.register_job_token(&task_id.job_id, &task.auth_token);
+    executor.register_job_key(&task_id.job_id, &task.encryption_key)?;
     let task_id_log = format!(
         "{}/{}/{}",
         task_id.job_id, task_id.stage_id, task_id.partition_id
     );
     info!("Received task {}", task_id_log);
+    let plan = match task.plan {
+        Some(plan) if !task.chunked_plan => plan,
+        _ => fetch_task_plan(scheduler, &task_id, task.plan_compression).await?,
+    };
+    let plan = executor.task_plan(&task_id.job_id, task_id.stage_id as usize, &plan)?;
+    // the first task of a job that reads broadcast variables fetches them
+    if executor.has_missing_broadcasts(&task_id.job_id, &plan) {
+        fetch_broadcasts(scheduler, &executor, &task_id.job_id).await?;
+    }
     available_tasks_slots.fetch_sub(1, Ordering::SeqCst);
-    let plan: Arc<dyn ExecutionPlan> = (&task.plan.unwrap()).try_into().unwrap();
     let shuffle_output_partitioning =
         parse_protobuf_hash_partitioning(task.output_partitioning.as_ref())?;
+    let timeout = Some(task.timeout_seconds)
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs);
 
-    tokio::spawn(async move {
+    // the records that the task logs can be fetched through the scheduler
+    let task = task_id.clone().try_into()?;
+    tokio::spawn(task_logs::capture(task, async move {
         let execution_result = executor
             .execute_shuffle_write(
                 task_id.job_id.clone(),
@@ -129,25 +298,77 @@ async fn run_received_tasks(
                 task_id.partition_id as usize,
                 plan,
                 shuffle_output_partitioning,
+                timeout,
             )
             .await;
         info!("Done with task {}", task_id_log);
         debug!("Statistics: {:?}", execution_result);
         available_tasks_slots.fetch_add(1, Ordering::SeqCst);
+        let job_disk_usage = executor.job_disk_usage(&task_id.job_id);
         let _ = task_status_sender.send(as_task_status(
             execution_result,
             executor_id,
             task_id,
+            job_disk_usage,
         ));
-    });
+    }));
 
     Ok(())
 }
 
+/// Saves the broadcast variables of a job, which the scheduler keeps for the executors
+async fn fetch_broadcasts(
+    scheduler: &mut SchedulerGrpcClient<Channel>,
+    executor: &Executor,
+    job_id: &str,
+) -> Result<(), BallistaError> {
+    let broadcasts = scheduler
+        .get_broadcasts(scheduler_request(GetBroadcastsParams {
+            job_id: job_id.to_owned(),
+        }))
+        .await?
+        .into_inner()
+        .broadcasts;
+    for broadcast in &broadcasts {
+        let (schema, batches) = decode_batches(&broadcast.data)?;
+        executor.save_broadcast(job_id, &broadcast.name, &schema, &batches)?;
+    }
+    debug!(
+        "Fetched {} broadcast variables of job {}",
+        broadcasts.len(),
+        job_id
+    );
+    Ok(())
+}
+
+/// Streams the plan of a task that was too large to be sent with its definition
+async fn fetch_task_plan(
+    scheduler: &mut SchedulerGrpcClient<Channel>,
+    task_id: &PartitionId,
+    compression: i32,
+) -> Result<PhysicalPlanNode, BallistaError> {
+    let compression = PlanCompression::from_i32(compression).ok_or_else(|| {
+        BallistaError::Internal(format!("Unknown plan compression {}", compression))
+    })?;
+    let mut chunks = scheduler
+        .get_task_plan(scheduler_request(GetTaskPlanParams {
+            task_id: Some(task_id.clone()),
+        }))
+        .await?
+        .into_inner();
+    let mut plan = vec![];
+    while let Some(chunk) = chunks.message().await? {
+        plan.extend_from_slice(&chunk.data);
+    }
+    debug!("Received a task plan of {} bytes in chunks", plan.len());
+    decode_chunks(compression, &plan, None)
+}
+
 fn as_task_status(
     execution_result: ballista_core::error::Result<Vec<ShuffleWritePartition>>,
     executor_id: String,
     task_id: PartitionId,
+    job_disk_usage: u64,
 ) -> TaskStatus {
     match execution_result {
         Ok(partitions) => {
@@ -158,6 +379,7 @@ fn as_task_status(
                 status: Some(task_status::Status::Completed(CompletedTask {
                     executor_id,
                     partitions,
+                    job_disk_usage,
                 })),
             }
         }
@@ -175,6 +397,25 @@ fn as_task_status(
     }
 }
 
+/// Running statuses that report the progress of the tasks that the executor is running
+fn running_tasks_status(executor: &Executor, executor_id: &str) -> Vec<TaskStatus> {
+    executor
+        .task_progress()
+        .into_iter()
+        .map(|progress| TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: progress.job_id,
+                stage_id: progress.stage_id as u32,
+                partition_id: progress.partition_id as u32,
+            }),
+            status: Some(task_status::Status::Running(RunningTask {
+                executor_id: executor_id.to_owned(),
+                num_rows: progress.num_rows,
+            })),
+        })
+        .collect()
+}
+
 async fn sample_tasks_status(
     task_status_receiver: &mut Receiver<TaskStatus>,
 ) -> Vec<TaskStatus> {
@@ -196,3 +437,24 @@ async fn sample_tasks_status(
 
     task_status
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll_backoff() {
+        let mut backoff =
+            PollBackoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays = (0..5).map(|_| backoff.next_delay()).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect::<Vec<_>>()
+        );
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+}
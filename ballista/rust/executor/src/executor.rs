@@ -17,19 +17,60 @@
 
 //! Ballista executor logic
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
 
+use ballista_core::encryption::ShuffleKey;
 use ballista_core::error::BallistaError;
-use ballista_core::execution_plans::ShuffleWriterExec;
-use ballista_core::serde::protobuf;
+use ballista_core::execution_plans::{
+    validate_broadcast_name, BroadcastExec, ShuffleWriterExec,
+};
+use ballista_core::serde::protobuf::{self, PhysicalPlanNode};
+use ballista_core::utils::constant_time_eq;
+use datafusion::arrow::datatypes::Schema;
+use datafusion::arrow::ipc::writer::FileWriter;
+use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::error::DataFusionError;
 use datafusion::physical_plan::display::DisplayableExecutionPlan;
 use datafusion::physical_plan::{ExecutionPlan, Partitioning};
 
+use crate::plan_cache::PlanCache;
+use crate::task_logs::{TaskLogs, DEFAULT_TASK_LOG_SIZE, DEFAULT_TASK_LOG_TASKS};
+
+/// Default number of stage plans that an executor keeps deserialized
+pub const DEFAULT_PLAN_CACHE_SIZE: usize = 16;
+
+/// Progress of a task that an executor is running
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskProgress {
+    pub job_id: String,
+    pub stage_id: usize,
+    pub partition_id: usize,
+    /// Rows that the plan of the task produced so far
+    pub num_rows: u64,
+}
+
 /// Ballista executor
 pub struct Executor {
     /// Directory for storing partial results
     work_dir: String,
+    /// Whether requests to fetch shuffle partitions must present the token of their job
+    shuffle_auth: bool,
+    /// Tokens of the jobs that this executor ran tasks for, by job id
+    job_tokens: RwLock<HashMap<String, String>>,
+    /// Keys that the shuffle files of the jobs are encrypted with, by job id
+    job_keys: RwLock<HashMap<String, ShuffleKey>>,
+    /// Bytes of the files that this executor keeps for each job, by job id
+    disk_usage: RwLock<HashMap<String, u64>>,
+    /// Shuffle writers of the tasks that are running, by job id, stage id and partition
+    running_tasks: RwLock<HashMap<(String, usize, usize), Arc<ShuffleWriterExec>>>,
+    /// Deserialized plans of the stages of recent tasks
+    plan_cache: PlanCache,
+    /// Captured log records of recent tasks
+    task_logs: Arc<TaskLogs>,
 }
 
 impl Executor {
@@ -37,14 +78,55 @@ impl Executor {
     pub fn new(work_dir: &str) -> Self {
         Self {
             work_dir: work_dir.to_owned(),
+            shuffle_auth: false,
+            job_tokens: RwLock::new(HashMap::new()),
+            job_keys: RwLock::new(HashMap::new()),
+            disk_usage: RwLock::new(HashMap::new()),
+            running_tasks: RwLock::new(HashMap::new()),
+            plan_cache: PlanCache::new(DEFAULT_PLAN_CACHE_SIZE),
+            task_logs: Arc::new(TaskLogs::new(
+                DEFAULT_TASK_LOG_SIZE,
+                DEFAULT_TASK_LOG_TASKS,
+            )),
         }
     }
+
+    /// Require requests to fetch shuffle partitions to present the token of their job
+    pub fn with_shuffle_auth(mut self, shuffle_auth: bool) -> Self {
+        self.shuffle_auth = shuffle_auth;
+        self
+    }
+
+    /// Keep the deserialized plans of the last `plan_cache_size` stages that this
+    /// executor ran tasks of, or none if 0
+    pub fn with_plan_cache_size(mut self, plan_cache_size: usize) -> Self {
+        self.plan_cache = PlanCache::new(plan_cache_size);
+        self
+    }
+
+    /// Keep the captured log records of tasks in `task_logs`, which should be the logs
+    /// that the installed [TaskLogger](crate::task_logs::TaskLogger) captures into
+    pub fn with_task_logs(mut self, task_logs: Arc<TaskLogs>) -> Self {
+        self.task_logs = task_logs;
+        self
+    }
 }
 
 impl Executor {
+    /// Deserializes the plan of a task, reusing the plan of a previous task of the same
+    /// stage when they are identical
+    pub fn task_plan(
+        &self,
+        job_id: &str,
+        stage_id: usize,
+        plan: &PhysicalPlanNode,
+    ) -> Result<Arc<dyn ExecutionPlan>, BallistaError> {
+        self.plan_cache.get_or_decode(job_id, stage_id, plan)
+    }
+
     /// Execute one partition of a query stage and persist the result to disk in IPC format. On
     /// success, return a RecordBatch containing metadata about the results, including path
-    /// and statistics.
+    /// and statistics. The task fails if it is still running after `timeout`.
     pub async fn execute_shuffle_write(
         &self,
         job_id: String,
@@ -52,41 +134,625 @@ impl Executor {
         part: usize,
         plan: Arc<dyn ExecutionPlan>,
         _shuffle_output_partitioning: Option<Partitioning>,
+        timeout: Option<Duration>,
     ) -> Result<Vec<protobuf::ShuffleWritePartition>, BallistaError> {
         let exec = if let Some(shuffle_writer) =
             plan.as_any().downcast_ref::<ShuffleWriterExec>()
         {
-            // recreate the shuffle writer with the correct working directory
+            // recreate the shuffle writer with the correct working directory, reading
+            // broadcast variables from the files they were saved to
+            let broadcast_dir = self.broadcast_dir(&job_id);
             ShuffleWriterExec::try_new(
                 job_id.clone(),
                 stage_id,
-                plan.children()[0].clone(),
+                resolve_broadcasts(plan.children()[0].clone(), &broadcast_dir)?,
                 self.work_dir.clone(),
                 shuffle_writer.shuffle_output_partitioning().cloned(),
             )
+            .map(|exec| {
+                exec.with_hash_scheme(shuffle_writer.hash_scheme())
+                    .with_results_path(shuffle_writer.results_path().map(str::to_owned))
+                    .with_encryption_key(self.job_key(&job_id))
+            })
         } else {
             Err(DataFusionError::Internal(
                 "Plan passed to execute_shuffle_write is not a ShuffleWriterExec"
                     .to_string(),
             ))
         }?;
+        let exec = Arc::new(exec);
+
+        // a task that is retried on this executor starts from an empty directory
+        let task_dir = exec.task_dir(part);
+        self.remove_task_dir(&job_id, &task_dir)?;
 
-        let partitions = exec.execute_shuffle_write(part).await?;
+        let task = (job_id.clone(), stage_id, part);
+        self.running_tasks
+            .write()
+            .unwrap()
+            .insert(task.clone(), exec.clone());
+        let result = match timeout {
+            // the task stops when its future is dropped
+            Some(timeout) => {
+                tokio::time::timeout(timeout, exec.execute_shuffle_write(part))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(DataFusionError::Execution(format!(
+                            "Task did not finish within its timeout of {} seconds",
+                            timeout.as_secs()
+                        )))
+                    })
+            }
+            None => exec.execute_shuffle_write(part).await,
+        };
+        self.running_tasks.write().unwrap().remove(&task);
+
+        let partitions = match result {
+            Ok(partitions) => partitions,
+            Err(e) => {
+                // a retry of the task starts from a newly deserialized plan
+                self.plan_cache.remove(&job_id, stage_id);
+                self.remove_task_dir(&job_id, &task_dir)?;
+                return Err(e.into());
+            }
+        };
+        let job_disk_usage = self.add_disk_usage(&job_id, dir_size(&task_dir)?);
 
         println!(
-            "=== [{}/{}/{}] Physical plan with metrics ===\n{}\n",
+            "=== [{}/{}/{}] Physical plan with metrics ===\n{}\nJob disk usage: {} bytes\n",
             job_id,
             stage_id,
             part,
-            DisplayableExecutionPlan::with_metrics(&exec)
+            DisplayableExecutionPlan::with_metrics(exec.as_ref())
                 .indent()
-                .to_string()
+                .to_string(),
+            job_disk_usage
         );
 
         Ok(partitions)
     }
 
+    /// Deletes the directory of a task with the files of a previous or failed attempt
+    fn remove_task_dir(
+        &self,
+        job_id: &str,
+        task_dir: &Path,
+    ) -> Result<(), BallistaError> {
+        if task_dir.exists() {
+            let size = dir_size(task_dir)?;
+            std::fs::remove_dir_all(task_dir)?;
+            if let Some(usage) = self.disk_usage.write().unwrap().get_mut(job_id) {
+                *usage = usage.saturating_sub(size);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `bytes` to the disk usage of a job and returns the new disk usage of the job
+    fn add_disk_usage(&self, job_id: &str, bytes: u64) -> u64 {
+        let mut disk_usage = self.disk_usage.write().unwrap();
+        let usage = disk_usage.entry(job_id.to_owned()).or_insert(0);
+        *usage += bytes;
+        *usage
+    }
+
+    /// Bytes of the shuffle files and broadcast variables that this executor keeps
+    /// for a job
+    pub fn job_disk_usage(&self, job_id: &str) -> u64 {
+        self.disk_usage
+            .read()
+            .unwrap()
+            .get(job_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Bytes of the files that this executor keeps for each job, by job id
+    pub fn disk_usage(&self) -> HashMap<String, u64> {
+        self.disk_usage.read().unwrap().clone()
+    }
+
+    /// The progress of the tasks that this executor is running
+    pub fn task_progress(&self) -> Vec<TaskProgress> {
+        self.running_tasks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|((job_id, stage_id, partition_id), exec)| TaskProgress {
+                job_id: job_id.clone(),
+                stage_id: *stage_id,
+                partition_id: *partition_id,
+                num_rows: exec.metrics()["inputRows"].value() as u64,
+            })
+            .collect()
+    }
+
     pub fn work_dir(&self) -> &str {
         &self.work_dir
     }
+
+    /// Path of the file with the partition `partition_id` of the results of a job,
+    /// which was written by the final stage `stage_id` of the job
+    pub fn results_path(
+        &self,
+        job_id: &str,
+        stage_id: usize,
+        partition_id: usize,
+    ) -> String {
+        format!(
+            "{}/{}/{}/{}/data.arrow",
+            self.work_dir, job_id, stage_id, partition_id
+        )
+    }
+
+    /// The captured log records of recent tasks
+    pub fn task_logs(&self) -> &TaskLogs {
+        &self.task_logs
+    }
+
+    /// Remember the token of a job that this executor received a task for
+    pub fn register_job_token(&self, job_id: &str, auth_token: &str) {
+        if !auth_token.is_empty() {
+            self.job_tokens
+                .write()
+                .unwrap()
+                .insert(job_id.to_owned(), auth_token.to_owned());
+        }
+    }
+
+    /// Remember the key that the shuffle files of a job that this executor received a
+    /// task for are encrypted with, which is empty if they are not encrypted
+    pub fn register_job_key(
+        &self,
+        job_id: &str,
+        encryption_key: &[u8],
+    ) -> Result<(), BallistaError> {
+        if let Some(key) = ShuffleKey::from_optional_bytes(encryption_key)? {
+            self.job_keys
+                .write()
+                .unwrap()
+                .insert(job_id.to_owned(), key);
+        }
+        Ok(())
+    }
+
+    /// The key that the shuffle files of a job are encrypted with, if any
+    pub fn job_key(&self, job_id: &str) -> Option<ShuffleKey> {
+        self.job_keys.read().unwrap().get(job_id).cloned()
+    }
+
+    /// Checks that a request presenting `auth_token` may push broadcast variables for
+    /// `job_id`. Only the token that this executor received with a task of the job is
+    /// accepted, so variables can only be pushed to the executors that run the job,
+    /// which fetch the variables of the job from the scheduler before its first task.
+    pub fn authorize_broadcast(
+        &self,
+        job_id: &str,
+        auth_token: &str,
+    ) -> Result<(), BallistaError> {
+        self.check_job_token(job_id, auth_token)
+    }
+
+    /// Returns whether `plan` reads broadcast variables of the job that were not saved
+    /// on this executor yet
+    pub fn has_missing_broadcasts(
+        &self,
+        job_id: &str,
+        plan: &Arc<dyn ExecutionPlan>,
+    ) -> bool {
+        if let Some(broadcast) = plan.as_any().downcast_ref::<BroadcastExec>() {
+            let path =
+                format!("{}/{}.arrow", self.broadcast_dir(job_id), broadcast.name());
+            if !Path::new(&path).exists() {
+                return true;
+            }
+        }
+        plan.children()
+            .iter()
+            .any(|child| self.has_missing_broadcasts(job_id, child))
+    }
+
+    /// Saves the batches of the broadcast variable `name` of a job, where the tasks of
+    /// the job read them. They are deleted together with the shuffle files of the job.
+    pub fn save_broadcast(
+        &self,
+        job_id: &str,
+        name: &str,
+        schema: &Schema,
+        batches: &[RecordBatch],
+    ) -> Result<(), BallistaError> {
+        validate_broadcast_name(name)?;
+        if job_id.is_empty()
+            || job_id.contains(|c: char| c == '/' || c == '\\' || c == '.')
+        {
+            return Err(BallistaError::General(format!("Invalid job id {}", job_id)));
+        }
+
+        let dir = self.broadcast_dir(job_id);
+        std::fs::create_dir_all(&dir)?;
+        // write to a temporary file first so that tasks never read a partial file
+        let path = format!("{}/{}.arrow", dir, name);
+        let tmp_path = format!("{}.tmp", path);
+        let mut writer = FileWriter::try_new(File::create(&tmp_path)?, schema)?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.finish()?;
+        std::fs::rename(&tmp_path, &path)?;
+        self.add_disk_usage(job_id, std::fs::metadata(&path)?.len());
+        Ok(())
+    }
+
+    fn broadcast_dir(&self, job_id: &str) -> String {
+        format!("{}/{}/broadcast", self.work_dir, job_id)
+    }
+
+    /// The ids of the jobs that have shuffle files on this executor
+    pub fn shuffle_job_ids(&self) -> Result<Vec<String>, BallistaError> {
+        let mut job_ids = vec![];
+        for entry in std::fs::read_dir(&self.work_dir)? {
+            let entry = entry?;
+            if entry.metadata()?.is_dir() {
+                job_ids.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(job_ids)
+    }
+
+    /// Deletes the shuffle files of the given jobs, which the scheduler found to have
+    /// expired. Only the ids of the jobs that have a directory in the work directory are
+    /// considered, and they are returned when their files were deleted.
+    pub fn remove_shuffle_files(
+        &self,
+        job_ids: &[String],
+    ) -> Result<Vec<String>, BallistaError> {
+        let existing = self.shuffle_job_ids()?;
+        let mut removed = vec![];
+        for job_id in job_ids {
+            if existing.contains(job_id) {
+                self.remove_job_dir(job_id)?;
+                removed.push(job_id.clone());
+            }
+        }
+        Ok(removed)
+    }
+
+    fn remove_job_dir(&self, job_id: &str) -> Result<(), BallistaError> {
+        std::fs::remove_dir_all(Path::new(&self.work_dir).join(job_id))?;
+        self.job_tokens.write().unwrap().remove(job_id);
+        self.job_keys.write().unwrap().remove(job_id);
+        self.disk_usage.write().unwrap().remove(job_id);
+        Ok(())
+    }
+
+    /// Deletes the shuffle files of the jobs whose directory was last modified at least
+    /// `ttl` ago, except the files of the `pinned` jobs, for schedulers that do not
+    /// report the jobs that finished. Returns the ids of the jobs whose files were
+    /// deleted.
+    pub fn remove_expired_shuffle_files(
+        &self,
+        ttl: Duration,
+        pinned: &HashSet<String>,
+    ) -> Result<Vec<String>, BallistaError> {
+        let now = SystemTime::now();
+        let mut removed = vec![];
+        for entry in std::fs::read_dir(&self.work_dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let job_id = entry.file_name().to_string_lossy().into_owned();
+            if !metadata.is_dir() || pinned.contains(&job_id) {
+                continue;
+            }
+            let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+            if age >= ttl {
+                self.remove_job_dir(&job_id)?;
+                removed.push(job_id);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Checks that a request presenting `auth_token` may read the log records of the
+    /// tasks of `job_id`. Without shuffle authentication all requests are allowed.
+    pub fn authorize_task_logs(
+        &self,
+        job_id: &str,
+        auth_token: &str,
+    ) -> Result<(), BallistaError> {
+        self.check_job_token(job_id, auth_token)
+    }
+
+    /// Checks that a request presenting `auth_token` may read the shuffle partition of
+    /// `job_id` at `path`. Without shuffle authentication all requests are allowed.
+    pub fn authorize_fetch(
+        &self,
+        job_id: &str,
+        path: &str,
+        auth_token: &str,
+    ) -> Result<(), BallistaError> {
+        if !self.shuffle_auth {
+            return Ok(());
+        }
+        self.check_job_token(job_id, auth_token)?;
+
+        // the token only grants access to the files of its own job
+        let job_dir = Path::new(&self.work_dir).join(job_id).canonicalize()?;
+        if !Path::new(path).canonicalize()?.starts_with(&job_dir) {
+            return Err(BallistaError::General(format!(
+                "Path {} is not a shuffle partition of job {}",
+                path, job_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that `auth_token` is the token that this executor received with the tasks
+    /// of `job_id`, unless shuffle authentication is disabled
+    fn check_job_token(
+        &self,
+        job_id: &str,
+        auth_token: &str,
+    ) -> Result<(), BallistaError> {
+        if !self.shuffle_auth {
+            return Ok(());
+        }
+        match self.job_tokens.read().unwrap().get(job_id) {
+            Some(token) if constant_time_eq(token.as_bytes(), auth_token.as_bytes()) => {
+                Ok(())
+            }
+            _ => Err(BallistaError::General(format!(
+                "Invalid shuffle token for job {}",
+                job_id
+            ))),
+        }
+    }
+}
+
+/// Replaces the broadcast variables in `plan` with ones that read the files in `dir`
+fn resolve_broadcasts(
+    plan: Arc<dyn ExecutionPlan>,
+    dir: &str,
+) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+    if let Some(broadcast) = plan.as_any().downcast_ref::<BroadcastExec>() {
+        let path = format!("{}/{}.arrow", dir, broadcast.name());
+        return Ok(Arc::new(broadcast.with_path(path)));
+    }
+    let children = plan.children();
+    if children.is_empty() {
+        return Ok(plan);
+    }
+    let resolved = children
+        .iter()
+        .map(|child| resolve_broadcasts(child.clone(), dir))
+        .collect::<Result<Vec<_>, _>>()?;
+    if children
+        .iter()
+        .zip(&resolved)
+        .all(|(child, resolved)| Arc::ptr_eq(child, resolved))
+    {
+        Ok(plan)
+    } else {
+        plan.with_new_children(resolved)
+    }
+}
+
+/// Total size of the files in `dir` and its subdirectories
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn authorize_fetch() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let work_dir = work_dir.path().to_str().unwrap();
+        let partition = format!("{}/job/1/0/data.arrow", work_dir);
+        std::fs::create_dir_all(format!("{}/job/1/0", work_dir))?;
+        std::fs::write(&partition, b"")?;
+        std::fs::create_dir_all(format!("{}/other", work_dir))?;
+
+        // everything is allowed without shuffle authentication
+        let executor = Executor::new(work_dir);
+        executor.authorize_fetch("job", &partition, "")?;
+
+        let executor = Executor::new(work_dir).with_shuffle_auth(true);
+        assert!(executor
+            .authorize_fetch("job", &partition, "token")
+            .is_err());
+
+        executor.register_job_token("job", "token");
+        executor.authorize_fetch("job", &partition, "token")?;
+        assert!(executor
+            .authorize_fetch("job", &partition, "tokem")
+            .is_err());
+        assert!(executor.authorize_fetch("job", &partition, "").is_err());
+
+        // paths outside of the directory of the job are rejected
+        let escape = format!("{}/job/../other", work_dir);
+        assert!(executor.authorize_fetch("job", &escape, "token").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn authorize_broadcast() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let executor =
+            Executor::new(work_dir.path().to_str().unwrap()).with_shuffle_auth(true);
+
+        // the token of a job is only known once a task of the job was received
+        assert!(executor.authorize_broadcast("job", "token").is_err());
+        executor.register_job_token("job", "token");
+        assert!(executor.authorize_broadcast("job", "").is_err());
+        executor.authorize_broadcast("job", "token")?;
+        assert!(executor.authorize_broadcast("job", "tokem").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn job_keys() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let executor = Executor::new(work_dir.path().to_str().unwrap());
+        let key = ShuffleKey::generate();
+
+        executor.register_job_key("plain", &[])?;
+        assert_eq!(executor.job_key("plain"), None);
+        executor.register_job_key("job", key.as_bytes())?;
+        assert_eq!(executor.job_key("job"), Some(key));
+        assert!(executor.register_job_key("job", b"short").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_broadcast() -> Result<(), BallistaError> {
+        use datafusion::arrow::array::Int32Array;
+        use datafusion::arrow::datatypes::{DataType, Field};
+        use datafusion::physical_plan::common::collect;
+
+        let work_dir = TempDir::new()?;
+        let executor = Executor::new(work_dir.path().to_str().unwrap());
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )?;
+        executor.save_broadcast("job", "dim", &schema, &[batch.clone()])?;
+        assert!(executor
+            .save_broadcast("../job", "dim", &schema, &[batch.clone()])
+            .is_err());
+
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(BroadcastExec::try_new_unresolved(
+            "dim",
+            schema.clone(),
+            None,
+        )?);
+        assert!(!executor.has_missing_broadcasts("job", &plan));
+        assert!(executor.has_missing_broadcasts("other", &plan));
+        let plan = resolve_broadcasts(plan, &executor.broadcast_dir("job"))?;
+        let batches = collect(plan.execute(0).await?).await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn task_dir_disk_usage() -> Result<(), BallistaError> {
+        use datafusion::arrow::array::Int32Array;
+        use datafusion::arrow::datatypes::{DataType, Field};
+        use datafusion::physical_plan::memory::MemoryExec;
+
+        let work_dir = TempDir::new()?;
+        let executor = Executor::new(work_dir.path().to_str().unwrap());
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone()], vec![batch]],
+            schema,
+            None,
+        )?);
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(ShuffleWriterExec::try_new(
+            "job".to_owned(),
+            1,
+            input,
+            "".to_owned(),
+            None,
+        )?);
+
+        executor
+            .execute_shuffle_write("job".to_owned(), 1, 0, plan.clone(), None, None)
+            .await?;
+        let task_dir = work_dir.path().join("job").join("1").join("0");
+        let usage = dir_size(&task_dir)?;
+        assert!(usage > 0);
+        assert_eq!(executor.job_disk_usage("job"), usage);
+
+        // a retried task does not see the files of the previous attempt
+        std::fs::write(task_dir.join("stale.arrow"), b"stale")?;
+        executor
+            .execute_shuffle_write("job".to_owned(), 1, 0, plan.clone(), None, None)
+            .await?;
+        assert!(!task_dir.join("stale.arrow").exists());
+        assert_eq!(executor.job_disk_usage("job"), usage);
+
+        // each task writes to its own directory
+        executor
+            .execute_shuffle_write("job".to_owned(), 1, 1, plan, None, None)
+            .await?;
+        assert!(work_dir.path().join("job").join("1").join("1").exists());
+        assert_eq!(executor.job_disk_usage("job"), 2 * usage);
+        assert_eq!(executor.disk_usage().len(), 1);
+        // finished tasks no longer report their progress
+        assert!(executor.task_progress().is_empty());
+
+        executor.remove_expired_shuffle_files(Duration::from_secs(0), &HashSet::new())?;
+        assert_eq!(executor.job_disk_usage("job"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_expired_shuffle_files() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let work_dir = work_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/job/1/0", work_dir))?;
+        std::fs::write(format!("{}/job/1/0/data.arrow", work_dir), b"")?;
+
+        let executor = Executor::new(work_dir);
+        let none = HashSet::new();
+        let removed =
+            executor.remove_expired_shuffle_files(Duration::from_secs(3600), &none)?;
+        assert!(removed.is_empty());
+        assert!(Path::new(work_dir).join("job").exists());
+
+        // the files of the pinned jobs are kept
+        let pinned = vec!["job".to_owned()].into_iter().collect();
+        let removed =
+            executor.remove_expired_shuffle_files(Duration::from_secs(0), &pinned)?;
+        assert!(removed.is_empty());
+
+        let removed =
+            executor.remove_expired_shuffle_files(Duration::from_secs(0), &none)?;
+        assert_eq!(removed, vec!["job".to_owned()]);
+        assert!(!Path::new(work_dir).join("job").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn remove_shuffle_files() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let work_dir = work_dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/job1/1/0", work_dir))?;
+        std::fs::create_dir_all(format!("{}/job2/1/0", work_dir))?;
+
+        let executor = Executor::new(work_dir);
+        let mut job_ids = executor.shuffle_job_ids()?;
+        job_ids.sort();
+        assert_eq!(job_ids, vec!["job1".to_owned(), "job2".to_owned()]);
+
+        // ids that are not directories of the work directory are ignored
+        let removed = executor.remove_shuffle_files(&[
+            "job1".to_owned(),
+            "..".to_owned(),
+            "job3".to_owned(),
+        ])?;
+        assert_eq!(removed, vec!["job1".to_owned()]);
+        assert!(!Path::new(work_dir).join("job1").exists());
+        assert!(Path::new(work_dir).join("job2").exists());
+        Ok(())
+    }
 }
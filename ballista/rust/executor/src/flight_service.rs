@@ -17,15 +17,17 @@
 
 //! Implementation of the Apache Arrow Flight protocol that wraps an executor.
 
-use std::fs::File;
+use std::convert::TryFrom;
 use std::pin::Pin;
 use std::sync::Arc;
 
 use crate::executor::Executor;
+use arrow_flight::utils::flight_data_to_arrow_batch;
 use arrow_flight::SchemaAsIpc;
+use ballista_core::encryption::{open_shuffle_file, ShuffleFileReader};
 use ballista_core::error::BallistaError;
 use ballista_core::serde::decode_protobuf;
-use ballista_core::serde::scheduler::Action as BallistaAction;
+use ballista_core::serde::scheduler::{Action as BallistaAction, PartitionId};
 
 use arrow_flight::{
     flight_service_server::FlightService, Action, ActionType, Criteria, Empty,
@@ -33,12 +35,14 @@ use arrow_flight::{
     PutResult, SchemaResult, Ticket,
 };
 use datafusion::arrow::{
-    error::ArrowError, ipc::reader::FileReader, ipc::writer::IpcWriteOptions,
-    record_batch::RecordBatch,
+    datatypes::Schema, error::ArrowError, ipc::reader::FileReader,
+    ipc::writer::IpcWriteOptions, record_batch::RecordBatch,
 };
+use datafusion::physical_plan::ipc::read_batch_lengths;
 use futures::{Stream, StreamExt};
 use log::{info, warn};
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 use tokio::sync::mpsc::channel;
 use tokio::{
     sync::mpsc::{Receiver, Sender},
@@ -61,6 +65,84 @@ impl BallistaFlightService {
     pub fn new(executor: Arc<Executor>) -> Self {
         Self { executor }
     }
+
+    /// Opens the shuffle partition of `job_id` at `path` for a request presenting
+    /// `auth_token`
+    fn open_partition(
+        &self,
+        job_id: &str,
+        path: &str,
+        auth_token: &str,
+    ) -> Result<FileReader<ShuffleFileReader>, Status> {
+        let file = self.open_file(job_id, path, auth_token)?;
+        FileReader::try_new(file).map_err(|e| from_arrow_err(&e))
+    }
+
+    /// Opens the file of the shuffle partition of `job_id` at `path` for a request
+    /// presenting `auth_token`
+    fn open_file(
+        &self,
+        job_id: &str,
+        path: &str,
+        auth_token: &str,
+    ) -> Result<ShuffleFileReader, Status> {
+        self.executor
+            .authorize_fetch(job_id, path, auth_token)
+            .map_err(|e| Status::permission_denied(e.to_string()))?;
+        let key = self.executor.job_key(job_id);
+        open_shuffle_file(path, key.as_ref())
+            .map_err(|e| {
+                BallistaError::General(format!(
+                    "Failed to open partition file at {}: {:?}",
+                    path, e
+                ))
+            })
+            .map_err(|e| from_ballista_err(&e))
+    }
+
+    /// Opens the partition of the results of a job that a `FetchResults` action reads,
+    /// positioned at the batch that contains row `offset`. Returns the reader together
+    /// with the number of rows of that batch that precede `offset`.
+    ///
+    /// The batches before it are skipped by their row counts in the record batch
+    /// headers, without reading their bodies.
+    fn open_results(
+        &self,
+        job_id: &str,
+        stage_id: usize,
+        partition_id: usize,
+        offset: usize,
+        auth_token: &str,
+    ) -> Result<(FileReader<ShuffleFileReader>, usize), Status> {
+        let path = self.executor.results_path(job_id, stage_id, partition_id);
+        if !Path::new(&path).exists() {
+            return Err(Status::not_found(format!(
+                "No results partition {}/{}/{} on this executor",
+                job_id, stage_id, partition_id
+            )));
+        }
+        if offset == 0 {
+            return Ok((self.open_partition(job_id, &path, auth_token)?, 0));
+        }
+        let mut file = self.open_file(job_id, &path, auth_token)?;
+        let batch_lengths = read_batch_lengths(&mut file)
+            .map_err(|e| Status::internal(format!("DataFusionError: {:?}", e)))?;
+        file.seek(SeekFrom::Start(0))
+            .map_err(|e| Status::internal(format!("{:?}", e)))?;
+        let mut reader = FileReader::try_new(file).map_err(|e| from_arrow_err(&e))?;
+
+        // stop at the last batch so that an offset past the end reads nothing
+        let mut index = 0;
+        let mut skip = offset;
+        while index + 1 < batch_lengths.len() && skip >= batch_lengths[index] {
+            skip -= batch_lengths[index];
+            index += 1;
+        }
+        if index > 0 {
+            reader.set_index(index).map_err(|e| from_arrow_err(&e))?;
+        }
+        Ok((reader, skip))
+    }
 }
 
 type BoxedFlightStream<T> =
@@ -86,40 +168,81 @@ impl FlightService for BallistaFlightService {
             decode_protobuf(&ticket.ticket).map_err(|e| from_ballista_err(&e))?;
 
         match &action {
-            BallistaAction::FetchPartition { path, .. } => {
+            BallistaAction::FetchPartition {
+                job_id,
+                path,
+                auth_token,
+                ..
+            } => {
+                let reader = self.open_partition(job_id, path, auth_token)?;
                 info!("FetchPartition reading {}", &path);
-                let file = File::open(&path)
-                    .map_err(|e| {
-                        BallistaError::General(format!(
-                            "Failed to open partition file at {}: {:?}",
-                            path, e
-                        ))
-                    })
-                    .map_err(|e| from_ballista_err(&e))?;
-                let reader = FileReader::try_new(file).map_err(|e| from_arrow_err(&e))?;
-
-                let (tx, rx): (FlightDataSender, FlightDataReceiver) = channel(2);
-
-                // Arrow IPC reader does not implement Sync + Send so we need to use a channel
-                // to communicate
-                task::spawn(async move {
-                    if let Err(e) = stream_flight_data(reader, tx).await {
-                        warn!("Error streaming results: {:?}", e);
-                    }
-                });
-
                 Ok(Response::new(
-                    Box::pin(ReceiverStream::new(rx)) as Self::DoGetStream
+                    stream_rows(reader, 0, None) as Self::DoGetStream
                 ))
             }
+            BallistaAction::FetchResults {
+                job_id,
+                stage_id,
+                partition_id,
+                offset,
+                max_rows,
+                auth_token,
+            } => {
+                let (reader, skip) = self.open_results(
+                    job_id,
+                    *stage_id,
+                    *partition_id,
+                    *offset as usize,
+                    auth_token,
+                )?;
+                info!(
+                    "FetchResults reading {}/{}/{} from row {}",
+                    job_id, stage_id, partition_id, offset
+                );
+                let max_rows = match max_rows {
+                    0 => None,
+                    max_rows => Some(*max_rows as usize),
+                };
+                Ok(Response::new(
+                    stream_rows(reader, skip, max_rows) as Self::DoGetStream
+                ))
+            }
+            _ => Err(Status::invalid_argument(format!(
+                "Action {:?} is not supported by do_get",
+                action
+            ))),
         }
     }
 
     async fn get_schema(
         &self,
-        _request: Request<FlightDescriptor>,
+        request: Request<FlightDescriptor>,
     ) -> Result<Response<SchemaResult>, Status> {
-        Err(Status::unimplemented("get_schema"))
+        let descriptor = request.into_inner();
+
+        let action =
+            decode_protobuf(&descriptor.cmd).map_err(|e| from_ballista_err(&e))?;
+
+        match &action {
+            BallistaAction::FetchResults {
+                job_id,
+                stage_id,
+                partition_id,
+                auth_token,
+                ..
+            } => {
+                let (reader, _) =
+                    self.open_results(job_id, *stage_id, *partition_id, 0, auth_token)?;
+                let options = IpcWriteOptions::default();
+                Ok(Response::new(
+                    SchemaAsIpc::new(reader.schema().as_ref(), &options).into(),
+                ))
+            }
+            _ => Err(Status::invalid_argument(format!(
+                "Action {:?} is not supported by get_schema",
+                action
+            ))),
+        }
     }
 
     async fn get_flight_info(
@@ -162,10 +285,44 @@ impl FlightService for BallistaFlightService {
     ) -> Result<Response<Self::DoActionStream>, Status> {
         let action = request.into_inner();
 
-        let _action =
+        let action =
             decode_protobuf(&action.body.to_vec()).map_err(|e| from_ballista_err(&e))?;
 
-        Err(Status::unimplemented("do_action"))
+        match &action {
+            BallistaAction::FetchTaskLogs {
+                job_id,
+                stage_id,
+                partition_id,
+                max_bytes,
+                auth_token,
+            } => {
+                self.executor
+                    .authorize_task_logs(job_id, auth_token)
+                    .map_err(|e| Status::permission_denied(e.to_string()))?;
+                let task = PartitionId::new(job_id, *stage_id, *partition_id);
+                let logs = self
+                    .executor
+                    .task_logs()
+                    .get(&task, *max_bytes as usize)
+                    .ok_or_else(|| {
+                        Status::not_found(format!(
+                            "No logs of task {}/{}/{}",
+                            job_id, stage_id, partition_id
+                        ))
+                    })?;
+                let result = arrow_flight::Result {
+                    body: logs.into_bytes(),
+                };
+                Ok(Response::new(
+                    Box::pin(futures::stream::iter(vec![Ok(result)]))
+                        as Self::DoActionStream,
+                ))
+            }
+            _ => Err(Status::invalid_argument(format!(
+                "Action {:?} is not supported by do_action",
+                action
+            ))),
+        }
     }
 
     async fn list_actions(
@@ -177,9 +334,59 @@ impl FlightService for BallistaFlightService {
 
     async fn do_exchange(
         &self,
-        _request: Request<Streaming<FlightData>>,
+        request: Request<Streaming<FlightData>>,
     ) -> Result<Response<Self::DoExchangeStream>, Status> {
-        Err(Status::unimplemented("do_exchange"))
+        let mut request = request.into_inner();
+
+        // the first message carries the action in its descriptor and the schema
+        let first = request
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("Empty exchange"))?;
+        let descriptor = first
+            .flight_descriptor
+            .as_ref()
+            .ok_or_else(|| Status::invalid_argument("Missing flight descriptor"))?;
+        let action =
+            decode_protobuf(&descriptor.cmd).map_err(|e| from_ballista_err(&e))?;
+
+        match &action {
+            BallistaAction::PutBroadcast {
+                job_id,
+                name,
+                auth_token,
+            } => {
+                self.executor
+                    .authorize_broadcast(job_id, auth_token)
+                    .map_err(|e| Status::permission_denied(e.to_string()))?;
+                let schema =
+                    Arc::new(Schema::try_from(&first).map_err(|e| from_arrow_err(&e))?);
+
+                let mut batches = vec![];
+                while let Some(data) = request.message().await? {
+                    batches.push(
+                        flight_data_to_arrow_batch(&data, schema.clone(), &[])
+                            .map_err(|e| from_arrow_err(&e))?,
+                    );
+                }
+                let num_rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+                self.executor
+                    .save_broadcast(job_id, name, &schema, &batches)
+                    .map_err(|e| from_ballista_err(&e))?;
+                info!(
+                    "PutBroadcast saved {} rows of {} for job {}",
+                    num_rows, name, job_id
+                );
+
+                Ok(Response::new(
+                    Box::pin(futures::stream::empty()) as Self::DoExchangeStream
+                ))
+            }
+            _ => Err(Status::invalid_argument(format!(
+                "Action {:?} is not supported by do_exchange",
+                action
+            ))),
+        }
     }
 }
 
@@ -199,8 +406,33 @@ fn create_flight_iter(
     )
 }
 
+/// Streams the schema and the rows of `reader` from its current batch, skipping the
+/// first `offset` rows and stopping after `max_rows` rows if set
+fn stream_rows<T>(
+    reader: FileReader<T>,
+    offset: usize,
+    max_rows: Option<usize>,
+) -> BoxedFlightStream<FlightData>
+where
+    T: Read + Seek + Send + 'static,
+{
+    let (tx, rx): (FlightDataSender, FlightDataReceiver) = channel(2);
+
+    // Arrow IPC reader does not implement Sync + Send so we need to use a channel
+    // to communicate
+    task::spawn(async move {
+        if let Err(e) = stream_flight_data(reader, offset, max_rows, tx).await {
+            warn!("Error streaming results: {:?}", e);
+        }
+    });
+
+    Box::pin(ReceiverStream::new(rx))
+}
+
 async fn stream_flight_data<T>(
     reader: FileReader<T>,
+    offset: usize,
+    max_rows: Option<usize>,
     tx: FlightDataSender,
 ) -> Result<(), Status>
 where
@@ -210,22 +442,50 @@ where
     let schema_flight_data = SchemaAsIpc::new(reader.schema().as_ref(), &options).into();
     send_response(&tx, Ok(schema_flight_data)).await?;
 
+    let mut skip = offset;
+    let mut remaining = max_rows.unwrap_or(usize::MAX);
     let mut row_count = 0;
     for batch in reader {
-        if let Ok(x) = &batch {
-            row_count += x.num_rows();
+        if remaining == 0 {
+            break;
         }
-        let batch_flight_data: Vec<_> = batch
-            .map(|b| create_flight_iter(&b, &options).collect())
-            .map_err(|e| from_arrow_err(&e))?;
+        let batch = batch.map_err(|e| from_arrow_err(&e))?;
+        if skip >= batch.num_rows() {
+            skip -= batch.num_rows();
+            continue;
+        }
+        let len = (batch.num_rows() - skip).min(remaining);
+        let batch = if len < batch.num_rows() {
+            slice_batch(&batch, skip, len).map_err(|e| from_arrow_err(&e))?
+        } else {
+            batch
+        };
+        skip = 0;
+        remaining -= len;
+        row_count += len;
+        let batch_flight_data: Vec<_> = create_flight_iter(&batch, &options).collect();
         for batch in batch_flight_data.into_iter() {
             send_response(&tx, batch).await?;
         }
     }
-    info!("FetchPartition streamed {} rows", row_count);
+    info!("Streamed {} rows", row_count);
     Ok(())
 }
 
+/// Returns the `len` rows of `batch` that start at row `offset`
+fn slice_batch(
+    batch: &RecordBatch,
+    offset: usize,
+    len: usize,
+) -> Result<RecordBatch, ArrowError> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| column.slice(offset, len))
+        .collect();
+    RecordBatch::try_new(batch.schema(), columns)
+}
+
 async fn send_response(
     tx: &FlightDataSender,
     data: Result<FlightData, Status>,
@@ -242,3 +502,109 @@ fn from_arrow_err(e: &ArrowError) -> Status {
 fn from_ballista_err(e: &ballista_core::error::BallistaError) -> Status {
     Status::internal(format!("Ballista Error: {:?}", e))
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use arrow_flight::flight_service_server::FlightServiceServer;
+    use ballista_core::client::BallistaClient;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field};
+    use datafusion::arrow::ipc::writer::FileWriter;
+    use datafusion::physical_plan::common::collect;
+    use std::fs::File;
+    use tempfile::TempDir;
+    use tokio::net::UnixListener;
+    use tokio_stream::wrappers::UnixListenerStream;
+    use tonic::transport::Server;
+
+    /// Serves the Flight endpoint of `executor` on a unix domain socket in `dir`, and
+    /// returns a client connected to it
+    async fn serve(
+        executor: Arc<Executor>,
+        dir: &Path,
+    ) -> Result<BallistaClient, BallistaError> {
+        let flight_socket = dir.join("executor.sock");
+        let listener = UnixListener::bind(&flight_socket)?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(BallistaFlightService::new(
+                    executor,
+                )))
+                .serve_with_incoming(UnixListenerStream::new(listener)),
+        );
+
+        // the host and port are not used when the socket exists
+        BallistaClient::try_new_local_or_remote(
+            "unreachable.invalid",
+            1,
+            flight_socket.to_str().unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn serve_on_unix_socket() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let executor = Arc::new(Executor::new(work_dir.path().to_str().unwrap()));
+        let mut client = serve(executor, work_dir.path()).await?;
+        let schema = Schema::new(vec![Field::new("a", DataType::Int32, false)]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )?;
+        client
+            .broadcast("job", "dim", "", &schema, &[batch])
+            .await?;
+        assert!(work_dir.path().join("job/broadcast").is_dir());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn fetch_results_by_page() -> Result<(), BallistaError> {
+        let work_dir = TempDir::new()?;
+        let executor = Arc::new(Executor::new(work_dir.path().to_str().unwrap()));
+
+        // the final stage 2 of the job wrote 10 rows in batches of 4, 4 and 2 rows
+        let path = executor.results_path("job", 2, 0);
+        std::fs::create_dir_all(Path::new(&path).parent().unwrap())?;
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let mut writer = FileWriter::try_new(File::create(&path)?, &schema)?;
+        for values in vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]] {
+            writer.write(&RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from(values))],
+            )?)?;
+        }
+        writer.finish()?;
+
+        let mut client = serve(executor, work_dir.path()).await?;
+        assert_eq!(schema, client.fetch_results_schema("job", 2, 0, "").await?);
+
+        let values = |batches: Vec<RecordBatch>| {
+            batches
+                .iter()
+                .flat_map(|batch| {
+                    let array = batch.column(0);
+                    let array = array.as_any().downcast_ref::<Int32Array>().unwrap();
+                    array.iter().map(|value| value.unwrap()).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        // a page that spans the three batches
+        let page = client.fetch_results("job", 2, 0, 3, 6, "").await?;
+        assert_eq!(vec![3, 4, 5, 6, 7, 8], values(collect(page).await?));
+        // the remaining rows
+        let page = client.fetch_results("job", 2, 0, 9, 0, "").await?;
+        assert_eq!(vec![9], values(collect(page).await?));
+        // pages that start at a batch boundary and past the last row
+        let page = client.fetch_results("job", 2, 0, 4, 2, "").await?;
+        assert_eq!(vec![4, 5], values(collect(page).await?));
+        let page = client.fetch_results("job", 2, 0, 12, 0, "").await?;
+        assert!(values(collect(page).await?).is_empty());
+
+        // partitions of results that the executor does not have
+        assert!(client.fetch_results("job", 2, 1, 0, 0, "").await.is_err());
+        assert!(client.fetch_results_schema("job", 3, 0, "").await.is_err());
+        Ok(())
+    }
+}
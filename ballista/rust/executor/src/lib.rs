@@ -24,6 +24,8 @@ pub mod collect;
 pub mod execution_loop;
 pub mod executor;
 pub mod flight_service;
+pub mod plan_cache;
+pub mod task_logs;
 
 mod standalone;
 pub use standalone::new_standalone_executor;
@@ -17,24 +17,31 @@
 
 //! Ballista Rust executor binary.
 
+use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use arrow_flight::flight_service_server::FlightServiceServer;
 use ballista_executor::execution_loop;
 use log::info;
 use tempfile::TempDir;
-use tonic::transport::Server;
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::{Identity, Server, ServerTlsConfig};
 use uuid::Uuid;
 
+use ballista_core::config::parse_labels;
 use ballista_core::serde::protobuf::{
     executor_registration, scheduler_grpc_client::SchedulerGrpcClient,
-    ExecutorRegistration,
+    ExecutorRegistration, KeyValuePair,
 };
-use ballista_core::{print_version, BALLISTA_VERSION};
+use ballista_core::serde::protocol::PROTOCOL_VERSION;
+use ballista_core::{client, print_version, BALLISTA_VERSION};
 use ballista_executor::executor::Executor;
 use ballista_executor::flight_service::BallistaFlightService;
+use ballista_executor::task_logs::{TaskLogger, TaskLogs};
 use config::prelude::*;
+use datafusion::execution::cpu_pool::configure_cpu_pool;
 
 #[macro_use]
 extern crate configure_me;
@@ -52,8 +59,6 @@ static ALLOC: snmalloc_rs::SnMalloc = snmalloc_rs::SnMalloc;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
-
     // parse command-line arguments
     let (opt, _remaining_args) =
         Config::including_optional_config_files(&["/etc/ballista/executor.toml"])
@@ -64,6 +69,13 @@ async fn main() -> Result<()> {
         std::process::exit(0);
     }
 
+    let task_log_level = opt
+        .task_log_level
+        .parse()
+        .with_context(|| format!("Invalid task log level: {}", opt.task_log_level))?;
+    let task_logs = Arc::new(TaskLogs::new(opt.task_log_size, opt.task_log_tasks));
+    TaskLogger::init(task_logs.clone(), task_log_level)?;
+
     let external_host = opt.external_host;
     let bind_host = opt.bind_host;
     let port = opt.bind_port;
@@ -87,29 +99,96 @@ async fn main() -> Result<()> {
     info!("Running with config:");
     info!("work_dir: {}", work_dir);
     info!("concurrent_tasks: {}", opt.concurrent_tasks);
+    info!("cpu_threads: {}", opt.cpu_threads);
+    configure_cpu_pool(opt.cpu_threads)?;
+    info!("shuffle_auth: {}", opt.shuffle_auth);
+    let labels = parse_labels(opt.labels.as_deref().unwrap_or_default())
+        .context("Could not parse the executor labels")?;
+    info!("labels: {:?}", labels);
+
+    if let Some(ca_cert_file) = &opt.tls_ca_cert_file {
+        let ca_cert = std::fs::read(ca_cert_file)
+            .with_context(|| format!("Could not read {}", ca_cert_file))?;
+        client::configure_tls(&ca_cert, opt.tls_domain_name)?;
+    }
+    if let Some(token) = &opt.scheduler_token {
+        client::configure_auth_token(token)?;
+    }
 
+    let executor_id = Uuid::new_v4().to_string(); // assign this executor a unique ID
+    let flight_socket = opt
+        .flight_socket_dir
+        .map(|dir| Path::new(&dir).join(format!("{}.sock", executor_id)));
     let executor_meta = ExecutorRegistration {
-        id: Uuid::new_v4().to_string(), // assign this executor a unique ID
+        id: executor_id,
         optional_host: external_host
             .clone()
             .map(executor_registration::OptionalHost::Host),
         port: port as u32,
+        labels: labels
+            .into_iter()
+            .map(|(key, value)| KeyValuePair { key, value })
+            .collect(),
+        flight_socket: flight_socket
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        protocol_version: PROTOCOL_VERSION,
     };
 
-    let scheduler = SchedulerGrpcClient::connect(scheduler_url)
+    let scheduler = client::create_channel(&scheduler_url)
         .await
+        .map(SchedulerGrpcClient::new)
         .context("Could not connect to scheduler")?;
 
-    let executor = Arc::new(Executor::new(&work_dir));
+    let executor = Arc::new(
+        Executor::new(&work_dir)
+            .with_shuffle_auth(opt.shuffle_auth)
+            .with_plan_cache_size(opt.plan_cache_size)
+            .with_task_logs(task_logs),
+    );
 
     let service = BallistaFlightService::new(executor.clone());
 
+    // clients on the same host fetch partitions through the socket, which is not
+    // served with TLS
+    if let Some(flight_socket) = &flight_socket {
+        if let Some(dir) = flight_socket.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {:?}", dir))?;
+        }
+        let listener = UnixListener::bind(flight_socket)
+            .with_context(|| format!("Could not bind {:?}", flight_socket))?;
+        info!("Serving the Flight endpoint on {:?}", flight_socket);
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(service.clone()))
+                .serve_with_incoming(UnixListenerStream::new(listener)),
+        );
+    }
+
     let server = FlightServiceServer::new(service);
     info!(
         "Ballista v{} Rust Executor listening on {:?}",
         BALLISTA_VERSION, addr
     );
-    let server_future = tokio::spawn(Server::builder().add_service(server).serve(addr));
+    let mut server_builder = Server::builder();
+    match (opt.tls_cert_file, opt.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let cert = std::fs::read(&cert_file)
+                .with_context(|| format!("Could not read {}", cert_file))?;
+            let key = std::fs::read(&key_file)
+                .with_context(|| format!("Could not read {}", key_file))?;
+            server_builder = server_builder
+                .tls_config(
+                    ServerTlsConfig::new().identity(Identity::from_pem(cert, key)),
+                )
+                .context("Invalid TLS configuration")?;
+        }
+        (None, None) => {}
+        _ => bail!("tls_cert_file and tls_key_file must be provided together"),
+    }
+    let server_future = tokio::spawn(server_builder.add_service(server).serve(addr));
     tokio::spawn(execution_loop::poll_loop(
         scheduler,
         executor,
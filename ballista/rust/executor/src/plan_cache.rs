@@ -0,0 +1,162 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Cache of the deserialized plans of query stages.
+//!
+//! The tasks of a stage all run the same plan on different partitions, so an executor
+//! that runs several tasks of a wide stage deserializes the plan, and initializes its
+//! scans, only once.
+
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+
+use ballista_core::error::BallistaError;
+use ballista_core::serde::protobuf::PhysicalPlanNode;
+use datafusion::physical_plan::ExecutionPlan;
+
+/// A deserialized stage plan and the plan it was deserialized from
+struct CachedPlan {
+    job_id: String,
+    stage_id: usize,
+    encoded: PhysicalPlanNode,
+    plan: Arc<dyn ExecutionPlan>,
+}
+
+/// Least recently used cache of stage plans, keyed by job and stage
+pub struct PlanCache {
+    capacity: usize,
+    /// The cached plans, the most recently used first
+    plans: Mutex<VecDeque<CachedPlan>>,
+}
+
+impl PlanCache {
+    /// Create a cache of at most `capacity` plans, which caches nothing if 0
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            plans: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns the cached plan of the stage if it was deserialized from the same
+    /// `encoded` plan, or deserializes `encoded` and caches the result. The plans of
+    /// the tasks of a stage differ when the scheduler relocated the partitions that they
+    /// read, in which case the new plan replaces the cached one.
+    pub fn get_or_decode(
+        &self,
+        job_id: &str,
+        stage_id: usize,
+        encoded: &PhysicalPlanNode,
+    ) -> Result<Arc<dyn ExecutionPlan>, BallistaError> {
+        if let Some(plan) = self.get(job_id, stage_id, encoded) {
+            return Ok(plan);
+        }
+        // deserialize without holding the lock, which other tasks may need
+        let plan: Arc<dyn ExecutionPlan> = encoded.try_into()?;
+        if self.capacity > 0 {
+            let mut plans = self.plans.lock().unwrap();
+            remove_entry(&mut plans, job_id, stage_id);
+            plans.push_front(CachedPlan {
+                job_id: job_id.to_owned(),
+                stage_id,
+                encoded: encoded.clone(),
+                plan: plan.clone(),
+            });
+            plans.truncate(self.capacity);
+        }
+        Ok(plan)
+    }
+
+    /// Forgets the plan of a stage, so that the next task of the stage gets a plan that
+    /// was not used by a failed task
+    pub fn remove(&self, job_id: &str, stage_id: usize) {
+        remove_entry(&mut self.plans.lock().unwrap(), job_id, stage_id);
+    }
+
+    fn get(
+        &self,
+        job_id: &str,
+        stage_id: usize,
+        encoded: &PhysicalPlanNode,
+    ) -> Option<Arc<dyn ExecutionPlan>> {
+        let mut plans = self.plans.lock().unwrap();
+        let index = plans.iter().position(|cached| {
+            cached.job_id == job_id
+                && cached.stage_id == stage_id
+                && cached.encoded == *encoded
+        })?;
+        let cached = plans.remove(index)?;
+        let plan = cached.plan.clone();
+        plans.push_front(cached);
+        Some(plan)
+    }
+}
+
+fn remove_entry(plans: &mut VecDeque<CachedPlan>, job_id: &str, stage_id: usize) {
+    plans.retain(|cached| cached.job_id != job_id || cached.stage_id != stage_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::empty::EmptyExec;
+
+    fn encoded_plan(column: &str) -> PhysicalPlanNode {
+        let schema = Schema::new(vec![Field::new(column, DataType::Int32, true)]);
+        let plan: Arc<dyn ExecutionPlan> =
+            Arc::new(EmptyExec::new(false, Arc::new(schema)));
+        plan.try_into().unwrap()
+    }
+
+    #[test]
+    fn reuses_plans_of_a_stage() -> Result<(), BallistaError> {
+        let cache = PlanCache::new(2);
+        let a = encoded_plan("a");
+        let plan = cache.get_or_decode("job", 1, &a)?;
+        assert!(Arc::ptr_eq(&plan, &cache.get_or_decode("job", 1, &a)?));
+
+        // another plan for the same stage replaces the cached one
+        let b = encoded_plan("b");
+        let plan_b = cache.get_or_decode("job", 1, &b)?;
+        assert!(!Arc::ptr_eq(&plan, &plan_b));
+        assert!(Arc::ptr_eq(&plan_b, &cache.get_or_decode("job", 1, &b)?));
+
+        cache.remove("job", 1);
+        assert!(!Arc::ptr_eq(&plan_b, &cache.get_or_decode("job", 1, &b)?));
+        Ok(())
+    }
+
+    #[test]
+    fn evicts_least_recently_used() -> Result<(), BallistaError> {
+        let cache = PlanCache::new(2);
+        let a = encoded_plan("a");
+        let plan1 = cache.get_or_decode("job", 1, &a)?;
+        let plan2 = cache.get_or_decode("job", 2, &a)?;
+        // stage 1 is used again, so stage 2 is evicted by stage 3
+        cache.get_or_decode("job", 1, &a)?;
+        cache.get_or_decode("job", 3, &a)?;
+        assert!(Arc::ptr_eq(&plan1, &cache.get_or_decode("job", 1, &a)?));
+        assert!(!Arc::ptr_eq(&plan2, &cache.get_or_decode("job", 2, &a)?));
+
+        let cache = PlanCache::new(0);
+        let plan = cache.get_or_decode("job", 1, &a)?;
+        assert!(!Arc::ptr_eq(&plan, &cache.get_or_decode("job", 1, &a)?));
+        Ok(())
+    }
+}
@@ -21,6 +21,7 @@ use arrow_flight::flight_service_server::FlightServiceServer;
 use ballista_core::{
     error::Result,
     serde::protobuf::{scheduler_grpc_client::SchedulerGrpcClient, ExecutorRegistration},
+    serde::protocol::PROTOCOL_VERSION,
     BALLISTA_VERSION,
 };
 use log::info;
@@ -61,6 +62,9 @@ pub async fn new_standalone_executor(
         id: Uuid::new_v4().to_string(), // assign this executor a unique ID
         optional_host: None,
         port: addr.port() as u32,
+        labels: vec![],
+        flight_socket: String::new(),
+        protocol_version: PROTOCOL_VERSION,
     };
     tokio::spawn(execution_loop::poll_loop(
         scheduler,
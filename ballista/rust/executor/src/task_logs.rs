@@ -0,0 +1,239 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Capture of the log records of tasks, so that the cause of a failed task can be
+//! retrieved through the scheduler instead of on the host of the executor.
+//!
+//! Records are attributed to the task whose future logs them, see [capture]. Records
+//! of threads that a task hands work to, such as the tasks that DataFusion spawns to
+//! repartition, are only written to the executor's log. Only records of the `log` crate
+//! are captured: the stdout and stderr of the process are shared by all the tasks that
+//! run concurrently, so what a task prints to them cannot be told apart.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use ballista_core::serde::scheduler::PartitionId;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Default number of bytes of the most recent log records kept for each task
+pub const DEFAULT_TASK_LOG_SIZE: usize = 64 * 1024;
+
+/// Default number of tasks whose log records are kept
+pub const DEFAULT_TASK_LOG_TASKS: usize = 256;
+
+/// Default level of the log records of tasks that are captured
+pub const DEFAULT_TASK_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+tokio::task_local! {
+    /// The task that the current future runs
+    static CURRENT_TASK: PartitionId;
+}
+
+/// Runs the future of a task, attributing the records that it logs to the task
+pub async fn capture<F: Future>(task: PartitionId, future: F) -> F::Output {
+    CURRENT_TASK.scope(task, future).await
+}
+
+/// The captured log records of the most recent tasks
+pub struct TaskLogs {
+    /// Bytes of the most recent records kept for each task
+    size: usize,
+    /// Number of tasks whose records are kept
+    max_tasks: usize,
+    logs: Mutex<CapturedLogs>,
+}
+
+#[derive(Default)]
+struct CapturedLogs {
+    /// The most recent records of each task
+    tasks: HashMap<PartitionId, VecDeque<u8>>,
+    /// The tasks in the order in which they logged their first record
+    order: VecDeque<PartitionId>,
+}
+
+impl TaskLogs {
+    /// Keep the last `size` bytes of the records of each of the last `max_tasks` tasks
+    pub fn new(size: usize, max_tasks: usize) -> Self {
+        Self {
+            size,
+            max_tasks,
+            logs: Mutex::new(CapturedLogs::default()),
+        }
+    }
+
+    /// Appends `text` to the log of a task, dropping its oldest bytes beyond the size
+    /// of a task's log, and the logs of the oldest tasks beyond the number of tasks
+    pub fn append(&self, task: &PartitionId, text: &str) {
+        if self.size == 0 || self.max_tasks == 0 {
+            return;
+        }
+        let mut logs = self.logs.lock().unwrap();
+        let logs = &mut *logs;
+        if !logs.tasks.contains_key(task) {
+            while logs.order.len() >= self.max_tasks {
+                if let Some(oldest) = logs.order.pop_front() {
+                    logs.tasks.remove(&oldest);
+                }
+            }
+            logs.order.push_back(task.clone());
+        }
+        let log = logs.tasks.entry(task.clone()).or_default();
+        let bytes = text.as_bytes();
+        let bytes = &bytes[bytes.len().saturating_sub(self.size)..];
+        let overflow = (log.len() + bytes.len()).saturating_sub(self.size);
+        log.drain(..overflow);
+        log.extend(bytes);
+    }
+
+    /// Returns the last `max_bytes` of the log of a task, or all of its kept log if
+    /// `max_bytes` is 0, or `None` if no records of the task are kept
+    pub fn get(&self, task: &PartitionId, max_bytes: usize) -> Option<String> {
+        let logs = self.logs.lock().unwrap();
+        let log = logs.tasks.get(task)?;
+        let skip = if max_bytes == 0 {
+            0
+        } else {
+            log.len().saturating_sub(max_bytes)
+        };
+        let mut bytes: Vec<u8> = log.iter().skip(skip).copied().collect();
+        // the log may have been cut inside of a character
+        let start = bytes
+            .iter()
+            .position(|b| (*b as i8) >= -0x40)
+            .unwrap_or(bytes.len());
+        bytes.drain(..start);
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Logger that writes records to the executor's log and captures the records of tasks
+pub struct TaskLogger {
+    inner: env_logger::Logger,
+    logs: Arc<TaskLogs>,
+    /// Records of tasks at this level or more severe are captured, independently of
+    /// the level of the executor's log
+    capture_level: LevelFilter,
+}
+
+impl TaskLogger {
+    /// Installs a logger that is configured like `env_logger::init` and captures the
+    /// records of tasks at `capture_level` or more severe in `logs`. This raises the
+    /// maximum level of the `log` crate to `capture_level`, and [LevelFilter::Off]
+    /// disables the capture.
+    pub fn init(
+        logs: Arc<TaskLogs>,
+        capture_level: LevelFilter,
+    ) -> Result<(), SetLoggerError> {
+        let inner = env_logger::Builder::from_default_env().build();
+        let max_level = inner.filter().max(capture_level);
+        log::set_boxed_logger(Box::new(Self {
+            inner,
+            logs,
+            capture_level,
+        }))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for TaskLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata) || metadata.level() <= self.capture_level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        if record.level() <= self.capture_level {
+            let _ = CURRENT_TASK.try_with(|task| {
+                let mut line = String::new();
+                let _ = writeln!(
+                    line,
+                    "[{} {}] {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+                self.logs.append(task, &line);
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_recent_logs() {
+        let logs = TaskLogs::new(8, 2);
+        let task = |partition_id| PartitionId::new("job", 1, partition_id);
+
+        logs.append(&task(0), "abcdef");
+        logs.append(&task(0), "ghij");
+        assert_eq!(logs.get(&task(0), 0), Some("cdefghij".to_owned()));
+        assert_eq!(logs.get(&task(0), 3), Some("hij".to_owned()));
+        logs.append(&task(0), "0123456789");
+        assert_eq!(logs.get(&task(0), 0), Some("23456789".to_owned()));
+
+        // a log is not cut inside of a character
+        logs.append(&task(1), "aé");
+        assert_eq!(logs.get(&task(1), 1), Some("".to_owned()));
+        assert_eq!(logs.get(&task(1), 2), Some("é".to_owned()));
+
+        // the log of the oldest task is dropped
+        logs.append(&task(2), "x");
+        assert_eq!(logs.get(&task(0), 0), None);
+        assert_eq!(logs.get(&task(2), 0), Some("x".to_owned()));
+        assert_eq!(logs.get(&task(3), 0), None);
+    }
+
+    #[tokio::test]
+    async fn captures_task_records() {
+        let logs = Arc::new(TaskLogs::new(1024, 2));
+        let logger = TaskLogger {
+            inner: env_logger::Builder::new().build(),
+            logs: logs.clone(),
+            capture_level: DEFAULT_TASK_LOG_LEVEL,
+        };
+        let record = |message| {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{}", message))
+                    .level(log::Level::Info)
+                    .target("test")
+                    .build(),
+            )
+        };
+
+        let task = PartitionId::new("job", 1, 0);
+        record("outside of a task");
+        capture(task.clone(), async { record("in a task") }).await;
+        assert_eq!(
+            logs.get(&task, 0),
+            Some("[INFO test] in a task\n".to_owned())
+        );
+    }
+}
@@ -10,15 +10,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::cluster_config::ClusterConfig;
 use crate::SchedulerServer;
+use ballista_core::serde::protobuf::{
+    execute_query_params::Query,
+    job_event, job_status,
+    query_stage_plan::{OptionalEstimatedBytes, OptionalEstimatedRows},
+    task_status, ExecuteQueryParams, JobEvent, KeyValuePair, PlanQueryResult,
+    QueryStagePlan, TaskStatus,
+};
+use ballista_core::serde::protocol::PROTOCOL_VERSION;
 use ballista_core::{serde::scheduler::ExecutorMeta, BALLISTA_VERSION};
-use warp::Rejection;
+use std::collections::HashMap;
+use warp::{http::StatusCode, Rejection};
 
 #[derive(Debug, serde::Serialize)]
 struct StateResponse {
     executors: Vec<ExecutorMeta>,
     started: u128,
     version: &'static str,
+    /// Protocol version of the scheduler
+    protocol_version: u32,
+    /// Whether the executors run different protocol versions than the scheduler or each
+    /// other, as during a rolling upgrade
+    mixed_protocol_versions: bool,
 }
 
 pub(crate) async fn scheduler_state(
@@ -33,10 +48,362 @@ pub(crate) async fn scheduler_state(
         .into_iter()
         .map(|(metadata, _duration)| metadata)
         .collect();
+    let mixed_protocol_versions = executors
+        .iter()
+        .any(|executor| executor.protocol_version != PROTOCOL_VERSION);
     let response = StateResponse {
         executors,
         started: data_server.start_time,
         version: BALLISTA_VERSION,
+        protocol_version: PROTOCOL_VERSION,
+        mixed_protocol_versions,
     };
     Ok(warp::reply::json(&response))
 }
+
+#[derive(Debug, serde::Serialize)]
+struct JobEventResponse {
+    /// Milliseconds since the UNIX epoch
+    timestamp: u64,
+    /// What happened, e.g. `job_failed` or `task_assigned`
+    event: &'static str,
+    stage_id: Option<u32>,
+    partition_id: Option<u32>,
+    executor_id: Option<String>,
+    error: Option<String>,
+    /// Bytes of the files that the executor keeps for the job after the task completed
+    job_disk_usage: Option<u64>,
+}
+
+impl From<JobEvent> for JobEventResponse {
+    fn from(job_event: JobEvent) -> Self {
+        let mut response = JobEventResponse {
+            timestamp: job_event.timestamp,
+            event: "unknown",
+            stage_id: None,
+            partition_id: None,
+            executor_id: None,
+            error: None,
+            job_disk_usage: None,
+        };
+        match job_event.event {
+            Some(job_event::Event::JobStatus(status)) => {
+                response.event = match status.status {
+                    Some(job_status::Status::Queued(_)) => "job_queued",
+                    Some(job_status::Status::Running(_)) => "job_running",
+                    Some(job_status::Status::Completed(_)) => "job_completed",
+                    Some(job_status::Status::Failed(failed)) => {
+                        response.error = Some(failed.error);
+                        "job_failed"
+                    }
+                    None => "unknown",
+                };
+            }
+            Some(job_event::Event::TaskStatus(status)) => {
+                response.stage_id = status.partition_id.as_ref().map(|p| p.stage_id);
+                response.partition_id =
+                    status.partition_id.as_ref().map(|p| p.partition_id);
+                response.event = match status.status {
+                    Some(task_status::Status::Running(running)) => {
+                        response.executor_id = Some(running.executor_id);
+                        "task_assigned"
+                    }
+                    Some(task_status::Status::Completed(completed)) => {
+                        response.executor_id = Some(completed.executor_id);
+                        response.job_disk_usage = Some(completed.job_disk_usage);
+                        "task_completed"
+                    }
+                    Some(task_status::Status::Failed(failed)) => {
+                        response.error = Some(failed.error);
+                        "task_failed"
+                    }
+                    Some(task_status::Status::Cancelled(_)) => "task_cancelled",
+                    None => "unknown",
+                };
+            }
+            Some(job_event::Event::TaskRescheduled(rescheduled)) => {
+                response.event = "task_rescheduled";
+                response.stage_id = rescheduled.partition_id.as_ref().map(|p| p.stage_id);
+                response.partition_id =
+                    rescheduled.partition_id.as_ref().map(|p| p.partition_id);
+                response.executor_id = Some(rescheduled.executor_id);
+            }
+            None => {}
+        }
+        response
+    }
+}
+
+pub(crate) async fn job_events(
+    job_id: String,
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let reply = match data_server.state.get_job_events(&job_id).await {
+        Ok(events) => {
+            let events: Vec<JobEventResponse> =
+                events.into_iter().map(|e| e.into()).collect();
+            warp::reply::with_status(warp::reply::json(&events), StatusCode::OK)
+        }
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&format!("Error reading job events: {}", e)),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    };
+    Ok(reply)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct TaskResponse {
+    stage_id: u32,
+    partition_id: u32,
+    /// `pending`, `running`, `completed`, `failed` or `cancelled`
+    status: &'static str,
+    executor_id: Option<String>,
+    /// Rows that a running task produced when its executor last reported its progress,
+    /// or that a completed task wrote
+    num_rows: Option<u64>,
+    error: Option<String>,
+}
+
+impl From<TaskStatus> for TaskResponse {
+    fn from(task_status: TaskStatus) -> Self {
+        let partition_id = task_status.partition_id.unwrap_or_default();
+        let mut response = TaskResponse {
+            stage_id: partition_id.stage_id,
+            partition_id: partition_id.partition_id,
+            status: "pending",
+            executor_id: None,
+            num_rows: None,
+            error: None,
+        };
+        match task_status.status {
+            Some(task_status::Status::Running(running)) => {
+                response.status = "running";
+                response.executor_id = Some(running.executor_id);
+                response.num_rows = Some(running.num_rows);
+            }
+            Some(task_status::Status::Completed(completed)) => {
+                response.status = "completed";
+                response.executor_id = Some(completed.executor_id);
+                response.num_rows =
+                    Some(completed.partitions.iter().map(|p| p.num_rows).sum());
+            }
+            Some(task_status::Status::Failed(failed)) => {
+                response.status = "failed";
+                response.error = Some(failed.error);
+            }
+            Some(task_status::Status::Cancelled(_)) => response.status = "cancelled",
+            None => {}
+        }
+        response
+    }
+}
+
+pub(crate) async fn job_tasks(
+    job_id: String,
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let reply = match data_server.state.get_job_tasks(&job_id).await {
+        Ok(tasks) => {
+            let mut tasks: Vec<TaskResponse> =
+                tasks.into_iter().map(|t| t.into()).collect();
+            tasks.sort_by_key(|task| (task.stage_id, task.partition_id));
+            warp::reply::with_status(warp::reply::json(&tasks), StatusCode::OK)
+        }
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&format!("Error reading job tasks: {}", e)),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    };
+    Ok(reply)
+}
+
+/// Query parameters of the logs of a task
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct TaskLogsQuery {
+    /// Number of bytes of the most recent records to return, all kept records if unset
+    max_bytes: Option<u64>,
+}
+
+/// Serves the captured log records of a task, which are fetched from the executor that
+/// ran the task last
+pub(crate) async fn task_logs(
+    job_id: String,
+    stage_id: u32,
+    partition_id: u32,
+    query: TaskLogsQuery,
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let reply = match data_server
+        .task_logs(
+            &job_id,
+            stage_id,
+            partition_id,
+            query.max_bytes.unwrap_or_default(),
+        )
+        .await
+    {
+        Ok((_executor_id, logs)) => warp::reply::with_status(logs, StatusCode::OK),
+        Err(e) => warp::reply::with_status(
+            format!("Error fetching task logs: {}", e),
+            StatusCode::BAD_GATEWAY,
+        ),
+    };
+    Ok(reply)
+}
+
+pub(crate) async fn get_cluster_config(
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    Ok(warp::reply::json(&data_server.state.cluster_config()))
+}
+
+pub(crate) async fn put_cluster_config(
+    body: warp::hyper::body::Bytes,
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let config = match ClusterConfig::from_json(&body) {
+        Ok(config) => config,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&e.to_string()),
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+    let reply = match data_server.state.save_cluster_config(&config).await {
+        Ok(()) => warp::reply::with_status(warp::reply::json(&config), StatusCode::OK),
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&format!("Error saving cluster configuration: {}", e)),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    };
+    Ok(reply)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct QueryStageResponse {
+    stage_id: u32,
+    /// Stages whose shuffle output the stage reads
+    input_stage_ids: Vec<u32>,
+    /// Number of tasks of the stage, one per input partition
+    task_count: u32,
+    /// Number of partitions that the output of the stage is shuffled into
+    output_partition_count: u32,
+    estimated_rows: Option<u64>,
+    estimated_bytes: Option<u64>,
+    plan: String,
+}
+
+impl From<QueryStagePlan> for QueryStageResponse {
+    fn from(stage: QueryStagePlan) -> Self {
+        QueryStageResponse {
+            stage_id: stage.stage_id,
+            input_stage_ids: stage.input_stage_ids,
+            task_count: stage.task_count,
+            output_partition_count: stage.output_partition_count,
+            estimated_rows: stage.optional_estimated_rows.map(|rows| match rows {
+                OptionalEstimatedRows::EstimatedRows(rows) => rows,
+            }),
+            estimated_bytes: stage.optional_estimated_bytes.map(|bytes| match bytes {
+                OptionalEstimatedBytes::EstimatedBytes(bytes) => bytes,
+            }),
+            plan: stage.plan,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct UnsupportedNodeResponse {
+    operator: String,
+    reason: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct DryRunResponse {
+    stages: Vec<QueryStageResponse>,
+    /// Operators that cannot be distributed, in which case no stages are planned
+    unsupported_nodes: Vec<UnsupportedNodeResponse>,
+}
+
+impl From<PlanQueryResult> for DryRunResponse {
+    fn from(result: PlanQueryResult) -> Self {
+        DryRunResponse {
+            stages: result.stages.into_iter().map(|s| s.into()).collect(),
+            unsupported_nodes: result
+                .unsupported_nodes
+                .into_iter()
+                .map(|node| UnsupportedNodeResponse {
+                    operator: node.operator,
+                    reason: node.reason,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Plans the SQL query of the request body into stages without executing it. The
+/// `session_id` query parameter selects the session whose tables the query uses, the
+/// other query parameters are settings of the query.
+pub(crate) async fn dry_run(
+    mut params: HashMap<String, String>,
+    body: warp::hyper::body::Bytes,
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let sql = match std::str::from_utf8(&body) {
+        Ok(sql) => sql.to_owned(),
+        Err(_) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&"The query must be UTF-8 text"),
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+    let session_id = params.remove("session_id").unwrap_or_default();
+    let settings = params
+        .into_iter()
+        .map(|(key, value)| KeyValuePair { key, value })
+        .collect();
+    let params = ExecuteQueryParams {
+        query: Some(Query::Sql(sql)),
+        settings,
+        session_id,
+    };
+    let reply = match data_server.dry_run(params).await {
+        Ok(result) => warp::reply::with_status(
+            warp::reply::json(&DryRunResponse::from(result)),
+            StatusCode::OK,
+        ),
+        Err(status) => warp::reply::with_status(
+            warp::reply::json(&status.message()),
+            StatusCode::BAD_REQUEST,
+        ),
+    };
+    Ok(reply)
+}
+
+/// Serves the metrics of the scheduler in the Prometheus text format
+pub(crate) async fn metrics(
+    data_server: SchedulerServer,
+) -> Result<impl warp::Reply, Rejection> {
+    let state = &data_server.state;
+    let alive_executors = state
+        .get_alive_executors_metadata(state.cluster_config().executor_timeout())
+        .await
+        .map(|executors| executors.len())
+        .unwrap_or_default();
+    state.metrics().set_alive_executors(alive_executors);
+
+    let reply = match state.metrics().encode() {
+        Ok(text) => warp::reply::with_status(text, StatusCode::OK),
+        Err(e) => warp::reply::with_status(
+            format!("Error encoding metrics: {}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    };
+    Ok(warp::reply::with_header(
+        reply,
+        "content-type",
+        "text/plain; version=0.0.4",
+    ))
+}
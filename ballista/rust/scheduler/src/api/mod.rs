@@ -12,14 +12,18 @@
 
 mod handlers;
 
+use crate::auth::{Role, SchedulerAuth};
 use crate::SchedulerServer;
 use anyhow::Result;
 use std::{
+    collections::HashMap,
+    future,
     pin::Pin,
     task::{Context as TaskContext, Poll},
 };
 use warp::filters::BoxedFilter;
-use warp::{Buf, Filter, Reply};
+use warp::http::StatusCode;
+use warp::{Buf, Filter, Rejection, Reply};
 
 pub enum EitherBody<A, B> {
     Left(A),
@@ -79,9 +83,177 @@ fn with_data_server(
     warp::any().map(move || db.clone())
 }
 
+/// Rejection of a request whose caller is not authorized to make it
+#[derive(Debug)]
+struct Unauthorized {
+    status: StatusCode,
+    message: String,
+}
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Requires the caller to present a token that grants one of the `allowed` roles, as
+/// the gRPC requests do. Admins are allowed to make any request.
+fn with_auth(
+    auth: SchedulerAuth,
+    allowed: &'static [Role],
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization: Option<String>| {
+            let result = auth
+                .authorize_header(authorization.as_deref(), allowed)
+                .map_err(|status| {
+                    let status_code = match status.code() {
+                        tonic::Code::PermissionDenied => StatusCode::FORBIDDEN,
+                        _ => StatusCode::UNAUTHORIZED,
+                    };
+                    warp::reject::custom(Unauthorized {
+                        status: status_code,
+                        message: status.message().to_owned(),
+                    })
+                });
+            future::ready(result)
+        })
+        .untuple_one()
+}
+
+async fn handle_rejection(
+    rejection: Rejection,
+) -> std::result::Result<impl Reply, Rejection> {
+    match rejection.find::<Unauthorized>() {
+        Some(unauthorized) => Ok(warp::reply::with_status(
+            warp::reply::json(&unauthorized.message),
+            unauthorized.status,
+        )),
+        None => Err(rejection),
+    }
+}
+
 pub fn get_routes(scheduler_server: SchedulerServer) -> BoxedFilter<(impl Reply,)> {
-    let routes = warp::path("state")
-        .and(with_data_server(scheduler_server))
+    let auth = scheduler_server.auth.clone();
+    let route_state = warp::path("state")
+        .and(with_auth(auth.clone(), &[Role::Submitter, Role::Monitor]))
+        .and(with_data_server(scheduler_server.clone()))
         .and_then(handlers::scheduler_state);
+    let route_job_events = warp::path!("jobs" / String / "events")
+        .and(with_auth(auth.clone(), &[Role::Submitter, Role::Monitor]))
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(handlers::job_events);
+    let route_job_tasks = warp::path!("jobs" / String / "tasks")
+        .and(with_auth(auth.clone(), &[Role::Submitter, Role::Monitor]))
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(handlers::job_tasks);
+    // log records may contain data, so only the callers that may submit queries can
+    // read them, as from the executors
+    let route_task_logs =
+        warp::path!("jobs" / String / "stages" / u32 / "partitions" / u32 / "logs")
+            .and(warp::get())
+            .and(with_auth(auth.clone(), &[Role::Submitter]))
+            .and(warp::query::<handlers::TaskLogsQuery>())
+            .and(with_data_server(scheduler_server.clone()))
+            .and_then(handlers::task_logs);
+    let route_get_config = warp::path!("config")
+        .and(warp::get())
+        .and(with_auth(auth.clone(), &[Role::Submitter, Role::Monitor]))
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(handlers::get_cluster_config);
+    // only admins may change the configuration of the cluster
+    let route_put_config = warp::path!("config")
+        .and(warp::put())
+        .and(with_auth(auth.clone(), &[]))
+        .and(warp::body::bytes())
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(handlers::put_cluster_config);
+    let route_dry_run = warp::path!("dry-run")
+        .and(warp::post())
+        .and(with_auth(auth.clone(), &[Role::Submitter]))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::body::bytes())
+        .and(with_data_server(scheduler_server.clone()))
+        .and_then(handlers::dry_run);
+    let route_metrics = warp::path!("metrics")
+        .and(warp::get())
+        .and(with_auth(auth, &[Role::Monitor]))
+        .and(with_data_server(scheduler_server))
+        .and_then(handlers::metrics);
+    let routes = route_state
+        .or(route_job_events)
+        .or(route_job_tasks)
+        .or(route_task_logs)
+        .or(route_get_config)
+        .or(route_put_config)
+        .or(route_dry_run)
+        .or(route_metrics)
+        .recover(handle_rejection);
     routes.boxed()
 }
+
+#[cfg(all(test, feature = "sled"))]
+mod tests {
+    use super::*;
+    use crate::state::StandaloneClient;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn authorize_routes() -> Result<()> {
+        let auth = SchedulerAuth::new()
+            .with_token(Role::Submitter, "submitter-secret")
+            .with_token(Role::Monitor, "monitor-secret");
+        let scheduler = SchedulerServer::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        )
+        .with_auth(auth);
+        let routes = get_routes(scheduler);
+
+        let status = |path: &'static str, method: &'static str, token: Option<&str>| {
+            let mut request = warp::test::request().method(method).path(path);
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            let routes = routes.clone();
+            async move { request.reply(&routes).await.status() }
+        };
+
+        assert_eq!(
+            status("/metrics", "GET", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/metrics", "GET", Some("wrong-secret")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status("/metrics", "GET", Some("submitter-secret")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/metrics", "GET", Some("monitor-secret")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("/config", "PUT", Some("monitor-secret")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/state", "GET", Some("submitter-secret")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(
+                "/jobs/job/stages/1/partitions/0/logs",
+                "GET",
+                Some("monitor-secret")
+            )
+            .await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("/dry-run", "POST", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Authentication and authorization of the callers of the scheduler.
+//!
+//! Callers present a shared-secret token in the `authorization` metadata of their
+//! requests, as `Bearer <token>`, or in the `Authorization` header of the requests of
+//! the REST API. Each token grants a [Role] that determines which requests the caller
+//! may make.
+
+use ballista_core::utils::constant_time_eq;
+use tonic::{Request, Status};
+
+/// Role granted to the callers presenting a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// May make any request
+    Admin,
+    /// May submit queries and follow the progress of jobs
+    Submitter,
+    /// May register as an executor and poll for work
+    Executor,
+    /// May read the state and the metrics of the cluster, e.g. a Prometheus server or
+    /// the autoscaler
+    Monitor,
+}
+
+/// Tokens accepted by the scheduler, and the roles they grant. Authentication is
+/// disabled when no token is configured.
+#[derive(Debug, Clone, Default)]
+pub struct SchedulerAuth {
+    tokens: Vec<(String, Role)>,
+}
+
+impl SchedulerAuth {
+    /// Create an empty configuration, which disables authentication
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `token` and grant `role` to callers presenting it
+    pub fn with_token(mut self, role: Role, token: impl Into<String>) -> Self {
+        self.tokens.push((token.into(), role));
+        self
+    }
+
+    /// Whether requests must present a token
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Checks that the caller of `request` presents a known token that grants one of
+    /// the `allowed` roles. Admins are allowed to make any request.
+    pub fn authorize<T>(
+        &self,
+        request: &Request<T>,
+        allowed: &[Role],
+    ) -> Result<(), Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        self.authorize_header(authorization, allowed)
+    }
+
+    /// Checks that the `authorization` header of a request, `Bearer <token>`, presents
+    /// a known token that grants one of the `allowed` roles
+    pub fn authorize_header(
+        &self,
+        authorization: Option<&str>,
+        allowed: &[Role],
+    ) -> Result<(), Status> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing auth token"))?;
+        // check all tokens so that the time taken does not depend on which matches
+        let role = self.tokens.iter().fold(None, |role, (t, r)| {
+            if constant_time_eq(t.as_bytes(), token.as_bytes()) {
+                Some(*r)
+            } else {
+                role
+            }
+        });
+        match role {
+            None => Err(Status::unauthenticated("Invalid auth token")),
+            Some(role) if role == Role::Admin || allowed.contains(&role) => Ok(()),
+            Some(role) => Err(Status::permission_denied(format!(
+                "Role {:?} is not allowed to make this request",
+                role
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::metadata::MetadataValue;
+
+    fn request(token: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request.metadata_mut().insert(
+                "authorization",
+                MetadataValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            );
+        }
+        request
+    }
+
+    #[test]
+    fn authorize() {
+        let auth = SchedulerAuth::new()
+            .with_token(Role::Admin, "admin-secret")
+            .with_token(Role::Submitter, "submitter-secret")
+            .with_token(Role::Executor, "executor-secret");
+
+        let allowed = [Role::Submitter];
+        assert!(auth
+            .authorize(&request(Some("submitter-secret")), &allowed)
+            .is_ok());
+        assert!(auth
+            .authorize(&request(Some("admin-secret")), &allowed)
+            .is_ok());
+
+        let status = auth
+            .authorize(&request(Some("executor-secret")), &allowed)
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = auth
+            .authorize(&request(Some("wrong-secret")), &allowed)
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let status = auth.authorize(&request(None), &allowed).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn disabled() {
+        let auth = SchedulerAuth::new();
+        assert!(auth.authorize(&request(None), &[Role::Executor]).is_ok());
+    }
+}
@@ -0,0 +1,180 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Cluster-wide configuration of the scheduler.
+//!
+//! The [ClusterConfig] document is stored as JSON in the config backend, so it is
+//! shared by all the schedulers of a namespace. Schedulers watch the document and
+//! apply changes to it without a restart.
+
+use std::time::Duration;
+
+use ballista_core::error::{BallistaError, Result};
+use serde::{Deserialize, Serialize};
+
+/// How a scheduler picks the task to assign to an executor that polls for work
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPolicy {
+    /// Prefer the task with the largest fraction of its shuffle inputs located on
+    /// the executor
+    PreferLocal,
+    /// Assign the first schedulable task that is found
+    FirstAvailable,
+}
+
+impl Default for SchedulingPolicy {
+    fn default() -> Self {
+        Self::PreferLocal
+    }
+}
+
+/// Settings shared by the schedulers of a namespace, which can be changed at runtime.
+/// Settings missing from the stored document take their default value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// Seconds without a heartbeat after which an executor is considered dead and
+    /// its tasks are rescheduled
+    pub executor_timeout_seconds: u64,
+    /// Maximum number of times a task is rescheduled because its executor died,
+    /// after which the task fails. Unlimited if not set
+    pub max_task_reschedules: Option<u32>,
+    /// How tasks are assigned to executors
+    pub scheduling_policy: SchedulingPolicy,
+    /// Seconds after a job completed or failed at which executors delete its shuffle
+    /// files. The files are kept until the executor stops if not set
+    pub shuffle_ttl_seconds: Option<u64>,
+    /// Seconds after which executors fail the tasks that are still running. Tasks run
+    /// until they finish if not set
+    pub task_timeout_seconds: Option<u64>,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            executor_timeout_seconds: 60,
+            max_task_reschedules: None,
+            scheduling_policy: SchedulingPolicy::default(),
+            shuffle_ttl_seconds: None,
+            task_timeout_seconds: None,
+        }
+    }
+}
+
+impl ClusterConfig {
+    /// Parse and validate a JSON configuration document
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        let config: Self = serde_json::from_slice(json).map_err(|e| {
+            BallistaError::General(format!("Invalid cluster configuration: {}", e))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Serialize the configuration to a JSON document
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| {
+            BallistaError::Internal(format!(
+                "Could not serialize cluster configuration: {}",
+                e
+            ))
+        })
+    }
+
+    /// Check that the settings are within their valid ranges
+    pub fn validate(&self) -> Result<()> {
+        if self.executor_timeout_seconds == 0 {
+            return Err(BallistaError::General(
+                "Invalid cluster configuration: executor_timeout_seconds must be positive"
+                    .to_owned(),
+            ));
+        }
+        if self.shuffle_ttl_seconds == Some(0) {
+            return Err(BallistaError::General(
+                "Invalid cluster configuration: shuffle_ttl_seconds must be positive"
+                    .to_owned(),
+            ));
+        }
+        if self.task_timeout_seconds == Some(0) {
+            return Err(BallistaError::General(
+                "Invalid cluster configuration: task_timeout_seconds must be positive"
+                    .to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time without a heartbeat after which an executor is considered dead
+    pub fn executor_timeout(&self) -> Duration {
+        Duration::from_secs(self.executor_timeout_seconds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ClusterConfig, SchedulingPolicy};
+
+    #[test]
+    fn missing_settings_take_defaults() {
+        let config =
+            ClusterConfig::from_json(br#"{"scheduling_policy": "first_available"}"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            ClusterConfig {
+                scheduling_policy: SchedulingPolicy::FirstAvailable,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            ClusterConfig::from_json(b"{}").unwrap(),
+            ClusterConfig::default()
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let config = ClusterConfig {
+            executor_timeout_seconds: 10,
+            max_task_reschedules: Some(3),
+            scheduling_policy: SchedulingPolicy::FirstAvailable,
+            shuffle_ttl_seconds: Some(3600),
+            task_timeout_seconds: Some(600),
+        };
+        let json = config.to_json().unwrap();
+        assert_eq!(ClusterConfig::from_json(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        for json in &[
+            r#"{"executor_timeout": 10}"#,
+            r#"{"executor_timeout_seconds": 0}"#,
+            r#"{"shuffle_ttl_seconds": 0}"#,
+            r#"{"task_timeout_seconds": 0}"#,
+            r#"{"scheduling_policy": "random"}"#,
+            "not json",
+        ] {
+            assert!(
+                ClusterConfig::from_json(json.as_bytes()).is_err(),
+                "{} should be rejected",
+                json
+            );
+        }
+    }
+}
@@ -21,7 +21,13 @@
 //! documentation.
 
 pub mod api;
+pub mod auth;
+pub mod cluster_config;
+pub mod metrics;
 pub mod planner;
+pub mod quota;
+pub mod scaler;
+pub mod session;
 #[cfg(feature = "sled")]
 mod standalone;
 pub mod state;
@@ -37,20 +43,35 @@ pub mod externalscaler {
     include!(concat!(env!("OUT_DIR"), "/externalscaler.rs"));
 }
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::{convert::TryInto, sync::Arc};
 use std::{fmt, net::IpAddr};
 
 use ballista_core::serde::protobuf::{
-    execute_query_params::Query, executor_registration::OptionalHost, job_status,
-    scheduler_grpc_server::SchedulerGrpc, task_status, ExecuteQueryParams,
-    ExecuteQueryResult, FailedJob, FilePartitionMetadata, FileType,
-    GetFileMetadataParams, GetFileMetadataResult, GetJobStatusParams, GetJobStatusResult,
-    JobStatus, PartitionId, PollWorkParams, PollWorkResult, QueuedJob, RunningJob,
-    TaskDefinition, TaskStatus,
+    execute_query_params::Query, executor_registration::OptionalHost, job_event,
+    job_status, scheduler_grpc_server::SchedulerGrpc, task_status, BroadcastVariable,
+    CompletedJob, CreateSessionParams, CreateSessionResult, ExecuteQueryChunk,
+    ExecuteQueryParams, ExecuteQueryResult, ExecuteStatementsParams,
+    ExecuteStatementsResult, FailedJob, FilePartitionMetadata, FileType,
+    GetBroadcastsParams, GetBroadcastsResult, GetFileMetadataParams,
+    GetFileMetadataResult, GetJobEventsParams, GetJobEventsResult, GetJobStatusParams,
+    GetJobStatusResult, GetTaskLogsParams, GetTaskLogsResult, GetTaskPlanParams,
+    JobEvent, JobStatus, JobSubmission, KeyValuePair, LogicalPlanNode, PartitionId,
+    PhysicalPlanNode, PlanCompression, PlanQueryResult, PollWorkParams, PollWorkResult,
+    QueuedJob, RunningJob, SessionMetadata, SessionStatement, TaskDefinition,
+    TaskPlanChunk,
 };
-use ballista_core::serde::scheduler::ExecutorMeta;
+use ballista_core::serde::protocol::{
+    executor_protocol_version, is_supported_protocol_version,
+    CHUNKED_PLAN_PROTOCOL_VERSION, COMPRESSED_PLAN_PROTOCOL_VERSION,
+    JOB_CLEANUP_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION, PINNED_JOBS_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
+use ballista_core::serde::scheduler::{ExecutorMeta, PartitionLocation};
 
 use clap::arg_enum;
+use datafusion::arrow::datatypes::Schema;
+use datafusion::logical_plan::{LogicalPlan, LogicalPlanBuilder};
 use datafusion::physical_plan::ExecutionPlan;
 #[cfg(feature = "sled")]
 extern crate sled_package as sled;
@@ -71,42 +92,90 @@ impl parse_arg::ParseArgFromStr for ConfigBackend {
     }
 }
 
+use crate::auth::{Role, SchedulerAuth};
 use crate::externalscaler::{
     external_scaler_server::ExternalScaler, GetMetricSpecResponse, GetMetricsRequest,
-    GetMetricsResponse, IsActiveResponse, MetricSpec, MetricValue, ScaledObjectRef,
+    GetMetricsResponse, IsActiveResponse, MetricValue, ScaledObjectRef,
 };
-use crate::planner::DistributedPlanner;
+use crate::metrics::{InstrumentedConfigBackend, SchedulerMetrics};
+use crate::planner::{describe_query_stages, DistributedPlanner};
+use crate::quota::NamespaceQuota;
+use crate::scaler::ScalerMetrics;
+use crate::session::{parse_set_statement, validate_statements, JobPins, SessionCatalog};
 
 use log::{debug, error, info, warn};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use tokio::sync::Notify;
 use tonic::{Request, Response, Status};
 
 use self::state::{ConfigBackendClient, SchedulerState};
-use ballista_core::config::BallistaConfig;
-use ballista_core::execution_plans::ShuffleWriterExec;
+use ballista_core::client::BallistaClient;
+use ballista_core::config::{satisfies_constraints, BallistaConfig};
+use ballista_core::datasource::JobResultTable;
+use ballista_core::encryption::ShuffleKey;
+use ballista_core::error::BallistaError;
+use ballista_core::execution_plans::{encode_batches, BroadcastExec, ShuffleWriterExec};
+use ballista_core::serde::compression::decompress;
+use ballista_core::serde::physical_plan::unsupported::find_unsupported_nodes;
 use ballista_core::serde::scheduler::to_proto::hash_partitioning_to_proto;
+use datafusion::physical_plan::csv::{CsvExec, CsvReadOptions};
 use datafusion::physical_plan::parquet::ParquetExec;
 use datafusion::prelude::{ExecutionConfig, ExecutionContext};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use futures::future::{BoxFuture, Shared};
+use futures::{FutureExt, Stream};
+use prost::Message;
+use std::pin::Pin;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
 pub struct SchedulerServer {
     caller_ip: IpAddr,
     pub(crate) state: Arc<SchedulerState>,
     start_time: u128,
+    quota: NamespaceQuota,
+    shuffle_auth: bool,
+    shuffle_encryption: bool,
+    auth: SchedulerAuth,
+    sessions: SessionCatalog,
+    /// The jobs whose results are registered as tables of the sessions
+    job_pins: JobPins,
+    plan_chunk_size: usize,
+    plan_compression: PlanCompression,
+    max_poll_wait: Duration,
 }
 
+/// Default size in bytes above which task plans are streamed to the executors in chunks
+pub const DEFAULT_PLAN_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Default maximum time that a poll for work is held until a task is available
+pub const DEFAULT_MAX_POLL_WAIT: Duration = Duration::from_secs(20);
+
+/// Interval at which a held poll for work looks for a task again, which finds the tasks
+/// of stages saved by the other schedulers of the namespace
+const POLL_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 impl SchedulerServer {
     pub fn new(
         config: Arc<dyn ConfigBackendClient>,
         namespace: String,
         caller_ip: IpAddr,
     ) -> Self {
-        let state = Arc::new(SchedulerState::new(config, namespace));
+        let metrics = Arc::new(SchedulerMetrics::new());
+        let config = Arc::new(InstrumentedConfigBackend::new(config, metrics.clone()));
+        let state =
+            Arc::new(SchedulerState::new(config, namespace).with_metrics(metrics));
         let state_clone = state.clone();
 
         // TODO: we should elect a leader in the scheduler cluster and run this only in the leader
         tokio::spawn(async move { state_clone.synchronize_job_status_loop().await });
+        let state_clone = state.clone();
+        tokio::spawn(async move { state_clone.reconcile_tasks_loop().await });
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = state_clone.watch_cluster_config_loop().await {
+                error!("Could not watch the cluster configuration: {}", e);
+            }
+        });
 
         Self {
             caller_ip,
@@ -115,54 +184,669 @@ impl SchedulerServer {
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis(),
+            quota: NamespaceQuota::default(),
+            shuffle_auth: false,
+            shuffle_encryption: false,
+            auth: SchedulerAuth::default(),
+            sessions: SessionCatalog::default(),
+            job_pins: JobPins::default(),
+            plan_chunk_size: DEFAULT_PLAN_CHUNK_SIZE,
+            plan_compression: PlanCompression::None,
+            max_poll_wait: DEFAULT_MAX_POLL_WAIT,
         }
     }
+
+    /// Limit the resources used by the jobs of this scheduler's namespace
+    pub fn with_quota(mut self, quota: NamespaceQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Generate a token for each job that executors can require to serve the shuffle
+    /// partitions of the job
+    pub fn with_shuffle_auth(mut self, shuffle_auth: bool) -> Self {
+        self.shuffle_auth = shuffle_auth;
+        self
+    }
+
+    /// Generate a key for each job that executors encrypt the shuffle files of the job
+    /// with, which is sent to them with the tasks of the job
+    pub fn with_shuffle_encryption(mut self, shuffle_encryption: bool) -> Self {
+        self.shuffle_encryption = shuffle_encryption;
+        self
+    }
+
+    /// Require callers to present one of the tokens of `auth`, which determine the
+    /// requests they are allowed to make
+    pub fn with_auth(mut self, auth: SchedulerAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Keep the sessions in `sessions`, which is shared by the servers of all the
+    /// connections of the scheduler
+    pub fn with_sessions(mut self, sessions: SessionCatalog) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Stream the task plans larger than `plan_chunk_size` bytes to the executors in
+    /// chunks of that size, so that no message exceeds the gRPC message size limits
+    pub fn with_plan_chunk_size(mut self, plan_chunk_size: usize) -> Self {
+        self.plan_chunk_size = plan_chunk_size.max(1);
+        self
+    }
+
+    /// Compress the task plans that are streamed to the executors in chunks with
+    /// `plan_compression`, unless the executors predate compressed plans
+    pub fn with_plan_compression(mut self, plan_compression: PlanCompression) -> Self {
+        self.plan_compression = plan_compression;
+        self
+    }
+
+    /// Hold the polls of executors without a task for at most `max_poll_wait`, until a
+    /// task is available. Polls are never held if zero.
+    pub fn with_max_poll_wait(mut self, max_poll_wait: Duration) -> Self {
+        self.max_poll_wait = max_poll_wait;
+        self
+    }
+
+    /// Wake the held polls through `task_notify`, which is shared by the servers of all
+    /// the connections of the scheduler, once stages or task statuses are saved
+    pub fn with_task_notify(mut self, task_notify: Arc<Notify>) -> Self {
+        self.state = Arc::new(self.state.as_ref().clone().with_task_notify(task_notify));
+        self
+    }
 }
 
-const INFLIGHT_TASKS_METRIC_NAME: &str = "inflight_tasks";
+impl SchedulerServer {
+    /// Assigns the next schedulable task to the executor, if there is one, and returns
+    /// its definition
+    async fn assign_task(
+        &self,
+        metadata: &ExecutorMeta,
+        protocol_version: u32,
+    ) -> Result<Option<TaskDefinition>, Status> {
+        let mut lock = self.state.lock().await.map_err(|e| {
+            let msg = format!("Could not lock the state: {}", e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        let task: Result<Option<_>, Status> = {
+            let plan = self
+                .state
+                .assign_next_schedulable_task(&metadata.id)
+                .await
+                .map_err(|e| {
+                    let msg = format!("Error finding next assignable task: {}", e);
+                    error!("{}", msg);
+                    tonic::Status::internal(msg)
+                })?;
+            if let Some((task, _plan)) = &plan {
+                let partition_id = task.partition_id.as_ref().unwrap();
+                info!(
+                    "Sending new task to {}: {}/{}/{}",
+                    metadata.id,
+                    partition_id.job_id,
+                    partition_id.stage_id,
+                    partition_id.partition_id
+                );
+            }
+            match plan {
+                Some((status, plan)) => {
+                    let plan_clone = plan.clone();
+                    let (output_partitioning, hash_scheme) = if let Some(shuffle_writer) =
+                        plan_clone.as_any().downcast_ref::<ShuffleWriterExec>()
+                    {
+                        (
+                            shuffle_writer.shuffle_output_partitioning(),
+                            shuffle_writer.hash_scheme(),
+                        )
+                    } else {
+                        return Err(Status::invalid_argument(format!(
+                            "Task root plan was not a ShuffleWriterExec: {:?}",
+                            plan_clone
+                        )));
+                    };
+                    let plan: PhysicalPlanNode = plan.try_into().map_err(|e| {
+                        let msg = format!("Could not serialize task plan: {}", e);
+                        error!("{}", msg);
+                        tonic::Status::internal(msg)
+                    })?;
+                    // executors that predate chunked plans receive them inline
+                    let chunked_plan = plan.encoded_len() > self.plan_chunk_size
+                        && protocol_version >= CHUNKED_PLAN_PROTOCOL_VERSION;
+                    let plan_compression = if chunked_plan
+                        && protocol_version >= COMPRESSED_PLAN_PROTOCOL_VERSION
+                    {
+                        self.plan_compression
+                    } else {
+                        PlanCompression::None
+                    };
+                    let partition_id = status.partition_id.as_ref().unwrap();
+                    if chunked_plan {
+                        self.state
+                            .save_task_plan(
+                                partition_id,
+                                &plan,
+                                self.plan_chunk_size,
+                                plan_compression,
+                            )
+                            .await
+                            .map_err(|e| {
+                                let msg = format!("Could not save task plan: {}", e);
+                                error!("{}", msg);
+                                tonic::Status::internal(msg)
+                            })?;
+                    }
+                    let job_id = &partition_id.job_id;
+                    let auth_token =
+                        self.state.get_job_token(job_id).await.map_err(|e| {
+                            let msg = format!("Could not get job token: {}", e);
+                            error!("{}", msg);
+                            tonic::Status::internal(msg)
+                        })?;
+                    let encryption_key =
+                        self.state.get_job_key(job_id).await.map_err(|e| {
+                            let msg = format!("Could not get job key: {}", e);
+                            error!("{}", msg);
+                            tonic::Status::internal(msg)
+                        })?;
+                    Ok(Some(TaskDefinition {
+                        plan: Some(plan).filter(|_| !chunked_plan),
+                        task_id: status.partition_id,
+                        auth_token,
+                        chunked_plan,
+                        plan_compression: plan_compression as i32,
+                        encryption_key,
+                        timeout_seconds: self
+                            .state
+                            .cluster_config()
+                            .task_timeout_seconds
+                            .unwrap_or_default(),
+                        output_partitioning: hash_partitioning_to_proto(
+                            output_partitioning,
+                            hash_scheme,
+                        )
+                        .map_err(|_| Status::internal("TBD".to_string()))?,
+                    }))
+                }
+                None => Ok(None),
+            }
+        };
+        lock.unlock().await;
+        task
+    }
 
-#[tonic::async_trait]
-impl ExternalScaler for SchedulerServer {
-    async fn is_active(
+    /// Fetches the captured log records of a task from the executor that ran it last,
+    /// and returns the id of the executor and the last `max_bytes` of the records, or
+    /// all records that the executor kept if 0
+    pub(crate) async fn task_logs(
         &self,
-        _request: Request<ScaledObjectRef>,
-    ) -> Result<Response<IsActiveResponse>, tonic::Status> {
-        let tasks = self.state.get_all_tasks().await.map_err(|e| {
+        job_id: &str,
+        stage_id: u32,
+        partition_id: u32,
+        max_bytes: u64,
+    ) -> Result<(String, String), BallistaError> {
+        let events = self.state.get_job_events(job_id).await?;
+        let executor_id = last_task_executor(&events, stage_id, partition_id)
+            .ok_or_else(|| {
+                BallistaError::General(format!(
+                    "Task {}/{}/{} was not assigned to an executor",
+                    job_id, stage_id, partition_id
+                ))
+            })?;
+        let executor = self
+            .state
+            .get_executors_metadata()
+            .await?
+            .into_iter()
+            .map(|(executor, _)| executor)
+            .find(|executor| executor.id == executor_id)
+            .ok_or_else(|| {
+                BallistaError::General(format!(
+                    "Executor {} of task {}/{}/{} is not registered",
+                    executor_id, job_id, stage_id, partition_id
+                ))
+            })?;
+        let auth_token = self.state.get_job_token(job_id).await?;
+        let mut client = BallistaClient::try_new(&executor.host, executor.port).await?;
+        let logs = client
+            .fetch_task_logs(
+                job_id,
+                stage_id as usize,
+                partition_id as usize,
+                max_bytes,
+                &auth_token,
+            )
+            .await?;
+        Ok((executor_id, logs))
+    }
+
+    /// Plans the query of `params` into stages like a job, without executing it, and
+    /// describes the stages with estimates of their output. Only queries are planned,
+    /// so that the tables and functions of the session are not changed, and the session
+    /// is not kept alive by planning.
+    pub(crate) async fn dry_run(
+        &self,
+        params: ExecuteQueryParams,
+    ) -> Result<PlanQueryResult, tonic::Status> {
+        let ExecuteQueryParams {
+            query,
+            settings,
+            session_id,
+        } = params;
+        let query =
+            query.ok_or_else(|| tonic::Status::invalid_argument("Missing query"))?;
+        let (ctx, config) = self.find_session(&session_id, &settings, false).await?;
+        let plan = match query {
+            Query::LogicalPlan(logical_plan) => {
+                (&logical_plan).try_into().map_err(|e| {
+                    tonic::Status::invalid_argument(format!(
+                        "Could not parse logical plan protobuf: {}",
+                        e
+                    ))
+                })?
+            }
+            // unlike sql(), this does not run the DDL statements
+            Query::Sql(sql) => ctx.create_logical_plan(&sql).map_err(|e| {
+                tonic::Status::invalid_argument(format!("Error parsing SQL: {}", e))
+            })?,
+        };
+        if matches!(
+            plan,
+            LogicalPlan::CreateExternalTable { .. }
+                | LogicalPlan::CreateFunction { .. }
+                | LogicalPlan::CopyTo { .. }
+        ) {
+            return Err(tonic::Status::invalid_argument(
+                "Only queries can be planned without running them",
+            ));
+        }
+
+        let datafusion_ctx = create_datafusion_context(&config);
+        let physical_plan = datafusion_ctx
+            .optimize(&plan)
+            .and_then(|plan| datafusion_ctx.create_physical_plan(&plan))
+            .map_err(|e| {
+                tonic::Status::invalid_argument(format!(
+                    "Could not create physical plan: {}",
+                    e
+                ))
+            })?;
+        let unsupported_nodes = find_unsupported_nodes(&physical_plan);
+        if !unsupported_nodes.is_empty() {
+            return Ok(PlanQueryResult {
+                stages: vec![],
+                unsupported_nodes,
+            });
+        }
+
+        let mut planner = DistributedPlanner::new()
+            .with_broadcast_threshold(config.broadcast_join_threshold())
+            .with_results_path(config.results_path().map(str::to_owned));
+        let stages = planner
+            .plan_query_stages("dry-run", physical_plan)
+            .map_err(|e| {
+                tonic::Status::invalid_argument(format!(
+                    "Could not plan query stages: {}",
+                    e
+                ))
+            })?;
+        Ok(PlanQueryResult {
+            stages: describe_query_stages(&stages),
+            unsupported_nodes: vec![],
+        })
+    }
+
+    /// Returns the context of the session and the configuration of a query of the
+    /// session, whose settings override the settings of the session. An empty session
+    /// id returns a new context.
+    async fn session(
+        &self,
+        session_id: &str,
+        settings: &[KeyValuePair],
+    ) -> Result<(ExecutionContext, BallistaConfig), tonic::Status> {
+        self.find_session(session_id, settings, true).await
+    }
+
+    /// Returns the context of the session and the configuration of a query like
+    /// [Self::session], extending the life of the session if `extend_life`
+    async fn find_session(
+        &self,
+        session_id: &str,
+        settings: &[KeyValuePair],
+        extend_life: bool,
+    ) -> Result<(ExecutionContext, BallistaConfig), tonic::Status> {
+        if session_id.is_empty() {
+            let config = parse_config(settings)?;
+            return Ok((create_datafusion_context(&config), config));
+        }
+        for expired in self.sessions.remove_expired() {
+            info!("Session {} expired", expired);
+            if let Err(e) = self.state.delete_session_metadata(&expired).await {
+                warn!("Could not remove session {}: {}", expired, e);
+            }
+        }
+
+        let session = if extend_life {
+            self.sessions.get(session_id)
+        } else {
+            self.sessions.peek(session_id)
+        };
+        let (ctx, session_settings) = match session {
+            Some(session) => session,
+            None => self.restore_session(session_id).await?,
+        };
+        if extend_life {
+            self.save_session(session_id, &session_settings).await?;
+        }
+        let mut all_settings = key_value_pairs(&session_settings);
+        all_settings.extend_from_slice(settings);
+        Ok((ctx, parse_config(&all_settings)?))
+    }
+
+    /// Restores a session that this scheduler does not have in memory from the state,
+    /// with its settings but without its tables and functions
+    async fn restore_session(
+        &self,
+        session_id: &str,
+    ) -> Result<(ExecutionContext, BTreeMap<String, String>), tonic::Status> {
+        let meta = self
+            .state
+            .get_session_metadata(session_id)
+            .await
+            .map_err(|e| {
+                let msg = format!("Could not read session metadata: {}", e);
+                error!("{}", msg);
+                tonic::Status::internal(msg)
+            })?;
+        let unknown =
+            || tonic::Status::not_found(format!("Unknown session {}", session_id));
+        let meta = match meta {
+            Some(meta) => meta,
+            None => return Err(unknown()),
+        };
+        let idle = Duration::from_millis(now_millis().saturating_sub(meta.last_used));
+        if idle >= self.sessions.ttl() {
+            info!("Session {} expired", session_id);
+            if let Err(e) = self.state.delete_session_metadata(session_id).await {
+                warn!("Could not remove session {}: {}", session_id, e);
+            }
+            return Err(unknown());
+        }
+
+        let config = parse_config(&meta.settings)?;
+        let settings = meta
+            .settings
+            .into_iter()
+            .map(|kv_pair| (kv_pair.key, kv_pair.value))
+            .collect();
+        self.sessions.restore(session_id, settings, &config);
+        warn!(
+            "Restored session {} without the tables and functions it registered",
+            session_id
+        );
+        self.sessions.get(session_id).ok_or_else(unknown)
+    }
+
+    /// Stores the settings of the session in the state, along with the time it was used
+    async fn save_session(
+        &self,
+        session_id: &str,
+        settings: &BTreeMap<String, String>,
+    ) -> Result<(), tonic::Status> {
+        let meta = SessionMetadata {
+            settings: key_value_pairs(settings),
+            last_used: now_millis(),
+        };
+        self.state
+            .save_session_metadata(session_id, &meta)
+            .await
+            .map_err(|e| {
+                let msg = format!("Could not save session metadata: {}", e);
+                error!("{}", msg);
+                tonic::Status::internal(msg)
+            })
+    }
+
+    /// Changes a setting for the later queries of the session, as requested by a `SET`
+    /// statement
+    async fn set_session_option(
+        &self,
+        session_id: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<(), tonic::Status> {
+        if !BallistaConfig::valid_entries().contains_key(key) {
+            return Err(tonic::Status::invalid_argument(format!(
+                "Unknown setting '{}'",
+                key
+            )));
+        }
+        BallistaConfig::builder()
+            .set(key, value)
+            .build()
+            .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+        let settings = self.sessions.set(session_id, key, value).ok_or_else(|| {
+            tonic::Status::not_found(format!("Unknown session {}", session_id))
+        })?;
+        info!("Set {} to {} in session {}", key, value, session_id);
+        self.save_session(session_id, &settings).await
+    }
+
+    /// Creates a job and returns its id. The job is queued until the namespace has the
+    /// capacity to run it, unless the quota rejects jobs over it, in which case the job
+    /// is admitted as running or rejected right away.
+    async fn queue_job(
+        &self,
+        config: &BallistaConfig,
+        submission: Option<&JobSubmission>,
+    ) -> Result<String, tonic::Status> {
+        // a job whose tasks no executor can run is rejected rather than left queued
+        let constraints = config.executor_constraints();
+        if !constraints.is_empty() {
+            let executors = self
+                .state
+                .get_alive_executors_metadata(
+                    self.state.cluster_config().executor_timeout(),
+                )
+                .await
+                .map_err(|e| {
+                    tonic::Status::internal(format!("Could not read executors: {}", e))
+                })?;
+            if !executors
+                .iter()
+                .any(|exec| satisfies_constraints(&exec.labels, &constraints))
+            {
+                let msg = format!(
+                    "No alive executor satisfies the executor constraints {:?}",
+                    constraints
+                );
+                warn!("Rejecting job: {}", msg);
+                return Err(tonic::Status::failed_precondition(msg));
+            }
+        }
+
+        if !self.quota.reject_over_quota {
+            let queued = job_status::Status::Queued(QueuedJob {});
+            return self.create_job(&constraints, submission, queued).await;
+        }
+        // the quota is checked and the job counted towards it under the global lock, so
+        // that concurrent submissions cannot take the same capacity
+        let mut lock = self.state.lock().await.map_err(|e| {
+            tonic::Status::internal(format!("Could not lock the scheduler state: {}", e))
+        })?;
+        let result = async {
+            let violation = self.state.check_quota(&self.quota).await.map_err(|e| {
+                let msg = format!("Could not check namespace quota: {}", e);
+                error!("{}", msg);
+                tonic::Status::internal(msg)
+            })?;
+            if let Some(reason) = violation {
+                warn!("Rejecting query: {}", reason);
+                return Err(tonic::Status::resource_exhausted(reason));
+            }
+            let running = job_status::Status::Running(RunningJob {});
+            self.create_job(&constraints, submission, running).await
+        }
+        .await;
+        lock.unlock().await;
+        result
+    }
+
+    /// Saves a new job with the given status and returns its id. The `submission` of the
+    /// job, if any, is saved before the job so that a restarted scheduler can plan any
+    /// job that it finds queued or running without stages.
+    async fn create_job(
+        &self,
+        constraints: &BTreeMap<String, String>,
+        submission: Option<&JobSubmission>,
+        status: job_status::Status,
+    ) -> Result<String, tonic::Status> {
+        let job_id: String = {
+            let mut rng = thread_rng();
+            std::iter::repeat(())
+                .map(|()| rng.sample(Alphanumeric))
+                .map(char::from)
+                .take(7)
+                .collect()
+        };
+
+        if self.shuffle_auth {
+            let token: String = {
+                let mut rng = thread_rng();
+                std::iter::repeat(())
+                    .map(|()| rng.sample(Alphanumeric))
+                    .map(char::from)
+                    .take(32)
+                    .collect()
+            };
+            self.state
+                .save_job_token(&job_id, &token)
+                .await
+                .map_err(|e| {
+                    tonic::Status::internal(format!("Could not save job token: {}", e))
+                })?;
+        }
+
+        if self.shuffle_encryption {
+            self.state
+                .save_job_key(&job_id, &ShuffleKey::generate())
+                .await
+                .map_err(|e| {
+                    tonic::Status::internal(format!("Could not save job key: {}", e))
+                })?;
+        }
+
+        if !constraints.is_empty() {
+            self.state
+                .save_job_constraints(&job_id, constraints)
+                .await
+                .map_err(|e| {
+                    tonic::Status::internal(format!(
+                        "Could not save executor constraints: {}",
+                        e
+                    ))
+                })?;
+        }
+
+        if let Some(submission) = submission {
+            self.state
+                .save_job_submission(&job_id, submission)
+                .await
+                .map_err(|e| {
+                    tonic::Status::internal(format!(
+                        "Could not save job submission: {}",
+                        e
+                    ))
+                })?;
+        }
+
+        // Save placeholder job metadata
+        self.state
+            .save_job_metadata(
+                &job_id,
+                &JobStatus {
+                    status: Some(status),
+                },
+            )
+            .await
+            .map_err(|e| {
+                tonic::Status::internal(format!("Could not save job metadata: {}", e))
+            })?;
+        self.state.metrics().record_job_submitted();
+
+        Ok(job_id)
+    }
+
+    /// Computes the load of the namespace requested by a KEDA `ScaledObject`
+    async fn scaler_metrics(
+        &self,
+        scaled_object: &ScaledObjectRef,
+    ) -> Result<ScalerMetrics, tonic::Status> {
+        let state = match scaler::requested_namespace(scaled_object) {
+            Some(namespace) => self.state.with_namespace(namespace),
+            None => self.state.as_ref().clone(),
+        };
+        let jobs = state.get_all_jobs().await.map_err(|e| {
+            let msg = format!("Error reading jobs: {}", e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        let tasks = state.get_all_tasks().await.map_err(|e| {
             let msg = format!("Error reading tasks: {}", e);
             error!("{}", msg);
             tonic::Status::internal(msg)
         })?;
-        let result = tasks.iter().any(|(_key, task)| {
-            !matches!(
-                task.status,
-                Some(task_status::Status::Completed(_))
-                    | Some(task_status::Status::Failed(_))
-            )
-        });
-        debug!("Are there active tasks? {}", result);
+        Ok(ScalerMetrics::new(&jobs, &tasks))
+    }
+}
+
+#[tonic::async_trait]
+impl ExternalScaler for SchedulerServer {
+    async fn is_active(
+        &self,
+        request: Request<ScaledObjectRef>,
+    ) -> Result<Response<IsActiveResponse>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Monitor])?;
+        let metrics = self.scaler_metrics(request.get_ref()).await?;
+        let result = metrics.is_active();
+        debug!("Are there active tasks or queued jobs? {}", result);
         Ok(Response::new(IsActiveResponse { result }))
     }
 
     async fn get_metric_spec(
         &self,
-        _request: Request<ScaledObjectRef>,
+        request: Request<ScaledObjectRef>,
     ) -> Result<Response<GetMetricSpecResponse>, tonic::Status> {
-        Ok(Response::new(GetMetricSpecResponse {
-            metric_specs: vec![MetricSpec {
-                metric_name: INFLIGHT_TASKS_METRIC_NAME.to_string(),
-                target_size: 1,
-            }],
-        }))
+        self.auth.authorize(&request, &[Role::Monitor])?;
+        let metric_specs = scaler::metric_specs(request.get_ref())
+            .map_err(tonic::Status::invalid_argument)?;
+        Ok(Response::new(GetMetricSpecResponse { metric_specs }))
     }
 
     async fn get_metrics(
         &self,
-        _request: Request<GetMetricsRequest>,
+        request: Request<GetMetricsRequest>,
     ) -> Result<Response<GetMetricsResponse>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Monitor])?;
+        let GetMetricsRequest {
+            scaled_object_ref,
+            metric_name,
+        } = request.into_inner();
+        let scaled_object = scaled_object_ref.ok_or_else(|| {
+            tonic::Status::invalid_argument("Missing scaled object reference")
+        })?;
+        let metrics = self.scaler_metrics(&scaled_object).await?;
+        let metric_value = metrics.value(&metric_name).ok_or_else(|| {
+            tonic::Status::invalid_argument(format!("Unknown metric {}", metric_name))
+        })?;
+        debug!("Reporting {} = {} to the scaler", metric_name, metric_value);
         Ok(Response::new(GetMetricsResponse {
             metric_values: vec![MetricValue {
-                metric_name: INFLIGHT_TASKS_METRIC_NAME.to_string(),
-                metric_value: 10000000, // A very high number to saturate the HPA
+                metric_name,
+                metric_value,
             }],
         }))
     }
@@ -174,13 +858,25 @@ impl SchedulerGrpc for SchedulerServer {
         &self,
         request: Request<PollWorkParams>,
     ) -> std::result::Result<Response<PollWorkResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Executor])?;
         if let PollWorkParams {
             metadata: Some(metadata),
             can_accept_task,
             task_status,
+            max_wait_ms,
+            shuffle_job_ids,
         } = request.into_inner()
         {
             debug!("Received poll_work request for {:?}", metadata);
+            let protocol_version = executor_protocol_version(metadata.protocol_version);
+            if !is_supported_protocol_version(protocol_version) {
+                let msg = format!(
+                    "Executor {} has protocol version {}, but the scheduler requires at least version {}",
+                    metadata.id, protocol_version, MIN_PROTOCOL_VERSION
+                );
+                warn!("{}", msg);
+                return Err(tonic::Status::failed_precondition(msg));
+            }
             let metadata: ExecutorMeta = ExecutorMeta {
                 id: metadata.id,
                 host: metadata
@@ -190,12 +886,15 @@ impl SchedulerGrpc for SchedulerServer {
                     })
                     .unwrap_or_else(|| self.caller_ip.to_string()),
                 port: metadata.port as u16,
+                labels: metadata
+                    .labels
+                    .into_iter()
+                    .map(|label| (label.key, label.value))
+                    .collect(),
+                flight_socket: Some(metadata.flight_socket)
+                    .filter(|path| !path.is_empty()),
+                protocol_version,
             };
-            let mut lock = self.state.lock().await.map_err(|e| {
-                let msg = format!("Could not lock the state: {}", e);
-                error!("{}", msg);
-                tonic::Status::internal(msg)
-            })?;
             self.state
                 .save_executor_metadata(metadata.clone())
                 .await
@@ -204,65 +903,95 @@ impl SchedulerGrpc for SchedulerServer {
                     error!("{}", msg);
                     tonic::Status::internal(msg)
                 })?;
-            for task_status in task_status {
-                self.state
-                    .save_task_status(&task_status)
-                    .await
-                    .map_err(|e| {
-                        let msg = format!("Could not save task status: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
-                    })?;
-            }
-            let task: Result<Option<_>, Status> = if can_accept_task {
-                let plan = self
-                    .state
-                    .assign_next_schedulable_task(&metadata.id)
-                    .await
-                    .map_err(|e| {
-                        let msg = format!("Error finding next assignable task: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
-                    })?;
-                if let Some((task, _plan)) = &plan {
-                    let partition_id = task.partition_id.as_ref().unwrap();
-                    info!(
-                        "Sending new task to {}: {}/{}/{}",
-                        metadata.id,
-                        partition_id.job_id,
-                        partition_id.stage_id,
-                        partition_id.partition_id
-                    );
+            // executors only report running tasks to report their progress
+            let (task_progress, task_status): (Vec<_>, Vec<_>) =
+                task_status.into_iter().partition(|status| {
+                    matches!(status.status, Some(task_status::Status::Running(_)))
+                });
+            // the statuses only need the locks of their jobs, which are taken in a fixed
+            // order so that concurrent polls cannot deadlock
+            let job_ids = task_status
+                .iter()
+                .chain(&task_progress)
+                .filter_map(|status| status.partition_id.as_ref())
+                .map(|partition_id| partition_id.job_id.clone())
+                .collect::<BTreeSet<_>>();
+            let mut job_locks = Vec::with_capacity(job_ids.len());
+            let saved: ballista_core::error::Result<()> = async {
+                for job_id in &job_ids {
+                    job_locks.push(self.state.lock_job(job_id).await?);
                 }
-                match plan {
-                    Some((status, plan)) => {
-                        let plan_clone = plan.clone();
-                        let output_partitioning = if let Some(shuffle_writer) =
-                            plan_clone.as_any().downcast_ref::<ShuffleWriterExec>()
-                        {
-                            shuffle_writer.shuffle_output_partitioning()
-                        } else {
-                            return Err(Status::invalid_argument(format!(
-                                "Task root plan was not a ShuffleWriterExec: {:?}",
-                                plan_clone
-                            )));
-                        };
-                        Ok(Some(TaskDefinition {
-                            plan: Some(plan.try_into().unwrap()),
-                            task_id: status.partition_id,
-                            output_partitioning: hash_partitioning_to_proto(
-                                output_partitioning,
-                            )
-                            .map_err(|_| Status::internal("TBD".to_string()))?,
-                        }))
+                self.state.save_task_statuses(&task_status).await?;
+                self.state.save_task_progress(&task_progress).await
+            }
+            .await;
+            for mut job_lock in job_locks {
+                job_lock.unlock().await;
+            }
+            saved.map_err(|e| {
+                let msg = format!("Could not save task statuses: {}", e);
+                error!("{}", msg);
+                tonic::Status::internal(msg)
+            })?;
+            let mut task = None;
+            if can_accept_task {
+                // the executor is considered dead if its polls are held for too long
+                let executor_timeout = self.state.cluster_config().executor_timeout();
+                let max_wait = Duration::from_millis(max_wait_ms)
+                    .min(self.max_poll_wait)
+                    .min(executor_timeout / 2);
+                let deadline = Instant::now() + max_wait;
+                loop {
+                    // created before looking for a task, so that the stages and statuses
+                    // saved in the meantime wake it
+                    let tasks_changed = self.state.tasks_changed();
+                    task = self.assign_task(&metadata, protocol_version).await?;
+                    let now = Instant::now();
+                    if task.is_some() || now >= deadline {
+                        break;
                     }
-                    None => Ok(None),
+                    let wait = (deadline - now).min(POLL_RECHECK_INTERVAL);
+                    let _ = tokio::time::timeout(wait, tasks_changed).await;
+                }
+            }
+            let pinned_job_ids = self.job_pins.pinned();
+            let shuffle_ttl = self.state.cluster_config().shuffle_ttl_seconds;
+            let mut expired_job_ids = vec![];
+            let shuffle_ttl_seconds = if protocol_version >= JOB_CLEANUP_PROTOCOL_VERSION
+            {
+                // the shuffle files expire the time to live after their job finished
+                if let Some(ttl) = shuffle_ttl.filter(|_| !shuffle_job_ids.is_empty()) {
+                    let pinned = pinned_job_ids.iter().cloned().collect();
+                    expired_job_ids = self
+                        .state
+                        .get_expired_jobs(
+                            &shuffle_job_ids,
+                            Duration::from_secs(ttl),
+                            &pinned,
+                        )
+                        .await
+                        .map_err(|e| {
+                            let msg = format!("Could not find the expired jobs: {}", e);
+                            error!("{}", msg);
+                            tonic::Status::internal(msg)
+                        })?;
                 }
+                0
+            } else if protocol_version < PINNED_JOBS_PROTOCOL_VERSION
+                && !pinned_job_ids.is_empty()
+            {
+                // older executors would delete the shuffle files of the pinned jobs
+                0
             } else {
-                Ok(None)
+                shuffle_ttl.unwrap_or_default()
             };
-            lock.unlock().await;
-            Ok(Response::new(PollWorkResult { task: task? }))
+            Ok(Response::new(PollWorkResult {
+                task,
+                shuffle_ttl_seconds,
+                protocol_version: PROTOCOL_VERSION,
+                pinned_job_ids,
+                expired_job_ids,
+            }))
         } else {
             warn!("Received invalid executor poll_work request");
             Err(tonic::Status::invalid_argument(
@@ -271,11 +1000,43 @@ impl SchedulerGrpc for SchedulerServer {
         }
     }
 
+    type GetTaskPlanStream = Pin<
+        Box<dyn Stream<Item = Result<TaskPlanChunk, Status>> + Send + Sync + 'static>,
+    >;
+
+    async fn get_task_plan(
+        &self,
+        request: Request<GetTaskPlanParams>,
+    ) -> std::result::Result<Response<Self::GetTaskPlanStream>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Executor])?;
+        let GetTaskPlanParams { task_id } = request.into_inner();
+        let task_id =
+            task_id.ok_or_else(|| tonic::Status::invalid_argument("Missing task id"))?;
+        let chunks = self.state.get_task_plan(&task_id).await.map_err(|e| {
+            let msg = format!("Could not get the plan of task {:?}: {}", task_id, e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        debug!(
+            "Streaming the plan of task {:?} in {} chunks",
+            task_id,
+            chunks.len()
+        );
+        let chunks = chunks.into_iter().map(|data| Ok(TaskPlanChunk { data }));
+        Ok(Response::new(Box::pin(futures::stream::iter(chunks))))
+    }
+
     async fn get_file_metadata(
         &self,
         request: Request<GetFileMetadataParams>,
     ) -> std::result::Result<Response<GetFileMetadataResult>, tonic::Status> {
-        let GetFileMetadataParams { path, file_type } = request.into_inner();
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let GetFileMetadataParams {
+            path,
+            file_type,
+            has_header,
+            delimiter,
+        } = request.into_inner();
 
         let file_type: FileType = file_type.try_into().map_err(|e| {
             let msg = format!("Error reading request: {}", e);
@@ -305,202 +1066,308 @@ impl SchedulerGrpc for SchedulerServer {
                         .collect(),
                 }))
             }
-            //TODO implement for CSV
+            FileType::Csv => {
+                let delimiter = match delimiter.as_bytes() {
+                    [] => b',',
+                    [delimiter] => *delimiter,
+                    _ => {
+                        let msg = format!("Invalid CSV delimiter '{}'", delimiter);
+                        return Err(tonic::Status::invalid_argument(msg));
+                    }
+                };
+                // compression is detected from the extension of each file
+                let options = CsvReadOptions::new()
+                    .has_header(has_header)
+                    .delimiter(delimiter);
+                let csv_exec = CsvExec::try_new(&path, options, None, 1024, None)
+                    .map_err(|e| {
+                        let msg = format!("Error opening csv files: {}", e);
+                        error!("{}", msg);
+                        tonic::Status::internal(msg)
+                    })?;
+
+                Ok(Response::new(GetFileMetadataResult {
+                    schema: Some(csv_exec.file_schema().as_ref().into()),
+                    partitions: csv_exec
+                        .filenames()
+                        .iter()
+                        .map(|filename| FilePartitionMetadata {
+                            filename: vec![filename.clone()],
+                        })
+                        .collect(),
+                }))
+            }
             _ => Err(tonic::Status::unimplemented(
                 "get_file_metadata unsupported file type",
             )),
         }
     }
 
+    async fn create_session(
+        &self,
+        request: Request<CreateSessionParams>,
+    ) -> std::result::Result<Response<CreateSessionResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let settings = request.into_inner().settings;
+        let config = parse_config(&settings)?;
+        let settings = settings
+            .into_iter()
+            .map(|kv_pair| (kv_pair.key, kv_pair.value))
+            .collect::<BTreeMap<_, _>>();
+        let session_id = self.sessions.create(settings.clone(), &config);
+        self.save_session(&session_id, &settings).await?;
+        info!("Created session {}", session_id);
+        Ok(Response::new(CreateSessionResult { session_id }))
+    }
+
     async fn execute_query(
         &self,
         request: Request<ExecuteQueryParams>,
     ) -> std::result::Result<Response<ExecuteQueryResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
         if let ExecuteQueryParams {
             query: Some(query),
             settings,
+            session_id,
         } = request.into_inner()
         {
-            // parse config
-            let mut config_builder = BallistaConfig::builder();
-            for kv_pair in &settings {
-                config_builder = config_builder.set(&kv_pair.key, &kv_pair.value);
-            }
-            let config = config_builder.build().map_err(|e| {
-                let msg = format!("Could not parse configs: {}", e);
-                error!("{}", msg);
-                tonic::Status::internal(msg)
-            })?;
+            let (mut ctx, config) = self.session(&session_id, &settings).await?;
 
+            let plan_size = match &query {
+                Query::LogicalPlan(logical_plan) => logical_plan.encoded_len(),
+                Query::Sql(sql) => sql.len(),
+            };
+            if let Some(reason) = self.quota.check_plan_size(plan_size) {
+                warn!("Rejecting query: {}", reason);
+                return Err(tonic::Status::resource_exhausted(reason));
+            }
+            let mut encoded_plan = None;
             let plan = match query {
                 Query::LogicalPlan(logical_plan) => {
                     // parse protobuf
-                    (&logical_plan).try_into().map_err(|e| {
+                    let plan = (&logical_plan).try_into().map_err(|e| {
                         let msg = format!("Could not parse logical plan protobuf: {}", e);
                         error!("{}", msg);
                         tonic::Status::internal(msg)
-                    })?
-                }
-                Query::Sql(sql) => {
-                    //TODO we can't just create a new context because we need a context that has
-                    // tables registered from previous SQL statements that have been executed
-                    let mut ctx = create_datafusion_context(&config);
-                    let df = ctx.sql(&sql).map_err(|e| {
-                        let msg = format!("Error parsing SQL: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
                     })?;
-                    df.to_logical_plan()
+                    encoded_plan = Some(logical_plan);
+                    plan
                 }
+                Query::Sql(sql) => match parse_set_statement(&sql) {
+                    // the job of a SET statement returns no rows, like the jobs of
+                    // other statements that only change the session
+                    Some((key, value)) if !session_id.is_empty() => {
+                        self.set_session_option(&session_id, &key, &value).await?;
+                        LogicalPlanBuilder::empty(false).build().map_err(|e| {
+                            tonic::Status::internal(format!("Could not plan SET: {}", e))
+                        })?
+                    }
+                    _ => {
+                        let df = ctx.sql(&sql).map_err(|e| {
+                            let msg = format!("Error parsing SQL: {}", e);
+                            error!("{}", msg);
+                            tonic::Status::internal(msg)
+                        })?;
+                        df.to_logical_plan()
+                    }
+                },
             };
             debug!("Received plan for execution: {:?}", plan);
-            let job_id: String = {
-                let mut rng = thread_rng();
-                std::iter::repeat(())
-                    .map(|()| rng.sample(Alphanumeric))
-                    .map(char::from)
-                    .take(7)
-                    .collect()
-            };
 
-            // Save placeholder job metadata
-            self.state
-                .save_job_metadata(
-                    &job_id,
-                    &JobStatus {
-                        status: Some(job_status::Status::Queued(QueuedJob {})),
-                    },
-                )
-                .await
-                .map_err(|e| {
-                    tonic::Status::internal(format!("Could not save job metadata: {}", e))
-                })?;
+            // plans that scan the in-memory tables of a session cannot be encoded, their
+            // jobs fail if the scheduler restarts before planning them
+            let encoded_plan = match encoded_plan {
+                Some(encoded_plan) => Some(encoded_plan),
+                None => {
+                    let encoded_plan: Result<LogicalPlanNode, _> = (&plan).try_into();
+                    encoded_plan
+                        .map_err(|e| {
+                            debug!("The job will not survive a scheduler restart: {}", e)
+                        })
+                        .ok()
+                }
+            };
+            let submission = encoded_plan.map(|plan| JobSubmission {
+                plan: Some(plan),
+                settings: config_settings(&config),
+            });
 
-            let state = self.state.clone();
-            let job_id_spawn = job_id.clone();
-            tokio::spawn(async move {
-                // create physical plan using DataFusion
+            // pre-flight check that the physical plan can be distributed, so that
+            // unsupported operators are reported to the client instead of failing the job
+            let physical_plan = if config.check_plan_serializable() {
                 let datafusion_ctx = create_datafusion_context(&config);
-                macro_rules! fail_job {
-                    ($code :expr) => {{
-                        match $code {
-                            Err(error) => {
-                                warn!("Job {} failed with {}", job_id_spawn, error);
-                                state
-                                    .save_job_metadata(
-                                        &job_id_spawn,
-                                        &JobStatus {
-                                            status: Some(job_status::Status::Failed(
-                                                FailedJob {
-                                                    error: format!("{}", error),
-                                                },
-                                            )),
-                                        },
-                                    )
-                                    .await
-                                    .unwrap();
-                                return;
-                            }
-                            Ok(value) => value,
-                        }
-                    }};
+                // planning errors are reported through the job status as before
+                let physical_plan = datafusion_ctx
+                    .optimize(&plan)
+                    .and_then(|plan| datafusion_ctx.create_physical_plan(&plan))
+                    .ok();
+                if let Some(physical_plan) = &physical_plan {
+                    let unsupported_nodes = find_unsupported_nodes(physical_plan);
+                    if !unsupported_nodes.is_empty() {
+                        warn!(
+                            "Rejecting query with unsupported operators: {:?}",
+                            unsupported_nodes
+                        );
+                        return Ok(Response::new(ExecuteQueryResult {
+                            job_id: String::new(),
+                            unsupported_nodes,
+                        }));
+                    }
                 }
+                physical_plan
+            } else {
+                None
+            };
 
-                let start = Instant::now();
+            let job_id = self.queue_job(&config, submission.as_ref()).await?;
+            tokio::spawn(run_job(
+                self.state.clone(),
+                self.quota.clone(),
+                config,
+                job_id.clone(),
+                plan,
+                physical_plan,
+            ));
 
-                let optimized_plan =
-                    fail_job!(datafusion_ctx.optimize(&plan).map_err(|e| {
-                        let msg =
-                            format!("Could not create optimized logical plan: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
-                    }));
+            Ok(Response::new(ExecuteQueryResult {
+                job_id,
+                unsupported_nodes: vec![],
+            }))
+        } else {
+            Err(tonic::Status::internal("Error parsing request"))
+        }
+    }
 
-                debug!("Calculated optimized plan: {:?}", optimized_plan);
+    async fn execute_query_stream(
+        &self,
+        request: Request<tonic::Streaming<ExecuteQueryChunk>>,
+    ) -> std::result::Result<Response<ExecuteQueryResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let metadata = request.metadata().clone();
+        let mut chunks = request.into_inner();
+        let mut compression = None;
+        let mut data = vec![];
+        while let Some(chunk) = chunks.message().await? {
+            compression.get_or_insert(chunk.compression);
+            data.extend_from_slice(&chunk.data);
+            // plans that are too large are rejected before they are received entirely
+            if let Some(reason) = self.quota.check_plan_size(data.len()) {
+                warn!("Rejecting query: {}", reason);
+                return Err(tonic::Status::resource_exhausted(reason));
+            }
+        }
+        let compression = PlanCompression::from_i32(compression.unwrap_or_default())
+            .ok_or_else(|| tonic::Status::invalid_argument("Unknown plan compression"))?;
+        // the limit applies to the decompressed plan too, which is only decompressed
+        // up to the first byte past the limit
+        let decompressed = decompress(compression, &data, self.quota.max_plan_size)
+            .map_err(|e| {
+                let msg = format!("Could not decompress the streamed query: {}", e);
+                error!("{}", msg);
+                tonic::Status::invalid_argument(msg)
+            })?;
+        if let Some(reason) = self.quota.check_plan_size(decompressed.len()) {
+            warn!("Rejecting query: {}", reason);
+            return Err(tonic::Status::resource_exhausted(reason));
+        }
+        let params =
+            ExecuteQueryParams::decode(decompressed.as_slice()).map_err(|e| {
+                let msg = format!("Could not decode the streamed query: {}", e);
+                error!("{}", msg);
+                tonic::Status::invalid_argument(msg)
+            })?;
+        debug!(
+            "Received a query of {} bytes, streamed as {} bytes",
+            params.encoded_len(),
+            data.len()
+        );
 
-                let plan = fail_job!(datafusion_ctx
-                    .create_physical_plan(&optimized_plan)
-                    .map_err(|e| {
-                        let msg = format!("Could not create physical plan: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
-                    }));
+        // the query is authorized like the chunks that carried it
+        let mut request = Request::new(params);
+        *request.metadata_mut() = metadata;
+        self.execute_query(request).await
+    }
 
-                info!(
-                    "DataFusion created physical plan in {} milliseconds",
-                    start.elapsed().as_millis(),
-                );
+    async fn plan_query(
+        &self,
+        request: Request<ExecuteQueryParams>,
+    ) -> std::result::Result<Response<PlanQueryResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let result = self.dry_run(request.into_inner()).await?;
+        Ok(Response::new(result))
+    }
 
-                // create distributed physical plan using Ballista
-                if let Err(e) = state
-                    .save_job_metadata(
-                        &job_id_spawn,
-                        &JobStatus {
-                            status: Some(job_status::Status::Running(RunningJob {})),
-                        },
-                    )
-                    .await
-                {
-                    warn!(
-                        "Could not update job {} status to running: {}",
-                        job_id_spawn, e
-                    );
-                }
-                let mut planner = DistributedPlanner::new();
-                let stages = fail_job!(planner
-                    .plan_query_stages(&job_id_spawn, plan)
-                    .map_err(|e| {
-                        let msg = format!("Could not plan query stages: {}", e);
-                        error!("{}", msg);
-                        tonic::Status::internal(msg)
-                    }));
-
-                // save stages into state
-                for shuffle_writer in stages {
-                    fail_job!(state
-                        .save_stage_plan(
-                            &job_id_spawn,
-                            shuffle_writer.stage_id(),
-                            shuffle_writer.clone()
-                        )
-                        .await
-                        .map_err(|e| {
-                            let msg = format!("Could not save stage plan: {}", e);
-                            error!("{}", msg);
-                            tonic::Status::internal(msg)
-                        }));
-                    let num_partitions =
-                        shuffle_writer.output_partitioning().partition_count();
-                    for partition_id in 0..num_partitions {
-                        let pending_status = TaskStatus {
-                            partition_id: Some(PartitionId {
-                                job_id: job_id_spawn.clone(),
-                                stage_id: shuffle_writer.stage_id() as u32,
-                                partition_id: partition_id as u32,
-                            }),
-                            status: None,
-                        };
-                        fail_job!(state.save_task_status(&pending_status).await.map_err(
-                            |e| {
-                                let msg = format!("Could not save task status: {}", e);
-                                error!("{}", msg);
-                                tonic::Status::internal(msg)
-                            }
-                        ));
+    async fn execute_statements(
+        &self,
+        request: Request<ExecuteStatementsParams>,
+    ) -> std::result::Result<Response<ExecuteStatementsResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let ExecuteStatementsParams {
+            session_id,
+            statements,
+            settings,
+        } = request.into_inner();
+        validate_statements(&statements).map_err(|e| {
+            warn!("Rejecting statements: {}", e);
+            tonic::Status::invalid_argument(e)
+        })?;
+        for statement in &statements {
+            if let Some(reason) = self.quota.check_plan_size(statement.sql.len()) {
+                warn!("Rejecting statements: {}", reason);
+                return Err(tonic::Status::resource_exhausted(reason));
+            }
+        }
+
+        let (ctx, config) = self.session(&session_id, &settings).await?;
+        let mut jobs = Vec::with_capacity(statements.len());
+        for _ in &statements {
+            jobs.push(self.queue_job(&config, None).await?);
+        }
+        let job_ids = jobs.clone();
+
+        // each statement waits for the statements it depends on, so that independent
+        // statements run concurrently
+        let mut named: HashMap<String, Shared<BoxFuture<'static, Result<(), String>>>> =
+            HashMap::new();
+        for (statement, job_id) in statements.into_iter().zip(jobs) {
+            let dependencies = statement
+                .depends_on
+                .iter()
+                .map(|name| (name.clone(), named[name].clone()))
+                .collect::<Vec<_>>();
+            let name = statement.name.clone();
+            let state = self.state.clone();
+            let quota = self.quota.clone();
+            let config = config.clone();
+            let ctx = ctx.clone();
+            let job_pins = self.job_pins.clone();
+            let future = async move {
+                for (name, dependency) in dependencies {
+                    if let Err(e) = dependency.await {
+                        let error = format!("Statement '{}' failed: {}", name, e);
+                        save_job_failure(&state, &job_id, &error).await;
+                        return Err(error);
                     }
                 }
-            });
-
-            Ok(Response::new(ExecuteQueryResult { job_id }))
-        } else {
-            Err(tonic::Status::internal("Error parsing request"))
+                run_statement(state, quota, config, ctx, job_pins, job_id, statement)
+                    .await
+            }
+            .boxed()
+            .shared();
+            if !name.is_empty() {
+                named.insert(name, future.clone());
+            }
+            tokio::spawn(future);
         }
+
+        Ok(Response::new(ExecuteStatementsResult { job_ids }))
     }
 
     async fn get_job_status(
         &self,
         request: Request<GetJobStatusParams>,
     ) -> std::result::Result<Response<GetJobStatusResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
         let job_id = request.into_inner().job_id;
         debug!("Received get_job_status request for job {}", job_id);
         let job_meta = self.state.get_job_metadata(&job_id).await.map_err(|e| {
@@ -512,30 +1379,590 @@ impl SchedulerGrpc for SchedulerServer {
             status: Some(job_meta),
         }))
     }
+
+    async fn get_job_events(
+        &self,
+        request: Request<GetJobEventsParams>,
+    ) -> std::result::Result<Response<GetJobEventsResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let job_id = request.into_inner().job_id;
+        debug!("Received get_job_events request for job {}", job_id);
+        let events = self.state.get_job_events(&job_id).await.map_err(|e| {
+            let msg = format!("Error reading job events: {}", e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        Ok(Response::new(GetJobEventsResult { events }))
+    }
+
+    async fn get_task_logs(
+        &self,
+        request: Request<GetTaskLogsParams>,
+    ) -> std::result::Result<Response<GetTaskLogsResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Submitter])?;
+        let GetTaskLogsParams { task_id, max_bytes } = request.into_inner();
+        let task_id =
+            task_id.ok_or_else(|| tonic::Status::invalid_argument("Missing task id"))?;
+        debug!("Received get_task_logs request for task {:?}", task_id);
+        let (executor_id, logs) = self
+            .task_logs(
+                &task_id.job_id,
+                task_id.stage_id,
+                task_id.partition_id,
+                max_bytes,
+            )
+            .await
+            .map_err(|e| {
+                let msg = format!("Error fetching task logs: {}", e);
+                warn!("{}", msg);
+                tonic::Status::unavailable(msg)
+            })?;
+        Ok(Response::new(GetTaskLogsResult { executor_id, logs }))
+    }
+
+    async fn get_broadcasts(
+        &self,
+        request: Request<GetBroadcastsParams>,
+    ) -> std::result::Result<Response<GetBroadcastsResult>, tonic::Status> {
+        self.auth.authorize(&request, &[Role::Executor])?;
+        let GetBroadcastsParams { job_id } = request.into_inner();
+        let broadcasts = self.state.get_job_broadcasts(&job_id).await.map_err(|e| {
+            let msg =
+                format!("Could not get the broadcast variables of {}: {}", job_id, e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        debug!(
+            "Sending {} broadcast variables of job {}",
+            broadcasts.len(),
+            job_id
+        );
+        Ok(Response::new(GetBroadcastsResult {
+            broadcasts: broadcasts
+                .into_iter()
+                .map(|(name, data)| BroadcastVariable { name, data })
+                .collect(),
+        }))
+    }
+}
+
+/// Returns the executor that the task of the job events ran on last
+fn last_task_executor(
+    events: &[JobEvent],
+    stage_id: u32,
+    partition_id: u32,
+) -> Option<String> {
+    let is_task = |id: &Option<PartitionId>| {
+        id.as_ref().map_or(false, |id| {
+            id.stage_id == stage_id && id.partition_id == partition_id
+        })
+    };
+    events
+        .iter()
+        .filter_map(|event| match &event.event {
+            Some(job_event::Event::TaskStatus(status))
+                if is_task(&status.partition_id) =>
+            {
+                match &status.status {
+                    Some(task_status::Status::Running(running)) => {
+                        Some(running.executor_id.clone())
+                    }
+                    Some(task_status::Status::Completed(completed)) => {
+                        Some(completed.executor_id.clone())
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .last()
+}
+
+/// Plans the job and saves its stages, whose tasks are then handed out to the executors
+async fn run_job(
+    state: Arc<SchedulerState>,
+    quota: NamespaceQuota,
+    config: BallistaConfig,
+    job_id: String,
+    plan: LogicalPlan,
+    physical_plan: Option<Arc<dyn ExecutionPlan>>,
+) {
+    // wait until the namespace has the capacity to run this job
+    if !quota.reject_over_quota {
+        if let Err(e) = wait_for_quota(&state, &quota, &job_id).await {
+            save_job_failure(&state, &job_id, &e.to_string()).await;
+            return;
+        }
+    }
+
+    // create physical plan using DataFusion
+    let datafusion_ctx = create_datafusion_context(&config);
+    macro_rules! fail_job {
+        ($code :expr) => {{
+            match $code {
+                Err(error) => {
+                    warn!("Job {} failed with {}", job_id, error);
+                    state
+                        .save_job_metadata(
+                            &job_id,
+                            &JobStatus {
+                                status: Some(job_status::Status::Failed(FailedJob {
+                                    error: format!("{}", error),
+                                })),
+                            },
+                        )
+                        .await
+                        .unwrap();
+                    delete_job_submission(&state, &job_id).await;
+                    return;
+                }
+                Ok(value) => value,
+            }
+        }};
+    }
+
+    let plan = match physical_plan {
+        // already planned by the serializability check
+        Some(physical_plan) => physical_plan,
+        None => {
+            let start = Instant::now();
+
+            let optimized_plan = fail_job!(datafusion_ctx.optimize(&plan).map_err(|e| {
+                let msg = format!("Could not create optimized logical plan: {}", e);
+                error!("{}", msg);
+                tonic::Status::internal(msg)
+            }));
+
+            debug!("Calculated optimized plan: {:?}", optimized_plan);
+
+            let plan = fail_job!(datafusion_ctx
+                .create_physical_plan(&optimized_plan)
+                .map_err(|e| {
+                    let msg = format!("Could not create physical plan: {}", e);
+                    error!("{}", msg);
+                    tonic::Status::internal(msg)
+                }));
+
+            info!(
+                "DataFusion created physical plan in {} milliseconds",
+                start.elapsed().as_millis(),
+            );
+            plan
+        }
+    };
+
+    // create distributed physical plan using Ballista
+    if let Err(e) = state
+        .save_job_metadata(
+            &job_id,
+            &JobStatus {
+                status: Some(job_status::Status::Running(RunningJob {})),
+            },
+        )
+        .await
+    {
+        warn!("Could not update job {} status to running: {}", job_id, e);
+    }
+    // save the broadcast variables for the executors before any task runs
+    fail_job!(save_broadcast_variables(&state, &quota, &job_id, &plan)
+        .await
+        .map_err(|e| {
+            let msg = format!("Could not save broadcast variables: {}", e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        }));
+    let scheduling_start = Instant::now();
+    let mut planner = DistributedPlanner::new()
+        .with_broadcast_threshold(config.broadcast_join_threshold())
+        .with_results_path(config.results_path().map(str::to_owned));
+    let stages = fail_job!(planner.plan_query_stages(&job_id, plan).map_err(|e| {
+        let msg = format!("Could not plan query stages: {}", e);
+        error!("{}", msg);
+        tonic::Status::internal(msg)
+    }));
+
+    fail_job!(check_stage_plan_sizes(&quota, &stages));
+
+    // save stages and their pending tasks into state
+    fail_job!(state.save_stages(&job_id, &stages).await.map_err(|e| {
+        let msg = format!("Could not save stages: {}", e);
+        error!("{}", msg);
+        tonic::Status::internal(msg)
+    }));
+    // the job no longer needs to be planned if the scheduler restarts
+    delete_job_submission(&state, &job_id).await;
+    state
+        .metrics()
+        .record_stage_scheduling(scheduling_start.elapsed());
+}
+
+async fn delete_job_submission(state: &SchedulerState, job_id: &str) {
+    if let Err(e) = state.delete_job_submission(job_id).await {
+        warn!("Could not delete the submission of job {}: {}", job_id, e);
+    }
+}
+
+/// Plans again the jobs whose stages were not saved when the scheduler stopped, which
+/// must be called once when the scheduler starts, before it accepts queries. Jobs that
+/// were submitted without an encodable plan, such as the statements of sessions, fail
+/// instead. Returns the number of jobs that are planned again.
+///
+/// Only one scheduler of a namespace may recover its jobs, since a job that another
+/// scheduler is planning would be planned twice.
+pub async fn recover_jobs(
+    state: Arc<SchedulerState>,
+    quota: NamespaceQuota,
+) -> ballista_core::error::Result<usize> {
+    let mut submissions = state.get_job_submissions().await?;
+    let mut recovered = 0;
+    for (job_key, status) in state.get_all_jobs().await? {
+        let job_id = match job_key.rsplit('/').next() {
+            Some(job_id) => job_id.to_owned(),
+            None => continue,
+        };
+        let submission = submissions.remove(&job_id);
+        let queued = match status.status {
+            Some(job_status::Status::Queued(_)) => true,
+            // the stages of a running job are saved once its submission is deleted
+            Some(job_status::Status::Running(_)) => submission.is_some(),
+            _ => false,
+        };
+        if !queued {
+            if submission.is_some() {
+                delete_job_submission(&state, &job_id).await;
+            }
+            continue;
+        }
+
+        let plan = submission.as_ref().and_then(|submission| {
+            let plan = submission.plan.as_ref()?;
+            let config = parse_config(&submission.settings).ok()?;
+            let plan: LogicalPlan = plan.try_into().ok()?;
+            Some((plan, config))
+        });
+        let (plan, config) = match plan {
+            Some(plan) => plan,
+            None => {
+                warn!("Job {} cannot be planned again after a restart", job_id);
+                save_job_failure(
+                    &state,
+                    &job_id,
+                    "The scheduler restarted before the job was planned",
+                )
+                .await;
+                delete_job_submission(&state, &job_id).await;
+                continue;
+            }
+        };
+        info!("Planning job {} again after a restart", job_id);
+        // the job waits for the capacity of the namespace again
+        state
+            .save_job_metadata(
+                &job_id,
+                &JobStatus {
+                    status: Some(job_status::Status::Queued(QueuedJob {})),
+                },
+            )
+            .await?;
+        tokio::spawn(run_job(
+            state.clone(),
+            quota.clone(),
+            config,
+            job_id,
+            plan,
+            None,
+        ));
+        recovered += 1;
+    }
+    // the submissions of jobs that were never queued were not acknowledged to clients
+    for job_id in submissions.keys() {
+        delete_job_submission(&state, job_id).await;
+    }
+    Ok(recovered)
+}
+
+/// Runs the SQL statement as the job `job_id`, with the tables of the session. The
+/// result of a named statement is registered as a table of the session once the job
+/// completed, and the job is pinned in `job_pins` until the table is dropped.
+async fn run_statement(
+    state: Arc<SchedulerState>,
+    quota: NamespaceQuota,
+    config: BallistaConfig,
+    mut ctx: ExecutionContext,
+    job_pins: JobPins,
+    job_id: String,
+    statement: SessionStatement,
+) -> Result<(), String> {
+    let plan = match ctx.sql(&statement.sql) {
+        Ok(df) => df.to_logical_plan(),
+        Err(e) => {
+            let error = format!("Error parsing SQL: {}", e);
+            save_job_failure(&state, &job_id, &error).await;
+            return Err(error);
+        }
+    };
+    run_job(state.clone(), quota, config, job_id.clone(), plan, None).await;
+    let completed = wait_for_job(&state, &job_id).await?;
+
+    if !statement.name.is_empty() {
+        let table = job_result_table(completed)
+            .map_err(|e| format!("Could not read the result of job {}: {}", job_id, e))?
+            .with_pin(job_pins.pin(&job_id));
+        ctx.register_table(statement.name.as_str(), Arc::new(table))
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Registered the result of job {} as table '{}'",
+            job_id, statement.name
+        );
+    }
+    Ok(())
+}
+
+fn job_result_table(
+    completed: CompletedJob,
+) -> ballista_core::error::Result<JobResultTable> {
+    let schema: Schema = completed
+        .schema
+        .as_ref()
+        .ok_or_else(|| BallistaError::General("Job has no schema".to_owned()))?
+        .try_into()?;
+    let partition_locations = completed
+        .partition_location
+        .into_iter()
+        .map(|location| location.try_into())
+        .collect::<ballista_core::error::Result<Vec<PartitionLocation>>>()?;
+    Ok(JobResultTable::new(Arc::new(schema), partition_locations))
+}
+
+/// Polls the status of the job until it completed or failed
+async fn wait_for_job(
+    state: &SchedulerState,
+    job_id: &str,
+) -> Result<CompletedJob, String> {
+    loop {
+        let status = state
+            .get_job_metadata(job_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        match status.status {
+            Some(job_status::Status::Completed(completed)) => return Ok(completed),
+            Some(job_status::Status::Failed(failed)) => return Err(failed.error),
+            _ => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+async fn save_job_failure(state: &SchedulerState, job_id: &str, error: &str) {
+    warn!("Job {} failed with {}", job_id, error);
+    let failed = JobStatus {
+        status: Some(job_status::Status::Failed(FailedJob {
+            error: error.to_owned(),
+        })),
+    };
+    if let Err(e) = state.save_job_metadata(job_id, &failed).await {
+        error!("Could not save job metadata: {}", e);
+    }
+}
+
+fn key_value_pairs(settings: &BTreeMap<String, String>) -> Vec<KeyValuePair> {
+    settings
+        .iter()
+        .map(|(key, value)| KeyValuePair {
+            key: key.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+/// The settings of `config`, to plan the query of a job again with the same settings
+fn config_settings(config: &BallistaConfig) -> Vec<KeyValuePair> {
+    let settings = config
+        .settings()
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    key_value_pairs(&settings)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn parse_config(settings: &[KeyValuePair]) -> Result<BallistaConfig, tonic::Status> {
+    let mut config_builder = BallistaConfig::builder();
+    for kv_pair in settings {
+        config_builder = config_builder.set(&kv_pair.key, &kv_pair.value);
+    }
+    config_builder.build().map_err(|e| {
+        let msg = format!("Could not parse configs: {}", e);
+        error!("{}", msg);
+        tonic::Status::internal(msg)
+    })
+}
+
+/// Waits until the namespace has the capacity to run another job and then marks the
+/// job as running, so that it counts towards the quota of subsequent jobs
+async fn wait_for_quota(
+    state: &SchedulerState,
+    quota: &NamespaceQuota,
+    job_id: &str,
+) -> ballista_core::error::Result<()> {
+    let mut prev_reason = None;
+    loop {
+        let mut lock = state.lock().await?;
+        let violation = state.check_quota(quota).await;
+        let reason = match violation {
+            Ok(None) => {
+                let result = state
+                    .save_job_metadata(
+                        job_id,
+                        &JobStatus {
+                            status: Some(job_status::Status::Running(RunningJob {})),
+                        },
+                    )
+                    .await;
+                lock.unlock().await;
+                return result;
+            }
+            Ok(Some(reason)) => reason,
+            Err(e) => {
+                lock.unlock().await;
+                return Err(e);
+            }
+        };
+        lock.unlock().await;
+        if prev_reason.as_ref() != Some(&reason) {
+            info!("Job {} is queued: {}", job_id, reason);
+            prev_reason = Some(reason);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Saves the batches of the broadcast variables in `plan`, which every executor fetches
+/// before it runs the first task of the job, so that executors that register after the
+/// job was submitted have them too. Fails before saving anything if a broadcast variable
+/// exceeds the inline limits of the quota.
+async fn save_broadcast_variables(
+    state: &SchedulerState,
+    quota: &NamespaceQuota,
+    job_id: &str,
+    plan: &Arc<dyn ExecutionPlan>,
+) -> ballista_core::error::Result<()> {
+    let mut broadcasts = HashMap::new();
+    collect_broadcasts(plan, &mut broadcasts);
+    if broadcasts.is_empty() {
+        return Ok(());
+    }
+    for broadcast in broadcasts.values() {
+        if let Some(batches) = broadcast.batches() {
+            let num_rows = batches.iter().map(|batch| batch.num_rows()).sum();
+            let num_bytes = batches
+                .iter()
+                .flat_map(|batch| batch.columns())
+                .map(|array| array.get_array_memory_size())
+                .sum();
+            if let Some(msg) =
+                quota.check_inline_size(broadcast.name(), num_rows, num_bytes)
+            {
+                return Err(BallistaError::General(msg));
+            }
+        }
+    }
+
+    let mut encoded = vec![];
+    for broadcast in broadcasts.values() {
+        if let Some(batches) = broadcast.batches() {
+            let data = encode_batches(&broadcast.broadcast_schema(), &batches)?;
+            encoded.push((broadcast.name().to_owned(), data));
+        }
+    }
+    debug!(
+        "Saving {} broadcast variables of job {}",
+        encoded.len(),
+        job_id
+    );
+    state.save_job_broadcasts(job_id, encoded).await
+}
+
+/// Fails if the encoded plan of a stage exceeds the limit of the quota
+fn check_stage_plan_sizes(
+    quota: &NamespaceQuota,
+    stages: &[Arc<ShuffleWriterExec>],
+) -> Result<(), tonic::Status> {
+    if quota.max_stage_plan_size.is_none() {
+        return Ok(());
+    }
+    for stage in stages {
+        let plan: Arc<dyn ExecutionPlan> = stage.clone();
+        let plan: PhysicalPlanNode = plan.try_into().map_err(|e| {
+            let msg = format!("Could not serialize stage plan: {}", e);
+            error!("{}", msg);
+            tonic::Status::internal(msg)
+        })?;
+        if let Some(msg) =
+            quota.check_stage_plan_size(stage.stage_id(), plan.encoded_len())
+        {
+            return Err(tonic::Status::resource_exhausted(msg));
+        }
+    }
+    Ok(())
+}
+
+/// Collects the broadcast variables in `plan`, by name
+fn collect_broadcasts(
+    plan: &Arc<dyn ExecutionPlan>,
+    broadcasts: &mut HashMap<String, BroadcastExec>,
+) {
+    if let Some(broadcast) = plan.as_any().downcast_ref::<BroadcastExec>() {
+        broadcasts
+            .entry(broadcast.name().to_owned())
+            .or_insert_with(|| broadcast.clone());
+    }
+    for child in plan.children() {
+        collect_broadcasts(&child, broadcasts);
+    }
 }
 
 /// Create a DataFusion context that is compatible with Ballista
 pub fn create_datafusion_context(config: &BallistaConfig) -> ExecutionContext {
-    let config =
-        ExecutionConfig::new().with_concurrency(config.default_shuffle_partitions());
+    let config = ExecutionConfig::new()
+        .with_concurrency(config.default_shuffle_partitions())
+        .with_hash_scheme(config.shuffle_hash_scheme());
     ExecutionContext::with_config(config)
 }
 
 #[cfg(all(test, feature = "sled"))]
 mod test {
     use std::{
+        collections::BTreeMap,
+        convert::TryInto,
         net::{IpAddr, Ipv4Addr},
         sync::Arc,
+        time::{Duration, Instant},
     };
 
     use tonic::Request;
 
+    use ballista_core::config::BallistaConfig;
     use ballista_core::error::BallistaError;
     use ballista_core::serde::protobuf::{
-        executor_registration::OptionalHost, ExecutorRegistration, PollWorkParams,
+        execute_query_params::Query, executor_registration::OptionalHost, job_event,
+        job_status, task_status, CompletedJob, CompletedTask, ExecuteQueryParams,
+        ExecutorRegistration, FailedTask, FileType, GetFileMetadataParams, JobEvent,
+        JobStatus, JobSubmission, KeyValuePair, PartitionId, PollWorkParams, QueuedJob,
+        RunningTask, TaskStatus,
     };
+    use ballista_core::serde::protocol::PROTOCOL_VERSION;
+    use datafusion::logical_plan::LogicalPlanBuilder;
 
     use super::{
+        last_task_executor,
+        quota::NamespaceQuota,
+        recover_jobs,
         state::{SchedulerState, StandaloneClient},
         SchedulerGrpc, SchedulerServer,
     };
@@ -554,11 +1981,19 @@ mod test {
             id: "abc".to_owned(),
             optional_host: Some(OptionalHost::Host("".to_owned())),
             port: 0,
+            labels: vec![KeyValuePair {
+                key: "gpu".to_owned(),
+                value: "true".to_owned(),
+            }],
+            flight_socket: "/tmp/abc.sock".to_owned(),
+            protocol_version: 0,
         };
         let request: Request<PollWorkParams> = Request::new(PollWorkParams {
             metadata: Some(exec_meta.clone()),
             can_accept_task: false,
             task_status: vec![],
+            max_wait_ms: 0,
+            shuffle_job_ids: vec![],
         });
         let response = scheduler
             .poll_work(request)
@@ -567,13 +2002,27 @@ mod test {
             .into_inner();
         // no response task since we told the scheduler we didn't want to accept one
         assert!(response.task.is_none());
-        // executor should be registered
-        assert_eq!(state.get_executors_metadata().await.unwrap().len(), 1);
+        // executor should be registered with its labels and socket
+        let executors = state.get_executors_metadata().await.unwrap();
+        assert_eq!(executors.len(), 1);
+        assert_eq!(
+            executors[0].0.labels.get("gpu").map(|v| v.as_str()),
+            Some("true")
+        );
+        assert_eq!(
+            executors[0].0.flight_socket.as_deref(),
+            Some("/tmp/abc.sock")
+        );
+        // executors that do not report a protocol version speak the first one
+        assert_eq!(executors[0].0.protocol_version, 1);
+        assert_eq!(response.protocol_version, PROTOCOL_VERSION);
 
         let request: Request<PollWorkParams> = Request::new(PollWorkParams {
             metadata: Some(exec_meta.clone()),
             can_accept_task: true,
             task_status: vec![],
+            max_wait_ms: 0,
+            shuffle_job_ids: vec![],
         });
         let response = scheduler
             .poll_work(request)
@@ -586,4 +2035,234 @@ mod test {
         assert_eq!(state.get_executors_metadata().await.unwrap().len(), 1);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_poll_work_held_until_timeout() -> Result<(), BallistaError> {
+        let scheduler = SchedulerServer::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        )
+        .with_max_poll_wait(Duration::from_millis(300));
+        let poll = |max_wait_ms| {
+            Request::new(PollWorkParams {
+                metadata: Some(ExecutorRegistration {
+                    id: "abc".to_owned(),
+                    optional_host: Some(OptionalHost::Host("".to_owned())),
+                    port: 0,
+                    labels: vec![],
+                    flight_socket: String::new(),
+                    protocol_version: PROTOCOL_VERSION,
+                }),
+                can_accept_task: true,
+                task_status: vec![],
+                max_wait_ms,
+                shuffle_job_ids: vec![],
+            })
+        };
+
+        // the poll is held for the requested time when there is no task
+        let start = Instant::now();
+        let response = scheduler.poll_work(poll(100)).await.unwrap().into_inner();
+        assert!(response.task.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // but no longer than the maximum wait of the scheduler
+        let start = Instant::now();
+        let response = scheduler
+            .poll_work(poll(60_000))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.task.is_none());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_secs(10));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_only_plans_queries() -> Result<(), BallistaError> {
+        let scheduler = SchedulerServer::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        );
+        let session_id = scheduler
+            .sessions
+            .create(BTreeMap::new(), &BallistaConfig::new()?);
+        let params = ExecuteQueryParams {
+            query: Some(Query::Sql(
+                "CREATE EXTERNAL TABLE t (a INT) STORED AS CSV LOCATION '/tmp/t.csv'"
+                    .to_owned(),
+            )),
+            settings: vec![],
+            session_id: session_id.clone(),
+        };
+        let status = scheduler.dry_run(params).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let (ctx, _) = scheduler.sessions.get(&session_id).unwrap();
+        assert!(ctx.table("t").is_err());
+        // planning does not keep the session alive
+        assert!(scheduler
+            .state
+            .get_session_metadata(&session_id)
+            .await?
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_jobs_over_quota() -> Result<(), BallistaError> {
+        let scheduler = SchedulerServer::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        )
+        .with_quota(NamespaceQuota {
+            max_concurrent_jobs: Some(1),
+            reject_over_quota: true,
+            ..Default::default()
+        });
+        let config = BallistaConfig::new()?;
+        // an admitted job counts towards the quota before it is planned
+        let job_id = scheduler.queue_job(&config, None).await.unwrap();
+        assert!(matches!(
+            scheduler.state.get_job_metadata(&job_id).await?.status,
+            Some(job_status::Status::Running(_))
+        ));
+        let status = scheduler.queue_job(&config, None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(scheduler.state.get_all_jobs().await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_csv_file_metadata() -> Result<(), BallistaError> {
+        let scheduler = SchedulerServer::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        );
+        let path = std::env::temp_dir().join(format!("{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "a;b\n1;x\n")?;
+        let params = |has_header, delimiter: &str| {
+            Request::new(GetFileMetadataParams {
+                path: path.to_str().unwrap().to_owned(),
+                file_type: FileType::Csv as i32,
+                has_header,
+                delimiter: delimiter.to_owned(),
+            })
+        };
+        let result = scheduler.get_file_metadata(params(true, ";")).await;
+        let invalid = scheduler.get_file_metadata(params(true, ";;")).await;
+        std::fs::remove_file(&path)?;
+
+        // the header names the columns, which the delimiter separates
+        let schema = result.unwrap().into_inner().schema.unwrap();
+        let names = schema
+            .columns
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(invalid.unwrap_err().code(), tonic::Code::InvalidArgument);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_jobs() -> Result<(), BallistaError> {
+        let state = Arc::new(SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "default".to_string(),
+        ));
+        let queued = JobStatus {
+            status: Some(job_status::Status::Queued(QueuedJob {})),
+        };
+        let plan = LogicalPlanBuilder::empty(false).build()?;
+        let submission = JobSubmission {
+            plan: Some((&plan).try_into()?),
+            settings: vec![],
+        };
+        // a queued job with a submission is planned again
+        state.save_job_submission("planned", &submission).await?;
+        state.save_job_metadata("planned", &queued).await?;
+        // a queued job without a submission fails
+        state.save_job_metadata("lost", &queued).await?;
+        // the submissions of completed or unknown jobs are deleted
+        state.save_job_submission("completed", &submission).await?;
+        state
+            .save_job_metadata(
+                "completed",
+                &JobStatus {
+                    status: Some(job_status::Status::Completed(CompletedJob::default())),
+                },
+            )
+            .await?;
+        state.save_job_submission("unknown", &submission).await?;
+
+        assert_eq!(
+            1,
+            recover_jobs(state.clone(), NamespaceQuota::default()).await?
+        );
+        assert!(matches!(
+            state.get_job_metadata("lost").await?.status,
+            Some(job_status::Status::Failed(_))
+        ));
+        let submissions = state.get_job_submissions().await?;
+        assert!(!submissions.contains_key("completed"));
+        assert!(!submissions.contains_key("unknown"));
+        assert!(!submissions.contains_key("lost"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_task_executor() {
+        let event = |partition_id: u32, status: task_status::Status| JobEvent {
+            timestamp: 0,
+            event: Some(job_event::Event::TaskStatus(TaskStatus {
+                partition_id: Some(PartitionId {
+                    job_id: "job".to_owned(),
+                    stage_id: 1,
+                    partition_id,
+                }),
+                status: Some(status),
+            })),
+        };
+        let running = |executor_id: &str| {
+            task_status::Status::Running(RunningTask {
+                executor_id: executor_id.to_owned(),
+                num_rows: 0,
+            })
+        };
+        let events = vec![
+            event(0, running("executor1")),
+            event(1, running("executor2")),
+            event(
+                0,
+                task_status::Status::Failed(FailedTask {
+                    error: "error".to_owned(),
+                }),
+            ),
+            event(0, running("executor3")),
+            event(
+                1,
+                task_status::Status::Completed(CompletedTask {
+                    executor_id: "executor4".to_owned(),
+                    partitions: vec![],
+                    job_disk_usage: 0,
+                }),
+            ),
+        ];
+        assert_eq!(
+            last_task_executor(&events, 1, 0),
+            Some("executor3".to_owned())
+        );
+        assert_eq!(
+            last_task_executor(&events, 1, 1),
+            Some("executor4".to_owned())
+        );
+        assert_eq!(last_task_executor(&events, 1, 2), None);
+        assert_eq!(last_task_executor(&events, 2, 0), None);
+    }
 }
@@ -17,15 +17,29 @@
 
 //! Ballista Rust scheduler binary.
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use ballista_scheduler::externalscaler::external_scaler_server::ExternalScalerServer;
 use futures::future::{self, Either, TryFutureExt};
+use futures::stream::{self, StreamExt};
+use hyper::server::accept;
 use hyper::{server::conn::AddrStream, service::make_service_fn, Server};
 use std::convert::Infallible;
-use std::{net::SocketAddr, sync::Arc};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::{NoClientAuth, PrivateKey, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
 use tonic::transport::Server as TonicServer;
 use tower::Service;
 
+use ballista_core::serde::compression::parse_plan_compression;
+use ballista_core::serde::protobuf::PlanCompression;
 use ballista_core::BALLISTA_VERSION;
 use ballista_core::{
     print_version, serde::protobuf::scheduler_grpc_server::SchedulerGrpcServer,
@@ -35,9 +49,15 @@ use ballista_scheduler::api::{get_routes, EitherBody, Error};
 use ballista_scheduler::state::EtcdClient;
 #[cfg(feature = "sled")]
 use ballista_scheduler::state::StandaloneClient;
-use ballista_scheduler::{state::ConfigBackendClient, ConfigBackend, SchedulerServer};
+use ballista_scheduler::{
+    auth::{Role, SchedulerAuth},
+    quota::NamespaceQuota,
+    session::SessionCatalog,
+    state::{ConfigBackendClient, SchedulerState},
+    ConfigBackend, SchedulerServer,
+};
 
-use log::info;
+use log::{info, warn};
 
 #[macro_use]
 extern crate configure_me;
@@ -53,55 +73,166 @@ mod config {
 }
 use config::prelude::*;
 
+/// Maximum number of TLS handshakes that are performed at the same time
+const MAX_CONCURRENT_TLS_HANDSHAKES: usize = 64;
+
+#[allow(clippy::too_many_arguments)]
 async fn start_server(
     config_backend: Arc<dyn ConfigBackendClient>,
     namespace: String,
     addr: SocketAddr,
+    quota: NamespaceQuota,
+    shuffle_auth: bool,
+    shuffle_encryption: bool,
+    auth: SchedulerAuth,
+    sessions: SessionCatalog,
+    plan_chunk_size: usize,
+    plan_compression: PlanCompression,
+    max_poll_wait: Duration,
+    tls_config: Option<ServerConfig>,
 ) -> Result<()> {
     info!(
         "Ballista v{} Scheduler listening on {:?}",
         BALLISTA_VERSION, addr
     );
 
-    Ok(Server::bind(&addr)
-        .serve(make_service_fn(move |request: &AddrStream| {
-            let scheduler_server = SchedulerServer::new(
-                config_backend.clone(),
-                namespace.clone(),
-                request.remote_addr().ip(),
-            );
-            let scheduler_grpc_server =
-                SchedulerGrpcServer::new(scheduler_server.clone());
-
-            let keda_scaler = ExternalScalerServer::new(scheduler_server.clone());
-
-            let mut tonic = TonicServer::builder()
-                .add_service(scheduler_grpc_server)
-                .add_service(keda_scaler)
-                .into_service();
-            let mut warp = warp::service(get_routes(scheduler_server));
-
-            future::ok::<_, Infallible>(tower::service_fn(
-                move |req: hyper::Request<hyper::Body>| {
-                    let header = req.headers().get(hyper::header::ACCEPT);
-                    if header.is_some() && header.unwrap().eq("application/json") {
-                        return Either::Left(
-                            warp.call(req)
-                                .map_ok(|res| res.map(EitherBody::Left))
-                                .map_err(Error::from),
-                        );
-                    }
-                    Either::Right(
-                        tonic
-                            .call(req)
-                            .map_ok(|res| res.map(EitherBody::Right))
-                            .map_err(Error::from),
-                    )
-                },
-            ))
-        }))
+    // wakes the polls of idle executors, which are held by the servers of their
+    // connections, when the server of another connection saves stages
+    let task_notify = Arc::new(Notify::new());
+
+    // plan the jobs whose planning was interrupted by a previous stop of the scheduler
+    let state = SchedulerState::new(config_backend.clone(), namespace.clone())
+        .with_task_notify(task_notify.clone());
+    let recovered = ballista_scheduler::recover_jobs(Arc::new(state), quota.clone())
         .await
-        .context("Could not start grpc server")?)
+        .context("Could not recover the jobs of the namespace")?;
+    if recovered > 0 {
+        info!("Recovered {} jobs", recovered);
+    }
+
+    // creates the service that handles the requests of a connection from `remote_ip`
+    let make_service = move |remote_ip: IpAddr| {
+        let scheduler_server =
+            SchedulerServer::new(config_backend.clone(), namespace.clone(), remote_ip)
+                .with_quota(quota.clone())
+                .with_shuffle_auth(shuffle_auth)
+                .with_shuffle_encryption(shuffle_encryption)
+                .with_auth(auth.clone())
+                .with_sessions(sessions.clone())
+                .with_plan_chunk_size(plan_chunk_size)
+                .with_plan_compression(plan_compression)
+                .with_max_poll_wait(max_poll_wait)
+                .with_task_notify(task_notify.clone());
+        let scheduler_grpc_server = SchedulerGrpcServer::new(scheduler_server.clone());
+
+        let keda_scaler = ExternalScalerServer::new(scheduler_server.clone());
+
+        let mut tonic = TonicServer::builder()
+            .add_service(scheduler_grpc_server)
+            .add_service(keda_scaler)
+            .into_service();
+        let mut warp = warp::service(get_routes(scheduler_server));
+
+        future::ok::<_, Infallible>(tower::service_fn(
+            move |req: hyper::Request<hyper::Body>| {
+                let header = req.headers().get(hyper::header::ACCEPT);
+                if header.is_some() && header.unwrap().eq("application/json") {
+                    return Either::Left(
+                        warp.call(req)
+                            .map_ok(|res| res.map(EitherBody::Left))
+                            .map_err(Error::from),
+                    );
+                }
+                Either::Right(
+                    tonic
+                        .call(req)
+                        .map_ok(|res| res.map(EitherBody::Right))
+                        .map_err(Error::from),
+                )
+            },
+        ))
+    };
+
+    match tls_config {
+        Some(tls_config) => {
+            let listener = TcpListener::bind(&addr).await?;
+            let acceptor = TlsAcceptor::from(Arc::new(tls_config));
+            let connections = stream::unfold(listener, |listener| async move {
+                Some((listener.accept().await, listener))
+            });
+            // connections that fail the handshake are dropped without stopping the server
+            let incoming = connections
+                .map(move |connection| {
+                    let acceptor = acceptor.clone();
+                    async move {
+                        let (tcp, _) = connection?;
+                        acceptor.accept(tcp).await
+                    }
+                })
+                .buffer_unordered(MAX_CONCURRENT_TLS_HANDSHAKES)
+                .filter_map(|connection| async move {
+                    match connection {
+                        Ok(connection) => Some(Ok::<_, std::io::Error>(connection)),
+                        Err(e) => {
+                            warn!("Could not accept TLS connection: {}", e);
+                            None
+                        }
+                    }
+                });
+            Server::builder(accept::from_stream(incoming))
+                .serve(make_service_fn(move |conn: &TlsStream<TcpStream>| {
+                    let remote_ip = conn
+                        .get_ref()
+                        .0
+                        .peer_addr()
+                        .map(|addr| addr.ip())
+                        .unwrap_or_else(|_| addr.ip());
+                    make_service(remote_ip)
+                }))
+                .await
+                .context("Could not start grpc server")
+        }
+        None => Server::bind(&addr)
+            .serve(make_service_fn(move |conn: &AddrStream| {
+                make_service(conn.remote_addr().ip())
+            }))
+            .await
+            .context("Could not start grpc server"),
+    }
+}
+
+/// Loads the PEM encoded certificate chain and private key to serve the scheduler with
+fn load_tls_config(cert_file: &str, key_file: &str) -> Result<ServerConfig> {
+    let certs = pemfile::certs(&mut BufReader::new(
+        File::open(cert_file).with_context(|| format!("Could not read {}", cert_file))?,
+    ))
+    .map_err(|_| anyhow!("Invalid certificate in {}", cert_file))?;
+
+    let mut keys = read_private_keys(key_file, pemfile::pkcs8_private_keys)?;
+    if keys.is_empty() {
+        keys = read_private_keys(key_file, pemfile::rsa_private_keys)?;
+    }
+    let key = keys
+        .pop()
+        .ok_or_else(|| anyhow!("No private key found in {}", key_file))?;
+
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    // gRPC requires HTTP/2, the REST API is also served over HTTP/1.1
+    config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+    Ok(config)
+}
+
+fn read_private_keys(
+    key_file: &str,
+    parse: fn(&mut dyn BufRead) -> std::result::Result<Vec<PrivateKey>, ()>,
+) -> Result<Vec<PrivateKey>> {
+    let mut reader = BufReader::new(
+        File::open(key_file).with_context(|| format!("Could not read {}", key_file))?,
+    );
+    parse(&mut reader).map_err(|_| anyhow!("Invalid private key in {}", key_file))
 }
 
 #[tokio::main]
@@ -125,6 +256,43 @@ async fn main() -> Result<()> {
     let addr = format!("{}:{}", bind_host, port);
     let addr = addr.parse()?;
 
+    let tls_config = match (&opt.tls_cert_file, &opt.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => Some(load_tls_config(cert_file, key_file)?),
+        (None, None) => None,
+        _ => bail!("tls_cert_file and tls_key_file must be provided together"),
+    };
+
+    let mut auth = SchedulerAuth::new();
+    if let Some(token) = opt.admin_token {
+        auth = auth.with_token(Role::Admin, token);
+    }
+    if let Some(token) = opt.submitter_token {
+        auth = auth.with_token(Role::Submitter, token);
+    }
+    if let Some(token) = opt.executor_token {
+        auth = auth.with_token(Role::Executor, token);
+    }
+    if let Some(token) = opt.monitor_token {
+        auth = auth.with_token(Role::Monitor, token);
+    }
+    info!("authentication enabled: {}", auth.is_enabled());
+    if opt.shuffle_encryption && tls_config.is_none() {
+        warn!("Shuffle encryption keys are sent to the executors without TLS");
+    }
+
+    let plan_compression = parse_plan_compression(&opt.plan_compression)
+        .context("Invalid plan_compression")?;
+
+    let quota = NamespaceQuota {
+        max_concurrent_jobs: opt.max_concurrent_jobs,
+        max_total_tasks: opt.max_total_tasks,
+        max_plan_size: opt.max_plan_size,
+        max_stage_plan_size: opt.max_stage_plan_size,
+        max_inline_rows: opt.max_inline_rows,
+        max_inline_bytes: opt.max_inline_bytes,
+        reject_over_quota: opt.reject_jobs_over_quota,
+    };
+
     let client: Arc<dyn ConfigBackendClient> = match opt.config_backend {
         #[cfg(not(any(feature = "sled", feature = "etcd")))]
         _ => std::compile_error!(
@@ -158,6 +326,20 @@ async fn main() -> Result<()> {
             )
         }
     };
-    start_server(client, namespace, addr).await?;
+    start_server(
+        client,
+        namespace,
+        addr,
+        quota,
+        opt.shuffle_auth,
+        opt.shuffle_encryption,
+        auth,
+        SessionCatalog::new(Duration::from_secs(opt.session_ttl_seconds)),
+        opt.plan_chunk_size,
+        plan_compression,
+        Duration::from_millis(opt.max_poll_wait_ms),
+        tls_config,
+    )
+    .await?;
     Ok(())
 }
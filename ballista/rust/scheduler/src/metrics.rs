@@ -0,0 +1,541 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Prometheus metrics of the scheduler, served on the `/metrics` endpoint of its HTTP
+//! server.
+//!
+//! Counters, histograms and the gauges of queued jobs and inflight tasks are recorded as
+//! the scheduler saves the statuses of jobs and tasks, so they only count the ones this
+//! scheduler saved. The tasks of a job stop being counted once it completed or failed,
+//! and jobs and tasks are dropped after [STALE_ENTRY_AGE] without a status, when
+//! another scheduler of the namespace is assumed to have resolved them.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ballista_core::error::{BallistaError, Result};
+use ballista_core::serde::protobuf::{
+    job_status, task_status, JobStatus, PartitionId, TaskStatus,
+};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntGauge, Registry,
+    TextEncoder,
+};
+
+use crate::state::{ConfigBackendClient, Lock, Watch};
+
+/// Buckets of the task durations, in seconds
+const TASK_DURATION_BUCKETS: &[f64] = &[
+    0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 3600.0,
+];
+/// Buckets of the latencies of the scheduler, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+/// Time after which a queued job or an inflight task whose status this scheduler did
+/// not save again stops being counted
+pub const STALE_ENTRY_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A pending or running task, as last saved by this scheduler
+struct InflightTask {
+    /// When the task was saved as pending, or assigned to an executor
+    since: Instant,
+    running: bool,
+}
+
+/// The jobs and tasks counted by the gauges
+#[derive(Default)]
+struct Inflight {
+    /// When the queued jobs were queued
+    queued_jobs: HashMap<String, Instant>,
+    tasks: HashMap<(String, u32, u32), InflightTask>,
+}
+
+/// The metrics of a scheduler
+pub struct SchedulerMetrics {
+    registry: Registry,
+    jobs_submitted: IntCounter,
+    queued_jobs: IntGauge,
+    pending_tasks: IntGauge,
+    running_tasks: IntGauge,
+    alive_executors: IntGauge,
+    stage_scheduling_latency: Histogram,
+    task_duration: HistogramVec,
+    state_operation_latency: HistogramVec,
+    inflight: Mutex<Inflight>,
+}
+
+impl SchedulerMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let jobs_submitted = IntCounter::new(
+            "ballista_jobs_submitted_total",
+            "Number of jobs submitted to the scheduler",
+        )
+        .unwrap();
+        let queued_jobs = IntGauge::new(
+            "ballista_queued_jobs",
+            "Jobs waiting for the namespace quota to allow them to run",
+        )
+        .unwrap();
+        let pending_tasks =
+            IntGauge::new("ballista_pending_tasks", "Tasks waiting for an executor")
+                .unwrap();
+        let running_tasks =
+            IntGauge::new("ballista_running_tasks", "Tasks assigned to an executor")
+                .unwrap();
+        let alive_executors = IntGauge::new(
+            "ballista_alive_executors",
+            "Executors that polled the scheduler within the executor timeout",
+        )
+        .unwrap();
+        let stage_scheduling_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "ballista_stage_scheduling_latency_seconds",
+                "Time to plan the stages of a job and save their tasks",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        let task_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "ballista_task_duration_seconds",
+                "Time from the assignment of a task to an executor until it completed or failed",
+            )
+            .buckets(TASK_DURATION_BUCKETS.to_vec()),
+            &["status"],
+        )
+        .unwrap();
+        let state_operation_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "ballista_state_operation_latency_seconds",
+                "Latency of the operations on the config backend",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["operation"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(jobs_submitted.clone())).unwrap();
+        registry.register(Box::new(queued_jobs.clone())).unwrap();
+        registry.register(Box::new(pending_tasks.clone())).unwrap();
+        registry.register(Box::new(running_tasks.clone())).unwrap();
+        registry
+            .register(Box::new(alive_executors.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(stage_scheduling_latency.clone()))
+            .unwrap();
+        registry.register(Box::new(task_duration.clone())).unwrap();
+        registry
+            .register(Box::new(state_operation_latency.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            jobs_submitted,
+            queued_jobs,
+            pending_tasks,
+            running_tasks,
+            alive_executors,
+            stage_scheduling_latency,
+            task_duration,
+            state_operation_latency,
+            inflight: Mutex::new(Inflight::default()),
+        }
+    }
+
+    pub fn record_job_submitted(&self) {
+        self.jobs_submitted.inc();
+    }
+
+    pub fn record_stage_scheduling(&self, latency: Duration) {
+        self.stage_scheduling_latency.observe(latency.as_secs_f64());
+    }
+
+    pub fn record_state_operation(&self, operation: &str, latency: Duration) {
+        self.state_operation_latency
+            .with_label_values(&[operation])
+            .observe(latency.as_secs_f64());
+    }
+
+    /// Counts the job while it is queued, and stops counting its tasks once it
+    /// completed or failed
+    pub fn record_job_status(&self, job_id: &str, status: &JobStatus) {
+        let mut inflight = self.inflight.lock().unwrap();
+        match status.status {
+            Some(job_status::Status::Queued(_)) => {
+                let queued = inflight
+                    .queued_jobs
+                    .insert(job_id.to_owned(), Instant::now());
+                if queued.is_none() {
+                    self.queued_jobs.inc();
+                }
+            }
+            Some(job_status::Status::Running(_)) | None => {
+                if inflight.queued_jobs.remove(job_id).is_some() {
+                    self.queued_jobs.dec();
+                }
+            }
+            Some(job_status::Status::Completed(_))
+            | Some(job_status::Status::Failed(_)) => {
+                if inflight.queued_jobs.remove(job_id).is_some() {
+                    self.queued_jobs.dec();
+                }
+                let tasks = inflight
+                    .tasks
+                    .keys()
+                    .filter(|(task_job_id, _, _)| task_job_id == job_id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for task in tasks {
+                    self.remove_task(&mut inflight, &task);
+                }
+            }
+        }
+    }
+
+    /// Counts the task while it is pending or running, and records its duration once it
+    /// completed, failed or was cancelled after being assigned to an executor
+    pub fn record_task_status(&self, status: &TaskStatus) {
+        let partition_id = match &status.partition_id {
+            Some(partition_id) => task_key(partition_id),
+            None => return,
+        };
+        let mut inflight = self.inflight.lock().unwrap();
+        let previous = self.remove_task(&mut inflight, &partition_id);
+        let label = match status.status {
+            // a pending task may also have been rescheduled
+            None | Some(task_status::Status::Running(_)) => {
+                let running = status.status.is_some();
+                if running {
+                    self.running_tasks.inc();
+                } else {
+                    self.pending_tasks.inc();
+                }
+                let task = InflightTask {
+                    since: Instant::now(),
+                    running,
+                };
+                inflight.tasks.insert(partition_id, task);
+                return;
+            }
+            Some(task_status::Status::Completed(_)) => "completed",
+            Some(task_status::Status::Failed(_)) => "failed",
+            Some(task_status::Status::Cancelled(_)) => "cancelled",
+        };
+        // tasks assigned by another scheduler have no start
+        if let Some(InflightTask {
+            since,
+            running: true,
+        }) = previous
+        {
+            self.task_duration
+                .with_label_values(&[label])
+                .observe(since.elapsed().as_secs_f64());
+        }
+    }
+
+    fn remove_task(
+        &self,
+        inflight: &mut Inflight,
+        partition_id: &(String, u32, u32),
+    ) -> Option<InflightTask> {
+        let task = inflight.tasks.remove(partition_id)?;
+        if task.running {
+            self.running_tasks.dec();
+        } else {
+            self.pending_tasks.dec();
+        }
+        Some(task)
+    }
+
+    /// Stops counting the queued jobs and inflight tasks whose statuses were saved
+    /// longer than `max_age` ago
+    fn evict_stale(&self, max_age: Duration) {
+        let mut inflight = self.inflight.lock().unwrap();
+        let queued_jobs = inflight.queued_jobs.len();
+        inflight
+            .queued_jobs
+            .retain(|_, since| since.elapsed() < max_age);
+        self.queued_jobs
+            .sub((queued_jobs - inflight.queued_jobs.len()) as i64);
+        let stale = inflight
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.since.elapsed() >= max_age)
+            .map(|(partition_id, _)| partition_id.clone())
+            .collect::<Vec<_>>();
+        for partition_id in stale {
+            self.remove_task(&mut inflight, &partition_id);
+        }
+    }
+
+    pub fn set_alive_executors(&self, alive_executors: usize) {
+        self.alive_executors.set(alive_executors as i64);
+    }
+
+    /// Encodes the metrics in the Prometheus text format
+    pub fn encode(&self) -> Result<String> {
+        self.evict_stale(STALE_ENTRY_AGE);
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| BallistaError::General(e.to_string()))?;
+        String::from_utf8(buffer).map_err(|e| BallistaError::General(e.to_string()))
+    }
+}
+
+impl Default for SchedulerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn task_key(partition_id: &PartitionId) -> (String, u32, u32) {
+    (
+        partition_id.job_id.clone(),
+        partition_id.stage_id,
+        partition_id.partition_id,
+    )
+}
+
+/// A [ConfigBackendClient] recording the latency of the operations of another one
+pub struct InstrumentedConfigBackend {
+    inner: Arc<dyn ConfigBackendClient>,
+    metrics: Arc<SchedulerMetrics>,
+}
+
+impl InstrumentedConfigBackend {
+    pub fn new(
+        inner: Arc<dyn ConfigBackendClient>,
+        metrics: Arc<SchedulerMetrics>,
+    ) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[tonic::async_trait]
+impl ConfigBackendClient for InstrumentedConfigBackend {
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let start = Instant::now();
+        let result = self.inner.get(key).await;
+        self.metrics.record_state_operation("get", start.elapsed());
+        result
+    }
+
+    async fn get_from_prefix(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let start = Instant::now();
+        let result = self.inner.get_from_prefix(prefix).await;
+        self.metrics
+            .record_state_operation("get_from_prefix", start.elapsed());
+        result
+    }
+
+    async fn put(&self, key: String, value: Vec<u8>) -> Result<()> {
+        let start = Instant::now();
+        let result = self.inner.put(key, value).await;
+        self.metrics.record_state_operation("put", start.elapsed());
+        result
+    }
+
+    async fn put_txn(&self, ops: Vec<(String, Vec<u8>)>) -> Result<()> {
+        let start = Instant::now();
+        let result = self.inner.put_txn(ops).await;
+        self.metrics
+            .record_state_operation("put_txn", start.elapsed());
+        result
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let start = Instant::now();
+        let result = self.inner.delete(key).await;
+        self.metrics
+            .record_state_operation("delete", start.elapsed());
+        result
+    }
+
+    async fn lock(&self, key: &str) -> Result<Box<dyn Lock>> {
+        let start = Instant::now();
+        let result = self.inner.lock(key).await;
+        self.metrics.record_state_operation("lock", start.elapsed());
+        result
+    }
+
+    async fn watch(&self, prefix: String) -> Result<Box<dyn Watch>> {
+        self.inner.watch(prefix).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sled")]
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use ballista_core::error::Result;
+    use ballista_core::serde::protobuf::{
+        job_status, task_status, CompletedTask, FailedJob, JobStatus, PartitionId,
+        QueuedJob, RunningJob, RunningTask, TaskStatus,
+    };
+
+    use super::SchedulerMetrics;
+
+    fn task_status(status: Option<task_status::Status>) -> TaskStatus {
+        partition_status(0, status)
+    }
+
+    fn partition_status(
+        partition_id: u32,
+        status: Option<task_status::Status>,
+    ) -> TaskStatus {
+        TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_owned(),
+                stage_id: 1,
+                partition_id,
+            }),
+            status,
+        }
+    }
+
+    fn running() -> Option<task_status::Status> {
+        Some(task_status::Status::Running(RunningTask {
+            executor_id: "executor".to_owned(),
+            num_rows: 0,
+        }))
+    }
+
+    fn job_status(status: job_status::Status) -> JobStatus {
+        JobStatus {
+            status: Some(status),
+        }
+    }
+
+    #[test]
+    fn encode_metrics() -> Result<()> {
+        let metrics = SchedulerMetrics::new();
+        metrics.record_job_submitted();
+        metrics.record_job_submitted();
+        metrics.record_stage_scheduling(Duration::from_millis(3));
+        metrics.record_job_status(
+            "queued",
+            &job_status(job_status::Status::Queued(QueuedJob {})),
+        );
+        metrics.record_job_status(
+            "queued",
+            &job_status(job_status::Status::Queued(QueuedJob {})),
+        );
+        metrics.record_job_status(
+            "started",
+            &job_status(job_status::Status::Queued(QueuedJob {})),
+        );
+        metrics.record_job_status(
+            "started",
+            &job_status(job_status::Status::Running(RunningJob {})),
+        );
+        for partition_id in 0..4 {
+            metrics.record_task_status(&partition_status(partition_id, None));
+        }
+        metrics.record_task_status(&partition_status(0, running()));
+        metrics.record_task_status(&partition_status(1, running()));
+        metrics.set_alive_executors(3);
+        let text = metrics.encode()?;
+        assert!(text.contains("ballista_jobs_submitted_total 2"));
+        assert!(text.contains("ballista_queued_jobs 1"));
+        assert!(text.contains("ballista_pending_tasks 2"));
+        assert!(text.contains("ballista_running_tasks 2"));
+        assert!(text.contains("ballista_alive_executors 3"));
+        assert!(text.contains("ballista_stage_scheduling_latency_seconds_count 1"));
+        Ok(())
+    }
+
+    #[test]
+    fn task_duration() -> Result<()> {
+        let metrics = SchedulerMetrics::new();
+        // a task that was not assigned by this scheduler is not timed
+        metrics.record_task_status(&task_status(Some(task_status::Status::Completed(
+            CompletedTask::default(),
+        ))));
+        assert!(!metrics
+            .encode()?
+            .contains("ballista_task_duration_seconds_count"));
+
+        metrics.record_task_status(&task_status(running()));
+        metrics.record_task_status(&task_status(Some(task_status::Status::Completed(
+            CompletedTask::default(),
+        ))));
+        let text = metrics.encode()?;
+        assert!(
+            text.contains("ballista_task_duration_seconds_count{status=\"completed\"} 1")
+        );
+        assert!(text.contains("ballista_running_tasks 0"));
+        Ok(())
+    }
+
+    #[test]
+    fn evict_tasks() -> Result<()> {
+        let metrics = SchedulerMetrics::new();
+        metrics.record_task_status(&partition_status(0, None));
+        metrics.record_task_status(&partition_status(1, running()));
+        // the tasks of a failed job that never report their status stop being counted
+        metrics.record_job_status(
+            "job",
+            &job_status(job_status::Status::Failed(FailedJob::default())),
+        );
+        let text = metrics.encode()?;
+        assert!(text.contains("ballista_pending_tasks 0"));
+        assert!(text.contains("ballista_running_tasks 0"));
+
+        // as do the jobs and tasks that another scheduler resolved
+        metrics.record_job_status(
+            "queued",
+            &job_status(job_status::Status::Queued(QueuedJob {})),
+        );
+        metrics.record_task_status(&partition_status(2, running()));
+        metrics.evict_stale(Duration::from_secs(0));
+        let text = metrics.encode()?;
+        assert!(text.contains("ballista_queued_jobs 0"));
+        assert!(text.contains("ballista_running_tasks 0"));
+        Ok(())
+    }
+
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn state_operation_latency() -> Result<()> {
+        use super::InstrumentedConfigBackend;
+        use crate::state::{ConfigBackendClient, StandaloneClient};
+
+        let metrics = Arc::new(SchedulerMetrics::new());
+        let client = InstrumentedConfigBackend::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            metrics.clone(),
+        );
+        client.put("key".to_owned(), vec![1]).await?;
+        assert_eq!(client.get("key").await?, vec![1]);
+        assert_eq!(client.get("other").await?, Vec::<u8>::new());
+        let text = metrics.encode()?;
+        assert!(text.contains(
+            "ballista_state_operation_latency_seconds_count{operation=\"get\"} 2"
+        ));
+        assert!(text.contains(
+            "ballista_state_operation_latency_seconds_count{operation=\"put\"} 1"
+        ));
+        Ok(())
+    }
+}
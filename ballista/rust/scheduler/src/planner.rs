@@ -26,24 +26,55 @@ use ballista_core::datasource::DfTableAdapter;
 use ballista_core::error::{BallistaError, Result};
 use ballista_core::{
     execution_plans::{ShuffleReaderExec, ShuffleWriterExec, UnresolvedShuffleExec},
+    serde::protobuf::{
+        query_stage_plan::{OptionalEstimatedBytes, OptionalEstimatedRows},
+        QueryStagePlan,
+    },
     serde::scheduler::PartitionLocation,
 };
 use datafusion::execution::context::ExecutionContext;
+use datafusion::logical_plan::JoinType;
+use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
 use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::hash_join::{HashJoinExec, PartitionMode};
+use datafusion::physical_plan::limit::{GlobalLimitExec, LocalLimitExec};
 use datafusion::physical_plan::repartition::RepartitionExec;
 use datafusion::physical_plan::windows::WindowAggExec;
-use datafusion::physical_plan::{ExecutionPlan, Partitioning};
+use datafusion::physical_plan::{displayable, ExecutionPlan, HashScheme, Partitioning};
 use log::info;
 
 type PartialQueryStageResult = (Arc<dyn ExecutionPlan>, Vec<Arc<ShuffleWriterExec>>);
 
 pub struct DistributedPlanner {
     next_stage_id: usize,
+    /// Maximum estimated number of rows of the build side of a broadcast join, 0 if
+    /// broadcast joins are disabled
+    broadcast_threshold: usize,
+    /// Object store location that the final stage writes the results of the job to
+    results_path: Option<String>,
 }
 
 impl DistributedPlanner {
     pub fn new() -> Self {
-        Self { next_stage_id: 0 }
+        Self {
+            next_stage_id: 0,
+            broadcast_threshold: 0,
+            results_path: None,
+        }
+    }
+
+    /// Plan joins whose build side is estimated to have at most `rows` rows as broadcast
+    /// joins, see [DistributedPlanner::plan_broadcast_join]
+    pub fn with_broadcast_threshold(mut self, rows: usize) -> Self {
+        self.broadcast_threshold = rows;
+        self
+    }
+
+    /// Write the results of the final stage to `results_path` in an object store,
+    /// instead of keeping them on the executors
+    pub fn with_results_path(mut self, results_path: Option<String>) -> Self {
+        self.results_path = results_path;
+        self
     }
 }
 
@@ -67,12 +98,15 @@ impl DistributedPlanner {
         info!("planning query stages");
         let (new_plan, mut stages) =
             self.plan_query_stages_internal(job_id, execution_plan)?;
-        stages.push(create_shuffle_writer(
-            job_id,
+        let final_stage = ShuffleWriterExec::try_new(
+            job_id.to_owned(),
             self.next_stage_id(),
             new_plan,
+            "".to_owned(), // executor will decide on the work_dir path
             None,
-        )?);
+        )?
+        .with_results_path(self.results_path.clone());
+        stages.push(Arc::new(final_stage));
         Ok(stages)
     }
 
@@ -89,6 +123,22 @@ impl DistributedPlanner {
             return Ok((execution_plan, vec![]));
         }
 
+        if let Some(join) = execution_plan.as_any().downcast_ref::<HashJoinExec>() {
+            if let Some(result) = self.plan_broadcast_join(job_id, join)? {
+                return Ok(result);
+            }
+        }
+
+        if let Some(limit) = execution_plan.as_any().downcast_ref::<GlobalLimitExec>() {
+            if let Some(coalesce) = limit
+                .input()
+                .as_any()
+                .downcast_ref::<CoalescePartitionsExec>()
+            {
+                return self.plan_global_limit(job_id, limit, coalesce);
+            }
+        }
+
         let mut stages = vec![];
         let mut children = vec![];
         for child in execution_plan.children() {
@@ -110,18 +160,22 @@ impl DistributedPlanner {
                 self.next_stage_id(),
                 children[0].clone(),
                 None,
+                HashScheme::default(),
             )?;
-            let unresolved_shuffle = Arc::new(UnresolvedShuffleExec::new(
-                shuffle_writer.stage_id(),
-                shuffle_writer.schema(),
-                shuffle_writer.output_partitioning().partition_count(),
-                shuffle_writer
-                    .shuffle_output_partitioning()
-                    .map(|p| p.partition_count())
-                    .unwrap_or_else(|| {
-                        shuffle_writer.output_partitioning().partition_count()
-                    }),
-            ));
+            let unresolved_shuffle = Arc::new(
+                UnresolvedShuffleExec::new(
+                    shuffle_writer.stage_id(),
+                    shuffle_writer.schema(),
+                    shuffle_writer.output_partitioning().partition_count(),
+                    shuffle_writer
+                        .shuffle_output_partitioning()
+                        .map(|p| p.partition_count())
+                        .unwrap_or_else(|| {
+                            shuffle_writer.output_partitioning().partition_count()
+                        }),
+                )
+                .with_statistics(shuffle_writer.statistics()),
+            );
             stages.push(shuffle_writer);
             Ok((
                 coalesce.with_new_children(vec![unresolved_shuffle])?,
@@ -137,18 +191,22 @@ impl DistributedPlanner {
                         self.next_stage_id(),
                         children[0].clone(),
                         Some(repart.partitioning().to_owned()),
+                        repart.hash_scheme(),
                     )?;
-                    let unresolved_shuffle = Arc::new(UnresolvedShuffleExec::new(
-                        shuffle_writer.stage_id(),
-                        shuffle_writer.schema(),
-                        shuffle_writer.output_partitioning().partition_count(),
-                        shuffle_writer
-                            .shuffle_output_partitioning()
-                            .map(|p| p.partition_count())
-                            .unwrap_or_else(|| {
-                                shuffle_writer.output_partitioning().partition_count()
-                            }),
-                    ));
+                    let unresolved_shuffle = Arc::new(
+                        UnresolvedShuffleExec::new(
+                            shuffle_writer.stage_id(),
+                            shuffle_writer.schema(),
+                            shuffle_writer.output_partitioning().partition_count(),
+                            shuffle_writer
+                                .shuffle_output_partitioning()
+                                .map(|p| p.partition_count())
+                                .unwrap_or_else(|| {
+                                    shuffle_writer.output_partitioning().partition_count()
+                                }),
+                        )
+                        .with_statistics(shuffle_writer.statistics()),
+                    );
                     stages.push(shuffle_writer);
                     Ok((unresolved_shuffle, stages))
                 }
@@ -169,6 +227,116 @@ impl DistributedPlanner {
         }
     }
 
+    /// Plans a partitioned hash join whose build side is estimated to be small as a
+    /// broadcast join, returning `None` if the join does not qualify.
+    ///
+    /// The build side is written by a broadcast stage with a single unpartitioned shuffle
+    /// output, which every task of the join stage reads fully. The probe side keeps its
+    /// partitioning, so it is not shuffled at all. Only inner and right joins qualify,
+    /// because the other join types emit unmatched build side rows, which would be
+    /// repeated by every task.
+    fn plan_broadcast_join(
+        &mut self,
+        job_id: &str,
+        join: &HashJoinExec,
+    ) -> Result<Option<PartialQueryStageResult>> {
+        if self.broadcast_threshold == 0
+            || *join.partition_mode() != PartitionMode::Partitioned
+            || !matches!(join.join_type(), JoinType::Inner | JoinType::Right)
+        {
+            return Ok(None);
+        }
+        let (build, probe) = match (
+            remove_hash_repartition(join.left()),
+            remove_hash_repartition(join.right()),
+        ) {
+            (Some(build), Some(probe)) => (build, probe),
+            _ => return Ok(None),
+        };
+        match build.statistics().num_rows {
+            Some(rows) if rows <= self.broadcast_threshold => {}
+            _ => return Ok(None),
+        }
+
+        let (build, mut stages) = self.plan_query_stages_internal(job_id, build)?;
+        let build: Arc<dyn ExecutionPlan> =
+            if build.output_partitioning().partition_count() > 1 {
+                Arc::new(CoalescePartitionsExec::new(build))
+            } else {
+                build
+            };
+        let shuffle_writer = create_shuffle_writer(
+            job_id,
+            self.next_stage_id(),
+            build,
+            None,
+            HashScheme::default(),
+        )?;
+        info!(
+            "Planning stage {} as the build side of a broadcast join",
+            shuffle_writer.stage_id()
+        );
+        let unresolved_shuffle = Arc::new(
+            UnresolvedShuffleExec::new(
+                shuffle_writer.stage_id(),
+                shuffle_writer.schema(),
+                1,
+                1,
+            )
+            .with_statistics(shuffle_writer.statistics()),
+        );
+        stages.push(shuffle_writer);
+
+        let (probe, mut probe_stages) = self.plan_query_stages_internal(job_id, probe)?;
+        stages.append(&mut probe_stages);
+
+        let join = HashJoinExec::try_new(
+            unresolved_shuffle,
+            probe,
+            join.on().to_vec(),
+            join.join_type(),
+            PartitionMode::CollectLeft,
+            join.null_equals_null(),
+        )?;
+        Ok(Some((Arc::new(join), stages)))
+    }
+
+    /// Plans a limit of the coalesced partitions of its input, which is written by a
+    /// stage of its own.
+    ///
+    /// Every task of that stage stops after the rows of the limit, and the scheduler
+    /// cancels its remaining tasks once the completed ones produced enough rows, see
+    /// [UnresolvedShuffleExec::limit].
+    fn plan_global_limit(
+        &mut self,
+        job_id: &str,
+        limit: &GlobalLimitExec,
+        coalesce: &CoalescePartitionsExec,
+    ) -> Result<PartialQueryStageResult> {
+        let rows = limit.limit().saturating_add(limit.offset());
+        let input = coalesce.input();
+        let input: Arc<dyn ExecutionPlan> =
+            if input.as_any().downcast_ref::<LocalLimitExec>().is_some() {
+                input.clone()
+            } else {
+                Arc::new(LocalLimitExec::new(input.clone(), rows))
+            };
+        let (coalesce, stages) = self.plan_query_stages_internal(
+            job_id,
+            coalesce.with_new_children(vec![input])?,
+        )?;
+        let shuffle = coalesce
+            .as_any()
+            .downcast_ref::<CoalescePartitionsExec>()
+            .and_then(|c| c.input().as_any().downcast_ref::<UnresolvedShuffleExec>());
+        let coalesce = match shuffle {
+            Some(shuffle) => coalesce
+                .with_new_children(vec![Arc::new(shuffle.clone().with_limit(rows))])?,
+            None => coalesce,
+        };
+        Ok((limit.with_new_children(vec![coalesce])?, stages))
+    }
+
     /// Generate a new stage ID
     fn next_stage_id(&mut self) -> usize {
         self.next_stage_id += 1;
@@ -176,6 +344,23 @@ impl DistributedPlanner {
     }
 }
 
+/// Returns the input of a hash repartition, which may be wrapped in a
+/// [CoalesceBatchesExec], or `None` if `plan` is not a hash repartition
+fn remove_hash_repartition(
+    plan: &Arc<dyn ExecutionPlan>,
+) -> Option<Arc<dyn ExecutionPlan>> {
+    let plan = match plan.as_any().downcast_ref::<CoalesceBatchesExec>() {
+        Some(coalesce) => coalesce.input(),
+        None => plan,
+    };
+    match plan.as_any().downcast_ref::<RepartitionExec>() {
+        Some(repart) if matches!(repart.partitioning(), Partitioning::Hash(_, _)) => {
+            Some(repart.input().clone())
+        }
+        _ => None,
+    }
+}
+
 pub fn remove_unresolved_shuffles(
     stage: &dyn ExecutionPlan,
     partition_locations: &HashMap<usize, HashMap<usize, Vec<PartitionLocation>>>,
@@ -229,31 +414,84 @@ pub fn remove_unresolved_shuffles(
     Ok(stage.with_new_children(new_children)?)
 }
 
+/// Describes the stages planned by [DistributedPlanner::plan_query_stages] for a dry
+/// run of a query. The estimates of the output of a stage are derived from the
+/// statistics of the tables it scans and of the stages it reads.
+pub fn describe_query_stages(stages: &[Arc<ShuffleWriterExec>]) -> Vec<QueryStagePlan> {
+    stages
+        .iter()
+        .map(|stage| {
+            let mut input_stage_ids = vec![];
+            collect_input_stage_ids(stage.as_ref(), &mut input_stage_ids);
+            let task_count = stage.output_partitioning().partition_count();
+            let output_partition_count = stage
+                .shuffle_output_partitioning()
+                .map_or(task_count, |p| p.partition_count());
+            let statistics = stage.statistics();
+            QueryStagePlan {
+                stage_id: stage.stage_id() as u32,
+                input_stage_ids,
+                task_count: task_count as u32,
+                output_partition_count: output_partition_count as u32,
+                optional_estimated_rows: statistics
+                    .num_rows
+                    .map(|rows| OptionalEstimatedRows::EstimatedRows(rows as u64)),
+                optional_estimated_bytes: statistics
+                    .total_byte_size
+                    .map(|bytes| OptionalEstimatedBytes::EstimatedBytes(bytes as u64)),
+                plan: displayable(stage.as_ref()).indent().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Appends the ids of the stages whose shuffle output `plan` reads to `stage_ids`
+fn collect_input_stage_ids(plan: &dyn ExecutionPlan, stage_ids: &mut Vec<u32>) {
+    match plan.as_any().downcast_ref::<UnresolvedShuffleExec>() {
+        Some(shuffle) => stage_ids.push(shuffle.stage_id as u32),
+        None => {
+            for child in plan.children() {
+                collect_input_stage_ids(child.as_ref(), stage_ids);
+            }
+        }
+    }
+}
+
 fn create_shuffle_writer(
     job_id: &str,
     stage_id: usize,
     plan: Arc<dyn ExecutionPlan>,
     partitioning: Option<Partitioning>,
+    hash_scheme: HashScheme,
 ) -> Result<Arc<ShuffleWriterExec>> {
-    Ok(Arc::new(ShuffleWriterExec::try_new(
-        job_id.to_owned(),
-        stage_id,
-        plan,
-        "".to_owned(), // executor will decide on the work_dir path
-        partitioning,
-    )?))
+    Ok(Arc::new(
+        ShuffleWriterExec::try_new(
+            job_id.to_owned(),
+            stage_id,
+            plan,
+            "".to_owned(), // executor will decide on the work_dir path
+            partitioning,
+        )?
+        .with_hash_scheme(hash_scheme),
+    ))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::planner::DistributedPlanner;
+    use crate::planner::{describe_query_stages, DistributedPlanner};
     use crate::test_utils::datafusion_test_context;
     use ballista_core::error::BallistaError;
     use ballista_core::execution_plans::UnresolvedShuffleExec;
     use ballista_core::serde::protobuf;
+    use ballista_core::serde::protobuf::query_stage_plan::OptionalEstimatedRows;
+    use datafusion::arrow::array::{Int32Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::datasource::MemTable;
     use datafusion::physical_plan::coalesce_batches::CoalesceBatchesExec;
     use datafusion::physical_plan::hash_aggregate::{AggregateMode, HashAggregateExec};
-    use datafusion::physical_plan::hash_join::HashJoinExec;
+    use datafusion::physical_plan::hash_join::{HashJoinExec, PartitionMode};
+    use datafusion::physical_plan::limit::{GlobalLimitExec, LocalLimitExec};
     use datafusion::physical_plan::sort::SortExec;
     use datafusion::physical_plan::{
         coalesce_partitions::CoalescePartitionsExec, projection::ProjectionExec,
@@ -406,7 +644,7 @@ order by
 
         ShuffleWriterExec: Some(Hash([Column { name: "l_orderkey", index: 0 }], 2))
           CoalesceBatchesExec: target_batch_size=4096
-            FilterExec: l_shipmode@4 IN ([Literal { value: Utf8("MAIL") }, Literal { value: Utf8("SHIP") }]) AND l_commitdate@2 < l_receiptdate@3 AND l_shipdate@1 < l_commitdate@2 AND l_receiptdate@3 >= 8766 AND l_receiptdate@3 < 9131
+            FilterExec: FUSED(l_receiptdate@3 >= 8766 AND l_receiptdate@3 < 9131) AND l_shipmode@4 IN ([Literal { value: Utf8("MAIL") }, Literal { value: Utf8("SHIP") }]) AND l_commitdate@2 < l_receiptdate@3 AND l_shipdate@1 < l_commitdate@2
               CsvExec: source=Path(testdata/lineitem: [testdata/lineitem/partition0.tbl,testdata/lineitem/partition1.tbl]), has_header=false
 
         ShuffleWriterExec: Some(Hash([Column { name: "o_orderkey", index: 0 }], 2))
@@ -523,6 +761,196 @@ order by
         Ok(())
     }
 
+    #[test]
+    fn distributed_broadcast_join_plan() -> Result<(), BallistaError> {
+        let mut ctx = datafusion_test_context("testdata")?;
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("d_orderkey", DataType::Int32, false),
+            Field::new("d_name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+            ],
+        )?;
+        ctx.register_table(
+            "dim",
+            Arc::new(MemTable::try_new(schema, vec![vec![batch]])?),
+        )?;
+
+        let df = ctx.sql(
+            "select d_name, l_quantity from dim join lineitem on d_orderkey = l_orderkey",
+        )?;
+        let plan = df.to_logical_plan();
+        let plan = ctx.optimize(&plan)?;
+        let plan = ctx.create_physical_plan(&plan)?;
+
+        // the build side is too large to be broadcast
+        let mut planner = DistributedPlanner::new().with_broadcast_threshold(2);
+        let stages =
+            planner.plan_query_stages(&Uuid::new_v4().to_string(), plan.clone())?;
+        assert_eq!(3, stages.len());
+
+        let mut planner = DistributedPlanner::new().with_broadcast_threshold(3);
+        let stages = planner.plan_query_stages(&Uuid::new_v4().to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent().to_string());
+        }
+
+        /* Expected result:
+
+        ShuffleWriterExec: None
+          MemoryExec: partitions=1, partition_sizes=[1]
+
+        ShuffleWriterExec: None
+          ProjectionExec: expr=[d_name@1 as d_name, l_quantity@6 as l_quantity]
+            CoalesceBatchesExec: target_batch_size=4096
+              HashJoinExec: mode=CollectLeft, join_type=Inner, on=[(Column { name: "d_orderkey", index: 0 }, Column { name: "l_orderkey", index: 0 })]
+                UnresolvedShuffleExec
+                CsvExec: source=Path(testdata/lineitem: [testdata/lineitem/partition0.tbl,testdata/lineitem/partition1.tbl]), has_header=false
+        */
+
+        assert_eq!(2, stages.len());
+
+        // the build side is written to a single partition
+        assert_eq!(1, stages[0].output_partitioning().partition_count());
+        assert!(stages[0].shuffle_output_partitioning().is_none());
+
+        // the probe side is read in place
+        let projection = stages[1].children()[0].clone();
+        let coalesce_batches = projection.children()[0].clone();
+        let join = coalesce_batches.children()[0].clone();
+        let join = downcast_exec!(join, HashJoinExec);
+        assert_eq!(*join.partition_mode(), PartitionMode::CollectLeft);
+        let build = downcast_exec!(join.left(), UnresolvedShuffleExec);
+        assert_eq!(build.stage_id, stages[0].stage_id());
+        assert_eq!(build.output_partition_count, 1);
+        assert_eq!(2, join.right().output_partitioning().partition_count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn distributed_limit_plan() -> Result<(), BallistaError> {
+        let mut ctx = datafusion_test_context("testdata")?;
+        let df = ctx.sql("select l_orderkey from lineitem limit 10")?;
+        let plan = df.to_logical_plan();
+        let plan = ctx.optimize(&plan)?;
+        let plan = ctx.create_physical_plan(&plan)?;
+
+        let mut planner = DistributedPlanner::new();
+        let stages = planner.plan_query_stages(&Uuid::new_v4().to_string(), plan)?;
+        for stage in &stages {
+            println!("{}", displayable(stage.as_ref()).indent().to_string());
+        }
+
+        /* Expected result:
+
+        ShuffleWriterExec: None
+          LocalLimitExec: limit=10
+            ProjectionExec: expr=[l_orderkey@0 as l_orderkey]
+              CsvExec: source=Path(testdata/lineitem: [testdata/lineitem/partition0.tbl,testdata/lineitem/partition1.tbl]), has_header=false
+
+        ShuffleWriterExec: None
+          GlobalLimitExec: limit=10
+            CoalescePartitionsExec
+              UnresolvedShuffleExec: limit=10
+        */
+
+        assert_eq!(2, stages.len());
+
+        // every task of the first stage stops after the rows of the limit
+        let local_limit = stages[0].children()[0].clone();
+        assert_eq!(downcast_exec!(local_limit, LocalLimitExec).limit(), 10);
+
+        // the remaining tasks are cancelled once the completed ones produced them
+        let global_limit = stages[1].children()[0].clone();
+        let global_limit = downcast_exec!(global_limit, GlobalLimitExec);
+        let coalesce_partitions = global_limit.children()[0].clone();
+        let unresolved_shuffle = coalesce_partitions.children()[0].clone();
+        let unresolved_shuffle =
+            downcast_exec!(unresolved_shuffle, UnresolvedShuffleExec);
+        assert_eq!(unresolved_shuffle.stage_id, stages[0].stage_id());
+        assert_eq!(unresolved_shuffle.limit, Some(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn distributed_plan_results_path() -> Result<(), BallistaError> {
+        let mut ctx = datafusion_test_context("testdata")?;
+        let df = ctx.sql("select l_orderkey from lineitem limit 10")?;
+        let plan = df.to_logical_plan();
+        let plan = ctx.optimize(&plan)?;
+        let plan = ctx.create_physical_plan(&plan)?;
+
+        let mut planner = DistributedPlanner::new()
+            .with_results_path(Some("s3://bucket/results".to_owned()));
+        let stages = planner.plan_query_stages(&Uuid::new_v4().to_string(), plan)?;
+        assert_eq!(2, stages.len());
+
+        // only the final stage writes to the object store
+        assert_eq!(None, stages[0].results_path());
+        assert_eq!(Some("s3://bucket/results"), stages[1].results_path());
+
+        Ok(())
+    }
+
+    #[test]
+    fn describe_stages() -> Result<(), BallistaError> {
+        let mut ctx = datafusion_test_context("testdata")?;
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("d_orderkey", DataType::Int32, false),
+            Field::new("d_name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "a"])),
+            ],
+        )?;
+        ctx.register_table(
+            "dim",
+            Arc::new(MemTable::try_new(schema, vec![vec![batch]])?),
+        )?;
+        let df = ctx.sql(
+            "select d_name, count(d_orderkey) from dim group by d_name order by d_name",
+        )?;
+        let plan = df.to_logical_plan();
+        let plan = ctx.optimize(&plan)?;
+        let plan = ctx.create_physical_plan(&plan)?;
+
+        let mut planner = DistributedPlanner::new();
+        let stages = planner.plan_query_stages(&Uuid::new_v4().to_string(), plan)?;
+        let stages = describe_query_stages(&stages);
+        assert_eq!(3, stages.len());
+
+        // the partial aggregate is shuffled into the partitions of the final aggregate
+        assert!(stages[0].input_stage_ids.is_empty());
+        assert_eq!(2, stages[0].output_partition_count);
+        assert!(stages[0].plan.starts_with("ShuffleWriterExec"));
+        assert_eq!(vec![stages[0].stage_id], stages[1].input_stage_ids);
+        assert_eq!(2, stages[1].task_count);
+        assert_eq!(vec![stages[1].stage_id], stages[2].input_stage_ids);
+        assert_eq!(1, stages[2].task_count);
+        assert_eq!(1, stages[2].output_partition_count);
+
+        // the number of rows of the table is an upper bound of the number of groups,
+        // which is passed on to the stages that read the shuffle
+        for stage in &stages {
+            assert_eq!(
+                Some(OptionalEstimatedRows::EstimatedRows(3)),
+                stage.optional_estimated_rows
+            );
+            assert_eq!(None, stage.optional_estimated_bytes);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn roundtrip_serde_hash_aggregate() -> Result<(), BallistaError> {
         let mut ctx = datafusion_test_context("testdata")?;
@@ -0,0 +1,273 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Admission control for jobs submitted to the scheduler.
+//!
+//! A [NamespaceQuota] limits the resources that the jobs of a namespace can use, so
+//! that a cluster shared by several teams cannot be monopolized by one of them.
+
+use std::collections::HashMap;
+
+use ballista_core::serde::protobuf::{job_status, task_status, JobStatus, TaskStatus};
+
+/// Limits on the jobs of a namespace, enforced when jobs are submitted
+#[derive(Debug, Clone, Default)]
+pub struct NamespaceQuota {
+    /// Maximum number of jobs that can be running at the same time
+    pub max_concurrent_jobs: Option<usize>,
+    /// Maximum number of tasks that are pending or running across all jobs
+    pub max_total_tasks: Option<usize>,
+    /// Maximum size in bytes of the query (serialized logical plan or SQL text) of a job
+    pub max_plan_size: Option<usize>,
+    /// Maximum size in bytes of the encoded physical plan of a stage, which is kept in
+    /// the state store and sent to the executors with each task of the stage
+    pub max_stage_plan_size: Option<usize>,
+    /// Maximum number of rows of the batches that a job inlines in its plan, such as
+    /// broadcast variables, which the scheduler holds in memory
+    pub max_inline_rows: Option<usize>,
+    /// Maximum size in bytes of the batches that a job inlines in its plan
+    pub max_inline_bytes: Option<usize>,
+    /// Whether jobs are rejected, rather than queued, when the namespace is at capacity
+    pub reject_over_quota: bool,
+}
+
+impl NamespaceQuota {
+    /// Returns a description of the violated limit if a query of `plan_size` bytes
+    /// cannot be accepted. Queries that are too large are always rejected.
+    pub fn check_plan_size(&self, plan_size: usize) -> Option<String> {
+        match self.max_plan_size {
+            Some(max_plan_size) if plan_size > max_plan_size => Some(format!(
+                "Plan size of {} bytes exceeds the limit of {} bytes",
+                plan_size, max_plan_size
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns a description of the violated limit if the plan of stage `stage_id` is
+    /// `plan_size` bytes once encoded
+    pub fn check_stage_plan_size(
+        &self,
+        stage_id: usize,
+        plan_size: usize,
+    ) -> Option<String> {
+        match self.max_stage_plan_size {
+            Some(max_stage_plan_size) if plan_size > max_stage_plan_size => {
+                Some(format!(
+                    "Plan of stage {} is {} bytes once encoded, which exceeds the limit of {} bytes",
+                    stage_id, plan_size, max_stage_plan_size
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a description of the violated limit if the inlined batches `name` of a
+    /// job, with `num_rows` rows of `num_bytes` bytes, are too large
+    pub fn check_inline_size(
+        &self,
+        name: &str,
+        num_rows: usize,
+        num_bytes: usize,
+    ) -> Option<String> {
+        match (self.max_inline_rows, self.max_inline_bytes) {
+            (Some(max_inline_rows), _) if num_rows > max_inline_rows => Some(format!(
+                "Inlined data {} has {} rows, which exceeds the limit of {} rows",
+                name, num_rows, max_inline_rows
+            )),
+            (_, Some(max_inline_bytes)) if num_bytes > max_inline_bytes => Some(format!(
+                "Inlined data {} is {} bytes, which exceeds the limit of {} bytes",
+                name, num_bytes, max_inline_bytes
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns a description of the violated limit if a new job cannot start running,
+    /// given the current jobs and tasks of the namespace
+    pub fn check_capacity(
+        &self,
+        jobs: &HashMap<String, JobStatus>,
+        tasks: &HashMap<String, TaskStatus>,
+    ) -> Option<String> {
+        if let Some(max_concurrent_jobs) = self.max_concurrent_jobs {
+            let running_jobs = jobs
+                .values()
+                .filter(|job| matches!(job.status, Some(job_status::Status::Running(_))))
+                .count();
+            if running_jobs >= max_concurrent_jobs {
+                return Some(format!(
+                    "Namespace has {} running jobs, the limit is {}",
+                    running_jobs, max_concurrent_jobs
+                ));
+            }
+        }
+        if let Some(max_total_tasks) = self.max_total_tasks {
+            let active_tasks = tasks
+                .values()
+                .filter(|task| {
+                    !matches!(
+                        task.status,
+                        Some(task_status::Status::Completed(_))
+                            | Some(task_status::Status::Failed(_))
+                            | Some(task_status::Status::Cancelled(_))
+                    )
+                })
+                .count();
+            if active_tasks >= max_total_tasks {
+                return Some(format!(
+                    "Namespace has {} pending or running tasks, the limit is {}",
+                    active_tasks, max_total_tasks
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use ballista_core::serde::protobuf::{
+        job_status, task_status, CompletedTask, JobStatus, PartitionId, QueuedJob,
+        RunningJob, RunningTask, TaskStatus,
+    };
+
+    use super::NamespaceQuota;
+
+    fn job(status: job_status::Status) -> JobStatus {
+        JobStatus {
+            status: Some(status),
+        }
+    }
+
+    fn task(partition_id: u32, status: Option<task_status::Status>) -> TaskStatus {
+        TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_owned(),
+                stage_id: 1,
+                partition_id,
+            }),
+            status,
+        }
+    }
+
+    #[test]
+    fn unlimited() {
+        let quota = NamespaceQuota::default();
+        let mut jobs = HashMap::new();
+        jobs.insert(
+            "a".to_owned(),
+            job(job_status::Status::Running(RunningJob {})),
+        );
+        assert_eq!(quota.check_plan_size(usize::MAX), None);
+        assert_eq!(quota.check_stage_plan_size(1, usize::MAX), None);
+        assert_eq!(quota.check_inline_size("t", usize::MAX, usize::MAX), None);
+        assert_eq!(quota.check_capacity(&jobs, &HashMap::new()), None);
+    }
+
+    #[test]
+    fn plan_size() {
+        let quota = NamespaceQuota {
+            max_plan_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(quota.check_plan_size(100), None);
+        assert!(quota.check_plan_size(101).is_some());
+    }
+
+    #[test]
+    fn stage_plan_size() {
+        let quota = NamespaceQuota {
+            max_stage_plan_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(quota.check_stage_plan_size(1, 100), None);
+        assert_eq!(
+            quota.check_stage_plan_size(2, 101),
+            Some(
+                "Plan of stage 2 is 101 bytes once encoded, which exceeds the limit of 100 bytes"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn inline_size() {
+        let quota = NamespaceQuota {
+            max_inline_rows: Some(10),
+            max_inline_bytes: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(quota.check_inline_size("t", 10, 1000), None);
+        assert!(quota.check_inline_size("t", 11, 1000).is_some());
+        assert!(quota.check_inline_size("t", 10, 1001).is_some());
+    }
+
+    #[test]
+    fn concurrent_jobs() {
+        let quota = NamespaceQuota {
+            max_concurrent_jobs: Some(1),
+            ..Default::default()
+        };
+        let mut jobs = HashMap::new();
+        jobs.insert(
+            "a".to_owned(),
+            job(job_status::Status::Queued(QueuedJob {})),
+        );
+        assert_eq!(quota.check_capacity(&jobs, &HashMap::new()), None);
+        jobs.insert(
+            "b".to_owned(),
+            job(job_status::Status::Running(RunningJob {})),
+        );
+        assert!(quota.check_capacity(&jobs, &HashMap::new()).is_some());
+    }
+
+    #[test]
+    fn total_tasks() {
+        let quota = NamespaceQuota {
+            max_total_tasks: Some(2),
+            ..Default::default()
+        };
+        let mut tasks = HashMap::new();
+        tasks.insert("0".to_owned(), task(0, None));
+        tasks.insert(
+            "1".to_owned(),
+            task(
+                1,
+                Some(task_status::Status::Completed(CompletedTask {
+                    executor_id: "".to_owned(),
+                    partitions: vec![],
+                    job_disk_usage: 0,
+                })),
+            ),
+        );
+        assert_eq!(quota.check_capacity(&HashMap::new(), &tasks), None);
+        tasks.insert(
+            "2".to_owned(),
+            task(
+                2,
+                Some(task_status::Status::Running(RunningTask {
+                    executor_id: "".to_owned(),
+                    num_rows: 0,
+                })),
+            ),
+        );
+        assert!(quota.check_capacity(&HashMap::new(), &tasks).is_some());
+    }
+}
@@ -0,0 +1,234 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Metrics reported to [KEDA](https://keda.sh) through its external scaler interface,
+//! which autoscales the executors with the load of a namespace.
+//!
+//! The `ScaledObject` can set the following metadata on its trigger:
+//!
+//! * `namespace`: the Ballista namespace to report, defaults to the scheduler's
+//! * `targetInflightTasks`: the pending or running tasks per executor, defaults to 1
+//! * `targetQueuedJobs`: the queued jobs per executor, defaults to 1
+
+use std::collections::HashMap;
+
+use ballista_core::serde::protobuf::{job_status, task_status, JobStatus, TaskStatus};
+
+use crate::externalscaler::{MetricSpec, ScaledObjectRef};
+
+/// Number of tasks that are pending or running
+pub const INFLIGHT_TASKS_METRIC_NAME: &str = "inflight_tasks";
+/// Number of jobs waiting for the namespace to have the capacity to run them
+pub const QUEUED_JOBS_METRIC_NAME: &str = "queued_jobs";
+
+const NAMESPACE_KEY: &str = "namespace";
+const TARGET_INFLIGHT_TASKS_KEY: &str = "targetInflightTasks";
+const TARGET_QUEUED_JOBS_KEY: &str = "targetQueuedJobs";
+
+/// The load of a namespace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScalerMetrics {
+    /// Tasks waiting for an executor
+    pub pending_tasks: usize,
+    /// Tasks assigned to an executor
+    pub running_tasks: usize,
+    /// Jobs waiting for the namespace quota to allow them to run
+    pub queued_jobs: usize,
+}
+
+impl ScalerMetrics {
+    /// Computes the metrics from the jobs and tasks of a namespace
+    pub fn new(
+        jobs: &HashMap<String, JobStatus>,
+        tasks: &HashMap<String, TaskStatus>,
+    ) -> Self {
+        let mut metrics = Self {
+            queued_jobs: jobs
+                .values()
+                .filter(|job| matches!(job.status, Some(job_status::Status::Queued(_))))
+                .count(),
+            ..Default::default()
+        };
+        for task in tasks.values() {
+            match task.status {
+                None => metrics.pending_tasks += 1,
+                Some(task_status::Status::Running(_)) => metrics.running_tasks += 1,
+                Some(_) => {}
+            }
+        }
+        metrics
+    }
+
+    /// Tasks that are pending or running
+    pub fn inflight_tasks(&self) -> usize {
+        self.pending_tasks + self.running_tasks
+    }
+
+    /// Whether the namespace needs executors
+    pub fn is_active(&self) -> bool {
+        self.inflight_tasks() > 0 || self.queued_jobs > 0
+    }
+
+    /// The value of the metric called `metric_name`, if there is one
+    pub fn value(&self, metric_name: &str) -> Option<i64> {
+        match metric_name {
+            INFLIGHT_TASKS_METRIC_NAME => Some(self.inflight_tasks() as i64),
+            QUEUED_JOBS_METRIC_NAME => Some(self.queued_jobs as i64),
+            _ => None,
+        }
+    }
+}
+
+/// The namespace requested by the metadata of a `ScaledObject`, if any
+pub fn requested_namespace(scaled_object: &ScaledObjectRef) -> Option<&str> {
+    scaled_object
+        .scaler_metadata
+        .get(NAMESPACE_KEY)
+        .map(|namespace| namespace.as_str())
+        .filter(|namespace| !namespace.is_empty())
+}
+
+/// The metrics reported for a `ScaledObject`, with the targets of its metadata
+pub fn metric_specs(scaled_object: &ScaledObjectRef) -> Result<Vec<MetricSpec>, String> {
+    let target = |key: &str| match scaled_object.scaler_metadata.get(key) {
+        Some(value) => match value.parse::<i64>() {
+            Ok(target) if target > 0 => Ok(target),
+            _ => Err(format!(
+                "Invalid value '{}' for {}, expected a positive integer",
+                value, key
+            )),
+        },
+        None => Ok(1),
+    };
+    Ok(vec![
+        MetricSpec {
+            metric_name: INFLIGHT_TASKS_METRIC_NAME.to_string(),
+            target_size: target(TARGET_INFLIGHT_TASKS_KEY)?,
+        },
+        MetricSpec {
+            metric_name: QUEUED_JOBS_METRIC_NAME.to_string(),
+            target_size: target(TARGET_QUEUED_JOBS_KEY)?,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use ballista_core::serde::protobuf::{
+        CompletedTask, PartitionId, QueuedJob, RunningJob, RunningTask,
+    };
+
+    use super::*;
+
+    fn scaled_object(metadata: Vec<(&str, &str)>) -> ScaledObjectRef {
+        ScaledObjectRef {
+            name: "ballista-executor".to_owned(),
+            namespace: "default".to_owned(),
+            scaler_metadata: metadata
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn metrics_count_active_tasks_and_queued_jobs() {
+        let jobs = vec![
+            ("j1", job_status::Status::Queued(QueuedJob {})),
+            ("j2", job_status::Status::Queued(QueuedJob {})),
+            ("j3", job_status::Status::Running(RunningJob {})),
+        ]
+        .into_iter()
+        .map(|(id, status)| {
+            (
+                id.to_owned(),
+                JobStatus {
+                    status: Some(status),
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+        let tasks = vec![
+            None,
+            None,
+            Some(task_status::Status::Running(RunningTask {
+                executor_id: "e1".to_owned(),
+                num_rows: 0,
+            })),
+            Some(task_status::Status::Completed(CompletedTask {
+                executor_id: "e1".to_owned(),
+                partitions: vec![],
+                job_disk_usage: 0,
+            })),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, status)| {
+            (
+                i.to_string(),
+                TaskStatus {
+                    partition_id: Some(PartitionId {
+                        job_id: "j3".to_owned(),
+                        stage_id: 1,
+                        partition_id: i as u32,
+                    }),
+                    status,
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+        let metrics = ScalerMetrics::new(&jobs, &tasks);
+        assert_eq!(
+            metrics,
+            ScalerMetrics {
+                pending_tasks: 2,
+                running_tasks: 1,
+                queued_jobs: 2,
+            }
+        );
+        assert!(metrics.is_active());
+        assert_eq!(metrics.value(INFLIGHT_TASKS_METRIC_NAME), Some(3));
+        assert_eq!(metrics.value(QUEUED_JOBS_METRIC_NAME), Some(2));
+        assert_eq!(metrics.value("unknown"), None);
+
+        assert!(!ScalerMetrics::new(&HashMap::new(), &HashMap::new()).is_active());
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(requested_namespace(&scaled_object(vec![])), None);
+        assert_eq!(
+            requested_namespace(&scaled_object(vec![("namespace", "etl")])),
+            Some("etl")
+        );
+
+        let specs = metric_specs(&scaled_object(vec![("targetInflightTasks", "4")]))
+            .unwrap()
+            .into_iter()
+            .map(|spec| (spec.metric_name, spec.target_size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            specs,
+            vec![
+                (INFLIGHT_TASKS_METRIC_NAME.to_owned(), 4),
+                (QUEUED_JOBS_METRIC_NAME.to_owned(), 1)
+            ]
+        );
+        assert!(metric_specs(&scaled_object(vec![("targetQueuedJobs", "0")])).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Sessions keep the tables registered by their SQL statements, such as external tables
+//! or the results of named statements, the functions of their `CREATE FUNCTION`
+//! statements and the settings of their `SET` statements, so that later statements can
+//! reference them.
+//!
+//! The jobs whose results are registered as tables are pinned until the tables are
+//! dropped along with their session, so that the executors keep their shuffle files.
+//!
+//! Sessions are created by the `CreateSession` request and expire once they have not
+//! been used for their time to live. Their settings are also stored in the scheduler
+//! state, so that a session survives a restart of the scheduler without its tables and
+//! functions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ballista_core::config::BallistaConfig;
+use ballista_core::serde::protobuf::SessionStatement;
+use datafusion::prelude::ExecutionContext;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+use crate::create_datafusion_context;
+
+/// Time to live of the sessions, unless configured otherwise
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The sessions of the scheduler, with their DataFusion contexts and settings
+#[derive(Clone)]
+pub struct SessionCatalog {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    ttl: Duration,
+}
+
+struct Session {
+    ctx: ExecutionContext,
+    settings: BTreeMap<String, String>,
+    last_used: Instant,
+}
+
+impl Default for SessionCatalog {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl SessionCatalog {
+    /// Creates a catalog whose sessions expire when they have not been used for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// How long a session is kept after its last use
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Creates a session with the settings, whose context is created with `config`, and
+    /// returns its id
+    pub fn create(
+        &self,
+        settings: BTreeMap<String, String>,
+        config: &BallistaConfig,
+    ) -> String {
+        let session_id: String = {
+            let mut rng = thread_rng();
+            std::iter::repeat(())
+                .map(|()| rng.sample(Alphanumeric))
+                .map(char::from)
+                .take(16)
+                .collect()
+        };
+        self.restore(&session_id, settings, config);
+        session_id
+    }
+
+    /// Adds a session that this scheduler does not have in memory, e.g. one that was
+    /// created before a restart, with a new context
+    pub fn restore(
+        &self,
+        session_id: &str,
+        settings: BTreeMap<String, String>,
+        config: &BallistaConfig,
+    ) {
+        let session = Session {
+            ctx: create_datafusion_context(config),
+            settings,
+            last_used: Instant::now(),
+        };
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(session_id.to_owned(), session);
+    }
+
+    /// Returns the context and the settings of the session, or None if the session does
+    /// not exist or expired. Using a session extends its life.
+    pub fn get(
+        &self,
+        session_id: &str,
+    ) -> Option<(ExecutionContext, BTreeMap<String, String>)> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id)?;
+        if session.last_used.elapsed() >= self.ttl {
+            sessions.remove(session_id);
+            return None;
+        }
+        session.last_used = Instant::now();
+        Some((session.ctx.clone(), session.settings.clone()))
+    }
+
+    /// Returns the context and the settings of the session like [Self::get], without
+    /// extending its life
+    pub fn peek(
+        &self,
+        session_id: &str,
+    ) -> Option<(ExecutionContext, BTreeMap<String, String>)> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id)?;
+        if session.last_used.elapsed() >= self.ttl {
+            return None;
+        }
+        Some((session.ctx.clone(), session.settings.clone()))
+    }
+
+    /// Changes a setting of the session and returns all its settings, or None if the
+    /// session does not exist
+    pub fn set(
+        &self,
+        session_id: &str,
+        key: &str,
+        value: &str,
+    ) -> Option<BTreeMap<String, String>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id)?;
+        session.settings.insert(key.to_owned(), value.to_owned());
+        Some(session.settings.clone())
+    }
+
+    /// Removes the sessions that have not been used for their time to live and returns
+    /// their ids
+    pub fn remove_expired(&self) -> Vec<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let expired = sessions
+            .iter()
+            .filter(|(_, session)| session.last_used.elapsed() >= self.ttl)
+            .map(|(session_id, _)| session_id.clone())
+            .collect::<Vec<_>>();
+        for session_id in &expired {
+            sessions.remove(session_id);
+        }
+        expired
+    }
+}
+
+/// The jobs whose results are registered as tables of sessions, whose shuffle files the
+/// executors keep regardless of their time to live
+#[derive(Clone, Default)]
+pub struct JobPins {
+    /// The number of pins of each job
+    jobs: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl JobPins {
+    /// Pins the job until the returned pin is dropped
+    pub fn pin(&self, job_id: &str) -> JobPin {
+        *self
+            .jobs
+            .lock()
+            .unwrap()
+            .entry(job_id.to_owned())
+            .or_default() += 1;
+        JobPin {
+            pins: self.clone(),
+            job_id: job_id.to_owned(),
+        }
+    }
+
+    /// Returns the ids of the pinned jobs
+    pub fn pinned(&self) -> Vec<String> {
+        self.jobs.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// A pin of a job, which is released when it is dropped, e.g. along with the table of
+/// the result of the job
+pub struct JobPin {
+    pins: JobPins,
+    job_id: String,
+}
+
+impl Drop for JobPin {
+    fn drop(&mut self) {
+        let mut jobs = self.pins.jobs.lock().unwrap();
+        if let Some(count) = jobs.get_mut(&self.job_id) {
+            *count -= 1;
+            if *count == 0 {
+                jobs.remove(&self.job_id);
+            }
+        }
+    }
+}
+
+/// Returns the key and the value of a `SET key = value` or `SET key TO value`
+/// statement, or None if `sql` is another statement. Keys and values may be quoted.
+pub fn parse_set_statement(sql: &str) -> Option<(String, String)> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let keyword = sql.get(..4)?;
+    if !keyword.eq_ignore_ascii_case("set ") {
+        return None;
+    }
+    let assignment = sql[4..].trim_start();
+    let (key, value) = match assignment.find('=') {
+        Some(i) => (&assignment[..i], &assignment[i + 1..]),
+        None => {
+            let i = assignment.to_ascii_lowercase().find(" to ")?;
+            (&assignment[..i], &assignment[i + 4..])
+        }
+    };
+    let unquote = |s: &str| {
+        let s = s.trim();
+        s.strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+            .unwrap_or(s)
+            .to_owned()
+    };
+    let (key, value) = (unquote(key), unquote(value));
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((key, value))
+}
+
+/// Checks that the statements form a DAG: the names are unique and every statement
+/// only depends on named statements that come before it
+pub fn validate_statements(statements: &[SessionStatement]) -> Result<(), String> {
+    if statements.is_empty() {
+        return Err("No statements to execute".to_owned());
+    }
+    let mut names = HashSet::new();
+    for (i, statement) in statements.iter().enumerate() {
+        for dependency in &statement.depends_on {
+            if !names.contains(dependency.as_str()) {
+                return Err(format!(
+                    "Statement {} depends on '{}', which is not the name of an earlier statement",
+                    i, dependency
+                ));
+            }
+        }
+        if !statement.name.is_empty() && !names.insert(statement.name.as_str()) {
+            return Err(format!("Duplicate statement name '{}'", statement.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn statement(name: &str, depends_on: &[&str]) -> SessionStatement {
+        SessionStatement {
+            name: name.to_owned(),
+            sql: "SELECT 1".to_owned(),
+            depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn validate_dag() {
+        let statements = vec![
+            statement("a", &[]),
+            statement("b", &[]),
+            statement("c", &["a", "b"]),
+            statement("", &["c", "a"]),
+        ];
+        assert!(validate_statements(&statements).is_ok());
+    }
+
+    #[test]
+    fn validate_invalid_dags() {
+        assert!(validate_statements(&[]).is_err());
+
+        let forward = vec![statement("a", &["b"]), statement("b", &[])];
+        assert!(validate_statements(&forward).is_err());
+
+        let cycle = vec![statement("a", &["a"])];
+        assert!(validate_statements(&cycle).is_err());
+
+        let duplicate = vec![statement("a", &[]), statement("a", &[])];
+        assert!(validate_statements(&duplicate).is_err());
+    }
+
+    #[test]
+    fn session_context_is_shared() {
+        let catalog = SessionCatalog::default();
+        let config = BallistaConfig::new().unwrap();
+        let s1 = catalog.create(BTreeMap::new(), &config);
+        let s2 = catalog.create(BTreeMap::new(), &config);
+        assert_ne!(s1, s2);
+        let (ctx, _) = catalog.get(&s1).unwrap();
+        assert!(Arc::ptr_eq(&ctx.state, &catalog.get(&s1).unwrap().0.state));
+        assert!(!Arc::ptr_eq(&ctx.state, &catalog.get(&s2).unwrap().0.state));
+        assert!(catalog.get("unknown").is_none());
+    }
+
+    #[test]
+    fn session_settings() {
+        let catalog = SessionCatalog::default();
+        let config = BallistaConfig::new().unwrap();
+        let mut settings = BTreeMap::new();
+        settings.insert("a".to_owned(), "1".to_owned());
+        let session_id = catalog.create(settings, &config);
+
+        let settings = catalog.set(&session_id, "b", "2").unwrap();
+        assert_eq!(2, settings.len());
+        assert_eq!(settings, catalog.get(&session_id).unwrap().1);
+        assert!(catalog.set("unknown", "b", "2").is_none());
+    }
+
+    #[test]
+    fn sessions_expire() {
+        let catalog = SessionCatalog::new(Duration::from_secs(0));
+        let config = BallistaConfig::new().unwrap();
+        let s1 = catalog.create(BTreeMap::new(), &config);
+        let s2 = catalog.create(BTreeMap::new(), &config);
+        assert!(catalog.get(&s1).is_none());
+        assert_eq!(vec![s2], catalog.remove_expired());
+        assert!(catalog.remove_expired().is_empty());
+    }
+
+    #[test]
+    fn set_statements() {
+        let set = |key: &str, value: &str| Some((key.to_owned(), value.to_owned()));
+        assert_eq!(
+            set("ballista.shuffle.partitions", "4"),
+            parse_set_statement("SET ballista.shuffle.partitions = 4")
+        );
+        assert_eq!(
+            set("ballista.shuffle.partitions", "4"),
+            parse_set_statement("set \"ballista.shuffle.partitions\" TO '4';")
+        );
+        assert_eq!(None, parse_set_statement("SELECT 1"));
+        assert_eq!(None, parse_set_statement("SET"));
+        assert_eq!(None, parse_set_statement("SET a ="));
+    }
+
+    #[test]
+    fn job_pins() {
+        let pins = JobPins::default();
+        let first = pins.pin("job");
+        let second = pins.pin("job");
+        assert_eq!(pins.pinned(), vec!["job".to_owned()]);
+        drop(first);
+        assert_eq!(pins.pinned(), vec!["job".to_owned()]);
+        drop(second);
+        assert!(pins.pinned().is_empty());
+    }
+}
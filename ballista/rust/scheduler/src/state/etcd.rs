@@ -22,12 +22,17 @@ use std::task::Poll;
 use crate::state::ConfigBackendClient;
 use ballista_core::error::{ballista_error, Result};
 
-use etcd_client::{GetOptions, LockResponse, WatchOptions, WatchStream, Watcher};
+use etcd_client::{
+    GetOptions, LockResponse, Txn, TxnOp, WatchOptions, WatchStream, Watcher,
+};
 use futures::{Stream, StreamExt};
 use log::warn;
 
 use super::{Lock, Watch, WatchEvent};
 
+/// The maximum number of operations etcd accepts in a transaction by default
+const MAX_TXN_OPS: usize = 128;
+
 /// A [`ConfigBackendClient`] implementation that uses etcd to save cluster configuration.
 #[derive(Clone)]
 pub struct EtcdClient {
@@ -79,16 +84,42 @@ impl ConfigBackendClient for EtcdClient {
             .map(|_| ())
     }
 
-    async fn lock(&self) -> Result<Box<dyn Lock>> {
+    async fn put_txn(&self, ops: Vec<(String, Vec<u8>)>) -> Result<()> {
         let mut etcd = self.etcd.clone();
-        // TODO: make this a namespaced-lock
-        let lock = etcd
-            .lock("/ballista_global_lock", None)
+        let mut ops = ops.into_iter().peekable();
+        // batches larger than what etcd accepts are split into several transactions,
+        // so a reader may see the pairs of the first transactions without the others
+        while ops.peek().is_some() {
+            let txn_ops = ops
+                .by_ref()
+                .take(MAX_TXN_OPS)
+                .map(|(key, value)| TxnOp::put(key, value, None))
+                .collect::<Vec<_>>();
+            etcd.txn(Txn::new().and_then(txn_ops)).await.map_err(|e| {
+                warn!("etcd transaction failed: {}", e);
+                ballista_error("etcd transaction failed")
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut etcd = self.etcd.clone();
+        etcd.delete(key, None)
             .await
             .map_err(|e| {
-                warn!("etcd lock failed: {}", e);
-                ballista_error("etcd lock failed")
-            })?;
+                warn!("etcd delete failed: {}", e);
+                ballista_error("etcd delete failed")
+            })
+            .map(|_| ())
+    }
+
+    async fn lock(&self, key: &str) -> Result<Box<dyn Lock>> {
+        let mut etcd = self.etcd.clone();
+        let lock = etcd.lock(key, None).await.map_err(|e| {
+            warn!("etcd lock failed: {}", e);
+            ballista_error("etcd lock failed")
+        })?;
         Ok(Box::new(EtcdLockGuard { etcd, lock }))
     }
 
@@ -17,25 +17,43 @@
 
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{
-    any::type_name, collections::HashMap, convert::TryInto, sync::Arc, time::Duration,
+    any::type_name,
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryInto,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use datafusion::physical_plan::ExecutionPlan;
 use futures::{Stream, StreamExt};
 use log::{debug, error, info};
 use prost::Message;
-use tokio::sync::OwnedMutexGuard;
-
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+
+use ballista_core::config::{parse_labels, satisfies_constraints};
+use ballista_core::encryption::ShuffleKey;
+use ballista_core::error::Result;
+use ballista_core::execution_plans::{ShuffleWriterExec, UnresolvedShuffleExec};
+use ballista_core::serde::compression::encode_chunks;
+use ballista_core::serde::protobuf::PlanCompression;
 use ballista_core::serde::protobuf::{
-    self, job_status, task_status, CompletedJob, CompletedTask, ExecutorHeartbeat,
-    ExecutorMetadata, FailedJob, FailedTask, JobStatus, PhysicalPlanNode, RunningJob,
-    RunningTask, TaskStatus,
+    self, job_event, job_status, task_status, CancelledTask, CompletedJob, CompletedTask,
+    ExecutorHeartbeat, ExecutorMetadata, FailedJob, FailedTask, JobEvent, JobStatus,
+    JobSubmission, PhysicalPlanNode, RunningJob, RunningTask, SessionMetadata,
+    TaskRescheduled, TaskStatus,
+};
+use ballista_core::serde::protocol::{
+    required_protocol_version, ENCRYPTED_SHUFFLE_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION,
 };
-use ballista_core::serde::scheduler::PartitionStats;
+use ballista_core::serde::scheduler::{PartitionLocation, PartitionStats};
 use ballista_core::{error::BallistaError, serde::scheduler::ExecutorMeta};
-use ballista_core::{error::Result, execution_plans::UnresolvedShuffleExec};
 
+use super::cluster_config::{ClusterConfig, SchedulingPolicy};
+use super::metrics::SchedulerMetrics;
 use super::planner::remove_unresolved_shuffles;
+use super::quota::NamespaceQuota;
 
 #[cfg(feature = "etcd")]
 mod etcd;
@@ -61,7 +79,19 @@ pub trait ConfigBackendClient: Send + Sync {
     /// Saves the value into the provided key, overriding any previous data that might have been associated to that key.
     async fn put(&self, key: String, value: Vec<u8>) -> Result<()>;
 
-    async fn lock(&self) -> Result<Box<dyn Lock>>;
+    /// Saves all the key-value pairs in a single round trip. The backend applies them
+    /// atomically, so readers either see all the new values or none of them, unless
+    /// there are more pairs than the backend accepts in a transaction. Larger batches
+    /// are split, in order, into several transactions that are each atomic, so readers
+    /// may see the first ones applied before the rest.
+    async fn put_txn(&self, ops: Vec<(String, Vec<u8>)>) -> Result<()>;
+
+    /// Removes the key and its data, if the key exists.
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Acquires the lock with the provided name, waiting until whoever holds it releases it.
+    /// Locks with different names are independent of each other.
+    async fn lock(&self, key: &str) -> Result<Box<dyn Lock>>;
 
     /// Watch all events that happen on a specific prefix.
     async fn watch(&self, prefix: String) -> Result<Box<dyn Watch>>;
@@ -82,10 +112,18 @@ pub enum WatchEvent {
     Delete(String),
 }
 
+/// Interval at which the lost and cancelled shuffle inputs of the pending tasks are
+/// handled, see [SchedulerState::reconcile_tasks]
+pub const TASK_RECONCILE_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
-pub(super) struct SchedulerState {
+pub struct SchedulerState {
     config_client: Arc<dyn ConfigBackendClient>,
     namespace: String,
+    cluster_config: Arc<RwLock<ClusterConfig>>,
+    metrics: Arc<SchedulerMetrics>,
+    /// Wakes the executors waiting for a task when tasks may have become schedulable
+    task_notify: Arc<Notify>,
 }
 
 impl SchedulerState {
@@ -93,6 +131,104 @@ impl SchedulerState {
         Self {
             config_client,
             namespace,
+            cluster_config: Arc::new(RwLock::new(ClusterConfig::default())),
+            metrics: Arc::new(SchedulerMetrics::default()),
+            task_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Records the metrics of this state in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<SchedulerMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics(&self) -> &SchedulerMetrics {
+        &self.metrics
+    }
+
+    /// Wakes the executors waiting for a task through `task_notify`, which is shared
+    /// with the states that save the stages and task statuses
+    pub fn with_task_notify(mut self, task_notify: Arc<Notify>) -> Self {
+        self.task_notify = task_notify;
+        self
+    }
+
+    /// Completes once stages or task statuses are saved after this call, which may
+    /// make tasks schedulable. Stages saved by other schedulers are not notified.
+    pub fn tasks_changed(&self) -> Notified<'_> {
+        self.task_notify.notified()
+    }
+
+    /// Returns a state reading and writing the keys of another namespace of the
+    /// same config backend
+    pub fn with_namespace(&self, namespace: &str) -> Self {
+        if namespace == self.namespace {
+            return self.clone();
+        }
+        Self::new(self.config_client.clone(), namespace.to_owned())
+            .with_metrics(self.metrics.clone())
+            .with_task_notify(self.task_notify.clone())
+    }
+
+    /// Returns the cluster configuration currently applied by this scheduler
+    pub fn cluster_config(&self) -> ClusterConfig {
+        self.cluster_config.read().unwrap().clone()
+    }
+
+    /// Stores the cluster configuration in the config backend, from where it is
+    /// applied by all the schedulers of the namespace
+    pub async fn save_cluster_config(&self, config: &ClusterConfig) -> Result<()> {
+        config.validate()?;
+        let key = get_cluster_config_key(&self.namespace);
+        self.config_client.put(key, config.to_json()?).await?;
+        self.apply_cluster_config(config.clone());
+        Ok(())
+    }
+
+    /// Applies the stored cluster configuration, then watches it and applies every
+    /// change to it. Invalid configuration documents are logged and ignored.
+    ///
+    /// The future returned by this function never returns (unless an error happens), so it is wise
+    /// to [tokio::spawn] calls to this method.
+    pub async fn watch_cluster_config_loop(&self) -> Result<()> {
+        let key = get_cluster_config_key(&self.namespace);
+        // start watching before reading the document, so that no change is missed
+        let watch = self.config_client.watch(key.clone()).await?;
+        let value = self.config_client.get(&key).await?;
+        if !value.is_empty() {
+            self.apply_cluster_config_document(&value);
+        }
+        let key = &key;
+        watch
+            .for_each(|event: WatchEvent| async move {
+                match event {
+                    WatchEvent::Put(k, value) if k == *key => {
+                        self.apply_cluster_config_document(&value)
+                    }
+                    WatchEvent::Delete(k) if k == *key => {
+                        self.apply_cluster_config(ClusterConfig::default())
+                    }
+                    _ => {}
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    fn apply_cluster_config_document(&self, json: &[u8]) {
+        match ClusterConfig::from_json(json) {
+            Ok(config) => self.apply_cluster_config(config),
+            Err(e) => error!("Ignoring cluster configuration update: {}", e),
+        }
+    }
+
+    fn apply_cluster_config(&self, config: ClusterConfig) {
+        let mut current = self.cluster_config.write().unwrap();
+        if *current != config {
+            info!("Applying cluster configuration {:?}", config);
+            *current = config;
         }
     }
 
@@ -147,6 +283,10 @@ impl SchedulerState {
         self.config_client.put(key, value).await
     }
 
+    /// Saves the status of a job. The shuffle encryption key and the executor constraints
+    /// of the job are deleted once it completed or failed, as no task of the job is
+    /// assigned anymore, and the time it finished is recorded for the cleanup of its
+    /// shuffle files.
     pub async fn save_job_metadata(
         &self,
         job_id: &str,
@@ -155,7 +295,82 @@ impl SchedulerState {
         debug!("Saving job metadata: {:?}", status);
         let key = get_job_key(&self.namespace, job_id);
         let value = encode_protobuf(status)?;
-        self.config_client.put(key, value).await
+        self.config_client.put(key, value).await?;
+        self.metrics.record_job_status(job_id, status);
+        self.save_job_event(job_id, job_event::Event::JobStatus(status.clone()))
+            .await?;
+        if matches!(
+            status.status,
+            Some(job_status::Status::Completed(_)) | Some(job_status::Status::Failed(_))
+        ) {
+            self.delete_job_key(job_id).await?;
+            self.config_client
+                .delete(&get_job_constraints_key(&self.namespace, job_id))
+                .await?;
+            let finished = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs();
+            let key = get_job_finish_key(&self.namespace, job_id);
+            self.config_client
+                .put(key, finished.to_string().into_bytes())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns when the job completed or failed, or None if it did not finish or
+    /// finished before the times were recorded
+    pub async fn get_job_finish_time(&self, job_id: &str) -> Result<Option<SystemTime>> {
+        let key = get_job_finish_key(&self.namespace, job_id);
+        let value = self.config_client.get(&key).await?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let secs = std::str::from_utf8(&value)
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .ok_or_else(|| {
+                BallistaError::Internal(format!("Invalid finish time of job {}", job_id))
+            })?;
+        Ok(Some(UNIX_EPOCH + Duration::from_secs(secs)))
+    }
+
+    /// Returns the jobs among `job_ids` whose shuffle files executors may delete, which
+    /// are the jobs that finished at least `ttl` ago and the jobs that are unknown or
+    /// finished before the times were recorded, except the `pinned` jobs
+    pub async fn get_expired_jobs(
+        &self,
+        job_ids: &[String],
+        ttl: Duration,
+        pinned: &HashSet<String>,
+    ) -> Result<Vec<String>> {
+        let now = SystemTime::now();
+        let mut expired = vec![];
+        for job_id in job_ids {
+            if pinned.contains(job_id) {
+                continue;
+            }
+            let is_expired = match self.get_job_finish_time(job_id).await? {
+                Some(finished) => now.duration_since(finished).unwrap_or_default() >= ttl,
+                None => {
+                    let value = self
+                        .config_client
+                        .get(&get_job_key(&self.namespace, job_id))
+                        .await?;
+                    value.is_empty()
+                        || matches!(
+                            decode_protobuf::<JobStatus>(&value)?.status,
+                            Some(job_status::Status::Completed(_))
+                                | Some(job_status::Status::Failed(_))
+                        )
+                }
+            };
+            if is_expired {
+                expired.push(job_id.clone());
+            }
+        }
+        Ok(expired)
     }
 
     pub async fn get_job_metadata(&self, job_id: &str) -> Result<JobStatus> {
@@ -171,7 +386,318 @@ impl SchedulerState {
         Ok(value)
     }
 
+    /// Stores the settings of a session and when it was last used
+    pub async fn save_session_metadata(
+        &self,
+        session_id: &str,
+        meta: &SessionMetadata,
+    ) -> Result<()> {
+        let key = get_session_key(&self.namespace, session_id);
+        let value = encode_protobuf(meta)?;
+        self.config_client.put(key, value).await
+    }
+
+    /// Returns the settings of a session, or None if the session does not exist
+    pub async fn get_session_metadata(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionMetadata>> {
+        let key = get_session_key(&self.namespace, session_id);
+        let value = self.config_client.get(&key).await?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(decode_protobuf(&value)?))
+    }
+
+    /// Removes a session that expired
+    pub async fn delete_session_metadata(&self, session_id: &str) -> Result<()> {
+        let key = get_session_key(&self.namespace, session_id);
+        self.config_client.delete(&key).await
+    }
+
+    /// Stores the query submitted as the job until the stages of the job are saved, so
+    /// that a restarted scheduler can plan the job again
+    pub async fn save_job_submission(
+        &self,
+        job_id: &str,
+        submission: &JobSubmission,
+    ) -> Result<()> {
+        let key = get_job_submission_key(&self.namespace, job_id);
+        let value = encode_protobuf(submission)?;
+        self.config_client.put(key, value).await
+    }
+
+    /// Removes the submitted query of a job that does not need to be planned again
+    pub async fn delete_job_submission(&self, job_id: &str) -> Result<()> {
+        let key = get_job_submission_key(&self.namespace, job_id);
+        self.config_client.delete(&key).await
+    }
+
+    /// Returns the submitted queries of the jobs whose stages were not saved, by job id
+    pub async fn get_job_submissions(&self) -> Result<HashMap<String, JobSubmission>> {
+        let prefix = get_job_submission_prefix(&self.namespace);
+        self.config_client
+            .get_from_prefix(&prefix)
+            .await?
+            .into_iter()
+            .map(|(key, bytes)| {
+                let job_id = key.strip_prefix(&prefix).unwrap_or(&key).to_owned();
+                Ok((job_id, decode_protobuf(&bytes)?))
+            })
+            .collect()
+    }
+
+    /// Stores the token that executors require to serve the shuffle partitions of the job
+    pub async fn save_job_token(&self, job_id: &str, token: &str) -> Result<()> {
+        let key = get_job_token_key(&self.namespace, job_id);
+        self.config_client.put(key, token.as_bytes().to_vec()).await
+    }
+
+    /// Returns the shuffle token of the job, which is empty if the job has none
+    pub async fn get_job_token(&self, job_id: &str) -> Result<String> {
+        let key = get_job_token_key(&self.namespace, job_id);
+        let value = self.config_client.get(&key).await?;
+        String::from_utf8(value).map_err(|e| {
+            BallistaError::Internal(format!("Invalid job token for {}: {}", job_id, e))
+        })
+    }
+
+    /// Saves the broadcast variables of a job, as pairs of names and batches encoded by
+    /// [ballista_core::execution_plans::encode_batches], which executors fetch before
+    /// they run the first task of the job
+    pub async fn save_job_broadcasts(
+        &self,
+        job_id: &str,
+        broadcasts: Vec<(String, Vec<u8>)>,
+    ) -> Result<()> {
+        let ops = broadcasts
+            .into_iter()
+            .map(|(name, data)| {
+                (get_job_broadcast_key(&self.namespace, job_id, &name), data)
+            })
+            .collect();
+        self.config_client.put_txn(ops).await
+    }
+
+    /// Returns the broadcast variables of a job saved by [Self::save_job_broadcasts]
+    pub async fn get_job_broadcasts(
+        &self,
+        job_id: &str,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let prefix = get_job_broadcast_prefix(&self.namespace, job_id);
+        Ok(self
+            .config_client
+            .get_from_prefix(&prefix)
+            .await?
+            .into_iter()
+            .map(|(key, data)| (key[prefix.len()..].to_owned(), data))
+            .collect())
+    }
+
+    /// Stores the key that executors encrypt the shuffle files of the job with
+    pub async fn save_job_key(&self, job_id: &str, key: &ShuffleKey) -> Result<()> {
+        let key_path = get_job_key_key(&self.namespace, job_id);
+        self.config_client
+            .put(key_path, key.as_bytes().to_vec())
+            .await
+    }
+
+    /// Returns the shuffle encryption key of the job, which is empty if the shuffle
+    /// files of the job are not encrypted
+    pub async fn get_job_key(&self, job_id: &str) -> Result<Vec<u8>> {
+        let key_path = get_job_key_key(&self.namespace, job_id);
+        self.config_client.get(&key_path).await
+    }
+
+    async fn delete_job_key(&self, job_id: &str) -> Result<()> {
+        let key_path = get_job_key_key(&self.namespace, job_id);
+        self.config_client.delete(&key_path).await
+    }
+
+    /// Stores the labels that the executors running the tasks of the job must have
+    pub async fn save_job_constraints(
+        &self,
+        job_id: &str,
+        constraints: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let key = get_job_constraints_key(&self.namespace, job_id);
+        let value = constraints
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.config_client.put(key, value.into_bytes()).await
+    }
+
+    /// Returns the executor constraints of the unfinished jobs that have any, by job id
+    pub async fn get_all_job_constraints(
+        &self,
+    ) -> Result<HashMap<String, BTreeMap<String, String>>> {
+        let prefix = get_job_constraints_prefix(&self.namespace);
+        self.config_client
+            .get_from_prefix(&prefix)
+            .await?
+            .into_iter()
+            .map(|(key, value)| {
+                let job_id = key[prefix.len()..].to_owned();
+                let value = String::from_utf8(value).map_err(|e| {
+                    BallistaError::Internal(format!(
+                        "Invalid executor constraints for {}: {}",
+                        job_id, e
+                    ))
+                })?;
+                Ok((job_id, parse_labels(&value)?))
+            })
+            .collect()
+    }
+
+    /// Appends an event to the event log of the job
+    pub async fn save_job_event(
+        &self,
+        job_id: &str,
+        event: job_event::Event,
+    ) -> Result<()> {
+        let (key, value) = self.job_event_entry(job_id, event)?;
+        self.config_client.put(key, value).await
+    }
+
+    /// Returns the key and value that store the event in the event log of the job
+    fn job_event_entry(
+        &self,
+        job_id: &str,
+        event: job_event::Event,
+    ) -> Result<(String, Vec<u8>)> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards");
+        let event = JobEvent {
+            timestamp: now.as_millis() as u64,
+            event: Some(event),
+        };
+        // the random suffix keeps events that happen at the same time from
+        // overwriting each other
+        let suffix: String = {
+            let mut rng = thread_rng();
+            std::iter::repeat(())
+                .map(|()| rng.sample(Alphanumeric))
+                .map(char::from)
+                .take(7)
+                .collect()
+        };
+        let key = get_job_event_key(&self.namespace, job_id, now.as_nanos(), &suffix);
+        let value = encode_protobuf(&event)?;
+        Ok((key, value))
+    }
+
+    /// Returns the event log of the job, oldest event first
+    pub async fn get_job_events(&self, job_id: &str) -> Result<Vec<JobEvent>> {
+        let mut entries = self
+            .config_client
+            .get_from_prefix(&get_job_event_prefix(&self.namespace, job_id))
+            .await?;
+        // the keys start with the time of the event
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+            .into_iter()
+            .map(|(_key, bytes)| decode_protobuf(&bytes))
+            .collect()
+    }
+
+    pub async fn get_all_jobs(&self) -> Result<HashMap<String, JobStatus>> {
+        self.config_client
+            .get_from_prefix(&get_job_prefix(&self.namespace))
+            .await?
+            .into_iter()
+            .map(|(key, bytes)| Ok((key, decode_protobuf(&bytes)?)))
+            .collect()
+    }
+
+    /// Returns a description of the violated limit if the namespace does not have the
+    /// capacity to start running another job
+    pub async fn check_quota(&self, quota: &NamespaceQuota) -> Result<Option<String>> {
+        if quota.max_concurrent_jobs.is_none() && quota.max_total_tasks.is_none() {
+            return Ok(None);
+        }
+        let jobs = self.get_all_jobs().await?;
+        let tasks = self.get_all_tasks().await?;
+        Ok(quota.check_capacity(&jobs, &tasks))
+    }
+
     pub async fn save_task_status(&self, status: &TaskStatus) -> Result<()> {
+        self.save_task_statuses(std::slice::from_ref(status)).await
+    }
+
+    /// Saves the statuses, along with their job events, in one batch. The caller holds
+    /// the locks of the jobs of the statuses.
+    pub async fn save_task_statuses(&self, statuses: &[TaskStatus]) -> Result<()> {
+        let mut ops = Vec::with_capacity(statuses.len() * 2);
+        for status in statuses {
+            ops.extend(self.task_status_entries(status)?);
+        }
+        self.config_client.put_txn(ops).await?;
+        for status in statuses {
+            self.metrics.record_task_status(status);
+        }
+        // completed tasks may resolve the inputs of other stages
+        if !statuses.is_empty() {
+            self.task_notify.notify_waiters();
+        }
+        Ok(())
+    }
+
+    /// Saves the progress that executors report for the tasks they are running. A report
+    /// only replaces the status of a task that is still running on the executor that
+    /// sent it, and is not logged as a job event. The caller holds the locks of the
+    /// jobs of the statuses.
+    pub async fn save_task_progress(&self, statuses: &[TaskStatus]) -> Result<()> {
+        let mut ops = vec![];
+        for status in statuses {
+            let (partition_id, executor_id) = match (&status.partition_id, &status.status)
+            {
+                (Some(partition_id), Some(task_status::Status::Running(running))) => {
+                    (partition_id, &running.executor_id)
+                }
+                _ => continue,
+            };
+            let key = get_task_status_key(
+                &self.namespace,
+                &partition_id.job_id,
+                partition_id.stage_id as usize,
+                partition_id.partition_id as usize,
+            );
+            let value = self.config_client.get(&key).await?;
+            if value.is_empty() {
+                continue;
+            }
+            // the task may have been cancelled or rescheduled in the meantime
+            let current: TaskStatus = decode_protobuf(&value)?;
+            if matches!(
+                &current.status,
+                Some(task_status::Status::Running(running))
+                    if running.executor_id == *executor_id
+            ) {
+                ops.push((key, encode_protobuf(status)?));
+            }
+        }
+        if !ops.is_empty() {
+            self.config_client.put_txn(ops).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the statuses of the tasks of a job
+    pub async fn get_job_tasks(&self, job_id: &str) -> Result<Vec<TaskStatus>> {
+        self.config_client
+            .get_from_prefix(&get_task_prefix_for_job(&self.namespace, job_id))
+            .await?
+            .into_iter()
+            .map(|(_key, value)| decode_protobuf(&value))
+            .collect()
+    }
+
+    /// Returns the entries that store the status of a task and its job event
+    fn task_status_entries(&self, status: &TaskStatus) -> Result<Vec<(String, Vec<u8>)>> {
         let partition_id = status.partition_id.as_ref().unwrap();
         let key = get_task_status_key(
             &self.namespace,
@@ -179,8 +705,15 @@ impl SchedulerState {
             partition_id.stage_id as usize,
             partition_id.partition_id as usize,
         );
-        let value = encode_protobuf(status)?;
-        self.config_client.put(key, value).await
+        let mut entries = vec![(key, encode_protobuf(status)?)];
+        // pending tasks are not logged, as every task of the job starts as one
+        if status.status.is_some() {
+            entries.push(self.job_event_entry(
+                &partition_id.job_id,
+                job_event::Event::TaskStatus(status.clone()),
+            )?);
+        }
+        Ok(entries)
     }
 
     pub async fn _get_task_status(
@@ -201,19 +734,53 @@ impl SchedulerState {
         Ok(value)
     }
 
-    // "Unnecessary" lifetime syntax due to https://github.com/rust-lang/rust/issues/63033
-    pub async fn save_stage_plan<'a>(
-        &'a self,
-        job_id: &'a str,
+    /// Saves the plans of the stages and a pending status for each of their tasks in one
+    /// batch, where the plan of each stage precedes its tasks so that no task is visible
+    /// before the plan of its stage
+    pub async fn save_stages(
+        &self,
+        job_id: &str,
+        stages: &[Arc<ShuffleWriterExec>],
+    ) -> Result<()> {
+        let mut ops = vec![];
+        let mut statuses = vec![];
+        for stage in stages {
+            ops.push(self.stage_plan_entry(job_id, stage.stage_id(), stage.clone())?);
+            let num_partitions = stage.output_partitioning().partition_count();
+            for partition_id in 0..num_partitions {
+                let pending_status = TaskStatus {
+                    partition_id: Some(protobuf::PartitionId {
+                        job_id: job_id.to_owned(),
+                        stage_id: stage.stage_id() as u32,
+                        partition_id: partition_id as u32,
+                    }),
+                    status: None,
+                };
+                ops.extend(self.task_status_entries(&pending_status)?);
+                statuses.push(pending_status);
+            }
+        }
+        // the tasks of a job are only written under its lock
+        let mut lock = self.lock_job(job_id).await?;
+        let saved = self.config_client.put_txn(ops).await;
+        lock.unlock().await;
+        saved?;
+        for status in &statuses {
+            self.metrics.record_task_status(status);
+        }
+        self.task_notify.notify_waiters();
+        Ok(())
+    }
+
+    fn stage_plan_entry(
+        &self,
+        job_id: &str,
         stage_id: usize,
         plan: Arc<dyn ExecutionPlan>,
-    ) -> Result<()> {
+    ) -> Result<(String, Vec<u8>)> {
         let key = get_stage_plan_key(&self.namespace, job_id, stage_id);
-        let value = {
-            let proto: PhysicalPlanNode = plan.try_into()?;
-            encode_protobuf(&proto)?
-        };
-        self.config_client.clone().put(key, value).await
+        let proto: PhysicalPlanNode = plan.try_into()?;
+        Ok((key, encode_protobuf(&proto)?))
     }
 
     pub async fn get_stage_plan(
@@ -233,6 +800,50 @@ impl SchedulerState {
         Ok((&value).try_into()?)
     }
 
+    /// Saves the encoded plan of a task in chunks of at most `chunk_size` bytes, which
+    /// the executor of the task streams because the plan is too large to send inline
+    pub async fn save_task_plan(
+        &self,
+        task_id: &protobuf::PartitionId,
+        plan: &PhysicalPlanNode,
+        chunk_size: usize,
+        compression: PlanCompression,
+    ) -> Result<()> {
+        let key = get_task_plan_key(&self.namespace, task_id);
+        let chunks = encode_chunks(plan, compression, chunk_size)?;
+        let mut ops = vec![(key.clone(), chunks.len().to_string().into_bytes())];
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            ops.push((format!("{}/{}", key, i), chunk));
+        }
+        self.config_client.put_txn(ops).await
+    }
+
+    /// Returns the chunks of the encoded plan of a task, in order
+    pub async fn get_task_plan(
+        &self,
+        task_id: &protobuf::PartitionId,
+    ) -> Result<Vec<Vec<u8>>> {
+        let key = get_task_plan_key(&self.namespace, task_id);
+        let value = self.config_client.get(&key).await?;
+        if value.is_empty() {
+            return Err(BallistaError::General(format!(
+                "No task plan found for {}",
+                key
+            )));
+        }
+        let num_chunks = String::from_utf8(value)
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .ok_or_else(|| {
+                BallistaError::Internal(format!("Invalid chunk count for {}", key))
+            })?;
+        let mut chunks = Vec::with_capacity(num_chunks);
+        for i in 0..num_chunks {
+            chunks.push(self.config_client.get(&format!("{}/{}", key, i)).await?);
+        }
+        Ok(chunks)
+    }
+
     pub async fn get_all_tasks(&self) -> Result<HashMap<String, TaskStatus>> {
         self.config_client
             .get_from_prefix(&get_task_prefix(&self.namespace))
@@ -242,66 +853,235 @@ impl SchedulerState {
             .collect()
     }
 
+    /// Returns the current status of the task, or None if the task does not exist
+    async fn current_task_status(
+        &self,
+        partition_id: &protobuf::PartitionId,
+    ) -> Result<Option<TaskStatus>> {
+        let key = get_task_status_key(
+            &self.namespace,
+            &partition_id.job_id,
+            partition_id.stage_id as usize,
+            partition_id.partition_id as usize,
+        );
+        let value = self.config_client.get(&key).await?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(decode_protobuf(&value)?))
+    }
+
     /// This function ensures that the task wasn't assigned to an executor that died.
-    /// If that is the case, then the task is re-scheduled.
-    /// Returns true if the task was dead, false otherwise.
+    /// If that is the case, then the task is re-scheduled, or failed if it was already
+    /// rescheduled `max_task_reschedules` times.
+    /// Returns true if the task was dead, or was updated since `task_status` was read,
+    /// false otherwise.
     async fn reschedule_dead_task(
         &self,
         task_status: &TaskStatus,
         executors: &[ExecutorMeta],
+        max_task_reschedules: Option<u32>,
     ) -> Result<bool> {
         let executor_id: &str = match &task_status.status {
             Some(task_status::Status::Completed(CompletedTask {
                 executor_id, ..
             })) => executor_id,
-            Some(task_status::Status::Running(RunningTask { executor_id })) => {
+            Some(task_status::Status::Running(RunningTask { executor_id, .. })) => {
                 executor_id
             }
             _ => return Ok(false),
         };
-        let executor_meta = executors.iter().find(|exec| exec.id == executor_id);
-        let task_is_dead = executor_meta.is_none();
-        if task_is_dead {
-            info!(
-                "Executor {} isn't alive. Rescheduling task {:?}",
-                executor_id,
-                task_status.partition_id.as_ref().unwrap()
-            );
-            // Task was handled in an executor that isn't alive anymore, so we can't resolve it
-            // We mark the task as pending again and continue
-            let mut task_status = task_status.clone();
-            task_status.status = None;
-            self.save_task_status(&task_status).await?;
+        if executors.iter().any(|exec| exec.id == executor_id) {
+            return Ok(false);
         }
-        Ok(task_is_dead)
+        // the executors write the statuses of the tasks under the lock of their job
+        let job_id = task_status.partition_id.as_ref().unwrap().job_id.clone();
+        let mut lock = self.lock_job(&job_id).await?;
+        let result = self
+            .reschedule_lost_task(task_status, executor_id, max_task_reschedules)
+            .await;
+        lock.unlock().await;
+        result.map(|()| true)
     }
 
+    /// Reschedules or fails a task that was lost with its executor. The caller holds the
+    /// lock of the job of the task.
+    async fn reschedule_lost_task(
+        &self,
+        task_status: &TaskStatus,
+        executor_id: &str,
+        max_task_reschedules: Option<u32>,
+    ) -> Result<()> {
+        let partition_id = task_status.partition_id.clone().unwrap();
+        let job_id = partition_id.job_id.clone();
+        // the task may have been updated since its status was read, in which case it is
+        // looked at again on the next poll
+        if self.current_task_status(&partition_id).await?.as_ref() != Some(task_status) {
+            return Ok(());
+        }
+        let reschedules_key = get_task_reschedules_key(
+            &self.namespace,
+            &job_id,
+            partition_id.stage_id as usize,
+            partition_id.partition_id as usize,
+        );
+        let value = self.config_client.get(&reschedules_key).await?;
+        let reschedules = if value.is_empty() {
+            0
+        } else {
+            std::str::from_utf8(&value)
+                .ok()
+                .and_then(|count| count.parse::<u32>().ok())
+                .ok_or_else(|| {
+                    BallistaError::Internal(format!(
+                        "Invalid reschedule count of task {:?}",
+                        partition_id
+                    ))
+                })?
+        };
+        if let Some(max_task_reschedules) = max_task_reschedules {
+            if reschedules >= max_task_reschedules {
+                info!(
+                    "Executor {} isn't alive. Failing task {:?}, which was already rescheduled {} times",
+                    executor_id, partition_id, reschedules
+                );
+                let mut task_status = task_status.clone();
+                task_status.status = Some(task_status::Status::Failed(FailedTask {
+                    error: format!(
+                        "Task was lost with executor {} after being rescheduled {} times",
+                        executor_id, reschedules
+                    ),
+                }));
+                self.save_task_status(&task_status).await?;
+                return Ok(());
+            }
+        }
+        info!(
+            "Executor {} isn't alive. Rescheduling task {:?}",
+            executor_id, partition_id
+        );
+        // Task was handled in an executor that isn't alive anymore, so we can't resolve it
+        // We mark the task as pending again and continue, counting the reschedule along
+        // with its job event
+        let mut ops = vec![
+            (reschedules_key, (reschedules + 1).to_string().into_bytes()),
+            self.job_event_entry(
+                &job_id,
+                job_event::Event::TaskRescheduled(TaskRescheduled {
+                    partition_id: Some(partition_id),
+                    executor_id: executor_id.to_owned(),
+                }),
+            )?,
+        ];
+        let mut task_status = task_status.clone();
+        task_status.status = None;
+        ops.extend(self.task_status_entries(&task_status)?);
+        self.config_client.put_txn(ops).await?;
+        self.metrics.record_task_status(&task_status);
+        self.task_notify.notify_waiters();
+        Ok(())
+    }
+
+    /// Finds a pending task whose shuffle inputs are all available and assigns it to
+    /// the executor. With the [SchedulingPolicy::PreferLocal] policy, the schedulable
+    /// task with the largest fraction of its shuffle input partitions located on the
+    /// executor is preferred, to reduce the amount of data fetched from other executors.
+    /// Tasks of jobs whose executor constraints the executor's labels do not satisfy
+    /// are left to other executors.
+    ///
+    /// This only reads the state besides assigning the task: the inputs lost with their
+    /// executors and the stages that reached their limit are handled by
+    /// [Self::reconcile_tasks].
     pub async fn assign_next_schedulable_task(
         &self,
         executor_id: &str,
     ) -> Result<Option<(TaskStatus, Arc<dyn ExecutionPlan>)>> {
         let tasks = self.get_all_tasks().await?;
-        // TODO: Make the duration a configurable parameter
+        let cluster_config = self.cluster_config();
         let executors = self
-            .get_alive_executors_metadata(Duration::from_secs(60))
+            .get_alive_executors_metadata(cluster_config.executor_timeout())
             .await?;
+        let executor = executors.iter().find(|exec| exec.id == executor_id);
+        let executor_labels =
+            executor.map(|exec| exec.labels.clone()).unwrap_or_default();
+        let executor_protocol_version = executor
+            .map(|exec| exec.protocol_version)
+            .unwrap_or(MIN_PROTOCOL_VERSION);
+        // read once, as the constraints of a job do not change
+        let job_constraints = self.get_all_job_constraints().await?;
+        let no_constraints = BTreeMap::new();
+        // the plans of the stages and the tokens of the jobs are read once per scan,
+        // and are None if the executor cannot run their tasks
+        let mut stage_plans: HashMap<(&str, u32), Option<Arc<dyn ExecutionPlan>>> =
+            HashMap::new();
+        let mut job_tokens: HashMap<&str, Option<String>> = HashMap::new();
+        // the schedulable task with the best locality so far
+        let mut best_task = None;
         'tasks: for (_key, status) in tasks.iter() {
             if status.status.is_none() {
                 let partition = status.partition_id.as_ref().unwrap();
-                let plan = self
-                    .get_stage_plan(&partition.job_id, partition.stage_id as usize)
-                    .await?;
+                let job_id = partition.job_id.as_str();
+                let constraints = job_constraints.get(job_id).unwrap_or(&no_constraints);
+                if !satisfies_constraints(&executor_labels, constraints) {
+                    continue;
+                }
+                let stage = (job_id, partition.stage_id);
+                let plan = match stage_plans.get(&stage) {
+                    Some(plan) => plan.clone(),
+                    None => {
+                        let plan = self
+                            .executable_stage_plan(
+                                job_id,
+                                partition.stage_id,
+                                executor_id,
+                                executor_protocol_version,
+                            )
+                            .await?;
+                        stage_plans.insert(stage, plan.clone());
+                        plan
+                    }
+                };
+                let plan = match plan {
+                    Some(plan) => plan,
+                    None => continue,
+                };
+                let auth_token = match job_tokens.get(job_id) {
+                    Some(auth_token) => auth_token.clone(),
+                    None => {
+                        let auth_token = self
+                            .executable_job_token(
+                                job_id,
+                                executor_id,
+                                executor_protocol_version,
+                            )
+                            .await?;
+                        job_tokens.insert(job_id, auth_token.clone());
+                        auth_token
+                    }
+                };
+                let auth_token = match auth_token {
+                    Some(auth_token) => auth_token,
+                    None => continue,
+                };
 
                 // Let's try to resolve any unresolved shuffles we find
                 let unresolved_shuffles = find_unresolved_shuffles(&plan)?;
                 let mut partition_locations: HashMap<
                     usize, // stage id
                     HashMap<
-                        usize, // shuffle output partition id
-                        Vec<ballista_core::serde::scheduler::PartitionLocation>, // shuffle partitions
+                        usize,                  // shuffle output partition id
+                        Vec<PartitionLocation>, // shuffle partitions
                     >,
                 > = HashMap::new();
                 for unresolved_shuffle in unresolved_shuffles {
+                    let limit_reached = match unresolved_shuffle.limit {
+                        Some(limit) => {
+                            let stage_tasks =
+                                self.stage_tasks(&tasks, job_id, &unresolved_shuffle);
+                            completed_rows(&stage_tasks) >= limit as u64
+                        }
+                        None => false,
+                    };
                     // we schedule one task per *input* partition and each input partition
                     // can produce multiple output partitions
                     for shuffle_input_partition_id in
@@ -310,23 +1090,34 @@ impl SchedulerState {
                         let referenced_task = tasks
                             .get(&get_task_status_key(
                                 &self.namespace,
-                                &partition.job_id,
+                                job_id,
                                 unresolved_shuffle.stage_id,
                                 shuffle_input_partition_id,
                             ))
                             .unwrap();
-                        let task_is_dead = self
-                            .reschedule_dead_task(referenced_task, &executors)
-                            .await?;
-                        if task_is_dead {
-                            continue 'tasks;
-                        } else if let Some(task_status::Status::Completed(
-                            CompletedTask {
-                                executor_id,
-                                partitions,
-                            },
-                        )) = &referenced_task.status
+                        let completed = matches!(
+                            referenced_task.status,
+                            Some(task_status::Status::Completed(_))
+                        );
+                        if limit_reached && !completed {
+                            // the task is cancelled, so none of its partitions are read
+                            continue;
+                        }
+                        if let Some(task_status::Status::Completed(CompletedTask {
+                            executor_id,
+                            partitions,
+                            ..
+                        })) = &referenced_task.status
                         {
+                            let executor_meta = match executors
+                                .iter()
+                                .find(|exec| exec.id == *executor_id)
+                            {
+                                Some(executor_meta) => executor_meta,
+                                // the partitions were lost with the executor, and the
+                                // task is rescheduled by [Self::reconcile_tasks]
+                                None => continue 'tasks,
+                            };
                             debug!("Task for unresolved shuffle input partition {} completed and produced these shuffle partitions:\n\t{}",
                                 shuffle_input_partition_id,
                                 partitions.iter().map(|p| format!("{}={}", p.partition_id, &p.path)).collect::<Vec<_>>().join("\n\t")
@@ -334,35 +1125,30 @@ impl SchedulerState {
                             let stage_shuffle_partition_locations = partition_locations
                                 .entry(unresolved_shuffle.stage_id)
                                 .or_insert_with(HashMap::new);
-                            let executor_meta = executors
-                                .iter()
-                                .find(|exec| exec.id == *executor_id)
-                                .unwrap()
-                                .clone();
 
                             for shuffle_write_partition in partitions {
                                 let temp = stage_shuffle_partition_locations
                                     .entry(shuffle_write_partition.partition_id as usize)
                                     .or_insert_with(Vec::new);
                                 let executor_meta = executor_meta.clone();
-                                let partition_location =
-                                    ballista_core::serde::scheduler::PartitionLocation {
-                                        partition_id:
-                                            ballista_core::serde::scheduler::PartitionId {
-                                                job_id: partition.job_id.clone(),
-                                                stage_id: unresolved_shuffle.stage_id,
-                                                partition_id: shuffle_write_partition
-                                                    .partition_id
-                                                    as usize,
-                                            },
-                                        executor_meta,
-                                        partition_stats: PartitionStats::new(
-                                            Some(shuffle_write_partition.num_rows),
-                                            Some(shuffle_write_partition.num_batches),
-                                            Some(shuffle_write_partition.num_bytes),
-                                        ),
-                                        path: shuffle_write_partition.path.clone(),
-                                    };
+                                let partition_location = PartitionLocation {
+                                    partition_id:
+                                        ballista_core::serde::scheduler::PartitionId {
+                                            job_id: partition.job_id.clone(),
+                                            stage_id: unresolved_shuffle.stage_id,
+                                            partition_id: shuffle_write_partition
+                                                .partition_id
+                                                as usize,
+                                        },
+                                    executor_meta,
+                                    partition_stats: PartitionStats::new(
+                                        Some(shuffle_write_partition.num_rows),
+                                        Some(shuffle_write_partition.num_batches),
+                                        Some(shuffle_write_partition.num_bytes),
+                                    ),
+                                    path: shuffle_write_partition.path.clone(),
+                                    auth_token: auth_token.clone(),
+                                };
                                 debug!(
                                     "Scheduler storing stage {} output partition {} path: {}",
                                     unresolved_shuffle.stage_id,
@@ -381,24 +1167,284 @@ impl SchedulerState {
                     }
                 }
 
+                // If we get here, there are no more unresolved shuffles and the task can be run
+                let score = locality_score(&partition_locations, executor_id);
+                let is_better = match &best_task {
+                    Some((best_score, _, _, _)) => score > *best_score,
+                    None => true,
+                };
+                if is_better {
+                    // either any schedulable task will do, the task reads no shuffle
+                    // partitions that could be local, or all the inputs of this one are
+                    // local and no task can do better
+                    let done = cluster_config.scheduling_policy
+                        == SchedulingPolicy::FirstAvailable
+                        || partition_locations.is_empty()
+                        || score >= 1.0;
+                    best_task = Some((score, status, plan, partition_locations));
+                    if done {
+                        break;
+                    }
+                }
+            }
+        }
+
+        match best_task {
+            Some((score, status, plan, partition_locations)) => {
+                debug!(
+                    "Assigning task {:?} to executor {} with locality score {}",
+                    status.partition_id, executor_id, score
+                );
                 let plan =
                     remove_unresolved_shuffles(plan.as_ref(), &partition_locations)?;
+                let partition_id = status.partition_id.as_ref().unwrap();
+                let mut lock = self.lock_job(&partition_id.job_id).await?;
+                let assigned = self.assign_pending_task(status, executor_id).await;
+                lock.unlock().await;
+                Ok(assigned?.map(|status| (status, plan)))
+            }
+            None => Ok(None),
+        }
+    }
 
-                // If we get here, there are no more unresolved shuffled and the task can be run
-                let mut status = status.clone();
-                status.status = Some(task_status::Status::Running(RunningTask {
-                    executor_id: executor_id.to_owned(),
-                }));
-                self.save_task_status(&status).await?;
-                return Ok(Some((status, plan)));
+    /// Returns the plan of the stage, or None if the executor's protocol version cannot
+    /// decode it
+    async fn executable_stage_plan(
+        &self,
+        job_id: &str,
+        stage_id: u32,
+        executor_id: &str,
+        executor_protocol_version: u32,
+    ) -> Result<Option<Arc<dyn ExecutionPlan>>> {
+        let plan = self.get_stage_plan(job_id, stage_id as usize).await?;
+        // during a rolling upgrade, the tasks that an older executor cannot decode wait
+        // for an upgraded one
+        let required_version = required_protocol_version(plan.as_ref());
+        if executor_protocol_version < required_version {
+            debug!(
+                "Executor {} with protocol version {} cannot run stage {} of job {}, which requires version {}",
+                executor_id, executor_protocol_version, stage_id, job_id, required_version
+            );
+            return Ok(None);
+        }
+        Ok(Some(plan))
+    }
+
+    /// Returns the token that authorizes the fetches of the shuffle partitions of the
+    /// job, or None if the executor's protocol version cannot encrypt them
+    async fn executable_job_token(
+        &self,
+        job_id: &str,
+        executor_id: &str,
+        executor_protocol_version: u32,
+    ) -> Result<Option<String>> {
+        // older executors would write the shuffle files of the job in plain text
+        if executor_protocol_version < ENCRYPTED_SHUFFLE_PROTOCOL_VERSION
+            && !self.get_job_key(job_id).await?.is_empty()
+        {
+            debug!(
+                "Executor {} with protocol version {} cannot encrypt the shuffle files of job {}",
+                executor_id, executor_protocol_version, job_id
+            );
+            return Ok(None);
+        }
+        Ok(Some(self.get_job_token(job_id).await?))
+    }
+
+    /// Reschedules the shuffle inputs of the pending tasks that were lost with their
+    /// executors, and cancels the unfinished inputs of the stages whose limit their
+    /// completed inputs reached. Each stage with pending tasks is looked at once.
+    pub async fn reconcile_tasks(&self) -> Result<()> {
+        let tasks = self.get_all_tasks().await?;
+        let cluster_config = self.cluster_config();
+        let executors = self
+            .get_alive_executors_metadata(cluster_config.executor_timeout())
+            .await?;
+        let pending_stages = tasks
+            .values()
+            .filter(|status| status.status.is_none())
+            .map(|status| {
+                let partition = status.partition_id.as_ref().unwrap();
+                (partition.job_id.as_str(), partition.stage_id)
+            })
+            .collect::<HashSet<_>>();
+        for (job_id, stage_id) in pending_stages {
+            let plan = self.get_stage_plan(job_id, stage_id as usize).await?;
+            for unresolved_shuffle in find_unresolved_shuffles(&plan)? {
+                let limit_reached = match unresolved_shuffle.limit {
+                    Some(limit) => {
+                        self.cancel_tasks_after_limit(
+                            &tasks,
+                            job_id,
+                            &unresolved_shuffle,
+                            limit,
+                        )
+                        .await?
+                    }
+                    None => false,
+                };
+                for input_task in self.stage_tasks(&tasks, job_id, &unresolved_shuffle) {
+                    let completed = matches!(
+                        input_task.status,
+                        Some(task_status::Status::Completed(_))
+                    );
+                    if limit_reached && !completed {
+                        // the task was cancelled, so none of its partitions are read
+                        continue;
+                    }
+                    self.reschedule_dead_task(
+                        input_task,
+                        &executors,
+                        cluster_config.max_task_reschedules,
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls [Self::reconcile_tasks] every [TASK_RECONCILE_INTERVAL].
+    ///
+    /// The future returned by this function never returns, so it is wise to
+    /// [tokio::spawn] calls to this method.
+    pub async fn reconcile_tasks_loop(&self) {
+        let mut interval = tokio::time::interval(TASK_RECONCILE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.reconcile_tasks().await {
+                error!("Could not reconcile the tasks: {}", e);
+            }
+        }
+    }
+
+    /// Marks the task as running on the executor if it is still pending, and returns its
+    /// new status. The caller holds the lock of the job of the task.
+    async fn assign_pending_task(
+        &self,
+        status: &TaskStatus,
+        executor_id: &str,
+    ) -> Result<Option<TaskStatus>> {
+        let partition_id = status.partition_id.as_ref().unwrap();
+        // the task may have been cancelled since its status was read
+        let current = self.current_task_status(partition_id).await?;
+        if !matches!(current, Some(TaskStatus { status: None, .. })) {
+            return Ok(None);
+        }
+        let mut status = status.clone();
+        status.status = Some(task_status::Status::Running(RunningTask {
+            executor_id: executor_id.to_owned(),
+            num_rows: 0,
+        }));
+        self.save_task_status(&status).await?;
+        Ok(Some(status))
+    }
+
+    /// Cancels the pending and running tasks of the stage once its completed tasks
+    /// produced at least `limit` rows, which is all that the consumer of the stage
+    /// needs, and returns whether they did. Executors cannot interrupt a running task,
+    /// but its output is no longer waited for.
+    async fn cancel_tasks_after_limit(
+        &self,
+        tasks: &HashMap<String, TaskStatus>,
+        job_id: &str,
+        stage: &UnresolvedShuffleExec,
+        limit: usize,
+    ) -> Result<bool> {
+        let stage_tasks = self.stage_tasks(tasks, job_id, stage);
+        let rows = completed_rows(&stage_tasks);
+        if rows < limit as u64 {
+            return Ok(false);
+        }
+        let unfinished = stage_tasks
+            .into_iter()
+            .filter(|task| is_unfinished(task))
+            .collect::<Vec<_>>();
+        if !unfinished.is_empty() {
+            let mut lock = self.lock_job(job_id).await?;
+            let cancelled = self
+                .cancel_unfinished_tasks(&unfinished, job_id, stage, rows, limit)
+                .await;
+            lock.unlock().await;
+            cancelled?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the tasks of the stage that produces the unresolved shuffle
+    fn stage_tasks<'a>(
+        &self,
+        tasks: &'a HashMap<String, TaskStatus>,
+        job_id: &str,
+        stage: &UnresolvedShuffleExec,
+    ) -> Vec<&'a TaskStatus> {
+        (0..stage.input_partition_count)
+            .filter_map(|partition_id| {
+                tasks.get(&get_task_status_key(
+                    &self.namespace,
+                    job_id,
+                    stage.stage_id,
+                    partition_id,
+                ))
+            })
+            .collect()
+    }
+
+    /// Cancels the tasks that are still pending or running. The caller holds the lock
+    /// of the job of the tasks.
+    async fn cancel_unfinished_tasks(
+        &self,
+        tasks: &[&TaskStatus],
+        job_id: &str,
+        stage: &UnresolvedShuffleExec,
+        rows: u64,
+        limit: usize,
+    ) -> Result<()> {
+        let mut cancelled = vec![];
+        for task in tasks {
+            // the executors may have finished the tasks since their statuses were read
+            let partition_id = task.partition_id.as_ref().unwrap();
+            match self.current_task_status(partition_id).await? {
+                Some(current) if is_unfinished(&current) => {}
+                _ => continue,
             }
+            cancelled.push(TaskStatus {
+                partition_id: task.partition_id.clone(),
+                status: Some(task_status::Status::Cancelled(CancelledTask {
+                    reason: format!(
+                        "The completed tasks of stage {} produced {} rows, which reach the limit of {}",
+                        stage.stage_id, rows, limit
+                    ),
+                })),
+            });
         }
-        Ok(None)
+        if !cancelled.is_empty() {
+            info!(
+                "Cancelling {} tasks of stage {} of job {}, whose completed tasks produced {} of {} rows",
+                cancelled.len(),
+                stage.stage_id,
+                job_id,
+                rows,
+                limit
+            );
+            self.save_task_statuses(&cancelled).await?;
+        }
+        Ok(())
     }
 
-    // Global lock for the state. We should get rid of this to be able to scale.
+    /// Lock for the decisions that span all the jobs of the namespace, such as assigning
+    /// tasks or admitting jobs. Updates that only concern one job should use [Self::lock_job].
     pub async fn lock(&self) -> Result<Box<dyn Lock>> {
-        self.config_client.lock().await
+        self.config_client
+            .lock(&get_global_lock_key(&self.namespace))
+            .await
+    }
+
+    /// Lock for the state of a single job, which does not block the updates of other jobs
+    pub async fn lock_job(&self, job_id: &str) -> Result<Box<dyn Lock>> {
+        self.config_client
+            .lock(&get_job_lock_key(&self.namespace, job_id))
+            .await
     }
 
     /// This function starts a watch over the task keys. Whenever a task changes, it re-evaluates
@@ -417,7 +1463,7 @@ impl SchedulerState {
                 WatchEvent::Delete(key) => key
             };
             let job_id = extract_job_id_from_task_key(&key).unwrap();
-            match self.lock().await {
+            match self.lock_job(job_id).await {
                 Ok(mut lock) => {
                     if let Err(e) = self.synchronize_job_status(job_id).await {
                         error!("Could not update job status for {}. This job might be stuck forever. Error: {}", job_id, e);
@@ -452,24 +1498,48 @@ impl SchedulerState {
                 );
                 debug!("Old status: {:?}", status);
                 debug!("New status: {:?}", new_status);
+                if let Some(job_status::Status::Failed(_)) = new_status.status {
+                    // the pending tasks would run without the key of the failed job
+                    self.cancel_pending_tasks(job_id).await?;
+                }
                 self.save_job_metadata(job_id, &new_status).await?;
             }
         }
         Ok(())
     }
 
+    /// Cancels the tasks of a failed job that were not assigned yet. The caller holds the
+    /// lock of the job.
+    async fn cancel_pending_tasks(&self, job_id: &str) -> Result<()> {
+        let cancelled = self
+            .get_job_tasks(job_id)
+            .await?
+            .into_iter()
+            .filter(|task| task.status.is_none())
+            .map(|task| TaskStatus {
+                partition_id: task.partition_id,
+                status: Some(task_status::Status::Cancelled(CancelledTask {
+                    reason: format!("Job {} failed", job_id),
+                })),
+            })
+            .collect::<Vec<_>>();
+        if !cancelled.is_empty() {
+            info!(
+                "Cancelling {} pending tasks of failed job {}",
+                cancelled.len(),
+                job_id
+            );
+            self.save_task_statuses(&cancelled).await?;
+        }
+        Ok(())
+    }
+
     async fn get_job_status_from_tasks(
         &self,
         job_id: &str,
         executors: &HashMap<String, ExecutorMeta>,
     ) -> Result<Option<JobStatus>> {
-        let statuses = self
-            .config_client
-            .get_from_prefix(&get_task_prefix_for_job(&self.namespace, job_id))
-            .await?
-            .into_iter()
-            .map(|(_k, v)| decode_protobuf::<TaskStatus>(&v))
-            .collect::<Result<Vec<_>>>()?;
+        let statuses = self.get_job_tasks(job_id).await?;
         if statuses.is_empty() {
             return Ok(None);
         }
@@ -484,12 +1554,21 @@ impl SchedulerState {
             .into_iter()
             .filter(|task| task.partition_id.as_ref().unwrap().stage_id == last_stage)
             .collect();
+        // The stage plan might not be available (e.g. it was never saved), in which case
+        // clients that attach to the job by id will not be able to decode the results
+        let auth_token = self.get_job_token(job_id).await?;
+        let schema: Option<protobuf::Schema> = self
+            .get_stage_plan(job_id, last_stage as usize)
+            .await
+            .ok()
+            .map(|plan| plan.schema().as_ref().into());
         let mut job_status = statuses
             .iter()
             .map(|status| match &status.status {
                 Some(task_status::Status::Completed(CompletedTask {
                     executor_id,
                     partitions,
+                    ..
                 })) => Ok((status, executor_id, partitions)),
                 _ => Err(BallistaError::General("Task not completed".to_string())),
             })
@@ -517,10 +1596,14 @@ impl SchedulerState {
                                 column_stats: vec![],
                             }),
                             path: shuffle_write_partition.path.clone(),
+                            auth_token: auth_token.clone(),
                         });
                     }
                 }
-                job_status::Status::Completed(CompletedJob { partition_location })
+                job_status::Status::Completed(CompletedJob {
+                    partition_location,
+                    schema,
+                })
             });
 
         if job_status.is_none() {
@@ -550,9 +1633,46 @@ pub trait Lock: Send + Sync {
     async fn unlock(&mut self);
 }
 
-#[tonic::async_trait]
-impl<T: Send + Sync> Lock for OwnedMutexGuard<T> {
-    async fn unlock(&mut self) {}
+/// Returns whether the task is pending or running
+/// Returns the rows that the completed tasks produced
+fn completed_rows(tasks: &[&TaskStatus]) -> u64 {
+    tasks
+        .iter()
+        .map(|task| match &task.status {
+            Some(task_status::Status::Completed(CompletedTask {
+                partitions, ..
+            })) => partitions.iter().map(|p| p.num_rows).sum(),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn is_unfinished(task: &TaskStatus) -> bool {
+    matches!(task.status, None | Some(task_status::Status::Running(_)))
+}
+
+/// Returns the fraction of the shuffle input partitions of a task that are located on
+/// the executor, or zero if the task has no shuffle inputs
+fn locality_score(
+    partition_locations: &HashMap<usize, HashMap<usize, Vec<PartitionLocation>>>,
+    executor_id: &str,
+) -> f64 {
+    let (local, total) = partition_locations
+        .values()
+        .flat_map(|stage| stage.values())
+        .flatten()
+        .fold((0, 0), |(local, total), location| {
+            if location.executor_meta.id == executor_id {
+                (local + 1, total + 1)
+            } else {
+                (local, total + 1)
+            }
+        });
+    if total == 0 {
+        0.0
+    } else {
+        local as f64 / total as f64
+    }
 }
 
 /// Returns the the unresolved shuffles in the execution plan
@@ -575,20 +1695,92 @@ fn find_unresolved_shuffles(
     }
 }
 
-fn get_executors_prefix(namespace: &str) -> String {
-    format!("/ballista/{}/executors", namespace)
+// The lock names must not be prefixes of each other, as etcd locks are prefix based
+fn get_global_lock_key(namespace: &str) -> String {
+    format!("/ballista/{}/locks/global", namespace)
+}
+
+fn get_job_lock_key(namespace: &str, job_id: &str) -> String {
+    format!("/ballista/{}/locks/jobs/{}", namespace, job_id)
+}
+
+fn get_executors_prefix(namespace: &str) -> String {
+    format!("/ballista/{}/executors", namespace)
+}
+
+fn get_executor_key(namespace: &str, id: &str) -> String {
+    format!("{}/{}", get_executors_prefix(namespace), id)
+}
+
+fn get_cluster_config_key(namespace: &str) -> String {
+    format!("/ballista/{}/config", namespace)
+}
+
+fn get_job_prefix(namespace: &str) -> String {
+    format!("/ballista/{}/jobs", namespace)
+}
+
+fn get_job_key(namespace: &str, id: &str) -> String {
+    format!("{}/{}", get_job_prefix(namespace), id)
+}
+
+fn get_session_key(namespace: &str, id: &str) -> String {
+    format!("/ballista/{}/sessions/{}", namespace, id)
+}
+
+fn get_job_submission_prefix(namespace: &str) -> String {
+    format!("/ballista/{}/submissions/", namespace)
+}
+
+fn get_job_submission_key(namespace: &str, id: &str) -> String {
+    format!("{}{}", get_job_submission_prefix(namespace), id)
+}
+
+fn get_job_token_key(namespace: &str, id: &str) -> String {
+    format!("/ballista/{}/tokens/{}", namespace, id)
+}
+
+fn get_job_key_key(namespace: &str, id: &str) -> String {
+    format!("/ballista/{}/keys/{}", namespace, id)
+}
+
+fn get_job_constraints_prefix(namespace: &str) -> String {
+    format!("/ballista/{}/constraints/", namespace)
 }
 
-fn get_executor_key(namespace: &str, id: &str) -> String {
-    format!("{}/{}", get_executors_prefix(namespace), id)
+fn get_job_constraints_key(namespace: &str, id: &str) -> String {
+    format!("{}{}", get_job_constraints_prefix(namespace), id)
 }
 
-fn get_job_prefix(namespace: &str) -> String {
-    format!("/ballista/{}/jobs", namespace)
+fn get_job_finish_key(namespace: &str, id: &str) -> String {
+    format!("/ballista/{}/finished/{}", namespace, id)
 }
 
-fn get_job_key(namespace: &str, id: &str) -> String {
-    format!("{}/{}", get_job_prefix(namespace), id)
+fn get_job_broadcast_prefix(namespace: &str, job_id: &str) -> String {
+    format!("/ballista/{}/broadcasts/{}/", namespace, job_id)
+}
+
+fn get_job_broadcast_key(namespace: &str, job_id: &str, name: &str) -> String {
+    format!("{}{}", get_job_broadcast_prefix(namespace, job_id), name)
+}
+
+fn get_job_event_prefix(namespace: &str, job_id: &str) -> String {
+    format!("/ballista/{}/events/{}/", namespace, job_id)
+}
+
+fn get_job_event_key(
+    namespace: &str,
+    job_id: &str,
+    timestamp_nanos: u128,
+    suffix: &str,
+) -> String {
+    // zero padded, so that the keys sort in the order of the events
+    format!(
+        "{}{:020}-{}",
+        get_job_event_prefix(namespace, job_id),
+        timestamp_nanos,
+        suffix
+    )
 }
 
 fn get_task_prefix(namespace: &str) -> String {
@@ -613,6 +1805,18 @@ fn get_task_status_key(
     )
 }
 
+fn get_task_reschedules_key(
+    namespace: &str,
+    job_id: &str,
+    stage_id: usize,
+    partition_id: usize,
+) -> String {
+    format!(
+        "/ballista/{}/reschedules/{}/{}/{}",
+        namespace, job_id, stage_id, partition_id
+    )
+}
+
 fn extract_job_id_from_task_key(job_key: &str) -> Result<&str> {
     job_key.split('/').nth(4).ok_or_else(|| {
         BallistaError::Internal(format!("Unexpected task key: {}", job_key))
@@ -623,6 +1827,13 @@ fn get_stage_plan_key(namespace: &str, job_id: &str, stage_id: usize) -> String
     format!("/ballista/{}/stages/{}/{}", namespace, job_id, stage_id,)
 }
 
+fn get_task_plan_key(namespace: &str, task_id: &protobuf::PartitionId) -> String {
+    format!(
+        "/ballista/{}/task_plans/{}/{}/{}",
+        namespace, task_id.job_id, task_id.stage_id, task_id.partition_id
+    )
+}
+
 fn decode_protobuf<T: Message + Default>(bytes: &[u8]) -> Result<T> {
     T::decode(bytes).map_err(|e| {
         BallistaError::Internal(format!(
@@ -647,18 +1858,35 @@ fn encode_protobuf<T: Message + Default>(msg: &T) -> Result<Vec<u8>> {
 
 #[cfg(all(test, feature = "sled"))]
 mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::convert::TryInto;
     use std::sync::Arc;
+    use std::time::Duration;
 
+    use ballista_core::config::parse_labels;
+    use ballista_core::execution_plans::{ShuffleWriterExec, UnresolvedShuffleExec};
+    use ballista_core::serde::compression::decode_chunks;
     use ballista_core::serde::protobuf::{
-        job_status, task_status, CompletedTask, FailedTask, JobStatus, PartitionId,
-        QueuedJob, RunningJob, RunningTask, TaskStatus,
+        job_event, job_status, task_status, CompletedTask, FailedJob, FailedTask,
+        JobStatus, KeyValuePair, PartitionId, PhysicalPlanNode, PlanCompression,
+        QueuedJob, RunningJob, RunningTask, SessionMetadata, ShuffleWritePartition,
+        TaskStatus,
     };
+    use ballista_core::serde::protocol::PROTOCOL_VERSION;
     use ballista_core::{error::BallistaError, serde::scheduler::ExecutorMeta};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::logical_plan::JoinType;
+    use datafusion::physical_plan::empty::EmptyExec;
+    use datafusion::physical_plan::expressions::Column;
+    use datafusion::physical_plan::hash_join::{HashJoinExec, PartitionMode};
+    use datafusion::physical_plan::ExecutionPlan;
+    use prost::Message;
 
     use super::{
-        extract_job_id_from_task_key, get_task_status_key, SchedulerState,
-        StandaloneClient,
+        extract_job_id_from_task_key, get_task_status_key, locality_score,
+        SchedulerState, StandaloneClient,
     };
+    use crate::cluster_config::ClusterConfig;
 
     #[tokio::test]
     async fn executor_metadata() -> Result<(), BallistaError> {
@@ -670,6 +1898,11 @@ mod test {
             id: "123".to_owned(),
             host: "localhost".to_owned(),
             port: 123,
+            labels: vec![("gpu".to_owned(), "true".to_owned())]
+                .into_iter()
+                .collect(),
+            flight_socket: None,
+            protocol_version: PROTOCOL_VERSION,
         };
         state.save_executor_metadata(meta.clone()).await?;
         let result: Vec<_> = state
@@ -716,6 +1949,64 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn expired_jobs() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let running = JobStatus {
+            status: Some(job_status::Status::Running(RunningJob {})),
+        };
+        let failed = JobStatus {
+            status: Some(job_status::Status::Failed(FailedJob {
+                error: "error".to_string(),
+            })),
+        };
+        state.save_job_metadata("running", &running).await?;
+        assert_eq!(state.get_job_finish_time("running").await?, None);
+        state.save_job_metadata("failed", &failed).await?;
+        assert!(state.get_job_finish_time("failed").await?.is_some());
+        state.save_job_metadata("pinned", &failed).await?;
+
+        let job_ids = ["running", "failed", "pinned", "unknown"]
+            .iter()
+            .map(|job_id| job_id.to_string())
+            .collect::<Vec<_>>();
+        let pinned: HashSet<String> = vec!["pinned".to_string()].into_iter().collect();
+        // the files of unknown jobs are always expired
+        let expired = state
+            .get_expired_jobs(&job_ids, Duration::from_secs(3600), &pinned)
+            .await?;
+        assert_eq!(expired, vec!["unknown".to_string()]);
+        let expired = state
+            .get_expired_jobs(&job_ids, Duration::from_secs(0), &pinned)
+            .await?;
+        assert_eq!(expired, vec!["failed".to_string(), "unknown".to_string()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn session_metadata() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        assert_eq!(None, state.get_session_metadata("session").await?);
+        let meta = SessionMetadata {
+            settings: vec![KeyValuePair {
+                key: "ballista.shuffle.partitions".to_owned(),
+                value: "4".to_owned(),
+            }],
+            last_used: 1000,
+        };
+        state.save_session_metadata("session", &meta).await?;
+        assert_eq!(Some(meta), state.get_session_metadata("session").await?);
+        state.delete_session_metadata("session").await?;
+        assert_eq!(None, state.get_session_metadata("session").await?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn task_status() -> Result<(), BallistaError> {
         let state = SchedulerState::new(
@@ -742,6 +2033,44 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn task_plan_chunks() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let task_id = PartitionId {
+            job_id: "job".to_owned(),
+            stage_id: 1,
+            partition_id: 2,
+        };
+        assert!(state.get_task_plan(&task_id).await.is_err());
+
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(EmptyExec::new(
+            false,
+            Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)])),
+        ));
+        let plan: PhysicalPlanNode = plan.try_into()?;
+        state
+            .save_task_plan(&task_id, &plan, 4, PlanCompression::None)
+            .await?;
+        let chunks = state.get_task_plan(&task_id).await?;
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 4));
+        let decoded = PhysicalPlanNode::decode(chunks.concat().as_slice()).unwrap();
+        assert_eq!(plan, decoded);
+
+        // the compressed plan replaces the previous one
+        state
+            .save_task_plan(&task_id, &plan, 4, PlanCompression::Zstd)
+            .await?;
+        let chunks = state.get_task_plan(&task_id).await?;
+        let decoded: PhysicalPlanNode =
+            decode_chunks(PlanCompression::Zstd, &chunks.concat(), None)?;
+        assert_eq!(plan, decoded);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn task_status_non_existant() -> Result<(), BallistaError> {
         let state = SchedulerState::new(
@@ -764,6 +2093,516 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn job_token() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        assert_eq!(state.get_job_token("job").await?, "");
+        state.save_job_token("job", "token").await?;
+        assert_eq!(state.get_job_token("job").await?, "token");
+        // tokens are not mistaken for jobs
+        assert!(state.get_all_jobs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn job_key() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        assert!(state.get_job_key("job").await?.is_empty());
+        let key = ShuffleKey::generate();
+        state.save_job_key("job", &key).await?;
+        assert_eq!(state.get_job_key("job").await?, key.as_bytes());
+        // keys are not mistaken for jobs
+        assert!(state.get_all_jobs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn job_broadcasts() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        state
+            .save_job_broadcasts("job1", vec![("dim".to_owned(), vec![1, 2, 3])])
+            .await?;
+        state
+            .save_job_broadcasts("job10", vec![("dim".to_owned(), vec![4])])
+            .await?;
+        assert_eq!(
+            state.get_job_broadcasts("job1").await?,
+            vec![("dim".to_owned(), vec![1, 2, 3])]
+        );
+        assert!(state.get_job_broadcasts("job2").await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn job_submissions() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let submission = JobSubmission {
+            plan: None,
+            settings: vec![protobuf::KeyValuePair {
+                key: "ballista.shuffle.partitions".to_owned(),
+                value: "4".to_owned(),
+            }],
+        };
+        state.save_job_submission("job1", &submission).await?;
+        state.save_job_submission("job2", &submission).await?;
+        state.delete_job_submission("job1").await?;
+        let submissions = state.get_job_submissions().await?;
+        assert_eq!(1, submissions.len());
+        assert_eq!(Some(&submission), submissions.get("job2"));
+        // submissions are not mistaken for jobs
+        assert!(state.get_all_jobs().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn executor_constraints() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let executor = |id: &str, labels: &str| ExecutorMeta {
+            id: id.to_owned(),
+            host: "localhost".to_owned(),
+            port: 123,
+            labels: parse_labels(labels).unwrap(),
+            flight_socket: None,
+            protocol_version: PROTOCOL_VERSION,
+        };
+        state
+            .save_executor_metadata(executor("cpu", "zone=us-east-1a"))
+            .await?;
+        state
+            .save_executor_metadata(executor("gpu", "zone=us-east-1a,gpu=true"))
+            .await?;
+
+        let constraints = parse_labels("gpu=true")?;
+        state.save_job_constraints("job", &constraints).await?;
+        let all_constraints = state.get_all_job_constraints().await?;
+        assert_eq!(all_constraints.len(), 1);
+        assert_eq!(all_constraints["job"], constraints);
+
+        let plan = Arc::new(ShuffleWriterExec::try_new(
+            "job".to_owned(),
+            1,
+            Arc::new(EmptyExec::new(false, Arc::new(Schema::empty()))),
+            "".to_owned(),
+            None,
+        )?);
+        state.save_stages("job", &[plan]).await?;
+        state
+            .save_task_status(&TaskStatus {
+                partition_id: Some(PartitionId {
+                    job_id: "job".to_string(),
+                    stage_id: 1,
+                    partition_id: 0,
+                }),
+                status: None,
+            })
+            .await?;
+
+        // the task is only handed out to the executor with the gpu label
+        assert!(state.assign_next_schedulable_task("cpu").await?.is_none());
+        let (status, _plan) = state.assign_next_schedulable_task("gpu").await?.unwrap();
+        assert!(matches!(
+            status.status,
+            Some(task_status::Status::Running(RunningTask { executor_id, .. }))
+                if executor_id == "gpu"
+        ));
+
+        // the constraints are deleted once the job finished
+        let failed = JobStatus {
+            status: Some(job_status::Status::Failed(FailedJob {
+                error: "error".to_string(),
+            })),
+        };
+        state.save_job_metadata("job", &failed).await?;
+        assert!(state.get_all_job_constraints().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn executor_protocol_versions() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let executor = |id: &str, protocol_version: u32| ExecutorMeta {
+            id: id.to_owned(),
+            host: "localhost".to_owned(),
+            port: 123,
+            labels: Default::default(),
+            flight_socket: None,
+            protocol_version,
+        };
+        state.save_executor_metadata(executor("old", 1)).await?;
+        state
+            .save_executor_metadata(executor("new", PROTOCOL_VERSION))
+            .await?;
+
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+        let join = HashJoinExec::try_new(
+            Arc::new(EmptyExec::new(false, schema.clone())),
+            Arc::new(EmptyExec::new(false, schema)),
+            vec![(Column::new("a", 0), Column::new("a", 0))],
+            &JoinType::NullAwareAnti,
+            PartitionMode::CollectLeft,
+            false,
+        )?;
+        let plan = Arc::new(ShuffleWriterExec::try_new(
+            "job".to_owned(),
+            1,
+            Arc::new(join),
+            "".to_owned(),
+            None,
+        )?);
+        state.save_stages("job", &[plan]).await?;
+        state
+            .save_task_status(&TaskStatus {
+                partition_id: Some(PartitionId {
+                    job_id: "job".to_string(),
+                    stage_id: 1,
+                    partition_id: 0,
+                }),
+                status: None,
+            })
+            .await?;
+
+        // the null aware anti join cannot be decoded by the old executor
+        assert!(state.assign_next_schedulable_task("old").await?.is_none());
+        let (status, _plan) = state.assign_next_schedulable_task("new").await?.unwrap();
+        assert!(matches!(
+            status.status,
+            Some(task_status::Status::Running(RunningTask { executor_id, .. }))
+                if executor_id == "new"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn job_events() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let queued = JobStatus {
+            status: Some(job_status::Status::Queued(QueuedJob {})),
+        };
+        let partition_id = PartitionId {
+            job_id: "job".to_string(),
+            stage_id: 1,
+            partition_id: 0,
+        };
+        let pending = TaskStatus {
+            partition_id: Some(partition_id.clone()),
+            status: None,
+        };
+        let failed = TaskStatus {
+            partition_id: Some(partition_id),
+            status: Some(task_status::Status::Failed(FailedTask {
+                error: "error".to_string(),
+            })),
+        };
+        state.save_job_metadata("job", &queued).await?;
+        state.save_task_status(&pending).await?;
+        state.save_task_status(&failed).await?;
+        // the events of other jobs are kept apart
+        state.save_job_metadata("job2", &queued).await?;
+
+        let events = state
+            .get_job_events("job")
+            .await?
+            .into_iter()
+            .map(|event| event.event.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                job_event::Event::JobStatus(queued),
+                job_event::Event::TaskStatus(failed),
+            ]
+        );
+        assert!(state.get_job_events("job3").await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cluster_config_hot_reload() -> Result<(), BallistaError> {
+        let client = Arc::new(StandaloneClient::try_new_temporary()?);
+        let writer = SchedulerState::new(client.clone(), "test".to_string());
+        let reader = SchedulerState::new(client, "test".to_string());
+        let watcher = reader.clone();
+        tokio::spawn(async move { watcher.watch_cluster_config_loop().await });
+        assert_eq!(reader.cluster_config(), ClusterConfig::default());
+
+        let config = ClusterConfig {
+            max_task_reschedules: Some(2),
+            ..Default::default()
+        };
+        writer.save_cluster_config(&config).await?;
+        assert_eq!(writer.cluster_config(), config);
+        // other schedulers apply the change when they are notified of it
+        for _ in 0..100 {
+            if reader.cluster_config() == config {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(reader.cluster_config(), config);
+
+        let invalid = ClusterConfig {
+            executor_timeout_seconds: 0,
+            ..Default::default()
+        };
+        assert!(writer.save_cluster_config(&invalid).await.is_err());
+        assert_eq!(writer.cluster_config(), config);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reschedule_dead_task_limit() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let running = TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_string(),
+                stage_id: 1,
+                partition_id: 0,
+            }),
+            status: Some(task_status::Status::Running(RunningTask {
+                executor_id: "dead".to_string(),
+                num_rows: 0,
+            })),
+        };
+        // no executor is alive, so the task is rescheduled once and then fails
+        state.save_task_status(&running).await?;
+        assert!(state.reschedule_dead_task(&running, &[], Some(1)).await?);
+        assert_eq!(state._get_task_status("job", 1, 0).await?.status, None);
+        // a status that is no longer current is left alone
+        assert!(state.reschedule_dead_task(&running, &[], Some(1)).await?);
+        assert_eq!(state._get_task_status("job", 1, 0).await?.status, None);
+        state.save_task_status(&running).await?;
+        assert!(state.reschedule_dead_task(&running, &[], Some(1)).await?);
+        assert!(matches!(
+            state._get_task_status("job", 1, 0).await?.status,
+            Some(task_status::Status::Failed(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reconcile_lost_inputs() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let plan = Arc::new(ShuffleWriterExec::try_new(
+            "job".to_owned(),
+            2,
+            Arc::new(UnresolvedShuffleExec::new(1, schema, 1, 1)),
+            "".to_owned(),
+            None,
+        )?);
+        state.save_stages("job", &[plan]).await?;
+        // the input of the pending task was produced by an executor that is gone
+        let input = TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_string(),
+                stage_id: 1,
+                partition_id: 0,
+            }),
+            status: Some(task_status::Status::Completed(CompletedTask {
+                executor_id: "dead".to_owned(),
+                partitions: vec![ShuffleWritePartition::default()],
+                job_disk_usage: 0,
+            })),
+        };
+        state.save_task_status(&input).await?;
+
+        // polling for work leaves the input alone
+        assert!(state.assign_next_schedulable_task("exec").await?.is_none());
+        assert_eq!(state._get_task_status("job", 1, 0).await?, input);
+
+        state.reconcile_tasks().await?;
+        assert_eq!(state._get_task_status("job", 1, 0).await?.status, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn task_progress() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let task = |partition_id: u32, executor_id: &str, num_rows: u64| TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_string(),
+                stage_id: 1,
+                partition_id,
+            }),
+            status: Some(task_status::Status::Running(RunningTask {
+                executor_id: executor_id.to_owned(),
+                num_rows,
+            })),
+        };
+        state
+            .save_task_statuses(&[task(0, "executor", 0), task(1, "other", 0)])
+            .await?;
+        let events = state.get_job_events("job").await?.len();
+
+        // the task 1 was reassigned to another executor, and task 2 does not exist
+        state
+            .save_task_progress(&[
+                task(0, "executor", 100),
+                task(1, "executor", 100),
+                task(2, "executor", 100),
+            ])
+            .await?;
+        assert_eq!(
+            state._get_task_status("job", 1, 0).await?,
+            task(0, "executor", 100)
+        );
+        assert_eq!(
+            state._get_task_status("job", 1, 1).await?,
+            task(1, "other", 0)
+        );
+        assert!(state._get_task_status("job", 1, 2).await.is_err());
+        // progress is not logged
+        assert_eq!(state.get_job_events("job").await?.len(), events);
+        assert_eq!(state.get_job_tasks("job").await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cancel_tasks_after_limit() -> Result<(), BallistaError> {
+        let state = SchedulerState::new(
+            Arc::new(StandaloneClient::try_new_temporary()?),
+            "test".to_string(),
+        );
+        let task = |partition_id: u32, status: Option<task_status::Status>| TaskStatus {
+            partition_id: Some(PartitionId {
+                job_id: "job".to_string(),
+                stage_id: 1,
+                partition_id,
+            }),
+            status,
+        };
+        let completed = |num_rows: u64| {
+            Some(task_status::Status::Completed(CompletedTask {
+                executor_id: "executor".to_owned(),
+                partitions: vec![ShuffleWritePartition {
+                    num_rows,
+                    ..Default::default()
+                }],
+                job_disk_usage: 0,
+            }))
+        };
+        let running = Some(task_status::Status::Running(RunningTask {
+            executor_id: "executor".to_owned(),
+            num_rows: 0,
+        }));
+        let tasks = vec![
+            task(0, completed(6)),
+            task(1, completed(5)),
+            task(2, running),
+            task(3, None),
+        ];
+        state.save_task_statuses(&tasks).await?;
+        let tasks = tasks
+            .into_iter()
+            .map(|task| {
+                let partition = task.partition_id.as_ref().unwrap();
+                let key = get_task_status_key(
+                    "test",
+                    "job",
+                    1,
+                    partition.partition_id as usize,
+                );
+                (key, task)
+            })
+            .collect::<HashMap<_, _>>();
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let stage = UnresolvedShuffleExec::new(1, schema, 4, 1);
+
+        // the completed tasks did not produce enough rows yet
+        assert!(
+            !state
+                .cancel_tasks_after_limit(&tasks, "job", &stage, 12)
+                .await?
+        );
+        assert_eq!(state._get_task_status("job", 1, 3).await?.status, None);
+
+        assert!(
+            state
+                .cancel_tasks_after_limit(&tasks, "job", &stage, 11)
+                .await?
+        );
+        for partition_id in 2..4 {
+            assert!(matches!(
+                state._get_task_status("job", 1, partition_id).await?.status,
+                Some(task_status::Status::Cancelled(_))
+            ));
+        }
+        assert!(matches!(
+            state._get_task_status("job", 1, 0).await?.status,
+            Some(task_status::Status::Completed(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn task_locality_score() {
+        let location = |partition_id: usize, executor_id: &str| {
+            ballista_core::serde::scheduler::PartitionLocation {
+                partition_id: ballista_core::serde::scheduler::PartitionId {
+                    job_id: "job".to_owned(),
+                    stage_id: 1,
+                    partition_id,
+                },
+                executor_meta: ExecutorMeta {
+                    id: executor_id.to_owned(),
+                    host: "localhost".to_owned(),
+                    port: 123,
+                    labels: Default::default(),
+                    flight_socket: None,
+                    protocol_version: PROTOCOL_VERSION,
+                },
+                partition_stats: Default::default(),
+                path: format!("/tmp/{}", partition_id),
+                auth_token: "".to_owned(),
+            }
+        };
+
+        let mut partition_locations = HashMap::new();
+        assert!(locality_score(&partition_locations, "exec1").abs() < f64::EPSILON);
+
+        let mut stage_locations = HashMap::new();
+        stage_locations.insert(0, vec![location(0, "exec1"), location(0, "exec2")]);
+        stage_locations.insert(1, vec![location(1, "exec1"), location(1, "exec1")]);
+        partition_locations.insert(1, stage_locations);
+        assert!(
+            (locality_score(&partition_locations, "exec1") - 0.75).abs() < f64::EPSILON
+        );
+        assert!(
+            (locality_score(&partition_locations, "exec2") - 0.25).abs() < f64::EPSILON
+        );
+        assert!(locality_score(&partition_locations, "exec3").abs() < f64::EPSILON);
+    }
+
     #[tokio::test]
     async fn task_synchronize_job_status_queued() -> Result<(), BallistaError> {
         let state = SchedulerState::new(
@@ -796,6 +2635,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -807,6 +2647,7 @@ mod test {
         let meta = TaskStatus {
             status: Some(task_status::Status::Running(RunningTask {
                 executor_id: "".to_owned(),
+                num_rows: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -836,6 +2677,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -874,6 +2716,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -886,6 +2729,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -918,6 +2762,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -930,6 +2775,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -938,12 +2784,14 @@ mod test {
             }),
         };
         state.save_task_status(&meta).await?;
+        state.save_job_key(job_id, &ShuffleKey::generate()).await?;
         state.synchronize_job_status(job_id).await?;
         let result = state.get_job_metadata(job_id).await?;
         match result.status.unwrap() {
             job_status::Status::Completed(_) => (),
             status => panic!("Received status: {:?}", status),
         }
+        assert!(state.get_job_key(job_id).await?.is_empty());
         Ok(())
     }
 
@@ -962,6 +2810,7 @@ mod test {
             status: Some(task_status::Status::Completed(CompletedTask {
                 executor_id: "".to_owned(),
                 partitions: vec![],
+                job_disk_usage: 0,
             })),
             partition_id: Some(PartitionId {
                 job_id: job_id.to_owned(),
@@ -990,12 +2839,23 @@ mod test {
             }),
         };
         state.save_task_status(&meta).await?;
+        state.save_job_key(job_id, &ShuffleKey::generate()).await?;
         state.synchronize_job_status(job_id).await?;
         let result = state.get_job_metadata(job_id).await?;
         match result.status.unwrap() {
             job_status::Status::Failed(_) => (),
             status => panic!("Received status: {:?}", status),
         }
+        // the pending task is cancelled and the key of the failed job deleted
+        let pending = state
+            .current_task_status(meta.partition_id.as_ref().unwrap())
+            .await?
+            .unwrap();
+        assert!(matches!(
+            pending.status,
+            Some(task_status::Status::Cancelled(_))
+        ));
+        assert!(state.get_job_key(job_id).await?.is_empty());
         Ok(())
     }
 
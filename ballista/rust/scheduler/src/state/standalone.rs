@@ -15,6 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::collections::HashMap;
 use std::{sync::Arc, task::Poll};
 
 use crate::state::ConfigBackendClient;
@@ -23,7 +24,7 @@ use ballista_core::error::{ballista_error, BallistaError, Result};
 use futures::{FutureExt, Stream};
 use log::warn;
 use sled::{Event, Subscriber};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 use super::{Lock, Watch, WatchEvent};
 
@@ -31,15 +32,18 @@ use super::{Lock, Watch, WatchEvent};
 #[derive(Clone)]
 pub struct StandaloneClient {
     db: sled::Db,
-    lock: Arc<Mutex<()>>,
+    locks: LockMap,
 }
 
+/// The locks that are held or waited for, by name
+type LockMap = Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>>;
+
 impl StandaloneClient {
     /// Creates a StandaloneClient that saves data to the specified file.
     pub fn try_new<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
         Ok(Self {
             db: sled::open(path).map_err(sled_to_ballista_error)?,
-            lock: Arc::new(Mutex::new(())),
+            locks: Arc::new(std::sync::Mutex::new(HashMap::new())),
         })
     }
 
@@ -50,7 +54,7 @@ impl StandaloneClient {
                 .temporary(true)
                 .open()
                 .map_err(sled_to_ballista_error)?,
-            lock: Arc::new(Mutex::new(())),
+            locks: Arc::new(std::sync::Mutex::new(HashMap::new())),
         })
     }
 }
@@ -99,8 +103,42 @@ impl ConfigBackendClient for StandaloneClient {
             .map(|_| ())
     }
 
-    async fn lock(&self) -> Result<Box<dyn Lock>> {
-        Ok(Box::new(self.lock.clone().lock_owned().await))
+    async fn put_txn(&self, ops: Vec<(String, Vec<u8>)>) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (key, value) in ops {
+            batch.insert(key.as_bytes(), value);
+        }
+        self.db.apply_batch(batch).map_err(|e| {
+            warn!("sled batch failed: {}", e);
+            ballista_error("sled batch failed")
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.db
+            .remove(key)
+            .map_err(|e| {
+                warn!("sled remove failed: {}", e);
+                ballista_error("sled remove failed")
+            })
+            .map(|_| ())
+    }
+
+    async fn lock(&self, key: &str) -> Result<Box<dyn Lock>> {
+        let mutex = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone();
+        let guard = mutex.clone().lock_owned().await;
+        Ok(Box::new(SledLockGuard {
+            key: key.to_owned(),
+            mutex,
+            guard: Some(guard),
+            locks: self.locks.clone(),
+        }))
     }
 
     async fn watch(&self, prefix: String) -> Result<Box<dyn Watch>> {
@@ -110,6 +148,32 @@ impl ConfigBackendClient for StandaloneClient {
     }
 }
 
+/// A held lock, which removes the lock from the map of locks when it is released and
+/// nobody else holds or waits for it, so that the map does not grow with every job
+struct SledLockGuard {
+    key: String,
+    mutex: Arc<Mutex<()>>,
+    guard: Option<OwnedMutexGuard<()>>,
+    locks: LockMap,
+}
+
+#[tonic::async_trait]
+impl Lock for SledLockGuard {
+    async fn unlock(&mut self) {}
+}
+
+impl Drop for SledLockGuard {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap();
+        self.guard.take();
+        // whoever waits for the lock took a reference to it while the map was locked,
+        // so only the map and this guard reference a lock that nobody waits for
+        if Arc::strong_count(&self.mutex) == 2 {
+            locks.remove(&self.key);
+        }
+    }
+}
+
 struct SledWatch {
     subscriber: Subscriber,
 }
@@ -169,6 +233,39 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn put_txn_read() -> Result<(), Box<dyn std::error::Error>> {
+        let client = create_instance()?;
+        let value = "value".as_bytes();
+        client
+            .put_txn(vec![
+                ("key/1".to_owned(), value.to_vec()),
+                ("key/2".to_owned(), value.to_vec()),
+            ])
+            .await?;
+        assert_eq!(client.get("key/1").await?, value);
+        assert_eq!(client.get("key/2").await?, value);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lock_per_key() -> Result<(), Box<dyn std::error::Error>> {
+        let client = create_instance()?;
+        let mut lock = client.lock("job1").await?;
+        // a lock with another name is not blocked by the first one
+        let mut other = client.lock("job2").await?;
+        other.unlock().await;
+        drop(other);
+        lock.unlock().await;
+        drop(lock);
+        let mut lock = client.lock("job1").await?;
+        lock.unlock().await;
+        drop(lock);
+        // released locks are not kept around
+        assert!(client.locks.lock().unwrap().is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn read_empty() -> Result<(), Box<dyn std::error::Error>> {
         let client = create_instance()?;
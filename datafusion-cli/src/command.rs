@@ -0,0 +1,213 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Meta-commands of the interactive shell, such as `\d`
+use crate::print_format::{all_print_formats, PrintFormat};
+use crate::PrintOptions;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::ExecutionContext;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// A command starting with a backslash, which is handled by the CLI instead of
+/// being planned as SQL
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    /// `\q`: exit the CLI
+    Quit,
+    /// `\?`: print the available commands
+    Help,
+    /// `\d` or `\dt`: list the tables
+    ListTables,
+    /// `\d name`: describe the columns of a table
+    DescribeTable(String),
+    /// `\timing`: toggle printing the row count and elapsed time
+    Timing,
+    /// `\pset format name`: change the output format, or print it when no name is given
+    OutputFormat(Option<String>),
+}
+
+const HELP: &str = "\
+\\?                  print this help
+\\q                  exit the CLI
+\\d                  list tables
+\\dt                 list tables
+\\d NAME             describe a table
+\\timing             toggle timing of queries
+\\pset format [FMT]  show or set the output format";
+
+impl FromStr for Command {
+    type Err = ();
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        let mut parts = s.trim().trim_end_matches(';').split_whitespace();
+        let command = parts.next().ok_or(())?;
+        let arg = parts.next();
+        let command = match (command, arg) {
+            ("\\q", None) => Self::Quit,
+            ("\\?", None) => Self::Help,
+            ("\\d", None) | ("\\dt", None) => Self::ListTables,
+            ("\\d", Some(name)) => Self::DescribeTable(name.to_owned()),
+            ("\\timing", None) => Self::Timing,
+            ("\\pset", Some("format")) => {
+                Self::OutputFormat(parts.next().map(|f| f.to_owned()))
+            }
+            _ => return Err(()),
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(command)
+    }
+}
+
+impl Command {
+    /// Runs the command; `Quit` is left to the caller
+    pub async fn execute(
+        &self,
+        ctx: &mut ExecutionContext,
+        print_options: &mut PrintOptions,
+    ) -> Result<()> {
+        let now = Instant::now();
+        match self {
+            Self::Quit => Err(DataFusionError::Internal(
+                "Unexpected quit, this should be handled outside".to_owned(),
+            )),
+            Self::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            Self::ListTables => {
+                let batches = ctx
+                    .sql(
+                        "SELECT table_catalog, table_schema, table_name, table_type \
+                         FROM information_schema.tables",
+                    )?
+                    .collect()
+                    .await?;
+                print_options.print_batches(&batches, now)
+            }
+            Self::DescribeTable(name) => {
+                let batches = ctx.sql(&describe_table_sql(name))?.collect().await?;
+                if batches.iter().all(|b| b.num_rows() == 0) {
+                    return Err(DataFusionError::Plan(format!(
+                        "Table '{}' not found",
+                        name
+                    )));
+                }
+                print_options.print_batches(&batches, now)
+            }
+            Self::Timing => {
+                print_options.quiet = !print_options.quiet;
+                println!(
+                    "Timing is {}.",
+                    if print_options.quiet { "off" } else { "on" }
+                );
+                Ok(())
+            }
+            Self::OutputFormat(None) => {
+                println!("Output format is {}.", print_options.format);
+                Ok(())
+            }
+            Self::OutputFormat(Some(format)) => {
+                print_options.format = format.parse::<PrintFormat>().map_err(|_| {
+                    DataFusionError::Plan(format!(
+                        "Unknown output format '{}', expected one of {}",
+                        format,
+                        all_print_formats()
+                            .iter()
+                            .map(|f| f.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })?;
+                println!("Output format is {}.", print_options.format);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Builds the `information_schema` query describing a table, which may be
+/// qualified with its schema
+fn describe_table_sql(name: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let filter = match name.rsplit_once('.') {
+        Some((schema, table)) => format!(
+            "table_schema = {} AND table_name = {}",
+            quote(schema),
+            quote(table)
+        ),
+        None => format!("table_name = {}", quote(name)),
+    };
+    format!(
+        "SELECT column_name, data_type, is_nullable \
+         FROM information_schema.columns WHERE {} \
+         ORDER BY ordinal_position",
+        filter
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Command::Quit, "\\q".parse().unwrap());
+        assert_eq!(Command::Help, "\\?".parse().unwrap());
+        assert_eq!(Command::ListTables, "\\d".parse().unwrap());
+        assert_eq!(Command::ListTables, "\\dt;".parse().unwrap());
+        assert_eq!(
+            Command::DescribeTable("t".to_owned()),
+            " \\d t ".parse().unwrap()
+        );
+        assert_eq!(Command::Timing, "\\timing".parse().unwrap());
+        assert_eq!(
+            Command::OutputFormat(Some("csv".to_owned())),
+            "\\pset format csv".parse().unwrap()
+        );
+        assert_eq!(
+            Command::OutputFormat(None),
+            "\\pset format".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_str_failure() {
+        assert!("\\x".parse::<Command>().is_err());
+        assert!("\\q now".parse::<Command>().is_err());
+        assert!("\\d a b".parse::<Command>().is_err());
+        assert!("\\pset".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_describe_table_sql() {
+        assert_eq!(
+            "SELECT column_name, data_type, is_nullable \
+             FROM information_schema.columns WHERE table_name = 'it''s' \
+             ORDER BY ordinal_position",
+            describe_table_sql("it's")
+        );
+        assert_eq!(
+            "SELECT column_name, data_type, is_nullable \
+             FROM information_schema.columns \
+             WHERE table_schema = 'public' AND table_name = 't' \
+             ORDER BY ordinal_position",
+            describe_table_sql("public.t")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Line editor helper of the interactive shell
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::Helper;
+
+/// Keeps reading lines into the same entry until the statement is complete,
+/// so that a multi-line query can be edited and recalled from the history
+/// as a whole
+#[derive(Debug, Default)]
+pub struct CliHelper {}
+
+impl Helper for CliHelper {}
+
+impl Completer for CliHelper {
+    type Candidate = String;
+}
+
+impl Hinter for CliHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CliHelper {}
+
+impl Validator for CliHelper {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext<'_>,
+    ) -> rustyline::Result<ValidationResult> {
+        if is_complete(ctx.input()) {
+            Ok(ValidationResult::Valid(None))
+        } else {
+            Ok(ValidationResult::Incomplete)
+        }
+    }
+}
+
+/// Returns the input without its comment lines
+pub fn strip_comments(input: &str) -> String {
+    input
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns true when the input can be executed: a SQL statement terminated
+/// by `;`, a meta-command, an exit command or nothing but comments
+pub fn is_complete(input: &str) -> bool {
+    let input = strip_comments(input);
+    let input = input.trim();
+    input.is_empty()
+        || input.ends_with(';')
+        || input.starts_with('\\')
+        || is_exit_command(input)
+}
+
+/// Returns true for the `quit` and `exit` commands
+pub fn is_exit_command(line: &str) -> bool {
+    let line = line.trim_end().to_lowercase();
+    line == "quit" || line == "exit"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("SELECT 1;"));
+        assert!(is_complete("SELECT\n  1;  "));
+        assert!(is_complete("\\d t"));
+        assert!(is_complete("quit"));
+        assert!(is_complete("-- a comment"));
+        assert!(is_complete(""));
+
+        assert!(!is_complete("SELECT 1"));
+        assert!(!is_complete("SELECT 1\n-- ends with a comment;"));
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(
+            "SELECT\n1;",
+            strip_comments("-- first\nSELECT\n  -- second\n1;")
+        );
+    }
+}
@@ -14,6 +14,8 @@
 // KIND, either express or implied.  See the License for the
 // specific language governing permissions and limitations
 // under the License.
+pub mod command;
+pub mod helper;
 pub mod print_format;
 
 use datafusion::arrow::record_batch::RecordBatch;
@@ -39,13 +41,15 @@ fn print_timing_info(row_count: usize, now: Instant) {
 impl PrintOptions {
     /// print the batches to stdout using the specified format
     pub fn print_batches(&self, batches: &[RecordBatch], now: Instant) -> Result<()> {
+        // the timing would corrupt the binary output
+        let timing = !self.quiet && self.format != PrintFormat::Parquet;
         if batches.is_empty() {
-            if !self.quiet {
+            if timing {
                 print_timing_info(0, now);
             }
         } else {
             self.format.print_batches(batches)?;
-            if !self.quiet {
+            if timing {
                 let row_count: usize = batches.iter().map(|b| b.num_rows()).sum();
                 print_timing_info(row_count, now);
             }
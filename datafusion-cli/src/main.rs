@@ -21,6 +21,8 @@ use clap::{crate_version, App, Arg};
 use datafusion::error::Result;
 use datafusion::execution::context::{ExecutionConfig, ExecutionContext};
 use datafusion_cli::{
+    command::Command,
+    helper::{is_exit_command, strip_comments, CliHelper},
     print_format::{all_print_formats, PrintFormat},
     PrintOptions,
 };
@@ -29,7 +31,7 @@ use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 #[tokio::main]
@@ -89,8 +91,25 @@ pub async fn main() {
                 .long("quiet")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("history-file")
+                .help("Path of the command history, default to ~/.datafusion_history")
+                .long("history-file")
+                .takes_value(true),
+        )
         .get_matches();
 
+    // resolve the history path before changing to the data directory
+    let history_path = matches
+        .value_of("history-file")
+        .map(PathBuf::from)
+        .or_else(default_history_path)
+        .map(|path| {
+            env::current_dir()
+                .map(|dir| dir.join(&path))
+                .unwrap_or(path)
+        });
+
     if let Some(path) = matches.value_of("data-path") {
         let p = Path::new(path);
         env::set_current_dir(&p).unwrap();
@@ -112,7 +131,7 @@ pub async fn main() {
         .expect("Invalid format");
 
     let quiet = matches.is_present("quiet");
-    let print_options = PrintOptions { format, quiet };
+    let mut print_options = PrintOptions { format, quiet };
 
     if let Some(file_paths) = matches.values_of("file") {
         let files = file_paths
@@ -121,18 +140,28 @@ pub async fn main() {
         let mut ctx = ExecutionContext::with_config(execution_config);
         for file in files {
             let mut reader = BufReader::new(file);
-            exec_from_lines(&mut ctx, &mut reader, print_options.clone()).await;
+            if !exec_from_lines(&mut ctx, &mut reader, &mut print_options).await {
+                break;
+            }
         }
     } else {
-        exec_from_repl(execution_config, print_options).await;
+        exec_from_repl(execution_config, print_options, history_path).await;
     }
 }
 
+/// The history is kept in the home directory so that it is shared by all the
+/// data directories
+fn default_history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| Path::new(&home).join(".datafusion_history"))
+}
+
+/// Executes the statements read from a file, returns false if a `\q` command
+/// was read
 async fn exec_from_lines(
     ctx: &mut ExecutionContext,
     reader: &mut BufReader<File>,
-    print_options: PrintOptions,
-) {
+    print_options: &mut PrintOptions,
+) -> bool {
     let mut query = "".to_owned();
 
     for line in reader.lines() {
@@ -140,11 +169,16 @@ async fn exec_from_lines(
             Ok(line) if line.starts_with("--") => {
                 continue;
             }
+            Ok(line) if query.is_empty() && line.starts_with('\\') => {
+                if !exec_command(ctx, print_options, &line).await {
+                    return false;
+                }
+            }
             Ok(line) => {
                 let line = line.trim_end();
                 query.push_str(line);
                 if line.ends_with(';') {
-                    match exec_and_print(ctx, print_options.clone(), query).await {
+                    match exec_and_print(ctx, print_options, query).await {
                         Ok(_) => {}
                         Err(err) => println!("{:?}", err),
                     }
@@ -166,35 +200,43 @@ async fn exec_from_lines(
             Err(err) => println!("{:?}", err),
         }
     }
+    true
 }
 
-async fn exec_from_repl(execution_config: ExecutionConfig, print_options: PrintOptions) {
+async fn exec_from_repl(
+    execution_config: ExecutionConfig,
+    mut print_options: PrintOptions,
+    history_path: Option<PathBuf>,
+) {
     let mut ctx = ExecutionContext::with_config(execution_config);
 
-    let mut rl = Editor::<()>::new();
-    rl.load_history(".history").ok();
+    let mut rl = Editor::<CliHelper>::new();
+    rl.set_helper(Some(CliHelper::default()));
+    if let Some(path) = &history_path {
+        rl.load_history(path).ok();
+    }
 
-    let mut query = "".to_owned();
     loop {
         match rl.readline("> ") {
-            Ok(ref line) if is_exit_command(line) && query.is_empty() => {
-                break;
-            }
-            Ok(ref line) if line.starts_with("--") => {
-                continue;
-            }
-            Ok(ref line) if line.trim_end().ends_with(';') => {
-                query.push_str(line.trim_end());
-                rl.add_history_entry(query.clone());
-                match exec_and_print(&mut ctx, print_options.clone(), query).await {
-                    Ok(_) => {}
-                    Err(err) => println!("{:?}", err),
+            Ok(line) => {
+                let statement = strip_comments(&line);
+                let statement = statement.trim();
+                if statement.is_empty() {
+                    continue;
+                }
+                rl.add_history_entry(line.trim_end());
+                if is_exit_command(statement) {
+                    break;
+                } else if statement.starts_with('\\') {
+                    if !exec_command(&mut ctx, &mut print_options, statement).await {
+                        break;
+                    }
+                } else {
+                    match exec_and_print(&mut ctx, &print_options, line).await {
+                        Ok(_) => {}
+                        Err(err) => println!("{:?}", err),
+                    }
                 }
-                query = "".to_owned();
-            }
-            Ok(ref line) => {
-                query.push_str(line);
-                query.push('\n');
             }
             Err(_) => {
                 break;
@@ -202,7 +244,27 @@ async fn exec_from_repl(execution_config: ExecutionConfig, print_options: PrintO
         }
     }
 
-    rl.save_history(".history").ok();
+    if let Some(path) = &history_path {
+        rl.save_history(path).ok();
+    }
+}
+
+/// Runs a meta-command, returns false if it is `\q`
+async fn exec_command(
+    ctx: &mut ExecutionContext,
+    print_options: &mut PrintOptions,
+    line: &str,
+) -> bool {
+    match line.parse::<Command>() {
+        Ok(Command::Quit) => return false,
+        Ok(command) => {
+            if let Err(err) = command.execute(ctx, print_options).await {
+                println!("{:?}", err)
+            }
+        }
+        Err(_) => println!("Unknown command '{}', type \\? for help", line.trim()),
+    }
+    true
 }
 
 fn is_valid_file(dir: String) -> std::result::Result<(), String> {
@@ -228,14 +290,9 @@ fn is_valid_batch_size(size: String) -> std::result::Result<(), String> {
     }
 }
 
-fn is_exit_command(line: &str) -> bool {
-    let line = line.trim_end().to_lowercase();
-    line == "quit" || line == "exit"
-}
-
 async fn exec_and_print(
     ctx: &mut ExecutionContext,
-    print_options: PrintOptions,
+    print_options: &PrintOptions,
     sql: String,
 ) -> Result<()> {
     let df = ctx.sql(&sql)?;
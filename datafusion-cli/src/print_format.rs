@@ -21,7 +21,10 @@ use arrow::json::{ArrayWriter, LineDelimitedWriter};
 use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::arrow::util::pretty;
 use datafusion::error::{DataFusionError, Result};
+use datafusion::parquet::arrow::ArrowWriter;
+use datafusion::parquet::file::writer::InMemoryWriteableCursor;
 use std::fmt;
+use std::io::Write;
 use std::str::FromStr;
 
 /// Allow records to be printed in different formats
@@ -32,6 +35,7 @@ pub enum PrintFormat {
     Table,
     Json,
     NdJson,
+    Parquet,
 }
 
 /// returns all print formats
@@ -42,6 +46,7 @@ pub fn all_print_formats() -> Vec<PrintFormat> {
         PrintFormat::Table,
         PrintFormat::Json,
         PrintFormat::NdJson,
+        PrintFormat::Parquet,
     ]
 }
 
@@ -54,6 +59,7 @@ impl FromStr for PrintFormat {
             "table" => Ok(Self::Table),
             "json" => Ok(Self::Json),
             "ndjson" => Ok(Self::NdJson),
+            "parquet" => Ok(Self::Parquet),
             _ => Err(()),
         }
     }
@@ -67,6 +73,7 @@ impl fmt::Display for PrintFormat {
             Self::Table => write!(f, "table"),
             Self::Json => write!(f, "json"),
             Self::NdJson => write!(f, "ndjson"),
+            Self::Parquet => write!(f, "parquet"),
         }
     }
 }
@@ -99,6 +106,23 @@ fn print_batches_with_sep(batches: &[RecordBatch], delimiter: u8) -> Result<Stri
     Ok(formatted)
 }
 
+/// Encodes the batches as a single Parquet file
+fn batches_to_parquet(batches: &[RecordBatch]) -> Result<Vec<u8>> {
+    let schema = match batches.first() {
+        Some(batch) => batch.schema(),
+        None => return Ok(vec![]),
+    };
+    let cursor = InMemoryWriteableCursor::default();
+    {
+        let mut writer = ArrowWriter::try_new(cursor.clone(), schema, None)?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.close()?;
+    }
+    Ok(cursor.data())
+}
+
 impl PrintFormat {
     /// print the batches to stdout using the specified format
     pub fn print_batches(&self, batches: &[RecordBatch]) -> Result<()> {
@@ -110,6 +134,12 @@ impl PrintFormat {
             Self::NdJson => {
                 println!("{}", batches_to_json!(LineDelimitedWriter, batches))
             }
+            Self::Parquet => {
+                let bytes = batches_to_parquet(batches)?;
+                let mut stdout = std::io::stdout();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
         }
         Ok(())
     }
@@ -138,6 +168,9 @@ mod tests {
 
         let format = "table".parse::<PrintFormat>().unwrap();
         assert_eq!(PrintFormat::Table, format);
+
+        let format = "parquet".parse::<PrintFormat>().unwrap();
+        assert_eq!(PrintFormat::Parquet, format);
     }
 
     #[test]
@@ -147,6 +180,7 @@ mod tests {
         assert_eq!("tsv", PrintFormat::Tsv.to_string());
         assert_eq!("json", PrintFormat::Json.to_string());
         assert_eq!("ndjson", PrintFormat::NdJson.to_string());
+        assert_eq!("parquet", PrintFormat::Parquet.to_string());
     }
 
     #[test]
@@ -213,4 +247,28 @@ mod tests {
         assert_eq!("{\"a\":1,\"b\":4,\"c\":7}\n{\"a\":2,\"b\":5,\"c\":8}\n{\"a\":3,\"b\":6,\"c\":9}\n", r);
         Ok(())
     }
+
+    #[test]
+    fn test_print_batches_to_parquet() -> Result<()> {
+        assert!(batches_to_parquet(&[])?.is_empty());
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(Int32Array::from(vec![4, 5, 6])),
+            ],
+        )
+        .unwrap();
+
+        let bytes = batches_to_parquet(&[batch])?;
+        assert_eq!(b"PAR1", &bytes[..4]);
+        assert_eq!(b"PAR1", &bytes[bytes.len() - 4..]);
+        Ok(())
+    }
 }
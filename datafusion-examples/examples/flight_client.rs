@@ -26,21 +26,19 @@ use arrow_flight::flight_service_client::FlightServiceClient;
 use arrow_flight::utils::flight_data_to_arrow_batch;
 use arrow_flight::{FlightDescriptor, Ticket};
 
-/// This example shows how to wrap DataFusion with `FlightService` to support looking up schema information for
-/// Parquet files and executing SQL queries against them on a remote server.
+/// This example shows how to query the tables of a remote DataFusion over Arrow Flight, to
+/// look up their schema and execute SQL queries against them.
 /// This example is run along-side the example `flight_server`.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let testdata = datafusion::arrow::util::test_util::parquet_test_data();
-
     // Create Flight client
     let mut client = FlightServiceClient::connect("http://localhost:50051").await?;
 
-    // Call get_schema to get the schema of a Parquet file
+    // Call get_schema to get the schema of a table
     let request = tonic::Request::new(FlightDescriptor {
         r#type: flight_descriptor::DescriptorType::Path as i32,
         cmd: vec![],
-        path: vec![format!("{}/alltypes_plain.parquet", testdata)],
+        path: vec!["alltypes_plain".to_owned()],
     });
 
     let schema_result = client.get_schema(request).await?.into_inner();
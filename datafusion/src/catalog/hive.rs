@@ -0,0 +1,1221 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Hive Metastore catalog
+//!
+//! [`HiveMetastoreCatalog`] exposes the databases of a Hive Metastore as schemas and
+//! their tables as tables, so that the tables of an existing Hadoop catalog can be
+//! queried. The files of a table are read from the location of its storage
+//! descriptor: Parquet tables as a [`ParquetTable`] and text tables as a [`CsvFile`],
+//! with the columns declared in the metastore. The partitions of a partitioned table
+//! are listed from the metastore with their own locations when the table is scanned,
+//! and read as a [`PartitionedTable`] whose `Utf8` partition columns follow the columns
+//! of the files.
+//!
+//! The metastore is queried with its Thrift binary protocol, with a timeout. The
+//! tables are cached for a minute by default, and their partitions are listed again by
+//! each scan, so queries see the tables and partitions that were added by other
+//! engines. Only local paths are read: the locations of other file systems, such
+//! as HDFS, must be mapped to the paths they are mounted at with
+//! [`HiveMetastoreCatalog::with_location_mapping`]. Views and the ORC, Avro and
+//! sequence file formats are not supported.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use log::warn;
+use thrift::protocol::{
+    TBinaryInputProtocol, TBinaryOutputProtocol, TFieldIdentifier, TInputProtocol,
+    TMessageIdentifier, TMessageType, TOutputProtocol, TStructIdentifier, TType,
+};
+use thrift::transport::{TBufferedReadTransport, TBufferedWriteTransport};
+use thrift::ProtocolErrorKind;
+
+use crate::catalog::catalog::CatalogProvider;
+use crate::catalog::schema::SchemaProvider;
+use crate::datasource::csv::{CsvFile, CsvReadOptions};
+use crate::datasource::datasource::{Statistics, TableProviderFilterPushDown};
+use crate::datasource::parquet::ParquetTable;
+use crate::datasource::partition_pruning::PartitionColumns;
+use crate::datasource::partitioned::PartitionedTable;
+use crate::datasource::TableProvider;
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Expr;
+use crate::physical_plan::common::FileListOptions;
+use crate::physical_plan::ExecutionPlan;
+
+const TEXT_INPUT_FORMAT: &str = "org.apache.hadoop.mapred.TextInputFormat";
+const OPEN_CSV_SERDE: &str = "org.apache.hadoop.hive.serde2.OpenCSVSerde";
+const VIRTUAL_VIEW: &str = "VIRTUAL_VIEW";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// A catalog whose schemas are the databases of a Hive Metastore
+#[derive(Clone)]
+pub struct HiveMetastoreCatalog {
+    client: Arc<HiveMetastoreClient>,
+    max_concurrency: usize,
+    /// Prefixes of the locations of the metastore and the local paths they map to
+    location_mappings: Vec<(String, String)>,
+    /// How long the tables read from the metastore are cached
+    cache_ttl: Duration,
+    /// The tables by database and name, with the time they were read at
+    tables: Arc<Mutex<HashMap<(String, String), (Instant, Arc<dyn TableProvider>)>>>,
+}
+
+impl HiveMetastoreCatalog {
+    /// Create a catalog of the metastore whose Thrift service listens at `address`,
+    /// e.g. `metastore:9083`
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            client: Arc::new(HiveMetastoreClient::new(address)),
+            max_concurrency: num_cpus::get(),
+            location_mappings: vec![],
+            cache_ttl: DEFAULT_CACHE_TTL,
+            tables: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Customize the timeout of connecting to the metastore and of reading or writing
+    /// its requests, 30 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Arc::new(
+            HiveMetastoreClient::new(self.client.address.clone()).with_timeout(timeout),
+        );
+        self
+    }
+
+    /// Customize how long the tables are cached before they are read from the
+    /// metastore again, a minute by default
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Customize the number of files of a Parquet table that are read concurrently
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// Read the locations that start with `prefix`, e.g. `hdfs://namenode:8020/`, from
+    /// the local path that replaces it, e.g. `/mnt/hdfs/`
+    pub fn with_location_mapping(
+        mut self,
+        prefix: impl Into<String>,
+        local_prefix: impl Into<String>,
+    ) -> Self {
+        self.location_mappings
+            .push((prefix.into(), local_prefix.into()));
+        self
+    }
+
+    /// Returns the table `name` of the database `database`, or None if the metastore
+    /// does not have it. The partitions of a partitioned table are listed when it is
+    /// scanned.
+    pub fn table(
+        &self,
+        database: &str,
+        name: &str,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        let key = (database.to_owned(), name.to_owned());
+        if let Some((read_at, table)) = self.tables.lock().unwrap().get(&key) {
+            if read_at.elapsed() < self.cache_ttl {
+                return Ok(Some(table.clone()));
+            }
+        }
+        // the cache is not locked while the metastore is queried
+        let table = self.read_table(database, name)?;
+        if let Some(table) = &table {
+            self.tables
+                .lock()
+                .unwrap()
+                .insert(key, (Instant::now(), table.clone()));
+        }
+        Ok(table)
+    }
+
+    /// Reads the table `name` of the database `database` from the metastore
+    fn read_table(
+        &self,
+        database: &str,
+        name: &str,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        let table = match self.client.get_table(database, name)? {
+            Some(table) => table,
+            None => return Ok(None),
+        };
+        if table.table_type == VIRTUAL_VIEW {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Hive view {}.{} is not supported",
+                database, name
+            )));
+        }
+
+        let file_schema = Arc::new(hive_schema(&table.sd.cols)?);
+        if table.partition_keys.is_empty() {
+            let format = StorageFormat::try_new(&table.sd, &table.parameters)?;
+            let table = self.storage_table(&format, &table.sd.location, &file_schema)?;
+            return Ok(Some(table));
+        }
+
+        let partition_cols = table
+            .partition_keys
+            .iter()
+            .map(|key| key.name.clone())
+            .collect::<Vec<_>>();
+        // the schema of the table, which has no partition until it is scanned
+        let unlisted = PartitionedTable::try_new_with_partitions(
+            file_schema.clone(),
+            &partition_cols,
+            vec![],
+        )?;
+        let parquet = matches!(
+            StorageFormat::try_new(&table.sd, &table.parameters),
+            Ok(StorageFormat::Parquet)
+        );
+        Ok(Some(Arc::new(HivePartitionedTable {
+            catalog: self.clone(),
+            database: database.to_owned(),
+            name: name.to_owned(),
+            file_schema,
+            partition_cols,
+            table_parameters: table.parameters,
+            unlisted,
+            parquet,
+        })))
+    }
+
+    /// Lists the partitions of the table `name` of the database `database`, whose
+    /// files are read as `file_schema`
+    fn partitioned_table(
+        &self,
+        database: &str,
+        name: &str,
+        file_schema: &SchemaRef,
+        partition_cols: &[String],
+        table_parameters: &HashMap<String, String>,
+    ) -> Result<PartitionedTable> {
+        let partitions = self
+            .client
+            .get_partitions(database, name)?
+            .into_iter()
+            .map(|partition| {
+                // the partitions may be stored with other formats than the table
+                let format = StorageFormat::try_new(&partition.sd, table_parameters)?;
+                let table =
+                    self.storage_table(&format, &partition.sd.location, file_schema)?;
+                Ok((partition.values, table))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        PartitionedTable::try_new_with_partitions(
+            file_schema.clone(),
+            partition_cols,
+            partitions,
+        )
+    }
+
+    /// Returns the table of the files at `location`, which are read as `schema`
+    fn storage_table(
+        &self,
+        format: &StorageFormat,
+        location: &str,
+        schema: &SchemaRef,
+    ) -> Result<Arc<dyn TableProvider>> {
+        let path = self.local_path(location)?;
+        Ok(match format {
+            StorageFormat::Parquet => Arc::new(ParquetTable::try_new_with_schema(
+                path,
+                Some(schema.clone()),
+                self.max_concurrency,
+                FileListOptions::default(),
+            )?),
+            StorageFormat::Text {
+                delimiter,
+                has_header,
+            } => {
+                // the files written by Hive do not have an extension
+                let options = CsvReadOptions::new()
+                    .schema(schema)
+                    .delimiter(*delimiter)
+                    .has_header(*has_header)
+                    .file_extension("");
+                Arc::new(CsvFile::try_new(path, options)?)
+            }
+        })
+    }
+
+    /// Returns the local path of the files at `location`
+    fn local_path(&self, location: &str) -> Result<String> {
+        for (prefix, local_prefix) in &self.location_mappings {
+            if let Some(rest) = location.strip_prefix(prefix.as_str()) {
+                return Ok(format!("{}{}", local_prefix, rest));
+            }
+        }
+        match location.strip_prefix("file:") {
+            // both file:/path and file:///path
+            Some(path) if path.starts_with("///") => Ok(path[2..].to_owned()),
+            Some(path) => Ok(path.to_owned()),
+            None if location.starts_with('/') => Ok(location.to_owned()),
+            None => Err(DataFusionError::NotImplemented(format!(
+                "Hive location {} is not a local path, it can be mapped to one with \
+                 HiveMetastoreCatalog::with_location_mapping",
+                location
+            ))),
+        }
+    }
+}
+
+impl CatalogProvider for HiveMetastoreCatalog {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema_names(&self) -> Vec<String> {
+        self.client.get_all_databases().unwrap_or_else(|e| {
+            warn!("Could not list the databases of the Hive Metastore: {}", e);
+            vec![]
+        })
+    }
+
+    /// Returns the database `name`, whose tables are looked up when they are
+    /// resolved, so that the databases are not listed by each lookup
+    fn schema(&self, name: &str) -> Option<Arc<dyn SchemaProvider>> {
+        Some(Arc::new(HiveMetastoreSchema {
+            catalog: self.clone(),
+            database: name.to_owned(),
+        }))
+    }
+}
+
+/// The tables of a database of a Hive Metastore
+struct HiveMetastoreSchema {
+    catalog: HiveMetastoreCatalog,
+    database: String,
+}
+
+impl SchemaProvider for HiveMetastoreSchema {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.catalog
+            .client
+            .get_all_tables(&self.database)
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Could not list the tables of the Hive database {}: {}",
+                    self.database, e
+                );
+                vec![]
+            })
+    }
+
+    fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>> {
+        self.try_table(name).ok().flatten()
+    }
+
+    fn try_table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>> {
+        self.catalog.table(&self.database, name)
+    }
+}
+
+/// A partitioned table of a Hive Metastore, whose partitions are listed when it is
+/// scanned
+struct HivePartitionedTable {
+    catalog: HiveMetastoreCatalog,
+    database: String,
+    name: String,
+    file_schema: SchemaRef,
+    partition_cols: Vec<String>,
+    table_parameters: HashMap<String, String>,
+    /// The table without its partitions, which has the schema of the table
+    unlisted: PartitionedTable,
+    /// Whether the table is stored as Parquet, whose scans use the filters of the
+    /// columns of the files
+    parquet: bool,
+}
+
+impl TableProvider for HivePartitionedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.unlisted.schema()
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> Result<TableProviderFilterPushDown> {
+        let columns =
+            PartitionColumns::new(self.schema(), self.file_schema.fields().len());
+        if columns.is_file_filter(filter) && self.parquet {
+            Ok(TableProviderFilterPushDown::Inexact)
+        } else {
+            self.unlisted.supports_filter_pushdown(filter)
+        }
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        batch_size: usize,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.catalog
+            .partitioned_table(
+                &self.database,
+                &self.name,
+                &self.file_schema,
+                &self.partition_cols,
+                &self.table_parameters,
+            )?
+            .scan(projection, batch_size, filters, limit)
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// How the files of a table or partition are read
+#[derive(Debug, PartialEq)]
+enum StorageFormat {
+    Parquet,
+    Text { delimiter: u8, has_header: bool },
+}
+
+impl StorageFormat {
+    fn try_new(
+        sd: &StorageDescriptor,
+        table_parameters: &HashMap<String, String>,
+    ) -> Result<Self> {
+        if sd.input_format.contains("Parquet") {
+            return Ok(StorageFormat::Parquet);
+        }
+        if sd.input_format != TEXT_INPUT_FORMAT {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Hive tables stored with {} are not supported",
+                sd.input_format
+            )));
+        }
+
+        let delimiter = if sd.serialization_lib == OPEN_CSV_SERDE {
+            sd.serde_parameters.get("separatorChar")
+        } else {
+            sd.serde_parameters
+                .get("field.delim")
+                .or_else(|| sd.serde_parameters.get("serialization.format"))
+        };
+        let delimiter = match delimiter {
+            Some(delimiter) => parse_delimiter(delimiter)?,
+            None if sd.serialization_lib == OPEN_CSV_SERDE => b',',
+            None => 1,
+        };
+        let has_header = match table_parameters
+            .get("skip.header.line.count")
+            .map(|count| count.trim())
+        {
+            None | Some("0") => false,
+            Some("1") => true,
+            Some(count) => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "Skipping {} header lines of Hive text tables is not supported",
+                    count
+                )))
+            }
+        };
+        Ok(StorageFormat::Text {
+            delimiter,
+            has_header,
+        })
+    }
+}
+
+/// Parses a delimiter of a Hive SerDe, which is either a single character or, as in
+/// Hive, the decimal value of the byte, e.g. `1` for `\x01`
+fn parse_delimiter(delimiter: &str) -> Result<u8> {
+    match (delimiter.parse::<u8>(), delimiter.as_bytes()) {
+        (Ok(byte), _) => Ok(byte),
+        (_, [byte]) => Ok(*byte),
+        _ => Err(DataFusionError::NotImplemented(format!(
+            "Hive delimiter {:?} is not supported",
+            delimiter
+        ))),
+    }
+}
+
+/// Returns the schema of the Hive columns `cols`
+fn hive_schema(cols: &[FieldSchema]) -> Result<Schema> {
+    let fields = cols
+        .iter()
+        .map(|col| Ok(Field::new(&col.name, hive_type(&col.type_name)?, true)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Schema::new(fields))
+}
+
+/// Returns the Arrow type of the Hive type `type_name`
+fn hive_type(type_name: &str) -> Result<DataType> {
+    let type_name = type_name.trim().to_ascii_lowercase();
+    let (base, args) = match type_name.find('(') {
+        Some(index) => (type_name[..index].trim(), &type_name[index..]),
+        None => (type_name.as_str(), ""),
+    };
+    Ok(match base {
+        "tinyint" => DataType::Int8,
+        "smallint" => DataType::Int16,
+        "int" | "integer" => DataType::Int32,
+        "bigint" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" | "double precision" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "string" | "varchar" | "char" => DataType::Utf8,
+        "binary" => DataType::Binary,
+        "date" => DataType::Date32,
+        "timestamp" => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        "decimal" | "numeric" => decimal_type(args)?,
+        _ => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Hive type {} is not supported",
+                type_name
+            )))
+        }
+    })
+}
+
+/// Returns the decimal type with the arguments `args`, e.g. `(12, 2)`, which default
+/// to a precision of 10 and a scale of 0 as in Hive
+fn decimal_type(args: &str) -> Result<DataType> {
+    let args = args.trim().trim_start_matches('(').trim_end_matches(')');
+    if args.trim().is_empty() {
+        return Ok(DataType::Decimal(10, 0));
+    }
+    let values = args
+        .split(',')
+        .map(|arg| arg.trim().parse::<usize>())
+        .collect::<std::result::Result<Vec<_>, _>>();
+    match values.as_deref() {
+        Ok([precision]) => Ok(DataType::Decimal(*precision, 0)),
+        Ok([precision, scale]) => Ok(DataType::Decimal(*precision, *scale)),
+        _ => Err(DataFusionError::Plan(format!(
+            "Invalid Hive type decimal({})",
+            args
+        ))),
+    }
+}
+
+/// Client of the Thrift service of a Hive Metastore. Each request is sent over an
+/// idle connection, or a new one if there is none, which is kept for the next requests
+/// unless it failed.
+pub struct HiveMetastoreClient {
+    address: String,
+    timeout: Duration,
+    idle_connections: Mutex<Vec<Connection>>,
+}
+
+impl HiveMetastoreClient {
+    /// Create a client of the metastore whose Thrift service listens at `address`
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            timeout: DEFAULT_TIMEOUT,
+            idle_connections: Mutex::new(vec![]),
+        }
+    }
+
+    /// Customize the timeout of connecting to the metastore and of reading or writing
+    /// its requests, 30 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the names of the databases of the metastore
+    pub fn get_all_databases(&self) -> Result<Vec<String>> {
+        self.call("get_all_databases", &|_| Ok(()), &mut |i| {
+            read_list(i, |i| i.read_string())
+        })?
+        .map_err(|e| e.into_error("get_all_databases"))
+    }
+
+    /// Returns the names of the tables of the database `database`
+    pub fn get_all_tables(&self, database: &str) -> Result<Vec<String>> {
+        self.call(
+            "get_all_tables",
+            &|o| write_string_field(o, "db_name", 1, database),
+            &mut |i| read_list(i, |i| i.read_string()),
+        )?
+        .map_err(|e| e.into_error("get_all_tables"))
+    }
+
+    /// Returns the table `name` of the database `database`, or None if there is none
+    fn get_table(&self, database: &str, name: &str) -> Result<Option<HiveTable>> {
+        let result = self.call(
+            "get_table",
+            &|o| {
+                write_string_field(o, "dbname", 1, database)?;
+                write_string_field(o, "tbl_name", 2, name)
+            },
+            &mut read_table,
+        )?;
+        match result {
+            Ok(table) => Ok(Some(table)),
+            // NoSuchObjectException
+            Err(MetastoreException { id: 2, .. }) => Ok(None),
+            Err(e) => Err(e.into_error("get_table")),
+        }
+    }
+
+    /// Returns all the partitions of the table `name` of the database `database`
+    fn get_partitions(&self, database: &str, name: &str) -> Result<Vec<HivePartition>> {
+        self.call(
+            "get_partitions",
+            &|o| {
+                write_string_field(o, "db_name", 1, database)?;
+                write_string_field(o, "tbl_name", 2, name)?;
+                // all the partitions
+                o.write_field_begin(&TFieldIdentifier::new("max_parts", TType::I16, 3))?;
+                o.write_i16(-1)?;
+                o.write_field_end()
+            },
+            &mut |i| read_list(i, read_partition),
+        )?
+        .map_err(|e| e.into_error("get_partitions"))
+    }
+
+    /// Calls the method `method` of the metastore, returning its result or the
+    /// exception that it declares and threw
+    fn call<T>(
+        &self,
+        method: &str,
+        write_args: &dyn Fn(&mut dyn TOutputProtocol) -> thrift::Result<()>,
+        read_result: &mut dyn FnMut(&mut dyn TInputProtocol) -> thrift::Result<T>,
+    ) -> Result<std::result::Result<T, MetastoreException>> {
+        // the connections are not locked while they wait for the metastore
+        let mut connection = self.idle_connections.lock().unwrap().pop();
+        // the metastore closes idle connections, so a request that fails on a
+        // connection that was already open is sent again on a new one
+        let reused = connection.is_some();
+        for attempt in 0..2 {
+            let mut open = match connection.take() {
+                Some(connection) => connection,
+                None => Connection::open(&self.address, self.timeout)?,
+            };
+            match open.call(method, write_args, read_result) {
+                Ok(result) => {
+                    self.idle_connections.lock().unwrap().push(open);
+                    return Ok(result);
+                }
+                // the connection may be in the middle of a message, so it is dropped
+                Err(e) => {
+                    if !reused || attempt > 0 {
+                        return Err(DataFusionError::Execution(format!(
+                            "Hive Metastore request {} failed: {}",
+                            method, e
+                        )));
+                    }
+                }
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// A connection to the Thrift service of a metastore
+struct Connection {
+    input: Box<dyn TInputProtocol + Send>,
+    output: Box<dyn TOutputProtocol + Send>,
+    sequence_number: i32,
+}
+
+impl Connection {
+    fn open(address: &str, timeout: Duration) -> Result<Self> {
+        let mut stream = Err(DataFusionError::Execution(format!(
+            "Hive Metastore address {} does not resolve",
+            address
+        )));
+        for address in address.to_socket_addrs()? {
+            stream = TcpStream::connect_timeout(&address, timeout)
+                .map_err(DataFusionError::from);
+            if stream.is_ok() {
+                break;
+            }
+        }
+        let stream = stream?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+        let input = TBufferedReadTransport::new(stream.try_clone()?);
+        let output = TBufferedWriteTransport::new(stream);
+        Ok(Self {
+            input: Box::new(TBinaryInputProtocol::new(input, true)),
+            output: Box::new(TBinaryOutputProtocol::new(output, true)),
+            sequence_number: 0,
+        })
+    }
+
+    fn call<T>(
+        &mut self,
+        method: &str,
+        write_args: &dyn Fn(&mut dyn TOutputProtocol) -> thrift::Result<()>,
+        read_result: &mut dyn FnMut(&mut dyn TInputProtocol) -> thrift::Result<T>,
+    ) -> thrift::Result<std::result::Result<T, MetastoreException>> {
+        self.sequence_number += 1;
+        let o = self.output.as_mut();
+        o.write_message_begin(&TMessageIdentifier::new(
+            method,
+            TMessageType::Call,
+            self.sequence_number,
+        ))?;
+        o.write_struct_begin(&TStructIdentifier::new(format!("{}_args", method)))?;
+        write_args(o)?;
+        o.write_field_stop()?;
+        o.write_struct_end()?;
+        o.write_message_end()?;
+        o.flush()?;
+
+        let i = self.input.as_mut();
+        let message = i.read_message_begin()?;
+        if message.message_type == TMessageType::Exception {
+            let error = thrift::Error::read_application_error_from_in_protocol(i)?;
+            i.read_message_end()?;
+            return Err(thrift::Error::Application(error));
+        }
+        if message.sequence_number != self.sequence_number {
+            return Err(thrift::new_protocol_error(
+                ProtocolErrorKind::InvalidData,
+                format!(
+                    "Response {} to request {} of {}",
+                    message.sequence_number, self.sequence_number, method
+                ),
+            ));
+        }
+
+        // the result is field 0 of the response and the exceptions the other fields
+        let mut result = None;
+        let mut exception = None;
+        read_struct(i, |i, id, field_type| {
+            match (id, field_type) {
+                (0, _) => result = Some(read_result(i)?),
+                (id, TType::Struct) => {
+                    exception = Some(MetastoreException {
+                        id,
+                        message: read_exception_message(i)?,
+                    })
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        i.read_message_end()?;
+        match (result, exception) {
+            (_, Some(exception)) => Ok(Err(exception)),
+            (Some(result), None) => Ok(Ok(result)),
+            (None, None) => Err(thrift::new_protocol_error(
+                ProtocolErrorKind::InvalidData,
+                format!("Response of {} without a result", method),
+            )),
+        }
+    }
+}
+
+/// An exception declared by a method of the metastore, with the id of its field in
+/// the response, e.g. a `NoSuchObjectException`
+#[derive(Debug)]
+struct MetastoreException {
+    id: i16,
+    message: String,
+}
+
+impl MetastoreException {
+    fn into_error(self, method: &str) -> DataFusionError {
+        DataFusionError::Execution(format!(
+            "Hive Metastore request {} failed: {}",
+            method, self.message
+        ))
+    }
+}
+
+/// A column of a table
+#[derive(Debug, Default)]
+struct FieldSchema {
+    name: String,
+    type_name: String,
+}
+
+/// The location and format of the files of a table or partition
+#[derive(Debug, Default)]
+struct StorageDescriptor {
+    cols: Vec<FieldSchema>,
+    location: String,
+    input_format: String,
+    serialization_lib: String,
+    serde_parameters: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct HiveTable {
+    sd: StorageDescriptor,
+    partition_keys: Vec<FieldSchema>,
+    parameters: HashMap<String, String>,
+    table_type: String,
+}
+
+#[derive(Debug, Default)]
+struct HivePartition {
+    values: Vec<String>,
+    sd: StorageDescriptor,
+}
+
+fn write_string_field(
+    o: &mut dyn TOutputProtocol,
+    name: &str,
+    id: i16,
+    value: &str,
+) -> thrift::Result<()> {
+    o.write_field_begin(&TFieldIdentifier::new(name, TType::String, id))?;
+    o.write_string(value)?;
+    o.write_field_end()
+}
+
+/// Reads a struct, calling `read_field` with the id and type of each of its fields.
+/// The fields for which it returns false, e.g. the fields that are not used, are
+/// skipped.
+fn read_struct<F>(i: &mut dyn TInputProtocol, mut read_field: F) -> thrift::Result<()>
+where
+    F: FnMut(&mut dyn TInputProtocol, i16, TType) -> thrift::Result<bool>,
+{
+    i.read_struct_begin()?;
+    loop {
+        let field = i.read_field_begin()?;
+        if field.field_type == TType::Stop {
+            break;
+        }
+        let read = match field.id {
+            Some(id) => read_field(i, id, field.field_type)?,
+            None => false,
+        };
+        if !read {
+            i.skip(field.field_type)?;
+        }
+        i.read_field_end()?;
+    }
+    i.read_struct_end()
+}
+
+fn read_list<T>(
+    i: &mut dyn TInputProtocol,
+    mut read_element: impl FnMut(&mut dyn TInputProtocol) -> thrift::Result<T>,
+) -> thrift::Result<Vec<T>> {
+    let list = i.read_list_begin()?;
+    let elements = (0..list.size)
+        .map(|_| read_element(i))
+        .collect::<thrift::Result<Vec<_>>>()?;
+    i.read_list_end()?;
+    Ok(elements)
+}
+
+fn read_string_map(
+    i: &mut dyn TInputProtocol,
+) -> thrift::Result<HashMap<String, String>> {
+    let map = i.read_map_begin()?;
+    let mut entries = HashMap::new();
+    for _ in 0..map.size {
+        let key = i.read_string()?;
+        let value = i.read_string()?;
+        entries.insert(key, value);
+    }
+    i.read_map_end()?;
+    Ok(entries)
+}
+
+fn read_exception_message(i: &mut dyn TInputProtocol) -> thrift::Result<String> {
+    let mut message = String::new();
+    read_struct(i, |i, id, field_type| match (id, field_type) {
+        (1, TType::String) => {
+            message = i.read_string()?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    })?;
+    Ok(message)
+}
+
+fn read_field_schema(i: &mut dyn TInputProtocol) -> thrift::Result<FieldSchema> {
+    let mut field = FieldSchema::default();
+    read_struct(i, |i, id, field_type| {
+        match (id, field_type) {
+            (1, TType::String) => field.name = i.read_string()?,
+            (2, TType::String) => field.type_name = i.read_string()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(field)
+}
+
+fn read_storage_descriptor(
+    i: &mut dyn TInputProtocol,
+) -> thrift::Result<StorageDescriptor> {
+    let mut sd = StorageDescriptor::default();
+    read_struct(i, |i, id, field_type| {
+        match (id, field_type) {
+            (1, TType::List) => sd.cols = read_list(i, read_field_schema)?,
+            (2, TType::String) => sd.location = i.read_string()?,
+            (3, TType::String) => sd.input_format = i.read_string()?,
+            // SerDeInfo
+            (7, TType::Struct) => read_struct(i, |i, id, field_type| {
+                match (id, field_type) {
+                    (2, TType::String) => sd.serialization_lib = i.read_string()?,
+                    (3, TType::Map) => sd.serde_parameters = read_string_map(i)?,
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            })?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(sd)
+}
+
+fn read_table(i: &mut dyn TInputProtocol) -> thrift::Result<HiveTable> {
+    let mut table = HiveTable::default();
+    read_struct(i, |i, id, field_type| {
+        match (id, field_type) {
+            (7, TType::Struct) => table.sd = read_storage_descriptor(i)?,
+            (8, TType::List) => table.partition_keys = read_list(i, read_field_schema)?,
+            (9, TType::Map) => table.parameters = read_string_map(i)?,
+            (12, TType::String) => table.table_type = i.read_string()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(table)
+}
+
+fn read_partition(i: &mut dyn TInputProtocol) -> thrift::Result<HivePartition> {
+    let mut partition = HivePartition::default();
+    read_struct(i, |i, id, field_type| {
+        match (id, field_type) {
+            (1, TType::List) => partition.values = read_list(i, |i| i.read_string())?,
+            (6, TType::Struct) => partition.sd = read_storage_descriptor(i)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    Ok(partition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_batches_eq;
+    use crate::prelude::ExecutionContext;
+    use std::fs;
+    use std::net::TcpListener;
+    use std::path::Path;
+    use thrift::protocol::{TListIdentifier, TMapIdentifier};
+
+    #[test]
+    fn hive_types() -> Result<()> {
+        assert_eq!(hive_type("INT")?, DataType::Int32);
+        assert_eq!(hive_type("varchar(10)")?, DataType::Utf8);
+        assert_eq!(hive_type("decimal")?, DataType::Decimal(10, 0));
+        assert_eq!(hive_type("decimal(12, 2)")?, DataType::Decimal(12, 2));
+        assert!(hive_type("decimal(a)").is_err());
+        assert!(hive_type("array<int>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn storage_formats() -> Result<()> {
+        let mut sd = StorageDescriptor {
+            input_format: TEXT_INPUT_FORMAT.to_owned(),
+            ..Default::default()
+        };
+        let mut parameters = HashMap::new();
+        let format = StorageFormat::try_new(&sd, &parameters)?;
+        assert_eq!(
+            format,
+            StorageFormat::Text {
+                delimiter: 1,
+                has_header: false
+            }
+        );
+
+        sd.serde_parameters
+            .insert("field.delim".to_owned(), "|".to_owned());
+        parameters.insert("skip.header.line.count".to_owned(), "1".to_owned());
+        let format = StorageFormat::try_new(&sd, &parameters)?;
+        assert_eq!(
+            format,
+            StorageFormat::Text {
+                delimiter: b'|',
+                has_header: true
+            }
+        );
+
+        sd.input_format = "org.apache.hadoop.hive.ql.io.orc.OrcInputFormat".to_owned();
+        assert!(StorageFormat::try_new(&sd, &parameters).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn local_paths() -> Result<()> {
+        let catalog = HiveMetastoreCatalog::new("localhost:9083")
+            .with_location_mapping("hdfs://namenode:8020/", "/mnt/hdfs/");
+        assert_eq!(catalog.local_path("file:/data/t")?, "/data/t");
+        assert_eq!(catalog.local_path("file:///data/t")?, "/data/t");
+        assert_eq!(
+            catalog.local_path("hdfs://namenode:8020/warehouse/t")?,
+            "/mnt/hdfs/warehouse/t"
+        );
+        assert!(catalog.local_path("s3a://bucket/t").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partitioned_text_table() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path().to_owned();
+        for (year, contents) in &[("2020", "1,x\n2,y\n"), ("2021", "3,z\n")] {
+            let dir = root.join(format!("year={}", year));
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("000000_0"), contents)?;
+        }
+
+        let (address, requests) = serve_metastore(&root)?;
+        let catalog = HiveMetastoreCatalog::new(address);
+        assert_eq!(catalog.schema_names(), vec!["default"]);
+        let schema = catalog.schema("default").unwrap();
+        assert_eq!(schema.table_names(), vec!["sales"]);
+        assert!(catalog.table("default", "missing")?.is_none());
+
+        // the table is cached, and its partitions are only listed by its scans
+        requests.lock().unwrap().clear();
+        let table = catalog.table("default", "sales")?.unwrap();
+        assert_eq!(table.schema().fields().len(), 3);
+        assert!(catalog.table("default", "sales")?.is_some());
+        assert_eq!(*requests.lock().unwrap(), vec!["get_table"]);
+
+        let mut ctx = ExecutionContext::new();
+        ctx.register_catalog("hive", Arc::new(catalog));
+        let batches = ctx
+            .sql("SELECT id, name, year FROM hive.default.sales ORDER BY id")?
+            .collect()
+            .await?;
+        let expected = vec![
+            "+----+------+------+",
+            "| id | name | year |",
+            "+----+------+------+",
+            "| 1  | x    | 2020 |",
+            "| 2  | y    | 2020 |",
+            "| 3  | z    | 2021 |",
+            "+----+------+------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["get_table", "get_partitions"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn metastore_errors() -> Result<()> {
+        // nothing listens at the address of a listener that was closed
+        let address = TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string();
+        let catalog =
+            HiveMetastoreCatalog::new(address).with_timeout(Duration::from_secs(1));
+        assert!(catalog.table("default", "sales").is_err());
+        let schema = catalog.schema("default").unwrap();
+        assert!(schema.try_table("sales").is_err());
+        Ok(())
+    }
+
+    /// Serves the metastore of a database `default` with the table `sales`, whose
+    /// partitions by year are the directories of `root`, and returns its address and
+    /// the names of the requests it received
+    fn serve_metastore(root: &Path) -> Result<(String, Arc<Mutex<Vec<String>>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?.to_string();
+        let root = root.to_owned();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let input = TBufferedReadTransport::new(stream.try_clone().unwrap());
+                let mut input = TBinaryInputProtocol::new(input, true);
+                let output = TBufferedWriteTransport::new(stream);
+                let mut output = TBinaryOutputProtocol::new(output, true);
+                while let Ok(message) = input.read_message_begin() {
+                    let mut args = HashMap::new();
+                    read_struct(&mut input, |i, id, field_type| {
+                        if field_type == TType::String {
+                            args.insert(id, i.read_string()?);
+                            return Ok(true);
+                        }
+                        Ok(false)
+                    })
+                    .unwrap();
+                    input.read_message_end().unwrap();
+                    received.lock().unwrap().push(message.name.clone());
+                    reply(&mut output, &message, &args, &root).unwrap();
+                }
+            }
+        });
+        Ok((address, requests))
+    }
+
+    fn reply(
+        o: &mut dyn TOutputProtocol,
+        message: &TMessageIdentifier,
+        args: &HashMap<i16, String>,
+        root: &Path,
+    ) -> thrift::Result<()> {
+        o.write_message_begin(&TMessageIdentifier::new(
+            message.name.clone(),
+            TMessageType::Reply,
+            message.sequence_number,
+        ))?;
+        o.write_struct_begin(&TStructIdentifier::new("result"))?;
+        match (message.name.as_str(), args.get(&2).map(|s| s.as_str())) {
+            ("get_all_databases", _) => write_strings(o, 0, &["default"])?,
+            ("get_all_tables", _) => write_strings(o, 0, &["sales"])?,
+            ("get_table", Some("sales")) => {
+                o.write_field_begin(&TFieldIdentifier::new("success", TType::Struct, 0))?;
+                o.write_struct_begin(&TStructIdentifier::new("Table"))?;
+                write_string_field(o, "tableName", 1, "sales")?;
+                write_storage_descriptor(o, 7, &format!("file:{}", root.display()))?;
+                o.write_field_begin(&TFieldIdentifier::new(
+                    "partitionKeys",
+                    TType::List,
+                    8,
+                ))?;
+                o.write_list_begin(&TListIdentifier::new(TType::Struct, 1))?;
+                write_field_schema(o, "year", "string")?;
+                o.write_list_end()?;
+                o.write_field_end()?;
+                write_string_field(o, "tableType", 12, "EXTERNAL_TABLE")?;
+                o.write_field_stop()?;
+                o.write_struct_end()?;
+                o.write_field_end()?;
+            }
+            ("get_table", _) => {
+                o.write_field_begin(&TFieldIdentifier::new("o2", TType::Struct, 2))?;
+                o.write_struct_begin(&TStructIdentifier::new("NoSuchObjectException"))?;
+                write_string_field(o, "message", 1, "table not found")?;
+                o.write_field_stop()?;
+                o.write_struct_end()?;
+                o.write_field_end()?;
+            }
+            ("get_partitions", _) => {
+                o.write_field_begin(&TFieldIdentifier::new("success", TType::List, 0))?;
+                o.write_list_begin(&TListIdentifier::new(TType::Struct, 2))?;
+                for year in &["2020", "2021"] {
+                    o.write_struct_begin(&TStructIdentifier::new("Partition"))?;
+                    write_strings(o, 1, &[year])?;
+                    let location = root.join(format!("year={}", year));
+                    write_storage_descriptor(o, 6, &location.display().to_string())?;
+                    o.write_field_stop()?;
+                    o.write_struct_end()?;
+                }
+                o.write_list_end()?;
+                o.write_field_end()?;
+            }
+            (name, _) => panic!("unexpected request {}", name),
+        }
+        o.write_field_stop()?;
+        o.write_struct_end()?;
+        o.write_message_end()?;
+        o.flush()
+    }
+
+    fn write_strings(
+        o: &mut dyn TOutputProtocol,
+        id: i16,
+        values: &[&str],
+    ) -> thrift::Result<()> {
+        o.write_field_begin(&TFieldIdentifier::new("values", TType::List, id))?;
+        o.write_list_begin(&TListIdentifier::new(TType::String, values.len() as i32))?;
+        for value in values {
+            o.write_string(value)?;
+        }
+        o.write_list_end()?;
+        o.write_field_end()
+    }
+
+    fn write_field_schema(
+        o: &mut dyn TOutputProtocol,
+        name: &str,
+        type_name: &str,
+    ) -> thrift::Result<()> {
+        o.write_struct_begin(&TStructIdentifier::new("FieldSchema"))?;
+        write_string_field(o, "name", 1, name)?;
+        write_string_field(o, "type", 2, type_name)?;
+        o.write_field_stop()?;
+        o.write_struct_end()
+    }
+
+    /// Writes the storage descriptor of a table or partition as its field `id`
+    fn write_storage_descriptor(
+        o: &mut dyn TOutputProtocol,
+        id: i16,
+        location: &str,
+    ) -> thrift::Result<()> {
+        o.write_field_begin(&TFieldIdentifier::new("sd", TType::Struct, id))?;
+        o.write_struct_begin(&TStructIdentifier::new("StorageDescriptor"))?;
+        o.write_field_begin(&TFieldIdentifier::new("cols", TType::List, 1))?;
+        o.write_list_begin(&TListIdentifier::new(TType::Struct, 2))?;
+        write_field_schema(o, "id", "int")?;
+        write_field_schema(o, "name", "string")?;
+        o.write_list_end()?;
+        o.write_field_end()?;
+        write_string_field(o, "location", 2, location)?;
+        write_string_field(o, "inputFormat", 3, TEXT_INPUT_FORMAT)?;
+        o.write_field_begin(&TFieldIdentifier::new("serdeInfo", TType::Struct, 7))?;
+        o.write_struct_begin(&TStructIdentifier::new("SerDeInfo"))?;
+        write_string_field(
+            o,
+            "serializationLib",
+            2,
+            "org.apache.hadoop.hive.serde2.lazy.LazySimpleSerDe",
+        )?;
+        o.write_field_begin(&TFieldIdentifier::new("parameters", TType::Map, 3))?;
+        o.write_map_begin(&TMapIdentifier::new(TType::String, TType::String, 1))?;
+        o.write_string("field.delim")?;
+        o.write_string(",")?;
+        o.write_map_end()?;
+        o.write_field_end()?;
+        o.write_field_stop()?;
+        o.write_struct_end()?;
+        o.write_field_end()?;
+        o.write_field_stop()?;
+        o.write_struct_end()?;
+        o.write_field_end()
+    }
+}
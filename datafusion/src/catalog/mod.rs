@@ -19,6 +19,8 @@
 //! of table namespacing concepts, including catalogs and schemas.
 
 pub mod catalog;
+#[cfg(feature = "hive")]
+pub mod hive;
 pub mod information_schema;
 pub mod schema;
 
@@ -36,6 +36,12 @@ pub trait SchemaProvider: Sync + Send {
     /// Retrieves a specific table from the schema by name, provided it exists.
     fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>>;
 
+    /// Retrieves a specific table from the schema by name, provided it exists, or
+    /// returns the error that prevented looking it up, e.g. from a remote catalog.
+    fn try_table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>> {
+        Ok(self.table(name))
+    }
+
     /// If supported by the implementation, adds a new table to this schema.
     /// If a table of the same name existed before, it is replaced in the schema and returned.
     #[allow(unused_variables)]
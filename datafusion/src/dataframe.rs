@@ -24,6 +24,7 @@ use crate::logical_plan::{
 };
 use std::sync::Arc;
 
+use crate::physical_plan::parquet_writer::ParquetWriteOptions;
 use crate::physical_plan::SendableRecordBatchStream;
 use async_trait::async_trait;
 
@@ -68,6 +69,21 @@ pub trait DataFrame: Send + Sync {
     /// ```
     fn select_columns(&self, columns: &[&str]) -> Result<Arc<dyn DataFrame>>;
 
+    /// Filter the DataFrame by column. Returns a new DataFrame containing all
+    /// columns except the specified ones, like `SELECT * EXCLUDE (...)` in SQL.
+    ///
+    /// ```
+    /// # use datafusion::prelude::*;
+    /// # use datafusion::error::Result;
+    /// # fn main() -> Result<()> {
+    /// let mut ctx = ExecutionContext::new();
+    /// let df = ctx.read_csv("tests/example.csv", CsvReadOptions::new())?;
+    /// let df = df.except_columns(&["a", "b"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn except_columns(&self, columns: &[&str]) -> Result<Arc<dyn DataFrame>>;
+
     /// Create a projection based on arbitrary expressions.
     ///
     /// ```
@@ -208,6 +224,37 @@ pub trait DataFrame: Send + Sync {
         partitioning_scheme: Partitioning,
     ) -> Result<Arc<dyn DataFrame>>;
 
+    /// Expand a list column into one row per list element, repeating the values of
+    /// the other columns. Rows whose list is null or empty are dropped.
+    ///
+    /// ```
+    /// # use datafusion::prelude::*;
+    /// # use datafusion::error::Result;
+    /// # use arrow::array::{Int32Array, Int32Builder, ListBuilder};
+    /// # use arrow::datatypes::{DataType, Field, Schema};
+    /// # use arrow::record_batch::RecordBatch;
+    /// # use datafusion::datasource::MemTable;
+    /// # use std::sync::Arc;
+    /// # fn main() -> Result<()> {
+    /// let mut builder = ListBuilder::new(Int32Builder::new(4));
+    /// builder.values().append_slice(&[1, 2])?;
+    /// builder.append(true)?;
+    /// let schema = Arc::new(Schema::new(vec![
+    ///     Field::new("id", DataType::Int32, false),
+    ///     Field::new("tags", DataType::List(Box::new(Field::new("item", DataType::Int32, true))), true),
+    /// ]));
+    /// let batch = RecordBatch::try_new(
+    ///     schema.clone(),
+    ///     vec![Arc::new(Int32Array::from(vec![1])), Arc::new(builder.finish())],
+    /// )?;
+    /// let mut ctx = ExecutionContext::new();
+    /// let df = ctx.read_table(Arc::new(MemTable::try_new(schema, vec![vec![batch]])?))?;
+    /// let df = df.unnest_column("tags")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn unnest_column(&self, column: &str) -> Result<Arc<dyn DataFrame>>;
+
     /// Executes this DataFrame and collects all results into a vector of RecordBatch.
     ///
     /// ```
@@ -223,6 +270,26 @@ pub trait DataFrame: Send + Sync {
     /// ```
     async fn collect(&self) -> Result<Vec<RecordBatch>>;
 
+    /// Executes this DataFrame and summarizes its numeric columns, similar to the
+    /// `describe` methods of Pandas and Spark DataFrames. The result has a `statistic`
+    /// column naming the `count`, `null_count`, `mean`, `stddev`, `min` and `max` rows
+    /// and a `Float64` column per numeric column of this DataFrame, whose statistics
+    /// are all computed by a single aggregation. `stddev` is the sample standard
+    /// deviation, which is null for columns with fewer than two values.
+    ///
+    /// ```
+    /// # use datafusion::prelude::*;
+    /// # use datafusion::error::Result;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// let mut ctx = ExecutionContext::new();
+    /// let df = ctx.read_csv("tests/example.csv", CsvReadOptions::new())?;
+    /// let summary = df.describe().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn describe(&self) -> Result<RecordBatch>;
+
     /// Executes this DataFrame and returns a stream over a single partition
     ///
     /// ```
@@ -269,6 +336,31 @@ pub trait DataFrame: Send + Sync {
     /// ```
     async fn execute_stream_partitioned(&self) -> Result<Vec<SendableRecordBatchStream>>;
 
+    /// Executes this DataFrame and writes each partition to a Parquet file in the new
+    /// directory `path`, with the compression, encodings and statistics of `options`.
+    ///
+    /// ```
+    /// # use datafusion::prelude::*;
+    /// # use datafusion::error::Result;
+    /// # use parquet::basic::Compression;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// let mut ctx = ExecutionContext::new();
+    /// let df = ctx.read_csv("tests/example.csv", CsvReadOptions::new())?;
+    /// let options = ParquetWriteOptions::new()
+    ///     .compression(Compression::SNAPPY)
+    ///     .column_compression("a", Compression::ZSTD)
+    ///     .column_dictionary_enabled("b", false);
+    /// # let dir = tempfile::tempdir()?;
+    /// # let path = dir.path().join("example");
+    /// # let path = path.to_str().unwrap();
+    /// df.write_parquet(path, options).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn write_parquet(&self, path: &str, options: ParquetWriteOptions)
+        -> Result<()>;
+
     /// Returns the schema describing the output of this DataFrame in terms of columns returned,
     /// where each column has a name, data type, and nullability attribute.
 
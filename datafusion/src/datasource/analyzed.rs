@@ -0,0 +1,89 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A table whose statistics were computed by scanning its data, see
+//! [`ExecutionContext::analyze_table`](crate::execution::context::ExecutionContext::analyze_table).
+
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::datatypes::SchemaRef;
+
+use crate::datasource::datasource::{Statistics, TableProviderFilterPushDown};
+use crate::datasource::{TableProvider, TableType};
+use crate::error::Result;
+use crate::logical_plan::Expr;
+use crate::physical_plan::ExecutionPlan;
+
+/// Wraps a table with the statistics of its data, including the distinct counts of its
+/// columns that the table itself may not know. The statistics describe the data when it
+/// was analyzed and become estimates if the data changes.
+pub struct AnalyzedTable {
+    table: Arc<dyn TableProvider>,
+    statistics: Statistics,
+}
+
+impl AnalyzedTable {
+    /// Wraps `table`, whose data has the given `statistics`
+    pub fn new(table: Arc<dyn TableProvider>, statistics: Statistics) -> Self {
+        Self { table, statistics }
+    }
+
+    /// The analyzed table
+    pub fn table(&self) -> Arc<dyn TableProvider> {
+        self.table.clone()
+    }
+}
+
+impl TableProvider for AnalyzedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.table.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        self.table.table_type()
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        batch_size: usize,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.table.scan(projection, batch_size, filters, limit)
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+
+    fn unbounded(&self) -> bool {
+        self.table.unbounded()
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> Result<TableProviderFilterPushDown> {
+        self.table.supports_filter_pushdown(filter)
+    }
+}
@@ -34,10 +34,12 @@
 //! ```
 
 use arrow::datatypes::SchemaRef;
+use bytes::Bytes;
 use std::any::Any;
-use std::io::{Read, Seek};
+use std::io::{Cursor, Read, Seek};
 use std::string::String;
 use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use crate::datasource::datasource::Statistics;
 use crate::datasource::{Source, TableProvider};
@@ -45,7 +47,8 @@ use crate::error::{DataFusionError, Result};
 use crate::logical_plan::Expr;
 use crate::physical_plan::csv::CsvExec;
 pub use crate::physical_plan::csv::CsvReadOptions;
-use crate::physical_plan::{common, ExecutionPlan};
+use crate::physical_plan::file_compression::FileCompressionType;
+use crate::physical_plan::{common, common::FileListOptions, ExecutionPlan};
 
 /// Represents a CSV file with a provided schema
 pub struct CsvFile {
@@ -54,6 +57,9 @@ pub struct CsvFile {
     has_header: bool,
     delimiter: u8,
     file_extension: String,
+    file_compression_type: Option<FileCompressionType>,
+    file_list_options: FileListOptions,
+    target_split_size: Option<usize>,
     statistics: Statistics,
 }
 
@@ -64,7 +70,14 @@ impl CsvFile {
         let schema = Arc::new(match options.schema {
             Some(s) => s.clone(),
             None => {
-                let filenames = common::build_file_list(&path, options.file_extension)?;
+                let filenames = common::build_file_list_with_options(
+                    &path,
+                    &FileCompressionType::file_extensions(
+                        options.file_extension,
+                        options.file_compression_type,
+                    ),
+                    &options.file_list_options,
+                )?;
                 if filenames.is_empty() {
                     return Err(DataFusionError::Plan(format!(
                         "No files found at {path} with file extension {file_extension}",
@@ -82,6 +95,9 @@ impl CsvFile {
             has_header: options.has_header,
             delimiter: options.delimiter,
             file_extension: String::from(options.file_extension),
+            file_compression_type: options.file_compression_type,
+            file_list_options: options.file_list_options,
+            target_split_size: options.target_split_size,
             statistics: Statistics::default(),
         })
     }
@@ -107,6 +123,9 @@ impl CsvFile {
             delimiter: options.delimiter,
             statistics: Statistics::default(),
             file_extension: String::new(),
+            file_compression_type: options.file_compression_type,
+            file_list_options: options.file_list_options,
+            target_split_size: None,
         })
     }
 
@@ -135,13 +154,43 @@ impl CsvFile {
             delimiter: options.delimiter,
             statistics: Statistics::default(),
             file_extension: String::new(),
+            file_compression_type: options.file_compression_type,
+            file_list_options: options.file_list_options,
+            target_split_size: None,
+        })
+    }
+
+    /// Attempt to initialize a `CsvFile` from an in-memory buffer, which can be scanned
+    /// more than once. The schema can be inferred automatically.
+    pub fn try_new_from_bytes(
+        bytes: impl Into<Bytes>,
+        options: CsvReadOptions,
+    ) -> Result<Self> {
+        let bytes = bytes.into();
+        let table =
+            Self::try_new_from_reader_infer_schema(Cursor::new(bytes.clone()), options)?;
+        Ok(Self {
+            source: Source::Bytes(bytes),
+            ..table
         })
     }
 
+    /// Attempt to initialize a `CsvFile` from an async reader, such as a pipe or a socket.
+    /// The whole input is read into memory before the table is created, so it must fit
+    /// in memory, and the schema can be inferred automatically.
+    pub async fn try_new_from_async_reader<R: AsyncRead + Unpin>(
+        mut reader: R,
+        options: CsvReadOptions<'_>,
+    ) -> Result<Self> {
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await?;
+        Self::try_new_from_bytes(buf, options)
+    }
+
     /// Get the path for the CSV file(s) represented by this CsvFile instance
     pub fn path(&self) -> &str {
         match &self.source {
-            Source::Reader(_) => "",
+            Source::Reader(_) | Source::Bytes(_) => "",
             Source::Path(path) => path,
         }
     }
@@ -160,6 +209,22 @@ impl CsvFile {
     pub fn file_extension(&self) -> &str {
         &self.file_extension
     }
+
+    /// Get the compression of the CSV file(s) represented by this CsvFile instance
+    pub fn file_compression_type(&self) -> Option<FileCompressionType> {
+        self.file_compression_type
+    }
+
+    /// Get how the files of the path of this CsvFile instance are listed
+    pub fn file_list_options(&self) -> FileListOptions {
+        self.file_list_options
+    }
+
+    /// Get the size of the byte ranges that the large uncompressed files of this CsvFile
+    /// instance are split into, if any
+    pub fn target_split_size(&self) -> Option<usize> {
+        self.target_split_size
+    }
 }
 
 impl TableProvider for CsvFile {
@@ -178,11 +243,14 @@ impl TableProvider for CsvFile {
         _filters: &[Expr],
         limit: Option<usize>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        let opts = CsvReadOptions::new()
+        let mut opts = CsvReadOptions::new()
             .schema(&self.schema)
             .has_header(self.has_header)
             .delimiter(self.delimiter)
-            .file_extension(self.file_extension.as_str());
+            .file_extension(self.file_extension.as_str())
+            .file_list_options(self.file_list_options);
+        opts.file_compression_type = self.file_compression_type;
+        opts.target_split_size = self.target_split_size;
         let batch_size = limit
             .map(|l| std::cmp::min(l, batch_size))
             .unwrap_or(batch_size);
@@ -204,6 +272,13 @@ impl TableProvider for CsvFile {
                     ));
                 }
             }
+            Source::Bytes(bytes) => CsvExec::try_new_from_reader(
+                Cursor::new(bytes.clone()),
+                opts,
+                projection.clone(),
+                batch_size,
+                limit,
+            )?,
             Source::Path(p) => {
                 CsvExec::try_new(p, opts, projection.clone(), batch_size, limit)?
             }
@@ -249,4 +324,49 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn csv_file_from_bytes() -> Result<()> {
+        let data = "c1,c2\n1,hello\n2,world\n";
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table(
+            "t",
+            Arc::new(CsvFile::try_new_from_bytes(data, CsvReadOptions::new())?),
+        )?;
+        let expected = vec![
+            "+-------+",
+            "| c2    |",
+            "+-------+",
+            "| world |",
+            "+-------+",
+        ];
+        // the buffer is read again by each scan
+        for _ in 0..2 {
+            let batches = ctx.sql("select c2 from t where c1 = 2")?.collect().await?;
+            crate::assert_batches_eq!(expected, &batches);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn csv_file_from_async_reader() -> Result<()> {
+        let data: &[u8] = b"c1,c2\n1,hello\n2,world\n";
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table(
+            "t",
+            Arc::new(
+                CsvFile::try_new_from_async_reader(data, CsvReadOptions::new()).await?,
+            ),
+        )?;
+        let batches = ctx.sql("select sum(c1) from t")?.collect().await?;
+        let expected = vec![
+            "+---------+",
+            "| SUM(c1) |",
+            "+---------+",
+            "| 3       |",
+            "+---------+",
+        ];
+        crate::assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
 }
@@ -37,7 +37,7 @@ pub struct Statistics {
     pub column_statistics: Option<Vec<ColumnStatistics>>,
 }
 /// This table statistics are estimates about column
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ColumnStatistics {
     /// Number of null values on column
     pub null_count: Option<usize>,
@@ -113,6 +113,13 @@ pub trait TableProvider: Sync + Send {
         false
     }
 
+    /// Returns whether this table is an unbounded source, such as a stream of
+    /// batches that keeps receiving data over time. Scans of unbounded tables can
+    /// only be consumed by operators that emit results incrementally.
+    fn unbounded(&self) -> bool {
+        false
+    }
+
     /// Tests whether the table provider can make use of a filter expression
     /// to optimise data retrieval.
     fn supports_filter_pushdown(
@@ -0,0 +1,865 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Delta Lake data source
+//!
+//! Reads a version of a [Delta Lake](https://delta.io) table. The commits of the
+//! transaction log in the `_delta_log` directory of the table are replayed to find the
+//! Parquet files of the version and their partition values, and the files of each
+//! partition are read with a [`ParquetExec`]. The partition columns follow the columns
+//! of the files, and the filters on them skip the partitions that do not match them, as
+//! for a [`PartitionedTable`](super::partitioned::PartitionedTable).
+//!
+//! Tables are read-only, and only the JSON commits of the log are read: tables whose
+//! first commits were removed after a checkpoint, and tables that require a reader
+//! version above 1, e.g. for column mapping, are not supported.
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use serde_json::Value;
+
+use crate::datasource::datasource::{Statistics, TableProviderFilterPushDown};
+use crate::datasource::partition_pruning::PartitionColumns;
+use crate::datasource::TableProvider;
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::{combine_filters, Expr};
+use crate::physical_plan::empty::EmptyExec;
+use crate::physical_plan::expressions::{self, lit};
+use crate::physical_plan::parquet::ParquetExec;
+use crate::physical_plan::projection::ProjectionExec;
+use crate::physical_plan::union::UnionExec;
+use crate::physical_plan::{ExecutionPlan, PhysicalExpr};
+use crate::scalar::ScalarValue;
+
+/// The files of a partition of a Delta Lake table
+struct DeltaPartition {
+    /// The values of the partition columns
+    values: Vec<ScalarValue>,
+    /// Paths of the Parquet files of the partition
+    files: Vec<String>,
+}
+
+/// A version of a Delta Lake table
+pub struct DeltaTable {
+    path: String,
+    version: i64,
+    /// The columns of the files followed by the partition columns
+    schema: SchemaRef,
+    /// Number of columns read from the files
+    num_file_columns: usize,
+    partitions: Vec<DeltaPartition>,
+    statistics: Statistics,
+    max_concurrency: usize,
+    enable_pruning: bool,
+}
+
+impl DeltaTable {
+    /// Attempt to initialize a `DeltaTable` from the latest version of the table at
+    /// `path`
+    pub fn try_new(path: impl Into<String>, max_concurrency: usize) -> Result<Self> {
+        Self::try_new_with_version(path, None, max_concurrency)
+    }
+
+    /// Attempt to initialize a `DeltaTable` from the given version of the table at
+    /// `path`, or from its latest version if `version` is `None`
+    pub fn try_new_with_version(
+        path: impl Into<String>,
+        version: Option<i64>,
+        max_concurrency: usize,
+    ) -> Result<Self> {
+        let path = path.into();
+        let log = DeltaLog::read(&path, version)?;
+
+        let (partition_fields, file_fields): (Vec<_>, Vec<_>) =
+            parse_schema(&log.schema_string)?
+                .into_iter()
+                .partition(|field| log.partition_columns.contains(field.name()));
+        // the partition columns are ordered as in the metadata of the table
+        let partition_fields = log
+            .partition_columns
+            .iter()
+            .map(|name| {
+                partition_fields
+                    .iter()
+                    .find(|field| field.name() == name)
+                    .map(|field| Field::new(name, field.data_type().clone(), true))
+                    .ok_or_else(|| {
+                        DataFusionError::Execution(format!(
+                            "Partition column {} is not a column of the Delta Lake table at {}",
+                            name, path
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut partitions: BTreeMap<Vec<Option<String>>, Vec<String>> = BTreeMap::new();
+        let mut num_rows = Some(0);
+        let mut total_byte_size = 0;
+        for (file_path, file) in &log.files {
+            let values = log
+                .partition_columns
+                .iter()
+                .map(|name| file.partition_values.get(name).cloned().flatten())
+                .collect();
+            partitions
+                .entry(values)
+                .or_default()
+                .push(resolve_path(&path, file_path)?);
+            num_rows = num_rows.zip(file.num_rows).map(|(sum, rows)| sum + rows);
+            total_byte_size += file.size;
+        }
+        let partitions = partitions
+            .into_iter()
+            .map(|(values, files)| {
+                let values = values
+                    .iter()
+                    .zip(&partition_fields)
+                    .map(|(value, field)| parse_partition_value(value, field))
+                    .collect::<Result<_>>()?;
+                Ok(DeltaPartition { values, files })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let num_file_columns = file_fields.len();
+        let schema =
+            Schema::new(file_fields.into_iter().chain(partition_fields).collect());
+        Ok(Self {
+            path,
+            version: log.version,
+            schema: Arc::new(schema),
+            num_file_columns,
+            partitions,
+            statistics: Statistics {
+                num_rows,
+                total_byte_size: Some(total_byte_size),
+                column_statistics: None,
+            },
+            max_concurrency,
+            enable_pruning: true,
+        })
+    }
+
+    /// Get the path of the table
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the version of the table that is read
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Get the paths of the Parquet files of the version
+    pub fn files(&self) -> Vec<&str> {
+        self.partitions
+            .iter()
+            .flat_map(|partition| partition.files.iter().map(|file| file.as_str()))
+            .collect()
+    }
+
+    /// Get parquet pruning option
+    pub fn get_enable_pruning(&self) -> bool {
+        self.enable_pruning
+    }
+
+    /// Set parquet pruning option
+    pub fn with_enable_pruning(mut self, enable_pruning: bool) -> Self {
+        self.enable_pruning = enable_pruning;
+        self
+    }
+
+    /// Returns the columns of the files and the partition columns
+    fn partition_columns(&self) -> PartitionColumns {
+        PartitionColumns::new(self.schema.clone(), self.num_file_columns)
+    }
+
+    /// Returns the values of each partition column, with one element per partition
+    fn partition_values(&self) -> Result<Vec<ArrayRef>> {
+        (0..self.schema.fields().len() - self.num_file_columns)
+            .map(|index| {
+                ScalarValue::iter_to_array(
+                    self.partitions
+                        .iter()
+                        .map(|partition| partition.values[index].clone()),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns for each partition whether its values may match all the `filters`,
+    /// which only reference partition columns
+    fn prune_partitions(&self, filters: &[&Expr]) -> Result<Vec<bool>> {
+        self.partition_columns().prune_partitions(
+            filters,
+            self.partition_values()?,
+            self.partitions.len(),
+        )
+    }
+}
+
+impl TableProvider for DeltaTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> Result<TableProviderFilterPushDown> {
+        let columns = self.partition_columns();
+        if columns.is_exact_partition_filter(filter) {
+            Ok(TableProviderFilterPushDown::Exact)
+        } else {
+            // the other filters on partition columns skip partitions, and the filters
+            // on the columns of the files skip row groups
+            Ok(TableProviderFilterPushDown::Inexact)
+        }
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        batch_size: usize,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let projection = projection
+            .clone()
+            .unwrap_or_else(|| (0..self.schema.fields().len()).collect());
+        let mut file_projection = projection
+            .iter()
+            .filter(|i| **i < self.num_file_columns)
+            .copied()
+            .collect::<Vec<_>>();
+        if file_projection.is_empty() && self.num_file_columns > 0 {
+            // the files are still read to know the number of rows
+            file_projection.push(0);
+        }
+        let file_schema = Arc::new(Schema::new(
+            self.schema.fields()[..self.num_file_columns].to_vec(),
+        ));
+        let columns = self.partition_columns();
+        let predicate = if self.enable_pruning {
+            let file_filters = filters
+                .iter()
+                .filter(|filter| columns.is_file_filter(filter))
+                .cloned()
+                .collect::<Vec<_>>();
+            combine_filters(&file_filters)
+        } else {
+            None
+        };
+        let partition_filters = filters
+            .iter()
+            .filter(|filter| columns.is_partition_filter(filter))
+            .collect::<Vec<_>>();
+        let keep = self.prune_partitions(&partition_filters)?;
+
+        let plans = self
+            .partitions
+            .iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|(partition, _)| {
+                let files = partition
+                    .files
+                    .iter()
+                    .map(|file| file.as_str())
+                    .collect::<Vec<_>>();
+                let input = ParquetExec::try_from_files_with_schema(
+                    &files,
+                    Some(file_schema.clone()),
+                    Some(file_projection.clone()),
+                    predicate.clone(),
+                    limit
+                        .map(|l| std::cmp::min(l, batch_size))
+                        .unwrap_or(batch_size),
+                    self.max_concurrency,
+                    limit,
+                )?;
+                let exprs = projection
+                    .iter()
+                    .map(|i| {
+                        let name = self.schema.field(*i).name().clone();
+                        let expr: Arc<dyn PhysicalExpr> = if *i < self.num_file_columns {
+                            let index =
+                                file_projection.iter().position(|p| p == i).unwrap();
+                            Arc::new(expressions::Column::new(&name, index))
+                        } else {
+                            lit(partition.values[*i - self.num_file_columns].clone())
+                        };
+                        (expr, name)
+                    })
+                    .collect();
+                Ok(Arc::new(ProjectionExec::try_new(exprs, Arc::new(input))?)
+                    as Arc<dyn ExecutionPlan>)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if plans.is_empty() {
+            let schema = Schema::new(
+                projection
+                    .iter()
+                    .map(|i| self.schema.field(*i).clone())
+                    .collect(),
+            );
+            return Ok(Arc::new(EmptyExec::new(false, Arc::new(schema))));
+        }
+        Ok(Arc::new(UnionExec::new(plans)))
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+}
+
+/// A file added to the table by the transaction log
+struct DeltaFile {
+    partition_values: BTreeMap<String, Option<String>>,
+    size: usize,
+    num_rows: Option<usize>,
+}
+
+/// The state of a version of a table, found by replaying its transaction log
+struct DeltaLog {
+    version: i64,
+    schema_string: String,
+    partition_columns: Vec<String>,
+    /// The files of the version by their path in the log
+    files: BTreeMap<String, DeltaFile>,
+}
+
+impl DeltaLog {
+    /// Replays the commits of the log of the table at `path` up to `version`, or all
+    /// of them if `version` is `None`
+    fn read(path: &str, version: Option<i64>) -> Result<Self> {
+        let log_dir = Path::new(path).join("_delta_log");
+        let mut commits = fs::read_dir(&log_dir)
+            .map_err(|e| {
+                DataFusionError::Plan(format!(
+                    "No Delta Lake transaction log found at {}: {}",
+                    log_dir.display(),
+                    e
+                ))
+            })?
+            .map(|entry| -> Result<Option<i64>> {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                Ok(name
+                    .strip_suffix(".json")
+                    .filter(|v| v.len() == 20)
+                    .and_then(|v| v.parse::<i64>().ok()))
+            })
+            .filter_map(|version| version.transpose())
+            .collect::<Result<Vec<_>>>()?;
+        commits.sort_unstable();
+
+        match commits.first() {
+            None => {
+                return Err(DataFusionError::Plan(format!(
+                    "No commits found in the Delta Lake transaction log at {}",
+                    log_dir.display()
+                )))
+            }
+            Some(first) if *first != 0 => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "The Delta Lake transaction log at {} starts at version {}, \
+                     reading checkpoints is not supported",
+                    log_dir.display(),
+                    first
+                )))
+            }
+            Some(_) => {}
+        }
+        let latest = *commits.last().unwrap();
+        let version = version.unwrap_or(latest);
+        if version < 0 || version > latest {
+            return Err(DataFusionError::Plan(format!(
+                "Version {} of the Delta Lake table at {} does not exist, the latest version is {}",
+                version, path, latest
+            )));
+        }
+
+        let mut log = Self {
+            version,
+            schema_string: String::new(),
+            partition_columns: vec![],
+            files: BTreeMap::new(),
+        };
+        for (expected, commit) in (0..=version).zip(commits) {
+            if commit != expected {
+                return Err(DataFusionError::Execution(format!(
+                    "Commit {} is missing from the Delta Lake transaction log at {}",
+                    expected,
+                    log_dir.display()
+                )));
+            }
+            let commit_path = log_dir.join(format!("{:020}.json", commit));
+            log.apply_commit(&commit_path).map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "Could not read the Delta Lake commit {}: {}",
+                    commit_path.display(),
+                    e
+                ))
+            })?;
+        }
+        if log.schema_string.is_empty() {
+            return Err(DataFusionError::Execution(format!(
+                "The Delta Lake transaction log at {} has no metadata",
+                log_dir.display()
+            )));
+        }
+        Ok(log)
+    }
+
+    /// Applies the actions of the commit at `path`, one JSON object per line
+    fn apply_commit(&mut self, path: &Path) -> Result<()> {
+        let commit = fs::read_to_string(path)?;
+        for line in commit.lines().filter(|line| !line.trim().is_empty()) {
+            let action: Value = serde_json::from_str(line)
+                .map_err(|e| DataFusionError::Execution(e.to_string()))?;
+            if let Some(protocol) = action.get("protocol") {
+                let reader_version = protocol["minReaderVersion"].as_i64().unwrap_or(1);
+                if reader_version > 1 {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "Delta Lake reader version {} is not supported",
+                        reader_version
+                    )));
+                }
+            } else if let Some(metadata) = action.get("metaData") {
+                self.schema_string = string_field(metadata, "schemaString")?.to_owned();
+                self.partition_columns = match metadata["partitionColumns"].as_array() {
+                    Some(columns) => columns
+                        .iter()
+                        .map(|column| match column.as_str() {
+                            Some(column) => Ok(column.to_owned()),
+                            None => Err(invalid_action("partitionColumns", column)),
+                        })
+                        .collect::<Result<_>>()?,
+                    None => vec![],
+                };
+            } else if let Some(add) = action.get("add") {
+                let partition_values = match add["partitionValues"].as_object() {
+                    Some(values) => values
+                        .iter()
+                        .map(|(name, value)| {
+                            (name.clone(), value.as_str().map(|v| v.to_owned()))
+                        })
+                        .collect(),
+                    None => BTreeMap::new(),
+                };
+                // the statistics of the file are an optional JSON string
+                let num_rows = add["stats"]
+                    .as_str()
+                    .and_then(|stats| serde_json::from_str::<Value>(stats).ok())
+                    .and_then(|stats| stats["numRecords"].as_u64())
+                    .map(|rows| rows as usize);
+                self.files.insert(
+                    string_field(add, "path")?.to_owned(),
+                    DeltaFile {
+                        partition_values,
+                        size: add["size"].as_u64().unwrap_or(0) as usize,
+                        num_rows,
+                    },
+                );
+            } else if let Some(remove) = action.get("remove") {
+                self.files.remove(string_field(remove, "path")?);
+            }
+            // the other actions, e.g. commitInfo and txn, do not change the files
+        }
+        Ok(())
+    }
+}
+
+fn string_field<'a>(action: &'a Value, name: &str) -> Result<&'a str> {
+    action[name]
+        .as_str()
+        .ok_or_else(|| invalid_action(name, &action[name]))
+}
+
+fn invalid_action(name: &str, value: &Value) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "Invalid {} in Delta Lake action: {}",
+        name, value
+    ))
+}
+
+/// Converts the schema of a Delta Lake table, serialized as JSON
+fn parse_schema(schema_string: &str) -> Result<Vec<Field>> {
+    let schema: Value = serde_json::from_str(schema_string)
+        .map_err(|e| DataFusionError::Execution(e.to_string()))?;
+    let fields = schema["fields"]
+        .as_array()
+        .ok_or_else(|| invalid_action("schemaString", &schema))?;
+    fields
+        .iter()
+        .map(|field| {
+            let name = string_field(field, "name")?;
+            let data_type = match &field["type"] {
+                Value::String(data_type) => parse_data_type(data_type)?,
+                data_type => {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "Unsupported Delta Lake type {} of column {}",
+                        data_type, name
+                    )))
+                }
+            };
+            let nullable = field["nullable"].as_bool().unwrap_or(true);
+            Ok(Field::new(name, data_type, nullable))
+        })
+        .collect()
+}
+
+/// Converts a primitive Delta Lake type
+fn parse_data_type(data_type: &str) -> Result<DataType> {
+    Ok(match data_type {
+        "string" => DataType::Utf8,
+        "long" => DataType::Int64,
+        "integer" => DataType::Int32,
+        "short" => DataType::Int16,
+        "byte" => DataType::Int8,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "binary" => DataType::Binary,
+        "date" => DataType::Date32,
+        "timestamp" => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        _ => {
+            let decimal = data_type
+                .strip_prefix("decimal(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|rest| rest.split_once(','))
+                .and_then(|(precision, scale)| {
+                    Some((precision.trim().parse().ok()?, scale.trim().parse().ok()?))
+                });
+            match decimal {
+                Some((precision, scale)) => DataType::Decimal(precision, scale),
+                None => {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "Unsupported Delta Lake type {}",
+                        data_type
+                    )))
+                }
+            }
+        }
+    })
+}
+
+/// Converts the value of a partition column, which the log stores as a string
+fn parse_partition_value(value: &Option<String>, field: &Field) -> Result<ScalarValue> {
+    let value = match value {
+        Some(value) => value,
+        None => return ScalarValue::try_from(field.data_type()),
+    };
+    let scalar = match field.data_type() {
+        DataType::Utf8 => ScalarValue::Utf8(Some(value.clone())),
+        DataType::Boolean => ScalarValue::Boolean(value.parse().ok()),
+        data_type => {
+            let array: ArrayRef = Arc::new(StringArray::from(vec![value.as_str()]));
+            ScalarValue::try_from_array(&cast(&array, data_type)?, 0)?
+        }
+    };
+    if scalar.is_null() {
+        return Err(DataFusionError::Execution(format!(
+            "Invalid value '{}' of partition column {}",
+            value,
+            field.name()
+        )));
+    }
+    Ok(scalar)
+}
+
+/// Returns the path of the file at `file_path` in the log of the table at `path`,
+/// which is relative to the table unless it is an absolute URI
+fn resolve_path(path: &str, file_path: &str) -> Result<String> {
+    if let Some(absolute) = file_path.strip_prefix("file:") {
+        return Ok(percent_decode(absolute.trim_start_matches("//")));
+    }
+    if file_path.contains("://") {
+        return Err(DataFusionError::NotImplemented(format!(
+            "Cannot read {}, only files of the local file system are supported",
+            file_path
+        )));
+    }
+    Ok(Path::new(path)
+        .join(percent_decode(file_path))
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Decodes the `%XX` escapes of the paths of a Delta Lake log
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_batches_eq;
+    use crate::logical_plan::{col, lit};
+    use crate::prelude::ExecutionContext;
+    use arrow::array::Int64Array;
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::fs::File;
+
+    const SCHEMA: &str = r#"{\"type\":\"struct\",\"fields\":[{\"name\":\"id\",\"type\":\"long\",\"nullable\":false,\"metadata\":{}},{\"name\":\"year\",\"type\":\"integer\",\"nullable\":true,\"metadata\":{}}]}"#;
+
+    /// Writes a Parquet file with the given ids at `path` below `root`
+    fn write_file(root: &Path, path: &str, ids: Vec<i64>) -> Result<()> {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(ids))])?;
+        let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    fn write_commit(root: &Path, version: i64, actions: &[String]) -> Result<()> {
+        let log_dir = root.join("_delta_log");
+        fs::create_dir_all(&log_dir)?;
+        fs::write(
+            log_dir.join(format!("{:020}.json", version)),
+            actions.join("\n"),
+        )?;
+        Ok(())
+    }
+
+    fn add(path: &str, year: &str, rows: usize) -> String {
+        format!(
+            r#"{{"add":{{"path":"{}","partitionValues":{{"year":{}}},"size":100,"dataChange":true,"stats":"{{\"numRecords\":{}}}"}}}}"#,
+            path, year, rows
+        )
+    }
+
+    /// Creates a table partitioned by year, with 3 versions:
+    /// 0. ids 1 and 2 in 2020, id 3 in 2021
+    /// 1. id 4 with a null year
+    /// 2. the file of 2021 is replaced by id 5
+    fn create_table(root: &Path) -> Result<()> {
+        write_file(root, "year=2020/part-0.parquet", vec![1, 2])?;
+        write_file(root, "year=2021/part%200.parquet", vec![3])?;
+        write_file(
+            root,
+            "year=__HIVE_DEFAULT_PARTITION__/part-0.parquet",
+            vec![4],
+        )?;
+        write_file(root, "year=2021/part-1.parquet", vec![5])?;
+        write_commit(
+            root,
+            0,
+            &[
+                r#"{"commitInfo":{"operation":"WRITE"}}"#.to_owned(),
+                r#"{"protocol":{"minReaderVersion":1,"minWriterVersion":2}}"#.to_owned(),
+                format!(
+                    r#"{{"metaData":{{"id":"1","format":{{"provider":"parquet","options":{{}}}},"schemaString":"{}","partitionColumns":["year"],"configuration":{{}}}}}}"#,
+                    SCHEMA
+                ),
+                add("year=2020/part-0.parquet", "\"2020\"", 2),
+                add("year=2021/part%25200.parquet", "\"2021\"", 1),
+            ],
+        )?;
+        write_commit(
+            root,
+            1,
+            &[add(
+                "year=__HIVE_DEFAULT_PARTITION__/part-0.parquet",
+                "null",
+                1,
+            )],
+        )?;
+        write_commit(
+            root,
+            2,
+            &[
+                r#"{"remove":{"path":"year=2021/part%25200.parquet","dataChange":true}}"#
+                    .to_owned(),
+                add("year=2021/part-1.parquet", "\"2021\"", 1),
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn read_versions() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        create_table(root)?;
+        let path = root.to_string_lossy();
+
+        let table = DeltaTable::try_new(path.as_ref(), 2)?;
+        assert_eq!(2, table.version());
+        assert_eq!(3, table.files().len());
+        assert_eq!(Some(4), table.statistics().num_rows);
+        assert_eq!("id", table.schema().field(0).name());
+        assert_eq!(&DataType::Int32, table.schema().field(1).data_type());
+
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(table))?;
+        let batches = ctx
+            .sql("SELECT id, year FROM t ORDER BY id")?
+            .collect()
+            .await?;
+        let expected = vec![
+            "+----+------+",
+            "| id | year |",
+            "+----+------+",
+            "| 1  | 2020 |",
+            "| 2  | 2020 |",
+            "| 4  |      |",
+            "| 5  | 2021 |",
+            "+----+------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+
+        // the first version, whose file name is escaped in the log
+        ctx.sql(&format!(
+            "CREATE EXTERNAL TABLE t0 STORED AS DELTA OPTIONS (version '0') LOCATION '{}'",
+            path
+        ))?;
+        let batches = ctx
+            .sql("SELECT year, SUM(id) FROM t0 GROUP BY year ORDER BY year")?
+            .collect()
+            .await?;
+        let expected = vec![
+            "+------+---------+",
+            "| year | SUM(id) |",
+            "+------+---------+",
+            "| 2020 | 3       |",
+            "| 2021 | 3       |",
+            "+------+---------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+
+        assert!(DeltaTable::try_new_with_version(path.as_ref(), Some(3), 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn skips_filtered_partitions() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        create_table(tmp_dir.path())?;
+        let path = tmp_dir.path().to_string_lossy();
+
+        let table = DeltaTable::try_new_with_version(path.as_ref(), Some(0), 1)?;
+        let plan = table.scan(&None, 1024, &[col("year").eq(lit(2021))], None)?;
+        assert_eq!(plan.children().len(), 1);
+        let plan = table.scan(&None, 1024, &[col("year").gt_eq(lit(2020))], None)?;
+        assert_eq!(plan.children().len(), 2);
+        let plan = table.scan(&None, 1024, &[col("year").eq(lit(1999))], None)?;
+        assert!(plan.as_any().downcast_ref::<EmptyExec>().is_some());
+        // filters on the columns of the files do not skip partitions
+        let plan = table.scan(&None, 1024, &[col("id").eq(lit(1i64))], None)?;
+        assert_eq!(plan.children().len(), 2);
+
+        // the partition whose value is null does not match the filter either
+        let table = DeltaTable::try_new(path.as_ref(), 1)?;
+        let filter = col("year").eq(lit(2021));
+        assert_eq!(
+            table.supports_filter_pushdown(&filter)?,
+            TableProviderFilterPushDown::Exact
+        );
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 1);
+        let plan = table.scan(&None, 1024, &[col("year").is_null()], None)?;
+        assert_eq!(plan.children().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_logs() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        assert!(DeltaTable::try_new(root.to_string_lossy(), 1).is_err());
+
+        // the commits before a checkpoint were removed
+        write_commit(root, 1, &[add("part-0.parquet", "null", 1)])?;
+        let err = DeltaTable::try_new(root.to_string_lossy(), 1)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("reading checkpoints is not supported"));
+
+        write_commit(
+            root,
+            0,
+            &[r#"{"protocol":{"minReaderVersion":2,"minWriterVersion":5}}"#.to_owned()],
+        )?;
+        let err = DeltaTable::try_new(root.to_string_lossy(), 1)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("reader version 2 is not supported"));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_types() -> Result<()> {
+        assert_eq!(DataType::Decimal(10, 2), parse_data_type("decimal(10,2)")?);
+        assert!(parse_data_type("interval").is_err());
+
+        let field = Field::new("d", DataType::Date32, true);
+        assert_eq!(
+            ScalarValue::Date32(Some(18628)),
+            parse_partition_value(&Some("2021-01-01".to_owned()), &field)?
+        );
+        assert_eq!(
+            ScalarValue::Date32(None),
+            parse_partition_value(&None, &field)?
+        );
+        let field = Field::new("i", DataType::Int32, true);
+        assert!(parse_partition_value(&Some("x".to_owned()), &field).is_err());
+
+        assert_eq!("/a/b c%", percent_decode("/a/b%20c%"));
+        Ok(())
+    }
+}
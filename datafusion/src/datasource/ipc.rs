@@ -0,0 +1,162 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Arrow IPC data source
+//!
+//! This data source allows Arrow IPC files, also known as Feather (version 2)
+//! files, to be used as input for queries. The schema and the statistics are
+//! read from the file footers, so registering a table does not read its data.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::datatypes::SchemaRef;
+
+use crate::datasource::datasource::Statistics;
+use crate::datasource::TableProvider;
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Expr;
+use crate::physical_plan::common;
+use crate::physical_plan::ipc::{ArrowExec, ArrowReadOptions};
+use crate::physical_plan::ExecutionPlan;
+
+/// Represents one or more Arrow IPC files with the same schema
+pub struct ArrowFile {
+    path: String,
+    filenames: Vec<String>,
+    schema: SchemaRef,
+    statistics: Statistics,
+    memory_map: bool,
+}
+
+impl ArrowFile {
+    /// Attempt to initialize a new `ArrowFile` from a file path or a directory of
+    /// files
+    pub fn try_new(path: &str, options: ArrowReadOptions) -> Result<Self> {
+        let filenames = common::build_file_list_with_options(
+            path,
+            &[options.file_extension],
+            &options.file_list_options,
+        )?;
+        if filenames.is_empty() {
+            return Err(DataFusionError::Plan(format!(
+                "No files found at {path} with file extension {file_extension}",
+                path = path,
+                file_extension = options.file_extension
+            )));
+        }
+        let (schema, statistics) = ArrowExec::try_read_metadata(&filenames)?;
+
+        Ok(Self {
+            path: path.to_string(),
+            filenames,
+            schema,
+            statistics,
+            memory_map: options.memory_map,
+        })
+    }
+
+    /// Get the path for the Arrow IPC file(s) represented by this table
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl TableProvider for ArrowFile {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(ArrowExec::new(
+            self.filenames.clone(),
+            self.schema.clone(),
+            projection.clone(),
+            self.statistics.clone(),
+            self.memory_map,
+            limit,
+        )))
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+
+    fn has_exact_statistics(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use arrow::array::{Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::ipc::writer::FileWriter;
+    use arrow::record_batch::RecordBatch;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn query_arrow_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.arrow");
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("value", DataType::Int64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(Int64Array::from(vec![Some(10), None, Some(30)])),
+            ],
+        )?;
+        let mut writer = FileWriter::try_new(std::fs::File::create(&path)?, &schema)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+
+        let table = ArrowFile::try_new(path.to_str().unwrap(), ArrowReadOptions::new())?;
+        assert_eq!(table.schema(), schema);
+        assert_eq!(table.statistics().num_rows, Some(3));
+
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(table))?;
+        let batches = ctx
+            .sql("SELECT SUM(value) FROM t WHERE id > 1")?
+            .collect()
+            .await?;
+        let sum = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(sum.len(), 1);
+        assert_eq!(sum.value(0), 30);
+        Ok(())
+    }
+}
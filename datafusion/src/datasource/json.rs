@@ -22,7 +22,7 @@
 
 use std::{
     any::Any,
-    io::{BufReader, Read, Seek},
+    io::{BufReader, Cursor, Read, Seek},
     sync::{Arc, Mutex},
 };
 
@@ -30,12 +30,15 @@ use crate::{
     datasource::{Source, TableProvider},
     error::{DataFusionError, Result},
     physical_plan::{
-        common,
+        common::{self, FileListOptions},
+        file_compression::FileCompressionType,
         json::{NdJsonExec, NdJsonReadOptions},
         ExecutionPlan,
     },
 };
 use arrow::{datatypes::SchemaRef, json::reader::infer_json_schema_from_seekable};
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use super::datasource::Statistics;
 
@@ -48,6 +51,8 @@ pub struct NdJsonFile {
     source: Source<Box<dyn SeekRead + Send + Sync + 'static>>,
     schema: SchemaRef,
     file_extension: String,
+    file_compression_type: Option<FileCompressionType>,
+    file_list_options: FileListOptions,
     statistics: Statistics,
 }
 
@@ -57,7 +62,14 @@ impl NdJsonFile {
         let schema = if let Some(schema) = options.schema {
             schema
         } else {
-            let filenames = common::build_file_list(path, options.file_extension)?;
+            let filenames = common::build_file_list_with_options(
+                path,
+                &FileCompressionType::file_extensions(
+                    options.file_extension,
+                    options.file_compression_type,
+                ),
+                &options.file_list_options,
+            )?;
             if filenames.is_empty() {
                 return Err(DataFusionError::Plan(format!(
                     "No files found at {path} with file extension {file_extension}",
@@ -69,6 +81,7 @@ impl NdJsonFile {
             NdJsonExec::try_infer_schema(
                 filenames,
                 Some(options.schema_infer_max_records),
+                options.file_compression_type,
             )?
             .into()
         };
@@ -77,6 +90,8 @@ impl NdJsonFile {
             source: Source::Path(path.to_string()),
             schema,
             file_extension: options.file_extension.to_string(),
+            file_compression_type: options.file_compression_type,
+            file_list_options: options.file_list_options,
             statistics: Statistics::default(),
         })
     }
@@ -103,8 +118,36 @@ impl NdJsonFile {
             schema,
             statistics: Statistics::default(),
             file_extension: String::new(),
+            file_compression_type: options.file_compression_type,
+            file_list_options: options.file_list_options,
         })
     }
+
+    /// Attempt to initialize a `NdJsonFile` from an in-memory buffer, which can be
+    /// scanned more than once. The schema can be inferred automatically.
+    pub fn try_new_from_bytes(
+        bytes: impl Into<Bytes>,
+        options: NdJsonReadOptions,
+    ) -> Result<Self> {
+        let bytes = bytes.into();
+        let table = Self::try_new_from_reader(Cursor::new(bytes.clone()), options)?;
+        Ok(Self {
+            source: Source::Bytes(bytes),
+            ..table
+        })
+    }
+
+    /// Attempt to initialize a `NdJsonFile` from an async reader, such as a pipe or a
+    /// socket. The whole input is read into memory before the table is created, so it
+    /// must fit in memory, and the schema can be inferred automatically.
+    pub async fn try_new_from_async_reader<R: AsyncRead + Unpin>(
+        mut reader: R,
+        options: NdJsonReadOptions<'_>,
+    ) -> Result<Self> {
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await?;
+        Self::try_new_from_bytes(buf, options)
+    }
 }
 impl TableProvider for NdJsonFile {
     fn as_any(&self) -> &dyn Any {
@@ -126,6 +169,8 @@ impl TableProvider for NdJsonFile {
             schema: Some(self.schema.clone()),
             schema_infer_max_records: 0, // schema will always be provided, so it's unnecessary to infer schema
             file_extension: self.file_extension.as_str(),
+            file_compression_type: self.file_compression_type,
+            file_list_options: self.file_list_options,
         };
         let batch_size = limit
             .map(|l| std::cmp::min(l, batch_size))
@@ -148,6 +193,13 @@ impl TableProvider for NdJsonFile {
                     ));
                 }
             }
+            Source::Bytes(bytes) => NdJsonExec::try_new_from_reader(
+                Cursor::new(bytes.clone()),
+                opts,
+                projection.clone(),
+                batch_size,
+                limit,
+            )?,
             Source::Path(p) => {
                 NdJsonExec::try_new(p, opts, projection.clone(), batch_size, limit)?
             }
@@ -187,4 +239,56 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn json_file_from_bytes() -> Result<()> {
+        let data = "{\"a\": 1, \"b\": \"hello\"}\n{\"a\": 2, \"b\": \"world\"}\n";
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table(
+            "ndjson",
+            Arc::new(NdJsonFile::try_new_from_bytes(data, Default::default())?),
+        )?;
+        let expected = vec![
+            "+-------+",
+            "| b     |",
+            "+-------+",
+            "| world |",
+            "+-------+",
+        ];
+        // the buffer is read again by each scan
+        for _ in 0..2 {
+            let batches = ctx
+                .sql("select b from ndjson where a = 2")?
+                .collect()
+                .await?;
+            crate::assert_batches_eq!(expected, &batches);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json_file_from_async_reader() -> Result<()> {
+        let path = format!("{}/2.json", TEST_DATA_BASE);
+        let buf = std::fs::read(path)?;
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table(
+            "ndjson",
+            Arc::new(
+                NdJsonFile::try_new_from_async_reader(&buf[..], Default::default())
+                    .await?,
+            ),
+        )?;
+        let df = ctx.sql("select sum(a) from ndjson")?;
+        let batches = df.collect().await?;
+        assert_eq!(
+            batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<arrow::array::Int64Array>()
+                .unwrap()
+                .value(0),
+            100000000000011
+        );
+        Ok(())
+    }
 }
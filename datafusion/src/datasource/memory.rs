@@ -18,11 +18,15 @@
 //! In-memory data source for presenting a Vec<RecordBatch> as a data source that can be
 //! queried by DataFusion. This allows data to be pre-loaded into memory and then
 //! repeatedly queried without incurring additional file I/O overhead.
+//!
+//! Batches can be appended to a [`MemTable`] while it is being queried, and the
+//! oldest batches are evicted once the table grows beyond its optional size bound.
 
 use futures::StreamExt;
 use log::debug;
 use std::any::Any;
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
 
 use arrow::datatypes::{Field, Schema, SchemaRef};
 use arrow::record_batch::RecordBatch;
@@ -43,45 +47,135 @@ use super::datasource::ColumnStatistics;
 /// In-memory table
 pub struct MemTable {
     schema: SchemaRef,
-    batches: Vec<Vec<RecordBatch>>,
+    state: RwLock<MemTableState>,
+    /// Maximum size of the batches of the table in bytes, beyond which the oldest
+    /// batches are evicted
+    max_bytes: Option<usize>,
+}
+
+/// The batches of a [`MemTable`], which scans take a consistent snapshot of
+struct MemTableState {
+    /// The batches of each partition, oldest first, with their sequence numbers
+    partitions: Vec<VecDeque<(u64, RecordBatch)>>,
+    /// Sequence number of the next appended batch, which orders the batches of all
+    /// partitions by age
+    next_seq: u64,
+    /// Partition that the next appended batch is added to
+    next_partition: usize,
+    /// Total size of the batches in bytes
+    num_bytes: usize,
+    /// Statistics of the batches, which are updated as batches are added and evicted
     statistics: Statistics,
 }
 
-// Calculates statistics based on partitions
-fn calculate_statistics(
-    schema: &SchemaRef,
-    partitions: &[Vec<RecordBatch>],
-) -> Statistics {
-    let num_rows: usize = partitions
-        .iter()
-        .flat_map(|batches| batches.iter().map(RecordBatch::num_rows))
-        .sum();
-
-    let mut null_count: Vec<usize> = vec![0; schema.fields().len()];
-    for partition in partitions.iter() {
-        for batch in partition {
-            for (i, array) in batch.columns().iter().enumerate() {
-                null_count[i] += array.null_count();
+impl MemTableState {
+    fn new(schema: &SchemaRef, partitions: Vec<Vec<RecordBatch>>) -> Self {
+        let mut state = Self {
+            partitions: Vec::with_capacity(partitions.len()),
+            next_seq: 0,
+            next_partition: 0,
+            num_bytes: 0,
+            statistics: empty_statistics(schema),
+        };
+        for batches in partitions {
+            let mut partition = VecDeque::with_capacity(batches.len());
+            for batch in batches {
+                state.num_bytes += batch_num_bytes(&batch);
+                state.add_statistics(&batch);
+                partition.push_back((state.next_seq, batch));
+                state.next_seq += 1;
             }
+            state.partitions.push(partition);
         }
+        state
     }
 
-    let column_statistics = Some(
-        null_count
+    /// The batches of each partition
+    fn snapshot(&self) -> Vec<Vec<RecordBatch>> {
+        self.partitions
             .iter()
-            .map(|null_count| ColumnStatistics {
-                null_count: Some(*null_count),
-                distinct_count: None,
-                max_value: None,
-                min_value: None,
-            })
-            .collect(),
-    );
+            .map(|batches| batches.iter().map(|(_, batch)| batch.clone()).collect())
+            .collect()
+    }
+
+    /// Merges the statistics of an added batch into those of the table
+    fn add_statistics(&mut self, batch: &RecordBatch) {
+        if let Some(num_rows) = &mut self.statistics.num_rows {
+            *num_rows += batch.num_rows();
+        }
+        for (stats, array) in self.column_statistics().zip(batch.columns()) {
+            if let Some(null_count) = &mut stats.null_count {
+                *null_count += array.null_count();
+            }
+        }
+    }
 
+    /// Removes the statistics of an evicted batch from those of the table
+    fn remove_statistics(&mut self, batch: &RecordBatch) {
+        if let Some(num_rows) = &mut self.statistics.num_rows {
+            *num_rows -= batch.num_rows();
+        }
+        for (stats, array) in self.column_statistics().zip(batch.columns()) {
+            if let Some(null_count) = &mut stats.null_count {
+                *null_count -= array.null_count();
+            }
+        }
+    }
+
+    fn column_statistics(&mut self) -> impl Iterator<Item = &mut ColumnStatistics> {
+        self.statistics.column_statistics.iter_mut().flatten()
+    }
+
+    /// Evicts the oldest batches until the table is no larger than `max_bytes`,
+    /// returning the number of evicted batches
+    fn evict(&mut self, max_bytes: usize) -> usize {
+        let mut evicted = 0;
+        while self.num_bytes > max_bytes {
+            let oldest = self
+                .partitions
+                .iter()
+                .enumerate()
+                .filter_map(|(i, batches)| batches.front().map(|(seq, _)| (*seq, i)))
+                .min();
+            match oldest {
+                Some((_, i)) => {
+                    let (_, batch) = self.partitions[i].pop_front().unwrap();
+                    self.num_bytes -= batch_num_bytes(&batch);
+                    self.remove_statistics(&batch);
+                    evicted += 1;
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+}
+
+/// The size of the buffers of the batch in bytes
+fn batch_num_bytes(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|array| array.get_array_memory_size())
+        .sum()
+}
+
+/// Statistics of a table without batches
+fn empty_statistics(schema: &SchemaRef) -> Statistics {
+    let column_statistics = schema
+        .fields()
+        .iter()
+        .map(|_| ColumnStatistics {
+            null_count: Some(0),
+            distinct_count: None,
+            max_value: None,
+            min_value: None,
+        })
+        .collect();
     Statistics {
-        num_rows: Some(num_rows),
+        num_rows: Some(0),
         total_byte_size: None,
-        column_statistics,
+        column_statistics: Some(column_statistics),
     }
 }
 
@@ -93,13 +187,13 @@ impl MemTable {
             .flatten()
             .all(|batches| schema.contains(&batches.schema()))
         {
-            let statistics = calculate_statistics(&schema, &partitions);
-            debug!("MemTable statistics: {:?}", statistics);
+            let state = MemTableState::new(&schema, partitions);
+            debug!("MemTable statistics: {:?}", state.statistics);
 
             Ok(Self {
                 schema,
-                batches: partitions,
-                statistics,
+                state: RwLock::new(state),
+                max_bytes: None,
             })
         } else {
             Err(DataFusionError::Plan(
@@ -161,6 +255,59 @@ impl MemTable {
         }
         MemTable::try_new(schema.clone(), data)
     }
+
+    /// Bound the size of the batches of the table to `max_bytes`. Once the table
+    /// grows beyond it, the batches that were added first are evicted.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self.state.get_mut().unwrap().evict(max_bytes);
+        self
+    }
+
+    /// Appends the batches to the table, distributing them over its partitions in a
+    /// round-robin fashion, and evicts the oldest batches if the table grew beyond
+    /// its size bound. Scans that already started do not see the new batches.
+    pub fn append(&self, batches: Vec<RecordBatch>) -> Result<()> {
+        if !batches
+            .iter()
+            .all(|batch| self.schema.contains(&batch.schema()))
+        {
+            return Err(DataFusionError::Plan(
+                "Mismatch between schema and batches".to_string(),
+            ));
+        }
+
+        let mut state = self.state.write().unwrap();
+        if state.partitions.is_empty() {
+            state.partitions.push(VecDeque::new());
+        }
+        for batch in batches {
+            let partition = state.next_partition % state.partitions.len();
+            let seq = state.next_seq;
+            state.num_bytes += batch_num_bytes(&batch);
+            state.add_statistics(&batch);
+            state.partitions[partition].push_back((seq, batch));
+            state.next_seq += 1;
+            state.next_partition = partition + 1;
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            let evicted = state.evict(max_bytes);
+            if evicted > 0 {
+                debug!("MemTable evicted {} batches", evicted);
+            }
+        }
+        Ok(())
+    }
+
+    /// Total size of the batches of the table in bytes
+    pub fn num_bytes(&self) -> usize {
+        self.state.read().unwrap().num_bytes
+    }
+
+    /// The batches of each partition of the table
+    pub fn batches(&self) -> Vec<Vec<RecordBatch>> {
+        self.state.read().unwrap().snapshot()
+    }
 }
 
 impl TableProvider for MemTable {
@@ -206,15 +353,18 @@ impl TableProvider for MemTable {
 
         let projected_schema = Arc::new(Schema::new(projected_columns?));
 
+        // the scan sees the batches of the table at this point, regardless of the
+        // batches that are appended or evicted later
+        let batches = self.batches();
         Ok(Arc::new(MemoryExec::try_new(
-            &self.batches.clone(),
+            &batches,
             projected_schema,
             projection.clone(),
         )?))
     }
 
     fn statistics(&self) -> Statistics {
-        self.statistics.clone()
+        self.state.read().unwrap().statistics.clone()
     }
 
     fn has_exact_statistics(&self) -> bool {
@@ -473,4 +623,116 @@ mod tests {
 
         Ok(())
     }
+
+    fn int_batch(schema: &SchemaRef, values: Vec<i32>) -> Result<RecordBatch> {
+        Ok(RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(values))],
+        )?)
+    }
+
+    #[tokio::test]
+    async fn append_snapshot() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let provider = MemTable::try_new(
+            schema.clone(),
+            vec![vec![int_batch(&schema, vec![1, 2])?], vec![]],
+        )?;
+
+        let exec = provider.scan(&None, 1024, &[], None)?;
+        provider.append(vec![
+            int_batch(&schema, vec![3])?,
+            int_batch(&schema, vec![4, 5, 6])?,
+        ])?;
+
+        // the batches are distributed over the partitions
+        let batches = provider.batches();
+        assert_eq!(2, batches[0].len());
+        assert_eq!(1, batches[1].len());
+        assert_eq!(provider.statistics().num_rows, Some(6));
+
+        // the scan that started before the append does not see the new batches
+        let rows: usize = common::collect(exec.execute(1).await?)
+            .await?
+            .iter()
+            .map(|batch| batch.num_rows())
+            .sum();
+        assert_eq!(0, rows);
+
+        let other_schema =
+            Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            other_schema,
+            vec![Arc::new(arrow::array::Int64Array::from(vec![1]))],
+        )?;
+        assert!(provider.append(vec![batch]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn append_merges_statistics() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let batch = |values: Vec<Option<i32>>| {
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))])
+        };
+        let null_count = |provider: &MemTable| {
+            provider.statistics().column_statistics.unwrap()[0].null_count
+        };
+        let provider = MemTable::try_new(schema.clone(), vec![vec![batch(vec![None])?]])?;
+        let size = provider.num_bytes();
+        let provider = provider.with_max_bytes(2 * size);
+
+        provider.append(vec![batch(vec![Some(1)])?])?;
+        assert_eq!(provider.statistics().num_rows, Some(2));
+        assert_eq!(null_count(&provider), Some(1));
+
+        // the statistics of the evicted batch are removed
+        provider.append(vec![batch(vec![Some(2)])?])?;
+        assert_eq!(provider.statistics().num_rows, Some(2));
+        assert_eq!(null_count(&provider), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn append_evicts_oldest_batches() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let size = batch_num_bytes(&int_batch(&schema, vec![0; 100])?);
+        let provider = MemTable::try_new(
+            schema.clone(),
+            vec![
+                vec![int_batch(&schema, vec![1; 100])?],
+                vec![int_batch(&schema, vec![2; 100])?],
+            ],
+        )?
+        .with_max_bytes(2 * size);
+        assert_eq!(2 * size, provider.num_bytes());
+
+        provider.append(vec![int_batch(&schema, vec![3; 100])?])?;
+        assert_eq!(2 * size, provider.num_bytes());
+        assert_eq!(provider.statistics().num_rows, Some(200));
+
+        // the first batch was evicted
+        let values = provider
+            .batches()
+            .iter()
+            .flatten()
+            .map(|batch| {
+                batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap()
+                    .value(0)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![3, 2], values);
+
+        // a bound below the size of a batch evicts all batches
+        let provider = provider.with_max_bytes(size - 1);
+        assert_eq!(0, provider.num_bytes());
+        assert_eq!(provider.statistics().num_rows, Some(0));
+
+        Ok(())
+    }
 }
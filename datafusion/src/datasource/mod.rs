@@ -17,16 +17,24 @@
 
 //! DataFusion data sources
 
+pub mod analyzed;
 pub mod csv;
 pub mod datasource;
+pub mod delta;
 pub mod empty;
+pub mod ipc;
 pub mod json;
 pub mod memory;
 pub mod parquet;
+pub(crate) mod partition_pruning;
+pub mod partitioned;
+pub mod streaming;
 
 pub use self::csv::{CsvFile, CsvReadOptions};
 pub use self::datasource::{TableProvider, TableType};
+pub use self::ipc::ArrowFile;
 pub use self::memory::MemTable;
+pub use self::streaming::StreamingTable;
 
 /// Source for table input data
 pub(crate) enum Source<R = Box<dyn std::io::Read + Send + Sync + 'static>> {
@@ -35,4 +43,7 @@ pub(crate) enum Source<R = Box<dyn std::io::Read + Send + Sync + 'static>> {
 
     /// Read data from a reader
     Reader(std::sync::Mutex<Option<R>>),
+
+    /// Read data from an in-memory buffer, from its start by each scan
+    Bytes(bytes::Bytes),
 }
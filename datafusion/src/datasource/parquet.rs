@@ -21,12 +21,15 @@ use std::any::Any;
 use std::string::String;
 use std::sync::Arc;
 
+use arrow::compute::SortOptions;
 use arrow::datatypes::*;
 
 use crate::datasource::datasource::Statistics;
 use crate::datasource::TableProvider;
-use crate::error::Result;
+use crate::error::{DataFusionError, Result};
 use crate::logical_plan::{combine_filters, Expr};
+use crate::physical_plan::common::FileListOptions;
+use crate::physical_plan::expressions::{Column, PhysicalSortExpr};
 use crate::physical_plan::parquet::ParquetExec;
 use crate::physical_plan::ExecutionPlan;
 
@@ -39,13 +42,51 @@ pub struct ParquetTable {
     statistics: Statistics,
     max_concurrency: usize,
     enable_pruning: bool,
+    file_list_options: FileListOptions,
+    file_sort_order: Vec<Expr>,
 }
 
 impl ParquetTable {
     /// Attempt to initialize a new `ParquetTable` from a file path.
     pub fn try_new(path: impl Into<String>, max_concurrency: usize) -> Result<Self> {
+        Self::try_new_with_file_list_options(
+            path,
+            max_concurrency,
+            FileListOptions::default(),
+        )
+    }
+
+    /// Attempt to initialize a new `ParquetTable` from a file path, directory or glob
+    /// pattern, whose files are listed with `file_list_options`.
+    pub fn try_new_with_file_list_options(
+        path: impl Into<String>,
+        max_concurrency: usize,
+        file_list_options: FileListOptions,
+    ) -> Result<Self> {
+        Self::try_new_with_schema(path, None, max_concurrency, file_list_options)
+    }
+
+    /// Attempt to initialize a new `ParquetTable` from a file path, directory or glob
+    /// pattern, whose files are listed with `file_list_options`. If `schema` is
+    /// provided, the files are read as that schema even if their own schemas differ
+    /// from it, otherwise it is determined from the files.
+    pub fn try_new_with_schema(
+        path: impl Into<String>,
+        schema: Option<SchemaRef>,
+        max_concurrency: usize,
+        file_list_options: FileListOptions,
+    ) -> Result<Self> {
         let path = path.into();
-        let parquet_exec = ParquetExec::try_from_path(&path, None, None, 0, 1, None)?;
+        let parquet_exec = ParquetExec::try_from_path_with_schema(
+            &path,
+            &file_list_options,
+            schema,
+            None,
+            None,
+            0,
+            1,
+            None,
+        )?;
         let schema = parquet_exec.schema();
         Ok(Self {
             path,
@@ -53,6 +94,8 @@ impl ParquetTable {
             statistics: parquet_exec.statistics().to_owned(),
             max_concurrency,
             enable_pruning: true,
+            file_list_options,
+            file_sort_order: vec![],
         })
     }
 
@@ -71,6 +114,72 @@ impl ParquetTable {
         self.enable_pruning = enable_pruning;
         self
     }
+
+    /// Get how the files of the path of this ParquetTable instance are listed
+    pub fn file_list_options(&self) -> FileListOptions {
+        self.file_list_options
+    }
+
+    /// Declares that the rows of each file are sorted by `file_sort_order`, a list of
+    /// sort expressions on the columns of the table such as `col("a").sort(true, false)`.
+    /// Operators that benefit from sorted inputs, such as the sorted aggregation, are
+    /// then planned for scans of this table.
+    pub fn with_file_sort_order(mut self, file_sort_order: Vec<Expr>) -> Result<Self> {
+        for expr in &file_sort_order {
+            match expr {
+                Expr::Sort { expr, .. } => match expr.as_ref() {
+                    Expr::Column(c) if self.schema.index_of(&c.name).is_ok() => {}
+                    _ => {
+                        return Err(DataFusionError::Plan(format!(
+                            "Parquet files can only be sorted by table columns, not {:?}",
+                            expr
+                        )))
+                    }
+                },
+                _ => {
+                    return Err(DataFusionError::Plan(format!(
+                        "Expected a sort expression for parquet files, got {:?}",
+                        expr
+                    )))
+                }
+            }
+        }
+        self.file_sort_order = file_sort_order;
+        Ok(self)
+    }
+
+    /// Get the order of the rows within each file
+    pub fn file_sort_order(&self) -> &[Expr] {
+        &self.file_sort_order
+    }
+
+    /// The longest prefix of the sort order of the files whose columns are in `schema`
+    fn physical_sort_order(&self, schema: &Schema) -> Vec<PhysicalSortExpr> {
+        let mut sort_order = vec![];
+        for expr in &self.file_sort_order {
+            if let Expr::Sort {
+                expr,
+                asc,
+                nulls_first,
+            } = expr
+            {
+                if let Expr::Column(c) = expr.as_ref() {
+                    if let Ok(index) = schema.index_of(&c.name) {
+                        sort_order.push(PhysicalSortExpr {
+                            expr: Arc::new(Column::new(&c.name, index)),
+                            options: SortOptions {
+                                descending: !asc,
+                                nulls_first: *nulls_first,
+                            },
+                        });
+                        continue;
+                    }
+                }
+            }
+            break;
+        }
+        sort_order
+    }
 }
 
 impl TableProvider for ParquetTable {
@@ -107,8 +216,10 @@ impl TableProvider for ParquetTable {
         } else {
             None
         };
-        Ok(Arc::new(ParquetExec::try_from_path(
+        let exec = ParquetExec::try_from_path_with_schema(
             &self.path,
+            &self.file_list_options,
+            Some(self.schema.clone()),
             projection.clone(),
             predicate,
             limit
@@ -116,7 +227,9 @@ impl TableProvider for ParquetTable {
                 .unwrap_or(batch_size),
             self.max_concurrency,
             limit,
-        )?))
+        )?;
+        let file_sort_order = self.physical_sort_order(&exec.schema());
+        Ok(Arc::new(exec.with_file_sort_order(file_sort_order)))
     }
 
     fn statistics(&self) -> Statistics {
@@ -352,6 +465,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn file_sort_order() -> Result<()> {
+        use crate::logical_plan::{col, lit};
+        let testdata = crate::test_util::parquet_test_data();
+        let filename = format!("{}/alltypes_plain.parquet", testdata);
+        let table = ParquetTable::try_new(&filename, 2)?.with_file_sort_order(vec![
+            col("id").sort(true, false),
+            col("bool_col").sort(false, true),
+        ])?;
+
+        // the sort order is in terms of the projected columns
+        let exec = table.scan(&Some(vec![1, 0]), 1024, &[], None)?;
+        let ordering = exec.output_ordering().expect("sorted scan");
+        assert_eq!(
+            vec!["id@1 ASC NULLS LAST", "bool_col@0 DESC"],
+            ordering.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        );
+
+        // only the prefix of the projected columns is known to be sorted
+        let exec = table.scan(&Some(vec![0, 2]), 1024, &[], None)?;
+        let ordering = exec.output_ordering().expect("sorted scan");
+        assert_eq!(1, ordering.len());
+        let exec = table.scan(&Some(vec![1]), 1024, &[], None)?;
+        assert!(exec.output_ordering().is_none());
+
+        let table = ParquetTable::try_new(&filename, 2)?;
+        assert!(table
+            .with_file_sort_order(vec![col("missing").sort(true, false)])
+            .is_err());
+        let table = ParquetTable::try_new(&filename, 2)?;
+        assert!(table.with_file_sort_order(vec![lit(1)]).is_err());
+        Ok(())
+    }
+
     fn load_table(name: &str) -> Result<Arc<dyn TableProvider>> {
         let testdata = crate::test_util::parquet_test_data();
         let filename = format!("{}/{}", testdata, name);
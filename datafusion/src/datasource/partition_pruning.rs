@@ -0,0 +1,322 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Pruning of the partitions of the tables whose columns are the columns of their
+//! files followed by their partition columns, e.g. [`PartitionedTable`] and
+//! [`DeltaTable`], using the values of the partition columns of each partition as its
+//! statistics.
+//!
+//! [`PartitionedTable`]: crate::datasource::partitioned::PartitionedTable
+//! [`DeltaTable`]: crate::datasource::delta::DeltaTable
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use std::convert::TryFrom;
+
+use arrow::array::{Array, ArrayRef, BooleanArray, UInt64Array};
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use log::debug;
+
+use crate::error::{DataFusionError, Result};
+use crate::execution::context::ExecutionContextState;
+use crate::logical_plan::{
+    unnormalize_col, Column, DFSchema, Expr, ExpressionVisitor, Recursion,
+};
+use crate::optimizer::utils::expr_to_columns;
+use crate::physical_optimizer::pruning::{PruningPredicate, PruningStatistics};
+use crate::physical_plan::functions::BuiltinScalarFunction;
+use crate::physical_plan::planner::DefaultPhysicalPlanner;
+use crate::physical_plan::PhysicalExpr;
+
+/// The columns of a table, which are the columns of its files followed by its
+/// partition columns
+pub(crate) struct PartitionColumns {
+    schema: SchemaRef,
+    /// Number of columns read from the files
+    num_file_columns: usize,
+}
+
+impl PartitionColumns {
+    pub(crate) fn new(schema: SchemaRef, num_file_columns: usize) -> Self {
+        Self {
+            schema,
+            num_file_columns,
+        }
+    }
+
+    /// Returns the index of `column` among the partition columns
+    fn partition_index(&self, column: &Column) -> Option<usize> {
+        self.schema.fields()[self.num_file_columns..]
+            .iter()
+            .position(|f| f.name() == &column.name)
+    }
+
+    /// Returns true if `filter` only references partition columns
+    pub(crate) fn is_partition_filter(&self, filter: &Expr) -> bool {
+        let mut columns = HashSet::new();
+        expr_to_columns(filter, &mut columns).is_ok()
+            && !columns.is_empty()
+            && columns.iter().all(|c| self.partition_index(c).is_some())
+    }
+
+    /// Returns true if `filter` only references the columns of the files
+    pub(crate) fn is_file_filter(&self, filter: &Expr) -> bool {
+        let mut columns = HashSet::new();
+        expr_to_columns(filter, &mut columns).is_ok()
+            && columns.iter().all(|c| self.partition_index(c).is_none())
+    }
+
+    /// Returns true if `filter` only references partition columns and is evaluated
+    /// exactly on the values of each partition by [`PartitionColumns::prune_partitions`],
+    /// so that it does not need to be evaluated again on the rows of the partitions
+    /// that match it
+    pub(crate) fn is_exact_partition_filter(&self, filter: &Expr) -> bool {
+        self.is_partition_filter(filter)
+            && is_exact_partition_filter(filter, &self.partition_schema())
+    }
+
+    /// Returns the schema of the partition columns
+    pub(crate) fn partition_schema(&self) -> Schema {
+        Schema::new(self.schema.fields()[self.num_file_columns..].to_vec())
+    }
+
+    /// Returns for each of the `num_partitions` partitions whether its values may match
+    /// all the `filters`, which only reference partition columns. `values` holds the
+    /// values of each partition column, with one element per partition. The partitions
+    /// that are kept match the exact partition filters, which are evaluated on the
+    /// values, and the other filters skip the partitions that cannot match them.
+    pub(crate) fn prune_partitions(
+        &self,
+        filters: &[&Expr],
+        values: Vec<ArrayRef>,
+        num_partitions: usize,
+    ) -> Result<Vec<bool>> {
+        let partition_schema = self.partition_schema();
+        let (exact_filters, filters): (Vec<&Expr>, Vec<&Expr>) = filters
+            .iter()
+            .copied()
+            .partition(|filter| is_exact_partition_filter(filter, &partition_schema));
+        let keep = self.prune_partitions_inexact(&filters, &values, num_partitions);
+        let matches =
+            evaluate_partition_filters(&exact_filters, partition_schema, values)?;
+        Ok(keep
+            .into_iter()
+            .zip(matches)
+            .map(|(keep, matches)| keep && matches)
+            .collect())
+    }
+
+    /// Returns for each of the `num_partitions` partitions whether its values may match
+    /// all the `filters`, using the values as the statistics of the partitions
+    fn prune_partitions_inexact(
+        &self,
+        filters: &[&Expr],
+        values: &[ArrayRef],
+        num_partitions: usize,
+    ) -> Vec<bool> {
+        let predicate = match filters.split_first() {
+            Some((first, rest)) => rest
+                .iter()
+                .fold((*first).clone(), |acc, filter| acc.and((*filter).clone())),
+            None => return vec![true; num_partitions],
+        };
+        let partition_schema = Arc::new(self.partition_schema());
+        let statistics = PartitionPruningStatistics {
+            partition_schema: &partition_schema,
+            values,
+            num_partitions,
+        };
+        match PruningPredicate::try_new(&predicate, partition_schema.clone())
+            .and_then(|predicate| predicate.prune(&statistics))
+        {
+            Ok(keep) => keep,
+            Err(e) => {
+                // the filters are still evaluated on the rows of all the partitions
+                debug!("Could not prune partitions with {:?}: {}", predicate, e);
+                vec![true; num_partitions]
+            }
+        }
+    }
+}
+
+/// The values of the partition columns, which are both the minimum and the maximum
+/// value of the column in their partition
+struct PartitionPruningStatistics<'a> {
+    partition_schema: &'a Schema,
+    values: &'a [ArrayRef],
+    num_partitions: usize,
+}
+
+impl<'a> PartitionPruningStatistics<'a> {
+    fn values(&self, column: &Column) -> Option<ArrayRef> {
+        let index = self.partition_schema.index_of(&column.name).ok()?;
+        self.values.get(index).cloned()
+    }
+}
+
+impl<'a> PruningStatistics for PartitionPruningStatistics<'a> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.values(column)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.values(column)
+    }
+
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        // the number of rows of the partitions whose value is null is not known, so
+        // these partitions are never skipped, and the other partitions have no nulls
+        if self.values(column)?.null_count() > 0 {
+            return None;
+        }
+        Some(Arc::new(UInt64Array::from(vec![0; self.num_partitions])))
+    }
+
+    fn num_containers(&self) -> usize {
+        self.num_partitions
+    }
+}
+
+/// Returns whether `filter`, which only references the partition columns of
+/// `partition_schema`, is evaluated exactly on the values of each partition by
+/// [`evaluate_partition_filters`], so that it does not need to be evaluated again on
+/// the rows of the partitions that match it
+fn is_exact_partition_filter(filter: &Expr, partition_schema: &Schema) -> bool {
+    // the result of a nondeterministic filter is not the same for all the rows
+    let deterministic = filter
+        .accept(DeterministicVisitor {
+            deterministic: true,
+        })
+        .map_or(false, |visitor| visitor.deterministic);
+    deterministic && partition_filter_expr(filter, partition_schema).is_ok()
+}
+
+/// Returns for each partition whether its values match all the `filters`, which are
+/// exact partition filters. `values` holds the values of each partition column, with
+/// one element per partition.
+fn evaluate_partition_filters(
+    filters: &[&Expr],
+    partition_schema: Schema,
+    values: Vec<ArrayRef>,
+) -> Result<Vec<bool>> {
+    let num_partitions = values.first().map_or(0, |values| values.len());
+    let mut matches = vec![true; num_partitions];
+    if filters.is_empty() {
+        return Ok(matches);
+    }
+    let batch = RecordBatch::try_new(Arc::new(partition_schema), values)?;
+    for filter in filters {
+        let expr = partition_filter_expr(filter, batch.schema().as_ref())?;
+        let result = expr.evaluate(&batch)?.into_array(num_partitions);
+        let result = result
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .ok_or_else(|| {
+                DataFusionError::Plan(format!(
+                    "Partition filter {:?} is not a boolean expression",
+                    filter
+                ))
+            })?;
+        for (index, matches) in matches.iter_mut().enumerate() {
+            // the rows of a partition whose filter is null do not match it either
+            *matches &= result.is_valid(index) && result.value(index);
+        }
+    }
+    Ok(matches)
+}
+
+fn partition_filter_expr(
+    filter: &Expr,
+    partition_schema: &Schema,
+) -> Result<Arc<dyn PhysicalExpr>> {
+    DefaultPhysicalPlanner::default().create_physical_expr(
+        &unnormalize_col(filter.clone()),
+        &DFSchema::try_from(partition_schema.clone())?,
+        partition_schema,
+        &ExecutionContextState::new(),
+    )
+}
+
+/// Finds the functions whose results may differ between rows with the same values
+struct DeterministicVisitor {
+    deterministic: bool,
+}
+
+impl ExpressionVisitor for DeterministicVisitor {
+    fn pre_visit(mut self, expr: &Expr) -> Result<Recursion<Self>> {
+        match expr {
+            Expr::ScalarFunction {
+                fun: BuiltinScalarFunction::Random,
+                ..
+            }
+            // user defined functions are not known to be deterministic
+            | Expr::ScalarUDF { .. } => {
+                self.deterministic = false;
+                Ok(Recursion::Stop(self))
+            }
+            _ => Ok(Recursion::Continue(self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::{col, lit};
+    use arrow::array::StringArray;
+    use arrow::datatypes::{DataType, Field};
+
+    #[test]
+    fn prune_partitions_by_values() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("year", DataType::Utf8, true),
+        ]);
+        let columns = PartitionColumns::new(Arc::new(schema), 1);
+        let year_filter = col("year").gt(lit("2020"));
+        assert!(columns.is_partition_filter(&year_filter));
+        assert!(!columns.is_file_filter(&year_filter));
+        assert!(columns.is_file_filter(&col("a").gt(lit(1))));
+        assert!(!columns.is_partition_filter(&col("a").gt(col("year"))));
+
+        let values: Vec<ArrayRef> = vec![Arc::new(StringArray::from(vec![
+            Some("2020"),
+            Some("2021"),
+            None,
+        ]))];
+        // the partition whose value is null is kept
+        assert_eq!(
+            columns.prune_partitions_inexact(&[&year_filter], &values, 3),
+            vec![false, true, true]
+        );
+        assert_eq!(
+            columns.prune_partitions_inexact(&[], &values, 3),
+            vec![true, true, true]
+        );
+
+        // the exact filters skip the partitions whose value is null
+        assert!(columns.is_exact_partition_filter(&year_filter));
+        assert!(!columns.is_exact_partition_filter(&col("a").gt(lit(1))));
+        assert_eq!(
+            columns.prune_partitions(&[&year_filter], values, 3)?,
+            vec![false, true, false]
+        );
+        Ok(())
+    }
+}
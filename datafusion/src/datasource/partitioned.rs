@@ -0,0 +1,501 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Partitioned data source
+//!
+//! Reads a directory whose files are partitioned by the values of some columns, with
+//! one sub-directory per value named `column=value`, e.g.
+//! `/data/year=2021/month=08/part-0.parquet`. The partition values are exposed as
+//! `Utf8` columns following the columns of the files.
+//!
+//! The filters on the partition columns are evaluated on the values of each partition,
+//! which are the same for all its rows, so the partitions that do not match them are
+//! skipped and the filters are not evaluated again on the rows of the others. A
+//! partition column that is only referenced by such filters is then not read at all.
+//! The filters that cannot be evaluated that way skip the partitions that cannot match
+//! them, using the partition values as the statistics of a [`PruningPredicate`].
+//!
+//! [`PruningPredicate`]: crate::physical_optimizer::pruning::PruningPredicate
+
+use std::any::Any;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+
+use crate::datasource::datasource::{Statistics, TableProviderFilterPushDown};
+use crate::datasource::partition_pruning::PartitionColumns;
+use crate::datasource::TableProvider;
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Expr;
+use crate::physical_plan::empty::EmptyExec;
+use crate::physical_plan::expressions::{self, lit};
+use crate::physical_plan::projection::ProjectionExec;
+use crate::physical_plan::union::UnionExec;
+use crate::physical_plan::{ExecutionPlan, PhysicalExpr};
+use crate::scalar::ScalarValue;
+
+/// A table made of one table per partition of a directory
+pub struct PartitionedTable {
+    /// The columns of the files followed by the partition columns
+    schema: SchemaRef,
+    /// Number of columns read from the files
+    num_file_columns: usize,
+    /// The partitions with their values, in the order of the partition columns
+    partitions: Vec<(Vec<String>, Arc<dyn TableProvider>)>,
+}
+
+impl PartitionedTable {
+    /// Lists the partitions of the directory at `path` and creates the table of each
+    /// of them by calling `create_table` with the path of its directory
+    pub fn try_new(
+        path: &str,
+        partition_cols: &[String],
+        mut create_table: impl FnMut(&str) -> Result<Arc<dyn TableProvider>>,
+    ) -> Result<Self> {
+        let mut partition_paths = vec![];
+        list_partitions(
+            Path::new(path),
+            partition_cols,
+            &mut vec![],
+            &mut partition_paths,
+        )?;
+
+        let partitions = partition_paths
+            .into_iter()
+            .map(|(values, path)| Ok((values, create_table(&path)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let file_schema = match partitions.first() {
+            Some((_, table)) => table.schema(),
+            None => {
+                return Err(DataFusionError::Plan(format!(
+                    "No partitions of {} found at {}",
+                    partition_cols.join(", "),
+                    path
+                )))
+            }
+        };
+        Self::try_new_with_partitions(file_schema, partition_cols, partitions).map_err(
+            |e| match e {
+                DataFusionError::Plan(msg) => {
+                    DataFusionError::Plan(format!("{} at {}", msg, path))
+                }
+                e => e,
+            },
+        )
+    }
+
+    /// Creates the table of `partitions`, whose values are in the order of
+    /// `partition_cols` and whose tables all have the schema `file_schema`, e.g. the
+    /// partitions listed by a catalog
+    pub fn try_new_with_partitions(
+        file_schema: SchemaRef,
+        partition_cols: &[String],
+        partitions: Vec<(Vec<String>, Arc<dyn TableProvider>)>,
+    ) -> Result<Self> {
+        if let Some((values, _)) = partitions
+            .iter()
+            .find(|(values, _)| values.len() != partition_cols.len())
+        {
+            return Err(DataFusionError::Plan(format!(
+                "Partition {} does not have a value for each of the partition columns {}",
+                values.join("/"),
+                partition_cols.join(", ")
+            )));
+        }
+        if let Some((values, _)) = partitions
+            .iter()
+            .find(|(_, table)| table.schema() != file_schema)
+        {
+            return Err(DataFusionError::Plan(format!(
+                "The files of partition {} do not have the same schema as the other partitions",
+                values.join("/"),
+            )));
+        }
+
+        let fields = file_schema
+            .fields()
+            .iter()
+            .cloned()
+            .chain(
+                partition_cols
+                    .iter()
+                    .map(|name| Field::new(name, DataType::Utf8, false)),
+            )
+            .collect();
+
+        Ok(Self {
+            schema: Arc::new(Schema::new(fields)),
+            num_file_columns: file_schema.fields().len(),
+            partitions,
+        })
+    }
+
+    /// Returns the schema of the tables of the partitions, without the partition
+    /// columns
+    pub fn file_schema(&self) -> SchemaRef {
+        Arc::new(Schema::new(
+            self.schema.fields()[..self.num_file_columns].to_vec(),
+        ))
+    }
+
+    /// Returns the names of the partition columns
+    pub fn partition_cols(&self) -> Vec<String> {
+        self.schema.fields()[self.num_file_columns..]
+            .iter()
+            .map(|f| f.name().clone())
+            .collect()
+    }
+
+    /// Returns the partitions with their values, in the order of the partition
+    /// columns, and their tables
+    pub fn partitions(&self) -> &[(Vec<String>, Arc<dyn TableProvider>)] {
+        &self.partitions
+    }
+
+    /// Returns the columns of the files and the partition columns
+    fn partition_columns(&self) -> PartitionColumns {
+        PartitionColumns::new(self.schema.clone(), self.num_file_columns)
+    }
+
+    /// Returns the values of each partition column, with one element per partition
+    fn partition_values(&self) -> Vec<ArrayRef> {
+        (0..self.schema.fields().len() - self.num_file_columns)
+            .map(|index| {
+                let values = self
+                    .partitions
+                    .iter()
+                    .map(|(values, _)| Some(values[index].as_str()))
+                    .collect::<StringArray>();
+                Arc::new(values) as ArrayRef
+            })
+            .collect()
+    }
+
+    /// Returns for each partition whether its values may match all the `filters`,
+    /// which only reference partition columns
+    fn prune_partitions(&self, filters: &[&Expr]) -> Result<Vec<bool>> {
+        self.partition_columns().prune_partitions(
+            filters,
+            self.partition_values(),
+            self.partitions.len(),
+        )
+    }
+}
+
+/// Recursively lists the directories of the partitions below `path`, one level per
+/// partition column
+fn list_partitions(
+    path: &Path,
+    partition_cols: &[String],
+    values: &mut Vec<String>,
+    partitions: &mut Vec<(Vec<String>, String)>,
+) -> Result<()> {
+    let column = match partition_cols.first() {
+        Some(column) => column,
+        None => {
+            partitions.push((values.clone(), path.to_string_lossy().into_owned()));
+            return Ok(());
+        }
+    };
+
+    let prefix = format!("{}=", column);
+    let mut entries = std::fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(value) = name.strip_prefix(&prefix) {
+            values.push(value.to_owned());
+            list_partitions(&entry.path(), &partition_cols[1..], values, partitions)?;
+            values.pop();
+        }
+    }
+    Ok(())
+}
+
+impl TableProvider for PartitionedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> Result<TableProviderFilterPushDown> {
+        let columns = self.partition_columns();
+        if columns.is_exact_partition_filter(filter) {
+            Ok(TableProviderFilterPushDown::Exact)
+        } else if columns.is_partition_filter(filter) {
+            // used to skip partitions, the filter is still evaluated on the rows
+            Ok(TableProviderFilterPushDown::Inexact)
+        } else if columns.is_file_filter(filter) {
+            match self.partitions.first() {
+                Some((_, table)) => table.supports_filter_pushdown(filter),
+                None => Ok(TableProviderFilterPushDown::Unsupported),
+            }
+        } else {
+            Ok(TableProviderFilterPushDown::Unsupported)
+        }
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        batch_size: usize,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let projection = projection
+            .clone()
+            .unwrap_or_else(|| (0..self.schema.fields().len()).collect());
+        let mut file_projection = projection
+            .iter()
+            .filter(|i| **i < self.num_file_columns)
+            .copied()
+            .collect::<Vec<_>>();
+        if file_projection.is_empty() && self.num_file_columns > 0 {
+            // the files are still read to know the number of rows
+            file_projection.push(0);
+        }
+        let columns = self.partition_columns();
+        let file_filters = filters
+            .iter()
+            .filter(|filter| columns.is_file_filter(filter))
+            .cloned()
+            .collect::<Vec<_>>();
+        let partition_filters = filters
+            .iter()
+            .filter(|filter| columns.is_partition_filter(filter))
+            .collect::<Vec<_>>();
+        let keep = self.prune_partitions(&partition_filters)?;
+
+        let plans = self
+            .partitions
+            .iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|((values, table), _)| {
+                let input = table.scan(
+                    &Some(file_projection.clone()),
+                    batch_size,
+                    &file_filters,
+                    limit,
+                )?;
+                let exprs = projection
+                    .iter()
+                    .map(|i| {
+                        let name = self.schema.field(*i).name().clone();
+                        let expr: Arc<dyn PhysicalExpr> = if *i < self.num_file_columns {
+                            let index =
+                                file_projection.iter().position(|p| p == i).unwrap();
+                            Arc::new(expressions::Column::new(&name, index))
+                        } else {
+                            let value = values[*i - self.num_file_columns].clone();
+                            lit(ScalarValue::Utf8(Some(value)))
+                        };
+                        (expr, name)
+                    })
+                    .collect();
+                Ok(Arc::new(ProjectionExec::try_new(exprs, input)?)
+                    as Arc<dyn ExecutionPlan>)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if plans.is_empty() {
+            let schema = Schema::new(
+                projection
+                    .iter()
+                    .map(|i| self.schema.field(*i).clone())
+                    .collect(),
+            );
+            return Ok(Arc::new(EmptyExec::new(false, Arc::new(schema))));
+        }
+        Ok(Arc::new(UnionExec::new(plans)))
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_batches_eq;
+    use crate::datasource::{CsvFile, CsvReadOptions};
+    use crate::logical_plan::{col, lit, random};
+    use crate::prelude::ExecutionContext;
+    use std::fs;
+
+    fn write_file(dir: &Path, name: &str, contents: &str) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(name), contents)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn csv_partitions() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        write_file(&root.join("year=2020/month=12"), "a.csv", "1|x\n2|y\n")?;
+        write_file(&root.join("year=2021/month=01"), "b.csv", "3|z\n")?;
+        write_file(&root.join("year=2021/month=02"), "c.csv", "4|w\n")?;
+        // directories that are not partitions are ignored
+        write_file(&root.join("_tmp"), "d.csv", "5|v\n")?;
+
+        let mut ctx = ExecutionContext::new();
+        ctx.sql(&format!(
+            "CREATE EXTERNAL TABLE t (id INT, name VARCHAR) STORED AS CSV \
+             PARTITIONED BY (year, month) OPTIONS (delimiter '|') LOCATION '{}'",
+            root.display()
+        ))?;
+
+        let batches = ctx
+            .sql("SELECT id, name, year, month FROM t ORDER BY id")?
+            .collect()
+            .await?;
+        let expected = vec![
+            "+----+------+------+-------+",
+            "| id | name | year | month |",
+            "+----+------+------+-------+",
+            "| 1  | x    | 2020 | 12    |",
+            "| 2  | y    | 2020 | 12    |",
+            "| 3  | z    | 2021 | 01    |",
+            "| 4  | w    | 2021 | 02    |",
+            "+----+------+------+-------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+
+        let batches = ctx
+            .sql("SELECT month, COUNT(*) FROM t WHERE year = '2021' GROUP BY month ORDER BY month")?
+            .collect()
+            .await?;
+        let expected = vec![
+            "+-------+-----------------+",
+            "| month | COUNT(UInt8(1)) |",
+            "+-------+-----------------+",
+            "| 01    | 1               |",
+            "| 02    | 1               |",
+            "+-------+-----------------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[test]
+    fn skips_filtered_partitions() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        write_file(&root.join("year=2020"), "a.csv", "1\n")?;
+        write_file(&root.join("year=2021"), "b.csv", "2\n")?;
+
+        let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
+        let table = PartitionedTable::try_new(
+            &root.to_string_lossy(),
+            &["year".to_string()],
+            |path| {
+                Ok(Arc::new(CsvFile::try_new(
+                    path,
+                    CsvReadOptions::new().schema(&schema).has_header(false),
+                )?))
+            },
+        )?;
+        assert_eq!(table.schema().field(1).name(), "year");
+
+        let filter = col("year").eq(lit("2021"));
+        assert_eq!(
+            table.supports_filter_pushdown(&filter)?,
+            TableProviderFilterPushDown::Exact
+        );
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 1);
+
+        let filter = col("year").eq(lit("1999"));
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert!(plan.as_any().downcast_ref::<EmptyExec>().is_some());
+
+        // ranges and lists of values prune partitions too
+        let filter = col("year").gt_eq(lit("2021"));
+        assert_eq!(
+            table.supports_filter_pushdown(&filter)?,
+            TableProviderFilterPushDown::Exact
+        );
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 1);
+        let filter = col("year").in_list(vec![lit("2020"), lit("2021")], false);
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 2);
+
+        // expressions of the partition values skip partitions too
+        let filter = Expr::Cast {
+            expr: Box::new(col("year")),
+            data_type: DataType::Int64,
+        }
+        .lt(lit(2021_i64));
+        assert_eq!(
+            table.supports_filter_pushdown(&filter)?,
+            TableProviderFilterPushDown::Exact
+        );
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 1);
+
+        // nondeterministic filters are still evaluated on the rows
+        let filter = col("year").eq(lit("2021")).or(random().lt(lit(0.5)));
+        assert_eq!(
+            table.supports_filter_pushdown(&filter)?,
+            TableProviderFilterPushDown::Inexact
+        );
+        let plan = table.scan(&None, 1024, &[filter], None)?;
+        assert_eq!(plan.children().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn partition_filter_columns_not_read() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        write_file(&root.join("year=2020"), "a.csv", "1\n")?;
+        write_file(&root.join("year=2021"), "b.csv", "2\n")?;
+
+        let mut ctx = ExecutionContext::new();
+        ctx.sql(&format!(
+            "CREATE EXTERNAL TABLE t (id INT) STORED AS CSV PARTITIONED BY (year) \
+             LOCATION '{}'",
+            root.display()
+        ))?;
+
+        // the filter on the partition column is only evaluated on the partition values
+        let plan = ctx.create_logical_plan("SELECT id FROM t WHERE year = '2021'")?;
+        let plan = format!("{:?}", ctx.optimize(&plan)?);
+        assert!(!plan.contains("Filter:"), "{}", plan);
+        assert!(
+            plan.contains("TableScan: t projection=Some([0])"),
+            "{}",
+            plan
+        );
+        Ok(())
+    }
+}
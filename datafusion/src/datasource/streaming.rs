@@ -0,0 +1,370 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Streaming data source
+//!
+//! This data source allows batches that arrive over time, for example from a
+//! message queue, to be queried as they are received. The table is unbounded, so
+//! queries against it are restricted to operators that emit their results
+//! incrementally, such as projections, filters and limits. A table whose rows arrive
+//! in the order of a time column can also be aggregated over windows of time, such as
+//! `GROUP BY date_bin(INTERVAL '1 minute', ts)`, and the aggregates of each window are
+//! emitted once the next window starts.
+
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+use arrow::compute::SortOptions;
+use arrow::datatypes::{Field, Schema, SchemaRef};
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::datasource::datasource::Statistics;
+use crate::datasource::TableProvider;
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Expr;
+use crate::physical_plan::expressions::{Column, PhysicalSortExpr};
+use crate::physical_plan::streaming::{StreamingExec, StreamingPartitions};
+use crate::physical_plan::ExecutionPlan;
+
+/// In-memory table that receives its batches over time through channels, one
+/// per partition
+pub struct StreamingTable {
+    schema: SchemaRef,
+    /// The channels of the partitions, which are taken by the first query that reads
+    /// them
+    partitions: StreamingPartitions,
+    /// The index of the column in whose order the rows of each partition arrive
+    time_column: Option<usize>,
+}
+
+/// Handle to send batches to a partition of a [`StreamingTable`]. The partition
+/// ends once all of its senders have been dropped.
+#[derive(Clone)]
+pub struct StreamingTableSender {
+    schema: SchemaRef,
+    sender: Sender<ArrowResult<RecordBatch>>,
+}
+
+impl StreamingTable {
+    /// Create a new streaming table with `partitions` partitions, each of which
+    /// buffers up to `capacity` batches until they are read. Returns the table
+    /// together with one sender per partition.
+    pub fn try_new(
+        schema: SchemaRef,
+        partitions: usize,
+        capacity: usize,
+    ) -> Result<(Self, Vec<StreamingTableSender>)> {
+        if partitions == 0 || capacity == 0 {
+            return Err(DataFusionError::Plan(
+                "A streaming table needs at least one partition and a non-zero capacity"
+                    .to_string(),
+            ));
+        }
+
+        let (senders, receivers) = (0..partitions)
+            .map(|_| {
+                let (sender, receiver) = channel(capacity);
+                let sender = StreamingTableSender {
+                    schema: schema.clone(),
+                    sender,
+                };
+                (sender, receiver)
+            })
+            .unzip();
+
+        let table = Self {
+            schema,
+            partitions: Arc::new(Mutex::new(receivers.into_iter().map(Some).collect())),
+            time_column: None,
+        };
+        Ok((table, senders))
+    }
+
+    /// Declares that the rows of each partition are sent in ascending order of the
+    /// timestamp column `name`, which allows queries to aggregate the table over
+    /// windows of time. The order is not checked, so rows that arrive late are
+    /// aggregated into windows of their own.
+    pub fn with_time_column(mut self, name: &str) -> Result<Self> {
+        self.time_column = Some(self.schema.index_of(name)?);
+        Ok(self)
+    }
+}
+
+impl StreamingTableSender {
+    /// Send a batch to the partition, waiting until there is capacity for it
+    pub async fn send(&self, batch: RecordBatch) -> Result<()> {
+        if !self.schema.contains(&batch.schema()) {
+            return Err(DataFusionError::Plan(
+                "Mismatch between schema and batches".to_string(),
+            ));
+        }
+        self.send_result(Ok(batch)).await
+    }
+
+    /// Send an error to the partition, which fails the queries reading from it
+    pub async fn send_error(&self, error: arrow::error::ArrowError) -> Result<()> {
+        self.send_result(Err(error)).await
+    }
+
+    async fn send_result(&self, result: ArrowResult<RecordBatch>) -> Result<()> {
+        self.sender.send(result).await.map_err(|_| {
+            DataFusionError::Execution(
+                "The query reading from the streaming table has finished".to_string(),
+            )
+        })
+    }
+}
+
+impl TableProvider for StreamingTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn scan(
+        &self,
+        projection: &Option<Vec<usize>>,
+        _batch_size: usize,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let projected_schema = match projection {
+            Some(columns) => {
+                let fields: Result<Vec<Field>> = columns
+                    .iter()
+                    .map(|i| {
+                        if *i < self.schema.fields().len() {
+                            Ok(self.schema.field(*i).clone())
+                        } else {
+                            Err(DataFusionError::Internal(
+                                "Projection index out of range".to_string(),
+                            ))
+                        }
+                    })
+                    .collect();
+                Arc::new(Schema::new(fields?))
+            }
+            None => self.schema.clone(),
+        };
+
+        // the rows are sorted on the time column if it is projected
+        let ordering = self.time_column.and_then(|time_column| {
+            let index = match projection {
+                Some(columns) => columns.iter().position(|i| *i == time_column)?,
+                None => time_column,
+            };
+            Some(vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new(projected_schema.field(index).name(), index)),
+                options: SortOptions::default(),
+            }])
+        });
+
+        // the channels are only taken when the plan is executed
+        Ok(Arc::new(StreamingExec::new(
+            self.partitions.clone(),
+            projected_schema,
+            projection.clone(),
+            ordering,
+        )))
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+
+    fn unbounded(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use arrow::array::{Int32Array, Int64Array, TimestampNanosecondArray};
+    use arrow::datatypes::{DataType, TimeUnit};
+    use futures::StreamExt;
+
+    fn test_table() -> Result<(StreamingTable, StreamingTableSender)> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let (table, mut senders) = StreamingTable::try_new(schema, 1, 4)?;
+        Ok((table, senders.remove(0)))
+    }
+
+    fn test_batch(a: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let b = a.iter().map(|v| v * 10).collect::<Vec<_>>();
+        RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(a)), Arc::new(Int32Array::from(b))],
+        )
+        .unwrap()
+    }
+
+    fn values(batch: &RecordBatch) -> Vec<i32> {
+        let array = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        array.values().to_vec()
+    }
+
+    #[tokio::test]
+    async fn query_emits_results_incrementally() -> Result<()> {
+        let (table, sender) = test_table()?;
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(table))?;
+
+        let df = ctx.sql("SELECT b FROM t WHERE a > 1")?;
+        let mut stream = df.execute_stream().await?;
+
+        sender.send(test_batch(vec![1, 2, 3])).await?;
+        let batch = stream.next().await.unwrap()?;
+        assert_eq!(values(&batch), vec![20, 30]);
+
+        sender.send(test_batch(vec![4])).await?;
+        let batch = stream.next().await.unwrap()?;
+        assert_eq!(values(&batch), vec![40]);
+
+        drop(sender);
+        assert!(stream.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limit_ends_unbounded_query() -> Result<()> {
+        let (table, sender) = test_table()?;
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(table))?;
+
+        sender.send(test_batch(vec![1, 2])).await?;
+        sender.send(test_batch(vec![3, 4])).await?;
+
+        // the sender is still alive, so the query only ends because of the limit
+        let batches = ctx.sql("SELECT a FROM t LIMIT 3")?.collect().await?;
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 3);
+        drop(sender);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject_blocking_operators() -> Result<()> {
+        for sql in &[
+            "SELECT COUNT(a) FROM t",
+            "SELECT a FROM t ORDER BY a",
+            "SELECT a FROM t GROUP BY a",
+        ] {
+            let (table, _sender) = test_table()?;
+            let mut ctx = ExecutionContext::new();
+            ctx.register_table("t", Arc::new(table))?;
+
+            let err = ctx.sql(sql)?.collect().await.unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("is not supported on unbounded input"),
+                "unexpected error for {}: {}",
+                sql,
+                err
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_only_once() -> Result<()> {
+        let (table, _sender) = test_table()?;
+        // planning does not take the channels
+        let first = table.scan(&None, 1024, &[], None)?;
+        let second = table.scan(&None, 1024, &[], None)?;
+        first.execute(0).await?;
+        assert!(second.execute(0).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn aggregate_windows_of_time() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("ts", DataType::Timestamp(TimeUnit::Nanosecond, None), false),
+            Field::new("a", DataType::Int32, false),
+        ]));
+        let (table, mut senders) = StreamingTable::try_new(schema.clone(), 1, 4)?;
+        let table = table.with_time_column("ts")?;
+        let sender = senders.remove(0);
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(table))?;
+
+        let df = ctx.sql(
+            "SELECT date_trunc('minute', ts) AS w, SUM(a) FROM t \
+             GROUP BY date_trunc('minute', ts)",
+        )?;
+        let mut stream = df.execute_stream().await?;
+        let batch = |seconds: Vec<i64>| {
+            let a = vec![1; seconds.len()];
+            let ts = seconds.iter().map(|s| s * 1_000_000_000).collect();
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(TimestampNanosecondArray::from_vec(ts, None)),
+                    Arc::new(Int32Array::from(a)),
+                ],
+            )
+        };
+        let sums = |batch: &RecordBatch| {
+            let array = batch
+                .column(1)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            array.values().to_vec()
+        };
+
+        // each window is emitted once the next one starts
+        sender.send(batch(vec![0, 10, 70])?).await?;
+        assert_eq!(sums(&stream.next().await.unwrap()?), vec![2]);
+        sender.send(batch(vec![80, 130])?).await?;
+        assert_eq!(sums(&stream.next().await.unwrap()?), vec![2]);
+        drop(sender);
+        assert_eq!(sums(&stream.next().await.unwrap()?), vec![1]);
+        assert!(stream.next().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject_mismatched_batch() -> Result<()> {
+        let (_table, sender) = test_table()?;
+        let schema = Arc::new(Schema::new(vec![Field::new("c", DataType::Utf8, true)]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(arrow::array::StringArray::from(vec!["x"]))],
+        )?;
+        assert!(sender.send(batch).await.is_err());
+        Ok(())
+    }
+}
@@ -56,6 +56,9 @@ pub enum DataFusionError {
     /// Error returned during execution of the query.
     /// Examples include files not found, errors in parsing certain types.
     Execution(String),
+    /// Error returned when a query exceeds the resources, such as the temporary disk
+    /// space, that it is allowed to use.
+    ResourcesExhausted(String),
 }
 
 impl DataFusionError {
@@ -113,6 +116,9 @@ impl Display for DataFusionError {
             DataFusionError::Execution(ref desc) => {
                 write!(f, "Execution error: {}", desc)
             }
+            DataFusionError::ResourcesExhausted(ref desc) => {
+                write!(f, "Resources exhausted: {}", desc)
+            }
         }
     }
 }
@@ -23,7 +23,9 @@ use crate::{
     },
     logical_plan::{PlanType, ToStringifiedPlan},
     optimizer::{
-        aggregate_statistics::AggregateStatistics, eliminate_limit::EliminateLimit,
+        aggregate_push_down::AggregatePushDown,
+        aggregate_statistics::AggregateStatistics,
+        eliminate_cross_join::EliminateCrossJoin, eliminate_limit::EliminateLimit,
         hash_build_probe_order::HashBuildProbeOrder,
     },
     physical_optimizer::optimizer::PhysicalOptimizerRule,
@@ -36,43 +38,64 @@ use std::sync::Arc;
 use std::{
     collections::{HashMap, HashSet},
     sync::Mutex,
+    time::Duration,
 };
 
 use futures::{StreamExt, TryStreamExt};
 use tokio::task::{self, JoinHandle};
 
 use arrow::csv;
+use arrow::datatypes::Schema;
+use arrow::record_batch::RecordBatch;
 
 use crate::catalog::{
     catalog::{CatalogProvider, MemoryCatalogProvider},
     schema::{MemorySchemaProvider, SchemaProvider},
     ResolvedTableReference, TableReference,
 };
+use crate::datasource::analyzed::AnalyzedTable;
 use crate::datasource::csv::CsvFile;
+use crate::datasource::datasource::{ColumnStatistics, Statistics};
+use crate::datasource::delta::DeltaTable;
+use crate::datasource::ipc::ArrowFile;
+use crate::datasource::json::NdJsonFile;
 use crate::datasource::parquet::ParquetTable;
+use crate::datasource::partitioned::PartitionedTable;
 use crate::datasource::TableProvider;
 use crate::error::{DataFusionError, Result};
 use crate::execution::dataframe_impl::DataFrameImpl;
+use crate::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
 use crate::logical_plan::{
-    FunctionRegistry, LogicalPlan, LogicalPlanBuilder, UNNAMED_TABLE,
+    count, count_distinct, lit, max, min, Column, Expr, FunctionRegistry, LogicalPlan,
+    LogicalPlanBuilder, UNNAMED_TABLE,
 };
 use crate::optimizer::constant_folding::ConstantFolding;
 use crate::optimizer::filter_push_down::FilterPushDown;
 use crate::optimizer::limit_push_down::LimitPushDown;
-use crate::optimizer::optimizer::OptimizerRule;
+use crate::optimizer::optimizer::{Optimizer, OptimizerRule};
 use crate::optimizer::projection_push_down::ProjectionPushDown;
 use crate::optimizer::simplify_expressions::SimplifyExpressions;
 use crate::physical_optimizer::coalesce_batches::CoalesceBatches;
 use crate::physical_optimizer::merge_exec::AddCoalescePartitionsExec;
 use crate::physical_optimizer::repartition::Repartition;
+use crate::physical_optimizer::sorted_aggregate::SortedAggregate;
 
+use crate::physical_plan::aggregates;
+use crate::physical_plan::common::FileListOptions;
 use crate::physical_plan::csv::CsvReadOptions;
+use crate::physical_plan::functions::BuiltinScalarFunction;
+use crate::physical_plan::ipc::ArrowReadOptions;
+use crate::physical_plan::json::NdJsonReadOptions;
 use crate::physical_plan::planner::DefaultPhysicalPlanner;
 use crate::physical_plan::udf::ScalarUDF;
+use crate::physical_plan::udwf::WindowUDF;
 use crate::physical_plan::ExecutionPlan;
+use crate::physical_plan::HashScheme;
 use crate::physical_plan::PhysicalPlanner;
+use crate::scalar::ScalarValue;
 use crate::sql::{
-    parser::{DFParser, FileType},
+    macros::SqlMacro,
+    parser::{DFParser, FileType, Statement as DFStatement},
     planner::{ContextProvider, SqlToRel},
 };
 use crate::variable::{VarProvider, VarType};
@@ -162,15 +185,62 @@ impl ExecutionContext {
                 scalar_functions: HashMap::new(),
                 var_provider: HashMap::new(),
                 aggregate_functions: HashMap::new(),
+                window_functions: HashMap::new(),
+                sql_macros: HashMap::new(),
+                runtime_env: Arc::new(RuntimeEnv::new(config.runtime_config.clone())),
+                execution_props: ExecutionProps::new()
+                    .with_current_user(config.current_user.clone()),
                 config,
-                execution_props: ExecutionProps::new(),
+                optimizer_rule_times: vec![],
             })),
         }
     }
 
     /// Creates a dataframe that will execute a SQL query.
+    ///
+    /// `sql` may be a script of statements separated by semicolons, in which case the
+    /// statements are run in order and the dataframe of the last one is returned. As
+    /// dataframes are executed lazily, the statements before the last one must be DDL
+    /// statements such as `CREATE EXTERNAL TABLE`: scripts whose statements produce
+    /// results are run by [`ExecutionContext::sql_script`].
     pub fn sql(&mut self, sql: &str) -> Result<Arc<dyn DataFrame>> {
-        let plan = self.create_logical_plan(sql)?;
+        let statements = DFParser::parse_sql(sql)?;
+        let (last, statements) = statements.split_last().ok_or_else(|| {
+            DataFusionError::Plan("The SQL script has no statements".to_string())
+        })?;
+        // nothing is run unless the whole script can be
+        if let Some(index) = statements.iter().position(|s| !is_ddl_statement(s)) {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Statement {} of the script produces results, which only the last \
+                 statement can with sql(). Scripts are run by sql_script()",
+                index + 1
+            )));
+        }
+        for statement in statements {
+            self.statement_to_dataframe(statement)?;
+        }
+        self.statement_to_dataframe(last)
+    }
+
+    /// Runs the statements of the SQL script `sql`, which are separated by semicolons,
+    /// in order, and returns the results of each statement. Every statement sees the
+    /// tables and functions created by the statements before it.
+    pub async fn sql_script(&mut self, sql: &str) -> Result<Vec<Vec<RecordBatch>>> {
+        let statements = DFParser::parse_sql(sql)?;
+        let mut results = Vec::with_capacity(statements.len());
+        for statement in &statements {
+            results.push(self.statement_to_dataframe(statement)?.collect().await?);
+        }
+        Ok(results)
+    }
+
+    /// Creates the dataframe of a single statement, running the DDL statements that
+    /// register tables and functions in the context
+    fn statement_to_dataframe(
+        &mut self,
+        statement: &DFStatement,
+    ) -> Result<Arc<dyn DataFrame>> {
+        let plan = self.statement_to_plan(statement)?;
         match plan {
             LogicalPlan::CreateExternalTable {
                 ref schema,
@@ -178,28 +248,44 @@ impl ExecutionContext {
                 ref location,
                 ref file_type,
                 ref has_header,
-            } => match file_type {
-                FileType::CSV => {
-                    self.register_csv(
-                        name,
-                        location,
-                        CsvReadOptions::new()
-                            .schema(&schema.as_ref().to_owned().into())
-                            .has_header(*has_header),
-                    )?;
-                    let plan = LogicalPlanBuilder::empty(false).build()?;
-                    Ok(Arc::new(DataFrameImpl::new(self.state.clone(), &plan)))
-                }
-                FileType::Parquet => {
-                    self.register_parquet(name, location)?;
-                    let plan = LogicalPlanBuilder::empty(false).build()?;
-                    Ok(Arc::new(DataFrameImpl::new(self.state.clone(), &plan)))
+                ref table_partition_cols,
+                ref options,
+            } => {
+                let table = self.create_external_table(
+                    schema.as_ref().to_owned().into(),
+                    location,
+                    *file_type,
+                    *has_header,
+                    table_partition_cols,
+                    options,
+                )?;
+                self.register_table(name.as_str(), table)?;
+                let plan = LogicalPlanBuilder::empty(false).build()?;
+                Ok(Arc::new(DataFrameImpl::new(self.state.clone(), &plan)))
+            }
+
+            LogicalPlan::CreateFunction {
+                name,
+                params,
+                body,
+                or_replace,
+                ..
+            } => {
+                let sql_macro = SqlMacro::try_new(name, params, body)?;
+                let mut state = self.state.lock().unwrap();
+                if !or_replace && state.sql_macros.contains_key(sql_macro.name()) {
+                    return Err(DataFusionError::Plan(format!(
+                        "Function {} already exists",
+                        sql_macro.name()
+                    )));
                 }
-                _ => Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported file type {:?}.",
-                    file_type
-                ))),
-            },
+                state
+                    .sql_macros
+                    .insert(sql_macro.name().to_owned(), Arc::new(sql_macro));
+                drop(state);
+                let plan = LogicalPlanBuilder::empty(false).build()?;
+                Ok(Arc::new(DataFrameImpl::new(self.state.clone(), &plan)))
+            }
 
             plan => Ok(Arc::new(DataFrameImpl::new(
                 self.state.clone(),
@@ -208,6 +294,147 @@ impl ExecutionContext {
         }
     }
 
+    /// Creates the table of a `CREATE EXTERNAL TABLE` statement, configuring the
+    /// data source with the options of the statement
+    fn create_external_table(
+        &self,
+        schema: Schema,
+        location: &str,
+        file_type: FileType,
+        has_header: bool,
+        table_partition_cols: &[String],
+        options: &HashMap<String, String>,
+    ) -> Result<Arc<dyn TableProvider>> {
+        let unsupported = |key: &str| {
+            Err(DataFusionError::Plan(format!(
+                "Unsupported option {} for {:?} tables",
+                key, file_type
+            )))
+        };
+
+        match file_type {
+            FileType::CSV => {
+                let mut csv_options =
+                    CsvReadOptions::new().schema(&schema).has_header(has_header);
+                for (key, value) in options {
+                    if apply_file_list_option(
+                        &mut csv_options.file_list_options,
+                        key,
+                        value,
+                    )? {
+                        continue;
+                    }
+                    csv_options = match key.as_str() {
+                        "delimiter" => match value.as_bytes() {
+                            [delimiter] => csv_options.delimiter(*delimiter),
+                            _ => {
+                                return Err(DataFusionError::Plan(format!(
+                                    "Invalid delimiter '{}', expected a single character",
+                                    value
+                                )))
+                            }
+                        },
+                        "has_header" => {
+                            csv_options.has_header(parse_table_option(key, value)?)
+                        }
+                        "compression" => {
+                            csv_options.file_compression_type(value.parse()?)
+                        }
+                        "file_extension" => csv_options.file_extension(value),
+                        _ => return unsupported(key),
+                    };
+                }
+                create_table(location, table_partition_cols, |path| {
+                    Ok(Arc::new(CsvFile::try_new(path, csv_options)?))
+                })
+            }
+            FileType::NdJson => {
+                let mut json_options = NdJsonReadOptions {
+                    schema: if schema.fields().is_empty() {
+                        None
+                    } else {
+                        Some(Arc::new(schema.clone()))
+                    },
+                    ..Default::default()
+                };
+                for (key, value) in options {
+                    if apply_file_list_option(
+                        &mut json_options.file_list_options,
+                        key,
+                        value,
+                    )? {
+                        continue;
+                    }
+                    match key.as_str() {
+                        "compression" => {
+                            json_options.file_compression_type = Some(value.parse()?)
+                        }
+                        "file_extension" => json_options.file_extension = value.as_str(),
+                        "schema_infer_max_records" => {
+                            json_options.schema_infer_max_records =
+                                parse_table_option(key, value)?
+                        }
+                        _ => return unsupported(key),
+                    }
+                }
+                create_table(location, table_partition_cols, |path| {
+                    let table = NdJsonFile::try_new(path, json_options.clone())?;
+                    // the other partitions use the schema inferred for the first one
+                    json_options.schema = Some(table.schema());
+                    Ok(Arc::new(table))
+                })
+            }
+            FileType::Parquet => {
+                let (concurrency, mut enable_pruning) = {
+                    let state = self.state.lock().unwrap();
+                    (state.config.concurrency, state.config.parquet_pruning)
+                };
+                let mut file_list_options = FileListOptions::default();
+                for (key, value) in options {
+                    if apply_file_list_option(&mut file_list_options, key, value)? {
+                        continue;
+                    }
+                    match key.as_str() {
+                        "enable_pruning" => {
+                            enable_pruning = parse_table_option(key, value)?
+                        }
+                        _ => return unsupported(key),
+                    }
+                }
+                create_table(location, table_partition_cols, |path| {
+                    Ok(Arc::new(
+                        ParquetTable::try_new_with_file_list_options(
+                            path,
+                            concurrency,
+                            file_list_options,
+                        )?
+                        .with_enable_pruning(enable_pruning),
+                    ))
+                })
+            }
+            FileType::Delta => {
+                let (concurrency, mut enable_pruning) = {
+                    let state = self.state.lock().unwrap();
+                    (state.config.concurrency, state.config.parquet_pruning)
+                };
+                let mut version = None;
+                for (key, value) in options {
+                    match key.as_str() {
+                        "version" => version = Some(parse_table_option(key, value)?),
+                        "enable_pruning" => {
+                            enable_pruning = parse_table_option(key, value)?
+                        }
+                        _ => return unsupported(key),
+                    }
+                }
+                Ok(Arc::new(
+                    DeltaTable::try_new_with_version(location, version, concurrency)?
+                        .with_enable_pruning(enable_pruning),
+                ))
+            }
+        }
+    }
+
     /// Creates a logical plan.
     ///
     /// This function is intended for internal use and should not be called directly.
@@ -220,10 +447,14 @@ impl ExecutionContext {
             ));
         }
 
+        self.statement_to_plan(&statements[0])
+    }
+
+    fn statement_to_plan(&self, statement: &DFStatement) -> Result<LogicalPlan> {
         // create a query planner
         let state = self.state.lock().unwrap().clone();
         let query_planner = SqlToRel::new(&state);
-        query_planner.statement_to_plan(&statements[0])
+        query_planner.statement_to_plan(statement)
     }
 
     /// Registers a variable provider within this context.
@@ -246,6 +477,11 @@ impl ExecutionContext {
     ///
     /// `SELECT MY_FUNC(x)...` will look for a function named `"my_func"`
     /// `SELECT "my_FUNC"(x)` will look for a function named `"my_FUNC"`
+    ///
+    /// Names that start with [`RESERVED_FUNCTION_PREFIX`] are reserved for the SQL
+    /// clauses that the parser rewrites into function calls.
+    ///
+    /// [`RESERVED_FUNCTION_PREFIX`]: crate::sql::parser::RESERVED_FUNCTION_PREFIX
     pub fn register_udf(&mut self, f: ScalarUDF) {
         self.state
             .lock()
@@ -261,6 +497,11 @@ impl ExecutionContext {
     ///
     /// `SELECT MY_UDAF(x)...` will look for an aggregate named `"my_udaf"`
     /// `SELECT "my_UDAF"(x)` will look for an aggregate named `"my_UDAF"`
+    ///
+    /// Names that start with [`RESERVED_FUNCTION_PREFIX`] are reserved for the SQL
+    /// clauses that the parser rewrites into function calls.
+    ///
+    /// [`RESERVED_FUNCTION_PREFIX`]: crate::sql::parser::RESERVED_FUNCTION_PREFIX
     pub fn register_udaf(&mut self, f: AggregateUDF) {
         self.state
             .lock()
@@ -269,6 +510,26 @@ impl ExecutionContext {
             .insert(f.name.clone(), Arc::new(f));
     }
 
+    /// Registers a window UDF within this context, to be used with an `OVER` clause.
+    ///
+    /// Note in SQL queries, window function names are looked up using
+    /// lowercase unless the query uses quotes. For example,
+    ///
+    /// `SELECT MY_UDWF(x) OVER (...)` will look for a window function named `"my_udwf"`
+    /// `SELECT "my_UDWF"(x) OVER (...)` will look for a window function named `"my_UDWF"`
+    ///
+    /// Names that start with [`RESERVED_FUNCTION_PREFIX`] are reserved for the SQL
+    /// clauses that the parser rewrites into function calls.
+    ///
+    /// [`RESERVED_FUNCTION_PREFIX`]: crate::sql::parser::RESERVED_FUNCTION_PREFIX
+    pub fn register_udwf(&mut self, f: WindowUDF) {
+        self.state
+            .lock()
+            .unwrap()
+            .window_functions
+            .insert(f.name.clone(), Arc::new(f));
+    }
+
     /// Creates a DataFrame for reading a CSV data source.
     pub fn read_csv(
         &mut self,
@@ -297,6 +558,15 @@ impl ExecutionContext {
         )))
     }
 
+    /// Creates a DataFrame for reading an Arrow IPC data source.
+    pub fn read_arrow(
+        &mut self,
+        filename: &str,
+        options: ArrowReadOptions,
+    ) -> Result<Arc<dyn DataFrame>> {
+        self.read_table(Arc::new(ArrowFile::try_new(filename, options)?))
+    }
+
     /// Creates a DataFrame for reading a custom TableProvider.
     pub fn read_table(
         &mut self,
@@ -332,6 +602,30 @@ impl ExecutionContext {
         Ok(())
     }
 
+    /// Registers the latest version of a Delta Lake table so that it can be referenced
+    /// from SQL statements executed against this context.
+    pub fn register_delta(&mut self, name: &str, path: &str) -> Result<()> {
+        let table = {
+            let m = self.state.lock().unwrap();
+            DeltaTable::try_new(path, m.config.concurrency)?
+                .with_enable_pruning(m.config.parquet_pruning)
+        };
+        self.register_table(name, Arc::new(table))?;
+        Ok(())
+    }
+
+    /// Registers an Arrow IPC data source so that it can be referenced from SQL
+    /// statements executed against this context.
+    pub fn register_arrow(
+        &mut self,
+        name: &str,
+        filename: &str,
+        options: ArrowReadOptions,
+    ) -> Result<()> {
+        self.register_table(name, Arc::new(ArrowFile::try_new(filename, options)?))?;
+        Ok(())
+    }
+
     /// Registers a named catalog using a custom `CatalogProvider` so that
     /// it can be referenced from SQL statements executed against this
     /// context.
@@ -407,7 +701,7 @@ impl ExecutionContext {
     ) -> Result<Arc<dyn DataFrame>> {
         let table_ref = table_ref.into();
         let schema = self.state.lock().unwrap().schema_for_ref(table_ref)?;
-        match schema.table(table_ref.table()) {
+        match schema.try_table(table_ref.table())? {
             Some(ref provider) => {
                 let plan = LogicalPlanBuilder::scan(
                     table_ref.table(),
@@ -424,6 +718,94 @@ impl ExecutionContext {
         }
     }
 
+    /// Computes the statistics of a registered table by scanning its data: its number of
+    /// rows and the number of nulls of its columns, as well as the minimum value, the
+    /// maximum value and the number of distinct values of the columns whose type has an
+    /// order. The table is registered again with these statistics, which are used to
+    /// estimate the cardinality of joins and aggregations.
+    pub async fn analyze_table<'a>(
+        &mut self,
+        table_ref: impl Into<TableReference<'a>>,
+    ) -> Result<Statistics> {
+        let table_ref = table_ref.into();
+        let schema = self.state.lock().unwrap().schema_for_ref(table_ref)?;
+        let provider = schema.try_table(table_ref.table())?.ok_or_else(|| {
+            DataFusionError::Plan(format!("No table named '{}'", table_ref.table()))
+        })?;
+        // a table that was analyzed before is analyzed again from its data
+        let provider = match provider.as_any().downcast_ref::<AnalyzedTable>() {
+            Some(analyzed) => analyzed.table(),
+            None => provider,
+        };
+
+        // COUNT(*), then COUNT(c) for each column c, followed by MIN(c), MAX(c) and
+        // COUNT(DISTINCT c) if the column has an order
+        let table_schema = provider.schema();
+        let mut aggr_expr = vec![count(lit(1u8))];
+        let mut ordered = vec![];
+        for field in table_schema.fields() {
+            let column = Expr::Column(Column::from_name(field.name()));
+            aggr_expr.push(count(column.clone()));
+            let is_ordered = aggregates::return_type(
+                &aggregates::AggregateFunction::Min,
+                &[field.data_type().clone()],
+            )
+            .is_ok();
+            if is_ordered {
+                aggr_expr.push(min(column.clone()));
+                aggr_expr.push(max(column.clone()));
+                aggr_expr.push(count_distinct(column));
+            }
+            ordered.push(is_ordered);
+        }
+        let plan = LogicalPlanBuilder::scan(table_ref.table(), provider.clone(), None)?
+            .aggregate(Vec::<Expr>::new(), aggr_expr)?
+            .build()?;
+        // the plan is not optimized, so that the aggregates are computed from the data
+        // rather than from the statistics of the table
+        let batches =
+            crate::physical_plan::collect(self.create_physical_plan(&plan)?).await?;
+        let batch = batches.first().ok_or_else(|| {
+            DataFusionError::Internal("The analysis returned no rows".to_owned())
+        })?;
+        let value = |i: usize| ScalarValue::try_from_array(batch.column(i), 0);
+        let count_value = |i: usize| match value(i)? {
+            ScalarValue::UInt64(Some(n)) => Ok(n as usize),
+            other => Err(DataFusionError::Internal(format!(
+                "Unexpected count {:?}",
+                other
+            ))),
+        };
+
+        let num_rows = count_value(0)?;
+        let mut i = 1;
+        let mut column_statistics = Vec::with_capacity(ordered.len());
+        for is_ordered in ordered {
+            let mut stats = ColumnStatistics {
+                null_count: Some(num_rows - count_value(i)?),
+                ..Default::default()
+            };
+            i += 1;
+            if is_ordered {
+                stats.min_value = Some(value(i)?);
+                stats.max_value = Some(value(i + 1)?);
+                stats.distinct_count = Some(count_value(i + 2)?);
+                i += 3;
+            }
+            column_statistics.push(stats);
+        }
+        let statistics = Statistics {
+            num_rows: Some(num_rows),
+            total_byte_size: provider.statistics().total_byte_size,
+            column_statistics: Some(column_statistics),
+        };
+        schema.register_table(
+            table_ref.table().to_owned(),
+            Arc::new(AnalyzedTable::new(provider, statistics.clone())),
+        )?;
+        Ok(statistics)
+    }
+
     /// Returns the set of available tables in the default catalog and schema.
     ///
     /// Use [`table`] to get a specific table.
@@ -445,10 +827,17 @@ impl ExecutionContext {
             .collect())
     }
 
+    /// Returns the resources shared by the queries of this context, such as the
+    /// disk manager that hands out spill files
+    pub fn runtime_env(&self) -> Arc<RuntimeEnv> {
+        self.state.lock().unwrap().runtime_env.clone()
+    }
+
     /// Optimizes the logical plan by applying optimizer rules.
     pub fn optimize(&self, plan: &LogicalPlan) -> Result<LogicalPlan> {
         if let LogicalPlan::Explain {
             verbose,
+            format,
             plan,
             stringified_plans,
             schema,
@@ -457,20 +846,27 @@ impl ExecutionContext {
             let mut stringified_plans = stringified_plans.clone();
 
             // optimize the child plan, capturing the output of each optimizer
-            let plan = self.optimize_internal(plan, |optimized_plan, optimizer| {
-                let optimizer_name = optimizer.name().to_string();
-                let plan_type = PlanType::OptimizedLogicalPlan { optimizer_name };
-                stringified_plans.push(optimized_plan.to_stringified(plan_type));
-            })?;
+            let plan =
+                self.optimize_internal(plan, |optimized_plan, optimizer, pass| {
+                    let optimizer_name = if pass > 1 {
+                        format!("{} (pass {})", optimizer.name(), pass)
+                    } else {
+                        optimizer.name().to_string()
+                    };
+                    let plan_type = PlanType::OptimizedLogicalPlan { optimizer_name };
+                    stringified_plans
+                        .push(optimized_plan.to_stringified(plan_type, *format));
+                })?;
 
             Ok(LogicalPlan::Explain {
                 verbose: *verbose,
+                format: *format,
                 plan: Arc::new(plan),
                 stringified_plans,
                 schema: schema.clone(),
             })
         } else {
-            self.optimize_internal(plan, |_, _| {})
+            self.optimize_internal(plan, |_, _, _| {})
         }
     }
 
@@ -578,21 +974,37 @@ impl ExecutionContext {
         mut observer: F,
     ) -> Result<LogicalPlan>
     where
-        F: FnMut(&LogicalPlan, &dyn OptimizerRule),
+        F: FnMut(&LogicalPlan, &dyn OptimizerRule, usize),
     {
         let state = &mut self.state.lock().unwrap();
         let execution_props = &mut state.execution_props.clone();
-        let optimizers = &state.config.optimizers;
+        let optimizer = Optimizer::new(state.config.optimizers.clone())
+            .with_max_passes(state.config.optimizer_max_passes)
+            .with_disabled_rules(state.config.disabled_optimizer_rules.clone());
 
         let execution_props = execution_props.start_execution();
 
-        let mut new_plan = plan.clone();
         debug!("Logical plan:\n {:?}", plan);
-        for optimizer in optimizers {
-            new_plan = optimizer.optimize(&new_plan, execution_props)?;
-            observer(&new_plan, optimizer.as_ref());
-        }
+        let mut rule_times: Vec<(String, Duration)> = vec![];
+        let new_plan = optimizer.optimize(
+            plan,
+            execution_props,
+            |optimized_plan, rule, pass, elapsed| {
+                debug!(
+                    "Optimizer rule {} took {:?} in pass {}",
+                    rule.name(),
+                    elapsed,
+                    pass
+                );
+                match rule_times.iter_mut().find(|(name, _)| name == rule.name()) {
+                    Some((_, time)) => *time += elapsed,
+                    None => rule_times.push((rule.name().to_owned(), elapsed)),
+                }
+                observer(optimized_plan, rule, pass);
+            },
+        )?;
         debug!("Optimized logical plan:\n {:?}", new_plan);
+        state.optimizer_rule_times = rule_times;
         Ok(new_plan)
     }
 }
@@ -649,8 +1061,16 @@ pub struct ExecutionConfig {
     pub concurrency: usize,
     /// Default batch size when reading data sources
     pub batch_size: usize,
+    /// Number of rows that small batches are concatenated to after filters, joins
+    /// and repartitions. Defaults to half of `batch_size` when not set
+    pub coalesce_target_batch_size: Option<usize>,
     /// Responsible for optimizing a logical plan
     optimizers: Vec<Arc<dyn OptimizerRule + Send + Sync>>,
+    /// Maximum number of passes over the optimizer rules, which are applied again
+    /// until a pass leaves the plan unchanged
+    optimizer_max_passes: usize,
+    /// Names of the optimizer rules that are skipped
+    disabled_optimizer_rules: HashSet<String>,
     /// Responsible for optimizing a physical execution plan
     pub physical_optimizers: Vec<Arc<dyn PhysicalOptimizerRule + Send + Sync>>,
     /// Responsible for planning `LogicalPlan`s, and `ExecutionPlan`
@@ -675,6 +1095,20 @@ pub struct ExecutionConfig {
     pub repartition_windows: bool,
     /// Should Datafusion parquet reader using the predicate to prune data
     parquet_pruning: bool,
+    /// Should comparisons that implicitly cast an argument with a lossy cast, such as
+    /// `Int64` to `Float64`, be planning errors
+    pub strict_type_coercion: bool,
+    /// Should the comparisons between a column and a literal of filter predicates be
+    /// evaluated by fused kernels, see
+    /// [FusedPredicateExpr](crate::physical_plan::expressions::FusedPredicateExpr)
+    pub fused_predicates: bool,
+    /// Hash function of the hash partitionings of joins, aggregates, window functions
+    /// and repartitions, see [partition_hash](crate::physical_plan::partition_hash)
+    pub hash_scheme: HashScheme,
+    /// User returned by `current_user()`
+    pub current_user: String,
+    /// Configuration for the resources shared by the queries of the context
+    runtime_config: RuntimeConfig,
 }
 
 impl Default for ExecutionConfig {
@@ -682,20 +1116,26 @@ impl Default for ExecutionConfig {
         Self {
             concurrency: num_cpus::get(),
             batch_size: 8192,
+            coalesce_target_batch_size: None,
             optimizers: vec![
                 Arc::new(ConstantFolding::new()),
                 Arc::new(EliminateLimit::new()),
                 Arc::new(AggregateStatistics::new()),
                 Arc::new(ProjectionPushDown::new()),
+                Arc::new(EliminateCrossJoin::new()),
                 Arc::new(FilterPushDown::new()),
+                Arc::new(AggregatePushDown::new()),
                 Arc::new(SimplifyExpressions::new()),
                 Arc::new(HashBuildProbeOrder::new()),
                 Arc::new(LimitPushDown::new()),
             ],
+            optimizer_max_passes: 3,
+            disabled_optimizer_rules: HashSet::new(),
             physical_optimizers: vec![
                 Arc::new(CoalesceBatches::new()),
                 Arc::new(Repartition::new()),
                 Arc::new(AddCoalescePartitionsExec::new()),
+                Arc::new(SortedAggregate::new()),
             ],
             query_planner: Arc::new(DefaultQueryPlanner {}),
             default_catalog: "datafusion".to_owned(),
@@ -706,6 +1146,11 @@ impl Default for ExecutionConfig {
             repartition_aggregations: true,
             repartition_windows: true,
             parquet_pruning: true,
+            strict_type_coercion: false,
+            fused_predicates: true,
+            hash_scheme: HashScheme::Internal,
+            current_user: DEFAULT_CURRENT_USER.to_owned(),
+            runtime_config: RuntimeConfig::new(),
         }
     }
 }
@@ -732,6 +1177,22 @@ impl ExecutionConfig {
         self
     }
 
+    /// Customize the number of rows that small batches are concatenated to
+    pub fn with_coalesce_target_batch_size(mut self, n: usize) -> Self {
+        // target batch size must be greater than zero
+        assert!(n > 0);
+        self.coalesce_target_batch_size = Some(n);
+        self
+    }
+
+    /// Number of rows that small batches are concatenated to. Half of the batch size
+    /// by default, to avoid copies when only a few rows are removed from a batch
+    pub fn coalesce_target_batch_size(&self) -> usize {
+        self.coalesce_target_batch_size
+            .unwrap_or(self.batch_size / 2)
+            .max(1)
+    }
+
     /// Replace the default query planner
     pub fn with_query_planner(
         mut self,
@@ -759,6 +1220,25 @@ impl ExecutionConfig {
         self
     }
 
+    /// Customize the maximum number of passes over the optimizer rules. The rules are
+    /// applied again until a pass leaves the plan unchanged or the limit is reached
+    pub fn with_optimizer_max_passes(mut self, n: usize) -> Self {
+        // there must be at least one pass
+        assert!(n > 0);
+        self.optimizer_max_passes = n;
+        self
+    }
+
+    /// Enables or disables the optimizer rule with the given name
+    pub fn with_optimizer_rule_enabled(mut self, name: &str, enabled: bool) -> Self {
+        if enabled {
+            self.disabled_optimizer_rules.remove(name);
+        } else {
+            self.disabled_optimizer_rules.insert(name.to_owned());
+        }
+        self
+    }
+
     /// Adds a new [`PhysicalOptimizerRule`]
     pub fn add_physical_optimizer_rule(
         mut self,
@@ -814,8 +1294,47 @@ impl ExecutionConfig {
         self.parquet_pruning = enabled;
         self
     }
+
+    /// Enables or disables strict type coercion, which rejects comparisons whose
+    /// arguments are implicitly cast with a lossy cast, see
+    /// [type_coercion](crate::physical_plan::type_coercion)
+    pub fn with_strict_type_coercion(mut self, enabled: bool) -> Self {
+        self.strict_type_coercion = enabled;
+        self
+    }
+
+    /// Enables or disables the evaluation of the comparisons between a column and a
+    /// literal of filter predicates by fused kernels
+    pub fn with_fused_predicates(mut self, enabled: bool) -> Self {
+        self.fused_predicates = enabled;
+        self
+    }
+
+    /// Customize the hash function of the hash partitionings of the plans, e.g.
+    /// [HashScheme::Murmur3V1] for partitions that are stable across releases
+    pub fn with_hash_scheme(mut self, hash_scheme: HashScheme) -> Self {
+        self.hash_scheme = hash_scheme;
+        self
+    }
+
+    /// Customize the user returned by `current_user()`, which defaults to
+    /// `datafusion`
+    pub fn with_current_user(mut self, current_user: impl Into<String>) -> Self {
+        self.current_user = current_user.into();
+        self
+    }
+
+    /// Customize the resources shared by the queries of the context, such as the
+    /// directories and quota for spill files
+    pub fn with_runtime_config(mut self, runtime_config: RuntimeConfig) -> Self {
+        self.runtime_config = runtime_config;
+        self
+    }
 }
 
+/// User returned by `current_user()` unless the context is configured with another one
+const DEFAULT_CURRENT_USER: &str = "datafusion";
+
 /// Holds per-execution properties and data (such as starting timestamps, etc).
 /// An instance of this struct is created each time a [`LogicalPlan`] is prepared for
 /// execution (optimized). If the same plan is optimized multiple times, a new
@@ -823,6 +1342,7 @@ impl ExecutionConfig {
 #[derive(Clone)]
 pub struct ExecutionProps {
     pub(crate) query_execution_start_time: DateTime<Utc>,
+    pub(crate) current_user: String,
 }
 
 /// Execution context for registering data sources and executing queries
@@ -836,10 +1356,19 @@ pub struct ExecutionContextState {
     pub var_provider: HashMap<VarType, Arc<dyn VarProvider + Send + Sync>>,
     /// Aggregate functions registered in the context
     pub aggregate_functions: HashMap<String, Arc<AggregateUDF>>,
+    /// Window functions registered in the context
+    pub window_functions: HashMap<String, Arc<WindowUDF>>,
+    /// SQL macros created by `CREATE FUNCTION` statements
+    pub sql_macros: HashMap<String, Arc<SqlMacro>>,
     /// Context configuration
     pub config: ExecutionConfig,
     /// Execution properties
     pub execution_props: ExecutionProps,
+    /// Resources shared by the queries of the context
+    pub runtime_env: Arc<RuntimeEnv>,
+    /// Time spent in each optimizer rule while optimizing the last plan, in the
+    /// order that the rules were first applied
+    pub optimizer_rule_times: Vec<(String, Duration)>,
 }
 
 impl ExecutionProps {
@@ -847,14 +1376,42 @@ impl ExecutionProps {
     pub fn new() -> Self {
         ExecutionProps {
             query_execution_start_time: chrono::Utc::now(),
+            current_user: DEFAULT_CURRENT_USER.to_owned(),
         }
     }
 
+    /// Sets the user returned by `current_user()`
+    pub fn with_current_user(mut self, current_user: impl Into<String>) -> Self {
+        self.current_user = current_user.into();
+        self
+    }
+
     /// Marks the execution of query started timestamp
     pub fn start_execution(&mut self) -> &Self {
         self.query_execution_start_time = chrono::Utc::now();
         &*self
     }
+
+    /// Returns the value of the query constant function `fun`, see
+    /// [BuiltinScalarFunction::is_query_constant]
+    pub fn query_constant(&self, fun: &BuiltinScalarFunction) -> Result<ScalarValue> {
+        match fun {
+            BuiltinScalarFunction::Now => Ok(ScalarValue::TimestampNanosecond(Some(
+                self.query_execution_start_time.timestamp_nanos(),
+            ))),
+            BuiltinScalarFunction::CurrentUser => {
+                Ok(ScalarValue::Utf8(Some(self.current_user.clone())))
+            }
+            BuiltinScalarFunction::Version => Ok(ScalarValue::Utf8(Some(format!(
+                "DataFusion {}",
+                env!("CARGO_PKG_VERSION")
+            )))),
+            other => Err(DataFusionError::Internal(format!(
+                "{} is not a query constant function",
+                other
+            ))),
+        }
+    }
 }
 
 impl ExecutionContextState {
@@ -865,8 +1422,12 @@ impl ExecutionContextState {
             scalar_functions: HashMap::new(),
             var_provider: HashMap::new(),
             aggregate_functions: HashMap::new(),
+            window_functions: HashMap::new(),
+            sql_macros: HashMap::new(),
             config: ExecutionConfig::new(),
             execution_props: ExecutionProps::new(),
+            runtime_env: Arc::new(RuntimeEnv::default()),
+            optimizer_rule_times: vec![],
         }
     }
 
@@ -910,6 +1471,17 @@ impl ContextProvider for ExecutionContextState {
         schema.table(resolved_ref.table)
     }
 
+    fn try_get_table_provider(
+        &self,
+        name: TableReference,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        let resolved_ref = self.resolve_table_ref(name);
+        match self.schema_for_ref(resolved_ref) {
+            Ok(schema) => schema.try_table(resolved_ref.table),
+            Err(_) => Ok(None),
+        }
+    }
+
     fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
         self.scalar_functions.get(name).cloned()
     }
@@ -917,6 +1489,14 @@ impl ContextProvider for ExecutionContextState {
     fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
         self.aggregate_functions.get(name).cloned()
     }
+
+    fn get_macro(&self, name: &str) -> Option<Arc<SqlMacro>> {
+        self.sql_macros.get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.window_functions.get(name).cloned()
+    }
 }
 
 impl FunctionRegistry for ExecutionContextState {
@@ -947,6 +1527,59 @@ impl FunctionRegistry for ExecutionContextState {
     }
 }
 
+/// Creates the table at `location` with `create`, or a [PartitionedTable] with a
+/// table per partition if the table is partitioned
+fn create_table(
+    location: &str,
+    table_partition_cols: &[String],
+    mut create: impl FnMut(&str) -> Result<Arc<dyn TableProvider>>,
+) -> Result<Arc<dyn TableProvider>> {
+    if table_partition_cols.is_empty() {
+        create(location)
+    } else {
+        Ok(Arc::new(PartitionedTable::try_new(
+            location,
+            table_partition_cols,
+            create,
+        )?))
+    }
+}
+
+/// Whether `statement` only registers a table or function in the context, and does not
+/// produce results
+fn is_ddl_statement(statement: &DFStatement) -> bool {
+    matches!(
+        statement,
+        DFStatement::CreateExternalTable(_) | DFStatement::CreateFunction(_)
+    )
+}
+
+/// Parses the value of an option of `CREATE EXTERNAL TABLE`
+fn parse_table_option<T: std::str::FromStr>(key: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| {
+        DataFusionError::Plan(format!("Invalid value '{}' for option {}", value, key))
+    })
+}
+
+/// Applies an option of a `CREATE EXTERNAL TABLE` statement that configures how the
+/// files of the location are listed, returning false if `key` is not such an option
+fn apply_file_list_option(
+    file_list_options: &mut FileListOptions,
+    key: &str,
+    value: &str,
+) -> Result<bool> {
+    match key {
+        "max_depth" => {
+            file_list_options.max_depth = Some(parse_table_option(key, value)?)
+        }
+        "include_hidden" => {
+            file_list_options.include_hidden = parse_table_option(key, value)?
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -961,12 +1594,13 @@ mod tests {
     };
     use crate::{
         datasource::{empty::EmptyTable, MemTable, TableType},
-        logical_plan::create_udaf,
+        logical_plan::{create_udaf, create_udwf},
         physical_plan::expressions::AvgAccumulator,
+        physical_plan::window_functions::PartitionEvaluator,
     };
     use arrow::array::{
-        Array, ArrayRef, BinaryArray, DictionaryArray, Float32Array, Float64Array,
-        Int16Array, Int32Array, Int64Array, Int8Array, LargeBinaryArray,
+        Array, ArrayRef, BinaryArray, BooleanArray, DictionaryArray, Float32Array,
+        Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, LargeBinaryArray,
         LargeStringArray, StringArray, TimestampNanosecondArray, UInt16Array,
         UInt32Array, UInt64Array, UInt8Array,
     };
@@ -974,12 +1608,52 @@ mod tests {
     use arrow::datatypes::*;
     use arrow::record_batch::RecordBatch;
     use std::fs::File;
+    use std::ops::Range;
     use std::sync::Weak;
     use std::thread::{self, JoinHandle};
     use std::{io::prelude::*, sync::Mutex};
     use tempfile::TempDir;
     use test::*;
 
+    #[test]
+    fn optimizer_rule_enabled() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]);
+        let plan = LogicalPlanBuilder::scan_empty(Some("employee"), &schema, None)?
+            .project(vec![col("id")])?
+            .build()?;
+
+        let ctx = ExecutionContext::new();
+        let optimized = ctx.optimize(&plan)?;
+        assert_eq!(
+            format!("{:?}", optimized),
+            "Projection: #employee.id\n  TableScan: employee projection=Some([0])"
+        );
+
+        let ctx = ExecutionContext::with_config(
+            ExecutionConfig::new()
+                .with_optimizer_rule_enabled("projection_push_down", false),
+        );
+        let optimized = ctx.optimize(&plan)?;
+        assert_eq!(
+            format!("{:?}", optimized),
+            "Projection: #employee.id\n  TableScan: employee projection=None"
+        );
+        let rules: Vec<String> = ctx
+            .state
+            .lock()
+            .unwrap()
+            .optimizer_rule_times
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        assert!(rules.contains(&"filter_push_down".to_owned()));
+        assert!(!rules.contains(&"projection_push_down".to_owned()));
+        Ok(())
+    }
+
     #[test]
     fn optimize_explain() {
         let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
@@ -1617,6 +2291,42 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn window_distribution_and_frames() -> Result<()> {
+        let results = execute(
+            "SELECT \
+            c1, \
+            c2, \
+            RANK() OVER (ORDER BY c1), \
+            PERCENT_RANK() OVER (ORDER BY c1), \
+            CUME_DIST() OVER (ORDER BY c1), \
+            NTH_VALUE(c2, 2) OVER (ORDER BY c1, c2 \
+                ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) AS nth_all, \
+            NTH_VALUE(c2, 2) OVER (ORDER BY c1, c2 \
+                ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) AS nth_rows \
+            FROM test \
+            ORDER BY c1, c2 \
+            LIMIT 5",
+            4,
+        )
+        .await?;
+
+        // the 11 rows with c1 = 0 are peers, which are a quarter of the 44 rows
+        let expected = vec![
+            "+----+----+--------+----------------+-------------+---------+----------+",
+            "| c1 | c2 | RANK() | PERCENT_RANK() | CUME_DIST() | nth_all | nth_rows |",
+            "+----+----+--------+----------------+-------------+---------+----------+",
+            "| 0  | 0  | 1      | 0              | 0.25        | 1       | 1        |",
+            "| 0  | 1  | 1      | 0              | 0.25        | 1       | 1        |",
+            "| 0  | 2  | 1      | 0              | 0.25        | 1       | 2        |",
+            "| 0  | 3  | 1      | 0              | 0.25        | 1       | 3        |",
+            "| 0  | 4  | 1      | 0              | 0.25        | 1       | 4        |",
+            "+----+----+--------+----------------+-------------+---------+----------+",
+        ];
+        assert_batches_eq!(expected, &results);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn aggregate() -> Result<()> {
         let results = execute("SELECT SUM(c1), SUM(c2) FROM test", 4).await?;
@@ -2046,6 +2756,61 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn group_by_date_bin() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let mut ctx = ExecutionContext::new();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("c2", DataType::UInt64, false),
+            Field::new(
+                "t1",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+        ]));
+
+        // generate a partitioned file
+        for partition in 0..4 {
+            let filename = format!("partition-{}.{}", partition, "csv");
+            let file_path = tmp_dir.path().join(&filename);
+            let mut file = File::create(file_path)?;
+
+            // generate some data
+            for i in 0..10 {
+                let data = format!("{},2020-12-10T00:{:02}:00.000\n", i, i * 5);
+                file.write_all(data.as_bytes())?;
+            }
+        }
+
+        ctx.register_csv(
+            "test",
+            tmp_dir.path().to_str().unwrap(),
+            CsvReadOptions::new().schema(&schema).has_header(false),
+        )?;
+
+        let results = plan_and_collect(
+            &mut ctx,
+            "SELECT date_bin(INTERVAL '15 minutes', t1, to_timestamp('2001-01-01T00:05:00')) AS bin, SUM(c2) \
+            FROM test \
+            GROUP BY date_bin(INTERVAL '15 minutes', t1, to_timestamp('2001-01-01T00:05:00'))",
+        )
+        .await?;
+
+        let expected = vec![
+            "+---------------------+---------+",
+            "| bin                 | SUM(c2) |",
+            "+---------------------+---------+",
+            "| 2020-12-09 23:50:00 | 0       |",
+            "| 2020-12-10 00:05:00 | 24      |",
+            "| 2020-12-10 00:20:00 | 60      |",
+            "| 2020-12-10 00:35:00 | 96      |",
+            "+---------------------+---------+",
+        ];
+        assert_batches_sorted_eq!(expected, &results);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn group_by_largeutf8() {
         {
@@ -2566,6 +3331,49 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn analyze_table() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Boolean, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), Some(2), None])),
+                Arc::new(BooleanArray::from(vec![Some(true), None, None, None])),
+            ],
+        )?;
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", Arc::new(MemTable::try_new(schema, vec![vec![batch]])?))?;
+
+        let expected = Statistics {
+            num_rows: Some(4),
+            total_byte_size: None,
+            column_statistics: Some(vec![
+                ColumnStatistics {
+                    null_count: Some(1),
+                    min_value: Some(ScalarValue::Int32(Some(1))),
+                    max_value: Some(ScalarValue::Int32(Some(2))),
+                    distinct_count: Some(2),
+                },
+                // booleans have no minimum or maximum
+                ColumnStatistics {
+                    null_count: Some(3),
+                    ..Default::default()
+                },
+            ]),
+        };
+        assert_eq!(expected, ctx.analyze_table("t").await?);
+        let table = ctx.state.lock().unwrap().schema_for_ref("t")?.table("t");
+        assert_eq!(expected, table.unwrap().statistics());
+
+        // analyzing again scans the table rather than wrapping it twice
+        assert_eq!(expected, ctx.analyze_table("t").await?);
+        assert!(ctx.analyze_table("u").await.is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn case_sensitive_identifiers_aggregates() {
         let mut ctx = ExecutionContext::new();
@@ -2714,6 +3522,102 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn strict_type_coercion() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let schema = populate_csv_partitions(&tmp_dir, 1, ".csv")?;
+        let mut ctx = ExecutionContext::with_config(
+            ExecutionConfig::new().with_strict_type_coercion(true),
+        );
+        ctx.register_csv(
+            "test",
+            tmp_dir.path().to_str().unwrap(),
+            CsvReadOptions::new().schema(&schema),
+        )?;
+
+        // literals are compared in the type of the column when their value is kept
+        let results = plan_and_collect(
+            &mut ctx,
+            "SELECT COUNT(*) FROM test WHERE c2 > 5 AND c2 BETWEEN 1 AND 9.0",
+        )
+        .await?;
+        let expected = vec![
+            "+-----------------+",
+            "| COUNT(UInt8(1)) |",
+            "+-----------------+",
+            "| 4               |",
+            "+-----------------+",
+        ];
+        assert_batches_eq!(expected, &results);
+
+        // UInt64 values do not fit in Int64
+        let err = plan_and_collect(
+            &mut ctx,
+            "SELECT * FROM test WHERE c2 = CAST(c1 AS BIGINT)",
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("strict type coercion"), "{}", err);
+        let err = plan_and_collect(&mut ctx, "SELECT * FROM test WHERE c2 > -1")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("strict type coercion"), "{}", err);
+
+        // the same comparisons with lenient type coercion
+        let mut ctx = create_ctx(&tmp_dir, 1)?;
+        plan_and_collect(&mut ctx, "SELECT * FROM test WHERE c2 = CAST(c1 AS BIGINT)")
+            .await?;
+
+        // types without a common type are rejected when the plan is built
+        let err = ctx.sql("SELECT * FROM test WHERE c2 = 'a'").unwrap_err();
+        assert!(
+            err.to_string().contains("there isn't a common type"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn copy_to_parquet() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let mut ctx = create_ctx(&tmp_dir, 4)?;
+
+        let out_dir = tmp_dir.as_ref().to_str().unwrap().to_string() + "/out";
+        let sql = format!(
+            "COPY (SELECT c1, c2 FROM test) TO '{}' \
+             OPTIONS (compression 'zstd', 'dictionary_enabled::c2' 'false')",
+            out_dir
+        );
+        let results = plan_and_collect(&mut ctx, &sql).await?;
+        let expected = vec![
+            "+-------+",
+            "| count |",
+            "+-------+",
+            "| 40    |",
+            "+-------+",
+        ];
+        assert_batches_eq!(expected, &results);
+
+        // the directory already exists
+        assert!(plan_and_collect(&mut ctx, &sql).await.is_err());
+
+        let mut ctx = ExecutionContext::new();
+        ctx.register_parquet("allparts", &out_dir)?;
+        let results =
+            plan_and_collect(&mut ctx, "SELECT COUNT(*), SUM(c2) FROM allparts").await?;
+        let expected = vec![
+            "+-----------------+------------------+",
+            "| COUNT(UInt8(1)) | SUM(allparts.c2) |",
+            "+-----------------+------------------+",
+            "| 40              | 220              |",
+            "+-----------------+------------------+",
+        ];
+        assert_batches_eq!(expected, &results);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn query_csv_with_custom_partition_extension() -> Result<()> {
         let tmp_dir = TempDir::new()?;
@@ -2976,6 +3880,91 @@ mod tests {
         Ok(())
     }
 
+    /// running sum of the values of a partition, in the order of its rows
+    struct RunningSumEvaluator {
+        values: ArrayRef,
+    }
+
+    impl PartitionEvaluator for RunningSumEvaluator {
+        fn evaluate_partition(&self, partition: Range<usize>) -> Result<ArrayRef> {
+            let values = self
+                .values
+                .slice(partition.start, partition.end - partition.start);
+            let values = values.as_any().downcast_ref::<Int32Array>().unwrap();
+            let sums = values
+                .iter()
+                .scan(0_i64, |sum, value| {
+                    *sum += value.unwrap_or_default() as i64;
+                    Some(Some(*sum))
+                })
+                .collect::<Int64Array>();
+            Ok(Arc::new(sums))
+        }
+    }
+
+    /// tests the creation, registration and usage of a UDWF
+    #[tokio::test]
+    async fn simple_udwf() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("g", DataType::Utf8, false),
+            Field::new("a", DataType::Int32, false),
+        ]);
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(StringArray::from(vec!["x", "x", "y", "x", "y"])),
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5])),
+            ],
+        )?;
+
+        let mut ctx = ExecutionContext::new();
+
+        let provider = MemTable::try_new(Arc::new(schema), vec![vec![batch]])?;
+        ctx.register_table("t", Arc::new(provider))?;
+
+        let running_sum = create_udwf(
+            "running_sum",
+            vec![DataType::Int32],
+            Arc::new(DataType::Int64),
+            Arc::new(|values| {
+                Ok(Box::new(RunningSumEvaluator {
+                    values: values[0].clone(),
+                }))
+            }),
+        );
+
+        ctx.register_udwf(running_sum);
+
+        let result = plan_and_collect(
+            &mut ctx,
+            "SELECT g, a, RUNNING_SUM(a) OVER (PARTITION BY g ORDER BY a) FROM t \
+            ORDER BY g, a",
+        )
+        .await?;
+
+        let expected = vec![
+            "+---+---+----------------+",
+            "| g | a | running_sum(a) |",
+            "+---+---+----------------+",
+            "| x | 1 | 1              |",
+            "| x | 2 | 3              |",
+            "| x | 4 | 7              |",
+            "| y | 3 | 3              |",
+            "| y | 5 | 8              |",
+            "+---+---+----------------+",
+        ];
+        assert_batches_eq!(expected, &result);
+
+        // an unknown window function is still an error
+        let err = plan_and_collect(&mut ctx, "SELECT MY_RANK() OVER () FROM t")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("my_rank"), "{}", err);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn custom_query_planner() -> Result<()> {
         let mut ctx = ExecutionContext::with_config(
@@ -3293,6 +4282,43 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn information_schema_describe_table() {
+        let mut ctx = ExecutionContext::new();
+        ctx.register_table("t", test::table_with_sequence(1, 1).unwrap())
+            .unwrap();
+
+        let err = plan_and_collect(&mut ctx, "DESCRIBE t").await.unwrap_err();
+        assert_eq!(err.to_string(), "Error during planning: DESCRIBE is not supported unless information_schema is enabled");
+
+        let mut ctx = ExecutionContext::with_config(
+            ExecutionConfig::new().with_information_schema(true),
+        );
+        ctx.register_table("t", test::table_with_sequence(1, 1).unwrap())
+            .unwrap();
+
+        let expected = vec![
+            "+-------------+-----------+-------------+",
+            "| column_name | data_type | is_nullable |",
+            "+-------------+-----------+-------------+",
+            "| i           | Int32     | YES         |",
+            "+-------------+-----------+-------------+",
+        ];
+        let result = plan_and_collect(&mut ctx, "DESCRIBE t").await.unwrap();
+        assert_batches_sorted_eq!(expected, &result);
+
+        let result = plan_and_collect(&mut ctx, "desc datafusion.public.t")
+            .await
+            .unwrap();
+        assert_batches_sorted_eq!(expected, &result);
+
+        let err = plan_and_collect(&mut ctx, "DESCRIBE t2").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Unknown relation for DESCRIBE: t2"
+        );
+    }
+
     // test errors with WHERE and LIKE
     #[tokio::test]
     async fn information_schema_show_columns_full_extended() {
@@ -3558,6 +4584,53 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn create_external_table_with_glob() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        for (file, value) in &[
+            ("a/1.csv", 1),
+            ("a/nested/2.csv", 2),
+            ("b/3.csv", 3),
+            ("_tmp/4.csv", 4),
+        ] {
+            let path = tmp_dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, format!("c1\n{}\n", value))?;
+        }
+        let root = tmp_dir.path().to_str().unwrap();
+
+        let sum = |location: String, options: &str| {
+            let root = root.to_owned();
+            async move {
+                let mut ctx = ExecutionContext::new();
+                let sql = format!(
+                    "CREATE EXTERNAL TABLE t (c1 INT) STORED AS CSV WITH HEADER ROW \
+                     {} LOCATION '{}/{}'",
+                    options, root, location
+                );
+                plan_and_collect(&mut ctx, &sql).await?;
+                let batches = plan_and_collect(&mut ctx, "SELECT SUM(c1) FROM t").await?;
+                let sums = batches[0]
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap();
+                Ok::<_, DataFusionError>(sums.value(0))
+            }
+        };
+
+        assert_eq!(sum("".to_owned(), "").await?, 6);
+        assert_eq!(sum("".to_owned(), "OPTIONS (max_depth '1')").await?, 4);
+        assert_eq!(
+            sum("".to_owned(), "OPTIONS (include_hidden 'true')").await?,
+            10
+        );
+        assert_eq!(sum("*/*.csv".to_owned(), "").await?, 4);
+        assert_eq!(sum("a/**/*.csv".to_owned(), "").await?, 3);
+        assert_eq!(sum("[ab]".to_owned(), "OPTIONS (max_depth '1')").await?, 4);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_external_table_with_timestamps() {
         let mut ctx = ExecutionContext::new();
@@ -3604,6 +4677,55 @@ mod tests {
         assert_batches_sorted_eq!(expected, &result);
     }
 
+    #[tokio::test]
+    async fn sql_scripts() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let file_path = tmp_dir.path().join("values.csv");
+        fs::write(&file_path, "c1\n1\n2\n3\n")?;
+        let create_table = format!(
+            "CREATE EXTERNAL TABLE t (c1 INT) STORED AS CSV WITH HEADER ROW LOCATION '{}'",
+            file_path.to_str().unwrap()
+        );
+
+        // the last statement sees the table and function created before it
+        let mut ctx = ExecutionContext::new();
+        let sql = format!(
+            "{}; CREATE FUNCTION double(x) AS (x * 2); \
+             SELECT SUM(double(c1)) AS total FROM t;",
+            create_table
+        );
+        let expected = vec![
+            "+-------+",
+            "| total |",
+            "+-------+",
+            "| 12    |",
+            "+-------+",
+        ];
+        assert_batches_eq!(expected, &plan_and_collect(&mut ctx, &sql).await?);
+
+        // only the last statement can produce results
+        let mut ctx = ExecutionContext::new();
+        let sql = format!(
+            "{}; SELECT 1 AS one; SELECT MAX(c1) AS max FROM t",
+            create_table
+        );
+        assert!(matches!(
+            ctx.sql(&sql),
+            Err(DataFusionError::NotImplemented(_))
+        ));
+        assert!(ctx.sql("").is_err());
+
+        // the results of every statement of a script
+        let results = ctx.sql_script(&sql).await?;
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_empty());
+        let expected = vec!["+-----+", "| one |", "+-----+", "| 1   |", "+-----+"];
+        assert_batches_eq!(expected, &results[1]);
+        let expected = vec!["+-----+", "| max |", "+-----+", "| 3   |", "+-----+"];
+        assert_batches_eq!(expected, &results[2]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn query_empty_table() {
         let mut ctx = ExecutionContext::new();
@@ -0,0 +1,81 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Pool of threads that runs the CPU-bound work of operators, such as sorting and
+//! aggregating batches, so that it holds up neither the tokio worker threads that
+//! drive network IO nor the blocking threads that read files.
+//!
+//! The pool is the global thread pool of rayon, which has one thread per CPU unless
+//! [configure_cpu_pool] sized it before its first use. Operators hand it chunks of
+//! work with [spawn] and await the result from their async tasks.
+
+use crate::error::{DataFusionError, Result};
+use std::panic::{self, AssertUnwindSafe};
+use tokio::sync::oneshot;
+
+/// Sets the number of threads of the pool that runs the CPU-bound work of the
+/// operators of this process, 0 for one thread per CPU. This can only be called
+/// once, before the first query runs.
+pub fn configure_cpu_pool(num_threads: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(|i| format!("datafusion-cpu-{}", i))
+        .build_global()
+        .map_err(|e| {
+            DataFusionError::Execution(format!("Could not configure the CPU pool: {}", e))
+        })
+}
+
+/// Runs `f` on a thread of the pool, and resolves to its result or to an error if
+/// `f` panicked
+pub(crate) async fn spawn<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    rayon::spawn(move || {
+        // a panic would abort the process from a thread of rayon, and the receiver
+        // is gone if the task awaiting the result was dropped
+        let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    });
+    match rx.await {
+        Ok(Ok(result)) => Ok(result),
+        _ => Err(DataFusionError::Execution(
+            "A task of the CPU pool panicked".to_owned(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spawn_tasks() -> Result<()> {
+        let tasks = (0..8u64)
+            .map(|i| spawn(move || (0..=i).sum::<u64>()))
+            .collect::<Vec<_>>();
+        let results = futures::future::try_join_all(tasks).await?;
+        assert_eq!(vec![0, 1, 3, 6, 10, 15, 21, 28], results);
+
+        // the thread survives the panic of a task
+        assert!(spawn(|| -> u64 { panic!("boom") }).await.is_err());
+        assert_eq!(42, spawn(|| 42).await?);
+        Ok(())
+    }
+}
@@ -19,18 +19,24 @@
 
 use std::sync::{Arc, Mutex};
 
+use crate::arrow::array::{Array, ArrayRef, Float64Array, StringArray};
+use crate::arrow::compute::cast;
+use crate::arrow::datatypes::{DataType, Field, Schema};
 use crate::arrow::record_batch::RecordBatch;
 use crate::error::Result;
 use crate::execution::context::{ExecutionContext, ExecutionContextState};
 use crate::logical_plan::{
-    col, DFSchema, Expr, FunctionRegistry, JoinType, LogicalPlan, LogicalPlanBuilder,
+    avg, builder::expand_wildcard_except, col, count, lit, max, min, sum, Column,
+    DFSchema, Expr, FunctionRegistry, JoinType, LogicalPlan, LogicalPlanBuilder,
     Partitioning,
 };
+use crate::physical_plan::expressions::coercion::is_numeric;
 use crate::{
     dataframe::*,
     physical_plan::{collect, collect_partitioned},
 };
 
+use crate::physical_plan::parquet_writer::{ParquetWriteOptions, ParquetWriterExec};
 use crate::physical_plan::{
     execute_stream, execute_stream_partitioned, ExecutionPlan, SendableRecordBatchStream,
 };
@@ -72,6 +78,17 @@ impl DataFrame for DataFrameImpl {
         self.select(expr)
     }
 
+    /// Create a projection of all columns except the given ones
+    fn except_columns(&self, columns: &[&str]) -> Result<Arc<dyn DataFrame>> {
+        let plan = self.to_logical_plan();
+        let excluded = columns
+            .iter()
+            .map(|name| Column::from_qualified_name(name))
+            .collect::<Vec<_>>();
+        let expr = expand_wildcard_except(plan.schema(), &plan, &excluded)?;
+        self.select(expr)
+    }
+
     /// Create a projection based on arbitrary expressions
     fn select(&self, expr_list: Vec<Expr>) -> Result<Arc<dyn DataFrame>> {
         let plan = LogicalPlanBuilder::from(self.to_logical_plan())
@@ -144,6 +161,13 @@ impl DataFrame for DataFrameImpl {
         Ok(Arc::new(DataFrameImpl::new(self.ctx_state.clone(), &plan)))
     }
 
+    fn unnest_column(&self, column: &str) -> Result<Arc<dyn DataFrame>> {
+        let plan = LogicalPlanBuilder::from(self.to_logical_plan())
+            .unnest_column(column)?
+            .build()?;
+        Ok(Arc::new(DataFrameImpl::new(self.ctx_state.clone(), &plan)))
+    }
+
     /// Convert to logical plan
     fn to_logical_plan(&self) -> LogicalPlan {
         self.plan.clone()
@@ -156,6 +180,93 @@ impl DataFrame for DataFrameImpl {
         Ok(collect(plan).await?)
     }
 
+    /// Summarize the numeric columns with a single aggregation that computes the number
+    /// of rows, and the count, mean, sum of squares, minimum and maximum of each column
+    async fn describe(&self) -> Result<RecordBatch> {
+        let fields = self
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| is_numeric(f.data_type()))
+            .collect::<Vec<_>>();
+        let mut aggr_expr = vec![count(lit(1u8))];
+        for field in &fields {
+            let column = Expr::Column(field.qualified_column());
+            let float = Expr::Cast {
+                expr: Box::new(column.clone()),
+                data_type: DataType::Float64,
+            };
+            aggr_expr.push(count(column.clone()));
+            aggr_expr.push(avg(column.clone()));
+            aggr_expr.push(sum(float.clone() * float));
+            aggr_expr.push(min(column.clone()));
+            aggr_expr.push(max(column));
+        }
+        let aggregates = self.aggregate(vec![], aggr_expr)?.collect().await?;
+        // an aggregation without grouping has a single row
+        let row = aggregates
+            .iter()
+            .find(|batch| batch.num_rows() > 0)
+            .map(|batch| {
+                batch
+                    .columns()
+                    .iter()
+                    .map(|array| -> Result<Option<f64>> {
+                        let array = cast(array, &DataType::Float64)?;
+                        let array =
+                            array.as_any().downcast_ref::<Float64Array>().unwrap();
+                        Ok(if array.is_null(0) {
+                            None
+                        } else {
+                            Some(array.value(0))
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let value = |i: usize| row.get(i).copied().flatten();
+
+        let rows = value(0).unwrap_or(0.0);
+        let mut schema_fields = vec![Field::new("statistic", DataType::Utf8, false)];
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(StringArray::from(vec![
+            "count",
+            "null_count",
+            "mean",
+            "stddev",
+            "min",
+            "max",
+        ]))];
+        for (i, field) in fields.iter().enumerate() {
+            let offset = 1 + i * 5;
+            let (n, mean, sum_squares) = (
+                value(offset).unwrap_or(0.0),
+                value(offset + 1),
+                value(offset + 2),
+            );
+            let stddev = match (mean, sum_squares) {
+                (Some(mean), Some(sum_squares)) if n > 1.0 => {
+                    let variance = (sum_squares - n * mean * mean) / (n - 1.0);
+                    Some(variance.max(0.0).sqrt())
+                }
+                _ => None,
+            };
+            schema_fields.push(Field::new(field.name(), DataType::Float64, true));
+            columns.push(Arc::new(Float64Array::from(vec![
+                Some(n),
+                Some(rows - n),
+                mean,
+                stddev,
+                value(offset + 3),
+                value(offset + 4),
+            ])));
+        }
+        Ok(RecordBatch::try_new(
+            Arc::new(Schema::new(schema_fields)),
+            columns,
+        )?)
+    }
+
     /// Convert the logical plan represented by this DataFrame into a physical plan and
     /// execute it, returning a stream over a single partition
     async fn execute_stream(&self) -> Result<SendableRecordBatchStream> {
@@ -178,6 +289,17 @@ impl DataFrame for DataFrameImpl {
         Ok(execute_stream_partitioned(plan).await?)
     }
 
+    /// Write the partitions of the physical plan to Parquet files
+    async fn write_parquet(
+        &self,
+        path: &str,
+        options: ParquetWriteOptions,
+    ) -> Result<()> {
+        let plan = self.create_physical_plan().await?;
+        collect(Arc::new(ParquetWriterExec::new(plan, path, options))).await?;
+        Ok(())
+    }
+
     /// Returns the schema from the logical plan
     fn schema(&self) -> &DFSchema {
         self.plan.schema()
@@ -208,11 +330,14 @@ mod tests {
     use std::vec;
 
     use super::*;
+    use crate::datasource::MemTable;
     use crate::logical_plan::*;
-    use crate::{assert_batches_sorted_eq, execution::context::ExecutionContext};
+    use crate::{
+        assert_batches_eq, assert_batches_sorted_eq, execution::context::ExecutionContext,
+    };
     use crate::{datasource::csv::CsvReadOptions, physical_plan::ColumnarValue};
     use crate::{physical_plan::functions::ScalarFunctionImplementation, test};
-    use arrow::datatypes::DataType;
+    use arrow::array::{Int32Array, Int32Builder, ListBuilder};
 
     #[test]
     fn select_columns() -> Result<()> {
@@ -230,6 +355,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn except_columns() -> Result<()> {
+        // build plan using Table API
+        let t = test_table()?;
+        let t2 = t.except_columns(&["c2", "aggregate_test_100.c3", "c5"])?;
+        let plan = t2.to_logical_plan();
+
+        // build query using SQL
+        let sql_plan = create_plan(
+            "SELECT c1, c4, c6, c7, c8, c9, c10, c11, c12, c13 FROM aggregate_test_100",
+        )?;
+
+        // the two plans should be identical
+        assert_same_plan(&plan, &sql_plan);
+
+        assert!(t.except_columns(&["c99"]).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn select_expr() -> Result<()> {
         // build plan using Table API
@@ -366,6 +511,118 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn unnest_column() -> Result<()> {
+        let mut builder = ListBuilder::new(Int32Builder::new(8));
+        builder.values().append_slice(&[10, 11])?;
+        builder.append(true)?;
+        builder.append(false)?;
+        builder.append(true)?;
+        builder.values().append_slice(&[30])?;
+        builder.append(true)?;
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new(
+                "tags",
+                DataType::List(Box::new(Field::new("item", DataType::Int32, true))),
+                true,
+            ),
+            Field::new("other", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4])),
+                Arc::new(builder.finish()),
+                Arc::new(StringArray::from(vec!["a", "b", "c", "d"])),
+            ],
+        )?;
+
+        let mut ctx = ExecutionContext::new();
+        let df =
+            ctx.read_table(Arc::new(MemTable::try_new(schema, vec![vec![batch]])?))?;
+        let df = df.unnest_column("tags")?;
+
+        let field = df.schema().field_with_unqualified_name("tags")?;
+        assert_eq!(field.data_type(), &DataType::Int32);
+        assert!(field.is_nullable());
+
+        let results = df.select_columns(&["id", "tags"])?.collect().await?;
+        assert_batches_sorted_eq!(
+            vec![
+                "+----+------+",
+                "| id | tags |",
+                "+----+------+",
+                "| 1  | 10   |",
+                "| 1  | 11   |",
+                "| 4  | 30   |",
+                "+----+------+",
+            ],
+            &results
+        );
+
+        assert!(df.unnest_column("id").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn describe() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Float64, true),
+            Field::new("c", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), Some(3), None])),
+                Arc::new(Float64Array::from(vec![Some(1.5), None, Some(2.5), None])),
+                Arc::new(StringArray::from(vec!["x", "y", "z", "w"])),
+            ],
+        )?;
+
+        let mut ctx = ExecutionContext::new();
+        let df =
+            ctx.read_table(Arc::new(MemTable::try_new(schema, vec![vec![batch]])?))?;
+        let summary = df.describe().await?;
+        assert_batches_eq!(
+            vec![
+                "+------------+-----+--------------------+",
+                "| statistic  | a   | b                  |",
+                "+------------+-----+--------------------+",
+                "| count      | 3   | 2                  |",
+                "| null_count | 1   | 2                  |",
+                "| mean       | 2   | 2                  |",
+                "| stddev     | 1   | 0.7071067811865476 |",
+                "| min        | 1   | 1.5                |",
+                "| max        | 3   | 2.5                |",
+                "+------------+-----+--------------------+",
+            ],
+            &[summary]
+        );
+
+        // the statistics of an empty DataFrame
+        let summary = df.filter(col("a").gt(lit(5)))?.describe().await?;
+        assert_batches_eq!(
+            vec![
+                "+------------+---+---+",
+                "| statistic  | a | b |",
+                "+------------+---+---+",
+                "| count      | 0 | 0 |",
+                "| null_count | 0 | 0 |",
+                "| mean       |   |   |",
+                "| stddev     |   |   |",
+                "| min        |   |   |",
+                "| max        |   |   |",
+                "+------------+---+---+",
+            ],
+            &[summary]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn sendable() {
         let df = test_table().unwrap();
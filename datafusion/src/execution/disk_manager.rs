@@ -0,0 +1,314 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Manages the temporary files that operators spill to when their intermediate
+//! state does not fit in memory
+
+use crate::error::{DataFusionError, Result};
+use std::fmt::{self, Debug, Formatter};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tempfile::{NamedTempFile, TempDir};
+
+/// Default limit of the bytes that can be held in spill files at any time
+pub const DEFAULT_MAX_TEMP_DIRECTORY_SIZE: u64 = 100 * 1024 * 1024 * 1024;
+
+/// Configuration for the [`DiskManager`]
+#[derive(Debug, Clone)]
+pub struct DiskManagerConfig {
+    /// Directories in which the spill directories are created. The temporary
+    /// directory of the operating system is used when empty.
+    pub local_dirs: Vec<PathBuf>,
+    /// Maximum number of bytes that can be held in spill files at any time
+    pub max_temp_directory_size: u64,
+}
+
+impl Default for DiskManagerConfig {
+    fn default() -> Self {
+        Self {
+            local_dirs: vec![],
+            max_temp_directory_size: DEFAULT_MAX_TEMP_DIRECTORY_SIZE,
+        }
+    }
+}
+
+impl DiskManagerConfig {
+    /// Create a disk manager config with default settings
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Customize the directories in which the spill directories are created
+    pub fn with_local_dirs(mut self, local_dirs: Vec<PathBuf>) -> Self {
+        self.local_dirs = local_dirs;
+        self
+    }
+
+    /// Customize the maximum number of bytes that can be held in spill files
+    pub fn with_max_temp_directory_size(mut self, max_temp_directory_size: u64) -> Self {
+        self.max_temp_directory_size = max_temp_directory_size;
+        self
+    }
+}
+
+/// Hands out the temporary files that operators spill to, and enforces a quota on
+/// the bytes held by them.
+///
+/// A spill directory is created in each of the configured directories when the
+/// first file is requested. Spill files are deleted when they are dropped, which
+/// happens when the query that created them completes, and the spill directories
+/// are removed when the disk manager is dropped.
+pub struct DiskManager {
+    config: DiskManagerConfig,
+    /// Spill directories, created on first use
+    spill_dirs: Mutex<Option<Vec<TempDir>>>,
+    /// Index of the spill directory in which the next file is created
+    next_dir: AtomicUsize,
+    /// Bytes currently held in spill files
+    used: Arc<AtomicU64>,
+}
+
+impl DiskManager {
+    /// Create a disk manager from the given configuration
+    pub fn new(config: DiskManagerConfig) -> Self {
+        Self {
+            config,
+            spill_dirs: Mutex::new(None),
+            next_dir: AtomicUsize::new(0),
+            used: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Create a new spill file. `request_description` identifies the operator
+    /// requesting the file in errors.
+    pub fn create_tmp_file(&self, request_description: &str) -> Result<SpillFile> {
+        let mut spill_dirs = self.spill_dirs.lock().unwrap();
+        if spill_dirs.is_none() {
+            *spill_dirs = Some(create_spill_dirs(&self.config.local_dirs)?);
+        }
+        let spill_dirs = spill_dirs.as_ref().unwrap();
+        let dir =
+            &spill_dirs[self.next_dir.fetch_add(1, Ordering::Relaxed) % spill_dirs.len()];
+
+        let file = tempfile::Builder::new()
+            .prefix("spill-")
+            .tempfile_in(dir.path())
+            .map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "Failed to create spill file for {}: {}",
+                    request_description, e
+                ))
+            })?;
+        Ok(SpillFile {
+            file,
+            description: request_description.to_owned(),
+            bytes_written: 0,
+            used: self.used.clone(),
+            limit: self.config.max_temp_directory_size,
+        })
+    }
+
+    /// Bytes currently held in spill files
+    pub fn used_disk_space(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Maximum number of bytes that can be held in spill files
+    pub fn max_temp_directory_size(&self) -> u64 {
+        self.config.max_temp_directory_size
+    }
+}
+
+impl Debug for DiskManager {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskManager")
+            .field("config", &self.config)
+            .field("used", &self.used_disk_space())
+            .finish()
+    }
+}
+
+fn create_spill_dirs(local_dirs: &[PathBuf]) -> Result<Vec<TempDir>> {
+    let local_dirs = if local_dirs.is_empty() {
+        vec![std::env::temp_dir()]
+    } else {
+        local_dirs.to_vec()
+    };
+    local_dirs
+        .iter()
+        .map(|dir| {
+            tempfile::Builder::new()
+                .prefix("datafusion-")
+                .tempdir_in(dir)
+                .map_err(|e| {
+                    DataFusionError::Execution(format!(
+                        "Failed to create spill directory in {:?}: {}",
+                        dir, e
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// A temporary file handed out by the [`DiskManager`]. The bytes written to the
+/// file count against the quota of the disk manager until the file is dropped, at
+/// which point it is deleted.
+pub struct SpillFile {
+    file: NamedTempFile,
+    description: String,
+    bytes_written: u64,
+    used: Arc<AtomicU64>,
+    limit: u64,
+}
+
+impl SpillFile {
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Open the file again, e.g. to read back the spilled data
+    pub fn reopen(&self) -> Result<File> {
+        Ok(self.file.reopen()?)
+    }
+
+    /// Number of bytes written to the file
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+}
+
+impl Write for SpillFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        let used = self.used.fetch_add(len, Ordering::SeqCst) + len;
+        if used > self.limit {
+            self.used.fetch_sub(len, Ordering::SeqCst);
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                DataFusionError::ResourcesExhausted(format!(
+                    "Spilling {} bytes for {} would exceed the limit of {} bytes for temporary files",
+                    len, self.description, self.limit
+                )),
+            ));
+        }
+        match self.file.write(buf) {
+            Ok(written) => {
+                self.used.fetch_sub(len - written as u64, Ordering::SeqCst);
+                self.bytes_written += written as u64;
+                Ok(written)
+            }
+            Err(e) => {
+                self.used.fetch_sub(len, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        self.used.fetch_sub(self.bytes_written, Ordering::SeqCst);
+    }
+}
+
+impl Debug for SpillFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpillFile")
+            .field("path", &self.path())
+            .field("description", &self.description)
+            .field("bytes_written", &self.bytes_written)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn spill_files_are_created_in_local_dirs() -> Result<()> {
+        let local_dir1 = TempDir::new()?;
+        let local_dir2 = TempDir::new()?;
+        let config = DiskManagerConfig::new().with_local_dirs(vec![
+            local_dir1.path().to_owned(),
+            local_dir2.path().to_owned(),
+        ]);
+        let disk_manager = DiskManager::new(config);
+
+        let file1 = disk_manager.create_tmp_file("test")?;
+        let file2 = disk_manager.create_tmp_file("test")?;
+        assert!(file1.path().starts_with(local_dir1.path()));
+        assert!(file2.path().starts_with(local_dir2.path()));
+        Ok(())
+    }
+
+    #[test]
+    fn spill_files_are_deleted_on_drop() -> Result<()> {
+        let local_dir = TempDir::new()?;
+        let config =
+            DiskManagerConfig::new().with_local_dirs(vec![local_dir.path().to_owned()]);
+        let disk_manager = DiskManager::new(config);
+
+        let mut file = disk_manager.create_tmp_file("test")?;
+        file.write_all(b"spilled")?;
+        file.flush()?;
+        let mut contents = String::new();
+        file.reopen()?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "spilled");
+        assert_eq!(disk_manager.used_disk_space(), 7);
+
+        let path = file.path().to_owned();
+        let spill_dir = path.parent().unwrap().to_owned();
+        drop(file);
+        assert!(!path.exists());
+        assert_eq!(disk_manager.used_disk_space(), 0);
+
+        drop(disk_manager);
+        assert!(!spill_dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn quota_is_enforced() -> Result<()> {
+        let local_dir = TempDir::new()?;
+        let config = DiskManagerConfig::new()
+            .with_local_dirs(vec![local_dir.path().to_owned()])
+            .with_max_temp_directory_size(10);
+        let disk_manager = DiskManager::new(config);
+
+        let mut file1 = disk_manager.create_tmp_file("test")?;
+        file1.write_all(b"12345678")?;
+        let mut file2 = disk_manager.create_tmp_file("test")?;
+        let err = file2.write_all(b"12345678").unwrap_err();
+        assert!(err.to_string().contains("limit of 10 bytes"));
+
+        // the quota is released when a file is dropped
+        drop(file1);
+        file2.write_all(b"12345678")?;
+        assert_eq!(disk_manager.used_disk_space(), 8);
+        Ok(())
+    }
+}
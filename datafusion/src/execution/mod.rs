@@ -18,4 +18,7 @@
 //! DataFusion query execution
 
 pub mod context;
+pub mod cpu_pool;
 pub mod dataframe_impl;
+pub mod disk_manager;
+pub mod runtime_env;
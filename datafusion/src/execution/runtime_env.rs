@@ -0,0 +1,93 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Execution runtime environment that holds the resources shared by the queries
+//! of an [`ExecutionContext`](super::context::ExecutionContext)
+
+use crate::execution::disk_manager::{DiskManager, DiskManagerConfig};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Resources shared by the queries of an execution context
+#[derive(Debug)]
+pub struct RuntimeEnv {
+    /// Hands out the temporary files that operators spill to
+    pub disk_manager: Arc<DiskManager>,
+    /// Bytes of input that a sort buffers in memory before it spills a sorted run
+    /// to disk, or None if sorts do not spill
+    pub sort_spill_threshold: Option<usize>,
+}
+
+impl RuntimeEnv {
+    /// Create a runtime environment from the given configuration
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self {
+            disk_manager: Arc::new(DiskManager::new(config.disk_manager)),
+            sort_spill_threshold: config.sort_spill_threshold,
+        }
+    }
+}
+
+impl Default for RuntimeEnv {
+    fn default() -> Self {
+        Self::new(RuntimeConfig::new())
+    }
+}
+
+/// Configuration for the [`RuntimeEnv`]
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeConfig {
+    /// Configuration for the disk manager
+    pub disk_manager: DiskManagerConfig,
+    /// Bytes of input that a sort buffers in memory before it spills a sorted run
+    /// to disk. Sorts do not spill if not set.
+    pub sort_spill_threshold: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// Create a runtime config with default settings
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Customize the disk manager configuration
+    pub fn with_disk_manager(mut self, disk_manager: DiskManagerConfig) -> Self {
+        self.disk_manager = disk_manager;
+        self
+    }
+
+    /// Customize the directories in which spill files are created
+    pub fn with_local_dirs(mut self, local_dirs: Vec<PathBuf>) -> Self {
+        self.disk_manager = self.disk_manager.with_local_dirs(local_dirs);
+        self
+    }
+
+    /// Customize the maximum number of bytes that can be held in spill files
+    pub fn with_max_temp_directory_size(mut self, max_temp_directory_size: u64) -> Self {
+        self.disk_manager = self
+            .disk_manager
+            .with_max_temp_directory_size(max_temp_directory_size);
+        self
+    }
+
+    /// Customize the bytes of input that a sort buffers in memory before it spills a
+    /// sorted run to disk
+    pub fn with_sort_spill_threshold(mut self, sort_spill_threshold: usize) -> Self {
+        self.sort_spill_threshold = Some(sort_spill_threshold);
+        self
+    }
+}
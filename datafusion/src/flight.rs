@@ -0,0 +1,394 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Arrow Flight service that runs SQL queries against an [`ExecutionContext`], so that
+//! the clients of any language with a Flight library can query an embedded DataFusion
+//! over the network.
+//!
+//! * `DoGet` runs the SQL query of the UTF-8 ticket and streams its results
+//! * `GetSchema` returns the schema of the SQL query of a command descriptor without
+//!   running it, or the schema of the table named by a path descriptor
+//!
+//! Only queries are accepted: statements that create tables or functions, or that
+//! write files, such as `CREATE EXTERNAL TABLE` and `COPY ... TO`, are rejected, so
+//! that clients cannot change the context or the files of the server.
+//!
+//! ```no_run
+//! # async fn serve() -> Result<(), Box<dyn std::error::Error>> {
+//! use arrow_flight::flight_service_server::FlightServiceServer;
+//! use datafusion::flight::ContextFlightService;
+//! use datafusion::prelude::*;
+//!
+//! let mut ctx = ExecutionContext::new();
+//! ctx.register_csv("example", "tests/example.csv", CsvReadOptions::new())?;
+//!
+//! let service = FlightServiceServer::new(ContextFlightService::new(ctx));
+//! tonic::transport::Server::builder()
+//!     .add_service(service)
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::pin::Pin;
+
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::flight_service_server::FlightService;
+use arrow_flight::utils::flight_data_from_arrow_batch;
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use futures::{Stream, StreamExt};
+use log::debug;
+use sqlparser::ast::Statement as SQLStatement;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::arrow::datatypes::Schema;
+use crate::arrow::ipc::writer::IpcWriteOptions;
+use crate::catalog::TableReference;
+use crate::error::DataFusionError;
+use crate::execution::context::ExecutionContext;
+use crate::physical_plan::SendableRecordBatchStream;
+use crate::sql::parser::{DFParser, Statement as DFStatement};
+
+type BoxedFlightStream<T> =
+    Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + Sync + 'static>>;
+
+/// Flight service that queries the tables of an [`ExecutionContext`] with SQL
+#[derive(Clone)]
+pub struct ContextFlightService {
+    ctx: ExecutionContext,
+}
+
+impl ContextFlightService {
+    /// Create a service that runs the queries it receives against `ctx`. The queries
+    /// share the tables and functions of `ctx`, including those registered later.
+    pub fn new(ctx: ExecutionContext) -> Self {
+        Self { ctx }
+    }
+
+    /// Returns the schema of the result of the SQL `query`, without running it
+    fn query_schema(&self, query: &str) -> Result<Schema, Status> {
+        check_query(query)?;
+        let mut ctx = self.ctx.clone();
+        let df = ctx.sql(query).map_err(to_invalid_argument)?;
+        Ok(df.schema().clone().into())
+    }
+
+    /// Returns the schema of the table at `path`, which is its name optionally preceded
+    /// by the names of its schema and catalog
+    fn table_schema(&self, path: &[String]) -> Result<Schema, Status> {
+        let table_ref = match path {
+            [table] => TableReference::Bare { table },
+            [schema, table] => TableReference::Partial { schema, table },
+            [catalog, schema, table] => TableReference::Full {
+                catalog,
+                schema,
+                table,
+            },
+            _ => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid table path {:?}",
+                    path
+                )))
+            }
+        };
+        let df = self.ctx.table(table_ref).map_err(to_invalid_argument)?;
+        Ok(df.schema().clone().into())
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for ContextFlightService {
+    type HandshakeStream = BoxedFlightStream<HandshakeResponse>;
+    type ListFlightsStream = BoxedFlightStream<FlightInfo>;
+    type DoGetStream = BoxedFlightStream<FlightData>;
+    type DoPutStream = BoxedFlightStream<PutResult>;
+    type DoActionStream = BoxedFlightStream<arrow_flight::Result>;
+    type ListActionsStream = BoxedFlightStream<ActionType>;
+    type DoExchangeStream = BoxedFlightStream<FlightData>;
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let descriptor = request.into_inner();
+        let schema = match DescriptorType::from_i32(descriptor.r#type) {
+            Some(DescriptorType::Cmd) => self.query_schema(&utf8(&descriptor.cmd)?)?,
+            Some(DescriptorType::Path) => self.table_schema(&descriptor.path)?,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "The descriptor must be a SQL command or a table path",
+                ))
+            }
+        };
+        let options = IpcWriteOptions::default();
+        Ok(Response::new(SchemaAsIpc::new(&schema, &options).into()))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let query = utf8(&request.into_inner().ticket)?;
+        debug!("Running query {}", query);
+        check_query(&query)?;
+        let mut ctx = self.ctx.clone();
+        let df = ctx.sql(&query).map_err(to_invalid_argument)?;
+        let stream = df.execute_stream().await.map_err(to_internal)?;
+
+        // the stream of the results is not Sync, so the batches are sent to the
+        // response through a channel
+        let (tx, rx) = channel(2);
+        tokio::spawn(async move {
+            if let Err(e) = stream_flight_data(stream, &tx).await {
+                // the client may have disconnected already
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::DoGetStream
+        ))
+    }
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("get_flight_info"))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("do_put"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange"))
+    }
+}
+
+/// Sends the schema of `stream` and then its batches, each preceded by its dictionaries
+async fn stream_flight_data(
+    mut stream: SendableRecordBatchStream,
+    tx: &Sender<Result<FlightData, Status>>,
+) -> Result<(), Status> {
+    let options = IpcWriteOptions::default();
+    let schema = stream.schema();
+    send(tx, SchemaAsIpc::new(schema.as_ref(), &options).into()).await?;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.map_err(|e| to_internal(e.into()))?;
+        let (dictionaries, batch) = flight_data_from_arrow_batch(&batch, &options);
+        for dictionary in dictionaries {
+            send(tx, dictionary).await?;
+        }
+        send(tx, batch).await?;
+    }
+    Ok(())
+}
+
+async fn send(
+    tx: &Sender<Result<FlightData, Status>>,
+    data: FlightData,
+) -> Result<(), Status> {
+    tx.send(Ok(data))
+        .await
+        .map_err(|_| Status::cancelled("The client disconnected"))
+}
+
+/// Checks that `sql` is a single query, since the other statements would change the
+/// context that is shared by all the clients, or write files
+fn check_query(sql: &str) -> Result<(), Status> {
+    let statements = DFParser::parse_sql(sql)
+        .map_err(|e| to_invalid_argument(DataFusionError::from(e)))?;
+    match statements.as_slice() {
+        [DFStatement::Statement(SQLStatement::Query(_))] => Ok(()),
+        [_] => Err(Status::permission_denied("Only queries can be run")),
+        _ => Err(Status::invalid_argument("Expected a single query")),
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, Status> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))
+}
+
+fn to_invalid_argument(e: DataFusionError) -> Status {
+    Status::invalid_argument(e.to_string())
+}
+
+fn to_internal(e: DataFusionError) -> Status {
+    Status::internal(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    use crate::arrow::datatypes::DataType;
+    use crate::prelude::CsvReadOptions;
+    use crate::test_util::arrow_test_data;
+
+    fn service() -> crate::error::Result<ContextFlightService> {
+        let mut ctx = ExecutionContext::new();
+        ctx.register_csv(
+            "aggregate_test_100",
+            &format!("{}/csv/aggregate_test_100.csv", arrow_test_data()),
+            CsvReadOptions::new(),
+        )?;
+        Ok(ContextFlightService::new(ctx))
+    }
+
+    #[tokio::test]
+    async fn get_schema() -> crate::error::Result<()> {
+        let service = service()?;
+        let descriptor = FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: b"SELECT c1, c2 + 1 AS c FROM aggregate_test_100".to_vec(),
+            path: vec![],
+        };
+        let result = service
+            .get_schema(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner();
+        let schema = Schema::try_from(&result)?;
+        assert_eq!(vec!["c1", "c"], field_names(&schema));
+
+        let descriptor = FlightDescriptor {
+            r#type: DescriptorType::Path as i32,
+            cmd: vec![],
+            path: vec!["aggregate_test_100".to_owned()],
+        };
+        let result = service
+            .get_schema(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner();
+        let schema = Schema::try_from(&result)?;
+        assert_eq!(13, schema.fields().len());
+        assert_eq!(&DataType::Utf8, schema.field(0).data_type());
+
+        let descriptor = FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: b"SELECT x FROM aggregate_test_100".to_vec(),
+            path: vec![],
+        };
+        let status = service
+            .get_schema(Request::new(descriptor))
+            .await
+            .unwrap_err();
+        assert_eq!(tonic::Code::InvalidArgument, status.code());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn do_get() -> crate::error::Result<()> {
+        let service = service()?;
+        let ticket = Ticket {
+            ticket: b"SELECT c1 FROM aggregate_test_100 LIMIT 5".to_vec(),
+        };
+        let messages = service
+            .do_get(Request::new(ticket))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect::<Vec<_>>()
+            .await;
+        // the schema and at least one batch
+        assert!(messages.len() >= 2);
+        assert!(messages.iter().all(|message| message.is_ok()));
+
+        let ticket = Ticket {
+            ticket: b"SELECT FROM".to_vec(),
+        };
+        let status = service.do_get(Request::new(ticket)).await.err().unwrap();
+        assert_eq!(tonic::Code::InvalidArgument, status.code());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject_statements() -> crate::error::Result<()> {
+        let service = service()?;
+        for sql in &[
+            "CREATE EXTERNAL TABLE t STORED AS CSV LOCATION '/etc/passwd'",
+            "COPY aggregate_test_100 TO '/tmp/out.csv'",
+            "SELECT 1; SELECT 2",
+        ] {
+            let ticket = Ticket {
+                ticket: sql.as_bytes().to_vec(),
+            };
+            assert!(service.do_get(Request::new(ticket)).await.is_err());
+
+            let descriptor = FlightDescriptor {
+                r#type: DescriptorType::Cmd as i32,
+                cmd: sql.as_bytes().to_vec(),
+                path: vec![],
+            };
+            assert!(service.get_schema(Request::new(descriptor)).await.is_err());
+        }
+        // nothing was registered by the rejected statements
+        assert!(service.ctx.table("t").is_err());
+        Ok(())
+    }
+
+    fn field_names(schema: &Schema) -> Vec<&str> {
+        schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect()
+    }
+}
@@ -23,7 +23,7 @@ use std::{
 };
 
 use arrow::{
-    datatypes::{Schema, SchemaRef},
+    datatypes::{DataType, Schema, SchemaRef},
     record_batch::RecordBatch,
 };
 
@@ -35,7 +35,10 @@ use crate::{
 };
 
 use super::dfschema::ToDFSchema;
-use super::{exprlist_to_fields, Expr, JoinConstraint, JoinType, LogicalPlan, PlanType};
+use super::{
+    exprlist_to_fields, ExplainFormat, Expr, JoinConstraint, JoinType, LogicalPlan,
+    PlanType,
+};
 use crate::logical_plan::{
     columnize_expr, normalize_col, normalize_cols, Column, DFField, DFSchema,
     DFSchemaRef, Partitioning,
@@ -241,6 +244,9 @@ impl LogicalPlanBuilder {
     /// Apply a filter
     pub fn filter(&self, expr: Expr) -> Result<Self> {
         let expr = normalize_col(expr, &self.plan)?;
+        // reject predicates that can not be evaluated, such as comparisons of types
+        // without a common type, when the plan is built rather than when it runs
+        expr.get_type(self.plan.schema())?;
         Ok(Self::from(LogicalPlan::Filter {
             predicate: expr,
             input: Arc::new(self.plan.clone()),
@@ -249,8 +255,14 @@ impl LogicalPlanBuilder {
 
     /// Apply a limit
     pub fn limit(&self, n: usize) -> Result<Self> {
+        self.limit_with_offset(n, 0)
+    }
+
+    /// Apply a limit of `n` rows after skipping the first `offset` rows
+    pub fn limit_with_offset(&self, n: usize, offset: usize) -> Result<Self> {
         Ok(Self::from(LogicalPlan::Limit {
             n,
+            offset,
             input: Arc::new(self.plan.clone()),
         }))
     }
@@ -274,6 +286,18 @@ impl LogicalPlanBuilder {
         right: &LogicalPlan,
         join_type: JoinType,
         join_keys: (Vec<impl Into<Column>>, Vec<impl Into<Column>>),
+    ) -> Result<Self> {
+        self.join_detailed(right, join_type, join_keys, false)
+    }
+
+    /// Apply a join with on constraint, where `null_equals_null` makes NULL keys
+    /// match each other, as the `IS NOT DISTINCT FROM` operator does
+    pub fn join_detailed(
+        &self,
+        right: &LogicalPlan,
+        join_type: JoinType,
+        join_keys: (Vec<impl Into<Column>>, Vec<impl Into<Column>>),
+        null_equals_null: bool,
     ) -> Result<Self> {
         if join_keys.0.len() != join_keys.1.len() {
             return Err(DataFusionError::Plan(
@@ -361,6 +385,7 @@ impl LogicalPlanBuilder {
             on,
             join_type,
             join_constraint: JoinConstraint::On,
+            null_equals_null,
             schema: DFSchemaRef::new(join_schema),
         }))
     }
@@ -392,6 +417,7 @@ impl LogicalPlanBuilder {
             on,
             join_type,
             join_constraint: JoinConstraint::Using,
+            null_equals_null: false,
             schema: DFSchemaRef::new(join_schema),
         }))
     }
@@ -414,6 +440,42 @@ impl LogicalPlanBuilder {
         }))
     }
 
+    /// Unnest the given list column, producing one output row per list element
+    /// with the values of all other columns repeated.
+    pub fn unnest_column(&self, column: impl Into<Column>) -> Result<Self> {
+        let column = column.into().normalize(&self.plan)?;
+        let schema = self.plan.schema();
+        let unnest_index = schema.index_of_column(&column)?;
+        let fields = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                if i != unnest_index {
+                    return Ok(f.clone());
+                }
+                match f.data_type() {
+                    DataType::List(item) | DataType::LargeList(item) => Ok(DFField::new(
+                        f.qualifier().map(|q| q.as_str()),
+                        f.name(),
+                        item.data_type().clone(),
+                        true,
+                    )),
+                    other => Err(DataFusionError::Plan(format!(
+                        "Cannot unnest column {} of type {:?}, expected a list",
+                        column, other
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::from(LogicalPlan::Unnest {
+            input: Arc::new(self.plan.clone()),
+            column,
+            schema: DFSchemaRef::new(DFSchema::new(fields)?),
+        }))
+    }
+
     /// Apply a window functions to extend the schema
     pub fn window(&self, window_expr: impl IntoIterator<Item = Expr>) -> Result<Self> {
         let window_expr = window_expr.into_iter().collect::<Vec<Expr>>();
@@ -456,13 +518,25 @@ impl LogicalPlanBuilder {
 
     /// Create an expression to represent the explanation of the plan
     pub fn explain(&self, verbose: bool) -> Result<Self> {
-        let stringified_plans =
-            vec![self.plan.to_stringified(PlanType::InitialLogicalPlan)];
+        self.explain_with_format(verbose, ExplainFormat::Text)
+    }
+
+    /// Create an expression to represent the explanation of the plan, in which the
+    /// plans are displayed in `format`
+    pub fn explain_with_format(
+        &self,
+        verbose: bool,
+        format: ExplainFormat,
+    ) -> Result<Self> {
+        let stringified_plans = vec![self
+            .plan
+            .to_stringified(PlanType::InitialLogicalPlan, format)];
 
         let schema = LogicalPlan::explain_schema();
 
         Ok(Self::from(LogicalPlan::Explain {
             verbose,
+            format,
             plan: Arc::new(self.plan.clone()),
             stringified_plans,
             schema: schema.to_dfschema_ref()?,
@@ -489,7 +563,7 @@ pub fn build_join_schema(
             // left then right
             left_fields.chain(right_fields).cloned().collect()
         }
-        JoinType::Semi | JoinType::Anti => {
+        JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => {
             // Only use the left side for the schema
             left.fields().clone()
         }
@@ -569,40 +643,75 @@ pub(crate) fn expand_wildcard(
     schema: &DFSchema,
     plan: &LogicalPlan,
 ) -> Result<Vec<Expr>> {
-    let using_columns = plan.using_columns()?;
-    let columns_to_skip = using_columns
+    Ok(wildcard_columns(schema, plan)?
         .into_iter()
-        // For each USING JOIN condition, only expand to one column in projection
-        .map(|cols| {
-            let mut cols = cols.into_iter().collect::<Vec<_>>();
-            // sort join columns to make sure we consistently keep the same
-            // qualified column
-            cols.sort();
-            cols.into_iter().skip(1)
+        .map(|(_, expr)| expr)
+        .collect())
+}
+
+/// Resolves an `Expr::Wildcard` to a collection of `Expr::Column`'s, leaving out the
+/// `excluded` columns. Returns an error if any of them is not part of the schema.
+pub(crate) fn expand_wildcard_except(
+    schema: &DFSchema,
+    plan: &LogicalPlan,
+    excluded: &[Column],
+) -> Result<Vec<Expr>> {
+    let excluded = excluded
+        .iter()
+        .map(|c| {
+            let c = c.clone().normalize(plan)?;
+            Ok(schema.field_from_column(&c)?.qualified_column())
         })
-        .flatten()
-        .collect::<HashSet<_>>();
+        .collect::<Result<HashSet<_>>>()?;
 
-    if columns_to_skip.is_empty() {
-        Ok(schema
-            .fields()
-            .iter()
-            .map(|f| Expr::Column(f.qualified_column()))
-            .collect::<Vec<Expr>>())
-    } else {
-        Ok(schema
-            .fields()
-            .iter()
-            .filter_map(|f| {
-                let col = f.qualified_column();
-                if !columns_to_skip.contains(&col) {
-                    Some(Expr::Column(col))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<Expr>>())
+    Ok(wildcard_columns(schema, plan)?
+        .into_iter()
+        .filter(|(column, _)| !excluded.contains(column))
+        .map(|(_, expr)| expr)
+        .collect())
+}
+
+/// Returns the expressions that a wildcard resolves to, along with the column that
+/// unqualified references to each of them resolve to. Each pair of USING JOIN columns
+/// only expands to one expression, at the position of the left column.
+fn wildcard_columns(
+    schema: &DFSchema,
+    plan: &LogicalPlan,
+) -> Result<Vec<(Column, Expr)>> {
+    let mut join_outputs = HashMap::new();
+    let mut columns_to_skip = HashSet::new();
+    for using_join in plan.using_joins()? {
+        for (left, right) in &using_join.on {
+            if schema.field_from_column(left).is_err() {
+                continue;
+            }
+            join_outputs.insert(
+                left.clone(),
+                (
+                    using_join.output_column(left, right).clone(),
+                    using_join.output_expr(left, right),
+                ),
+            );
+            columns_to_skip.insert(right.clone());
+        }
     }
+
+    Ok(schema
+        .fields()
+        .iter()
+        .filter_map(|f| {
+            let col = f.qualified_column();
+            if columns_to_skip.contains(&col) {
+                None
+            } else {
+                Some(
+                    join_outputs
+                        .remove(&col)
+                        .unwrap_or_else(|| (col.clone(), Expr::Column(col))),
+                )
+            }
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -118,32 +118,116 @@ pub fn display_schema(schema: &Schema) -> impl fmt::Display + '_ {
     Wrapper(schema)
 }
 
+/// Formats plans as a JSON tree with an object per node. For example:
+///
+/// {"node":"Projection: #id","schema":"[id:Int32]","inputs":[
+///   {"node":"TableScan: employee.csv projection=Some([0])","schema":"[id:Int32]","filters":[],"inputs":[]}]}
+pub struct JsonVisitor<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    /// Whether the node being visited at each depth already has an input written
+    has_inputs: Vec<bool>,
+}
+
+impl<'a, 'b> JsonVisitor<'a, 'b> {
+    /// Create a visitor that will write a LogicalPlan formatted as JSON to f
+    pub fn new(f: &'a mut fmt::Formatter<'b>) -> Self {
+        Self {
+            f,
+            has_inputs: vec![],
+        }
+    }
+}
+
+impl<'a, 'b> PlanVisitor for JsonVisitor<'a, 'b> {
+    type Error = fmt::Error;
+
+    fn pre_visit(&mut self, plan: &LogicalPlan) -> std::result::Result<bool, fmt::Error> {
+        if let Some(has_input) = self.has_inputs.last_mut() {
+            if *has_input {
+                write!(self.f, ",")?;
+            }
+            *has_input = true;
+        }
+        write!(
+            self.f,
+            "{{\"node\":{},\"schema\":{}",
+            json_string(&plan.display().to_string()),
+            json_string(
+                &display_schema(&plan.schema().as_ref().to_owned().into()).to_string()
+            )
+        )?;
+        if let LogicalPlan::TableScan { filters, .. } = plan {
+            let filters: Vec<String> = filters
+                .iter()
+                .map(|filter| json_string(&format!("{:?}", filter)))
+                .collect();
+            write!(self.f, ",\"filters\":[{}]", filters.join(","))?;
+        }
+        write!(self.f, ",\"inputs\":[")?;
+
+        self.has_inputs.push(false);
+        Ok(true)
+    }
+
+    fn post_visit(
+        &mut self,
+        _plan: &LogicalPlan,
+    ) -> std::result::Result<bool, fmt::Error> {
+        self.has_inputs.pop();
+        write!(self.f, "]}}")?;
+        Ok(true)
+    }
+}
+
+/// Quotes `s` as a JSON string
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 /// Logic related to creating DOT language graphs.
 #[derive(Default)]
-struct GraphvizBuilder {
+pub(crate) struct GraphvizBuilder {
     id_gen: usize,
 }
 
 impl GraphvizBuilder {
-    fn next_id(&mut self) -> usize {
+    pub(crate) fn next_id(&mut self) -> usize {
         self.id_gen += 1;
         self.id_gen
     }
 
     // write out the start of the subgraph cluster
-    fn start_cluster(&mut self, f: &mut fmt::Formatter, title: &str) -> fmt::Result {
+    pub(crate) fn start_cluster(
+        &mut self,
+        f: &mut fmt::Formatter,
+        title: &str,
+    ) -> fmt::Result {
         writeln!(f, "  subgraph cluster_{}", self.next_id())?;
         writeln!(f, "  {{")?;
         writeln!(f, "    graph[label={}]", Self::quoted(title))
     }
 
     // write out the end of the subgraph cluster
-    fn end_cluster(&mut self, f: &mut fmt::Formatter) -> fmt::Result {
+    pub(crate) fn end_cluster(&mut self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "  }}")
     }
 
     /// makes a quoted string suitable for inclusion in a graphviz chart
-    fn quoted(label: &str) -> String {
+    pub(crate) fn quoted(label: &str) -> String {
         let label = label.replace('"', "_");
         format!("\"{}\"", label)
     }
@@ -248,6 +332,15 @@ mod tests {
         assert_eq!("[]", format!("{}", display_schema(&schema)));
     }
 
+    #[test]
+    fn test_json_string() {
+        assert_eq!(r#""a""#, json_string("a"));
+        assert_eq!(
+            r#""say \"hi\"\n\\ \u0001""#,
+            json_string("say \"hi\"\n\\ \u{1}")
+        );
+    }
+
     #[test]
     fn test_display_schema() {
         let schema = Schema::new(vec![
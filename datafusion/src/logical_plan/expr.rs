@@ -20,7 +20,8 @@
 
 pub use super::Operator;
 use crate::error::{DataFusionError, Result};
-use crate::logical_plan::{window_frames, DFField, DFSchema, LogicalPlan};
+use crate::logical_plan::{window_frames, DFField, DFSchema, LogicalPlan, UsingJoin};
+use crate::physical_plan::udwf::{PartitionEvaluatorFunctionImplementation, WindowUDF};
 use crate::physical_plan::{
     aggregates, expressions::binary_operator_data_type, functions, udf::ScalarUDF,
     window_functions,
@@ -93,15 +94,15 @@ impl Column {
     /// column named `foo` in a relation named `t` found in `schemas`
     pub fn normalize(self, plan: &LogicalPlan) -> Result<Self> {
         let schemas = plan.all_schemas();
-        let using_columns = plan.using_columns()?;
-        self.normalize_with_schemas(&schemas, &using_columns)
+        let using_joins = plan.using_joins()?;
+        self.normalize_with_schemas(&schemas, &using_joins)
     }
 
     // Internal implementation of normalize
     fn normalize_with_schemas(
         self,
         schemas: &[&Arc<DFSchema>],
-        using_columns: &[HashSet<Column>],
+        using_joins: &[UsingJoin],
     ) -> Result<Self> {
         if self.relation.is_some() {
             return Ok(self);
@@ -125,17 +126,20 @@ impl Column {
                     // ```
                     //
                     // In this case, both `t1.id` and `t2.id` will match unqualified column `id`.
-                    // We will use the relation from the first matched field to normalize self.
-
-                    // Compare matched fields with one USING JOIN clause at a time
-                    for using_col in using_columns {
-                        let all_matched = fields
-                            .iter()
-                            .all(|f| using_col.contains(&f.qualified_column()));
-                        // All matched fields belong to the same using column set, in orther words
-                        // the same join clause. We simply pick the qualifer from the first match.
-                        if all_matched {
-                            return Ok(fields[0].qualified_column());
+                    // We will use the join column that the join preserves to normalize self.
+
+                    // Compare matched fields with one pair of USING JOIN columns at a time
+                    for using_join in using_joins {
+                        for (left, right) in &using_join.on {
+                            let all_matched = fields.iter().all(|f| {
+                                let column = f.qualified_column();
+                                column == *left || column == *right
+                            });
+                            // All matched fields are the join columns of the same pair, in
+                            // other words the same column of the join output
+                            if all_matched {
+                                return Ok(using_join.output_column(left, right).clone());
+                            }
                         }
                     }
                 }
@@ -326,6 +330,9 @@ pub enum Expr {
         args: Vec<Expr>,
         /// Whether this is a DISTINCT aggregation or not
         distinct: bool,
+        /// The `FILTER` clause of the aggregation, which restricts the aggregated rows
+        /// to those for which it is true
+        filter: Option<Box<Expr>>,
     },
     /// Represents the call of a window function with arguments.
     WindowFunction {
@@ -339,6 +346,9 @@ pub enum Expr {
         order_by: Vec<Expr>,
         /// Window frame
         window_frame: Option<window_frames::WindowFrame>,
+        /// Whether the aggregate function only aggregates the distinct values of its
+        /// arguments in the frame
+        distinct: bool,
     },
     /// aggregate function
     AggregateUDF {
@@ -346,6 +356,9 @@ pub enum Expr {
         fun: Arc<AggregateUDF>,
         /// List of expressions to feed to the functions as arguments
         args: Vec<Expr>,
+        /// The `FILTER` clause of the aggregation, which restricts the aggregated rows
+        /// to those for which it is true
+        filter: Option<Box<Expr>>,
     },
     /// Returns whether the list contains the expr value.
     InList {
@@ -399,8 +412,10 @@ impl Expr {
                 window_functions::return_type(fun, &data_types)
             }
             Expr::AggregateFunction { fun, args, .. } => {
+                // the sort expressions of ordered aggregates are not arguments
                 let data_types = args
                     .iter()
+                    .filter(|e| !matches!(e, Expr::Sort { .. }))
                     .map(|e| e.get_type(schema))
                     .collect::<Result<Vec<_>>>()?;
                 aggregates::return_type(fun, &data_types)
@@ -475,6 +490,14 @@ impl Expr {
             Expr::Negative(expr) => expr.nullable(input_schema),
             Expr::IsNull(_) => Ok(false),
             Expr::IsNotNull(_) => Ok(false),
+            Expr::BinaryExpr {
+                op: Operator::IsDistinctFrom,
+                ..
+            }
+            | Expr::BinaryExpr {
+                op: Operator::IsNotDistinctFrom,
+                ..
+            } => Ok(false),
             Expr::BinaryExpr {
                 ref left,
                 ref right,
@@ -599,6 +622,36 @@ impl Expr {
         binary_expr(self, Operator::NotLike, other)
     }
 
+    /// Return `self ILIKE other`
+    pub fn ilike(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::ILike, other)
+    }
+
+    /// Return `self NOT ILIKE other`
+    pub fn not_ilike(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::NotILike, other)
+    }
+
+    /// Return `self SIMILAR TO other`
+    pub fn similar_to(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::SimilarTo, other)
+    }
+
+    /// Return `self NOT SIMILAR TO other`
+    pub fn not_similar_to(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::NotSimilarTo, other)
+    }
+
+    /// Return `self IS DISTINCT FROM other`
+    pub fn is_distinct_from(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::IsDistinctFrom, other)
+    }
+
+    /// Return `self IS NOT DISTINCT FROM other`
+    pub fn is_not_distinct_from(self, other: Expr) -> Expr {
+        binary_expr(self, Operator::IsNotDistinctFrom, other)
+    }
+
     /// Return `self AS name` alias expression
     pub fn alias(self, name: &str) -> Expr {
         Expr::Alias(Box::new(self), name.to_owned())
@@ -749,12 +802,17 @@ impl Expr {
                     .try_fold(visitor, |visitor, arg| arg.accept(visitor))?;
                 Ok(visitor)
             }
-            Expr::AggregateFunction { args, .. } => args
-                .iter()
-                .try_fold(visitor, |visitor, arg| arg.accept(visitor)),
-            Expr::AggregateUDF { args, .. } => args
-                .iter()
-                .try_fold(visitor, |visitor, arg| arg.accept(visitor)),
+            Expr::AggregateFunction { args, filter, .. }
+            | Expr::AggregateUDF { args, filter, .. } => {
+                let visitor = args
+                    .iter()
+                    .try_fold(visitor, |visitor, arg| arg.accept(visitor))?;
+                if let Some(filter) = filter {
+                    filter.accept(visitor)
+                } else {
+                    Ok(visitor)
+                }
+            }
             Expr::InList { expr, list, .. } => {
                 let visitor = expr.accept(visitor)?;
                 list.iter()
@@ -888,25 +946,30 @@ impl Expr {
                 partition_by,
                 order_by,
                 window_frame,
+                distinct,
             } => Expr::WindowFunction {
                 args: rewrite_vec(args, rewriter)?,
                 fun,
                 partition_by: rewrite_vec(partition_by, rewriter)?,
                 order_by: rewrite_vec(order_by, rewriter)?,
                 window_frame,
+                distinct,
             },
             Expr::AggregateFunction {
                 args,
                 fun,
                 distinct,
+                filter,
             } => Expr::AggregateFunction {
                 args: rewrite_vec(args, rewriter)?,
                 fun,
                 distinct,
+                filter: rewrite_option_box(filter, rewriter)?,
             },
-            Expr::AggregateUDF { args, fun } => Expr::AggregateUDF {
+            Expr::AggregateUDF { args, fun, filter } => Expr::AggregateUDF {
                 args: rewrite_vec(args, rewriter)?,
                 fun,
+                filter: rewrite_option_box(filter, rewriter)?,
             },
             Expr::InList {
                 expr,
@@ -1190,7 +1253,7 @@ pub fn replace_col(e: Expr, replace_map: &HashMap<&Column, &Column>) -> Result<E
 /// Recursively call [`Column::normalize`] on all Column expressions
 /// in the `expr` expression tree.
 pub fn normalize_col(expr: Expr, plan: &LogicalPlan) -> Result<Expr> {
-    normalize_col_with_schemas(expr, &plan.all_schemas(), &plan.using_columns()?)
+    normalize_col_with_schemas(expr, &plan.all_schemas(), &plan.using_joins()?)
 }
 
 /// Recursively call [`Column::normalize`] on all Column expressions
@@ -1198,20 +1261,19 @@ pub fn normalize_col(expr: Expr, plan: &LogicalPlan) -> Result<Expr> {
 fn normalize_col_with_schemas(
     expr: Expr,
     schemas: &[&Arc<DFSchema>],
-    using_columns: &[HashSet<Column>],
+    using_joins: &[UsingJoin],
 ) -> Result<Expr> {
     struct ColumnNormalizer<'a> {
         schemas: &'a [&'a Arc<DFSchema>],
-        using_columns: &'a [HashSet<Column>],
+        using_joins: &'a [UsingJoin],
     }
 
     impl<'a> ExprRewriter for ColumnNormalizer<'a> {
         fn mutate(&mut self, expr: Expr) -> Result<Expr> {
             if let Expr::Column(c) = expr {
-                Ok(Expr::Column(c.normalize_with_schemas(
-                    self.schemas,
-                    self.using_columns,
-                )?))
+                Ok(Expr::Column(
+                    c.normalize_with_schemas(self.schemas, self.using_joins)?,
+                ))
             } else {
                 Ok(expr)
             }
@@ -1220,7 +1282,7 @@ fn normalize_col_with_schemas(
 
     expr.rewrite(&mut ColumnNormalizer {
         schemas,
-        using_columns,
+        using_joins,
     })
 }
 
@@ -1271,6 +1333,7 @@ pub fn min(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Min,
         distinct: false,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1280,6 +1343,7 @@ pub fn max(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Max,
         distinct: false,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1289,6 +1353,7 @@ pub fn sum(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Sum,
         distinct: false,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1298,6 +1363,7 @@ pub fn avg(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Avg,
         distinct: false,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1307,6 +1373,7 @@ pub fn count(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Count,
         distinct: false,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1316,6 +1383,7 @@ pub fn count_distinct(expr: Expr) -> Expr {
         fun: aggregates::AggregateFunction::Count,
         distinct: true,
         args: vec![expr],
+        filter: None,
     }
 }
 
@@ -1346,6 +1414,18 @@ impl Literal for String {
     }
 }
 
+impl Literal for &[u8] {
+    fn lit(&self) -> Expr {
+        Expr::Literal(ScalarValue::Binary(Some((*self).to_owned())))
+    }
+}
+
+impl Literal for Vec<u8> {
+    fn lit(&self) -> Expr {
+        Expr::Literal(ScalarValue::Binary(Some((*self).to_owned())))
+    }
+}
+
 impl Literal for ScalarValue {
     fn lit(&self) -> Expr {
         Expr::Literal(self.clone())
@@ -1408,6 +1488,22 @@ pub fn random() -> Expr {
     }
 }
 
+/// Returns the user that runs the query
+pub fn current_user() -> Expr {
+    Expr::ScalarFunction {
+        fun: functions::BuiltinScalarFunction::CurrentUser,
+        args: vec![],
+    }
+}
+
+/// Returns the version of DataFusion that runs the query
+pub fn version() -> Expr {
+    Expr::ScalarFunction {
+        fun: functions::BuiltinScalarFunction::Version,
+        args: vec![],
+    }
+}
+
 /// Create an convenience function representing a unary scalar function
 macro_rules! unary_scalar_expr {
     ($ENUM:ident, $FUNC:ident) => {
@@ -1494,6 +1590,16 @@ unary_scalar_expr!(Upper, upper);
 // date functions
 binary_scalar_expr!(DatePart, date_part);
 binary_scalar_expr!(DateTrunc, date_trunc);
+binary_scalar_expr!(ToChar, to_char);
+
+/// Truncates `source` to the start of the interval of width `stride` that contains
+/// it, with the intervals aligned on the timestamp `origin`
+pub fn date_bin(stride: Expr, source: Expr, origin: Expr) -> Expr {
+    Expr::ScalarFunction {
+        fun: functions::BuiltinScalarFunction::DateBin,
+        args: vec![stride, source, origin],
+    }
+}
 
 /// returns an array of fixed size with each argument on it.
 pub fn array(args: Vec<Expr>) -> Expr {
@@ -1539,6 +1645,23 @@ pub fn create_udaf(
     )
 }
 
+/// Creates a new UDWF with a specific signature and return type.
+/// The signature must match the arguments expected by the `PartitionEvaluator` of `partition_evaluator`.
+pub fn create_udwf(
+    name: &str,
+    input_types: Vec<DataType>,
+    return_type: Arc<DataType>,
+    partition_evaluator: PartitionEvaluatorFunctionImplementation,
+) -> WindowUDF {
+    let return_type: ReturnTypeFunction = Arc::new(move |_| Ok(return_type.clone()));
+    WindowUDF::new(
+        name,
+        &Signature::Exact(input_types),
+        &return_type,
+        &partition_evaluator,
+    )
+}
+
 fn fmt_function(
     f: &mut fmt::Formatter,
     fun: &str,
@@ -1619,8 +1742,9 @@ impl fmt::Debug for Expr {
                 partition_by,
                 order_by,
                 window_frame,
+                distinct,
             } => {
-                fmt_function(f, &fun.to_string(), false, args)?;
+                fmt_function(f, &fun.to_string(), *distinct, args)?;
                 if !partition_by.is_empty() {
                     write!(f, " PARTITION BY {:?}", partition_by)?;
                 }
@@ -1628,13 +1752,7 @@ impl fmt::Debug for Expr {
                     write!(f, " ORDER BY {:?}", order_by)?;
                 }
                 if let Some(window_frame) = window_frame {
-                    write!(
-                        f,
-                        " {} BETWEEN {} AND {}",
-                        window_frame.units,
-                        window_frame.start_bound,
-                        window_frame.end_bound
-                    )?;
+                    write!(f, " {}", window_frame)?;
                 }
                 Ok(())
             }
@@ -1642,10 +1760,24 @@ impl fmt::Debug for Expr {
                 fun,
                 distinct,
                 ref args,
-                ..
-            } => fmt_function(f, &fun.to_string(), *distinct, args),
-            Expr::AggregateUDF { fun, ref args, .. } => {
-                fmt_function(f, &fun.name, false, args)
+                filter,
+            } => {
+                fmt_function(f, &fun.to_string(), *distinct, args)?;
+                if let Some(filter) = filter {
+                    write!(f, " FILTER (WHERE {:?})", filter)?;
+                }
+                Ok(())
+            }
+            Expr::AggregateUDF {
+                fun,
+                ref args,
+                filter,
+            } => {
+                fmt_function(f, &fun.name, false, args)?;
+                if let Some(filter) = filter {
+                    write!(f, " FILTER (WHERE {:?})", filter)?;
+                }
+                Ok(())
             }
             Expr::Between {
                 expr,
@@ -1675,6 +1807,21 @@ impl fmt::Debug for Expr {
     }
 }
 
+fn create_filtered_aggregate_name(
+    name: String,
+    filter: &Option<Box<Expr>>,
+    input_schema: &DFSchema,
+) -> Result<String> {
+    match filter {
+        Some(filter) => Ok(format!(
+            "{} FILTER (WHERE {})",
+            name,
+            create_name(filter, input_schema)?
+        )),
+        None => Ok(name),
+    }
+}
+
 fn create_function_name(
     fun: &str,
     distinct: bool,
@@ -1747,6 +1894,16 @@ fn create_name(e: &Expr, input_schema: &DFSchema) -> Result<String> {
             let expr = create_name(expr, input_schema)?;
             Ok(format!("{} IS NOT NULL", expr))
         }
+        Expr::Sort {
+            expr,
+            asc,
+            nulls_first,
+        } => {
+            let expr = create_name(expr, input_schema)?;
+            let order = if *asc { "ASC" } else { "DESC" };
+            let nulls = if *nulls_first { "FIRST" } else { "LAST" };
+            Ok(format!("{} {} NULLS {}", expr, order, nulls))
+        }
         Expr::ScalarFunction { fun, args, .. } => {
             create_function_name(&fun.to_string(), false, args, input_schema)
         }
@@ -1759,10 +1916,11 @@ fn create_name(e: &Expr, input_schema: &DFSchema) -> Result<String> {
             window_frame,
             partition_by,
             order_by,
+            distinct,
         } => {
             let mut parts: Vec<String> = vec![create_function_name(
                 &fun.to_string(),
-                false,
+                *distinct,
                 args,
                 input_schema,
             )?];
@@ -1781,14 +1939,19 @@ fn create_name(e: &Expr, input_schema: &DFSchema) -> Result<String> {
             fun,
             distinct,
             args,
-            ..
-        } => create_function_name(&fun.to_string(), *distinct, args, input_schema),
-        Expr::AggregateUDF { fun, args } => {
+            filter,
+        } => {
+            let name =
+                create_function_name(&fun.to_string(), *distinct, args, input_schema)?;
+            create_filtered_aggregate_name(name, filter, input_schema)
+        }
+        Expr::AggregateUDF { fun, args, filter } => {
             let mut names = Vec::with_capacity(args.len());
             for e in args {
                 names.push(create_name(e, input_schema)?);
             }
-            Ok(format!("{}({})", fun.name, names.join(",")))
+            let name = format!("{}({})", fun.name, names.join(","));
+            create_filtered_aggregate_name(name, filter, input_schema)
         }
         Expr::InList {
             expr,
@@ -52,6 +52,18 @@ pub enum Operator {
     Like,
     /// Does not match a wildcard pattern
     NotLike,
+    /// Matches a wildcard pattern, ignoring case
+    ILike,
+    /// Does not match a wildcard pattern, ignoring case
+    NotILike,
+    /// Matches a SQL regular expression
+    SimilarTo,
+    /// Does not match a SQL regular expression
+    NotSimilarTo,
+    /// Values differ, where NULL is distinct from any value but NULL
+    IsDistinctFrom,
+    /// Values are equal, where NULL is only equal to NULL
+    IsNotDistinctFrom,
 }
 
 impl fmt::Display for Operator {
@@ -72,6 +84,12 @@ impl fmt::Display for Operator {
             Operator::Or => "OR",
             Operator::Like => "LIKE",
             Operator::NotLike => "NOT LIKE",
+            Operator::ILike => "ILIKE",
+            Operator::NotILike => "NOT ILIKE",
+            Operator::SimilarTo => "SIMILAR TO",
+            Operator::NotSimilarTo => "NOT SIMILAR TO",
+            Operator::IsDistinctFrom => "IS DISTINCT FROM",
+            Operator::IsNotDistinctFrom => "IS NOT DISTINCT FROM",
         };
         write!(f, "{}", display)
     }
@@ -17,16 +17,18 @@
 //! This module contains the  `LogicalPlan` enum that describes queries
 //! via a logical query plan.
 
-use super::display::{GraphvizVisitor, IndentVisitor};
+use super::display::{GraphvizVisitor, IndentVisitor, JsonVisitor};
 use super::expr::{Column, Expr};
 use super::extension::UserDefinedLogicalNode;
+use super::Operator;
 use crate::datasource::TableProvider;
 use crate::error::DataFusionError;
 use crate::logical_plan::dfschema::DFSchemaRef;
+use crate::physical_plan::parquet_writer::ParquetWriteOptions;
 use crate::sql::parser::FileType;
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::{self, Display},
     sync::Arc,
 };
@@ -46,6 +48,10 @@ pub enum JoinType {
     Semi,
     /// Anti Join
     Anti,
+    /// Anti Join with the NULL semantics of `NOT IN`: no row is produced when the right
+    /// side has a NULL key, and rows with a NULL key are only produced when the right
+    /// side is empty
+    NullAwareAnti,
 }
 
 /// Join constraint
@@ -57,6 +63,44 @@ pub enum JoinConstraint {
     Using,
 }
 
+/// The join columns of a join with a `Using` constraint, whose output has a single
+/// column for each pair of join columns
+#[derive(Debug, Clone)]
+pub struct UsingJoin {
+    /// The type of the join
+    pub join_type: JoinType,
+    /// Pairs of (left, right) join columns
+    pub on: Vec<(Column, Column)>,
+}
+
+impl UsingJoin {
+    /// Returns the join column that unqualified references to the pair of join columns
+    /// resolve to, which is the right one if only the right side is preserved
+    pub fn output_column<'a>(&self, left: &'a Column, right: &'a Column) -> &'a Column {
+        match self.join_type {
+            JoinType::Right => right,
+            _ => left,
+        }
+    }
+
+    /// Returns the expression of the single output column of the pair of join columns,
+    /// which takes the value of the side that matched in a full join
+    pub fn output_expr(&self, left: &Column, right: &Column) -> Expr {
+        match self.join_type {
+            JoinType::Full => Expr::Case {
+                expr: None,
+                when_then_expr: vec![(
+                    Box::new(Expr::Column(left.clone()).is_null()),
+                    Box::new(Expr::Column(right.clone())),
+                )],
+                else_expr: Some(Box::new(Expr::Column(left.clone()))),
+            }
+            .alias(&left.name),
+            _ => Expr::Column(self.output_column(left, right).clone()),
+        }
+    }
+}
+
 /// A LogicalPlan represents the different types of relational
 /// operators (such as Projection, Filter, etc) and can be created by
 /// the SQL query planner and the DataFrame API.
@@ -133,6 +177,8 @@ pub enum LogicalPlan {
         join_constraint: JoinConstraint,
         /// The output schema, containing fields from the left and right inputs
         schema: DFSchemaRef,
+        /// Whether NULL keys are equal to each other, as with `IS NOT DISTINCT FROM`
+        null_equals_null: bool,
     },
     /// Apply Cross Join to two logical plans
     CrossJoin {
@@ -143,6 +189,16 @@ pub enum LogicalPlan {
         /// The output schema, containing fields from the left and right inputs
         schema: DFSchemaRef,
     },
+    /// Expands a list column of its input into one row per list
+    /// element, repeating the values of the other columns.
+    Unnest {
+        /// The incoming logical plan
+        input: Arc<LogicalPlan>,
+        /// The list column to unnest
+        column: Column,
+        /// The output schema, where the list column is replaced by its element type
+        schema: DFSchemaRef,
+    },
     /// Repartition the plan based on a partitioning scheme.
     Repartition {
         /// The incoming logical plan
@@ -181,10 +237,13 @@ pub enum LogicalPlan {
         /// The schema description of the output
         schema: DFSchemaRef,
     },
-    /// Produces the first `n` tuples from its input and discards the rest.
+    /// Skips the first `offset` tuples of its input, produces the next `n` tuples and
+    /// discards the rest.
     Limit {
         /// The limit
         n: usize,
+        /// The number of tuples to skip
+        offset: usize,
         /// The logical plan
         input: Arc<LogicalPlan>,
     },
@@ -200,12 +259,44 @@ pub enum LogicalPlan {
         file_type: FileType,
         /// Whether the CSV file contains a header
         has_header: bool,
+        /// Columns the files are partitioned by
+        table_partition_cols: Vec<String>,
+        /// Options of the file format
+        options: HashMap<String, String>,
+    },
+    /// Creates a SQL macro, a function that is replaced by an expression of its
+    /// arguments while planning.
+    CreateFunction {
+        /// The function name
+        name: String,
+        /// The parameter names
+        params: Vec<String>,
+        /// The expression of the parameters the function is replaced with
+        body: Expr,
+        /// Whether an existing function of the same name is replaced
+        or_replace: bool,
+        /// The empty schema of the statement
+        schema: DFSchemaRef,
+    },
+    /// Writes each partition of its input to a Parquet file in a new directory and
+    /// produces the number of written rows.
+    CopyTo {
+        /// The written logical plan
+        input: Arc<LogicalPlan>,
+        /// The directory that the files are written to
+        path: String,
+        /// Options of the written files
+        options: ParquetWriteOptions,
+        /// The output schema, a single count column
+        schema: DFSchemaRef,
     },
     /// Produces a relation with string representations of
     /// various parts of the plan
     Explain {
         /// Should extra (detailed, intermediate plans) be included?
         verbose: bool,
+        /// The format the plans are displayed in
+        format: ExplainFormat,
         /// The logical plan that is being EXPLAIN'd
         plan: Arc<LogicalPlan>,
         /// Represent the various stages plans have gone through
@@ -235,9 +326,12 @@ impl LogicalPlan {
             LogicalPlan::Sort { input, .. } => input.schema(),
             LogicalPlan::Join { schema, .. } => schema,
             LogicalPlan::CrossJoin { schema, .. } => schema,
+            LogicalPlan::Unnest { schema, .. } => schema,
             LogicalPlan::Repartition { input, .. } => input.schema(),
             LogicalPlan::Limit { input, .. } => input.schema(),
             LogicalPlan::CreateExternalTable { schema, .. } => schema,
+            LogicalPlan::CreateFunction { schema, .. } => schema,
+            LogicalPlan::CopyTo { schema, .. } => schema,
             LogicalPlan::Explain { schema, .. } => schema,
             LogicalPlan::Extension { node } => node.schema(),
             LogicalPlan::Union { schema, .. } => schema,
@@ -251,6 +345,8 @@ impl LogicalPlan {
                 projected_schema, ..
             } => vec![projected_schema],
             LogicalPlan::Window { input, schema, .. }
+            | LogicalPlan::Unnest { input, schema, .. }
+            | LogicalPlan::CopyTo { input, schema, .. }
             | LogicalPlan::Aggregate { input, schema, .. }
             | LogicalPlan::Projection { input, schema, .. } => {
                 let mut schemas = input.all_schemas();
@@ -279,7 +375,8 @@ impl LogicalPlan {
             LogicalPlan::Extension { node } => vec![node.schema()],
             LogicalPlan::Explain { schema, .. }
             | LogicalPlan::EmptyRelation { schema, .. }
-            | LogicalPlan::CreateExternalTable { schema, .. } => vec![schema],
+            | LogicalPlan::CreateExternalTable { schema, .. }
+            | LogicalPlan::CreateFunction { schema, .. } => vec![schema],
             LogicalPlan::Limit { input, .. }
             | LogicalPlan::Repartition { input, .. }
             | LogicalPlan::Sort { input, .. }
@@ -320,12 +417,15 @@ impl LogicalPlan {
                 .flat_map(|(l, r)| vec![Expr::Column(l.clone()), Expr::Column(r.clone())])
                 .collect(),
             LogicalPlan::Sort { expr, .. } => expr.clone(),
+            LogicalPlan::Unnest { column, .. } => vec![Expr::Column(column.clone())],
             LogicalPlan::Extension { node } => node.expressions(),
             // plans without expressions
             LogicalPlan::TableScan { .. }
             | LogicalPlan::EmptyRelation { .. }
             | LogicalPlan::Limit { .. }
             | LogicalPlan::CreateExternalTable { .. }
+            | LogicalPlan::CreateFunction { .. }
+            | LogicalPlan::CopyTo { .. }
             | LogicalPlan::CrossJoin { .. }
             | LogicalPlan::Explain { .. }
             | LogicalPlan::Union { .. } => {
@@ -346,6 +446,8 @@ impl LogicalPlan {
             LogicalPlan::Sort { input, .. } => vec![input],
             LogicalPlan::Join { left, right, .. } => vec![left, right],
             LogicalPlan::CrossJoin { left, right, .. } => vec![left, right],
+            LogicalPlan::Unnest { input, .. } => vec![input],
+            LogicalPlan::CopyTo { input, .. } => vec![input],
             LogicalPlan::Limit { input, .. } => vec![input],
             LogicalPlan::Extension { node } => node.inputs(),
             LogicalPlan::Union { inputs, .. } => inputs.iter().collect(),
@@ -353,43 +455,57 @@ impl LogicalPlan {
             // plans without inputs
             LogicalPlan::TableScan { .. }
             | LogicalPlan::EmptyRelation { .. }
-            | LogicalPlan::CreateExternalTable { .. } => vec![],
+            | LogicalPlan::CreateExternalTable { .. }
+            | LogicalPlan::CreateFunction { .. } => vec![],
         }
     }
 
     /// returns all `Using` join columns in a logical plan
     pub fn using_columns(&self) -> Result<Vec<HashSet<Column>>, DataFusionError> {
-        struct UsingJoinColumnVisitor {
-            using_columns: Vec<HashSet<Column>>,
+        Ok(self
+            .using_joins()?
+            .into_iter()
+            .map(|join| {
+                join.on
+                    .into_iter()
+                    .map(|(left, right)| vec![left, right])
+                    .flatten()
+                    .collect::<HashSet<Column>>()
+            })
+            .collect())
+    }
+
+    /// returns all joins with a `Using` constraint in a logical plan
+    pub fn using_joins(&self) -> Result<Vec<UsingJoin>, DataFusionError> {
+        struct UsingJoinVisitor {
+            using_joins: Vec<UsingJoin>,
         }
 
-        impl PlanVisitor for UsingJoinColumnVisitor {
+        impl PlanVisitor for UsingJoinVisitor {
             type Error = DataFusionError;
 
             fn pre_visit(&mut self, plan: &LogicalPlan) -> Result<bool, Self::Error> {
                 if let LogicalPlan::Join {
                     join_constraint: JoinConstraint::Using,
                     on,
+                    join_type,
                     ..
                 } = plan
                 {
-                    self.using_columns.push(
-                        on.iter()
-                            .map(|entry| [&entry.0, &entry.1])
-                            .flatten()
-                            .cloned()
-                            .collect::<HashSet<Column>>(),
-                    );
+                    self.using_joins.push(UsingJoin {
+                        join_type: *join_type,
+                        on: on.clone(),
+                    });
                 }
                 Ok(true)
             }
         }
 
-        let mut visitor = UsingJoinColumnVisitor {
-            using_columns: vec![],
+        let mut visitor = UsingJoinVisitor {
+            using_joins: vec![],
         };
         self.accept(&mut visitor)?;
-        Ok(visitor.using_columns)
+        Ok(visitor.using_joins)
     }
 }
 
@@ -473,6 +589,8 @@ impl LogicalPlan {
             LogicalPlan::Window { input, .. } => input.accept(visitor)?,
             LogicalPlan::Aggregate { input, .. } => input.accept(visitor)?,
             LogicalPlan::Sort { input, .. } => input.accept(visitor)?,
+            LogicalPlan::Unnest { input, .. } => input.accept(visitor)?,
+            LogicalPlan::CopyTo { input, .. } => input.accept(visitor)?,
             LogicalPlan::Join { left, right, .. }
             | LogicalPlan::CrossJoin { left, right, .. } => {
                 left.accept(visitor)? && right.accept(visitor)?
@@ -498,7 +616,8 @@ impl LogicalPlan {
             // plans without inputs
             LogicalPlan::TableScan { .. }
             | LogicalPlan::EmptyRelation { .. }
-            | LogicalPlan::CreateExternalTable { .. } => true,
+            | LogicalPlan::CreateExternalTable { .. }
+            | LogicalPlan::CreateFunction { .. } => true,
         };
         if !recurse {
             return Ok(false);
@@ -656,6 +775,47 @@ impl LogicalPlan {
         Wrapper(self)
     }
 
+    /// Return a `format`able structure that produces a JSON tree of the
+    /// plan, meant to be consumed by external tools. Every node is an
+    /// object with the description of the node, its output schema, the
+    /// filters pushed down into table scans, and its inputs. For example:
+    ///
+    /// ```text
+    /// {"node":"Filter: #foo_csv.id Eq Int32(5)","schema":"[id:Int32]","inputs":[
+    ///   {"node":"TableScan: foo_csv projection=None","schema":"[id:Int32]","filters":[],"inputs":[]}]}
+    /// ```
+    ///
+    /// ```
+    /// use arrow::datatypes::{Field, Schema, DataType};
+    /// use datafusion::logical_plan::{lit, col, LogicalPlanBuilder};
+    /// let schema = Schema::new(vec![
+    ///     Field::new("id", DataType::Int32, false),
+    /// ]);
+    /// let plan = LogicalPlanBuilder::scan_empty(Some("foo_csv"), &schema, None).unwrap()
+    ///     .filter(col("id").eq(lit(5))).unwrap()
+    ///     .build().unwrap();
+    ///
+    /// // Format using display_json
+    /// let json_string = format!("{}", plan.display_json());
+    ///
+    /// assert_eq!("{\"node\":\"Filter: #foo_csv.id Eq Int32(5)\",\"schema\":\"[id:Int32]\",\"inputs\":[\
+    ///             {\"node\":\"TableScan: foo_csv projection=None\",\"schema\":\"[id:Int32]\",\"filters\":[],\"inputs\":[]}]}",
+    ///             json_string);
+    /// ```
+    pub fn display_json(&self) -> impl fmt::Display + '_ {
+        // Boilerplate structure to wrap LogicalPlan with something
+        // that that can be formatted
+        struct Wrapper<'a>(&'a LogicalPlan);
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mut visitor = JsonVisitor::new(f);
+                self.0.accept(&mut visitor)?;
+                Ok(())
+            }
+        }
+        Wrapper(self)
+    }
+
     /// Return a `format`able structure with the a human readable
     /// description of this LogicalPlan node per node, not including
     /// children. For example:
@@ -749,10 +909,18 @@ impl LogicalPlan {
                     LogicalPlan::Join {
                         on: ref keys,
                         join_constraint,
+                        null_equals_null,
                         ..
                     } => {
-                        let join_expr: Vec<String> =
-                            keys.iter().map(|(l, r)| format!("{} = {}", l, r)).collect();
+                        let op = if *null_equals_null {
+                            Operator::IsNotDistinctFrom
+                        } else {
+                            Operator::Eq
+                        };
+                        let join_expr: Vec<String> = keys
+                            .iter()
+                            .map(|(l, r)| format!("{} {} {}", l, op, r))
+                            .collect();
                         match join_constraint {
                             JoinConstraint::On => {
                                 write!(f, "Join: {}", join_expr.join(", "))
@@ -765,6 +933,9 @@ impl LogicalPlan {
                     LogicalPlan::CrossJoin { .. } => {
                         write!(f, "CrossJoin:")
                     }
+                    LogicalPlan::Unnest { ref column, .. } => {
+                        write!(f, "Unnest: {}", column)
+                    }
                     LogicalPlan::Repartition {
                         partitioning_scheme,
                         ..
@@ -785,10 +956,35 @@ impl LogicalPlan {
                             )
                         }
                     },
-                    LogicalPlan::Limit { ref n, .. } => write!(f, "Limit: {}", n),
+                    LogicalPlan::Limit {
+                        ref n, ref offset, ..
+                    } => {
+                        if *offset > 0 {
+                            write!(f, "Limit: {}, offset={}", n, offset)
+                        } else {
+                            write!(f, "Limit: {}", n)
+                        }
+                    }
                     LogicalPlan::CreateExternalTable { ref name, .. } => {
                         write!(f, "CreateExternalTable: {:?}", name)
                     }
+                    LogicalPlan::CreateFunction {
+                        ref name,
+                        ref params,
+                        ref body,
+                        ..
+                    } => {
+                        write!(
+                            f,
+                            "CreateFunction: {}({}) AS {:?}",
+                            name,
+                            params.join(", "),
+                            body
+                        )
+                    }
+                    LogicalPlan::CopyTo { ref path, .. } => {
+                        write!(f, "CopyTo: {}", path)
+                    }
                     LogicalPlan::Explain { .. } => write!(f, "Explain"),
                     LogicalPlan::Union { .. } => write!(f, "Union"),
                     LogicalPlan::Extension { ref node } => node.fmt_for_explain(f),
@@ -805,6 +1001,23 @@ impl fmt::Debug for LogicalPlan {
     }
 }
 
+/// The format of the plans displayed by EXPLAIN
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExplainFormat {
+    /// One line per node, indented by depth, see [LogicalPlan::display_indent]
+    Text,
+    /// A JSON tree of nodes, see [LogicalPlan::display_json]
+    Json,
+    /// A graph in the `DOT` language, see [LogicalPlan::display_graphviz]
+    Graphviz,
+}
+
+impl Default for ExplainFormat {
+    fn default() -> Self {
+        ExplainFormat::Text
+    }
+}
+
 /// Represents which type of plan, when storing multiple
 /// for use in EXPLAIN plans
 #[derive(Debug, Clone, PartialEq)]
@@ -878,13 +1091,26 @@ impl StringifiedPlan {
 
 /// Trait for something that can be formatted as a stringified plan
 pub trait ToStringifiedPlan {
-    /// Create a stringified plan with the specified type
-    fn to_stringified(&self, plan_type: PlanType) -> StringifiedPlan;
+    /// Create a stringified plan with the specified type, formatted as `format`
+    fn to_stringified(
+        &self,
+        plan_type: PlanType,
+        format: ExplainFormat,
+    ) -> StringifiedPlan;
 }
 
 impl ToStringifiedPlan for LogicalPlan {
-    fn to_stringified(&self, plan_type: PlanType) -> StringifiedPlan {
-        StringifiedPlan::new(plan_type, self.display_indent().to_string())
+    fn to_stringified(
+        &self,
+        plan_type: PlanType,
+        format: ExplainFormat,
+    ) -> StringifiedPlan {
+        let plan = match format {
+            ExplainFormat::Text => self.display_indent().to_string(),
+            ExplainFormat::Json => self.display_json().to_string(),
+            ExplainFormat::Graphviz => self.display_graphviz().to_string(),
+        };
+        StringifiedPlan::new(plan_type, plan)
     }
 }
 
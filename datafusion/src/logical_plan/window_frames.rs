@@ -28,6 +28,7 @@ use sqlparser::ast;
 use std::cmp::Ordering;
 use std::convert::{From, TryFrom};
 use std::fmt;
+use std::str::FromStr;
 
 /// The frame-spec determines which output rows are read by an aggregate window function.
 ///
@@ -42,6 +43,8 @@ pub struct WindowFrame {
     pub start_bound: WindowFrameBound,
     /// An ending frame boundary
     pub end_bound: WindowFrameBound,
+    /// The rows around the current row that are excluded from the frame
+    pub exclude: WindowFrameExclusion,
 }
 
 impl fmt::Display for WindowFrame {
@@ -51,6 +54,9 @@ impl fmt::Display for WindowFrame {
             "{} BETWEEN {} AND {}",
             self.units, self.start_bound, self.end_bound
         )?;
+        if self.exclude != WindowFrameExclusion::NoOthers {
+            write!(f, " EXCLUDE {}", self.exclude)?;
+        }
         Ok(())
     }
 }
@@ -102,6 +108,7 @@ impl TryFrom<ast::WindowFrame> for WindowFrame {
                 units,
                 start_bound,
                 end_bound,
+                exclude: WindowFrameExclusion::NoOthers,
             })
         }
     }
@@ -113,6 +120,7 @@ impl Default for WindowFrame {
             units: WindowFrameUnits::Range,
             start_bound: WindowFrameBound::Preceding(None),
             end_bound: WindowFrameBound::CurrentRow,
+            exclude: WindowFrameExclusion::NoOthers,
         }
     }
 }
@@ -248,6 +256,55 @@ impl From<ast::WindowFrameUnits> for WindowFrameUnits {
     }
 }
 
+/// The EXCLUDE clause removes the current row, or its peers, from the frame even if the
+/// bounds include them. The peers of a row are the rows with the same values of the
+/// window ORDER BY clause, or all the rows of the partition without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowFrameExclusion {
+    /// EXCLUDE NO OTHERS, the default, excludes no rows
+    NoOthers,
+    /// EXCLUDE CURRENT ROW excludes the current row
+    CurrentRow,
+    /// EXCLUDE GROUP excludes the current row and its peers
+    Group,
+    /// EXCLUDE TIES excludes the peers of the current row, but not the row itself
+    Ties,
+}
+
+impl Default for WindowFrameExclusion {
+    fn default() -> Self {
+        WindowFrameExclusion::NoOthers
+    }
+}
+
+impl fmt::Display for WindowFrameExclusion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WindowFrameExclusion::NoOthers => "NO OTHERS",
+            WindowFrameExclusion::CurrentRow => "CURRENT ROW",
+            WindowFrameExclusion::Group => "GROUP",
+            WindowFrameExclusion::Ties => "TIES",
+        })
+    }
+}
+
+impl FromStr for WindowFrameExclusion {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "NO OTHERS" => Ok(WindowFrameExclusion::NoOthers),
+            "CURRENT ROW" => Ok(WindowFrameExclusion::CurrentRow),
+            "GROUP" => Ok(WindowFrameExclusion::Group),
+            "TIES" => Ok(WindowFrameExclusion::Ties),
+            _ => Err(DataFusionError::Plan(format!(
+                "Invalid window frame exclusion: {}",
+                s
+            ))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,6 +365,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_exclusion() -> Result<()> {
+        let window_frame = WindowFrame {
+            exclude: WindowFrameExclusion::from_str("current row")?,
+            ..Default::default()
+        };
+        assert_eq!(
+            "RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW EXCLUDE CURRENT ROW",
+            window_frame.to_string()
+        );
+        assert_eq!(
+            "RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW",
+            WindowFrame::default().to_string()
+        );
+        assert!(WindowFrameExclusion::from_str("others").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_eq() {
         assert_eq!(
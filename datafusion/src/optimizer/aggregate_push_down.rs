@@ -0,0 +1,461 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Optimizer rule that computes partial aggregates below `UNION ALL` and joins, so that
+//! fewer rows reach the union and the join.
+use std::collections::{HashMap, HashSet};
+
+use crate::error::Result;
+use crate::execution::context::ExecutionProps;
+use crate::logical_plan::{
+    replace_col, Column, DFSchema, Expr, JoinType, LogicalPlan, LogicalPlanBuilder,
+};
+use crate::optimizer::optimizer::OptimizerRule;
+use crate::physical_plan::aggregates::AggregateFunction;
+
+use super::utils;
+
+/// Optimization rule that pushes partial aggregates below `UNION ALL` and inner joins.
+///
+/// An aggregate over a union is computed for every input of the union, and the partial
+/// results are combined by a final aggregate:
+///
+/// ```text
+/// Aggregate: groupBy=[[#a]], aggr=[[COUNT(#b)]]
+///   Union
+///     TableScan: t1
+///     TableScan: t2
+/// ```
+///
+/// becomes
+///
+/// ```text
+/// Projection: #a, #SUM(COUNT(t1.b)) AS COUNT(b)
+///   Aggregate: groupBy=[[#a]], aggr=[[SUM(#COUNT(t1.b))]]
+///     Union
+///       Aggregate: groupBy=[[#t1.a]], aggr=[[COUNT(#t1.b)]]
+///         TableScan: t1
+///       Aggregate: groupBy=[[#t2.a]], aggr=[[COUNT(#t2.b)]]
+///         TableScan: t2
+/// ```
+///
+/// When all the aggregated columns come from one side of an inner join, that side is
+/// aggregated before the join (eager aggregation), grouped by its join keys and by its
+/// columns in the grouping. The join keys determine the rows of the other side that
+/// a partial group joins with, so the final aggregate above the join combines the
+/// partial results correctly. In a star schema, this reduces the fact table to one row
+/// per foreign key before it is joined with the dimension tables.
+///
+/// Only `SUM`, `MIN`, `MAX` and `COUNT` without `DISTINCT`, grouped by columns, are
+/// split into partial and final aggregates.
+pub struct AggregatePushDown;
+
+impl AggregatePushDown {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl OptimizerRule for AggregatePushDown {
+    fn optimize(
+        &self,
+        plan: &LogicalPlan,
+        execution_props: &ExecutionProps,
+    ) -> Result<LogicalPlan> {
+        // optimize the inputs first, so that the partial aggregates created below are
+        // not pushed down again
+        let plan = utils::optimize_children(self, plan, execution_props)?;
+        match &plan {
+            LogicalPlan::Aggregate {
+                input,
+                group_expr,
+                aggr_expr,
+                ..
+            } => {
+                let split_aggr_expr = match split_aggregates(group_expr, aggr_expr) {
+                    Some(split_aggr_expr) => split_aggr_expr,
+                    None => return Ok(plan),
+                };
+                let pushed = match input.as_ref() {
+                    LogicalPlan::Union { .. } => {
+                        push_down_union(input, group_expr, &split_aggr_expr)?
+                    }
+                    LogicalPlan::Join {
+                        join_type: JoinType::Inner,
+                        ..
+                    } if !group_expr.is_empty() => {
+                        push_down_join(input, group_expr, &split_aggr_expr)?
+                    }
+                    _ => None,
+                };
+                match pushed {
+                    Some((new_input, final_aggr_expr)) => {
+                        finish_aggregate(&plan, new_input, group_expr, final_aggr_expr)
+                    }
+                    None => Ok(plan),
+                }
+            }
+            _ => Ok(plan),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "aggregate_push_down"
+    }
+}
+
+/// An aggregate expression split into a partial aggregate and the function that
+/// combines the partial results
+type SplitAggregate = (Expr, AggregateFunction);
+
+/// Splits the aggregate expressions, if the grouping and all the aggregates support it
+fn split_aggregates(
+    group_expr: &[Expr],
+    aggr_expr: &[Expr],
+) -> Option<Vec<SplitAggregate>> {
+    if !group_expr.iter().all(|e| matches!(e, Expr::Column(_))) {
+        return None;
+    }
+    aggr_expr
+        .iter()
+        .map(|e| match e {
+            Expr::AggregateFunction {
+                fun,
+                distinct: false,
+                ..
+            } => {
+                let final_fun = match fun {
+                    AggregateFunction::Sum
+                    | AggregateFunction::Min
+                    | AggregateFunction::Max => fun.clone(),
+                    AggregateFunction::Count => AggregateFunction::Sum,
+                    _ => return None,
+                };
+                Some((e.clone(), final_fun))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Combines the partial aggregates of the `partial` column with `fun`
+fn final_aggregate(fun: &AggregateFunction, partial: Column) -> Expr {
+    Expr::AggregateFunction {
+        fun: fun.clone(),
+        args: vec![Expr::Column(partial)],
+        distinct: false,
+        filter: None,
+    }
+}
+
+/// Aggregates every input of `union`, returning the new union and the final aggregates
+fn push_down_union(
+    union: &LogicalPlan,
+    group_expr: &[Expr],
+    split_aggr_expr: &[SplitAggregate],
+) -> Result<Option<(LogicalPlan, Vec<Expr>)>> {
+    let (inputs, schema, alias) = match union {
+        LogicalPlan::Union {
+            inputs,
+            schema,
+            alias,
+        } => (inputs, schema, alias),
+        _ => return Ok(None),
+    };
+    if inputs
+        .iter()
+        .all(|input| matches!(input, LogicalPlan::Aggregate { .. }))
+    {
+        // already pushed down
+        return Ok(None);
+    }
+
+    let new_inputs = inputs
+        .iter()
+        .map(|input| {
+            // the columns of the union and of its inputs match by position
+            let columns = schema
+                .fields()
+                .iter()
+                .map(|f| f.qualified_column())
+                .zip(input.schema().fields().iter().map(|f| f.qualified_column()))
+                .collect::<Vec<_>>();
+            let replace_map: HashMap<&Column, &Column> =
+                columns.iter().map(|(u, i)| (u, i)).collect();
+            let group_expr = group_expr
+                .iter()
+                .map(|e| replace_col(e.clone(), &replace_map))
+                .collect::<Result<Vec<_>>>()?;
+            let aggr_expr = split_aggr_expr
+                .iter()
+                .map(|(e, _)| replace_col(e.clone(), &replace_map))
+                .collect::<Result<Vec<_>>>()?;
+            LogicalPlanBuilder::from(input.clone())
+                .aggregate(group_expr, aggr_expr)?
+                .build()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let new_schema = new_inputs[0].schema().as_ref().clone();
+    let new_schema = match alias {
+        Some(alias) => new_schema.replace_qualifier(alias),
+        None => new_schema.strip_qualifiers(),
+    };
+    let final_aggr_expr = split_aggr_expr
+        .iter()
+        .enumerate()
+        .map(|(i, (_, fun))| {
+            let partial = new_schema.field(group_expr.len() + i).qualified_column();
+            final_aggregate(fun, partial)
+        })
+        .collect();
+    let new_union = LogicalPlan::Union {
+        inputs: new_inputs,
+        schema: new_schema.into(),
+        alias: alias.clone(),
+    };
+    Ok(Some((new_union, final_aggr_expr)))
+}
+
+/// Aggregates the side of `join` that all the aggregates read, returning the new join
+/// and the final aggregates
+fn push_down_join(
+    join: &LogicalPlan,
+    group_expr: &[Expr],
+    split_aggr_expr: &[SplitAggregate],
+) -> Result<Option<(LogicalPlan, Vec<Expr>)>> {
+    let (left, right, on) = match join {
+        LogicalPlan::Join {
+            left, right, on, ..
+        } => (left, right, on),
+        _ => return Ok(None),
+    };
+
+    let mut aggr_columns = HashSet::new();
+    utils::exprlist_to_columns(
+        &split_aggr_expr
+            .iter()
+            .map(|(e, _)| e.clone())
+            .collect::<Vec<_>>(),
+        &mut aggr_columns,
+    )?;
+    let reads_only = |schema: &DFSchema| {
+        aggr_columns
+            .iter()
+            .all(|c| schema.field_from_column(c).is_ok())
+    };
+    let (input, join_keys, push_left) = if reads_only(left.schema().as_ref()) {
+        (left, on.iter().map(|(l, _)| l).collect::<Vec<_>>(), true)
+    } else if reads_only(right.schema().as_ref()) {
+        (right, on.iter().map(|(_, r)| r).collect::<Vec<_>>(), false)
+    } else {
+        return Ok(None);
+    };
+    if matches!(input.as_ref(), LogicalPlan::Aggregate { .. }) {
+        // already pushed down
+        return Ok(None);
+    }
+
+    // group the pushed aggregate by the join keys and by the grouping columns of `input`
+    let mut partial_group_expr: Vec<Expr> = vec![];
+    let grouping = group_expr
+        .iter()
+        .filter_map(|e| match e {
+            Expr::Column(c) if input.schema().field_from_column(c).is_ok() => Some(c),
+            _ => None,
+        })
+        .chain(join_keys);
+    for column in grouping {
+        let e = Expr::Column(column.clone());
+        if !partial_group_expr.contains(&e) {
+            partial_group_expr.push(e);
+        }
+    }
+    let partial_group_len = partial_group_expr.len();
+    let partial = LogicalPlanBuilder::from(input.as_ref().clone())
+        .aggregate(
+            partial_group_expr,
+            split_aggr_expr.iter().map(|(e, _)| e.clone()),
+        )?
+        .build()?;
+    let final_aggr_expr = split_aggr_expr
+        .iter()
+        .enumerate()
+        .map(|(i, (_, fun))| {
+            let partial = partial.schema().field(partial_group_len + i);
+            final_aggregate(fun, partial.qualified_column())
+        })
+        .collect();
+
+    let new_inputs = if push_left {
+        vec![partial, right.as_ref().clone()]
+    } else {
+        vec![left.as_ref().clone(), partial]
+    };
+    let new_join = utils::from_plan(join, &join.expressions(), &new_inputs)?;
+    Ok(Some((new_join, final_aggr_expr)))
+}
+
+/// Aggregates the partial results of `new_input`, then renames the final aggregates
+/// to the names of the aggregates of `plan`
+fn finish_aggregate(
+    plan: &LogicalPlan,
+    new_input: LogicalPlan,
+    group_expr: &[Expr],
+    final_aggr_expr: Vec<Expr>,
+) -> Result<LogicalPlan> {
+    let final_plan = LogicalPlanBuilder::from(new_input)
+        .aggregate(group_expr.to_vec(), final_aggr_expr)?
+        .build()?;
+    let projection = final_plan
+        .schema()
+        .fields()
+        .iter()
+        .zip(plan.schema().fields())
+        .enumerate()
+        .map(|(i, (field, original))| {
+            let column = Expr::Column(field.qualified_column());
+            if i < group_expr.len() {
+                column
+            } else {
+                column.alias(original.name())
+            }
+        })
+        .collect::<Vec<_>>();
+    LogicalPlanBuilder::from(final_plan)
+        .project(projection)?
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::{avg, col, count, count_distinct, max, sum};
+    use crate::test::*;
+
+    fn assert_optimized_plan_eq(plan: &LogicalPlan, expected: &str) {
+        let rule = AggregatePushDown::new();
+        let optimized_plan = rule
+            .optimize(plan, &ExecutionProps::new())
+            .expect("failed to optimize plan");
+        let formatted_plan = format!("{:?}", optimized_plan);
+        assert_eq!(formatted_plan, expected);
+        assert_eq!(plan.schema(), optimized_plan.schema());
+    }
+
+    #[test]
+    fn aggregate_below_union() -> Result<()> {
+        let plan = LogicalPlanBuilder::from(test_table_scan_with_name("t1")?)
+            .union(test_table_scan_with_name("t2")?)?
+            .aggregate(vec![col("a")], vec![count(col("b")), max(col("c"))])?
+            .build()?;
+
+        let expected =
+            "Projection: #a, #SUM(COUNT(t1.b)) AS COUNT(b), #MAX(MAX(t1.c)) AS MAX(c)\
+        \n  Aggregate: groupBy=[[#a]], aggr=[[SUM(#COUNT(t1.b)), MAX(#MAX(t1.c))]]\
+        \n    Union\
+        \n      Aggregate: groupBy=[[#t1.a]], aggr=[[COUNT(#t1.b), MAX(#t1.c)]]\
+        \n        TableScan: t1 projection=None\
+        \n      Aggregate: groupBy=[[#t2.a]], aggr=[[COUNT(#t2.b), MAX(#t2.c)]]\
+        \n        TableScan: t2 projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn eager_aggregate_before_join() -> Result<()> {
+        let plan = LogicalPlanBuilder::from(test_table_scan_with_name("fact")?)
+            .join(
+                &test_table_scan_with_name("dim")?,
+                JoinType::Inner,
+                (vec!["fact.a"], vec!["dim.a"]),
+            )?
+            .aggregate(vec![col("dim.b")], vec![sum(col("fact.c"))])?
+            .build()?;
+
+        let expected = "Projection: #dim.b, #SUM(SUM(fact.c)) AS SUM(fact.c)\
+        \n  Aggregate: groupBy=[[#dim.b]], aggr=[[SUM(#SUM(fact.c))]]\
+        \n    Join: #fact.a = #dim.a\
+        \n      Aggregate: groupBy=[[#fact.a]], aggr=[[SUM(#fact.c)]]\
+        \n        TableScan: fact projection=None\
+        \n      TableScan: dim projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn eager_aggregate_keeps_grouping_columns() -> Result<()> {
+        let plan = LogicalPlanBuilder::from(test_table_scan_with_name("dim")?)
+            .join(
+                &test_table_scan_with_name("fact")?,
+                JoinType::Inner,
+                (vec!["dim.a"], vec!["fact.a"]),
+            )?
+            .aggregate(
+                vec![col("dim.b"), col("fact.b")],
+                vec![count(col("fact.c"))],
+            )?
+            .build()?;
+
+        let expected =
+            "Projection: #dim.b, #fact.b, #SUM(COUNT(fact.c)) AS COUNT(fact.c)\
+        \n  Aggregate: groupBy=[[#dim.b, #fact.b]], aggr=[[SUM(#COUNT(fact.c))]]\
+        \n    Join: #dim.a = #fact.a\
+        \n      TableScan: dim projection=None\
+        \n      Aggregate: groupBy=[[#fact.b, #fact.a]], aggr=[[COUNT(#fact.c)]]\
+        \n        TableScan: fact projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_aggregates_are_not_pushed_down() -> Result<()> {
+        let join = LogicalPlanBuilder::from(test_table_scan_with_name("fact")?)
+            .join(
+                &test_table_scan_with_name("dim")?,
+                JoinType::Inner,
+                (vec!["fact.a"], vec!["dim.a"]),
+            )?
+            .build()?;
+        let plans = vec![
+            // AVG can't be combined from partial averages
+            LogicalPlanBuilder::from(join.clone())
+                .aggregate(vec![col("dim.b")], vec![avg(col("fact.c"))])?
+                .build()?,
+            LogicalPlanBuilder::from(join.clone())
+                .aggregate(vec![col("dim.b")], vec![count_distinct(col("fact.c"))])?
+                .build()?,
+            // the aggregates read both sides
+            LogicalPlanBuilder::from(join.clone())
+                .aggregate(
+                    vec![col("dim.b")],
+                    vec![sum(col("fact.c")), sum(col("dim.c"))],
+                )?
+                .build()?,
+            // a global COUNT must return 0 when the join returns no rows
+            LogicalPlanBuilder::from(join)
+                .aggregate(vec![], vec![count(col("fact.c"))])?
+                .build()?,
+        ];
+        for plan in plans {
+            let expected = format!("{:?}", plan);
+            assert_optimized_plan_eq(&plan, &expected);
+        }
+        Ok(())
+    }
+}
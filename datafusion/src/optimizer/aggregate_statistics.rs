@@ -100,6 +100,7 @@ impl OptimizerRule for AggregateStatistics {
                                 fun: AggregateFunction::Count,
                                 args,
                                 distinct: false,
+                                filter: None,
                             } if args
                                 == &[Expr::Literal(ScalarValue::UInt8(Some(1)))] =>
                             {
@@ -113,6 +114,7 @@ impl OptimizerRule for AggregateStatistics {
                             Expr::AggregateFunction {
                                 fun: AggregateFunction::Max,
                                 args,
+                                filter: None,
                                 ..
                             } => match &args[0] {
                                 Expr::Column(c) => match max_values.get(&c.flat_name()) {
@@ -140,6 +142,7 @@ impl OptimizerRule for AggregateStatistics {
                             Expr::AggregateFunction {
                                 fun: AggregateFunction::Min,
                                 args,
+                                filter: None,
                                 ..
                             } => match &args[0] {
                                 Expr::Column(c) => match min_values.get(&c.flat_name()) {
@@ -77,13 +77,16 @@ impl OptimizerRule for ConstantFolding {
             | LogicalPlan::Aggregate { .. }
             | LogicalPlan::Repartition { .. }
             | LogicalPlan::CreateExternalTable { .. }
+            | LogicalPlan::CreateFunction { .. }
+            | LogicalPlan::CopyTo { .. }
             | LogicalPlan::Extension { .. }
             | LogicalPlan::Sort { .. }
             | LogicalPlan::Explain { .. }
             | LogicalPlan::Limit { .. }
             | LogicalPlan::Union { .. }
             | LogicalPlan::Join { .. }
-            | LogicalPlan::CrossJoin { .. } => {
+            | LogicalPlan::CrossJoin { .. }
+            | LogicalPlan::Unnest { .. } => {
                 // apply the optimization to all inputs of the plan
                 let inputs = plan.inputs();
                 let new_inputs = inputs
@@ -211,19 +214,16 @@ impl<'a> ExprRewriter for ConstantRewriter<'a> {
                     Expr::Not(inner)
                 }
             }
-            Expr::ScalarFunction {
-                fun: BuiltinScalarFunction::Now,
-                ..
-            } => Expr::Literal(ScalarValue::TimestampNanosecond(Some(
-                self.execution_props
-                    .query_execution_start_time
-                    .timestamp_nanos(),
-            ))),
+            // evaluated once per query, see `BuiltinScalarFunction::is_query_constant`
+            Expr::ScalarFunction { fun, .. } if fun.is_query_constant() => {
+                Expr::Literal(self.execution_props.query_constant(&fun)?)
+            }
             Expr::ScalarFunction {
                 fun: BuiltinScalarFunction::ToTimestamp,
                 args,
             } => {
-                if !args.is_empty() {
+                // timestamps parsed with a format are not folded
+                if args.len() == 1 {
                     match &args[0] {
                         Expr::Literal(ScalarValue::Utf8(Some(val))) => {
                             match string_to_timestamp_nanos(val) {
@@ -674,6 +674,7 @@ mod tests {
         let rule = ConstantFolding::new();
         let execution_props = ExecutionProps {
             query_execution_start_time: *date_time,
+            current_user: "alice".to_owned(),
         };
 
         let optimized_plan = rule
@@ -839,4 +840,33 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn query_constant_exprs() {
+        let table_scan = test_table_scan().unwrap();
+        let proj = vec![
+            Expr::ScalarFunction {
+                args: vec![],
+                fun: BuiltinScalarFunction::CurrentUser,
+            },
+            Expr::ScalarFunction {
+                args: vec![],
+                fun: BuiltinScalarFunction::Version,
+            },
+        ];
+        let plan = LogicalPlanBuilder::from(table_scan)
+            .project(proj)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let actual = get_optimized_plan_formatted(&plan, &chrono::Utc::now());
+        let expected = format!(
+            "Projection: Utf8(\"alice\"), Utf8(\"DataFusion {}\")\
+            \n  TableScan: test projection=None",
+            env!("CARGO_PKG_VERSION")
+        );
+
+        assert_eq!(actual, expected);
+    }
 }
@@ -0,0 +1,307 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Optimizer rule that rewrites a filter above a cross join into an inner join, using
+//! the equality predicates between the two sides of the cross join as join keys.
+//!
+//! The keys of a predicate such as `a.x + 1 = b.y` are normalized into columns: a key
+//! that is not a column of its side, or that needs a cast to the type of the other key,
+//! is computed by a projection below the join, and the columns of the cross join are
+//! projected again above it.
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow::datatypes::DataType;
+
+use crate::error::Result;
+use crate::execution::context::ExecutionProps;
+use crate::logical_plan::{
+    Column, DFSchema, Expr, JoinType, LogicalPlan, LogicalPlanBuilder, Operator,
+};
+use crate::optimizer::optimizer::OptimizerRule;
+use crate::physical_plan::expressions::coercion::eq_coercion;
+
+use super::utils;
+
+/// Optimization rule that replaces a [LogicalPlan::Filter] above a
+/// [LogicalPlan::CrossJoin] by an inner [LogicalPlan::Join] when the filter has
+/// equality predicates between the two sides
+pub struct EliminateCrossJoin;
+
+impl EliminateCrossJoin {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl OptimizerRule for EliminateCrossJoin {
+    fn optimize(
+        &self,
+        plan: &LogicalPlan,
+        execution_props: &ExecutionProps,
+    ) -> Result<LogicalPlan> {
+        if let LogicalPlan::Filter { predicate, input } = plan {
+            if let LogicalPlan::CrossJoin {
+                left,
+                right,
+                schema,
+            } = input.as_ref()
+            {
+                let left = self.optimize(left, execution_props)?;
+                let right = self.optimize(right, execution_props)?;
+                return match try_inner_join(predicate, &left, &right, schema)? {
+                    Some(plan) => Ok(plan),
+                    None => Ok(LogicalPlan::Filter {
+                        predicate: predicate.clone(),
+                        input: Arc::new(LogicalPlan::CrossJoin {
+                            left: Arc::new(left),
+                            right: Arc::new(right),
+                            schema: schema.clone(),
+                        }),
+                    }),
+                };
+            }
+        }
+        utils::optimize_children(self, plan, execution_props)
+    }
+
+    fn name(&self) -> &str {
+        "eliminate_cross_join"
+    }
+}
+
+/// Returns the inner join of `left` and `right` on the equality predicates of
+/// `predicate` between them, filtered by the remaining predicates, or `None` if the
+/// predicate has no such equality
+fn try_inner_join(
+    predicate: &Expr,
+    left: &LogicalPlan,
+    right: &LogicalPlan,
+    cross_join_schema: &DFSchema,
+) -> Result<Option<LogicalPlan>> {
+    let mut predicates = vec![];
+    utils::split_conjunction(predicate, &mut predicates);
+
+    let mut join_keys = vec![];
+    let mut residual = vec![];
+    for predicate in predicates {
+        match join_key(&predicate, left.schema(), right.schema())? {
+            Some(keys) => join_keys.push(keys),
+            None => residual.push(predicate),
+        }
+    }
+    if join_keys.is_empty() {
+        return Ok(None);
+    }
+
+    let (left_keys, right_keys): (Vec<Expr>, Vec<Expr>) = join_keys.into_iter().unzip();
+    let (left, left_keys) = project_keys(left, left_keys)?;
+    let (right, right_keys) = project_keys(right, right_keys)?;
+    let mut builder = LogicalPlanBuilder::from(left).join(
+        &right,
+        JoinType::Inner,
+        (left_keys, right_keys),
+    )?;
+    if let Some(predicate) = residual.into_iter().reduce(|acc, p| acc.and(p)) {
+        builder = builder.filter(predicate)?;
+    }
+    let plan = builder.build()?;
+    // remove the keys computed below the join
+    if plan.schema().fields().len() == cross_join_schema.fields().len() {
+        return Ok(Some(plan));
+    }
+    let plan = LogicalPlanBuilder::from(plan)
+        .project(
+            cross_join_schema
+                .fields()
+                .iter()
+                .map(|field| Expr::Column(field.qualified_column())),
+        )?
+        .build()?;
+    Ok(Some(plan))
+}
+
+/// Returns the left and right keys of `predicate` if it is an equality between an
+/// expression of the columns of the left side and one of the columns of the right side,
+/// cast to the type they are compared as
+fn join_key(
+    predicate: &Expr,
+    left_schema: &DFSchema,
+    right_schema: &DFSchema,
+) -> Result<Option<(Expr, Expr)>> {
+    let (l, r) = match predicate {
+        Expr::BinaryExpr {
+            left,
+            op: Operator::Eq,
+            right,
+        } => (left.as_ref(), right.as_ref()),
+        _ => return Ok(None),
+    };
+    let (l, r) = if references_only(l, left_schema)? && references_only(r, right_schema)?
+    {
+        (l, r)
+    } else if references_only(r, left_schema)? && references_only(l, right_schema)? {
+        (r, l)
+    } else {
+        return Ok(None);
+    };
+
+    let l_type = l.get_type(left_schema)?;
+    let r_type = r.get_type(right_schema)?;
+    let key_type = match eq_coercion(&l_type, &r_type) {
+        Some(key_type) => key_type,
+        None => return Ok(None),
+    };
+    Ok(Some((
+        cast_to(l, &l_type, &key_type),
+        cast_to(r, &r_type, &key_type),
+    )))
+}
+
+/// Whether `expr` references columns, all of which are columns of `schema`
+fn references_only(expr: &Expr, schema: &DFSchema) -> Result<bool> {
+    let mut columns = HashSet::new();
+    utils::expr_to_columns(expr, &mut columns)?;
+    Ok(!columns.is_empty()
+        && columns
+            .iter()
+            .all(|column| schema.field_from_column(column).is_ok()))
+}
+
+fn cast_to(expr: &Expr, from: &DataType, to: &DataType) -> Expr {
+    if from == to {
+        expr.clone()
+    } else {
+        Expr::Cast {
+            expr: Box::new(expr.clone()),
+            data_type: to.clone(),
+        }
+    }
+}
+
+/// Returns `plan` with the keys that are not columns of it computed by a projection,
+/// and the columns of the keys
+fn project_keys(
+    plan: &LogicalPlan,
+    keys: Vec<Expr>,
+) -> Result<(LogicalPlan, Vec<Column>)> {
+    let schema = plan.schema();
+    let mut exprs: Vec<Expr> = schema
+        .fields()
+        .iter()
+        .map(|field| Expr::Column(field.qualified_column()))
+        .collect();
+    let mut columns = vec![];
+    for key in keys {
+        match key {
+            Expr::Column(column) => columns.push(column),
+            key => {
+                let name = key.name(schema)?;
+                if !exprs
+                    .iter()
+                    .any(|expr| matches!(expr, Expr::Alias(_, alias) if *alias == name))
+                {
+                    exprs.push(key.alias(&name));
+                }
+                columns.push(Column::from_name(name));
+            }
+        }
+    }
+    if exprs.len() == schema.fields().len() {
+        return Ok((plan.clone(), columns));
+    }
+    let plan = LogicalPlanBuilder::from(plan.clone())
+        .project(exprs)?
+        .build()?;
+    Ok((plan, columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::{col, lit};
+    use crate::test::*;
+
+    fn assert_optimized_plan_eq(plan: &LogicalPlan, expected: &str) {
+        let rule = EliminateCrossJoin::new();
+        let optimized_plan = rule
+            .optimize(plan, &ExecutionProps::new())
+            .expect("failed to optimize plan");
+        let formatted_plan = format!("{:?}", optimized_plan);
+        assert_eq!(formatted_plan, expected);
+        assert_eq!(plan.schema(), optimized_plan.schema());
+    }
+
+    #[test]
+    fn extract_join_keys() -> Result<()> {
+        let t1 = test_table_scan_with_name("t1")?;
+        let t2 = test_table_scan_with_name("t2")?;
+        let plan = LogicalPlanBuilder::from(t1)
+            .cross_join(&t2)?
+            .filter(
+                col("t2.a")
+                    .eq(col("t1.a"))
+                    .and(col("t1.b").gt(lit(5u32)))
+                    .and(col("t1.c").eq(col("t2.c"))),
+            )?
+            .build()?;
+
+        let expected = "Filter: #t1.b Gt UInt32(5)\
+        \n  Join: #t1.a = #t2.a, #t1.c = #t2.c\
+        \n    TableScan: t1 projection=None\
+        \n    TableScan: t2 projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_expression_keys() -> Result<()> {
+        let t1 = test_table_scan_with_name("t1")?;
+        let t2 = test_table_scan_with_name("t2")?;
+        let plan = LogicalPlanBuilder::from(t1)
+            .cross_join(&t2)?
+            .filter((col("t1.a") + lit(1u32)).eq(col("t2.b")))?
+            .build()?;
+
+        let expected = "Projection: #t1.a, #t1.b, #t1.c, #t2.a, #t2.b, #t2.c\
+        \n  Join: #t1.a Plus UInt32(1) = #t2.b\
+        \n    Projection: #t1.a, #t1.b, #t1.c, #t1.a Plus UInt32(1) AS t1.a Plus UInt32(1)\
+        \n      TableScan: t1 projection=None\
+        \n    TableScan: t2 projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn keep_cross_join_without_equality() -> Result<()> {
+        let t1 = test_table_scan_with_name("t1")?;
+        let t2 = test_table_scan_with_name("t2")?;
+        // an equality between the columns of the same side is not a join key
+        let plan = LogicalPlanBuilder::from(t1)
+            .cross_join(&t2)?
+            .filter(col("t1.a").eq(col("t1.b")).and(col("t1.c").lt(col("t2.c"))))?
+            .build()?;
+
+        let expected = "Filter: #t1.a Eq #t1.b And #t1.c Lt #t2.c\
+        \n  CrossJoin:\
+        \n    TableScan: t1 projection=None\
+        \n    TableScan: t2 projection=None";
+        assert_optimized_plan_eq(&plan, expected);
+        Ok(())
+    }
+}
@@ -41,7 +41,7 @@ impl OptimizerRule for EliminateLimit {
         execution_props: &ExecutionProps,
     ) -> Result<LogicalPlan> {
         match plan {
-            LogicalPlan::Limit { n, input } if *n == 0 => {
+            LogicalPlan::Limit { n, input, .. } if *n == 0 => {
                 Ok(LogicalPlan::EmptyRelation {
                     produce_one_row: false,
                     schema: input.schema().clone(),
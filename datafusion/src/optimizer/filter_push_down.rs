@@ -16,7 +16,7 @@
 
 use crate::datasource::datasource::TableProviderFilterPushDown;
 use crate::execution::context::ExecutionProps;
-use crate::logical_plan::{and, replace_col, Column, LogicalPlan};
+use crate::logical_plan::{and, replace_col, Column, JoinType, LogicalPlan};
 use crate::logical_plan::{DFSchema, Expr};
 use crate::optimizer::optimizer::OptimizerRule;
 use crate::optimizer::utils;
@@ -386,7 +386,11 @@ fn optimize(plan: &LogicalPlan, mut state: State) -> Result<LogicalPlan> {
             optimize_join(state, plan, left, right)
         }
         LogicalPlan::Join {
-            left, right, on, ..
+            left,
+            right,
+            on,
+            join_type,
+            ..
         } => {
             // duplicate filters for joined columns so filters can be pushed down to both sides.
             // Take the following query as an example:
@@ -400,48 +404,52 @@ fn optimize(plan: &LogicalPlan, mut state: State) -> Result<LogicalPlan> {
             //
             // Join clauses with `Using` constraints also take advantage of this logic to make sure
             // predicates reference the shared join columns are pushed to both sides.
-            let join_side_filters = state
-                .filters
-                .iter()
-                .filter_map(|(predicate, columns)| {
-                    let mut join_cols_to_replace = HashMap::new();
-                    for col in columns.iter() {
-                        for (l, r) in on {
-                            if col == l {
-                                join_cols_to_replace.insert(col, r);
-                                break;
-                            } else if col == r {
-                                join_cols_to_replace.insert(col, l);
-                                break;
+            // filtering the right side of a null aware anti join could remove its NULL
+            // keys or all of its rows, which changes the result
+            if *join_type != JoinType::NullAwareAnti {
+                let join_side_filters = state
+                    .filters
+                    .iter()
+                    .filter_map(|(predicate, columns)| {
+                        let mut join_cols_to_replace = HashMap::new();
+                        for col in columns.iter() {
+                            for (l, r) in on {
+                                if col == l {
+                                    join_cols_to_replace.insert(col, r);
+                                    break;
+                                } else if col == r {
+                                    join_cols_to_replace.insert(col, l);
+                                    break;
+                                }
                             }
                         }
-                    }
 
-                    if join_cols_to_replace.is_empty() {
-                        return None;
-                    }
+                        if join_cols_to_replace.is_empty() {
+                            return None;
+                        }
 
-                    let join_side_predicate =
-                        match replace_col(predicate.clone(), &join_cols_to_replace) {
-                            Ok(p) => p,
-                            Err(e) => {
-                                return Some(Err(e));
-                            }
-                        };
-
-                    let join_side_columns = columns
-                        .clone()
-                        .into_iter()
-                        // replace keys in join_cols_to_replace with values in resulting column
-                        // set
-                        .filter(|c| !join_cols_to_replace.contains_key(c))
-                        .chain(join_cols_to_replace.iter().map(|(_, v)| (*v).clone()))
-                        .collect();
-
-                    Some(Ok((join_side_predicate, join_side_columns)))
-                })
-                .collect::<Result<Vec<_>>>()?;
-            state.filters.extend(join_side_filters);
+                        let join_side_predicate =
+                            match replace_col(predicate.clone(), &join_cols_to_replace) {
+                                Ok(p) => p,
+                                Err(e) => {
+                                    return Some(Err(e));
+                                }
+                            };
+
+                        let join_side_columns = columns
+                            .clone()
+                            .into_iter()
+                            // replace keys in join_cols_to_replace with values in resulting column
+                            // set
+                            .filter(|c| !join_cols_to_replace.contains_key(c))
+                            .chain(join_cols_to_replace.iter().map(|(_, v)| (*v).clone()))
+                            .collect();
+
+                        Some(Ok((join_side_predicate, join_side_columns)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                state.filters.extend(join_side_filters);
+            }
 
             optimize_join(state, plan, left, right)
         }
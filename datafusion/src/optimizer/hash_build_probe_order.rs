@@ -22,7 +22,7 @@
 
 use std::sync::Arc;
 
-use crate::logical_plan::{Expr, LogicalPlan, LogicalPlanBuilder};
+use crate::logical_plan::{Column, Expr, LogicalPlan, LogicalPlanBuilder};
 use crate::optimizer::optimizer::OptimizerRule;
 use crate::{error::Result, prelude::JoinType};
 
@@ -37,8 +37,10 @@ use crate::execution::context::ExecutionProps;
 /// so that it could be optimized manually in a query.
 pub struct HashBuildProbeOrder {}
 
-// Gets exact number of rows, if known by the statistics of the underlying
-fn get_num_rows(logical_plan: &LogicalPlan) -> Option<usize> {
+// Gets the number of rows, if known by the statistics of the underlying tables. The
+// number of rows of joins and aggregations is estimated from the distinct counts of
+// their keys.
+pub(crate) fn get_num_rows(logical_plan: &LogicalPlan) -> Option<usize> {
     match logical_plan {
         LogicalPlan::TableScan { source, .. } => source.statistics().num_rows,
         LogicalPlan::EmptyRelation {
@@ -50,27 +52,60 @@ fn get_num_rows(logical_plan: &LogicalPlan) -> Option<usize> {
                 Some(0)
             }
         }
-        LogicalPlan::Limit { n: limit, input } => {
+        LogicalPlan::Limit {
+            n: limit,
+            offset,
+            input,
+        } => {
             let num_rows_input = get_num_rows(input);
-            num_rows_input.map(|rows| std::cmp::min(*limit, rows))
+            num_rows_input.map(|rows| std::cmp::min(*limit, rows.saturating_sub(*offset)))
         }
         LogicalPlan::Window { input, .. } => {
             // window functions do not change num of rows
             get_num_rows(input)
         }
-        LogicalPlan::Aggregate { .. } => {
-            // we cannot yet predict how many rows will be produced by an aggregate because
-            // we do not know the cardinality of the grouping keys
-            None
-        }
+        LogicalPlan::Aggregate {
+            input, group_expr, ..
+        } => estimate_num_groups(input, group_expr),
         LogicalPlan::Filter { .. } => {
             // we cannot yet predict how many rows will be produced by a filter because
             // we don't know how selective it is (how many rows it will filter out)
             None
         }
-        LogicalPlan::Join { .. } => {
-            // we cannot predict the cardinality of the join output
-            None
+        LogicalPlan::Join {
+            left,
+            right,
+            on,
+            join_type,
+            ..
+        } => {
+            let left_rows = get_num_rows(left)?;
+            let right_rows = get_num_rows(right)?;
+            // each key value of the side with the most distinct keys matches the rows
+            // with that value on the other side
+            let max_distinct = on
+                .iter()
+                .filter_map(|(l, r)| {
+                    match (get_distinct_count(left, l), get_distinct_count(right, r)) {
+                        (Some(l), Some(r)) => Some(l.max(r)),
+                        (Some(d), None) | (None, Some(d)) => Some(d),
+                        (None, None) => None,
+                    }
+                })
+                .max()?;
+            let inner_rows = if max_distinct > 0 {
+                left_rows.saturating_mul(right_rows) / max_distinct
+            } else {
+                0
+            };
+            match join_type {
+                JoinType::Inner => Some(inner_rows),
+                JoinType::Left => Some(inner_rows.max(left_rows)),
+                JoinType::Right => Some(inner_rows.max(right_rows)),
+                JoinType::Full => Some(inner_rows.max(left_rows).max(right_rows)),
+                JoinType::Semi => Some(inner_rows.min(left_rows)),
+                JoinType::Anti | JoinType::NullAwareAnti => None,
+            }
         }
         LogicalPlan::CrossJoin { left, right, .. } => {
             // number of rows is equal to num_left * num_right
@@ -80,8 +115,14 @@ fn get_num_rows(logical_plan: &LogicalPlan) -> Option<usize> {
             // we cannot predict how rows will be repartitioned
             None
         }
+        LogicalPlan::Unnest { .. } => {
+            // we cannot predict how many elements the unnested lists contain
+            None
+        }
         // the following operators are special cases and not querying data
         LogicalPlan::CreateExternalTable { .. } => None,
+        LogicalPlan::CreateFunction { .. } => None,
+        LogicalPlan::CopyTo { .. } => None,
         LogicalPlan::Explain { .. } => None,
         // we do not support estimating rows with extensions yet
         LogicalPlan::Extension { .. } => None,
@@ -95,6 +136,76 @@ fn get_num_rows(logical_plan: &LogicalPlan) -> Option<usize> {
     }
 }
 
+/// Estimates the number of groups of an aggregation of `input` by `group_expr`, which
+/// is the product of the distinct counts of the grouping columns, or None if any of
+/// them is unknown.
+pub(crate) fn estimate_num_groups(
+    input: &LogicalPlan,
+    group_expr: &[Expr],
+) -> Option<usize> {
+    let input_rows = get_num_rows(input)?;
+    if group_expr.is_empty() {
+        return Some(1);
+    }
+    let groups = group_expr
+        .iter()
+        .try_fold(1usize, |groups, expr| match expr {
+            Expr::Column(c) => Some(groups.saturating_mul(get_distinct_count(input, c)?)),
+            _ => None,
+        })?;
+    Some(groups.min(input_rows))
+}
+
+/// Gets the number of distinct values of `column` in the output of `plan`, if known by
+/// the statistics of the table that the column comes from
+fn get_distinct_count(plan: &LogicalPlan, column: &Column) -> Option<usize> {
+    match plan {
+        LogicalPlan::TableScan { source, .. } => {
+            let column_statistics = source.statistics().column_statistics?;
+            let index = source.schema().index_of(&column.name).ok()?;
+            column_statistics.get(index)?.distinct_count
+        }
+        LogicalPlan::Projection {
+            expr,
+            input,
+            schema,
+        } => {
+            let index = schema.index_of_column(column).ok()?;
+            match expr.get(index)? {
+                Expr::Column(c) => get_distinct_count(input, c),
+                Expr::Alias(expr, _) => match expr.as_ref() {
+                    Expr::Column(c) => get_distinct_count(input, c),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        // the following operators keep the values of the columns of their input, and at
+        // most its distinct values
+        LogicalPlan::Filter { input, .. }
+        | LogicalPlan::Sort { input, .. }
+        | LogicalPlan::Limit { input, .. }
+        | LogicalPlan::Repartition { input, .. }
+        | LogicalPlan::Window { input, .. }
+        | LogicalPlan::Aggregate { input, .. } => {
+            let distinct_count = get_distinct_count(input, column)?;
+            Some(match get_num_rows(plan) {
+                Some(rows) => distinct_count.min(rows),
+                None => distinct_count,
+            })
+        }
+        LogicalPlan::Join { left, right, .. }
+        | LogicalPlan::CrossJoin { left, right, .. } => {
+            if left.schema().index_of_column(column).is_ok() {
+                get_distinct_count(left, column)
+            } else {
+                get_distinct_count(right, column)
+            }
+        }
+        _ => None,
+    }
+}
+
 // Finds out whether to swap left vs right order based on statistics
 fn should_swap_join_order(left: &LogicalPlan, right: &LogicalPlan) -> bool {
     let left_rows = get_num_rows(left);
@@ -109,7 +220,7 @@ fn should_swap_join_order(left: &LogicalPlan, right: &LogicalPlan) -> bool {
 fn supports_swap(join_type: JoinType) -> bool {
     match join_type {
         JoinType::Inner | JoinType::Left | JoinType::Right | JoinType::Full => true,
-        JoinType::Semi | JoinType::Anti => false,
+        JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => false,
     }
 }
 
@@ -132,6 +243,7 @@ impl OptimizerRule for HashBuildProbeOrder {
                 on,
                 join_type,
                 join_constraint,
+                null_equals_null,
                 schema,
             } => {
                 let left = self.optimize(left, execution_props)?;
@@ -144,6 +256,7 @@ impl OptimizerRule for HashBuildProbeOrder {
                         on: on.iter().map(|(l, r)| (r.clone(), l.clone())).collect(),
                         join_type: swap_join_type(*join_type),
                         join_constraint: *join_constraint,
+                        null_equals_null: *null_equals_null,
                         schema: schema.clone(),
                     })
                 } else {
@@ -154,6 +267,7 @@ impl OptimizerRule for HashBuildProbeOrder {
                         on: on.clone(),
                         join_type: *join_type,
                         join_constraint: *join_constraint,
+                        null_equals_null: *null_equals_null,
                         schema: schema.clone(),
                     })
                 }
@@ -200,8 +314,11 @@ impl OptimizerRule for HashBuildProbeOrder {
             | LogicalPlan::EmptyRelation { .. }
             | LogicalPlan::Sort { .. }
             | LogicalPlan::CreateExternalTable { .. }
+            | LogicalPlan::CreateFunction { .. }
+            | LogicalPlan::CopyTo { .. }
             | LogicalPlan::Explain { .. }
             | LogicalPlan::Union { .. }
+            | LogicalPlan::Unnest { .. }
             | LogicalPlan::Extension { .. } => {
                 let expr = plan.expressions();
 
@@ -42,14 +42,16 @@ fn limit_push_down(
     execution_props: &ExecutionProps,
 ) -> Result<LogicalPlan> {
     match (plan, upper_limit) {
-        (LogicalPlan::Limit { n, input }, upper_limit) => {
+        (LogicalPlan::Limit { n, offset, input }, upper_limit) => {
             let smallest = upper_limit.map(|x| std::cmp::min(x, *n)).unwrap_or(*n);
             Ok(LogicalPlan::Limit {
                 n: smallest,
-                // push down limit to plan (minimum of upper limit and current limit)
+                offset: *offset,
+                // push down limit to plan (minimum of upper limit and current limit),
+                // which needs to include the rows skipped by the offset
                 input: Arc::new(limit_push_down(
                     optimizer,
-                    Some(smallest),
+                    Some(smallest.saturating_add(*offset)),
                     input.as_ref(),
                     execution_props,
                 )?),
@@ -107,15 +109,23 @@ fn limit_push_down(
             let new_inputs = inputs
                 .iter()
                 .map(|x| {
-                    Ok(LogicalPlan::Limit {
-                        n: upper_limit,
-                        input: Arc::new(limit_push_down(
-                            optimizer,
-                            Some(upper_limit),
-                            x,
-                            execution_props,
-                        )?),
-                    })
+                    let input = limit_push_down(
+                        optimizer,
+                        Some(upper_limit),
+                        x,
+                        execution_props,
+                    )?;
+                    // inputs that are already limited, e.g. by an earlier pass of this
+                    // rule, are not limited again
+                    if matches!(input, LogicalPlan::Limit { .. }) {
+                        Ok(input)
+                    } else {
+                        Ok(LogicalPlan::Limit {
+                            n: upper_limit,
+                            offset: 0,
+                            input: Arc::new(input),
+                        })
+                    }
                 })
                 .collect::<Result<_>>()?;
             Ok(LogicalPlan::Union {
@@ -212,6 +222,27 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn limit_push_down_with_offset() -> Result<()> {
+        let table_scan = test_table_scan()?;
+
+        let plan = LogicalPlanBuilder::from(table_scan)
+            .project(vec![col("a")])?
+            .limit_with_offset(10, 100)?
+            .limit(5)?
+            .build()?;
+
+        // The scan needs to produce the skipped rows as well
+        let expected = "Limit: 5\
+        \n  Limit: 5, offset=100\
+        \n    Projection: #test.a\
+        \n      TableScan: test projection=None, limit=105";
+
+        assert_optimized_plan_eq(&plan, expected);
+
+        Ok(())
+    }
+
     #[test]
     fn limit_doesnt_push_down_aggregation() -> Result<()> {
         let table_scan = test_table_scan()?;
@@ -250,6 +281,11 @@ mod test {
 
         assert_optimized_plan_eq(&plan, expected);
 
+        // the limits are not pushed down again
+        let optimized_plan =
+            LimitPushDown::new().optimize(&plan, &ExecutionProps::new())?;
+        assert_optimized_plan_eq(&optimized_plan, expected);
+
         Ok(())
     }
 
@@ -18,8 +18,10 @@
 //! This module contains a query optimizer that operates against a logical plan and applies
 //! some simple rules to a logical plan, such as "Projection Push Down" and "Type Coercion".
 
+pub mod aggregate_push_down;
 pub mod aggregate_statistics;
 pub mod constant_folding;
+pub mod eliminate_cross_join;
 pub mod eliminate_limit;
 pub mod filter_push_down;
 pub mod hash_build_probe_order;
@@ -17,6 +17,10 @@
 
 //! Query optimizer traits
 
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use crate::error::Result;
 use crate::execution::context::ExecutionProps;
 use crate::logical_plan::LogicalPlan;
@@ -35,3 +39,148 @@ pub trait OptimizerRule {
     /// A human readable name for this optimizer rule
     fn name(&self) -> &str;
 }
+
+/// Applies a list of [`OptimizerRule`]s to a plan in passes until a pass leaves the
+/// plan unchanged, so that a rule can rewrite what later rules exposed.
+pub struct Optimizer {
+    /// The rules, in the order that they are applied in each pass
+    rules: Vec<Arc<dyn OptimizerRule + Send + Sync>>,
+    /// Maximum number of passes over the rules
+    max_passes: usize,
+    /// Names of the rules that are skipped
+    disabled_rules: HashSet<String>,
+}
+
+impl Optimizer {
+    /// Create an optimizer that applies `rules` in a single pass
+    pub fn new(rules: Vec<Arc<dyn OptimizerRule + Send + Sync>>) -> Self {
+        Self {
+            rules,
+            max_passes: 1,
+            disabled_rules: HashSet::new(),
+        }
+    }
+
+    /// Apply the rules at most `max_passes` times
+    pub fn with_max_passes(mut self, max_passes: usize) -> Self {
+        // there must be at least one pass
+        assert!(max_passes > 0);
+        self.max_passes = max_passes;
+        self
+    }
+
+    /// Skip the rules with the given names
+    pub fn with_disabled_rules(mut self, disabled_rules: HashSet<String>) -> Self {
+        self.disabled_rules = disabled_rules;
+        self
+    }
+
+    /// Optimize `plan`, calling `observer` with the plan produced by each rule, the
+    /// rule, the pass starting at 1, and the time that the rule took.
+    pub fn optimize<F>(
+        &self,
+        plan: &LogicalPlan,
+        execution_props: &ExecutionProps,
+        mut observer: F,
+    ) -> Result<LogicalPlan>
+    where
+        F: FnMut(&LogicalPlan, &dyn OptimizerRule, usize, Duration),
+    {
+        let mut new_plan = plan.clone();
+        let mut previous = format!("{}", new_plan.display_indent_schema());
+        for pass in 1..=self.max_passes {
+            for rule in &self.rules {
+                if self.disabled_rules.contains(rule.name()) {
+                    continue;
+                }
+                let start = Instant::now();
+                new_plan = rule.optimize(&new_plan, execution_props)?;
+                observer(&new_plan, rule.as_ref(), pass, start.elapsed());
+            }
+            let current = format!("{}", new_plan.display_indent_schema());
+            if current == previous {
+                break;
+            }
+            previous = current;
+        }
+        Ok(new_plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::{col, LogicalPlanBuilder};
+    use crate::test::test_table_scan;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Adds a limit on top of the plan the first `n` times it is applied
+    struct AddLimit {
+        remaining: AtomicUsize,
+    }
+
+    impl OptimizerRule for AddLimit {
+        fn optimize(
+            &self,
+            plan: &LogicalPlan,
+            _execution_props: &ExecutionProps,
+        ) -> Result<LogicalPlan> {
+            if self.remaining.load(Ordering::SeqCst) == 0 {
+                return Ok(plan.clone());
+            }
+            self.remaining.fetch_sub(1, Ordering::SeqCst);
+            LogicalPlanBuilder::from(plan.clone()).limit(10)?.build()
+        }
+
+        fn name(&self) -> &str {
+            "add_limit"
+        }
+    }
+
+    fn optimize(optimizer: &Optimizer) -> Result<(String, Vec<usize>)> {
+        let plan = LogicalPlanBuilder::from(test_table_scan()?)
+            .project(vec![col("a")])?
+            .build()?;
+        let mut passes = vec![];
+        let plan =
+            optimizer.optimize(&plan, &ExecutionProps::new(), |_, _, pass, _| {
+                passes.push(pass)
+            })?;
+        Ok((format!("{:?}", plan), passes))
+    }
+
+    fn add_limit(n: usize) -> Vec<Arc<dyn OptimizerRule + Send + Sync>> {
+        vec![Arc::new(AddLimit {
+            remaining: AtomicUsize::new(n),
+        })]
+    }
+
+    #[test]
+    fn fixed_point() -> Result<()> {
+        // the third pass does not change the plan
+        let (plan, passes) = optimize(&Optimizer::new(add_limit(2)).with_max_passes(5))?;
+        assert_eq!(passes, vec![1, 2, 3]);
+        assert_eq!(
+            plan,
+            "Limit: 10\n  Limit: 10\n    Projection: #test.a\n      TableScan: test projection=None"
+        );
+
+        // the number of passes is limited
+        let (_, passes) = optimize(&Optimizer::new(add_limit(5)).with_max_passes(2))?;
+        assert_eq!(passes, vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_rules() -> Result<()> {
+        let optimizer = Optimizer::new(add_limit(1))
+            .with_disabled_rules(vec!["add_limit".to_owned()].into_iter().collect());
+        let (plan, passes) = optimize(&optimizer)?;
+        assert!(passes.is_empty());
+        assert_eq!(
+            plan,
+            "Projection: #test.a\n  TableScan: test projection=None"
+        );
+        Ok(())
+    }
+}
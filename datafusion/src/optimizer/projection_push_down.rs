@@ -199,6 +199,7 @@ fn optimize_plan(
             on,
             join_type,
             join_constraint,
+            null_equals_null,
             ..
         } => {
             for (l, r) in on {
@@ -234,6 +235,7 @@ fn optimize_plan(
                 join_type: *join_type,
                 join_constraint: *join_constraint,
                 on: on.clone(),
+                null_equals_null: *null_equals_null,
                 schema: DFSchemaRef::new(schema),
             })
         }
@@ -356,6 +358,23 @@ fn optimize_plan(
         LogicalPlan::Explain { .. } => Err(DataFusionError::Internal(
             "Unsupported logical plan: Explain must be root of the plan".to_string(),
         )),
+        LogicalPlan::CopyTo { input, .. } => {
+            // all columns of the input are written
+            let required_columns = input
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.qualified_column())
+                .collect();
+            let new_input = optimize_plan(
+                optimizer,
+                input,
+                &required_columns,
+                has_projection,
+                execution_props,
+            )?;
+            utils::from_plan(plan, &[], &[new_input])
+        }
         LogicalPlan::Union {
             inputs,
             schema,
@@ -406,7 +425,9 @@ fn optimize_plan(
         | LogicalPlan::EmptyRelation { .. }
         | LogicalPlan::Sort { .. }
         | LogicalPlan::CreateExternalTable { .. }
+        | LogicalPlan::CreateFunction { .. }
         | LogicalPlan::CrossJoin { .. }
+        | LogicalPlan::Unnest { .. }
         | LogicalPlan::Extension { .. } => {
             let expr = plan.expressions();
             // collect all required columns by this plan
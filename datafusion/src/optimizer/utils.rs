@@ -91,6 +91,21 @@ pub fn expr_to_columns(expr: &Expr, accum: &mut HashSet<Column>) -> Result<()> {
     Ok(())
 }
 
+/// Splits a conjunction of predicates into the individual predicates
+pub fn split_conjunction(expr: &Expr, accum: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        } => {
+            split_conjunction(left, accum);
+            split_conjunction(right, accum);
+        }
+        other => accum.push(other.clone()),
+    }
+}
+
 /// Convenience rule for writing optimizers: recursively invoke
 /// optimize on plan's children and then return a node of the same
 /// type. Useful for optimizer rules which want to leave the type
@@ -165,6 +180,7 @@ pub fn from_plan(
             join_type,
             join_constraint,
             on,
+            null_equals_null,
             ..
         } => {
             let schema =
@@ -175,6 +191,7 @@ pub fn from_plan(
                 join_type: *join_type,
                 join_constraint: *join_constraint,
                 on: on.clone(),
+                null_equals_null: *null_equals_null,
                 schema: DFSchemaRef::new(schema),
             })
         }
@@ -183,8 +200,23 @@ pub fn from_plan(
             let right = &inputs[1];
             LogicalPlanBuilder::from(left).cross_join(right)?.build()
         }
-        LogicalPlan::Limit { n, .. } => Ok(LogicalPlan::Limit {
+        LogicalPlan::Unnest { column, .. } => LogicalPlanBuilder::from(inputs[0].clone())
+            .unnest_column(column.clone())?
+            .build(),
+        LogicalPlan::CopyTo {
+            path,
+            options,
+            schema,
+            ..
+        } => Ok(LogicalPlan::CopyTo {
+            input: Arc::new(inputs[0].clone()),
+            path: path.clone(),
+            options: options.clone(),
+            schema: schema.clone(),
+        }),
+        LogicalPlan::Limit { n, offset, .. } => Ok(LogicalPlan::Limit {
             n: *n,
+            offset: *offset,
             input: Arc::new(inputs[0].clone()),
         }),
         LogicalPlan::Extension { node } => Ok(LogicalPlan::Extension {
@@ -198,6 +230,7 @@ pub fn from_plan(
         LogicalPlan::EmptyRelation { .. }
         | LogicalPlan::TableScan { .. }
         | LogicalPlan::CreateExternalTable { .. }
+        | LogicalPlan::CreateFunction { .. }
         | LogicalPlan::Explain { .. } => Ok(plan.clone()),
     }
 }
@@ -227,8 +260,13 @@ pub fn expr_sub_expressions(expr: &Expr) -> Result<Vec<Expr>> {
             expr_list.extend(order_by.clone());
             Ok(expr_list)
         }
-        Expr::AggregateFunction { args, .. } => Ok(args.clone()),
-        Expr::AggregateUDF { args, .. } => Ok(args.clone()),
+        // the filter of an aggregate follows its arguments
+        Expr::AggregateFunction { args, filter, .. }
+        | Expr::AggregateUDF { args, filter, .. } => Ok(args
+            .iter()
+            .chain(filter.iter().map(|filter| filter.as_ref()))
+            .cloned()
+            .collect()),
         Expr::Case {
             expr,
             when_then_expr,
@@ -279,6 +317,20 @@ pub fn expr_sub_expressions(expr: &Expr) -> Result<Vec<Expr>> {
     }
 }
 
+/// Splits the sub expressions of an aggregate into its arguments and its filter, which
+/// is the last one if the aggregate has a filter
+fn split_aggregate_filter(
+    expressions: &[Expr],
+    has_filter: bool,
+) -> (Vec<Expr>, Option<Box<Expr>>) {
+    match expressions.split_last() {
+        Some((filter, args)) if has_filter => {
+            (args.to_vec(), Some(Box::new(filter.clone())))
+        }
+        _ => (expressions.to_vec(), None),
+    }
+}
+
 /// returns a new expression where the expressions in `expr` are replaced by the ones in
 /// `expressions`.
 /// This is used in conjunction with ``expr_expressions`` to re-write expressions.
@@ -300,7 +352,10 @@ pub fn rewrite_expression(expr: &Expr, expressions: &[Expr]) -> Result<Expr> {
             args: expressions.to_vec(),
         }),
         Expr::WindowFunction {
-            fun, window_frame, ..
+            fun,
+            window_frame,
+            distinct,
+            ..
         } => {
             let partition_index = expressions
                 .iter()
@@ -339,18 +394,32 @@ pub fn rewrite_expression(expr: &Expr, expressions: &[Expr]) -> Result<Expr> {
                     partition_by: expressions[partition_index + 1..sort_index].to_vec(),
                     order_by: expressions[sort_index + 1..].to_vec(),
                     window_frame: *window_frame,
+                    distinct: *distinct,
                 })
             }
         }
-        Expr::AggregateFunction { fun, distinct, .. } => Ok(Expr::AggregateFunction {
-            fun: fun.clone(),
-            args: expressions.to_vec(),
-            distinct: *distinct,
-        }),
-        Expr::AggregateUDF { fun, .. } => Ok(Expr::AggregateUDF {
-            fun: fun.clone(),
-            args: expressions.to_vec(),
-        }),
+        Expr::AggregateFunction {
+            fun,
+            distinct,
+            filter,
+            ..
+        } => {
+            let (args, filter) = split_aggregate_filter(expressions, filter.is_some());
+            Ok(Expr::AggregateFunction {
+                fun: fun.clone(),
+                args,
+                distinct: *distinct,
+                filter,
+            })
+        }
+        Expr::AggregateUDF { fun, filter, .. } => {
+            let (args, filter) = split_aggregate_filter(expressions, filter.is_some());
+            Ok(Expr::AggregateUDF {
+                fun: fun.clone(),
+                args,
+                filter,
+            })
+        }
         Expr::Case { .. } => {
             let mut base_expr: Option<Box<Expr>> = None;
             let mut when_then: Vec<(Box<Expr>, Box<Expr>)> = vec![];
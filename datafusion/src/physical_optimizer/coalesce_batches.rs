@@ -22,8 +22,9 @@ use super::optimizer::PhysicalOptimizerRule;
 use crate::{
     error::Result,
     physical_plan::{
-        coalesce_batches::CoalesceBatchesExec, filter::FilterExec,
-        hash_join::HashJoinExec, repartition::RepartitionExec,
+        coalesce_batches::CoalesceBatchesExec, cross_join::CrossJoinExec,
+        filter::FilterExec, hash_join::HashJoinExec, interval_join::IntervalJoinExec,
+        repartition::RepartitionExec,
     },
 };
 use std::sync::Arc;
@@ -56,9 +57,14 @@ impl PhysicalOptimizerRule for CoalesceBatches {
         // or having an API so that operators can declare when their inputs or outputs
         // need to be wrapped in a coalesce batches operator.
         // See https://issues.apache.org/jira/browse/ARROW-11068
-        let wrap_in_coalesce = plan_any.downcast_ref::<FilterExec>().is_some()
-            || plan_any.downcast_ref::<HashJoinExec>().is_some()
-            || plan_any.downcast_ref::<RepartitionExec>().is_some();
+        // unbounded inputs are not wrapped, as buffering could hold back results
+        // until more data arrives, which may never happen
+        let wrap_in_coalesce = !plan.unbounded()
+            && (plan_any.downcast_ref::<FilterExec>().is_some()
+                || plan_any.downcast_ref::<HashJoinExec>().is_some()
+                || plan_any.downcast_ref::<CrossJoinExec>().is_some()
+                || plan_any.downcast_ref::<IntervalJoinExec>().is_some()
+                || plan_any.downcast_ref::<RepartitionExec>().is_some());
 
         //TODO we should also do this for HashAggregateExec but we need to update tests
         // as part of this work - see https://issues.apache.org/jira/browse/ARROW-11068
@@ -70,12 +76,10 @@ impl PhysicalOptimizerRule for CoalesceBatches {
         } else {
             let plan = plan.with_new_children(children)?;
             Ok(if wrap_in_coalesce {
-                //TODO we should add specific configuration settings for coalescing batches and
-                // we should do that once https://issues.apache.org/jira/browse/ARROW-11059 is
-                // implemented. For now, we choose half the configured batch size to avoid copies
-                // when a small number of rows are removed from a batch
-                let target_batch_size = config.batch_size / 2;
-                Arc::new(CoalesceBatchesExec::new(plan.clone(), target_batch_size))
+                Arc::new(CoalesceBatchesExec::new(
+                    plan.clone(),
+                    config.coalesce_target_batch_size(),
+                ))
             } else {
                 plan.clone()
             })
@@ -86,3 +90,47 @@ impl PhysicalOptimizerRule for CoalesceBatches {
         "coalesce_batches"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::context::ExecutionConfig;
+    use crate::physical_plan::expressions::lit;
+    use crate::physical_plan::memory::MemoryExec;
+    use crate::physical_plan::ExecutionPlan;
+    use crate::scalar::ScalarValue;
+    use arrow::datatypes::Schema;
+
+    fn filter() -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = Arc::new(Schema::empty());
+        let input = Arc::new(MemoryExec::try_new(&[vec![]], schema, None)?);
+        Ok(Arc::new(FilterExec::try_new(
+            lit(ScalarValue::Boolean(Some(true))),
+            input,
+        )?))
+    }
+
+    fn target_batch_size(plan: &Arc<dyn ExecutionPlan>) -> Option<usize> {
+        plan.as_any()
+            .downcast_ref::<CoalesceBatchesExec>()
+            .map(|coalesce| coalesce.target_batch_size())
+    }
+
+    #[test]
+    fn defaults_to_half_the_batch_size() -> Result<()> {
+        let config = ExecutionConfig::new().with_batch_size(1000);
+        let plan = CoalesceBatches::new().optimize(filter()?, &config)?;
+        assert_eq!(target_batch_size(&plan), Some(500));
+        Ok(())
+    }
+
+    #[test]
+    fn configured_target_batch_size() -> Result<()> {
+        let config = ExecutionConfig::new()
+            .with_batch_size(1000)
+            .with_coalesce_target_batch_size(300);
+        let plan = CoalesceBatches::new().optimize(filter()?, &config)?;
+        assert_eq!(target_batch_size(&plan), Some(300));
+        Ok(())
+    }
+}
@@ -23,3 +23,4 @@ pub mod merge_exec;
 pub mod optimizer;
 pub mod pruning;
 pub mod repartition;
+pub mod sorted_aggregate;
@@ -22,11 +22,14 @@
 //!
 //! For example, it is used to prune (skip) row groups while reading
 //! parquet files if it can be determined from the predicate that
-//! nothing in the row group can match.
+//! nothing in the row group can match, and to skip the directories of
+//! a [`PartitionedTable`](crate::datasource::partitioned::PartitionedTable)
+//! whose partition values cannot match.
 //!
-//! This code is currently specific to Parquet, but soon (TM), via
-//! https://github.com/apache/arrow-datafusion/issues/363 it will
-//! be genericized.
+//! Any [`TableProvider`](crate::datasource::TableProvider) can prune
+//! the containers it reads by implementing [`PruningStatistics`] for
+//! them and evaluating a [`PruningPredicate`] built from the filters
+//! pushed down to its scan.
 
 use std::convert::TryFrom;
 use std::{collections::HashSet, sync::Arc};
@@ -63,6 +66,8 @@ use crate::{
 /// max_values("a") -> Some([20, Null, 30])
 /// min_values("X") -> None
 /// ```
+///
+/// Containers whose statistics are unknown are never pruned.
 pub trait PruningStatistics {
     /// return the minimum values for the named column, if known.
     /// Note: the returned array must contain `num_containers()` rows
@@ -72,6 +77,13 @@ pub trait PruningStatistics {
     /// Note: the returned array must contain `num_containers()` rows.
     fn max_values(&self, column: &Column) -> Option<ArrayRef>;
 
+    /// return the number of null values of the named column in each
+    /// container as a `UInt64Array`, if known.
+    /// Note: the returned array must contain `num_containers()` rows.
+    fn null_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        None
+    }
+
     /// return the number of containers (e.g. row groups) being
     /// pruned with these statistics
     fn num_containers(&self) -> usize;
@@ -281,6 +293,22 @@ impl RequiredStatColumns {
     ) -> Result<Expr> {
         self.stat_column_expr(column, column_expr, field, StatisticsType::Max, "max")
     }
+
+    /// rewrite col --> col_null_count
+    fn null_count_column_expr(
+        &mut self,
+        column: &Column,
+        column_expr: &Expr,
+    ) -> Result<Expr> {
+        let field = Field::new(&column.flat_name(), DataType::UInt64, true);
+        self.stat_column_expr(
+            column,
+            column_expr,
+            &field,
+            StatisticsType::NullCount,
+            "null_count",
+        )
+    }
 }
 
 impl From<Vec<(Column, StatisticsType, Field)>> for RequiredStatColumns {
@@ -329,6 +357,7 @@ fn build_statistics_record_batch<S: PruningStatistics>(
         let array = match statistics_type {
             StatisticsType::Min => statistics.min_values(column),
             StatisticsType::Max => statistics.max_values(column),
+            StatisticsType::NullCount => statistics.null_counts(column),
         };
         let array = array.unwrap_or_else(|| new_null_array(data_type, num_containers));
 
@@ -617,6 +646,35 @@ fn build_predicate_expression(
                 return Ok(unhandled);
             }
         }
+        // col IS NULL => col_null_count > 0
+        Expr::IsNull(input) => {
+            let expr = match input.as_ref() {
+                Expr::Column(col) if schema.field_with_name(&col.name).is_ok() => {
+                    required_columns
+                        .null_count_column_expr(col, input)?
+                        .gt(logical_plan::lit(0u64))
+                }
+                _ => unhandled,
+            };
+            return Ok(expr);
+        }
+        // col IN (a, b) => col = a OR col = b
+        Expr::InList {
+            expr,
+            list,
+            negated: false,
+        } if !list.is_empty() => {
+            let mut predicates = list.iter().map(|value| {
+                let eq = logical_plan::binary_expr(
+                    expr.as_ref().clone(),
+                    Operator::Eq,
+                    value.clone(),
+                );
+                build_predicate_expression(&eq, schema, required_columns)
+            });
+            let first = predicates.next().unwrap()?;
+            return predicates.try_fold(first, |acc, predicate| Ok(acc.or(predicate?)));
+        }
         _ => {
             return Ok(unhandled);
         }
@@ -702,6 +760,7 @@ fn build_statistics_expr(expr_builder: &mut PruningExpressionBuilder) -> Result<
 enum StatisticsType {
     Min,
     Max,
+    NullCount,
 }
 
 #[cfg(test)]
@@ -712,7 +771,7 @@ mod tests {
     use crate::logical_plan::{col, lit};
     use crate::{assert_batches_eq, physical_optimizer::pruning::StatisticsType};
     use arrow::{
-        array::{BinaryArray, Int32Array, Int64Array, StringArray},
+        array::{BinaryArray, Int32Array, Int64Array, StringArray, UInt64Array},
         datatypes::{DataType, TimeUnit},
     };
 
@@ -721,6 +780,7 @@ mod tests {
     struct ContainerStats {
         min: ArrayRef,
         max: ArrayRef,
+        null_counts: Option<ArrayRef>,
     }
 
     impl ContainerStats {
@@ -731,6 +791,7 @@ mod tests {
             Self {
                 min: Arc::new(min.into_iter().collect::<Int32Array>()),
                 max: Arc::new(max.into_iter().collect::<Int32Array>()),
+                null_counts: None,
             }
         }
 
@@ -741,6 +802,7 @@ mod tests {
             Self {
                 min: Arc::new(min.into_iter().collect::<StringArray>()),
                 max: Arc::new(max.into_iter().collect::<StringArray>()),
+                null_counts: None,
             }
         }
 
@@ -751,9 +813,19 @@ mod tests {
             Self {
                 min: Arc::new(min.into_iter().collect::<BooleanArray>()),
                 max: Arc::new(max.into_iter().collect::<BooleanArray>()),
+                null_counts: None,
             }
         }
 
+        fn with_null_counts(
+            mut self,
+            null_counts: impl IntoIterator<Item = Option<u64>>,
+        ) -> Self {
+            self.null_counts =
+                Some(Arc::new(null_counts.into_iter().collect::<UInt64Array>()));
+            self
+        }
+
         fn min(&self) -> Option<ArrayRef> {
             Some(self.min.clone())
         }
@@ -805,6 +877,12 @@ mod tests {
                 .unwrap_or(None)
         }
 
+        fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+            self.stats
+                .get(column)
+                .and_then(|container_stats| container_stats.null_counts.clone())
+        }
+
         fn num_containers(&self) -> usize {
             self.stats
                 .values()
@@ -1290,6 +1368,54 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn prune_is_null() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s1", DataType::Int32, true)]));
+        let expr = col("s1").is_null();
+
+        let statistics = TestStatistics::new().with(
+            "s1",
+            ContainerStats::new_i32(
+                vec![Some(0), Some(4), Some(3)], // min
+                vec![Some(5), Some(6), Some(9)], // max
+            )
+            .with_null_counts(vec![Some(0), Some(2), None]),
+        );
+        // no nulls ==> no rows should pass
+        // 2 nulls ==> some rows could pass
+        // unknown null count ==> some rows could pass
+        let p = PruningPredicate::try_new(&expr, schema.clone()).unwrap();
+        assert_eq!(p.prune(&statistics).unwrap(), vec![false, true, true]);
+
+        // without null counts nothing is pruned
+        let statistics = TestStatistics::new()
+            .with("s1", ContainerStats::new_i32(vec![Some(0)], vec![Some(5)]));
+        let p = PruningPredicate::try_new(&expr, schema).unwrap();
+        assert_eq!(p.prune(&statistics).unwrap(), vec![true]);
+    }
+
+    #[test]
+    fn prune_in_list() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s1", DataType::Int32, true)]));
+        let expr = col("s1").in_list(vec![lit(1), lit(7)], false);
+
+        let statistics = TestStatistics::new().with(
+            "s1",
+            ContainerStats::new_i32(
+                vec![Some(0), Some(2), Some(6)], // min
+                vec![Some(1), Some(5), Some(9)], // max
+            ),
+        );
+        // [0, 1] contains 1, [2, 5] contains neither, [6, 9] contains 7
+        let p = PruningPredicate::try_new(&expr, schema.clone()).unwrap();
+        assert_eq!(p.prune(&statistics).unwrap(), vec![true, false, true]);
+
+        // NOT IN is not used for pruning
+        let expr = col("s1").in_list(vec![lit(1), lit(7)], true);
+        let p = PruningPredicate::try_new(&expr, schema).unwrap();
+        assert_eq!(p.prune(&statistics).unwrap(), vec![true, true, true]);
+    }
+
     #[test]
     fn prune_not_eq_data() {
         let schema = Arc::new(Schema::new(vec![Field::new("s1", DataType::Utf8, true)]));
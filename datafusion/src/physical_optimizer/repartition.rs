@@ -76,7 +76,15 @@ fn optimize_concurrency(
     // But also not very useful to inlude
     let is_empty_exec = plan.as_any().downcast_ref::<EmptyExec>().is_some();
 
-    if perform_repartition && !requires_single_partition && !is_empty_exec {
+    // the order of an unbounded input, such as the order of the time column of a
+    // stream, cannot be restored by sorting it, so it is not scattered
+    let is_ordered_stream = new_plan.unbounded() && new_plan.output_ordering().is_some();
+
+    if perform_repartition
+        && !requires_single_partition
+        && !is_empty_exec
+        && !is_ordered_stream
+    {
         Ok(Arc::new(RepartitionExec::try_new(
             new_plan,
             RoundRobinBatch(concurrency),
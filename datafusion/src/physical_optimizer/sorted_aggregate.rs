@@ -0,0 +1,149 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! SortedAggregate optimizer rule that replaces hash aggregates of inputs that are
+//! sorted on the group keys by sorted aggregates, which do not keep all groups in memory
+use super::optimizer::PhysicalOptimizerRule;
+use crate::{
+    error::Result,
+    execution::context::ExecutionConfig,
+    physical_plan::{
+        hash_aggregate::HashAggregateExec,
+        sorted_aggregate::{is_grouped_by_ordering, SortedAggregateExec},
+        ExecutionPlan,
+    },
+};
+use std::sync::Arc;
+
+/// Optimizer rule that selects the [`SortedAggregateExec`] for aggregates whose input is
+/// sorted on the group keys
+pub struct SortedAggregate {}
+
+impl SortedAggregate {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PhysicalOptimizerRule for SortedAggregate {
+    fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        config: &ExecutionConfig,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if plan.children().is_empty() {
+            // leaf node, children cannot be replaced
+            return Ok(plan);
+        }
+        // the inputs are optimized first, so that the final aggregate of a sorted
+        // partial aggregate sees its ordering
+        let children = plan
+            .children()
+            .iter()
+            .map(|child| self.optimize(child.clone(), config))
+            .collect::<Result<Vec<_>>>()?;
+        let plan = plan.with_new_children(children)?;
+
+        match plan.as_any().downcast_ref::<HashAggregateExec>() {
+            Some(aggregate)
+                if aggregate
+                    .input()
+                    .output_ordering()
+                    .map_or(false, |ordering| {
+                        is_grouped_by_ordering(aggregate.group_expr(), &ordering)
+                    }) =>
+            {
+                Ok(Arc::new(SortedAggregateExec::try_new(
+                    *aggregate.mode(),
+                    aggregate.group_expr().to_vec(),
+                    aggregate.aggr_expr().to_vec(),
+                    aggregate.input().clone(),
+                    aggregate.input_schema(),
+                )?))
+            }
+            _ => Ok(plan),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "sorted_aggregate"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physical_plan::expressions::{col, Count, PhysicalSortExpr};
+    use crate::physical_plan::hash_aggregate::AggregateMode;
+    use crate::physical_plan::memory::MemoryExec;
+    use crate::physical_plan::sort::SortExec;
+    use crate::physical_plan::{displayable, AggregateExpr, PhysicalExpr};
+    use arrow::compute::SortOptions;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    fn aggregate(input: Arc<dyn ExecutionPlan>) -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = input.schema();
+        let groups: Vec<(Arc<dyn PhysicalExpr>, String)> =
+            vec![(col("a", &schema)?, "a".to_owned())];
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(Count::new(
+            col("b", &schema)?,
+            "COUNT(b)".to_owned(),
+            DataType::UInt64,
+        ))];
+        Ok(Arc::new(HashAggregateExec::try_new(
+            AggregateMode::Partial,
+            groups,
+            aggregates,
+            input,
+            schema.clone(),
+        )?))
+    }
+
+    fn optimize(plan: Arc<dyn ExecutionPlan>) -> Result<String> {
+        let plan = SortedAggregate::new().optimize(plan, &ExecutionConfig::new())?;
+        Ok(displayable(plan.as_ref()).indent().to_string())
+    }
+
+    #[test]
+    fn sorted_input() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("b", DataType::Int64, false),
+        ]));
+        let input = Arc::new(MemoryExec::try_new(&[vec![]], schema.clone(), None)?);
+        let sort = |column: &str| -> Result<Arc<dyn ExecutionPlan>> {
+            Ok(Arc::new(SortExec::try_new(
+                vec![PhysicalSortExpr {
+                    expr: col(column, &schema)?,
+                    options: SortOptions::default(),
+                }],
+                input.clone(),
+            )?))
+        };
+
+        let plan = optimize(aggregate(sort("a")?)?)?;
+        assert!(plan.starts_with("SortedAggregateExec: mode=Partial, gby=[a@0 as a]"));
+
+        // the input is not sorted on the group keys
+        let plan = optimize(aggregate(sort("b")?)?)?;
+        assert!(plan.starts_with("HashAggregateExec"));
+        let plan = optimize(aggregate(input.clone())?)?;
+        assert!(plan.starts_with("HashAggregateExec"));
+        Ok(())
+    }
+}
@@ -33,8 +33,9 @@ use super::{
 };
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::distinct_expressions;
-use crate::physical_plan::expressions;
-use arrow::datatypes::{DataType, Schema, TimeUnit};
+use crate::physical_plan::expressions::{self, PhysicalSortExpr};
+use crate::scalar::ScalarValue;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
 use expressions::{avg_return_type, sum_return_type};
 use std::{fmt, str::FromStr, sync::Arc};
 /// the implementation of an aggregate function
@@ -59,12 +60,26 @@ pub enum AggregateFunction {
     Max,
     /// avg
     Avg,
+    /// array_agg, the values in the order of its `ORDER BY` clause
+    ArrayAgg,
+    /// first_value, the first value in the order of its `ORDER BY` clause
+    FirstValue,
+    /// last_value, the last value in the order of its `ORDER BY` clause
+    LastValue,
+    /// string_agg, the concatenation of the strings with a delimiter
+    StringAgg,
 }
 
 impl fmt::Display for AggregateFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // uppercase of the debug.
-        write!(f, "{}", format!("{:?}", self).to_uppercase())
+        match self {
+            AggregateFunction::ArrayAgg => write!(f, "ARRAY_AGG"),
+            AggregateFunction::FirstValue => write!(f, "FIRST_VALUE"),
+            AggregateFunction::LastValue => write!(f, "LAST_VALUE"),
+            AggregateFunction::StringAgg => write!(f, "STRING_AGG"),
+            // uppercase of the debug.
+            _ => write!(f, "{}", format!("{:?}", self).to_uppercase()),
+        }
     }
 }
 
@@ -77,6 +92,10 @@ impl FromStr for AggregateFunction {
             "count" => AggregateFunction::Count,
             "avg" => AggregateFunction::Avg,
             "sum" => AggregateFunction::Sum,
+            "array_agg" => AggregateFunction::ArrayAgg,
+            "first_value" => AggregateFunction::FirstValue,
+            "last_value" => AggregateFunction::LastValue,
+            "string_agg" => AggregateFunction::StringAgg,
             _ => {
                 return Err(DataFusionError::Plan(format!(
                     "There is no built-in function named {}",
@@ -100,6 +119,14 @@ pub fn return_type(fun: &AggregateFunction, arg_types: &[DataType]) -> Result<Da
         AggregateFunction::Max | AggregateFunction::Min => Ok(arg_types[0].clone()),
         AggregateFunction::Sum => sum_return_type(&arg_types[0]),
         AggregateFunction::Avg => avg_return_type(&arg_types[0]),
+        AggregateFunction::ArrayAgg => Ok(DataType::List(Box::new(Field::new(
+            "item",
+            arg_types[0].clone(),
+            true,
+        )))),
+        AggregateFunction::FirstValue
+        | AggregateFunction::LastValue
+        | AggregateFunction::StringAgg => Ok(arg_types[0].clone()),
     }
 }
 
@@ -111,8 +138,35 @@ pub fn create_aggregate_expr(
     args: &[Arc<dyn PhysicalExpr>],
     input_schema: &Schema,
     name: impl Into<String>,
+) -> Result<Arc<dyn AggregateExpr>> {
+    create_aggregate_expr_with_ordering(fun, distinct, args, &[], input_schema, name)
+}
+
+/// Create a physical (function) expression whose input rows are sorted by `ordering`,
+/// the `ORDER BY` clause of the arguments of `ARRAY_AGG`, `FIRST_VALUE`, `LAST_VALUE`
+/// and `STRING_AGG`. The other functions do not accept an ordering.
+pub fn create_aggregate_expr_with_ordering(
+    fun: &AggregateFunction,
+    distinct: bool,
+    args: &[Arc<dyn PhysicalExpr>],
+    ordering: &[PhysicalSortExpr],
+    input_schema: &Schema,
+    name: impl Into<String>,
 ) -> Result<Arc<dyn AggregateExpr>> {
     let name = name.into();
+    let ordered = matches!(
+        fun,
+        AggregateFunction::ArrayAgg
+            | AggregateFunction::FirstValue
+            | AggregateFunction::LastValue
+            | AggregateFunction::StringAgg
+    );
+    if !(ordering.is_empty() || ordered) {
+        return Err(DataFusionError::Plan(format!(
+            "{} does not support ORDER BY in its arguments",
+            fun
+        )));
+    }
     let arg = coerce(args, input_schema, &signature(fun))?;
     if arg.is_empty() {
         return Err(DataFusionError::Plan(format!(
@@ -163,9 +217,57 @@ pub fn create_aggregate_expr(
                 "AVG(DISTINCT) aggregations are not available".to_string(),
             ));
         }
+        (
+            AggregateFunction::ArrayAgg
+            | AggregateFunction::FirstValue
+            | AggregateFunction::LastValue
+            | AggregateFunction::StringAgg,
+            false,
+        ) => {
+            let delimiter = match fun {
+                AggregateFunction::StringAgg => Some(string_agg_delimiter(&args[1])?),
+                _ => None,
+            };
+            Arc::new(expressions::OrderedAggregate::try_new(
+                fun.clone(),
+                arg,
+                arg_types[0].clone(),
+                delimiter,
+                ordering.to_vec(),
+                input_schema,
+                name,
+            )?)
+        }
+        (
+            AggregateFunction::ArrayAgg
+            | AggregateFunction::FirstValue
+            | AggregateFunction::LastValue
+            | AggregateFunction::StringAgg,
+            true,
+        ) => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "{}(DISTINCT) aggregations are not available",
+                fun
+            )));
+        }
     })
 }
 
+/// The delimiter of `STRING_AGG`, which must be a string literal
+fn string_agg_delimiter(arg: &Arc<dyn PhysicalExpr>) -> Result<String> {
+    match arg
+        .as_any()
+        .downcast_ref::<expressions::Literal>()
+        .map(|literal| literal.value())
+    {
+        Some(ScalarValue::Utf8(Some(delimiter)))
+        | Some(ScalarValue::LargeUtf8(Some(delimiter))) => Ok(delimiter.clone()),
+        _ => Err(DataFusionError::Plan(
+            "The delimiter of STRING_AGG must be a string literal".to_string(),
+        )),
+    }
+}
+
 static STRINGS: &[DataType] = &[DataType::Utf8, DataType::LargeUtf8];
 
 static NUMERICS: &[DataType] = &[
@@ -208,6 +310,20 @@ pub fn signature(fun: &AggregateFunction) -> Signature {
         AggregateFunction::Avg | AggregateFunction::Sum => {
             Signature::Uniform(1, NUMERICS.to_vec())
         }
+        AggregateFunction::ArrayAgg
+        | AggregateFunction::FirstValue
+        | AggregateFunction::LastValue => {
+            let valid = STRINGS
+                .iter()
+                .chain(NUMERICS.iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            Signature::Uniform(1, valid)
+        }
+        AggregateFunction::StringAgg => Signature::OneOf(vec![
+            Signature::Exact(vec![DataType::Utf8, DataType::Utf8]),
+            Signature::Exact(vec![DataType::LargeUtf8, DataType::Utf8]),
+        ]),
     }
 }
 
@@ -259,6 +375,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_ordered_return_types() -> Result<()> {
+        let observed = return_type(&AggregateFunction::ArrayAgg, &[DataType::Int32])?;
+        assert_eq!(
+            DataType::List(Box::new(Field::new("item", DataType::Int32, true))),
+            observed
+        );
+
+        let observed = return_type(&AggregateFunction::FirstValue, &[DataType::Utf8])?;
+        assert_eq!(DataType::Utf8, observed);
+
+        let observed = return_type(
+            &AggregateFunction::StringAgg,
+            &[DataType::LargeUtf8, DataType::Utf8],
+        )?;
+        assert_eq!(DataType::LargeUtf8, observed);
+
+        let observed = return_type(&AggregateFunction::StringAgg, &[DataType::Utf8]);
+        assert!(observed.is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_avg_no_utf8() {
         let observed = return_type(&AggregateFunction::Avg, &[DataType::Utf8]);
@@ -23,7 +23,9 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
+use crate::physical_plan::expressions::PhysicalSortExpr;
 use crate::physical_plan::{
     DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
     SendableRecordBatchStream,
@@ -90,6 +92,10 @@ impl ExecutionPlan for CoalesceBatchesExec {
         self.input.output_partitioning()
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        self.input.output_ordering()
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -131,6 +137,10 @@ impl ExecutionPlan for CoalesceBatchesExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
 }
 
 struct CoalesceBatchesStream {
@@ -30,7 +30,9 @@ use arrow::record_batch::RecordBatch;
 use arrow::{datatypes::SchemaRef, error::Result as ArrowResult};
 
 use super::RecordBatchStream;
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
+use crate::physical_plan::expressions::PhysicalSortExpr;
 use crate::physical_plan::{DisplayFormatType, ExecutionPlan, Partitioning};
 
 use super::SendableRecordBatchStream;
@@ -77,6 +79,15 @@ impl ExecutionPlan for CoalescePartitionsExec {
         Partitioning::UnknownPartitioning(1)
     }
 
+    /// The rows of a single input partition keep their order
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        if self.input.output_partitioning().partition_count() == 1 {
+            self.input.output_ordering()
+        } else {
+            None
+        }
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -139,6 +150,10 @@ impl ExecutionPlan for CoalescePartitionsExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
 }
 
 pin_project! {
@@ -27,8 +27,10 @@ use arrow::error::Result as ArrowResult;
 use arrow::record_batch::RecordBatch;
 use futures::channel::mpsc;
 use futures::{SinkExt, Stream, StreamExt, TryStreamExt};
+use std::ffi::OsStr;
 use std::fs;
 use std::fs::metadata;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::task::JoinHandle;
@@ -107,40 +109,274 @@ pub(crate) fn combine_batches(
     }
 }
 
+/// Options of the listing of the files of a path by [build_file_list_with_options]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileListOptions {
+    /// Number of levels of directories below the listed directory that are searched
+    /// for files, where 0 only lists the files directly in the directory. Unlimited
+    /// if None, the default.
+    pub max_depth: Option<usize>,
+    /// Whether hidden files and directories, whose name starts with `.` or `_`, are
+    /// listed. Defaults to false, which skips files such as `_SUCCESS` markers and
+    /// the temporary files of writers.
+    pub include_hidden: bool,
+}
+
+impl FileListOptions {
+    /// Create file list options with default presets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of levels of directories that are searched for files
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Configure whether hidden files and directories are listed
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+}
+
 /// Recursively builds a list of files in a directory with a given extension
 pub fn build_file_list(dir: &str, ext: &str) -> Result<Vec<String>> {
-    let mut filenames: Vec<String> = Vec::new();
-    build_file_list_recurse(dir, &mut filenames, ext)?;
-    Ok(filenames)
+    build_file_list_with_extensions(dir, &[ext])
 }
 
-/// Recursively build a list of files in a directory with a given extension with an accumulator list
-fn build_file_list_recurse(
+/// Recursively builds a list of files in a directory with any of the given extensions
+pub fn build_file_list_with_extensions<S: AsRef<str>>(
     dir: &str,
-    filenames: &mut Vec<String>,
-    ext: &str,
-) -> Result<()> {
-    let metadata = metadata(dir)?;
-    if metadata.is_file() {
-        if dir.ends_with(ext) {
-            filenames.push(dir.to_string());
+    exts: &[S],
+) -> Result<Vec<String>> {
+    build_file_list_with_options(dir, exts, &FileListOptions::default())
+}
+
+/// Builds a list of the files with any of the given extensions at `path`, which is a
+/// file, a directory that is searched recursively, or a glob pattern.
+///
+/// In a pattern, `*` matches any sequence of characters within a file or directory
+/// name, `?` matches any character, `[abc]` and `[a-z]` match any of the characters
+/// of the set (any other character if it starts with `!`), and a `**` component
+/// matches any number of levels of directories, e.g. `data/**/*.parquet`. Directories
+/// matched by a pattern are searched recursively. The depth of the directories is
+/// counted from the last component of `path` without wildcards.
+pub fn build_file_list_with_options<S: AsRef<str>>(
+    path: &str,
+    exts: &[S],
+    options: &FileListOptions,
+) -> Result<Vec<String>> {
+    let mut lister = FileLister {
+        exts,
+        options,
+        filenames: vec![],
+    };
+    let path = Path::new(path);
+    let components = path.components().collect::<Vec<_>>();
+    match components
+        .iter()
+        .position(|c| is_glob_pattern(c.as_os_str()))
+    {
+        None => lister.list(path, 0)?,
+        Some(index) => {
+            let base = components[..index].iter().collect::<PathBuf>();
+            let base = if base.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                base
+            };
+            let patterns = components[index..]
+                .iter()
+                .map(|c| c.as_os_str().to_str().ok_or_else(invalid_path))
+                .collect::<Result<Vec<_>>>()?;
+            lister.list_matches(&base, &patterns, 0)?;
         }
-    } else {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some(path_name) = path.to_str() {
-                if path.is_dir() {
-                    build_file_list_recurse(path_name, filenames, ext)?;
-                } else if path_name.ends_with(ext) {
-                    filenames.push(path_name.to_string());
+    }
+    Ok(lister.filenames)
+}
+
+/// Accumulates the files found by [build_file_list_with_options]
+struct FileLister<'a, S> {
+    exts: &'a [S],
+    options: &'a FileListOptions,
+    filenames: Vec<String>,
+}
+
+impl<'a, S: AsRef<str>> FileLister<'a, S> {
+    /// Lists `path`, which is a file or a directory at `depth`
+    fn list(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let path_name = path.to_str().ok_or_else(invalid_path)?;
+        if metadata(path)?.is_file() {
+            if self
+                .exts
+                .iter()
+                .any(|ext| path_name.ends_with(ext.as_ref()))
+            {
+                self.filenames.push(path_name.to_string());
+            }
+            return Ok(());
+        }
+        for entry in self.read_dir(path)? {
+            if entry.is_dir() {
+                if self.descends_into(depth) {
+                    self.list(&entry, depth + 1)?;
                 }
             } else {
-                return Err(DataFusionError::Plan("Invalid path".to_string()));
+                self.list(&entry, depth)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists the entries below `dir`, a directory at `depth`, that match `patterns`,
+    /// the components of a glob pattern
+    fn list_matches(
+        &mut self,
+        dir: &Path,
+        patterns: &[&str],
+        depth: usize,
+    ) -> Result<()> {
+        let (pattern, rest) = match patterns.split_first() {
+            Some(split) => split,
+            None => return self.list(dir, depth),
+        };
+        if !metadata(dir)?.is_dir() {
+            return Ok(());
+        }
+        if *pattern == "**" {
+            // zero levels of directories, then one more
+            self.list_matches(dir, rest, depth)?;
+            if self.descends_into(depth) {
+                for entry in self.read_dir(dir)? {
+                    if entry.is_dir() {
+                        self.list_matches(&entry, patterns, depth + 1)?;
+                    }
+                }
+            }
+            return Ok(());
+        }
+        for entry in self.read_dir(dir)? {
+            let name = entry.file_name().and_then(|name| name.to_str());
+            if !name.map_or(false, |name| glob_match(pattern, name)) {
+                continue;
+            }
+            if !entry.is_dir() {
+                if rest.is_empty() {
+                    self.list(&entry, depth)?;
+                }
+            } else if self.descends_into(depth) {
+                self.list_matches(&entry, rest, depth + 1)?;
             }
         }
+        Ok(())
+    }
+
+    /// Whether the directories below a directory at `depth` are searched
+    fn descends_into(&self, depth: usize) -> bool {
+        self.options
+            .max_depth
+            .map_or(true, |max_depth| depth < max_depth)
+    }
+
+    /// Returns the paths of the entries of `dir`, without the hidden ones unless
+    /// they are included
+    fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().ok_or_else(invalid_path)?;
+            let name = name.to_str().ok_or_else(invalid_path)?;
+            if self.options.include_hidden || !is_hidden(name) {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+fn invalid_path() -> DataFusionError {
+    DataFusionError::Plan("Invalid path".to_string())
+}
+
+/// Whether a file or directory is hidden by convention
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.') || name.starts_with('_')
+}
+
+/// Whether a path component contains any wildcard
+fn is_glob_pattern(component: &OsStr) -> bool {
+    component
+        .to_str()
+        .map_or(false, |c| c.contains(|ch| matches!(ch, '*' | '?' | '[')))
+}
+
+/// Whether `name` matches the glob `pattern`, which does not match across `/`
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // the positions after the last `*` and the character of the name it matches up
+    // to, from where the matching backtracks when the rest of the pattern fails
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        let matched = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_char_class(&pattern[p..], name[n]).map(|len| p + len),
+            Some(ch) if *ch == name[n] => Some(p + 1),
+            _ => None,
+        };
+        match (matched, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                n += 1;
+            }
+            (None, Some((star_p, star_n))) => {
+                backtrack = Some((star_p, star_n + 1));
+                p = star_p;
+                n = star_n + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+/// Matches `ch` against the character class at the start of `pattern`, returning the
+/// length of the class if it matches
+fn match_char_class(pattern: &[char], ch: char) -> Option<usize> {
+    let negated = matches!(pattern.get(1), Some('!') | Some('^'));
+    let mut i = if negated { 2 } else { 1 };
+    let mut found = false;
+    let mut first = true;
+    while i < pattern.len() && (first || pattern[i] != ']') {
+        first = false;
+        if pattern.get(i + 1) == Some(&'-')
+            && i + 2 < pattern.len()
+            && pattern[i + 2] != ']'
+        {
+            found |= pattern[i] <= ch && ch <= pattern[i + 2];
+            i += 3;
+        } else {
+            found |= pattern[i] == ch;
+            i += 1;
+        }
+    }
+    if i >= pattern.len() {
+        // an unterminated class only matches a literal `[`
+        return if ch == '[' { Some(1) } else { None };
+    }
+    if found != negated {
+        Some(i + 1)
+    } else {
+        None
     }
-    Ok(())
 }
 
 /// Spawns a task to the tokio threadpool and writes its outputs to the provided mpsc sender
@@ -169,6 +405,19 @@ pub(crate) fn spawn_execution(
     })
 }
 
+/// Runs the blocking IO of `f`, such as writing a spill file, on the blocking thread
+/// pool of the tokio runtime, so that it does not hold up the tokio worker threads.
+/// CPU-bound work goes to the [CPU pool](crate::execution::cpu_pool) instead.
+pub(crate) async fn spawn_blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| DataFusionError::Execution(format!("Blocking task failed: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +466,105 @@ mod tests {
         assert_eq!(batch_count * batch_size, result.num_rows());
         Ok(())
     }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.csv", "a.csv"));
+        assert!(glob_match("*.csv", ".csv"));
+        assert!(!glob_match("*.csv", "a.csv.gz"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("part-?.parquet", "part-1.parquet"));
+        assert!(!glob_match("part-?.parquet", "part-10.parquet"));
+        assert!(glob_match("year=20[12][0-9]", "year=2019"));
+        assert!(!glob_match("year=20[12][0-9]", "year=2030"));
+        assert!(glob_match("[!a]*", "b"));
+        assert!(!glob_match("[!a]*", "abc"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_build_file_list_with_options() -> Result<()> {
+        let tmp_dir = tempfile::TempDir::new()?;
+        let root = tmp_dir.path();
+        for file in &[
+            "a.csv",
+            "_SUCCESS.csv",
+            "x/b.csv",
+            "x/b.json",
+            "x/.c.csv",
+            "x/y/d.csv",
+            "z/e.csv",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, "")?;
+        }
+        let list = |path: &str, options: FileListOptions| -> Result<Vec<String>> {
+            let path = root.join(path);
+            let mut files = build_file_list_with_options(
+                path.to_str().unwrap(),
+                &[".csv"],
+                &options,
+            )?
+            .into_iter()
+            .map(|file| {
+                Path::new(&file)
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+            files.sort();
+            Ok(files)
+        };
+        let default = FileListOptions::new();
+
+        assert_eq!(
+            list("", default)?,
+            vec!["a.csv", "x/b.csv", "x/y/d.csv", "z/e.csv"]
+        );
+        assert_eq!(
+            list("", default.include_hidden(true))?,
+            vec![
+                "_SUCCESS.csv",
+                "a.csv",
+                "x/.c.csv",
+                "x/b.csv",
+                "x/y/d.csv",
+                "z/e.csv"
+            ]
+        );
+        assert_eq!(list("", default.max_depth(0))?, vec!["a.csv"]);
+        assert_eq!(
+            list("", default.max_depth(1))?,
+            vec!["a.csv", "x/b.csv", "z/e.csv"]
+        );
+
+        // a file is listed even if it is hidden
+        assert_eq!(list("_SUCCESS.csv", default)?, vec!["_SUCCESS.csv"]);
+
+        // patterns
+        assert_eq!(list("*.csv", default)?, vec!["a.csv"]);
+        assert_eq!(list("x/*.csv", default)?, vec!["x/b.csv"]);
+        assert_eq!(
+            list("[xz]", default)?,
+            vec!["x/b.csv", "x/y/d.csv", "z/e.csv"]
+        );
+        assert_eq!(list("*/*.csv", default)?, vec!["x/b.csv", "z/e.csv"]);
+        assert_eq!(
+            list("**/*.csv", default)?,
+            vec!["a.csv", "x/b.csv", "x/y/d.csv", "z/e.csv"]
+        );
+        assert_eq!(
+            list("**/*.csv", default.max_depth(1))?,
+            vec!["a.csv", "x/b.csv", "z/e.csv"]
+        );
+        assert_eq!(list("x/**/d.csv", default)?, vec!["x/y/d.csv"]);
+        assert!(list("*.json", default)?.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,126 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Reuse of the arrays of constant values, such as literal projections and the
+//! partition columns of partitioned tables.
+//!
+//! An expression whose value is the same for every row evaluates to a
+//! [`ColumnarValue::Scalar`], which operators used to materialize into a new array for
+//! every batch. [`ConstantArrays`] materializes the value once and hands out zero-copy
+//! slices of it, which matters most after selective filters, when many small batches
+//! would each allocate their own copy of the constant.
+
+use arrow::array::ArrayRef;
+
+use super::ColumnarValue;
+use crate::scalar::ScalarValue;
+
+/// The array materialized for the constant value of one expression
+struct ConstantArray {
+    value: ScalarValue,
+    array: ArrayRef,
+}
+
+/// Cache of the arrays materialized for the constant values of a fixed list of
+/// expressions, such as the expressions of a projection
+pub struct ConstantArrays {
+    arrays: Vec<Option<ConstantArray>>,
+}
+
+impl ConstantArrays {
+    /// Create a cache for `num_exprs` expressions
+    pub fn new(num_exprs: usize) -> Self {
+        Self {
+            arrays: (0..num_exprs).map(|_| None).collect(),
+        }
+    }
+
+    /// Convert the value of the expression at `index` into an array of `num_rows`
+    /// rows. A scalar is materialized only when the expression had a different value
+    /// before, or only smaller arrays were materialized for it, otherwise the result
+    /// is a slice of the cached array.
+    pub fn into_array(
+        &mut self,
+        index: usize,
+        value: ColumnarValue,
+        num_rows: usize,
+    ) -> ArrayRef {
+        let value = match value {
+            ColumnarValue::Array(array) => return array,
+            ColumnarValue::Scalar(value) => value,
+        };
+        match &self.arrays[index] {
+            Some(cached) if cached.value == value && cached.array.len() >= num_rows => {
+                if cached.array.len() == num_rows {
+                    cached.array.clone()
+                } else {
+                    cached.array.slice(0, num_rows)
+                }
+            }
+            _ => {
+                let array = value.to_array_of_size(num_rows);
+                self.arrays[index] = Some(ConstantArray {
+                    value,
+                    array: array.clone(),
+                });
+                array
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int32Array, StringArray};
+    use std::sync::Arc;
+
+    #[test]
+    fn reuses_constant_arrays() {
+        let mut constants = ConstantArrays::new(2);
+        let value = || ColumnarValue::Scalar(ScalarValue::Utf8(Some("a".to_owned())));
+
+        let first = constants.into_array(0, value(), 8);
+        assert_eq!(first.len(), 8);
+        // smaller batches share the data of the materialized array
+        let second = constants.into_array(0, value(), 3);
+        assert_eq!(second.len(), 3);
+        assert_eq!(
+            second.data().buffers()[1].as_ptr(),
+            first.data().buffers()[1].as_ptr()
+        );
+        let strings = second.as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(strings.iter().all(|s| s == Some("a")));
+
+        // a larger batch or another value is materialized again
+        let third = constants.into_array(0, value(), 10);
+        assert_eq!(third.len(), 10);
+        assert_ne!(
+            third.data().buffers()[1].as_ptr(),
+            first.data().buffers()[1].as_ptr()
+        );
+        let other = ColumnarValue::Scalar(ScalarValue::Utf8(Some("b".to_owned())));
+        let fourth = constants.into_array(0, other, 4);
+        let strings = fourth.as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(strings.iter().all(|s| s == Some("b")));
+
+        // arrays are passed through
+        let array: ArrayRef = Arc::new(Int32Array::from(vec![1, 2]));
+        let result = constants.into_array(1, ColumnarValue::Array(array.clone()), 2);
+        assert!(Arc::ptr_eq(&array, &result));
+    }
+}
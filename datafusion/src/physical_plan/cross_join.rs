@@ -31,6 +31,7 @@ use super::{
     coalesce_partitions::CoalescePartitionsExec, hash_utils::check_join_is_valid,
 };
 use crate::{
+    datasource::datasource::Statistics,
     error::{DataFusionError, Result},
     scalar::ScalarValue,
 };
@@ -207,6 +208,24 @@ impl ExecutionPlan for CrossJoinExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        let left = self.left.statistics();
+        let right = self.right.statistics();
+        let num_rows = match (left.num_rows, right.num_rows) {
+            (Some(l), Some(r)) => Some(l.saturating_mul(r)),
+            _ => None,
+        };
+        let column_statistics = match (left.column_statistics, right.column_statistics) {
+            (Some(l), Some(r)) => Some(l.into_iter().chain(r).collect()),
+            _ => None,
+        };
+        Statistics {
+            num_rows,
+            total_byte_size: None,
+            column_statistics,
+        }
+    }
 }
 
 /// A stream that issues [RecordBatch]es as they arrive from the right  of the join.
@@ -18,16 +18,21 @@
 //! Execution plan for reading CSV files
 
 use crate::error::{DataFusionError, Result};
+use crate::physical_plan::file_compression::{read_lines, FileCompressionType};
 use crate::physical_plan::ExecutionPlan;
-use crate::physical_plan::{common, source::Source, Partitioning};
+use crate::physical_plan::{
+    common, common::FileListOptions, schema_adapter::SchemaAdapter, source::Source,
+    Partitioning,
+};
 use arrow::csv;
-use arrow::datatypes::{Schema, SchemaRef};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use arrow::error::Result as ArrowResult;
 use arrow::record_batch::RecordBatch;
 use futures::Stream;
 use std::any::Any;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -54,6 +59,16 @@ pub struct CsvReadOptions<'a> {
     /// File extension; only files with this extension are selected for data input.
     /// Defaults to ".csv".
     pub file_extension: &'a str,
+    /// Compression of the CSV files. If None, it is determined from the extension of
+    /// each file and files with the extension of any supported compression (e.g.
+    /// ".csv.gz") are selected for data input. Defaults to None.
+    pub file_compression_type: Option<FileCompressionType>,
+    /// How the files of a directory or glob pattern are listed
+    pub file_list_options: FileListOptions,
+    /// Uncompressed files larger than this number of bytes are split at record
+    /// boundaries into byte ranges of about this size, which are read by separate
+    /// partitions. Defaults to None, which reads each file with a single partition.
+    pub target_split_size: Option<usize>,
 }
 
 impl<'a> CsvReadOptions<'a> {
@@ -65,6 +80,9 @@ impl<'a> CsvReadOptions<'a> {
             schema_infer_max_records: 1000,
             delimiter: b',',
             file_extension: ".csv",
+            file_compression_type: None,
+            file_list_options: FileListOptions::default(),
+            target_split_size: None,
         }
     }
 
@@ -86,6 +104,27 @@ impl<'a> CsvReadOptions<'a> {
         self
     }
 
+    /// Specify the compression of the CSV files
+    pub fn file_compression_type(
+        mut self,
+        file_compression_type: FileCompressionType,
+    ) -> Self {
+        self.file_compression_type = Some(file_compression_type);
+        self
+    }
+
+    /// Specify how the files of a directory or glob pattern are listed
+    pub fn file_list_options(mut self, file_list_options: FileListOptions) -> Self {
+        self.file_list_options = file_list_options;
+        self
+    }
+
+    /// Specify the size of the byte ranges that large uncompressed files are split into
+    pub fn target_split_size(mut self, target_split_size: usize) -> Self {
+        self.target_split_size = Some(target_split_size);
+        self
+    }
+
     /// Configure delimiter setting with Option, None value will be ignored
     pub fn delimiter_option(mut self, delimiter: Option<u8>) -> Self {
         if let Some(d) = delimiter {
@@ -107,11 +146,25 @@ impl<'a> CsvReadOptions<'a> {
     }
 }
 
+/// A file, or a byte range of an uncompressed file, that is read by one partition
+#[derive(Debug, Clone, PartialEq)]
+struct CsvPartition {
+    /// Index of the file in the file names of the source
+    file_index: usize,
+    /// Range of bytes of the file whose records are read, or None to read the whole
+    /// file. The range is not aligned to the records, which are found when reading.
+    byte_range: Option<Range<u64>>,
+}
+
 /// Execution plan for scanning a CSV file
 #[derive(Debug, Clone)]
 pub struct CsvExec {
     /// Where the data comes from.
     source: Source,
+    /// What each partition reads when the data comes from files
+    partitions: Vec<CsvPartition>,
+    /// Size of the byte ranges that large uncompressed files are split into
+    target_split_size: Option<usize>,
     /// Schema representing the CSV file
     schema: SchemaRef,
     /// Does the CSV file have a header?
@@ -120,6 +173,8 @@ pub struct CsvExec {
     delimiter: Option<u8>,
     /// File extension
     file_extension: String,
+    /// Compression of the files, determined from the file extension if None
+    file_compression_type: Option<FileCompressionType>,
     /// Optional projection for which columns to load
     projection: Option<Vec<usize>>,
     /// Schema after the projection has been applied
@@ -141,7 +196,14 @@ impl CsvExec {
     ) -> Result<Self> {
         let file_extension = String::from(options.file_extension);
 
-        let filenames = common::build_file_list(path, file_extension.as_str())?;
+        let filenames = common::build_file_list_with_options(
+            path,
+            &FileCompressionType::file_extensions(
+                &file_extension,
+                options.file_compression_type,
+            ),
+            &options.file_list_options,
+        )?;
         if filenames.is_empty() {
             return Err(DataFusionError::Execution(format!(
                 "No files found at {path} with file extension {file_extension}",
@@ -160,15 +222,24 @@ impl CsvExec {
             Some(p) => Schema::new(p.iter().map(|i| schema.field(*i).clone()).collect()),
         };
 
+        let partitions = CsvExec::plan_partitions(
+            &filenames,
+            options.file_compression_type,
+            options.target_split_size,
+        )?;
+
         Ok(Self {
             source: Source::PartitionedFiles {
                 path: path.to_string(),
                 filenames,
             },
+            partitions,
+            target_split_size: options.target_split_size,
             schema: Arc::new(schema),
             has_header: options.has_header,
             delimiter: Some(options.delimiter),
             file_extension,
+            file_compression_type: options.file_compression_type,
             projection,
             projected_schema: Arc::new(projected_schema),
             batch_size,
@@ -200,10 +271,13 @@ impl CsvExec {
 
         Ok(Self {
             source: Source::Reader(Mutex::new(Some(Box::new(reader)))),
+            partitions: vec![],
+            target_split_size: None,
             schema: Arc::new(schema),
             has_header: options.has_header,
             delimiter: Some(options.delimiter),
             file_extension: String::new(),
+            file_compression_type: options.file_compression_type,
             projection,
             projected_schema: Arc::new(projected_schema),
             batch_size,
@@ -236,6 +310,11 @@ impl CsvExec {
         &self.file_extension
     }
 
+    /// Compression of the CSV files, determined from the extension of each file if None
+    pub fn file_compression_type(&self) -> Option<FileCompressionType> {
+        self.file_compression_type
+    }
+
     /// Get the schema of the CSV file
     pub fn file_schema(&self) -> SchemaRef {
         self.schema.clone()
@@ -256,17 +335,146 @@ impl CsvExec {
         self.limit
     }
 
+    /// Size of the byte ranges that large uncompressed files are split into, if any
+    pub fn target_split_size(&self) -> Option<usize> {
+        self.target_split_size
+    }
+
+    /// Matches the columns in the header of a file to the table columns by name. Returns
+    /// the schema to read the file with and the adapter of its batches to the table
+    /// schema, or None if the file has the columns of the table in the same order.
+    /// Files without any column of the table are read by position, as if they had no
+    /// header.
+    fn map_header(
+        &self,
+        filename: &str,
+        compression: FileCompressionType,
+    ) -> Result<Option<(SchemaRef, SchemaAdapter)>> {
+        let mut lines = read_lines(compression.decompress(File::open(filename)?)?, 1)?;
+        let (header, _) = csv::reader::infer_file_schema(
+            &mut lines,
+            self.delimiter.unwrap_or(b','),
+            Some(0),
+            true,
+        )?;
+        let names = header.fields().iter().map(|field| field.name());
+        if names
+            .clone()
+            .eq(self.schema.fields().iter().map(|field| field.name()))
+            || !names
+                .clone()
+                .any(|name| self.schema.field_with_name(name).is_ok())
+        {
+            return Ok(None);
+        }
+        // the columns that the table does not have are read as strings, if at all
+        let file_schema = Schema::new(
+            names
+                .map(|name| match self.schema.field_with_name(name) {
+                    Ok(field) => field.clone(),
+                    Err(_) => Field::new(name, DataType::Utf8, true),
+                })
+                .collect(),
+        );
+        let adapter = SchemaAdapter::new(self.schema.clone(), self.projection.as_deref());
+        Ok(Some((Arc::new(file_schema), adapter)))
+    }
+
+    /// Assigns each file to a partition, or each byte range of `target_split_size` if
+    /// the file is uncompressed and larger than that. Only the lengths of the files are
+    /// read, and the partitions read the records that start within their ranges.
+    fn plan_partitions(
+        filenames: &[String],
+        file_compression_type: Option<FileCompressionType>,
+        target_split_size: Option<usize>,
+    ) -> Result<Vec<CsvPartition>> {
+        let mut partitions = vec![];
+        for (file_index, filename) in filenames.iter().enumerate() {
+            let compression = file_compression_type
+                .unwrap_or_else(|| FileCompressionType::from_path(filename));
+            let split_size = match target_split_size {
+                Some(size) if compression == FileCompressionType::Uncompressed => {
+                    size.max(1) as u64
+                }
+                _ => {
+                    partitions.push(CsvPartition {
+                        file_index,
+                        byte_range: None,
+                    });
+                    continue;
+                }
+            };
+            let len = std::fs::metadata(filename)?.len();
+            if len <= split_size {
+                partitions.push(CsvPartition {
+                    file_index,
+                    byte_range: None,
+                });
+                continue;
+            }
+            let mut start = 0;
+            while start < len {
+                let end = (start + split_size).min(len);
+                partitions.push(CsvPartition {
+                    file_index,
+                    byte_range: Some(start..end),
+                });
+                start = end;
+            }
+        }
+        Ok(partitions)
+    }
+
     /// Infer schema for given CSV dataset
     pub fn try_infer_schema(
         filenames: &[String],
         options: &CsvReadOptions,
     ) -> Result<Schema> {
-        Ok(csv::infer_schema_from_files(
-            filenames,
-            options.delimiter,
-            Some(options.schema_infer_max_records),
-            options.has_header,
-        )?)
+        let compression_types = filenames
+            .iter()
+            .map(|f| {
+                options
+                    .file_compression_type
+                    .unwrap_or_else(|| FileCompressionType::from_path(f))
+            })
+            .collect::<Vec<_>>();
+        if compression_types
+            .iter()
+            .all(|t| *t == FileCompressionType::Uncompressed)
+        {
+            return Ok(csv::infer_schema_from_files(
+                filenames,
+                options.delimiter,
+                Some(options.schema_infer_max_records),
+                options.has_header,
+            )?);
+        }
+
+        // schema inference requires a seekable reader, so only the lines needed for
+        // inference are decompressed into memory
+        let mut schemas = vec![];
+        let mut records_to_read = options.schema_infer_max_records;
+        for (filename, compression) in filenames.iter().zip(compression_types) {
+            if records_to_read == 0 {
+                break;
+            }
+            let reader = compression.decompress(File::open(filename)?)?;
+            let header_lines = if options.has_header { 1 } else { 0 };
+            let mut lines = read_lines(reader, records_to_read + header_lines)?;
+            let (schema, records_read) = csv::reader::infer_file_schema(
+                &mut lines,
+                options.delimiter,
+                Some(records_to_read),
+                options.has_header,
+            )?;
+            if records_read == 0 {
+                continue;
+            }
+            schemas.push(schema);
+            records_to_read -= records_read;
+        }
+
+        Ok(Schema::try_merge(schemas)?)
     }
 }
 
@@ -285,7 +493,7 @@ impl ExecutionPlan for CsvExec {
     /// Get the output partitioning of this plan
     fn output_partitioning(&self) -> Partitioning {
         Partitioning::UnknownPartitioning(match &self.source {
-            Source::PartitionedFiles { filenames, .. } => filenames.len(),
+            Source::PartitionedFiles { .. } => self.partitions.len(),
             Source::Reader(_) => 1,
         })
     }
@@ -312,15 +520,39 @@ impl ExecutionPlan for CsvExec {
     async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
         match &self.source {
             Source::PartitionedFiles { filenames, .. } => {
-                Ok(Box::pin(CsvStream::try_new(
-                    &filenames[partition],
-                    self.schema.clone(),
-                    self.has_header,
-                    self.delimiter,
-                    &self.projection,
-                    self.batch_size,
-                    self.limit,
-                )?))
+                let CsvPartition {
+                    file_index,
+                    byte_range,
+                } = &self.partitions[partition];
+                let filename = &filenames[*file_index];
+                let compression = self
+                    .file_compression_type
+                    .unwrap_or_else(|| FileCompressionType::from_path(filename));
+                let schema_adapter = if self.has_header {
+                    self.map_header(filename, compression)?
+                } else {
+                    None
+                };
+                let (schema, projection) = match &schema_adapter {
+                    Some((file_schema, adapter)) => {
+                        (file_schema.clone(), Some(adapter.map_schema(file_schema)?))
+                    }
+                    None => (self.schema.clone(), self.projection.clone()),
+                };
+                Ok(Box::pin(
+                    CsvStream::try_new(
+                        filename,
+                        compression,
+                        byte_range.clone(),
+                        schema,
+                        self.has_header,
+                        self.delimiter,
+                        &projection,
+                        self.batch_size,
+                        self.limit,
+                    )?
+                    .with_schema_adapter(schema_adapter.map(|(_, adapter)| adapter)),
+                ))
             }
             Source::Reader(rdr) => {
                 if partition != 0 {
@@ -359,21 +591,66 @@ impl ExecutionPlan for CsvExec {
                     f,
                     "CsvExec: source={}, has_header={}",
                     self.source, self.has_header
-                )
+                )?;
+                if let Some(target_split_size) = self.target_split_size {
+                    write!(f, ", target_split_size={}", target_split_size)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the bytes of the records that start within `range` of a file that `reader`
+/// reads from its start. Newlines within quoted fields do not end records, so the quotes
+/// are tracked from the start of the file up to the end of the range, which is much
+/// cheaper than parsing them.
+fn record_range(reader: impl Read, range: &Range<u64>) -> Result<Range<u64>> {
+    let mut reader = BufReader::new(reader);
+    let mut start = if range.start == 0 { Some(0) } else { None };
+    let mut offset = 0;
+    let mut quoted = false;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let len = buf.len();
+        for &byte in buf {
+            offset += 1;
+            match byte {
+                // an escaped quote ("") toggles twice and leaves the state unchanged
+                b'"' => quoted = !quoted,
+                b'\n' if !quoted && offset >= range.start => match start {
+                    None if offset >= range.end => return Ok(offset..offset),
+                    None => start = Some(offset),
+                    Some(start) if offset >= range.end => return Ok(start..offset),
+                    Some(_) => {}
+                },
+                _ => {}
             }
         }
+        reader.consume(len);
     }
+    let start = start.unwrap_or(offset);
+    Ok(start..offset)
 }
 
 /// Iterator over batches
 struct CsvStream<R: Read> {
     /// Arrow CSV reader
     reader: csv::Reader<R>,
+    /// Adapts the batches to the table schema if the file has other columns
+    schema_adapter: Option<SchemaAdapter>,
 }
-impl CsvStream<File> {
-    /// Create an iterator for a CSV file
+impl CsvStream<Box<dyn Read + Send + Sync>> {
+    /// Create an iterator for a CSV file, or for the records that start within a byte
+    /// range of an uncompressed file. Only the range at the start of the file has the
+    /// header.
     pub fn try_new(
         filename: &str,
+        compression: FileCompressionType,
+        byte_range: Option<Range<u64>>,
         schema: SchemaRef,
         has_header: bool,
         delimiter: Option<u8>,
@@ -381,7 +658,18 @@ impl CsvStream<File> {
         batch_size: usize,
         limit: Option<usize>,
     ) -> Result<Self> {
-        let file = File::open(filename)?;
+        let mut file = File::open(filename)?;
+        let (file, has_header): (Box<dyn Read + Send + Sync>, bool) = match byte_range {
+            Some(range) => {
+                let records = record_range(&mut file, &range)?;
+                file.seek(SeekFrom::Start(records.start))?;
+                (
+                    Box::new(file.take(records.end - records.start)),
+                    has_header && records.start == 0,
+                )
+            }
+            None => (compression.decompress(file)?, has_header),
+        };
         Self::try_new_from_reader(
             file, schema, has_header, delimiter, projection, batch_size, limit,
         )
@@ -411,7 +699,16 @@ impl<R: Read> CsvStream<R> {
             projection.clone(),
         );
 
-        Ok(Self { reader })
+        Ok(Self {
+            reader,
+            schema_adapter: None,
+        })
+    }
+
+    /// Adapt the batches of the file with `schema_adapter`, if any
+    fn with_schema_adapter(mut self, schema_adapter: Option<SchemaAdapter>) -> Self {
+        self.schema_adapter = schema_adapter;
+        self
     }
 }
 
@@ -422,14 +719,23 @@ impl<R: Read + Unpin> Stream for CsvStream<R> {
         mut self: Pin<&mut Self>,
         _: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        Poll::Ready(self.reader.next())
+        let batch = self.reader.next();
+        Poll::Ready(match &self.schema_adapter {
+            Some(adapter) => {
+                batch.map(|batch| batch.and_then(|b| adapter.adapt_batch(b)))
+            }
+            None => batch,
+        })
     }
 }
 
 impl<R: Read + Unpin> RecordBatchStream for CsvStream<R> {
     /// Get the schema
     fn schema(&self) -> SchemaRef {
-        self.reader.schema()
+        match &self.schema_adapter {
+            Some(adapter) => adapter.projected_schema(),
+            None => self.reader.schema(),
+        }
     }
 }
 
@@ -524,4 +830,183 @@ mod tests {
         assert_eq!("c5", batch_schema.field(2).name());
         Ok(())
     }
+
+    #[test]
+    fn record_range_skips_quoted_newlines() -> Result<()> {
+        // the records start at offsets 0, 4, 12 and 20, and the newline at 8 is quoted
+        let data = "a,b\n1,\"x\ny\"\n2,\"\"\"z\"\n3,w\n";
+        let range = |range| record_range(data.as_bytes(), &range);
+        assert_eq!(0..12, range(0..8)?);
+        assert_eq!(12..20, range(8..16)?);
+        assert_eq!(20..24, range(16..24)?);
+        // no record starts within the range
+        assert_eq!(12..12, range(5..10)?);
+        assert_eq!(0..24, range(0..100)?);
+        assert_eq!(24..24, range(22..100)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn csv_exec_with_byte_range_splits() -> Result<()> {
+        let schema = aggr_test_schema();
+        let testdata = crate::test_util::arrow_test_data();
+        let path = format!("{}/csv/aggregate_test_100.csv", testdata);
+        let csv = CsvExec::try_new(
+            &path,
+            CsvReadOptions::new()
+                .schema(&schema)
+                .target_split_size(1000),
+            Some(vec![0, 1]),
+            1024,
+            None,
+        )?;
+        let partitions = csv.output_partitioning().partition_count();
+        assert!(partitions > 1);
+        let mut rows = 0;
+        for partition in 0..partitions {
+            let batches =
+                crate::physical_plan::common::collect(csv.execute(partition).await?)
+                    .await?;
+            rows += batches.iter().map(|b| b.num_rows()).sum::<usize>();
+        }
+        assert_eq!(100, rows);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn csv_exec_splits_with_quoted_newlines() -> Result<()> {
+        use arrow::array::StringArray;
+
+        let tmp_dir = tempfile::TempDir::new()?;
+        let path = tmp_dir.path().join("quoted.csv");
+        let mut data = String::from("id,text\n");
+        for i in 0..20 {
+            data.push_str(&format!("{},\"line {}\nstill \"\"{}\"\"\"\n", i, i, i));
+        }
+        std::fs::write(&path, data)?;
+
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("text", DataType::Utf8, false),
+        ]);
+        let csv = CsvExec::try_new(
+            path.to_str().unwrap(),
+            CsvReadOptions::new().schema(&schema).target_split_size(30),
+            None,
+            1024,
+            None,
+        )?;
+        let partitions = csv.output_partitioning().partition_count();
+        assert!(partitions > 1);
+        let mut texts = vec![];
+        for partition in 0..partitions {
+            let batches =
+                crate::physical_plan::common::collect(csv.execute(partition).await?)
+                    .await?;
+            for batch in batches {
+                let column = batch
+                    .column(1)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                texts.extend(column.iter().map(|v| v.unwrap().to_owned()));
+            }
+        }
+        let expected = (0..20)
+            .map(|i| format!("line {}\nstill \"{}\"", i, i))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, texts);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn csv_exec_maps_header_to_schema() -> Result<()> {
+        use arrow::array::{Int64Array, StringArray};
+
+        let tmp_dir = tempfile::TempDir::new()?;
+        std::fs::write(tmp_dir.path().join("1.csv"), "c,a\nx,1\ny,2\n")?;
+        // no column of the table, so the columns are read by position
+        std::fs::write(tmp_dir.path().join("2.csv"), "e,f,g\n3,u,z\n")?;
+
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("b", DataType::Utf8, true),
+            Field::new("c", DataType::Utf8, false),
+        ]);
+        let csv = CsvExec::try_new(
+            tmp_dir.path().to_str().unwrap(),
+            CsvReadOptions::new().schema(&schema),
+            Some(vec![2, 0, 1]),
+            1024,
+            None,
+        )?;
+        assert_eq!(2, csv.output_partitioning().partition_count());
+        let mut values = vec![];
+        for partition in 0..2 {
+            let batches =
+                crate::physical_plan::common::collect(csv.execute(partition).await?)
+                    .await?;
+            for batch in batches {
+                assert_eq!(csv.schema(), batch.schema());
+                let c = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                let a = batch
+                    .column(1)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .unwrap();
+                let b = batch
+                    .column(2)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                for i in 0..batch.num_rows() {
+                    values.push(format!(
+                        "{},{},{}",
+                        c.value(i),
+                        a.value(i),
+                        b.iter().nth(i).unwrap().unwrap_or("NULL")
+                    ));
+                }
+            }
+        }
+        values.sort();
+        assert_eq!(vec!["x,1,NULL", "y,2,NULL", "z,3,u"], values);
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn csv_exec_gzip_compressed() -> Result<()> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let testdata = crate::test_util::arrow_test_data();
+        let data = std::fs::read(format!("{}/csv/aggregate_test_100.csv", testdata))?;
+        let tmp_dir = tempfile::TempDir::new()?;
+        let path = tmp_dir.path().join("aggregate_test_100.csv.gz");
+        let mut encoder =
+            GzEncoder::new(File::create(&path)?, flate2::Compression::default());
+        encoder.write_all(&data)?;
+        encoder.finish()?;
+
+        // compression and schema are both inferred from the file
+        let csv = CsvExec::try_new(
+            tmp_dir.path().to_str().unwrap(),
+            CsvReadOptions::new(),
+            None,
+            1024,
+            None,
+        )?;
+        assert_eq!(1, csv.filenames().len());
+        assert_eq!(13, csv.file_schema().fields().len());
+        let batches =
+            crate::physical_plan::common::collect(csv.execute(0).await?).await?;
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(100, rows);
+        Ok(())
+    }
 }
@@ -16,6 +16,8 @@
 // under the License.
 
 //! DateTime expressions
+use std::convert::TryFrom;
+use std::fmt::Write;
 use std::sync::Arc;
 
 use super::ColumnarValue;
@@ -24,11 +26,15 @@ use crate::{
     scalar::{ScalarType, ScalarValue},
 };
 use arrow::{
-    array::{Array, ArrayRef, GenericStringArray, PrimitiveArray, StringOffsetSizeTrait},
+    array::{
+        Array, ArrayRef, GenericStringArray, PrimitiveArray, StringArray,
+        StringOffsetSizeTrait,
+    },
     compute::kernels::cast_utils::string_to_timestamp_nanos,
     datatypes::{
-        ArrowPrimitiveType, DataType, TimestampMicrosecondType, TimestampMillisecondType,
-        TimestampNanosecondType, TimestampSecondType,
+        ArrowNumericType, ArrowPrimitiveType, ArrowTemporalType, DataType,
+        TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+        TimestampSecondType,
     },
 };
 use arrow::{
@@ -40,8 +46,9 @@ use arrow::{
     datatypes::TimeUnit,
     temporal_conversions::timestamp_ns_to_datetime,
 };
+use chrono::format::{Fixed, Item, StrftimeItems};
 use chrono::prelude::*;
-use chrono::Duration;
+use chrono::{Duration, LocalResult, Locale};
 
 /// given a function `op` that maps a `&str` to a Result of an arrow native type,
 /// returns a `PrimitiveArray` after the application
@@ -137,6 +144,160 @@ pub fn to_timestamp(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     )
 }
 
+/// Parses `s` with the `strftime` style `format`. Timestamps without a time zone are
+/// in the local time zone, like the timestamps parsed by [`string_to_timestamp_nanos`],
+/// and dates without a time are at midnight.
+fn string_to_timestamp_nanos_with_format(s: &str, format: &str) -> Result<i64> {
+    if let Ok(datetime) = DateTime::parse_from_str(s, format) {
+        return Ok(datetime.timestamp_nanos());
+    }
+    let datetime = NaiveDateTime::parse_from_str(s, format).or_else(|e| {
+        NaiveDate::parse_from_str(s, format)
+            .map(|date| date.and_hms(0, 0, 0))
+            .map_err(|_| {
+                DataFusionError::Execution(format!(
+                    "Error parsing '{}' as timestamp with format '{}': {}",
+                    s, format, e
+                ))
+            })
+    })?;
+    match Local.from_local_datetime(&datetime) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Ok(datetime.timestamp_nanos())
+        }
+        LocalResult::None => Err(DataFusionError::Execution(format!(
+            "Error parsing '{}' as timestamp: {} does not exist in the local time zone",
+            s, datetime
+        ))),
+    }
+}
+
+/// Returns the value of the `format` argument of `name`
+fn format_arg<'a>(format: &'a ColumnarValue, name: &str) -> Result<&'a str> {
+    if let ColumnarValue::Scalar(ScalarValue::Utf8(Some(v))) = format {
+        Ok(v)
+    } else {
+        Err(DataFusionError::Execution(format!(
+            "Format of `{}` must be non-null scalar Utf8",
+            name
+        )))
+    }
+}
+
+/// Returns the locale named by the optional `locale` argument of `name`, such as
+/// `de_DE` or `fr_FR`
+fn locale_arg(locale: Option<&ColumnarValue>, name: &str) -> Result<Option<Locale>> {
+    match locale {
+        None => Ok(None),
+        Some(ColumnarValue::Scalar(ScalarValue::Utf8(Some(v)))) => {
+            Locale::try_from(v.as_str()).map(Some).map_err(|_| {
+                DataFusionError::Execution(format!(
+                    "Unknown locale '{}' for `{}`",
+                    v, name
+                ))
+            })
+        }
+        Some(_) => Err(DataFusionError::Execution(format!(
+            "Locale of `{}` must be non-null scalar Utf8",
+            name
+        ))),
+    }
+}
+
+/// The month and weekday names of a locale, with the English names that chrono parses,
+/// longest first so that full names are preferred over their abbreviations
+fn locale_names(locale: Locale) -> Vec<(String, String)> {
+    let mut names = vec![];
+    let mut add = |datetime: DateTime<Utc>, format: &str| {
+        let localized = datetime.format_localized(format, locale).to_string();
+        let english = datetime.format(format).to_string();
+        if !localized.is_empty() {
+            names.push((localized, english));
+        }
+    };
+    for month in 1..=12 {
+        let datetime = Utc.ymd(2020, month, 1).and_hms(0, 0, 0);
+        add(datetime, "%B");
+        add(datetime, "%b");
+    }
+    // 2020-06-01 is a Monday
+    for day in 1..=7 {
+        let datetime = Utc.ymd(2020, 6, day).and_hms(0, 0, 0);
+        add(datetime, "%A");
+        add(datetime, "%a");
+    }
+    names.sort_by_key(|(localized, _)| std::cmp::Reverse(localized.chars().count()));
+    names
+}
+
+/// Returns the rest of `s` after `prefix`, ignoring case
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut chars = s.char_indices();
+    for p in prefix.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or("", |(i, _)| &s[i..]))
+}
+
+/// Replaces the month and weekday names of `names` in `s` by their English names, which
+/// chrono parses. Names only match whole words.
+fn translate_names(s: &str, names: &[(String, String)]) -> String {
+    let mut translated = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut word_start = true;
+    'chars: while let Some(c) = rest.chars().next() {
+        if word_start {
+            for (localized, english) in names {
+                if let Some(after) = strip_prefix_ignore_case(rest, localized) {
+                    if !after.starts_with(char::is_alphabetic) {
+                        translated.push_str(english);
+                        rest = after;
+                        word_start = false;
+                        continue 'chars;
+                    }
+                }
+            }
+        }
+        translated.push(c);
+        rest = &rest[c.len_utf8()..];
+        word_start = !c.is_alphabetic();
+    }
+    translated
+}
+
+/// to_timestamp SQL function with a `strftime` style format, such as
+/// `to_timestamp('08/09/2020 12:00', '%m/%d/%Y %H:%M')`, and an optional locale whose
+/// month and weekday names are parsed, such as
+/// `to_timestamp('8 März 2020', '%d %B %Y', 'de_DE')`
+pub fn to_timestamp_with_format(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(DataFusionError::Execution(
+            "Expected two or three arguments in TO_TIMESTAMP".to_string(),
+        ));
+    }
+    let format = format_arg(&args[1], "to_timestamp")?;
+    let names = locale_arg(args.get(2), "to_timestamp")?.map(locale_names);
+    handle::<TimestampNanosecondType, _, TimestampNanosecondType>(
+        &args[..1],
+        |s| match &names {
+            Some(names) => {
+                string_to_timestamp_nanos_with_format(&translate_names(s, names), format)
+                    .map_err(|_| {
+                        DataFusionError::Execution(format!(
+                            "Error parsing '{}' as timestamp with format '{}'",
+                            s, format
+                        ))
+                    })
+            }
+            None => string_to_timestamp_nanos_with_format(s, format),
+        },
+        "to_timestamp",
+    )
+}
+
 /// to_timestamp_millis SQL function
 pub fn to_timestamp_millis(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     handle::<TimestampMillisecondType, _, TimestampMillisecondType>(
@@ -260,16 +421,107 @@ pub fn date_trunc(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     })
 }
 
+const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+
+/// Returns the length in nanoseconds of the `stride` argument of `date_bin`, which
+/// is an `IntervalDayTime` holding the days in its upper and the milliseconds in
+/// its lower 32 bits
+fn date_bin_stride(stride: &ColumnarValue) -> Result<i64> {
+    let stride =
+        if let ColumnarValue::Scalar(ScalarValue::IntervalDayTime(Some(v))) = stride {
+            *v
+        } else {
+            return Err(DataFusionError::Execution(
+                "Stride of `date_bin` must be a non-null scalar day-time interval"
+                    .to_string(),
+            ));
+        };
+
+    let days = (stride >> 32) as i32 as i64;
+    let millis = stride as i32 as i64;
+    let nanos = days
+        .checked_mul(NANOSECONDS_PER_DAY)
+        .and_then(|days| days.checked_add(millis * 1_000_000))
+        .ok_or_else(|| {
+            DataFusionError::Execution("Stride of `date_bin` is too large".to_string())
+        })?;
+
+    if nanos <= 0 {
+        return Err(DataFusionError::Execution(
+            "Stride of `date_bin` must be positive".to_string(),
+        ));
+    }
+    Ok(nanos)
+}
+
+/// Returns the start of the bin of width `stride` aligned on `origin` that contains
+/// `value`. Values before `origin` fall into the bins preceding it.
+fn date_bin_single(stride: i64, value: i64, origin: i64) -> i64 {
+    let delta = value as i128 - origin as i128;
+    let bin = delta.div_euclid(stride as i128) * stride as i128;
+    (origin as i128 + bin) as i64
+}
+
+/// date_bin SQL function, which truncates timestamps to the start of the interval
+/// of width `stride` that contains them, with the intervals aligned on `origin`
+/// (the unix epoch when not given), e.g.
+/// `date_bin(INTERVAL '15 minutes', ts, to_timestamp('2001-01-01T00:00:00'))`
+pub fn date_bin(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(DataFusionError::Execution(
+            "Expected two or three arguments in DATE_BIN".to_string(),
+        ));
+    }
+    let stride = date_bin_stride(&args[0])?;
+    let origin = match args.get(2) {
+        None => 0,
+        Some(ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(Some(v)))) => *v,
+        Some(_) => {
+            return Err(DataFusionError::Execution(
+                "Origin of `date_bin` must be a non-null scalar timestamp".to_string(),
+            ));
+        }
+    };
+
+    let f = |x: Option<i64>| x.map(|x| date_bin_single(stride, x, origin));
+
+    Ok(match &args[1] {
+        ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(v)) => {
+            ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(f(*v)))
+        }
+        ColumnarValue::Scalar(scalar) => {
+            return Err(DataFusionError::Execution(format!(
+                "Unsupported data type {:?} for `date_bin`",
+                scalar.get_datatype()
+            )));
+        }
+        ColumnarValue::Array(array) => {
+            let array = array
+                .as_any()
+                .downcast_ref::<TimestampNanosecondArray>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Unsupported data type {:?} for `date_bin`",
+                        array.data_type()
+                    ))
+                })?;
+            let array = array.iter().map(f).collect::<TimestampNanosecondArray>();
+
+            ColumnarValue::Array(Arc::new(array))
+        }
+    })
+}
+
 macro_rules! extract_date_part {
-    ($ARRAY: expr, $FN:expr) => {
+    ($ARRAY: expr, $FN:expr $(, $ARGS:expr)*) => {
         match $ARRAY.data_type() {
             DataType::Date32 => {
                 let array = $ARRAY.as_any().downcast_ref::<Date32Array>().unwrap();
-                Ok($FN(array)?)
+                Ok($FN(array $(, $ARGS)*)?)
             }
             DataType::Date64 => {
                 let array = $ARRAY.as_any().downcast_ref::<Date64Array>().unwrap();
-                Ok($FN(array)?)
+                Ok($FN(array $(, $ARGS)*)?)
             }
             DataType::Timestamp(time_unit, None) => match time_unit {
                 TimeUnit::Second => {
@@ -277,28 +529,28 @@ macro_rules! extract_date_part {
                         .as_any()
                         .downcast_ref::<TimestampSecondArray>()
                         .unwrap();
-                    Ok($FN(array)?)
+                    Ok($FN(array $(, $ARGS)*)?)
                 }
                 TimeUnit::Millisecond => {
                     let array = $ARRAY
                         .as_any()
                         .downcast_ref::<TimestampMillisecondArray>()
                         .unwrap();
-                    Ok($FN(array)?)
+                    Ok($FN(array $(, $ARGS)*)?)
                 }
                 TimeUnit::Microsecond => {
                     let array = $ARRAY
                         .as_any()
                         .downcast_ref::<TimestampMicrosecondArray>()
                         .unwrap();
-                    Ok($FN(array)?)
+                    Ok($FN(array $(, $ARGS)*)?)
                 }
                 TimeUnit::Nanosecond => {
                     let array = $ARRAY
                         .as_any()
                         .downcast_ref::<TimestampNanosecondArray>()
                         .unwrap();
-                    Ok($FN(array)?)
+                    Ok($FN(array $(, $ARGS)*)?)
                 }
             },
             datatype => Err(DataFusionError::Internal(format!(
@@ -352,6 +604,98 @@ pub fn date_part(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     })
 }
 
+/// Formats the dates or timestamps of `array` with the parsed format `items`, in the
+/// language of `locale` if any
+fn to_char_array<T>(
+    array: &PrimitiveArray<T>,
+    items: &[Item],
+    locale: Option<Locale>,
+) -> Result<StringArray>
+where
+    T: ArrowTemporalType + ArrowNumericType,
+    i64: From<T::Native>,
+{
+    (0..array.len())
+        .map(|i| {
+            let datetime = match array.value_as_datetime(i) {
+                Some(datetime) if !array.is_null(i) => datetime,
+                _ => return Ok(None),
+            };
+            let datetime = Utc.from_utc_datetime(&datetime);
+            let mut formatted = String::new();
+            let result = match locale {
+                Some(locale) => write!(
+                    formatted,
+                    "{}",
+                    datetime.format_localized_with_items(items.iter(), locale)
+                ),
+                None => write!(formatted, "{}", datetime.format_with_items(items.iter())),
+            };
+            result.map(|_| Some(formatted)).map_err(|_| {
+                DataFusionError::Execution(format!("Could not format {}", datetime))
+            })
+        })
+        .collect()
+}
+
+/// TO_CHAR SQL function, which formats dates and timestamps with a `strftime` style
+/// format, such as `to_char(ts, '%Y-%m-%d %H:%M')`, and an optional locale whose month
+/// and weekday names are used, such as `to_char(ts, '%A %e %B', 'fr_FR')`
+pub fn to_char(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(DataFusionError::Execution(
+            "Expected two or three arguments in TO_CHAR".to_string(),
+        ));
+    }
+    let (array, format) = (&args[0], &args[1]);
+
+    let format = format_arg(format, "to_char")?;
+    let locale = locale_arg(args.get(2), "to_char")?;
+    let items = StrftimeItems::new(format).collect::<Vec<_>>();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(DataFusionError::Execution(format!(
+            "Invalid format '{}' for `to_char`",
+            format
+        )));
+    }
+    // dates and timestamps without a time zone have no offset to format
+    if items.iter().any(|item| {
+        matches!(
+            item,
+            Item::Fixed(Fixed::TimezoneName)
+                | Item::Fixed(Fixed::TimezoneOffset)
+                | Item::Fixed(Fixed::TimezoneOffsetColon)
+                | Item::Fixed(Fixed::TimezoneOffsetColonZ)
+                | Item::Fixed(Fixed::TimezoneOffsetZ)
+                | Item::Fixed(Fixed::RFC2822)
+                | Item::Fixed(Fixed::RFC3339)
+        )
+    }) {
+        return Err(DataFusionError::Execution(format!(
+            "Format '{}' of `to_char` has a time zone, which the values do not have",
+            format
+        )));
+    }
+
+    let is_scalar = matches!(array, ColumnarValue::Scalar(_));
+
+    let array = match array {
+        ColumnarValue::Array(array) => array.clone(),
+        ColumnarValue::Scalar(scalar) => scalar.to_array(),
+    };
+
+    let arr: StringArray = extract_date_part!(array, to_char_array, &items, locale)?;
+
+    Ok(if is_scalar {
+        ColumnarValue::Scalar(ScalarValue::try_from_array(
+            &(Arc::new(arr) as ArrayRef),
+            0,
+        )?)
+    } else {
+        ColumnarValue::Array(Arc::new(arr))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -387,6 +731,139 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn to_timestamp_with_format_test() -> Result<()> {
+        let cases = vec![
+            (
+                "09/08/2020 13:42:29 +0000",
+                "%m/%d/%Y %H:%M:%S %z",
+                1599572549000000000,
+            ),
+            (
+                "2020-09-08 13:42:29 +0200",
+                "%Y-%m-%d %H:%M:%S %z",
+                1599565349000000000,
+            ),
+        ];
+        for (string, format, expected) in cases {
+            let result = to_timestamp_with_format(&[
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(string.to_owned()))),
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(format.to_owned()))),
+            ])?;
+            match result {
+                ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(Some(v))) => {
+                    assert_eq!(v, expected, "{} {}", string, format)
+                }
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+
+        // timestamps without a time zone are in the local time zone
+        let expected = Local.ymd(2020, 9, 8).and_hms(0, 0, 0).timestamp_nanos();
+        assert_eq!(
+            string_to_timestamp_nanos_with_format("08.09.2020", "%d.%m.%Y")?,
+            expected
+        );
+
+        let err =
+            string_to_timestamp_nanos_with_format("2020-09-08", "%d.%m.%Y").unwrap_err();
+        assert!(err.to_string().contains("Error parsing '2020-09-08'"));
+
+        // the month and weekday names of the locale are parsed
+        let cases = vec![
+            ("Dienstag, 8. September 2020", "%A, %d. %B %Y", "de_DE"),
+            ("8 MARS 2020", "%d %B %Y", "fr_FR"),
+            ("mar. 8 sept. 2020", "%a %d %b %Y", "fr_FR"),
+            ("8 de septiembre de 2020", "%d de %B de %Y", "es_ES"),
+        ];
+        for (string, format, locale) in cases {
+            let result = to_timestamp_with_format(&[
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(string.to_owned()))),
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(format.to_owned()))),
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(locale.to_owned()))),
+            ])?;
+            let month = if string.contains("MARS") { 3 } else { 9 };
+            let expected = Local.ymd(2020, month, 8).and_hms(0, 0, 0).timestamp_nanos();
+            match result {
+                ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(Some(v))) => {
+                    assert_eq!(v, expected, "{} {}", string, locale)
+                }
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn to_char_test() -> Result<()> {
+        let timestamps = TimestampNanosecondArray::from_opt_vec(
+            vec![Some(1599572549190855000), None],
+            None,
+        );
+        let result = to_char(&[
+            ColumnarValue::Array(Arc::new(timestamps)),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(
+                "%d/%m/%Y %H:%M:%S%.3f".to_owned(),
+            ))),
+        ])?;
+        let expected = StringArray::from(vec![Some("08/09/2020 13:42:29.190"), None]);
+        match result {
+            ColumnarValue::Array(array) => {
+                assert_eq!(&expected as &dyn Array, array.as_ref())
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let result = to_char(&[
+            ColumnarValue::Scalar(ScalarValue::Date32(Some(18513))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("%A %e %B %Y".to_owned()))),
+        ])?;
+        match result {
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(v))) => {
+                assert_eq!(v, "Tuesday  8 September 2020")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let err = to_char(&[
+            ColumnarValue::Scalar(ScalarValue::Date32(Some(18513))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("%Q".to_owned()))),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid format '%Q'"));
+
+        // dates have no time zone to format
+        for format in &["%z", "%Z", "%:z", "%+"] {
+            let err = to_char(&[
+                ColumnarValue::Scalar(ScalarValue::Date32(Some(18513))),
+                ColumnarValue::Scalar(ScalarValue::Utf8(Some(format.to_string()))),
+            ])
+            .unwrap_err();
+            assert!(err.to_string().contains("has a time zone"), "{}", format);
+        }
+
+        let result = to_char(&[
+            ColumnarValue::Scalar(ScalarValue::Date32(Some(18513))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("%A %e %B %Y".to_owned()))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("fr_FR".to_owned()))),
+        ])?;
+        match result {
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(v))) => {
+                assert_eq!(v, "mardi  8 septembre 2020")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let err = to_char(&[
+            ColumnarValue::Scalar(ScalarValue::Date32(Some(18513))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("%Y".to_owned()))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("xx_XX".to_owned()))),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown locale 'xx_XX'"));
+        Ok(())
+    }
+
     #[test]
     fn date_trunc_test() {
         let cases = vec![
@@ -445,6 +922,89 @@ mod tests {
         });
     }
 
+    #[test]
+    fn date_bin_test() {
+        let cases = vec![
+            (
+                "2020-09-08T13:42:29.190855Z",
+                "2001-01-01T00:00:00Z",
+                "2020-09-08T13:30:00Z",
+            ),
+            (
+                "2020-09-08T13:42:29.190855Z",
+                "2001-01-01T00:02:30Z",
+                "2020-09-08T13:32:30Z",
+            ),
+            (
+                "2020-09-08T13:45:00Z",
+                "2001-01-01T00:00:00Z",
+                "2020-09-08T13:45:00Z",
+            ),
+            // values before the origin fall into the preceding bins
+            (
+                "1999-12-31T23:59:59Z",
+                "2001-01-01T00:00:00Z",
+                "1999-12-31T23:45:00Z",
+            ),
+        ];
+        // INTERVAL '15 minutes'
+        let stride = date_bin_stride(&ColumnarValue::Scalar(
+            ScalarValue::IntervalDayTime(Some(15 * 60 * 1000)),
+        ))
+        .unwrap();
+
+        cases.iter().for_each(|(original, origin, expected)| {
+            let original = string_to_timestamp_nanos(original).unwrap();
+            let origin = string_to_timestamp_nanos(origin).unwrap();
+            let expected = string_to_timestamp_nanos(expected).unwrap();
+            let result = date_bin_single(stride, original, origin);
+            assert_eq!(result, expected);
+        });
+    }
+
+    #[test]
+    fn date_bin_array() -> Result<()> {
+        // INTERVAL '1 day 12 hours'
+        let stride = ColumnarValue::Scalar(ScalarValue::IntervalDayTime(Some(
+            (1 << 32) | (12 * 60 * 60 * 1000),
+        )));
+        let timestamps = vec![
+            Some(string_to_timestamp_nanos("2020-09-08T13:42:29Z")?),
+            None,
+            Some(string_to_timestamp_nanos("2020-09-10T01:00:00Z")?),
+        ];
+        let array =
+            ColumnarValue::Array(Arc::new(TimestampNanosecondArray::from(timestamps)));
+
+        let result = match date_bin(&[stride, array])? {
+            ColumnarValue::Array(array) => array,
+            _ => panic!("Expected an array"),
+        };
+        let result = result
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        let expected = TimestampNanosecondArray::from(vec![
+            Some(string_to_timestamp_nanos("2020-09-08T00:00:00Z")?),
+            None,
+            Some(string_to_timestamp_nanos("2020-09-09T12:00:00Z")?),
+        ]);
+        assert_eq!(result, &expected);
+        Ok(())
+    }
+
+    #[test]
+    fn date_bin_invalid_stride() {
+        let ts = ColumnarValue::Scalar(ScalarValue::TimestampNanosecond(Some(0)));
+        let zero = ColumnarValue::Scalar(ScalarValue::IntervalDayTime(Some(0)));
+        let months = ColumnarValue::Scalar(ScalarValue::IntervalYearMonth(Some(1)));
+
+        let err = date_bin(&[zero, ts.clone()]).unwrap_err();
+        assert!(err.to_string().contains("must be positive"), "{}", err);
+        let err = date_bin(&[months, ts]).unwrap_err();
+        assert!(err.to_string().contains("day-time interval"), "{}", err);
+    }
+
     #[test]
     fn to_timestamp_invalid_input_type() -> Result<()> {
         // pass the wrong type of input array to to_timestamp and test
@@ -21,7 +21,9 @@
 
 use std::fmt;
 
-use crate::logical_plan::{StringifiedPlan, ToStringifiedPlan};
+use crate::logical_plan::{
+    json_string, ExplainFormat, GraphvizBuilder, StringifiedPlan, ToStringifiedPlan,
+};
 
 use super::{accept, ExecutionPlan, ExecutionPlanVisitor};
 
@@ -90,6 +92,66 @@ impl<'a> DisplayableExecutionPlan<'a> {
             with_metrics: self.with_metrics,
         }
     }
+
+    /// Return a `format`able structure that produces a JSON tree of the
+    /// plan with an object per node, which includes the number of output
+    /// partitions and the estimated statistics of the node.
+    ///
+    /// ```text
+    /// {"node":"ProjectionExec: expr=[a@0 as a]","partitions":3,
+    ///  "statistics":{"num_rows":null,"total_byte_size":null},"inputs":[...]}
+    /// ```
+    pub fn json(&self) -> impl fmt::Display + 'a {
+        struct Wrapper<'a> {
+            plan: &'a dyn ExecutionPlan,
+        }
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mut visitor = JsonVisitor {
+                    t: DisplayFormatType::Default,
+                    f,
+                    has_inputs: vec![],
+                };
+                accept(self.plan, &mut visitor)
+            }
+        }
+        Wrapper { plan: self.inner }
+    }
+
+    /// Return a `format`able structure that produces lines meant for
+    /// graphical display using the `DOT` language. This format can be
+    /// visualized using software from [`graphviz`](https://graphviz.org/)
+    pub fn graphviz(&self) -> impl fmt::Display + 'a {
+        struct Wrapper<'a> {
+            plan: &'a dyn ExecutionPlan,
+        }
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                writeln!(
+                    f,
+                    "// Begin DataFusion GraphViz Plan (see https://graphviz.org)"
+                )?;
+                writeln!(f, "digraph {{")?;
+
+                let mut visitor = GraphvizVisitor {
+                    t: DisplayFormatType::Default,
+                    f,
+                    graphviz_builder: GraphvizBuilder::default(),
+                    parent_ids: vec![],
+                };
+                visitor
+                    .graphviz_builder
+                    .start_cluster(visitor.f, "ExecutionPlan")?;
+                accept(self.plan, &mut visitor)?;
+                visitor.graphviz_builder.end_cluster(visitor.f)?;
+
+                writeln!(f, "}}")?;
+                writeln!(f, "// End DataFusion GraphViz Plan")?;
+                Ok(())
+            }
+        }
+        Wrapper { plan: self.inner }
+    }
 }
 
 /// Formats plans with a single line per node.
@@ -134,11 +196,128 @@ impl<'a, 'b> ExecutionPlanVisitor for IndentVisitor<'a, 'b> {
     }
 }
 
+/// Formats plans as a JSON tree with an object per node.
+struct JsonVisitor<'a, 'b> {
+    /// How to format each node
+    t: DisplayFormatType,
+    /// Write to this formatter
+    f: &'a mut fmt::Formatter<'b>,
+    /// Whether the node being visited at each depth already has an input written
+    has_inputs: Vec<bool>,
+}
+
+impl<'a, 'b> ExecutionPlanVisitor for JsonVisitor<'a, 'b> {
+    type Error = fmt::Error;
+    fn pre_visit(
+        &mut self,
+        plan: &dyn ExecutionPlan,
+    ) -> std::result::Result<bool, Self::Error> {
+        if let Some(has_input) = self.has_inputs.last_mut() {
+            if *has_input {
+                write!(self.f, ",")?;
+            }
+            *has_input = true;
+        }
+        let statistics = plan.statistics();
+        let optional = |value: Option<usize>| {
+            value
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "null".to_owned())
+        };
+        write!(
+            self.f,
+            "{{\"node\":{},\"partitions\":{},\"statistics\":{{\"num_rows\":{},\"total_byte_size\":{}}},\"inputs\":[",
+            json_string(&FormatAs(plan, self.t).to_string()),
+            plan.output_partitioning().partition_count(),
+            optional(statistics.num_rows),
+            optional(statistics.total_byte_size),
+        )?;
+        self.has_inputs.push(false);
+        Ok(true)
+    }
+
+    fn post_visit(&mut self, _plan: &dyn ExecutionPlan) -> Result<bool, Self::Error> {
+        self.has_inputs.pop();
+        write!(self.f, "]}}")?;
+        Ok(true)
+    }
+}
+
+/// Formats plans for graphical display using the `DOT` language, with a
+/// node per plan that also shows its number of output partitions
+struct GraphvizVisitor<'a, 'b> {
+    /// How to format each node
+    t: DisplayFormatType,
+    /// Write to this formatter
+    f: &'a mut fmt::Formatter<'b>,
+    graphviz_builder: GraphvizBuilder,
+    /// Holds the ids (as generated from `graphviz_builder` of all
+    /// parent nodes
+    parent_ids: Vec<usize>,
+}
+
+impl<'a, 'b> ExecutionPlanVisitor for GraphvizVisitor<'a, 'b> {
+    type Error = fmt::Error;
+    fn pre_visit(
+        &mut self,
+        plan: &dyn ExecutionPlan,
+    ) -> std::result::Result<bool, Self::Error> {
+        let id = self.graphviz_builder.next_id();
+
+        let mut label = format!(
+            r"{}\nPartitions: {}",
+            FormatAs(plan, self.t),
+            plan.output_partitioning().partition_count()
+        );
+        if let Some(num_rows) = plan.statistics().num_rows {
+            label.push_str(&format!(r"\nRows: {}", num_rows));
+        }
+        writeln!(
+            self.f,
+            "    {}[shape=box label={}]",
+            id,
+            GraphvizBuilder::quoted(&label)
+        )?;
+
+        if let Some(parent_id) = self.parent_ids.last() {
+            writeln!(
+                self.f,
+                "    {} -> {} [arrowhead=none, arrowtail=normal, dir=back]",
+                parent_id, id
+            )?;
+        }
+
+        self.parent_ids.push(id);
+        Ok(true)
+    }
+
+    fn post_visit(&mut self, _plan: &dyn ExecutionPlan) -> Result<bool, Self::Error> {
+        // always be non-empty as pre_visit always pushes
+        self.parent_ids.pop().unwrap();
+        Ok(true)
+    }
+}
+
+/// Formats a single plan node with [ExecutionPlan::fmt_as]
+struct FormatAs<'a>(&'a dyn ExecutionPlan, DisplayFormatType);
+
+impl<'a> fmt::Display for FormatAs<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_as(self.1, f)
+    }
+}
+
 impl<'a> ToStringifiedPlan for DisplayableExecutionPlan<'a> {
     fn to_stringified(
         &self,
         plan_type: crate::logical_plan::PlanType,
+        format: ExplainFormat,
     ) -> StringifiedPlan {
-        StringifiedPlan::new(plan_type, self.indent().to_string())
+        let plan = match format {
+            ExplainFormat::Text => self.indent().to_string(),
+            ExplainFormat::Json => self.json().to_string(),
+            ExplainFormat::Graphviz => self.graphviz().to_string(),
+        };
+        StringifiedPlan::new(plan_type, plan)
     }
 }
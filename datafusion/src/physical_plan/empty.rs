@@ -20,6 +20,7 @@
 use std::any::Any;
 use std::sync::Arc;
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::{
     memory::MemoryStream, DisplayFormatType, Distribution, ExecutionPlan, Partitioning,
@@ -133,6 +134,13 @@ impl ExecutionPlan for EmptyExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        Statistics {
+            num_rows: Some(if self.produce_one_row { 1 } else { 0 }),
+            ..Default::default()
+        }
+    }
 }
 
 #[cfg(test)]
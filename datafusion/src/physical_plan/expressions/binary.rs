@@ -22,14 +22,13 @@ use arrow::array::*;
 use arrow::compute::kernels::arithmetic::{
     add, divide, divide_scalar, modulus, modulus_scalar, multiply, subtract,
 };
-use arrow::compute::kernels::boolean::{and_kleene, or_kleene};
+use arrow::compute::kernels::boolean::{and_kleene, not, or_kleene};
 use arrow::compute::kernels::comparison::{eq, gt, gt_eq, lt, lt_eq, neq};
 use arrow::compute::kernels::comparison::{
     eq_scalar, gt_eq_scalar, gt_scalar, lt_eq_scalar, lt_scalar, neq_scalar,
 };
 use arrow::compute::kernels::comparison::{
-    eq_utf8, gt_eq_utf8, gt_utf8, like_utf8, like_utf8_scalar, lt_eq_utf8, lt_utf8,
-    neq_utf8, nlike_utf8, nlike_utf8_scalar,
+    eq_utf8, gt_eq_utf8, gt_utf8, lt_eq_utf8, lt_utf8, neq_utf8,
 };
 use arrow::compute::kernels::comparison::{
     eq_utf8_scalar, gt_eq_utf8_scalar, gt_utf8_scalar, lt_eq_utf8_scalar, lt_utf8_scalar,
@@ -40,7 +39,8 @@ use arrow::record_batch::RecordBatch;
 
 use crate::error::{DataFusionError, Result};
 use crate::logical_plan::Operator;
-use crate::physical_plan::expressions::try_cast;
+use crate::physical_plan::expressions::{pattern_match, pattern_match_scalar, try_cast};
+use crate::physical_plan::type_coercion::{comparison_cast_type, is_comparison_operator};
 use crate::physical_plan::{ColumnarValue, PhysicalExpr};
 use crate::scalar::ScalarValue;
 
@@ -108,7 +108,9 @@ macro_rules! compute_utf8_op_scalar {
             .as_any()
             .downcast_ref::<$DT>()
             .expect("compute_op failed to downcast array");
-        if let ScalarValue::Utf8(Some(string_value)) = $RIGHT {
+        if let ScalarValue::Utf8(Some(string_value))
+        | ScalarValue::LargeUtf8(Some(string_value)) = $RIGHT
+        {
             Ok(Arc::new(paste::expr! {[<$OP _utf8_scalar>]}(
                 &ll,
                 &string_value,
@@ -122,6 +124,75 @@ macro_rules! compute_utf8_op_scalar {
     }};
 }
 
+fn eq_bytes(l: &[u8], r: &[u8]) -> bool {
+    l == r
+}
+
+fn neq_bytes(l: &[u8], r: &[u8]) -> bool {
+    l != r
+}
+
+fn lt_bytes(l: &[u8], r: &[u8]) -> bool {
+    l < r
+}
+
+fn lt_eq_bytes(l: &[u8], r: &[u8]) -> bool {
+    l <= r
+}
+
+fn gt_bytes(l: &[u8], r: &[u8]) -> bool {
+    l > r
+}
+
+fn gt_eq_bytes(l: &[u8], r: &[u8]) -> bool {
+    l >= r
+}
+
+/// Compare a pair of binary arrays element wise. Arrow has no comparison
+/// kernels for binary arrays, so they are compared using the `*_bytes` functions
+macro_rules! compute_binary_op {
+    ($LEFT:expr, $RIGHT:expr, $OP:ident, $DT:ident) => {{
+        let ll = $LEFT
+            .as_any()
+            .downcast_ref::<$DT>()
+            .expect("compute_op failed to downcast array");
+        let rr = $RIGHT
+            .as_any()
+            .downcast_ref::<$DT>()
+            .expect("compute_op failed to downcast array");
+        let result: BooleanArray = ll
+            .iter()
+            .zip(rr.iter())
+            .map(|(l, r)| Some(paste::expr! {[<$OP _bytes>]}(l?, r?)))
+            .collect();
+        Ok(Arc::new(result))
+    }};
+}
+
+/// Compare a binary array with a scalar value element wise
+macro_rules! compute_binary_op_scalar {
+    ($LEFT:expr, $RIGHT:expr, $OP:ident, $DT:ident) => {{
+        let ll = $LEFT
+            .as_any()
+            .downcast_ref::<$DT>()
+            .expect("compute_op failed to downcast array");
+        if let ScalarValue::Binary(Some(value)) | ScalarValue::LargeBinary(Some(value)) =
+            $RIGHT
+        {
+            let result: BooleanArray = ll
+                .iter()
+                .map(|l| Some(paste::expr! {[<$OP _bytes>]}(l?, value.as_slice())))
+                .collect();
+            Ok(Arc::new(result))
+        } else {
+            Err(DataFusionError::Internal(format!(
+                "compute_binary_op_scalar failed to cast literal value {}",
+                $RIGHT
+            )))
+        }
+    }};
+}
+
 /// Invoke a compute kernel on a data array and a scalar value
 macro_rules! compute_op_scalar {
     ($LEFT:expr, $RIGHT:expr, $OP:ident, $DT:ident) => {{
@@ -163,31 +234,6 @@ macro_rules! compute_op {
     }};
 }
 
-macro_rules! binary_string_array_op_scalar {
-    ($LEFT:expr, $RIGHT:expr, $OP:ident) => {{
-        let result: Result<Arc<dyn Array>> = match $LEFT.data_type() {
-            DataType::Utf8 => compute_utf8_op_scalar!($LEFT, $RIGHT, $OP, StringArray),
-            other => Err(DataFusionError::Internal(format!(
-                "Data type {:?} not supported for scalar operation on string array",
-                other
-            ))),
-        };
-        Some(result)
-    }};
-}
-
-macro_rules! binary_string_array_op {
-    ($LEFT:expr, $RIGHT:expr, $OP:ident) => {{
-        match $LEFT.data_type() {
-            DataType::Utf8 => compute_utf8_op!($LEFT, $RIGHT, $OP, StringArray),
-            other => Err(DataFusionError::Internal(format!(
-                "Data type {:?} not supported for binary operation on string arrays",
-                other
-            ))),
-        }
-    }};
-}
-
 /// Invoke a compute kernel on a pair of arrays
 /// The binary_primitive_array_op macro only evaluates for primitive types
 /// like integers and floats.
@@ -254,6 +300,15 @@ macro_rules! binary_array_op_scalar {
             DataType::Float32 => compute_op_scalar!($LEFT, $RIGHT, $OP, Float32Array),
             DataType::Float64 => compute_op_scalar!($LEFT, $RIGHT, $OP, Float64Array),
             DataType::Utf8 => compute_utf8_op_scalar!($LEFT, $RIGHT, $OP, StringArray),
+            DataType::LargeUtf8 => {
+                compute_utf8_op_scalar!($LEFT, $RIGHT, $OP, LargeStringArray)
+            }
+            DataType::Binary => {
+                compute_binary_op_scalar!($LEFT, $RIGHT, $OP, BinaryArray)
+            }
+            DataType::LargeBinary => {
+                compute_binary_op_scalar!($LEFT, $RIGHT, $OP, LargeBinaryArray)
+            }
             DataType::Timestamp(TimeUnit::Nanosecond, None) => {
                 compute_op_scalar!($LEFT, $RIGHT, $OP, TimestampNanosecondArray)
             }
@@ -298,6 +353,11 @@ macro_rules! binary_array_op {
             DataType::Float32 => compute_op!($LEFT, $RIGHT, $OP, Float32Array),
             DataType::Float64 => compute_op!($LEFT, $RIGHT, $OP, Float64Array),
             DataType::Utf8 => compute_utf8_op!($LEFT, $RIGHT, $OP, StringArray),
+            DataType::LargeUtf8 => compute_utf8_op!($LEFT, $RIGHT, $OP, LargeStringArray),
+            DataType::Binary => compute_binary_op!($LEFT, $RIGHT, $OP, BinaryArray),
+            DataType::LargeBinary => {
+                compute_binary_op!($LEFT, $RIGHT, $OP, LargeBinaryArray)
+            }
             DataType::Timestamp(TimeUnit::Nanosecond, None) => {
                 compute_op!($LEFT, $RIGHT, $OP, TimestampNanosecondArray)
             }
@@ -354,9 +414,17 @@ fn common_binary_type(
             _ => None,
         },
         // logical equality operators have their own rules, and always return a boolean
-        Operator::Eq | Operator::NotEq => eq_coercion(lhs_type, rhs_type),
-        // "like" operators operate on strings and always return a boolean
-        Operator::Like | Operator::NotLike => string_coercion(lhs_type, rhs_type),
+        Operator::Eq
+        | Operator::NotEq
+        | Operator::IsDistinctFrom
+        | Operator::IsNotDistinctFrom => eq_coercion(lhs_type, rhs_type),
+        // pattern matching operators operate on strings and always return a boolean
+        Operator::Like
+        | Operator::NotLike
+        | Operator::ILike
+        | Operator::NotILike
+        | Operator::SimilarTo
+        | Operator::NotSimilarTo => string_coercion(lhs_type, rhs_type),
         // order-comparison operators have their own rules
         Operator::Lt | Operator::Gt | Operator::GtEq | Operator::LtEq => {
             order_coercion(lhs_type, rhs_type)
@@ -403,10 +471,16 @@ pub fn binary_operator_data_type(
         | Operator::Or
         | Operator::Like
         | Operator::NotLike
+        | Operator::ILike
+        | Operator::NotILike
+        | Operator::SimilarTo
+        | Operator::NotSimilarTo
         | Operator::Lt
         | Operator::Gt
         | Operator::GtEq
-        | Operator::LtEq => Ok(DataType::Boolean),
+        | Operator::LtEq
+        | Operator::IsDistinctFrom
+        | Operator::IsNotDistinctFrom => Ok(DataType::Boolean),
         // math operations return the same value as the common coerced type
         Operator::Plus
         | Operator::Minus
@@ -416,6 +490,72 @@ pub fn binary_operator_data_type(
     }
 }
 
+impl BinaryExpr {
+    /// Evaluates `AND` and `OR`. The right operand is only evaluated for the rows
+    /// whose result is not already decided by the left operand, which is false for
+    /// `AND` and true for `OR`.
+    fn evaluate_logical(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let num_rows = batch.num_rows();
+        let decisive = self.op == Operator::Or;
+        let left = match self.left.evaluate(batch)? {
+            ColumnarValue::Scalar(ScalarValue::Boolean(Some(value)))
+                if value == decisive =>
+            {
+                return Ok(ColumnarValue::Scalar(ScalarValue::Boolean(Some(value))));
+            }
+            left => left.into_array(num_rows),
+        };
+        let left_values = match left.as_any().downcast_ref::<BooleanArray>() {
+            Some(left_values) => left_values,
+            None => return Err(self.logical_type_error(batch, left.data_type())),
+        };
+
+        let selected = left_values
+            .iter()
+            .filter(|value| *value != Some(decisive))
+            .count();
+        let right = if selected == 0 {
+            return Ok(ColumnarValue::Array(left));
+        } else if selected == num_rows {
+            self.right.evaluate(batch)?
+        } else {
+            // the unselected rows of the right operand are NULL, which does not change
+            // the result of `false AND NULL` or `true OR NULL`
+            let selection: BooleanArray = left_values
+                .iter()
+                .map(|value| Some(value != Some(decisive)))
+                .collect();
+            self.right.evaluate_selection(batch, &selection)?
+        };
+        let right = right.into_array(num_rows);
+        if right.data_type() != &DataType::Boolean {
+            return Err(self.logical_type_error(batch, left.data_type()));
+        }
+
+        let result: Result<ArrayRef> = if self.op == Operator::And {
+            boolean_op!(left, right, and_kleene)
+        } else {
+            boolean_op!(left, right, or_kleene)
+        };
+        result.map(ColumnarValue::Array)
+    }
+
+    fn logical_type_error(
+        &self,
+        batch: &RecordBatch,
+        left_data_type: &DataType,
+    ) -> DataFusionError {
+        let right_data_type = self
+            .right
+            .data_type(&batch.schema())
+            .unwrap_or_else(|_| left_data_type.clone());
+        DataFusionError::Internal(format!(
+            "Cannot evaluate binary expression {:?} with types {:?} and {:?}",
+            self.op, left_data_type, right_data_type
+        ))
+    }
+}
+
 impl PhysicalExpr for BinaryExpr {
     /// Return a reference to Any that can be used for downcasting
     fn as_any(&self) -> &dyn Any {
@@ -431,10 +571,20 @@ impl PhysicalExpr for BinaryExpr {
     }
 
     fn nullable(&self, input_schema: &Schema) -> Result<bool> {
-        Ok(self.left.nullable(input_schema)? || self.right.nullable(input_schema)?)
+        match self.op {
+            Operator::IsDistinctFrom | Operator::IsNotDistinctFrom => Ok(false),
+            _ => {
+                Ok(self.left.nullable(input_schema)?
+                    || self.right.nullable(input_schema)?)
+            }
+        }
     }
 
     fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        if matches!(self.op, Operator::And | Operator::Or) {
+            return self.evaluate_logical(batch);
+        }
+
         let left_value = self.left.evaluate(batch)?;
         let right_value = self.right.evaluate(batch)?;
         let left_data_type = left_value.data_type();
@@ -463,11 +613,13 @@ impl PhysicalExpr for BinaryExpr {
                     Operator::NotEq => {
                         binary_array_op_scalar!(array, scalar.clone(), neq)
                     }
-                    Operator::Like => {
-                        binary_string_array_op_scalar!(array, scalar.clone(), like)
-                    }
-                    Operator::NotLike => {
-                        binary_string_array_op_scalar!(array, scalar.clone(), nlike)
+                    Operator::Like
+                    | Operator::NotLike
+                    | Operator::ILike
+                    | Operator::NotILike
+                    | Operator::SimilarTo
+                    | Operator::NotSimilarTo => {
+                        Some(pattern_match_scalar(&self.op, array, scalar))
                     }
                     Operator::Divide => {
                         binary_primitive_array_op_scalar!(array, scalar.clone(), divide)
@@ -512,46 +664,56 @@ impl PhysicalExpr for BinaryExpr {
         );
 
         let result: Result<ArrayRef> = match &self.op {
-            Operator::Like => binary_string_array_op!(left, right, like),
-            Operator::NotLike => binary_string_array_op!(left, right, nlike),
+            Operator::Like
+            | Operator::NotLike
+            | Operator::ILike
+            | Operator::NotILike
+            | Operator::SimilarTo
+            | Operator::NotSimilarTo => pattern_match(&self.op, &left, &right),
             Operator::Lt => binary_array_op!(left, right, lt),
             Operator::LtEq => binary_array_op!(left, right, lt_eq),
             Operator::Gt => binary_array_op!(left, right, gt),
             Operator::GtEq => binary_array_op!(left, right, gt_eq),
             Operator::Eq => binary_array_op!(left, right, eq),
             Operator::NotEq => binary_array_op!(left, right, neq),
+            Operator::IsDistinctFrom => is_not_distinct_from(&left, &right)
+                .and_then(|a| not(&a).map_err(DataFusionError::ArrowError))
+                .map(|a| Arc::new(a) as ArrayRef),
+            Operator::IsNotDistinctFrom => {
+                is_not_distinct_from(&left, &right).map(|a| Arc::new(a) as ArrayRef)
+            }
             Operator::Plus => binary_primitive_array_op!(left, right, add),
             Operator::Minus => binary_primitive_array_op!(left, right, subtract),
             Operator::Multiply => binary_primitive_array_op!(left, right, multiply),
             Operator::Divide => binary_primitive_array_op!(left, right, divide),
             Operator::Modulus => binary_primitive_array_op!(left, right, modulus),
-            Operator::And => {
-                if left_data_type == DataType::Boolean {
-                    boolean_op!(left, right, and_kleene)
-                } else {
-                    return Err(DataFusionError::Internal(format!(
-                        "Cannot evaluate binary expression {:?} with types {:?} and {:?}",
-                        self.op,
-                        left.data_type(),
-                        right.data_type()
-                    )));
-                }
-            }
-            Operator::Or => {
-                if left_data_type == DataType::Boolean {
-                    boolean_op!(left, right, or_kleene)
-                } else {
-                    return Err(DataFusionError::Internal(format!(
-                        "Cannot evaluate binary expression {:?} with types {:?} and {:?}",
-                        self.op, left_data_type, right_data_type
-                    )));
-                }
+            Operator::And | Operator::Or => {
+                return Err(DataFusionError::Internal(format!(
+                    "Binary expression {:?} must be evaluated as a logical expression",
+                    self.op
+                )));
             }
         };
         result.map(|a| ColumnarValue::Array(a))
     }
 }
 
+/// Compares the values of `left` and `right` like `=`, except that NULL is equal to
+/// NULL and not equal to any other value, so that the result is never NULL
+fn is_not_distinct_from(left: &ArrayRef, right: &ArrayRef) -> Result<BooleanArray> {
+    let eq = binary_array_op!(left, right, eq)?;
+    let eq = eq.as_any().downcast_ref::<BooleanArray>().unwrap();
+    Ok((0..left.len())
+        .map(|i| {
+            Some(match (left.is_null(i), right.is_null(i)) {
+                (true, true) => true,
+                (false, false) => eq.value(i),
+                _ => false,
+            })
+        })
+        .collect())
+}
+
 /// return two physical expressions that are optionally coerced to a
 /// common type that the binary operator supports.
 fn binary_cast(
@@ -564,6 +726,12 @@ fn binary_cast(
     let rhs_type = &rhs.data_type(input_schema)?;
 
     let cast_type = common_binary_type(lhs_type, op, rhs_type)?;
+    // comparisons with literals may be evaluated in the type of the other argument
+    let cast_type = if is_comparison_operator(op) {
+        comparison_cast_type(&lhs, &rhs, input_schema)?.unwrap_or(cast_type)
+    } else {
+        cast_type
+    };
 
     Ok((
         try_cast(lhs, input_schema, cast_type.clone())?,
@@ -591,7 +759,7 @@ mod tests {
 
     use super::*;
     use crate::error::Result;
-    use crate::physical_plan::expressions::col;
+    use crate::physical_plan::expressions::{col, lit};
 
     // Create a binary expression without coercion. Used here when we do not want to coerce the expressions
     // to valid types. Usage can result in an execution (after plan) error.
@@ -632,6 +800,92 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn binary_is_distinct_from() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, true),
+        ]);
+        let a = Int32Array::from(vec![Some(1), Some(2), None, None]);
+        let b = Int32Array::from(vec![Some(1), Some(3), Some(3), None]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(a), Arc::new(b)],
+        )?;
+
+        let cases = vec![
+            (Operator::IsDistinctFrom, vec![false, true, true, false]),
+            (Operator::IsNotDistinctFrom, vec![true, false, false, true]),
+        ];
+        for (op, expected) in cases {
+            let expr = binary(col("a", &schema)?, op, col("b", &schema)?, &schema)?;
+            assert_eq!(expr.data_type(&schema)?, DataType::Boolean);
+            assert!(!expr.nullable(&schema)?);
+            let result = expr.evaluate(&batch)?.into_array(batch.num_rows());
+            let result = result
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .expect("failed to downcast to BooleanArray");
+            assert_eq!(result.null_count(), 0);
+            let result = result.iter().map(|v| v.unwrap()).collect::<Vec<_>>();
+            assert_eq!(result, expected, "{}", op);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn binary_comparison_binary_arrays() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Binary, true),
+            Field::new("b", DataType::Binary, true),
+        ]);
+        let a = BinaryArray::from(vec![
+            Some(&b"ab"[..]),
+            Some(&b"b"[..]),
+            Some(&b"c"[..]),
+            None,
+        ]);
+        let b = BinaryArray::from(vec![
+            Some(&b"b"[..]),
+            Some(&b"b"[..]),
+            Some(&b"a"[..]),
+            Some(&b"a"[..]),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(a), Arc::new(b)],
+        )?;
+
+        // expression: "a < b"
+        let expr = binary_simple(col("a", &schema)?, Operator::Lt, col("b", &schema)?);
+        let result = expr.evaluate(&batch)?.into_array(batch.num_rows());
+        let result = result
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .expect("failed to downcast to BooleanArray");
+        let expected =
+            BooleanArray::from(vec![Some(true), Some(false), Some(false), None]);
+        assert_eq!(result, &expected);
+
+        // expression: "a = X'62'"
+        let expr = binary_simple(
+            col("a", &schema)?,
+            Operator::Eq,
+            lit(ScalarValue::Binary(Some(b"b".to_vec()))),
+        );
+        let result = expr.evaluate(&batch)?.into_array(batch.num_rows());
+        let result = result
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .expect("failed to downcast to BooleanArray");
+        let expected =
+            BooleanArray::from(vec![Some(false), Some(true), Some(false), None]);
+        assert_eq!(result, &expected);
+
+        Ok(())
+    }
+
     #[test]
     fn binary_nested() -> Result<()> {
         let schema = Schema::new(vec![
@@ -774,6 +1028,18 @@ mod tests {
             DataType::Boolean,
             vec![true, false]
         );
+        test_coercion!(
+            LargeStringArray,
+            DataType::LargeUtf8,
+            vec!["hello", "world"],
+            StringArray,
+            DataType::Utf8,
+            vec!["hello", "hello"],
+            Operator::Eq,
+            BooleanArray,
+            DataType::Boolean,
+            vec![true, false]
+        );
         test_coercion!(
             StringArray,
             DataType::Utf8,
@@ -1135,6 +1401,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn logical_op_short_circuits() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let a = Int32Array::from(vec![Some(0), Some(5), None, Some(0), Some(20)]);
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(a)])?;
+
+        // "100 / a > 10" fails on the rows where a is 0, so it must only be evaluated
+        // on the rows where the left operand does not decide the result
+        let zero = || lit(ScalarValue::Int32(Some(0)));
+        let quotient = || -> Result<Arc<dyn PhysicalExpr>> {
+            let div = binary_simple(
+                lit(ScalarValue::Int32(Some(100))),
+                Operator::Divide,
+                col("a", &schema)?,
+            );
+            Ok(binary_simple(
+                div,
+                Operator::Gt,
+                lit(ScalarValue::Int32(Some(10))),
+            ))
+        };
+
+        // expression: "a = 0 OR 100 / a > 10"
+        let or = binary_simple(
+            binary_simple(col("a", &schema)?, Operator::Eq, zero()),
+            Operator::Or,
+            quotient()?,
+        );
+        let result = or.evaluate(&batch)?.into_array(batch.num_rows());
+        let expected = BooleanArray::from(vec![
+            Some(true),
+            Some(true),
+            None,
+            Some(true),
+            Some(false),
+        ]);
+        assert_eq!(result.as_ref(), &expected);
+
+        // expression: "a != 0 AND 100 / a > 10"
+        let and = binary_simple(
+            binary_simple(col("a", &schema)?, Operator::NotEq, zero()),
+            Operator::And,
+            quotient()?,
+        );
+        let result = and.evaluate(&batch)?.into_array(batch.num_rows());
+        let expected = BooleanArray::from(vec![
+            Some(false),
+            Some(true),
+            None,
+            Some(false),
+            Some(false),
+        ]);
+        assert_eq!(result.as_ref(), &expected);
+
+        // a decisive literal does not evaluate the right operand at all
+        let and = binary_simple(
+            lit(ScalarValue::Boolean(Some(false))),
+            Operator::And,
+            quotient()?,
+        );
+        let result = and.evaluate(&batch)?.into_array(batch.num_rows());
+        assert_eq!(result.as_ref(), &BooleanArray::from(vec![false; 5]));
+
+        Ok(())
+    }
+
     #[test]
     fn test_coersion_error() -> Result<()> {
         let expr =
@@ -23,11 +23,13 @@ use super::ColumnarValue;
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::PhysicalExpr;
 use crate::scalar::ScalarValue;
+use arrow::array::{Array, ArrayRef};
 use arrow::compute;
 use arrow::compute::kernels;
 use arrow::compute::CastOptions;
 use arrow::datatypes::{DataType, Schema};
 use arrow::record_batch::RecordBatch;
+use arrow::util::display::array_value_to_string;
 use compute::can_cast_types;
 
 /// provide Datafusion default cast options
@@ -67,6 +69,11 @@ impl CastExpr {
     pub fn cast_type(&self) -> &DataType {
         &self.cast_type
     }
+
+    /// The cast options
+    pub fn cast_options(&self) -> &CastOptions {
+        &self.cast_options
+    }
 }
 
 impl fmt::Display for CastExpr {
@@ -102,19 +109,53 @@ pub fn cast_column(
     cast_options: &CastOptions,
 ) -> Result<ColumnarValue> {
     match value {
-        ColumnarValue::Array(array) => Ok(ColumnarValue::Array(
-            kernels::cast::cast_with_options(array, cast_type, cast_options)?,
-        )),
+        ColumnarValue::Array(array) => Ok(ColumnarValue::Array(cast_array(
+            array,
+            cast_type,
+            cast_options,
+        )?)),
         ColumnarValue::Scalar(scalar) => {
             let scalar_array = scalar.to_array();
-            let cast_array =
-                kernels::cast::cast_with_options(&scalar_array, cast_type, cast_options)?;
+            let cast_array = cast_array(&scalar_array, cast_type, cast_options)?;
             let cast_scalar = ScalarValue::try_from_array(&cast_array, 0)?;
             Ok(ColumnarValue::Scalar(cast_scalar))
         }
     }
 }
 
+/// Casts `array` to `cast_type`. When the cast fails, the error reports the first row
+/// of the batch whose value cannot be cast, if the failure is caused by a value.
+fn cast_array(
+    array: &ArrayRef,
+    cast_type: &DataType,
+    cast_options: &CastOptions,
+) -> Result<ArrayRef> {
+    kernels::cast::cast_with_options(array, cast_type, cast_options).map_err(|e| {
+        match first_invalid_value(array, cast_type) {
+            Some((row, value)) => DataFusionError::Execution(format!(
+                "Cannot cast value '{}' in row {} of the batch to {:?}: {}",
+                value, row, cast_type, e
+            )),
+            None => DataFusionError::ArrowError(e),
+        }
+    })
+}
+
+/// Returns the index and the display value of the first non-null value of `array`
+/// that cannot be cast to `cast_type`
+fn first_invalid_value(
+    array: &ArrayRef,
+    cast_type: &DataType,
+) -> Option<(usize, String)> {
+    // a safe cast replaces the values that cannot be cast with nulls
+    let cast =
+        kernels::cast::cast_with_options(array, cast_type, &CastOptions { safe: true })
+            .ok()?;
+    (0..array.len())
+        .find(|i| array.is_valid(*i) && cast.is_null(*i))
+        .map(|i| (i, array_value_to_string(array, i).unwrap_or_default()))
+}
+
 /// Return a PhysicalExpression representing `expr` casted to
 /// `cast_type`, if any casting is needed.
 ///
@@ -280,6 +321,22 @@ mod tests {
         result.expect_err("expected Invalid CAST");
     }
 
+    #[test]
+    fn invalid_cast_reports_row() -> Result<()> {
+        let schema = Schema::new(vec![Field::new("a", DataType::Utf8, true)]);
+        let a = StringArray::from(vec![Some("1"), None, Some("x2"), Some("x3")]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), vec![Arc::new(a)])?;
+        let expression = cast(col("a", &schema)?, &schema, DataType::Int64)?;
+
+        let e = expression.evaluate(&batch).unwrap_err().to_string();
+        assert!(
+            e.contains("Cannot cast value 'x2' in row 2 of the batch to Int64"),
+            "unexpected error: {}",
+            e
+        );
+        Ok(())
+    }
+
     #[test]
     fn invalid_cast_with_options_error() -> Result<()> {
         // Ensure a useful error happens at plan time if invalid casts are used
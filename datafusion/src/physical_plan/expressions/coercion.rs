@@ -19,6 +19,8 @@
 
 use arrow::datatypes::DataType;
 
+use crate::physical_plan::type_coercion::comparison_coercion;
+
 /// Determine if a DataType is signed numeric or not
 pub fn is_signed_numeric(dt: &DataType) -> bool {
     matches!(
@@ -155,29 +157,16 @@ pub fn numerical_coercion(lhs_type: &DataType, rhs_type: &DataType) -> Option<Da
     }
 }
 
-// coercion rules for equality operations. This is a superset of all numerical coercion rules.
+/// Coercion rules for equality operations, see
+/// [comparison_coercion](crate::physical_plan::type_coercion::comparison_coercion)
 pub fn eq_coercion(lhs_type: &DataType, rhs_type: &DataType) -> Option<DataType> {
-    if lhs_type == rhs_type {
-        // same type => equality is possible
-        return Some(lhs_type.clone());
-    }
-    numerical_coercion(lhs_type, rhs_type)
-        .or_else(|| dictionary_coercion(lhs_type, rhs_type))
-        .or_else(|| temporal_coercion(lhs_type, rhs_type))
+    comparison_coercion(lhs_type, rhs_type)
 }
 
-// coercion rules that assume an ordered set, such as "less than".
-// These are the union of all numerical coercion rules and all string coercion rules
+/// Coercion rules that assume an ordered set, such as "less than", see
+/// [comparison_coercion](crate::physical_plan::type_coercion::comparison_coercion)
 pub fn order_coercion(lhs_type: &DataType, rhs_type: &DataType) -> Option<DataType> {
-    if lhs_type == rhs_type {
-        // same type => all good
-        return Some(lhs_type.clone());
-    }
-
-    numerical_coercion(lhs_type, rhs_type)
-        .or_else(|| string_coercion(lhs_type, rhs_type))
-        .or_else(|| dictionary_coercion(lhs_type, rhs_type))
-        .or_else(|| temporal_coercion(lhs_type, rhs_type))
+    comparison_coercion(lhs_type, rhs_type)
 }
 
 #[cfg(test)]
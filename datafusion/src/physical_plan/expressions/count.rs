@@ -26,7 +26,7 @@ use crate::scalar::ScalarValue;
 use arrow::compute;
 use arrow::datatypes::DataType;
 use arrow::{
-    array::{ArrayRef, UInt64Array},
+    array::{Array, ArrayRef, BooleanArray, UInt64Array},
     datatypes::Field,
 };
 
@@ -112,6 +112,25 @@ impl Accumulator for CountAccumulator {
         Ok(())
     }
 
+    fn update_batch_selected(
+        &mut self,
+        values: &[ArrayRef],
+        selection: Option<&BooleanArray>,
+    ) -> Result<()> {
+        let selection = match selection {
+            Some(selection) => selection,
+            None => return self.update_batch(values),
+        };
+        // counts the selected non-null values without filtering the array
+        let array = &values[0];
+        self.count += (0..array.len())
+            .filter(|i| {
+                selection.is_valid(*i) && selection.value(*i) && array.is_valid(*i)
+            })
+            .count() as u64;
+        Ok(())
+    }
+
     fn update(&mut self, values: &[ScalarValue]) -> Result<()> {
         let value = &values[0];
         if !value.is_null() {
@@ -0,0 +1,143 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines physical expression for `cume_dist` that can evaluated
+//! at runtime during query execution
+
+use crate::error::Result;
+use crate::physical_plan::window_functions::PartitionEvaluator;
+use crate::physical_plan::{window_functions::BuiltInWindowFunctionExpr, PhysicalExpr};
+use arrow::array::ArrayRef;
+use arrow::array::Float64Array;
+use arrow::datatypes::{DataType, Field};
+use arrow::record_batch::RecordBatch;
+use std::any::Any;
+use std::iter;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// CumeDist calculates the cume_dist in the window function with order by
+#[derive(Debug)]
+pub struct CumeDist {
+    name: String,
+}
+
+/// Create a cume_dist window function
+pub fn cume_dist(name: String) -> CumeDist {
+    CumeDist { name }
+}
+
+impl BuiltInWindowFunctionExpr for CumeDist {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        let nullable = false;
+        let data_type = DataType::Float64;
+        Ok(Field::new(self.name(), data_type, nullable))
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn create_evaluator(
+        &self,
+        _batch: &RecordBatch,
+    ) -> Result<Box<dyn PartitionEvaluator>> {
+        Ok(Box::new(CumeDistEvaluator {}))
+    }
+}
+
+pub(crate) struct CumeDistEvaluator;
+
+impl PartitionEvaluator for CumeDistEvaluator {
+    fn include_rank(&self) -> bool {
+        true
+    }
+
+    fn evaluate_partition(&self, _partition: Range<usize>) -> Result<ArrayRef> {
+        unreachable!(
+            "cume_dist evaluation must be called with evaluate_partition_with_rank"
+        )
+    }
+
+    fn evaluate_partition_with_rank(
+        &self,
+        partition: Range<usize>,
+        ranks_in_partition: &[Range<usize>],
+    ) -> Result<ArrayRef> {
+        // the fraction of the rows of the partition that precede the row or are its peers
+        let num_rows = (partition.end - partition.start) as f64;
+        let result =
+            Float64Array::from_iter_values(ranks_in_partition.iter().flat_map(|range| {
+                let len = range.end - range.start;
+                let value = (range.end - partition.start) as f64 / num_rows;
+                iter::repeat(value).take(len)
+            }));
+        Ok(Arc::new(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::{array::*, datatypes::*};
+
+    fn test_i32_result(
+        expr: &CumeDist,
+        num_rows: usize,
+        ranks: Vec<Range<usize>>,
+        expected: Vec<f64>,
+    ) -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from_iter_values(0..num_rows as i32));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, false)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let result = expr
+            .create_evaluator(&batch)?
+            .evaluate_with_rank(vec![0..num_rows], ranks)?;
+        assert_eq!(1, result.len());
+        let result = result[0].as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(expected, result.values());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cume_dist() -> Result<()> {
+        let r = cume_dist("arr".into());
+
+        // empty partition
+        test_i32_result(&r, 0, vec![], vec![])?;
+
+        // singleton partition
+        test_i32_result(&r, 1, vec![0..1], vec![1.0])?;
+
+        // uniform partition
+        test_i32_result(&r, 2, vec![0..2], vec![1.0, 1.0])?;
+
+        // peers share the fraction of their last row
+        test_i32_result(&r, 4, vec![0..2, 2..4], vec![0.5, 0.5, 1.0, 1.0])?;
+        test_i32_result(&r, 4, vec![0..1, 1..4], vec![0.25, 1.0, 1.0, 1.0])?;
+        Ok(())
+    }
+}
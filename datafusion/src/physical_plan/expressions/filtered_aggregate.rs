@@ -0,0 +1,213 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines the aggregate expressions with a `FILTER` clause, e.g.
+//! `COUNT(x) FILTER (WHERE y > 0)`, which only aggregate the rows that pass the filter.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::error::{DataFusionError, Result};
+use crate::physical_plan::{Accumulator, AggregateExpr, PhysicalExpr};
+use crate::scalar::ScalarValue;
+use arrow::array::{ArrayRef, BooleanArray};
+use arrow::datatypes::{DataType, Field, Schema};
+
+/// An aggregate expression that only aggregates the rows for which its filter is true.
+///
+/// The filter is evaluated together with the arguments of the aggregate, as its last
+/// expression, and is passed to the accumulator of the aggregate as the selection of
+/// [`Accumulator::update_batch_selected`]. The state of the accumulators is the one of
+/// the aggregate, as merging states is not affected by the filter.
+#[derive(Debug)]
+pub struct FilteredAggregate {
+    aggregate: Arc<dyn AggregateExpr>,
+    filter: Arc<dyn PhysicalExpr>,
+}
+
+impl FilteredAggregate {
+    /// Create a new aggregate expression that only aggregates the rows of
+    /// `input_schema` for which the boolean expression `filter` is true.
+    pub fn try_new(
+        aggregate: Arc<dyn AggregateExpr>,
+        filter: Arc<dyn PhysicalExpr>,
+        input_schema: &Schema,
+    ) -> Result<Self> {
+        match filter.data_type(input_schema)? {
+            DataType::Boolean => Ok(Self { aggregate, filter }),
+            data_type => Err(DataFusionError::Plan(format!(
+                "The FILTER clause of {} must be a boolean expression, not {:?}",
+                aggregate.name(),
+                data_type
+            ))),
+        }
+    }
+
+    /// The aggregate expression whose input rows are filtered
+    pub fn aggregate(&self) -> &Arc<dyn AggregateExpr> {
+        &self.aggregate
+    }
+
+    /// The filter of the input rows
+    pub fn filter(&self) -> &Arc<dyn PhysicalExpr> {
+        &self.filter
+    }
+}
+
+impl AggregateExpr for FilteredAggregate {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        self.aggregate.field()
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        self.aggregate.state_fields()
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        let mut expressions = self.aggregate.expressions();
+        expressions.push(self.filter.clone());
+        expressions
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(FilteredAccumulator {
+            accumulator: self.aggregate.create_accumulator()?,
+        }))
+    }
+
+    fn name(&self) -> &str {
+        self.aggregate.name()
+    }
+}
+
+/// Accumulator of a [`FilteredAggregate`], whose last input is the filter
+#[derive(Debug)]
+struct FilteredAccumulator {
+    accumulator: Box<dyn Accumulator>,
+}
+
+impl Accumulator for FilteredAccumulator {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        self.accumulator.state()
+    }
+
+    fn update(&mut self, values: &[ScalarValue]) -> Result<()> {
+        let (filter, values) = values.split_last().unwrap();
+        match filter {
+            ScalarValue::Boolean(Some(true)) => self.accumulator.update(values),
+            _ => Ok(()),
+        }
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        self.update_batch_selected(values, None)
+    }
+
+    fn update_batch_selected(
+        &mut self,
+        values: &[ArrayRef],
+        selection: Option<&BooleanArray>,
+    ) -> Result<()> {
+        let (filter, values) = values.split_last().unwrap();
+        let filter = filter
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .ok_or_else(|| {
+                DataFusionError::Internal(
+                    "The filter of an aggregate must be a boolean array".to_string(),
+                )
+            })?;
+        // the rows that are selected and pass the filter
+        let selection = match selection {
+            Some(selection) => arrow::compute::and(selection, filter)?,
+            None => filter.clone(),
+        };
+        self.accumulator
+            .update_batch_selected(values, Some(&selection))
+    }
+
+    fn merge(&mut self, states: &[ScalarValue]) -> Result<()> {
+        self.accumulator.merge(states)
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        self.accumulator.merge_batch(states)
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        self.accumulator.evaluate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::Operator;
+    use crate::physical_plan::expressions::{binary, col, lit, Count, Sum};
+    use arrow::array::Int32Array;
+    use arrow::record_batch::RecordBatch;
+
+    fn aggregate(agg: &dyn AggregateExpr, batch: &RecordBatch) -> Result<ScalarValue> {
+        let values = agg
+            .expressions()
+            .iter()
+            .map(|e| e.evaluate(batch).map(|v| v.into_array(batch.num_rows())))
+            .collect::<Result<Vec<_>>>()?;
+        let mut accumulator = agg.create_accumulator()?;
+        accumulator.update_batch(&values)?;
+        accumulator.evaluate()
+    }
+
+    #[test]
+    fn filtered_aggregates() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None, Some(3), Some(4)])),
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), None, Some(4)])),
+            ],
+        )?;
+        // b > 1, which is null for the third row
+        let filter = binary(
+            col("b", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::Int32(Some(1))),
+            &schema,
+        )?;
+
+        let count = Arc::new(Count::new(col("a", &schema)?, "count", DataType::UInt64));
+        let count = FilteredAggregate::try_new(count, filter.clone(), &schema)?;
+        assert_eq!(aggregate(&count, &batch)?, ScalarValue::UInt64(Some(1)));
+
+        let sum = Arc::new(Sum::new(col("a", &schema)?, "sum", DataType::Int64));
+        let sum = FilteredAggregate::try_new(sum, filter, &schema)?;
+        assert_eq!(aggregate(&sum, &batch)?, ScalarValue::Int64(Some(4)));
+
+        // the filter must be a boolean expression
+        let count = Arc::new(Count::new(col("a", &schema)?, "count", DataType::UInt64));
+        assert!(FilteredAggregate::try_new(count, col("b", &schema)?, &schema).is_err());
+        Ok(())
+    }
+}
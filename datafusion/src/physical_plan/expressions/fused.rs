@@ -0,0 +1,495 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines fused predicates, which evaluate a conjunction of comparisons between a
+//! column and a literal, e.g. `a > 1 AND CAST(b AS Int64) <= 10`, in one pass over each
+//! column instead of evaluating the expression tree of each comparison.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use super::{cast_column, BinaryExpr, CastExpr, Column, Literal};
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Operator;
+use crate::physical_plan::{ColumnarValue, PhysicalExpr};
+use crate::scalar::ScalarValue;
+use arrow::array::*;
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+
+/// A comparison compiled for the column of a batch, which updates the results of the
+/// conjunction for each row: a row that is `false` stays `false`, and a comparison
+/// with a null value makes the result of a row `NULL` unless it is `false`.
+type CompiledComparison<'a> = Box<dyn Fn(&mut [Option<bool>]) + 'a>;
+
+/// Compiles the comparison of the values of `$ARRAY`, converted to `$NATIVE`, with
+/// the literal `$VALUE`, or returns `None` if `$ARRAY` is not a `$ARRAY_TYPE`
+macro_rules! compile_comparison {
+    ($ARRAY:expr, $ARRAY_TYPE:ident, $NATIVE:ty, $OP:expr, $VALUE:expr) => {{
+        let array = $ARRAY.as_any().downcast_ref::<$ARRAY_TYPE>()?;
+        let value: $NATIVE = $VALUE;
+        let cmp = comparator::<$NATIVE>($OP)?;
+        #[allow(clippy::unnecessary_cast)]
+        let compiled: CompiledComparison = Box::new(move |results| {
+            for (row, result) in results.iter_mut().enumerate() {
+                if *result == Some(false) {
+                    continue;
+                }
+                if array.is_null(row) {
+                    *result = None;
+                } else if !cmp(&(array.value(row) as $NATIVE), &value) {
+                    *result = Some(false);
+                }
+            }
+        });
+        Some(compiled)
+    }};
+}
+
+/// Compiles the comparison of a primitive array with a literal, whose type is either
+/// the type of the array or a type that the values of the array are cast to without
+/// loss, for the listed pairs of array types and literal types
+macro_rules! compile_primitive_comparison {
+    ($ARRAY:expr, $OP:expr, $LITERAL:expr,
+     $(($DATA_TYPE:ident, $ARRAY_TYPE:ident => $($SCALAR:ident: $NATIVE:ty),+)),+) => {
+        match ($ARRAY.data_type(), $LITERAL) {
+            $($(
+                (DataType::$DATA_TYPE, ScalarValue::$SCALAR(Some(value))) => {
+                    compile_comparison!($ARRAY, $ARRAY_TYPE, $NATIVE, $OP, *value)
+                }
+            )+)+
+            _ => None,
+        }
+    };
+}
+
+/// Returns the function that compares two values with the comparison operator `op`
+fn comparator<T: PartialOrd>(op: Operator) -> Option<fn(&T, &T) -> bool> {
+    let cmp: fn(&T, &T) -> bool = match op {
+        Operator::Eq => T::eq,
+        Operator::NotEq => T::ne,
+        Operator::Lt => T::lt,
+        Operator::LtEq => T::le,
+        Operator::Gt => T::gt,
+        Operator::GtEq => T::ge,
+        _ => return None,
+    };
+    Some(cmp)
+}
+
+/// Returns the comparison operator `op'` such that `a op b` is `b op' a`, or `None` if
+/// `op` is not a comparison operator
+fn commute(op: Operator) -> Option<Operator> {
+    match op {
+        Operator::Eq | Operator::NotEq => Some(op),
+        Operator::Lt => Some(Operator::Gt),
+        Operator::LtEq => Some(Operator::GtEq),
+        Operator::Gt => Some(Operator::Lt),
+        Operator::GtEq => Some(Operator::LtEq),
+        _ => None,
+    }
+}
+
+/// Returns the value of a literal, or of a cast literal
+fn literal_value(expr: &Arc<dyn PhysicalExpr>) -> Option<ScalarValue> {
+    if let Some(literal) = expr.as_any().downcast_ref::<Literal>() {
+        return Some(literal.value().clone());
+    }
+    let cast = expr.as_any().downcast_ref::<CastExpr>()?;
+    let literal = cast.expr().as_any().downcast_ref::<Literal>()?;
+    let value = ColumnarValue::Scalar(literal.value().clone());
+    match cast_column(&value, cast.cast_type(), cast.cast_options()) {
+        Ok(ColumnarValue::Scalar(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// The comparison `column op value`, or `CAST(column AS cast_type) op value`
+#[derive(Debug, Clone)]
+struct ColumnComparison {
+    column: Column,
+    /// The type the column is cast to before it is compared, if it is cast
+    cast_type: Option<DataType>,
+    op: Operator,
+    value: ScalarValue,
+}
+
+impl ColumnComparison {
+    /// Returns the comparison that `expr` is, if it compares a column, possibly cast,
+    /// with a non null literal
+    fn try_from_expr(expr: &Arc<dyn PhysicalExpr>) -> Option<Self> {
+        let binary = expr.as_any().downcast_ref::<BinaryExpr>()?;
+        let op = *binary.op();
+        let (column, op, value) =
+            match (literal_value(binary.right()), literal_value(binary.left())) {
+                (Some(value), _) if commute(op).is_some() => (binary.left(), op, value),
+                (_, Some(value)) => (binary.right(), commute(op)?, value),
+                _ => return None,
+            };
+        if value.is_null() {
+            return None;
+        }
+        let (column, cast_type) = match column.as_any().downcast_ref::<CastExpr>() {
+            Some(cast) if *cast.cast_type() == value.get_datatype() => {
+                (cast.expr(), Some(cast.cast_type().clone()))
+            }
+            Some(_) => return None,
+            None => (column, None),
+        };
+        let column = column.as_any().downcast_ref::<Column>()?.clone();
+        Some(Self {
+            column,
+            cast_type,
+            op,
+            value,
+        })
+    }
+
+    /// Returns whether the fused kernels support the type of the column in
+    /// `input_schema` and its cast
+    fn is_supported(&self, input_schema: &Schema) -> bool {
+        match input_schema.fields().get(self.column.index()) {
+            Some(field) => self
+                .compile(&new_null_array(field.data_type(), 0))
+                .is_some(),
+            None => false,
+        }
+    }
+
+    /// Compiles the comparison for the values of the column, or returns `None` if the
+    /// fused kernels do not support their type
+    fn compile<'a>(&'a self, array: &'a ArrayRef) -> Option<CompiledComparison<'a>> {
+        // the values of a column that is not cast are only compared with a literal of
+        // the same type
+        if self.cast_type.is_none() && *array.data_type() != self.value.get_datatype() {
+            return None;
+        }
+        if let (DataType::Utf8, ScalarValue::Utf8(Some(value))) =
+            (array.data_type(), &self.value)
+        {
+            return compile_comparison!(array, StringArray, &str, self.op, value);
+        }
+        compile_primitive_comparison!(array, self.op, &self.value,
+            (Int8, Int8Array => Int8: i8, Int16: i16, Int32: i32, Int64: i64,
+                Float32: f32, Float64: f64),
+            (Int16, Int16Array => Int16: i16, Int32: i32, Int64: i64, Float32: f32,
+                Float64: f64),
+            (Int32, Int32Array => Int32: i32, Int64: i64, Float64: f64),
+            (Int64, Int64Array => Int64: i64, Float64: f64),
+            (UInt8, UInt8Array => UInt8: u8, UInt16: u16, UInt32: u32, UInt64: u64,
+                Int16: i16, Int32: i32, Int64: i64, Float32: f32, Float64: f64),
+            (UInt16, UInt16Array => UInt16: u16, UInt32: u32, UInt64: u64, Int32: i32,
+                Int64: i64, Float32: f32, Float64: f64),
+            (UInt32, UInt32Array => UInt32: u32, UInt64: u64, Int64: i64, Float64: f64),
+            (UInt64, UInt64Array => UInt64: u64, Float64: f64),
+            (Float32, Float32Array => Float32: f32, Float64: f64),
+            (Float64, Float64Array => Float64: f64),
+            (Date32, Date32Array => Date32: i32),
+            (Date64, Date64Array => Date64: i64)
+        )
+    }
+}
+
+impl fmt::Display for ColumnComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.cast_type {
+            Some(cast_type) => write!(
+                f,
+                "CAST({} AS {:?}) {} {}",
+                self.column, cast_type, self.op, self.value
+            ),
+            None => write!(f, "{} {} {}", self.column, self.op, self.value),
+        }
+    }
+}
+
+/// A predicate that evaluates a conjunction of comparisons between a column, which may
+/// be cast, and a literal with kernels that are compiled for the types of the columns.
+///
+/// The comparisons are evaluated one column at a time over all the rows of a batch,
+/// skipping the rows that are already `false`, without materializing the casts of the
+/// columns or the results of the individual comparisons. The result is the one of
+/// evaluating the `AND`s of the comparisons.
+#[derive(Debug)]
+pub struct FusedPredicateExpr {
+    /// The conjunction of the comparisons
+    predicate: Arc<dyn PhysicalExpr>,
+    comparisons: Vec<ColumnComparison>,
+}
+
+impl FusedPredicateExpr {
+    /// Create a fused predicate that evaluates `predicate`, a conjunction of
+    /// comparisons between a column and a literal
+    pub fn try_new(predicate: Arc<dyn PhysicalExpr>) -> Result<Self> {
+        let mut conjuncts = vec![];
+        split_conjunction(&predicate, &mut conjuncts);
+        let comparisons = conjuncts
+            .iter()
+            .map(|conjunct| {
+                ColumnComparison::try_from_expr(conjunct).ok_or_else(|| {
+                    DataFusionError::Plan(format!(
+                        "{} is not a comparison between a column and a literal",
+                        conjunct
+                    ))
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            predicate,
+            comparisons,
+        })
+    }
+
+    /// The conjunction of the comparisons that is evaluated
+    pub fn predicate(&self) -> &Arc<dyn PhysicalExpr> {
+        &self.predicate
+    }
+}
+
+impl fmt::Display for FusedPredicateExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FUSED({})", self.predicate)
+    }
+}
+
+impl PhysicalExpr for FusedPredicateExpr {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        self.predicate.nullable(input_schema)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let compiled = self
+            .comparisons
+            .iter()
+            .map(|comparison| comparison.compile(batch.column(comparison.column.index())))
+            .collect::<Option<Vec<_>>>();
+        match compiled {
+            Some(compiled) => {
+                let mut results = vec![Some(true); batch.num_rows()];
+                for comparison in compiled {
+                    comparison(&mut results);
+                }
+                Ok(ColumnarValue::Array(Arc::new(BooleanArray::from(results))))
+            }
+            // the columns of the batch have types without fused kernels
+            None => self.predicate.evaluate(batch),
+        }
+    }
+}
+
+/// Adds the operands of the `AND`s of `expr` to `conjuncts`
+fn split_conjunction(
+    expr: &Arc<dyn PhysicalExpr>,
+    conjuncts: &mut Vec<Arc<dyn PhysicalExpr>>,
+) {
+    match expr.as_any().downcast_ref::<BinaryExpr>() {
+        Some(binary) if *binary.op() == Operator::And => {
+            split_conjunction(binary.left(), conjuncts);
+            split_conjunction(binary.right(), conjuncts);
+        }
+        _ => conjuncts.push(expr.clone()),
+    }
+}
+
+/// Returns the `AND` of `conjuncts`, which must not be empty
+fn conjunction(conjuncts: Vec<Arc<dyn PhysicalExpr>>) -> Arc<dyn PhysicalExpr> {
+    conjuncts
+        .into_iter()
+        .reduce(|left, right| -> Arc<dyn PhysicalExpr> {
+            Arc::new(BinaryExpr::new(left, Operator::And, right))
+        })
+        .unwrap()
+}
+
+/// Returns `predicate` with the comparisons between a column and a literal of its
+/// conjunction fused in a [`FusedPredicateExpr`], which is evaluated before the rest
+/// of the conjunction.
+///
+/// A single comparison of a column that is not cast is left as is, as the comparison
+/// kernels of arrow evaluate it as fast.
+pub fn fuse_predicate(
+    predicate: Arc<dyn PhysicalExpr>,
+    input_schema: &Schema,
+) -> Result<Arc<dyn PhysicalExpr>> {
+    let mut conjuncts = vec![];
+    split_conjunction(&predicate, &mut conjuncts);
+    let mut fused = vec![];
+    let mut rest = vec![];
+    let mut has_cast = false;
+    for conjunct in conjuncts {
+        match ColumnComparison::try_from_expr(&conjunct) {
+            Some(comparison) if comparison.is_supported(input_schema) => {
+                has_cast |= comparison.cast_type.is_some();
+                fused.push(conjunct);
+            }
+            _ => rest.push(conjunct),
+        }
+    }
+    if fused.len() < 2 && !has_cast {
+        return Ok(predicate);
+    }
+    let fused: Arc<dyn PhysicalExpr> =
+        Arc::new(FusedPredicateExpr::try_new(conjunction(fused))?);
+    Ok(rest
+        .into_iter()
+        .fold(fused, |left, right| -> Arc<dyn PhysicalExpr> {
+            Arc::new(BinaryExpr::new(left, Operator::And, right))
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physical_plan::expressions::{binary, cast, col, lit};
+    use arrow::datatypes::Field;
+
+    fn batch() -> Result<RecordBatch> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Float64, true),
+            Field::new("c", DataType::Utf8, true),
+        ]);
+        Ok(RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![
+                    Some(1),
+                    Some(5),
+                    None,
+                    Some(7),
+                    Some(9),
+                ])),
+                Arc::new(Float64Array::from(vec![
+                    Some(1.5),
+                    Some(2.5),
+                    Some(3.5),
+                    None,
+                    Some(0.5),
+                ])),
+                Arc::new(StringArray::from(vec!["x", "y", "x", "x", "z"])),
+            ],
+        )?)
+    }
+
+    fn evaluate(expr: &dyn PhysicalExpr, batch: &RecordBatch) -> Result<ArrayRef> {
+        Ok(expr.evaluate(batch)?.into_array(batch.num_rows()))
+    }
+
+    #[test]
+    fn fused_comparisons() -> Result<()> {
+        let batch = batch()?;
+        let schema = batch.schema();
+        // CAST(a AS Int64) > 2 AND 3.0 > b AND c != 'z'
+        let predicate = binary(
+            binary(
+                binary(
+                    cast(col("a", &schema)?, &schema, DataType::Int64)?,
+                    Operator::Gt,
+                    lit(ScalarValue::Int64(Some(2))),
+                    &schema,
+                )?,
+                Operator::And,
+                binary(
+                    lit(ScalarValue::Float64(Some(3.0))),
+                    Operator::Gt,
+                    col("b", &schema)?,
+                    &schema,
+                )?,
+                &schema,
+            )?,
+            Operator::And,
+            binary(
+                col("c", &schema)?,
+                Operator::NotEq,
+                lit(ScalarValue::Utf8(Some("z".to_owned()))),
+                &schema,
+            )?,
+            &schema,
+        )?;
+
+        let fused = fuse_predicate(predicate.clone(), &schema)?;
+        assert!(fused.as_any().is::<FusedPredicateExpr>());
+        let expected = evaluate(predicate.as_ref(), &batch)?;
+        let actual = evaluate(fused.as_ref(), &batch)?;
+        assert_eq!(&expected, &actual);
+        let expected = BooleanArray::from(vec![
+            Some(false),
+            Some(true),
+            Some(false),
+            None,
+            Some(false),
+        ]);
+        assert_eq!(
+            actual.as_any().downcast_ref::<BooleanArray>().unwrap(),
+            &expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fuse_supported_comparisons() -> Result<()> {
+        let batch = batch()?;
+        let schema = batch.schema();
+        let a_gt_1 = binary(
+            col("a", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::Int32(Some(1))),
+            &schema,
+        )?;
+        let b_lt_a = binary(
+            col("b", &schema)?,
+            Operator::Lt,
+            cast(col("a", &schema)?, &schema, DataType::Float64)?,
+            &schema,
+        )?;
+
+        // a single comparison without a cast is not fused
+        let fused = fuse_predicate(a_gt_1.clone(), &schema)?;
+        assert!(fused.as_any().is::<BinaryExpr>());
+
+        // the comparisons of two columns are evaluated after the fused comparisons
+        let predicate = binary(
+            b_lt_a,
+            Operator::And,
+            binary(a_gt_1.clone(), Operator::And, a_gt_1, &schema)?,
+            &schema,
+        )?;
+        let fused = fuse_predicate(predicate.clone(), &schema)?;
+        assert_eq!(
+            fused.to_string(),
+            "FUSED(a@0 > 1 AND a@0 > 1) AND b@1 < CAST(a@0 AS Float64)"
+        );
+        assert_eq!(
+            evaluate(predicate.as_ref(), &batch)?,
+            evaluate(fused.as_ref(), &batch)?
+        );
+
+        // only conjunctions of comparisons with a literal can be fused
+        assert!(FusedPredicateExpr::try_new(predicate).is_err());
+        Ok(())
+    }
+}
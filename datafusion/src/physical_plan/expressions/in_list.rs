@@ -18,6 +18,7 @@
 //! InList expression
 
 use std::any::Any;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use arrow::array::GenericStringArray;
@@ -33,6 +34,7 @@ use arrow::{
 };
 
 use crate::error::{DataFusionError, Result};
+use crate::physical_plan::expressions::{cast_column, CastExpr, Literal};
 use crate::physical_plan::{ColumnarValue, PhysicalExpr};
 use crate::scalar::ScalarValue;
 use arrow::array::*;
@@ -60,12 +62,22 @@ macro_rules! compare_op_scalar {
     }};
 }
 
+/// Lists with at least this many constant values are evaluated by probing a hash set
+/// rather than by comparing each value against every list item
+pub const IN_LIST_HASH_THRESHOLD: usize = 16;
+
 /// InList
-#[derive(Debug)]
 pub struct InListExpr {
     expr: Arc<dyn PhysicalExpr>,
     list: Vec<Arc<dyn PhysicalExpr>>,
     negated: bool,
+    /// The list values, if every list expression is constant. These are evaluated
+    /// (and therefore coerced to the type of `expr`) once, when the expression is created
+    static_values: Option<Vec<ScalarValue>>,
+    /// Hash set of the non-null `static_values`, only built for large lists
+    static_set: Option<HashSet<ScalarValue>>,
+    /// The strings of `static_set`, which string arrays are probed against
+    static_utf8_set: Option<HashSet<String>>,
 }
 
 macro_rules! make_contains {
@@ -178,6 +190,56 @@ macro_rules! make_contains_primitive {
     }};
 }
 
+macro_rules! set_contains_primitive {
+    ($ARRAY:expr, $SET:expr, $CONTAINS_NULL:expr, $NEGATED:expr, $SCALAR_VALUE:ident, $ARRAY_TYPE:ident) => {{
+        let array = $ARRAY.as_any().downcast_ref::<$ARRAY_TYPE>().unwrap();
+        Ok(ColumnarValue::Array(Arc::new(in_set(
+            array
+                .iter()
+                .map(|x| x.map(|v| $SET.contains(&ScalarValue::$SCALAR_VALUE(Some(v))))),
+            $CONTAINS_NULL,
+            $NEGATED,
+        ))))
+    }};
+}
+
+/// Applies SQL three-valued logic to the result of probing the list for each value:
+/// a NULL value yields NULL, and a value that is not found yields NULL if the list
+/// contains NULL
+fn in_set(
+    contains: impl Iterator<Item = Option<bool>>,
+    contains_null: bool,
+    negated: bool,
+) -> BooleanArray {
+    contains
+        .map(|x| match x {
+            Some(true) => Some(!negated),
+            Some(false) if contains_null => None,
+            Some(false) => Some(negated),
+            None => None,
+        })
+        .collect()
+}
+
+/// Evaluates `expr` to a scalar value if it consists only of literals and casts
+fn evaluate_constant(expr: &Arc<dyn PhysicalExpr>) -> Option<ScalarValue> {
+    if let Some(literal) = expr.as_any().downcast_ref::<Literal>() {
+        Some(literal.value().clone())
+    } else if let Some(cast) = expr.as_any().downcast_ref::<CastExpr>() {
+        let value = evaluate_constant(cast.expr())?;
+        match cast_column(
+            &ColumnarValue::Scalar(value),
+            cast.cast_type(),
+            cast.cast_options(),
+        ) {
+            Ok(ColumnarValue::Scalar(value)) => Some(value),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
 // whether each value on the left (can be null) is contained in the non-null list
 fn in_list_primitive<T: ArrowPrimitiveType>(
     array: &PrimitiveArray<T>,
@@ -224,10 +286,37 @@ impl InListExpr {
         list: Vec<Arc<dyn PhysicalExpr>>,
         negated: bool,
     ) -> Self {
+        let static_values = list
+            .iter()
+            .map(evaluate_constant)
+            .collect::<Option<Vec<_>>>();
+        let static_set = static_values
+            .as_ref()
+            .filter(|values| values.len() >= IN_LIST_HASH_THRESHOLD)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter(|v| !v.is_null())
+                    .cloned()
+                    .collect::<HashSet<_>>()
+            });
+        let static_utf8_set = static_set.as_ref().map(|set| {
+            set.iter()
+                .filter_map(|v| match v {
+                    ScalarValue::Utf8(Some(v)) | ScalarValue::LargeUtf8(Some(v)) => {
+                        Some(v.clone())
+                    }
+                    _ => None,
+                })
+                .collect::<HashSet<_>>()
+        });
         Self {
             expr,
             list,
             negated,
+            static_values,
+            static_set,
+            static_utf8_set,
         }
     }
 
@@ -246,6 +335,133 @@ impl InListExpr {
         self.negated
     }
 
+    /// Probe the hash set for specific utf8 types
+    fn set_contains_utf8<T: StringOffsetSizeTrait>(
+        &self,
+        array: ArrayRef,
+        set: &HashSet<String>,
+        contains_null: bool,
+    ) -> ColumnarValue {
+        let array = array
+            .as_any()
+            .downcast_ref::<GenericStringArray<T>>()
+            .unwrap();
+
+        ColumnarValue::Array(Arc::new(in_set(
+            array.iter().map(|x| x.map(|v| set.contains(v))),
+            contains_null,
+            self.negated,
+        )))
+    }
+
+    /// Evaluate the expression by probing the hash set of list values. Returns `None`
+    /// for types that are not supported by the hash based evaluation.
+    fn evaluate_set(
+        &self,
+        array: ArrayRef,
+        data_type: &DataType,
+        set: &HashSet<ScalarValue>,
+        contains_null: bool,
+    ) -> Option<Result<ColumnarValue>> {
+        let negated = self.negated;
+        let result = match data_type {
+            DataType::Float32 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                Float32,
+                Float32Array
+            ),
+            DataType::Float64 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                Float64,
+                Float64Array
+            ),
+            DataType::Int8 => {
+                set_contains_primitive!(
+                    array,
+                    set,
+                    contains_null,
+                    negated,
+                    Int8,
+                    Int8Array
+                )
+            }
+            DataType::Int16 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                Int16,
+                Int16Array
+            ),
+            DataType::Int32 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                Int32,
+                Int32Array
+            ),
+            DataType::Int64 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                Int64,
+                Int64Array
+            ),
+            DataType::UInt8 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                UInt8,
+                UInt8Array
+            ),
+            DataType::UInt16 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                UInt16,
+                UInt16Array
+            ),
+            DataType::UInt32 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                UInt32,
+                UInt32Array
+            ),
+            DataType::UInt64 => set_contains_primitive!(
+                array,
+                set,
+                contains_null,
+                negated,
+                UInt64,
+                UInt64Array
+            ),
+            DataType::Utf8 => Ok(self.set_contains_utf8::<i32>(
+                array,
+                self.static_utf8_set.as_ref()?,
+                contains_null,
+            )),
+            DataType::LargeUtf8 => Ok(self.set_contains_utf8::<i64>(
+                array,
+                self.static_utf8_set.as_ref()?,
+                contains_null,
+            )),
+            _ => return None,
+        };
+        Some(result)
+    }
+
     /// Compare for specific utf8 types
     #[allow(clippy::unnecessary_wraps)]
     fn compare_utf8<T: StringOffsetSizeTrait>(
@@ -312,6 +528,17 @@ impl InListExpr {
     }
 }
 
+// the values cached from the list are left out
+impl std::fmt::Debug for InListExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("InListExpr")
+            .field("expr", &self.expr)
+            .field("list", &self.list)
+            .field("negated", &self.negated)
+            .finish()
+    }
+}
+
 impl std::fmt::Display for InListExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         if self.negated {
@@ -339,17 +566,34 @@ impl PhysicalExpr for InListExpr {
     fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
         let value = self.expr.evaluate(batch)?;
         let value_data_type = value.data_type();
-        let list_values = self
-            .list
-            .iter()
-            .map(|expr| expr.evaluate(batch))
-            .collect::<Result<Vec<_>>>()?;
+        let list_values = match &self.static_values {
+            Some(values) => values
+                .iter()
+                .map(|v| ColumnarValue::Scalar(v.clone()))
+                .collect::<Vec<_>>(),
+            None => self
+                .list
+                .iter()
+                .map(|expr| expr.evaluate(batch))
+                .collect::<Result<Vec<_>>>()?,
+        };
 
         let array = match value {
             ColumnarValue::Array(array) => array,
             ColumnarValue::Scalar(scalar) => scalar.to_array(),
         };
 
+        if let Some(set) = &self.static_set {
+            let contains_null = list_values
+                .iter()
+                .any(|v| matches!(v, ColumnarValue::Scalar(s) if s.is_null()));
+            if let Some(result) =
+                self.evaluate_set(array.clone(), &value_data_type, set, contains_null)
+            {
+                return result;
+            }
+        }
+
         match value_data_type {
             DataType::Float32 => {
                 make_contains_primitive!(
@@ -679,6 +923,89 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn in_list_int64_large_list() -> Result<()> {
+        let schema = Schema::new(vec![Field::new("a", DataType::Int64, true)]);
+        let a = Int64Array::from(vec![Some(0), Some(2), Some(100), None]);
+        let col_a = col("a", &schema)?;
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(a)])?;
+
+        // expression: "a in (0, 2, 4, ...)"
+        let list = (0..IN_LIST_HASH_THRESHOLD as i64 * 2)
+            .step_by(2)
+            .map(|v| lit(ScalarValue::Int64(Some(v))))
+            .collect::<Vec<_>>();
+        in_list!(
+            batch,
+            list.clone(),
+            &false,
+            vec![Some(true), Some(true), Some(false), None],
+            col_a.clone()
+        );
+
+        // expression: "a not in (0, 2, 4, ...)"
+        in_list!(
+            batch,
+            list.clone(),
+            &true,
+            vec![Some(false), Some(false), Some(true), None],
+            col_a.clone()
+        );
+
+        // expression: "a in (0, 2, 4, ..., NULL)"
+        let mut list_with_null = list;
+        list_with_null.push(lit(ScalarValue::Utf8(None)));
+        in_list!(
+            batch,
+            list_with_null.clone(),
+            &false,
+            vec![Some(true), Some(true), None, None],
+            col_a.clone()
+        );
+
+        // expression: "a not in (0, 2, 4, ..., NULL)"
+        in_list!(
+            batch,
+            list_with_null,
+            &true,
+            vec![Some(false), Some(false), None, None],
+            col_a.clone()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_list_utf8_large_list() -> Result<()> {
+        let schema = Schema::new(vec![Field::new("a", DataType::Utf8, true)]);
+        let a = StringArray::from(vec![Some("v0"), Some("x"), None]);
+        let col_a = col("a", &schema)?;
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(a)])?;
+
+        // expression: "a in ("v0", "v1", ...)"
+        let list = (0..IN_LIST_HASH_THRESHOLD)
+            .map(|v| lit(ScalarValue::Utf8(Some(format!("v{}", v)))))
+            .collect::<Vec<_>>();
+        in_list!(
+            batch,
+            list.clone(),
+            &false,
+            vec![Some(true), Some(false), None],
+            col_a.clone()
+        );
+
+        // expression: "a not in ("v0", "v1", ...)"
+        in_list!(
+            batch,
+            list,
+            &true,
+            vec![Some(false), Some(true), None],
+            col_a.clone()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn in_list_bool() -> Result<()> {
         let schema = Schema::new(vec![Field::new("a", DataType::Boolean, true)]);
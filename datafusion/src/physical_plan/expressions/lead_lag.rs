@@ -22,7 +22,7 @@ use crate::error::{DataFusionError, Result};
 use crate::physical_plan::window_functions::PartitionEvaluator;
 use crate::physical_plan::{window_functions::BuiltInWindowFunctionExpr, PhysicalExpr};
 use crate::scalar::ScalarValue;
-use arrow::array::ArrayRef;
+use arrow::array::{ArrayRef, UInt32Array};
 use arrow::compute::cast;
 use arrow::datatypes::{DataType, Field};
 use arrow::record_batch::RecordBatch;
@@ -39,6 +39,15 @@ pub struct WindowShift {
     shift_offset: i64,
     expr: Arc<dyn PhysicalExpr>,
     default_value: Option<ScalarValue>,
+    ignore_nulls: bool,
+}
+
+impl WindowShift {
+    /// Skip null values when counting the offset rows (`IGNORE NULLS`)
+    pub fn with_ignore_nulls(mut self, ignore_nulls: bool) -> Self {
+        self.ignore_nulls = ignore_nulls;
+        self
+    }
 }
 
 /// lead() window function
@@ -55,6 +64,7 @@ pub fn lead(
         shift_offset: shift_offset.map(|v| v.neg()).unwrap_or(-1),
         expr,
         default_value,
+        ignore_nulls: false,
     }
 }
 
@@ -72,6 +82,7 @@ pub fn lag(
         shift_offset: shift_offset.unwrap_or(1),
         expr,
         default_value,
+        ignore_nulls: false,
     }
 }
 
@@ -108,6 +119,7 @@ impl BuiltInWindowFunctionExpr for WindowShift {
             shift_offset: self.shift_offset,
             values,
             default_value: self.default_value.clone(),
+            ignore_nulls: self.ignore_nulls,
         }))
     }
 }
@@ -116,6 +128,7 @@ pub(crate) struct WindowShiftEvaluator {
     shift_offset: i64,
     values: Vec<ArrayRef>,
     default_value: Option<ScalarValue>,
+    ignore_nulls: bool,
 }
 
 fn create_empty_array(
@@ -167,11 +180,59 @@ fn shift_with_default_value(
     }
 }
 
+/// Shift `array` by `offset` rows counting only the non-null values, i.e. each row takes
+/// the `offset`-th non-null value before it (or after it for a negative offset)
+fn shift_ignore_nulls_with_default_value(
+    array: &ArrayRef,
+    offset: i64,
+    value: &Option<ScalarValue>,
+) -> Result<ArrayRef> {
+    use arrow::compute::{concat, take};
+
+    let non_null_indices = (0..array.len())
+        .filter(|i| array.is_valid(*i))
+        .collect::<Vec<_>>();
+    let steps = offset.unsigned_abs() as usize;
+    // number of non-null values before the current row
+    let mut non_nulls_before = 0;
+    let indices = (0..array.len())
+        .map(|i| {
+            let target = if offset == 0 {
+                Some(i)
+            } else if offset > 0 {
+                non_nulls_before
+                    .checked_sub(steps)
+                    .map(|idx| non_null_indices[idx])
+            } else {
+                let non_nulls_until = non_nulls_before + array.is_valid(i) as usize;
+                non_null_indices.get(non_nulls_until + steps - 1).copied()
+            };
+            if array.is_valid(i) {
+                non_nulls_before += 1;
+            }
+            // rows without a target take the default value appended after the array
+            target.unwrap_or_else(|| array.len()) as u32
+        })
+        .collect::<UInt32Array>();
+
+    let default_value = create_empty_array(value, array.data_type(), 1)?;
+    let values = concat(&[array.as_ref(), default_value.as_ref()])?;
+    take(values.as_ref(), &indices, None).map_err(DataFusionError::ArrowError)
+}
+
 impl PartitionEvaluator for WindowShiftEvaluator {
     fn evaluate_partition(&self, partition: Range<usize>) -> Result<ArrayRef> {
         let value = &self.values[0];
         let value = value.slice(partition.start, partition.end - partition.start);
-        shift_with_default_value(&value, self.shift_offset, &self.default_value)
+        if self.ignore_nulls {
+            shift_ignore_nulls_with_default_value(
+                &value,
+                self.shift_offset,
+                &self.default_value,
+            )
+        } else {
+            shift_with_default_value(&value, self.shift_offset, &self.default_value)
+        }
     }
 }
 
@@ -264,4 +325,57 @@ mod tests {
         )?;
         Ok(())
     }
+
+    #[test]
+    fn lead_lag_ignore_nulls() -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from(vec![
+            Some(1),
+            None,
+            Some(3),
+            None,
+            None,
+            Some(6),
+        ]));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let evaluate = |expr: WindowShift| -> Result<ArrayRef> {
+            let mut result = expr
+                .with_ignore_nulls(true)
+                .create_evaluator(&batch)?
+                .evaluate(vec![0..6])?;
+            Ok(result.remove(0))
+        };
+
+        let result = evaluate(lag(
+            "lag".to_owned(),
+            DataType::Int32,
+            Arc::new(Column::new("arr", 0)),
+            None,
+            None,
+        ))?;
+        assert_eq!(
+            Int32Array::from(vec![None, Some(1), Some(1), Some(3), Some(3), Some(3)]),
+            *result.as_any().downcast_ref::<Int32Array>().unwrap()
+        );
+
+        let result = evaluate(lead(
+            "lead".to_owned(),
+            DataType::Int32,
+            Arc::new(Column::new("arr", 0)),
+            Some(2),
+            Some(ScalarValue::Int32(Some(100))),
+        ))?;
+        assert_eq!(
+            Int32Array::from(vec![
+                Some(6),
+                Some(6),
+                Some(100),
+                Some(100),
+                Some(100),
+                Some(100)
+            ]),
+            *result.as_any().downcast_ref::<Int32Array>().unwrap()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,395 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines the kernels of the pattern matching operators `LIKE`, `ILIKE` and
+//! `SIMILAR TO` and their negations.
+//!
+//! In the patterns of all operators `%` matches any sequence of characters, `_` any
+//! single character and a backslash escapes the character that follows it. `SIMILAR
+//! TO` patterns may additionally use the regular expression operators `|`, `*`, `+`,
+//! `?`, `{m,n}`, `(...)` and `[...]`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    new_null_array, Array, ArrayRef, BooleanArray, GenericStringArray,
+    StringOffsetSizeTrait,
+};
+use arrow::datatypes::DataType;
+use regex::Regex;
+
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Operator;
+use crate::scalar::ScalarValue;
+
+/// Translates `pattern`, whose escape character is `escape` or that has no escape
+/// character if `escape` is `None`, into the equivalent pattern whose escape character
+/// is a backslash, the escape character of the patterns of the operators.
+pub fn escape_pattern(pattern: &str, escape: Option<char>) -> Result<String> {
+    if escape == Some('\\') {
+        return Ok(pattern.to_owned());
+    }
+    let mut result = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if Some(c) == escape {
+            let escaped = chars.next().ok_or_else(|| trailing_escape(pattern))?;
+            result.push('\\');
+            result.push(escaped);
+        } else if c == '\\' {
+            result.push_str("\\\\");
+        } else {
+            result.push(c);
+        }
+    }
+    Ok(result)
+}
+
+/// Matches strings against a compiled pattern
+enum Matcher {
+    /// The pattern has no wildcards
+    Equals(String),
+    /// The pattern is a literal followed by `%`
+    Prefix(String),
+    /// The pattern is `%` followed by a literal
+    Suffix(String),
+    /// The pattern is a literal enclosed by `%`
+    Contains(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn try_new(op: &Operator, pattern: &str) -> Result<Self> {
+        match op {
+            Operator::Like | Operator::NotLike => match literal_matcher(pattern) {
+                Some(matcher) => Ok(matcher),
+                None => like_regex(pattern, false).map(Matcher::Regex),
+            },
+            Operator::ILike | Operator::NotILike => {
+                like_regex(pattern, true).map(Matcher::Regex)
+            }
+            Operator::SimilarTo | Operator::NotSimilarTo => {
+                similar_to_regex(pattern).map(Matcher::Regex)
+            }
+            _ => Err(DataFusionError::Internal(format!(
+                "{} is not a pattern matching operator",
+                op
+            ))),
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Matcher::Equals(literal) => value == literal,
+            Matcher::Prefix(literal) => value.starts_with(literal.as_str()),
+            Matcher::Suffix(literal) => value.ends_with(literal.as_str()),
+            Matcher::Contains(literal) => value.contains(literal.as_str()),
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Returns a matcher that does not need a regular expression for the LIKE `pattern`,
+/// if the pattern only has `%` wildcards at its ends and no escapes
+fn literal_matcher(pattern: &str) -> Option<Matcher> {
+    let leading = pattern.starts_with('%');
+    let trailing = pattern.len() > 1 && pattern.ends_with('%');
+    let literal = &pattern[leading as usize..pattern.len() - trailing as usize];
+    if literal.contains(|c| c == '%' || c == '_' || c == '\\') {
+        return None;
+    }
+    let literal = literal.to_owned();
+    Some(match (leading, trailing) {
+        (false, false) => Matcher::Equals(literal),
+        (false, true) => Matcher::Prefix(literal),
+        (true, false) => Matcher::Suffix(literal),
+        (true, true) => Matcher::Contains(literal),
+    })
+}
+
+/// Returns the error of a pattern that ends with its escape character
+fn trailing_escape(pattern: &str) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "Pattern '{}' must not end with the escape character",
+        pattern
+    ))
+}
+
+/// Compiles the LIKE `pattern` into a regular expression that matches whole strings
+fn like_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    let mut regex = String::from(if case_insensitive { "(?is)^" } else { "(?s)^" });
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| trailing_escape(pattern))?;
+                regex.push_str(&regex::escape(escaped.encode_utf8(&mut [0; 4])));
+            }
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    compile(pattern, &regex)
+}
+
+/// Compiles the SIMILAR TO `pattern` into a regular expression that matches whole
+/// strings
+fn similar_to_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("(?s)^(?:");
+    let mut chars = pattern.chars();
+    let mut in_bracket = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| trailing_escape(pattern))?;
+                regex.push_str(&regex::escape(escaped.encode_utf8(&mut [0; 4])));
+            }
+            // characters of bracket expressions are passed through
+            ']' if in_bracket => {
+                in_bracket = false;
+                regex.push(c);
+            }
+            c if in_bracket => regex.push(c),
+            '[' => {
+                in_bracket = true;
+                regex.push(c);
+            }
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '|' | '*' | '+' | '?' | '{' | '}' | '(' | ')' => regex.push(c),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push_str(")$");
+    compile(pattern, &regex)
+}
+
+fn compile(pattern: &str, regex: &str) -> Result<Regex> {
+    Regex::new(regex).map_err(|e| {
+        DataFusionError::Execution(format!("Invalid pattern '{}': {}", pattern, e))
+    })
+}
+
+/// Returns whether the matches of `op` are negated
+fn is_negated(op: &Operator) -> bool {
+    matches!(
+        op,
+        Operator::NotLike | Operator::NotILike | Operator::NotSimilarTo
+    )
+}
+
+fn pattern_match_scalar_impl<OffsetSize: StringOffsetSizeTrait>(
+    op: &Operator,
+    values: &ArrayRef,
+    pattern: &str,
+) -> Result<ArrayRef> {
+    let values = values
+        .as_any()
+        .downcast_ref::<GenericStringArray<OffsetSize>>()
+        .unwrap();
+    let matcher = Matcher::try_new(op, pattern)?;
+    let negated = is_negated(op);
+    let result = values
+        .iter()
+        .map(|value| value.map(|value| matcher.matches(value) != negated))
+        .collect::<BooleanArray>();
+    Ok(Arc::new(result))
+}
+
+fn pattern_match_impl<OffsetSize: StringOffsetSizeTrait>(
+    op: &Operator,
+    values: &ArrayRef,
+    patterns: &ArrayRef,
+) -> Result<ArrayRef> {
+    let values = values
+        .as_any()
+        .downcast_ref::<GenericStringArray<OffsetSize>>()
+        .unwrap();
+    let patterns = patterns
+        .as_any()
+        .downcast_ref::<GenericStringArray<OffsetSize>>()
+        .unwrap();
+    let negated = is_negated(op);
+    // each distinct pattern is compiled once
+    let mut matchers = HashMap::new();
+    let result = values
+        .iter()
+        .zip(patterns.iter())
+        .map(|(value, pattern)| match (value, pattern) {
+            (Some(value), Some(pattern)) => {
+                if !matchers.contains_key(pattern) {
+                    matchers.insert(pattern, Matcher::try_new(op, pattern)?);
+                }
+                Ok(Some(matchers[pattern].matches(value) != negated))
+            }
+            _ => Ok(None),
+        })
+        .collect::<Result<BooleanArray>>()?;
+    Ok(Arc::new(result))
+}
+
+/// Evaluates the pattern matching operator `op` for the strings of `values` and the
+/// pattern `pattern`
+pub fn pattern_match_scalar(
+    op: &Operator,
+    values: &ArrayRef,
+    pattern: &ScalarValue,
+) -> Result<ArrayRef> {
+    match (values.data_type(), pattern) {
+        (DataType::Utf8, ScalarValue::Utf8(Some(pattern))) => {
+            pattern_match_scalar_impl::<i32>(op, values, pattern)
+        }
+        (DataType::LargeUtf8, ScalarValue::LargeUtf8(Some(pattern))) => {
+            pattern_match_scalar_impl::<i64>(op, values, pattern)
+        }
+        (DataType::Utf8, ScalarValue::Utf8(None))
+        | (DataType::LargeUtf8, ScalarValue::LargeUtf8(None)) => {
+            Ok(new_null_array(&DataType::Boolean, values.len()))
+        }
+        (data_type, pattern) => Err(DataFusionError::Internal(format!(
+            "Cannot evaluate {} with types {:?} and {:?}",
+            op,
+            data_type,
+            pattern.get_datatype()
+        ))),
+    }
+}
+
+/// Evaluates the pattern matching operator `op` for the strings of `values` and the
+/// patterns of `patterns`
+pub fn pattern_match(
+    op: &Operator,
+    values: &ArrayRef,
+    patterns: &ArrayRef,
+) -> Result<ArrayRef> {
+    match (values.data_type(), patterns.data_type()) {
+        (DataType::Utf8, DataType::Utf8) => {
+            pattern_match_impl::<i32>(op, values, patterns)
+        }
+        (DataType::LargeUtf8, DataType::LargeUtf8) => {
+            pattern_match_impl::<i64>(op, values, patterns)
+        }
+        (values, patterns) => Err(DataFusionError::Internal(format!(
+            "Cannot evaluate {} with types {:?} and {:?}",
+            op, values, patterns
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::StringArray;
+
+    fn matches(
+        op: Operator,
+        values: Vec<Option<&str>>,
+        pattern: &str,
+    ) -> Vec<Option<bool>> {
+        let values: ArrayRef = Arc::new(StringArray::from(values));
+        let pattern = ScalarValue::Utf8(Some(pattern.to_owned()));
+        let result = pattern_match_scalar(&op, &values, &pattern).unwrap();
+        let result = result.as_any().downcast_ref::<BooleanArray>().unwrap();
+        result.iter().collect()
+    }
+
+    #[test]
+    fn like() {
+        let values = vec![Some("abc"), Some("ABC"), Some("a%c"), Some("a\nc"), None];
+        assert_eq!(
+            matches(Operator::Like, values.clone(), "a%"),
+            vec![Some(true), Some(false), Some(true), Some(true), None]
+        );
+        assert_eq!(
+            matches(Operator::Like, values.clone(), "a_c"),
+            vec![Some(true), Some(false), Some(true), Some(true), None]
+        );
+        assert_eq!(
+            matches(Operator::NotLike, values.clone(), "a\\%c"),
+            vec![Some(true), Some(true), Some(false), Some(true), None]
+        );
+        assert_eq!(
+            matches(Operator::ILike, values.clone(), "A%C"),
+            vec![Some(true), Some(true), Some(true), Some(true), None]
+        );
+        assert_eq!(
+            matches(Operator::NotILike, values, "%B%"),
+            vec![Some(false), Some(false), Some(true), Some(true), None]
+        );
+        // regular expression characters are literals
+        assert_eq!(
+            matches(Operator::Like, vec![Some("a.c"), Some("abc")], "a.c"),
+            vec![Some(true), Some(false)]
+        );
+    }
+
+    #[test]
+    fn similar_to() {
+        let values = vec![Some("abc"), Some("abd"), Some("a.c"), Some("xabc"), None];
+        assert_eq!(
+            matches(Operator::SimilarTo, values.clone(), "ab(c|d)"),
+            vec![Some(true), Some(true), Some(false), Some(false), None]
+        );
+        assert_eq!(
+            matches(Operator::SimilarTo, values.clone(), "%[b.]_"),
+            vec![Some(true), Some(true), Some(true), Some(true), None]
+        );
+        assert_eq!(
+            matches(Operator::NotSimilarTo, values, "a.c"),
+            vec![Some(true), Some(true), Some(false), Some(true), None]
+        );
+    }
+
+    #[test]
+    fn patterns() -> Result<()> {
+        let values: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("abc"),
+            Some("abc"),
+            None,
+            Some("x"),
+        ]));
+        let patterns: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("a%"),
+            Some("b%"),
+            Some("a%"),
+            None,
+        ]));
+        let result = pattern_match(&Operator::Like, &values, &patterns)?;
+        let result = result.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(
+            result.iter().collect::<Vec<_>>(),
+            vec![Some(true), Some(false), None, None]
+        );
+
+        let invalid: ArrayRef = Arc::new(StringArray::from(vec!["a\\"; 4]));
+        assert!(pattern_match(&Operator::Like, &values, &invalid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn escape() -> Result<()> {
+        assert_eq!(escape_pattern("a#%b", Some('#'))?, "a\\%b");
+        assert_eq!(escape_pattern("a##\\", Some('#'))?, "a\\#\\\\");
+        assert_eq!(escape_pattern("a\\%", None)?, "a\\\\%");
+        assert_eq!(escape_pattern("a\\%", Some('\\'))?, "a\\%");
+        assert!(escape_pattern("a#", Some('#')).is_err());
+        Ok(())
+    }
+}
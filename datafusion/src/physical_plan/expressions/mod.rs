@@ -30,19 +30,25 @@ mod average;
 mod binary;
 mod case;
 mod cast;
-mod coercion;
+pub(crate) mod coercion;
 mod column;
 mod count;
+mod cume_dist;
+mod filtered_aggregate;
+mod fused;
 mod in_list;
 mod is_not_null;
 mod is_null;
 mod lead_lag;
+mod like;
 mod literal;
 mod min_max;
 mod negative;
 mod not;
 mod nth_value;
+mod ntile;
 mod nullif;
+mod ordered_aggregate;
 mod rank;
 mod row_number;
 mod sum;
@@ -56,18 +62,24 @@ pub use cast::{
 };
 pub use column::{col, Column};
 pub use count::Count;
+pub use cume_dist::cume_dist;
+pub use filtered_aggregate::FilteredAggregate;
+pub use fused::{fuse_predicate, FusedPredicateExpr};
 pub use in_list::{in_list, InListExpr};
 pub use is_not_null::{is_not_null, IsNotNullExpr};
 pub use is_null::{is_null, IsNullExpr};
 pub use lead_lag::{lag, lead};
+pub use like::{escape_pattern, pattern_match, pattern_match_scalar};
 pub use literal::{lit, Literal};
 pub use min_max::{Max, Min};
 pub(crate) use min_max::{MaxAccumulator, MinAccumulator};
 pub use negative::{negative, NegativeExpr};
 pub use not::{not, NotExpr};
 pub use nth_value::NthValue;
+pub use ntile::Ntile;
 pub use nullif::{nullif_func, SUPPORTED_NULLIF_TYPES};
-pub use rank::{dense_rank, rank};
+pub use ordered_aggregate::OrderedAggregate;
+pub use rank::{dense_rank, percent_rank, rank};
 pub use row_number::RowNumber;
 pub use sum::{sum_return_type, Sum};
 pub use try_cast::{try_cast, TryCastExpr};
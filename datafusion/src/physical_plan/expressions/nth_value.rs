@@ -22,11 +22,12 @@ use crate::error::{DataFusionError, Result};
 use crate::physical_plan::window_functions::PartitionEvaluator;
 use crate::physical_plan::{window_functions::BuiltInWindowFunctionExpr, PhysicalExpr};
 use crate::scalar::ScalarValue;
-use arrow::array::{new_null_array, ArrayRef};
-use arrow::compute::kernels::window::shift;
+use arrow::array::{ArrayRef, UInt32Builder};
+use arrow::compute::take;
 use arrow::datatypes::{DataType, Field};
 use arrow::record_batch::RecordBatch;
 use std::any::Any;
+use std::convert::TryFrom;
 use std::iter;
 use std::ops::Range;
 use std::sync::Arc;
@@ -46,6 +47,7 @@ pub struct NthValue {
     expr: Arc<dyn PhysicalExpr>,
     data_type: DataType,
     kind: NthValueKind,
+    ignore_nulls: bool,
 }
 
 impl NthValue {
@@ -60,6 +62,7 @@ impl NthValue {
             expr,
             data_type,
             kind: NthValueKind::First,
+            ignore_nulls: false,
         }
     }
 
@@ -74,6 +77,7 @@ impl NthValue {
             expr,
             data_type,
             kind: NthValueKind::Last,
+            ignore_nulls: false,
         }
     }
 
@@ -93,9 +97,16 @@ impl NthValue {
                 expr,
                 data_type,
                 kind: NthValueKind::Nth(n),
+                ignore_nulls: false,
             }),
         }
     }
+
+    /// Skip null values when looking for the first, last or nth value (`IGNORE NULLS`)
+    pub fn with_ignore_nulls(mut self, ignore_nulls: bool) -> Self {
+        self.ignore_nulls = ignore_nulls;
+        self
+    }
 }
 
 impl BuiltInWindowFunctionExpr for NthValue {
@@ -130,6 +141,7 @@ impl BuiltInWindowFunctionExpr for NthValue {
         Ok(Box::new(NthValueEvaluator {
             kind: self.kind,
             values,
+            ignore_nulls: self.ignore_nulls,
         }))
     }
 }
@@ -138,6 +150,36 @@ impl BuiltInWindowFunctionExpr for NthValue {
 pub(crate) struct NthValueEvaluator {
     kind: NthValueKind,
     values: Vec<ArrayRef>,
+    ignore_nulls: bool,
+}
+
+impl NthValueEvaluator {
+    /// Evaluate with `IGNORE NULLS`: every row takes the first, last or nth non-null value
+    /// of the default window frame, i.e. from the start of the partition up to the last
+    /// peer of the current row
+    fn evaluate_ignore_nulls(
+        &self,
+        partition: Range<usize>,
+        ranks_in_partition: &[Range<usize>],
+    ) -> Result<ArrayRef> {
+        let arr = &self.values[0];
+        let mut non_null_indices = vec![];
+        let mut next_row = partition.start;
+        let mut indices = UInt32Builder::new(partition.end - partition.start);
+        for range in ranks_in_partition {
+            non_null_indices.extend((next_row..range.end).filter(|i| arr.is_valid(*i)));
+            next_row = range.end;
+            let index = match self.kind {
+                NthValueKind::First => non_null_indices.first(),
+                NthValueKind::Last => non_null_indices.last(),
+                NthValueKind::Nth(n) => non_null_indices.get(n as usize - 1),
+            };
+            for _ in range.clone() {
+                indices.append_option(index.map(|i| *i as u32))?;
+            }
+        }
+        take(arr.as_ref(), &indices.finish(), None).map_err(DataFusionError::ArrowError)
+    }
 }
 
 impl PartitionEvaluator for NthValueEvaluator {
@@ -145,15 +187,39 @@ impl PartitionEvaluator for NthValueEvaluator {
         true
     }
 
+    fn uses_window_frame(&self) -> bool {
+        true
+    }
+
     fn evaluate_partition(&self, _partition: Range<usize>) -> Result<ArrayRef> {
         unreachable!("first, last, and nth_value evaluation must be called with evaluate_partition_with_rank")
     }
 
+    fn evaluate_frame(&self, frame: &[Range<usize>]) -> Result<ScalarValue> {
+        let arr = &self.values[0];
+        let mut rows = frame
+            .iter()
+            .flat_map(|range| range.clone())
+            .filter(|row| !self.ignore_nulls || arr.is_valid(*row));
+        let row = match self.kind {
+            NthValueKind::First => rows.next(),
+            NthValueKind::Last => rows.last(),
+            NthValueKind::Nth(n) => rows.nth(n as usize - 1),
+        };
+        match row {
+            Some(row) => ScalarValue::try_from_array(arr, row),
+            None => ScalarValue::try_from(arr.data_type()),
+        }
+    }
+
     fn evaluate_partition_with_rank(
         &self,
         partition: Range<usize>,
         ranks_in_partition: &[Range<usize>],
     ) -> Result<ArrayRef> {
+        if self.ignore_nulls {
+            return self.evaluate_ignore_nulls(partition, ranks_in_partition);
+        }
         let arr = &self.values[0];
         let num_rows = partition.end - partition.start;
         match self.kind {
@@ -177,18 +243,21 @@ impl PartitionEvaluator for NthValueEvaluator {
                 ScalarValue::iter_to_array(values)
             }
             NthValueKind::Nth(n) => {
-                let index = (n as usize) - 1;
-                if index >= num_rows {
-                    Ok(new_null_array(arr.data_type(), num_rows))
+                // the default window frame ends with the last peer of the current row,
+                // so only the rows whose peers reach the nth row have an nth value
+                let index = partition.start + n as usize - 1;
+                let null = ScalarValue::try_from(arr.data_type())?;
+                let value = if index < partition.end {
+                    ScalarValue::try_from_array(arr, index)?
                 } else {
-                    let value =
-                        ScalarValue::try_from_array(arr, partition.start + index)?;
-                    let arr = value.to_array_of_size(num_rows);
-                    // because the default window frame is between unbounded preceding and current
-                    // row, hence the shift because for values with indices < index they should be
-                    // null. This changes when window frames other than default is implemented
-                    shift(arr.as_ref(), index as i64).map_err(DataFusionError::ArrowError)
-                }
+                    null.clone()
+                };
+                let values = ranks_in_partition.iter().flat_map(|range| {
+                    let len = range.end - range.start;
+                    let value = if index < range.end { &value } else { &null };
+                    iter::repeat(value.clone()).take(len)
+                });
+                ScalarValue::iter_to_array(values)
             }
         }
     }
@@ -203,13 +272,21 @@ mod tests {
     use arrow::{array::*, datatypes::*};
 
     fn test_i32_result(expr: NthValue, expected: Int32Array) -> Result<()> {
+        test_i32_result_with_peers(expr, vec![0..8], expected)
+    }
+
+    fn test_i32_result_with_peers(
+        expr: NthValue,
+        peers: Vec<Range<usize>>,
+        expected: Int32Array,
+    ) -> Result<()> {
         let arr: ArrayRef = Arc::new(Int32Array::from(vec![1, -2, 3, -4, 5, -6, 7, 8]));
         let values = vec![arr];
         let schema = Schema::new(vec![Field::new("arr", DataType::Int32, false)]);
         let batch = RecordBatch::try_new(Arc::new(schema), values.clone())?;
         let result = expr
             .create_evaluator(&batch)?
-            .evaluate_with_rank(vec![0..8], vec![0..8])?;
+            .evaluate_with_rank(vec![0..8], peers)?;
         assert_eq!(1, result.len());
         let result = result[0].as_any().downcast_ref::<Int32Array>().unwrap();
         assert_eq!(expected, *result);
@@ -252,14 +329,22 @@ mod tests {
 
     #[test]
     fn nth_value_2() -> Result<()> {
-        let nth_value = NthValue::nth_value(
-            "nth_value".to_owned(),
-            Arc::new(Column::new("arr", 0)),
-            DataType::Int32,
-            2,
-        )?;
-        test_i32_result(
-            nth_value,
+        let nth_value = |n| {
+            NthValue::nth_value(
+                "nth_value".to_owned(),
+                Arc::new(Column::new("arr", 0)),
+                DataType::Int32,
+                n,
+            )
+        };
+        // all the rows are peers, so the frame of every row is the whole partition
+        test_i32_result(nth_value(2)?, Int32Array::from_iter_values(vec![-2; 8]))?;
+        test_i32_result(nth_value(9)?, Int32Array::from(vec![None::<i32>; 8]))?;
+
+        // the first row is the only one whose frame ends before the second row
+        test_i32_result_with_peers(
+            nth_value(2)?,
+            (0..8).map(|i| i..i + 1).collect(),
             Int32Array::from(vec![
                 None,
                 Some(-2),
@@ -273,4 +358,112 @@ mod tests {
         )?;
         Ok(())
     }
+
+    #[test]
+    fn first_last_nth_value_ignore_nulls() -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from(vec![
+            None,
+            Some(2),
+            None,
+            Some(4),
+            Some(5),
+            None,
+        ]));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let peers = vec![0..1, 1..2, 2..3, 3..4, 4..5, 5..6];
+        let evaluate = |expr: NthValue| -> Result<ArrayRef> {
+            let mut result = expr
+                .with_ignore_nulls(true)
+                .create_evaluator(&batch)?
+                .evaluate_with_rank(vec![0..6], peers.clone())?;
+            Ok(result.remove(0))
+        };
+
+        let result = evaluate(NthValue::first_value(
+            "first_value".to_owned(),
+            Arc::new(Column::new("arr", 0)),
+            DataType::Int32,
+        ))?;
+        assert_eq!(
+            Int32Array::from(vec![None, Some(2), Some(2), Some(2), Some(2), Some(2)]),
+            *result.as_any().downcast_ref::<Int32Array>().unwrap()
+        );
+
+        let result = evaluate(NthValue::last_value(
+            "last_value".to_owned(),
+            Arc::new(Column::new("arr", 0)),
+            DataType::Int32,
+        ))?;
+        assert_eq!(
+            Int32Array::from(vec![None, Some(2), Some(2), Some(4), Some(5), Some(5)]),
+            *result.as_any().downcast_ref::<Int32Array>().unwrap()
+        );
+
+        let result = evaluate(NthValue::nth_value(
+            "nth_value".to_owned(),
+            Arc::new(Column::new("arr", 0)),
+            DataType::Int32,
+            2,
+        )?)?;
+        assert_eq!(
+            Int32Array::from(vec![None, None, None, Some(4), Some(4), Some(4)]),
+            *result.as_any().downcast_ref::<Int32Array>().unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nth_value_peers() -> Result<()> {
+        let nth_value = NthValue::nth_value(
+            "nth_value".to_owned(),
+            Arc::new(Column::new("arr", 0)),
+            DataType::Int32,
+            3,
+        )?;
+        // the third row is a peer of the second one, so both have the third value
+        test_i32_result_with_peers(
+            nth_value,
+            vec![0..1, 1..3, 3..8],
+            Int32Array::from(vec![
+                None,
+                Some(3),
+                Some(3),
+                Some(3),
+                Some(3),
+                Some(3),
+                Some(3),
+                Some(3),
+            ]),
+        )
+    }
+
+    #[test]
+    fn nth_value_frame() -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(3)]));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let nth_value = NthValue::nth_value(
+            "nth_value".to_owned(),
+            Arc::new(Column::new("arr", 0)),
+            DataType::Int32,
+            2,
+        )?;
+        let evaluator = nth_value.create_evaluator(&batch)?;
+        assert!(evaluator.uses_window_frame());
+        assert_eq!(ScalarValue::Int32(None), evaluator.evaluate_frame(&[0..2])?);
+        assert_eq!(ScalarValue::Int32(None), evaluator.evaluate_frame(&[0..1])?);
+        // the excluded rows are skipped
+        assert_eq!(
+            ScalarValue::Int32(Some(3)),
+            evaluator.evaluate_frame(&[0..1, 2..3])?
+        );
+
+        let evaluator = nth_value.with_ignore_nulls(true).create_evaluator(&batch)?;
+        assert_eq!(
+            ScalarValue::Int32(Some(3)),
+            evaluator.evaluate_frame(&[0..3])?
+        );
+        Ok(())
+    }
 }
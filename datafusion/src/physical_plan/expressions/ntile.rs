@@ -0,0 +1,141 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines physical expression for `ntile` that can evaluated at runtime during query execution
+
+use crate::error::{DataFusionError, Result};
+use crate::physical_plan::window_functions::PartitionEvaluator;
+use crate::physical_plan::{window_functions::BuiltInWindowFunctionExpr, PhysicalExpr};
+use arrow::array::{ArrayRef, UInt32Array};
+use arrow::datatypes::{DataType, Field};
+use arrow::record_batch::RecordBatch;
+use std::any::Any;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// ntile expression
+#[derive(Debug)]
+pub struct Ntile {
+    name: String,
+    n: u64,
+}
+
+impl Ntile {
+    /// Create a new NTILE function dividing each partition into `n` buckets
+    pub fn new(name: impl Into<String>, n: u64) -> Result<Self> {
+        match n {
+            0 => Err(DataFusionError::Execution(
+                "ntile expect n to be > 0".to_owned(),
+            )),
+            _ => Ok(Self {
+                name: name.into(),
+                n,
+            }),
+        }
+    }
+}
+
+impl BuiltInWindowFunctionExpr for Ntile {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        let nullable = false;
+        let data_type = DataType::UInt32;
+        Ok(Field::new(self.name(), data_type, nullable))
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![]
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn create_evaluator(
+        &self,
+        _batch: &RecordBatch,
+    ) -> Result<Box<dyn PartitionEvaluator>> {
+        Ok(Box::new(NtileEvaluator { n: self.n }))
+    }
+}
+
+pub(crate) struct NtileEvaluator {
+    n: u64,
+}
+
+impl PartitionEvaluator for NtileEvaluator {
+    fn evaluate_partition(&self, partition: Range<usize>) -> Result<ArrayRef> {
+        let num_rows = (partition.end - partition.start) as u64;
+        // the first `remainder` buckets hold one row more than the others
+        let bucket_size = num_rows / self.n;
+        let remainder = num_rows % self.n;
+        let large_rows = remainder * (bucket_size + 1);
+        Ok(Arc::new(UInt32Array::from_iter_values((0..num_rows).map(
+            |i| {
+                let bucket = if i < large_rows {
+                    i / (bucket_size + 1)
+                } else {
+                    remainder + (i - large_rows) / bucket_size
+                };
+                bucket as u32 + 1
+            },
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use arrow::record_batch::RecordBatch;
+    use arrow::{array::*, datatypes::*};
+
+    fn test_ntile(n: u64, num_rows: usize, expected: Vec<u32>) -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from_iter_values(0..num_rows as i32));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, false)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let ntile = Ntile::new("ntile".to_owned(), n)?;
+        let result = ntile
+            .create_evaluator(&batch)?
+            .evaluate(vec![0..num_rows])?;
+        assert_eq!(1, result.len());
+        let result = result[0].as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert_eq!(expected, result.values());
+        Ok(())
+    }
+
+    #[test]
+    fn ntile_uneven_buckets() -> Result<()> {
+        test_ntile(3, 8, vec![1, 1, 1, 2, 2, 2, 3, 3])?;
+        test_ntile(4, 8, vec![1, 1, 2, 2, 3, 3, 4, 4])?;
+        Ok(())
+    }
+
+    #[test]
+    fn ntile_more_buckets_than_rows() -> Result<()> {
+        test_ntile(10, 4, vec![1, 2, 3, 4])
+    }
+
+    #[test]
+    fn ntile_zero_buckets() {
+        assert!(Ntile::new("ntile".to_owned(), 0).is_err());
+    }
+}
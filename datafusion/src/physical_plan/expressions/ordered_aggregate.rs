@@ -0,0 +1,476 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines the aggregates whose result depends on the order of their input rows:
+//! `ARRAY_AGG`, `FIRST_VALUE`, `LAST_VALUE` and `STRING_AGG`. The order is given by
+//! the `ORDER BY` clause of their arguments, e.g. `ARRAY_AGG(x ORDER BY y DESC)`.
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::error::{DataFusionError, Result};
+use crate::physical_plan::aggregates::AggregateFunction;
+use crate::physical_plan::{Accumulator, AggregateExpr, PhysicalExpr};
+use crate::scalar::ScalarValue;
+use arrow::compute::SortOptions;
+use arrow::datatypes::{DataType, Field, Schema};
+
+use super::{format_state_name, PhysicalSortExpr};
+
+/// An `ARRAY_AGG`, `FIRST_VALUE`, `LAST_VALUE` or `STRING_AGG` aggregate expression,
+/// which sorts its input rows by its ordering before computing its result.
+///
+/// The state of the accumulators is a list of the values and a list of the keys of each
+/// sort expression, so that partial aggregates can be merged in any order.
+#[derive(Debug)]
+pub struct OrderedAggregate {
+    name: String,
+    fun: AggregateFunction,
+    expr: Arc<dyn PhysicalExpr>,
+    data_type: DataType,
+    delimiter: Option<String>,
+    ordering: Vec<PhysicalSortExpr>,
+    ordering_types: Vec<DataType>,
+}
+
+impl OrderedAggregate {
+    /// Create a new ordered aggregate of the values of `expr`, whose type is
+    /// `data_type`. `delimiter` is the delimiter of `STRING_AGG`.
+    pub fn try_new(
+        fun: AggregateFunction,
+        expr: Arc<dyn PhysicalExpr>,
+        data_type: DataType,
+        delimiter: Option<String>,
+        ordering: Vec<PhysicalSortExpr>,
+        input_schema: &Schema,
+        name: impl Into<String>,
+    ) -> Result<Self> {
+        let ordering_types = ordering
+            .iter()
+            .map(|sort_expr| sort_expr.expr.data_type(input_schema))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(data_type) = ordering_types
+            .iter()
+            .find(|data_type| !is_supported_ordering_type(data_type))
+        {
+            return Err(DataFusionError::NotImplemented(format!(
+                "{} cannot be ordered by an expression of type {:?}",
+                fun, data_type
+            )));
+        }
+        if matches!(fun, AggregateFunction::StringAgg) && delimiter.is_none() {
+            return Err(DataFusionError::Plan(
+                "STRING_AGG requires a delimiter".to_string(),
+            ));
+        }
+        Ok(Self {
+            name: name.into(),
+            fun,
+            expr,
+            data_type,
+            delimiter,
+            ordering,
+            ordering_types,
+        })
+    }
+
+    /// The aggregate function
+    pub fn fun(&self) -> &AggregateFunction {
+        &self.fun
+    }
+
+    /// The expression of the aggregated values
+    pub fn expr(&self) -> &Arc<dyn PhysicalExpr> {
+        &self.expr
+    }
+
+    /// The delimiter of `STRING_AGG`
+    pub fn delimiter(&self) -> Option<&str> {
+        self.delimiter.as_deref()
+    }
+
+    /// The order of the aggregated values
+    pub fn ordering(&self) -> &[PhysicalSortExpr] {
+        &self.ordering
+    }
+}
+
+/// Whether the values of the type can be kept in the list state of the accumulators
+fn is_supported_ordering_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Utf8
+            | DataType::LargeUtf8
+    )
+}
+
+impl AggregateExpr for OrderedAggregate {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        let data_type = match self.fun {
+            AggregateFunction::ArrayAgg => {
+                DataType::List(Box::new(Field::new("item", self.data_type.clone(), true)))
+            }
+            _ => self.data_type.clone(),
+        };
+        Ok(Field::new(&self.name, data_type, true))
+    }
+
+    fn state_fields(&self) -> Result<Vec<Field>> {
+        let list = |data_type: &DataType| {
+            DataType::List(Box::new(Field::new("item", data_type.clone(), true)))
+        };
+        let mut fields = vec![Field::new(
+            &format_state_name(&self.name, "values"),
+            list(&self.data_type),
+            true,
+        )];
+        fields.extend(
+            self.ordering_types
+                .iter()
+                .enumerate()
+                .map(|(i, data_type)| {
+                    Field::new(
+                        &format_state_name(&self.name, &format!("order {}", i)),
+                        list(data_type),
+                        true,
+                    )
+                }),
+        );
+        Ok(fields)
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        let mut exprs = vec![self.expr.clone()];
+        exprs.extend(self.ordering.iter().map(|sort_expr| sort_expr.expr.clone()));
+        exprs
+    }
+
+    fn create_accumulator(&self) -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(OrderedAccumulator {
+            fun: self.fun.clone(),
+            data_type: self.data_type.clone(),
+            delimiter: self.delimiter.clone(),
+            ordering_types: self.ordering_types.clone(),
+            options: self.ordering.iter().map(|e| e.options).collect(),
+            rows: vec![],
+        }))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug)]
+struct OrderedAccumulator {
+    fun: AggregateFunction,
+    data_type: DataType,
+    delimiter: Option<String>,
+    ordering_types: Vec<DataType>,
+    options: Vec<SortOptions>,
+    /// the values with their sort keys, of which `FIRST_VALUE` and `LAST_VALUE` only
+    /// keep the one of their result
+    rows: Vec<(ScalarValue, Vec<ScalarValue>)>,
+}
+
+impl OrderedAccumulator {
+    fn add(&mut self, value: ScalarValue, keys: Vec<ScalarValue>) {
+        match self.fun {
+            // the first of equal rows is the first one added
+            AggregateFunction::FirstValue => {
+                let replace = match self.rows.first() {
+                    Some((_, current)) => {
+                        compare_keys(&keys, current, &self.options) == Ordering::Less
+                    }
+                    None => true,
+                };
+                if replace {
+                    self.rows = vec![(value, keys)];
+                }
+            }
+            // the last of equal rows is the last one added
+            AggregateFunction::LastValue => {
+                let replace = match self.rows.first() {
+                    Some((_, current)) => {
+                        compare_keys(&keys, current, &self.options) != Ordering::Less
+                    }
+                    None => true,
+                };
+                if replace {
+                    self.rows = vec![(value, keys)];
+                }
+            }
+            _ => self.rows.push((value, keys)),
+        }
+    }
+
+    /// The values in the order of their keys
+    fn sorted_values(&self) -> Vec<ScalarValue> {
+        let mut rows = self.rows.iter().collect::<Vec<_>>();
+        rows.sort_by(|(_, a), (_, b)| compare_keys(a, b, &self.options));
+        rows.into_iter().map(|(value, _)| value.clone()).collect()
+    }
+}
+
+/// Compares the sort keys of two rows like the `ORDER BY` clause with `options`
+fn compare_keys(
+    a: &[ScalarValue],
+    b: &[ScalarValue],
+    options: &[SortOptions],
+) -> Ordering {
+    for ((a, b), options) in a.iter().zip(b).zip(options) {
+        let ordering = match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if options.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if options.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ordering = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                if options.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+impl Accumulator for OrderedAccumulator {
+    fn state(&self) -> Result<Vec<ScalarValue>> {
+        let list = |values: Vec<ScalarValue>, data_type: &DataType| {
+            ScalarValue::List(Some(Box::new(values)), Box::new(data_type.clone()))
+        };
+        let mut state = vec![list(
+            self.rows.iter().map(|(value, _)| value.clone()).collect(),
+            &self.data_type,
+        )];
+        for (i, data_type) in self.ordering_types.iter().enumerate() {
+            state.push(list(
+                self.rows.iter().map(|(_, keys)| keys[i].clone()).collect(),
+                data_type,
+            ));
+        }
+        Ok(state)
+    }
+
+    fn update(&mut self, values: &[ScalarValue]) -> Result<()> {
+        self.add(values[0].clone(), values[1..].to_vec());
+        Ok(())
+    }
+
+    fn merge(&mut self, states: &[ScalarValue]) -> Result<()> {
+        let lists = states
+            .iter()
+            .map(|state| match state {
+                ScalarValue::List(Some(values), _) => Ok(values.as_slice()),
+                ScalarValue::List(None, _) => Ok(&[][..]),
+                _ => Err(DataFusionError::Internal(format!(
+                    "Unexpected accumulator state {:?}",
+                    state
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (i, value) in lists[0].iter().enumerate() {
+            let keys = lists[1..].iter().map(|keys| keys[i].clone()).collect();
+            self.add(value.clone(), keys);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ScalarValue> {
+        let values = self.sorted_values();
+        match self.fun {
+            AggregateFunction::ArrayAgg if values.is_empty() => {
+                Ok(ScalarValue::List(None, Box::new(self.data_type.clone())))
+            }
+            AggregateFunction::ArrayAgg => Ok(ScalarValue::List(
+                Some(Box::new(values)),
+                Box::new(self.data_type.clone()),
+            )),
+            AggregateFunction::StringAgg => {
+                let delimiter = self.delimiter.as_deref().unwrap_or_default();
+                let strings = values
+                    .iter()
+                    .filter_map(|value| match value {
+                        ScalarValue::Utf8(value) | ScalarValue::LargeUtf8(value) => {
+                            value.as_deref()
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let result = match strings.is_empty() {
+                    true => None,
+                    false => Some(strings.join(delimiter)),
+                };
+                Ok(match self.data_type {
+                    DataType::LargeUtf8 => ScalarValue::LargeUtf8(result),
+                    _ => ScalarValue::Utf8(result),
+                })
+            }
+            _ => match values.into_iter().next() {
+                Some(value) => Ok(value),
+                None => ScalarValue::try_from(&self.data_type),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use crate::physical_plan::expressions::col;
+    use arrow::array::{ArrayRef, Int32Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+
+    fn ordered_aggregate(
+        fun: AggregateFunction,
+        delimiter: Option<&str>,
+        schema: &Schema,
+        descending: bool,
+    ) -> Result<OrderedAggregate> {
+        let ordering = vec![PhysicalSortExpr {
+            expr: col("b", schema)?,
+            options: SortOptions {
+                descending,
+                nulls_first: false,
+            },
+        }];
+        OrderedAggregate::try_new(
+            fun,
+            col("a", schema)?,
+            schema.field(0).data_type().clone(),
+            delimiter.map(|d| d.to_owned()),
+            ordering,
+            schema,
+            "agg",
+        )
+    }
+
+    fn aggregate(agg: &OrderedAggregate, batches: &[RecordBatch]) -> Result<ScalarValue> {
+        // each batch is aggregated by its own accumulator, whose states are merged
+        let mut states = vec![];
+        for batch in batches {
+            let mut accumulator = agg.create_accumulator()?;
+            let values = agg
+                .expressions()
+                .iter()
+                .map(|e| e.evaluate(batch).map(|v| v.into_array(batch.num_rows())))
+                .collect::<Result<Vec<_>>>()?;
+            accumulator.update_batch(&values)?;
+            states.push(accumulator.state()?);
+        }
+        let mut accumulator = agg.create_accumulator()?;
+        for state in states.iter().rev() {
+            accumulator.merge(state)?;
+        }
+        accumulator.evaluate()
+    }
+
+    fn batches() -> Result<(Schema, Vec<RecordBatch>)> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Utf8, true),
+            Field::new("b", DataType::Int32, true),
+        ]);
+        let batch = |a: Vec<Option<&str>>, b: Vec<Option<i32>>| {
+            RecordBatch::try_new(
+                Arc::new(schema.clone()),
+                vec![
+                    Arc::new(StringArray::from(a)) as ArrayRef,
+                    Arc::new(Int32Array::from(b)),
+                ],
+            )
+        };
+        let batches = vec![
+            batch(vec![Some("b"), Some("d")], vec![Some(2), None])?,
+            batch(
+                vec![Some("c"), None, Some("a")],
+                vec![Some(3), Some(0), Some(1)],
+            )?,
+        ];
+        Ok((schema, batches))
+    }
+
+    #[test]
+    fn array_agg_ordered() -> Result<()> {
+        let (schema, batches) = batches()?;
+        let agg = ordered_aggregate(AggregateFunction::ArrayAgg, None, &schema, false)?;
+        let expected = ScalarValue::List(
+            Some(Box::new(vec![
+                ScalarValue::Utf8(None),
+                ScalarValue::Utf8(Some("a".to_owned())),
+                ScalarValue::Utf8(Some("b".to_owned())),
+                ScalarValue::Utf8(Some("c".to_owned())),
+                ScalarValue::Utf8(Some("d".to_owned())),
+            ])),
+            Box::new(DataType::Utf8),
+        );
+        assert_eq!(expected, aggregate(&agg, &batches)?);
+        Ok(())
+    }
+
+    #[test]
+    fn first_and_last_value_ordered() -> Result<()> {
+        let (schema, batches) = batches()?;
+        let first =
+            ordered_aggregate(AggregateFunction::FirstValue, None, &schema, true)?;
+        assert_eq!(
+            ScalarValue::Utf8(Some("c".to_owned())),
+            aggregate(&first, &batches)?
+        );
+        let last = ordered_aggregate(AggregateFunction::LastValue, None, &schema, true)?;
+        assert_eq!(
+            ScalarValue::Utf8(Some("d".to_owned())),
+            aggregate(&last, &batches)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn string_agg_ordered() -> Result<()> {
+        let (schema, batches) = batches()?;
+        let agg =
+            ordered_aggregate(AggregateFunction::StringAgg, Some(", "), &schema, true)?;
+        assert_eq!(
+            ScalarValue::Utf8(Some("c, b, a, d".to_owned())),
+            aggregate(&agg, &batches)?
+        );
+        Ok(())
+    }
+}
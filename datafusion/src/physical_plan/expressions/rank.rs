@@ -21,7 +21,7 @@ use crate::error::Result;
 use crate::physical_plan::window_functions::PartitionEvaluator;
 use crate::physical_plan::{window_functions::BuiltInWindowFunctionExpr, PhysicalExpr};
 use arrow::array::ArrayRef;
-use arrow::array::UInt64Array;
+use arrow::array::{Float64Array, UInt64Array};
 use arrow::datatypes::{DataType, Field};
 use arrow::record_batch::RecordBatch;
 use std::any::Any;
@@ -33,17 +33,42 @@ use std::sync::Arc;
 #[derive(Debug)]
 pub struct Rank {
     name: String,
-    dense: bool,
+    rank_type: RankType,
+}
+
+/// The kind of rank that is computed
+#[derive(Debug, Copy, Clone)]
+enum RankType {
+    /// The position of the first peer of the row, with gaps after peer groups
+    Basic,
+    /// The number of the peer group of the row, without gaps
+    Dense,
+    /// `(rank - 1) / (rows in partition - 1)`, from 0 to 1
+    Percent,
 }
 
 /// Create a rank window function
 pub fn rank(name: String) -> Rank {
-    Rank { name, dense: false }
+    Rank {
+        name,
+        rank_type: RankType::Basic,
+    }
 }
 
 /// Create a dense rank window function
 pub fn dense_rank(name: String) -> Rank {
-    Rank { name, dense: true }
+    Rank {
+        name,
+        rank_type: RankType::Dense,
+    }
+}
+
+/// Create a percent rank window function
+pub fn percent_rank(name: String) -> Rank {
+    Rank {
+        name,
+        rank_type: RankType::Percent,
+    }
 }
 
 impl BuiltInWindowFunctionExpr for Rank {
@@ -54,7 +79,10 @@ impl BuiltInWindowFunctionExpr for Rank {
 
     fn field(&self) -> Result<Field> {
         let nullable = false;
-        let data_type = DataType::UInt64;
+        let data_type = match self.rank_type {
+            RankType::Percent => DataType::Float64,
+            _ => DataType::UInt64,
+        };
         Ok(Field::new(self.name(), data_type, nullable))
     }
 
@@ -70,12 +98,14 @@ impl BuiltInWindowFunctionExpr for Rank {
         &self,
         _batch: &RecordBatch,
     ) -> Result<Box<dyn PartitionEvaluator>> {
-        Ok(Box::new(RankEvaluator { dense: self.dense }))
+        Ok(Box::new(RankEvaluator {
+            rank_type: self.rank_type,
+        }))
     }
 }
 
 pub(crate) struct RankEvaluator {
-    dense: bool,
+    rank_type: RankType,
 }
 
 impl PartitionEvaluator for RankEvaluator {
@@ -89,30 +119,38 @@ impl PartitionEvaluator for RankEvaluator {
 
     fn evaluate_partition_with_rank(
         &self,
-        _partition: Range<usize>,
+        partition: Range<usize>,
         ranks_in_partition: &[Range<usize>],
     ) -> Result<ArrayRef> {
-        let result = if self.dense {
-            UInt64Array::from_iter_values(ranks_in_partition.iter().zip(1u64..).flat_map(
-                |(range, rank)| {
-                    let len = range.end - range.start;
-                    iter::repeat(rank).take(len)
-                },
-            ))
-        } else {
-            UInt64Array::from_iter_values(
+        let ranks = ranks_in_partition
+            .iter()
+            .scan(1_u64, |acc, range| {
+                let len = range.end - range.start;
+                let result = iter::repeat(*acc).take(len);
+                *acc += len as u64;
+                Some(result)
+            })
+            .flatten();
+        let result: ArrayRef = match self.rank_type {
+            RankType::Dense => Arc::new(UInt64Array::from_iter_values(
                 ranks_in_partition
                     .iter()
-                    .scan(1_u64, |acc, range| {
+                    .zip(1u64..)
+                    .flat_map(|(range, rank)| {
                         let len = range.end - range.start;
-                        let result = iter::repeat(*acc).take(len);
-                        *acc += len as u64;
-                        Some(result)
-                    })
-                    .flatten(),
-            )
+                        iter::repeat(rank).take(len)
+                    }),
+            )),
+            RankType::Basic => Arc::new(UInt64Array::from_iter_values(ranks)),
+            RankType::Percent => {
+                // a partition of a single row has a percent rank of 0
+                let denominator = (partition.end - partition.start).max(2) as f64 - 1.0;
+                Arc::new(Float64Array::from_iter_values(
+                    ranks.map(|rank| (rank - 1) as f64 / denominator),
+                ))
+            }
         };
-        Ok(Arc::new(result))
+        Ok(result)
     }
 }
 
@@ -121,6 +159,24 @@ mod tests {
     use super::*;
     use arrow::{array::*, datatypes::*};
 
+    fn test_f64_result(
+        expr: &Rank,
+        num_rows: usize,
+        ranks: Vec<Range<usize>>,
+        expected: Vec<f64>,
+    ) -> Result<()> {
+        let arr: ArrayRef = Arc::new(Int32Array::from_iter_values(0..num_rows as i32));
+        let schema = Schema::new(vec![Field::new("arr", DataType::Int32, false)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![arr])?;
+        let result = expr
+            .create_evaluator(&batch)?
+            .evaluate_with_rank(vec![0..num_rows], ranks)?;
+        assert_eq!(1, result.len());
+        let result = result[0].as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(expected, result.values());
+        Ok(())
+    }
+
     fn test_with_rank(expr: &Rank, expected: Vec<u64>) -> Result<()> {
         test_i32_result(
             expr,
@@ -169,4 +225,27 @@ mod tests {
         test_with_rank(&r, vec![1, 1, 3, 4, 4, 4, 7, 8])?;
         Ok(())
     }
+
+    #[test]
+    fn test_percent_rank() -> Result<()> {
+        let r = percent_rank("arr".into());
+
+        // empty partition
+        test_f64_result(&r, 0, vec![], vec![])?;
+
+        // singleton partition
+        test_f64_result(&r, 1, vec![0..1], vec![0.0])?;
+
+        // uniform partition
+        test_f64_result(&r, 7, vec![0..7], vec![0.0; 7])?;
+
+        // non-trivial partition
+        test_f64_result(
+            &r,
+            7,
+            vec![0..3, 3..7],
+            vec![0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5],
+        )?;
+        Ok(())
+    }
 }
@@ -0,0 +1,265 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! File compression types for CSV and line-delimited JSON sources
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::sync::Mutex;
+
+use crate::error::{DataFusionError, Result};
+
+/// Compression codec of a CSV or line-delimited JSON file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCompressionType {
+    /// The file is not compressed
+    Uncompressed,
+    /// Gzip compression, file extension `.gz`
+    Gzip,
+    /// Bzip2 compression, file extension `.bz2`
+    Bzip2,
+    /// Zstandard compression, file extension `.zst`
+    Zstd,
+}
+
+impl FileCompressionType {
+    /// All compression types
+    pub const ALL: [FileCompressionType; 4] = [
+        FileCompressionType::Uncompressed,
+        FileCompressionType::Gzip,
+        FileCompressionType::Bzip2,
+        FileCompressionType::Zstd,
+    ];
+
+    /// The extension that is appended to the file extension of compressed files,
+    /// e.g. `.gz` for `data.csv.gz`
+    pub fn get_ext(&self) -> &'static str {
+        match self {
+            FileCompressionType::Uncompressed => "",
+            FileCompressionType::Gzip => ".gz",
+            FileCompressionType::Bzip2 => ".bz2",
+            FileCompressionType::Zstd => ".zst",
+        }
+    }
+
+    /// Determine the compression type of a file from its extension
+    pub fn from_path(path: &str) -> Self {
+        FileCompressionType::ALL
+            .iter()
+            .skip(1)
+            .find(|t| path.ends_with(t.get_ext()))
+            .copied()
+            .unwrap_or(FileCompressionType::Uncompressed)
+    }
+
+    /// The file extensions of files of the given format that can be read with this
+    /// compression type, or with any compression type if `compression` is `None`
+    pub fn file_extensions(
+        file_extension: &str,
+        compression: Option<FileCompressionType>,
+    ) -> Vec<String> {
+        match compression {
+            Some(compression) => {
+                vec![format!("{}{}", file_extension, compression.get_ext())]
+            }
+            None => FileCompressionType::ALL
+                .iter()
+                .map(|t| format!("{}{}", file_extension, t.get_ext()))
+                .collect(),
+        }
+    }
+
+    /// Wrap `reader` in a reader that decompresses its contents
+    pub fn decompress<R: Read + Send + 'static>(
+        &self,
+        reader: R,
+    ) -> Result<Box<dyn Read + Send + Sync>> {
+        let reader: Box<dyn Read + Send> = match self {
+            FileCompressionType::Uncompressed => Box::new(reader),
+            // files may consist of several members, such as the files of bgzip or the
+            // concatenation of compressed files
+            #[cfg(feature = "compression")]
+            FileCompressionType::Gzip => {
+                Box::new(flate2::read::MultiGzDecoder::new(reader))
+            }
+            #[cfg(feature = "compression")]
+            FileCompressionType::Bzip2 => {
+                Box::new(bzip2::read::MultiBzDecoder::new(reader))
+            }
+            #[cfg(feature = "compression")]
+            FileCompressionType::Zstd => Box::new(zstd::Decoder::new(reader)?),
+            #[cfg(not(feature = "compression"))]
+            _ => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "Reading {} compressed files requires the `compression` feature",
+                    self
+                )))
+            }
+        };
+        Ok(Box::new(SyncReader(Mutex::new(reader))))
+    }
+}
+
+impl fmt::Display for FileCompressionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FileCompressionType::Uncompressed => "UNCOMPRESSED",
+            FileCompressionType::Gzip => "GZIP",
+            FileCompressionType::Bzip2 => "BZIP2",
+            FileCompressionType::Zstd => "ZSTD",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for FileCompressionType {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "UNCOMPRESSED" | "" => Ok(FileCompressionType::Uncompressed),
+            "GZIP" | "GZ" => Ok(FileCompressionType::Gzip),
+            "BZIP2" | "BZ2" => Ok(FileCompressionType::Bzip2),
+            "ZSTD" | "ZST" => Ok(FileCompressionType::Zstd),
+            _ => Err(DataFusionError::Plan(format!(
+                "Unknown file compression type '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Decompressors are not necessarily `Sync`, which is required for them to be used in a
+/// `SendableRecordBatchStream`. As reading only requires a mutable reference, the mutex
+/// is never contended.
+struct SyncReader(Mutex<Box<dyn Read + Send>>);
+
+impl Read for SyncReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.get_mut().unwrap().read(buf)
+    }
+}
+
+/// Read at most `max_lines` lines of `reader` into memory. Schema inference requires a
+/// seekable reader, which decompressed streams are not.
+pub(crate) fn read_lines(reader: impl Read, max_lines: usize) -> Result<Cursor<Vec<u8>>> {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    for _ in 0..max_lines {
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+    }
+    Ok(Cursor::new(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_from_path() {
+        assert_eq!(
+            FileCompressionType::from_path("a/b.csv"),
+            FileCompressionType::Uncompressed
+        );
+        assert_eq!(
+            FileCompressionType::from_path("a/b.csv.gz"),
+            FileCompressionType::Gzip
+        );
+        assert_eq!(
+            FileCompressionType::from_path("b.json.bz2"),
+            FileCompressionType::Bzip2
+        );
+        assert_eq!(
+            FileCompressionType::from_path("b.json.zst"),
+            FileCompressionType::Zstd
+        );
+    }
+
+    #[test]
+    fn compression_file_extensions() {
+        assert_eq!(
+            FileCompressionType::file_extensions(".csv", Some(FileCompressionType::Gzip)),
+            vec![".csv.gz"]
+        );
+        assert_eq!(
+            FileCompressionType::file_extensions(".csv", None),
+            vec![".csv", ".csv.gz", ".csv.bz2", ".csv.zst"]
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decompress_gzip() -> Result<()> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"a,b\n1,2\n3,4\n")?;
+        let compressed = encoder.finish()?;
+
+        let reader = FileCompressionType::Gzip.decompress(Cursor::new(compressed))?;
+        let lines = read_lines(reader, 2)?;
+        assert_eq!(lines.into_inner(), b"a,b\n1,2\n".to_vec());
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decompress_concatenated_members() -> Result<()> {
+        use std::io::Write;
+
+        let gzip = |data: &[u8]| -> Result<Vec<u8>> {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        };
+        let bzip2 = |data: &[u8]| -> Result<Vec<u8>> {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        };
+
+        let members = vec![
+            (
+                FileCompressionType::Gzip,
+                gzip(b"a,b\n1,2\n")?,
+                gzip(b"3,4\n")?,
+            ),
+            (
+                FileCompressionType::Bzip2,
+                bzip2(b"a,b\n1,2\n")?,
+                bzip2(b"3,4\n")?,
+            ),
+        ];
+        for (compression, mut compressed, second) in members {
+            compressed.extend(second);
+            let reader = compression.decompress(Cursor::new(compressed))?;
+            let lines = read_lines(reader, 10)?;
+            assert_eq!(
+                lines.into_inner(),
+                b"a,b\n1,2\n3,4\n".to_vec(),
+                "{}",
+                compression
+            );
+        }
+        Ok(())
+    }
+}
@@ -23,11 +23,18 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use super::expressions::{
+    BinaryExpr, CastExpr, Column, IsNotNullExpr, IsNullExpr, Literal, NotExpr,
+    PhysicalSortExpr, TryCastExpr,
+};
 use super::{RecordBatchStream, SendableRecordBatchStream};
+use crate::datasource::datasource::{ColumnStatistics, Statistics};
 use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Operator;
 use crate::physical_plan::{
-    DisplayFormatType, ExecutionPlan, Partitioning, PhysicalExpr,
+    ColumnarValue, DisplayFormatType, ExecutionPlan, Partitioning, PhysicalExpr,
 };
+use crate::scalar::ScalarValue;
 use arrow::array::BooleanArray;
 use arrow::compute::filter_record_batch;
 use arrow::datatypes::{DataType, SchemaRef};
@@ -99,6 +106,10 @@ impl ExecutionPlan for FilterExec {
         self.input.output_partitioning()
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        self.input.output_ordering()
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -133,6 +144,217 @@ impl ExecutionPlan for FilterExec {
             }
         }
     }
+
+    /// The output statistics are the input statistics scaled by the estimated
+    /// selectivity of the predicate
+    fn statistics(&self) -> Statistics {
+        let input_stats = self.input.statistics();
+        let selectivity = estimate_selectivity(&self.predicate, &input_stats);
+        let scale = |v: usize| (v as f64 * selectivity).round() as usize;
+        let num_rows = input_stats.num_rows.map(scale);
+
+        let column_statistics = input_stats.column_statistics.map(|cols| {
+            cols.into_iter()
+                .map(|c| ColumnStatistics {
+                    null_count: c.null_count.map(scale),
+                    distinct_count: match (c.distinct_count, num_rows) {
+                        (Some(d), Some(n)) => Some(d.min(n)),
+                        (d, _) => d,
+                    },
+                    max_value: c.max_value,
+                    min_value: c.min_value,
+                })
+                .collect()
+        });
+
+        Statistics {
+            num_rows,
+            total_byte_size: input_stats.total_byte_size.map(scale),
+            column_statistics,
+        }
+    }
+}
+
+/// Selectivity used when nothing is known about a predicate
+const DEFAULT_SELECTIVITY: f64 = 0.2;
+/// Selectivity of an equality predicate on a column without a known distinct count
+const DEFAULT_EQUALITY_SELECTIVITY: f64 = 0.1;
+/// Selectivity of a range predicate on a column without known bounds
+const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// Estimates the fraction of input rows, between 0 and 1, for which `predicate`
+/// evaluates to true, using the column statistics of the input where available.
+pub(crate) fn estimate_selectivity(
+    predicate: &Arc<dyn PhysicalExpr>,
+    stats: &Statistics,
+) -> f64 {
+    let any = predicate.as_any();
+    let selectivity = if let Some(binary) = any.downcast_ref::<BinaryExpr>() {
+        match binary.op() {
+            Operator::And => {
+                estimate_selectivity(binary.left(), stats)
+                    * estimate_selectivity(binary.right(), stats)
+            }
+            Operator::Or => {
+                let l = estimate_selectivity(binary.left(), stats);
+                let r = estimate_selectivity(binary.right(), stats);
+                l + r - l * r
+            }
+            op => comparison_selectivity(binary.left(), op, binary.right(), stats),
+        }
+    } else if let Some(not) = any.downcast_ref::<NotExpr>() {
+        1.0 - estimate_selectivity(not.arg(), stats)
+    } else if let Some(is_null) = any.downcast_ref::<IsNullExpr>() {
+        null_fraction(is_null.arg(), stats).unwrap_or(DEFAULT_SELECTIVITY)
+    } else if let Some(is_not_null) = any.downcast_ref::<IsNotNullExpr>() {
+        null_fraction(is_not_null.arg(), stats)
+            .map(|f| 1.0 - f)
+            .unwrap_or(1.0 - DEFAULT_SELECTIVITY)
+    } else if let Some(literal) = any.downcast_ref::<Literal>() {
+        match literal.value() {
+            ScalarValue::Boolean(Some(true)) => 1.0,
+            ScalarValue::Boolean(_) => 0.0,
+            _ => DEFAULT_SELECTIVITY,
+        }
+    } else {
+        DEFAULT_SELECTIVITY
+    };
+    selectivity.max(0.0).min(1.0)
+}
+
+/// Selectivity of `left op right` where one side is a column and the other a literal
+fn comparison_selectivity(
+    left: &Arc<dyn PhysicalExpr>,
+    op: &Operator,
+    right: &Arc<dyn PhysicalExpr>,
+    stats: &Statistics,
+) -> f64 {
+    let (column, op, value) = match (as_column(left), as_literal(right)) {
+        (Some(column), Some(value)) => (column, *op, value),
+        _ => match (as_literal(left), as_column(right)) {
+            // `literal op column` is equivalent to `column swapped(op) literal`
+            (Some(value), Some(column)) => {
+                let op = match op {
+                    Operator::Lt => Operator::Gt,
+                    Operator::LtEq => Operator::GtEq,
+                    Operator::Gt => Operator::Lt,
+                    Operator::GtEq => Operator::LtEq,
+                    op => *op,
+                };
+                (column, op, value)
+            }
+            _ => {
+                return match op {
+                    Operator::Eq => DEFAULT_EQUALITY_SELECTIVITY,
+                    Operator::NotEq => 1.0 - DEFAULT_EQUALITY_SELECTIVITY,
+                    _ => DEFAULT_SELECTIVITY,
+                }
+            }
+        },
+    };
+    let column_stats = column_statistics(column, stats);
+
+    match op {
+        Operator::Eq => equality_selectivity(column_stats),
+        Operator::NotEq => 1.0 - equality_selectivity(column_stats),
+        Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq => {
+            let bounds = column_stats.and_then(|c| {
+                let min = c.min_value.as_ref().and_then(scalar_to_f64)?;
+                let max = c.max_value.as_ref().and_then(scalar_to_f64)?;
+                Some((min, max))
+            });
+            match (bounds, scalar_to_f64(value)) {
+                (Some((min, max)), Some(v)) if max > min => match op {
+                    Operator::Lt | Operator::LtEq => (v - min) / (max - min),
+                    _ => (max - v) / (max - min),
+                },
+                (Some((min, _)), Some(v)) => {
+                    let matches = match op {
+                        Operator::Lt => min < v,
+                        Operator::LtEq => min <= v,
+                        Operator::Gt => min > v,
+                        _ => min >= v,
+                    };
+                    if matches {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            }
+        }
+        _ => DEFAULT_SELECTIVITY,
+    }
+}
+
+fn equality_selectivity(column_stats: Option<&ColumnStatistics>) -> f64 {
+    match column_stats.and_then(|c| c.distinct_count) {
+        Some(distinct) if distinct > 0 => 1.0 / distinct as f64,
+        _ => DEFAULT_EQUALITY_SELECTIVITY,
+    }
+}
+
+/// Fraction of rows for which the column referenced by `expr` is null
+fn null_fraction(expr: &Arc<dyn PhysicalExpr>, stats: &Statistics) -> Option<f64> {
+    let null_count = column_statistics(as_column(expr)?, stats)?.null_count?;
+    match stats.num_rows? {
+        0 => Some(0.0),
+        num_rows => Some(null_count as f64 / num_rows as f64),
+    }
+}
+
+fn column_statistics<'a>(
+    column: &Column,
+    stats: &'a Statistics,
+) -> Option<&'a ColumnStatistics> {
+    stats
+        .column_statistics
+        .as_ref()
+        .and_then(|cols| cols.get(column.index()))
+}
+
+/// Returns the column referenced by `expr`, looking through casts
+fn as_column(expr: &Arc<dyn PhysicalExpr>) -> Option<&Column> {
+    let any = expr.as_any();
+    if let Some(cast) = any.downcast_ref::<CastExpr>() {
+        as_column(cast.expr())
+    } else if let Some(cast) = any.downcast_ref::<TryCastExpr>() {
+        as_column(cast.expr())
+    } else {
+        any.downcast_ref::<Column>()
+    }
+}
+
+/// Returns the value of the literal `expr`, looking through casts
+fn as_literal(expr: &Arc<dyn PhysicalExpr>) -> Option<&ScalarValue> {
+    let any = expr.as_any();
+    if let Some(cast) = any.downcast_ref::<CastExpr>() {
+        as_literal(cast.expr())
+    } else if let Some(cast) = any.downcast_ref::<TryCastExpr>() {
+        as_literal(cast.expr())
+    } else {
+        any.downcast_ref::<Literal>().map(|l| l.value())
+    }
+}
+
+/// Converts numeric and temporal scalars to `f64` for interpolating between bounds
+fn scalar_to_f64(value: &ScalarValue) -> Option<f64> {
+    match value {
+        ScalarValue::Int8(v) => v.map(|v| v as f64),
+        ScalarValue::Int16(v) => v.map(|v| v as f64),
+        ScalarValue::Int32(v) => v.map(|v| v as f64),
+        ScalarValue::Int64(v) => v.map(|v| v as f64),
+        ScalarValue::UInt8(v) => v.map(|v| v as f64),
+        ScalarValue::UInt16(v) => v.map(|v| v as f64),
+        ScalarValue::UInt32(v) => v.map(|v| v as f64),
+        ScalarValue::UInt64(v) => v.map(|v| v as f64),
+        ScalarValue::Float32(v) => v.map(|v| v as f64),
+        ScalarValue::Float64(v) => *v,
+        ScalarValue::Date32(v) => v.map(|v| v as f64),
+        ScalarValue::Date64(v) => v.map(|v| v as f64),
+        _ => None,
+    }
 }
 
 /// The FilterExec streams wraps the input iterator and applies the predicate expression to
@@ -150,23 +372,33 @@ fn batch_filter(
     batch: &RecordBatch,
     predicate: &Arc<dyn PhysicalExpr>,
 ) -> ArrowResult<RecordBatch> {
-    predicate
+    let value = predicate
         .evaluate(batch)
-        .map(|v| v.into_array(batch.num_rows()))
-        .map_err(DataFusionError::into_arrow_external_error)
-        .and_then(|array| {
-            array
-                .as_any()
-                .downcast_ref::<BooleanArray>()
-                .ok_or_else(|| {
-                    DataFusionError::Internal(
-                        "Filter predicate evaluated to non-boolean value".to_string(),
-                    )
-                    .into_arrow_external_error()
-                })
-                // apply filter array to record batch
-                .and_then(|filter_array| filter_record_batch(batch, filter_array))
-        })
+        .map_err(DataFusionError::into_arrow_external_error)?;
+    // a constant predicate, such as one on the partition columns, keeps all or none
+    // of the rows without materializing a filter array
+    match value {
+        ColumnarValue::Scalar(ScalarValue::Boolean(Some(true))) => {
+            return Ok(batch.clone())
+        }
+        ColumnarValue::Scalar(ScalarValue::Boolean(_)) => {
+            return Ok(RecordBatch::new_empty(batch.schema()))
+        }
+        _ => {}
+    }
+    let array = value.into_array(batch.num_rows());
+    let filter_array =
+        array
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .ok_or_else(|| {
+                DataFusionError::Internal(
+                    "Filter predicate evaluated to non-boolean value".to_string(),
+                )
+                .into_arrow_external_error()
+            })?;
+    // apply filter array to record batch
+    filter_record_batch(batch, filter_array)
 }
 
 impl Stream for FilterExecStream {
@@ -198,12 +430,14 @@ impl RecordBatchStream for FilterExecStream {
 mod tests {
 
     use super::*;
+    use crate::physical_plan::collect;
     use crate::physical_plan::csv::{CsvExec, CsvReadOptions};
     use crate::physical_plan::expressions::*;
     use crate::physical_plan::ExecutionPlan;
     use crate::scalar::ScalarValue;
     use crate::test;
-    use crate::{logical_plan::Operator, physical_plan::collect};
+    use crate::test::exec::StatisticsExec;
+    use arrow::datatypes::{Field, Schema};
     use std::iter::Iterator;
 
     #[tokio::test]
@@ -251,4 +485,91 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn constant_predicate() -> Result<()> {
+        let batch = test::make_partition(5);
+
+        let filtered = batch_filter(&batch, &lit(ScalarValue::Boolean(Some(true))))?;
+        assert_eq!(filtered.num_rows(), 5);
+        assert!(Arc::ptr_eq(filtered.column(0), batch.column(0)));
+
+        for value in vec![Some(false), None] {
+            let filtered = batch_filter(&batch, &lit(ScalarValue::Boolean(value)))?;
+            assert_eq!(filtered.num_rows(), 0);
+            assert_eq!(filtered.schema(), batch.schema());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn filter_statistics() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        let input = Arc::new(StatisticsExec::new(
+            Statistics {
+                num_rows: Some(1000),
+                total_byte_size: Some(8000),
+                column_statistics: Some(vec![
+                    ColumnStatistics {
+                        null_count: Some(100),
+                        distinct_count: Some(50),
+                        ..Default::default()
+                    },
+                    ColumnStatistics {
+                        null_count: Some(0),
+                        min_value: Some(ScalarValue::Int32(Some(0))),
+                        max_value: Some(ScalarValue::Int32(Some(100))),
+                        ..Default::default()
+                    },
+                ]),
+            },
+            schema.clone(),
+        ));
+
+        // a = 5 matches one of the 50 distinct values
+        let predicate = binary(
+            col("a", &schema)?,
+            Operator::Eq,
+            lit(ScalarValue::from(5i32)),
+            &schema,
+        )?;
+        let filter = FilterExec::try_new(predicate, input.clone())?;
+        let stats = filter.statistics();
+        assert_eq!(stats.num_rows, Some(20));
+        assert_eq!(stats.total_byte_size, Some(160));
+        let columns = stats.column_statistics.unwrap();
+        assert_eq!(columns[0].null_count, Some(2));
+        assert_eq!(columns[0].distinct_count, Some(20));
+
+        // 25 < b AND a IS NOT NULL keeps 3/4 of the range and 9/10 of the rows
+        let predicate = binary(
+            binary(
+                lit(ScalarValue::from(25i32)),
+                Operator::Lt,
+                col("b", &schema)?,
+                &schema,
+            )?,
+            Operator::And,
+            is_not_null(col("a", &schema)?)?,
+            &schema,
+        )?;
+        let filter = FilterExec::try_new(predicate, input.clone())?;
+        assert_eq!(filter.statistics().num_rows, Some(675));
+
+        // unknown input statistics stay unknown
+        let predicate = binary(
+            col("b", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::from(25i32)),
+            &schema,
+        )?;
+        let input = Arc::new(StatisticsExec::new(Statistics::default(), schema));
+        let filter = FilterExec::try_new(predicate, input)?;
+        assert_eq!(filter.statistics(), Statistics::default());
+
+        Ok(())
+    }
 }
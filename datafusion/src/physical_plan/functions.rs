@@ -37,7 +37,8 @@ use crate::execution::context::ExecutionContextState;
 use crate::physical_plan::array_expressions;
 use crate::physical_plan::datetime_expressions;
 use crate::physical_plan::expressions::{
-    cast_column, nullif_func, DEFAULT_DATAFUSION_CAST_OPTIONS, SUPPORTED_NULLIF_TYPES,
+    cast_column, nullif_func, Literal, DEFAULT_DATAFUSION_CAST_OPTIONS,
+    SUPPORTED_NULLIF_TYPES,
 };
 use crate::physical_plan::math_expressions;
 use crate::physical_plan::string_expressions;
@@ -49,7 +50,7 @@ use arrow::{
     array::{ArrayRef, NullArray},
     compute::kernels::length::{bit_length, length},
     datatypes::TimeUnit,
-    datatypes::{DataType, Field, Int32Type, Int64Type, Schema},
+    datatypes::{DataType, Field, Int32Type, Int64Type, IntervalUnit, Schema},
     record_batch::RecordBatch,
 };
 use fmt::{Debug, Formatter};
@@ -151,10 +152,14 @@ pub enum BuiltinScalarFunction {
     Concat,
     /// concat_ws
     ConcatWithSeparator,
+    /// current_user
+    CurrentUser,
     /// date_part
     DatePart,
     /// date_trunc
     DateTrunc,
+    /// date_bin
+    DateBin,
     /// initcap
     InitCap,
     /// left
@@ -203,6 +208,8 @@ pub enum BuiltinScalarFunction {
     Strpos,
     /// substr
     Substr,
+    /// to_char
+    ToChar,
     /// to_hex
     ToHex,
     /// to_timestamp
@@ -223,6 +230,8 @@ pub enum BuiltinScalarFunction {
     Upper,
     /// regexp_match
     RegexpMatch,
+    /// version
+    Version,
 }
 
 impl BuiltinScalarFunction {
@@ -231,7 +240,24 @@ impl BuiltinScalarFunction {
     fn supports_zero_argument(&self) -> bool {
         matches!(
             self,
-            BuiltinScalarFunction::Random | BuiltinScalarFunction::Now
+            BuiltinScalarFunction::Random
+                | BuiltinScalarFunction::Now
+                | BuiltinScalarFunction::CurrentUser
+                | BuiltinScalarFunction::Version
+        )
+    }
+
+    /// Returns true for the functions that are evaluated once per query, such as
+    /// `now()`, rather than once per batch. They are planned as literals with the
+    /// values of the [ExecutionProps](crate::execution::context::ExecutionProps) of
+    /// the query, so that every partition, and every task of a distributed query,
+    /// sees the same value.
+    pub fn is_query_constant(&self) -> bool {
+        matches!(
+            self,
+            BuiltinScalarFunction::Now
+                | BuiltinScalarFunction::CurrentUser
+                | BuiltinScalarFunction::Version
         )
     }
 }
@@ -277,8 +303,10 @@ impl FromStr for BuiltinScalarFunction {
             "concat" => BuiltinScalarFunction::Concat,
             "concat_ws" => BuiltinScalarFunction::ConcatWithSeparator,
             "chr" => BuiltinScalarFunction::Chr,
+            "current_user" => BuiltinScalarFunction::CurrentUser,
             "date_part" | "datepart" => BuiltinScalarFunction::DatePart,
             "date_trunc" | "datetrunc" => BuiltinScalarFunction::DateTrunc,
+            "date_bin" => BuiltinScalarFunction::DateBin,
             "initcap" => BuiltinScalarFunction::InitCap,
             "left" => BuiltinScalarFunction::Left,
             "length" => BuiltinScalarFunction::CharacterLength,
@@ -304,6 +332,7 @@ impl FromStr for BuiltinScalarFunction {
             "starts_with" => BuiltinScalarFunction::StartsWith,
             "strpos" => BuiltinScalarFunction::Strpos,
             "substr" => BuiltinScalarFunction::Substr,
+            "to_char" => BuiltinScalarFunction::ToChar,
             "to_hex" => BuiltinScalarFunction::ToHex,
             "to_timestamp" => BuiltinScalarFunction::ToTimestamp,
             "to_timestamp_millis" => BuiltinScalarFunction::ToTimestampMillis,
@@ -314,6 +343,7 @@ impl FromStr for BuiltinScalarFunction {
             "trim" => BuiltinScalarFunction::Trim,
             "upper" => BuiltinScalarFunction::Upper,
             "regexp_match" => BuiltinScalarFunction::RegexpMatch,
+            "version" => BuiltinScalarFunction::Version,
             _ => {
                 return Err(DataFusionError::Plan(format!(
                     "There is no built-in function named {}",
@@ -373,10 +403,14 @@ pub fn return_type(
         BuiltinScalarFunction::Chr => Ok(DataType::Utf8),
         BuiltinScalarFunction::Concat => Ok(DataType::Utf8),
         BuiltinScalarFunction::ConcatWithSeparator => Ok(DataType::Utf8),
+        BuiltinScalarFunction::CurrentUser => Ok(DataType::Utf8),
         BuiltinScalarFunction::DatePart => Ok(DataType::Int32),
         BuiltinScalarFunction::DateTrunc => {
             Ok(DataType::Timestamp(TimeUnit::Nanosecond, None))
         }
+        BuiltinScalarFunction::DateBin => {
+            Ok(DataType::Timestamp(TimeUnit::Nanosecond, None))
+        }
         BuiltinScalarFunction::InitCap => utf8_to_str_type(&arg_types[0], "initcap"),
         BuiltinScalarFunction::Left => utf8_to_str_type(&arg_types[0], "left"),
         BuiltinScalarFunction::Lower => utf8_to_str_type(&arg_types[0], "lower"),
@@ -420,6 +454,7 @@ pub fn return_type(
                 ));
             }
         }),
+        BuiltinScalarFunction::ToChar => Ok(DataType::Utf8),
         BuiltinScalarFunction::ToTimestamp => {
             Ok(DataType::Timestamp(TimeUnit::Nanosecond, None))
         }
@@ -450,6 +485,7 @@ pub fn return_type(
                 ));
             }
         }),
+        BuiltinScalarFunction::Version => Ok(DataType::Utf8),
 
         BuiltinScalarFunction::Abs
         | BuiltinScalarFunction::Acos
@@ -640,11 +676,14 @@ pub fn create_physical_fun(
         }
         BuiltinScalarFunction::DatePart => Arc::new(datetime_expressions::date_part),
         BuiltinScalarFunction::DateTrunc => Arc::new(datetime_expressions::date_trunc),
-        BuiltinScalarFunction::Now => {
-            // bind value for now at plan time
-            Arc::new(datetime_expressions::make_now(
-                ctx_state.execution_props.query_execution_start_time,
-            ))
+        BuiltinScalarFunction::DateBin => Arc::new(datetime_expressions::date_bin),
+        BuiltinScalarFunction::ToChar => Arc::new(datetime_expressions::to_char),
+        BuiltinScalarFunction::Now
+        | BuiltinScalarFunction::CurrentUser
+        | BuiltinScalarFunction::Version => {
+            // bind the value of query constants at plan time
+            let value = ctx_state.execution_props.query_constant(fun)?;
+            Arc::new(move |_: &[ColumnarValue]| Ok(ColumnarValue::Scalar(value.clone())))
         }
         BuiltinScalarFunction::InitCap => Arc::new(|args| match args[0].data_type() {
             DataType::Utf8 => {
@@ -979,6 +1018,12 @@ pub fn create_physical_expr(
     input_schema: &Schema,
     ctx_state: &ExecutionContextState,
 ) -> Result<Arc<dyn PhysicalExpr>> {
+    if fun.is_query_constant() {
+        coerce(args, input_schema, &signature(fun))?;
+        let value = ctx_state.execution_props.query_constant(fun)?;
+        return Ok(Arc::new(Literal::new(value)));
+    }
+
     let fun_expr: ScalarFunctionImplementation = match fun {
         // These functions need args and input schema to pick an implementation
         // Unlike the string functions, which actually figure out the function to use with each array,
@@ -995,6 +1040,9 @@ pub fn create_physical_expr(
                         )
                     }
                 }
+                Ok(DataType::Utf8) if args.len() > 1 => {
+                    datetime_expressions::to_timestamp_with_format
+                }
                 Ok(DataType::Utf8) => datetime_expressions::to_timestamp,
                 other => {
                     return Err(DataFusionError::Internal(format!(
@@ -1147,15 +1195,38 @@ fn signature(fun: &BuiltinScalarFunction) -> Signature {
             Signature::Exact(vec![DataType::Utf8, DataType::Int64]),
             Signature::Exact(vec![DataType::LargeUtf8, DataType::Int64]),
         ]),
-        BuiltinScalarFunction::ToTimestamp => Signature::Uniform(
-            1,
+        BuiltinScalarFunction::ToTimestamp => Signature::OneOf(vec![
+            Signature::Uniform(
+                1,
+                vec![
+                    DataType::Utf8,
+                    DataType::Int64,
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    DataType::Timestamp(TimeUnit::Microsecond, None),
+                    DataType::Timestamp(TimeUnit::Second, None),
+                ],
+            ),
+            Signature::Exact(vec![DataType::Utf8, DataType::Utf8]),
+            Signature::Exact(vec![DataType::Utf8, DataType::Utf8, DataType::Utf8]),
+        ]),
+        // a date or timestamp, a format and an optional locale
+        BuiltinScalarFunction::ToChar => Signature::OneOf(
             vec![
-                DataType::Utf8,
-                DataType::Int64,
+                DataType::Date32,
+                DataType::Date64,
+                DataType::Timestamp(TimeUnit::Second, None),
                 DataType::Timestamp(TimeUnit::Millisecond, None),
                 DataType::Timestamp(TimeUnit::Microsecond, None),
-                DataType::Timestamp(TimeUnit::Second, None),
-            ],
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+            ]
+            .into_iter()
+            .flat_map(|t| {
+                vec![
+                    Signature::Exact(vec![t.clone(), DataType::Utf8]),
+                    Signature::Exact(vec![t, DataType::Utf8, DataType::Utf8]),
+                ]
+            })
+            .collect(),
         ),
         BuiltinScalarFunction::ToTimestampMillis => Signature::Uniform(
             1,
@@ -1191,6 +1262,17 @@ fn signature(fun: &BuiltinScalarFunction) -> Signature {
             DataType::Utf8,
             DataType::Timestamp(TimeUnit::Nanosecond, None),
         ]),
+        BuiltinScalarFunction::DateBin => Signature::OneOf(vec![
+            Signature::Exact(vec![
+                DataType::Interval(IntervalUnit::DayTime),
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+            ]),
+            Signature::Exact(vec![
+                DataType::Interval(IntervalUnit::DayTime),
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+            ]),
+        ]),
         BuiltinScalarFunction::DatePart => Signature::OneOf(vec![
             Signature::Exact(vec![DataType::Utf8, DataType::Date32]),
             Signature::Exact(vec![DataType::Utf8, DataType::Date64]),
@@ -1264,7 +1346,10 @@ fn signature(fun: &BuiltinScalarFunction) -> Signature {
             Signature::Exact(vec![DataType::Utf8, DataType::Utf8, DataType::Utf8]),
             Signature::Exact(vec![DataType::LargeUtf8, DataType::Utf8, DataType::Utf8]),
         ]),
-        BuiltinScalarFunction::Random => Signature::Exact(vec![]),
+        BuiltinScalarFunction::Random
+        | BuiltinScalarFunction::Now
+        | BuiltinScalarFunction::CurrentUser
+        | BuiltinScalarFunction::Version => Signature::Exact(vec![]),
         // math expressions expect 1 argument of type f64 or f32
         // priority is given to f64 because e.g. `sqrt(1i32)` is in IR (real numbers) and thus we
         // return the best approximation for it (in f64).
@@ -28,7 +28,9 @@ use futures::{
     Future,
 };
 
+use crate::datasource::datasource::{ColumnStatistics, Statistics};
 use crate::error::{DataFusionError, Result};
+use crate::execution::cpu_pool;
 use crate::physical_plan::{
     Accumulator, AggregateExpr, DisplayFormatType, Distribution, ExecutionPlan,
     Partitioning, PhysicalExpr, SQLMetric,
@@ -36,38 +38,28 @@ use crate::physical_plan::{
 use crate::scalar::ScalarValue;
 
 use arrow::{
-    array::{Array, UInt32Builder},
-    error::{ArrowError, Result as ArrowResult},
-};
-use arrow::{
-    array::{
-        ArrayRef, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
-        Int8Array, StringArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
-    },
+    array::{Array, ArrayRef, UInt32Builder},
     compute,
-};
-use arrow::{
-    array::{BooleanArray, Date32Array, DictionaryArray},
     compute::cast,
-    datatypes::{
-        ArrowDictionaryKeyType, ArrowNativeType, Int16Type, Int32Type, Int64Type,
-        Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
-    },
+    error::{ArrowError, Result as ArrowResult},
 };
 use arrow::{
-    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+    datatypes::{Field, Schema, SchemaRef},
     record_batch::RecordBatch,
 };
+use hashbrown::raw::RawTable;
 use hashbrown::HashMap;
 use pin_project_lite::pin_project;
 
-use arrow::array::{
-    LargeStringArray, TimestampMicrosecondArray, TimestampMillisecondArray,
-    TimestampNanosecondArray,
-};
 use async_trait::async_trait;
 
-use super::{expressions::Column, RecordBatchStream, SendableRecordBatchStream};
+use super::{
+    expressions::Column, hash_utils::create_hashes, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+
+/// Rows of input that a grouped aggregation hands to the CPU pool at once
+const AGGREGATE_CHUNK_ROWS: usize = 64 * 1024;
 
 /// Hash aggregate modes
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -83,6 +75,12 @@ pub enum AggregateMode {
     /// with Hash repartitioning on the group keys. If a group key is
     /// duplicated, duplicate groups would be produced
     FinalPartitioned,
+    /// Single aggregate that computes the final result directly from the input rows of
+    /// each partition, without a partial aggregate.
+    ///
+    /// This has the same requirement on the input partitioning as `FinalPartitioned`
+    /// and is used when the input is already hash partitioned on the group keys.
+    SinglePartitioned,
 }
 
 /// Hash aggregate execution plan
@@ -106,7 +104,7 @@ pub struct HashAggregateExec {
     output_rows: Arc<SQLMetric>,
 }
 
-fn create_schema(
+pub(crate) fn create_schema(
     input_schema: &Schema,
     group_expr: &[(Arc<dyn PhysicalExpr>, String)],
     aggr_expr: &[Arc<dyn AggregateExpr>],
@@ -128,7 +126,9 @@ fn create_schema(
                 fields.extend(expr.state_fields()?.iter().cloned())
             }
         }
-        AggregateMode::Final | AggregateMode::FinalPartitioned => {
+        AggregateMode::Final
+        | AggregateMode::FinalPartitioned
+        | AggregateMode::SinglePartitioned => {
             // in final mode, the field with the final result of the accumulator
             for expr in aggr_expr {
                 fields.push(expr.field()?)
@@ -209,9 +209,11 @@ impl ExecutionPlan for HashAggregateExec {
     fn required_child_distribution(&self) -> Distribution {
         match &self.mode {
             AggregateMode::Partial => Distribution::UnspecifiedDistribution,
-            AggregateMode::FinalPartitioned => Distribution::HashPartitioned(
-                self.group_expr.iter().map(|x| x.0.clone()).collect(),
-            ),
+            AggregateMode::FinalPartitioned | AggregateMode::SinglePartitioned => {
+                Distribution::HashPartitioned(
+                    self.group_expr.iter().map(|x| x.0.clone()).collect(),
+                )
+            }
             AggregateMode::Final => Distribution::SinglePartition,
         }
     }
@@ -300,6 +302,71 @@ impl ExecutionPlan for HashAggregateExec {
         }
         Ok(())
     }
+
+    /// The number of output rows is estimated from the distinct counts of the
+    /// grouping columns, bounded by the number of input rows
+    fn statistics(&self) -> Statistics {
+        aggregate_statistics(self.mode, &self.group_expr, &self.input, &self.schema)
+    }
+}
+
+/// Estimates the statistics of the output of an aggregation in `mode`
+pub(crate) fn aggregate_statistics(
+    mode: AggregateMode,
+    group_expr: &[(Arc<dyn PhysicalExpr>, String)],
+    input: &Arc<dyn ExecutionPlan>,
+    schema: &Schema,
+) -> Statistics {
+    let input_stats = input.statistics();
+    // partial aggregates produce their groups once per input partition, while the
+    // other modes see all the rows of a group in the same partition
+    let partitions = match mode {
+        AggregateMode::Partial => input.output_partitioning().partition_count(),
+        AggregateMode::Final
+        | AggregateMode::FinalPartitioned
+        | AggregateMode::SinglePartitioned => 1,
+    };
+
+    let group_stats: Vec<ColumnStatistics> = group_expr
+        .iter()
+        .map(|(e, _)| {
+            e.as_any()
+                .downcast_ref::<Column>()
+                .and_then(|c| {
+                    input_stats
+                        .column_statistics
+                        .as_ref()
+                        .and_then(|cols| cols.get(c.index()).cloned())
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let num_rows = if group_expr.is_empty() {
+        Some(partitions)
+    } else {
+        let groups = group_stats
+            .iter()
+            .map(|c| c.distinct_count)
+            .try_fold(1usize, |acc, d| d.map(|d| acc.saturating_mul(d)));
+        match (groups, input_stats.num_rows) {
+            (Some(g), Some(n)) => Some(g.saturating_mul(partitions).min(n)),
+            (None, n) => n,
+            (Some(_), None) => None,
+        }
+    };
+
+    let column_statistics = input_stats.column_statistics.map(|_| {
+        let mut cols = group_stats;
+        cols.resize(schema.fields().len(), ColumnStatistics::default());
+        cols
+    });
+
+    Statistics {
+        num_rows,
+        total_byte_size: None,
+        column_statistics,
+    }
 }
 
 /*
@@ -339,6 +406,7 @@ pin_project! {
 
 fn group_aggregate_batch(
     mode: &AggregateMode,
+    random_state: &RandomState,
     group_expr: &[Arc<dyn PhysicalExpr>],
     aggr_expr: &[Arc<dyn AggregateExpr>],
     batch: RecordBatch,
@@ -353,65 +421,68 @@ fn group_aggregate_batch(
     // of them anyways, it is more performant to do it while they are together.
     let aggr_input_values = evaluate_many(aggregate_expressions, &batch)?;
 
-    // create vector large enough to hold the grouping key
-    // this is an optimization to avoid allocating `key` on every row.
-    // it will be overwritten on every iteration of the loop below
-    let mut group_by_values = Vec::with_capacity(group_values.len());
-    for _ in 0..group_values.len() {
-        group_by_values.push(ScalarValue::UInt32(Some(0)));
-    }
-
-    let mut group_by_values = group_by_values.into_boxed_slice();
+    // 1.1 calculate the group keys for the group values
+    // 1.2 look up the group of each row in the hash table, creating new groups
+    //     for keys that have not been seen before
+    // 1.3 add the row's index to the `indices` of its group
 
-    let mut key = Vec::with_capacity(group_values.len());
+    // Indices of the groups that received rows in this batch
+    let mut groups_with_rows = vec![];
 
-    // 1.1 construct the key from the group values
-    // 1.2 construct the mapping key if it does not exist
-    // 1.3 add the row' index to `indices`
-
-    // Make sure we can create the accumulators or otherwise return an error
-    create_accumulators(aggr_expr).map_err(DataFusionError::into_arrow_external_error)?;
+    // 1.1
+    let mut batch_hashes = vec![0; batch.num_rows()];
+    create_hashes(&group_values, random_state, &mut batch_hashes)?;
 
-    // Keys received in this batch
-    let mut batch_keys = vec![];
+    for (row, hash) in batch_hashes.into_iter().enumerate() {
+        let Accumulators { map, group_states } = &mut accumulators;
 
-    for row in 0..batch.num_rows() {
-        // 1.1
-        create_key(&group_values, row, &mut key)
-            .map_err(DataFusionError::into_arrow_external_error)?;
+        // 1.2 the hash only selects candidate groups, which are then compared
+        // value by value to rule out collisions
+        let entry = map.get_mut(hash, |(_hash, group_idx)| {
+            let group_state = &group_states[*group_idx];
+            group_values
+                .iter()
+                .zip(group_state.group_by_values.iter())
+                .all(|(array, scalar)| scalar.eq_array(array, row))
+        });
 
-        accumulators
-            .raw_entry_mut()
-            .from_key(&key)
+        match entry {
             // 1.3
-            .and_modify(|_, (_, _, v)| {
-                if v.is_empty() {
-                    batch_keys.push(key.clone())
+            Some((_hash, group_idx)) => {
+                let group_state = &mut group_states[*group_idx];
+                if group_state.indices.is_empty() {
+                    groups_with_rows.push(*group_idx);
                 };
-                v.push(row as u32)
-            })
-            // 1.2
-            .or_insert_with(|| {
-                // We can safely unwrap here as we checked we can create an accumulator before
-                let accumulator_set = create_accumulators(aggr_expr).unwrap();
-                batch_keys.push(key.clone());
-                // Note it would be nice to make this a real error (rather than panic)
-                // but it is better than silently ignoring the issue and getting wrong results
-                create_group_by_values(&group_values, row, &mut group_by_values)
-                    .expect("can not create group by value");
-                (
-                    key.clone(),
-                    (group_by_values.clone(), accumulator_set, vec![row as u32]),
-                )
-            });
+                group_state.indices.push(row as u32);
+            }
+            None => {
+                let accumulator_set = create_accumulators(aggr_expr)?;
+                let group_by_values = group_values
+                    .iter()
+                    .map(|col| ScalarValue::try_from_array(col, row))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let group_idx = group_states.len();
+                group_states.push(GroupState {
+                    group_by_values: group_by_values.into_boxed_slice(),
+                    accumulator_set,
+                    indices: vec![row as u32],
+                });
+                groups_with_rows.push(group_idx);
+
+                // the hash of every entry is stored alongside it so that the
+                // table can grow without recomputing hashes from the group values
+                map.insert(hash, (hash, group_idx), |(hash, _group_idx)| *hash);
+            }
+        };
     }
 
-    // Collect all indices + offsets based on keys in this vec
+    // Collect all indices + offsets based on the groups of this batch
     let mut batch_indices: UInt32Builder = UInt32Builder::new(0);
     let mut offsets = vec![0];
     let mut offset_so_far = 0;
-    for key in batch_keys.iter() {
-        let (_, _, indices) = accumulators.get_mut(key).unwrap();
+    for group_idx in groups_with_rows.iter() {
+        let indices = &accumulators.group_states[*group_idx].indices;
         batch_indices.append_slice(indices)?;
         offset_so_far += indices.len();
         offsets.push(offset_so_far);
@@ -437,18 +508,19 @@ fn group_aggregate_batch(
         })
         .collect();
 
-    // 2.1 for each key in this batch
+    // 2.1 for each group that received rows in this batch
     // 2.2 for each aggregation
-    // 2.3 `slice` from each of its arrays the keys' values
+    // 2.3 `slice` from each of its arrays the group's values
     // 2.4 update / merge the accumulator with the values
     // 2.5 clear indices
-    batch_keys
-        .iter_mut()
+    groups_with_rows
+        .iter()
         .zip(offsets.windows(2))
-        .try_for_each(|(key, offsets)| {
-            let (_, accumulator_set, indices) = accumulators.get_mut(key).unwrap();
+        .try_for_each(|(group_idx, offsets)| {
+            let group_state = &mut accumulators.group_states[*group_idx];
             // 2.2
-            accumulator_set
+            group_state
+                .accumulator_set
                 .iter_mut()
                 .zip(values.iter())
                 .map(|(accumulator, aggr_array)| {
@@ -464,7 +536,9 @@ fn group_aggregate_batch(
                     )
                 })
                 .try_for_each(|(accumulator, values)| match mode {
-                    AggregateMode::Partial => accumulator.update_batch(&values),
+                    AggregateMode::Partial | AggregateMode::SinglePartitioned => {
+                        accumulator.update_batch(&values)
+                    }
                     AggregateMode::FinalPartitioned | AggregateMode::Final => {
                         // note: the aggregation here is over states, not values, thus the merge
                         accumulator.merge_batch(&values)
@@ -472,240 +546,13 @@ fn group_aggregate_batch(
                 })
                 // 2.5
                 .and({
-                    indices.clear();
+                    group_state.indices.clear();
                     Ok(())
                 })
         })?;
     Ok(accumulators)
 }
 
-/// Appends a sequence of [u8] bytes for the value in `col[row]` to
-/// `vec` to be used as a key into the hash map for a dictionary type
-///
-/// Note that ideally, for dictionary encoded columns, we would be
-/// able to simply use the dictionary idicies themselves (no need to
-/// look up values) or possibly simply build the hash table entirely
-/// on the dictionary indexes.
-///
-/// This aproach would likely work (very) well for the common case,
-/// but it also has to to handle the case where the dictionary itself
-/// is not the same across all record batches (and thus indexes in one
-/// record batch may not correspond to the same index in another)
-fn dictionary_create_key_for_col<K: ArrowDictionaryKeyType>(
-    col: &ArrayRef,
-    row: usize,
-    vec: &mut Vec<u8>,
-) -> Result<()> {
-    let dict_col = col.as_any().downcast_ref::<DictionaryArray<K>>().unwrap();
-
-    // look up the index in the values dictionary
-    let keys_col = dict_col.keys();
-    let values_index = keys_col.value(row).to_usize().ok_or_else(|| {
-        DataFusionError::Internal(format!(
-            "Can not convert index to usize in dictionary of type creating group by value {:?}",
-            keys_col.data_type()
-        ))
-    })?;
-
-    create_key_for_col(dict_col.values(), values_index, vec)
-}
-
-/// Appends a sequence of [u8] bytes for the value in `col[row]` to
-/// `vec` to be used as a key into the hash map.
-///
-/// NOTE: This function does not check col.is_valid(). Caller must do so
-fn create_key_for_col(col: &ArrayRef, row: usize, vec: &mut Vec<u8>) -> Result<()> {
-    match col.data_type() {
-        DataType::Boolean => {
-            let array = col.as_any().downcast_ref::<BooleanArray>().unwrap();
-            vec.extend_from_slice(&[array.value(row) as u8]);
-        }
-        DataType::Float32 => {
-            let array = col.as_any().downcast_ref::<Float32Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Float64 => {
-            let array = col.as_any().downcast_ref::<Float64Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::UInt8 => {
-            let array = col.as_any().downcast_ref::<UInt8Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::UInt16 => {
-            let array = col.as_any().downcast_ref::<UInt16Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::UInt32 => {
-            let array = col.as_any().downcast_ref::<UInt32Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::UInt64 => {
-            let array = col.as_any().downcast_ref::<UInt64Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Int8 => {
-            let array = col.as_any().downcast_ref::<Int8Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Int16 => {
-            let array = col.as_any().downcast_ref::<Int16Array>().unwrap();
-            vec.extend(array.value(row).to_le_bytes().iter());
-        }
-        DataType::Int32 => {
-            let array = col.as_any().downcast_ref::<Int32Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Int64 => {
-            let array = col.as_any().downcast_ref::<Int64Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Timestamp(TimeUnit::Millisecond, None) => {
-            let array = col
-                .as_any()
-                .downcast_ref::<TimestampMillisecondArray>()
-                .unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Timestamp(TimeUnit::Microsecond, None) => {
-            let array = col
-                .as_any()
-                .downcast_ref::<TimestampMicrosecondArray>()
-                .unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Timestamp(TimeUnit::Nanosecond, None) => {
-            let array = col
-                .as_any()
-                .downcast_ref::<TimestampNanosecondArray>()
-                .unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Utf8 => {
-            let array = col.as_any().downcast_ref::<StringArray>().unwrap();
-            let value = array.value(row);
-            // store the size
-            vec.extend_from_slice(&value.len().to_le_bytes());
-            // store the string value
-            vec.extend_from_slice(value.as_bytes());
-        }
-        DataType::LargeUtf8 => {
-            let array = col.as_any().downcast_ref::<LargeStringArray>().unwrap();
-            let value = array.value(row);
-            // store the size
-            vec.extend_from_slice(&value.len().to_le_bytes());
-            // store the string value
-            vec.extend_from_slice(value.as_bytes());
-        }
-        DataType::Date32 => {
-            let array = col.as_any().downcast_ref::<Date32Array>().unwrap();
-            vec.extend_from_slice(&array.value(row).to_le_bytes());
-        }
-        DataType::Dictionary(index_type, _) => match **index_type {
-            DataType::Int8 => {
-                dictionary_create_key_for_col::<Int8Type>(col, row, vec)?;
-            }
-            DataType::Int16 => {
-                dictionary_create_key_for_col::<Int16Type>(col, row, vec)?;
-            }
-            DataType::Int32 => {
-                dictionary_create_key_for_col::<Int32Type>(col, row, vec)?;
-            }
-            DataType::Int64 => {
-                dictionary_create_key_for_col::<Int64Type>(col, row, vec)?;
-            }
-            DataType::UInt8 => {
-                dictionary_create_key_for_col::<UInt8Type>(col, row, vec)?;
-            }
-            DataType::UInt16 => {
-                dictionary_create_key_for_col::<UInt16Type>(col, row, vec)?;
-            }
-            DataType::UInt32 => {
-                dictionary_create_key_for_col::<UInt32Type>(col, row, vec)?;
-            }
-            DataType::UInt64 => {
-                dictionary_create_key_for_col::<UInt64Type>(col, row, vec)?;
-            }
-            _ => {
-                return Err(DataFusionError::Internal(format!(
-                "Unsupported GROUP BY type (dictionary index type not supported creating key) {}",
-                col.data_type(),
-            )))
-            }
-        },
-        _ => {
-            // This is internal because we should have caught this before.
-            return Err(DataFusionError::Internal(format!(
-                "Unsupported GROUP BY type creating key {}",
-                col.data_type(),
-            )));
-        }
-    }
-    Ok(())
-}
-
-/// Create a key `Vec<u8>` that is used as key for the hashmap
-///
-/// This looks like
-/// [null_byte][col_value_bytes][null_byte][col_value_bytes]
-///
-/// Note that relatively uncommon patterns (e.g. not 0x00) are chosen
-/// for the null_byte to make debugging easier. The actual values are
-/// arbitrary.
-///
-/// For a NULL value in a column, the key looks like
-/// [0xFE]
-///
-/// For a Non-NULL value in a column, this looks like:
-/// [0xFF][byte representation of column value]
-///
-/// Example of a key with no NULL values:
-/// ```text
-///                        0xFF byte at the start of each column
-///                           signifies the value is non-null
-///                                          │
-///
-///                      ┌ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┴ ─ ─ ─ ─ ─ ─ ─ ┐
-///
-///                      │        string len                 │  0x1234
-/// {                    ▼       (as usize le)      "foo"    ▼(as u16 le)
-///   k1: "foo"        ╔ ═┌──┬──┬──┬──┬──┬──┬──┬──┬──┬──┬──╦ ═┌──┬──┐
-///   k2: 0x1234u16     FF║03│00│00│00│00│00│00│00│"f│"o│"o│FF║34│12│
-/// }                  ╚ ═└──┴──┴──┴──┴──┴──┴──┴──┴──┴──┴──╩ ═└──┴──┘
-///                     0  1  2  3  4  5  6  7  8  9  10 11 12 13 14
-/// ```
-///
-///  Example of a key with NULL values:
-///
-///```text
-///                         0xFE byte at the start of k1 column
-///                     ┌ ─     signifies the value is NULL
-///
-///                     └ ┐
-///                              0x1234
-/// {                     ▼    (as u16 le)
-///   k1: NULL          ╔ ═╔ ═┌──┬──┐
-///   k2: 0x1234u16      FE║FF║12│34│
-/// }                   ╚ ═╚ ═└──┴──┘
-///                       0  1  2  3
-///```
-pub(crate) fn create_key(
-    group_by_keys: &[ArrayRef],
-    row: usize,
-    vec: &mut Vec<u8>,
-) -> Result<()> {
-    vec.clear();
-    for col in group_by_keys {
-        if !col.is_valid(row) {
-            vec.push(0xFE);
-        } else {
-            vec.push(0xFF);
-            create_key_for_col(col, row, vec)?
-        }
-    }
-    Ok(())
-}
-
 async fn compute_grouped_hash_aggregate(
     mode: AggregateMode,
     schema: SchemaRef,
@@ -720,28 +567,65 @@ async fn compute_grouped_hash_aggregate(
         aggregate_expressions(&aggr_expr, &mode, group_expr.len())
             .map_err(DataFusionError::into_arrow_external_error)?;
 
-    // mapping key -> (set of accumulators, indices of the key in the batch)
-    // * the indexes are updated at each row
-    // * the accumulators are updated at the end of each batch
-    // * the indexes are `clear`ed at the end of each batch
-    //let mut accumulators: Accumulators = FnvHashMap::default();
+    // the group keys of all batches are hashed with the same random state
+    let random_state = RandomState::new();
+
+    // the batches are aggregated on the CPU pool, which keeps the tokio worker threads
+    // free for IO, so the expressions are shared with its jobs
+    let group_expr: Arc<[Arc<dyn PhysicalExpr>]> = group_expr.into();
+    let aggr_expr: Arc<[Arc<dyn AggregateExpr>]> = aggr_expr.into();
+    let aggregate_expressions: Arc<[Vec<Arc<dyn PhysicalExpr>>]> =
+        aggregate_expressions.into();
+    // returns a job of the pool that updates the accumulators with a chunk of batches
+    let aggregate_chunk = |batches: Vec<RecordBatch>, accumulators: Accumulators| {
+        let random_state = random_state.clone();
+        let group_expr = group_expr.clone();
+        let aggr_expr = aggr_expr.clone();
+        let aggregate_expressions = aggregate_expressions.clone();
+        move || {
+            batches
+                .into_iter()
+                .try_fold(accumulators, |accumulators, batch| {
+                    group_aggregate_batch(
+                        &mode,
+                        &random_state,
+                        &group_expr,
+                        &aggr_expr,
+                        batch,
+                        accumulators,
+                        &aggregate_expressions,
+                    )
+                })
+                .map_err(DataFusionError::into_arrow_external_error)
+        }
+    };
 
-    // iterate over all input batches and update the accumulators
+    // iterate over all input batches and update the accumulators, a chunk at a time
     let mut accumulators = Accumulators::default();
+    let mut chunk = vec![];
+    let mut chunk_rows = 0;
     while let Some(batch) = input.next().await {
         let batch = batch?;
-        accumulators = group_aggregate_batch(
-            &mode,
-            &group_expr,
-            &aggr_expr,
-            batch,
-            accumulators,
-            &aggregate_expressions,
-        )
-        .map_err(DataFusionError::into_arrow_external_error)?;
+        chunk_rows += batch.num_rows();
+        chunk.push(batch);
+        if chunk_rows >= AGGREGATE_CHUNK_ROWS {
+            let job = aggregate_chunk(std::mem::take(&mut chunk), accumulators);
+            accumulators = cpu_pool::spawn(job)
+                .await
+                .map_err(DataFusionError::into_arrow_external_error)??;
+            chunk_rows = 0;
+        }
     }
 
-    create_batch_from_map(&mode, &accumulators, group_expr.len(), &schema)
+    // the last chunk is aggregated by the same job that produces the batch
+    let num_group_expr = group_expr.len();
+    let job = aggregate_chunk(chunk, accumulators);
+    cpu_pool::spawn(move || {
+        let accumulators = job()?;
+        create_batch_from_map(&mode, &accumulators.group_states, num_group_expr, &schema)
+    })
+    .await
+    .map_err(DataFusionError::into_arrow_external_error)?
 }
 
 impl GroupedHashAggregateStream {
@@ -778,9 +662,35 @@ impl GroupedHashAggregateStream {
     }
 }
 
-type AccumulatorItem = Box<dyn Accumulator>;
-type Accumulators =
-    HashMap<Vec<u8>, (Box<[ScalarValue]>, Vec<AccumulatorItem>, Vec<u32>), RandomState>;
+pub(crate) type AccumulatorItem = Box<dyn Accumulator>;
+
+/// The state that is built for each group in the output
+pub(crate) struct GroupState {
+    /// The actual group by values, one for each group column
+    pub(crate) group_by_values: Box<[ScalarValue]>,
+
+    /// Accumulator state, one for each aggregate
+    pub(crate) accumulator_set: Vec<AccumulatorItem>,
+
+    /// scratch space used to collect the indices of the input rows of this
+    /// group in the current batch
+    /// * updated at each row
+    /// * used as take-indices when the accumulators are updated at the end
+    ///   of each batch
+    /// * `clear`ed at the end of each batch
+    pub(crate) indices: Vec<u32>,
+}
+
+/// The state of all the groups
+#[derive(Default)]
+struct Accumulators {
+    /// Maps the hash of the group values to the index of the group in
+    /// `group_states`. Collisions are resolved by comparing the group values.
+    map: RawTable<(u64, usize)>,
+
+    /// State for each group, in the order in which the groups were first seen
+    group_states: Vec<GroupState>,
+}
 
 impl Stream for GroupedHashAggregateStream {
     type Item = ArrowResult<RecordBatch>;
@@ -827,7 +737,7 @@ impl RecordBatchStream for GroupedHashAggregateStream {
 }
 
 /// Evaluates expressions against a record batch.
-fn evaluate(
+pub(crate) fn evaluate(
     expr: &[Arc<dyn PhysicalExpr>],
     batch: &RecordBatch,
 ) -> Result<Vec<ArrayRef>> {
@@ -838,7 +748,7 @@ fn evaluate(
 }
 
 /// Evaluates expressions against a record batch.
-fn evaluate_many(
+pub(crate) fn evaluate_many(
     expr: &[Vec<Arc<dyn PhysicalExpr>>],
     batch: &RecordBatch,
 ) -> Result<Vec<Vec<ArrayRef>>> {
@@ -867,15 +777,15 @@ fn merge_expressions(
 
 /// returns physical expressions to evaluate against a batch
 /// The expressions are different depending on `mode`:
-/// * Partial: AggregateExpr::expressions
-/// * Final: columns of `AggregateExpr::state_fields()`
-fn aggregate_expressions(
+/// * Partial, SinglePartitioned: AggregateExpr::expressions
+/// * Final, FinalPartitioned: columns of `AggregateExpr::state_fields()`
+pub(crate) fn aggregate_expressions(
     aggr_expr: &[Arc<dyn AggregateExpr>],
     mode: &AggregateMode,
     col_idx_base: usize,
 ) -> Result<Vec<Vec<Arc<dyn PhysicalExpr>>>> {
     match mode {
-        AggregateMode::Partial => {
+        AggregateMode::Partial | AggregateMode::SinglePartitioned => {
             Ok(aggr_expr.iter().map(|agg| agg.expressions()).collect())
         }
         // in this mode, we build the merge expressions of the aggregation
@@ -978,7 +888,9 @@ fn aggregate_batch(
 
             // 1.3
             match mode {
-                AggregateMode::Partial => accum.update_batch(values),
+                AggregateMode::Partial | AggregateMode::SinglePartitioned => {
+                    accum.update_batch(values)
+                }
                 AggregateMode::Final | AggregateMode::FinalPartitioned => {
                     accum.merge_batch(values)
                 }
@@ -1025,16 +937,16 @@ impl RecordBatchStream for HashAggregateStream {
 }
 
 /// Create a RecordBatch with all group keys and accumulator' states or values.
-fn create_batch_from_map(
+pub(crate) fn create_batch_from_map(
     mode: &AggregateMode,
-    accumulators: &Accumulators,
+    group_states: &[GroupState],
     num_group_expr: usize,
     output_schema: &Schema,
 ) -> ArrowResult<RecordBatch> {
-    if accumulators.is_empty() {
+    if group_states.is_empty() {
         return Ok(RecordBatch::new_empty(Arc::new(output_schema.to_owned())));
     }
-    let (_, (_, accs, _)) = accumulators.iter().next().unwrap();
+    let accs = &group_states[0].accumulator_set;
     let mut acc_data_types: Vec<usize> = vec![];
 
     // Calculate number/shape of state arrays
@@ -1047,7 +959,9 @@ fn create_batch_from_map(
                 acc_data_types.push(state.len());
             }
         }
-        AggregateMode::Final | AggregateMode::FinalPartitioned => {
+        AggregateMode::Final
+        | AggregateMode::FinalPartitioned
+        | AggregateMode::SinglePartitioned => {
             acc_data_types = vec![1; accs.len()];
         }
     }
@@ -1055,9 +969,9 @@ fn create_batch_from_map(
     let mut columns = (0..num_group_expr)
         .map(|i| {
             ScalarValue::iter_to_array(
-                accumulators
-                    .into_iter()
-                    .map(|(_, (group_by_values, _, _))| group_by_values[i].clone()),
+                group_states
+                    .iter()
+                    .map(|group_state| group_state.group_by_values[i].clone()),
             )
         })
         .collect::<Result<Vec<_>>>()
@@ -1068,9 +982,9 @@ fn create_batch_from_map(
         for y in 0..state_len {
             match mode {
                 AggregateMode::Partial => {
-                    let res = ScalarValue::iter_to_array(accumulators.into_iter().map(
-                        |(_, (_, accumulator, _))| {
-                            let x = accumulator[x].state().unwrap();
+                    let res = ScalarValue::iter_to_array(group_states.iter().map(
+                        |group_state| {
+                            let x = group_state.accumulator_set[x].state().unwrap();
                             x[y].clone()
                         },
                     ))
@@ -1078,9 +992,11 @@ fn create_batch_from_map(
 
                     columns.push(res);
                 }
-                AggregateMode::Final | AggregateMode::FinalPartitioned => {
-                    let res = ScalarValue::iter_to_array(accumulators.into_iter().map(
-                        |(_, (_, accumulator, _))| accumulator[x].evaluate().unwrap(),
+                AggregateMode::Final
+                | AggregateMode::FinalPartitioned
+                | AggregateMode::SinglePartitioned => {
+                    let res = ScalarValue::iter_to_array(group_states.iter().map(
+                        |group_state| group_state.accumulator_set[x].evaluate().unwrap(),
                     ))
                     .map_err(DataFusionError::into_arrow_external_error)?;
                     columns.push(res);
@@ -1101,7 +1017,7 @@ fn create_batch_from_map(
     RecordBatch::try_new(Arc::new(output_schema.to_owned()), columns)
 }
 
-fn create_accumulators(
+pub(crate) fn create_accumulators(
     aggr_expr: &[Arc<dyn AggregateExpr>],
 ) -> Result<Vec<AccumulatorItem>> {
     aggr_expr
@@ -1130,7 +1046,9 @@ fn finalize_aggregation(
                 .collect::<Result<Vec<_>>>()?;
             Ok(a.iter().flatten().cloned().collect::<Vec<_>>())
         }
-        AggregateMode::Final | AggregateMode::FinalPartitioned => {
+        AggregateMode::Final
+        | AggregateMode::FinalPartitioned
+        | AggregateMode::SinglePartitioned => {
             // merge the state to the final value
             accumulators
                 .iter()
@@ -1140,34 +1058,19 @@ fn finalize_aggregation(
     }
 }
 
-/// Extract the value in `col[row]` as a GroupByScalar
-fn create_group_by_value(col: &ArrayRef, row: usize) -> Result<ScalarValue> {
-    ScalarValue::try_from_array(col, row)
-}
-
-/// Extract the values in `group_by_keys` arrow arrays into the target vector
-/// as GroupByScalar values
-pub(crate) fn create_group_by_values(
-    group_by_keys: &[ArrayRef],
-    row: usize,
-    vec: &mut Box<[ScalarValue]>,
-) -> Result<()> {
-    for (i, col) in group_by_keys.iter().enumerate() {
-        vec[i] = create_group_by_value(col, row)?
-    }
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
 
-    use arrow::array::Float64Array;
+    use arrow::array::{Float64Array, Int64Array, StringArray, UInt32Array};
+    use arrow::datatypes::DataType;
 
     use super::*;
     use crate::physical_plan::expressions::{col, Avg};
     use crate::{assert_batches_sorted_eq, physical_plan::common};
 
     use crate::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+    use crate::physical_plan::memory::MemoryExec;
+    use crate::test::exec::StatisticsExec;
 
     /// some mock data to aggregates
     fn some_data() -> (Arc<Schema>, Vec<RecordBatch>) {
@@ -1367,6 +1270,79 @@ mod tests {
 
     //// Tests ////
 
+    #[tokio::test]
+    async fn aggregate_multiple_keys_with_nulls() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Utf8, true),
+            Field::new("b", DataType::Int64, true),
+            Field::new("c", DataType::Float64, false),
+        ]));
+        let batch1 = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("x"),
+                    Some("x"),
+                    None,
+                    Some("y"),
+                ])),
+                Arc::new(Int64Array::from(vec![Some(1), None, Some(1), Some(1)])),
+                Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0, 4.0])),
+            ],
+        )?;
+        let batch2 = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("x"),
+                    None,
+                    Some("y"),
+                    Some("x"),
+                ])),
+                Arc::new(Int64Array::from(vec![Some(1), Some(1), Some(2), None])),
+                Arc::new(Float64Array::from(vec![3.0, 5.0, 6.0, 4.0])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![batch1, batch2]],
+            schema.clone(),
+            None,
+        )?);
+
+        let groups: Vec<(Arc<dyn PhysicalExpr>, String)> = vec![
+            (col("a", &schema)?, "a".to_string()),
+            (col("b", &schema)?, "b".to_string()),
+        ];
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(Avg::new(
+            col("c", &schema)?,
+            "AVG(c)".to_string(),
+            DataType::Float64,
+        ))];
+        let aggregate = Arc::new(HashAggregateExec::try_new(
+            AggregateMode::SinglePartitioned,
+            groups,
+            aggregates,
+            input,
+            schema,
+        )?);
+
+        let result = common::collect(aggregate.execute(0).await?).await?;
+        let expected = vec![
+            "+---+---+--------+",
+            "| a | b | AVG(c) |",
+            "+---+---+--------+",
+            "|   | 1 | 4      |",
+            "| x |   | 3      |",
+            "| x | 1 | 2      |",
+            "| y | 1 | 4      |",
+            "| y | 2 | 6      |",
+            "+---+---+--------+",
+        ];
+        assert_batches_sorted_eq!(expected, &result);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn aggregate_source_not_yielding() -> Result<()> {
         let input: Arc<dyn ExecutionPlan> =
@@ -1382,4 +1358,77 @@ mod tests {
 
         check_aggregates(input).await
     }
+
+    #[test]
+    fn aggregate_statistics() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::UInt32, false),
+            Field::new("b", DataType::Float64, false),
+        ]);
+        let input: Arc<dyn ExecutionPlan> = Arc::new(StatisticsExec::new(
+            Statistics {
+                num_rows: Some(1000),
+                total_byte_size: None,
+                column_statistics: Some(vec![
+                    ColumnStatistics {
+                        distinct_count: Some(10),
+                        ..Default::default()
+                    },
+                    ColumnStatistics::default(),
+                ]),
+            },
+            schema.clone(),
+        ));
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(Avg::new(
+            col("b", &schema)?,
+            "AVG(b)".to_string(),
+            DataType::Float64,
+        ))];
+
+        // StatisticsExec reports two partitions
+        let groups = vec![(col("a", &schema)?, "a".to_string())];
+        let partial = Arc::new(HashAggregateExec::try_new(
+            AggregateMode::Partial,
+            groups,
+            aggregates.clone(),
+            input.clone(),
+            input.schema(),
+        )?);
+        let stats = partial.statistics();
+        assert_eq!(stats.num_rows, Some(20));
+        let columns = stats.column_statistics.unwrap();
+        assert_eq!(columns.len(), partial.schema().fields().len());
+        assert_eq!(columns[0].distinct_count, Some(10));
+
+        // an input partitioned on the group keys has each group in one partition
+        let single_partitioned = HashAggregateExec::try_new(
+            AggregateMode::SinglePartitioned,
+            vec![(col("a", &schema)?, "a".to_string())],
+            aggregates.clone(),
+            input.clone(),
+            input.schema(),
+        )?;
+        assert_eq!(single_partitioned.statistics().num_rows, Some(10));
+
+        let final_groups = vec![(col("a", &partial.schema())?, "a".to_string())];
+        let merged = HashAggregateExec::try_new(
+            AggregateMode::Final,
+            final_groups,
+            aggregates.clone(),
+            partial.clone(),
+            input.schema(),
+        )?;
+        assert_eq!(merged.statistics().num_rows, Some(10));
+
+        let ungrouped = HashAggregateExec::try_new(
+            AggregateMode::Final,
+            vec![],
+            aggregates,
+            partial,
+            input.schema(),
+        )?;
+        assert_eq!(ungrouped.statistics().num_rows, Some(1));
+
+        Ok(())
+    }
 }
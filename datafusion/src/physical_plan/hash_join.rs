@@ -55,13 +55,14 @@ use super::expressions::Column;
 use super::hash_utils::create_hashes;
 use super::{
     coalesce_partitions::CoalescePartitionsExec,
-    hash_utils::{build_join_schema, check_join_is_valid, JoinOn},
+    hash_utils::{build_join_schema, check_join_is_valid, join_statistics, JoinOn},
 };
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
 use crate::logical_plan::JoinType;
 
 use super::{
-    DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    DisplayFormatType, Distribution, ExecutionPlan, Partitioning, RecordBatchStream,
     SendableRecordBatchStream,
 };
 use crate::physical_plan::coalesce_batches::concat_batches;
@@ -113,6 +114,8 @@ pub struct HashJoinExec {
     mode: PartitionMode,
     /// Metrics
     metrics: Arc<HashJoinMetrics>,
+    /// Whether NULL keys match each other
+    null_equals_null: bool,
 }
 
 /// Metrics for HashJoinExec
@@ -169,10 +172,17 @@ impl HashJoinExec {
         on: JoinOn,
         join_type: &JoinType,
         partition_mode: PartitionMode,
+        null_equals_null: bool,
     ) -> Result<Self> {
         let left_schema = left.schema();
         let right_schema = right.schema();
         check_join_is_valid(&left_schema, &right_schema, &on)?;
+        if *join_type == JoinType::NullAwareAnti && on.len() != 1 {
+            return Err(DataFusionError::Plan(format!(
+                "Null aware anti join requires exactly one join key, found {}",
+                on.len()
+            )));
+        }
 
         let schema = Arc::new(build_join_schema(&left_schema, &right_schema, join_type));
 
@@ -188,6 +198,7 @@ impl HashJoinExec {
             random_state,
             mode: partition_mode,
             metrics: Arc::new(HashJoinMetrics::new()),
+            null_equals_null,
         })
     }
 
@@ -216,6 +227,11 @@ impl HashJoinExec {
         &self.mode
     }
 
+    /// Whether NULL keys match each other
+    pub fn null_equals_null(&self) -> bool {
+        self.null_equals_null
+    }
+
     /// Calculates column indices and left/right placement on input / output schemas and jointype
     fn column_indices_from_schema(&self) -> ArrowResult<Vec<ColumnIndex>> {
         let (primary_is_left, primary_schema, secondary_schema) = match self.join_type {
@@ -223,7 +239,8 @@ impl HashJoinExec {
             | JoinType::Left
             | JoinType::Full
             | JoinType::Semi
-            | JoinType::Anti => (true, self.left.schema(), self.right.schema()),
+            | JoinType::Anti
+            | JoinType::NullAwareAnti => (true, self.left.schema(), self.right.schema()),
             JoinType::Right => (false, self.right.schema(), self.left.schema()),
         };
         let mut column_indices = Vec::with_capacity(self.schema.fields().len());
@@ -274,6 +291,7 @@ impl ExecutionPlan for HashJoinExec {
                 self.on.clone(),
                 &self.join_type,
                 self.mode,
+                self.null_equals_null,
             )?)),
             _ => Err(DataFusionError::Internal(
                 "HashJoinExec wrong number of children".to_string(),
@@ -285,6 +303,16 @@ impl ExecutionPlan for HashJoinExec {
         self.right.output_partitioning()
     }
 
+    fn required_child_distribution(&self) -> Distribution {
+        // whether a row of a null aware anti join is produced depends on the whole
+        // right side, which the stream of a single partition sees
+        if self.join_type == JoinType::NullAwareAnti {
+            Distribution::SinglePartition
+        } else {
+            Distribution::UnspecifiedDistribution
+        }
+    }
+
     async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
         let on_left = self.on.iter().map(|on| on.0.clone()).collect::<Vec<_>>();
         // we only want to compute the build side once for PartitionMode::CollectLeft
@@ -410,9 +438,11 @@ impl ExecutionPlan for HashJoinExec {
         let column_indices = self.column_indices_from_schema()?;
         let num_rows = left_data.1.num_rows();
         let visited_left_side = match self.join_type {
-            JoinType::Left | JoinType::Full | JoinType::Semi | JoinType::Anti => {
-                vec![false; num_rows]
-            }
+            JoinType::Left
+            | JoinType::Full
+            | JoinType::Semi
+            | JoinType::Anti
+            | JoinType::NullAwareAnti => vec![false; num_rows],
             JoinType::Inner | JoinType::Right => vec![],
         };
         Ok(Box::pin(HashJoinStream::new(
@@ -426,6 +456,7 @@ impl ExecutionPlan for HashJoinExec {
             self.random_state.clone(),
             visited_left_side,
             self.metrics.clone(),
+            self.null_equals_null,
         )))
     }
 
@@ -440,7 +471,11 @@ impl ExecutionPlan for HashJoinExec {
                     f,
                     "HashJoinExec: mode={:?}, join_type={:?}, on={:?}",
                     self.mode, self.join_type, self.on
-                )
+                )?;
+                if self.null_equals_null {
+                    write!(f, ", null_equals_null=true")?;
+                }
+                Ok(())
             }
         }
     }
@@ -460,6 +495,15 @@ impl ExecutionPlan for HashJoinExec {
         metrics.insert("outputRows".to_owned(), (*self.metrics.output_rows).clone());
         metrics
     }
+
+    fn statistics(&self) -> Statistics {
+        join_statistics(
+            self.left.statistics(),
+            self.right.statistics(),
+            &self.on,
+            &self.join_type,
+        )
+    }
 }
 
 /// Updates `hash` with new entries from [RecordBatch] evaluated against the expressions `on`,
@@ -523,6 +567,13 @@ struct HashJoinStream {
     is_exhausted: bool,
     /// Metrics
     metrics: Arc<HashJoinMetrics>,
+    /// Whether NULL keys match each other
+    null_equals_null: bool,
+    /// Number of right rows probed so far
+    right_rows: usize,
+    /// Whether a right row with a NULL key was probed, which only matters for
+    /// [JoinType::NullAwareAnti]
+    right_has_null: bool,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -538,6 +589,7 @@ impl HashJoinStream {
         random_state: RandomState,
         visited_left_side: Vec<bool>,
         metrics: Arc<HashJoinMetrics>,
+        null_equals_null: bool,
     ) -> Self {
         HashJoinStream {
             schema,
@@ -551,6 +603,9 @@ impl HashJoinStream {
             visited_left_side,
             is_exhausted: false,
             metrics,
+            null_equals_null,
+            right_rows: 0,
+            right_has_null: false,
         }
     }
 }
@@ -602,12 +657,23 @@ fn build_batch(
     schema: &Schema,
     column_indices: &[ColumnIndex],
     random_state: &RandomState,
+    null_equals_null: bool,
 ) -> ArrowResult<(RecordBatch, UInt64Array)> {
-    let (left_indices, right_indices) =
-        build_join_indexes(left_data, batch, join_type, on_left, on_right, random_state)
-            .unwrap();
+    let (left_indices, right_indices) = build_join_indexes(
+        left_data,
+        batch,
+        join_type,
+        on_left,
+        on_right,
+        random_state,
+        null_equals_null,
+    )
+    .unwrap();
 
-    if matches!(join_type, JoinType::Semi | JoinType::Anti) {
+    if matches!(
+        join_type,
+        JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti
+    ) {
         return Ok((
             RecordBatch::new_empty(Arc::new(schema.clone())),
             left_indices,
@@ -658,6 +724,7 @@ fn build_join_indexes(
     left_on: &[Column],
     right_on: &[Column],
     random_state: &RandomState,
+    null_equals_null: bool,
 ) -> Result<(UInt64Array, UInt32Array)> {
     let keys_values = right_on
         .iter()
@@ -672,7 +739,7 @@ fn build_join_indexes(
     let left = &left_data.0;
 
     match join_type {
-        JoinType::Inner | JoinType::Semi | JoinType::Anti => {
+        JoinType::Inner | JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => {
             // Using a buffer builder to avoid slower normal builder
             let mut left_indices = UInt64BufferBuilder::new(0);
             let mut right_indices = UInt32BufferBuilder::new(0);
@@ -689,7 +756,13 @@ fn build_join_indexes(
                 {
                     for &i in indices {
                         // Check hash collisions
-                        if equal_rows(i as usize, row, &left_join_values, &keys_values)? {
+                        if equal_rows(
+                            i as usize,
+                            row,
+                            &left_join_values,
+                            &keys_values,
+                            null_equals_null,
+                        )? {
                             left_indices.append(i);
                             right_indices.append(row as u32);
                         }
@@ -721,7 +794,13 @@ fn build_join_indexes(
                 {
                     for &i in indices {
                         // Collision check
-                        if equal_rows(i as usize, row, &left_join_values, &keys_values)? {
+                        if equal_rows(
+                            i as usize,
+                            row,
+                            &left_join_values,
+                            &keys_values,
+                            null_equals_null,
+                        )? {
                             left_indices.append_value(i)?;
                             right_indices.append_value(row as u32)?;
                         }
@@ -744,6 +823,7 @@ fn build_join_indexes(
                                 row,
                                 &left_join_values,
                                 &keys_values,
+                                null_equals_null,
                             )? {
                                 left_indices.append_value(i)?;
                                 right_indices.append_value(row as u32)?;
@@ -770,23 +850,27 @@ fn build_join_indexes(
 }
 
 macro_rules! equal_rows_elem {
-    ($array_type:ident, $l: ident, $r: ident, $left: ident, $right: ident) => {{
+    ($array_type:ident, $l: ident, $r: ident, $left: ident, $right: ident,
+     $null_equals_null: ident) => {{
         let left_array = $l.as_any().downcast_ref::<$array_type>().unwrap();
         let right_array = $r.as_any().downcast_ref::<$array_type>().unwrap();
 
         match (left_array.is_null($left), right_array.is_null($right)) {
             (false, false) => left_array.value($left) == right_array.value($right),
+            (true, true) => $null_equals_null,
             _ => false,
         }
     }};
 }
 
 /// Left and right row have equal values
+/// If `null_equals_null` is true, null == null else null != null
 fn equal_rows(
     left: usize,
     right: usize,
     left_arrays: &[ArrayRef],
     right_arrays: &[ArrayRef],
+    null_equals_null: bool,
 ) -> Result<bool> {
     let mut err = None;
     let res = left_arrays
@@ -794,20 +878,42 @@ fn equal_rows(
         .zip(right_arrays)
         .all(|(l, r)| match l.data_type() {
             DataType::Null => true,
-            DataType::Boolean => equal_rows_elem!(BooleanArray, l, r, left, right),
-            DataType::Int8 => equal_rows_elem!(Int8Array, l, r, left, right),
-            DataType::Int16 => equal_rows_elem!(Int16Array, l, r, left, right),
-            DataType::Int32 => equal_rows_elem!(Int32Array, l, r, left, right),
-            DataType::Int64 => equal_rows_elem!(Int64Array, l, r, left, right),
-            DataType::UInt8 => equal_rows_elem!(UInt8Array, l, r, left, right),
-            DataType::UInt16 => equal_rows_elem!(UInt16Array, l, r, left, right),
-            DataType::UInt32 => equal_rows_elem!(UInt32Array, l, r, left, right),
-            DataType::UInt64 => equal_rows_elem!(UInt64Array, l, r, left, right),
+            DataType::Boolean => {
+                equal_rows_elem!(BooleanArray, l, r, left, right, null_equals_null)
+            }
+            DataType::Int8 => {
+                equal_rows_elem!(Int8Array, l, r, left, right, null_equals_null)
+            }
+            DataType::Int16 => {
+                equal_rows_elem!(Int16Array, l, r, left, right, null_equals_null)
+            }
+            DataType::Int32 => {
+                equal_rows_elem!(Int32Array, l, r, left, right, null_equals_null)
+            }
+            DataType::Int64 => {
+                equal_rows_elem!(Int64Array, l, r, left, right, null_equals_null)
+            }
+            DataType::UInt8 => {
+                equal_rows_elem!(UInt8Array, l, r, left, right, null_equals_null)
+            }
+            DataType::UInt16 => {
+                equal_rows_elem!(UInt16Array, l, r, left, right, null_equals_null)
+            }
+            DataType::UInt32 => {
+                equal_rows_elem!(UInt32Array, l, r, left, right, null_equals_null)
+            }
+            DataType::UInt64 => {
+                equal_rows_elem!(UInt64Array, l, r, left, right, null_equals_null)
+            }
             DataType::Timestamp(_, None) => {
-                equal_rows_elem!(Int64Array, l, r, left, right)
+                equal_rows_elem!(Int64Array, l, r, left, right, null_equals_null)
+            }
+            DataType::Utf8 => {
+                equal_rows_elem!(StringArray, l, r, left, right, null_equals_null)
+            }
+            DataType::LargeUtf8 => {
+                equal_rows_elem!(LargeStringArray, l, r, left, right, null_equals_null)
             }
-            DataType::Utf8 => equal_rows_elem!(StringArray, l, r, left, right),
-            DataType::LargeUtf8 => equal_rows_elem!(LargeStringArray, l, r, left, right),
             _ => {
                 // This is internal because we should have caught this before.
                 err = Some(Err(DataFusionError::Internal(
@@ -820,6 +926,50 @@ fn equal_rows(
     err.unwrap_or(Ok(res))
 }
 
+/// Returns whether any of the `on` keys of the batch is NULL
+fn has_null_keys(on: &[Column], batch: &RecordBatch) -> ArrowResult<bool> {
+    for column in on {
+        let array = column
+            .evaluate(batch)
+            .map_err(DataFusionError::into_arrow_external_error)?
+            .into_array(batch.num_rows());
+        if array.null_count() > 0 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Marks as visited the unmatched left rows for which `NOT IN` is NULL rather than true
+// once a non-empty right side was probed: all of them if the right side has a NULL key,
+// otherwise those with a NULL key
+fn exclude_null_aware_anti_rows(
+    visited_left_side: &mut [bool],
+    left_data: &JoinLeftData,
+    on_left: &[Column],
+    right_has_null: bool,
+) -> ArrowResult<()> {
+    if right_has_null {
+        visited_left_side
+            .iter_mut()
+            .for_each(|visited| *visited = true);
+        return Ok(());
+    }
+    let batch = &left_data.1;
+    for column in on_left {
+        let array = column
+            .evaluate(batch)
+            .map_err(DataFusionError::into_arrow_external_error)?
+            .into_array(batch.num_rows());
+        for (row, visited) in visited_left_side.iter_mut().enumerate() {
+            if array.is_null(row) {
+                *visited = true;
+            }
+        }
+    }
+    Ok(())
+}
+
 // Produces a batch for left-side rows that have/have not been matched during the whole join
 fn produce_from_matched(
     visited_left_side: &[bool],
@@ -886,9 +1036,17 @@ impl Stream for HashJoinStream {
                         &self.schema,
                         &self.column_indices,
                         &self.random_state,
+                        self.null_equals_null,
                     );
                     self.metrics.input_batches.add(1);
                     self.metrics.input_rows.add(batch.num_rows());
+                    self.right_rows += batch.num_rows();
+                    if self.join_type == JoinType::NullAwareAnti && !self.right_has_null {
+                        match has_null_keys(&self.on_right, &batch) {
+                            Ok(has_null) => self.right_has_null = has_null,
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
                     if let Ok((ref batch, ref left_side)) = result {
                         self.metrics
                             .join_time
@@ -900,7 +1058,8 @@ impl Stream for HashJoinStream {
                             JoinType::Left
                             | JoinType::Full
                             | JoinType::Semi
-                            | JoinType::Anti => {
+                            | JoinType::Anti
+                            | JoinType::NullAwareAnti => {
                                 left_side.iter().flatten().for_each(|x| {
                                     self.visited_left_side[x as usize] = true;
                                 });
@@ -918,8 +1077,29 @@ impl Stream for HashJoinStream {
                         | JoinType::Full
                         | JoinType::Semi
                         | JoinType::Anti
+                        | JoinType::NullAwareAnti
                             if !self.is_exhausted =>
                         {
+                            if self.join_type == JoinType::NullAwareAnti
+                                && self.right_rows > 0
+                            {
+                                let right_has_null = self.right_has_null;
+                                let HashJoinStream {
+                                    visited_left_side,
+                                    left_data,
+                                    on_left,
+                                    ..
+                                } = &mut *self;
+                                if let Err(e) = exclude_null_aware_anti_rows(
+                                    visited_left_side,
+                                    left_data,
+                                    on_left,
+                                    right_has_null,
+                                ) {
+                                    self.is_exhausted = true;
+                                    return Some(Err(e));
+                                }
+                            }
                             let result = produce_from_matched(
                                 &self.visited_left_side,
                                 &self.schema,
@@ -945,6 +1125,7 @@ impl Stream for HashJoinStream {
                         | JoinType::Full
                         | JoinType::Semi
                         | JoinType::Anti
+                        | JoinType::NullAwareAnti
                         | JoinType::Inner
                         | JoinType::Right => {}
                     }
@@ -966,6 +1147,7 @@ mod tests {
     };
 
     use super::*;
+    use arrow::datatypes::Field;
     use std::sync::Arc;
 
     fn build_table(
@@ -984,7 +1166,14 @@ mod tests {
         on: JoinOn,
         join_type: &JoinType,
     ) -> Result<HashJoinExec> {
-        HashJoinExec::try_new(left, right, on, join_type, PartitionMode::CollectLeft)
+        HashJoinExec::try_new(
+            left,
+            right,
+            on,
+            join_type,
+            PartitionMode::CollectLeft,
+            false,
+        )
     }
 
     async fn join_collect(
@@ -1032,6 +1221,7 @@ mod tests {
             on,
             join_type,
             PartitionMode::Partitioned,
+            false,
         )?;
 
         let columns = columns(&join.schema());
@@ -1089,6 +1279,59 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn join_inner_null_equals_null() -> Result<()> {
+        let build = |a: Vec<Option<i32>>, b: Vec<i32>| {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("a", DataType::Int32, true),
+                Field::new("b", DataType::Int32, false),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from(a)), Arc::new(Int32Array::from(b))],
+            )
+            .unwrap();
+            Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+                as Arc<dyn ExecutionPlan>
+        };
+        let left = build(vec![Some(1), None, Some(3)], vec![10, 20, 30]);
+        let right = build(vec![None, Some(1), Some(2)], vec![40, 50, 60]);
+        let on = vec![(Column::new("a", 0), Column::new("a", 0))];
+
+        let expected_eq = vec![
+            "+---+----+---+----+",
+            "| a | b  | a | b  |",
+            "+---+----+---+----+",
+            "| 1 | 10 | 1 | 50 |",
+            "+---+----+---+----+",
+        ];
+        let expected_null_eq = vec![
+            "+---+----+---+----+",
+            "| a | b  | a | b  |",
+            "+---+----+---+----+",
+            "|   | 20 |   | 40 |",
+            "| 1 | 10 | 1 | 50 |",
+            "+---+----+---+----+",
+        ];
+        for (null_equals_null, expected) in
+            vec![(false, expected_eq), (true, expected_null_eq)]
+        {
+            let join = HashJoinExec::try_new(
+                left.clone(),
+                right.clone(),
+                on.clone(),
+                &JoinType::Inner,
+                PartitionMode::CollectLeft,
+                null_equals_null,
+            )?;
+            let stream = join.execute(0).await?;
+            let batches = common::collect(stream).await?;
+            assert_batches_sorted_eq!(expected, &batches);
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn partitioned_join_inner_one() -> Result<()> {
         let left = build_table(
@@ -1642,6 +1885,54 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn join_null_aware_anti() -> Result<()> {
+        let build = |a: Vec<Option<i32>>| {
+            let schema =
+                Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+            let batch =
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(a))])
+                    .unwrap();
+            Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+                as Arc<dyn ExecutionPlan>
+        };
+        let left = build(vec![Some(1), Some(2), None]);
+        let on = vec![(Column::new("a", 0), Column::new("a", 0))];
+
+        let cases = vec![
+            // NULL NOT IN (1, 3) is NULL
+            (vec![Some(1), Some(3)], vec![Some(2)]),
+            // no value is NOT IN a list with a NULL
+            (vec![Some(1), None], vec![]),
+            // every value is NOT IN an empty list, including NULL
+            (vec![], vec![None, Some(1), Some(2)]),
+        ];
+        for (right, expected) in cases {
+            let join = join(
+                left.clone(),
+                build(right),
+                on.clone(),
+                &JoinType::NullAwareAnti,
+            )?;
+            let stream = join.execute(0).await?;
+            let batches = common::collect(stream).await?;
+            let mut values = batches
+                .iter()
+                .flat_map(|batch| {
+                    let array = batch
+                        .column(0)
+                        .as_any()
+                        .downcast_ref::<Int32Array>()
+                        .unwrap();
+                    array.iter().collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            values.sort_unstable();
+            assert_eq!(values, expected);
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn join_right_one() -> Result<()> {
         let left = build_table(
@@ -1787,6 +2078,7 @@ mod tests {
             &[Column::new("a", 0)],
             &[Column::new("a", 0)],
             &random_state,
+            false,
         )?;
 
         let mut left_ids = UInt64Builder::new(0);
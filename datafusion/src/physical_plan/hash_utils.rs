@@ -17,13 +17,15 @@
 
 //! Functionality used both on logical and physical plans
 
+use crate::datasource::datasource::{ColumnStatistics, Statistics};
 use crate::error::{DataFusionError, Result};
 use ahash::{CallHasher, RandomState};
 use arrow::array::{
     Array, ArrayRef, BooleanArray, Date32Array, Date64Array, DictionaryArray,
     Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
     LargeStringArray, StringArray, TimestampMicrosecondArray, TimestampMillisecondArray,
-    TimestampNanosecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+    TimestampNanosecondArray, TimestampSecondArray, UInt16Array, UInt32Array,
+    UInt64Array, UInt8Array,
 };
 use arrow::datatypes::{
     ArrowDictionaryKeyType, ArrowNativeType, DataType, Field, Int16Type, Int32Type,
@@ -107,11 +109,118 @@ pub fn build_join_schema(left: &Schema, right: &Schema, join_type: &JoinType) ->
             // left then right
             left_fields.chain(right_fields).cloned().collect()
         }
-        JoinType::Semi | JoinType::Anti => left.fields().clone(),
+        JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => {
+            left.fields().clone()
+        }
     };
     Schema::new(fields)
 }
 
+/// Estimates the statistics of an equijoin of `left` and `right` on the columns `on`.
+///
+/// The number of rows of an inner join is estimated as the product of the input rows
+/// divided by the largest distinct count of the join keys, and outer joins produce at
+/// least the rows of their preserved sides.
+pub fn join_statistics(
+    left: Statistics,
+    right: Statistics,
+    on: JoinOnRef,
+    join_type: &JoinType,
+) -> Statistics {
+    let num_rows = match (left.num_rows, right.num_rows) {
+        (Some(l), Some(r)) => {
+            let inner = inner_join_num_rows(&left, &right, on, l, r);
+            Some(match join_type {
+                JoinType::Inner => inner,
+                JoinType::Left => inner.max(l),
+                JoinType::Right => inner.max(r),
+                JoinType::Full => inner.max(l) + inner.max(r) - inner,
+                JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => l,
+            })
+        }
+        (Some(l), None)
+            if matches!(
+                join_type,
+                JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti
+            ) =>
+        {
+            Some(l)
+        }
+        _ => None,
+    };
+
+    let column_statistics = match join_type {
+        JoinType::Semi | JoinType::Anti | JoinType::NullAwareAnti => {
+            left.column_statistics
+        }
+        _ => match (left.column_statistics, right.column_statistics) {
+            (Some(l), Some(r)) => {
+                let (left_outer, right_outer) = match join_type {
+                    JoinType::Left => (false, true),
+                    JoinType::Right => (true, false),
+                    JoinType::Full => (true, true),
+                    _ => (false, false),
+                };
+                // unmatched rows of an outer join add nulls on the other side
+                let with_nulls = |c: ColumnStatistics, outer: bool| {
+                    if outer {
+                        ColumnStatistics {
+                            null_count: None,
+                            ..c
+                        }
+                    } else {
+                        c
+                    }
+                };
+                Some(
+                    l.into_iter()
+                        .map(|c| with_nulls(c, left_outer))
+                        .chain(r.into_iter().map(|c| with_nulls(c, right_outer)))
+                        .collect(),
+                )
+            }
+            _ => None,
+        },
+    };
+
+    Statistics {
+        num_rows,
+        total_byte_size: None,
+        column_statistics,
+    }
+}
+
+fn inner_join_num_rows(
+    left: &Statistics,
+    right: &Statistics,
+    on: JoinOnRef,
+    left_rows: usize,
+    right_rows: usize,
+) -> usize {
+    let distinct_count = |stats: &Statistics, column: &Column| {
+        stats
+            .column_statistics
+            .as_ref()
+            .and_then(|cols| cols.get(column.index()))
+            .and_then(|c| c.distinct_count)
+    };
+    let max_distinct = on
+        .iter()
+        .filter_map(
+            |(l, r)| match (distinct_count(left, l), distinct_count(right, r)) {
+                (Some(l), Some(r)) => Some(l.max(r)),
+                (Some(d), None) | (None, Some(d)) => Some(d),
+                (None, None) => None,
+            },
+        )
+        .max();
+    match max_distinct {
+        Some(d) if d > 0 => left_rows.saturating_mul(right_rows) / d,
+        // without key statistics, assume a foreign key join
+        _ => left_rows.max(right_rows),
+    }
+}
+
 // Combines two hashes into one hash
 #[inline]
 fn combine_hashes(l: u64, r: u64) -> u64 {
@@ -430,6 +539,16 @@ pub fn create_hashes<'a>(
                     multi_col
                 );
             }
+            DataType::Timestamp(TimeUnit::Second, None) => {
+                hash_array_primitive!(
+                    TimestampSecondArray,
+                    col,
+                    i64,
+                    hashes_buffer,
+                    random_state,
+                    multi_col
+                );
+            }
             DataType::Timestamp(TimeUnit::Millisecond, None) => {
                 hash_array_primitive!(
                     TimestampMillisecondArray,
@@ -602,6 +721,57 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn join_statistics_estimates() {
+        let stats = |num_rows, distinct_count| Statistics {
+            num_rows: Some(num_rows),
+            total_byte_size: None,
+            column_statistics: Some(vec![ColumnStatistics {
+                null_count: Some(0),
+                distinct_count,
+                ..Default::default()
+            }]),
+        };
+        let on = vec![(Column::new("a", 0), Column::new("b", 0))];
+
+        let inner = join_statistics(
+            stats(1000, Some(100)),
+            stats(50, Some(10)),
+            &on,
+            &JoinType::Inner,
+        );
+        assert_eq!(inner.num_rows, Some(500));
+        assert_eq!(inner.column_statistics.unwrap().len(), 2);
+
+        let left = join_statistics(
+            stats(100, Some(100)),
+            stats(10, Some(10)),
+            &on,
+            &JoinType::Left,
+        );
+        assert_eq!(left.num_rows, Some(100));
+        let columns = left.column_statistics.unwrap();
+        assert_eq!(columns[0].null_count, Some(0));
+        assert_eq!(columns[1].null_count, None);
+
+        let full = join_statistics(
+            stats(100, Some(200)),
+            stats(10, Some(200)),
+            &on,
+            &JoinType::Full,
+        );
+        assert_eq!(full.num_rows, Some(105));
+
+        let semi = join_statistics(
+            stats(100, None),
+            Statistics::default(),
+            &on,
+            &JoinType::Semi,
+        );
+        assert_eq!(semi.num_rows, Some(100));
+        assert_eq!(semi.column_statistics.unwrap().len(), 1);
+    }
+
     fn check(left: &[Column], right: &[Column], on: &[(Column, Column)]) -> Result<()> {
         let left = left
             .iter()
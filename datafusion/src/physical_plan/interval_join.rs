@@ -0,0 +1,869 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines the interval join plan, which joins rows whose point value falls within
+//! the interval of a row on the other side, e.g. `a.ts BETWEEN b.lo AND b.hi`.
+//!
+//! The interval side is loaded in memory and indexed: the intervals are sorted by
+//! their lower bound and a max-tree is built over their upper bounds, so that the
+//! intervals containing a point are found without comparing against every interval.
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use arrow::array::{Array, ArrayRef, PrimitiveArray, UInt32Array, UInt32Builder};
+use arrow::compute::take;
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType, Date32Type, Date64Type, Float32Type, Float64Type,
+    Int16Type, Int32Type, Int64Type, Int8Type, Schema, SchemaRef, TimeUnit,
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use futures::{Stream, StreamExt, TryStreamExt};
+use log::debug;
+
+use super::coalesce_batches::concat_batches;
+use super::coalesce_partitions::CoalescePartitionsExec;
+use super::hash_utils::check_join_is_valid;
+use super::{
+    DisplayFormatType, ExecutionPlan, Partitioning, PhysicalExpr, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use crate::error::{DataFusionError, Result};
+
+/// The condition of an interval join: a row of the point side matches a row of the
+/// interval side when `lower < point < upper`, where each comparison can be inclusive.
+#[derive(Debug, Clone)]
+pub struct IntervalJoinOn {
+    /// The point, evaluated on the point side
+    pub point: Arc<dyn PhysicalExpr>,
+    /// The lower bound of the interval, evaluated on the interval side
+    pub lower: Arc<dyn PhysicalExpr>,
+    /// Whether a point equal to the lower bound is within the interval
+    pub lower_inclusive: bool,
+    /// The upper bound of the interval, evaluated on the interval side
+    pub upper: Arc<dyn PhysicalExpr>,
+    /// Whether a point equal to the upper bound is within the interval
+    pub upper_inclusive: bool,
+    /// Whether the intervals are on the left input and the points on the right
+    pub intervals_on_left: bool,
+}
+
+/// Data of the interval (build) side
+struct IntervalData {
+    /// All rows of the interval side
+    batch: RecordBatch,
+    /// Index over the intervals of `batch`
+    index: Box<dyn IntervalIndex>,
+}
+
+/// Joins every row of the point side with the rows of the interval side whose
+/// interval contains the point. The interval side is loaded in memory, the point
+/// side is streamed. Rows with a null point or bound never match.
+#[derive(Debug)]
+pub struct IntervalJoinExec {
+    /// left side of the join
+    left: Arc<dyn ExecutionPlan>,
+    /// right side of the join
+    right: Arc<dyn ExecutionPlan>,
+    /// The join condition
+    on: IntervalJoinOn,
+    /// The schema once the join is applied
+    schema: SchemaRef,
+    /// Build-side data
+    build_side: Arc<Mutex<Option<Arc<IntervalData>>>>,
+}
+
+impl Debug for IntervalData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IntervalData")
+            .field("num_rows", &self.batch.num_rows())
+            .finish()
+    }
+}
+
+impl IntervalJoinExec {
+    /// Tries to create a new [IntervalJoinExec].
+    /// # Error
+    /// This function errors when left and right schema's can't be combined, or the
+    /// point and bounds are not of the same supported type
+    pub fn try_new(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: IntervalJoinOn,
+    ) -> Result<Self> {
+        let left_schema = left.schema();
+        let right_schema = right.schema();
+        check_join_is_valid(&left_schema, &right_schema, &[])?;
+
+        let (interval_schema, point_schema) = if on.intervals_on_left {
+            (&left_schema, &right_schema)
+        } else {
+            (&right_schema, &left_schema)
+        };
+        let point_type = on.point.data_type(point_schema)?;
+        let lower_type = on.lower.data_type(interval_schema)?;
+        let upper_type = on.upper.data_type(interval_schema)?;
+        if point_type != lower_type || point_type != upper_type {
+            return Err(DataFusionError::Plan(format!(
+                "Interval join requires the point and bounds to have the same type, got {:?}, {:?} and {:?}",
+                point_type, lower_type, upper_type
+            )));
+        }
+        if !supports_data_type(&point_type) {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Interval join is not supported for type {:?}",
+                point_type
+            )));
+        }
+
+        // left then right
+        let all_columns = left_schema
+            .fields()
+            .iter()
+            .chain(right_schema.fields().iter())
+            .cloned()
+            .collect();
+
+        Ok(IntervalJoinExec {
+            left,
+            right,
+            on,
+            schema: Arc::new(Schema::new(all_columns)),
+            build_side: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// left side of the join
+    pub fn left(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.left
+    }
+
+    /// right side of the join
+    pub fn right(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.right
+    }
+
+    /// The join condition
+    pub fn on(&self) -> &IntervalJoinOn {
+        &self.on
+    }
+
+    fn interval_side(&self) -> &Arc<dyn ExecutionPlan> {
+        if self.on.intervals_on_left {
+            &self.left
+        } else {
+            &self.right
+        }
+    }
+
+    fn point_side(&self) -> &Arc<dyn ExecutionPlan> {
+        if self.on.intervals_on_left {
+            &self.right
+        } else {
+            &self.left
+        }
+    }
+
+    /// Loads and indexes the interval side
+    async fn load_intervals(&self) -> Result<Arc<IntervalData>> {
+        let start = Instant::now();
+        let interval_side = self.interval_side();
+
+        // merge all parts into a single stream
+        let merge = CoalescePartitionsExec::new(interval_side.clone());
+        let stream = merge.execute(0).await?;
+
+        // Load all batches and count the rows
+        let (batches, num_rows) = stream
+            .try_fold((Vec::new(), 0usize), |mut acc, batch| async {
+                acc.1 += batch.num_rows();
+                acc.0.push(batch);
+                Ok(acc)
+            })
+            .await?;
+        let batch = concat_batches(&interval_side.schema(), &batches, num_rows)?;
+
+        let lower = self.on.lower.evaluate(&batch)?.into_array(num_rows);
+        let upper = self.on.upper.evaluate(&batch)?.into_array(num_rows);
+        let index = build_index(&lower, &upper, &self.on)?;
+
+        debug!(
+            "Built interval side of interval join containing {} rows in {} ms",
+            num_rows,
+            start.elapsed().as_millis()
+        );
+
+        Ok(Arc::new(IntervalData { batch, index }))
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for IntervalJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            2 => Ok(Arc::new(IntervalJoinExec::try_new(
+                children[0].clone(),
+                children[1].clone(),
+                self.on.clone(),
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "IntervalJoinExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.point_side().output_partitioning()
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        // we only want to compute the build side once
+        let intervals = {
+            let mut build_side = self.build_side.lock().await;
+            match build_side.as_ref() {
+                Some(intervals) => intervals.clone(),
+                None => {
+                    let intervals = self.load_intervals().await?;
+                    *build_side = Some(intervals.clone());
+                    intervals
+                }
+            }
+        };
+
+        Ok(Box::pin(IntervalJoinStream {
+            schema: self.schema.clone(),
+            on: self.on.clone(),
+            intervals,
+            points: self.point_side().execute(partition).await?,
+            num_input_batches: 0,
+            num_input_rows: 0,
+            num_output_batches: 0,
+            num_output_rows: 0,
+            join_time: 0,
+        }))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let (lower_op, upper_op) = (
+                    if self.on.lower_inclusive { "<=" } else { "<" },
+                    if self.on.upper_inclusive { "<=" } else { "<" },
+                );
+                write!(
+                    f,
+                    "IntervalJoinExec: {:?} {} {:?} {} {:?}, intervals_on_left={}",
+                    self.on.lower,
+                    lower_op,
+                    self.on.point,
+                    upper_op,
+                    self.on.upper,
+                    self.on.intervals_on_left
+                )
+            }
+        }
+    }
+}
+
+/// Returns true if an interval join can index points and bounds of this type
+pub fn supports_data_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Date32
+            | DataType::Date64
+            | DataType::Timestamp(_, _)
+    )
+}
+
+/// Finds the intervals containing a batch of points
+trait IntervalIndex: Send + Sync {
+    /// Returns the matching (interval row, point row) pairs
+    fn probe(&self, points: &ArrayRef) -> Result<(UInt32Array, UInt32Array)>;
+}
+
+fn build_index(
+    lower: &ArrayRef,
+    upper: &ArrayRef,
+    on: &IntervalJoinOn,
+) -> Result<Box<dyn IntervalIndex>> {
+    macro_rules! build {
+        ($ARROW_TYPE:ty) => {
+            Box::new(PrimitiveIntervalIndex::<$ARROW_TYPE>::try_new(
+                lower,
+                upper,
+                on.lower_inclusive,
+                on.upper_inclusive,
+            )?)
+        };
+    }
+
+    Ok(match lower.data_type() {
+        DataType::Int8 => build!(Int8Type),
+        DataType::Int16 => build!(Int16Type),
+        DataType::Int32 => build!(Int32Type),
+        DataType::Int64 => build!(Int64Type),
+        DataType::UInt8 => build!(UInt8Type),
+        DataType::UInt16 => build!(UInt16Type),
+        DataType::UInt32 => build!(UInt32Type),
+        DataType::UInt64 => build!(UInt64Type),
+        DataType::Float32 => build!(Float32Type),
+        DataType::Float64 => build!(Float64Type),
+        DataType::Date32 => build!(Date32Type),
+        DataType::Date64 => build!(Date64Type),
+        DataType::Timestamp(TimeUnit::Second, _) => build!(TimestampSecondType),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            build!(TimestampMillisecondType)
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            build!(TimestampMicrosecondType)
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            build!(TimestampNanosecondType)
+        }
+        other => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Interval join is not supported for type {:?}",
+                other
+            )))
+        }
+    })
+}
+
+fn downcast<'a, T: ArrowPrimitiveType>(
+    array: &'a ArrayRef,
+) -> Result<&'a PrimitiveArray<T>> {
+    array
+        .as_any()
+        .downcast_ref::<PrimitiveArray<T>>()
+        .ok_or_else(|| {
+            DataFusionError::Internal(format!(
+                "Unexpected array of type {:?} in interval join",
+                array.data_type()
+            ))
+        })
+}
+
+/// Interval index over primitive bounds
+struct PrimitiveIntervalIndex<T: ArrowPrimitiveType> {
+    /// Interval rows with non-null and non-NaN bounds, sorted by lower bound
+    rows: Vec<u32>,
+    /// The lower bounds, in the order of `rows`
+    lower: Vec<T::Native>,
+    /// The upper bounds, in the order of `rows`
+    upper: MaxTree<T::Native>,
+    lower_inclusive: bool,
+    upper_inclusive: bool,
+}
+
+impl<T: ArrowPrimitiveType> PrimitiveIntervalIndex<T> {
+    fn try_new(
+        lower: &ArrayRef,
+        upper: &ArrayRef,
+        lower_inclusive: bool,
+        upper_inclusive: bool,
+    ) -> Result<Self> {
+        let lower = downcast::<T>(lower)?;
+        let upper = downcast::<T>(upper)?;
+
+        // intervals with a NaN bound contain no point, and would break the binary
+        // search over the sorted lower bounds
+        let is_comparable = |v: T::Native| v.partial_cmp(&v).is_some();
+        let mut rows = (0..lower.len())
+            .filter(|i| lower.is_valid(*i) && upper.is_valid(*i))
+            .filter(|i| is_comparable(lower.value(*i)) && is_comparable(upper.value(*i)))
+            .map(|i| i as u32)
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            lower
+                .value(*a as usize)
+                .partial_cmp(&lower.value(*b as usize))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let upper_values = rows
+            .iter()
+            .map(|i| upper.value(*i as usize))
+            .collect::<Vec<_>>();
+        Ok(Self {
+            lower: rows.iter().map(|i| lower.value(*i as usize)).collect(),
+            upper: MaxTree::new(&upper_values),
+            rows,
+            lower_inclusive,
+            upper_inclusive,
+        })
+    }
+}
+
+impl<T: ArrowPrimitiveType> IntervalIndex for PrimitiveIntervalIndex<T> {
+    fn probe(&self, points: &ArrayRef) -> Result<(UInt32Array, UInt32Array)> {
+        let points = downcast::<T>(points)?;
+        let mut interval_indices = UInt32Builder::new(points.len());
+        let mut point_indices = UInt32Builder::new(points.len());
+        let mut matches = vec![];
+
+        for i in 0..points.len() {
+            if points.is_null(i) {
+                continue;
+            }
+            let point = points.value(i);
+
+            // only the intervals starting before the point can contain it
+            let end = if self.lower_inclusive {
+                self.lower.partition_point(|lower| *lower <= point)
+            } else {
+                self.lower.partition_point(|lower| *lower < point)
+            };
+
+            matches.clear();
+            if self.upper_inclusive {
+                self.upper.find(end, &|upper| upper >= point, &mut matches);
+            } else {
+                self.upper.find(end, &|upper| upper > point, &mut matches);
+            }
+
+            for position in &matches {
+                interval_indices.append_value(self.rows[*position])?;
+                point_indices.append_value(i as u32)?;
+            }
+        }
+
+        Ok((interval_indices.finish(), point_indices.finish()))
+    }
+}
+
+/// A segment tree that keeps the maximum of every range of values, used to find
+/// the values above a threshold without visiting the ranges below it.
+struct MaxTree<T> {
+    /// Number of values
+    len: usize,
+    /// The maximum of each node's range. The root is at index 1 and the children
+    /// of node `n` are at `2n` and `2n + 1`
+    nodes: Vec<T>,
+}
+
+impl<T: PartialOrd + Copy> MaxTree<T> {
+    fn new(values: &[T]) -> Self {
+        let mut tree = Self {
+            len: values.len(),
+            nodes: vec![],
+        };
+        if let Some(first) = values.first() {
+            tree.nodes = vec![*first; 4 * values.len()];
+            tree.build(1, 0, values.len(), values);
+        }
+        tree
+    }
+
+    fn build(&mut self, node: usize, start: usize, end: usize, values: &[T]) -> T {
+        let max = if end - start == 1 {
+            values[start]
+        } else {
+            let mid = (start + end) / 2;
+            let left = self.build(2 * node, start, mid, values);
+            let right = self.build(2 * node + 1, mid, end, values);
+            // a NaN on the left must not hide a larger value on the right
+            if right > left || left.partial_cmp(&left).is_none() {
+                right
+            } else {
+                left
+            }
+        };
+        self.nodes[node] = max;
+        max
+    }
+
+    /// Appends the positions in `0..end` whose value satisfies `predicate` to `out`.
+    /// `predicate` must be monotonic: if it holds for a value, it holds for any
+    /// larger value.
+    fn find(&self, end: usize, predicate: &dyn Fn(T) -> bool, out: &mut Vec<usize>) {
+        if self.len > 0 {
+            self.find_in(1, 0, self.len, end, predicate, out);
+        }
+    }
+
+    fn find_in(
+        &self,
+        node: usize,
+        start: usize,
+        node_end: usize,
+        end: usize,
+        predicate: &dyn Fn(T) -> bool,
+        out: &mut Vec<usize>,
+    ) {
+        if start >= end || !predicate(self.nodes[node]) {
+            return;
+        }
+        if node_end - start == 1 {
+            out.push(start);
+            return;
+        }
+        let mid = (start + node_end) / 2;
+        self.find_in(2 * node, start, mid, end, predicate, out);
+        self.find_in(2 * node + 1, mid, node_end, end, predicate, out);
+    }
+}
+
+/// A stream that joins the batches of the point side with the indexed intervals
+struct IntervalJoinStream {
+    /// Output schema
+    schema: SchemaRef,
+    /// The join condition
+    on: IntervalJoinOn,
+    /// The indexed interval side
+    intervals: Arc<IntervalData>,
+    /// The point side
+    points: SendableRecordBatchStream,
+    /// number of input batches
+    num_input_batches: usize,
+    /// number of input rows
+    num_input_rows: usize,
+    /// number of batches produced
+    num_output_batches: usize,
+    /// number of rows produced
+    num_output_rows: usize,
+    /// total time for joining point batches to the intervals
+    join_time: usize,
+}
+
+impl IntervalJoinStream {
+    fn join_batch(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let points = self.on.point.evaluate(batch)?.into_array(batch.num_rows());
+        let (interval_indices, point_indices) = self.intervals.index.probe(&points)?;
+
+        let interval_columns = self
+            .intervals
+            .batch
+            .columns()
+            .iter()
+            .map(|c| take(c.as_ref(), &interval_indices, None))
+            .collect::<ArrowResult<Vec<_>>>()?;
+        let point_columns = batch
+            .columns()
+            .iter()
+            .map(|c| take(c.as_ref(), &point_indices, None))
+            .collect::<ArrowResult<Vec<_>>>()?;
+
+        // left then right
+        let columns = if self.on.intervals_on_left {
+            interval_columns.into_iter().chain(point_columns)
+        } else {
+            point_columns.into_iter().chain(interval_columns)
+        };
+        Ok(RecordBatch::try_new(
+            self.schema.clone(),
+            columns.collect(),
+        )?)
+    }
+}
+
+impl RecordBatchStream for IntervalJoinStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for IntervalJoinStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.points
+            .poll_next_unpin(cx)
+            .map(|maybe_batch| match maybe_batch {
+                Some(Ok(batch)) => {
+                    let start = Instant::now();
+                    let result = self
+                        .join_batch(&batch)
+                        .map_err(|e| e.into_arrow_external_error());
+                    self.num_input_batches += 1;
+                    self.num_input_rows += batch.num_rows();
+                    if let Ok(ref batch) = result {
+                        self.join_time += start.elapsed().as_millis() as usize;
+                        self.num_output_batches += 1;
+                        self.num_output_rows += batch.num_rows();
+                    }
+                    Some(result)
+                }
+                other => {
+                    debug!(
+                        "Processed {} point-side input batches containing {} rows and \
+                    produced {} output batches containing {} rows in {} ms",
+                        self.num_input_batches,
+                        self.num_input_rows,
+                        self.num_output_batches,
+                        self.num_output_rows,
+                        self.join_time
+                    );
+                    other
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assert_batches_sorted_eq,
+        physical_plan::{common, expressions::Column, memory::MemoryExec},
+        test::{build_table_i32, columns},
+    };
+    use arrow::array::{Float64Array, Int64Array};
+
+    fn build_table(
+        a: (&str, &Vec<i32>),
+        b: (&str, &Vec<i32>),
+        c: (&str, &Vec<i32>),
+    ) -> Arc<dyn ExecutionPlan> {
+        let batch = build_table_i32(a, b, c);
+        let schema = batch.schema();
+        Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+    }
+
+    fn col(name: &str, index: usize) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Column::new(name, index))
+    }
+
+    async fn join_collect(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: IntervalJoinOn,
+    ) -> Result<(Vec<String>, Vec<RecordBatch>)> {
+        let join = IntervalJoinExec::try_new(left, right, on)?;
+        let columns = columns(&join.schema());
+
+        let stream = join.execute(0).await?;
+        let batches = common::collect(stream).await?;
+
+        Ok((columns, batches))
+    }
+
+    #[tokio::test]
+    async fn join_inclusive_bounds() -> Result<()> {
+        let left = build_table(
+            ("a1", &vec![1, 5, 10, 20]),
+            ("b1", &vec![4, 5, 6, 7]),
+            ("c1", &vec![7, 8, 9, 10]),
+        );
+        let right = build_table(
+            ("lo", &vec![0, 4, 9]),
+            ("hi", &vec![5, 6, 9]),
+            ("c2", &vec![70, 80, 90]),
+        );
+        let on = IntervalJoinOn {
+            point: col("a1", 0),
+            lower: col("lo", 0),
+            lower_inclusive: true,
+            upper: col("hi", 1),
+            upper_inclusive: true,
+            intervals_on_left: false,
+        };
+
+        let (columns, batches) = join_collect(left, right, on).await?;
+        assert_eq!(columns, vec!["a1", "b1", "c1", "lo", "hi", "c2"]);
+
+        let expected = vec![
+            "+----+----+----+----+----+----+",
+            "| a1 | b1 | c1 | lo | hi | c2 |",
+            "+----+----+----+----+----+----+",
+            "| 1  | 4  | 7  | 0  | 5  | 70 |",
+            "| 5  | 5  | 8  | 0  | 5  | 70 |",
+            "| 5  | 5  | 8  | 4  | 6  | 80 |",
+            "+----+----+----+----+----+----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_exclusive_bounds_intervals_on_left() -> Result<()> {
+        let left = build_table(
+            ("lo", &vec![0, 4, 9]),
+            ("hi", &vec![5, 6, 9]),
+            ("c1", &vec![70, 80, 90]),
+        );
+        let right = build_table(
+            ("a2", &vec![0, 5, 9, 4]),
+            ("b2", &vec![4, 5, 6, 7]),
+            ("c2", &vec![7, 8, 9, 10]),
+        );
+        let on = IntervalJoinOn {
+            point: col("a2", 0),
+            lower: col("lo", 0),
+            lower_inclusive: false,
+            upper: col("hi", 1),
+            upper_inclusive: false,
+            intervals_on_left: true,
+        };
+
+        let (columns, batches) = join_collect(left, right, on).await?;
+        assert_eq!(columns, vec!["lo", "hi", "c1", "a2", "b2", "c2"]);
+
+        let expected = vec![
+            "+----+----+----+----+----+----+",
+            "| lo | hi | c1 | a2 | b2 | c2 |",
+            "+----+----+----+----+----+----+",
+            "| 0  | 5  | 70 | 4  | 7  | 10 |",
+            "| 4  | 6  | 80 | 5  | 5  | 8  |",
+            "+----+----+----+----+----+----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        Ok(())
+    }
+
+    #[test]
+    fn index_matches_nested_loop() -> Result<()> {
+        let lower: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(3),
+            Some(-2),
+            None,
+            Some(7),
+            Some(3),
+            Some(0),
+            Some(10),
+            Some(1),
+        ]));
+        let upper: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(8),
+            Some(1),
+            Some(4),
+            Some(7),
+            Some(3),
+            None,
+            Some(12),
+            Some(9),
+        ]));
+        let points: ArrayRef = Arc::new(Int64Array::from(
+            (-3..14).map(Some).chain(vec![None]).collect::<Vec<_>>(),
+        ));
+
+        for (lower_inclusive, upper_inclusive) in
+            vec![(true, true), (true, false), (false, true), (false, false)]
+        {
+            let index = PrimitiveIntervalIndex::<Int64Type>::try_new(
+                &lower,
+                &upper,
+                lower_inclusive,
+                upper_inclusive,
+            )?;
+            let (intervals, matched_points) = index.probe(&points)?;
+            let mut actual = intervals
+                .values()
+                .iter()
+                .zip(matched_points.values())
+                .map(|(i, p)| (*i, *p))
+                .collect::<Vec<_>>();
+            actual.sort_unstable();
+
+            let lower = downcast::<Int64Type>(&lower)?;
+            let upper = downcast::<Int64Type>(&upper)?;
+            let points = downcast::<Int64Type>(&points)?;
+            let mut expected = vec![];
+            for i in 0..lower.len() {
+                for p in 0..points.len() {
+                    if lower.is_null(i) || upper.is_null(i) || points.is_null(p) {
+                        continue;
+                    }
+                    let (lo, hi, point) =
+                        (lower.value(i), upper.value(i), points.value(p));
+                    let above = if lower_inclusive {
+                        lo <= point
+                    } else {
+                        lo < point
+                    };
+                    let below = if upper_inclusive {
+                        point <= hi
+                    } else {
+                        point < hi
+                    };
+                    if above && below {
+                        expected.push((i as u32, p as u32));
+                    }
+                }
+            }
+            expected.sort_unstable();
+
+            assert_eq!(actual, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn index_skips_nan_bounds() -> Result<()> {
+        let lower: ArrayRef =
+            Arc::new(Float64Array::from(vec![2.0, f64::NAN, 0.0, 5.0, 1.0]));
+        let upper: ArrayRef =
+            Arc::new(Float64Array::from(vec![3.0, 10.0, f64::NAN, 6.0, 4.0]));
+        let points: ArrayRef =
+            Arc::new(Float64Array::from(vec![0.5, 2.5, 5.5, f64::NAN]));
+        let index =
+            PrimitiveIntervalIndex::<Float64Type>::try_new(&lower, &upper, true, true)?;
+        let (intervals, matched_points) = index.probe(&points)?;
+        let mut actual = intervals
+            .values()
+            .iter()
+            .zip(matched_points.values())
+            .map(|(i, p)| (*i, *p))
+            .collect::<Vec<_>>();
+        actual.sort_unstable();
+        assert_eq!(actual, vec![(0, 1), (3, 2), (4, 1)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,868 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Execution plan for reading Arrow IPC files, also known as Feather (version 2) files
+
+use std::any::Any;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow::array::ArrayRef;
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::error::Result as ArrowResult;
+use arrow::ipc;
+use arrow::ipc::reader::{read_dictionary, read_record_batch, FileReader};
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use futures::Stream;
+use memmap2::Mmap;
+
+use super::{
+    common, common::FileListOptions, DisplayFormatType, ExecutionPlan, Partitioning,
+    RecordBatchStream, SendableRecordBatchStream,
+};
+use crate::datasource::datasource::{ColumnStatistics, Statistics};
+use crate::error::{DataFusionError, Result};
+
+/// Magic bytes at the start and the end of an Arrow IPC file
+const ARROW_MAGIC: [u8; 6] = *b"ARROW1";
+
+/// Marker that precedes the length of an encapsulated IPC message
+const CONTINUATION_MARKER: [u8; 4] = [0xff; 4];
+
+/// Arrow IPC read options
+#[derive(Clone)]
+pub struct ArrowReadOptions<'a> {
+    /// File extension; only files with this extension are selected for data input.
+    /// Defaults to ".arrow".
+    pub file_extension: &'a str,
+
+    /// Whether to memory map the files rather than reading them through buffered
+    /// file reads. Defaults to false.
+    ///
+    /// The record batches of mapped files are decoded straight from the page cache,
+    /// rather than being read into an intermediate buffer first, so the data is only
+    /// copied once, into the buffers of the arrays. The files must not be modified or
+    /// truncated while they are mapped.
+    pub memory_map: bool,
+
+    /// How the files of a directory or glob pattern are listed
+    pub file_list_options: FileListOptions,
+}
+
+impl<'a> Default for ArrowReadOptions<'a> {
+    fn default() -> Self {
+        Self {
+            file_extension: ".arrow",
+            memory_map: false,
+            file_list_options: FileListOptions::default(),
+        }
+    }
+}
+
+impl<'a> ArrowReadOptions<'a> {
+    /// Create Arrow IPC read options with default values
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Specify the file extension of the files to read
+    pub fn file_extension(mut self, file_extension: &'a str) -> Self {
+        self.file_extension = file_extension;
+        self
+    }
+
+    /// Specify whether to memory map the files
+    pub fn memory_map(mut self, memory_map: bool) -> Self {
+        self.memory_map = memory_map;
+        self
+    }
+
+    /// Specify how the files of a directory or glob pattern are listed
+    pub fn file_list_options(mut self, file_list_options: FileListOptions) -> Self {
+        self.file_list_options = file_list_options;
+        self
+    }
+}
+
+/// Iterator over the record batches of an Arrow IPC file
+type BatchReader = Box<dyn Iterator<Item = ArrowResult<RecordBatch>> + Send>;
+
+/// Execution plan for scanning one or more Arrow IPC files, one partition per file
+#[derive(Debug, Clone)]
+pub struct ArrowExec {
+    filenames: Vec<String>,
+    schema: SchemaRef,
+    projection: Option<Vec<usize>>,
+    projected_schema: SchemaRef,
+    statistics: Statistics,
+    memory_map: bool,
+    limit: Option<usize>,
+}
+
+impl ArrowExec {
+    /// Create a new execution plan for reading the Arrow IPC files at a path. The
+    /// schema and statistics are read from the file footers.
+    pub fn try_from_path(
+        path: &str,
+        options: ArrowReadOptions,
+        projection: Option<Vec<usize>>,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        let filenames = common::build_file_list_with_options(
+            path,
+            &[options.file_extension],
+            &options.file_list_options,
+        )?;
+        if filenames.is_empty() {
+            return Err(DataFusionError::Plan(format!(
+                "No files found at {path} with file extension {file_extension}",
+                path = path,
+                file_extension = options.file_extension
+            )));
+        }
+        let (schema, statistics) = Self::try_read_metadata(&filenames)?;
+        Ok(Self::new(
+            filenames,
+            schema,
+            projection,
+            statistics,
+            options.memory_map,
+            limit,
+        ))
+    }
+
+    /// Create a new execution plan for reading Arrow IPC files with a known schema
+    /// and statistics
+    pub fn new(
+        filenames: Vec<String>,
+        schema: SchemaRef,
+        projection: Option<Vec<usize>>,
+        statistics: Statistics,
+        memory_map: bool,
+        limit: Option<usize>,
+    ) -> Self {
+        let projected_schema = match &projection {
+            None => schema.clone(),
+            Some(p) => Arc::new(Schema::new(
+                p.iter().map(|i| schema.field(*i).clone()).collect(),
+            )),
+        };
+
+        Self {
+            filenames,
+            schema,
+            projection,
+            projected_schema,
+            statistics,
+            memory_map,
+            limit,
+        }
+    }
+
+    /// The individual files read by this plan
+    pub fn filenames(&self) -> &[String] {
+        &self.filenames
+    }
+
+    /// Get the schema of the Arrow IPC files
+    pub fn file_schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Optional projection for which columns to load
+    pub fn projection(&self) -> Option<&Vec<usize>> {
+        self.projection.as_ref()
+    }
+
+    /// Whether the files are memory mapped
+    pub fn memory_map(&self) -> bool {
+        self.memory_map
+    }
+
+    /// Limit
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Read the schema and the statistics of a set of Arrow IPC files from their
+    /// footers, without reading the data. All files must have the same schema.
+    pub fn try_read_metadata(filenames: &[String]) -> Result<(SchemaRef, Statistics)> {
+        let mut schema: Option<SchemaRef> = None;
+        let mut statistics = Statistics::default();
+
+        for filename in filenames {
+            let mut reader = BufReader::new(File::open(filename)?);
+            let (file_schema, file_statistics) = read_file_metadata(&mut reader)
+                .map_err(|e| {
+                    DataFusionError::Execution(format!(
+                        "Error reading Arrow IPC file {}: {}",
+                        filename, e
+                    ))
+                })?;
+
+            match &schema {
+                Some(schema) if schema.fields() != file_schema.fields() => {
+                    return Err(DataFusionError::Plan(format!(
+                        "Arrow IPC file {} has a different schema than the other files",
+                        filename
+                    )));
+                }
+                Some(_) => statistics = merge_statistics(statistics, file_statistics),
+                None => {
+                    schema = Some(Arc::new(file_schema));
+                    statistics = file_statistics;
+                }
+            }
+        }
+
+        let schema = schema.ok_or_else(|| {
+            DataFusionError::Plan(
+                "No Arrow IPC files found to read the schema from".to_string(),
+            )
+        })?;
+        Ok((schema, statistics))
+    }
+}
+
+/// Read the number of rows of each record batch of an Arrow IPC file from the record
+/// batch headers, without reading the record batch bodies
+pub fn read_batch_lengths<R: Read + Seek>(reader: &mut R) -> Result<Vec<usize>> {
+    let footer_data = read_footer_data(reader)?;
+    let footer =
+        ipc::root_as_footer(&footer_data).map_err(|e| invalid_flatbuffer("footer", e))?;
+    footer
+        .recordBatches()
+        .unwrap_or_default()
+        .iter()
+        .map(|block| {
+            let meta_data = read_batch_header_data(reader, block)?;
+            let batch = batch_header(&meta_data)?;
+            checked_len(batch.length(), "record batch length")
+        })
+        .collect()
+}
+
+/// Read the schema from the footer of an Arrow IPC file, together with the number of
+/// rows, the size of the record batch bodies and the null count of each column from
+/// the record batch headers
+fn read_file_metadata<R: Read + Seek>(reader: &mut R) -> Result<(Schema, Statistics)> {
+    let footer_data = read_footer_data(reader)?;
+    let footer =
+        ipc::root_as_footer(&footer_data).map_err(|e| invalid_flatbuffer("footer", e))?;
+    let schema = footer.schema().ok_or_else(|| {
+        DataFusionError::Execution("Unable to get schema from Arrow file".to_string())
+    })?;
+    let schema = ipc::convert::fb_to_schema(schema);
+
+    // the field nodes of a record batch are the flattened columns in depth-first
+    // order, so the node of each top-level column is preceded by the nodes of the
+    // children of the columns before it
+    let node_counts: Vec<usize> = schema
+        .fields()
+        .iter()
+        .map(|field| field_node_count(field.data_type()))
+        .collect();
+    let mut num_rows = 0;
+    let mut total_byte_size = 0;
+    let mut null_counts = Some(vec![0; schema.fields().len()]);
+
+    for block in footer.recordBatches().unwrap_or_default() {
+        let meta_data = read_batch_header_data(reader, block)?;
+        let batch = batch_header(&meta_data)?;
+        num_rows += checked_len(batch.length(), "record batch length")?;
+        total_byte_size += checked_len(block.bodyLength(), "record batch body length")?;
+
+        let nodes = batch.nodes().unwrap_or_default();
+        if nodes.len() != node_counts.iter().sum::<usize>() {
+            // not a layout we know how to map to the top-level columns
+            null_counts = None;
+        }
+        if let Some(null_counts) = null_counts.as_mut() {
+            let mut node = 0;
+            for (null_count, node_count) in null_counts.iter_mut().zip(&node_counts) {
+                *null_count += checked_len(nodes[node].null_count(), "null count")?;
+                node += node_count;
+            }
+        }
+    }
+
+    let column_statistics = null_counts.map(|null_counts| {
+        null_counts
+            .into_iter()
+            .map(|null_count| ColumnStatistics {
+                null_count: Some(null_count),
+                ..Default::default()
+            })
+            .collect()
+    });
+    let statistics = Statistics {
+        num_rows: Some(num_rows),
+        total_byte_size: Some(total_byte_size),
+        column_statistics,
+    };
+    Ok((schema, statistics))
+}
+
+/// Read the flatbuffer of the footer of an Arrow IPC file
+fn read_footer_data<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut magic = [0; 6];
+    reader.seek(SeekFrom::End(-6))?;
+    reader.read_exact(&mut magic)?;
+    if magic != ARROW_MAGIC {
+        return Err(DataFusionError::Execution(
+            "Arrow file does not contain correct footer".to_string(),
+        ));
+    }
+
+    let mut footer_len = [0; 4];
+    let footer_end = reader.seek(SeekFrom::End(-10))?;
+    reader.read_exact(&mut footer_len)?;
+    let footer_len = checked_len(i32::from_le_bytes(footer_len), "footer length")?;
+    let footer_start = footer_end
+        .checked_sub(footer_len as u64)
+        .ok_or_else(|| truncated("footer"))?;
+    let mut footer_data = vec![0; footer_len];
+    reader.seek(SeekFrom::Start(footer_start))?;
+    reader.read_exact(&mut footer_data)?;
+    Ok(footer_data)
+}
+
+/// Read the flatbuffer of the message header of the record batch at `block`
+fn read_batch_header_data<R: Read + Seek>(
+    reader: &mut R,
+    block: &ipc::Block,
+) -> Result<Vec<u8>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let offset = checked_len(block.offset(), "record batch offset")?;
+    reader.seek(SeekFrom::Start(offset as u64))?;
+    let mut meta_len = [0; 4];
+    reader.read_exact(&mut meta_len)?;
+    if meta_len == CONTINUATION_MARKER {
+        reader.read_exact(&mut meta_len)?;
+    }
+    let meta_len = checked_len(i32::from_le_bytes(meta_len), "message length")?;
+    // the length is checked before it is allocated
+    if reader.seek(SeekFrom::Current(0))? + meta_len as u64 > file_len {
+        return Err(truncated("message"));
+    }
+    let mut meta_data = vec![0; meta_len];
+    reader.read_exact(&mut meta_data)?;
+    Ok(meta_data)
+}
+
+/// Returns the message header and the body of the message at `block` of the data of
+/// a memory mapped Arrow IPC file
+fn message_at<'a>(
+    data: &'a [u8],
+    block: &ipc::Block,
+) -> Result<(ipc::Message<'a>, &'a [u8])> {
+    let slice = move |start: usize, len: usize, what: &str| {
+        start
+            .checked_add(len)
+            .and_then(|end| data.get(start..end))
+            .ok_or_else(|| truncated(what))
+    };
+
+    let offset = checked_len(block.offset(), "message offset")?;
+    let mut meta_start = offset + 4;
+    let mut meta_len = slice(offset, 4, "message")?;
+    if meta_len == CONTINUATION_MARKER {
+        meta_len = slice(meta_start, 4, "message")?;
+        meta_start += 4;
+    }
+    let meta_len =
+        i32::from_le_bytes([meta_len[0], meta_len[1], meta_len[2], meta_len[3]]);
+    let meta_data = slice(
+        meta_start,
+        checked_len(meta_len, "message length")?,
+        "message",
+    )?;
+    let message =
+        ipc::root_as_message(meta_data).map_err(|e| invalid_flatbuffer("message", e))?;
+
+    let body_start = offset
+        .checked_add(checked_len(
+            block.metaDataLength(),
+            "message header length",
+        )?)
+        .ok_or_else(|| truncated("message"))?;
+    let body_len = checked_len(block.bodyLength(), "message body length")?;
+    Ok((message, slice(body_start, body_len, "message body")?))
+}
+
+/// Converts a length or offset read from an Arrow IPC file to a `usize`, which fails
+/// for the negative values of corrupt files
+fn checked_len<T>(value: T, what: &str) -> Result<usize>
+where
+    T: Copy + std::fmt::Display,
+    usize: TryFrom<T>,
+{
+    usize::try_from(value).map_err(|_| {
+        DataFusionError::Execution(format!("Invalid {} in Arrow file: {}", what, value))
+    })
+}
+
+fn truncated(what: &str) -> DataFusionError {
+    DataFusionError::Execution(format!("Arrow file is truncated within a {}", what))
+}
+
+/// Parse the record batch header out of the flatbuffer of a message header
+fn batch_header(meta_data: &[u8]) -> Result<ipc::RecordBatch> {
+    let message =
+        ipc::root_as_message(meta_data).map_err(|e| invalid_flatbuffer("message", e))?;
+    message.header_as_record_batch().ok_or_else(|| {
+        DataFusionError::Execution(
+            "Unable to read record batch header from Arrow file".to_string(),
+        )
+    })
+}
+
+/// The number of field nodes that a column of the given type is flattened into
+fn field_node_count(data_type: &DataType) -> usize {
+    match data_type {
+        DataType::List(field)
+        | DataType::LargeList(field)
+        | DataType::FixedSizeList(field, _) => 1 + field_node_count(field.data_type()),
+        DataType::Struct(fields) | DataType::Union(fields) => {
+            1 + fields
+                .iter()
+                .map(|field| field_node_count(field.data_type()))
+                .sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+fn invalid_flatbuffer(what: &str, e: impl std::fmt::Debug) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "Unable to get root as {} from Arrow file: {:?}",
+        what, e
+    ))
+}
+
+/// Sum the statistics of two sets of files with the same schema
+fn merge_statistics(left: Statistics, right: Statistics) -> Statistics {
+    let add = |l: Option<usize>, r: Option<usize>| Some(l? + r?);
+    let column_statistics = match (left.column_statistics, right.column_statistics) {
+        (Some(l), Some(r)) => Some(
+            l.into_iter()
+                .zip(r)
+                .map(|(l, r)| ColumnStatistics {
+                    null_count: add(l.null_count, r.null_count),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        _ => None,
+    };
+    Statistics {
+        num_rows: add(left.num_rows, right.num_rows),
+        total_byte_size: add(left.total_byte_size, right.total_byte_size),
+        column_statistics,
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for ArrowExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.projected_schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.filenames.len())
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        // this is a leaf node and has no children
+        vec![]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if children.is_empty() {
+            Ok(Arc::new(self.clone()))
+        } else {
+            Err(DataFusionError::Internal(format!(
+                "Children cannot be replaced in {:?}",
+                self
+            )))
+        }
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        let filename = &self.filenames[partition];
+        let file = File::open(filename)?;
+        let (schema, reader): (SchemaRef, BatchReader) = if self.memory_map {
+            // Safety: the mapping is only sound as long as the file is not modified
+            // or truncated while it is being read, which is documented as a
+            // requirement of `ArrowReadOptions::memory_map`
+            let mmap = unsafe { Mmap::map(&file)? };
+            let reader = MmapFileReader::try_new(mmap)?;
+            (reader.schema.clone(), Box::new(reader))
+        } else {
+            let reader = FileReader::try_new(BufReader::new(file))?;
+            (reader.schema(), Box::new(reader))
+        };
+
+        if schema.fields() != self.schema.fields() {
+            return Err(DataFusionError::Execution(format!(
+                "Arrow IPC file {} has a different schema than the table",
+                filename
+            )));
+        }
+
+        Ok(Box::pin(ArrowStream {
+            reader,
+            projection: self.projection.clone(),
+            schema: self.projected_schema.clone(),
+            remain: self.limit,
+        }))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(
+                    f,
+                    "ArrowExec: memory_map={}, limit={:?}, files=[{}]",
+                    self.memory_map,
+                    self.limit,
+                    self.filenames.join(", ")
+                )
+            }
+        }
+    }
+
+    /// The statistics of the projected columns, summed over all files
+    fn statistics(&self) -> Statistics {
+        let column_statistics = match &self.projection {
+            Some(projection) => self.statistics.column_statistics.as_ref().map(|cols| {
+                projection
+                    .iter()
+                    .map(|i| cols.get(*i).cloned().unwrap_or_default())
+                    .collect()
+            }),
+            None => self.statistics.column_statistics.clone(),
+        };
+        let num_rows = match (self.statistics.num_rows, self.limit) {
+            (Some(n), Some(limit)) => Some(n.min(limit)),
+            (n, _) => n,
+        };
+        Statistics {
+            num_rows,
+            total_byte_size: self.statistics.total_byte_size,
+            column_statistics,
+        }
+    }
+}
+
+/// Reader of the record batches of a memory mapped Arrow IPC file, which decodes them
+/// straight from the mapped data rather than reading them into a buffer first
+struct MmapFileReader {
+    mmap: Mmap,
+    schema: SchemaRef,
+    /// The locations of the record batches
+    blocks: Vec<ipc::Block>,
+    /// The dictionaries of the dictionary encoded columns
+    dictionaries: Vec<Option<ArrayRef>>,
+    /// The index of the next record batch
+    index: usize,
+}
+
+impl MmapFileReader {
+    fn try_new(mmap: Mmap) -> Result<Self> {
+        let footer_data = read_footer_data(&mut Cursor::new(&mmap[..]))?;
+        let footer = ipc::root_as_footer(&footer_data)
+            .map_err(|e| invalid_flatbuffer("footer", e))?;
+        let schema = footer.schema().ok_or_else(|| {
+            DataFusionError::Execution("Unable to get schema from Arrow file".to_string())
+        })?;
+        let schema = Arc::new(ipc::convert::fb_to_schema(schema));
+
+        let mut dictionaries = vec![None; schema.fields().len()];
+        for block in footer.dictionaries().unwrap_or_default() {
+            let (message, body) = message_at(&mmap, block)?;
+            let batch = message.header_as_dictionary_batch().ok_or_else(|| {
+                DataFusionError::Execution(
+                    "Unable to read dictionary batch header from Arrow file".to_string(),
+                )
+            })?;
+            read_dictionary(body, batch, &schema, &mut dictionaries)?;
+        }
+        let blocks = footer
+            .recordBatches()
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .collect();
+
+        Ok(Self {
+            mmap,
+            schema,
+            blocks,
+            dictionaries,
+            index: 0,
+        })
+    }
+
+    fn read_batch(&self, block: &ipc::Block) -> Result<RecordBatch> {
+        let (message, body) = message_at(&self.mmap, block)?;
+        let batch = message.header_as_record_batch().ok_or_else(|| {
+            DataFusionError::Execution(
+                "Unable to read record batch header from Arrow file".to_string(),
+            )
+        })?;
+        Ok(read_record_batch(
+            body,
+            batch,
+            self.schema.clone(),
+            &self.dictionaries,
+        )?)
+    }
+}
+
+impl Iterator for MmapFileReader {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = *self.blocks.get(self.index)?;
+        self.index += 1;
+        Some(
+            self.read_batch(&block)
+                .map_err(DataFusionError::into_arrow_external_error),
+        )
+    }
+}
+
+/// Stream of the projected record batches of an Arrow IPC file
+struct ArrowStream {
+    reader: BatchReader,
+    projection: Option<Vec<usize>>,
+    schema: SchemaRef,
+    remain: Option<usize>,
+}
+
+impl ArrowStream {
+    fn project(&self, batch: RecordBatch) -> ArrowResult<RecordBatch> {
+        match &self.projection {
+            Some(projection) => RecordBatch::try_new(
+                self.schema.clone(),
+                projection
+                    .iter()
+                    .map(|i| batch.column(*i).clone())
+                    .collect(),
+            ),
+            None => Ok(batch),
+        }
+    }
+}
+
+impl Stream for ArrowStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.remain == Some(0) {
+            return Poll::Ready(None);
+        }
+
+        let batch = match self.reader.next() {
+            Some(Ok(batch)) => batch,
+            other => return Poll::Ready(other),
+        };
+        let batch = match self.remain.as_mut() {
+            Some(remain) if *remain < batch.num_rows() => {
+                let len = *remain;
+                *remain = 0;
+                RecordBatch::try_new(
+                    batch.schema(),
+                    batch
+                        .columns()
+                        .iter()
+                        .map(|column| column.slice(0, len))
+                        .collect(),
+                )
+            }
+            Some(remain) => {
+                *remain -= batch.num_rows();
+                Ok(batch)
+            }
+            None => Ok(batch),
+        };
+        Poll::Ready(Some(batch.and_then(|batch| self.project(batch))))
+    }
+}
+
+impl RecordBatchStream for ArrowStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::Field;
+    use arrow::ipc::writer::FileWriter;
+    use futures::StreamExt;
+    use tempfile::TempDir;
+
+    /// Write an Arrow IPC file with two record batches of two rows each
+    fn write_file(dir: &TempDir, name: &str) -> Result<String> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, false),
+        ]));
+        let path = dir.path().join(name);
+        let mut writer = FileWriter::try_new(File::create(&path)?, &schema)?;
+        for (a, b) in &[
+            ([Some(1), None], ["x", "y"]),
+            ([Some(3), Some(4)], ["z", "w"]),
+        ] {
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from(a.to_vec())),
+                    Arc::new(StringArray::from(b.to_vec())),
+                ],
+            )?;
+            writer.write(&batch)?;
+        }
+        writer.finish()?;
+        Ok(path.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn read_projection_and_limit() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = write_file(&dir, "data.arrow")?;
+        for memory_map in &[false, true] {
+            let options = ArrowReadOptions::new().memory_map(*memory_map);
+            let exec = ArrowExec::try_from_path(&path, options, Some(vec![1]), Some(3))?;
+            assert_eq!(exec.output_partitioning().partition_count(), 1);
+            assert_eq!(exec.schema().fields().len(), 1);
+            assert_eq!(exec.schema().field(0).name(), "b");
+
+            let mut stream = exec.execute(0).await?;
+            let mut values = vec![];
+            while let Some(batch) = stream.next().await {
+                let batch = batch?;
+                let array = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                values.extend((0..array.len()).map(|i| array.value(i).to_string()));
+            }
+            assert_eq!(values, vec!["x", "y", "z"]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_statistics_from_footer() -> Result<()> {
+        let dir = TempDir::new()?;
+        write_file(&dir, "1.arrow")?;
+        write_file(&dir, "2.arrow")?;
+        let path = dir.path().to_str().unwrap();
+
+        let exec = ArrowExec::try_from_path(path, ArrowReadOptions::new(), None, None)?;
+        assert_eq!(exec.output_partitioning().partition_count(), 2);
+        let statistics = exec.statistics();
+        assert_eq!(statistics.num_rows, Some(8));
+        assert!(statistics.total_byte_size.unwrap() > 0);
+        let null_counts: Vec<_> = statistics
+            .column_statistics
+            .unwrap()
+            .iter()
+            .map(|c| c.null_count)
+            .collect();
+        assert_eq!(null_counts, vec![Some(2), Some(0)]);
+        let mut file = File::open(dir.path().join("1.arrow"))?;
+        assert_eq!(read_batch_lengths(&mut file)?, vec![2, 2]);
+
+        let exec = ArrowExec::try_from_path(
+            path,
+            ArrowReadOptions::new(),
+            Some(vec![0]),
+            Some(5),
+        )?;
+        let statistics = exec.statistics();
+        assert_eq!(statistics.num_rows, Some(5));
+        assert_eq!(statistics.column_statistics.unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_lengths() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = write_file(&dir, "data.arrow")?;
+        let mut data = std::fs::read(&path)?;
+        let footer_len = data.len() - 10;
+        data[footer_len..footer_len + 4].copy_from_slice(&(-5i32).to_le_bytes());
+        std::fs::write(&path, &data)?;
+
+        let err = ArrowExec::try_from_path(&path, ArrowReadOptions::new(), None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid footer length"), "{}", err);
+
+        data[footer_len..footer_len + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+        std::fs::write(&path, &data)?;
+        let err = ArrowExec::try_from_path(&path, ArrowReadOptions::new(), None, None)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("truncated within a footer"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_files_found() -> Result<()> {
+        let dir = TempDir::new()?;
+        write_file(&dir, "data.feather")?;
+        let path = dir.path().to_str().unwrap();
+
+        let err = ArrowExec::try_from_path(path, ArrowReadOptions::new(), None, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("No files found"), "{}", err);
+
+        let options = ArrowReadOptions::new().file_extension(".feather");
+        let exec = ArrowExec::try_from_path(path, options, None, None)?;
+        assert_eq!(exec.statistics().num_rows, Some(4));
+        Ok(())
+    }
+}
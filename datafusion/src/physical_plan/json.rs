@@ -19,7 +19,11 @@
 use async_trait::async_trait;
 use futures::Stream;
 
-use super::{common, source::Source, ExecutionPlan, Partitioning, RecordBatchStream};
+use super::file_compression::FileCompressionType;
+use super::{
+    common, common::FileListOptions, schema_adapter::SchemaAdapter, source::Source,
+    ExecutionPlan, Partitioning, RecordBatchStream,
+};
 use crate::error::{DataFusionError, Result};
 use arrow::json::reader::{infer_json_schema_from_iterator, ValueIter};
 use arrow::{
@@ -49,6 +53,14 @@ pub struct NdJsonReadOptions<'a> {
     /// File extension; only files with this extension are selected for data input.
     /// Defaults to ".json".
     pub file_extension: &'a str,
+
+    /// Compression of the JSON files. If None, it is determined from the extension of
+    /// each file and files with the extension of any supported compression (e.g.
+    /// ".json.gz") are selected for data input. Defaults to None.
+    pub file_compression_type: Option<FileCompressionType>,
+
+    /// How the files of a directory or glob pattern are listed
+    pub file_list_options: FileListOptions,
 }
 
 impl<'a> Default for NdJsonReadOptions<'a> {
@@ -57,6 +69,8 @@ impl<'a> Default for NdJsonReadOptions<'a> {
             schema: None,
             schema_infer_max_records: 1000,
             file_extension: ".json",
+            file_compression_type: None,
+            file_list_options: FileListOptions::default(),
         }
     }
 }
@@ -72,6 +86,7 @@ pub struct NdJsonExec {
     projection: Option<Vec<usize>>,
     projected_schema: SchemaRef,
     file_extension: String,
+    file_compression_type: Option<FileCompressionType>,
     batch_size: usize,
     limit: Option<usize>,
 }
@@ -87,7 +102,14 @@ impl NdJsonExec {
     ) -> Result<Self> {
         let file_extension = options.file_extension.to_string();
 
-        let filenames = common::build_file_list(path, &file_extension)?;
+        let filenames = common::build_file_list_with_options(
+            path,
+            &FileCompressionType::file_extensions(
+                &file_extension,
+                options.file_compression_type,
+            ),
+            &options.file_list_options,
+        )?;
 
         if filenames.is_empty() {
             return Err(DataFusionError::Execution(format!(
@@ -102,6 +124,7 @@ impl NdJsonExec {
             None => Arc::new(NdJsonExec::try_infer_schema(
                 filenames.clone(),
                 Some(options.schema_infer_max_records),
+                options.file_compression_type,
             )?),
         };
 
@@ -119,6 +142,7 @@ impl NdJsonExec {
             },
             schema,
             file_extension,
+            file_compression_type: options.file_compression_type,
             projection,
             projected_schema,
             batch_size,
@@ -154,6 +178,7 @@ impl NdJsonExec {
             source: Source::Reader(Mutex::new(Some(Box::new(reader)))),
             schema,
             file_extension: String::new(),
+            file_compression_type: options.file_compression_type,
             projection,
             projected_schema,
             batch_size,
@@ -176,6 +201,11 @@ impl NdJsonExec {
         &self.file_extension
     }
 
+    /// Compression of the files, determined from the extension of each file if None
+    pub fn file_compression_type(&self) -> Option<FileCompressionType> {
+        self.file_compression_type
+    }
+
     /// Get the schema of the CSV file
     pub fn file_schema(&self) -> SchemaRef {
         self.schema.clone()
@@ -200,11 +230,15 @@ impl NdJsonExec {
     pub fn try_infer_schema(
         mut filenames: Vec<String>,
         max_records: Option<usize>,
+        file_compression_type: Option<FileCompressionType>,
     ) -> Result<Schema> {
         let mut schemas = Vec::new();
         let mut records_to_read = max_records.unwrap_or(usize::MAX);
         while records_to_read > 0 && !filenames.is_empty() {
-            let file = File::open(filenames.pop().unwrap())?;
+            let filename = filenames.pop().unwrap();
+            let compression = file_compression_type
+                .unwrap_or_else(|| FileCompressionType::from_path(&filename));
+            let file = compression.decompress(File::open(filename)?)?;
             let mut reader = BufReader::new(file);
             let iter = ValueIter::new(&mut reader, None);
             let schema = infer_json_schema_from_iterator(iter.take_while(|_| {
@@ -261,6 +295,7 @@ impl ExecutionPlan for NdJsonExec {
                 batch_size: self.batch_size,
                 limit: self.limit,
                 file_extension: self.file_extension.clone(),
+                file_compression_type: self.file_compression_type,
             }))
         } else {
             Err(DataFusionError::Internal(
@@ -285,14 +320,45 @@ impl ExecutionPlan for NdJsonExec {
                     .collect(),
             );
         }
+        // the reader selects the projected columns in the order of the schema and
+        // decodes the values of each file by key, so its batches are only reordered
+        let schema_adapter =
+            SchemaAdapter::new(self.schema.clone(), self.projection.as_deref());
         match &self.source {
             Source::PartitionedFiles { filenames, .. } => {
-                let file = File::open(&filenames[partition])?;
-
-                Ok(Box::pin(NdJsonStream::new(
-                    builder.build(file)?,
-                    self.limit,
-                )))
+                let filename = &filenames[partition];
+                let compression = self
+                    .file_compression_type
+                    .unwrap_or_else(|| FileCompressionType::from_path(filename));
+                let file = File::open(filename)?;
+
+                if compression == FileCompressionType::Uncompressed {
+                    Ok(Box::pin(NdJsonStream::new(
+                        builder.build(file)?,
+                        schema_adapter,
+                        self.limit,
+                    )))
+                } else {
+                    // decompressed streams are not seekable, which is fine since the
+                    // schema is always known at this point
+                    let projection = self.projection.as_ref().map(|proj| {
+                        proj.iter()
+                            .map(|col_idx| self.schema.field(*col_idx).name())
+                            .cloned()
+                            .collect()
+                    });
+                    let reader = json::Reader::new(
+                        compression.decompress(file)?,
+                        self.schema.clone(),
+                        self.batch_size,
+                        projection,
+                    );
+                    Ok(Box::pin(NdJsonStream::new(
+                        reader,
+                        schema_adapter,
+                        self.limit,
+                    )))
+                }
             }
             Source::Reader(rdr) => {
                 if partition != 0 {
@@ -301,7 +367,11 @@ impl ExecutionPlan for NdJsonExec {
                             .to_string(),
                     ))
                 } else if let Some(rdr) = rdr.lock().unwrap().take() {
-                    Ok(Box::pin(NdJsonStream::new(builder.build(rdr)?, self.limit)))
+                    Ok(Box::pin(NdJsonStream::new(
+                        builder.build(rdr)?,
+                        schema_adapter,
+                        self.limit,
+                    )))
                 } else {
                     Err(DataFusionError::Execution(
                         "Error reading CSV: Data can only be read a single time when the source is a reader"
@@ -315,13 +385,19 @@ impl ExecutionPlan for NdJsonExec {
 
 struct NdJsonStream<R: Read> {
     reader: json::Reader<R>,
+    schema_adapter: SchemaAdapter,
     remain: Option<usize>,
 }
 
 impl<R: Read> NdJsonStream<R> {
-    fn new(reader: json::Reader<R>, limit: Option<usize>) -> Self {
+    fn new(
+        reader: json::Reader<R>,
+        schema_adapter: SchemaAdapter,
+        limit: Option<usize>,
+    ) -> Self {
         Self {
             reader,
+            schema_adapter,
             remain: limit,
         }
     }
@@ -340,7 +416,10 @@ impl<R: Read + Unpin> Stream for NdJsonStream<R> {
             }
         }
 
-        Poll::Ready(match self.reader.next() {
+        let next = self.reader.next().and_then(|item| {
+            item.map(|b| self.schema_adapter.adapt_batch(b)).transpose()
+        });
+        Poll::Ready(match next {
             Ok(Some(item)) => {
                 if let Some(remain) = self.remain.as_mut() {
                     if *remain >= item.num_rows() {
@@ -369,7 +448,7 @@ impl<R: Read + Unpin> Stream for NdJsonStream<R> {
 
 impl<R: Read + Unpin> RecordBatchStream for NdJsonStream<R> {
     fn schema(&self) -> SchemaRef {
-        self.reader.schema()
+        self.schema_adapter.projected_schema()
     }
 }
 
@@ -451,6 +530,31 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn nd_json_exec_file_projection_order() -> Result<()> {
+        let path = format!("{}/1.json", TEST_DATA_BASE);
+        let exec =
+            NdJsonExec::try_new(&path, Default::default(), Some(vec![3, 0]), 1024, None)?;
+
+        let mut it = exec.execute(0).await?;
+        let batch = it.next().await.unwrap()?;
+        assert_eq!(exec.schema(), batch.schema());
+
+        let d = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<arrow::array::StringArray>()
+            .unwrap();
+        assert_eq!(d.value(2), "text");
+        let a = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<arrow::array::Int64Array>()
+            .unwrap();
+        assert_eq!(a.value(1), -10);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn nd_json_exec_from_reader() -> Result<()> {
         let content = r#"{"a":"aaa", "b":[2.0, 1.3, -6.1], "c":[false, true], "d":"4"}
@@ -25,7 +25,9 @@ use std::task::{Context, Poll};
 use futures::stream::Stream;
 use futures::stream::StreamExt;
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
+use crate::physical_plan::expressions::PhysicalSortExpr;
 use crate::physical_plan::{
     DisplayFormatType, Distribution, ExecutionPlan, Partitioning,
 };
@@ -46,12 +48,29 @@ pub struct GlobalLimitExec {
     input: Arc<dyn ExecutionPlan>,
     /// Maximum number of rows to return
     limit: usize,
+    /// Number of rows to skip before the rows that are returned
+    offset: usize,
 }
 
 impl GlobalLimitExec {
     /// Create a new GlobalLimitExec
     pub fn new(input: Arc<dyn ExecutionPlan>, limit: usize) -> Self {
-        GlobalLimitExec { input, limit }
+        GlobalLimitExec {
+            input,
+            limit,
+            offset: 0,
+        }
+    }
+
+    /// Skip the first `offset` rows of the input before returning up to `limit` rows
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Number of rows to skip before the rows that are returned
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
     /// Input execution plan
@@ -89,15 +108,19 @@ impl ExecutionPlan for GlobalLimitExec {
         Partitioning::UnknownPartitioning(1)
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        self.input.output_ordering()
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(GlobalLimitExec::new(
-                children[0].clone(),
-                self.limit,
-            ))),
+            1 => Ok(Arc::new(
+                GlobalLimitExec::new(children[0].clone(), self.limit)
+                    .with_offset(self.offset),
+            )),
             _ => Err(DataFusionError::Internal(
                 "GlobalLimitExec wrong number of children".to_string(),
             )),
@@ -121,7 +144,9 @@ impl ExecutionPlan for GlobalLimitExec {
         }
 
         let stream = self.input.execute(0).await?;
-        Ok(Box::pin(LimitStream::new(stream, self.limit)))
+        Ok(Box::pin(
+            LimitStream::new(stream, self.limit).with_offset(self.offset),
+        ))
     }
 
     fn fmt_as(
@@ -131,10 +156,23 @@ impl ExecutionPlan for GlobalLimitExec {
     ) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default => {
-                write!(f, "GlobalLimitExec: limit={}", self.limit)
+                write!(f, "GlobalLimitExec: limit={}", self.limit)?;
+                if self.offset > 0 {
+                    write!(f, ", offset={}", self.offset)?;
+                }
+                Ok(())
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        limit_statistics(self.input.statistics(), self.offset, self.limit)
+    }
+
+    fn unbounded(&self) -> bool {
+        // the output ends once the limit is reached, even if the input never does
+        false
+    }
 }
 
 /// LocalLimitExec applies a limit to a single partition
@@ -182,6 +220,10 @@ impl ExecutionPlan for LocalLimitExec {
         self.input.output_partitioning()
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        self.input.output_ordering()
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -213,6 +255,35 @@ impl ExecutionPlan for LocalLimitExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        // the limit applies to each partition
+        limit_statistics(
+            self.input.statistics(),
+            0,
+            self.limit * self.output_partitioning().partition_count(),
+        )
+    }
+
+    fn unbounded(&self) -> bool {
+        false
+    }
+}
+
+/// Caps the number of rows of `stats` at `limit`, after skipping `offset` rows
+fn limit_statistics(stats: Statistics, offset: usize, limit: usize) -> Statistics {
+    match stats.num_rows {
+        Some(n) if offset == 0 && n <= limit => stats,
+        _ => Statistics {
+            num_rows: Some(
+                stats
+                    .num_rows
+                    .map_or(limit, |n| n.saturating_sub(offset).min(limit)),
+            ),
+            total_byte_size: None,
+            column_statistics: stats.column_statistics,
+        },
+    }
 }
 
 /// Truncate a RecordBatch to maximum of n rows
@@ -224,10 +295,25 @@ pub fn truncate_batch(batch: &RecordBatch, n: usize) -> RecordBatch {
     RecordBatch::try_new(batch.schema(), limited_columns).unwrap()
 }
 
-/// A Limit stream limits the stream to up to `limit` rows.
+/// Skip the first n rows of a RecordBatch
+fn skip_batch(batch: &RecordBatch, n: usize) -> RecordBatch {
+    let len = batch.num_rows() - n;
+    let skipped_columns: Vec<ArrayRef> = (0..batch.num_columns())
+        .map(|i| batch.column(i).slice(n, len))
+        .collect();
+
+    RecordBatch::try_new(batch.schema(), skipped_columns).unwrap()
+}
+
+/// A Limit stream limits the stream to up to `limit` rows, after skipping the first
+/// `offset` rows.
 struct LimitStream {
     /// The maximum number of rows to produce
     limit: usize,
+    /// The number of rows to skip
+    offset: usize,
+    /// The number of rows which have been skipped so far
+    skipped: usize,
     /// The input to read from. This is set to None once the limit is
     /// reached to enable early termination
     input: Option<SendableRecordBatchStream>,
@@ -242,18 +328,44 @@ impl LimitStream {
         let schema = input.schema();
         Self {
             limit,
+            offset: 0,
+            skipped: 0,
             input: Some(input),
             schema,
             current_len: 0,
         }
     }
 
+    fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Drops the rows of the batch that are still to be skipped, returning None if
+    /// the entire batch was skipped
+    fn stream_offset(&mut self, batch: RecordBatch) -> Option<RecordBatch> {
+        let remaining = self.offset - self.skipped;
+        if remaining == 0 {
+            Some(batch)
+        } else if batch.num_rows() <= remaining {
+            self.skipped += batch.num_rows();
+            None
+        } else {
+            self.skipped = self.offset;
+            Some(skip_batch(&batch, remaining))
+        }
+    }
+
     fn stream_limit(&mut self, batch: RecordBatch) -> Option<RecordBatch> {
         if self.current_len == self.limit {
             self.input = None; // clear input so it can be dropped early
             None
         } else if self.current_len + batch.num_rows() <= self.limit {
             self.current_len += batch.num_rows();
+            if self.current_len == self.limit {
+                // don't wait for more input, which may never arrive
+                self.input = None;
+            }
             Some(batch)
         } else {
             let batch_rows = self.limit - self.current_len;
@@ -271,13 +383,20 @@ impl Stream for LimitStream {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        match &mut self.input {
-            Some(input) => input.poll_next_unpin(cx).map(|x| match x {
-                Some(Ok(batch)) => Ok(self.stream_limit(batch)).transpose(),
+        loop {
+            let poll = match &mut self.input {
+                Some(input) => input.poll_next_unpin(cx),
+                // input has been cleared
+                None => return Poll::Ready(None),
+            };
+            return match poll {
+                Poll::Ready(Some(Ok(batch))) => match self.stream_offset(batch) {
+                    Some(batch) => Poll::Ready(Ok(self.stream_limit(batch)).transpose()),
+                    // the whole batch was skipped, poll the next one
+                    None => continue,
+                },
                 other => other,
-            }),
-            // input has been cleared
-            None => Poll::Ready(None),
+            };
         }
     }
 }
@@ -333,6 +452,37 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn limit_with_offset() -> Result<()> {
+        let batches = vec![
+            test::make_partition(5),
+            test::make_partition(10),
+            test::make_partition(15),
+        ];
+        let input = test::exec::TestStream::new(batches);
+
+        // skips the first batch and 3 rows of the second one
+        let limit_stream = LimitStream::new(Box::pin(input), 10).with_offset(8);
+        let results = collect(Box::pin(limit_stream)).await.unwrap();
+        let num_rows: usize = results.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(num_rows, 10);
+        // make_partition produces the values 0..n
+        let first = results[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<arrow::array::Int32Array>()
+            .unwrap();
+        assert_eq!(first.value(0), 3);
+
+        // an offset beyond the input produces no rows
+        let input = test::exec::TestStream::new(vec![test::make_partition(5)]);
+        let limit_stream = LimitStream::new(Box::pin(input), 10).with_offset(8);
+        let results = collect(Box::pin(limit_stream)).await.unwrap();
+        assert!(results.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn limit_early_shutdown() -> Result<()> {
         let batches = vec![
@@ -26,6 +26,7 @@ use super::{
     DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
     SendableRecordBatchStream,
 };
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
 use arrow::datatypes::SchemaRef;
 use arrow::error::Result as ArrowResult;
@@ -110,6 +111,19 @@ impl ExecutionPlan for MemoryExec {
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        let num_rows = self
+            .partitions
+            .iter()
+            .flatten()
+            .map(|batch| batch.num_rows())
+            .sum();
+        Statistics {
+            num_rows: Some(num_rows),
+            ..Default::default()
+        }
+    }
 }
 
 impl MemoryExec {
@@ -30,11 +30,14 @@ use self::{
 };
 use crate::physical_plan::expressions::PhysicalSortExpr;
 use crate::{
+    datasource::datasource::Statistics,
     error::{DataFusionError, Result},
     scalar::ScalarValue,
 };
+use arrow::array::{make_array, Array, BooleanArray, MutableArrayData};
 use arrow::compute::kernels::partition::lexicographical_partition_ranges;
 use arrow::compute::kernels::sort::{SortColumn, SortOptions};
+use arrow::compute::{filter, filter_record_batch};
 use arrow::datatypes::{DataType, Schema, SchemaRef};
 use arrow::error::Result as ArrowResult;
 use arrow::record_batch::RecordBatch;
@@ -43,6 +46,7 @@ use async_trait::async_trait;
 pub use display::DisplayFormatType;
 use futures::stream::Stream;
 use hashbrown::HashMap;
+pub use partition_hash::HashScheme;
 
 /// Trait for types that stream [arrow::record_batch::RecordBatch]
 pub trait RecordBatchStream: Stream<Item = ArrowResult<RecordBatch>> {
@@ -198,6 +202,25 @@ pub trait ExecutionPlan: Debug + Send + Sync {
         HashMap::new()
     }
 
+    /// Returns the estimated statistics of the output of this plan, derived from the
+    /// statistics of its inputs where possible. The default is unknown statistics.
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+
+    /// Returns whether the output of this plan may never end, because it is
+    /// (derived from) a scan of an unbounded source. By default a plan is unbounded
+    /// if any of its children is.
+    fn unbounded(&self) -> bool {
+        self.children().iter().any(|child| child.unbounded())
+    }
+
+    /// Returns the order of the rows within each output partition of this plan, if it
+    /// is known. The default is no known order.
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        None
+    }
+
     /// Format this `ExecutionPlan` to `f` in the specified type.
     ///
     /// Should not include a newline
@@ -465,6 +488,50 @@ pub trait PhysicalExpr: Send + Sync + Display + Debug {
     fn nullable(&self, input_schema: &Schema) -> Result<bool>;
     /// Evaluate an expression against a RecordBatch
     fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue>;
+    /// Evaluate an expression against only the rows of a RecordBatch for which
+    /// `selection` is true. The result has one value per row of `batch`, which is NULL
+    /// for the rows that are not selected.
+    ///
+    /// The default implementation evaluates the expression against the selected rows
+    /// and scatters the values back to their positions.
+    fn evaluate_selection(
+        &self,
+        batch: &RecordBatch,
+        selection: &BooleanArray,
+    ) -> Result<ColumnarValue> {
+        let selected_batch = filter_record_batch(batch, selection)?;
+        if selected_batch.num_rows() == batch.num_rows() {
+            return self.evaluate(batch);
+        }
+        let selected = self
+            .evaluate(&selected_batch)?
+            .into_array(selected_batch.num_rows());
+        Ok(ColumnarValue::Array(scatter(selection, selected.as_ref())))
+    }
+}
+
+/// Spreads the values of `values` over the positions where `selection` is true and
+/// fills the other positions with NULL
+fn scatter(selection: &BooleanArray, values: &dyn Array) -> ArrayRef {
+    let mut data = MutableArrayData::new(vec![values.data()], true, selection.len());
+    let mut value_idx = 0;
+    let mut row = 0;
+    while row < selection.len() {
+        let selected = selection.is_valid(row) && selection.value(row);
+        let start = row;
+        while row < selection.len()
+            && (selection.is_valid(row) && selection.value(row)) == selected
+        {
+            row += 1;
+        }
+        if selected {
+            data.extend(0, value_idx, value_idx + row - start);
+            value_idx += row - start;
+        } else {
+            data.extend_nulls(row - start);
+        }
+    }
+    make_array(data.freeze())
 }
 
 /// An aggregate expression that:
@@ -590,7 +657,8 @@ pub trait WindowExpr: Send + Sync + Debug {
 /// generically accumulates values.
 ///
 /// An accumulator knows how to:
-/// * update its state from inputs via `update`
+/// * update its state from inputs via `update_batch`, optionally restricted to the rows
+///   of a selection, e.g. the rows that pass the `FILTER` clause of the aggregate
 /// * convert its internal state to a vector of scalar values
 /// * update its state from multiple accumulators' states via `merge`
 /// * compute the final value from its internal state via `evaluate`
@@ -617,6 +685,27 @@ pub trait Accumulator: Send + Sync + Debug {
         })
     }
 
+    /// updates the accumulator's state from the rows of a vector of arrays whose
+    /// `selection` is true, or from all the rows when there is no selection.
+    /// Accumulators that can skip the unselected rows without copying the arrays should
+    /// override it.
+    fn update_batch_selected(
+        &mut self,
+        values: &[ArrayRef],
+        selection: Option<&BooleanArray>,
+    ) -> Result<()> {
+        match selection {
+            None => self.update_batch(values),
+            Some(selection) => {
+                let values = values
+                    .iter()
+                    .map(|array| filter(array.as_ref(), selection))
+                    .collect::<ArrowResult<Vec<_>>>()?;
+                self.update_batch(&values)
+            }
+        }
+    }
+
     /// updates the accumulator's state from a vector of scalars.
     fn merge(&mut self, states: &[ScalarValue]) -> Result<()>;
 
@@ -643,6 +732,7 @@ pub mod array_expressions;
 pub mod coalesce_batches;
 pub mod coalesce_partitions;
 pub mod common;
+pub mod constant;
 pub mod cross_join;
 #[cfg(feature = "crypto_expressions")]
 pub mod crypto_expressions;
@@ -653,30 +743,40 @@ pub mod distinct_expressions;
 pub mod empty;
 pub mod explain;
 pub mod expressions;
+pub mod file_compression;
 pub mod filter;
 pub mod functions;
 pub mod hash_aggregate;
 pub mod hash_join;
 pub mod hash_utils;
+pub mod interval_join;
+pub mod ipc;
 pub mod json;
 pub mod limit;
 pub mod math_expressions;
 pub mod memory;
 pub mod parquet;
+pub mod parquet_writer;
+pub mod partition_hash;
 pub mod planner;
 pub mod projection;
 #[cfg(feature = "regex_expressions")]
 pub mod regex_expressions;
 pub mod repartition;
+pub mod schema_adapter;
 pub mod sort;
 pub mod sort_preserving_merge;
+pub mod sorted_aggregate;
 pub mod source;
+pub mod streaming;
 pub mod string_expressions;
 pub mod type_coercion;
 pub mod udaf;
 pub mod udf;
+pub mod udwf;
 #[cfg(feature = "unicode_expressions")]
 pub mod unicode_expressions;
 pub mod union;
+pub mod unnest;
 pub mod window_functions;
 pub mod windows;
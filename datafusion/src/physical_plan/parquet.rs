@@ -19,6 +19,7 @@
 
 use std::fmt;
 use std::fs::File;
+use std::ops::Range;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::{any::Any, convert::TryInto};
@@ -28,14 +29,15 @@ use crate::{
     logical_plan::{Column, Expr},
     physical_optimizer::pruning::{PruningPredicate, PruningStatistics},
     physical_plan::{
-        common, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+        common, common::FileListOptions, schema_adapter::SchemaAdapter,
+        DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
         SendableRecordBatchStream,
     },
     scalar::ScalarValue,
 };
 
 use arrow::{
-    array::ArrayRef,
+    array::{ArrayRef, UInt64Array},
     datatypes::{DataType, Schema, SchemaRef},
     error::{ArrowError, Result as ArrowResult},
     record_batch::RecordBatch,
@@ -49,7 +51,7 @@ use parquet::file::{
 };
 
 use fmt::Debug;
-use parquet::arrow::{ArrowReader, ParquetFileArrowReader};
+use parquet::arrow::{parquet_to_arrow_schema, ArrowReader, ParquetFileArrowReader};
 
 use tokio::{
     sync::mpsc::{channel, Receiver, Sender},
@@ -62,7 +64,9 @@ use async_trait::async_trait;
 use futures::stream::{Stream, StreamExt};
 
 use super::SQLMetric;
-use crate::physical_plan::expressions::{MaxAccumulator, MinAccumulator};
+use crate::physical_plan::expressions::{
+    MaxAccumulator, MinAccumulator, PhysicalSortExpr,
+};
 use crate::physical_plan::Accumulator;
 
 /// Execution plan for scanning one or more Parquet partitions
@@ -74,6 +78,8 @@ pub struct ParquetExec {
     schema: SchemaRef,
     /// Projection for which columns to load
     projection: Vec<usize>,
+    /// Adapts the batches of files whose schemas differ from the table schema
+    schema_adapter: SchemaAdapter,
     /// Batch size
     batch_size: usize,
     /// Statistics for the data set (sum of statistics for all partitions)
@@ -84,13 +90,13 @@ pub struct ParquetExec {
     predicate_builder: Option<PruningPredicate>,
     /// Optional limit of the number of rows
     limit: Option<usize>,
+    /// The order of the rows within each file, in terms of the projected columns
+    file_sort_order: Vec<PhysicalSortExpr>,
 }
 
-/// Represents one partition of a Parquet data set and this currently means one Parquet file.
-///
-/// In the future it would be good to support subsets of files based on ranges of row groups
-/// so that we can better parallelize reads of large files across available cores (see
-/// [ARROW-10995](https://issues.apache.org/jira/browse/ARROW-10995)).
+/// Represents one partition of a Parquet data set, which is either one or more Parquet
+/// files, or a range of the row groups of a single file so that we can better
+/// parallelize reads of large files across available cores.
 ///
 /// We may also want to support reading Parquet files that are partitioned based on a key and
 /// in this case we would want this partition struct to represent multiple files for a given
@@ -99,6 +105,9 @@ pub struct ParquetExec {
 pub struct ParquetPartition {
     /// The Parquet filename for this partition
     pub filenames: Vec<String>,
+    /// The range of row groups to read if this partition covers part of a single
+    /// file, or `None` to read all row groups
+    pub row_groups: Option<Range<usize>>,
     /// Statistics for this partition
     pub statistics: Statistics,
     /// Execution metrics
@@ -132,9 +141,59 @@ impl ParquetExec {
         max_concurrency: usize,
         limit: Option<usize>,
     ) -> Result<Self> {
-        // build a list of filenames from the specified path, which could be a single file or
-        // a directory containing one or more parquet files
-        let filenames = common::build_file_list(path, ".parquet")?;
+        Self::try_from_path_with_file_list_options(
+            path,
+            &FileListOptions::default(),
+            projection,
+            predicate,
+            batch_size,
+            max_concurrency,
+            limit,
+        )
+    }
+
+    /// Create a new Parquet reader execution plan based on the specified Parquet filename,
+    /// directory containing Parquet files or glob pattern, whose files are listed with
+    /// `file_list_options`
+    pub fn try_from_path_with_file_list_options(
+        path: &str,
+        file_list_options: &FileListOptions,
+        projection: Option<Vec<usize>>,
+        predicate: Option<Expr>,
+        batch_size: usize,
+        max_concurrency: usize,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        Self::try_from_path_with_schema(
+            path,
+            file_list_options,
+            None,
+            projection,
+            predicate,
+            batch_size,
+            max_concurrency,
+            limit,
+        )
+    }
+
+    /// Create a new Parquet reader execution plan like
+    /// [`try_from_path_with_file_list_options`](Self::try_from_path_with_file_list_options),
+    /// whose files are read as `schema` if it is provided
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_from_path_with_schema(
+        path: &str,
+        file_list_options: &FileListOptions,
+        schema: Option<SchemaRef>,
+        projection: Option<Vec<usize>>,
+        predicate: Option<Expr>,
+        batch_size: usize,
+        max_concurrency: usize,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        // build a list of filenames from the specified path, which could be a single file,
+        // a directory containing one or more parquet files or a glob pattern
+        let filenames =
+            common::build_file_list_with_options(path, &[".parquet"], file_list_options)?;
         if filenames.is_empty() {
             Err(DataFusionError::Plan(format!(
                 "No Parquet files (with .parquet extension) found at path {}",
@@ -145,8 +204,9 @@ impl ParquetExec {
                 .iter()
                 .map(|filename| filename.as_str())
                 .collect::<Vec<&str>>();
-            Self::try_from_files(
+            Self::try_from_files_with_schema(
                 &filenames,
+                schema,
                 projection,
                 predicate,
                 batch_size,
@@ -165,6 +225,30 @@ impl ParquetExec {
         batch_size: usize,
         max_concurrency: usize,
         limit: Option<usize>,
+    ) -> Result<Self> {
+        Self::try_from_files_with_schema(
+            filenames,
+            None,
+            projection,
+            predicate,
+            batch_size,
+            max_concurrency,
+            limit,
+        )
+    }
+
+    /// Create a new Parquet reader execution plan based on the specified list of Parquet
+    /// files. If `schema` is provided, the files are read as that schema: their columns
+    /// are matched by name, cast to its types and filled with nulls if missing.
+    /// Otherwise all the files must have the same schema.
+    pub fn try_from_files_with_schema(
+        filenames: &[&str],
+        schema: Option<SchemaRef>,
+        projection: Option<Vec<usize>>,
+        predicate: Option<Expr>,
+        batch_size: usize,
+        max_concurrency: usize,
+        limit: Option<usize>,
     ) -> Result<Self> {
         debug!("Creating ParquetExec, filenames: {:?}, projection {:?}, predicate: {:?}, limit: {:?}",
                filenames, projection, predicate, limit);
@@ -173,19 +257,29 @@ impl ParquetExec {
         let mut schemas: Vec<Schema> = vec![];
         let mut partitions = Vec::with_capacity(max_concurrency);
         let filenames: Vec<String> = filenames.iter().map(|s| s.to_string()).collect();
-        let chunks = split_files(&filenames, max_concurrency);
-        let mut num_rows = 0;
+        let chunks = split_row_groups(&filenames, max_concurrency)?;
+        // total number of rows planned so far, to stop once the limit is reached
+        let mut total_num_rows = 0;
         let mut num_fields = 0;
         let mut fields = Vec::new();
-        let mut total_byte_size = 0;
         let mut null_counts = Vec::new();
+        let mut distinct_counts = Vec::new();
         let mut max_values: Vec<Option<MaxAccumulator>> = Vec::new();
         let mut min_values: Vec<Option<MinAccumulator>> = Vec::new();
         let mut limit_exhausted = false;
-        for chunk in chunks {
-            let mut filenames: Vec<String> =
-                chunk.iter().map(|x| x.to_string()).collect();
+        for (chunk, row_groups) in chunks {
+            let mut filenames = chunk;
             let mut total_files = 0;
+            // statistics of this partition
+            let mut num_rows = 0;
+            let mut total_byte_size = 0;
+            if let Some(schema) = schemas.first() {
+                null_counts = vec![0; num_fields];
+                distinct_counts = vec![Some(0); num_fields];
+                let (max, min) = create_max_min_accs(schema);
+                max_values = max;
+                min_values = min;
+            }
             for filename in &filenames {
                 total_files += 1;
                 let file = File::open(filename)?;
@@ -198,21 +292,19 @@ impl ParquetExec {
                     fields = schema.fields().to_vec();
                     num_fields = schema.fields().len();
                     null_counts = vec![0; num_fields];
-                    max_values = schema
-                        .fields()
-                        .iter()
-                        .map(|field| MaxAccumulator::try_new(field.data_type()).ok())
-                        .collect::<Vec<_>>();
-                    min_values = schema
-                        .fields()
-                        .iter()
-                        .map(|field| MinAccumulator::try_new(field.data_type()).ok())
-                        .collect::<Vec<_>>();
+                    distinct_counts = vec![Some(0); num_fields];
+                    let (max, min) = create_max_min_accs(&schema);
+                    max_values = max;
+                    min_values = min;
                     schemas.push(schema);
                 }
 
-                for row_group_meta in meta_data.row_groups() {
+                let row_group_range = row_groups
+                    .clone()
+                    .unwrap_or_else(|| 0..meta_data.num_row_groups());
+                for row_group_meta in &meta_data.row_groups()[row_group_range] {
                     num_rows += row_group_meta.num_rows();
+                    total_num_rows += row_group_meta.num_rows();
                     total_byte_size += row_group_meta.total_byte_size();
 
                     // Currently assumes every Parquet file has same schema
@@ -227,6 +319,14 @@ impl ParquetExec {
                     }
 
                     for (i, column) in row_group_meta.columns().iter().enumerate() {
+                        let row_group_distinct_count = column
+                            .statistics()
+                            .and_then(|stats| stats.distinct_count())
+                            .map(|d| d as usize);
+                        distinct_counts[i] = max_distinct_count(
+                            distinct_counts[i],
+                            row_group_distinct_count,
+                        );
                         if let Some(stat) = column.statistics() {
                             match stat {
                                 ParquetStatistics::Boolean(s) => {
@@ -364,7 +464,7 @@ impl ParquetExec {
                         }
                     }
 
-                    if limit.map(|x| num_rows >= x as i64).unwrap_or(false) {
+                    if limit.map(|x| total_num_rows >= x as i64).unwrap_or(false) {
                         limit_exhausted = true;
                         break;
                     }
@@ -384,7 +484,7 @@ impl ParquetExec {
                         null_count: Some(null_counts[i] as usize),
                         max_value,
                         min_value,
-                        distinct_count: None,
+                        distinct_count: distinct_counts[i],
                     }
                 })
                 .collect();
@@ -396,23 +496,38 @@ impl ParquetExec {
             };
             // remove files that are not needed in case of limit
             filenames.truncate(total_files);
-            partitions.push(ParquetPartition::new(filenames, statistics));
+            let mut partition = ParquetPartition::new(filenames, statistics);
+            partition.row_groups = row_groups;
+            partitions.push(partition);
             if limit_exhausted {
                 break;
             }
         }
 
-        // we currently get the schema information from the first file rather than do
-        // schema merging and this is a limitation.
-        // See https://issues.apache.org/jira/browse/ARROW-11017
-        if schemas.len() > 1 {
-            return Err(DataFusionError::Plan(format!(
-                "The Parquet files have {} different schemas and DataFusion does \
-                not yet support schema merging",
-                schemas.len()
-            )));
-        }
-        let schema = Arc::new(schemas.pop().unwrap());
+        let schema = match schema {
+            Some(schema) => {
+                // the column statistics are gathered in the order of the file columns
+                if schemas.iter().any(|s| s.fields() != schema.fields()) {
+                    for partition in &mut partitions {
+                        partition.statistics.column_statistics = None;
+                    }
+                }
+                schema
+            }
+            None => {
+                // we currently get the schema information from the first file rather
+                // than do schema merging and this is a limitation.
+                // See https://issues.apache.org/jira/browse/ARROW-11017
+                if schemas.len() > 1 {
+                    return Err(DataFusionError::Plan(format!(
+                        "The Parquet files have {} different schemas and DataFusion does \
+                        not yet support schema merging",
+                        schemas.len()
+                    )));
+                }
+                Arc::new(schemas.pop().unwrap())
+            }
+        };
         let metrics = ParquetExecMetrics::new();
 
         let predicate_builder = predicate.and_then(|predicate_expr| {
@@ -466,6 +581,8 @@ impl ParquetExec {
         let mut num_rows: Option<usize> = None;
         let mut total_byte_size: Option<usize> = None;
         let mut null_counts: Vec<usize> = vec![0; schema.fields().len()];
+        let mut distinct_counts: Vec<Option<usize>> =
+            vec![Some(0); schema.fields().len()];
         let mut has_statistics = false;
         let mut max_values = schema
             .fields()
@@ -496,6 +613,8 @@ impl ParquetExec {
 
                 for &i in projection.iter() {
                     null_counts[i] = part_nulls[i].unwrap_or(0);
+                    distinct_counts[i] =
+                        max_distinct_count(distinct_counts[i], x[i].distinct_count);
                     if let Some(part_max_value) = part_max_values[i].clone() {
                         if let Some(max_value) = &mut max_values[i] {
                             match max_value.update(&[part_max_value]) {
@@ -536,7 +655,7 @@ impl ParquetExec {
                             null_count: Some(null_counts[i] as usize),
                             max_value,
                             min_value,
-                            distinct_count: None,
+                            distinct_count: distinct_counts[i],
                         }
                     })
                     .collect(),
@@ -550,18 +669,37 @@ impl ParquetExec {
             total_byte_size,
             column_statistics: column_stats,
         };
+        let schema_adapter = SchemaAdapter::new(schema, Some(&projection));
         Self {
             partitions,
             schema: Arc::new(projected_schema),
             projection,
+            schema_adapter,
             metrics,
             predicate_builder,
             batch_size,
             statistics,
             limit,
+            file_sort_order: vec![],
         }
     }
 
+    /// Declares that the rows of each file are sorted by `file_sort_order`, which is
+    /// given in terms of the projected columns. Partitions that read a single file, or
+    /// a range of its row groups, then report this order as their output ordering.
+    pub fn with_file_sort_order(
+        mut self,
+        file_sort_order: Vec<PhysicalSortExpr>,
+    ) -> Self {
+        self.file_sort_order = file_sort_order;
+        self
+    }
+
+    /// The order of the rows within each file
+    pub fn file_sort_order(&self) -> &[PhysicalSortExpr] {
+        &self.file_sort_order
+    }
+
     /// Parquet partitions to read
     pub fn partitions(&self) -> &[ParquetPartition] {
         &self.partitions
@@ -572,6 +710,11 @@ impl ParquetExec {
         &self.projection
     }
 
+    /// Schema of the table that the files are read as
+    pub fn table_schema(&self) -> SchemaRef {
+        self.schema_adapter.table_schema()
+    }
+
     /// Batch size
     pub fn batch_size(&self) -> usize {
         self.batch_size
@@ -588,6 +731,7 @@ impl ParquetPartition {
     pub fn new(filenames: Vec<String>, statistics: Statistics) -> Self {
         Self {
             filenames,
+            row_groups: None,
             statistics,
             metrics: ParquetPartitionMetrics::new(),
         }
@@ -598,6 +742,11 @@ impl ParquetPartition {
         &self.filenames
     }
 
+    /// The range of row groups to read if this partition covers part of a single file
+    pub fn row_groups(&self) -> Option<&Range<usize>> {
+        self.row_groups.as_ref()
+    }
+
     /// Statistics for this partition
     pub fn statistics(&self) -> &Statistics {
         &self.statistics
@@ -644,6 +793,17 @@ impl ExecutionPlan for ParquetExec {
         Partitioning::UnknownPartitioning(self.partitions.len())
     }
 
+    /// The rows of several files read by one partition are in no particular order
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        if self.file_sort_order.is_empty()
+            || self.partitions.iter().any(|p| p.filenames.len() > 1)
+        {
+            None
+        } else {
+            Some(self.file_sort_order.clone())
+        }
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -668,8 +828,9 @@ impl ExecutionPlan for ParquetExec {
 
         let partition = &self.partitions[partition];
         let filenames = partition.filenames.clone();
+        let row_groups = partition.row_groups.clone();
         let metrics = partition.metrics.clone();
-        let projection = self.projection.clone();
+        let schema_adapter = self.schema_adapter.clone();
         let predicate_builder = self.predicate_builder.clone();
         let batch_size = self.batch_size;
         let limit = self.limit;
@@ -677,8 +838,9 @@ impl ExecutionPlan for ParquetExec {
         task::spawn_blocking(move || {
             if let Err(e) = read_files(
                 &filenames,
+                row_groups,
                 metrics,
-                &projection,
+                &schema_adapter,
                 &predicate_builder,
                 batch_size,
                 response_tx,
@@ -704,9 +866,15 @@ impl ExecutionPlan for ParquetExec {
                 let files: Vec<_> = self
                     .partitions
                     .iter()
-                    .map(|pp| pp.filenames.iter())
-                    .flatten()
-                    .map(|s| s.as_str())
+                    .flat_map(|pp| {
+                        pp.filenames.iter().map(move |s| match &pp.row_groups {
+                            Some(range) => format!(
+                                "{}[row_groups={}..{}]",
+                                s, range.start, range.end
+                            ),
+                            None => s.to_string(),
+                        })
+                    })
                     .collect();
 
                 write!(
@@ -744,6 +912,48 @@ impl ExecutionPlan for ParquetExec {
             )))
             .collect()
     }
+
+    /// The statistics of the projected columns, summed over all partitions
+    fn statistics(&self) -> Statistics {
+        let column_statistics = self.statistics.column_statistics.as_ref().map(|cols| {
+            self.projection
+                .iter()
+                .map(|i| cols.get(*i).cloned().unwrap_or_default())
+                .collect()
+        });
+        let num_rows = match (self.statistics.num_rows, self.limit) {
+            (Some(n), Some(limit)) => Some(n.min(limit)),
+            (n, _) => n,
+        };
+        Statistics {
+            num_rows,
+            total_byte_size: self.statistics.total_byte_size,
+            column_statistics,
+        }
+    }
+}
+
+/// Creates the accumulators for the min and max values of each column of `schema`
+fn create_max_min_accs(
+    schema: &Schema,
+) -> (Vec<Option<MaxAccumulator>>, Vec<Option<MinAccumulator>>) {
+    let max_values = schema
+        .fields()
+        .iter()
+        .map(|field| MaxAccumulator::try_new(field.data_type()).ok())
+        .collect::<Vec<_>>();
+    let min_values = schema
+        .fields()
+        .iter()
+        .map(|field| MinAccumulator::try_new(field.data_type()).ok())
+        .collect::<Vec<_>>();
+    (max_values, min_values)
+}
+
+/// Combines the distinct counts of two parts of a column, which has at least as many
+/// distinct values as either part. Unknown if the count of either part is unknown.
+fn max_distinct_count(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    Some(a?.max(b?))
 }
 
 fn send_result(
@@ -836,6 +1046,20 @@ impl<'a> PruningStatistics for RowGroupPruningStatistics<'a> {
         get_min_max_values!(self, column, max, max_bytes)
     }
 
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        let (column_index, _) = self.parquet_schema.column_with_name(&column.name)?;
+        let null_counts = self
+            .row_group_metadata
+            .iter()
+            .map(|meta| {
+                meta.column(column_index)
+                    .statistics()
+                    .map(|stats| stats.null_count())
+            })
+            .collect::<UInt64Array>();
+        Some(Arc::new(null_counts))
+    }
+
     fn num_containers(&self) -> usize {
         self.row_group_metadata.len()
     }
@@ -873,8 +1097,9 @@ fn build_row_group_predicate(
 
 fn read_files(
     filenames: &[String],
+    row_groups: Option<Range<usize>>,
     metrics: ParquetPartitionMetrics,
-    projection: &[usize],
+    schema_adapter: &SchemaAdapter,
     predicate_builder: &Option<PruningPredicate>,
     batch_size: usize,
     response_tx: Sender<ArrowResult<RecordBatch>>,
@@ -884,19 +1109,50 @@ fn read_files(
     'outer: for filename in filenames {
         let file = File::open(&filename)?;
         let mut file_reader = SerializedFileReader::new(file)?;
+        let file_metadata = file_reader.metadata().file_metadata();
+        let file_schema = parquet_to_arrow_schema(
+            file_metadata.schema_descr(),
+            file_metadata.key_value_metadata(),
+        )?;
+        let projection = match schema_adapter.map_schema(&file_schema) {
+            Ok(projection) => projection,
+            Err(e) => {
+                let err_msg = format!("Error reading {}: {}", filename, e);
+                send_result(
+                    &response_tx,
+                    Err(ArrowError::ParquetError(err_msg.clone())),
+                )?;
+                return Err(DataFusionError::Execution(err_msg));
+            }
+        };
+        // the statistics of the row groups are looked up by the column indices of the
+        // table schema, so they can only be used if the file has the same columns
+        let predicate_builder = predicate_builder
+            .as_ref()
+            .filter(|_| schema_adapter.matches(&file_schema));
         if let Some(predicate_builder) = predicate_builder {
             let row_group_predicate = build_row_group_predicate(
                 predicate_builder,
                 metrics.clone(),
                 file_reader.metadata().row_groups(),
             );
-            file_reader.filter_row_groups(&row_group_predicate);
+            match &row_groups {
+                Some(range) => file_reader.filter_row_groups(&|meta, i| {
+                    range.contains(&i) && row_group_predicate(meta, i)
+                }),
+                None => file_reader.filter_row_groups(&row_group_predicate),
+            }
+        } else if let Some(range) = &row_groups {
+            file_reader.filter_row_groups(&|_, i| range.contains(&i));
         }
         let mut arrow_reader = ParquetFileArrowReader::new(Arc::new(file_reader));
-        let mut batch_reader = arrow_reader
-            .get_record_reader_by_columns(projection.to_owned(), batch_size)?;
+        let mut batch_reader =
+            arrow_reader.get_record_reader_by_columns(projection, batch_size)?;
         loop {
-            match batch_reader.next() {
+            match batch_reader
+                .next()
+                .map(|batch| batch.and_then(|batch| schema_adapter.adapt_batch(batch)))
+            {
                 Some(Ok(batch)) => {
                     total_rows += batch.num_rows();
                     send_result(&response_tx, Ok(batch))?;
@@ -930,6 +1186,51 @@ fn read_files(
     Ok(())
 }
 
+/// Splits the files into at most `n` partitions. If there are fewer files than
+/// partitions, the row groups of each file are divided into contiguous ranges that
+/// are read by separate partitions, so that a few large files are still read in
+/// parallel.
+fn split_row_groups(
+    filenames: &[String],
+    n: usize,
+) -> Result<Vec<(Vec<String>, Option<Range<usize>>)>> {
+    if filenames.is_empty() || filenames.len() >= n {
+        return Ok(split_files(filenames, n)
+            .into_iter()
+            .map(|chunk| (chunk.to_vec(), None))
+            .collect());
+    }
+
+    let ranges_per_file = n / filenames.len();
+    let mut partitions = Vec::with_capacity(n);
+    for filename in filenames {
+        let file = File::open(filename)?;
+        let num_row_groups = SerializedFileReader::new(file)?.metadata().num_row_groups();
+        if ranges_per_file < 2 || num_row_groups < 2 {
+            partitions.push((vec![filename.clone()], None));
+        } else {
+            partitions.extend(
+                split_range(num_row_groups, ranges_per_file)
+                    .into_iter()
+                    .map(|range| (vec![filename.clone()], Some(range))),
+            );
+        }
+    }
+    Ok(partitions)
+}
+
+/// Splits `0..len` into at most `n` contiguous ranges of (nearly) equal length
+fn split_range(len: usize, n: usize) -> Vec<Range<usize>> {
+    let mut chunk_size = len / n;
+    if len % n > 0 {
+        chunk_size += 1;
+    }
+    (0..len)
+        .step_by(chunk_size)
+        .map(|start| start..len.min(start + chunk_size))
+        .collect()
+}
+
 fn split_files(filenames: &[String], n: usize) -> Vec<&[String]> {
     let mut chunk_size = filenames.len() / n;
     if filenames.len() % n > 0 {
@@ -1007,6 +1308,176 @@ mod tests {
         assert_eq!(1, chunks[4].len());
     }
 
+    #[test]
+    fn test_split_range() {
+        assert_eq!(vec![0..10], split_range(10, 1));
+        assert_eq!(vec![0..5, 5..10], split_range(10, 2));
+        assert_eq!(vec![0..4, 4..8, 8..10], split_range(10, 3));
+        assert_eq!(vec![0..1, 1..2, 2..3], split_range(3, 8));
+    }
+
+    #[tokio::test]
+    async fn split_single_file_by_row_groups() -> Result<()> {
+        use arrow::array::Int32Array;
+        use parquet::arrow::ArrowWriter;
+        use parquet::file::properties::WriterProperties;
+
+        let tmp_dir = tempfile::TempDir::new()?;
+        let filename = tmp_dir.path().join("row_groups.parquet");
+        let filename = filename.to_str().unwrap();
+
+        // 8 rows in 4 row groups of 2 rows each
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(filename)?, schema.clone(), Some(props))?;
+        for i in 0..4 {
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int32Array::from(vec![i * 2, i * 2 + 1]))],
+            )?;
+            writer.write(&batch)?;
+        }
+        writer.close()?;
+
+        let parquet_exec =
+            ParquetExec::try_from_files(&[filename], None, None, 1024, 2, None)?;
+        assert_eq!(parquet_exec.output_partitioning().partition_count(), 2);
+        assert_eq!(parquet_exec.partitions()[0].row_groups(), Some(&(0..2)));
+        assert_eq!(parquet_exec.partitions()[1].row_groups(), Some(&(2..4)));
+        assert_eq!(parquet_exec.statistics().num_rows, Some(8));
+
+        let mut values = vec![];
+        for partition in 0..2 {
+            assert_eq!(
+                parquet_exec.partitions()[partition].statistics().num_rows,
+                Some(4)
+            );
+            let batches = common::collect(parquet_exec.execute(partition).await?).await?;
+            for batch in batches {
+                let array = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap();
+                values.extend_from_slice(array.values());
+            }
+        }
+        assert_eq!(values, (0..8).collect::<Vec<_>>());
+
+        // more partitions than row groups
+        let parquet_exec =
+            ParquetExec::try_from_files(&[filename], None, None, 1024, 8, None)?;
+        assert_eq!(parquet_exec.output_partitioning().partition_count(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_files_with_different_schemas() -> Result<()> {
+        use arrow::array::{Float64Array, Int32Array, Int64Array, StringArray};
+        use parquet::arrow::ArrowWriter;
+
+        let tmp_dir = tempfile::TempDir::new()?;
+        let write = |name: &str, batch: RecordBatch| -> Result<String> {
+            let filename = tmp_dir.path().join(name);
+            let mut writer =
+                ArrowWriter::try_new(File::create(&filename)?, batch.schema(), None)?;
+            writer.write(&batch)?;
+            writer.close()?;
+            Ok(filename.to_str().unwrap().to_owned())
+        };
+        let file1 = write(
+            "1.parquet",
+            RecordBatch::try_new(
+                Arc::new(Schema::new(vec![
+                    Field::new("a", DataType::Int32, false),
+                    Field::new("b", DataType::Utf8, false),
+                ])),
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2])),
+                    Arc::new(StringArray::from(vec!["x", "y"])),
+                ],
+            )?,
+        )?;
+        // other column order, no column a and an extra column
+        let file2 = write(
+            "2.parquet",
+            RecordBatch::try_new(
+                Arc::new(Schema::new(vec![
+                    Field::new("c", DataType::Float64, false),
+                    Field::new("b", DataType::Utf8, false),
+                    Field::new("d", DataType::Int32, false),
+                ])),
+                vec![
+                    Arc::new(Float64Array::from(vec![1.5])),
+                    Arc::new(StringArray::from(vec!["z"])),
+                    Arc::new(Int32Array::from(vec![0])),
+                ],
+            )?,
+        )?;
+
+        let filenames = [file1.as_str(), file2.as_str()];
+        // the schemas of the files cannot be merged without a table schema
+        assert!(
+            ParquetExec::try_from_files(&filenames, None, None, 1024, 1, None).is_err()
+        );
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, false),
+            Field::new("c", DataType::Float64, true),
+        ]));
+        let parquet_exec = ParquetExec::try_from_files_with_schema(
+            &filenames,
+            Some(schema.clone()),
+            Some(vec![2, 0]),
+            None,
+            1024,
+            1,
+            None,
+        )?;
+        assert_eq!(parquet_exec.table_schema(), schema);
+        let batches = common::collect(parquet_exec.execute(0).await?).await?;
+        assert_eq!(2, batches.len());
+        for batch in &batches {
+            assert_eq!(parquet_exec.schema(), batch.schema());
+        }
+        let c = batches[0].column(0);
+        assert_eq!(2, c.null_count());
+        let a = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(a.values(), &[1, 2]);
+        let c = batches[1]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(c.values(), &[1.5]);
+        assert_eq!(1, batches[1].column(1).null_count());
+
+        // a column that cannot be null must be in every file
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, false)]));
+        let parquet_exec = ParquetExec::try_from_files_with_schema(
+            &filenames,
+            Some(schema),
+            None,
+            None,
+            1024,
+            1,
+            None,
+        )?;
+        assert!(common::collect(parquet_exec.execute(0).await?)
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test() -> Result<()> {
         let testdata = crate::test_util::parquet_test_data();
@@ -1226,6 +1697,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn row_group_predicate_null_count() -> Result<()> {
+        use crate::logical_plan::col;
+        let expr = col("c1").is_null();
+        let schema = Arc::new(Schema::new(vec![Field::new("c1", DataType::Int32, true)]));
+        let predicate_builder = PruningPredicate::try_new(&expr, schema)?;
+
+        let schema_descr = get_test_schema_descr(vec![("c1", PhysicalType::INT32)]);
+        let rgm1 = get_row_group_meta_data(
+            &schema_descr,
+            vec![ParquetStatistics::int32(Some(1), Some(10), None, 0, false)],
+        );
+        let rgm2 = get_row_group_meta_data(
+            &schema_descr,
+            vec![ParquetStatistics::int32(Some(11), Some(20), None, 3, false)],
+        );
+        let row_group_metadata = vec![rgm1, rgm2];
+        let row_group_predicate = build_row_group_predicate(
+            &predicate_builder,
+            ParquetPartitionMetrics::new(),
+            &row_group_metadata,
+        );
+        let row_group_filter = row_group_metadata
+            .iter()
+            .enumerate()
+            .map(|(i, g)| row_group_predicate(g, i))
+            .collect::<Vec<_>>();
+        // only the second row group has nulls
+        assert_eq!(row_group_filter, vec![false, true]);
+
+        Ok(())
+    }
+
     fn get_row_group_meta_data(
         schema_descr: &SchemaDescPtr,
         column_statistics: Vec<ParquetStatistics>,
@@ -0,0 +1,527 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Execution plan that writes its input to Parquet files, and the options of the
+//! written files
+
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::UInt64Array;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use futures::StreamExt;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, Encoding};
+use parquet::file::properties::WriterProperties;
+use parquet::schema::types::ColumnPath;
+use tokio::task::{self, JoinHandle};
+
+use super::memory::MemoryStream;
+use super::{DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream};
+use crate::error::{DataFusionError, Result};
+
+/// Options of a column of written Parquet files, which override the options of the
+/// files for the column
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParquetColumnOptions {
+    /// Compression of the column
+    pub compression: Option<Compression>,
+    /// Encoding of the column's values when they are not dictionary encoded
+    pub encoding: Option<Encoding>,
+    /// Whether the column is dictionary encoded
+    pub dictionary_enabled: Option<bool>,
+    /// Whether statistics are written for the column
+    pub statistics_enabled: Option<bool>,
+}
+
+/// Parquet write options
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParquetWriteOptions {
+    /// Compression of the columns. Defaults to uncompressed.
+    pub compression: Compression,
+    /// Encoding of the values of columns that are not dictionary encoded. Defaults to
+    /// the encoding that the writer picks for the type of the column.
+    pub encoding: Option<Encoding>,
+    /// Whether columns are dictionary encoded, which falls back to plain encoding when
+    /// the dictionary of a column chunk grows too large. Defaults to true.
+    pub dictionary_enabled: bool,
+    /// Whether the minimum, maximum and null count of the column chunks and pages are
+    /// written, which readers use to skip row groups. Defaults to true.
+    pub statistics_enabled: bool,
+    /// Options of single columns, by column path, where the fields of nested columns
+    /// are separated by dots
+    pub columns: BTreeMap<String, ParquetColumnOptions>,
+}
+
+impl Default for ParquetWriteOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::UNCOMPRESSED,
+            encoding: None,
+            dictionary_enabled: true,
+            statistics_enabled: true,
+            columns: BTreeMap::new(),
+        }
+    }
+}
+
+impl ParquetWriteOptions {
+    /// Create Parquet write options with default values
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create Parquet write options from key value pairs, such as the options of
+    /// `COPY ... TO ... OPTIONS (...)`, see [ParquetWriteOptions::set]
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut write_options = Self::new();
+        for (key, value) in options {
+            write_options.set(key, value)?;
+        }
+        Ok(write_options)
+    }
+
+    /// Specify the compression of the columns
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Specify the encoding of the values of columns that are not dictionary encoded
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Specify whether columns are dictionary encoded
+    pub fn dictionary_enabled(mut self, dictionary_enabled: bool) -> Self {
+        self.dictionary_enabled = dictionary_enabled;
+        self
+    }
+
+    /// Specify whether column statistics are written
+    pub fn statistics_enabled(mut self, statistics_enabled: bool) -> Self {
+        self.statistics_enabled = statistics_enabled;
+        self
+    }
+
+    /// Specify the compression of a column
+    pub fn column_compression(mut self, column: &str, compression: Compression) -> Self {
+        self.column(column).compression = Some(compression);
+        self
+    }
+
+    /// Specify the encoding of the values of a column that are not dictionary encoded
+    pub fn column_encoding(mut self, column: &str, encoding: Encoding) -> Self {
+        self.column(column).encoding = Some(encoding);
+        self
+    }
+
+    /// Specify whether a column is dictionary encoded
+    pub fn column_dictionary_enabled(
+        mut self,
+        column: &str,
+        dictionary_enabled: bool,
+    ) -> Self {
+        self.column(column).dictionary_enabled = Some(dictionary_enabled);
+        self
+    }
+
+    /// Specify whether statistics are written for a column
+    pub fn column_statistics_enabled(
+        mut self,
+        column: &str,
+        statistics_enabled: bool,
+    ) -> Self {
+        self.column(column).statistics_enabled = Some(statistics_enabled);
+        self
+    }
+
+    fn column(&mut self, column: &str) -> &mut ParquetColumnOptions {
+        self.columns.entry(column.to_owned()).or_default()
+    }
+
+    /// Sets an option from its name and value. The options of a single column are
+    /// named `<option>::<column>`, such as `compression::a`.
+    ///
+    /// * `compression`: one of `uncompressed`, `snappy`, `gzip`, `lzo`, `brotli`,
+    ///   `lz4` or `zstd`
+    /// * `encoding`: one of `plain`, `rle`, `delta_binary_packed`,
+    ///   `delta_length_byte_array` or `delta_byte_array`
+    /// * `dictionary_enabled`: `true` or `false`
+    /// * `statistics_enabled`: `true` or `false`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let key = key.to_lowercase();
+        let (name, column) = match key.split_once("::") {
+            Some((name, column)) => (name, Some(column)),
+            None => (key.as_str(), None),
+        };
+        match (name, column) {
+            ("compression", None) => self.compression = parse_compression(value)?,
+            ("compression", Some(column)) => {
+                self.column(column).compression = Some(parse_compression(value)?)
+            }
+            ("encoding", None) => self.encoding = Some(parse_encoding(value)?),
+            ("encoding", Some(column)) => {
+                self.column(column).encoding = Some(parse_encoding(value)?)
+            }
+            ("dictionary_enabled", None) => {
+                self.dictionary_enabled = parse_bool(&key, value)?
+            }
+            ("dictionary_enabled", Some(column)) => {
+                self.column(column).dictionary_enabled = Some(parse_bool(&key, value)?)
+            }
+            ("statistics_enabled", None) => {
+                self.statistics_enabled = parse_bool(&key, value)?
+            }
+            ("statistics_enabled", Some(column)) => {
+                self.column(column).statistics_enabled = Some(parse_bool(&key, value)?)
+            }
+            ("bloom_filter_enabled", _) => {
+                return Err(DataFusionError::NotImplemented(
+                    "Writing Parquet bloom filters is not supported".to_owned(),
+                ))
+            }
+            _ => {
+                return Err(DataFusionError::Plan(format!(
+                    "Unsupported option {} for Parquet files",
+                    key
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// The properties of the Parquet writer that writes files with these options
+    pub fn writer_properties(&self) -> WriterProperties {
+        let mut builder = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_dictionary_enabled(self.dictionary_enabled)
+            .set_statistics_enabled(self.statistics_enabled);
+        if let Some(encoding) = self.encoding {
+            builder = builder.set_encoding(encoding);
+        }
+        for (column, options) in &self.columns {
+            let path = || ColumnPath::new(column.split('.').map(String::from).collect());
+            if let Some(compression) = options.compression {
+                builder = builder.set_column_compression(path(), compression);
+            }
+            if let Some(encoding) = options.encoding {
+                builder = builder.set_column_encoding(path(), encoding);
+            }
+            if let Some(dictionary_enabled) = options.dictionary_enabled {
+                builder =
+                    builder.set_column_dictionary_enabled(path(), dictionary_enabled);
+            }
+            if let Some(statistics_enabled) = options.statistics_enabled {
+                builder =
+                    builder.set_column_statistics_enabled(path(), statistics_enabled);
+            }
+        }
+        builder.build()
+    }
+}
+
+fn parse_compression(value: &str) -> Result<Compression> {
+    match value.to_lowercase().as_str() {
+        "uncompressed" | "none" => Ok(Compression::UNCOMPRESSED),
+        "snappy" => Ok(Compression::SNAPPY),
+        "gzip" => Ok(Compression::GZIP),
+        "lzo" => Ok(Compression::LZO),
+        "brotli" => Ok(Compression::BROTLI),
+        "lz4" => Ok(Compression::LZ4),
+        "zstd" => Ok(Compression::ZSTD),
+        _ => Err(DataFusionError::Plan(format!(
+            "Unsupported Parquet compression '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_encoding(value: &str) -> Result<Encoding> {
+    // dictionary encoding is enabled through dictionary_enabled, which the writer
+    // falls back from to the encoding of the column
+    match value.to_lowercase().as_str() {
+        "plain" => Ok(Encoding::PLAIN),
+        "rle" => Ok(Encoding::RLE),
+        "delta_binary_packed" => Ok(Encoding::DELTA_BINARY_PACKED),
+        "delta_length_byte_array" => Ok(Encoding::DELTA_LENGTH_BYTE_ARRAY),
+        "delta_byte_array" => Ok(Encoding::DELTA_BYTE_ARRAY),
+        _ => Err(DataFusionError::Plan(format!(
+            "Unsupported Parquet encoding '{}'",
+            value
+        ))),
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    value.parse().map_err(|_| {
+        DataFusionError::Plan(format!("Invalid value '{}' for option {}", value, key))
+    })
+}
+
+/// Execution plan that writes each partition of its input to a Parquet file in a new
+/// directory, named `part-<partition>.parquet`. Its output is the number of written
+/// rows.
+#[derive(Debug)]
+pub struct ParquetWriterExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// The directory that the files are written to
+    path: String,
+    options: ParquetWriteOptions,
+    /// The schema of the number of written rows
+    schema: SchemaRef,
+}
+
+impl ParquetWriterExec {
+    /// Create an execution plan that writes the partitions of `input` to the
+    /// directory `path`, which must not exist
+    pub fn new(
+        input: Arc<dyn ExecutionPlan>,
+        path: impl Into<String>,
+        options: ParquetWriteOptions,
+    ) -> Self {
+        Self {
+            input,
+            path: path.into(),
+            options,
+            schema: Arc::new(Self::count_schema()),
+        }
+    }
+
+    /// The schema of the output of writing files, a single row with the number of
+    /// written rows
+    pub fn count_schema() -> Schema {
+        Schema::new(vec![Field::new("count", DataType::UInt64, false)])
+    }
+
+    /// The directory that the files are written to
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The options of the written files
+    pub fn options(&self) -> &ParquetWriteOptions {
+        &self.options
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for ParquetWriterExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(ParquetWriterExec::new(
+                children[0].clone(),
+                self.path.clone(),
+                self.options.clone(),
+            ))),
+            _ => Err(DataFusionError::Internal(
+                "ParquetWriterExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        if 0 != partition {
+            return Err(DataFusionError::Internal(format!(
+                "ParquetWriterExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let dir = Path::new(&self.path);
+        fs::create_dir(dir).map_err(|e| {
+            DataFusionError::Execution(format!(
+                "Could not create directory {}: {:?}",
+                self.path, e
+            ))
+        })?;
+        let properties = self.options.writer_properties();
+        let mut tasks = vec![];
+        for i in 0..self.input.output_partitioning().partition_count() {
+            let file = fs::File::create(dir.join(format!("part-{}.parquet", i)))?;
+            let mut writer = ArrowWriter::try_new(
+                file,
+                self.input.schema(),
+                Some(properties.clone()),
+            )?;
+            let mut stream = self.input.execute(i).await?;
+            let handle: JoinHandle<Result<u64>> = task::spawn(async move {
+                let mut num_rows = 0;
+                while let Some(batch) = stream.next().await {
+                    let batch = batch?;
+                    num_rows += batch.num_rows() as u64;
+                    writer.write(&batch)?;
+                }
+                writer.close()?;
+                Ok(num_rows)
+            });
+            tasks.push(handle);
+        }
+        let mut count = 0;
+        for result in futures::future::join_all(tasks).await {
+            count += result.map_err(|e| DataFusionError::Execution(e.to_string()))??;
+        }
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![Arc::new(UInt64Array::from(vec![count]))],
+        )?;
+        Ok(Box::pin(MemoryStream::try_new(
+            vec![batch],
+            self.schema.clone(),
+            None,
+        )?))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(f, "ParquetWriterExec: path={}", self.path)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physical_plan::collect;
+    use crate::physical_plan::memory::MemoryExec;
+    use arrow::array::{Int32Array, StringArray};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn parse_options() -> Result<()> {
+        let options: HashMap<String, String> = vec![
+            ("compression", "zstd"),
+            ("dictionary_enabled", "false"),
+            ("compression::a", "snappy"),
+            ("Encoding::b.c", "delta_binary_packed"),
+            ("statistics_enabled::b.c", "false"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+        let expected = ParquetWriteOptions::new()
+            .compression(Compression::ZSTD)
+            .dictionary_enabled(false)
+            .column_compression("a", Compression::SNAPPY)
+            .column_encoding("b.c", Encoding::DELTA_BINARY_PACKED)
+            .column_statistics_enabled("b.c", false);
+        assert_eq!(ParquetWriteOptions::from_options(&options)?, expected);
+
+        let mut options = ParquetWriteOptions::new();
+        assert!(options.set("compression", "snappy2").is_err());
+        assert!(options.set("encoding", "plain_dictionary").is_err());
+        assert!(options.set("encoding::a", "rle_dictionary").is_err());
+        assert!(options.set("dictionary_enabled", "yes").is_err());
+        assert!(options.set("row_group_size", "10").is_err());
+        assert!(matches!(
+            options.set("bloom_filter_enabled::a", "true"),
+            Err(DataFusionError::NotImplemented(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn write_files() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["x", "y", "x"])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone()], vec![batch]],
+            schema,
+            None,
+        )?);
+        let tmp_dir = tempfile::TempDir::new()?;
+        let path = tmp_dir.path().join("out");
+        let options = ParquetWriteOptions::new()
+            .compression(Compression::SNAPPY)
+            .column_compression("b", Compression::ZSTD)
+            .column_dictionary_enabled("a", false)
+            .column_statistics_enabled("a", false);
+        let plan = Arc::new(ParquetWriterExec::new(
+            input,
+            path.to_str().unwrap(),
+            options,
+        ));
+
+        let results = collect(plan.clone()).await?;
+        let count = results[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(count.value(0), 6);
+
+        for partition in 0..2 {
+            let file = fs::File::open(path.join(format!("part-{}.parquet", partition)))?;
+            let reader = SerializedFileReader::new(file)?;
+            let row_group = reader.metadata().row_group(0);
+            assert_eq!(row_group.num_rows(), 3);
+            let a = row_group.column(0);
+            assert_eq!(a.compression(), Compression::SNAPPY);
+            assert!(!a.encodings().contains(&Encoding::PLAIN_DICTIONARY));
+            assert!(a.statistics().is_none());
+            let b = row_group.column(1);
+            assert_eq!(b.compression(), Compression::ZSTD);
+            assert!(b.encodings().contains(&Encoding::PLAIN_DICTIONARY));
+            assert!(b.statistics().is_some());
+        }
+
+        // the directory must not exist
+        assert!(collect(plan).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Hash functions that assign the rows of a
+//! [`Partitioning::Hash`](super::Partitioning::Hash) to partitions.
+//!
+//! A row is sent to partition `hash % n` of the `n` partitions, where `hash` is the
+//! hash of the values of its partitioning expressions under the [`HashScheme`] of the
+//! operator that partitions the rows, see
+//! [`RepartitionExec`](super::repartition::RepartitionExec::with_hash_scheme).
+//! The default scheme is the hash function of DataFusion's hash tables,
+//! which is fast but not specified, so that the partition of a row may change between
+//! releases. Data that is partitioned by one release and read by another, such as
+//! reused shuffle files, or that is exchanged with other engines should be partitioned
+//! with [`HashScheme::Murmur3V1`], which is specified below and never changes.
+//!
+//! # `murmur3_v1`
+//!
+//! The hash of a row is an unsigned 32 bit integer. It starts as the seed 42, and each
+//! partitioning expression in turn replaces it with the 32 bit x86 variant of
+//! MurmurHash3 of the bytes of its value, seeded with the hash so far. Null values leave
+//! the hash unchanged. The bytes of a value are:
+//!
+//! * `Int8`, `Int16`, `Int32`, `UInt8`, `UInt16`, `UInt32`, `Boolean` and `Date32`: the
+//!   value as a 4 byte little endian integer, sign extended for signed types. `true` is
+//!   1 and `false` is 0.
+//! * `Int64`, `UInt64`, `Date64` and timestamps: the value as an 8 byte little endian
+//!   integer, timestamps in their own unit.
+//! * `Float32` and `Float64`: the little endian IEEE 754 bits of the value, after `-0.0`
+//!   is replaced with `0.0` and every NaN with the quiet NaN `0x7fc00000` or
+//!   `0x7ff8000000000000`.
+//! * `Utf8` and `LargeUtf8`: the UTF-8 bytes of the string. `Binary` and `LargeBinary`:
+//!   the bytes themselves.
+//! * Dictionaries: the bytes of the value that the key refers to.
+//!
+//! The hashes of integers, booleans, dates and floats are those of the `hash` function
+//! of Spark SQL.
+
+use std::fmt;
+
+use ahash::RandomState;
+use arrow::array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Date64Array, Float32Array,
+    Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, LargeBinaryArray,
+    LargeStringArray, StringArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+    TimestampNanosecondArray, TimestampSecondArray, UInt16Array, UInt32Array,
+    UInt64Array, UInt8Array,
+};
+use arrow::datatypes::{DataType, TimeUnit};
+
+use super::hash_utils::create_hashes;
+use crate::error::{DataFusionError, Result};
+
+/// Hash function of the rows of a [`Partitioning::Hash`](super::Partitioning::Hash),
+/// [`HashScheme::Internal`] unless the operator that partitions them specifies another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashScheme {
+    /// The hash function of DataFusion's hash tables, which may change between releases
+    Internal,
+    /// MurmurHash3 of the values of the row, as specified in the
+    /// [module documentation](self)
+    Murmur3V1,
+}
+
+impl Default for HashScheme {
+    fn default() -> Self {
+        HashScheme::Internal
+    }
+}
+
+impl fmt::Display for HashScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HashScheme::Internal => "internal",
+            HashScheme::Murmur3V1 => "murmur3_v1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for HashScheme {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "internal" => Ok(HashScheme::Internal),
+            "murmur3_v1" => Ok(HashScheme::Murmur3V1),
+            _ => Err(DataFusionError::Plan(format!(
+                "Unknown hash scheme '{}', expected internal or murmur3_v1",
+                s
+            ))),
+        }
+    }
+}
+
+/// Seed of the `murmur3_v1` hashes of the rows
+const MURMUR3_V1_SEED: u32 = 42;
+
+/// Creates the hash of every row of `arrays`, the values of the partitioning
+/// expressions, under `scheme`.
+///
+/// The number of rows to hash is determined by `hashes_buffer.len()`.
+/// `hashes_buffer` should be pre-sized appropriately
+pub fn create_partition_hashes<'a>(
+    arrays: &[ArrayRef],
+    scheme: HashScheme,
+    hashes_buffer: &'a mut Vec<u64>,
+) -> Result<&'a mut Vec<u64>> {
+    match scheme {
+        HashScheme::Internal => {
+            let random_state = RandomState::with_seeds(0, 0, 0, 0);
+            create_hashes(arrays, &random_state, hashes_buffer)
+        }
+        HashScheme::Murmur3V1 => {
+            for hash in hashes_buffer.iter_mut() {
+                *hash = MURMUR3_V1_SEED as u64;
+            }
+            for array in arrays {
+                murmur3_v1_hashes(array, hashes_buffer)?;
+            }
+            Ok(hashes_buffer)
+        }
+    }
+}
+
+/// Replaces the hashes of the rows whose value in `array` is not null with the
+/// MurmurHash3 of `$bytes`, the bytes of the value `$value`, seeded with the hash
+macro_rules! hash_values {
+    ($array:ident, $array_type:ident, $hashes:ident, |$value:ident| $bytes:expr) => {{
+        let array = $array.as_any().downcast_ref::<$array_type>().unwrap();
+        for (i, hash) in $hashes.iter_mut().enumerate() {
+            if !array.is_null(i) {
+                let $value = array.value(i);
+                *hash = murmur3_32(&$bytes, *hash as u32) as u64;
+            }
+        }
+    }};
+}
+
+fn murmur3_v1_hashes(array: &ArrayRef, hashes: &mut Vec<u64>) -> Result<()> {
+    match array.data_type() {
+        DataType::Int8 => {
+            hash_values!(array, Int8Array, hashes, |v| (v as i32).to_le_bytes())
+        }
+        DataType::Int16 => {
+            hash_values!(array, Int16Array, hashes, |v| (v as i32).to_le_bytes())
+        }
+        DataType::Int32 => {
+            hash_values!(array, Int32Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Int64 => {
+            hash_values!(array, Int64Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::UInt8 => {
+            hash_values!(array, UInt8Array, hashes, |v| (v as u32).to_le_bytes())
+        }
+        DataType::UInt16 => {
+            hash_values!(array, UInt16Array, hashes, |v| (v as u32).to_le_bytes())
+        }
+        DataType::UInt32 => {
+            hash_values!(array, UInt32Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::UInt64 => {
+            hash_values!(array, UInt64Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Boolean => {
+            hash_values!(array, BooleanArray, hashes, |v| (v as i32).to_le_bytes())
+        }
+        DataType::Date32 => {
+            hash_values!(array, Date32Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Date64 => {
+            hash_values!(array, Date64Array, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            hash_values!(array, TimestampSecondArray, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            hash_values!(array, TimestampMillisecondArray, hashes, |v| v
+                .to_le_bytes())
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            hash_values!(array, TimestampMicrosecondArray, hashes, |v| v
+                .to_le_bytes())
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            hash_values!(array, TimestampNanosecondArray, hashes, |v| v.to_le_bytes())
+        }
+        DataType::Float32 => {
+            hash_values!(array, Float32Array, hashes, |v| float32_bits(v)
+                .to_le_bytes())
+        }
+        DataType::Float64 => {
+            hash_values!(array, Float64Array, hashes, |v| float64_bits(v)
+                .to_le_bytes())
+        }
+        DataType::Utf8 => {
+            hash_values!(array, StringArray, hashes, |v| v.as_bytes())
+        }
+        DataType::LargeUtf8 => {
+            hash_values!(array, LargeStringArray, hashes, |v| v.as_bytes())
+        }
+        DataType::Binary => {
+            hash_values!(array, BinaryArray, hashes, |v| v)
+        }
+        DataType::LargeBinary => {
+            hash_values!(array, LargeBinaryArray, hashes, |v| v)
+        }
+        DataType::Dictionary(_, value_type) => {
+            let values = arrow::compute::cast(array, value_type)?;
+            murmur3_v1_hashes(&values, hashes)?;
+        }
+        data_type => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Hash partitioning with the {} hash scheme does not support {:?} values",
+                HashScheme::Murmur3V1,
+                data_type
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn float32_bits(v: f32) -> u32 {
+    if v == 0.0 {
+        0
+    } else if v.is_nan() {
+        f32::NAN.to_bits()
+    } else {
+        v.to_bits()
+    }
+}
+
+fn float64_bits(v: f64) -> u64 {
+    if v == 0.0 {
+        0
+    } else if v.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        v.to_bits()
+    }
+}
+
+const C1: u32 = 0xcc9e_2d51;
+const C2: u32 = 0x1b87_3593;
+
+/// The 32 bit x86 variant of MurmurHash3 of `data` with the seed `seed`
+pub fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    let mut hash = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        hash ^= mix_k(k);
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .enumerate()
+            .fold(0u32, |k, (i, byte)| k | (*byte as u32) << (8 * i));
+        hash ^= mix_k(k);
+    }
+    hash ^= data.len() as u32;
+    fmix(hash)
+}
+
+fn mix_k(k: u32) -> u32 {
+    k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+fn fmix(mut hash: u32) -> u32 {
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::DictionaryArray;
+    use arrow::datatypes::Int8Type;
+    use std::sync::Arc;
+
+    #[test]
+    fn murmur3_test_vectors() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"", 1), 0x514e_28b7);
+        assert_eq!(murmur3_32(b"", 0xffff_ffff), 0x81f1_6f39);
+        assert_eq!(murmur3_32(b"\0\0\0\0", 0), 0x2362_f9de);
+        assert_eq!(murmur3_32(b"abc", 0), 0xb3dd_93fa);
+        assert_eq!(murmur3_32(b"aaaa", 0x9747_b28c), 0x5a97_808a);
+        assert_eq!(murmur3_32(b"Hello, world!", 0x9747_b28c), 0x2488_4cba);
+        assert_eq!(
+            murmur3_32(b"The quick brown fox jumps over the lazy dog", 0x9747_b28c),
+            0x2fa8_26cd
+        );
+    }
+
+    fn hashes(arrays: Vec<ArrayRef>) -> Result<Vec<u64>> {
+        let mut hashes = vec![0; arrays[0].len()];
+        create_partition_hashes(&arrays, HashScheme::Murmur3V1, &mut hashes)?;
+        Ok(hashes)
+    }
+
+    #[test]
+    fn murmur3_v1_test_vectors() -> Result<()> {
+        // the same as `hash(1)` of Spark SQL, and nulls keep the seed
+        let ints: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(-1)]));
+        assert_eq!(
+            hashes(vec![ints.clone()])?,
+            vec![0xdea5_78e3, 42, 0xa059_0e3d]
+        );
+        let longs: ArrayRef = Arc::new(Int64Array::from(vec![1, 0]));
+        assert_eq!(hashes(vec![longs])?, vec![0x99f0_149d, 0x9c67_b85d]);
+        let bools: ArrayRef = Arc::new(BooleanArray::from(vec![true, false]));
+        assert_eq!(hashes(vec![bools])?, vec![0xdea5_78e3, 0x379f_ae8f]);
+        // -0.0 is 0.0
+        let doubles: ArrayRef = Arc::new(Float64Array::from(vec![0.0, -0.0, 1.5]));
+        assert_eq!(
+            hashes(vec![doubles])?,
+            vec![0x9c67_b85d, 0x9c67_b85d, 0x4cef_7de5]
+        );
+        let strings: ArrayRef =
+            Arc::new(StringArray::from(vec![Some("hello"), Some(""), None]));
+        assert_eq!(hashes(vec![strings])?, vec![0xe2db_d2e1, 0x087f_cd5c, 42]);
+
+        // the hash of each column seeds the hash of the next one
+        let strings: ArrayRef =
+            Arc::new(StringArray::from(vec![Some("hello"), None, Some("a")]));
+        assert_eq!(
+            hashes(vec![ints, strings])?,
+            vec![0x49bb_d9d4, 42, 0xb357_fd94]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn murmur3_v1_dictionaries() -> Result<()> {
+        let dict: DictionaryArray<Int8Type> = vec![Some("hello"), None, Some("hello")]
+            .into_iter()
+            .collect();
+        let strings: ArrayRef =
+            Arc::new(StringArray::from(vec![Some("hello"), None, Some("hello")]));
+        assert_eq!(hashes(vec![Arc::new(dict)])?, hashes(vec![strings])?);
+        Ok(())
+    }
+
+    #[test]
+    fn hash_schemes() -> Result<()> {
+        assert_eq!("murmur3_v1".parse::<HashScheme>()?, HashScheme::Murmur3V1);
+        assert_eq!("INTERNAL".parse::<HashScheme>()?, HashScheme::Internal);
+        assert!("murmur3".parse::<HashScheme>().is_err());
+        assert_eq!(HashScheme::Murmur3V1.to_string(), "murmur3_v1");
+        Ok(())
+    }
+}
@@ -17,28 +17,42 @@
 
 //! Physical query planner
 
+use super::interval_join::{self, IntervalJoinExec, IntervalJoinOn};
 use super::{
     aggregates, cross_join::CrossJoinExec, empty::EmptyExec, expressions::binary,
     functions, hash_join::PartitionMode, udaf, union::UnionExec, windows,
 };
 use crate::execution::context::ExecutionContextState;
 use crate::logical_plan::{
-    unnormalize_cols, DFSchema, Expr, LogicalPlan, Operator,
+    and, unnormalize_cols, DFSchema, Expr, JoinType, LogicalPlan, Operator,
     Partitioning as LogicalPartitioning, PlanType, ToStringifiedPlan,
     UserDefinedLogicalNode,
 };
+use crate::optimizer::hash_build_probe_order::{estimate_num_groups, get_num_rows};
+use crate::optimizer::utils::{expr_to_columns, split_conjunction};
 use crate::physical_optimizer::optimizer::PhysicalOptimizerRule;
+use crate::physical_plan::coalesce_batches::CoalesceBatchesExec;
+use crate::physical_plan::coalesce_partitions::CoalescePartitionsExec;
 use crate::physical_plan::explain::ExplainExec;
 use crate::physical_plan::expressions;
-use crate::physical_plan::expressions::{CaseExpr, Column, Literal, PhysicalSortExpr};
+use crate::physical_plan::expressions::{
+    CaseExpr, Column, FilteredAggregate, Literal, PhysicalSortExpr,
+};
 use crate::physical_plan::filter::FilterExec;
 use crate::physical_plan::hash_aggregate::{AggregateMode, HashAggregateExec};
 use crate::physical_plan::hash_join::HashJoinExec;
 use crate::physical_plan::limit::{GlobalLimitExec, LocalLimitExec};
+use crate::physical_plan::parquet_writer::ParquetWriterExec;
 use crate::physical_plan::projection::ProjectionExec;
 use crate::physical_plan::repartition::RepartitionExec;
 use crate::physical_plan::sort::SortExec;
+use crate::physical_plan::sorted_aggregate::{
+    is_grouped_by_ordering, SortedAggregateExec,
+};
+use crate::physical_plan::type_coercion::check_lossless_comparison;
 use crate::physical_plan::udf;
+use crate::physical_plan::unnest::UnnestExec;
+use crate::physical_plan::window_functions::{BuiltInWindowFunction, WindowFunction};
 use crate::physical_plan::windows::WindowAggExec;
 use crate::physical_plan::{hash_utils, Partitioning};
 use crate::physical_plan::{AggregateExpr, ExecutionPlan, PhysicalExpr, WindowExpr};
@@ -54,6 +68,7 @@ use arrow::datatypes::{Schema, SchemaRef};
 use arrow::{compute::can_cast_types, datatypes::DataType};
 use expressions::col;
 use log::debug;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 fn create_function_physical_name(
@@ -74,6 +89,21 @@ fn create_function_physical_name(
     Ok(format!("{}({}{})", fun, distinct_str, names.join(",")))
 }
 
+fn filtered_aggregate_physical_name(
+    name: String,
+    filter: &Option<Box<Expr>>,
+    input_schema: &DFSchema,
+) -> Result<String> {
+    match filter {
+        Some(filter) => Ok(format!(
+            "{} FILTER (WHERE {})",
+            name,
+            physical_name(filter, input_schema)?
+        )),
+        None => Ok(name),
+    }
+}
+
 fn physical_name(e: &Expr, input_schema: &DFSchema) -> Result<String> {
     match e {
         Expr::Column(c) => Ok(c.name.clone()),
@@ -127,29 +157,51 @@ fn physical_name(e: &Expr, input_schema: &DFSchema) -> Result<String> {
             let expr = physical_name(expr, input_schema)?;
             Ok(format!("{} IS NOT NULL", expr))
         }
+        Expr::Sort {
+            expr,
+            asc,
+            nulls_first,
+        } => {
+            let expr = physical_name(expr, input_schema)?;
+            let order = if *asc { "ASC" } else { "DESC" };
+            let nulls = if *nulls_first { "FIRST" } else { "LAST" };
+            Ok(format!("{} {} NULLS {}", expr, order, nulls))
+        }
         Expr::ScalarFunction { fun, args, .. } => {
             create_function_physical_name(&fun.to_string(), false, args, input_schema)
         }
         Expr::ScalarUDF { fun, args, .. } => {
             create_function_physical_name(&fun.name, false, args, input_schema)
         }
-        Expr::WindowFunction { fun, args, .. } => {
-            create_function_physical_name(&fun.to_string(), false, args, input_schema)
+        Expr::WindowFunction {
+            fun,
+            args,
+            distinct,
+            ..
+        } => {
+            create_function_physical_name(&fun.to_string(), *distinct, args, input_schema)
         }
         Expr::AggregateFunction {
             fun,
             distinct,
             args,
-            ..
+            filter,
         } => {
-            create_function_physical_name(&fun.to_string(), *distinct, args, input_schema)
+            let name = create_function_physical_name(
+                &fun.to_string(),
+                *distinct,
+                args,
+                input_schema,
+            )?;
+            filtered_aggregate_physical_name(name, filter, input_schema)
         }
-        Expr::AggregateUDF { fun, args } => {
+        Expr::AggregateUDF { fun, args, filter } => {
             let mut names = Vec::with_capacity(args.len());
             for e in args {
                 names.push(physical_name(e, input_schema)?);
             }
-            Ok(format!("{}({})", fun.name, names.join(",")))
+            let name = format!("{}({})", fun.name, names.join(","));
+            filtered_aggregate_physical_name(name, filter, input_schema)
         }
         Expr::InList {
             expr,
@@ -245,7 +297,9 @@ impl PhysicalPlanner for DefaultPhysicalPlanner {
             Some(plan) => Ok(plan),
             None => {
                 let plan = self.create_initial_plan(logical_plan, ctx_state)?;
-                self.optimize_internal(plan, ctx_state, |_, _| {})
+                let plan = self.optimize_internal(plan, ctx_state, |_, _| {})?;
+                check_unbounded_inputs(plan.as_ref())?;
+                Ok(plan)
             }
         }
     }
@@ -339,10 +393,16 @@ impl DefaultPhysicalPlanner {
                             )
                         })
                         .collect::<Result<Vec<Arc<dyn PhysicalExpr>>>>()?;
-                    Arc::new(RepartitionExec::try_new(
-                        input_exec,
-                        Partitioning::Hash(partition_keys, ctx_state.config.concurrency),
-                    )?)
+                    Arc::new(
+                        RepartitionExec::try_new(
+                            input_exec,
+                            Partitioning::Hash(
+                                partition_keys,
+                                ctx_state.config.concurrency,
+                            ),
+                        )?
+                        .with_hash_scheme(ctx_state.config.hash_scheme),
+                    )
                 } else {
                     input_exec
                 };
@@ -392,11 +452,14 @@ impl DefaultPhysicalPlanner {
                             _ => unreachable!(),
                         })
                         .collect::<Result<Vec<_>>>()?;
-                    Arc::new(if can_repartition {
-                        SortExec::new_with_partitioning(sort_keys, input_exec, true)
-                    } else {
-                        SortExec::try_new(sort_keys, input_exec)?
-                    })
+                    Arc::new(spilling_sort(
+                        if can_repartition {
+                            SortExec::new_with_partitioning(sort_keys, input_exec, true)
+                        } else {
+                            SortExec::try_new(sort_keys, input_exec)?
+                        },
+                        ctx_state,
+                    ))
                 };
 
                 let physical_input_schema = input_exec.schema();
@@ -455,18 +518,34 @@ impl DefaultPhysicalPlanner {
                     })
                     .collect::<Result<Vec<_>>>()?;
 
-                let initial_aggr = Arc::new(HashAggregateExec::try_new(
-                    AggregateMode::Partial,
-                    groups.clone(),
-                    aggregates.clone(),
-                    input_exec,
-                    physical_input_schema.clone(),
-                )?);
+                if input_exec.unbounded()
+                    && input_exec.output_partitioning().partition_count() == 1
+                    && input_exec.output_ordering().map_or(false, |ordering| {
+                        is_grouped_by_ordering(&groups, &ordering)
+                    })
+                {
+                    // Groups of an unbounded input, such as windows of time over a
+                    // stream, are emitted as soon as the next one starts
+                    return Ok(Arc::new(SortedAggregateExec::try_new(
+                        AggregateMode::SinglePartitioned,
+                        groups,
+                        aggregates,
+                        input_exec,
+                        physical_input_schema,
+                    )?));
+                }
 
-                // update group column indices based on partial aggregate plan evaluation
-                let final_group: Vec<Arc<dyn PhysicalExpr>> = (0..groups.len())
-                    .map(|i| col(&groups[i].1, &initial_aggr.schema()))
-                    .collect::<Result<_>>()?;
+                if input_partitioned_on_group_keys(&input_exec, &groups) {
+                    // All rows of a group are already in the same partition, so a
+                    // single aggregation per partition produces the final result
+                    return Ok(Arc::new(HashAggregateExec::try_new(
+                        AggregateMode::SinglePartitioned,
+                        groups,
+                        aggregates,
+                        input_exec,
+                        physical_input_schema,
+                    )?));
+                }
 
                 // TODO: dictionary type not yet supported in Hash Repartition
                 let contains_dict = groups
@@ -479,18 +558,56 @@ impl DefaultPhysicalPlanner {
                     && ctx_state.config.repartition_aggregations
                     && !contains_dict;
 
+                if can_repartition && !partial_aggregation_is_useful(input, group_expr) {
+                    // Nearly every row is a group of its own, so the rows are hash
+                    // partitioned by the group keys and aggregated only once
+                    let hash_repartition = Arc::new(
+                        RepartitionExec::try_new(
+                            input_exec,
+                            Partitioning::Hash(
+                                groups.iter().map(|(expr, _)| expr.clone()).collect(),
+                                ctx_state.config.concurrency,
+                            ),
+                        )?
+                        .with_hash_scheme(ctx_state.config.hash_scheme),
+                    );
+                    return Ok(Arc::new(HashAggregateExec::try_new(
+                        AggregateMode::SinglePartitioned,
+                        groups,
+                        aggregates,
+                        hash_repartition,
+                        physical_input_schema,
+                    )?));
+                }
+
+                let initial_aggr = Arc::new(HashAggregateExec::try_new(
+                    AggregateMode::Partial,
+                    groups.clone(),
+                    aggregates.clone(),
+                    input_exec,
+                    physical_input_schema.clone(),
+                )?);
+
+                // update group column indices based on partial aggregate plan evaluation
+                let final_group: Vec<Arc<dyn PhysicalExpr>> = (0..groups.len())
+                    .map(|i| col(&groups[i].1, &initial_aggr.schema()))
+                    .collect::<Result<_>>()?;
+
                 let (initial_aggr, next_partition_mode): (
                     Arc<dyn ExecutionPlan>,
                     AggregateMode,
                 ) = if can_repartition {
                     // Divide partial hash aggregates into multiple partitions by hash key
-                    let hash_repartition = Arc::new(RepartitionExec::try_new(
-                        initial_aggr,
-                        Partitioning::Hash(
-                            final_group.clone(),
-                            ctx_state.config.concurrency,
-                        ),
-                    )?);
+                    let hash_repartition = Arc::new(
+                        RepartitionExec::try_new(
+                            initial_aggr,
+                            Partitioning::Hash(
+                                final_group.clone(),
+                                ctx_state.config.concurrency,
+                            ),
+                        )?
+                        .with_hash_scheme(ctx_state.config.hash_scheme),
+                    );
                     // Combine hash aggregates within the partition
                     (hash_repartition, AggregateMode::FinalPartitioned)
                 } else {
@@ -566,15 +683,31 @@ impl DefaultPhysicalPlanner {
             LogicalPlan::Filter {
                 input, predicate, ..
             } => {
+                if let LogicalPlan::CrossJoin {
+                    left,
+                    right,
+                    schema,
+                } = input.as_ref()
+                {
+                    if let Some(plan) = self
+                        .plan_interval_join(predicate, left, right, schema, ctx_state)?
+                    {
+                        return Ok(plan);
+                    }
+                }
                 let physical_input = self.create_initial_plan(input, ctx_state)?;
                 let input_schema = physical_input.as_ref().schema();
                 let input_dfschema = input.as_ref().schema();
-                let runtime_expr = self.create_physical_expr(
+                let mut runtime_expr = self.create_physical_expr(
                     predicate,
                     input_dfschema,
                     &input_schema,
                     ctx_state,
                 )?;
+                if ctx_state.config.fused_predicates {
+                    runtime_expr =
+                        expressions::fuse_predicate(runtime_expr, &input_schema)?;
+                }
                 Ok(Arc::new(FilterExec::try_new(runtime_expr, physical_input)?))
             }
             LogicalPlan::Union { inputs, .. } => {
@@ -610,10 +743,10 @@ impl DefaultPhysicalPlanner {
                         Partitioning::Hash(runtime_expr, *n)
                     }
                 };
-                Ok(Arc::new(RepartitionExec::try_new(
-                    physical_input,
-                    physical_partitioning,
-                )?))
+                Ok(Arc::new(
+                    RepartitionExec::try_new(physical_input, physical_partitioning)?
+                        .with_hash_scheme(ctx_state.config.hash_scheme),
+                ))
             }
             LogicalPlan::Sort { expr, input, .. } => {
                 let physical_input = self.create_initial_plan(input, ctx_state)?;
@@ -643,13 +776,17 @@ impl DefaultPhysicalPlanner {
                     })
                     .collect::<Result<Vec<_>>>()?;
 
-                Ok(Arc::new(SortExec::try_new(sort_expr, physical_input)?))
+                Ok(Arc::new(spilling_sort(
+                    SortExec::try_new(sort_expr, physical_input)?,
+                    ctx_state,
+                )))
             }
             LogicalPlan::Join {
                 left,
                 right,
                 on: keys,
                 join_type,
+                null_equals_null,
                 ..
             } => {
                 let left_df_schema = left.schema();
@@ -666,7 +803,25 @@ impl DefaultPhysicalPlanner {
                     })
                     .collect::<Result<hash_utils::JoinOn>>()?;
 
-                if ctx_state.config.concurrency > 1 && ctx_state.config.repartition_joins
+                if *join_type == JoinType::NullAwareAnti {
+                    // whether a row is produced depends on the whole right side, so it
+                    // is probed as a single partition
+                    let physical_right =
+                        if physical_right.output_partitioning().partition_count() > 1 {
+                            Arc::new(CoalescePartitionsExec::new(physical_right))
+                        } else {
+                            physical_right
+                        };
+                    Ok(Arc::new(HashJoinExec::try_new(
+                        physical_left,
+                        physical_right,
+                        join_on,
+                        join_type,
+                        PartitionMode::CollectLeft,
+                        *null_equals_null,
+                    )?))
+                } else if ctx_state.config.concurrency > 1
+                    && ctx_state.config.repartition_joins
                 {
                     let (left_expr, right_expr) = join_on
                         .iter()
@@ -680,17 +835,30 @@ impl DefaultPhysicalPlanner {
 
                     // Use hash partition by default to parallelize hash joins
                     Ok(Arc::new(HashJoinExec::try_new(
-                        Arc::new(RepartitionExec::try_new(
-                            physical_left,
-                            Partitioning::Hash(left_expr, ctx_state.config.concurrency),
-                        )?),
-                        Arc::new(RepartitionExec::try_new(
-                            physical_right,
-                            Partitioning::Hash(right_expr, ctx_state.config.concurrency),
-                        )?),
+                        Arc::new(
+                            RepartitionExec::try_new(
+                                physical_left,
+                                Partitioning::Hash(
+                                    left_expr,
+                                    ctx_state.config.concurrency,
+                                ),
+                            )?
+                            .with_hash_scheme(ctx_state.config.hash_scheme),
+                        ),
+                        Arc::new(
+                            RepartitionExec::try_new(
+                                physical_right,
+                                Partitioning::Hash(
+                                    right_expr,
+                                    ctx_state.config.concurrency,
+                                ),
+                            )?
+                            .with_hash_scheme(ctx_state.config.hash_scheme),
+                        ),
                         join_on,
                         join_type,
                         PartitionMode::Partitioned,
+                        *null_equals_null,
                     )?))
                 } else {
                     Ok(Arc::new(HashJoinExec::try_new(
@@ -699,6 +867,7 @@ impl DefaultPhysicalPlanner {
                         join_on,
                         join_type,
                         PartitionMode::CollectLeft,
+                        *null_equals_null,
                     )?))
                 }
             }
@@ -707,6 +876,32 @@ impl DefaultPhysicalPlanner {
                 let right = self.create_initial_plan(right, ctx_state)?;
                 Ok(Arc::new(CrossJoinExec::try_new(left, right)?))
             }
+            LogicalPlan::Unnest {
+                input,
+                column,
+                schema,
+            } => {
+                let column_index = input.schema().index_of_column(column)?;
+                let input = self.create_initial_plan(input, ctx_state)?;
+                Ok(Arc::new(UnnestExec::try_new(
+                    input,
+                    column_index,
+                    SchemaRef::new(schema.as_ref().to_owned().into()),
+                )?))
+            }
+            LogicalPlan::CopyTo {
+                input,
+                path,
+                options,
+                ..
+            } => {
+                let input = self.create_initial_plan(input, ctx_state)?;
+                Ok(Arc::new(ParquetWriterExec::new(
+                    input,
+                    path.clone(),
+                    options.clone(),
+                )))
+            }
             LogicalPlan::EmptyRelation {
                 produce_one_row,
                 schema,
@@ -714,7 +909,7 @@ impl DefaultPhysicalPlanner {
                 *produce_one_row,
                 SchemaRef::new(schema.as_ref().to_owned().into()),
             ))),
-            LogicalPlan::Limit { input, n, .. } => {
+            LogicalPlan::Limit { input, n, offset } => {
                 let limit = *n;
                 let input = self.create_initial_plan(input, ctx_state)?;
 
@@ -723,11 +918,14 @@ impl DefaultPhysicalPlanner {
                     input
                 } else {
                     // Apply a LocalLimitExec to each partition. The optimizer will also insert
-                    // a CoalescePartitionsExec between the GlobalLimitExec and LocalLimitExec
-                    Arc::new(LocalLimitExec::new(input, limit))
+                    // a CoalescePartitionsExec between the GlobalLimitExec and LocalLimitExec.
+                    // Each partition may have to produce all the rows that are skipped.
+                    Arc::new(LocalLimitExec::new(input, limit.saturating_add(*offset)))
                 };
 
-                Ok(Arc::new(GlobalLimitExec::new(input, limit)))
+                Ok(Arc::new(
+                    GlobalLimitExec::new(input, limit).with_offset(*offset),
+                ))
             }
             LogicalPlan::CreateExternalTable { .. } => {
                 // There is no default plan for "CREATE EXTERNAL
@@ -738,6 +936,12 @@ impl DefaultPhysicalPlanner {
                     "Unsupported logical plan: CreateExternalTable".to_string(),
                 ))
             }
+            LogicalPlan::CreateFunction { .. } => {
+                // "CREATE FUNCTION" registers the function with the context
+                Err(DataFusionError::Internal(
+                    "Unsupported logical plan: CreateFunction".to_string(),
+                ))
+            }
             LogicalPlan::Explain { .. } => Err(DataFusionError::Internal(
                 "Unsupported logical plan: Explain must be root of the plan".to_string(),
             )),
@@ -784,6 +988,90 @@ impl DefaultPhysicalPlanner {
         }
     }
 
+    /// Plans a filter over a cross join as an [IntervalJoinExec] when one of its
+    /// predicates restricts an expression of one input to an interval of the other,
+    /// e.g. `a.ts BETWEEN b.lo AND b.hi`. The other predicates are evaluated on the
+    /// output of the join. Returns `None` if there is no such predicate.
+    fn plan_interval_join(
+        &self,
+        predicate: &Expr,
+        left: &LogicalPlan,
+        right: &LogicalPlan,
+        join_schema: &DFSchema,
+        ctx_state: &ExecutionContextState,
+    ) -> Result<Option<Arc<dyn ExecutionPlan>>> {
+        let mut predicates = vec![];
+        split_conjunction(predicate, &mut predicates);
+
+        let (condition, remaining) = match find_interval_condition(
+            &predicates,
+            left.schema(),
+            right.schema(),
+            join_schema,
+        )? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        let physical_left = self.create_initial_plan(left, ctx_state)?;
+        let physical_right = self.create_initial_plan(right, ctx_state)?;
+        let left_schema = physical_left.schema();
+        let right_schema = physical_right.schema();
+        let ((interval_dfschema, interval_schema), (point_dfschema, point_schema)) =
+            if condition.intervals_on_left {
+                (
+                    (left.schema(), &left_schema),
+                    (right.schema(), &right_schema),
+                )
+            } else {
+                (
+                    (right.schema(), &right_schema),
+                    (left.schema(), &left_schema),
+                )
+            };
+        let on = IntervalJoinOn {
+            point: self.create_physical_expr(
+                &condition.point,
+                point_dfschema,
+                point_schema,
+                ctx_state,
+            )?,
+            lower: self.create_physical_expr(
+                &condition.lower,
+                interval_dfschema,
+                interval_schema,
+                ctx_state,
+            )?,
+            lower_inclusive: condition.lower_inclusive,
+            upper: self.create_physical_expr(
+                &condition.upper,
+                interval_dfschema,
+                interval_schema,
+                ctx_state,
+            )?,
+            upper_inclusive: condition.upper_inclusive,
+            intervals_on_left: condition.intervals_on_left,
+        };
+        let join: Arc<dyn ExecutionPlan> = Arc::new(IntervalJoinExec::try_new(
+            physical_left,
+            physical_right,
+            on,
+        )?);
+
+        match remaining.into_iter().reduce(and) {
+            Some(filter) => {
+                let runtime_expr = self.create_physical_expr(
+                    &filter,
+                    join_schema,
+                    &join.schema(),
+                    ctx_state,
+                )?;
+                Ok(Some(Arc::new(FilterExec::try_new(runtime_expr, join)?)))
+            }
+            None => Ok(Some(join)),
+        }
+    }
+
     /// Create a physical expression from a logical expression
     pub fn create_physical_expr(
         &self,
@@ -842,6 +1130,9 @@ impl DefaultPhysicalPlanner {
                     input_schema,
                     ctx_state,
                 )?;
+                if ctx_state.config.strict_type_coercion {
+                    check_lossless_comparison(&lhs, op, &rhs, input_schema)?;
+                }
                 binary(lhs, *op, rhs, input_schema)
             }
             Expr::Case {
@@ -993,6 +1284,21 @@ impl DefaultPhysicalPlanner {
                     ctx_state,
                 )?;
 
+                if ctx_state.config.strict_type_coercion {
+                    check_lossless_comparison(
+                        &value_expr,
+                        &Operator::GtEq,
+                        &low_expr,
+                        input_schema,
+                    )?;
+                    check_lossless_comparison(
+                        &value_expr,
+                        &Operator::LtEq,
+                        &high_expr,
+                        input_schema,
+                    )?;
+                }
+
                 // rewrite the between into the two binary operators
                 let binary_expr = binary(
                     binary(value_expr.clone(), Operator::GtEq, low_expr, input_schema)?,
@@ -1092,6 +1398,7 @@ impl DefaultPhysicalPlanner {
                 partition_by,
                 order_by,
                 window_frame,
+                distinct,
             } => {
                 let args = args
                     .iter()
@@ -1137,11 +1444,27 @@ impl DefaultPhysicalPlanner {
                         )),
                     })
                     .collect::<Result<Vec<_>>>()?;
-                if window_frame.is_some() {
-                    return Err(DataFusionError::NotImplemented(
-                            "window expression with window frame definition is not yet supported"
-                                .to_owned(),
-                        ));
+                // of the built-in window functions, only the value functions take the
+                // rows of the window frame into account
+                if window_frame.is_some()
+                    && !matches!(
+                        fun,
+                        WindowFunction::AggregateFunction(_)
+                            | WindowFunction::BuiltInWindowFunction(
+                                BuiltInWindowFunction::FirstValue
+                            )
+                            | WindowFunction::BuiltInWindowFunction(
+                                BuiltInWindowFunction::LastValue
+                            )
+                            | WindowFunction::BuiltInWindowFunction(
+                                BuiltInWindowFunction::NthValue
+                            )
+                    )
+                {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "window frame definitions are only supported by aggregate window functions and FIRST_VALUE, LAST_VALUE and NTH_VALUE, not {}",
+                        fun
+                    )));
                 }
                 windows::create_window_expr(
                     fun,
@@ -1150,6 +1473,7 @@ impl DefaultPhysicalPlanner {
                     &partition_by,
                     &order_by,
                     *window_frame,
+                    *distinct,
                     physical_input_schema,
                 )
             }
@@ -1191,15 +1515,38 @@ impl DefaultPhysicalPlanner {
         physical_input_schema: &Schema,
         ctx_state: &ExecutionContextState,
     ) -> Result<Arc<dyn AggregateExpr>> {
-        match e {
+        let (aggregate, filter) = match e {
             Expr::AggregateFunction {
                 fun,
                 distinct,
                 args,
-                ..
+                filter,
             } => {
+                // the sort expressions of ordered aggregates are their ordering
+                let (ordering, args): (Vec<&Expr>, Vec<&Expr>) =
+                    args.iter().partition(|e| matches!(e, Expr::Sort { .. }));
+                let ordering = ordering
+                    .into_iter()
+                    .map(|e| match e {
+                        Expr::Sort {
+                            expr,
+                            asc,
+                            nulls_first,
+                        } => self.create_physical_sort_expr(
+                            expr,
+                            logical_input_schema,
+                            physical_input_schema,
+                            SortOptions {
+                                descending: !*asc,
+                                nulls_first: *nulls_first,
+                            },
+                            ctx_state,
+                        ),
+                        _ => unreachable!(),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
                 let args = args
-                    .iter()
+                    .into_iter()
                     .map(|e| {
                         self.create_physical_expr(
                             e,
@@ -1209,15 +1556,17 @@ impl DefaultPhysicalPlanner {
                         )
                     })
                     .collect::<Result<Vec<_>>>()?;
-                aggregates::create_aggregate_expr(
+                let aggregate = aggregates::create_aggregate_expr_with_ordering(
                     fun,
                     *distinct,
                     &args,
+                    &ordering,
                     physical_input_schema,
                     name,
-                )
+                )?;
+                (aggregate, filter)
             }
-            Expr::AggregateUDF { fun, args, .. } => {
+            Expr::AggregateUDF { fun, args, filter } => {
                 let args = args
                     .iter()
                     .map(|e| {
@@ -1230,12 +1579,32 @@ impl DefaultPhysicalPlanner {
                     })
                     .collect::<Result<Vec<_>>>()?;
 
-                udaf::create_aggregate_expr(fun, &args, physical_input_schema, name)
+                let aggregate =
+                    udaf::create_aggregate_expr(fun, &args, physical_input_schema, name)?;
+                (aggregate, filter)
             }
-            other => Err(DataFusionError::Internal(format!(
-                "Invalid aggregate expression '{:?}'",
-                other
-            ))),
+            other => {
+                return Err(DataFusionError::Internal(format!(
+                    "Invalid aggregate expression '{:?}'",
+                    other
+                )))
+            }
+        };
+        match filter {
+            Some(filter) => {
+                let filter = self.create_physical_expr(
+                    filter,
+                    logical_input_schema,
+                    physical_input_schema,
+                    ctx_state,
+                )?;
+                Ok(Arc::new(FilteredAggregate::try_new(
+                    aggregate,
+                    filter,
+                    physical_input_schema,
+                )?))
+            }
+            None => Ok(aggregate),
         }
     }
 
@@ -1294,29 +1663,34 @@ impl DefaultPhysicalPlanner {
     ) -> Result<Option<Arc<dyn ExecutionPlan>>> {
         if let LogicalPlan::Explain {
             verbose,
+            format,
             plan,
             stringified_plans,
             schema,
         } = logical_plan
         {
             use PlanType::*;
+            let format = *format;
             let mut stringified_plans = stringified_plans.clone();
 
-            stringified_plans.push(plan.to_stringified(FinalLogicalPlan));
+            stringified_plans.push(plan.to_stringified(FinalLogicalPlan, format));
 
             let input = self.create_initial_plan(plan, ctx_state)?;
 
-            stringified_plans
-                .push(displayable(input.as_ref()).to_stringified(InitialPhysicalPlan));
+            stringified_plans.push(
+                displayable(input.as_ref()).to_stringified(InitialPhysicalPlan, format),
+            );
 
             let input = self.optimize_internal(input, ctx_state, |plan, optimizer| {
                 let optimizer_name = optimizer.name().to_string();
                 let plan_type = OptimizedPhysicalPlan { optimizer_name };
-                stringified_plans.push(displayable(plan).to_stringified(plan_type));
+                stringified_plans
+                    .push(displayable(plan).to_stringified(plan_type, format));
             })?;
 
-            stringified_plans
-                .push(displayable(input.as_ref()).to_stringified(FinalPhysicalPlan));
+            stringified_plans.push(
+                displayable(input.as_ref()).to_stringified(FinalPhysicalPlan, format),
+            );
 
             Ok(Some(Arc::new(ExplainExec::new(
                 SchemaRef::new(schema.as_ref().to_owned().into()),
@@ -1352,6 +1726,199 @@ impl DefaultPhysicalPlanner {
     }
 }
 
+/// Lets the sort spill to the disk manager of the context if the context has a spill
+/// threshold for sorts
+fn spilling_sort(sort: SortExec, ctx_state: &ExecutionContextState) -> SortExec {
+    let runtime_env = &ctx_state.runtime_env;
+    match runtime_env.sort_spill_threshold {
+        Some(threshold) => sort.with_spill(runtime_env.disk_manager.clone(), threshold),
+        None => sort,
+    }
+}
+
+/// Checks that unbounded inputs are only consumed by operators that emit their
+/// results incrementally, as any other operator would wait for the end of an
+/// input that may never come
+fn check_unbounded_inputs(plan: &dyn ExecutionPlan) -> Result<()> {
+    let children = plan.children();
+    if children.iter().any(|child| child.unbounded()) && !supports_unbounded_input(plan) {
+        let display = displayable(plan).indent().to_string();
+        return Err(DataFusionError::Plan(format!(
+            "{} is not supported on unbounded input",
+            display.lines().next().unwrap_or_default().trim()
+        )));
+    }
+    children
+        .iter()
+        .try_for_each(|child| check_unbounded_inputs(child.as_ref()))
+}
+
+/// Returns true if `plan` emits its output as its input arrives
+fn supports_unbounded_input(plan: &dyn ExecutionPlan) -> bool {
+    let plan_any = plan.as_any();
+    plan_any.downcast_ref::<ProjectionExec>().is_some()
+        || plan_any.downcast_ref::<FilterExec>().is_some()
+        || plan_any.downcast_ref::<CoalesceBatchesExec>().is_some()
+        || plan_any.downcast_ref::<CoalescePartitionsExec>().is_some()
+        || plan_any.downcast_ref::<RepartitionExec>().is_some()
+        || plan_any.downcast_ref::<UnionExec>().is_some()
+        || plan_any.downcast_ref::<LocalLimitExec>().is_some()
+        || plan_any.downcast_ref::<GlobalLimitExec>().is_some()
+        || plan_any.downcast_ref::<SortedAggregateExec>().is_some()
+}
+
+/// A predicate restricting an expression of one join input to an interval given by
+/// two expressions of the other input
+struct IntervalCondition {
+    point: Expr,
+    lower: Expr,
+    lower_inclusive: bool,
+    upper: Expr,
+    upper_inclusive: bool,
+    intervals_on_left: bool,
+}
+
+/// Finds a predicate among `predicates` that an [IntervalJoinExec] can evaluate,
+/// either `point BETWEEN lower AND upper` or a pair of comparisons such as
+/// `lower <= point AND point < upper`. Returns the condition together with the
+/// predicates it does not cover.
+fn find_interval_condition(
+    predicates: &[Expr],
+    left: &DFSchema,
+    right: &DFSchema,
+    join_schema: &DFSchema,
+) -> Result<Option<(IntervalCondition, Vec<Expr>)>> {
+    let remaining = |used: &[usize]| {
+        predicates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !used.contains(i))
+            .map(|(_, predicate)| predicate.clone())
+            .collect::<Vec<_>>()
+    };
+
+    for (i, predicate) in predicates.iter().enumerate() {
+        if let Expr::Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        } = predicate
+        {
+            if let Some(condition) = interval_condition(
+                expr,
+                (low.as_ref(), true),
+                (high.as_ref(), true),
+                left,
+                right,
+                join_schema,
+            )? {
+                return Ok(Some((condition, remaining(&[i]))));
+            }
+        }
+    }
+
+    let comparisons = predicates.iter().map(as_less_than).collect::<Vec<_>>();
+    for (i, lower) in comparisons.iter().enumerate() {
+        for (j, upper) in comparisons.iter().enumerate() {
+            if let (
+                Some((lower, point, lower_inclusive)),
+                Some((other, upper, upper_inclusive)),
+            ) = (lower, upper)
+            {
+                if i == j || point != other {
+                    continue;
+                }
+                if let Some(condition) = interval_condition(
+                    point,
+                    (*lower, *lower_inclusive),
+                    (*upper, *upper_inclusive),
+                    left,
+                    right,
+                    join_schema,
+                )? {
+                    return Ok(Some((condition, remaining(&[i, j]))));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns an [IntervalCondition] if `point` only references one input, both bounds
+/// only reference the other input, and all three have the same supported type
+fn interval_condition(
+    point: &Expr,
+    (lower, lower_inclusive): (&Expr, bool),
+    (upper, upper_inclusive): (&Expr, bool),
+    left: &DFSchema,
+    right: &DFSchema,
+    join_schema: &DFSchema,
+) -> Result<Option<IntervalCondition>> {
+    let intervals_on_left = match (
+        input_side(point, left, right)?,
+        input_side(lower, left, right)?,
+        input_side(upper, left, right)?,
+    ) {
+        (Some(point_on_left), Some(lower_on_left), Some(upper_on_left))
+            if point_on_left != lower_on_left && lower_on_left == upper_on_left =>
+        {
+            lower_on_left
+        }
+        _ => return Ok(None),
+    };
+
+    let data_type = point.get_type(join_schema)?;
+    if !interval_join::supports_data_type(&data_type)
+        || lower.get_type(join_schema)? != data_type
+        || upper.get_type(join_schema)? != data_type
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(IntervalCondition {
+        point: point.clone(),
+        lower: lower.clone(),
+        lower_inclusive,
+        upper: upper.clone(),
+        upper_inclusive,
+        intervals_on_left,
+    }))
+}
+
+/// Returns `(a, b, inclusive)` if `expr` compares `a < b` or `a <= b`, in any
+/// direction
+fn as_less_than(expr: &Expr) -> Option<(&Expr, &Expr, bool)> {
+    match expr {
+        Expr::BinaryExpr { left, op, right } => match op {
+            Operator::Lt => Some((left.as_ref(), right.as_ref(), false)),
+            Operator::LtEq => Some((left.as_ref(), right.as_ref(), true)),
+            Operator::Gt => Some((right.as_ref(), left.as_ref(), false)),
+            Operator::GtEq => Some((right.as_ref(), left.as_ref(), true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns `Some(true)` if all columns of `expr` come from the left input,
+/// `Some(false)` if they all come from the right input, and `None` otherwise
+fn input_side(expr: &Expr, left: &DFSchema, right: &DFSchema) -> Result<Option<bool>> {
+    let mut columns = HashSet::new();
+    expr_to_columns(expr, &mut columns)?;
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    let on_left = columns.iter().all(|c| left.index_of_column(c).is_ok());
+    let on_right = columns.iter().all(|c| right.index_of_column(c).is_ok());
+    Ok(match (on_left, on_right) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    })
+}
+
 fn tuple_err<T, R>(value: (Result<T>, Result<R>)) -> Result<(T, R)> {
     match value {
         (Ok(e), Ok(e1)) => Ok((e, e1)),
@@ -1361,9 +1928,55 @@ fn tuple_err<T, R>(value: (Result<T>, Result<R>)) -> Result<(T, R)> {
     }
 }
 
+/// The partial aggregation is skipped when it is estimated to reduce the number of rows
+/// by less than this factor
+const MIN_PARTIAL_AGGREGATION_REDUCTION: usize = 2;
+
+/// Returns false if the statistics of the tables of `input` show that aggregating it by
+/// `group_expr` produces about as many groups as there are rows, in which case a partial
+/// aggregation before the rows are repartitioned would only add work
+fn partial_aggregation_is_useful(input: &LogicalPlan, group_expr: &[Expr]) -> bool {
+    match (get_num_rows(input), estimate_num_groups(input, group_expr)) {
+        (Some(rows), Some(groups)) => {
+            groups.saturating_mul(MIN_PARTIAL_AGGREGATION_REDUCTION) <= rows
+        }
+        _ => true,
+    }
+}
+
+/// Returns true if `input` hash partitions its rows on a non-empty subset of the group
+/// expressions, which guarantees that all rows with the same group key are in the same
+/// partition. Only a [RepartitionExec] is trusted: the operators that pass the
+/// partitioning of their input through, such as projections renaming or reordering its
+/// columns, do not map its expressions to their own columns.
+fn input_partitioned_on_group_keys(
+    input: &Arc<dyn ExecutionPlan>,
+    group_expr: &[(Arc<dyn PhysicalExpr>, String)],
+) -> bool {
+    if input.as_any().downcast_ref::<RepartitionExec>().is_none() {
+        return false;
+    }
+    let is_group_column =
+        |expr: &Arc<dyn PhysicalExpr>| match expr.as_any().downcast_ref::<Column>() {
+            Some(column) => group_expr.iter().any(|(group, _)| {
+                group.as_any().downcast_ref::<Column>() == Some(column)
+            }),
+            None => false,
+        };
+    match input.output_partitioning() {
+        Partitioning::Hash(exprs, _) => {
+            !exprs.is_empty() && exprs.iter().all(is_group_column)
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::datasource::analyzed::AnalyzedTable;
+    use crate::datasource::datasource::{ColumnStatistics, Statistics};
+    use crate::datasource::empty::EmptyTable;
     use crate::logical_plan::{DFField, DFSchema, DFSchemaRef};
     use crate::physical_plan::{csv::CsvReadOptions, expressions, Partitioning};
     use crate::scalar::ScalarValue;
@@ -1644,6 +2257,119 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn hash_agg_group_by_hash_partitioned_input() -> Result<()> {
+        let testdata = crate::test_util::arrow_test_data();
+        let path = format!("{}/csv/aggregate_test_100.csv", testdata);
+
+        let options = CsvReadOptions::new().schema_infer_max_records(100);
+        let logical_plan = LogicalPlanBuilder::scan_csv(path, options, None)?
+            .repartition(LogicalPartitioning::Hash(vec![col("c1")], 4))?
+            .aggregate(vec![col("c1"), col("c2")], vec![sum(col("c3"))])?
+            .build()?;
+
+        let execution_plan = plan(&logical_plan)?;
+        let hash_agg = execution_plan
+            .as_any()
+            .downcast_ref::<HashAggregateExec>()
+            .expect("hash aggregate");
+
+        // the input is already partitioned on the group keys, so there is no need
+        // for a partial and a final aggregate
+        assert_eq!(*hash_agg.mode(), AggregateMode::SinglePartitioned);
+        assert!(hash_agg
+            .input()
+            .as_any()
+            .downcast_ref::<RepartitionExec>()
+            .is_some());
+        assert_eq!("SUM(c3)", hash_agg.schema().field(2).name());
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_agg_group_by_renamed_partition_keys() -> Result<()> {
+        let testdata = crate::test_util::arrow_test_data();
+        let path = format!("{}/csv/aggregate_test_100.csv", testdata);
+
+        let options = CsvReadOptions::new().schema_infer_max_records(100);
+        let logical_plan = LogicalPlanBuilder::scan_csv(path, options, None)?
+            .repartition(LogicalPartitioning::Hash(vec![col("c1")], 4))?
+            // c1 is now a column that the input is not partitioned on
+            .project(vec![
+                col("c2").alias("c1"),
+                col("c1").alias("c2"),
+                col("c3"),
+            ])?
+            .aggregate(vec![col("c1")], vec![sum(col("c3"))])?
+            .build()?;
+
+        let execution_plan = plan(&logical_plan)?;
+        let hash_agg = execution_plan
+            .as_any()
+            .downcast_ref::<HashAggregateExec>()
+            .expect("hash aggregate");
+        assert_ne!(*hash_agg.mode(), AggregateMode::SinglePartitioned);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_agg_skips_partial_aggregation() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let table = |distinct_count| {
+            let statistics = Statistics {
+                num_rows: Some(1000),
+                total_byte_size: None,
+                column_statistics: Some(vec![
+                    ColumnStatistics {
+                        distinct_count: Some(distinct_count),
+                        ..Default::default()
+                    },
+                    ColumnStatistics::default(),
+                ]),
+            };
+            let table = Arc::new(EmptyTable::new(schema.clone()));
+            Arc::new(AnalyzedTable::new(table, statistics))
+        };
+
+        // every row is a group of its own
+        let logical_plan = LogicalPlanBuilder::scan("t", table(1000), None)?
+            .aggregate(vec![col("a")], vec![sum(col("b"))])?
+            .build()?;
+        let execution_plan = plan(&logical_plan)?;
+        let hash_agg = execution_plan
+            .as_any()
+            .downcast_ref::<HashAggregateExec>()
+            .expect("hash aggregate");
+        assert_eq!(*hash_agg.mode(), AggregateMode::SinglePartitioned);
+        assert!(hash_agg
+            .input()
+            .as_any()
+            .downcast_ref::<RepartitionExec>()
+            .is_some());
+
+        // the partial aggregation reduces the rows, as it does without statistics
+        for table in vec![table(10), table(400)] {
+            let logical_plan = LogicalPlanBuilder::scan("t", table, None)?
+                .aggregate(vec![col("a")], vec![sum(col("b"))])?
+                .build()?;
+            let formatted = format!("{:?}", plan(&logical_plan)?);
+            assert!(formatted.contains("FinalPartitioned"));
+        }
+        // the group keys are not known to be nearly unique
+        let logical_plan = LogicalPlanBuilder::scan("t", table(1000), None)?
+            .aggregate(vec![col("b")], vec![sum(col("a"))])?
+            .build()?;
+        let formatted = format!("{:?}", plan(&logical_plan)?);
+        assert!(formatted.contains("FinalPartitioned"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_explain() {
         let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
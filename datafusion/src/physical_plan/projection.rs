@@ -25,6 +25,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::{
     DisplayFormatType, ExecutionPlan, Partitioning, PhysicalExpr,
@@ -33,6 +34,8 @@ use arrow::datatypes::{Field, Schema, SchemaRef};
 use arrow::error::Result as ArrowResult;
 use arrow::record_batch::RecordBatch;
 
+use super::constant::ConstantArrays;
+use super::expressions::{Column, PhysicalSortExpr};
 use super::{RecordBatchStream, SendableRecordBatchStream};
 use async_trait::async_trait;
 
@@ -110,6 +113,37 @@ impl ExecutionPlan for ProjectionExec {
         self.input.output_partitioning()
     }
 
+    /// The longest prefix of the ordering of the input whose columns are projected
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        let mut ordering = vec![];
+        for sort_expr in self.input.output_ordering()? {
+            let projected =
+                sort_expr
+                    .expr
+                    .as_any()
+                    .downcast_ref::<Column>()
+                    .and_then(|column| {
+                        self.expr.iter().enumerate().find(|(_, (e, _))| {
+                            e.as_any()
+                                .downcast_ref::<Column>()
+                                .map_or(false, |c| c.index() == column.index())
+                        })
+                    });
+            match projected {
+                Some((i, (_, name))) => ordering.push(PhysicalSortExpr {
+                    expr: Arc::new(Column::new(name, i)),
+                    options: sort_expr.options,
+                }),
+                None => break,
+            }
+        }
+        if ordering.is_empty() {
+            None
+        } else {
+            Some(ordering)
+        }
+    }
+
     fn with_new_children(
         &self,
         children: Vec<Arc<dyn ExecutionPlan>>,
@@ -129,6 +163,7 @@ impl ExecutionPlan for ProjectionExec {
         Ok(Box::pin(ProjectionStream {
             schema: self.schema.clone(),
             expr: self.expr.iter().map(|x| x.0.clone()).collect(),
+            constants: ConstantArrays::new(self.expr.len()),
             input: self.input.execute(partition).await?,
         }))
     }
@@ -157,17 +192,43 @@ impl ExecutionPlan for ProjectionExec {
             }
         }
     }
+
+    /// Column references keep the statistics of the input column, computed
+    /// expressions have unknown statistics
+    fn statistics(&self) -> Statistics {
+        let input_stats = self.input.statistics();
+        let column_statistics = input_stats.column_statistics.map(|input_cols| {
+            self.expr
+                .iter()
+                .map(|(e, _)| {
+                    e.as_any()
+                        .downcast_ref::<Column>()
+                        .and_then(|c| input_cols.get(c.index()).cloned())
+                        .unwrap_or_default()
+                })
+                .collect()
+        });
+        Statistics {
+            num_rows: input_stats.num_rows,
+            total_byte_size: None,
+            column_statistics,
+        }
+    }
 }
 
 fn batch_project(
     batch: &RecordBatch,
     expressions: &[Arc<dyn PhysicalExpr>],
+    constants: &mut ConstantArrays,
     schema: &SchemaRef,
 ) -> ArrowResult<RecordBatch> {
     expressions
         .iter()
-        .map(|expr| expr.evaluate(batch))
-        .map(|r| r.map(|v| v.into_array(batch.num_rows())))
+        .enumerate()
+        .map(|(i, expr)| {
+            expr.evaluate(batch)
+                .map(|v| constants.into_array(i, v, batch.num_rows()))
+        })
         .collect::<Result<Vec<_>>>()
         .map_or_else(
             |e| Err(DataFusionError::into_arrow_external_error(e)),
@@ -179,6 +240,8 @@ fn batch_project(
 struct ProjectionStream {
     schema: SchemaRef,
     expr: Vec<Arc<dyn PhysicalExpr>>,
+    /// The arrays of the expressions with constant values, such as literals
+    constants: ConstantArrays,
     input: SendableRecordBatchStream,
 }
 
@@ -189,8 +252,14 @@ impl Stream for ProjectionStream {
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        self.input.poll_next_unpin(cx).map(|x| match x {
-            Some(Ok(batch)) => Some(batch_project(&batch, &self.expr, &self.schema)),
+        let this = &mut *self;
+        this.input.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => Some(batch_project(
+                &batch,
+                &this.expr,
+                &mut this.constants,
+                &this.schema,
+            )),
             other => other,
         })
     }
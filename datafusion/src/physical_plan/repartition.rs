@@ -24,8 +24,10 @@ use std::task::{Context, Poll};
 use std::time::Instant;
 use std::{any::Any, vec};
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
-use crate::physical_plan::hash_utils::create_hashes;
+use crate::physical_plan::expressions::PhysicalSortExpr;
+use crate::physical_plan::partition_hash::{create_partition_hashes, HashScheme};
 use crate::physical_plan::{DisplayFormatType, ExecutionPlan, Partitioning, SQLMetric};
 use arrow::record_batch::RecordBatch;
 use arrow::{array::Array, error::Result as ArrowResult};
@@ -54,6 +56,8 @@ pub struct RepartitionExec {
     input: Arc<dyn ExecutionPlan>,
     /// Partitioning scheme to use
     partitioning: Partitioning,
+    /// Hash function of a hash partitioning
+    hash_scheme: HashScheme,
     /// Channels for sending batches from input partitions to output partitions.
     /// Key is the partition number
     channels: Arc<
@@ -107,6 +111,11 @@ impl RepartitionExec {
     pub fn partitioning(&self) -> &Partitioning {
         &self.partitioning
     }
+
+    /// Hash function of a hash partitioning
+    pub fn hash_scheme(&self) -> HashScheme {
+        self.hash_scheme
+    }
 }
 
 #[async_trait]
@@ -130,10 +139,10 @@ impl ExecutionPlan for RepartitionExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(RepartitionExec::try_new(
-                children[0].clone(),
-                self.partitioning.clone(),
-            )?)),
+            1 => Ok(Arc::new(
+                RepartitionExec::try_new(children[0].clone(), self.partitioning.clone())?
+                    .with_hash_scheme(self.hash_scheme),
+            )),
             _ => Err(DataFusionError::Internal(
                 "RepartitionExec wrong number of children".to_string(),
             )),
@@ -144,6 +153,19 @@ impl ExecutionPlan for RepartitionExec {
         self.partitioning.clone()
     }
 
+    /// Round robin repartitioning of a single partition sends each output partition a
+    /// subsequence of the input batches, which keeps their order
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        match self.partitioning {
+            Partitioning::RoundRobinBatch(_)
+                if self.input.output_partitioning().partition_count() == 1 =>
+            {
+                self.input.output_ordering()
+            }
+            _ => None,
+        }
+    }
+
     async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
         // lock mutexes
         let mut channels = self.channels.lock().await;
@@ -165,9 +187,6 @@ impl ExecutionPlan for RepartitionExec {
                     mpsc::unbounded_channel::<Option<ArrowResult<RecordBatch>>>();
                 channels.insert(partition, (sender, receiver));
             }
-            // Use fixed random state
-            let random = ahash::RandomState::with_seeds(0, 0, 0, 0);
-
             // launch one async task per *input* partition
             for i in 0..num_input_partitions {
                 let txs: HashMap<_, _> = channels
@@ -177,11 +196,11 @@ impl ExecutionPlan for RepartitionExec {
 
                 let input_task: JoinHandle<Result<()>> =
                     tokio::spawn(Self::pull_from_input(
-                        random.clone(),
                         self.input.clone(),
                         i,
                         txs.clone(),
                         self.partitioning.clone(),
+                        self.hash_scheme,
                         self.metrics.clone(),
                     ));
 
@@ -212,10 +231,18 @@ impl ExecutionPlan for RepartitionExec {
     ) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default => {
-                write!(f, "RepartitionExec: partitioning={:?}", self.partitioning)
+                write!(f, "RepartitionExec: partitioning={:?}", self.partitioning)?;
+                if self.hash_scheme != HashScheme::default() {
+                    write!(f, ", hash_scheme={}", self.hash_scheme)?;
+                }
+                Ok(())
             }
         }
     }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
 }
 
 impl RepartitionExec {
@@ -227,11 +254,19 @@ impl RepartitionExec {
         Ok(RepartitionExec {
             input,
             partitioning,
+            hash_scheme: HashScheme::default(),
             channels: Arc::new(Mutex::new(HashMap::new())),
             metrics: RepartitionMetrics::new(),
         })
     }
 
+    /// Hash the rows of a hash partitioning with `hash_scheme` rather than the
+    /// default [`HashScheme::Internal`]
+    pub fn with_hash_scheme(mut self, hash_scheme: HashScheme) -> Self {
+        self.hash_scheme = hash_scheme;
+        self
+    }
+
     /// Pulls data from the specified input plan, feeding it to the
     /// output partitions based on the desired partitioning
     ///
@@ -239,11 +274,11 @@ impl RepartitionExec {
     ///
     /// txs hold the output sending channels for each output partition
     async fn pull_from_input(
-        random_state: ahash::RandomState,
         input: Arc<dyn ExecutionPlan>,
         i: usize,
         mut txs: HashMap<usize, UnboundedSender<Option<ArrowResult<RecordBatch>>>>,
         partitioning: Partitioning,
+        hash_scheme: HashScheme,
         metrics: RepartitionMetrics,
     ) -> Result<()> {
         let num_output_partitions = txs.len();
@@ -295,9 +330,10 @@ impl RepartitionExec {
                         })
                         .collect::<Result<Vec<_>>>()?;
                     hashes_buf.clear();
-                    hashes_buf.resize(arrays[0].len(), 0);
+                    hashes_buf.resize(input_batch.num_rows(), 0);
                     // Hash arrays and compute buckets based on number of partitions
-                    let hashes = create_hashes(&arrays, &random_state, hashes_buf)?;
+                    let hashes =
+                        create_partition_hashes(&arrays, hash_scheme, hashes_buf)?;
                     let mut indices = vec![vec![]; num_output_partitions];
                     for (index, hash) in hashes.iter().enumerate() {
                         indices[(*hash % num_output_partitions as u64) as usize]
@@ -436,7 +472,14 @@ mod tests {
     use super::*;
     use crate::{
         assert_batches_sorted_eq,
-        physical_plan::{expressions::col, memory::MemoryExec},
+        logical_plan::Operator,
+        physical_plan::{
+            common::collect,
+            displayable,
+            expressions::{binary, col, lit},
+            memory::MemoryExec,
+        },
+        scalar::ScalarValue,
         test::exec::{BarrierExec, ErrorExec, MockExec},
     };
     use arrow::datatypes::{DataType, Field, Schema};
@@ -526,6 +569,92 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn hash_partition_by_expression() -> Result<()> {
+        let schema = test_schema();
+        let partition = create_vec_batches(&schema, 10);
+        let partitions = vec![partition.clone(), partition];
+
+        // rows with the same key end up in the same partition
+        let key = binary(
+            col("c0", &schema)?,
+            Operator::Modulus,
+            lit(ScalarValue::UInt32(Some(3))),
+            &schema,
+        )?;
+        let output_partitions =
+            repartition(&schema, partitions, Partitioning::Hash(vec![key], 4)).await?;
+        assert_eq!(4, output_partitions.len());
+
+        let mut num_rows = 0;
+        let mut seen_keys = vec![];
+        for partition in output_partitions {
+            let mut keys = partition
+                .iter()
+                .flat_map(|batch| {
+                    let c0 = batch
+                        .column(0)
+                        .as_any()
+                        .downcast_ref::<UInt32Array>()
+                        .unwrap();
+                    c0.values().iter().map(|v| v % 3).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            num_rows += keys.len();
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                assert!(!seen_keys.contains(&key), "key {} is split", key);
+                seen_keys.push(key);
+            }
+        }
+        assert_eq!(8 * 10 * 2, num_rows);
+        assert_eq!(3, seen_keys.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn murmur3_hash_partition() -> Result<()> {
+        let schema = test_schema();
+        let partitions = vec![vec![create_batch(&schema)]];
+
+        // the partitions of the murmur3_v1 hashes of the values
+        let exec = MemoryExec::try_new(&partitions, schema.clone(), None)?;
+        let partitioning = Partitioning::Hash(vec![col("c0", &schema)?], 4);
+        let exec = RepartitionExec::try_new(Arc::new(exec), partitioning)?
+            .with_hash_scheme(HashScheme::Murmur3V1);
+        assert_eq!(
+            "RepartitionExec: partitioning=Hash([Column { name: \"c0\", index: 0 }], 4), \
+             hash_scheme=murmur3_v1\n",
+            displayable(&exec).indent().to_string()
+        );
+        let mut output_partitions = vec![];
+        for i in 0..4 {
+            output_partitions.push(collect(exec.execute(i).await?).await?);
+        }
+        let values = output_partitions
+            .iter()
+            .map(|partition| {
+                partition
+                    .iter()
+                    .flat_map(|batch| {
+                        let c0 = batch
+                            .column(0)
+                            .as_any()
+                            .downcast_ref::<UInt32Array>()
+                            .unwrap();
+                        c0.values().to_vec()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![vec![], vec![6], vec![2, 4, 5], vec![1, 3, 7, 8]]
+        );
+        Ok(())
+    }
+
     fn test_schema() -> Arc<Schema> {
         Arc::new(Schema::new(vec![Field::new("c0", DataType::UInt32, false)]))
     }
@@ -0,0 +1,213 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Adapts the record batches read from a file to the schema of the table that the file
+//! belongs to, when the schema of the file differs from it.
+
+use std::sync::Arc;
+
+use arrow::array::new_null_array;
+use arrow::compute::{can_cast_types, cast};
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+
+use crate::error::{DataFusionError, Result};
+
+/// Maps the columns of a file to the projected columns of a table schema by name, so
+/// that a file whose columns are in another order, have other types or lack some
+/// nullable columns of the table is read as the table schema.
+#[derive(Debug, Clone)]
+pub struct SchemaAdapter {
+    /// The schema of the table
+    table_schema: SchemaRef,
+    /// The schema of the table after the projection has been applied
+    projected_schema: SchemaRef,
+}
+
+impl SchemaAdapter {
+    /// Create an adapter to the columns of `table_schema` selected by `projection`, or
+    /// to all its columns if None
+    pub fn new(table_schema: SchemaRef, projection: Option<&[usize]>) -> Self {
+        let projected_schema = match projection {
+            Some(projection) => Arc::new(Schema::new(
+                projection
+                    .iter()
+                    .map(|i| table_schema.field(*i).clone())
+                    .collect(),
+            )),
+            None => table_schema.clone(),
+        };
+        Self {
+            table_schema,
+            projected_schema,
+        }
+    }
+
+    /// The schema of the table
+    pub fn table_schema(&self) -> SchemaRef {
+        self.table_schema.clone()
+    }
+
+    /// The schema of the adapted record batches
+    pub fn projected_schema(&self) -> SchemaRef {
+        self.projected_schema.clone()
+    }
+
+    /// Does the file have the columns of the table, in the same order and with the
+    /// same types, so that its batches need no adaptation?
+    pub fn matches(&self, file_schema: &Schema) -> bool {
+        file_schema.fields().len() == self.table_schema.fields().len()
+            && file_schema
+                .fields()
+                .iter()
+                .zip(self.table_schema.fields())
+                .all(|(file_field, field)| {
+                    file_field.name() == field.name()
+                        && file_field.data_type() == field.data_type()
+                })
+    }
+
+    /// Returns the indices of the columns of `file_schema` to read, in ascending order.
+    /// Fails if a column of the file cannot be cast to the type of the table column,
+    /// or if the file lacks a projected column that is not nullable.
+    pub fn map_schema(&self, file_schema: &Schema) -> Result<Vec<usize>> {
+        let mut projection = vec![];
+        for field in self.projected_schema.fields() {
+            match file_schema.column_with_name(field.name()) {
+                Some((i, file_field)) => {
+                    if !can_cast_types(file_field.data_type(), field.data_type()) {
+                        return Err(DataFusionError::Execution(format!(
+                            "Cannot cast column {} of the file from {:?} to {:?}",
+                            field.name(),
+                            file_field.data_type(),
+                            field.data_type()
+                        )));
+                    }
+                    projection.push(i);
+                }
+                None if field.is_nullable() => {}
+                None => {
+                    return Err(DataFusionError::Execution(format!(
+                        "Column {} is not nullable but is missing from the file",
+                        field.name()
+                    )));
+                }
+            }
+        }
+        projection.sort_unstable();
+        projection.dedup();
+        Ok(projection)
+    }
+
+    /// Adapts a batch read from a file to the projected schema: its columns are
+    /// reordered by name and cast to the types of the table, and the columns that the
+    /// file lacks are filled with nulls
+    pub fn adapt_batch(&self, batch: RecordBatch) -> ArrowResult<RecordBatch> {
+        if batch.schema() == self.projected_schema {
+            return Ok(batch);
+        }
+        let batch_schema = batch.schema();
+        let columns = self
+            .projected_schema
+            .fields()
+            .iter()
+            .map(|field| match batch_schema.column_with_name(field.name()) {
+                Some((i, _)) if batch.column(i).data_type() == field.data_type() => {
+                    Ok(batch.column(i).clone())
+                }
+                Some((i, _)) => cast(batch.column(i), field.data_type()),
+                None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+            })
+            .collect::<ArrowResult<Vec<_>>>()?;
+        RecordBatch::try_new(self.projected_schema.clone(), columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field};
+
+    fn table_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("b", DataType::Utf8, true),
+            Field::new("c", DataType::Int32, true),
+        ]))
+    }
+
+    #[test]
+    fn map_schema() -> Result<()> {
+        let adapter = SchemaAdapter::new(table_schema(), Some(&[2, 0]));
+        let file_schema = Schema::new(vec![
+            Field::new("c", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+            Field::new("a", DataType::Int32, false),
+        ]);
+        assert_eq!(vec![0, 2], adapter.map_schema(&file_schema)?);
+        assert!(!adapter.matches(&file_schema));
+        assert!(adapter.matches(&table_schema()));
+
+        // c is nullable and may be missing, a may not
+        let file_schema = Schema::new(vec![Field::new("a", DataType::Int64, false)]);
+        assert_eq!(vec![0], adapter.map_schema(&file_schema)?);
+        let file_schema = Schema::new(vec![Field::new("c", DataType::Int32, true)]);
+        assert!(adapter.map_schema(&file_schema).is_err());
+
+        let file_schema = Schema::new(vec![Field::new(
+            "a",
+            DataType::List(Box::new(Field::new("item", DataType::Int64, true))),
+            false,
+        )]);
+        assert!(adapter.map_schema(&file_schema).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn adapt_batch() -> Result<()> {
+        let adapter = SchemaAdapter::new(table_schema(), None);
+        let file_schema = Arc::new(Schema::new(vec![
+            Field::new("b", DataType::Utf8, true),
+            Field::new("a", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            file_schema,
+            vec![
+                Arc::new(StringArray::from(vec!["x", "y"])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )?;
+        let batch = adapter.adapt_batch(batch)?;
+        assert_eq!(adapter.projected_schema(), batch.schema());
+        let a = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(vec![Some(1), Some(2)], a.iter().collect::<Vec<_>>());
+        let b = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(vec![Some("x"), Some("y")], b.iter().collect::<Vec<_>>());
+        assert_eq!(2, batch.column(2).null_count());
+        Ok(())
+    }
+}
@@ -18,20 +18,28 @@
 //! Defines the SORT plan
 
 use super::{RecordBatchStream, SendableRecordBatchStream};
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
+use crate::execution::cpu_pool;
+use crate::execution::disk_manager::{DiskManager, SpillFile};
 use crate::physical_plan::expressions::PhysicalSortExpr;
+use crate::physical_plan::memory::MemoryStream;
+use crate::physical_plan::sort_preserving_merge::SortPreservingMergeStream;
 use crate::physical_plan::{
     common, DisplayFormatType, Distribution, ExecutionPlan, Partitioning, SQLMetric,
 };
+use arrow::array::ArrayRef;
 pub use arrow::compute::SortOptions;
 use arrow::compute::{lexsort_to_indices, take, SortColumn, TakeOptions};
 use arrow::datatypes::SchemaRef;
 use arrow::error::Result as ArrowResult;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
 use arrow::record_batch::RecordBatch;
-use arrow::{array::ArrayRef, error::ArrowError};
 use async_trait::async_trait;
+use futures::channel::mpsc;
 use futures::stream::Stream;
-use futures::Future;
+use futures::{SinkExt, StreamExt};
 use hashbrown::HashMap;
 use pin_project_lite::pin_project;
 use std::any::Any;
@@ -53,6 +61,19 @@ pub struct SortExec {
     sort_time_nanos: Arc<SQLMetric>,
     /// Preserve partitions of input plan
     preserve_partitioning: bool,
+    /// Where and when the sort spills to disk, if it may
+    spill: Option<SortSpill>,
+    /// Number of sorted runs spilled to disk
+    spill_count: Arc<SQLMetric>,
+}
+
+/// Configures a sort to spill sorted runs of its input to disk
+#[derive(Debug, Clone)]
+struct SortSpill {
+    /// Hands out the files that the runs are spilled to
+    disk_manager: Arc<DiskManager>,
+    /// Bytes of input batches that are buffered in memory before they are spilled
+    threshold: usize,
 }
 
 impl SortExec {
@@ -77,9 +98,26 @@ impl SortExec {
             preserve_partitioning,
             output_rows: SQLMetric::counter(),
             sort_time_nanos: SQLMetric::time_nanos(),
+            spill: None,
+            spill_count: SQLMetric::counter(),
         }
     }
 
+    /// Spill sorted runs of the input to files of the `disk_manager` whenever more than
+    /// `threshold` bytes of input batches are buffered, and merge the runs to produce
+    /// the output. The input is sorted in memory otherwise.
+    pub fn with_spill(
+        mut self,
+        disk_manager: Arc<DiskManager>,
+        threshold: usize,
+    ) -> Self {
+        self.spill = Some(SortSpill {
+            disk_manager,
+            threshold,
+        });
+        self
+    }
+
     /// Input schema
     pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
         &self.input
@@ -89,6 +127,11 @@ impl SortExec {
     pub fn expr(&self) -> &[PhysicalSortExpr] {
         &self.expr
     }
+
+    /// Whether the partitioning of the input plan is preserved
+    pub fn preserve_partitioning(&self) -> bool {
+        self.preserve_partitioning
+    }
 }
 
 #[async_trait]
@@ -115,6 +158,10 @@ impl ExecutionPlan for SortExec {
         }
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        Some(self.expr.clone())
+    }
+
     fn required_child_distribution(&self) -> Distribution {
         if self.preserve_partitioning {
             Distribution::UnspecifiedDistribution
@@ -128,10 +175,15 @@ impl ExecutionPlan for SortExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(SortExec::try_new(
-                self.expr.clone(),
-                children[0].clone(),
-            )?)),
+            1 => {
+                let mut sort = SortExec::new_with_partitioning(
+                    self.expr.clone(),
+                    children[0].clone(),
+                    self.preserve_partitioning,
+                );
+                sort.spill = self.spill.clone();
+                Ok(Arc::new(sort))
+            }
             _ => Err(DataFusionError::Internal(
                 "SortExec wrong number of children".to_string(),
             )),
@@ -162,6 +214,8 @@ impl ExecutionPlan for SortExec {
             self.expr.clone(),
             self.output_rows.clone(),
             self.sort_time_nanos.clone(),
+            self.spill.clone(),
+            self.spill_count.clone(),
         )))
     }
 
@@ -182,8 +236,13 @@ impl ExecutionPlan for SortExec {
         let mut metrics = HashMap::new();
         metrics.insert("outputRows".to_owned(), (*self.output_rows).clone());
         metrics.insert("sortTime".to_owned(), (*self.sort_time_nanos).clone());
+        metrics.insert("spillCount".to_owned(), (*self.spill_count).clone());
         metrics
     }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
 }
 
 fn sort_batch(
@@ -222,12 +281,153 @@ fn sort_batch(
     )
 }
 
+/// Sorts the input, spilling sorted runs of it to disk whenever the batches buffered
+/// in memory exceed the threshold of `spill`, and returns the sorted output
+async fn sort_input(
+    mut input: SendableRecordBatchStream,
+    expr: Vec<PhysicalSortExpr>,
+    sort_time: Arc<SQLMetric>,
+    spill: Option<SortSpill>,
+    spill_count: Arc<SQLMetric>,
+) -> Result<SendableRecordBatchStream> {
+    let schema = input.schema();
+    let mut buffered = vec![];
+    let mut buffered_bytes = 0;
+    let mut runs = vec![];
+    // the merged runs are output in batches as large as the largest input batch
+    let mut batch_size = 1;
+    while let Some(batch) = input.next().await {
+        let batch = batch?;
+        batch_size = batch_size.max(batch.num_rows());
+        buffered_bytes += batch_num_bytes(&batch);
+        buffered.push(batch);
+        if let Some(spill) = &spill {
+            if buffered_bytes > spill.threshold {
+                let batches = std::mem::take(&mut buffered);
+                buffered_bytes = 0;
+                let sorted =
+                    sort_batches(batches, schema.clone(), &expr, &sort_time).await?;
+                runs.push(
+                    write_run(sorted, schema.clone(), batch_size, &spill.disk_manager)
+                        .await?,
+                );
+                spill_count.add(1);
+            }
+        }
+    }
+    let sorted = sort_batches(buffered, schema.clone(), &expr, &sort_time).await?;
+    if runs.is_empty() {
+        return Ok(Box::pin(MemoryStream::try_new(
+            sorted.into_iter().collect(),
+            schema,
+            None,
+        )?));
+    }
+
+    let mut streams = runs.into_iter().map(read_run).collect::<Vec<_>>();
+    if let Some(batch) = sorted {
+        // a new channel has room for a message of each sender
+        let (mut sender, receiver) = mpsc::channel(1);
+        sender.try_send(Ok(batch)).unwrap();
+        streams.push(receiver);
+    }
+    Ok(Box::pin(SortPreservingMergeStream::new(
+        streams, schema, &expr, batch_size,
+    )))
+}
+
+/// Sorts the batches into one on the CPU pool, which keeps the tokio worker threads
+/// free for IO
+async fn sort_batches(
+    batches: Vec<RecordBatch>,
+    schema: SchemaRef,
+    expr: &[PhysicalSortExpr],
+    sort_time: &Arc<SQLMetric>,
+) -> Result<Option<RecordBatch>> {
+    let expr = expr.to_vec();
+    let sort_time = sort_time.clone();
+    let sorted = cpu_pool::spawn(move || -> ArrowResult<Option<RecordBatch>> {
+        let now = Instant::now();
+        // combine all record batches into one for each column
+        let combined = common::combine_batches(&batches, schema.clone())?;
+        // sort combined record batch
+        let result = combined
+            .map(|batch| sort_batch(batch, schema, &expr))
+            .transpose()?;
+        sort_time.add(now.elapsed().as_nanos() as usize);
+        Ok(result)
+    })
+    .await??;
+    Ok(sorted)
+}
+
+/// Writes a sorted run to a spill file, in batches of `batch_size` rows so that it can
+/// be merged without reading it entirely
+async fn write_run(
+    sorted: Option<RecordBatch>,
+    schema: SchemaRef,
+    batch_size: usize,
+    disk_manager: &Arc<DiskManager>,
+) -> Result<SpillFile> {
+    let disk_manager = disk_manager.clone();
+    common::spawn_blocking(move || -> Result<SpillFile> {
+        let mut file = disk_manager.create_tmp_file("SortExec")?;
+        let mut writer = FileWriter::try_new(&mut file, &schema)?;
+        if let Some(batch) = sorted {
+            let mut offset = 0;
+            while offset < batch.num_rows() {
+                let len = batch_size.min(batch.num_rows() - offset);
+                writer.write(&batch.slice(offset, len))?;
+                offset += len;
+            }
+        }
+        writer.finish()?;
+        drop(writer);
+        Ok(file)
+    })
+    .await?
+}
+
+/// Reads back a sorted run on a blocking thread. The spill file is deleted once it was
+/// read or the receiver was dropped.
+fn read_run(run: SpillFile) -> mpsc::Receiver<ArrowResult<RecordBatch>> {
+    let (mut sender, receiver) = mpsc::channel(1);
+    tokio::task::spawn_blocking(move || {
+        let reader = run
+            .reopen()
+            .and_then(|file| Ok(FileReader::try_new(file)?))
+            .map_err(DataFusionError::into_arrow_external_error);
+        let reader = match reader {
+            Ok(reader) => reader,
+            Err(e) => {
+                futures::executor::block_on(sender.send(Err(e))).ok();
+                return;
+            }
+        };
+        for batch in reader {
+            if futures::executor::block_on(sender.send(batch)).is_err() {
+                break;
+            }
+        }
+        drop(run);
+    });
+    receiver
+}
+
+/// The size of the buffers of the batch in bytes
+fn batch_num_bytes(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|array| array.get_array_memory_size())
+        .sum()
+}
+
 pin_project! {
     /// stream for sort plan
     struct SortStream {
         #[pin]
-        output: futures::channel::oneshot::Receiver<ArrowResult<Option<RecordBatch>>>,
-        finished: bool,
+        output: mpsc::Receiver<ArrowResult<RecordBatch>>,
         schema: SchemaRef,
         output_rows: Arc<SQLMetric>,
     }
@@ -239,32 +439,31 @@ impl SortStream {
         expr: Vec<PhysicalSortExpr>,
         output_rows: Arc<SQLMetric>,
         sort_time: Arc<SQLMetric>,
+        spill: Option<SortSpill>,
+        spill_count: Arc<SQLMetric>,
     ) -> Self {
-        let (tx, rx) = futures::channel::oneshot::channel();
+        let (mut tx, rx) = mpsc::channel(1);
         let schema = input.schema();
         tokio::spawn(async move {
-            let schema = input.schema();
-            let sorted_batch = common::collect(input)
-                .await
-                .map_err(DataFusionError::into_arrow_external_error)
-                .and_then(move |batches| {
-                    let now = Instant::now();
-                    // combine all record batches into one for each column
-                    let combined = common::combine_batches(&batches, schema.clone())?;
-                    // sort combined record batch
-                    let result = combined
-                        .map(|batch| sort_batch(batch, schema, &expr))
-                        .transpose()?;
-                    sort_time.add(now.elapsed().as_nanos() as usize);
-                    Ok(result)
-                });
-
-            tx.send(sorted_batch)
+            match sort_input(input, expr, sort_time, spill, spill_count).await {
+                Ok(mut sorted) => {
+                    while let Some(batch) = sorted.next().await {
+                        // the stream was dropped if the send fails
+                        if tx.send(batch).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tx.send(Err(DataFusionError::into_arrow_external_error(e)))
+                        .await
+                        .ok();
+                }
+            }
         });
 
         Self {
             output: rx,
-            finished: false,
             schema,
             output_rows,
         }
@@ -275,34 +474,12 @@ impl Stream for SortStream {
     type Item = ArrowResult<RecordBatch>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let output_rows = self.output_rows.clone();
-
-        if self.finished {
-            return Poll::Ready(None);
-        }
-
-        // is the output ready?
         let this = self.project();
-        let output_poll = this.output.poll(cx);
-
-        match output_poll {
-            Poll::Ready(result) => {
-                *this.finished = true;
-
-                // check for error in receiving channel and unwrap actual result
-                let result = match result {
-                    Err(e) => Some(Err(ArrowError::ExternalError(Box::new(e)))), // error receiving
-                    Ok(result) => result.transpose(),
-                };
-
-                if let Some(Ok(batch)) = &result {
-                    output_rows.add(batch.num_rows());
-                }
-
-                Poll::Ready(result)
-            }
-            Poll::Pending => Poll::Pending,
+        let poll = this.output.poll_next(cx);
+        if let Poll::Ready(Some(Ok(batch))) = &poll {
+            this.output_rows.add(batch.num_rows());
         }
+        poll
     }
 }
 
@@ -315,6 +492,7 @@ impl RecordBatchStream for SortStream {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::execution::disk_manager::DiskManagerConfig;
     use crate::physical_plan::coalesce_partitions::CoalescePartitionsExec;
     use crate::physical_plan::expressions::col;
     use crate::physical_plan::memory::MemoryExec;
@@ -325,6 +503,7 @@ mod tests {
     use crate::test;
     use arrow::array::*;
     use arrow::datatypes::*;
+    use arrow::util::pretty::pretty_format_batches;
 
     #[tokio::test]
     async fn test_sort() -> Result<()> {
@@ -380,6 +559,59 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_sort_spill() -> Result<()> {
+        let schema = test::aggr_test_schema();
+        let partitions = 4;
+        let path = test::create_partitioned_csv("aggregate_test_100.csv", partitions)?;
+        let csv = CsvExec::try_new(
+            &path,
+            CsvReadOptions::new().schema(&schema),
+            None,
+            10,
+            None,
+        )?;
+        let input = Arc::new(CoalescePartitionsExec::new(Arc::new(csv)));
+        let expr = vec![
+            PhysicalSortExpr {
+                expr: col("c2", &schema)?,
+                options: SortOptions::default(),
+            },
+            PhysicalSortExpr {
+                expr: col("c7", &schema)?,
+                options: SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                },
+            },
+            PhysicalSortExpr {
+                expr: col("c9", &schema)?,
+                options: SortOptions::default(),
+            },
+        ];
+        let tmp_dir = tempfile::TempDir::new()?;
+        let disk_manager = Arc::new(DiskManager::new(
+            DiskManagerConfig::new().with_local_dirs(vec![tmp_dir.path().to_owned()]),
+        ));
+
+        // every batch is spilled as a run of its own
+        let spilling = Arc::new(
+            SortExec::try_new(expr.clone(), input.clone())?
+                .with_spill(disk_manager.clone(), 1),
+        );
+        let spilled = collect(spilling.clone()).await?;
+        let expected = collect(Arc::new(SortExec::try_new(expr, input)?)).await?;
+        assert_eq!(
+            pretty_format_batches(&spilled)?,
+            pretty_format_batches(&expected)?
+        );
+        assert!(spilling.metrics()["spillCount"].value() > 1);
+        assert_eq!(spilling.metrics()["outputRows"].value(), 100);
+        // the spill files are deleted once they were merged
+        assert_eq!(disk_manager.used_disk_space(), 0);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_lex_sort_by_float() -> Result<()> {
         let schema = Arc::new(Schema::new(vec![
@@ -482,4 +714,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn with_new_children_preserves_partitioning() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let input: Arc<dyn ExecutionPlan> = Arc::new(MemoryExec::try_new(
+            &[vec![], vec![]],
+            schema.clone(),
+            None,
+        )?);
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: col("a", &schema)?,
+            options: SortOptions {
+                descending: false,
+                nulls_first: false,
+            },
+        }];
+        let sort = SortExec::new_with_partitioning(sort_exprs, input.clone(), true);
+        let sort = sort.with_new_children(vec![input])?;
+        assert_eq!(sort.output_partitioning().partition_count(), 2);
+        Ok(())
+    }
 }
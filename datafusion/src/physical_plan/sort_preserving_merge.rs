@@ -84,6 +84,11 @@ impl SortPreservingMergeExec {
     pub fn expr(&self) -> &[PhysicalSortExpr] {
         &self.expr
     }
+
+    /// The target size of yielded batches
+    pub fn target_batch_size(&self) -> usize {
+        self.target_batch_size
+    }
 }
 
 #[async_trait]
@@ -102,6 +107,10 @@ impl ExecutionPlan for SortPreservingMergeExec {
         Partitioning::UnknownPartitioning(1)
     }
 
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        Some(self.expr.clone())
+    }
+
     fn required_child_distribution(&self) -> Distribution {
         Distribution::UnspecifiedDistribution
     }
@@ -320,7 +329,7 @@ struct RowIndex {
 }
 
 #[derive(Debug)]
-struct SortPreservingMergeStream {
+pub(crate) struct SortPreservingMergeStream {
     /// The schema of the RecordBatches yielded by this stream
     schema: SchemaRef,
     /// The sorted input streams to merge together
@@ -346,7 +355,7 @@ struct SortPreservingMergeStream {
 }
 
 impl SortPreservingMergeStream {
-    fn new(
+    pub(crate) fn new(
         streams: Vec<mpsc::Receiver<ArrowResult<RecordBatch>>>,
         schema: SchemaRef,
         expressions: &[PhysicalSortExpr],
@@ -0,0 +1,636 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Defines the execution plan for the sorted aggregate operation, which aggregates an
+//! input that is sorted on the group keys one group at a time
+
+use std::any::Any;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow::compute::kernels::partition::lexicographical_partition_ranges;
+use arrow::compute::SortColumn;
+use arrow::datatypes::SchemaRef;
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use hashbrown::HashMap;
+
+use crate::datasource::datasource::Statistics;
+use crate::error::{DataFusionError, Result};
+use crate::physical_plan::expressions::{Column, Literal, PhysicalSortExpr};
+use crate::physical_plan::functions::{BuiltinScalarFunction, ScalarFunctionExpr};
+use crate::physical_plan::hash_aggregate::{
+    aggregate_expressions, aggregate_statistics, create_accumulators,
+    create_batch_from_map, create_schema, evaluate, evaluate_many, AggregateMode,
+    GroupState,
+};
+use crate::physical_plan::{
+    AggregateExpr, DisplayFormatType, Distribution, ExecutionPlan, Partitioning,
+    PhysicalExpr, RecordBatchStream, SQLMetric, SendableRecordBatchStream,
+};
+use crate::scalar::ScalarValue;
+
+/// Sorted aggregate execution plan.
+///
+/// Unlike the [`HashAggregateExec`](super::hash_aggregate::HashAggregateExec), which
+/// keeps all groups in memory until its input is exhausted, this operator requires the
+/// rows of each group to be adjacent in each input partition, as they are when the input
+/// is sorted on the group keys. It then only keeps the group that is being aggregated in
+/// memory, and produces the groups of each input batch as soon as the next group starts.
+#[derive(Debug)]
+pub struct SortedAggregateExec {
+    /// Aggregation mode (full, partial)
+    mode: AggregateMode,
+    /// Grouping expressions
+    group_expr: Vec<(Arc<dyn PhysicalExpr>, String)>,
+    /// Aggregate expressions
+    aggr_expr: Vec<Arc<dyn AggregateExpr>>,
+    /// Input plan, could be a partial aggregate or the input to the aggregate
+    input: Arc<dyn ExecutionPlan>,
+    /// Schema after the aggregate is applied
+    schema: SchemaRef,
+    /// Input schema before any aggregation is applied
+    input_schema: SchemaRef,
+    /// Metric to track number of output rows
+    output_rows: Arc<SQLMetric>,
+}
+
+impl SortedAggregateExec {
+    /// Create a new sorted aggregate execution plan
+    pub fn try_new(
+        mode: AggregateMode,
+        group_expr: Vec<(Arc<dyn PhysicalExpr>, String)>,
+        aggr_expr: Vec<Arc<dyn AggregateExpr>>,
+        input: Arc<dyn ExecutionPlan>,
+        input_schema: SchemaRef,
+    ) -> Result<Self> {
+        if group_expr.is_empty() {
+            return Err(DataFusionError::Plan(
+                "SortedAggregateExec requires at least one grouping expression"
+                    .to_owned(),
+            ));
+        }
+        let schema = create_schema(&input.schema(), &group_expr, &aggr_expr, mode)?;
+
+        Ok(SortedAggregateExec {
+            mode,
+            group_expr,
+            aggr_expr,
+            input,
+            schema: Arc::new(schema),
+            input_schema,
+            output_rows: SQLMetric::counter(),
+        })
+    }
+
+    /// Aggregation mode (full, partial)
+    pub fn mode(&self) -> &AggregateMode {
+        &self.mode
+    }
+
+    /// Grouping expressions
+    pub fn group_expr(&self) -> &[(Arc<dyn PhysicalExpr>, String)] {
+        &self.group_expr
+    }
+
+    /// Aggregate expressions
+    pub fn aggr_expr(&self) -> &[Arc<dyn AggregateExpr>] {
+        &self.aggr_expr
+    }
+
+    /// Input plan
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+
+    /// Get the input schema before any aggregates are applied
+    pub fn input_schema(&self) -> SchemaRef {
+        self.input_schema.clone()
+    }
+}
+
+/// Returns the index of the column that `expr` refers to, if it is a column or a
+/// window of time over one, such as `date_bin(INTERVAL '1 minute', ts)` or
+/// `date_trunc('hour', ts)`, whose values keep the order of the column
+fn column_index(expr: &Arc<dyn PhysicalExpr>) -> Option<usize> {
+    if let Some(column) = expr.as_any().downcast_ref::<Column>() {
+        return Some(column.index());
+    }
+    let fun = expr.as_any().downcast_ref::<ScalarFunctionExpr>()?;
+    let is_time_window = fun.name() == BuiltinScalarFunction::DateBin.to_string()
+        || fun.name() == BuiltinScalarFunction::DateTrunc.to_string();
+    match fun.args() {
+        // the width of the windows and their origin must be constants
+        [width, source, origin @ ..]
+            if is_time_window
+                && width.as_any().is::<Literal>()
+                && origin.iter().all(|arg| arg.as_any().is::<Literal>()) =>
+        {
+            source.as_any().downcast_ref::<Column>().map(|c| c.index())
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether the rows of each group of `group_expr` are adjacent in an input with
+/// `ordering`, which is the case if the ordering starts with all the grouping columns,
+/// in any order, or with the columns of the windows of time that are grouped by
+pub fn is_grouped_by_ordering(
+    group_expr: &[(Arc<dyn PhysicalExpr>, String)],
+    ordering: &[PhysicalSortExpr],
+) -> bool {
+    if group_expr.is_empty() || ordering.len() < group_expr.len() {
+        return false;
+    }
+    let group_columns = group_expr
+        .iter()
+        .map(|(e, _)| column_index(e))
+        .collect::<Option<Vec<_>>>();
+    let sort_columns = ordering[..group_expr.len()]
+        .iter()
+        .map(|e| column_index(&e.expr))
+        .collect::<Option<Vec<_>>>();
+    match (group_columns, sort_columns) {
+        (Some(mut group_columns), Some(mut sort_columns)) => {
+            group_columns.sort_unstable();
+            sort_columns.sort_unstable();
+            group_columns == sort_columns
+        }
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for SortedAggregateExec {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn required_child_distribution(&self) -> Distribution {
+        match &self.mode {
+            AggregateMode::Partial => Distribution::UnspecifiedDistribution,
+            AggregateMode::FinalPartitioned | AggregateMode::SinglePartitioned => {
+                Distribution::HashPartitioned(
+                    self.group_expr.iter().map(|x| x.0.clone()).collect(),
+                )
+            }
+            AggregateMode::Final => Distribution::SinglePartition,
+        }
+    }
+
+    /// Get the output partitioning of this plan
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    /// The groups are produced in the order of the input, so the output is sorted on
+    /// the grouping columns like the input
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        let ordering = self.input.output_ordering()?;
+        if !is_grouped_by_ordering(&self.group_expr, &ordering) {
+            return None;
+        }
+        ordering[..self.group_expr.len()]
+            .iter()
+            .map(|sort_expr| {
+                let index = column_index(&sort_expr.expr)?;
+                let i = self
+                    .group_expr
+                    .iter()
+                    .position(|(e, _)| column_index(e) == Some(index))?;
+                Some(PhysicalSortExpr {
+                    expr: Arc::new(Column::new(&self.group_expr[i].1, i)),
+                    options: sort_expr.options,
+                })
+            })
+            .collect()
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        let input = self.input.execute(partition).await?;
+        let aggregate_expressions =
+            aggregate_expressions(&self.aggr_expr, &self.mode, self.group_expr.len())?;
+
+        Ok(Box::pin(SortedAggregateStream {
+            mode: self.mode,
+            schema: self.schema.clone(),
+            group_expr: self.group_expr.iter().map(|x| x.0.clone()).collect(),
+            aggr_expr: self.aggr_expr.clone(),
+            aggregate_expressions,
+            input,
+            current: None,
+            finished: false,
+            output_rows: self.output_rows.clone(),
+        }))
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(SortedAggregateExec::try_new(
+                self.mode,
+                self.group_expr.clone(),
+                self.aggr_expr.clone(),
+                children[0].clone(),
+                self.input_schema.clone(),
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "SortedAggregateExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn metrics(&self) -> HashMap<String, SQLMetric> {
+        let mut metrics = HashMap::new();
+        metrics.insert("outputRows".to_owned(), (*self.output_rows).clone());
+        metrics
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(f, "SortedAggregateExec: mode={:?}", self.mode)?;
+                let g: Vec<String> = self
+                    .group_expr
+                    .iter()
+                    .map(|(e, alias)| {
+                        let e = e.to_string();
+                        if &e != alias {
+                            format!("{} as {}", e, alias)
+                        } else {
+                            e
+                        }
+                    })
+                    .collect();
+                write!(f, ", gby=[{}]", g.join(", "))?;
+
+                let a: Vec<String> = self
+                    .aggr_expr
+                    .iter()
+                    .map(|agg| agg.name().to_string())
+                    .collect();
+                write!(f, ", aggr=[{}]", a.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn statistics(&self) -> Statistics {
+        aggregate_statistics(self.mode, &self.group_expr, &self.input, &self.schema)
+    }
+}
+
+/// Stream of the groups of a sorted input, which produces the groups of each input batch
+/// that are complete
+struct SortedAggregateStream {
+    mode: AggregateMode,
+    schema: SchemaRef,
+    group_expr: Vec<Arc<dyn PhysicalExpr>>,
+    aggr_expr: Vec<Arc<dyn AggregateExpr>>,
+    /// The expressions to evaluate the batch, one vec of expressions per aggregation
+    aggregate_expressions: Vec<Vec<Arc<dyn PhysicalExpr>>>,
+    input: SendableRecordBatchStream,
+    /// The last group of the input so far, which may continue in the next batch
+    current: Option<GroupState>,
+    finished: bool,
+    output_rows: Arc<SQLMetric>,
+}
+
+impl SortedAggregateStream {
+    /// Aggregates the rows of `batch`, and returns the groups that ended in it
+    fn aggregate_batch(&mut self, batch: &RecordBatch) -> Result<Vec<GroupState>> {
+        let mut completed = vec![];
+        if batch.num_rows() == 0 {
+            return Ok(completed);
+        }
+        let group_values = evaluate(&self.group_expr, batch)?;
+        let aggr_input_values = evaluate_many(&self.aggregate_expressions, batch)?;
+
+        // the rows of each group are adjacent, so that the groups are the ranges of
+        // equal group values regardless of the direction of the sort
+        let sort_columns = group_values
+            .iter()
+            .map(|values| SortColumn {
+                values: values.clone(),
+                options: None,
+            })
+            .collect::<Vec<_>>();
+        let ranges = lexicographical_partition_ranges(&sort_columns)
+            .map_err(DataFusionError::ArrowError)?;
+
+        for range in ranges {
+            // the first group of a batch may continue the last group of the previous one
+            let continues = self.current.as_ref().map_or(false, |group| {
+                group
+                    .group_by_values
+                    .iter()
+                    .zip(group_values.iter())
+                    .all(|(scalar, array)| scalar.eq_array(array, range.start))
+            });
+            if !continues {
+                let group_by_values = group_values
+                    .iter()
+                    .map(|array| ScalarValue::try_from_array(array, range.start))
+                    .collect::<Result<Vec<_>>>()?;
+                let group = GroupState {
+                    group_by_values: group_by_values.into_boxed_slice(),
+                    accumulator_set: create_accumulators(&self.aggr_expr)?,
+                    indices: vec![],
+                };
+                if let Some(group) = self.current.replace(group) {
+                    completed.push(group);
+                }
+            }
+
+            let mode = self.mode;
+            let group = self.current.as_mut().unwrap();
+            group
+                .accumulator_set
+                .iter_mut()
+                .zip(aggr_input_values.iter())
+                .try_for_each(|(accumulator, values)| {
+                    let values = values
+                        .iter()
+                        .map(|array| array.slice(range.start, range.end - range.start))
+                        .collect::<Vec<_>>();
+                    match mode {
+                        AggregateMode::Partial | AggregateMode::SinglePartitioned => {
+                            accumulator.update_batch(&values)
+                        }
+                        AggregateMode::Final | AggregateMode::FinalPartitioned => {
+                            accumulator.merge_batch(&values)
+                        }
+                    }
+                })?;
+        }
+        Ok(completed)
+    }
+
+    fn create_batch(&self, groups: &[GroupState]) -> ArrowResult<RecordBatch> {
+        let batch = create_batch_from_map(
+            &self.mode,
+            groups,
+            self.group_expr.len(),
+            &self.schema,
+        )?;
+        self.output_rows.add(batch.num_rows());
+        Ok(batch)
+    }
+}
+
+impl Stream for SortedAggregateStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match futures::ready!(self.input.poll_next_unpin(cx)) {
+                Some(Ok(batch)) => match self.aggregate_batch(&batch) {
+                    Ok(groups) if groups.is_empty() => continue,
+                    Ok(groups) => return Poll::Ready(Some(self.create_batch(&groups))),
+                    Err(e) => {
+                        self.finished = true;
+                        return Poll::Ready(Some(Err(e.into_arrow_external_error())));
+                    }
+                },
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                None => {
+                    // the last group ends with the input
+                    self.finished = true;
+                    let groups = self.current.take().into_iter().collect::<Vec<_>>();
+                    if groups.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(self.create_batch(&groups)));
+                }
+            }
+        }
+    }
+}
+
+impl RecordBatchStream for SortedAggregateStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_batches_eq;
+    use crate::physical_plan::common;
+    use crate::physical_plan::expressions::{col, Avg};
+    use crate::physical_plan::memory::MemoryExec;
+    use crate::physical_plan::sort::SortExec;
+    use arrow::array::{Float64Array, UInt32Array};
+    use arrow::compute::SortOptions;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    /// batches sorted on `a`, whose groups span batches
+    fn sorted_input() -> Result<Arc<MemoryExec>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::UInt32, true),
+            Field::new("b", DataType::Float64, false),
+        ]));
+        let batch = |a: Vec<Option<u32>>, b: Vec<f64>| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(UInt32Array::from(a)),
+                    Arc::new(Float64Array::from(b)),
+                ],
+            )
+        };
+        let batches = vec![
+            batch(vec![Some(2), Some(3), Some(3)], vec![1.0, 2.0, 3.0])?,
+            batch(vec![Some(3), Some(4)], vec![4.0, 5.0])?,
+            batch(vec![], vec![])?,
+            batch(vec![Some(4), None, None], vec![6.0, 7.0, 8.0])?,
+        ];
+        Ok(Arc::new(MemoryExec::try_new(&[batches], schema, None)?))
+    }
+
+    #[tokio::test]
+    async fn sorted_aggregate() -> Result<()> {
+        let input = sorted_input()?;
+        let input_schema = input.schema();
+        let input = Arc::new(SortExec::try_new(
+            vec![PhysicalSortExpr {
+                expr: col("a", &input_schema)?,
+                options: SortOptions {
+                    descending: false,
+                    nulls_first: false,
+                },
+            }],
+            input,
+        )?);
+        let groups: Vec<(Arc<dyn PhysicalExpr>, String)> =
+            vec![(col("a", &input_schema)?, "a".to_string())];
+        let aggregates: Vec<Arc<dyn AggregateExpr>> = vec![Arc::new(Avg::new(
+            col("b", &input_schema)?,
+            "AVG(b)".to_string(),
+            DataType::Float64,
+        ))];
+        assert!(is_grouped_by_ordering(
+            &groups,
+            &input.output_ordering().unwrap()
+        ));
+
+        let partial = Arc::new(SortedAggregateExec::try_new(
+            AggregateMode::Partial,
+            groups.clone(),
+            aggregates.clone(),
+            input,
+            input_schema.clone(),
+        )?);
+        let final_groups = vec![(col("a", &partial.schema())?, "a".to_string())];
+        let aggregate = SortedAggregateExec::try_new(
+            AggregateMode::Final,
+            final_groups,
+            aggregates,
+            partial,
+            input_schema,
+        )?;
+        assert_eq!(
+            vec!["a@0 ASC NULLS LAST"],
+            aggregate
+                .output_ordering()
+                .unwrap()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let result = common::collect(aggregate.execute(0).await?).await?;
+        let expected = vec![
+            "+---+--------+",
+            "| a | AVG(b) |",
+            "+---+--------+",
+            "| 2 | 1      |",
+            "| 3 | 3      |",
+            "| 4 | 5.5    |",
+            "|   | 7.5    |",
+            "+---+--------+",
+        ];
+        assert_batches_eq!(expected, &result);
+        let output_rows = aggregate.metrics()["outputRows"].value();
+        assert_eq!(4, output_rows);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorted_aggregate_streams_groups() -> Result<()> {
+        let input = sorted_input()?;
+        let input_schema = input.schema();
+        let groups: Vec<(Arc<dyn PhysicalExpr>, String)> =
+            vec![(col("a", &input_schema)?, "a".to_string())];
+        let aggregate = SortedAggregateExec::try_new(
+            AggregateMode::Partial,
+            groups,
+            vec![],
+            input,
+            input_schema,
+        )?;
+
+        // the groups that end in a batch are produced before the next one is read,
+        // and the last group when the input ends
+        let mut stream = aggregate.execute(0).await?;
+        let mut group_counts = vec![];
+        while let Some(batch) = stream.next().await {
+            group_counts.push(batch?.num_rows());
+        }
+        assert_eq!(vec![1, 1, 1, 1], group_counts);
+        Ok(())
+    }
+
+    #[test]
+    fn grouped_by_ordering() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::UInt32, false),
+            Field::new("b", DataType::UInt32, false),
+            Field::new("c", DataType::UInt32, false),
+        ]);
+        let group = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| Ok((col(name, &schema)?, name.to_string())))
+                .collect::<Result<Vec<_>>>()
+        };
+        let ordering = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| {
+                    Ok(PhysicalSortExpr {
+                        expr: col(name, &schema)?,
+                        options: SortOptions::default(),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        assert!(is_grouped_by_ordering(&group(&["a"])?, &ordering(&["a"])?));
+        assert!(is_grouped_by_ordering(
+            &group(&["a"])?,
+            &ordering(&["a", "b"])?
+        ));
+        assert!(is_grouped_by_ordering(
+            &group(&["b", "a"])?,
+            &ordering(&["a", "b", "c"])?
+        ));
+        assert!(!is_grouped_by_ordering(
+            &group(&["b"])?,
+            &ordering(&["a", "b"])?
+        ));
+        assert!(!is_grouped_by_ordering(
+            &group(&["a", "b"])?,
+            &ordering(&["a"])?
+        ));
+        assert!(!is_grouped_by_ordering(
+            &group(&["a", "c"])?,
+            &ordering(&["a", "b"])?
+        ));
+        assert!(!is_grouped_by_ordering(&[], &ordering(&["a"])?));
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Execution plan for reading batches from an unbounded stream
+
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use super::expressions::PhysicalSortExpr;
+use super::{
+    DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use crate::error::{DataFusionError, Result};
+use arrow::datatypes::SchemaRef;
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc::Receiver;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// The channels of the partitions of a streaming source, which are taken by the
+/// partition that reads them
+pub type StreamingPartitions =
+    Arc<Mutex<Vec<Option<Receiver<ArrowResult<RecordBatch>>>>>>;
+
+/// Execution plan for reading batches from channels that receive data over time.
+///
+/// Each partition reads from its own channel and emits batches as soon as they
+/// arrive. A partition ends once all senders of its channel have been dropped,
+/// which may never happen, so the plan reports itself as unbounded.
+#[derive(Debug)]
+pub struct StreamingExec {
+    /// The channels to read from, one per partition. Each one can only be
+    /// executed once, by any of the plans that share it.
+    partitions: StreamingPartitions,
+    /// Number of partitions
+    num_partitions: usize,
+    /// Schema representing the data after the optional projection is applied
+    schema: SchemaRef,
+    /// Optional projection
+    projection: Option<Vec<usize>>,
+    /// The order in which the rows of each partition arrive
+    ordering: Option<Vec<PhysicalSortExpr>>,
+}
+
+impl StreamingExec {
+    /// Create a new execution plan for reading from the provided channels
+    pub fn new(
+        partitions: StreamingPartitions,
+        schema: SchemaRef,
+        projection: Option<Vec<usize>>,
+        ordering: Option<Vec<PhysicalSortExpr>>,
+    ) -> Self {
+        let num_partitions = partitions.lock().unwrap().len();
+        Self {
+            partitions,
+            num_partitions,
+            schema,
+            projection,
+            ordering,
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for StreamingExec {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// Get the schema for this execution plan
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        // this is a leaf node and has no children
+        vec![]
+    }
+
+    /// Get the output partitioning of this plan
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.num_partitions)
+    }
+
+    fn output_ordering(&self) -> Option<Vec<PhysicalSortExpr>> {
+        self.ordering.clone()
+    }
+
+    fn with_new_children(
+        &self,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Err(DataFusionError::Internal(format!(
+            "Children cannot be replaced in {:?}",
+            self
+        )))
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        let receiver = self
+            .partitions
+            .lock()
+            .unwrap()
+            .get_mut(partition)
+            .and_then(|receiver| receiver.take())
+            .ok_or_else(|| {
+                DataFusionError::Execution(format!(
+                    "Partition {} of the streaming source has already been read",
+                    partition
+                ))
+            })?;
+
+        Ok(Box::pin(StreamingStream {
+            schema: self.schema.clone(),
+            projection: self.projection.clone(),
+            inner: ReceiverStream::new(receiver),
+        }))
+    }
+
+    fn unbounded(&self) -> bool {
+        true
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(f, "StreamingExec: partitions={}", self.num_partitions)
+            }
+        }
+    }
+}
+
+/// Stream of the batches received by a single channel
+struct StreamingStream {
+    /// Schema representing the data after the optional projection is applied
+    schema: SchemaRef,
+    /// Optional projection for which columns to load
+    projection: Option<Vec<usize>>,
+    /// The channel to read from
+    inner: ReceiverStream<ArrowResult<RecordBatch>>,
+}
+
+impl Stream for StreamingStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        poll.map(|maybe_batch| {
+            maybe_batch.map(|batch| {
+                let batch = batch?;
+                // apply projection
+                match &self.projection {
+                    Some(columns) => RecordBatch::try_new(
+                        self.schema.clone(),
+                        columns.iter().map(|i| batch.column(*i).clone()).collect(),
+                    ),
+                    None => Ok(batch),
+                }
+            })
+        })
+    }
+}
+
+impl RecordBatchStream for StreamingStream {
+    /// Get the schema
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
@@ -15,7 +15,8 @@
 // specific language governing permissions and limitations
 // under the License.
 
-//! Type coercion rules for functions with multiple valid signatures
+//! Type coercion rules for functions with multiple valid signatures, and for the
+//! arguments of comparisons
 //!
 //! Coercion is performed automatically by DataFusion when the types
 //! of arguments passed to a function do not exacty match the types
@@ -23,19 +24,49 @@
 //! *coerce* the arguments to types accepted by the function by
 //! inserting CAST operations.
 //!
-//! CAST operations added by coercion are lossless and never discard
-//! information. For example coercion from i32 -> i64 might be
+//! CAST operations added by coercion of function arguments are lossless and never
+//! discard information. For example coercion from i32 -> i64 might be
 //! performed because all valid i32 values can be represented using an
 //! i64. However, i64 -> i32 is never performed as there are i64
 //! values which can not be represented by i32 values.
+//!
+//! # Comparisons
+//!
+//! The arguments of `=`, `<>`, `<`, `<=`, `>`, `>=` and `IS [NOT] DISTINCT FROM` are
+//! cast to a common type, see [comparison_coercion]:
+//!
+//! | Left             | Right            | Common type                                 | Lossy for          |
+//! | ---------------- | ---------------- | ------------------------------------------- | ------------------ |
+//! | signed integer   | signed integer   | the wider integer                           |                    |
+//! | unsigned integer | unsigned integer | the wider integer                           |                    |
+//! | signed integer   | unsigned integer | the narrowest signed integer of both, Int64 | UInt64             |
+//! | integer          | float            | Float32 up to 16 bits, Float64 otherwise    | 64 bit integers    |
+//! | float            | float            | the wider float                             |                    |
+//! | Utf8             | LargeUtf8        | LargeUtf8                                   |                    |
+//! | Utf8             | Date32 or Date64 | the date type                               | strings not dates  |
+//! | dictionary       | any              | the common type of the dictionary values    | as for the values  |
+//!
+//! A numeric literal is instead cast to the type of the other argument when its value is
+//! kept, see [comparison_cast_type]. The matrix is symmetric. Other pairs of different types, such as `Utf8` and `Int64`,
+//! can not be compared without an explicit CAST, and their comparisons are planning
+//! errors. Lenient coercion, the default, performs lossy casts. With strict coercion,
+//! see [ExecutionConfig::with_strict_type_coercion], a comparison that needs a lossy
+//! cast is a planning error unless the cast argument is a literal whose value is kept,
+//! such as `float_column > 5`.
+//!
+//! [ExecutionConfig::with_strict_type_coercion]: crate::execution::context::ExecutionConfig::with_strict_type_coercion
 
 use std::{sync::Arc, vec};
 
+use arrow::array::Array;
+use arrow::compute::kernels::cast;
 use arrow::datatypes::{DataType, Schema, TimeUnit};
 
 use super::{functions::Signature, PhysicalExpr};
 use crate::error::{DataFusionError, Result};
-use crate::physical_plan::expressions::try_cast;
+use crate::logical_plan::Operator;
+use crate::physical_plan::expressions::{try_cast, Literal};
+use crate::scalar::ScalarValue;
 
 /// Returns `expressions` coerced to types compatible with
 /// `signature`, if possible.
@@ -206,10 +237,241 @@ pub fn can_coerce_from(type_into: &DataType, type_from: &DataType) -> bool {
     }
 }
 
+/// The range of values of a numeric type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumericRange {
+    /// Signed integer of the number of bits
+    Signed(u8),
+    /// Unsigned integer of the number of bits
+    Unsigned(u8),
+    /// Float with the number of bits of its significand
+    Float(u8),
+}
+
+impl NumericRange {
+    fn of(data_type: &DataType) -> Option<Self> {
+        use arrow::datatypes::DataType::*;
+        Some(match data_type {
+            Int8 => Self::Signed(8),
+            Int16 => Self::Signed(16),
+            Int32 => Self::Signed(32),
+            Int64 => Self::Signed(64),
+            UInt8 => Self::Unsigned(8),
+            UInt16 => Self::Unsigned(16),
+            UInt32 => Self::Unsigned(32),
+            UInt64 => Self::Unsigned(64),
+            Float16 => Self::Float(11),
+            Float32 => Self::Float(24),
+            Float64 => Self::Float(53),
+            _ => return None,
+        })
+    }
+
+    /// Whether all values of `self` are values of `other`
+    fn fits_in(self, other: Self) -> bool {
+        use NumericRange::*;
+        match (self, other) {
+            (Signed(a), Signed(b))
+            | (Unsigned(a), Unsigned(b))
+            | (Float(a), Float(b)) => a <= b,
+            (Unsigned(a), Signed(b)) => a < b,
+            (Signed(_), Unsigned(_))
+            | (Float(_), Signed(_))
+            | (Float(_), Unsigned(_)) => false,
+            // the magnitude of the integer must fit in the significand
+            (Signed(a), Float(b)) => a - 1 <= b,
+            (Unsigned(a), Float(b)) => a <= b,
+        }
+    }
+}
+
+fn signed_type(bits: u8) -> DataType {
+    match bits {
+        8 => DataType::Int8,
+        16 => DataType::Int16,
+        32 => DataType::Int32,
+        _ => DataType::Int64,
+    }
+}
+
+fn unsigned_type(bits: u8) -> DataType {
+    match bits {
+        8 => DataType::UInt8,
+        16 => DataType::UInt16,
+        32 => DataType::UInt32,
+        _ => DataType::UInt64,
+    }
+}
+
+/// The common type of a comparison of numeric types: the narrowest type that holds the
+/// values of both, or the widest of the candidates if there is none
+fn numeric_comparison_coercion(lhs: NumericRange, rhs: NumericRange) -> DataType {
+    use NumericRange::*;
+    match (lhs, rhs) {
+        (Signed(a), Signed(b)) => signed_type(a.max(b)),
+        (Unsigned(a), Unsigned(b)) => unsigned_type(a.max(b)),
+        (Signed(s), Unsigned(u)) | (Unsigned(u), Signed(s)) => {
+            if s > u {
+                signed_type(s)
+            } else {
+                // Int64 when u is 64, which is lossy
+                signed_type(u.saturating_mul(2))
+            }
+        }
+        (Float(a), Float(b)) if a.max(b) > 24 => DataType::Float64,
+        (Float(_), Float(_)) => DataType::Float32,
+        (Float(f), int) | (int, Float(f)) => {
+            if f <= 24 && int.fits_in(Float(24)) {
+                DataType::Float32
+            } else {
+                // Float64 for 64 bit integers, which is lossy
+                DataType::Float64
+            }
+        }
+    }
+}
+
+/// Returns the type that the arguments of a comparison of `lhs_type` with `rhs_type`
+/// are cast to, or `None` if they can not be compared. See the [module
+/// documentation](self) for the rules.
+pub fn comparison_coercion(lhs_type: &DataType, rhs_type: &DataType) -> Option<DataType> {
+    use arrow::datatypes::DataType::*;
+    if lhs_type == rhs_type {
+        return Some(lhs_type.clone());
+    }
+    match (lhs_type, rhs_type) {
+        (Dictionary(_, value_type), _) => comparison_coercion(value_type, rhs_type),
+        (_, Dictionary(_, value_type)) => comparison_coercion(lhs_type, value_type),
+        (Utf8, LargeUtf8) | (LargeUtf8, Utf8) => Some(LargeUtf8),
+        (Utf8, Date32) | (Date32, Utf8) => Some(Date32),
+        (Utf8, Date64) | (Date64, Utf8) => Some(Date64),
+        _ => match (NumericRange::of(lhs_type), NumericRange::of(rhs_type)) {
+            (Some(lhs), Some(rhs)) => Some(numeric_comparison_coercion(lhs, rhs)),
+            _ => None,
+        },
+    }
+}
+
+/// Returns whether casting `from_type` to `to_type` keeps all values, such as `Int32`
+/// to `Int64` but not `Int64` to `Float64`
+pub fn is_lossless_cast(from_type: &DataType, to_type: &DataType) -> bool {
+    use arrow::datatypes::DataType::*;
+    if from_type == to_type {
+        return true;
+    }
+    match (from_type, to_type) {
+        (Dictionary(_, value_type), _) => is_lossless_cast(value_type, to_type),
+        (Utf8, LargeUtf8) => true,
+        _ => match (NumericRange::of(from_type), NumericRange::of(to_type)) {
+            (Some(from), Some(to)) => from.fits_in(to),
+            _ => false,
+        },
+    }
+}
+
+/// Returns whether the comparison operator `op` coerces its arguments with
+/// [comparison_coercion]
+pub fn is_comparison_operator(op: &Operator) -> bool {
+    matches!(
+        op,
+        Operator::Eq
+            | Operator::NotEq
+            | Operator::Lt
+            | Operator::LtEq
+            | Operator::Gt
+            | Operator::GtEq
+            | Operator::IsDistinctFrom
+            | Operator::IsNotDistinctFrom
+    )
+}
+
+/// Returns the type that the arguments of a comparison of `lhs` with `rhs` are cast
+/// to. A numeric literal is cast to the type of the other argument when its value is
+/// kept, so that `uint64_column > 5` compares `UInt64` values, and other arguments to
+/// their [comparison_coercion].
+pub fn comparison_cast_type(
+    lhs: &Arc<dyn PhysicalExpr>,
+    rhs: &Arc<dyn PhysicalExpr>,
+    schema: &Schema,
+) -> Result<Option<DataType>> {
+    let lhs_type = lhs.data_type(schema)?;
+    let rhs_type = rhs.data_type(schema)?;
+    if NumericRange::of(&lhs_type).is_some() && NumericRange::of(&rhs_type).is_some() {
+        if literal_is_kept(rhs, &lhs_type) {
+            return Ok(Some(lhs_type));
+        }
+        if literal_is_kept(lhs, &rhs_type) {
+            return Ok(Some(rhs_type));
+        }
+    }
+    Ok(comparison_coercion(&lhs_type, &rhs_type))
+}
+
+/// Checks that the arguments of the comparison `lhs op rhs` are cast to the type of
+/// [comparison_cast_type] without losing values, which strict coercion requires. A
+/// literal argument may be cast with a lossy cast when its value is kept, such as
+/// `Int64(5)` cast to `Float64`. Other operators are not checked.
+pub fn check_lossless_comparison(
+    lhs: &Arc<dyn PhysicalExpr>,
+    op: &Operator,
+    rhs: &Arc<dyn PhysicalExpr>,
+    schema: &Schema,
+) -> Result<()> {
+    if !is_comparison_operator(op) {
+        return Ok(());
+    }
+    let lhs_type = lhs.data_type(schema)?;
+    let rhs_type = rhs.data_type(schema)?;
+    // comparisons without a common type are rejected when they are planned
+    let common_type = match comparison_cast_type(lhs, rhs, schema)? {
+        Some(common_type) => common_type,
+        None => return Ok(()),
+    };
+    for (expr, data_type) in [(lhs, &lhs_type), (rhs, &rhs_type)].iter() {
+        if !is_lossless_cast(data_type, &common_type)
+            && !literal_is_kept(expr, &common_type)
+        {
+            return Err(DataFusionError::Plan(format!(
+                "'{:?} {} {:?}' casts {} to {:?}, which loses information and is not \
+                 allowed with strict type coercion. Add an explicit CAST",
+                lhs_type, op, rhs_type, expr, common_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `expr` is a literal whose value is kept when it is cast to `data_type`: a
+/// string that is parsed, or a value that is the same when it is cast back
+fn literal_is_kept(expr: &Arc<dyn PhysicalExpr>, data_type: &DataType) -> bool {
+    let value = match expr.as_any().downcast_ref::<Literal>() {
+        Some(literal) => literal.value(),
+        None => return false,
+    };
+    if value.is_null() {
+        return true;
+    }
+    let array = value.to_array();
+    let cast_array = match cast::cast(&array, data_type) {
+        Ok(cast_array) if cast_array.is_valid(0) => cast_array,
+        _ => return false,
+    };
+    if matches!(array.data_type(), DataType::Utf8 | DataType::LargeUtf8) {
+        return true;
+    }
+    match cast::cast(&cast_array, array.data_type()) {
+        Ok(round_trip) => matches!(
+            ScalarValue::try_from_array(&round_trip, 0),
+            Ok(round_trip) if round_trip == *value
+        ),
+        Err(_) => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::physical_plan::expressions::col;
+    use crate::physical_plan::expressions::{col, lit};
     use arrow::datatypes::{DataType, Field, Schema};
 
     #[test]
@@ -388,4 +650,132 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn comparison_matrix() {
+        use DataType::*;
+        let cases = vec![
+            (Int8, Int32, Some(Int32), true),
+            (UInt16, UInt64, Some(UInt64), true),
+            (Int16, UInt8, Some(Int16), true),
+            (Int16, UInt16, Some(Int32), true),
+            (Int8, UInt32, Some(Int64), true),
+            (Int8, UInt64, Some(Int64), false),
+            (Int64, UInt64, Some(Int64), false),
+            (Int16, Float32, Some(Float32), true),
+            (UInt16, Float16, Some(Float32), true),
+            (Int32, Float32, Some(Float64), true),
+            (UInt32, Float64, Some(Float64), true),
+            (Int64, Float32, Some(Float64), false),
+            (Float16, Float64, Some(Float64), true),
+            (Utf8, LargeUtf8, Some(LargeUtf8), true),
+            (Utf8, Date32, Some(Date32), false),
+            (
+                Dictionary(Box::new(Int8), Box::new(Utf8)),
+                Utf8,
+                Some(Utf8),
+                true,
+            ),
+            (
+                Dictionary(Box::new(Int8), Box::new(Int16)),
+                Dictionary(Box::new(Int32), Box::new(UInt16)),
+                Some(Int32),
+                true,
+            ),
+            (Utf8, Int64, None, false),
+            (Boolean, Int8, None, false),
+            (Date64, Date32, None, false),
+        ];
+        for (lhs, rhs, expected, lossless) in cases {
+            for (lhs, rhs) in vec![(&lhs, &rhs), (&rhs, &lhs)] {
+                let common_type = comparison_coercion(lhs, rhs);
+                assert_eq!(common_type, expected, "{:?} and {:?}", lhs, rhs);
+                if let Some(common_type) = common_type {
+                    assert_eq!(
+                        is_lossless_cast(lhs, &common_type)
+                            && is_lossless_cast(rhs, &common_type),
+                        lossless,
+                        "{:?} and {:?}",
+                        lhs,
+                        rhs
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn literal_comparison_type() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("u", DataType::UInt64, true),
+            Field::new("i", DataType::Int8, true),
+        ]);
+        let cast_type = |lhs: Arc<dyn PhysicalExpr>, rhs: Arc<dyn PhysicalExpr>| {
+            comparison_cast_type(&lhs, &rhs, &schema)
+        };
+        let u = col("u", &schema)?;
+        let i = col("i", &schema)?;
+        let int64 = |v| lit(ScalarValue::Int64(Some(v)));
+        assert_eq!(cast_type(u.clone(), int64(5))?, Some(DataType::UInt64));
+        assert_eq!(cast_type(int64(5), u.clone())?, Some(DataType::UInt64));
+        assert_eq!(cast_type(u.clone(), int64(-5))?, Some(DataType::Int64));
+        assert_eq!(cast_type(i.clone(), int64(-5))?, Some(DataType::Int8));
+        assert_eq!(cast_type(i.clone(), int64(300))?, Some(DataType::Int64));
+        assert_eq!(cast_type(i, u)?, Some(DataType::Int64));
+        Ok(())
+    }
+
+    #[test]
+    fn strict_comparisons() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("i", DataType::Int64, true),
+            Field::new("u", DataType::UInt64, true),
+            Field::new("f", DataType::Float64, true),
+            Field::new("s", DataType::Utf8, true),
+        ]);
+        let check = |lhs: Arc<dyn PhysicalExpr>, op, rhs: Arc<dyn PhysicalExpr>| {
+            check_lossless_comparison(&lhs, &op, &rhs, &schema)
+        };
+
+        check(
+            col("i", &schema)?,
+            Operator::Eq,
+            lit(ScalarValue::UInt8(Some(1))),
+        )?;
+        check(
+            col("u", &schema)?,
+            Operator::Eq,
+            lit(ScalarValue::Int64(Some(1))),
+        )?;
+        check(
+            col("f", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::Int64(Some(5))),
+        )?;
+        check(col("i", &schema)?, Operator::Plus, col("f", &schema)?)?;
+        check(
+            lit(ScalarValue::Utf8(Some("2021-01-01".to_owned()))),
+            Operator::Lt,
+            lit(ScalarValue::Date32(Some(18628))),
+        )?;
+
+        let err = check(col("i", &schema)?, Operator::Eq, col("u", &schema)?)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("'Int64 = UInt64' casts u@1 to Int64"),
+            "{}",
+            err
+        );
+        assert!(check(col("i", &schema)?, Operator::Lt, col("f", &schema)?).is_err());
+        let value = lit(ScalarValue::Int64(Some(-1)));
+        assert!(check(col("u", &schema)?, Operator::Eq, value).is_err());
+        // 2^53 + 1 is not a Float64
+        let value = lit(ScalarValue::Int64(Some((1 << 53) + 1)));
+        assert!(check(col("f", &schema)?, Operator::GtEq, value).is_err());
+        let value = lit(ScalarValue::Utf8(Some("yesterday".to_owned())));
+        let date = lit(ScalarValue::Date32(Some(18628)));
+        assert!(check(value, Operator::Eq, date).is_err());
+        Ok(())
+    }
 }
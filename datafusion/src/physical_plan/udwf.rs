@@ -0,0 +1,169 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! This module contains functions and structs supporting user-defined window functions.
+
+use fmt::{Debug, Formatter};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use arrow::array::ArrayRef;
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+
+use crate::physical_plan::PhysicalExpr;
+use crate::{error::Result, logical_plan::Expr};
+
+use super::{
+    functions::{ReturnTypeFunction, Signature},
+    type_coercion::coerce,
+    window_functions::{BuiltInWindowFunctionExpr, PartitionEvaluator, WindowFunction},
+};
+
+/// Creates the [PartitionEvaluator] of a UDWF from the values of its arguments for all
+/// the rows of a batch. The rows are sorted by the PARTITION BY expressions, and then
+/// by the ORDER BY expressions, of the OVER clause.
+pub type PartitionEvaluatorFunctionImplementation =
+    Arc<dyn Fn(&[ArrayRef]) -> Result<Box<dyn PartitionEvaluator>> + Send + Sync>;
+
+/// Logical representation of a user-defined window function (UDWF)
+/// A UDWF produces a value for every row of a window partition, and is evaluated
+/// over all the rows of the partition at once.
+#[derive(Clone)]
+pub struct WindowUDF {
+    /// name
+    pub name: String,
+    /// signature
+    pub signature: Signature,
+    /// Return type
+    pub return_type: ReturnTypeFunction,
+    /// actual implementation
+    pub partition_evaluator: PartitionEvaluatorFunctionImplementation,
+}
+
+impl Debug for WindowUDF {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowUDF")
+            .field("name", &self.name)
+            .field("signature", &self.signature)
+            .field("fun", &"<FUNC>")
+            .finish()
+    }
+}
+
+impl PartialEq for WindowUDF {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.signature == other.signature
+    }
+}
+
+impl Eq for WindowUDF {}
+
+impl WindowUDF {
+    /// Create a new WindowUDF
+    pub fn new(
+        name: &str,
+        signature: &Signature,
+        return_type: &ReturnTypeFunction,
+        partition_evaluator: &PartitionEvaluatorFunctionImplementation,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            signature: signature.clone(),
+            return_type: return_type.clone(),
+            partition_evaluator: partition_evaluator.clone(),
+        }
+    }
+
+    /// creates a logical expression with a call of the UDWF over the whole input, which
+    /// can be narrowed with the `partition_by` and `order_by` fields of the expression.
+    /// This utility allows using the UDWF without requiring access to the registry.
+    pub fn call(&self, args: Vec<Expr>) -> Expr {
+        Expr::WindowFunction {
+            fun: WindowFunction::WindowUDF(Arc::new(self.clone())),
+            args,
+            partition_by: vec![],
+            order_by: vec![],
+            window_frame: None,
+            distinct: false,
+        }
+    }
+}
+
+/// Creates a physical expression of the UDWF, that includes all necessary type coercion.
+/// This function errors when `args`' can't be coerced to a valid argument type of the UDWF.
+pub(crate) fn create_window_udf_expr(
+    fun: &Arc<WindowUDF>,
+    args: &[Arc<dyn PhysicalExpr>],
+    input_schema: &Schema,
+    name: String,
+) -> Result<Arc<dyn BuiltInWindowFunctionExpr>> {
+    // coerce
+    let args = coerce(args, input_schema, &fun.signature)?;
+
+    let arg_types = args
+        .iter()
+        .map(|arg| arg.data_type(input_schema))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Arc::new(WindowUDFExpr {
+        fun: fun.clone(),
+        data_type: (fun.return_type)(&arg_types)?.as_ref().clone(),
+        args,
+        name,
+    }))
+}
+
+/// Physical window function expression of a UDWF.
+#[derive(Debug)]
+struct WindowUDFExpr {
+    fun: Arc<WindowUDF>,
+    args: Vec<Arc<dyn PhysicalExpr>>,
+    data_type: DataType,
+    name: String,
+}
+
+impl BuiltInWindowFunctionExpr for WindowUDFExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn field(&self) -> Result<Field> {
+        Ok(Field::new(&self.name, self.data_type.clone(), true))
+    }
+
+    fn expressions(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        self.args.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn create_evaluator(
+        &self,
+        batch: &RecordBatch,
+    ) -> Result<Box<dyn PartitionEvaluator>> {
+        let values = self
+            .args
+            .iter()
+            .map(|arg| Ok(arg.evaluate(batch)?.into_array(batch.num_rows())))
+            .collect::<Result<Vec<_>>>()?;
+        (self.fun.partition_evaluator)(&values)
+    }
+}
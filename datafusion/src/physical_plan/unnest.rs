@@ -0,0 +1,321 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! UnnestExec expands a list column into one row per list element, repeating the
+//! values of the other columns for every element.
+
+use std::any::Any;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use super::{RecordBatchStream, SendableRecordBatchStream};
+use crate::error::{DataFusionError, Result};
+use crate::physical_plan::{DisplayFormatType, ExecutionPlan, Partitioning};
+use arrow::array::{
+    Array, ArrayRef, GenericListArray, LargeListArray, ListArray, OffsetSizeTrait,
+    UInt64Array,
+};
+use arrow::compute::take;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::error::Result as ArrowResult;
+use arrow::record_batch::RecordBatch;
+
+use async_trait::async_trait;
+
+use futures::stream::{Stream, StreamExt};
+
+/// UnnestExec expands a list column into one row per list element. Rows whose list
+/// is null or empty produce no output.
+#[derive(Debug)]
+pub struct UnnestExec {
+    /// The input plan
+    input: Arc<dyn ExecutionPlan>,
+    /// Index of the list column in the input schema
+    column_index: usize,
+    /// The output schema, where the list column is replaced by its element type
+    schema: SchemaRef,
+}
+
+impl UnnestExec {
+    /// Create a new UnnestExec that unnests the list column at `column_index`
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        column_index: usize,
+        schema: SchemaRef,
+    ) -> Result<Self> {
+        let input_schema = input.schema();
+        if column_index >= input_schema.fields().len() {
+            return Err(DataFusionError::Plan(format!(
+                "Unnest column index {} is out of bounds for input with {} columns",
+                column_index,
+                input_schema.fields().len()
+            )));
+        }
+        match input_schema.field(column_index).data_type() {
+            DataType::List(_) | DataType::LargeList(_) => Ok(Self {
+                input,
+                column_index,
+                schema,
+            }),
+            other => Err(DataFusionError::Plan(format!(
+                "Cannot unnest column of type {:?}, expected a list",
+                other
+            ))),
+        }
+    }
+
+    /// The input plan
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+
+    /// Index of the list column in the input schema
+    pub fn column_index(&self) -> usize {
+        self.column_index
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for UnnestExec {
+    /// Return a reference to Any that can be used for downcasting
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    /// Get the output partitioning of this plan
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(UnnestExec::try_new(
+                children[0].clone(),
+                self.column_index,
+                self.schema.clone(),
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "UnnestExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    async fn execute(&self, partition: usize) -> Result<SendableRecordBatchStream> {
+        Ok(Box::pin(UnnestStream {
+            schema: self.schema.clone(),
+            column_index: self.column_index,
+            input: self.input.execute(partition).await?,
+        }))
+    }
+
+    fn fmt_as(
+        &self,
+        t: DisplayFormatType,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let name = self.input.schema().field(self.column_index).name().clone();
+                write!(f, "UnnestExec: column={}", name)
+            }
+        }
+    }
+}
+
+struct UnnestStream {
+    /// Output schema
+    schema: SchemaRef,
+    /// Index of the list column in the input batches
+    column_index: usize,
+    /// The input partition to unnest
+    input: SendableRecordBatchStream,
+}
+
+/// Returns the index of the input row for every output row, together with the
+/// list values in output order
+fn unnest_list<OffsetSize: OffsetSizeTrait>(
+    list: &GenericListArray<OffsetSize>,
+) -> ArrowResult<(UInt64Array, ArrayRef)> {
+    let offsets = list.value_offsets();
+    let mut row_indices = vec![];
+    let mut value_indices = vec![];
+    for row in 0..list.len() {
+        if list.is_null(row) {
+            continue;
+        }
+        let start = offsets[row].to_usize().unwrap();
+        let end = offsets[row + 1].to_usize().unwrap();
+        for value in start..end {
+            row_indices.push(row as u64);
+            value_indices.push(value as u64);
+        }
+    }
+    let values = take(
+        list.values().as_ref(),
+        &UInt64Array::from(value_indices),
+        None,
+    )?;
+    Ok((UInt64Array::from(row_indices), values))
+}
+
+fn unnest_batch(
+    batch: &RecordBatch,
+    column_index: usize,
+    schema: &SchemaRef,
+) -> ArrowResult<RecordBatch> {
+    let column = batch.column(column_index);
+    let (row_indices, values) = match column.data_type() {
+        DataType::List(_) => {
+            unnest_list(column.as_any().downcast_ref::<ListArray>().unwrap())?
+        }
+        DataType::LargeList(_) => {
+            unnest_list(column.as_any().downcast_ref::<LargeListArray>().unwrap())?
+        }
+        other => {
+            return Err(DataFusionError::Internal(format!(
+                "Cannot unnest column of type {:?}",
+                other
+            ))
+            .into_arrow_external_error())
+        }
+    };
+
+    let columns = batch
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if i == column_index {
+                Ok(values.clone())
+            } else {
+                take(c.as_ref(), &row_indices, None)
+            }
+        })
+        .collect::<ArrowResult<Vec<_>>>()?;
+
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+impl Stream for UnnestStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.input.poll_next_unpin(cx).map(|x| match x {
+            Some(Ok(batch)) => {
+                Some(unnest_batch(&batch, self.column_index, &self.schema))
+            }
+            other => other,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // same number of record batches
+        self.input.size_hint()
+    }
+}
+
+impl RecordBatchStream for UnnestStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physical_plan::collect;
+    use crate::physical_plan::memory::MemoryExec;
+    use arrow::array::{Int32Array, Int32Builder, ListBuilder, StringArray};
+    use arrow::datatypes::{Field, Schema};
+
+    #[tokio::test]
+    async fn unnest_list_column() -> Result<()> {
+        let item = Field::new("item", DataType::Int32, true);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("values", DataType::List(Box::new(item)), true),
+        ]));
+
+        let mut builder = ListBuilder::new(Int32Builder::new(8));
+        builder.values().append_value(1)?;
+        builder.values().append_value(2)?;
+        builder.append(true)?;
+        builder.append(false)?;
+        builder.append(true)?;
+        builder.values().append_value(3)?;
+        builder.append(true)?;
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b", "c", "d"])),
+                Arc::new(builder.finish()),
+            ],
+        )?;
+
+        let output_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("values", DataType::Int32, true),
+        ]));
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None)?);
+        let unnest = Arc::new(UnnestExec::try_new(input, 1, output_schema)?);
+
+        let batches = collect(unnest).await?;
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let values = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(
+            (0..ids.len()).map(|i| ids.value(i)).collect::<Vec<_>>(),
+            vec!["a", "a", "d"]
+        );
+        assert_eq!(values.values(), &[1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unnest_requires_list_column() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Utf8, false)]));
+        let input = Arc::new(MemoryExec::try_new(&[], schema.clone(), None)?);
+        assert!(UnnestExec::try_new(input, 0, schema).is_err());
+        Ok(())
+    }
+}
@@ -23,8 +23,10 @@
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::{
     aggregates, aggregates::AggregateFunction, functions::Signature,
-    type_coercion::data_types, windows::find_ranges_in_range, PhysicalExpr,
+    type_coercion::data_types, udwf::WindowUDF, windows::find_ranges_in_range,
+    PhysicalExpr,
 };
+use crate::scalar::ScalarValue;
 use arrow::array::ArrayRef;
 use arrow::datatypes::DataType;
 use arrow::datatypes::Field;
@@ -41,18 +43,20 @@ pub enum WindowFunction {
     AggregateFunction(AggregateFunction),
     /// window function that leverages a built-in window function
     BuiltInWindowFunction(BuiltInWindowFunction),
+    /// window function that leverages a user-defined window function
+    WindowUDF(Arc<WindowUDF>),
 }
 
 impl FromStr for WindowFunction {
     type Err = DataFusionError;
     fn from_str(name: &str) -> Result<WindowFunction> {
         let name = name.to_lowercase();
-        if let Ok(aggregate) = AggregateFunction::from_str(name.as_str()) {
-            Ok(WindowFunction::AggregateFunction(aggregate))
-        } else if let Ok(built_in_function) =
-            BuiltInWindowFunction::from_str(name.as_str())
-        {
+        // first_value and last_value are also aggregate functions, but over a window
+        // frame they are the built-in window functions
+        if let Ok(built_in_function) = BuiltInWindowFunction::from_str(name.as_str()) {
             Ok(WindowFunction::BuiltInWindowFunction(built_in_function))
+        } else if let Ok(aggregate) = AggregateFunction::from_str(name.as_str()) {
+            Ok(WindowFunction::AggregateFunction(aggregate))
         } else {
             Err(DataFusionError::Plan(format!(
                 "There is no window function named {}",
@@ -85,6 +89,7 @@ impl fmt::Display for WindowFunction {
         match self {
             WindowFunction::AggregateFunction(fun) => fun.fmt(f),
             WindowFunction::BuiltInWindowFunction(fun) => fun.fmt(f),
+            WindowFunction::WindowUDF(fun) => write!(f, "{}", fun.name),
         }
     }
 }
@@ -154,6 +159,10 @@ pub fn return_type(fun: &WindowFunction, arg_types: &[DataType]) -> Result<DataT
         WindowFunction::BuiltInWindowFunction(fun) => {
             return_type_for_built_in(fun, arg_types)
         }
+        WindowFunction::WindowUDF(fun) => {
+            data_types(arg_types, &fun.signature)?;
+            Ok((fun.return_type)(arg_types)?.as_ref().clone())
+        }
     }
 }
 
@@ -189,6 +198,7 @@ pub fn signature(fun: &WindowFunction) -> Signature {
     match fun {
         WindowFunction::AggregateFunction(fun) => aggregates::signature(fun),
         WindowFunction::BuiltInWindowFunction(fun) => signature_for_built_in(fun),
+        WindowFunction::WindowUDF(fun) => fun.signature.clone(),
     }
 }
 
@@ -201,28 +211,44 @@ pub(super) fn signature_for_built_in(fun: &BuiltInWindowFunction) -> Signature {
         | BuiltInWindowFunction::DenseRank
         | BuiltInWindowFunction::PercentRank
         | BuiltInWindowFunction::CumeDist => Signature::Any(0),
+        // the optional trailing boolean argument of the value functions is the
+        // `IGNORE NULLS` flag
         BuiltInWindowFunction::Lag | BuiltInWindowFunction::Lead => {
             Signature::OneOf(vec![
                 Signature::Any(1),
                 Signature::Any(2),
                 Signature::Any(3),
+                Signature::Any(4),
             ])
         }
         BuiltInWindowFunction::FirstValue | BuiltInWindowFunction::LastValue => {
-            Signature::Any(1)
+            Signature::OneOf(vec![Signature::Any(1), Signature::Any(2)])
+        }
+        // the number of buckets must be a positive integer literal, which is checked
+        // when creating the physical expression
+        BuiltInWindowFunction::Ntile => Signature::Any(1),
+        BuiltInWindowFunction::NthValue => {
+            Signature::OneOf(vec![Signature::Any(2), Signature::Any(3)])
         }
-        BuiltInWindowFunction::Ntile => Signature::Exact(vec![DataType::UInt64]),
-        BuiltInWindowFunction::NthValue => Signature::Any(2),
     }
 }
 
-/// Partition evaluator
-pub(crate) trait PartitionEvaluator {
+/// Partition evaluator of a window function, created for the values of its arguments in
+/// a batch whose rows are sorted by the PARTITION BY and then the ORDER BY expressions.
+/// Implemented by user-defined window functions, see [WindowUDF].
+pub trait PartitionEvaluator {
     /// Whether the evaluator should be evaluated with rank
     fn include_rank(&self) -> bool {
         false
     }
 
+    /// Whether the value of a row depends on the rows of its window frame, in which case
+    /// windows with an explicit frame evaluate the evaluator against the frame of each
+    /// row with [PartitionEvaluator::evaluate_frame]
+    fn uses_window_frame(&self) -> bool {
+        false
+    }
+
     /// evaluate the partition evaluator against the partitions
     fn evaluate(&self, partition_points: Vec<Range<usize>>) -> Result<Vec<ArrayRef>> {
         partition_points
@@ -260,6 +286,14 @@ pub(crate) trait PartitionEvaluator {
             "evaluate_partition_with_rank is not implemented by default".into(),
         ))
     }
+
+    /// evaluate the partition evaluator against the rows of the window frame of a row,
+    /// less the excluded ones, which are given as ranges in the order of the rows
+    fn evaluate_frame(&self, _frame: &[Range<usize>]) -> Result<ScalarValue> {
+        Err(DataFusionError::NotImplemented(
+            "evaluate_frame is not implemented by default".into(),
+        ))
+    }
 }
 
 /// A window expression that is a built-in window function.
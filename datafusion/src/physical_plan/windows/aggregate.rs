@@ -18,15 +18,22 @@
 //! Physical exec for aggregate window function expressions.
 
 use crate::error::{DataFusionError, Result};
-use crate::logical_plan::window_frames::{WindowFrame, WindowFrameUnits};
+use crate::logical_plan::window_frames::{
+    WindowFrame, WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
+};
+use crate::physical_plan::aggregates::AggregateFunction;
 use crate::physical_plan::windows::find_ranges_in_range;
 use crate::physical_plan::{
     expressions::PhysicalSortExpr, Accumulator, AggregateExpr, PhysicalExpr, WindowExpr,
 };
+use crate::scalar::ScalarValue;
+use arrow::array::{new_empty_array, BooleanArray};
 use arrow::compute::concat;
 use arrow::record_batch::RecordBatch;
 use arrow::{array::ArrayRef, datatypes::Field};
 use std::any::Any;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::iter::IntoIterator;
 use std::ops::Range;
 use std::sync::Arc;
@@ -34,38 +41,74 @@ use std::sync::Arc;
 /// A window expr that takes the form of an aggregate function
 #[derive(Debug)]
 pub struct AggregateWindowExpr {
+    fun: AggregateFunction,
+    args: Vec<Arc<dyn PhysicalExpr>>,
     aggregate: Arc<dyn AggregateExpr>,
     partition_by: Vec<Arc<dyn PhysicalExpr>>,
     order_by: Vec<PhysicalSortExpr>,
     window_frame: Option<WindowFrame>,
+    distinct: bool,
 }
 
 impl AggregateWindowExpr {
     /// create a new aggregate window function expression
     pub(super) fn new(
+        fun: AggregateFunction,
+        args: &[Arc<dyn PhysicalExpr>],
         aggregate: Arc<dyn AggregateExpr>,
         partition_by: &[Arc<dyn PhysicalExpr>],
         order_by: &[PhysicalSortExpr],
         window_frame: Option<WindowFrame>,
     ) -> Self {
         Self {
+            fun,
+            args: args.to_vec(),
             aggregate,
             partition_by: partition_by.to_vec(),
             order_by: order_by.to_vec(),
             window_frame,
+            distinct: false,
         }
     }
 
-    /// the aggregate window function operates based on window frame, and by default the mode is
-    /// "range".
-    fn evaluation_mode(&self) -> WindowFrameUnits {
-        self.window_frame.unwrap_or_default().units
+    /// Whether only the distinct values of the arguments in the frame are aggregated
+    pub(super) fn with_distinct(mut self, distinct: bool) -> Self {
+        self.distinct = distinct;
+        self
+    }
+
+    /// The aggregate function
+    pub fn fun(&self) -> &AggregateFunction {
+        &self.fun
+    }
+
+    /// The arguments of the aggregate function, before they are coerced to its signature
+    pub fn args(&self) -> &[Arc<dyn PhysicalExpr>] {
+        &self.args
+    }
+
+    /// The frame of the window, if it was specified
+    pub fn window_frame(&self) -> Option<&WindowFrame> {
+        self.window_frame.as_ref()
+    }
+
+    /// Whether only the distinct values of the arguments in the frame are aggregated
+    pub fn distinct(&self) -> bool {
+        self.distinct
     }
 
     /// create a new accumulator based on the underlying aggregation function
     fn create_accumulator(&self) -> Result<AggregateWindowAccumulator> {
         let accumulator = self.aggregate.create_accumulator()?;
-        Ok(AggregateWindowAccumulator { accumulator })
+        let distinct_values = if self.distinct {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        Ok(AggregateWindowAccumulator {
+            accumulator,
+            distinct_values,
+        })
     }
 
     /// peer based evaluation based on the fact that batch is pre-sorted given the sort columns
@@ -97,18 +140,78 @@ impl AggregateWindowExpr {
         concat(&results).map_err(DataFusionError::ArrowError)
     }
 
-    fn group_based_evaluate(&self, _batch: &RecordBatch) -> Result<ArrayRef> {
-        Err(DataFusionError::NotImplemented(format!(
-            "Group based evaluation for {} is not yet implemented",
-            self.name()
-        )))
-    }
-
-    fn row_based_evaluate(&self, _batch: &RecordBatch) -> Result<ArrayRef> {
-        Err(DataFusionError::NotImplemented(format!(
-            "Row based evaluation for {} is not yet implemented",
-            self.name()
-        )))
+    /// frame based evaluation. Frames that span the whole partition are aggregated once,
+    /// and frames that start at the start of the partition are aggregated incrementally,
+    /// as they only grow from one row to the next. The rows of the other frames, which
+    /// slide or exclude rows, are aggregated separately for each row.
+    fn frame_based_evaluate(
+        &self,
+        batch: &RecordBatch,
+        window_frame: &WindowFrame,
+    ) -> Result<ArrayRef> {
+        let num_rows = batch.num_rows();
+        if num_rows == 0 {
+            return Ok(new_empty_array(self.field()?.data_type()));
+        }
+        let partition_points =
+            self.evaluate_partition_points(num_rows, &self.partition_columns(batch)?)?;
+        let sort_partition_points =
+            self.evaluate_partition_points(num_rows, &self.sort_columns(batch)?)?;
+        let values = self.evaluate_args(batch)?;
+        let mut results = Vec::with_capacity(num_rows);
+        let unbounded_preceding = window_frame.start_bound
+            == WindowFrameBound::Preceding(None)
+            && window_frame.exclude == WindowFrameExclusion::NoOthers;
+        for partition_range in &partition_points {
+            let peers = find_ranges_in_range(partition_range, &sort_partition_points);
+            if unbounded_preceding
+                && window_frame.end_bound == WindowFrameBound::Following(None)
+            {
+                let mut accumulator = self.create_accumulator()?;
+                accumulator.update(&values, partition_range)?;
+                let value = accumulator.accumulator.evaluate()?;
+                results.extend(std::iter::repeat(value).take(partition_range.len()));
+            } else if unbounded_preceding {
+                // the frame of each row is the one of the previous row, and maybe more
+                let mut accumulator = self.create_accumulator()?;
+                let mut end = partition_range.start;
+                for (group, peer_range) in peers.iter().enumerate() {
+                    for row in peer_range.clone() {
+                        let frame = frame_ranges(
+                            window_frame,
+                            partition_range,
+                            peers,
+                            group,
+                            row,
+                        )?;
+                        let frame_end = frame.last().map_or(end, |range| range.end);
+                        if frame_end > end {
+                            accumulator.update(&values, &(end..frame_end))?;
+                            end = frame_end;
+                        }
+                        results.push(accumulator.accumulator.evaluate()?);
+                    }
+                }
+            } else {
+                for (group, peer_range) in peers.iter().enumerate() {
+                    for row in peer_range.clone() {
+                        let mut accumulator = self.create_accumulator()?;
+                        let frame = frame_ranges(
+                            window_frame,
+                            partition_range,
+                            peers,
+                            group,
+                            row,
+                        )?;
+                        for range in frame.iter().filter(|range| !range.is_empty()) {
+                            accumulator.update(&values, range)?;
+                        }
+                        results.push(accumulator.accumulator.evaluate()?);
+                    }
+                }
+            }
+        }
+        ScalarValue::iter_to_array(results)
     }
 }
 
@@ -140,12 +243,93 @@ impl WindowExpr for AggregateWindowExpr {
 
     /// evaluate the window function values against the batch
     fn evaluate(&self, batch: &RecordBatch) -> Result<ArrayRef> {
-        match self.evaluation_mode() {
-            WindowFrameUnits::Range => self.peer_based_evaluate(batch),
-            WindowFrameUnits::Rows => self.row_based_evaluate(batch),
-            WindowFrameUnits::Groups => self.group_based_evaluate(batch),
+        match &self.window_frame {
+            // the default frame ends with the peers of the current row, which the
+            // cumulative peer based evaluation supports
+            Some(window_frame) if *window_frame != WindowFrame::default() => {
+                self.frame_based_evaluate(batch, window_frame)
+            }
+            _ => self.peer_based_evaluate(batch),
+        }
+    }
+}
+
+/// Returns the rows of the frame of `row`, less the excluded ones, as ranges in the order
+/// of the rows. `groups` are the peer groups of the partition of the row, of which the
+/// row belongs to the one at `group`.
+pub(super) fn frame_ranges(
+    window_frame: &WindowFrame,
+    partition: &Range<usize>,
+    groups: &[Range<usize>],
+    group: usize,
+    row: usize,
+) -> Result<Vec<Range<usize>>> {
+    let offset = |n: u64| usize::try_from(n).unwrap_or(usize::MAX);
+    let start = match (window_frame.units, window_frame.start_bound) {
+        (_, WindowFrameBound::Preceding(None)) => partition.start,
+        (_, WindowFrameBound::Following(None)) => partition.end,
+        (WindowFrameUnits::Rows, WindowFrameBound::Preceding(Some(n))) => {
+            row.saturating_sub(offset(n)).max(partition.start)
+        }
+        (WindowFrameUnits::Rows, WindowFrameBound::CurrentRow) => row,
+        (WindowFrameUnits::Rows, WindowFrameBound::Following(Some(n))) => {
+            row.saturating_add(offset(n)).min(partition.end)
+        }
+        (WindowFrameUnits::Groups, WindowFrameBound::Preceding(Some(n))) => {
+            groups[group.saturating_sub(offset(n))].start
+        }
+        (WindowFrameUnits::Groups, WindowFrameBound::CurrentRow) => groups[group].start,
+        (WindowFrameUnits::Groups, WindowFrameBound::Following(Some(n))) => groups
+            .get(group.saturating_add(offset(n)))
+            .map_or(partition.end, |g| g.start),
+        (WindowFrameUnits::Range, bound) if bound == WindowFrameBound::CurrentRow => {
+            groups[group].start
+        }
+        (WindowFrameUnits::Range, bound) => return Err(range_offset_error(bound)),
+    };
+    let end = match (window_frame.units, window_frame.end_bound) {
+        (_, WindowFrameBound::Preceding(None)) => partition.start,
+        (_, WindowFrameBound::Following(None)) => partition.end,
+        (WindowFrameUnits::Rows, WindowFrameBound::Preceding(Some(n))) => {
+            (row + 1).saturating_sub(offset(n)).max(partition.start)
         }
+        (WindowFrameUnits::Rows, WindowFrameBound::CurrentRow) => row + 1,
+        (WindowFrameUnits::Rows, WindowFrameBound::Following(Some(n))) => {
+            (row + 1).saturating_add(offset(n)).min(partition.end)
+        }
+        (WindowFrameUnits::Groups, WindowFrameBound::Preceding(Some(n))) => group
+            .checked_sub(offset(n))
+            .map_or(partition.start, |g| groups[g].end),
+        (WindowFrameUnits::Groups, WindowFrameBound::CurrentRow) => groups[group].end,
+        (WindowFrameUnits::Groups, WindowFrameBound::Following(Some(n))) => groups
+            .get(group.saturating_add(offset(n)))
+            .map_or(partition.end, |g| g.end),
+        (WindowFrameUnits::Range, bound) if bound == WindowFrameBound::CurrentRow => {
+            groups[group].end
+        }
+        (WindowFrameUnits::Range, bound) => return Err(range_offset_error(bound)),
+    };
+    let frame = start..end.max(start);
+
+    let excluded = match window_frame.exclude {
+        WindowFrameExclusion::NoOthers => return Ok(vec![frame]),
+        WindowFrameExclusion::CurrentRow => row..row + 1,
+        WindowFrameExclusion::Group | WindowFrameExclusion::Ties => groups[group].clone(),
+    };
+    let mut ranges = vec![frame.start..excluded.start.clamp(frame.start, frame.end)];
+    if window_frame.exclude == WindowFrameExclusion::Ties && frame.contains(&row) {
+        ranges.push(row..row + 1);
     }
+    ranges.push(excluded.end.clamp(frame.start, frame.end)..frame.end);
+    Ok(ranges)
+}
+
+fn range_offset_error(bound: WindowFrameBound) -> DataFusionError {
+    DataFusionError::NotImplemented(format!(
+        "With WindowFrameUnits={}, the bound cannot be {} at the moment",
+        WindowFrameUnits::Range,
+        bound
+    ))
 }
 
 /// Aggregate window accumulator utilizes the accumulator from aggregation and do a accumulative sum
@@ -153,9 +337,39 @@ impl WindowExpr for AggregateWindowExpr {
 #[derive(Debug)]
 struct AggregateWindowAccumulator {
     accumulator: Box<dyn Accumulator>,
+    /// The values of the arguments that were aggregated, for DISTINCT aggregates
+    distinct_values: Option<HashSet<Vec<ScalarValue>>>,
 }
 
 impl AggregateWindowAccumulator {
+    /// aggregate the values (as arguments to window function) given by the value_range,
+    /// skipping those that were already aggregated for DISTINCT aggregates.
+    fn update(&mut self, values: &[ArrayRef], value_range: &Range<usize>) -> Result<()> {
+        // the rows whose values were not aggregated yet
+        let selection = match &mut self.distinct_values {
+            Some(distinct_values) => Some(
+                value_range
+                    .clone()
+                    .map(|row| {
+                        let row_values = values
+                            .iter()
+                            .map(|v| ScalarValue::try_from_array(v, row))
+                            .collect::<Result<Vec<_>>>()?;
+                        Ok(distinct_values.insert(row_values))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        let len = value_range.end - value_range.start;
+        let values = values
+            .iter()
+            .map(|v| v.slice(value_range.start, len))
+            .collect::<Vec<_>>();
+        self.accumulator
+            .update_batch_selected(&values, selection.map(BooleanArray::from).as_ref())
+    }
+
     /// scan one peer group of values (as arguments to window function) given by the value_range
     /// and return evaluation result that are of the same number of rows.
     fn scan_peers(
@@ -168,13 +382,8 @@ impl AggregateWindowAccumulator {
                 "Value range cannot be empty".to_owned(),
             ));
         }
-        let len = value_range.end - value_range.start;
-        let values = values
-            .iter()
-            .map(|v| v.slice(value_range.start, len))
-            .collect::<Vec<_>>();
-        self.accumulator.update_batch(&values)?;
+        self.update(values, value_range)?;
         let value = self.accumulator.evaluate()?;
-        Ok(value.to_array_of_size(len))
+        Ok(value.to_array_of_size(value_range.end - value_range.start))
     }
 }
@@ -19,21 +19,26 @@
 
 use crate::error::{DataFusionError, Result};
 use crate::logical_plan::window_frames::WindowFrame;
+use crate::physical_plan::windows::aggregate::frame_ranges;
+use crate::physical_plan::windows::find_ranges_in_range;
 use crate::physical_plan::{
     expressions::PhysicalSortExpr,
-    window_functions::{BuiltInWindowFunction, BuiltInWindowFunctionExpr},
+    window_functions::{BuiltInWindowFunctionExpr, PartitionEvaluator, WindowFunction},
     PhysicalExpr, WindowExpr,
 };
+use crate::scalar::ScalarValue;
+use arrow::array::new_empty_array;
 use arrow::compute::concat;
 use arrow::record_batch::RecordBatch;
 use arrow::{array::ArrayRef, datatypes::Field};
 use std::any::Any;
 use std::sync::Arc;
 
-/// A window expr that takes the form of a built in window function
+/// A window expr that takes the form of a built in or user-defined window function
 #[derive(Debug)]
 pub struct BuiltInWindowExpr {
-    fun: BuiltInWindowFunction,
+    fun: WindowFunction,
+    args: Vec<Arc<dyn PhysicalExpr>>,
     expr: Arc<dyn BuiltInWindowFunctionExpr>,
     partition_by: Vec<Arc<dyn PhysicalExpr>>,
     order_by: Vec<PhysicalSortExpr>,
@@ -43,7 +48,8 @@ pub struct BuiltInWindowExpr {
 impl BuiltInWindowExpr {
     /// create a new built-in window function expression
     pub(super) fn new(
-        fun: BuiltInWindowFunction,
+        fun: WindowFunction,
+        args: &[Arc<dyn PhysicalExpr>],
         expr: Arc<dyn BuiltInWindowFunctionExpr>,
         partition_by: &[Arc<dyn PhysicalExpr>],
         order_by: &[PhysicalSortExpr],
@@ -51,12 +57,58 @@ impl BuiltInWindowExpr {
     ) -> Self {
         Self {
             fun,
+            args: args.to_vec(),
             expr,
             partition_by: partition_by.to_vec(),
             order_by: order_by.to_vec(),
             window_frame,
         }
     }
+
+    /// The window function
+    pub fn fun(&self) -> &WindowFunction {
+        &self.fun
+    }
+
+    /// The arguments of the window function, before they are coerced to its signature
+    pub fn args(&self) -> &[Arc<dyn PhysicalExpr>] {
+        &self.args
+    }
+
+    /// The frame of the window, if it was specified
+    pub fn window_frame(&self) -> Option<&WindowFrame> {
+        self.window_frame.as_ref()
+    }
+
+    /// frame based evaluation, which evaluates the function against the frame of each
+    /// row, less the excluded rows, separately.
+    fn frame_based_evaluate(
+        &self,
+        evaluator: &dyn PartitionEvaluator,
+        batch: &RecordBatch,
+        window_frame: &WindowFrame,
+    ) -> Result<ArrayRef> {
+        let num_rows = batch.num_rows();
+        if num_rows == 0 {
+            return Ok(new_empty_array(self.field()?.data_type()));
+        }
+        let partition_points =
+            self.evaluate_partition_points(num_rows, &self.partition_columns(batch)?)?;
+        let sort_partition_points =
+            self.evaluate_partition_points(num_rows, &self.sort_columns(batch)?)?;
+        let mut results = Vec::with_capacity(num_rows);
+        for partition_range in &partition_points {
+            let peers = find_ranges_in_range(partition_range, &sort_partition_points);
+            for (group, peer_range) in peers.iter().enumerate() {
+                for row in peer_range.clone() {
+                    let frame =
+                        frame_ranges(window_frame, partition_range, peers, group, row)?;
+                    results.push(evaluator.evaluate_frame(&frame)?);
+                }
+            }
+        }
+        ScalarValue::iter_to_array(results)
+    }
 }
 
 impl WindowExpr for BuiltInWindowExpr {
@@ -87,6 +139,21 @@ impl WindowExpr for BuiltInWindowExpr {
 
     fn evaluate(&self, batch: &RecordBatch) -> Result<ArrayRef> {
         let evaluator = self.expr.create_evaluator(batch)?;
+        match &self.window_frame {
+            // the default frame ends with the peers of the current row, which the
+            // evaluation with rank supports
+            Some(window_frame)
+                if evaluator.uses_window_frame()
+                    && *window_frame != WindowFrame::default() =>
+            {
+                return self.frame_based_evaluate(
+                    evaluator.as_ref(),
+                    batch,
+                    window_frame,
+                );
+            }
+            _ => {}
+        }
         let num_rows = batch.num_rows();
         let partition_points =
             self.evaluate_partition_points(num_rows, &self.partition_columns(batch)?)?;
@@ -22,9 +22,11 @@ use crate::logical_plan::window_frames::WindowFrame;
 use crate::physical_plan::{
     aggregates,
     expressions::{
-        dense_rank, lag, lead, rank, Literal, NthValue, PhysicalSortExpr, RowNumber,
+        cume_dist, dense_rank, lag, lead, percent_rank, rank, Literal, NthValue, Ntile,
+        PhysicalSortExpr, RowNumber,
     },
     type_coercion::coerce,
+    udwf,
     window_functions::{
         signature_for_built_in, BuiltInWindowFunction, BuiltInWindowFunctionExpr,
         WindowFunction,
@@ -45,7 +47,9 @@ pub use aggregate::AggregateWindowExpr;
 pub use built_in::BuiltInWindowExpr;
 pub use window_agg_exec::WindowAggExec;
 
-/// Create a physical expression for window function
+/// Create a physical expression for window function, which only aggregates the
+/// distinct values of its arguments in the frame if `distinct` is set
+#[allow(clippy::too_many_arguments)]
 pub fn create_window_expr(
     fun: &WindowFunction,
     name: String,
@@ -53,18 +57,43 @@ pub fn create_window_expr(
     partition_by: &[Arc<dyn PhysicalExpr>],
     order_by: &[PhysicalSortExpr],
     window_frame: Option<WindowFrame>,
+    distinct: bool,
     input_schema: &Schema,
 ) -> Result<Arc<dyn WindowExpr>> {
+    if distinct && !matches!(fun, WindowFunction::AggregateFunction(_)) {
+        return Err(DataFusionError::Plan(format!(
+            "DISTINCT is only supported by aggregate window functions, not {}",
+            fun
+        )));
+    }
     Ok(match fun {
-        WindowFunction::AggregateFunction(fun) => Arc::new(AggregateWindowExpr::new(
-            aggregates::create_aggregate_expr(fun, false, args, input_schema, name)?,
-            partition_by,
-            order_by,
-            window_frame,
-        )),
-        WindowFunction::BuiltInWindowFunction(fun) => Arc::new(BuiltInWindowExpr::new(
+        WindowFunction::AggregateFunction(fun) => Arc::new(
+            AggregateWindowExpr::new(
+                fun.clone(),
+                args,
+                // the window expression skips the repeated values itself, so that any
+                // aggregate function can be DISTINCT
+                aggregates::create_aggregate_expr(fun, false, args, input_schema, name)?,
+                partition_by,
+                order_by,
+                window_frame,
+            )
+            .with_distinct(distinct),
+        ),
+        WindowFunction::BuiltInWindowFunction(built_in) => {
+            Arc::new(BuiltInWindowExpr::new(
+                fun.clone(),
+                args,
+                create_built_in_window_expr(built_in, args, input_schema, name)?,
+                partition_by,
+                order_by,
+                window_frame,
+            ))
+        }
+        WindowFunction::WindowUDF(udwf) => Arc::new(BuiltInWindowExpr::new(
             fun.clone(),
-            create_built_in_window_expr(fun, args, input_schema, name)?,
+            args,
+            udwf::create_window_udf_expr(udwf, args, input_schema, name)?,
             partition_by,
             order_by,
             window_frame,
@@ -85,6 +114,24 @@ fn get_scalar_value_from_args(
     })
 }
 
+/// Returns whether the optional boolean `IGNORE NULLS` argument at `index` is set
+fn get_ignore_nulls_from_args(
+    args: &[Arc<dyn PhysicalExpr>],
+    index: usize,
+) -> Result<bool> {
+    let arg = match args.get(index) {
+        Some(arg) => arg,
+        None => return Ok(false),
+    };
+    match arg.as_any().downcast_ref::<Literal>().map(|v| v.value()) {
+        Some(ScalarValue::Boolean(ignore_nulls)) => Ok(ignore_nulls.unwrap_or(false)),
+        _ => Err(DataFusionError::Plan(format!(
+            "The IGNORE NULLS argument of a window function must be a boolean literal, got {:?}",
+            arg
+        ))),
+    }
+}
+
 fn create_built_in_window_expr(
     fun: &BuiltInWindowFunction,
     args: &[Arc<dyn PhysicalExpr>],
@@ -95,6 +142,8 @@ fn create_built_in_window_expr(
         BuiltInWindowFunction::RowNumber => Arc::new(RowNumber::new(name)),
         BuiltInWindowFunction::Rank => Arc::new(rank(name)),
         BuiltInWindowFunction::DenseRank => Arc::new(dense_rank(name)),
+        BuiltInWindowFunction::PercentRank => Arc::new(percent_rank(name)),
+        BuiltInWindowFunction::CumeDist => Arc::new(cume_dist(name)),
         BuiltInWindowFunction::Lag => {
             let coerced_args = coerce(args, input_schema, &signature_for_built_in(fun))?;
             let arg = coerced_args[0].clone();
@@ -103,7 +152,11 @@ fn create_built_in_window_expr(
                 .map(|v| v.try_into())
                 .and_then(|v| v.ok());
             let default_value = get_scalar_value_from_args(&coerced_args, 2);
-            Arc::new(lag(name, data_type, arg, shift_offset, default_value))
+            let ignore_nulls = get_ignore_nulls_from_args(&coerced_args, 3)?;
+            Arc::new(
+                lag(name, data_type, arg, shift_offset, default_value)
+                    .with_ignore_nulls(ignore_nulls),
+            )
         }
         BuiltInWindowFunction::Lead => {
             let coerced_args = coerce(args, input_schema, &signature_for_built_in(fun))?;
@@ -113,7 +166,11 @@ fn create_built_in_window_expr(
                 .map(|v| v.try_into())
                 .and_then(|v| v.ok());
             let default_value = get_scalar_value_from_args(&coerced_args, 2);
-            Arc::new(lead(name, data_type, arg, shift_offset, default_value))
+            let ignore_nulls = get_ignore_nulls_from_args(&coerced_args, 3)?;
+            Arc::new(
+                lead(name, data_type, arg, shift_offset, default_value)
+                    .with_ignore_nulls(ignore_nulls),
+            )
         }
         BuiltInWindowFunction::NthValue => {
             let coerced_args = coerce(args, input_schema, &signature_for_built_in(fun))?;
@@ -129,25 +186,45 @@ fn create_built_in_window_expr(
                 .map_err(|e| DataFusionError::Execution(format!("{:?}", e)))?;
             let n: u32 = n as u32;
             let data_type = args[0].data_type(input_schema)?;
-            Arc::new(NthValue::nth_value(name, arg, data_type, n)?)
+            let ignore_nulls = get_ignore_nulls_from_args(&coerced_args, 2)?;
+            Arc::new(
+                NthValue::nth_value(name, arg, data_type, n)?
+                    .with_ignore_nulls(ignore_nulls),
+            )
         }
         BuiltInWindowFunction::FirstValue => {
-            let arg =
-                coerce(args, input_schema, &signature_for_built_in(fun))?[0].clone();
+            let coerced_args = coerce(args, input_schema, &signature_for_built_in(fun))?;
+            let arg = coerced_args[0].clone();
             let data_type = args[0].data_type(input_schema)?;
-            Arc::new(NthValue::first_value(name, arg, data_type))
+            let ignore_nulls = get_ignore_nulls_from_args(&coerced_args, 1)?;
+            Arc::new(
+                NthValue::first_value(name, arg, data_type)
+                    .with_ignore_nulls(ignore_nulls),
+            )
         }
         BuiltInWindowFunction::LastValue => {
-            let arg =
-                coerce(args, input_schema, &signature_for_built_in(fun))?[0].clone();
+            let coerced_args = coerce(args, input_schema, &signature_for_built_in(fun))?;
+            let arg = coerced_args[0].clone();
             let data_type = args[0].data_type(input_schema)?;
-            Arc::new(NthValue::last_value(name, arg, data_type))
+            let ignore_nulls = get_ignore_nulls_from_args(&coerced_args, 1)?;
+            Arc::new(
+                NthValue::last_value(name, arg, data_type)
+                    .with_ignore_nulls(ignore_nulls),
+            )
         }
-        _ => {
-            return Err(DataFusionError::NotImplemented(format!(
-                "Window function with {:?} not yet implemented",
-                fun
-            )))
+        BuiltInWindowFunction::Ntile => {
+            let n: i64 = args[0]
+                .as_any()
+                .downcast_ref::<Literal>()
+                .and_then(|v| v.value().clone().try_into().ok())
+                .filter(|n| *n > 0)
+                .ok_or_else(|| {
+                    DataFusionError::Plan(format!(
+                        "ntile expects a positive integer literal, got {:?}",
+                        args[0]
+                    ))
+                })?;
+            Arc::new(Ntile::new(name, n as u64)?)
         }
     })
 }
@@ -172,13 +249,18 @@ pub(crate) fn find_ranges_in_range<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::logical_plan::window_frames::{
+        WindowFrameBound, WindowFrameExclusion, WindowFrameUnits,
+    };
     use crate::physical_plan::aggregates::AggregateFunction;
     use crate::physical_plan::collect;
     use crate::physical_plan::csv::{CsvExec, CsvReadOptions};
     use crate::physical_plan::expressions::col;
+    use crate::physical_plan::memory::MemoryExec;
     use crate::test;
     use arrow::array::*;
-    use arrow::datatypes::SchemaRef;
+    use arrow::compute::SortOptions;
+    use arrow::datatypes::{DataType, Field, SchemaRef};
     use arrow::record_batch::RecordBatch;
 
     fn create_test_schema(partitions: usize) -> Result<(Arc<CsvExec>, SchemaRef)> {
@@ -209,6 +291,7 @@ mod tests {
                     &[],
                     &[],
                     Some(WindowFrame::default()),
+                    false,
                     schema.as_ref(),
                 )?,
                 create_window_expr(
@@ -218,6 +301,7 @@ mod tests {
                     &[],
                     &[],
                     Some(WindowFrame::default()),
+                    false,
                     schema.as_ref(),
                 )?,
                 create_window_expr(
@@ -227,6 +311,7 @@ mod tests {
                     &[],
                     &[],
                     Some(WindowFrame::default()),
+                    false,
                     schema.as_ref(),
                 )?,
             ],
@@ -255,4 +340,84 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn window_frame_exclusion_and_distinct() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![10, 10, 20, 30])),
+                Arc::new(Int32Array::from(vec![1, 2, 2, 3])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+        let order_by = vec![PhysicalSortExpr {
+            expr: col("b", &schema)?,
+            options: SortOptions::default(),
+        }];
+        let sum = |window_frame, distinct| {
+            create_window_expr(
+                &WindowFunction::AggregateFunction(AggregateFunction::Sum),
+                "sum".to_owned(),
+                &[col("a", &schema)?],
+                &[],
+                &order_by,
+                Some(window_frame),
+                distinct,
+                schema.as_ref(),
+            )
+        };
+
+        let window_exec = Arc::new(WindowAggExec::try_new(
+            vec![
+                sum(
+                    WindowFrame {
+                        units: WindowFrameUnits::Rows,
+                        start_bound: WindowFrameBound::Preceding(Some(1)),
+                        end_bound: WindowFrameBound::Following(Some(1)),
+                        exclude: WindowFrameExclusion::CurrentRow,
+                    },
+                    false,
+                )?,
+                sum(WindowFrame::default(), true)?,
+                sum(
+                    WindowFrame {
+                        units: WindowFrameUnits::Range,
+                        start_bound: WindowFrameBound::Preceding(None),
+                        end_bound: WindowFrameBound::Following(None),
+                        exclude: WindowFrameExclusion::Ties,
+                    },
+                    false,
+                )?,
+                sum(
+                    WindowFrame {
+                        units: WindowFrameUnits::Groups,
+                        start_bound: WindowFrameBound::Preceding(Some(1)),
+                        end_bound: WindowFrameBound::CurrentRow,
+                        exclude: WindowFrameExclusion::Group,
+                    },
+                    false,
+                )?,
+            ],
+            input,
+            schema.clone(),
+        )?);
+
+        let result: Vec<RecordBatch> = collect(window_exec).await?;
+        let columns = result[0].columns();
+        let values = |i: usize| -> Vec<Option<i64>> {
+            as_primitive_array::<arrow::datatypes::Int64Type>(&columns[i])
+                .iter()
+                .collect()
+        };
+        assert_eq!(vec![Some(10), Some(30), Some(40), Some(20)], values(0));
+        assert_eq!(vec![Some(10), Some(30), Some(30), Some(60)], values(1));
+        assert_eq!(vec![Some(70), Some(50), Some(60), Some(70)], values(2));
+        assert_eq!(vec![None, Some(10), Some(10), Some(30)], values(3));
+        Ok(())
+    }
 }
@@ -0,0 +1,497 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Structural comparison of plans, for tests and CI checks that guard the plans of
+//! queries against unexpected changes, e.g. after an upgrade or a new optimizer rule.
+//!
+//! The nodes of the two plans are matched by their position in the tree. Nodes with the
+//! same operator are compared by their description and expressions, while a node whose
+//! operator changed is reported as removed along with its inputs, and its replacement as
+//! added.
+//!
+//! ```
+//! # use datafusion::error::Result;
+//! # fn main() -> Result<()> {
+//! use datafusion::arrow::datatypes::{DataType, Field, Schema};
+//! use datafusion::logical_plan::{col, lit, LogicalPlanBuilder};
+//! use datafusion::plan_diff::diff_logical_plans;
+//!
+//! let schema = Schema::new(vec![Field::new("a", DataType::Int32, false)]);
+//! let scan = LogicalPlanBuilder::scan_empty(Some("t"), &schema, None)?;
+//! let before = scan.filter(col("a").gt(lit(1)))?.build()?;
+//! let after = scan.filter(col("a").gt(lit(2)))?.build()?;
+//!
+//! let diff = diff_logical_plans(&before, &after);
+//! assert_eq!(1, diff.changes.len());
+//! assert_eq!(
+//!     "~ [] Filter\n    - #t.a Gt Int32(1)\n    + #t.a Gt Int32(2)\n",
+//!     diff.to_string()
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::logical_plan::{json_string, LogicalPlan};
+use crate::physical_plan::{DisplayFormatType, ExecutionPlan};
+
+/// The differences between two plans, in the order of a depth first traversal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanDiff {
+    /// The changed nodes
+    pub changes: Vec<PlanChange>,
+}
+
+/// A node that differs between two plans
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanChange {
+    /// The position of the node, as the indices of the inputs to follow from the root
+    pub path: Vec<usize>,
+    /// How the node differs
+    pub kind: PlanChangeKind,
+}
+
+/// How a node differs between two plans
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanChangeKind {
+    /// The node and its inputs only exist in the second plan
+    Added(PlanNode),
+    /// The node and its inputs only exist in the first plan
+    Removed(PlanNode),
+    /// The node has the same operator in both plans but a different description
+    Changed {
+        /// The operator of the node
+        operator: String,
+        /// The description of the node in the first plan
+        before: String,
+        /// The description of the node in the second plan
+        after: String,
+        /// The expressions of the node that only the first plan has
+        removed_exprs: Vec<String>,
+        /// The expressions of the node that only the second plan has
+        added_exprs: Vec<String>,
+    },
+}
+
+/// A node of a plan, with the parts of it that are compared
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanNode {
+    /// The name of the operator, e.g. `Filter` or `FilterExec`
+    pub operator: String,
+    /// The one line description of the node, as displayed by `EXPLAIN`
+    pub description: String,
+    /// The expressions of the node, empty for physical plans
+    pub exprs: Vec<String>,
+    /// The inputs of the node
+    pub inputs: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// Returns the tree of the nodes of a logical plan
+    pub fn from_logical_plan(plan: &LogicalPlan) -> Self {
+        Self::new(
+            plan.display().to_string(),
+            plan.expressions()
+                .iter()
+                .map(|expr| format!("{:?}", expr))
+                .collect(),
+            plan.inputs()
+                .into_iter()
+                .map(Self::from_logical_plan)
+                .collect(),
+        )
+    }
+
+    /// Returns the tree of the nodes of a physical plan
+    pub fn from_physical_plan(plan: &Arc<dyn ExecutionPlan>) -> Self {
+        struct Wrapper<'a>(&'a dyn ExecutionPlan);
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt_as(DisplayFormatType::Default, f)
+            }
+        }
+        Self::new(
+            Wrapper(plan.as_ref()).to_string(),
+            vec![],
+            plan.children()
+                .iter()
+                .map(Self::from_physical_plan)
+                .collect(),
+        )
+    }
+
+    fn new(description: String, exprs: Vec<String>, inputs: Vec<PlanNode>) -> Self {
+        let operator = match description.find(':') {
+            Some(end) => description[..end].to_owned(),
+            None => description.clone(),
+        };
+        Self {
+            operator,
+            description,
+            exprs,
+            inputs,
+        }
+    }
+
+    /// Writes the node and its inputs as a JSON object
+    fn write_json(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{\"operator\":{},\"description\":{},\"exprs\":",
+            json_string(&self.operator),
+            json_string(&self.description)
+        )?;
+        write_json_strings(f, &self.exprs)?;
+        write!(f, ",\"inputs\":[")?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            input.write_json(f)?;
+        }
+        write!(f, "]}}")
+    }
+
+    /// Writes the node and its inputs, one line per node
+    fn write_indented(
+        &self,
+        f: &mut fmt::Formatter,
+        prefix: &str,
+        indent: usize,
+    ) -> fmt::Result {
+        writeln!(
+            f,
+            "{}{:indent$}{}",
+            prefix,
+            "",
+            self.description,
+            indent = indent
+        )?;
+        for input in &self.inputs {
+            input.write_indented(f, prefix, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two logical plans
+pub fn diff_logical_plans(before: &LogicalPlan, after: &LogicalPlan) -> PlanDiff {
+    diff_plan_nodes(
+        &PlanNode::from_logical_plan(before),
+        &PlanNode::from_logical_plan(after),
+    )
+}
+
+/// Compares two physical plans
+pub fn diff_physical_plans(
+    before: &Arc<dyn ExecutionPlan>,
+    after: &Arc<dyn ExecutionPlan>,
+) -> PlanDiff {
+    diff_plan_nodes(
+        &PlanNode::from_physical_plan(before),
+        &PlanNode::from_physical_plan(after),
+    )
+}
+
+/// Compares two trees of plan nodes
+pub fn diff_plan_nodes(before: &PlanNode, after: &PlanNode) -> PlanDiff {
+    let mut diff = PlanDiff::default();
+    diff_nodes(before, after, &mut vec![], &mut diff.changes);
+    diff
+}
+
+fn diff_nodes(
+    before: &PlanNode,
+    after: &PlanNode,
+    path: &mut Vec<usize>,
+    changes: &mut Vec<PlanChange>,
+) {
+    if before.operator != after.operator {
+        changes.push(PlanChange {
+            path: path.clone(),
+            kind: PlanChangeKind::Removed(before.clone()),
+        });
+        changes.push(PlanChange {
+            path: path.clone(),
+            kind: PlanChangeKind::Added(after.clone()),
+        });
+        return;
+    }
+    if before.description != after.description || before.exprs != after.exprs {
+        changes.push(PlanChange {
+            path: path.clone(),
+            kind: PlanChangeKind::Changed {
+                operator: before.operator.clone(),
+                before: before.description.clone(),
+                after: after.description.clone(),
+                removed_exprs: missing_from(&before.exprs, &after.exprs),
+                added_exprs: missing_from(&after.exprs, &before.exprs),
+            },
+        });
+    }
+    let common = before.inputs.len().min(after.inputs.len());
+    for i in 0..before.inputs.len().max(after.inputs.len()) {
+        path.push(i);
+        if i < common {
+            diff_nodes(&before.inputs[i], &after.inputs[i], path, changes);
+        } else if i < before.inputs.len() {
+            changes.push(PlanChange {
+                path: path.clone(),
+                kind: PlanChangeKind::Removed(before.inputs[i].clone()),
+            });
+        } else {
+            changes.push(PlanChange {
+                path: path.clone(),
+                kind: PlanChangeKind::Added(after.inputs[i].clone()),
+            });
+        }
+        path.pop();
+    }
+}
+
+/// Returns the expressions of `exprs` that `other` does not have
+fn missing_from(exprs: &[String], other: &[String]) -> Vec<String> {
+    exprs
+        .iter()
+        .filter(|expr| !other.contains(expr))
+        .cloned()
+        .collect()
+}
+
+impl PlanDiff {
+    /// Whether the plans are the same
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Return a `format`able structure that produces a JSON array with an object per
+    /// change, for tools that process the differences.
+    ///
+    /// ```text
+    /// [{"path":[0],"change":"changed","operator":"Filter","before":"Filter: #a Gt Int32(1)",
+    ///   "after":"Filter: #a Gt Int32(2)","removed_exprs":[...],"added_exprs":[...]}]
+    /// ```
+    pub fn json(&self) -> impl fmt::Display + '_ {
+        struct Wrapper<'a>(&'a PlanDiff);
+        impl<'a> fmt::Display for Wrapper<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "[")?;
+                for (i, change) in self.0.changes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    change.write_json(f)?;
+                }
+                write!(f, "]")
+            }
+        }
+        Wrapper(self)
+    }
+}
+
+impl PlanChange {
+    fn write_json(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = self
+            .path
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(f, "{{\"path\":[{}],", path)?;
+        match &self.kind {
+            PlanChangeKind::Added(node) => {
+                write!(f, "\"change\":\"added\",\"node\":")?;
+                node.write_json(f)?;
+            }
+            PlanChangeKind::Removed(node) => {
+                write!(f, "\"change\":\"removed\",\"node\":")?;
+                node.write_json(f)?;
+            }
+            PlanChangeKind::Changed {
+                operator,
+                before,
+                after,
+                removed_exprs,
+                added_exprs,
+            } => {
+                write!(
+                    f,
+                    "\"change\":\"changed\",\"operator\":{},\"before\":{},\"after\":{}",
+                    json_string(operator),
+                    json_string(before),
+                    json_string(after)
+                )?;
+                write!(f, ",\"removed_exprs\":")?;
+                write_json_strings(f, removed_exprs)?;
+                write!(f, ",\"added_exprs\":")?;
+                write_json_strings(f, added_exprs)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+fn write_json_strings(f: &mut fmt::Formatter, strings: &[String]) -> fmt::Result {
+    let strings = strings
+        .iter()
+        .map(|s| json_string(s))
+        .collect::<Vec<_>>()
+        .join(",");
+    write!(f, "[{}]", strings)
+}
+
+/// Formats the changes for people, with the added nodes preceded by `+`, the removed
+/// nodes by `-` and the changed nodes by `~`, each followed by its path.
+impl fmt::Display for PlanDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            let path = change
+                .path
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            match &change.kind {
+                PlanChangeKind::Added(node) => {
+                    writeln!(f, "+ [{}]", path)?;
+                    node.write_indented(f, "    + ", 0)?;
+                }
+                PlanChangeKind::Removed(node) => {
+                    writeln!(f, "- [{}]", path)?;
+                    node.write_indented(f, "    - ", 0)?;
+                }
+                PlanChangeKind::Changed {
+                    operator,
+                    before,
+                    after,
+                    removed_exprs,
+                    added_exprs,
+                } => {
+                    writeln!(f, "~ [{}] {}", path, operator)?;
+                    if removed_exprs.is_empty() && added_exprs.is_empty() {
+                        // the difference is not in the expressions
+                        writeln!(f, "    - {}", before)?;
+                        writeln!(f, "    + {}", after)?;
+                    }
+                    for expr in removed_exprs {
+                        writeln!(f, "    - {}", expr)?;
+                    }
+                    for expr in added_exprs {
+                        writeln!(f, "    + {}", expr)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::datatypes::{DataType, Field, Schema};
+    use crate::error::Result;
+    use crate::logical_plan::{col, lit, LogicalPlanBuilder};
+    use crate::physical_plan::empty::EmptyExec;
+    use crate::physical_plan::limit::GlobalLimitExec;
+
+    fn scan() -> Result<LogicalPlanBuilder> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        LogicalPlanBuilder::scan_empty(Some("t"), &schema, None)
+    }
+
+    #[test]
+    fn same_plans() -> Result<()> {
+        let plan = scan()?.filter(col("a").gt(lit(1)))?.build()?;
+        let diff = diff_logical_plans(&plan, &plan.clone());
+        assert!(diff.is_empty());
+        assert_eq!("", diff.to_string());
+        assert_eq!("[]", diff.json().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn changed_expressions() -> Result<()> {
+        let before = scan()?
+            .filter(col("a").gt(lit(1)))?
+            .project(vec![col("a"), col("b")])?
+            .build()?;
+        let after = scan()?
+            .filter(col("a").gt(lit(1)))?
+            .project(vec![col("a")])?
+            .build()?;
+        let diff = diff_logical_plans(&before, &after);
+        assert_eq!(
+            vec![PlanChange {
+                path: vec![],
+                kind: PlanChangeKind::Changed {
+                    operator: "Projection".to_owned(),
+                    before: "Projection: #t.a, #t.b".to_owned(),
+                    after: "Projection: #t.a".to_owned(),
+                    removed_exprs: vec!["#t.b".to_owned()],
+                    added_exprs: vec![],
+                },
+            }],
+            diff.changes
+        );
+        assert_eq!("~ [] Projection\n    - #t.b\n", diff.to_string());
+        assert_eq!(
+            "[{\"path\":[],\"change\":\"changed\",\"operator\":\"Projection\",\
+             \"before\":\"Projection: #t.a, #t.b\",\"after\":\"Projection: #t.a\",\
+             \"removed_exprs\":[\"#t.b\"],\"added_exprs\":[]}]",
+            diff.json().to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn added_and_removed_nodes() -> Result<()> {
+        let before = scan()?.filter(col("a").gt(lit(1)))?.limit(10)?.build()?;
+        let after = scan()?.limit(10)?.build()?;
+        let diff = diff_logical_plans(&before, &after);
+        let expected = "- [0]\n    \
+                        - Filter: #t.a Gt Int32(1)\n    \
+                        -   TableScan: t projection=None\n\
+                        + [0]\n    \
+                        + TableScan: t projection=None\n";
+        assert_eq!(expected, diff.to_string());
+        assert_eq!(2, diff.changes.len());
+        Ok(())
+    }
+
+    #[test]
+    fn physical_plans() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let empty: Arc<dyn ExecutionPlan> = Arc::new(EmptyExec::new(false, schema));
+        let before: Arc<dyn ExecutionPlan> =
+            Arc::new(GlobalLimitExec::new(empty.clone(), 10));
+        let after: Arc<dyn ExecutionPlan> = Arc::new(GlobalLimitExec::new(empty, 20));
+        let diff = diff_physical_plans(&before, &after);
+        assert_eq!(
+            "~ [] GlobalLimitExec\n    \
+             - GlobalLimitExec: limit=10\n    \
+             + GlobalLimitExec: limit=20\n",
+            diff.to_string()
+        );
+        assert!(diff_physical_plans(&before, &before).is_empty());
+    }
+}
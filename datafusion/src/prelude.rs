@@ -29,10 +29,12 @@ pub use crate::dataframe::DataFrame;
 pub use crate::execution::context::{ExecutionConfig, ExecutionContext};
 pub use crate::logical_plan::{
     array, ascii, avg, bit_length, btrim, character_length, chr, col, concat, concat_ws,
-    count, create_udf, date_part, date_trunc, in_list, initcap, left, length, lit, lower,
-    lpad, ltrim, max, md5, min, now, octet_length, random, regexp_replace, repeat,
-    replace, reverse, right, rpad, rtrim, sha224, sha256, sha384, sha512, split_part,
-    starts_with, strpos, substr, sum, to_hex, translate, trim, upper, Column, JoinType,
-    Partitioning,
+    count, create_udf, date_bin, date_part, date_trunc, in_list, initcap, left, length,
+    lit, lower, lpad, ltrim, max, md5, min, now, octet_length, random, regexp_replace,
+    repeat, replace, reverse, right, rpad, rtrim, sha224, sha256, sha384, sha512,
+    split_part, starts_with, strpos, substr, sum, to_char, to_hex, translate, trim,
+    upper, Column, JoinType, Partitioning,
 };
 pub use crate::physical_plan::csv::CsvReadOptions;
+pub use crate::physical_plan::ipc::ArrowReadOptions;
+pub use crate::physical_plan::parquet_writer::ParquetWriteOptions;
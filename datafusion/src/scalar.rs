@@ -435,6 +435,21 @@ macro_rules! eq_array_primitive {
     }};
 }
 
+// floats are compared with `OrderedFloat`, consistent with `PartialEq`, so that
+// NaN values are equal to each other
+macro_rules! eq_array_float {
+    ($array:expr, $index:expr, $ARRAYTYPE:ident, $VALUE:expr) => {{
+        let array = $array.as_any().downcast_ref::<$ARRAYTYPE>().unwrap();
+        let is_valid = array.is_valid($index);
+        match $VALUE {
+            Some(val) => {
+                is_valid && OrderedFloat(array.value($index)) == OrderedFloat(*val)
+            }
+            None => !is_valid,
+        }
+    }};
+}
+
 impl ScalarValue {
     /// Getter for the `DataType` of the value
     pub fn get_datatype(&self) -> DataType {
@@ -518,6 +533,8 @@ impl ScalarValue {
                 | ScalarValue::Date64(None)
                 | ScalarValue::Utf8(None)
                 | ScalarValue::LargeUtf8(None)
+                | ScalarValue::Binary(None)
+                | ScalarValue::LargeBinary(None)
                 | ScalarValue::List(None, _)
                 | ScalarValue::TimestampMillisecond(None)
                 | ScalarValue::TimestampMicrosecond(None)
@@ -929,6 +946,10 @@ impl ScalarValue {
             DataType::Int8 => typed_cast!(array, index, Int8Array, Int8),
             DataType::Utf8 => typed_cast!(array, index, StringArray, Utf8),
             DataType::LargeUtf8 => typed_cast!(array, index, LargeStringArray, LargeUtf8),
+            DataType::Binary => typed_cast!(array, index, BinaryArray, Binary),
+            DataType::LargeBinary => {
+                typed_cast!(array, index, LargeBinaryArray, LargeBinary)
+            }
             DataType::List(nested_type) => {
                 let list_array =
                     array.as_any().downcast_ref::<ListArray>().ok_or_else(|| {
@@ -1038,10 +1059,10 @@ impl ScalarValue {
                 eq_array_primitive!(array, index, BooleanArray, val)
             }
             ScalarValue::Float32(val) => {
-                eq_array_primitive!(array, index, Float32Array, val)
+                eq_array_float!(array, index, Float32Array, val)
             }
             ScalarValue::Float64(val) => {
-                eq_array_primitive!(array, index, Float64Array, val)
+                eq_array_float!(array, index, Float64Array, val)
             }
             ScalarValue::Int8(val) => eq_array_primitive!(array, index, Int8Array, val),
             ScalarValue::Int16(val) => eq_array_primitive!(array, index, Int16Array, val),
@@ -1267,6 +1288,19 @@ impl From<Option<&str>> for ScalarValue {
     }
 }
 
+impl From<&[u8]> for ScalarValue {
+    fn from(value: &[u8]) -> Self {
+        Some(value).into()
+    }
+}
+
+impl From<Option<&[u8]>> for ScalarValue {
+    fn from(value: Option<&[u8]>) -> Self {
+        let value = value.map(|b| b.to_vec());
+        ScalarValue::Binary(value)
+    }
+}
+
 impl FromStr for ScalarValue {
     type Err = Infallible;
 
@@ -1362,6 +1396,8 @@ impl TryFrom<&DataType> for ScalarValue {
             DataType::UInt64 => ScalarValue::UInt64(None),
             DataType::Utf8 => ScalarValue::Utf8(None),
             DataType::LargeUtf8 => ScalarValue::LargeUtf8(None),
+            DataType::Binary => ScalarValue::Binary(None),
+            DataType::LargeBinary => ScalarValue::LargeBinary(None),
             DataType::Date32 => ScalarValue::Date32(None),
             DataType::Date64 => ScalarValue::Date64(None),
             DataType::Timestamp(TimeUnit::Second, _) => {
@@ -1768,6 +1804,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scalar_try_from_array_binary() {
+        let array: ArrayRef = Arc::new(BinaryArray::from(vec![Some(&b"ab"[..]), None]));
+        assert_eq!(
+            ScalarValue::Binary(Some(b"ab".to_vec())),
+            ScalarValue::try_from_array(&array, 0).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::Binary(None),
+            ScalarValue::try_from_array(&array, 1).unwrap()
+        );
+        assert!(ScalarValue::try_from_array(&array, 1).unwrap().is_null());
+
+        let array: ArrayRef =
+            Arc::new(LargeBinaryArray::from(vec![Some(&b"ab"[..]), None]));
+        assert_eq!(
+            ScalarValue::LargeBinary(Some(b"ab".to_vec())),
+            ScalarValue::try_from_array(&array, 0).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::LargeBinary(None),
+            ScalarValue::try_from_array(&array, 1).unwrap()
+        );
+    }
+
     #[test]
     fn scalar_try_from_dict_datatype() {
         let data_type =
@@ -0,0 +1,336 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! The `arrow_cast` and `arrow_try_cast` functions, which cast an expression to an
+//! Arrow data type given by its name, e.g. `arrow_cast(a, 'Timestamp(Millisecond, None)')`.
+//! Type names are written the way [DataType] is debug formatted.
+
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::Expr;
+use crate::scalar::ScalarValue;
+use arrow::datatypes::{DataType, IntervalUnit, TimeUnit};
+
+/// Name of the function that casts to a type given by its name
+pub(crate) const ARROW_CAST: &str = "arrow_cast";
+/// Name of the function that casts to a type given by its name, returning NULL for
+/// the values that cannot be cast
+pub(crate) const ARROW_TRY_CAST: &str = "arrow_try_cast";
+
+/// Creates the cast expression for a call of [ARROW_CAST] or [ARROW_TRY_CAST]
+pub(crate) fn create_arrow_cast(name: &str, mut args: Vec<Expr>) -> Result<Expr> {
+    if args.len() != 2 {
+        return Err(DataFusionError::Plan(format!(
+            "{} needs 2 arguments, {} provided",
+            name,
+            args.len()
+        )));
+    }
+    let data_type = match args.pop() {
+        Some(Expr::Literal(ScalarValue::Utf8(Some(type_name)))) => {
+            parse_data_type(&type_name)?
+        }
+        arg => {
+            return Err(DataFusionError::Plan(format!(
+                "The second argument of {} must be a string literal with a type name, found {:?}",
+                name, arg
+            )))
+        }
+    };
+    let expr = Box::new(args.pop().unwrap());
+    Ok(if name == ARROW_TRY_CAST {
+        Expr::TryCast { expr, data_type }
+    } else {
+        Expr::Cast { expr, data_type }
+    })
+}
+
+/// Parses the name of a [DataType], as it is debug formatted, e.g. `Int64`,
+/// `Decimal(10, 2)` or `Timestamp(Nanosecond, Some("UTC"))`
+pub fn parse_data_type(name: &str) -> Result<DataType> {
+    let mut parser = TypeParser {
+        name,
+        tokens: tokenize(name)?.into_iter().peekable(),
+    };
+    let data_type = parser.data_type()?;
+    match parser.tokens.next() {
+        None => Ok(data_type),
+        Some(token) => Err(parser.error(&format!("unexpected {:?}", token))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Integer(i64),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(name: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => {
+                            return Err(DataFusionError::Plan(format!(
+                                "Unterminated string in type name '{}'",
+                                name
+                            )))
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_whitespace() => {}
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut value = c.to_string();
+                while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    value.push(*c);
+                    chars.next();
+                }
+                tokens.push(Token::Integer(value.parse().map_err(|_| {
+                    DataFusionError::Plan(format!(
+                        "Invalid number '{}' in type name '{}'",
+                        value, name
+                    ))
+                })?));
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut value = c.to_string();
+                while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    value.push(*c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(value));
+            }
+            c => {
+                return Err(DataFusionError::Plan(format!(
+                    "Unexpected character '{}' in type name '{}'",
+                    c, name
+                )))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct TypeParser<'a> {
+    name: &'a str,
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl<'a> TypeParser<'a> {
+    fn data_type(&mut self) -> Result<DataType> {
+        let name = self.ident()?;
+        Ok(match name.as_str() {
+            "Null" => DataType::Null,
+            "Boolean" => DataType::Boolean,
+            "Int8" => DataType::Int8,
+            "Int16" => DataType::Int16,
+            "Int32" => DataType::Int32,
+            "Int64" => DataType::Int64,
+            "UInt8" => DataType::UInt8,
+            "UInt16" => DataType::UInt16,
+            "UInt32" => DataType::UInt32,
+            "UInt64" => DataType::UInt64,
+            "Float16" => DataType::Float16,
+            "Float32" => DataType::Float32,
+            "Float64" => DataType::Float64,
+            "Utf8" => DataType::Utf8,
+            "LargeUtf8" => DataType::LargeUtf8,
+            "Binary" => DataType::Binary,
+            "LargeBinary" => DataType::LargeBinary,
+            "Date32" => DataType::Date32,
+            "Date64" => DataType::Date64,
+            "Timestamp" => {
+                self.expect(Token::LParen)?;
+                let unit = self.time_unit()?;
+                self.expect(Token::Comma)?;
+                let timezone = match self.ident()?.as_str() {
+                    "None" => None,
+                    "Some" => {
+                        self.expect(Token::LParen)?;
+                        let timezone = self.string()?;
+                        self.expect(Token::RParen)?;
+                        Some(timezone)
+                    }
+                    other => {
+                        return Err(
+                            self.error(&format!("expected a timezone, found {}", other))
+                        )
+                    }
+                };
+                self.expect(Token::RParen)?;
+                DataType::Timestamp(unit, timezone)
+            }
+            "Time32" => DataType::Time32(self.time_unit_arg()?),
+            "Time64" => DataType::Time64(self.time_unit_arg()?),
+            "Duration" => DataType::Duration(self.time_unit_arg()?),
+            "Interval" => {
+                self.expect(Token::LParen)?;
+                let unit = match self.ident()?.as_str() {
+                    "YearMonth" => IntervalUnit::YearMonth,
+                    "DayTime" => IntervalUnit::DayTime,
+                    other => {
+                        return Err(self.error(&format!(
+                            "expected an interval unit, found {}",
+                            other
+                        )))
+                    }
+                };
+                self.expect(Token::RParen)?;
+                DataType::Interval(unit)
+            }
+            "FixedSizeBinary" => {
+                self.expect(Token::LParen)?;
+                let size = self.integer()?;
+                self.expect(Token::RParen)?;
+                DataType::FixedSizeBinary(size as i32)
+            }
+            "Decimal" => {
+                self.expect(Token::LParen)?;
+                let precision = self.integer()?;
+                self.expect(Token::Comma)?;
+                let scale = self.integer()?;
+                self.expect(Token::RParen)?;
+                DataType::Decimal(precision as usize, scale as usize)
+            }
+            "Dictionary" => {
+                self.expect(Token::LParen)?;
+                let key_type = self.data_type()?;
+                self.expect(Token::Comma)?;
+                let value_type = self.data_type()?;
+                self.expect(Token::RParen)?;
+                DataType::Dictionary(Box::new(key_type), Box::new(value_type))
+            }
+            other => return Err(self.error(&format!("unknown type {}", other))),
+        })
+    }
+
+    fn time_unit_arg(&mut self) -> Result<TimeUnit> {
+        self.expect(Token::LParen)?;
+        let unit = self.time_unit()?;
+        self.expect(Token::RParen)?;
+        Ok(unit)
+    }
+
+    fn time_unit(&mut self) -> Result<TimeUnit> {
+        match self.ident()?.as_str() {
+            "Second" => Ok(TimeUnit::Second),
+            "Millisecond" => Ok(TimeUnit::Millisecond),
+            "Microsecond" => Ok(TimeUnit::Microsecond),
+            "Nanosecond" => Ok(TimeUnit::Nanosecond),
+            other => Err(self.error(&format!("expected a time unit, found {}", other))),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.tokens.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            token => Err(self.error(&format!("expected a name, found {:?}", token))),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64> {
+        match self.tokens.next() {
+            Some(Token::Integer(value)) if value >= 0 => Ok(value),
+            token => Err(self.error(&format!("expected a number, found {:?}", token))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.tokens.next() {
+            Some(Token::Str(value)) => Ok(value),
+            token => Err(self.error(&format!("expected a string, found {:?}", token))),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.tokens.next() {
+            Some(token) if token == expected => Ok(()),
+            token => {
+                Err(self.error(&format!("expected {:?}, found {:?}", expected, token)))
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> DataFusionError {
+        DataFusionError::Plan(format!("Invalid type name '{}': {}", self.name, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_data_types() -> Result<()> {
+        let data_types = vec![
+            DataType::Null,
+            DataType::Boolean,
+            DataType::Int8,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::Utf8,
+            DataType::LargeBinary,
+            DataType::Date32,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            DataType::Timestamp(TimeUnit::Second, Some("+08:00".to_string())),
+            DataType::Time32(TimeUnit::Millisecond),
+            DataType::Time64(TimeUnit::Microsecond),
+            DataType::Duration(TimeUnit::Second),
+            DataType::Interval(IntervalUnit::DayTime),
+            DataType::FixedSizeBinary(16),
+            DataType::Decimal(10, 2),
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+        ];
+        for data_type in data_types {
+            // type names are written the way data types are debug formatted
+            let name = format!("{:?}", data_type);
+            assert_eq!(parse_data_type(&name)?, data_type, "{}", name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_data_types() {
+        for name in &[
+            "",
+            "Int",
+            "Int64(",
+            "Int64 Int64",
+            "Timestamp(Nanosecond)",
+            "Timestamp(Nanosecond, Some(UTC))",
+            "Decimal(10, -2)",
+            "Utf8;",
+        ] {
+            let e = parse_data_type(name).unwrap_err().to_string();
+            assert!(e.contains("type name"), "{}: {}", name, e);
+        }
+    }
+}
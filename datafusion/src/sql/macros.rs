@@ -0,0 +1,158 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! SQL macros, the functions of `CREATE FUNCTION f(x) AS (x * 2 + 1)` statements. A
+//! call of a macro is replaced by its body while planning, with the arguments of the
+//! call substituted for the parameters.
+
+use std::collections::HashMap;
+
+use crate::error::{DataFusionError, Result};
+use crate::logical_plan::{Column, Expr, ExprRewriter};
+use crate::sql::utils::find_column_exprs;
+
+/// A function defined in SQL by an expression of its parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlMacro {
+    name: String,
+    params: Vec<String>,
+    body: Expr,
+}
+
+impl SqlMacro {
+    /// Creates a macro, checking that `body` only references columns that are
+    /// parameters of the macro
+    pub fn try_new(
+        name: impl Into<String>,
+        params: Vec<String>,
+        body: Expr,
+    ) -> Result<Self> {
+        let name = name.into();
+        for (i, param) in params.iter().enumerate() {
+            if params[..i].contains(param) {
+                return Err(DataFusionError::Plan(format!(
+                    "Parameter {} of function {} specified more than once",
+                    param, name
+                )));
+            }
+        }
+        for expr in find_column_exprs(&[body.clone()]) {
+            match expr {
+                Expr::Column(Column {
+                    relation: None,
+                    name: column,
+                }) if params.contains(&column) => {}
+                Expr::Column(column) => {
+                    return Err(DataFusionError::Plan(format!(
+                        "Function {} references {}, which is not one of its parameters",
+                        name, column
+                    )))
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(Self { name, params, body })
+    }
+
+    /// The name of the macro
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The names of the parameters of the macro
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// The expression of the parameters the macro is replaced with
+    pub fn body(&self) -> &Expr {
+        &self.body
+    }
+
+    /// Returns the body of the macro with `args` substituted for its parameters
+    pub fn expand(&self, args: Vec<Expr>) -> Result<Expr> {
+        if args.len() != self.params.len() {
+            return Err(DataFusionError::Plan(format!(
+                "Function {} expects {} arguments, got {}",
+                self.name,
+                self.params.len(),
+                args.len()
+            )));
+        }
+        let mut rewriter = ParamReplacer {
+            args: self.params.iter().cloned().zip(args).collect(),
+        };
+        self.body.clone().rewrite(&mut rewriter)
+    }
+}
+
+/// Replaces the parameters of a macro by the arguments of its call
+struct ParamReplacer {
+    args: HashMap<String, Expr>,
+}
+
+impl ExprRewriter for ParamReplacer {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        match expr {
+            Expr::Column(Column {
+                relation: None,
+                ref name,
+            }) => Ok(self.args.get(name).cloned().unwrap_or(expr)),
+            expr => Ok(expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_plan::{col, lit};
+
+    #[test]
+    fn expand() -> Result<()> {
+        let double = SqlMacro::try_new(
+            "double_plus",
+            vec!["x".to_owned(), "y".to_owned()],
+            col("x") * lit(2) + col("y"),
+        )?;
+        let expr = double.expand(vec![col("a") + lit(1), lit(3)])?;
+        assert_eq!(expr, (col("a") + lit(1)) * lit(2) + lit(3));
+
+        let err = double.expand(vec![col("a")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Function double_plus expects 2 arguments, got 1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_column() {
+        let err = SqlMacro::try_new("f", vec!["x".to_owned()], col("x") + col("z"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Function f references #z, which is not one of its parameters"
+        );
+        let err = SqlMacro::try_new("f", vec!["x".to_owned(), "x".to_owned()], col("x"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Parameter x of function f specified more than once"
+        );
+    }
+}
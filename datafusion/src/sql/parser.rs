@@ -20,13 +20,19 @@
 //! Declares a SQL parser based on sqlparser that handles custom formats that we need.
 
 use sqlparser::{
-    ast::{ColumnDef, ColumnOptionDef, Statement as SQLStatement, TableConstraint},
+    ast::{
+        ColumnDef, ColumnOptionDef, Expr as SQLExpr, Ident, ObjectName, Query,
+        Statement as SQLStatement, TableConstraint, Value,
+    },
     dialect::{keywords::Keyword, Dialect, GenericDialect},
     parser::{Parser, ParserError},
     tokenizer::{Token, Tokenizer},
 };
+use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::logical_plan::ExplainFormat;
+
 // Use `Parser::expected` instead, if possible
 macro_rules! parser_err {
     ($MSG:expr) => {
@@ -43,6 +49,8 @@ pub enum FileType {
     Parquet,
     /// Comma separated values
     CSV,
+    /// Delta Lake table, whose Parquet files are listed by its transaction log
+    Delta,
 }
 
 impl FromStr for FileType {
@@ -53,8 +61,9 @@ impl FromStr for FileType {
             "PARQUET" => Ok(Self::Parquet),
             "NDJSON" => Ok(Self::NdJson),
             "CSV" => Ok(Self::CSV),
+            "DELTA" => Ok(Self::Delta),
             other => Err(ParserError::ParserError(format!(
-                "expect one of PARQUET, NDJSON, or CSV, found: {}",
+                "expect one of PARQUET, NDJSON, CSV, or DELTA, found: {}",
                 other
             ))),
         }
@@ -74,6 +83,63 @@ pub struct CreateExternalTable {
     pub has_header: bool,
     /// Path to file
     pub location: String,
+    /// Columns the files are partitioned by, from `PARTITIONED BY (...)`
+    pub table_partition_cols: Vec<String>,
+    /// Options of the file format, from `OPTIONS (key 'value', ...)`
+    pub options: HashMap<String, String>,
+}
+
+/// DataFusion extension DDL for `CREATE [OR REPLACE] FUNCTION f(x) AS (x * 2 + 1)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateFunction {
+    /// Function name
+    pub name: Ident,
+    /// Parameter names
+    pub params: Vec<Ident>,
+    /// Expression of the parameters the function is replaced with
+    pub body: SQLExpr,
+    /// Whether an existing function of the same name is replaced
+    pub or_replace: bool,
+}
+
+/// DataFusion extension for `EXPLAIN (FORMAT JSON, VERBOSE) <statement>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explain {
+    /// Whether the intermediate plans are displayed as well
+    pub verbose: bool,
+    /// The format the plans are displayed in
+    pub format: ExplainFormat,
+    /// The statement that is explained
+    pub statement: Box<SQLStatement>,
+}
+
+/// DataFusion extension for `DESCRIBE <table>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeTable {
+    /// Table name
+    pub table_name: ObjectName,
+}
+
+/// The data that `COPY ... TO` writes
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopySource {
+    /// All rows of a table, from `COPY <table> TO ...`
+    Table(ObjectName),
+    /// The result of a query, from `COPY (<query>) TO ...`
+    Query(Box<Query>),
+}
+
+/// DataFusion extension for `COPY <source> TO 'path' [STORED AS ...] [OPTIONS (...)]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyTo {
+    /// The written data
+    pub source: CopySource,
+    /// The directory that the files are written to
+    pub path: String,
+    /// File type of the written files, Parquet unless given
+    pub file_type: FileType,
+    /// Options of the file format, from `OPTIONS (key 'value', ...)`
+    pub options: HashMap<String, String>,
 }
 
 /// DataFusion Statement representations.
@@ -85,6 +151,774 @@ pub enum Statement {
     Statement(SQLStatement),
     /// Extension: `CREATE EXTERNAL TABLE`
     CreateExternalTable(CreateExternalTable),
+    /// Extension: `DESCRIBE <table>`
+    DescribeTable(DescribeTable),
+    /// Extension: `CREATE FUNCTION`
+    CreateFunction(CreateFunction),
+    /// Extension: `EXPLAIN (FORMAT ...)`
+    Explain(Explain),
+    /// Extension: `COPY ... TO`
+    CopyTo(CopyTo),
+}
+
+/// Prefix of the names of the functions that the clauses and operators which sqlparser
+/// does not support are rewritten into. The names that start with it are reserved:
+/// `CREATE FUNCTION` rejects them, and a registered function with such a name may be
+/// shadowed by a rewritten clause.
+pub const RESERVED_FUNCTION_PREFIX: &str = "__";
+
+/// Rewrites the clauses and operators that sqlparser does not support into calls of
+/// functions whose names start with [`RESERVED_FUNCTION_PREFIX`], in a single walk over
+/// the tokens. A rewrite can move the tokens around the one it starts at into the call,
+/// and the walk then resumes at the start of the call, so that the clauses within it
+/// are rewritten as well.
+fn rewrite_tokens(
+    mut tokens: Vec<Token>,
+    dialect: &dyn Dialect,
+) -> Result<Vec<Token>, ParserError> {
+    let mut i = 0;
+    while i < tokens.len() {
+        let resume = match &tokens[i] {
+            Token::Mul => rewrite_wildcard_except(&mut tokens, i),
+            Token::Word(w) if w.quote_style.is_none() => {
+                match w.value.to_uppercase().as_str() {
+                    "IGNORE" | "RESPECT" => {
+                        rewrite_null_treatment(&mut tokens, i, dialect)?
+                    }
+                    "OVER" => rewrite_window_exclusion(&mut tokens, i),
+                    "LIKE" | "ILIKE" | "SIMILAR" => {
+                        rewrite_pattern_matching(&mut tokens, i)
+                    }
+                    "IS" => rewrite_distinct_from(&mut tokens, i),
+                    "QUALIFY" => rewrite_qualify(&mut tokens, i),
+                    "FILTER" => rewrite_aggregate_filter(&mut tokens, i),
+                    "ALL" => rewrite_by_all(&mut tokens, i),
+                    name if ORDERED_AGGREGATES.contains(&name) => {
+                        rewrite_aggregate_order_by(&mut tokens, i)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        i = resume.unwrap_or(i + 1);
+    }
+    Ok(tokens)
+}
+
+/// Rewrites the null treatment clause of window functions at `i`, which sqlparser does
+/// not support, into the trailing boolean argument of the function. For example
+/// `LAG(c1) IGNORE NULLS OVER (...)` becomes `LAG(c1, 1, NULL, true) OVER (...)` and
+/// `RESPECT NULLS`, the default, is dropped.
+fn rewrite_null_treatment(
+    tokens: &mut Vec<Token>,
+    i: usize,
+    dialect: &dyn Dialect,
+) -> Result<Option<usize>, ParserError> {
+    let nulls = next_token(tokens, i + 1);
+    let over = nulls.and_then(|nulls| next_token(tokens, nulls + 1));
+    let over = match (nulls, over) {
+        (Some(nulls), Some(over))
+            if is_keyword(&tokens[nulls], "NULLS")
+                && is_keyword(&tokens[over], "OVER") =>
+        {
+            over
+        }
+        _ => return Ok(None),
+    };
+    let ignore_nulls = is_keyword(&tokens[i], "IGNORE");
+    tokens.drain(i..over);
+    // the walk resumes at the OVER keyword
+    if ignore_nulls {
+        Ok(Some(i + add_ignore_nulls_argument(tokens, i, dialect)?))
+    } else {
+        Ok(Some(i))
+    }
+}
+
+/// Appends the `IGNORE NULLS` argument to the function call that ends before `end`,
+/// filling in the defaults of any omitted optional arguments before it, and returns
+/// the number of added tokens
+fn add_ignore_nulls_argument(
+    tokens: &mut Vec<Token>,
+    end: usize,
+    dialect: &dyn Dialect,
+) -> Result<usize, ParserError> {
+    let rparen = match previous_token(tokens, end) {
+        Some(rparen) if tokens[rparen] == Token::RParen => rparen,
+        _ => return parser_err!("Expected a function call before IGNORE NULLS"),
+    };
+
+    // walk back over the arguments to the opening parenthesis
+    let mut num_commas = 0;
+    let mut arg_end = rparen;
+    let lparen = loop {
+        let arg_start = left_operand_start(tokens, arg_end, &|t| *t == Token::Comma);
+        match arg_start.checked_sub(1).map(|i| (i, &tokens[i])) {
+            Some((comma, Token::Comma)) => {
+                num_commas += 1;
+                arg_end = comma;
+            }
+            Some((lparen, Token::LParen)) => break lparen,
+            _ => return parser_err!("Expected a function call before IGNORE NULLS"),
+        }
+    };
+    let num_args = if tokens[lparen + 1..rparen].iter().all(is_whitespace) {
+        0
+    } else {
+        num_commas + 1
+    };
+    let name = match previous_token(tokens, lparen).map(|name| &tokens[name]) {
+        Some(Token::Word(w)) => w.value.to_uppercase(),
+        _ => return parser_err!("Expected a function call before IGNORE NULLS"),
+    };
+
+    // number of arguments before the IGNORE NULLS argument and the defaults of the
+    // trailing optional ones
+    let (num_params, defaults): (usize, &[&str]) = match name.as_str() {
+        "LAG" | "LEAD" => (3, &["1", "NULL"]),
+        "FIRST_VALUE" | "LAST_VALUE" => (1, &[]),
+        "NTH_VALUE" => (2, &[]),
+        _ => {
+            return parser_err!(format!(
+                "IGNORE NULLS is not supported for function {}",
+                name
+            ))
+        }
+    };
+    let num_required = num_params - defaults.len();
+    if num_args < num_required || num_args > num_params {
+        return parser_err!(format!(
+            "Invalid number of arguments for {} with IGNORE NULLS: {}",
+            name, num_args
+        ));
+    }
+
+    let mut args = defaults[num_args - num_required..]
+        .iter()
+        .map(|default| format!(", {}", default))
+        .collect::<String>();
+    args.push_str(", true");
+    let args = Tokenizer::new(dialect, &args).tokenize()?;
+    let num_added = args.len();
+    tokens.splice(rparen..rparen, args);
+    Ok(num_added)
+}
+
+/// Name of the function that `* EXCLUDE (...)` is rewritten into, so that it can be
+/// parsed by sqlparser and expanded by the SQL planner
+pub(crate) const WILDCARD_EXCEPT: &str = "__wildcard_except";
+
+/// Rewrites the `* EXCLUDE (c1, c2)` and `* EXCEPT (c1, c2)` select items at `i`, which
+/// sqlparser does not support, into a call of the [`WILDCARD_EXCEPT`] function with
+/// the excluded columns as arguments.
+fn rewrite_wildcard_except(tokens: &mut Vec<Token>, i: usize) -> Option<usize> {
+    // only a wildcard if it starts a select item
+    let is_wildcard = match previous_token(tokens, i).map(|previous| &tokens[previous]) {
+        Some(Token::Comma) => true,
+        Some(t) => ["SELECT", "DISTINCT", "ALL"]
+            .iter()
+            .any(|keyword| is_keyword(t, keyword)),
+        None => false,
+    };
+    let keyword = next_token(tokens, i + 1)?;
+    let lparen = next_token(tokens, keyword + 1)?;
+    let first_arg = next_token(tokens, lparen + 1)?;
+    if is_wildcard
+        && (is_keyword(&tokens[keyword], "EXCLUDE")
+            || is_keyword(&tokens[keyword], "EXCEPT"))
+        && tokens[lparen] == Token::LParen
+        // `SELECT * EXCEPT (SELECT ...)` is a set operation
+        && !is_keyword(&tokens[first_arg], "SELECT")
+    {
+        tokens.splice(i..lparen, vec![Token::make_word(WILDCARD_EXCEPT, None)]);
+        Some(i + 1)
+    } else {
+        None
+    }
+}
+
+/// Names of the functions that `IS DISTINCT FROM` and `IS NOT DISTINCT FROM` are
+/// rewritten into, so that they can be parsed by sqlparser and planned as operators
+pub(crate) const IS_DISTINCT_FROM: &str = "__is_distinct_from";
+pub(crate) const IS_NOT_DISTINCT_FROM: &str = "__is_not_distinct_from";
+
+/// Keywords that end the operands of `IS [NOT] DISTINCT FROM`, whose precedence is
+/// lower than the comparison and arithmetic operators
+const DISTINCT_FROM_BOUNDARIES: &[&str] = &[
+    "SELECT",
+    "DISTINCT",
+    "ALL",
+    "FROM",
+    "WHERE",
+    "GROUP",
+    "BY",
+    "HAVING",
+    "QUALIFY",
+    "WINDOW",
+    "ORDER",
+    "LIMIT",
+    "OFFSET",
+    "FETCH",
+    "UNION",
+    "EXCEPT",
+    "INTERSECT",
+    "ON",
+    "USING",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+    "AND",
+    "OR",
+    "NOT",
+    "WHEN",
+    "THEN",
+    "ELSE",
+    "AS",
+    "ASC",
+    "DESC",
+    "NULLS",
+    "IS",
+    "BETWEEN",
+    "LIKE",
+    "ILIKE",
+    "IN",
+];
+
+/// Returns whether `token` ends an operand of `IS [NOT] DISTINCT FROM`
+fn is_distinct_from_boundary(token: &Token) -> bool {
+    matches!(token, Token::Comma | Token::SemiColon)
+        || DISTINCT_FROM_BOUNDARIES
+            .iter()
+            .any(|keyword| is_keyword(token, keyword))
+}
+
+/// Rewrites `a IS [NOT] DISTINCT FROM b` at `is`, which sqlparser does not support,
+/// into `__is_[not_]distinct_from(a, b)`. The operands extend to the nearest keyword of
+/// lower precedence, comma or enclosing parenthesis.
+fn rewrite_distinct_from(tokens: &mut Vec<Token>, is: usize) -> Option<usize> {
+    // the positions of the words of the operator
+    let words = (is..tokens.len())
+        .filter(|i| !is_whitespace(&tokens[*i]))
+        .take(4)
+        .collect::<Vec<_>>();
+    let (name, op_end) = match words.as_slice() {
+        [_, distinct, from, ..]
+            if is_keyword(&tokens[*distinct], "DISTINCT")
+                && is_keyword(&tokens[*from], "FROM") =>
+        {
+            (IS_DISTINCT_FROM, from + 1)
+        }
+        [_, not, distinct, from]
+            if is_keyword(&tokens[*not], "NOT")
+                && is_keyword(&tokens[*distinct], "DISTINCT")
+                && is_keyword(&tokens[*from], "FROM") =>
+        {
+            (IS_NOT_DISTINCT_FROM, from + 1)
+        }
+        _ => return None,
+    };
+
+    let left_start = left_operand_start(tokens, is, &is_distinct_from_boundary);
+    // the pattern matching operators have a higher precedence, so those that end the
+    // right operand are rewritten into part of it first
+    let right_end = loop {
+        let right_end = right_operand_end(tokens, op_end, &is_distinct_from_boundary);
+        let op = match next_token(tokens, right_end) {
+            Some(not) if is_keyword(&tokens[not], "NOT") => next_token(tokens, not + 1),
+            op => op,
+        };
+        let rewritten = match op {
+            Some(op)
+                if ["LIKE", "ILIKE", "SIMILAR"]
+                    .iter()
+                    .any(|keyword| is_keyword(&tokens[op], keyword)) =>
+            {
+                rewrite_pattern_matching(tokens, op).is_some()
+            }
+            _ => false,
+        };
+        if !rewritten {
+            break right_end;
+        }
+    };
+
+    // the call replaces the operands without their leading whitespace
+    let call_start = (left_start..is).find(|i| !is_whitespace(&tokens[*i]))?;
+    let right = tokens[op_end..right_end].to_vec();
+    if right.iter().all(is_whitespace) {
+        return None;
+    }
+    let mut call = vec![Token::make_word(name, None), Token::LParen];
+    call.extend(tokens[call_start..is].iter().cloned());
+    call.push(Token::Comma);
+    call.extend(right);
+    call.push(Token::RParen);
+    tokens.splice(call_start..right_end, call);
+    Some(call_start)
+}
+
+/// Returns the start of the operand that ends before the operator at `op_start`,
+/// which is the token after the nearest boundary or unbalanced opening parenthesis
+fn left_operand_start(
+    tokens: &[Token],
+    op_start: usize,
+    is_boundary: &dyn Fn(&Token) -> bool,
+) -> usize {
+    let mut depth = 0;
+    for i in (0..op_start).rev() {
+        let token = &tokens[i];
+        if *token == Token::RParen || is_keyword(token, "END") {
+            depth += 1;
+        } else if *token == Token::LParen || is_keyword(token, "CASE") {
+            if depth == 0 {
+                return i + 1;
+            }
+            depth -= 1;
+        } else if depth == 0 && is_boundary(token) {
+            return i + 1;
+        }
+    }
+    0
+}
+
+/// Returns the end of the operand that starts after the operator at `op_end`, which is
+/// the nearest boundary or unbalanced closing parenthesis
+fn right_operand_end(
+    tokens: &[Token],
+    op_end: usize,
+    is_boundary: &dyn Fn(&Token) -> bool,
+) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(op_end) {
+        if *token == Token::LParen || is_keyword(token, "CASE") {
+            depth += 1;
+        } else if *token == Token::RParen || is_keyword(token, "END") {
+            if depth == 0 {
+                return i;
+            }
+            depth -= 1;
+        } else if depth == 0 && (is_boundary(token) || *token == Token::EOF) {
+            return i;
+        }
+    }
+    tokens.len()
+}
+
+/// Returns the position of the first token from `from` on that is not whitespace
+fn next_token(tokens: &[Token], from: usize) -> Option<usize> {
+    (from..tokens.len()).find(|i| !is_whitespace(&tokens[*i]))
+}
+
+/// Returns the position of the last token before `end` that is not whitespace
+fn previous_token(tokens: &[Token], end: usize) -> Option<usize> {
+    tokens[..end].iter().rposition(|t| !is_whitespace(t))
+}
+
+fn is_whitespace(token: &Token) -> bool {
+    matches!(token, Token::Whitespace(_))
+}
+
+/// Returns whether `token` is the keyword `value`. Quoted words are identifiers, so
+/// `"qualify"` is never mistaken for the `QUALIFY` keyword.
+fn is_keyword(token: &Token, value: &str) -> bool {
+    matches!(token, Token::Word(w)
+        if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(value))
+}
+
+/// Names of the functions that `ILIKE`, `SIMILAR TO` and the pattern matching
+/// operators with an `ESCAPE` clause are rewritten into, so that they can be parsed by
+/// sqlparser and planned as operators
+pub(crate) const LIKE: &str = "__like";
+pub(crate) const NOT_LIKE: &str = "__not_like";
+pub(crate) const ILIKE: &str = "__ilike";
+pub(crate) const NOT_ILIKE: &str = "__not_ilike";
+pub(crate) const SIMILAR_TO: &str = "__similar_to";
+pub(crate) const NOT_SIMILAR_TO: &str = "__not_similar_to";
+
+/// Returns whether `token` ends an operand of the pattern matching operators
+fn is_pattern_matching_boundary(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Comma
+            | Token::SemiColon
+            | Token::Eq
+            | Token::Neq
+            | Token::Lt
+            | Token::Gt
+            | Token::LtEq
+            | Token::GtEq
+    ) || is_keyword(token, "ESCAPE")
+        || DISTINCT_FROM_BOUNDARIES
+            .iter()
+            .any(|keyword| is_keyword(token, keyword))
+}
+
+/// Rewrites `a [NOT] ILIKE b` and `a [NOT] SIMILAR TO b` at `op`, which sqlparser does
+/// not support, into `__[not_]ilike(a, b)` and `__[not_]similar_to(a, b)`. An `ESCAPE c`
+/// clause of these operators or of `[NOT] LIKE` is passed as a third argument, as in
+/// `__[not_]like(a, b, c)`. The operands extend to the nearest comparison operator,
+/// keyword of lower precedence, comma or enclosing parenthesis.
+fn rewrite_pattern_matching(tokens: &mut Vec<Token>, op: usize) -> Option<usize> {
+    let negated = match previous_token(tokens, op) {
+        Some(not) if is_keyword(&tokens[not], "NOT") => Some(not),
+        _ => None,
+    };
+    let op_start = negated.unwrap_or(op);
+    let (name, op_end) = if is_keyword(&tokens[op], "SIMILAR") {
+        match next_token(tokens, op + 1) {
+            Some(to) if is_keyword(&tokens[to], "TO") => (
+                if negated.is_some() {
+                    NOT_SIMILAR_TO
+                } else {
+                    SIMILAR_TO
+                },
+                to + 1,
+            ),
+            _ => return None,
+        }
+    } else if is_keyword(&tokens[op], "ILIKE") {
+        (if negated.is_some() { NOT_ILIKE } else { ILIKE }, op + 1)
+    } else {
+        (if negated.is_some() { NOT_LIKE } else { LIKE }, op + 1)
+    };
+
+    let left_start = left_operand_start(tokens, op_start, &is_pattern_matching_boundary);
+    let right_end = right_operand_end(tokens, op_end, &is_pattern_matching_boundary);
+    let (escape, end) = match next_token(tokens, right_end) {
+        Some(escape) if is_keyword(&tokens[escape], "ESCAPE") => {
+            let end =
+                right_operand_end(tokens, escape + 1, &is_pattern_matching_boundary);
+            (Some(tokens[escape + 1..end].to_vec()), end)
+        }
+        _ => (None, right_end),
+    };
+    // LIKE without an ESCAPE clause is supported by sqlparser
+    if (name == LIKE || name == NOT_LIKE) && escape.is_none() {
+        return None;
+    }
+
+    // the call replaces the operands without their leading whitespace
+    let call_start = (left_start..op_start).find(|i| !is_whitespace(&tokens[*i]))?;
+    let right = tokens[op_end..right_end].to_vec();
+    if right.iter().all(is_whitespace) {
+        return None;
+    }
+    let mut call = vec![Token::make_word(name, None), Token::LParen];
+    call.extend(tokens[call_start..op_start].iter().cloned());
+    call.push(Token::Comma);
+    call.extend(right);
+    if let Some(escape) = escape {
+        call.push(Token::Comma);
+        call.extend(escape);
+    }
+    call.push(Token::RParen);
+    tokens.splice(call_start..end, call);
+    Some(call_start)
+}
+
+/// Name of the function that the `QUALIFY` clause is rewritten into, so that it can
+/// be parsed by sqlparser and planned by the SQL planner
+pub(crate) const QUALIFY: &str = "__qualify";
+
+/// Rewrites the `QUALIFY <predicate>` clause at `qualify`, which sqlparser does not
+/// support, into a trailing `__qualify(<predicate>)` select item of its query. For
+/// example `SELECT a FROM t QUALIFY ROW_NUMBER() OVER () = 1` becomes
+/// `SELECT a, __qualify(ROW_NUMBER() OVER () = 1) FROM t`.
+fn rewrite_qualify(tokens: &mut Vec<Token>, qualify: usize) -> Option<usize> {
+    // the clauses that can follow QUALIFY
+    let ends_predicate = |token: &Token| {
+        *token == Token::SemiColon
+            || [
+                "ORDER",
+                "LIMIT",
+                "OFFSET",
+                "FETCH",
+                "UNION",
+                "EXCEPT",
+                "INTERSECT",
+            ]
+            .iter()
+            .any(|keyword| is_keyword(token, keyword))
+    };
+
+    let from = query_from(tokens, qualify)?;
+    let end = right_operand_end(tokens, qualify + 1, &ends_predicate);
+    let mut item = vec![Token::Comma, Token::make_word(QUALIFY, None), Token::LParen];
+    item.extend(tokens.drain(qualify..end).skip(1));
+    item.push(Token::RParen);
+    tokens.splice(from..from, item);
+    Some(from)
+}
+
+/// Returns the position of the `FROM` keyword of the query that the clause at `clause`
+/// belongs to, which is the first one after the `SELECT` keyword of the query at the
+/// same level of parentheses
+fn query_from(tokens: &[Token], clause: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut from = None;
+    for i in (0..clause).rev() {
+        let token = &tokens[i];
+        if *token == Token::RParen {
+            depth += 1;
+        } else if *token == Token::LParen {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if depth == 0 && is_keyword(token, "SELECT") {
+            break;
+        } else if depth == 0 && is_keyword(token, "FROM") {
+            from = Some(i);
+        }
+    }
+    from
+}
+
+/// Name of the function that the `ORDER BY` clause of the arguments of an aggregate
+/// function is rewritten into, so that it can be parsed by sqlparser and planned by
+/// the SQL planner
+pub(crate) const ORDER_BY: &str = "__order_by";
+
+/// The aggregate functions whose arguments can have an `ORDER BY` clause
+const ORDERED_AGGREGATES: &[&str] =
+    &["ARRAY_AGG", "FIRST_VALUE", "LAST_VALUE", "STRING_AGG"];
+
+/// Rewrites the `ORDER BY` clause of the arguments of the ordered aggregate function
+/// at `name`, which sqlparser does not support, into trailing `__order_by(<expr>,
+/// <asc>, <nulls_first>)` arguments with the defaults of `ORDER BY` filled in. For
+/// example `ARRAY_AGG(a ORDER BY b DESC, c)` becomes
+/// `ARRAY_AGG(a, __order_by(b, false, true), __order_by(c, true, true))`.
+fn rewrite_aggregate_order_by(tokens: &mut Vec<Token>, name: usize) -> Option<usize> {
+    let lparen = match next_token(tokens, name + 1) {
+        Some(lparen) if tokens[lparen] == Token::LParen => lparen,
+        _ => return None,
+    };
+
+    // the positions of the ORDER keyword and of the closing parenthesis
+    let order = right_operand_end(tokens, lparen + 1, &|t| is_keyword(t, "ORDER"));
+    if order == tokens.len() || !is_keyword(&tokens[order], "ORDER") {
+        return None;
+    }
+    let rparen = right_operand_end(tokens, order + 1, &|_| false);
+    if tokens.get(rparen) != Some(&Token::RParen) {
+        return None;
+    }
+    let by = match next_token(&tokens[..rparen], order + 1) {
+        Some(by) if is_keyword(&tokens[by], "BY") => by,
+        _ => return None,
+    };
+
+    // split the sort expressions at the commas outside of parentheses
+    let mut keys = vec![];
+    let mut key_start = by + 1;
+    loop {
+        let key_end = right_operand_end(tokens, key_start, &|t| *t == Token::Comma);
+        keys.push(tokens[key_start..key_end].to_vec());
+        if key_end >= rparen {
+            break;
+        }
+        key_start = key_end + 1;
+    }
+
+    let mut args = vec![];
+    for mut key in keys {
+        // strip the trailing options of the sort expression
+        let mut words = vec![];
+        while let Some(last) = key.iter().rposition(|t| !is_whitespace(t)) {
+            let option = ["ASC", "DESC", "NULLS", "FIRST", "LAST"]
+                .iter()
+                .find(|option| is_keyword(&key[last], option));
+            match option {
+                Some(option) => {
+                    words.push(*option);
+                    key.truncate(last);
+                }
+                None => break,
+            }
+        }
+        let asc = !words.contains(&"DESC");
+        let nulls_first = !words.contains(&"LAST");
+        args.push(Token::Comma);
+        args.push(Token::make_word(ORDER_BY, None));
+        args.push(Token::LParen);
+        args.extend(key);
+        args.push(Token::Comma);
+        args.push(Token::make_word(&asc.to_string(), None));
+        args.push(Token::Comma);
+        args.push(Token::make_word(&nulls_first.to_string(), None));
+        args.push(Token::RParen);
+    }
+    tokens.splice(order..rparen, args);
+    Some(name + 1)
+}
+
+/// Name of the function that the `EXCLUDE` clause of a window frame is rewritten into,
+/// so that it can be parsed by sqlparser and planned by the SQL planner
+pub(crate) const WINDOW_EXCLUDE: &str = "__window_exclude";
+
+/// Rewrites the `EXCLUDE` clause of the window frame after the `OVER` keyword at
+/// `over`, which sqlparser does not support, into a trailing
+/// `__window_exclude('<exclusion>')` argument of the window function. For example
+/// `SUM(a) OVER (ORDER BY b ROWS 1 PRECEDING EXCLUDE CURRENT ROW)` becomes
+/// `SUM(a, __window_exclude('CURRENT ROW')) OVER (ORDER BY b ROWS 1 PRECEDING)` and
+/// `EXCLUDE NO OTHERS`, the default, is dropped.
+fn rewrite_window_exclusion(tokens: &mut Vec<Token>, over: usize) -> Option<usize> {
+    let lparen = match next_token(tokens, over + 1) {
+        Some(lparen) if tokens[lparen] == Token::LParen => lparen,
+        _ => return None,
+    };
+
+    // the positions of the EXCLUDE keyword and of the closing parenthesis
+    let exclude = right_operand_end(tokens, lparen + 1, &|t| is_keyword(t, "EXCLUDE"));
+    if exclude == tokens.len() || !is_keyword(&tokens[exclude], "EXCLUDE") {
+        return None;
+    }
+    let rparen = right_operand_end(tokens, exclude + 1, &|_| false);
+    if tokens.get(rparen) != Some(&Token::RParen) {
+        return None;
+    }
+    let mut words = vec![];
+    for token in &tokens[exclude + 1..rparen] {
+        match token {
+            Token::Word(w) if w.quote_style.is_none() => {
+                words.push(w.value.to_uppercase())
+            }
+            token if is_whitespace(token) => {}
+            _ => break,
+        }
+    }
+    let exclusion = words.join(" ");
+    if !["NO OTHERS", "CURRENT ROW", "GROUP", "TIES"].contains(&exclusion.as_str()) {
+        return None;
+    }
+    // the closing parenthesis of the arguments of the window function
+    let args_end = match previous_token(tokens, over) {
+        Some(args_end) if tokens[args_end] == Token::RParen => args_end,
+        _ => return None,
+    };
+
+    tokens.drain(exclude..rparen);
+    if exclusion == "NO OTHERS" {
+        return Some(over + 1);
+    }
+    let mut arg = vec![];
+    if previous_token(tokens, args_end).map(|previous| &tokens[previous])
+        != Some(&Token::LParen)
+    {
+        arg.push(Token::Comma);
+    }
+    arg.push(Token::make_word(WINDOW_EXCLUDE, None));
+    arg.push(Token::LParen);
+    arg.push(Token::SingleQuotedString(exclusion));
+    arg.push(Token::RParen);
+    let resume = over + arg.len() + 1;
+    tokens.splice(args_end..args_end, arg);
+    Some(resume)
+}
+
+/// Name of the function that the `FILTER` clause of an aggregate function is rewritten
+/// into, so that it can be parsed by sqlparser and planned by the SQL planner
+pub(crate) const AGGREGATE_FILTER: &str = "__filter";
+
+/// Rewrites the `FILTER (WHERE <predicate>)` clause of aggregate functions at `filter`,
+/// which sqlparser does not support, into a trailing `__filter(<predicate>)` argument
+/// of the function. For example `COUNT(a) FILTER (WHERE b > 1)` becomes
+/// `COUNT(a, __filter(b > 1))`.
+fn rewrite_aggregate_filter(tokens: &mut Vec<Token>, filter: usize) -> Option<usize> {
+    // the closing parenthesis of the arguments of the aggregate function
+    let args_end = match previous_token(tokens, filter) {
+        Some(args_end) if tokens[args_end] == Token::RParen => args_end,
+        _ => return None,
+    };
+    let lparen = match next_token(tokens, filter + 1) {
+        Some(lparen) if tokens[lparen] == Token::LParen => lparen,
+        _ => return None,
+    };
+    let where_ = match next_token(tokens, lparen + 1) {
+        Some(where_) if is_keyword(&tokens[where_], "WHERE") => where_,
+        _ => return None,
+    };
+    let rparen = right_operand_end(tokens, where_ + 1, &|_| false);
+    if rparen == tokens.len() {
+        return None;
+    }
+
+    let predicate = tokens[where_ + 1..rparen].to_vec();
+    tokens.drain(filter..=rparen);
+    let mut arg = vec![];
+    if previous_token(tokens, args_end).map(|previous| &tokens[previous])
+        != Some(&Token::LParen)
+    {
+        arg.push(Token::Comma);
+    }
+    arg.push(Token::make_word(AGGREGATE_FILTER, None));
+    arg.push(Token::LParen);
+    arg.extend(predicate);
+    arg.push(Token::RParen);
+    tokens.splice(args_end..args_end, arg);
+    // the walk resumes at the argument, so that the clauses of the predicate are
+    // rewritten as well
+    Some(args_end)
+}
+
+/// Name of the function that the `ALL` of `GROUP BY ALL` and `ORDER BY ALL` is
+/// rewritten into, so that it can be parsed by sqlparser and expanded by the SQL planner
+pub(crate) const BY_ALL: &str = "__by_all";
+
+/// Keywords that can follow the `ALL` of `GROUP BY ALL` and `ORDER BY ALL`, unlike the
+/// expressions that follow the `ALL` quantifier of `GROUP BY ALL <expr>`
+const BY_ALL_FOLLOWERS: &[&str] = &[
+    "HAVING",
+    "QUALIFY",
+    "WINDOW",
+    "ORDER",
+    "LIMIT",
+    "OFFSET",
+    "FETCH",
+    "UNION",
+    "EXCEPT",
+    "INTERSECT",
+    "ASC",
+    "DESC",
+    "NULLS",
+];
+
+/// Rewrites the `ALL` of `GROUP BY ALL` and `ORDER BY ALL` at `all`, which sqlparser
+/// does not support, into `__by_all()`. For example
+/// `SELECT a, SUM(b) FROM t GROUP BY ALL ORDER BY ALL DESC` becomes
+/// `SELECT a, SUM(b) FROM t GROUP BY __by_all() ORDER BY __by_all() DESC`.
+fn rewrite_by_all(tokens: &mut Vec<Token>, all: usize) -> Option<usize> {
+    let by = previous_token(tokens, all)?;
+    let clause = previous_token(tokens, by)?;
+    let follows_by = is_keyword(&tokens[by], "BY")
+        && (is_keyword(&tokens[clause], "GROUP") || is_keyword(&tokens[clause], "ORDER"));
+    if !follows_by {
+        return None;
+    }
+    let ends_clause = match next_token(tokens, all + 1) {
+        Some(next) => {
+            // a trailing comma is rejected by the SQL planner
+            matches!(
+                tokens[next],
+                Token::Comma | Token::RParen | Token::SemiColon
+            ) || BY_ALL_FOLLOWERS
+                .iter()
+                .any(|keyword| is_keyword(&tokens[next], keyword))
+        }
+        None => true,
+    };
+    if !ends_clause {
+        return None;
+    }
+    tokens.splice(
+        all..=all,
+        vec![Token::make_word(BY_ALL, None), Token::LParen, Token::RParen],
+    );
+    Some(all + 3)
 }
 
 /// SQL Parser
@@ -105,7 +939,7 @@ impl<'a> DFParser<'a> {
         dialect: &'a dyn Dialect,
     ) -> Result<Self, ParserError> {
         let mut tokenizer = Tokenizer::new(dialect, sql);
-        let tokens = tokenizer.tokenize()?;
+        let tokens = rewrite_tokens(tokenizer.tokenize()?, dialect)?;
 
         Ok(DFParser {
             parser: Parser::new(tokens, dialect),
@@ -162,6 +996,29 @@ impl<'a> DFParser<'a> {
                         // use custom parsing
                         self.parse_create()
                     }
+                    Keyword::COPY => {
+                        // move one token forward
+                        self.parser.next_token();
+                        self.parse_copy()
+                    }
+                    Keyword::EXPLAIN => {
+                        // move one token forward
+                        self.parser.next_token();
+                        if self.parser.consume_token(&Token::LParen) {
+                            self.parse_explain_options()
+                        } else {
+                            // leave EXPLAIN without options to the native parser
+                            self.parser.prev_token();
+                            Ok(Statement::Statement(self.parser.parse_statement()?))
+                        }
+                    }
+                    _ if w.value.eq_ignore_ascii_case("DESCRIBE")
+                        || w.value.eq_ignore_ascii_case("DESC") =>
+                    {
+                        // move one token forward
+                        self.parser.next_token();
+                        self.parse_describe()
+                    }
                     _ => {
                         // use the native parser
                         Ok(Statement::Statement(self.parser.parse_statement()?))
@@ -175,10 +1032,92 @@ impl<'a> DFParser<'a> {
         }
     }
 
+    /// Parse the options of `EXPLAIN (FORMAT JSON, VERBOSE) <statement>` after the
+    /// opening parenthesis, followed by the explained statement
+    fn parse_explain_options(&mut self) -> Result<Statement, ParserError> {
+        let mut verbose = false;
+        let mut format = ExplainFormat::Text;
+        loop {
+            if self.parse_words(&["VERBOSE"]) {
+                verbose = true;
+            } else if self.parse_words(&["FORMAT"]) {
+                let name = self.parser.parse_identifier()?;
+                format = match name.value.to_uppercase().as_str() {
+                    "TEXT" => ExplainFormat::Text,
+                    "JSON" => ExplainFormat::Json,
+                    "GRAPHVIZ" => ExplainFormat::Graphviz,
+                    _ => {
+                        return parser_err!(format!(
+                            "Expected one of TEXT, JSON or GRAPHVIZ, found: {}",
+                            name
+                        ))
+                    }
+                };
+            } else {
+                return self.expected("VERBOSE or FORMAT", self.parser.peek_token());
+            }
+            if !self.parser.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.parser.expect_token(&Token::RParen)?;
+
+        let statement = Box::new(self.parser.parse_statement()?);
+        Ok(Statement::Explain(Explain {
+            verbose,
+            format,
+            statement,
+        }))
+    }
+
+    /// Parse a SQL DESCRIBE statement
+    pub fn parse_describe(&mut self) -> Result<Statement, ParserError> {
+        // DESCRIBE TABLE t is accepted as well as DESCRIBE t
+        self.parser.parse_keyword(Keyword::TABLE);
+        let table_name = self.parser.parse_object_name()?;
+        Ok(Statement::DescribeTable(DescribeTable { table_name }))
+    }
+
+    /// Parse a SQL COPY statement, only `COPY ... TO` of files is supported
+    pub fn parse_copy(&mut self) -> Result<Statement, ParserError> {
+        let source = if self.parser.consume_token(&Token::LParen) {
+            let query = self.parser.parse_query()?;
+            self.parser.expect_token(&Token::RParen)?;
+            CopySource::Query(Box::new(query))
+        } else {
+            CopySource::Table(self.parser.parse_object_name()?)
+        };
+        self.parser.expect_keyword(Keyword::TO)?;
+        let path = self.parser.parse_literal_string()?;
+
+        let mut file_type = FileType::Parquet;
+        let mut options = HashMap::new();
+        loop {
+            if self.parse_words(&["STORED", "AS"]) {
+                file_type = self.parse_file_format()?;
+            } else if self.parse_words(&["OPTIONS"]) {
+                options = self.parse_options()?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(Statement::CopyTo(CopyTo {
+            source,
+            path,
+            file_type,
+            options,
+        }))
+    }
+
     /// Parse a SQL CREATE statement
     pub fn parse_create(&mut self) -> Result<Statement, ParserError> {
         if self.parser.parse_keyword(Keyword::EXTERNAL) {
             self.parse_create_external_table()
+        } else if self.parse_words(&["OR", "REPLACE", "FUNCTION"]) {
+            self.parse_create_function(true)
+        } else if self.parse_words(&["FUNCTION"]) {
+            self.parse_create_function(false)
         } else {
             Ok(Statement::Statement(self.parser.parse_create()?))
         }
@@ -267,10 +1206,31 @@ impl<'a> DFParser<'a> {
         // THIS is the main difference: we parse a different file format.
         let file_type = self.parse_file_format()?;
 
-        let has_header = self.parse_csv_has_header();
-
-        self.parser.expect_keyword(Keyword::LOCATION)?;
-        let location = self.parser.parse_literal_string()?;
+        // the remaining clauses can be given in any order
+        let mut has_header = false;
+        let mut location = None;
+        let mut table_partition_cols = vec![];
+        let mut options = HashMap::new();
+        loop {
+            if self.parse_words(&["WITH", "HEADER", "ROW"]) {
+                has_header = true;
+            } else if self.parse_words(&["LOCATION"]) {
+                if location.is_some() {
+                    return parser_err!("LOCATION specified more than once");
+                }
+                location = Some(self.parser.parse_literal_string()?);
+            } else if self.parse_words(&["PARTITIONED", "BY"]) {
+                table_partition_cols = self.parse_partition_columns()?;
+            } else if self.parse_words(&["OPTIONS"]) {
+                options = self.parse_options()?;
+            } else {
+                break;
+            }
+        }
+        let location = match location {
+            Some(location) => location,
+            None => return self.expected("LOCATION", self.parser.peek_token()),
+        };
 
         let create = CreateExternalTable {
             name: table_name.to_string(),
@@ -278,10 +1238,82 @@ impl<'a> DFParser<'a> {
             file_type,
             has_header,
             location,
+            table_partition_cols,
+            options,
         };
         Ok(Statement::CreateExternalTable(create))
     }
 
+    /// Parses `f(x, y) AS (x * 2 + y)`, the remainder of a CREATE FUNCTION statement
+    fn parse_create_function(
+        &mut self,
+        or_replace: bool,
+    ) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_identifier()?;
+        self.parser.expect_token(&Token::LParen)?;
+        let params = if self.parser.consume_token(&Token::RParen) {
+            vec![]
+        } else {
+            let params = self
+                .parser
+                .parse_comma_separated(|parser| parser.parse_identifier())?;
+            self.parser.expect_token(&Token::RParen)?;
+            params
+        };
+        self.parser.expect_keyword(Keyword::AS)?;
+        let body = self.parser.parse_expr()?;
+        Ok(Statement::CreateFunction(CreateFunction {
+            name,
+            params,
+            body,
+            or_replace,
+        }))
+    }
+
+    /// Parses the column names of `PARTITIONED BY (a, b)`
+    fn parse_partition_columns(&mut self) -> Result<Vec<String>, ParserError> {
+        self.parser.expect_token(&Token::LParen)?;
+        let columns = self
+            .parser
+            .parse_comma_separated(|parser| parser.parse_identifier())?;
+        self.parser.expect_token(&Token::RParen)?;
+        Ok(columns.into_iter().map(|ident| ident.value).collect())
+    }
+
+    /// Parses the key value pairs of `OPTIONS (key 'value', 'other_key' 1)`. Keys
+    /// are case insensitive and returned in lower case.
+    fn parse_options(&mut self) -> Result<HashMap<String, String>, ParserError> {
+        self.parser.expect_token(&Token::LParen)?;
+        let pairs = self.parser.parse_comma_separated(|parser| {
+            let key = match parser.next_token() {
+                Token::Word(w) => w.value,
+                Token::SingleQuotedString(s) => s,
+                unexpected => return parser.expected("option name", unexpected),
+            };
+            let value = match parser.parse_value()? {
+                Value::SingleQuotedString(s) => s,
+                Value::Number(n, _) => n,
+                Value::Boolean(b) => b.to_string(),
+                unexpected => {
+                    return parser_err!(format!(
+                    "Expected string, number or boolean value for option {}, found: {}",
+                    key, unexpected
+                ))
+                }
+            };
+            Ok((key.to_lowercase(), value))
+        })?;
+        self.parser.expect_token(&Token::RParen)?;
+
+        let mut options = HashMap::new();
+        for (key, value) in pairs {
+            if options.insert(key.clone(), value).is_some() {
+                return parser_err!(format!("Option {} specified more than once", key));
+            }
+        }
+        Ok(options)
+    }
+
     /// Parses the set of valid formats
     fn parse_file_format(&mut self) -> Result<FileType, ParserError> {
         match self.parser.next_token() {
@@ -290,26 +1322,30 @@ impl<'a> DFParser<'a> {
         }
     }
 
-    fn consume_token(&mut self, expected: &str) -> bool {
-        if self.parser.peek_token().to_string() == *expected {
-            self.parser.next_token();
-            true
-        } else {
-            false
+    /// Consumes the next tokens if they are the given words, ignoring case, and
+    /// leaves them in place otherwise
+    fn parse_words(&mut self, words: &[&str]) -> bool {
+        for (i, word) in words.iter().enumerate() {
+            match self.parser.peek_token() {
+                Token::Word(w) if w.value.eq_ignore_ascii_case(word) => {
+                    self.parser.next_token();
+                }
+                _ => {
+                    for _ in 0..i {
+                        self.parser.prev_token();
+                    }
+                    return false;
+                }
+            }
         }
-    }
-
-    fn parse_csv_has_header(&mut self) -> bool {
-        self.consume_token("WITH")
-            & self.consume_token("HEADER")
-            & self.consume_token("ROW")
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sqlparser::ast::{DataType, Ident};
+    use sqlparser::ast::DataType;
 
     fn expect_parse_ok(sql: &str, expected: Statement) -> Result<(), ParserError> {
         let statements = DFParser::parse_sql(sql)?;
@@ -365,6 +1401,8 @@ mod tests {
             file_type: FileType::CSV,
             has_header: false,
             location: "foo.csv".into(),
+            table_partition_cols: vec![],
+            options: HashMap::new(),
         });
         expect_parse_ok(sql, expected)?;
 
@@ -376,6 +1414,8 @@ mod tests {
             file_type: FileType::Parquet,
             has_header: false,
             location: "foo.parquet".into(),
+            table_partition_cols: vec![],
+            options: HashMap::new(),
         });
         expect_parse_ok(sql, expected)?;
 
@@ -387,14 +1427,504 @@ mod tests {
             file_type: FileType::Parquet,
             has_header: false,
             location: "foo.parquet".into(),
+            table_partition_cols: vec![],
+            options: HashMap::new(),
+        });
+        expect_parse_ok(sql, expected)?;
+
+        // positive case: partition columns and options, in any order
+        let sql = "CREATE EXTERNAL TABLE t(c1 int) STORED AS CSV \
+            PARTITIONED BY (year, month) WITH HEADER ROW LOCATION 'foo' \
+            OPTIONS (delimiter '|', 'COMPRESSION' 'gzip', schema_infer_max_records 10)";
+        let expected = Statement::CreateExternalTable(CreateExternalTable {
+            name: "t".into(),
+            columns: vec![make_column_def("c1", DataType::Int)],
+            file_type: FileType::CSV,
+            has_header: true,
+            location: "foo".into(),
+            table_partition_cols: vec!["year".into(), "month".into()],
+            options: vec![
+                ("delimiter", "|"),
+                ("compression", "gzip"),
+                ("schema_infer_max_records", "10"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
         });
         expect_parse_ok(sql, expected)?;
 
         // Error cases: Invalid type
         let sql =
             "CREATE EXTERNAL TABLE t(c1 int) STORED AS UNKNOWN_TYPE LOCATION 'foo.csv'";
-        expect_parse_error(sql, "expect one of PARQUET, NDJSON, or CSV");
+        expect_parse_error(sql, "expect one of PARQUET, NDJSON, CSV, or DELTA");
+
+        // Error cases: missing location
+        let sql = "CREATE EXTERNAL TABLE t(c1 int) STORED AS CSV WITH HEADER ROW";
+        expect_parse_error(sql, "Expected LOCATION, found: EOF");
+
+        // Error cases: repeated option
+        let sql = "CREATE EXTERNAL TABLE t STORED AS PARQUET LOCATION 'foo' \
+            OPTIONS (a '1', A '2')";
+        expect_parse_error(sql, "Option a specified more than once");
+
+        Ok(())
+    }
+
+    #[test]
+    fn copy_to() -> Result<(), ParserError> {
+        let sql =
+            "COPY t TO 'out' OPTIONS (compression 'zstd', 'compression::c1' 'none')";
+        let expected = Statement::CopyTo(CopyTo {
+            source: CopySource::Table(ObjectName(vec![Ident::new("t")])),
+            path: "out".into(),
+            file_type: FileType::Parquet,
+            options: vec![
+                ("compression".to_owned(), "zstd".to_owned()),
+                ("compression::c1".to_owned(), "none".to_owned()),
+            ]
+            .into_iter()
+            .collect(),
+        });
+        expect_parse_ok(sql, expected)?;
+
+        let sql = "COPY (SELECT c1 FROM t WHERE c2 > 1) TO 'out' STORED AS CSV";
+        let statements = DFParser::parse_sql(sql)?;
+        match &statements[..] {
+            [Statement::CopyTo(copy)] => {
+                match &copy.source {
+                    CopySource::Query(query) => {
+                        assert_eq!(query.to_string(), "SELECT c1 FROM t WHERE c2 > 1")
+                    }
+                    other => panic!("Expected a query, got {:?}", other),
+                }
+                assert_eq!(copy.file_type, FileType::CSV);
+                assert!(copy.options.is_empty());
+            }
+            other => panic!("Expected COPY TO, got {:?}", other),
+        }
+
+        expect_parse_error("COPY t FROM 'out'", "Expected TO, found: FROM");
+        Ok(())
+    }
+
+    #[test]
+    fn describe_table() -> Result<(), ParserError> {
+        let expected = |idents: Vec<&str>| {
+            Statement::DescribeTable(DescribeTable {
+                table_name: ObjectName(idents.into_iter().map(Ident::new).collect()),
+            })
+        };
+        expect_parse_ok("DESCRIBE t", expected(vec!["t"]))?;
+        expect_parse_ok("describe table public.t", expected(vec!["public", "t"]))?;
+        expect_parse_ok(
+            "DESC datafusion.public.t;",
+            expected(vec!["datafusion", "public", "t"]),
+        )?;
+
+        expect_parse_error("DESCRIBE", "Expected identifier, found: EOF");
+        Ok(())
+    }
+
+    #[test]
+    fn create_function() -> Result<(), ParserError> {
+        let sql = "CREATE FUNCTION f(x, y) AS (x * 2 + y)";
+        let statements = DFParser::parse_sql(sql)?;
+        match &statements[..] {
+            [Statement::CreateFunction(create)] => {
+                assert_eq!(create.name, Ident::new("f"));
+                assert_eq!(create.params, vec![Ident::new("x"), Ident::new("y")]);
+                assert_eq!(create.body.to_string(), "(x * 2 + y)");
+                assert!(!create.or_replace);
+            }
+            other => panic!("Expected CREATE FUNCTION, got {:?}", other),
+        }
+
+        let sql = "CREATE OR REPLACE FUNCTION pi() AS 3.14";
+        let statements = DFParser::parse_sql(sql)?;
+        match &statements[..] {
+            [Statement::CreateFunction(create)] => {
+                assert!(create.params.is_empty());
+                assert!(create.or_replace);
+            }
+            other => panic!("Expected CREATE FUNCTION, got {:?}", other),
+        }
+
+        // other CREATE statements are still parsed by sqlparser
+        let statements = DFParser::parse_sql("CREATE OR REPLACE VIEW v AS SELECT 1")?;
+        assert!(matches!(statements[..], [Statement::Statement(_)]));
+
+        expect_parse_error("CREATE FUNCTION f(x)", "Expected AS, found: EOF");
+        Ok(())
+    }
+
+    #[test]
+    fn explain_format() -> Result<(), ParserError> {
+        let sql = "EXPLAIN (FORMAT JSON, VERBOSE) SELECT 1";
+        let statements = DFParser::parse_sql(sql)?;
+        match &statements[..] {
+            [Statement::Explain(explain)] => {
+                assert!(explain.verbose);
+                assert_eq!(explain.format, ExplainFormat::Json);
+                assert_eq!(explain.statement.to_string(), "SELECT 1");
+            }
+            other => panic!("Expected EXPLAIN, got {:?}", other),
+        }
+
+        let statements = DFParser::parse_sql("explain (format graphviz) SELECT 1")?;
+        assert!(matches!(
+            &statements[..],
+            [Statement::Explain(Explain {
+                verbose: false,
+                format: ExplainFormat::Graphviz,
+                ..
+            })]
+        ));
+
+        // EXPLAIN without options is still parsed by sqlparser
+        let statements = DFParser::parse_sql("EXPLAIN VERBOSE SELECT 1")?;
+        assert!(matches!(statements[..], [Statement::Statement(_)]));
+
+        expect_parse_error(
+            "EXPLAIN (FORMAT XML) SELECT 1",
+            "Expected one of TEXT, JSON or GRAPHVIZ, found: XML",
+        );
+        expect_parse_error(
+            "EXPLAIN (ANALYZE) SELECT 1",
+            "Expected VERBOSE or FORMAT, found: ANALYZE",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn window_function_null_treatment() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT LAG(c1) IGNORE NULLS OVER (ORDER BY c2) FROM t")?,
+            parse("SELECT LAG(c1, 1, NULL, true) OVER (ORDER BY c2) FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT LEAD(c1, 2) IGNORE NULLS OVER (ORDER BY c2) FROM t")?,
+            parse("SELECT LEAD(c1, 2, NULL, true) OVER (ORDER BY c2) FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT FIRST_VALUE(abs(c1)) IGNORE NULLS OVER () FROM t")?,
+            parse("SELECT FIRST_VALUE(abs(c1), true) OVER () FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT LAST_VALUE(c1) RESPECT NULLS OVER () FROM t")?,
+            parse("SELECT LAST_VALUE(c1) OVER () FROM t")?
+        );
+
+        expect_parse_error(
+            "SELECT MAX(c1) IGNORE NULLS OVER () FROM t",
+            "IGNORE NULLS is not supported for function MAX",
+        );
+        expect_parse_error(
+            "SELECT NTH_VALUE(c1) IGNORE NULLS OVER () FROM t",
+            "Invalid number of arguments for NTH_VALUE with IGNORE NULLS: 1",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn window_frame_exclusion() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse(
+                "SELECT SUM(c1) OVER (ORDER BY c2 ROWS 1 PRECEDING EXCLUDE CURRENT ROW), \
+                 COUNT(DISTINCT c1) OVER (PARTITION BY c3 EXCLUDE ties) FROM t"
+            )?,
+            parse(
+                "SELECT SUM(c1, __window_exclude('CURRENT ROW')) \
+                 OVER (ORDER BY c2 ROWS 1 PRECEDING), \
+                 COUNT(DISTINCT c1, __window_exclude('TIES')) OVER (PARTITION BY c3) \
+                 FROM t"
+            )?
+        );
+        assert_eq!(
+            parse("SELECT ROW_NUMBER() OVER (ORDER BY c2 EXCLUDE GROUP) FROM t")?,
+            parse(
+                "SELECT ROW_NUMBER(__window_exclude('GROUP')) OVER (ORDER BY c2) FROM t"
+            )?
+        );
+        assert_eq!(
+            parse("SELECT SUM(c1) OVER (ORDER BY c2 EXCLUDE NO OTHERS) FROM t")?,
+            parse("SELECT SUM(c1) OVER (ORDER BY c2) FROM t")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wildcard_except() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT * EXCLUDE (c1, t.c2) FROM t")?,
+            parse("SELECT __wildcard_except(c1, t.c2) FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT c3, * except(c1) FROM t")?,
+            parse("SELECT c3, __wildcard_except(c1) FROM t")?
+        );
+
+        // multiplication and set operations are left alone
+        assert_eq!(
+            parse("SELECT c1 * EXCLUDE (c2) FROM t")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(&GenericDialect {}, "SELECT c1 * EXCLUDE (c2) FROM t")?
+                    .remove(0)
+            )]
+        );
+        assert_eq!(
+            parse("SELECT * EXCEPT (SELECT 1)")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(&GenericDialect {}, "SELECT * EXCEPT (SELECT 1)")?
+                    .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn distinct_from() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a IS DISTINCT FROM b")?,
+            parse("SELECT a FROM t WHERE __is_distinct_from(a, b)")?
+        );
+        assert_eq!(
+            parse(
+                "SELECT a + 1 IS NOT DISTINCT FROM b * 2 AS c, d FROM t \
+                 WHERE x > 1 AND (y IS DISTINCT FROM NULL)"
+            )?,
+            parse(
+                "SELECT __is_not_distinct_from(a + 1, b * 2) AS c, d FROM t \
+                 WHERE x > 1 AND (__is_distinct_from(y, NULL))"
+            )?
+        );
+        assert_eq!(
+            parse(
+                "SELECT * FROM l JOIN r ON l.a IS NOT DISTINCT FROM r.a AND l.b = r.b"
+            )?,
+            parse(
+                "SELECT * FROM l JOIN r ON __is_not_distinct_from(l.a, r.a) AND l.b = r.b"
+            )?
+        );
+        assert_eq!(
+            parse(
+                "SELECT CASE WHEN f(a, b) IS DISTINCT FROM c THEN 1 END \
+                 FROM t ORDER BY a"
+            )?,
+            parse(
+                "SELECT CASE WHEN __is_distinct_from(f(a, b), c) THEN 1 END \
+                 FROM t ORDER BY a"
+            )?
+        );
+        // quoted identifiers are not keywords
+        assert_eq!(
+            parse("SELECT \"from\" IS DISTINCT FROM \"not\" FROM t")?,
+            parse("SELECT __is_distinct_from(\"from\", \"not\") FROM t")?
+        );
+        // IS NULL is left alone
+        assert_eq!(
+            parse("SELECT a IS NOT NULL FROM t")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(&GenericDialect {}, "SELECT a IS NOT NULL FROM t")?
+                    .remove(0)
+            )]
+        );
+        Ok(())
+    }
 
+    #[test]
+    fn pattern_matching() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a ILIKE 'x%' AND b NOT ILIKE c || '%'")?,
+            parse("SELECT a FROM t WHERE __ilike(a, 'x%') AND __not_ilike(b, c || '%')")?
+        );
+        assert_eq!(
+            parse("SELECT a SIMILAR TO '(x|y)%' AS c, NOT b NOT SIMILAR TO 'z' FROM t")?,
+            parse(
+                "SELECT __similar_to(a, '(x|y)%') AS c, NOT __not_similar_to(b, 'z') \
+                 FROM t"
+            )?
+        );
+        assert_eq!(
+            parse(
+                "SELECT a FROM t WHERE a LIKE 'x#%%' ESCAPE '#' = (b ILIKE c ESCAPE '')"
+            )?,
+            parse("SELECT a FROM t WHERE __like(a, 'x#%%', '#') = (__ilike(b, c, ''))")?
+        );
+        assert_eq!(
+            parse("SELECT CASE WHEN a NOT LIKE '%!_' ESCAPE '!' THEN 1 END FROM t")?,
+            parse("SELECT CASE WHEN __not_like(a, '%!_', '!') THEN 1 END FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT a FROM t WHERE \"like\" ILIKE \"escape\"")?,
+            parse("SELECT a FROM t WHERE __ilike(\"like\", \"escape\")")?
+        );
+        // LIKE without ESCAPE is left alone
+        assert_eq!(
+            parse("SELECT a NOT LIKE 'x%' FROM t")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(&GenericDialect {}, "SELECT a NOT LIKE 'x%' FROM t")?
+                    .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn qualify() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT a FROM t QUALIFY ROW_NUMBER() OVER (PARTITION BY b) = 1")?,
+            parse("SELECT a, __qualify(ROW_NUMBER() OVER (PARTITION BY b) = 1) FROM t")?
+        );
+        assert_eq!(
+            parse(
+                "SELECT a, RANK() OVER (ORDER BY b) AS r FROM t WHERE a > 1 \
+                 QUALIFY r < 3 ORDER BY a LIMIT 10"
+            )?,
+            parse(
+                "SELECT a, RANK() OVER (ORDER BY b) AS r, __qualify(r < 3) FROM t \
+                 WHERE a > 1 ORDER BY a LIMIT 10"
+            )?
+        );
+        // subqueries are rewritten on their own
+        assert_eq!(
+            parse(
+                "SELECT * FROM (SELECT a FROM t QUALIFY RANK() OVER () = 1) \
+                 WHERE a IN (SELECT b FROM u)"
+            )?,
+            parse(
+                "SELECT * FROM (SELECT a, __qualify(RANK() OVER () = 1) FROM t) \
+                 WHERE a IN (SELECT b FROM u)"
+            )?
+        );
+        // a table named qualify is left alone
+        assert_eq!(
+            parse("SELECT a FROM t AS \"qualify\" WHERE a > 1")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(
+                    &GenericDialect {},
+                    "SELECT a FROM t AS \"qualify\" WHERE a > 1"
+                )?
+                .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate_order_by() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT ARRAY_AGG(a ORDER BY b DESC NULLS LAST, c) FROM t")?,
+            parse(
+                "SELECT ARRAY_AGG(a, __order_by(b, false, false), \
+                 __order_by(c, true, true)) FROM t"
+            )?
+        );
+        assert_eq!(
+            parse("SELECT string_agg(a, ', ' order by abs(b) asc nulls first) FROM t")?,
+            parse("SELECT string_agg(a, ', ', __order_by(abs(b), true, true)) FROM t")?
+        );
+        // the ORDER BY of a window is left alone
+        assert_eq!(
+            parse("SELECT FIRST_VALUE(a) OVER (ORDER BY b) FROM t")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(
+                    &GenericDialect {},
+                    "SELECT FIRST_VALUE(a) OVER (ORDER BY b) FROM t"
+                )?
+                .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate_filter() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT COUNT(a) FILTER (WHERE b > (1)), SUM(a) FROM t")?,
+            parse("SELECT COUNT(a, __filter(b > (1))), SUM(a) FROM t")?
+        );
+        assert_eq!(
+            parse("SELECT count(*) filter (where b) FROM t GROUP BY c")?,
+            parse("SELECT count(*, __filter(b)) FROM t GROUP BY c")?
+        );
+        // the FILTER clause follows the ORDER BY of the arguments
+        assert_eq!(
+            parse("SELECT ARRAY_AGG(a ORDER BY b) FILTER (WHERE c) FROM t")?,
+            parse("SELECT ARRAY_AGG(a, __order_by(b, true, true), __filter(c)) FROM t")?
+        );
+        // columns named filter are left alone
+        assert_eq!(
+            parse("SELECT filter FROM t WHERE (filter)")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(
+                    &GenericDialect {},
+                    "SELECT filter FROM t WHERE (filter)"
+                )?
+                .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn by_all() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        assert_eq!(
+            parse("SELECT a, SUM(b) FROM t GROUP BY ALL ORDER BY ALL DESC NULLS LAST")?,
+            parse(
+                "SELECT a, SUM(b) FROM t GROUP BY __by_all() \
+                 ORDER BY __by_all() DESC NULLS LAST"
+            )?
+        );
+        assert_eq!(
+            parse("SELECT * FROM (SELECT a FROM t group by all) ORDER BY all;")?,
+            parse(
+                "SELECT * FROM (SELECT a FROM t GROUP BY __by_all()) \
+                 ORDER BY __by_all();"
+            )?
+        );
+        // a column named all is left alone
+        assert_eq!(
+            parse("SELECT a FROM t ORDER BY \"ALL\", b")?,
+            vec![Statement::Statement(
+                Parser::parse_sql(
+                    &GenericDialect {},
+                    "SELECT a FROM t ORDER BY \"ALL\", b"
+                )?
+                .remove(0)
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_rewrites() -> Result<(), ParserError> {
+        let parse = |sql: &str| DFParser::parse_sql(sql);
+        // the pattern matching operators bind more tightly than IS DISTINCT FROM
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a IS DISTINCT FROM b NOT ILIKE c")?,
+            parse("SELECT a FROM t WHERE __is_distinct_from(a, __not_ilike(b, c))")?
+        );
+        // the clauses of a moved QUALIFY predicate are rewritten as well
+        assert_eq!(
+            parse(
+                "SELECT a FROM t GROUP BY ALL \
+                 QUALIFY COUNT(b) FILTER (WHERE b ILIKE 'x') OVER () > 1"
+            )?,
+            parse(
+                "SELECT a, __qualify(COUNT(b, __filter(__ilike(b, 'x'))) OVER () > 1) \
+                 FROM t GROUP BY __by_all()"
+            )?
+        );
         Ok(())
     }
 }
@@ -24,39 +24,55 @@ use std::{convert::TryInto, vec};
 
 use crate::catalog::TableReference;
 use crate::datasource::TableProvider;
-use crate::logical_plan::window_frames::{WindowFrame, WindowFrameUnits};
+use crate::logical_plan::window_frames::{
+    WindowFrame, WindowFrameExclusion, WindowFrameUnits,
+};
 use crate::logical_plan::Expr::Alias;
 use crate::logical_plan::{
-    and, builder::expand_wildcard, col, lit, normalize_col, union_with_alias, Column,
-    DFSchema, Expr, LogicalPlan, LogicalPlanBuilder, Operator, PlanType, ToDFSchema,
+    and,
+    builder::{expand_wildcard, expand_wildcard_except},
+    col, lit, normalize_col, replace_col, union_with_alias, Column, DFSchema,
+    ExplainFormat, Expr, LogicalPlan, LogicalPlanBuilder, Operator, PlanType, ToDFSchema,
     ToStringifiedPlan,
 };
-use crate::optimizer::utils::exprlist_to_columns;
+use crate::optimizer::utils::{expr_to_columns, exprlist_to_columns, split_conjunction};
 use crate::prelude::JoinType;
 use crate::scalar::ScalarValue;
 use crate::{
     error::{DataFusionError, Result},
+    physical_plan::expressions::escape_pattern,
     physical_plan::udaf::AggregateUDF,
+    physical_plan::udwf::WindowUDF,
 };
 use crate::{
+    physical_plan::parquet_writer::{ParquetWriteOptions, ParquetWriterExec},
     physical_plan::udf::ScalarUDF,
     physical_plan::{aggregates, functions, window_functions},
-    sql::parser::{CreateExternalTable, FileType, Statement as DFStatement},
+    sql::macros::SqlMacro,
+    sql::parser::{
+        CopySource, CopyTo, CreateExternalTable, CreateFunction, FileType,
+        Statement as DFStatement,
+    },
 };
 use arrow::datatypes::*;
 use hashbrown::HashMap;
 use sqlparser::ast::{
     BinaryOperator, DataType as SQLDataType, DateTimeField, Expr as SQLExpr, FunctionArg,
-    Ident, Join, JoinConstraint, JoinOperator, ObjectName, Query, Select, SelectItem,
-    SetExpr, SetOperator, ShowStatementFilter, TableFactor, TableWithJoins,
-    UnaryOperator, Value,
+    Ident, Join, JoinConstraint, JoinOperator, ObjectName, Offset, Query, Select,
+    SelectItem, SetExpr, SetOperator, ShowStatementFilter, TableAlias, TableFactor,
+    TableWithJoins, UnaryOperator, Value,
 };
 use sqlparser::ast::{ColumnDef as SQLColumnDef, ColumnOption};
 use sqlparser::ast::{OrderByExpr, Statement};
 use sqlparser::parser::ParserError::ParserError;
 
 use super::{
-    parser::DFParser,
+    arrow_cast::{create_arrow_cast, ARROW_CAST, ARROW_TRY_CAST},
+    parser::{
+        DFParser, AGGREGATE_FILTER, BY_ALL, ILIKE, IS_DISTINCT_FROM,
+        IS_NOT_DISTINCT_FROM, LIKE, NOT_ILIKE, NOT_LIKE, NOT_SIMILAR_TO, ORDER_BY,
+        QUALIFY, RESERVED_FUNCTION_PREFIX, SIMILAR_TO, WILDCARD_EXCEPT, WINDOW_EXCLUDE,
+    },
     utils::{
         can_columns_satisfy_exprs, expr_as_column_expr, extract_aliases,
         find_aggregate_exprs, find_column_exprs, find_window_exprs,
@@ -70,10 +86,25 @@ use super::{
 pub trait ContextProvider {
     /// Getter for a datasource
     fn get_table_provider(&self, name: TableReference) -> Option<Arc<dyn TableProvider>>;
+    /// Getter for a datasource that returns the error that prevented looking it up
+    fn try_get_table_provider(
+        &self,
+        name: TableReference,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        Ok(self.get_table_provider(name))
+    }
     /// Getter for a UDF description
     fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>>;
     /// Getter for a UDAF description
     fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>>;
+    /// Getter for a SQL macro, a function created by `CREATE FUNCTION`
+    fn get_macro(&self, _name: &str) -> Option<Arc<SqlMacro>> {
+        None
+    }
+    /// Getter for a UDWF description
+    fn get_window_meta(&self, _name: &str) -> Option<Arc<WindowUDF>> {
+        None
+    }
 }
 
 /// SQL query planner
@@ -91,6 +122,12 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
     pub fn statement_to_plan(&self, statement: &DFStatement) -> Result<LogicalPlan> {
         match statement {
             DFStatement::CreateExternalTable(s) => self.external_table_to_plan(s),
+            DFStatement::DescribeTable(s) => self.describe_table_to_plan(&s.table_name),
+            DFStatement::CreateFunction(s) => self.create_function_to_plan(s),
+            DFStatement::CopyTo(s) => self.copy_to_plan(s),
+            DFStatement::Explain(s) => {
+                self.explain_statement_to_plan(s.verbose, s.format, &s.statement)
+            }
             DFStatement::Statement(s) => self.sql_statement_to_plan(s),
         }
     }
@@ -102,7 +139,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 verbose,
                 statement,
                 analyze: _,
-            } => self.explain_statement_to_plan(*verbose, statement),
+            } => self.explain_statement_to_plan(*verbose, ExplainFormat::Text, statement),
             Statement::Query(query) => self.query_to_plan(query),
             Statement::ShowVariable { variable } => self.show_variable_to_plan(variable),
             Statement::ShowColumns {
@@ -147,7 +184,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
         let plan = self.order_by(plan, &query.order_by)?;
 
-        self.limit(plan, &query.limit)
+        self.limit(plan, &query.limit, &query.offset)
     }
 
     fn set_expr_to_plan(
@@ -192,6 +229,8 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             file_type,
             has_header,
             location,
+            table_partition_cols,
+            options,
         } = statement;
 
         // semantic checks
@@ -212,8 +251,33 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 }
             }
             FileType::NdJson => {}
+            FileType::Delta => {
+                if !columns.is_empty() || !table_partition_cols.is_empty() {
+                    return Err(DataFusionError::Plan(
+                        "Columns and partition columns can not be specified for DELTA \
+                         tables, they are read from the transaction log."
+                            .into(),
+                    ));
+                }
+            }
         };
 
+        // partition columns are read from the directory names, not from the files
+        for (i, partition_col) in table_partition_cols.iter().enumerate() {
+            if columns.iter().any(|c| c.name.value == *partition_col) {
+                return Err(DataFusionError::Plan(format!(
+                    "Partition column {} must not be declared as a column of the files",
+                    partition_col
+                )));
+            }
+            if table_partition_cols[..i].contains(partition_col) {
+                return Err(DataFusionError::Plan(format!(
+                    "Partition column {} specified more than once",
+                    partition_col
+                )));
+            }
+        }
+
         let schema = self.build_schema(columns)?;
 
         Ok(LogicalPlan::CreateExternalTable {
@@ -222,25 +286,30 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             location: location.clone(),
             file_type: *file_type,
             has_header: *has_header,
+            table_partition_cols: table_partition_cols.clone(),
+            options: options.clone(),
         })
     }
 
-    /// Generate a plan for EXPLAIN ... that will print out a plan
+    /// Generate a plan for EXPLAIN ... that will print out a plan in `format`
     ///
     pub fn explain_statement_to_plan(
         &self,
         verbose: bool,
+        format: ExplainFormat,
         statement: &Statement,
     ) -> Result<LogicalPlan> {
         let plan = self.sql_statement_to_plan(statement)?;
 
-        let stringified_plans = vec![plan.to_stringified(PlanType::InitialLogicalPlan)];
+        let stringified_plans =
+            vec![plan.to_stringified(PlanType::InitialLogicalPlan, format)];
 
         let schema = LogicalPlan::explain_schema();
         let plan = Arc::new(plan);
 
         Ok(LogicalPlan::Explain {
             verbose,
+            format,
             plan,
             stringified_plans,
             schema: schema.to_dfschema_ref()?,
@@ -290,13 +359,27 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
         from: &[TableWithJoins],
         ctes: &mut HashMap<String, LogicalPlan>,
     ) -> Result<Vec<LogicalPlan>> {
-        match from.len() {
-            0 => Ok(vec![LogicalPlanBuilder::empty(true).build()?]),
-            _ => from
-                .iter()
-                .map(|t| self.plan_table_with_joins(t, ctes))
-                .collect::<Result<Vec<_>>>(),
+        if from.is_empty() {
+            return Ok(vec![LogicalPlanBuilder::empty(true).build()?]);
+        }
+        let mut plans = vec![];
+        for t in from {
+            let relation = if is_lateral(&t.relation) {
+                // a LATERAL item may reference any of the preceding FROM items, so
+                // these are combined into the outer side of the lateral join
+                let outer = if plans.is_empty() {
+                    LogicalPlanBuilder::empty(true).build()?
+                } else {
+                    self.plan_selection(&plans, None)?
+                };
+                plans.clear();
+                self.plan_lateral(outer, &t.relation, ctes)?
+            } else {
+                self.create_relation(&t.relation, ctes)?
+            };
+            plans.push(self.plan_joins(relation, &t.joins, ctes)?);
         }
+        Ok(plans)
     }
 
     fn plan_table_with_joins(
@@ -305,16 +388,20 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
         ctes: &mut HashMap<String, LogicalPlan>,
     ) -> Result<LogicalPlan> {
         let left = self.create_relation(&t.relation, ctes)?;
-        match t.joins.len() {
-            0 => Ok(left),
-            n => {
-                let mut left = self.parse_relation_join(left, &t.joins[0], ctes)?;
-                for i in 1..n {
-                    left = self.parse_relation_join(left, &t.joins[i], ctes)?;
-                }
-                Ok(left)
-            }
+        self.plan_joins(left, &t.joins, ctes)
+    }
+
+    fn plan_joins(
+        &self,
+        left: LogicalPlan,
+        joins: &[Join],
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        let mut left = left;
+        for join in joins {
+            left = self.parse_relation_join(left, join, ctes)?;
         }
+        Ok(left)
     }
 
     fn parse_relation_join(
@@ -323,6 +410,11 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
         join: &Join,
         ctes: &mut HashMap<String, LogicalPlan>,
     ) -> Result<LogicalPlan> {
+        if is_lateral(&join.relation)
+            || matches!(join.join_operator, JoinOperator::CrossApply)
+        {
+            return self.parse_lateral_join(left, join, ctes);
+        }
         let right = self.create_relation(&join.relation, ctes)?;
         match &join.join_operator {
             JoinOperator::LeftOuter(constraint) => {
@@ -344,6 +436,267 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             ))),
         }
     }
+
+    fn parse_lateral_join(
+        &self,
+        left: LogicalPlan,
+        join: &Join,
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        match &join.join_operator {
+            JoinOperator::CrossJoin | JoinOperator::CrossApply => {
+                self.plan_lateral(left, &join.relation, ctes)
+            }
+            JoinOperator::Inner(JoinConstraint::On(sql_expr)) => {
+                let plan = self.plan_lateral(left, &join.relation, ctes)?;
+                match self.sql_to_rex(sql_expr, plan.schema())? {
+                    Expr::Literal(ScalarValue::Boolean(Some(true))) => Ok(plan),
+                    expr => LogicalPlanBuilder::from(plan).filter(expr)?.build(),
+                }
+            }
+            other => Err(DataFusionError::NotImplemented(format!(
+                "Unsupported LATERAL JOIN operator {:?}",
+                other
+            ))),
+        }
+    }
+
+    /// Plans a LATERAL FROM item, which may reference the columns of `outer`.
+    ///
+    /// The item is decorrelated into a join with `outer`: equality predicates
+    /// between the item and `outer` become the keys of a hash join, any other
+    /// correlated predicate filters the output of a nested loop (cross) join.
+    fn plan_lateral(
+        &self,
+        outer: LogicalPlan,
+        relation: &TableFactor,
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        match relation {
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let columns_alias = match alias {
+                    Some(alias) => alias.columns.as_slice(),
+                    None => &[],
+                };
+                self.plan_lateral_subquery(outer, subquery, columns_alias, ctes)
+            }
+            TableFactor::Table {
+                name, args, alias, ..
+            } => self.plan_table_function(outer, name, args, alias.as_ref()),
+            _ => Err(DataFusionError::NotImplemented(format!(
+                "Unsupported LATERAL relation {:?}",
+                relation
+            ))),
+        }
+    }
+
+    fn plan_lateral_subquery(
+        &self,
+        outer: LogicalPlan,
+        query: &Query,
+        columns_alias: &[Ident],
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        let select = match &query.body {
+            SetExpr::Select(select)
+                if query.with.is_none()
+                    && query.order_by.is_empty()
+                    && query.limit.is_none()
+                    && query.offset.is_none()
+                    && query.fetch.is_none() =>
+            {
+                select
+            }
+            _ => {
+                return Err(DataFusionError::NotImplemented(
+                    "LATERAL subqueries must be a single SELECT without WITH, ORDER BY or LIMIT"
+                        .to_string(),
+                ))
+            }
+        };
+        if select.distinct || !select.group_by.is_empty() || select.having.is_some() {
+            return Err(DataFusionError::NotImplemented(
+                "DISTINCT, GROUP BY and HAVING are not supported in LATERAL subqueries"
+                    .to_string(),
+            ));
+        }
+
+        let inner_plans = self.plan_from_tables(&select.from, ctes)?;
+        // only used to resolve the columns of the subquery's own FROM items
+        let inner = self.plan_selection(&inner_plans, None)?;
+        let join_schema = outer.schema().join(inner.schema())?;
+
+        // split the WHERE clause into the predicates local to the subquery and the
+        // ones correlated with the outer FROM items
+        let mut local = vec![];
+        let mut correlated = vec![];
+        if let Some(selection) = &select.selection {
+            let mut predicates = vec![];
+            split_conjunction(
+                &self.sql_to_rex(selection, &join_schema)?,
+                &mut predicates,
+            );
+            for predicate in predicates {
+                let (predicate, is_correlated) =
+                    resolve_lateral_columns(predicate, &inner, &outer)?;
+                if is_correlated {
+                    correlated.push(predicate);
+                } else {
+                    local.push(predicate);
+                }
+            }
+        }
+
+        if select
+            .projection
+            .iter()
+            .any(|item| matches!(qualify_predicate(item), Ok(Some(_))))
+        {
+            return Err(DataFusionError::NotImplemented(
+                "QUALIFY is not supported in LATERAL subqueries".to_string(),
+            ));
+        }
+        let mut select_exprs = self.prepare_select_exprs(&inner, &select.projection)?;
+        if !find_aggregate_exprs(&select_exprs).is_empty()
+            || !find_window_exprs(&select_exprs).is_empty()
+        {
+            return Err(DataFusionError::NotImplemented(
+                "Aggregate and window functions are not supported in LATERAL subqueries"
+                    .to_string(),
+            ));
+        }
+        if !columns_alias.is_empty() {
+            if columns_alias.len() != select_exprs.len() {
+                return Err(DataFusionError::Plan(format!(
+                    "Source table contains {} columns but only {} names given as column alias",
+                    select_exprs.len(),
+                    columns_alias.len(),
+                )));
+            }
+            select_exprs = select_exprs
+                .into_iter()
+                .zip(columns_alias)
+                .map(|(expr, ident)| match expr {
+                    Expr::Alias(expr, _) => (*expr).alias(&ident.value),
+                    expr => expr.alias(&ident.value),
+                })
+                .collect();
+        }
+
+        // the subquery's columns referenced by correlated predicates are carried
+        // through its projection so that they can be evaluated by the join
+        let mut columns = HashSet::new();
+        exprlist_to_columns(&correlated, &mut columns)?;
+        let mut columns = columns
+            .into_iter()
+            .filter(|c| has_column(inner.schema(), c))
+            .collect::<Vec<_>>();
+        columns.sort_by_key(|c| c.flat_name());
+        let carried = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c, Column::from_name(format!("__lateral_{}", i))))
+            .collect::<Vec<_>>();
+
+        let output_len = select_exprs.len();
+        let mut exprs = select_exprs;
+        exprs.extend(
+            carried
+                .iter()
+                .map(|(c, carried)| Expr::Column((*c).clone()).alias(&carried.name)),
+        );
+        let inner = self.project(
+            self.plan_selection(&inner_plans, local.into_iter().reduce(and))?,
+            exprs,
+        )?;
+
+        let replace_map: std::collections::HashMap<&Column, &Column> =
+            carried.iter().map(|(c, carried)| (*c, carried)).collect();
+        let correlated = correlated
+            .into_iter()
+            .map(|e| replace_col(e, &replace_map))
+            .collect::<Result<Vec<_>>>()?;
+        let plan = self.plan_selection(
+            &[outer.clone(), inner.clone()],
+            correlated.into_iter().reduce(and),
+        )?;
+
+        // remove the carried columns from the output
+        let output = outer
+            .schema()
+            .fields()
+            .iter()
+            .chain(inner.schema().fields().iter().take(output_len))
+            .map(|f| Expr::Column(f.qualified_column()))
+            .collect();
+        self.project(plan, output)
+    }
+
+    /// Plans a table function in the FROM clause whose arguments may reference the
+    /// columns of `outer`. Only `unnest` is supported, which returns a row for each
+    /// element of a list column together with the columns of `outer`.
+    fn plan_table_function(
+        &self,
+        outer: LogicalPlan,
+        name: &ObjectName,
+        args: &[FunctionArg],
+        alias: Option<&TableAlias>,
+    ) -> Result<LogicalPlan> {
+        if name.to_string().to_lowercase() != "unnest" {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Unsupported table function {}",
+                name
+            )));
+        }
+        let column = match args {
+            [FunctionArg::Unnamed(arg)] => {
+                match normalize_col(self.sql_to_rex(arg, outer.schema())?, &outer)? {
+                    Expr::Column(c) => c,
+                    expr => {
+                        return Err(DataFusionError::NotImplemented(format!(
+                            "unnest only supports column arguments, found {:?}",
+                            expr
+                        )))
+                    }
+                }
+            }
+            _ => {
+                return Err(DataFusionError::Plan(
+                    "unnest requires a single argument".to_string(),
+                ))
+            }
+        };
+        // the elements are returned in a column named after the column alias, the
+        // table alias or else the function
+        let element_name = match alias {
+            Some(TableAlias { name, columns }) => match columns.as_slice() {
+                [] => name.value.clone(),
+                [column] => column.value.clone(),
+                columns => {
+                    return Err(DataFusionError::Plan(format!(
+                        "unnest returns 1 column but {} names given as column alias",
+                        columns.len()
+                    )))
+                }
+            },
+            None => "unnest".to_string(),
+        };
+
+        let mut exprs = outer
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| Expr::Column(f.qualified_column()))
+            .collect::<Vec<_>>();
+        exprs.push(Expr::Column(column).alias(&element_name));
+        LogicalPlanBuilder::from(outer)
+            .project(exprs)?
+            .unnest_column(Column::from_name(element_name))?
+            .build()
+    }
+
     fn parse_cross_join(
         &self,
         left: LogicalPlan,
@@ -369,9 +722,22 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
                 // expression that didn't match equi-join pattern
                 let mut filter = vec![];
+                // keys compared with `IS NOT DISTINCT FROM`
+                let mut null_safe_keys = vec![];
 
                 // extract join keys
-                extract_join_keys(&expr, &mut keys, &mut filter);
+                extract_join_keys(&expr, &mut keys, &mut null_safe_keys, &mut filter);
+
+                // null-safe keys can only be used as join keys when they are not
+                // mixed with regular equality keys, otherwise they are filtered
+                let null_equals_null = keys.is_empty() && !null_safe_keys.is_empty();
+                if null_equals_null {
+                    keys = null_safe_keys;
+                } else {
+                    filter.extend(null_safe_keys.into_iter().map(|(l, r)| {
+                        Expr::Column(l).is_not_distinct_from(Expr::Column(r))
+                    }));
+                }
 
                 let mut cols = HashSet::new();
                 exprlist_to_columns(&filter, &mut cols)?;
@@ -381,17 +747,19 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
                 // return the logical plan representing the join
                 if filter.is_empty() {
-                    let join = LogicalPlanBuilder::from(left).join(
+                    let join = LogicalPlanBuilder::from(left).join_detailed(
                         &right,
                         join_type,
                         (left_keys, right_keys),
+                        null_equals_null,
                     )?;
                     join.build()
                 } else if join_type == JoinType::Inner {
-                    let join = LogicalPlanBuilder::from(left).join(
+                    let join = LogicalPlanBuilder::from(left).join_detailed(
                         &right,
                         join_type,
                         (left_keys, right_keys),
+                        null_equals_null,
                     )?;
                     join.filter(
                         filter
@@ -418,7 +786,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                     )
                 {
                     LogicalPlanBuilder::from(left)
-                        .join(
+                        .join_detailed(
                             &LogicalPlanBuilder::from(right)
                                 .filter(
                                     filter
@@ -431,6 +799,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                                 .build()?,
                             join_type,
                             (left_keys, right_keys),
+                            null_equals_null,
                         )?
                         .build()
                 }
@@ -456,7 +825,12 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                                 .skip(1)
                                 .fold(filter[0].clone(), |acc, e| acc.and(e.clone())),
                         )?
-                        .join(&right, join_type, (left_keys, right_keys))?
+                        .join_detailed(
+                            &right,
+                            join_type,
+                            (left_keys, right_keys),
+                            null_equals_null,
+                        )?
                         .build()
                 } else {
                     Err(DataFusionError::NotImplemented(format!(
@@ -475,10 +849,35 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                     .build()
             }
             JoinConstraint::Natural => {
-                // https://issues.apache.org/jira/browse/ARROW-10727
-                Err(DataFusionError::NotImplemented(
-                    "NATURAL JOIN is not supported (https://issues.apache.org/jira/browse/ARROW-10727)".to_string(),
-                ))
+                // the columns that both sides have, in the order of the left side
+                let right_names = right
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name())
+                    .collect::<HashSet<_>>();
+                let mut names = HashSet::new();
+                let keys = left
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name())
+                    .filter(|name| right_names.contains(name) && names.insert(*name))
+                    .map(|name| Column::from_name(name.clone()))
+                    .collect::<Vec<_>>();
+                if !keys.is_empty() {
+                    LogicalPlanBuilder::from(left)
+                        .join_using(&right, join_type, keys)?
+                        .build()
+                } else if join_type == JoinType::Inner {
+                    // without common columns, every pair of rows matches
+                    self.parse_cross_join(left, &right)
+                } else {
+                    Err(DataFusionError::NotImplemented(format!(
+                        "Unsupported {:?} NATURAL JOIN of relations without common columns",
+                        join_type
+                    )))
+                }
             }
             JoinConstraint::None => Err(DataFusionError::NotImplemented(
                 "NONE constraint is not supported".to_string(),
@@ -499,7 +898,8 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 (
                     match (
                         cte,
-                        self.schema_provider.get_table_provider(name.try_into()?),
+                        self.schema_provider
+                            .try_get_table_provider(name.try_into()?)?,
                     ) {
                         (Some(cte_plan), _) => Ok(cte_plan.clone()),
                         (_, Some(provider)) => LogicalPlanBuilder::scan(
@@ -568,25 +968,15 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
         }
     }
 
-    /// Generate a logic plan from an SQL select
-    fn select_to_plan(
+    /// Join the plans of the FROM items, using the `<column> = <column>` predicates
+    /// of the filter as join keys where possible, and apply the rest of the filter
+    fn plan_selection(
         &self,
-        select: &Select,
-        ctes: &mut HashMap<String, LogicalPlan>,
+        plans: &[LogicalPlan],
+        filter_expr: Option<Expr>,
     ) -> Result<LogicalPlan> {
-        let plans = self.plan_from_tables(&select.from, ctes)?;
-
-        let plan = match &select.selection {
-            Some(predicate_expr) => {
-                // build join schema
-                let mut fields = vec![];
-                for plan in &plans {
-                    fields.extend_from_slice(plan.schema().fields());
-                }
-                let join_schema = DFSchema::new(fields)?;
-
-                let filter_expr = self.sql_to_rex(predicate_expr, &join_schema)?;
-
+        match filter_expr {
+            Some(filter_expr) => {
                 // look for expressions of the form `<column> = <column>`
                 let mut possible_join_keys = vec![];
                 extract_possible_join_keys(&filter_expr, &mut possible_join_keys)?;
@@ -645,11 +1035,99 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                     Ok(left)
                 }
             }
+        }
+    }
+
+    /// Joins `plan` with the uncorrelated subqueries of `[NOT] IN (<subquery>)` predicates:
+    /// a semi join for `IN`, and a null aware anti join for `NOT IN`, so that NULLs on
+    /// either side give the same results as Postgres
+    fn plan_in_subqueries(
+        &self,
+        plan: LogicalPlan,
+        in_subqueries: &[(&SQLExpr, &Query, bool)],
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        let mut plan = plan;
+        for (expr, subquery, negated) in in_subqueries {
+            let subquery_plan =
+                self.query_to_plan_with_alias(subquery, None, &mut ctes.clone())?;
+            let subquery_schema = subquery_plan.schema();
+            if subquery_schema.fields().len() != 1 {
+                return Err(DataFusionError::Plan(format!(
+                    "IN subquery must return exactly one column, found {}",
+                    subquery_schema.fields().len()
+                )));
+            }
+            let right_key = subquery_schema.field(0).qualified_column();
+            let left_key = match self.sql_to_rex(expr, plan.schema())? {
+                Expr::Column(column) => column,
+                other => {
+                    return Err(DataFusionError::NotImplemented(format!(
+                        "Only columns are supported on the left side of IN subqueries, found {:?}",
+                        other
+                    )))
+                }
+            };
+            let join_type = if *negated {
+                JoinType::NullAwareAnti
+            } else {
+                JoinType::Semi
+            };
+            plan = LogicalPlanBuilder::from(plan)
+                .join(&subquery_plan, join_type, (vec![left_key], vec![right_key]))?
+                .build()?;
+        }
+        Ok(plan)
+    }
+
+    /// Generate a logic plan from an SQL select
+    fn select_to_plan(
+        &self,
+        select: &Select,
+        ctes: &mut HashMap<String, LogicalPlan>,
+    ) -> Result<LogicalPlan> {
+        let plans = self.plan_from_tables(&select.from, ctes)?;
+
+        // the `[NOT] IN (<subquery>)` conjuncts are planned as joins
+        let mut in_subqueries = vec![];
+        let selection = select
+            .selection
+            .as_ref()
+            .and_then(|predicate| split_in_subqueries(predicate, &mut in_subqueries));
+
+        let filter_expr = match &selection {
+            Some(predicate_expr) => {
+                // build join schema
+                let mut fields = vec![];
+                for plan in &plans {
+                    fields.extend_from_slice(plan.schema().fields());
+                }
+                let join_schema = DFSchema::new(fields)?;
+
+                Some(self.sql_to_rex(predicate_expr, &join_schema)?)
+            }
+            None => None,
         };
-        let plan = plan?;
+        let plan = self.plan_selection(&plans, filter_expr)?;
+        let plan = self.plan_in_subqueries(plan, &in_subqueries, ctes)?;
+
+        // The parser rewrites the QUALIFY clause into a select item
+        let mut qualify_opt = None;
+        let mut projection = vec![];
+        for item in &select.projection {
+            match qualify_predicate(item)? {
+                Some(predicate) if qualify_opt.is_none() => qualify_opt = Some(predicate),
+                Some(_) => {
+                    return Err(DataFusionError::Plan(
+                        "A query can only have one QUALIFY clause".to_string(),
+                    ))
+                }
+                None => projection.push(item.clone()),
+            }
+        }
 
         // The SELECT expressions, with wildcards expanded.
-        let select_exprs = self.prepare_select_exprs(&plan, &select.projection)?;
+        let select_exprs = self.prepare_select_exprs(&plan, &projection)?;
 
         // having and group by clause may reference aliases defined in select projection
         let projected_plan = self.project(plan.clone(), select_exprs.clone())?;
@@ -686,27 +1164,73 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             })
             .transpose()?;
 
+        // Optionally the QUALIFY expression, which may refer to aliases like HAVING
+        let qualify_expr_opt = qualify_opt
+            .map::<Result<Expr>, _>(|qualify_expr| {
+                let qualify_expr =
+                    self.sql_expr_to_logical_expr(qualify_expr, &combined_schema)?;
+                let qualify_expr = resolve_aliases_to_exprs(&qualify_expr, &alias_map)?;
+                normalize_col(qualify_expr, &projected_plan)
+            })
+            .transpose()?;
+
         // The outer expressions we will search through for
         // aggregates. Aggregates may be sourced from the SELECT...
         let mut aggr_expr_haystack = select_exprs.clone();
-        // ... or from the HAVING.
+        // ... or from the HAVING ...
         if let Some(having_expr) = &having_expr_opt {
             aggr_expr_haystack.push(having_expr.clone());
         }
+        // ... or from the QUALIFY.
+        if let Some(qualify_expr) = &qualify_expr_opt {
+            aggr_expr_haystack.push(qualify_expr.clone());
+        }
 
         // All of the aggregate expressions (deduplicated).
         let aggr_exprs = find_aggregate_exprs(&aggr_expr_haystack);
 
-        let group_by_exprs = select
+        // A name in GROUP BY refers to a column of the FROM clause if there is one, and
+        // only otherwise to an alias of the SELECT list, as in the SQL standard
+        let group_by_alias_map = alias_map
+            .iter()
+            .filter(|(alias, _)| {
+                !plan.schema().fields().iter().any(|f| f.name() == *alias)
+            })
+            .map(|(alias, expr)| (alias.clone(), expr.clone()))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let group_by_all = select.group_by.iter().any(is_by_all);
+        if group_by_all && select.group_by.len() > 1 {
+            return Err(DataFusionError::Plan(
+                "GROUP BY ALL cannot be combined with other grouping expressions"
+                    .to_string(),
+            ));
+        }
+
+        let mut group_by_exprs = select
             .group_by
             .iter()
+            .filter(|e| !is_by_all(e))
             .map(|e| {
                 let group_by_expr = self.sql_expr_to_logical_expr(e, &combined_schema)?;
-                let group_by_expr = resolve_aliases_to_exprs(&group_by_expr, &alias_map)?;
+                let group_by_expr =
+                    resolve_aliases_to_exprs(&group_by_expr, &group_by_alias_map)?;
                 let group_by_expr =
                     resolve_positions_to_exprs(&group_by_expr, &select_exprs)
                         .unwrap_or(group_by_expr);
-                let group_by_expr = normalize_col(group_by_expr, &projected_plan)?;
+                // the remaining names are columns of the FROM clause, even those that
+                // an alias of the SELECT list shadows
+                let group_by_expr = normalize_col(group_by_expr, &plan)?;
+                // an ordinal or an alias may refer to an aggregate of the SELECT list
+                let group_by_expr_list = [group_by_expr.clone()];
+                if !find_aggregate_exprs(&group_by_expr_list).is_empty()
+                    || !find_window_exprs(&group_by_expr_list).is_empty()
+                {
+                    return Err(DataFusionError::Plan(format!(
+                        "Cannot GROUP BY an aggregate or window expression: {:?}",
+                        group_by_expr
+                    )));
+                }
                 self.validate_schema_satisfies_exprs(
                     plan.schema(),
                     &[group_by_expr.clone()],
@@ -714,38 +1238,59 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 Ok(group_by_expr)
             })
             .collect::<Result<Vec<Expr>>>()?;
+        if group_by_all {
+            // GROUP BY ALL groups by the SELECT expressions without aggregates
+            group_by_exprs = select_exprs
+                .iter()
+                .filter(|expr| {
+                    let expr_list = [(*expr).clone()];
+                    find_aggregate_exprs(&expr_list).is_empty()
+                        && find_window_exprs(&expr_list).is_empty()
+                })
+                .map(|expr| match expr {
+                    Expr::Alias(nested_expr, _alias_name) => *nested_expr.clone(),
+                    _ => expr.clone(),
+                })
+                .collect();
+        }
 
-        let (plan, select_exprs_post_aggr, having_expr_post_aggr_opt) = if !group_by_exprs
-            .is_empty()
-            || !aggr_exprs.is_empty()
-        {
-            self.aggregate(
-                plan,
-                &select_exprs,
-                &having_expr_opt,
-                group_by_exprs,
-                aggr_exprs,
-            )?
-        } else {
-            if let Some(having_expr) = &having_expr_opt {
-                let available_columns = select_exprs
-                    .iter()
-                    .map(|expr| expr_as_column_expr(expr, &plan))
-                    .collect::<Result<Vec<Expr>>>()?;
+        // The QUALIFY expression is rebased on the aggregation like the SELECT ones
+        let mut select_exprs = select_exprs;
+        if let Some(qualify_expr) = &qualify_expr_opt {
+            select_exprs.push(qualify_expr.clone());
+        }
 
-                // Ensure the HAVING expression is using only columns
-                // provided by the SELECT.
-                if !can_columns_satisfy_exprs(&available_columns, &[having_expr.clone()])?
-                {
-                    return Err(DataFusionError::Plan(
-                        "Having references column(s) not provided by the select"
-                            .to_owned(),
-                    ));
+        let (plan, mut select_exprs_post_aggr, having_expr_post_aggr_opt) =
+            if !group_by_exprs.is_empty() || !aggr_exprs.is_empty() {
+                self.aggregate(
+                    plan,
+                    &select_exprs,
+                    &having_expr_opt,
+                    group_by_exprs,
+                    aggr_exprs,
+                )?
+            } else {
+                if let Some(having_expr) = &having_expr_opt {
+                    let available_columns = select_exprs
+                        .iter()
+                        .map(|expr| expr_as_column_expr(expr, &plan))
+                        .collect::<Result<Vec<Expr>>>()?;
+
+                    // Ensure the HAVING expression is using only columns
+                    // provided by the SELECT.
+                    if !can_columns_satisfy_exprs(
+                        &available_columns,
+                        &[having_expr.clone()],
+                    )? {
+                        return Err(DataFusionError::Plan(
+                            "Having references column(s) not provided by the select"
+                                .to_owned(),
+                        ));
+                    }
                 }
-            }
 
-            (plan, select_exprs, having_expr_opt)
-        };
+                (plan, select_exprs, having_expr_opt)
+            };
 
         let plan = if let Some(having_expr_post_aggr) = having_expr_post_aggr_opt {
             LogicalPlanBuilder::from(plan)
@@ -755,13 +1300,33 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             plan
         };
 
+        let qualify_expr_post_aggr_opt = if qualify_expr_opt.is_some() {
+            select_exprs_post_aggr.pop()
+        } else {
+            None
+        };
+
         // window function
-        let window_func_exprs = find_window_exprs(&select_exprs_post_aggr);
+        let mut window_expr_haystack = select_exprs_post_aggr.clone();
+        if let Some(qualify_expr) = &qualify_expr_post_aggr_opt {
+            window_expr_haystack.push(qualify_expr.clone());
+        }
+        let window_func_exprs = find_window_exprs(&window_expr_haystack);
 
-        let plan = if window_func_exprs.is_empty() {
-            plan
+        let window_plan = if window_func_exprs.is_empty() {
+            plan.clone()
         } else {
-            self.window(plan, window_func_exprs)?
+            self.window(plan.clone(), window_func_exprs.clone())?
+        };
+
+        // QUALIFY filters on the results of the window functions
+        let plan = if let Some(qualify_expr) = qualify_expr_post_aggr_opt {
+            let qualify_expr = rebase_expr(&qualify_expr, &window_func_exprs, &plan)?;
+            LogicalPlanBuilder::from(window_plan)
+                .filter(qualify_expr)?
+                .build()?
+        } else {
+            window_plan
         };
 
         let plan = if select.distinct {
@@ -777,7 +1342,8 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
     /// Returns the `Expr`'s corresponding to a SQL query's SELECT expressions.
     ///
-    /// Wildcards are expanded into the concrete list of columns.
+    /// Wildcards, including the ones excluding some columns, are expanded into the
+    /// concrete list of columns.
     fn prepare_select_exprs(
         &self,
         plan: &LogicalPlan,
@@ -787,14 +1353,35 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
         projection
             .iter()
-            .map(|expr| self.sql_select_to_rex(expr, input_schema))
-            .collect::<Result<Vec<Expr>>>()?
-            .into_iter()
-            .map(|expr| {
-                Ok(match expr {
+            .map(|item| match item {
+                SelectItem::UnnamedExpr(SQLExpr::Function(function))
+                    if function.name.to_string() == WILDCARD_EXCEPT =>
+                {
+                    let excluded = function
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            FunctionArg::Unnamed(arg) => {
+                                match self.sql_to_rex(arg, input_schema)? {
+                                    Expr::Column(c) => Ok(c),
+                                    expr => Err(DataFusionError::Plan(format!(
+                                        "Only columns can be excluded from a wildcard, found {:?}",
+                                        expr
+                                    ))),
+                                }
+                            }
+                            FunctionArg::Named { .. } => Err(DataFusionError::Plan(
+                                "Only columns can be excluded from a wildcard"
+                                    .to_string(),
+                            )),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    expand_wildcard_except(input_schema, plan, &excluded)
+                }
+                _ => Ok(match self.sql_select_to_rex(item, input_schema)? {
                     Expr::Wildcard => expand_wildcard(input_schema, plan)?,
-                    _ => vec![normalize_col(expr, plan)?],
-                })
+                    expr => vec![normalize_col(expr, plan)?],
+                }),
             })
             .flat_map(|res| match res {
                 Ok(v) => v.into_iter().map(Ok).collect(),
@@ -894,7 +1481,23 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
     }
 
     /// Wrap a plan in a limit
-    fn limit(&self, input: LogicalPlan, limit: &Option<SQLExpr>) -> Result<LogicalPlan> {
+    fn limit(
+        &self,
+        input: LogicalPlan,
+        limit: &Option<SQLExpr>,
+        offset: &Option<Offset>,
+    ) -> Result<LogicalPlan> {
+        let offset = match offset {
+            Some(offset) => match self.sql_to_rex(&offset.value, input.schema())? {
+                Expr::Literal(ScalarValue::Int64(Some(n))) if n >= 0 => n as usize,
+                _ => {
+                    return Err(DataFusionError::Plan(
+                        "Unexpected expression for OFFSET clause".to_string(),
+                    ))
+                }
+            },
+            None => 0,
+        };
         match *limit {
             Some(ref limit_expr) => {
                 let n = match self.sql_to_rex(limit_expr, input.schema())? {
@@ -904,9 +1507,14 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                     )),
                 }?;
 
-                LogicalPlanBuilder::from(input).limit(n)?.build()
+                LogicalPlanBuilder::from(input)
+                    .limit_with_offset(n, offset)?
+                    .build()
             }
-            _ => Ok(input),
+            None if offset > 0 => Err(DataFusionError::NotImplemented(
+                "OFFSET without LIMIT is not supported".to_string(),
+            )),
+            None => Ok(input),
         }
     }
 
@@ -920,10 +1528,33 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             return Ok(plan);
         }
 
-        let order_by_rex = order_by
-            .iter()
-            .map(|e| self.order_by_to_sort_expr(e, plan.schema()))
-            .collect::<Result<Vec<_>>>()?;
+        let order_by_rex = match order_by {
+            // ORDER BY ALL sorts by every column of the result, from left to right
+            [OrderByExpr {
+                expr,
+                asc,
+                nulls_first,
+            }] if is_by_all(expr) => plan
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| Expr::Sort {
+                    expr: Box::new(Expr::Column(field.qualified_column())),
+                    asc: asc.unwrap_or(true),
+                    nulls_first: nulls_first.unwrap_or(true),
+                })
+                .collect(),
+            _ if order_by.iter().any(|e| is_by_all(&e.expr)) => {
+                return Err(DataFusionError::Plan(
+                    "ORDER BY ALL cannot be combined with other sort expressions"
+                        .to_string(),
+                ))
+            }
+            _ => order_by
+                .iter()
+                .map(|e| self.order_by_to_sort_expr(e, plan.schema()))
+                .collect::<Result<Vec<_>>>()?,
+        };
 
         LogicalPlanBuilder::from(plan).sort(order_by_rex)?.build()
     }
@@ -1047,6 +1678,10 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             },
             SQLExpr::Value(Value::SingleQuotedString(ref s)) => Ok(lit(s.clone())),
 
+            SQLExpr::Value(Value::HexStringLiteral(ref s)) => {
+                Ok(lit(parse_hex_literal(s)?))
+            }
+
             SQLExpr::Value(Value::Boolean(n)) => Ok(lit(*n)),
 
             SQLExpr::Value(Value::Null) => Ok(Expr::Literal(ScalarValue::Utf8(None))),
@@ -1280,6 +1915,127 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                     }
                 };
 
+                // the parser only rewrites the ORDER BY clause of the arguments of
+                // ordered aggregates, which are planned by `aggregate_fn_to_expr`
+                if name == ORDER_BY {
+                    return Err(DataFusionError::Plan(
+                        "ORDER BY is only supported in the arguments of ARRAY_AGG, \
+                         FIRST_VALUE, LAST_VALUE and STRING_AGG"
+                            .to_string(),
+                    ));
+                }
+
+                // the parser only rewrites the ALL of GROUP BY ALL and ORDER BY ALL,
+                // which are expanded into the columns they stand for
+                if name == BY_ALL {
+                    return Err(DataFusionError::Plan(
+                        "ALL is only supported as the whole GROUP BY or ORDER BY clause \
+                         of a query"
+                            .to_string(),
+                    ));
+                }
+
+                // the parser only rewrites the EXCLUDE clause of window frames, which
+                // is planned with the window function
+                if name == WINDOW_EXCLUDE {
+                    return Err(DataFusionError::Plan(
+                        "EXCLUDE is only supported in window frames".to_string(),
+                    ));
+                }
+
+                // the parser rewrites the FILTER clause of aggregates into a trailing
+                // argument, which is planned with the aggregate below
+                let (function, filter) = split_aggregate_filter(function)?;
+                let function = &function;
+                let filter = match filter {
+                    Some(_) if function.over.is_some() => {
+                        return Err(DataFusionError::NotImplemented(
+                            "FILTER is not supported by window functions".to_string(),
+                        ))
+                    }
+                    Some(filter)
+                        if aggregates::AggregateFunction::from_str(&name).is_ok()
+                            || self
+                                .schema_provider
+                                .get_aggregate_meta(&name)
+                                .is_some() =>
+                    {
+                        Some(Box::new(self.sql_expr_to_logical_expr(&filter, schema)?))
+                    }
+                    Some(_) => {
+                        return Err(DataFusionError::Plan(format!(
+                            "FILTER is only supported by aggregate functions, not {}",
+                            name
+                        )))
+                    }
+                    None => None,
+                };
+
+                // the parser rewrites IS [NOT] DISTINCT FROM into a function call
+                if name == IS_DISTINCT_FROM || name == IS_NOT_DISTINCT_FROM {
+                    let op = if name == IS_DISTINCT_FROM {
+                        Operator::IsDistinctFrom
+                    } else {
+                        Operator::IsNotDistinctFrom
+                    };
+                    let mut args = self.function_args_to_expr(function, schema)?;
+                    if args.len() != 2 {
+                        return Err(DataFusionError::Internal(format!(
+                            "{} expects two operands",
+                            op
+                        )));
+                    }
+                    let right = args.pop().unwrap();
+                    let left = args.pop().unwrap();
+                    return Ok(Expr::BinaryExpr {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                    });
+                }
+
+                // the parser rewrites ILIKE, SIMILAR TO and the ESCAPE clause of the
+                // pattern matching operators into function calls
+                let pattern_match_op = match name.as_str() {
+                    LIKE => Some(Operator::Like),
+                    NOT_LIKE => Some(Operator::NotLike),
+                    ILIKE => Some(Operator::ILike),
+                    NOT_ILIKE => Some(Operator::NotILike),
+                    SIMILAR_TO => Some(Operator::SimilarTo),
+                    NOT_SIMILAR_TO => Some(Operator::NotSimilarTo),
+                    _ => None,
+                };
+                if let Some(op) = pattern_match_op {
+                    let mut args = self.function_args_to_expr(function, schema)?;
+                    let escape = match args.len() {
+                        2 => None,
+                        3 => args.pop(),
+                        _ => {
+                            return Err(DataFusionError::Internal(format!(
+                            "{} expects two operands and an optional escape character",
+                            op
+                        )))
+                        }
+                    };
+                    let pattern = args.pop().unwrap();
+                    let expr = args.pop().unwrap();
+                    let pattern = match escape {
+                        Some(escape) => escape_literal_pattern(pattern, escape)?,
+                        None => pattern,
+                    };
+                    return Ok(Expr::BinaryExpr {
+                        left: Box::new(expr),
+                        op,
+                        right: Box::new(pattern),
+                    });
+                }
+
+                // casts to a type given by its name
+                if name == ARROW_CAST || name == ARROW_TRY_CAST {
+                    let args = self.function_args_to_expr(function, schema)?;
+                    return create_arrow_cast(&name, args);
+                }
+
                 // first, scalar built-in
                 if let Ok(fun) = functions::BuiltinScalarFunction::from_str(&name) {
                     let args = self.function_args_to_expr(function, schema)?;
@@ -1289,6 +2045,8 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
                 // then, window function
                 if let Some(window) = &function.over {
+                    let (function, exclude) = split_window_exclusion(function)?;
+                    let function = &function;
                     let partition_by = window
                         .partition_by
                         .iter()
@@ -1315,7 +2073,32 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
 
                         })
                         .transpose()?;
-                    let fun = window_functions::WindowFunction::from_str(&name)?;
+                    let window_frame = match exclude {
+                        Some(exclude) => Some(WindowFrame {
+                            exclude,
+                            ..window_frame.unwrap_or_default()
+                        }),
+                        None => window_frame,
+                    };
+                    // built-in window functions and aggregates, then UDWFs
+                    let fun = match window_functions::WindowFunction::from_str(&name) {
+                        Ok(fun) => fun,
+                        Err(e) => match self.schema_provider.get_window_meta(&name) {
+                            Some(fm) => window_functions::WindowFunction::WindowUDF(fm),
+                            None => return Err(e),
+                        },
+                    };
+                    if function.distinct
+                        && !matches!(
+                            fun,
+                            window_functions::WindowFunction::AggregateFunction(_)
+                        )
+                    {
+                        return Err(DataFusionError::Plan(format!(
+                            "DISTINCT is only supported by aggregate window functions, not {}",
+                            fun
+                        )));
+                    }
                     match fun {
                         window_functions::WindowFunction::AggregateFunction(
                             aggregate_fun,
@@ -1332,6 +2115,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                                 partition_by,
                                 order_by,
                                 window_frame,
+                                distinct: function.distinct,
                             });
                         }
                         window_functions::WindowFunction::BuiltInWindowFunction(
@@ -1345,6 +2129,17 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                                 partition_by,
                                 order_by,
                                 window_frame,
+                                distinct: false,
+                            });
+                        }
+                        window_functions::WindowFunction::WindowUDF(_) => {
+                            return Ok(Expr::WindowFunction {
+                                fun,
+                                args: self.function_args_to_expr(function, schema)?,
+                                partition_by,
+                                order_by,
+                                window_frame,
+                                distinct: false,
                             });
                         }
                     }
@@ -1357,26 +2152,34 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                         fun,
                         distinct: function.distinct,
                         args,
+                        filter,
                     });
                 };
 
-                // finally, user-defined functions (UDF) and UDAF
-                match self.schema_provider.get_function_meta(&name) {
-                    Some(fm) => {
-                        let args = self.function_args_to_expr(function, schema)?;
+                // then, user-defined functions (UDF) and UDAF
+                if let Some(fm) = self.schema_provider.get_function_meta(&name) {
+                    let args = self.function_args_to_expr(function, schema)?;
+                    return Ok(Expr::ScalarUDF { fun: fm, args });
+                }
+                if let Some(fm) = self.schema_provider.get_aggregate_meta(&name) {
+                    let args = self.function_args_to_expr(function, schema)?;
+                    return Ok(Expr::AggregateUDF {
+                        fun: fm,
+                        args,
+                        filter,
+                    });
+                }
 
-                        Ok(Expr::ScalarUDF { fun: fm, args })
+                // finally, SQL macros, which are replaced by their body
+                match self.schema_provider.get_macro(&name) {
+                    Some(sql_macro) => {
+                        let args = self.function_args_to_expr(function, schema)?;
+                        sql_macro.expand(args)
                     }
-                    None => match self.schema_provider.get_aggregate_meta(&name) {
-                        Some(fm) => {
-                            let args = self.function_args_to_expr(function, schema)?;
-                            Ok(Expr::AggregateUDF { fun: fm, args })
-                        }
-                        _ => Err(DataFusionError::Plan(format!(
-                            "Invalid function '{}'",
-                            name
-                        ))),
-                    },
+                    None => Err(DataFusionError::Plan(format!(
+                        "Invalid function '{}'",
+                        name
+                    ))),
                 }
             }
 
@@ -1420,7 +2223,46 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 })
                 .collect::<Result<Vec<Expr>>>()
         } else {
-            self.function_args_to_expr(function, schema)
+            function
+                .args
+                .iter()
+                .map(|a| match a {
+                    // the parser rewrites the ORDER BY clause into trailing arguments
+                    FunctionArg::Unnamed(SQLExpr::Function(f))
+                        if f.name.to_string() == ORDER_BY =>
+                    {
+                        self.order_by_arg_to_sort_expr(f, schema)
+                    }
+                    _ => self.sql_fn_arg_to_logical_expr(a, schema),
+                })
+                .collect::<Result<Vec<Expr>>>()
+        }
+    }
+
+    /// Plans an `__order_by(<expr>, <asc>, <nulls_first>)` argument of an ordered
+    /// aggregate as the sort expression it was rewritten from
+    fn order_by_arg_to_sort_expr(
+        &self,
+        function: &sqlparser::ast::Function,
+        schema: &DFSchema,
+    ) -> Result<Expr> {
+        let option = |arg: &FunctionArg| match arg {
+            FunctionArg::Unnamed(SQLExpr::Value(Value::Boolean(value))) => Ok(*value),
+            _ => Err(DataFusionError::Internal(format!(
+                "Unexpected argument of {}: {}",
+                ORDER_BY, arg
+            ))),
+        };
+        match function.args.as_slice() {
+            [expr, asc, nulls_first] => Ok(Expr::Sort {
+                expr: Box::new(self.sql_fn_arg_to_logical_expr(expr, schema)?),
+                asc: option(asc)?,
+                nulls_first: option(nulls_first)?,
+            }),
+            _ => Err(DataFusionError::Internal(format!(
+                "{} expects three arguments",
+                ORDER_BY
+            ))),
         }
     }
 
@@ -1634,20 +2476,148 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
             ));
         }
 
+        let where_clause = self.columns_filter(table_name, "SHOW COLUMNS")?;
+
+        // treat both FULL and EXTENDED as the same
+        let select_list = if full || extended {
+            "*"
+        } else {
+            "table_catalog, table_schema, table_name, column_name, data_type, is_nullable"
+        };
+
+        let query = format!(
+            "SELECT {} FROM information_schema.columns WHERE {}",
+            select_list, where_clause
+        );
+
+        let rewrite = DFParser::parse_sql(&query)?;
+        self.statement_to_plan(&rewrite[0])
+    }
+
+    /// Generate a logical plan from a CREATE FUNCTION statement
+    pub fn create_function_to_plan(
+        &self,
+        statement: &CreateFunction,
+    ) -> Result<LogicalPlan> {
+        let CreateFunction {
+            name,
+            params,
+            body,
+            or_replace,
+        } = statement;
+        // function calls are looked up in lowercase unless quoted
+        let name = match name.quote_style {
+            Some(_) => name.value.clone(),
+            None => name.value.to_ascii_lowercase(),
+        };
+        if functions::BuiltinScalarFunction::from_str(&name).is_ok()
+            || aggregates::AggregateFunction::from_str(&name).is_ok()
+        {
+            return Err(DataFusionError::Plan(format!(
+                "Function {} is a built-in function and can not be redefined",
+                name
+            )));
+        }
+        if name.starts_with(RESERVED_FUNCTION_PREFIX) {
+            return Err(DataFusionError::Plan(format!(
+                "Function {} can not be created, names starting with {} are reserved",
+                name, RESERVED_FUNCTION_PREFIX
+            )));
+        }
+        let params = params.iter().map(|param| param.value.clone()).collect();
+        // the parameters are not columns of a relation, so the body is not validated
+        // against a schema
+        let body = self.sql_expr_to_logical_expr(body, &DFSchema::empty())?;
+        let sql_macro = SqlMacro::try_new(name, params, body)?;
+
+        Ok(LogicalPlan::CreateFunction {
+            name: sql_macro.name().to_owned(),
+            params: sql_macro.params().to_vec(),
+            body: sql_macro.body().clone(),
+            or_replace: *or_replace,
+            schema: Arc::new(DFSchema::empty()),
+        })
+    }
+
+    /// Generate a logical plan from a COPY ... TO statement
+    pub fn copy_to_plan(&self, statement: &CopyTo) -> Result<LogicalPlan> {
+        let CopyTo {
+            source,
+            path,
+            file_type,
+            options,
+        } = statement;
+        if *file_type != FileType::Parquet {
+            return Err(DataFusionError::NotImplemented(format!(
+                "COPY TO only supports Parquet files, found {:?}",
+                file_type
+            )));
+        }
+        let input = match source {
+            CopySource::Table(name) => {
+                match self
+                    .schema_provider
+                    .try_get_table_provider(name.try_into()?)?
+                {
+                    Some(provider) => {
+                        LogicalPlanBuilder::scan(&name.to_string(), provider, None)?
+                            .build()?
+                    }
+                    None => {
+                        return Err(DataFusionError::Plan(format!(
+                            "Table with name '{}' not found",
+                            name
+                        )))
+                    }
+                }
+            }
+            CopySource::Query(query) => self.query_to_plan(query)?,
+        };
+        let options = ParquetWriteOptions::from_options(options)?;
+        let schema = ParquetWriterExec::count_schema().to_dfschema_ref()?;
+        Ok(LogicalPlan::CopyTo {
+            input: Arc::new(input),
+            path: path.clone(),
+            options,
+            schema,
+        })
+    }
+
+    fn describe_table_to_plan(&self, table_name: &ObjectName) -> Result<LogicalPlan> {
+        if !self.has_table("information_schema", "columns") {
+            return Err(DataFusionError::Plan(
+                "DESCRIBE is not supported unless information_schema is enabled"
+                    .to_string(),
+            ));
+        }
+
+        let where_clause = self.columns_filter(table_name, "DESCRIBE")?;
+
+        let query = format!(
+            "SELECT column_name, data_type, is_nullable FROM information_schema.columns WHERE {}",
+            where_clause
+        );
+
+        let rewrite = DFParser::parse_sql(&query)?;
+        self.statement_to_plan(&rewrite[0])
+    }
+
+    /// Returns the predicate on `information_schema.columns` that selects the columns
+    /// of `table_name`, erroring if the table does not exist
+    fn columns_filter(&self, table_name: &ObjectName, statement: &str) -> Result<String> {
         if self
             .schema_provider
             .get_table_provider(table_name.try_into()?)
             .is_none()
         {
             return Err(DataFusionError::Plan(format!(
-                "Unknown relation for SHOW COLUMNS: {}",
-                table_name
+                "Unknown relation for {}: {}",
+                statement, table_name
             )));
         }
 
-        // Figure out the where clause
         let columns = vec!["table_name", "table_schema", "table_catalog"].into_iter();
-        let where_clause = table_name
+        Ok(table_name
             .0
             .iter()
             .rev()
@@ -1656,22 +2626,7 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
                 format!(r#"{} = '{}'"#, column_name, ident.to_string())
             })
             .collect::<Vec<_>>()
-            .join(" AND ");
-
-        // treat both FULL and EXTENDED as the same
-        let select_list = if full || extended {
-            "*"
-        } else {
-            "table_catalog, table_schema, table_name, column_name, data_type, is_nullable"
-        };
-
-        let query = format!(
-            "SELECT {} FROM information_schema.columns WHERE {}",
-            select_list, where_clause
-        );
-
-        let rewrite = DFParser::parse_sql(&query)?;
-        self.statement_to_plan(&rewrite[0])
+            .join(" AND "))
     }
 
     /// Return true if there is a table provider available for "schema.table"
@@ -1683,6 +2638,87 @@ impl<'a, S: ContextProvider> SqlToRel<'a, S> {
     }
 }
 
+/// Returns true if `relation` is a FROM item that may reference the preceding
+/// FROM items, i.e. a LATERAL subquery or a table function
+fn is_lateral(relation: &TableFactor) -> bool {
+    match relation {
+        TableFactor::Derived { lateral, .. } => *lateral,
+        TableFactor::Table { args, .. } => !args.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns true if `column` can be resolved against `schema`
+fn has_column(schema: &DFSchema, column: &Column) -> bool {
+    match &column.relation {
+        Some(r) => schema.field_with_qualified_name(r, &column.name).is_ok(),
+        None => !schema.fields_with_unqualified_name(&column.name).is_empty(),
+    }
+}
+
+/// Qualifies the columns of an expression of a LATERAL subquery, resolving them
+/// against the subquery's own FROM items (`inner`) before the preceding FROM items
+/// (`outer`). Returns true alongside the expression if it references `outer`.
+fn resolve_lateral_columns(
+    expr: Expr,
+    inner: &LogicalPlan,
+    outer: &LogicalPlan,
+) -> Result<(Expr, bool)> {
+    let mut columns = HashSet::new();
+    expr_to_columns(&expr, &mut columns)?;
+
+    let mut is_correlated = false;
+    let mut resolved = vec![];
+    for column in &columns {
+        if has_column(inner.schema(), column) {
+            resolved.push((column, column.clone().normalize(inner)?));
+        } else {
+            is_correlated = true;
+            resolved.push((column, column.clone().normalize(outer)?));
+        }
+    }
+    let replace_map: std::collections::HashMap<&Column, &Column> =
+        resolved.iter().map(|(c, r)| (*c, r)).collect();
+    Ok((replace_col(expr, &replace_map)?, is_correlated))
+}
+
+/// Moves the `[NOT] IN (<subquery>)` conjuncts of `predicate` to `in_subqueries`,
+/// returning the remaining conjuncts, if any
+fn split_in_subqueries<'a>(
+    predicate: &'a SQLExpr,
+    in_subqueries: &mut Vec<(&'a SQLExpr, &'a Query, bool)>,
+) -> Option<SQLExpr> {
+    match predicate {
+        SQLExpr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let left = split_in_subqueries(left, in_subqueries);
+            let right = split_in_subqueries(right, in_subqueries);
+            match (left, right) {
+                (Some(left), Some(right)) => Some(SQLExpr::BinaryOp {
+                    left: Box::new(left),
+                    op: BinaryOperator::And,
+                    right: Box::new(right),
+                }),
+                (left, None) => left,
+                (None, right) => right,
+            }
+        }
+        SQLExpr::InSubquery {
+            expr,
+            subquery,
+            negated,
+        } => {
+            in_subqueries.push((expr.as_ref(), subquery.as_ref(), *negated));
+            None
+        }
+        SQLExpr::Nested(inner) => split_in_subqueries(inner, in_subqueries),
+        _ => Some(predicate.clone()),
+    }
+}
+
 /// Remove join expressions from a filter expression
 fn remove_join_expressions(
     expr: &Expr,
@@ -1730,6 +2766,7 @@ fn remove_join_expressions(
 fn extract_join_keys(
     expr: &Expr,
     accum: &mut Vec<(Column, Column)>,
+    accum_null_safe: &mut Vec<(Column, Column)>,
     accum_filter: &mut Vec<Expr>,
 ) {
     match expr {
@@ -1742,9 +2779,17 @@ fn extract_join_keys(
                     accum_filter.push(expr.clone());
                 }
             },
+            Operator::IsNotDistinctFrom => match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(l), Expr::Column(r)) => {
+                    accum_null_safe.push((l.clone(), r.clone()));
+                }
+                _other => {
+                    accum_filter.push(expr.clone());
+                }
+            },
             Operator::And => {
-                extract_join_keys(left, accum, accum_filter);
-                extract_join_keys(right, accum, accum_filter);
+                extract_join_keys(left, accum, accum_null_safe, accum_filter);
+                extract_join_keys(right, accum, accum_null_safe, accum_filter);
             }
             _other
                 if matches!(**left, Expr::Column(_))
@@ -1753,8 +2798,8 @@ fn extract_join_keys(
                 accum_filter.push(expr.clone());
             }
             _other => {
-                extract_join_keys(left, accum, accum_filter);
-                extract_join_keys(right, accum, accum_filter);
+                extract_join_keys(left, accum, accum_null_safe, accum_filter);
+                extract_join_keys(right, accum, accum_null_safe, accum_filter);
             }
         },
         _other => {
@@ -1787,6 +2832,130 @@ fn extract_possible_join_keys(
     }
 }
 
+/// Whether `expr` is the function that the parser rewrites the `ALL` of `GROUP BY ALL`
+/// and `ORDER BY ALL` into
+fn is_by_all(expr: &SQLExpr) -> bool {
+    matches!(expr, SQLExpr::Function(function) if function.name.to_string() == BY_ALL)
+}
+
+/// Returns the predicate of the select item that the parser rewrites the QUALIFY
+/// clause into, or `None` if `item` is a regular select item
+fn qualify_predicate(item: &SelectItem) -> Result<Option<&SQLExpr>> {
+    match item {
+        SelectItem::UnnamedExpr(SQLExpr::Function(function))
+            if function.name.to_string() == QUALIFY =>
+        {
+            match function.args.as_slice() {
+                [FunctionArg::Unnamed(predicate)] => Ok(Some(predicate)),
+                _ => Err(DataFusionError::Plan(
+                    "QUALIFY requires a single predicate".to_string(),
+                )),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Removes the trailing argument that the parser rewrites the EXCLUDE clause of the
+/// window frame of `function` into, and returns the remaining function and the exclusion
+fn split_window_exclusion(
+    function: &sqlparser::ast::Function,
+) -> Result<(sqlparser::ast::Function, Option<WindowFrameExclusion>)> {
+    let mut function = function.clone();
+    let exclude = match function.args.last() {
+        Some(FunctionArg::Unnamed(SQLExpr::Function(f)))
+            if f.name.to_string() == WINDOW_EXCLUDE =>
+        {
+            match f.args.as_slice() {
+                [FunctionArg::Unnamed(SQLExpr::Value(Value::SingleQuotedString(
+                    exclude,
+                )))] => WindowFrameExclusion::from_str(exclude)?,
+                _ => {
+                    return Err(DataFusionError::Internal(format!(
+                        "{} expects the exclusion as a string",
+                        WINDOW_EXCLUDE
+                    )))
+                }
+            }
+        }
+        _ => return Ok((function, None)),
+    };
+    function.args.pop();
+    Ok((function, Some(exclude)))
+}
+
+/// Removes the trailing argument that the parser rewrites the FILTER clause of the
+/// aggregate `function` into, and returns the remaining function and the predicate
+fn split_aggregate_filter(
+    function: &sqlparser::ast::Function,
+) -> Result<(sqlparser::ast::Function, Option<SQLExpr>)> {
+    let mut function = function.clone();
+    let filter = match function.args.last() {
+        Some(FunctionArg::Unnamed(SQLExpr::Function(f)))
+            if f.name.to_string() == AGGREGATE_FILTER =>
+        {
+            match f.args.as_slice() {
+                [FunctionArg::Unnamed(predicate)] => predicate.clone(),
+                _ => {
+                    return Err(DataFusionError::Internal(format!(
+                        "{} expects a single predicate",
+                        AGGREGATE_FILTER
+                    )))
+                }
+            }
+        }
+        _ => return Ok((function, None)),
+    };
+    function.args.pop();
+    Ok((function, Some(filter)))
+}
+
+/// Translates the literal `pattern` of a pattern matching operator with the `ESCAPE`
+/// clause `escape` into the equivalent pattern with the default escape character. An
+/// empty escape character disables escaping.
+fn escape_literal_pattern(pattern: Expr, escape: Expr) -> Result<Expr> {
+    let escape = match &escape {
+        Expr::Literal(ScalarValue::Utf8(Some(escape))) if escape.chars().count() <= 1 => {
+            escape.chars().next()
+        }
+        _ => {
+            return Err(DataFusionError::Plan(format!(
+                "ESCAPE expects a single character literal, got {:?}",
+                escape
+            )))
+        }
+    };
+    match pattern {
+        Expr::Literal(ScalarValue::Utf8(Some(pattern))) => Ok(Expr::Literal(
+            ScalarValue::Utf8(Some(escape_pattern(&pattern, escape)?)),
+        )),
+        Expr::Literal(ScalarValue::Utf8(None)) => Ok(pattern),
+        _ => Err(DataFusionError::NotImplemented(
+            "ESCAPE is only supported for literal patterns".to_string(),
+        )),
+    }
+}
+
+/// Parse the digits of a hex string literal such as `X'1F'` into bytes
+fn parse_hex_literal(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return Err(DataFusionError::Plan(format!(
+            "Hex string literal X'{}' must have an even number of digits",
+            s
+        )));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| {
+                    DataFusionError::Plan(format!("Invalid hex string literal X'{}'", s))
+                })
+        })
+        .collect()
+}
+
 /// Convert SQL data type to relational representation of data type
 pub fn convert_data_type(sql: &SQLDataType) -> Result<DataType> {
     match sql {
@@ -1797,6 +2966,10 @@ pub fn convert_data_type(sql: &SQLDataType) -> Result<DataType> {
         SQLDataType::Float(_) | SQLDataType::Real => Ok(DataType::Float64),
         SQLDataType::Double => Ok(DataType::Float64),
         SQLDataType::Char(_) | SQLDataType::Varchar(_) => Ok(DataType::Utf8),
+        SQLDataType::Binary(_)
+        | SQLDataType::Varbinary(_)
+        | SQLDataType::Blob(_)
+        | SQLDataType::Bytea => Ok(DataType::Binary),
         SQLDataType::Timestamp => Ok(DataType::Timestamp(TimeUnit::Nanosecond, None)),
         SQLDataType::Date => Ok(DataType::Date32),
         other => Err(DataFusionError::NotImplemented(format!(
@@ -1822,6 +2995,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn select_hex_string_literal() {
+        quick_test(
+            "SELECT X'0A0b'",
+            "Projection: Binary(\"10,11\")\
+             \n  EmptyRelation",
+        );
+
+        let err = logical_plan("SELECT X'0A0'").expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Hex string literal X'0A0' must have an even number of digits\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn select_column_does_not_exist() {
         let sql = "SELECT doesnotexist FROM person";
@@ -1861,6 +3049,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn select_wildcard_except() {
+        let expected = "Projection: #person.id, #person.last_name, #person.age, #person.salary, #person.birth_date, #person.😀\
+            \n  TableScan: person projection=None";
+        quick_test("SELECT * EXCLUDE (first_name, state) FROM person", expected);
+        quick_test(
+            "SELECT * EXCEPT (person.first_name, state) FROM person",
+            expected,
+        );
+        quick_test(
+            "SELECT age + 1 AS a, * EXCLUDE (id, first_name, last_name, state, salary, birth_date, \"😀\") FROM person",
+            "Projection: #person.age Plus Int64(1) AS a, #person.age\
+            \n  TableScan: person projection=None",
+        );
+    }
+
+    #[test]
+    fn select_wildcard_except_unknown_column() {
+        let err = logical_plan("SELECT * EXCLUDE (doesnotexist) FROM person")
+            .expect_err("query should have failed");
+        assert!(
+            err.to_string().contains("doesnotexist"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
     #[test]
     fn select_scalar_func_with_literal_no_relation() {
         quick_test(
@@ -2382,6 +3597,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn select_filtered_aggregate() {
+        quick_test(
+            "SELECT COUNT(*) FILTER (WHERE age > 30), SUM(age) FROM person",
+            "Projection: #COUNT(UInt8(1)) FILTER (WHERE person.age Gt Int64(30)), #SUM(person.age)\
+            \n  Aggregate: groupBy=[[]], aggr=[[COUNT(UInt8(1)) FILTER (WHERE #person.age Gt Int64(30)), SUM(#person.age)]]\
+            \n    TableScan: person projection=None",
+        );
+        let err = logical_plan("SELECT ABS(age) FILTER (WHERE age > 30) FROM person")
+            .expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"FILTER is only supported by aggregate functions, not abs\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn select_simple_aggregate_column_does_not_exist() {
         let sql = "SELECT MIN(doesnotexist) FROM person";
@@ -2523,19 +3754,43 @@ mod tests {
     }
 
     #[test]
-    fn select_simple_aggregate_with_groupby_can_use_positions() {
+    fn select_simple_aggregate_with_groupby_can_use_positions() {
+        quick_test(
+            "SELECT state, age AS b, COUNT(1) FROM person GROUP BY 1, 2",
+            "Projection: #person.state, #person.age AS b, #COUNT(UInt8(1))\
+             \n  Aggregate: groupBy=[[#person.state, #person.age]], aggr=[[COUNT(UInt8(1))]]\
+             \n    TableScan: person projection=None",
+        );
+        quick_test(
+            "SELECT state, age AS b, COUNT(1) FROM person GROUP BY 2, 1",
+            "Projection: #person.state, #person.age AS b, #COUNT(UInt8(1))\
+             \n  Aggregate: groupBy=[[#person.age, #person.state]], aggr=[[COUNT(UInt8(1))]]\
+             \n    TableScan: person projection=None",
+        );
+    }
+
+    #[test]
+    fn select_simple_aggregate_with_groupby_all() {
         quick_test(
-            "SELECT state, age AS b, COUNT(1) FROM person GROUP BY 1, 2",
+            "SELECT state, age AS b, COUNT(1) FROM person GROUP BY ALL",
             "Projection: #person.state, #person.age AS b, #COUNT(UInt8(1))\
              \n  Aggregate: groupBy=[[#person.state, #person.age]], aggr=[[COUNT(UInt8(1))]]\
              \n    TableScan: person projection=None",
         );
+        // expressions that contain an aggregate are not grouped by
         quick_test(
-            "SELECT state, age AS b, COUNT(1) FROM person GROUP BY 2, 1",
-            "Projection: #person.state, #person.age AS b, #COUNT(UInt8(1))\
-             \n  Aggregate: groupBy=[[#person.age, #person.state]], aggr=[[COUNT(UInt8(1))]]\
+            "SELECT age + 1, MAX(age) - MIN(age) AS r FROM person GROUP BY ALL",
+            "Projection: #person.age Plus Int64(1), #MAX(person.age) Minus #MIN(person.age) AS r\
+             \n  Aggregate: groupBy=[[#person.age Plus Int64(1)]], aggr=[[MAX(#person.age), MIN(#person.age)]]\
              \n    TableScan: person projection=None",
         );
+
+        let sql = "SELECT state, COUNT(1) FROM person GROUP BY ALL, age";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"GROUP BY ALL cannot be combined with other grouping expressions\")",
+            format!("{:?}", err)
+        );
     }
 
     #[test]
@@ -2565,6 +3820,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn select_simple_aggregate_with_groupby_computed_expression_by_position_or_alias() {
+        let expected = "Projection: #person.age Plus Int64(1) AS a, #MIN(person.first_name)\
+             \n  Aggregate: groupBy=[[#person.age Plus Int64(1)]], aggr=[[MIN(#person.first_name)]]\
+             \n    TableScan: person projection=None";
+        quick_test(
+            "SELECT age + 1 AS a, MIN(first_name) FROM person GROUP BY 1",
+            expected,
+        );
+        quick_test(
+            "SELECT age + 1 AS a, MIN(first_name) FROM person GROUP BY a",
+            expected,
+        );
+        quick_test(
+            "SELECT age + 1 AS a, MIN(first_name) FROM person GROUP BY age + 1",
+            expected,
+        );
+    }
+
+    #[test]
+    fn select_simple_aggregate_with_groupby_column_shadowed_by_alias() {
+        // the column of the table takes precedence over the alias
+        quick_test(
+            "SELECT age + 1 AS age, COUNT(1) FROM person GROUP BY age",
+            "Projection: #person.age Plus Int64(1) AS age, #COUNT(UInt8(1))\
+             \n  Aggregate: groupBy=[[#person.age]], aggr=[[COUNT(UInt8(1))]]\
+             \n    TableScan: person projection=None",
+        );
+    }
+
+    #[test]
+    fn select_simple_aggregate_with_groupby_aggregate_by_position_or_alias() {
+        let sql = "SELECT state, MIN(age) FROM person GROUP BY 2";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Cannot GROUP BY an aggregate or window expression: MIN(#person.age)\")",
+            format!("{:?}", err)
+        );
+
+        let sql = "SELECT state, MIN(age) AS m FROM person GROUP BY state, m";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Cannot GROUP BY an aggregate or window expression: MIN(#person.age)\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn select_simple_aggregate_with_groupby_aggregate_repeated() {
         let sql = "SELECT state, MIN(age), MIN(age) FROM person GROUP BY state";
@@ -2766,6 +4068,46 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn select_order_by_all() {
+        let sql = "SELECT id, age FROM person ORDER BY ALL DESC NULLS LAST";
+        let expected = "Sort: #person.id DESC NULLS LAST, #person.age DESC NULLS LAST\
+                        \n  Projection: #person.id, #person.age\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT state, COUNT(1) FROM person GROUP BY ALL ORDER BY ALL";
+        let expected = "Sort: #person.state ASC NULLS FIRST, #COUNT(UInt8(1)) ASC NULLS FIRST\
+                        \n  Projection: #person.state, #COUNT(UInt8(1))\
+                        \n    Aggregate: groupBy=[[#person.state]], aggr=[[COUNT(UInt8(1))]]\
+                        \n      TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let err = logical_plan("SELECT id FROM person ORDER BY ALL, id")
+            .expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"ORDER BY ALL cannot be combined with other sort expressions\")",
+            format!("{:?}", err)
+        );
+    }
+
+    #[test]
+    fn select_order_by_limit_offset() {
+        let sql = "SELECT id FROM person ORDER BY id LIMIT 10 OFFSET 100";
+        let expected = "Limit: 10, offset=100\
+                        \n  Sort: #person.id ASC NULLS FIRST\
+                        \n    Projection: #person.id\
+                        \n      TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let err = logical_plan("SELECT id FROM person OFFSET 100")
+            .expect_err("query should have failed");
+        assert_eq!(
+            "NotImplemented(\"OFFSET without LIMIT is not supported\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn select_order_by_desc() {
         let sql = "SELECT id FROM person ORDER BY id DESC";
@@ -2852,6 +4194,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn create_function() {
+        let sql = "CREATE FUNCTION F(x, y) AS (x * 2 + y)";
+        let expected = "CreateFunction: f(x, y) AS #x Multiply Int64(2) Plus #y";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn copy_to() {
+        let sql = "COPY (SELECT id FROM person WHERE age > 20) TO 'out' \
+                   OPTIONS (compression 'zstd')";
+        let expected = "CopyTo: out\
+                        \n  Projection: #person.id\
+                        \n    Filter: #person.age Gt Int64(20)\
+                        \n      TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let sql = "COPY person TO 'out' STORED AS CSV";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "NotImplemented(\"COPY TO only supports Parquet files, found CSV\")",
+            format!("{:?}", err)
+        );
+
+        let sql = "COPY person TO 'out' OPTIONS (compression 'snappy2')";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Unsupported Parquet compression 'snappy2'\")",
+            format!("{:?}", err)
+        );
+    }
+
+    #[test]
+    fn create_function_invalid() {
+        let sql = "CREATE FUNCTION f(x) AS (x + y)";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Function f references #y, which is not one of its parameters\")",
+            format!("{:?}", err)
+        );
+
+        let sql = "CREATE FUNCTION abs(x) AS (x)";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Function abs is a built-in function and can not be redefined\")",
+            format!("{:?}", err)
+        );
+
+        let sql = "CREATE FUNCTION __ilike(x, y) AS (x = y)";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Function __ilike can not be created, names starting with __ are reserved\")",
+            format!("{:?}", err)
+        );
+    }
+
+    #[test]
+    fn select_sql_macro() {
+        let sql =
+            "SELECT double_plus_one(age + 1) FROM person WHERE double_plus_one(id) > 3";
+        let expected = "Projection: #person.age Plus Int64(1) Multiply Int64(2) Plus Int64(1)\
+                        \n  Filter: #person.id Multiply Int64(2) Plus Int64(1) Gt Int64(3)\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT double_plus_one(age, 1) FROM person";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Function double_plus_one expects 1 arguments, got 2\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn create_external_table_csv() {
         let sql = "CREATE EXTERNAL TABLE t(c1 int) STORED AS CSV LOCATION 'foo.csv'";
@@ -2887,6 +4302,17 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn create_external_table_declared_partition_column() {
+        let sql = "CREATE EXTERNAL TABLE t(c1 int, year int) STORED AS CSV \
+            PARTITIONED BY (year) LOCATION 'foo'";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"Partition column year must not be declared as a column of the files\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn equijoin_explicit_syntax() {
         let sql = "SELECT id, order_id \
@@ -2914,6 +4340,123 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn equijoin_is_not_distinct_from() {
+        let sql = "SELECT id, order_id \
+            FROM person \
+            JOIN orders \
+            ON id IS NOT DISTINCT FROM customer_id";
+        let expected = "Projection: #person.id, #orders.order_id\
+        \n  Join: #person.id IS NOT DISTINCT FROM #orders.customer_id\
+        \n    TableScan: person projection=None\
+        \n    TableScan: orders projection=None";
+        quick_test(sql, expected);
+
+        // mixed with regular equality, the null-safe comparison is filtered
+        let sql = "SELECT id, order_id \
+            FROM person \
+            JOIN orders \
+            ON id = customer_id AND age IS NOT DISTINCT FROM order_id";
+        let expected = "Projection: #person.id, #orders.order_id\
+        \n  Filter: #person.age IsNotDistinctFrom #orders.order_id\
+        \n    Join: #person.id = #orders.customer_id\
+        \n      TableScan: person projection=None\
+        \n      TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn lateral_subquery_equijoin() {
+        let sql = "SELECT id, order_id \
+            FROM person, \
+            LATERAL (SELECT order_id FROM orders WHERE customer_id = id) AS o";
+        let expected = "Projection: #person.id, #orders.order_id\
+        \n  Projection: #person.id, #person.first_name, #person.last_name, #person.age, #person.state, #person.salary, #person.birth_date, #person.😀, #orders.order_id\
+        \n    Join: #person.id = #__lateral_0\
+        \n      TableScan: person projection=None\
+        \n      Projection: #orders.order_id, #orders.customer_id AS __lateral_0\
+        \n        TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn lateral_subquery_non_equijoin() {
+        let sql = "SELECT id, order_id \
+            FROM person \
+            CROSS JOIN LATERAL (SELECT order_id FROM orders WHERE qty > age AND price > 10) AS o";
+        let expected = "Projection: #person.id, #orders.order_id\
+        \n  Projection: #person.id, #person.first_name, #person.last_name, #person.age, #person.state, #person.salary, #person.birth_date, #person.😀, #orders.order_id\
+        \n    Filter: #__lateral_0 Gt #person.age\
+        \n      CrossJoin:\
+        \n        TableScan: person projection=None\
+        \n        Projection: #orders.order_id, #orders.qty AS __lateral_0\
+        \n          Filter: #orders.price Gt Int64(10)\
+        \n            TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn lateral_subquery_aggregate_not_supported() {
+        let sql = "SELECT id \
+            FROM person, \
+            LATERAL (SELECT MAX(qty) FROM orders WHERE customer_id = id) AS o";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert!(matches!(err, DataFusionError::NotImplemented(_)));
+    }
+
+    #[test]
+    fn in_subquery() {
+        let sql = "SELECT id FROM person \
+            WHERE age > 21 AND id IN (SELECT customer_id FROM orders)";
+        let expected = "Projection: #person.id\
+        \n  Join: #person.id = #orders.customer_id\
+        \n    Filter: #person.age Gt Int64(21)\
+        \n      TableScan: person projection=None\
+        \n    Projection: #orders.customer_id\
+        \n      TableScan: orders projection=None";
+        quick_test(sql, expected);
+        let plan = logical_plan(sql).unwrap();
+        assert!(matches!(
+            plan.inputs()[0],
+            LogicalPlan::Join {
+                join_type: JoinType::Semi,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn not_in_subquery() {
+        let sql = "SELECT id FROM person \
+            WHERE id NOT IN (SELECT customer_id FROM orders WHERE qty > 1)";
+        let expected = "Projection: #person.id\
+        \n  Join: #person.id = #orders.customer_id\
+        \n    TableScan: person projection=None\
+        \n    Projection: #orders.customer_id\
+        \n      Filter: #orders.qty Gt Int64(1)\
+        \n        TableScan: orders projection=None";
+        quick_test(sql, expected);
+        let plan = logical_plan(sql).unwrap();
+        assert!(matches!(
+            plan.inputs()[0],
+            LogicalPlan::Join {
+                join_type: JoinType::NullAwareAnti,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn in_subquery_multiple_columns() {
+        let sql = "SELECT id FROM person \
+            WHERE id IN (SELECT customer_id, qty FROM orders)";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Error during planning: IN subquery must return exactly one column, found 2",
+            format!("{}", err)
+        );
+    }
+
     #[test]
     fn left_equijoin_unsupported_expression() {
         let sql = "SELECT id, order_id \
@@ -2981,6 +4524,73 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn project_wildcard_on_outer_join_with_using() {
+        // the join column of the preserved side is kept
+        let sql = "SELECT * \
+            FROM lineitem \
+            RIGHT JOIN lineitem as lineitem2 \
+            USING (l_item_id)";
+        let expected = "Projection: #lineitem2.l_item_id, #lineitem.l_description, #lineitem.price, #lineitem2.l_description, #lineitem2.price\
+        \n  Join: Using #lineitem.l_item_id = #lineitem2.l_item_id\
+        \n    TableScan: lineitem projection=None\
+        \n    TableScan: lineitem2 projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT l_item_id, lineitem.price \
+            FROM lineitem \
+            RIGHT JOIN lineitem as lineitem2 \
+            USING (l_item_id)";
+        let expected = "Projection: #lineitem2.l_item_id, #lineitem.price\
+        \n  Join: Using #lineitem.l_item_id = #lineitem2.l_item_id\
+        \n    TableScan: lineitem projection=None\
+        \n    TableScan: lineitem2 projection=None";
+        quick_test(sql, expected);
+
+        // both sides are preserved by a full join
+        let sql = "SELECT * EXCEPT (price) \
+            FROM lineitem \
+            FULL JOIN lineitem as lineitem2 \
+            USING (l_item_id, price)";
+        let expected = "Projection: CASE WHEN #lineitem.l_item_id IS NULL THEN #lineitem2.l_item_id ELSE #lineitem.l_item_id END AS l_item_id, #lineitem.l_description, #lineitem2.l_description\
+        \n  Join: Using #lineitem.l_item_id = #lineitem2.l_item_id, #lineitem.price = #lineitem2.price\
+        \n    TableScan: lineitem projection=None\
+        \n    TableScan: lineitem2 projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn natural_join() {
+        let sql = "SELECT * FROM lineitem NATURAL JOIN lineitem as lineitem2";
+        let expected = "Projection: #lineitem.l_item_id, #lineitem.l_description, #lineitem.price\
+        \n  Join: Using #lineitem.l_item_id = #lineitem2.l_item_id, #lineitem.l_description = #lineitem2.l_description, #lineitem.price = #lineitem2.price\
+        \n    TableScan: lineitem projection=None\
+        \n    TableScan: lineitem2 projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT order_id, price FROM orders NATURAL LEFT JOIN lineitem";
+        let expected = "Projection: #orders.order_id, #orders.price\
+        \n  Join: Using #orders.price = #lineitem.price\
+        \n    TableScan: orders projection=None\
+        \n    TableScan: lineitem projection=None";
+        quick_test(sql, expected);
+
+        // relations without common columns are joined with every row of each other
+        let sql = "SELECT id, order_id FROM person NATURAL JOIN orders";
+        let expected = "Projection: #person.id, #orders.order_id\
+        \n  CrossJoin:\
+        \n    TableScan: person projection=None\
+        \n    TableScan: orders projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT id, order_id FROM person NATURAL LEFT JOIN orders";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "NotImplemented(\"Unsupported Left NATURAL JOIN of relations without common columns\")",
+            format!("{:?}", err)
+        );
+    }
+
     #[test]
     fn equijoin_explicit_syntax_3_tables() {
         let sql = "SELECT id, order_id, l_description \
@@ -3109,6 +4719,30 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn qualify_window_alias() {
+        let sql = "SELECT order_id, MAX(qty) OVER (PARTITION BY order_id) AS max_qty FROM orders QUALIFY max_qty > 10";
+        let expected = "\
+        Projection: #orders.order_id, #MAX(orders.qty) PARTITION BY [#orders.order_id] AS max_qty\
+        \n  Filter: #MAX(orders.qty) PARTITION BY [#orders.order_id] Gt Int64(10)\
+        \n    WindowAggr: windowExpr=[[MAX(#orders.qty) PARTITION BY [#orders.order_id]]]\
+        \n      TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn qualify_window_not_projected() {
+        let sql = "SELECT order_id FROM orders WHERE qty > 1 QUALIFY MIN(qty) OVER (PARTITION BY order_id) < 5 ORDER BY order_id";
+        let expected = "\
+        Sort: #orders.order_id ASC NULLS FIRST\
+        \n  Projection: #orders.order_id\
+        \n    Filter: #MIN(orders.qty) PARTITION BY [#orders.order_id] Lt Int64(5)\
+        \n      WindowAggr: windowExpr=[[MIN(#orders.qty) PARTITION BY [#orders.order_id]]]\
+        \n        Filter: #orders.qty Gt Int64(1)\
+        \n          TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
     /// psql result
     /// ```
     ///                               QUERY PLAN
@@ -3216,6 +4850,26 @@ mod tests {
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn over_distinct_with_window_frame_exclusion() {
+        let sql = "SELECT order_id, COUNT(DISTINCT qty) OVER (ORDER BY order_id ROWS 3 PRECEDING EXCLUDE CURRENT ROW), SUM(qty) OVER (ORDER BY order_id EXCLUDE TIES) from orders";
+        let expected = "\
+        Projection: #orders.order_id, #COUNT(DISTINCT orders.qty) ORDER BY [#orders.order_id ASC NULLS FIRST] ROWS BETWEEN 3 PRECEDING AND CURRENT ROW EXCLUDE CURRENT ROW, #SUM(orders.qty) ORDER BY [#orders.order_id ASC NULLS FIRST] RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW EXCLUDE TIES\
+        \n  WindowAggr: windowExpr=[[COUNT(DISTINCT #orders.qty) ORDER BY [#orders.order_id ASC NULLS FIRST] ROWS BETWEEN 3 PRECEDING AND CURRENT ROW EXCLUDE CURRENT ROW, SUM(#orders.qty) ORDER BY [#orders.order_id ASC NULLS FIRST] RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW EXCLUDE TIES]]\
+        \n    TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn over_distinct_built_in_window_function() {
+        let sql = "SELECT order_id, ROW_NUMBER(DISTINCT order_id) OVER (ORDER BY order_id) from orders";
+        let err = logical_plan(sql).expect_err("query should have failed");
+        assert_eq!(
+            "Plan(\"DISTINCT is only supported by aggregate window functions, not ROW_NUMBER\")",
+            format!("{:?}", err)
+        );
+    }
+
     /// psql result
     /// ```
     ///                                     QUERY PLAN
@@ -3516,7 +5170,21 @@ mod tests {
         }
 
         fn get_aggregate_meta(&self, _name: &str) -> Option<Arc<AggregateUDF>> {
-            unimplemented!()
+            None
+        }
+
+        fn get_macro(&self, name: &str) -> Option<Arc<SqlMacro>> {
+            match name {
+                "double_plus_one" => Some(Arc::new(
+                    SqlMacro::try_new(
+                        "double_plus_one",
+                        vec!["x".to_owned()],
+                        col("x") * lit(2) + lit(1),
+                    )
+                    .unwrap(),
+                )),
+                _ => None,
+            }
         }
     }
 
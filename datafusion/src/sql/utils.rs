@@ -216,6 +216,7 @@ where
                 fun,
                 args,
                 distinct,
+                filter,
             } => Ok(Expr::AggregateFunction {
                 fun: fun.clone(),
                 args: args
@@ -223,6 +224,12 @@ where
                     .map(|e| clone_with_replacement(e, replacement_fn))
                     .collect::<Result<Vec<Expr>>>()?,
                 distinct: *distinct,
+                filter: match filter {
+                    Some(filter) => {
+                        Some(Box::new(clone_with_replacement(&**filter, replacement_fn)?))
+                    }
+                    None => None,
+                },
             }),
             Expr::WindowFunction {
                 fun,
@@ -230,6 +237,7 @@ where
                 partition_by,
                 order_by,
                 window_frame,
+                distinct,
             } => Ok(Expr::WindowFunction {
                 fun: fun.clone(),
                 args: args
@@ -245,13 +253,20 @@ where
                     .map(|e| clone_with_replacement(e, replacement_fn))
                     .collect::<Result<Vec<_>>>()?,
                 window_frame: *window_frame,
+                distinct: *distinct,
             }),
-            Expr::AggregateUDF { fun, args } => Ok(Expr::AggregateUDF {
+            Expr::AggregateUDF { fun, args, filter } => Ok(Expr::AggregateUDF {
                 fun: fun.clone(),
                 args: args
                     .iter()
                     .map(|e| clone_with_replacement(e, replacement_fn))
                     .collect::<Result<Vec<Expr>>>()?,
+                filter: match filter {
+                    Some(filter) => {
+                        Some(Box::new(clone_with_replacement(&**filter, replacement_fn)?))
+                    }
+                    None => None,
+                },
             }),
             Expr::Alias(nested_expr, alias_name) => Ok(Expr::Alias(
                 Box::new(clone_with_replacement(&**nested_expr, replacement_fn)?),
@@ -522,6 +537,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![],
             window_frame: None,
+            distinct: false,
         };
         let max2 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Max),
@@ -529,6 +545,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![],
             window_frame: None,
+            distinct: false,
         };
         let min3 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Min),
@@ -536,6 +553,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![],
             window_frame: None,
+            distinct: false,
         };
         let sum4 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Sum),
@@ -543,6 +561,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![],
             window_frame: None,
+            distinct: false,
         };
         let exprs = &[max1.clone(), max2.clone(), min3.clone(), sum4.clone()];
         let result = group_window_expr_by_sort_keys(exprs)?;
@@ -576,6 +595,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![age_asc.clone(), name_desc.clone()],
             window_frame: None,
+            distinct: false,
         };
         let max2 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Max),
@@ -583,6 +603,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![],
             window_frame: None,
+            distinct: false,
         };
         let min3 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Min),
@@ -590,6 +611,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![age_asc.clone(), name_desc.clone()],
             window_frame: None,
+            distinct: false,
         };
         let sum4 = Expr::WindowFunction {
             fun: WindowFunction::AggregateFunction(AggregateFunction::Sum),
@@ -597,6 +619,7 @@ mod tests {
             partition_by: vec![],
             order_by: vec![name_desc.clone(), age_asc.clone(), created_at_desc.clone()],
             window_frame: None,
+            distinct: false,
         };
         // FIXME use as_ref
         let exprs = &[max1.clone(), max2.clone(), min3.clone(), sum4.clone()];
@@ -635,6 +658,7 @@ mod tests {
                     },
                 ],
                 window_frame: None,
+                distinct: false,
             },
             Expr::WindowFunction {
                 fun: WindowFunction::AggregateFunction(AggregateFunction::Sum),
@@ -658,6 +682,7 @@ mod tests {
                     },
                 ],
                 window_frame: None,
+                distinct: false,
             },
         ];
         let expected = vec![
@@ -33,6 +33,7 @@ use arrow::{
 use futures::{Stream, StreamExt};
 use tokio_stream::wrappers::ReceiverStream;
 
+use crate::datasource::datasource::Statistics;
 use crate::error::{DataFusionError, Result};
 use crate::physical_plan::{
     ExecutionPlan, Partitioning, RecordBatchStream, SendableRecordBatchStream,
@@ -365,3 +366,61 @@ impl ExecutionPlan for ErrorExec {
         )))
     }
 }
+
+/// A mock execution plan that only reports the given statistics
+#[derive(Debug)]
+pub struct StatisticsExec {
+    schema: SchemaRef,
+    statistics: Statistics,
+}
+impl StatisticsExec {
+    pub fn new(statistics: Statistics, schema: Schema) -> Self {
+        Self {
+            schema: Arc::new(schema),
+            statistics,
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionPlan for StatisticsExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(2)
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        &self,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if children.is_empty() {
+            Ok(Arc::new(Self {
+                schema: self.schema.clone(),
+                statistics: self.statistics.clone(),
+            }))
+        } else {
+            Err(DataFusionError::Internal(
+                "Children cannot be replaced in StatisticsExec".to_string(),
+            ))
+        }
+    }
+
+    async fn execute(&self, _partition: usize) -> Result<SendableRecordBatchStream> {
+        unimplemented!("StatisticsExec only reports statistics")
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+}
@@ -836,6 +836,55 @@ async fn csv_query_avg() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn query_ordered_aggregates() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("k", DataType::Utf8, false),
+        Field::new("v", DataType::Utf8, true),
+        Field::new("o", DataType::Int32, true),
+    ]));
+    let batch = |k: Vec<&str>, v: Vec<Option<&str>>, o: Vec<Option<i32>>| {
+        RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(k)),
+                Arc::new(StringArray::from(v)),
+                Arc::new(Int32Array::from(o)),
+            ],
+        )
+    };
+    // two partitions, so that the partial states are merged
+    let partitions = vec![
+        vec![batch(
+            vec!["x", "x", "y"],
+            vec![Some("b"), Some("d"), Some("e")],
+            vec![Some(2), None, Some(1)],
+        )?],
+        vec![batch(
+            vec!["x", "x", "y"],
+            vec![Some("c"), Some("a"), None],
+            vec![Some(3), Some(1), Some(2)],
+        )?],
+    ];
+    let table = MemTable::try_new(schema.clone(), partitions)?;
+    ctx.register_table("t", Arc::new(table))?;
+
+    let sql = "SELECT k, \
+        ARRAY_AGG(v ORDER BY o), \
+        FIRST_VALUE(v ORDER BY o DESC NULLS LAST), \
+        LAST_VALUE(v ORDER BY o), \
+        STRING_AGG(v, '-' ORDER BY o DESC) \
+        FROM t GROUP BY k ORDER BY k";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["x", "[d, a, b, c]", "c", "c", "d-c-b-a"],
+        vec!["y", "[e, ]", "NULL", "NULL", "e"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn csv_query_group_by_avg() -> Result<()> {
     let mut ctx = ExecutionContext::new();
@@ -993,7 +1042,7 @@ async fn csv_query_window_with_partition_by() -> Result<()> {
         "+-----------+------------------------+------------------------+--------------------------+------------------------+------------------------+--------------------------------+-------------------------------+---------------------------------------+",
         "| 28774375  | -16110                 | -16110                 | 1                        | -16110                 | -16110                 | -16110                         | -16110                        |                                       |",
         "| 63044568  | 3917                   | 3917                   | 1                        | 3917                   | 3917                   | 3917                           | 3917                          |                                       |",
-        "| 141047417 | -38455                 | -19227.5               | 2                        | -16974                 | -21481                 | -16974                         | -21481                        |                                       |",
+        "| 141047417 | -38455                 | -19227.5               | 2                        | -16974                 | -21481                 | -16974                         | -21481                        | -21481                                |",
         "| 141680161 | -1114                  | -1114                  | 1                        | -1114                  | -1114                  | -1114                          | -1114                         |                                       |",
         "| 145294611 | 15673                  | 15673                  | 1                        | 15673                  | 15673                  | 15673                          | 15673                         |                                       |",
         "+-----------+------------------------+------------------------+--------------------------+------------------------+------------------------+--------------------------------+-------------------------------+---------------------------------------+",
@@ -1002,6 +1051,161 @@ async fn csv_query_window_with_partition_by() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn query_window_qualify() -> Result<()> {
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "k",
+            Arc::new(StringArray::from(vec!["a", "a", "b", "b", "b"])) as ArrayRef,
+        ),
+        (
+            "v",
+            Arc::new(Int32Array::from(vec![1, 3, 2, 5, 4])) as ArrayRef,
+        ),
+    ])?;
+    let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("t", Arc::new(table))?;
+
+    // keep the row with the largest value of each key
+    let sql = "SELECT k, v FROM t \
+               QUALIFY ROW_NUMBER() OVER (PARTITION BY k ORDER BY v DESC) = 1 \
+               ORDER BY k";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["a", "3"], vec!["b", "5"]];
+    assert_eq!(expected, actual);
+
+    // window results can be referenced by their alias
+    let sql = "SELECT k, v, MAX(v) OVER (PARTITION BY k) AS m FROM t \
+               QUALIFY v < m \
+               ORDER BY k, v";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["a", "1", "3"],
+        vec!["b", "2", "5"],
+        vec!["b", "4", "5"],
+    ];
+    assert_eq!(expected, actual);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_group_by_all_order_by_all() -> Result<()> {
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "k",
+            Arc::new(StringArray::from(vec!["a", "b", "a", "b", "a"])) as ArrayRef,
+        ),
+        (
+            "v",
+            Arc::new(Int32Array::from(vec![1, 3, 2, 5, 2])) as ArrayRef,
+        ),
+    ])?;
+    let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("t", Arc::new(table))?;
+
+    let sql = "SELECT k, v % 2 AS odd, SUM(v) FROM t GROUP BY ALL ORDER BY ALL";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["a", "0", "4"],
+        vec!["a", "1", "1"],
+        vec!["b", "1", "8"],
+    ];
+    assert_eq!(expected, actual);
+
+    let sql = "SELECT k, v FROM t ORDER BY ALL DESC";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["b", "5"],
+        vec!["b", "3"],
+        vec!["a", "2"],
+        vec!["a", "2"],
+        vec!["a", "1"],
+    ];
+    assert_eq!(expected, actual);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn window_frame_exclusion_and_distinct() -> Result<()> {
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "a",
+            Arc::new(Int32Array::from(vec![10, 10, 20, 30])) as ArrayRef,
+        ),
+        (
+            "b",
+            Arc::new(Int32Array::from(vec![1, 2, 2, 3])) as ArrayRef,
+        ),
+    ])?;
+    let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("t", Arc::new(table))?;
+
+    let sql = "SELECT b, a, \
+               SUM(a) OVER (ORDER BY b, a ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING \
+                 EXCLUDE CURRENT ROW), \
+               COUNT(DISTINCT a) OVER (ORDER BY b), \
+               SUM(a) OVER (ORDER BY b RANGE BETWEEN UNBOUNDED PRECEDING \
+                 AND UNBOUNDED FOLLOWING EXCLUDE TIES) \
+               FROM t ORDER BY b, a";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["1", "10", "10", "1", "70"],
+        vec!["2", "10", "30", "2", "50"],
+        vec!["2", "20", "40", "2", "60"],
+        vec!["3", "30", "20", "3", "70"],
+    ];
+    assert_eq!(expected, actual);
+
+    // frames that start at the start of the partition
+    let sql = "SELECT b, a, \
+               SUM(a) OVER (ORDER BY b, a ROWS BETWEEN UNBOUNDED PRECEDING \
+                 AND 1 PRECEDING), \
+               SUM(a) OVER (ORDER BY b, a ROWS BETWEEN UNBOUNDED PRECEDING \
+                 AND 1 FOLLOWING), \
+               MAX(a) OVER (PARTITION BY b ORDER BY a ROWS BETWEEN UNBOUNDED PRECEDING \
+                 AND UNBOUNDED FOLLOWING) \
+               FROM t ORDER BY b, a";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["1", "10", "NULL", "20", "10"],
+        vec!["2", "10", "10", "40", "20"],
+        vec!["2", "20", "20", "70", "20"],
+        vec!["3", "30", "40", "70", "30"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_order_by_nulls() -> Result<()> {
+    let batch = RecordBatch::try_from_iter(vec![(
+        "v",
+        Arc::new(Int32Array::from(vec![Some(2), None, Some(1)])) as ArrayRef,
+    )])?;
+    let table = MemTable::try_new(batch.schema(), vec![vec![batch]])?;
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("t", Arc::new(table))?;
+
+    let cases = vec![
+        ("v ASC NULLS FIRST", vec!["NULL", "1", "2"]),
+        ("v ASC NULLS LAST", vec!["1", "2", "NULL"]),
+        ("v DESC NULLS FIRST", vec!["NULL", "2", "1"]),
+        ("v DESC NULLS LAST", vec!["2", "1", "NULL"]),
+    ];
+    for (order_by, expected) in cases {
+        let sql = format!("SELECT v FROM t ORDER BY {}", order_by);
+        let actual = execute(&mut ctx, &sql).await;
+        let expected: Vec<Vec<&str>> = expected.into_iter().map(|v| vec![v]).collect();
+        assert_eq!(expected, actual, "ORDER BY {}", order_by);
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn csv_query_window_with_order_by() -> Result<()> {
     let mut ctx = ExecutionContext::new();
@@ -1035,6 +1239,33 @@ async fn csv_query_window_with_order_by() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn csv_query_window_ntile() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv(&mut ctx)?;
+    let sql = "select \
+               c9, \
+               ntile(4) over (order by c9), \
+               ntile(200) over (order by c9) \
+               from aggregate_test_100 \
+               order by c9 \
+               limit 5";
+    let actual = execute_to_batches(&mut ctx, sql).await;
+    let expected = vec![
+        "+-----------+-----------------+-------------------+",
+        "| c9        | NTILE(Int64(4)) | NTILE(Int64(200)) |",
+        "+-----------+-----------------+-------------------+",
+        "| 28774375  | 1               | 1                 |",
+        "| 63044568  | 1               | 2                 |",
+        "| 141047417 | 1               | 3                 |",
+        "| 141680161 | 1               | 4                 |",
+        "| 145294611 | 1               | 5                 |",
+        "+-----------+-----------------+-------------------+",
+    ];
+    assert_batches_eq!(expected, &actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn csv_query_group_by_int_count() -> Result<()> {
     let mut ctx = ExecutionContext::new();
@@ -1329,6 +1560,22 @@ async fn csv_union_all() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn csv_union_all_aggregate() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv(&mut ctx)?;
+    let sql = "SELECT c1, COUNT(c2), SUM(c3), MIN(c4) FROM (\
+        SELECT c1, c2, c3, c4 FROM aggregate_test_100 \
+        UNION ALL SELECT c1, c2, c3, c4 FROM aggregate_test_100) \
+        GROUP BY c1 ORDER BY c1";
+    let actual = execute(&mut ctx, sql).await;
+    let sql = "SELECT c1, COUNT(c2) + COUNT(c2), SUM(c3) + SUM(c3), MIN(c4) \
+        FROM aggregate_test_100 GROUP BY c1 ORDER BY c1";
+    let expected = execute(&mut ctx, sql).await;
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn csv_query_limit() -> Result<()> {
     let mut ctx = ExecutionContext::new();
@@ -1575,6 +1822,48 @@ async fn csv_query_limit_zero() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn csv_query_limit_offset() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv(&mut ctx)?;
+    let sql = "SELECT c9 FROM aggregate_test_100 ORDER BY c9";
+    let all = execute(&mut ctx, sql).await;
+    let sql = "SELECT c9 FROM aggregate_test_100 ORDER BY c9 LIMIT 3 OFFSET 2";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(all[2..5].to_vec(), actual);
+
+    // the offset is past the last row
+    let sql = "SELECT c9 FROM aggregate_test_100 ORDER BY c9 LIMIT 3 OFFSET 100";
+    let actual = execute(&mut ctx, sql).await;
+    let expected: Vec<Vec<String>> = vec![];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_function() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv(&mut ctx)?;
+    ctx.sql("CREATE FUNCTION scale(x, factor) AS (x * factor + 1)")?;
+    let sql = "SELECT scale(MAX(c2), 10), scale(c2, c2) FROM aggregate_test_100 \
+        GROUP BY c2 ORDER BY c2 LIMIT 2";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["11", "2"], vec!["21", "5"]];
+    assert_eq!(expected, actual);
+
+    let err = ctx
+        .sql("CREATE FUNCTION scale(x) AS (x * 2)")
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "Error during planning: Function scale already exists");
+
+    ctx.sql("CREATE OR REPLACE FUNCTION scale(x) AS (x * 2)")?;
+    let sql = "SELECT scale(MAX(c2)) FROM aggregate_test_100";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(vec![vec!["10"]], actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn csv_query_create_external_table() {
     let mut ctx = ExecutionContext::new();
@@ -1744,6 +2033,61 @@ async fn equijoin() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn equijoin_is_not_distinct_from() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    for (name, ids, names) in vec![
+        ("t1", vec![Some(11), None, Some(33)], vec!["a", "b", "c"]),
+        ("t2", vec![None, Some(11), Some(44)], vec!["z", "y", "x"]),
+    ] {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(&format!("{}_id", name), DataType::UInt32, true),
+            Field::new(&format!("{}_name", name), DataType::Utf8, false),
+        ]));
+        let data = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(UInt32Array::from(ids)),
+                Arc::new(StringArray::from(names)),
+            ],
+        )?;
+        let table = MemTable::try_new(schema, vec![vec![data]])?;
+        ctx.register_table(name, Arc::new(table))?;
+    }
+
+    let sql = "SELECT t1_id, t1_name, t2_name FROM t1 \
+        JOIN t2 ON t1_id IS NOT DISTINCT FROM t2_id ORDER BY t1_name";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["11", "a", "y"], vec!["NULL", "b", "z"]];
+    assert_eq!(expected, actual);
+
+    let sql = "SELECT t1_name, t1_id IS DISTINCT FROM 11, \
+        t1_id IS NOT DISTINCT FROM 33 FROM t1 ORDER BY t1_name";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["a", "false", "false"],
+        vec!["b", "true", "false"],
+        vec!["c", "true", "true"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn equijoin_aggregate() -> Result<()> {
+    let mut ctx = create_join_context("t1_id", "t2_id")?;
+    let sql = "SELECT t2_name, SUM(t1_id), COUNT(t1_name), MAX(t1_name) \
+        FROM t1 JOIN t2 ON t1_id = t2_id GROUP BY t2_name ORDER BY t2_name";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["x", "44", "1", "d"],
+        vec!["y", "22", "1", "b"],
+        vec!["z", "11", "1", "a"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn equijoin_multiple_condition_ordering() -> Result<()> {
     let mut ctx = create_join_context("t1_id", "t2_id")?;
@@ -1829,6 +2173,130 @@ async fn equijoin_and_unsupported_condition() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn lateral_join() -> Result<()> {
+    let mut ctx = create_join_context("t1_id", "t2_id")?;
+    let equivalent_sql = [
+        "SELECT t1_id, t1_name, t2_name FROM t1, LATERAL (SELECT t2_name FROM t2 WHERE t2_id = t1_id) AS t ORDER BY t1_id",
+        "SELECT t1_id, t1_name, t2_name FROM t1 JOIN LATERAL (SELECT t2_name FROM t2 WHERE t1_id = t2_id) AS t ON true ORDER BY t1_id",
+    ];
+    let expected = vec![
+        vec!["11", "a", "z"],
+        vec!["22", "b", "y"],
+        vec!["44", "d", "x"],
+    ];
+    for sql in equivalent_sql.iter() {
+        let actual = execute(&mut ctx, sql).await;
+        assert_eq!(expected, actual);
+    }
+
+    let sql = "SELECT t1_id, t1_name, t2_name FROM t1 \
+        CROSS JOIN LATERAL (SELECT t2_name FROM t2 WHERE t2_id > t1_id AND t2_id < 50) AS t \
+        ORDER BY t1_id, t2_name";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["11", "a", "x"],
+        vec!["11", "a", "y"],
+        vec!["22", "b", "x"],
+        vec!["33", "c", "x"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn lateral_unnest() -> Result<()> {
+    let mut builder = ListBuilder::new(Int32Builder::new(8));
+    builder.values().append_slice(&[10, 11])?;
+    builder.append(true)?;
+    builder.append(true)?;
+    builder.values().append_slice(&[30])?;
+    builder.append(true)?;
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int32, false),
+        Field::new(
+            "tags",
+            DataType::List(Box::new(Field::new("item", DataType::Int32, true))),
+            true,
+        ),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int32Array::from(vec![1, 2, 3])),
+            Arc::new(builder.finish()),
+        ],
+    )?;
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("t", Arc::new(MemTable::try_new(schema, vec![vec![batch]])?))?;
+
+    let sql = "SELECT id, tag FROM t, unnest(t.tags) AS u(tag) ORDER BY id, tag";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["1", "10"], vec!["1", "11"], vec!["3", "30"]];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn interval_join() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    let events_schema = Arc::new(Schema::new(vec![
+        Field::new("ts", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, false),
+    ]));
+    let events = RecordBatch::try_new(
+        events_schema.clone(),
+        vec![
+            Arc::new(Int64Array::from(vec![1, 5, 10, 15])),
+            Arc::new(StringArray::from(vec!["a", "b", "c", "d"])),
+        ],
+    )?;
+    ctx.register_table(
+        "events",
+        Arc::new(MemTable::try_new(events_schema, vec![vec![events]])?),
+    )?;
+    let windows_schema = Arc::new(Schema::new(vec![
+        Field::new("win", DataType::Utf8, false),
+        Field::new("lo", DataType::Int64, false),
+        Field::new("hi", DataType::Int64, false),
+    ]));
+    let windows = RecordBatch::try_new(
+        windows_schema.clone(),
+        vec![
+            Arc::new(StringArray::from(vec!["w1", "w2", "w3"])),
+            Arc::new(Int64Array::from(vec![0, 5, 20])),
+            Arc::new(Int64Array::from(vec![5, 12, 30])),
+        ],
+    )?;
+    ctx.register_table(
+        "windows",
+        Arc::new(MemTable::try_new(windows_schema, vec![vec![windows]])?),
+    )?;
+
+    let sql = "SELECT name, win FROM events, windows WHERE ts BETWEEN lo AND hi ORDER BY name, win";
+    let plan = ctx.create_logical_plan(sql)?;
+    let plan = ctx.optimize(&plan)?;
+    let plan = ctx.create_physical_plan(&plan)?;
+    let formatted = displayable(plan.as_ref()).indent().to_string();
+    assert!(formatted.contains("IntervalJoinExec"), "{}", formatted);
+
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["a", "w1"],
+        vec!["b", "w1"],
+        vec!["b", "w2"],
+        vec!["c", "w2"],
+    ];
+    assert_eq!(expected, actual);
+
+    let sql = "SELECT name, win FROM events, windows \
+        WHERE ts >= lo AND hi > ts AND hi - ts > 2 ORDER BY name, win";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["a", "w1"], vec!["b", "w2"]];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn left_join() -> Result<()> {
     let mut ctx = create_join_context("t1_id", "t2_id")?;
@@ -1915,6 +2383,49 @@ async fn left_join_using() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn outer_join_using() -> Result<()> {
+    let mut ctx = create_join_context("id", "id")?;
+    // the join column of a right join comes from the right side
+    let sql = "SELECT id, t1_name, t2_name FROM t1 RIGHT JOIN t2 USING (id) ORDER BY id";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["11", "a", "z"],
+        vec!["22", "b", "y"],
+        vec!["44", "d", "x"],
+        vec!["55", "NULL", "w"],
+    ];
+    assert_eq!(expected, actual);
+
+    // and the one of a full join from either side
+    let sql = "SELECT * FROM t1 FULL JOIN t2 USING (id)";
+    let mut actual = execute(&mut ctx, sql).await;
+    actual.sort();
+    let expected = vec![
+        vec!["11", "a", "z"],
+        vec!["22", "b", "y"],
+        vec!["33", "c", "NULL"],
+        vec!["44", "d", "x"],
+        vec!["55", "NULL", "w"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn natural_join() -> Result<()> {
+    let mut ctx = create_join_context("id", "id")?;
+    let sql = "SELECT * FROM t1 NATURAL JOIN t2 ORDER BY id";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["11", "a", "z"],
+        vec!["22", "b", "y"],
+        vec!["44", "d", "x"],
+    ];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn equijoin_implicit_syntax() -> Result<()> {
     let mut ctx = create_join_context("t1_id", "t2_id")?;
@@ -2022,6 +2533,81 @@ async fn cross_join() {
     assert_eq!(4 * 4 * 2, actual.len());
 }
 
+#[tokio::test]
+async fn cross_join_with_equality_filter() -> Result<()> {
+    let mut ctx = create_join_context("t1_id", "t2_id")?;
+
+    let sql = "SELECT t1_id, t1_name, t2_name FROM t1 CROSS JOIN t2 \
+        WHERE t1_id + 11 = t2_id AND t2_name <> 'x' ORDER BY t1_id";
+    let plan = ctx.create_logical_plan(sql)?;
+    let plan = ctx.optimize(&plan)?;
+    let formatted = plan.display_indent().to_string();
+    assert!(formatted.contains("Join: "), "{}", formatted);
+    assert!(!formatted.contains("CrossJoin"), "{}", formatted);
+
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![vec!["11", "a", "y"], vec!["44", "d", "w"]];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn in_subquery_with_nulls() -> Result<()> {
+    check_in_subquery_with_nulls(1).await
+}
+
+#[tokio::test]
+async fn in_subquery_with_nulls_concurrent() -> Result<()> {
+    // the right side of NOT IN is probed as one partition, so the partitions that the
+    // repartitioning leaves empty do not produce every row
+    check_in_subquery_with_nulls(4).await
+}
+
+async fn check_in_subquery_with_nulls(concurrency: usize) -> Result<()> {
+    let config = ExecutionConfig::new().with_concurrency(concurrency);
+    let mut ctx = ExecutionContext::with_config(config);
+    let t_schema = Arc::new(Schema::new(vec![Field::new("a", DataType::UInt32, true)]));
+    let t_data = RecordBatch::try_new(
+        t_schema.clone(),
+        vec![Arc::new(UInt32Array::from(vec![Some(1), Some(2), None]))],
+    )?;
+    ctx.register_table(
+        "t",
+        Arc::new(MemTable::try_new(t_schema, vec![vec![t_data]])?),
+    )?;
+    let u_schema = Arc::new(Schema::new(vec![Field::new("b", DataType::UInt32, true)]));
+    let u_data = RecordBatch::try_new(
+        u_schema.clone(),
+        vec![Arc::new(UInt32Array::from(vec![Some(1), Some(3), None]))],
+    )?;
+    ctx.register_table(
+        "u",
+        Arc::new(MemTable::try_new(u_schema, vec![vec![u_data]])?),
+    )?;
+
+    let cases = vec![
+        ("a IN (SELECT b FROM u)", vec![vec!["1"]]),
+        (
+            "a NOT IN (SELECT b FROM u WHERE b IS NOT NULL)",
+            vec![vec!["2"]],
+        ),
+        // NULL makes every NOT IN predicate either false or NULL
+        ("a NOT IN (SELECT b FROM u)", vec![]),
+        // every value is NOT IN an empty subquery, including NULL
+        (
+            "a NOT IN (SELECT b FROM u WHERE b > 10)",
+            vec![vec!["1"], vec!["2"], vec!["NULL"]],
+        ),
+    ];
+    for (predicate, expected) in cases {
+        let sql = format!("SELECT a FROM t WHERE {}", predicate);
+        let mut actual = execute(&mut ctx, &sql).await;
+        actual.sort();
+        assert_eq!(expected, actual, "{} with concurrency {}", sql, concurrency);
+    }
+    Ok(())
+}
+
 fn create_join_context(
     column_left: &str,
     column_right: &str,
@@ -2126,7 +2712,7 @@ async fn csv_explain() {
         vec!["physical_plan",
              "ProjectionExec: expr=[c1@0 as c1]\
               \n  CoalesceBatchesExec: target_batch_size=4096\
-              \n    FilterExec: CAST(c2@1 AS Int64) > 10\
+              \n    FilterExec: c2@1 > CAST(10 AS UInt32)\
               \n      RepartitionExec: partitioning=RoundRobinBatch(NUM_CORES)\
               \n        CsvExec: source=Path(ARROW_TEST_DATA/csv/aggregate_test_100.csv: [ARROW_TEST_DATA/csv/aggregate_test_100.csv]), has_header=true\
               \n"
@@ -2352,6 +2938,46 @@ async fn csv_explain_verbose() {
     );
 }
 
+#[tokio::test]
+async fn csv_explain_formats() {
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv_by_sql(&mut ctx).await;
+
+    let sql = "EXPLAIN (FORMAT JSON) SELECT c1 FROM aggregate_test_100 where c2 > 10";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(2, actual.len());
+    assert_eq!("logical_plan", actual[0][0]);
+    assert!(
+        actual[0][1].starts_with(
+            r#"{"node":"Projection: #aggregate_test_100.c1","schema":"[c1:Utf8]","inputs":[{"node":"Filter: #aggregate_test_100.c2 Gt Int64(10)""#
+        ),
+        "Actual: '{}'",
+        actual[0][1]
+    );
+    assert!(actual[0][1].contains(r#""filters":[]"#));
+    assert!(
+        actual[0][1].ends_with("]}]}]}"),
+        "Actual: '{}'",
+        actual[0][1]
+    );
+    assert_eq!("physical_plan", actual[1][0]);
+    assert!(
+        actual[1][1]
+            .starts_with(r#"{"node":"ProjectionExec: expr=[c1@0 as c1]","partitions":"#),
+        "Actual: '{}'",
+        actual[1][1]
+    );
+    assert!(actual[1][1].contains(r#""statistics":{"num_rows":"#));
+
+    let sql = "EXPLAIN (FORMAT GRAPHVIZ) SELECT c1 FROM aggregate_test_100 where c2 > 10";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(2, actual.len());
+    for (_, plan) in actual.iter().map(|row| (&row[0], &row[1])) {
+        assert!(plan.contains("digraph {"), "Actual: '{}'", plan);
+    }
+    assert!(actual[1][1].contains("ProjectionExec: expr=[c1@0 as c1]\\nPartitions: "));
+}
+
 #[tokio::test]
 async fn csv_explain_verbose_plans() {
     // This test verify the look of each plan in its full cycle plan creation
@@ -2530,7 +3156,7 @@ async fn csv_explain_verbose_plans() {
     );
     assert!(actual.contains("physical_plan"), "Actual: '{}'", actual);
     assert!(
-        actual.contains("FilterExec: CAST(c2@1 AS Int64) > 10"),
+        actual.contains("FilterExec: c2@1 > CAST(10 AS UInt32)"),
         "Actual: '{}'",
         actual
     );
@@ -2878,6 +3504,75 @@ async fn like() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn pattern_matching() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    let schema = Arc::new(Schema::new(vec![Field::new("name", DataType::Utf8, false)]));
+    let data = RecordBatch::try_new(
+        schema.clone(),
+        vec![Arc::new(StringArray::from(vec![
+            "apple pie",
+            "banana",
+            "50%",
+            "Apple",
+        ]))],
+    )?;
+    let table = MemTable::try_new(schema, vec![vec![data]])?;
+    ctx.register_table("t", Arc::new(table))?;
+
+    let sql = "SELECT name, name ILIKE 'a%', name SIMILAR TO '(apple|banana)%', \
+        name LIKE '%!%' ESCAPE '!' FROM t ORDER BY name";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["50%", "false", "false", "true"],
+        vec!["Apple", "true", "false", "false"],
+        vec!["apple pie", "true", "true", "false"],
+        vec!["banana", "false", "true", "false"],
+    ];
+    assert_eq!(expected, actual);
+
+    let sql =
+        "SELECT name FROM t WHERE name NOT ILIKE '%P%' AND name NOT SIMILAR TO '_0%'";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(vec![vec!["banana"]], actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn filtered_aggregates() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("g", DataType::Utf8, false),
+        Field::new("v", DataType::Int64, false),
+    ]));
+    let data = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(StringArray::from(vec!["a", "a", "a", "b", "b", "b"])),
+            Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5, 6])),
+        ],
+    )?;
+    let table = MemTable::try_new(schema, vec![vec![data]])?;
+    ctx.register_table("t", Arc::new(table))?;
+
+    let sql =
+        "SELECT g, COUNT(*) FILTER (WHERE v > 1), SUM(v) FILTER (WHERE v % 2 = 0), \
+        COUNT(DISTINCT v) FILTER (WHERE v < 5), SUM(v) FROM t GROUP BY g ORDER BY g";
+    let actual = execute(&mut ctx, sql).await;
+    let expected = vec![
+        vec!["a", "2", "2", "3", "6"],
+        vec!["b", "3", "10", "1", "15"],
+    ];
+    assert_eq!(expected, actual);
+
+    // no row passes the filter
+    let sql =
+        "SELECT COUNT(v) FILTER (WHERE v > 10), MAX(v) FILTER (WHERE v > 10) FROM t";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(vec![vec!["0", "NULL"]], actual);
+    Ok(())
+}
+
 fn make_timestamp_table<A>() -> Result<Arc<MemTable>>
 where
     A: ArrowTimestampType,
@@ -2934,6 +3629,44 @@ async fn to_timestamp() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn to_timestamp_with_format() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table("ts_data", make_timestamp_nano_table()?)?;
+
+    let sql = "SELECT COUNT(*) FROM ts_data \
+        WHERE ts > to_timestamp('08/09/2020 12:00 +0000', '%d/%m/%Y %H:%M %z')";
+    let actual = execute(&mut ctx, sql).await;
+
+    let expected = vec![vec!["2"]];
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
+#[tokio::test]
+async fn to_char() -> Result<()> {
+    let mut ctx = ExecutionContext::new();
+    ctx.register_table(
+        "ts_data",
+        make_timestamp_table::<TimestampMillisecondType>()?,
+    )?;
+
+    let sql = "SELECT to_char(ts, '%b %e, %Y %H:%M:%S%.3f') FROM ts_data ORDER BY ts";
+    let actual = execute(&mut ctx, sql).await;
+
+    let expected = vec![
+        vec!["Sep  8, 2020 11:42:29.190"],
+        vec!["Sep  8, 2020 12:42:29.190"],
+        vec!["Sep  8, 2020 13:42:29.190"],
+    ];
+    assert_eq!(expected, actual);
+
+    let sql = "SELECT to_char(ts, '%A %e %B', 'de_DE') FROM ts_data LIMIT 1";
+    let actual = execute(&mut ctx, sql).await;
+    assert_eq!(vec![vec!["Dienstag  8 September"]], actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn to_timestamp_millis() -> Result<()> {
     let mut ctx = ExecutionContext::new();
@@ -4009,6 +4742,38 @@ async fn test_cast_expressions() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_arrow_cast_expressions() -> Result<()> {
+    test_expression!("arrow_cast('1', 'Int64')", "1");
+    test_expression!("arrow_cast(1, 'Utf8')", "1");
+    test_expression!("arrow_cast(NULL, 'Float32')", "NULL");
+    test_expression!("arrow_try_cast('x', 'UInt8')", "NULL");
+
+    let mut ctx = ExecutionContext::new();
+    let e = ctx
+        .create_logical_plan("SELECT arrow_cast(1, 'Int')")
+        .unwrap_err();
+    assert!(e.to_string().contains("Invalid type name 'Int'"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cast_error_reports_row() -> Result<()> {
+    let mut ctx = create_ctx()?;
+    register_aggregate_csv(&mut ctx)?;
+    let sql = "SELECT arrow_cast(c1, 'Int64') FROM aggregate_test_100";
+    let plan = ctx.create_logical_plan(sql)?;
+    let plan = ctx.optimize(&plan)?;
+    let plan = ctx.create_physical_plan(&plan)?;
+    let e = collect(plan).await.unwrap_err().to_string();
+    assert!(
+        e.contains("Cannot cast value 'c' in row 0 of the batch to Int64"),
+        "unexpected error: {}",
+        e
+    );
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_current_timestamp_expressions() -> Result<()> {
     let t1 = chrono::Utc::now().timestamp();
@@ -4056,6 +4821,29 @@ async fn test_current_timestamp_expressions_non_optimized() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_query_constant_expressions() -> Result<()> {
+    let config = ExecutionConfig::new().with_current_user("alice");
+    let mut ctx = ExecutionContext::with_config(config);
+    let sql = "SELECT current_user(), version()";
+    let expected = vec![vec![
+        "alice".to_owned(),
+        format!("DataFusion {}", env!("CARGO_PKG_VERSION")),
+    ]];
+    assert_eq!(execute(&mut ctx, sql).await, expected);
+
+    // query constants are planned as literals when they are not folded
+    let plan = ctx.create_logical_plan(sql)?;
+    let plan = ctx.create_physical_plan(&plan)?;
+    assert_eq!(result_vec(&collect(plan).await?), expected);
+
+    let plan = ctx.create_logical_plan("SELECT version(1)");
+    assert!(plan
+        .and_then(|plan| ctx.create_physical_plan(&plan))
+        .is_err());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_random_expression() -> Result<()> {
     let mut ctx = create_ctx()?;
@@ -4090,6 +4878,30 @@ async fn test_cast_expressions_error() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn query_fused_predicates() -> Result<()> {
+    let sql = "SELECT c1, c2, c3 FROM aggregate_test_100 \
+               WHERE c2 > 2 AND c3 < 50 AND c12 < 0.5 AND c1 <> 'a' \
+               ORDER BY c1, c2, c3";
+    let mut ctx = ExecutionContext::new();
+    register_aggregate_csv(&mut ctx)?;
+    let plan = ctx.create_logical_plan(sql)?;
+    let plan = ctx.optimize(&plan)?;
+    let physical_plan = ctx.create_physical_plan(&plan)?;
+    let plan = format!("{}", displayable(physical_plan.as_ref()).indent());
+    assert!(plan.contains("FilterExec: FUSED("), "Plan: {}", plan);
+    let actual = execute(&mut ctx, sql).await;
+
+    let mut ctx = ExecutionContext::with_config(
+        ExecutionConfig::new().with_fused_predicates(false),
+    );
+    register_aggregate_csv(&mut ctx)?;
+    let expected = execute(&mut ctx, sql).await;
+    assert!(!expected.is_empty());
+    assert_eq!(expected, actual);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_physical_plan_display_indent() {
     // Hard code concurrency as it appears in the RepartitionExec output
@@ -231,7 +231,12 @@ impl OptimizerRule for TopKOptimizerRule {
         // Note: this code simply looks for the pattern of a Limit followed by a
         // Sort and replaces it by a TopK node. It does not handle many
         // edge cases (e.g multiple sort columns, sort ASC / DESC), etc.
-        if let LogicalPlan::Limit { ref n, ref input } = plan {
+        if let LogicalPlan::Limit {
+            ref n,
+            offset: 0,
+            ref input,
+        } = plan
+        {
             if let LogicalPlan::Sort {
                 ref expr,
                 ref input,